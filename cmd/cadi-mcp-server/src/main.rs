@@ -15,6 +15,8 @@ mod protocol;
 mod tools;
 mod resources;
 mod prompts;
+mod orientation;
+mod session;
 
 use clap::Parser;
 use protocol::McpServer;
@@ -32,6 +34,10 @@ struct Args {
     /// Bind address for HTTP mode (ignored in stdio mode)
     #[arg(short, long, default_value = "0.0.0.0:9090", env = "CADI_MCP_BIND_ADDRESS")]
     bind: String,
+
+    /// Bearer token HTTP clients must send (ignored in stdio mode)
+    #[arg(long, env = "CADI_MCP_AUTH_TOKEN")]
+    auth_token: Option<String>,
 }
 
 #[tokio::main]
@@ -49,7 +55,7 @@ async fn main() {
     tracing::info!("Starting CADI MCP server in {} mode", args.transport);
 
     // Create the server
-    let server = McpServer::new().await;
+    let server = McpServer::new().await.with_auth_token(args.auth_token.clone());
 
     let result = match args.transport.as_str() {
         "stdio" => server.run_stdio().await,
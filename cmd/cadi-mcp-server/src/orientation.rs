@@ -0,0 +1,263 @@
+//! Project orientation generated from the local chunk graph
+//!
+//! Backs the `cadi_project_orientation` prompt and the `cadi://graph/summary`
+//! resource: the namespaces, most-connected chunks and languages of what has
+//! been imported, plus the registry in use, so an agent starts out knowing
+//! which aliases to reach for.
+
+use cadi_core::graph::{GraphNode, GraphStore};
+use cadi_core::tokens::TokenEstimator;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Chunks listed in an orientation
+pub const TOP_CHUNKS: usize = 15;
+
+/// Default token budget of the orientation prompt
+pub const DEFAULT_TOKEN_BUDGET: usize = 1500;
+
+/// Location of the graph the MCP tools import into
+pub fn graph_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("dev.cadi.cadi")
+        .join("graph")
+}
+
+/// A chunk worth knowing about
+#[derive(Debug, Clone, Serialize)]
+pub struct ChunkSummary {
+    pub chunk_id: String,
+    pub alias: Option<String>,
+    pub description: String,
+    pub language: String,
+    /// Edges in and out of the chunk
+    pub connections: usize,
+}
+
+/// What the local graph holds
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphSummary {
+    pub chunks: usize,
+    pub edges: usize,
+    /// Alias namespaces with their chunk counts
+    pub namespaces: BTreeMap<String, usize>,
+    /// Languages with their chunk counts
+    pub languages: BTreeMap<String, usize>,
+    /// Most-connected chunks, most connected first
+    pub top_chunks: Vec<ChunkSummary>,
+    pub registry_url: String,
+}
+
+impl GraphSummary {
+    /// Summarize the graph at `dir`, or `None` if there is no graph or it
+    /// is empty
+    pub fn load(dir: &Path, registry_url: &str) -> Option<Self> {
+        if !dir.exists() {
+            return None;
+        }
+        let store = GraphStore::open(dir).ok()?;
+        Self::collect(&store, registry_url).ok().filter(|summary| summary.chunks > 0)
+    }
+
+    /// Summarize a graph store
+    pub fn collect(store: &GraphStore, registry_url: &str) -> cadi_core::CadiResult<Self> {
+        let nodes = store.list_nodes()?;
+        let edges = store.list_edges()?;
+
+        let mut connections: HashMap<&str, usize> = HashMap::new();
+        for (from, to, _) in &edges {
+            *connections.entry(from.as_str()).or_default() += 1;
+            *connections.entry(to.as_str()).or_default() += 1;
+        }
+
+        let mut namespaces = BTreeMap::new();
+        let mut languages = BTreeMap::new();
+        for node in &nodes {
+            if let Some(namespace) = node.primary_alias.as_deref().and_then(|a| a.split_once('/')).map(|(ns, _)| ns) {
+                *namespaces.entry(namespace.to_string()).or_default() += 1;
+            }
+            if !node.language.is_empty() {
+                *languages.entry(node.language.clone()).or_default() += 1;
+            }
+        }
+
+        let mut ranked: Vec<(&GraphNode, usize)> = nodes
+            .iter()
+            .map(|node| (node, connections.get(node.chunk_id.as_str()).copied().unwrap_or(0)))
+            .collect();
+        ranked.sort_by(|(a, a_count), (b, b_count)| {
+            b_count
+                .cmp(a_count)
+                .then_with(|| a.primary_alias.cmp(&b.primary_alias))
+                .then_with(|| a.chunk_id.cmp(&b.chunk_id))
+        });
+        let top_chunks = ranked
+            .into_iter()
+            .take(TOP_CHUNKS)
+            .map(|(node, connections)| ChunkSummary {
+                chunk_id: node.chunk_id.clone(),
+                alias: node.primary_alias.clone(),
+                description: describe(node),
+                language: node.language.clone(),
+                connections,
+            })
+            .collect();
+
+        Ok(Self {
+            chunks: nodes.len(),
+            edges: edges.len(),
+            namespaces,
+            languages,
+            top_chunks,
+            registry_url: registry_url.to_string(),
+        })
+    }
+
+    /// Orientation prompt text, cut to fit `token_budget` tokens as counted
+    /// by `estimator`. Chunks are dropped from the end of the list first.
+    pub fn to_prompt(&self, token_budget: usize, estimator: &dyn TokenEstimator) -> String {
+        let counts = |map: &BTreeMap<String, usize>| {
+            map.iter().map(|(name, count)| format!("`{}` ({})", name, count)).collect::<Vec<_>>().join(", ")
+        };
+
+        let mut head = format!(
+            "# Project Orientation\n\nThe local CADI graph holds {} chunks and {} edges.\n\n",
+            self.chunks, self.edges
+        );
+        if !self.namespaces.is_empty() {
+            head.push_str(&format!("**Namespaces:** {}\n", counts(&self.namespaces)));
+        }
+        if !self.languages.is_empty() {
+            head.push_str(&format!("**Languages:** {}\n", counts(&self.languages)));
+        }
+        head.push_str(&format!("**Registry:** {}\n\n## Most-connected chunks\n\n", self.registry_url));
+
+        let tail = "\n## Workflow\n\
+            1. Resolve the aliases above with `cadi_resolve_alias` instead of reading files.\n\
+            2. Use `cadi_view_context` to assemble the chunks you need with their imports.\n\
+            3. `cadi_search` before writing anything new; `cadi_import` what you write.\n";
+
+        let omitted = |count: usize| {
+            format!(
+                "- …{} more omitted to fit the token budget; read `cadi://graph/summary` for all\n",
+                count
+            )
+        };
+
+        // Lines are added while they, the note about the rest and the
+        // workflow still fit
+        let mut text = head;
+        let mut listed = 0;
+        for chunk in &self.top_chunks {
+            let line = format!(
+                "- `{}` ({}, {} connections): {}\n",
+                chunk.alias.as_deref().unwrap_or(&chunk.chunk_id),
+                chunk.language,
+                chunk.connections,
+                chunk.description
+            );
+            let rest = self.top_chunks.len() - listed - 1;
+            let note = if rest > 0 { omitted(rest) } else { String::new() };
+            if estimator.estimate(&format!("{}{}{}{}", text, line, note, tail), None) > token_budget {
+                break;
+            }
+            text.push_str(&line);
+            listed += 1;
+        }
+        if listed < self.top_chunks.len() {
+            text.push_str(&omitted(self.top_chunks.len() - listed));
+        }
+        text.push_str(tail);
+        text
+    }
+}
+
+/// One line about a chunk: its description if it was imported with one,
+/// else what it is, what it defines and where it came from
+fn describe(node: &GraphNode) -> String {
+    if let Some(description) = node.metadata.get("description") {
+        return description.lines().next().unwrap_or_default().to_string();
+    }
+
+    let mut line = node.granularity.to_lowercase();
+    if !node.symbols_defined.is_empty() {
+        let mut symbols = node.symbols_defined.iter().take(3).cloned().collect::<Vec<_>>().join(", ");
+        if node.symbols_defined.len() > 3 {
+            symbols.push_str(", …");
+        }
+        line.push_str(&format!(" defining {}", symbols));
+    }
+    if let Some(file) = &node.source_file {
+        line.push_str(&format!(" in {}", file));
+    }
+    line
+}
+
+/// Prompt for when nothing has been imported yet
+pub fn no_graph_prompt(registry_url: &str) -> String {
+    format!(
+        "# Project Orientation\n\n\
+        No CADI graph exists yet, so there is nothing to orient on.\n\n\
+        ## Action\n\
+        Call `cadi_import` with the project path (and a `namespace` for its aliases) first. \
+        Then request this prompt again to see the project's namespaces, key chunks and languages.\n\n\
+        **Registry:** {}\n",
+        registry_url
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cadi_core::graph::EdgeType;
+    use cadi_core::tokens::ByteHeuristic;
+
+    fn store() -> GraphStore {
+        let store = GraphStore::in_memory().unwrap();
+        let node = |id: &str, alias: &str, language: &str| {
+            GraphNode::new(id, id)
+                .with_alias(alias)
+                .with_language(language)
+                .with_granularity("function")
+                .with_defines(vec![alias.rsplit('/').next().unwrap().to_string()])
+        };
+        store.insert_node(&node("chunk:a", "app/main", "rust")).unwrap();
+        store.insert_node(&node("chunk:b", "app/utils/parse", "rust")).unwrap();
+        store.insert_node(&node("chunk:c", "web/render", "typescript")).unwrap();
+        store.add_dependency("chunk:a", "chunk:b", EdgeType::Imports).unwrap();
+        store.add_dependency("chunk:c", "chunk:b", EdgeType::Imports).unwrap();
+        store
+    }
+
+    #[test]
+    fn test_summary_ranks_chunks_by_connections() {
+        let summary = GraphSummary::collect(&store(), "https://registry.example").unwrap();
+        assert_eq!(summary.chunks, 3);
+        assert_eq!(summary.edges, 2);
+        assert_eq!(summary.namespaces, BTreeMap::from([("app".to_string(), 2), ("web".to_string(), 1)]));
+        assert_eq!(summary.languages["rust"], 2);
+
+        let aliases: Vec<_> = summary.top_chunks.iter().map(|c| c.alias.clone().unwrap()).collect();
+        assert_eq!(aliases, vec!["app/utils/parse", "app/main", "web/render"]);
+        assert_eq!(summary.top_chunks[0].connections, 2);
+        assert_eq!(summary.top_chunks[0].description, "function defining parse");
+    }
+
+    #[test]
+    fn test_prompt_fits_token_budget() {
+        let summary = GraphSummary::collect(&store(), "https://registry.example").unwrap();
+
+        let estimator = ByteHeuristic;
+        let full = summary.to_prompt(DEFAULT_TOKEN_BUDGET, &estimator);
+        assert!(full.contains("`app/utils/parse` (rust, 2 connections): function defining parse"));
+        assert!(full.contains("**Registry:** https://registry.example"));
+        assert!(!full.contains("omitted"));
+
+        let tight = summary.to_prompt(160, &estimator);
+        assert!(estimator.estimate(&tight, None) <= 160);
+        assert!(tight.contains("more omitted to fit the token budget"));
+        assert!(tight.contains("## Workflow"));
+    }
+}
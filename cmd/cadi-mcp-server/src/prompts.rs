@@ -3,6 +3,9 @@
 //! Pre-built prompt templates that guide agents to use CADI efficiently.
 //! These prompts encode the "CADI-first" workflow to save tokens.
 
+use crate::orientation::{self, GraphSummary};
+use crate::session::SessionConfig;
+use cadi_core::tokens::TokenEstimatorKind;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
@@ -36,6 +39,17 @@ pub fn get_prompts() -> Vec<PromptDefinition> {
                 },
             ]),
         },
+        PromptDefinition {
+            name: "cadi_project_orientation".to_string(),
+            description: "Orientation on the imported project, generated from the local graph: namespaces, key chunks and their aliases, languages".to_string(),
+            arguments: Some(vec![
+                PromptArgument {
+                    name: "token_budget".to_string(),
+                    description: format!("Approximate size limit in tokens (default {})", orientation::DEFAULT_TOKEN_BUDGET),
+                    required: false,
+                },
+            ]),
+        },
         PromptDefinition {
             name: "import_project".to_string(),
             description: "Import a codebase into CADI for reuse - do this first with any new project".to_string(),
@@ -83,9 +97,10 @@ pub fn get_prompts() -> Vec<PromptDefinition> {
 }
 
 /// Get a prompt by name with arguments filled in
-pub fn get_prompt(name: &str, arguments: &Value) -> Result<Vec<Value>, String> {
+pub fn get_prompt(name: &str, arguments: &Value, session: &SessionConfig) -> Result<Vec<Value>, String> {
     match name {
         "cadi_workflow" => get_cadi_workflow_prompt(arguments),
+        "cadi_project_orientation" => get_project_orientation_prompt(arguments, session),
         "import_project" => get_import_project_prompt(arguments),
         "find_reusable_code" => get_find_reusable_prompt(arguments),
         "use_chunk" => get_use_chunk_prompt(arguments),
@@ -136,6 +151,29 @@ If no atom exists:
     })])
 }
 
+fn get_project_orientation_prompt(args: &Value, session: &SessionConfig) -> Result<Vec<Value>, String> {
+    // Arguments arrive as strings from most clients
+    let token_budget = match args.get("token_budget") {
+        Some(Value::String(s)) => s.parse().map_err(|_| format!("Invalid token_budget: {}", s))?,
+        Some(value) => value.as_u64().map(|n| n as usize).unwrap_or(orientation::DEFAULT_TOKEN_BUDGET),
+        None => orientation::DEFAULT_TOKEN_BUDGET,
+    };
+    let registry_url = session.registry_url().unwrap_or_else(|| "http://localhost:8080".to_string());
+
+    let text = match GraphSummary::load(&orientation::graph_dir(), &registry_url) {
+        Some(summary) => summary.to_prompt(token_budget, TokenEstimatorKind::default().estimator().as_ref()),
+        None => orientation::no_graph_prompt(&registry_url),
+    };
+
+    Ok(vec![json!({
+        "role": "user",
+        "content": {
+            "type": "text",
+            "text": text
+        }
+    })])
+}
+
 fn get_import_project_prompt(args: &Value) -> Result<Vec<Value>, String> {
     let path = args.get("path").and_then(|p| p.as_str()).unwrap_or(".");
     let namespace = args.get("namespace").and_then(|n| n.as_str()).unwrap_or("project");
@@ -2,24 +2,39 @@
 
 use axum::{
     extract::State,
-    http::StatusCode,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::io::{BufRead, Write};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::TraceLayer;
 use surrealdb::engine::local::Mem;
 use crate::prompts::PromptDefinition;
+use crate::resources::{ResourceError, Subscriptions};
+use crate::session::{SessionConfig, SESSION_HEADER};
 
 /// MCP Server
 pub struct McpServer {
     tools: Vec<ToolDefinition>,
     resources: Vec<ResourceDefinition>,
+    resource_templates: Vec<ResourceTemplate>,
     prompts: Vec<PromptDefinition>,
     pub db: surrealdb::Surreal<surrealdb::engine::local::Db>,
+    /// Bearer token required on HTTP requests, if set
+    auth_token: Option<String>,
+    /// HTTP sessions keyed by session ID
+    sessions: RwLock<HashMap<String, Arc<SessionConfig>>>,
+    /// Resources clients subscribed to
+    subscriptions: Arc<tokio::sync::Mutex<Subscriptions>>,
+    /// Whether resource updates can be pushed to the client, which only
+    /// the stdio transport supports
+    notifies: AtomicBool,
 }
 
 impl McpServer {
@@ -30,20 +45,61 @@ impl McpServer {
         Self {
             tools: crate::tools::get_tools(),
             resources: crate::resources::get_resources(),
+            resource_templates: crate::resources::get_resource_templates(),
             prompts: crate::prompts::get_prompts(),
             db,
+            auth_token: None,
+            sessions: RwLock::new(HashMap::new()),
+            subscriptions: Arc::new(tokio::sync::Mutex::new(Subscriptions::default())),
+            notifies: AtomicBool::new(false),
         }
     }
 
+    /// Require `Authorization: Bearer <token>` on HTTP requests
+    pub fn with_auth_token(mut self, token: Option<String>) -> Self {
+        self.auth_token = token.filter(|t| !t.is_empty());
+        self
+    }
+
     /// Run the server in stdio mode (for local MCP clients like Claude Desktop)
     pub async fn run_stdio(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let stdin = std::io::stdin();
-        let stdout = std::io::stdout();
-        let mut stdout_lock = stdout.lock();
-
         tracing::info!("Running in stdio mode - reading from stdin");
 
-        for line in stdin.lock().lines() {
+        // Read stdin off the runtime so subscribed resources can be polled
+        // while waiting for the next request
+        let (tx, mut lines) = tokio::sync::mpsc::unbounded_channel();
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        self.notifies.store(true, Ordering::Relaxed);
+        let watcher = tokio::spawn({
+            let subscriptions = self.subscriptions.clone();
+            async move {
+                let mut interval = tokio::time::interval(crate::resources::POLL_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    for uri in subscriptions.lock().await.poll().await {
+                        let notification = JsonRpcNotification::new(
+                            "notifications/resources/updated",
+                            serde_json::json!({ "uri": uri }),
+                        );
+                        if write_message(&notification).is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+
+        // A stdio connection is a single session
+        let mut session = SessionConfig::default();
+
+        while let Some(line) = lines.recv().await {
             let line = line?;
             if line.is_empty() {
                 continue;
@@ -57,17 +113,26 @@ impl McpServer {
                         -32700,
                         format!("Parse error: {}", e),
                     );
-                    writeln!(stdout_lock, "{}", serde_json::to_string(&error_response)?)?;
-                    stdout_lock.flush()?;
+                    write_message(&error_response)?;
                     continue;
                 }
             };
 
-            let response = self.handle_request(request).await;
-            writeln!(stdout_lock, "{}", serde_json::to_string(&response)?)?;
-            stdout_lock.flush()?;
+            if request.method == "initialize" {
+                match SessionConfig::from_initialize(request.params.as_ref()) {
+                    Ok(config) => session = config,
+                    Err(e) => {
+                        write_message(&JsonRpcResponse::error(request.id, -32602, e))?;
+                        continue;
+                    }
+                }
+            }
+
+            let response = self.handle_request(request, &session).await;
+            write_message(&response)?;
         }
 
+        watcher.abort();
         Ok(())
     }
 
@@ -78,13 +143,14 @@ impl McpServer {
         let cors = CorsLayer::new()
             .allow_origin(Any)
             .allow_methods(Any)
-            .allow_headers(Any);
+            .allow_headers(Any)
+            .expose_headers([header::HeaderName::from_static(SESSION_HEADER)]);
 
         let app = Router::new()
             .route("/health", get(health_check))
-            .route("/", post(handle_jsonrpc))
-            .route("/mcp", post(handle_jsonrpc))
-            .route("/jsonrpc", post(handle_jsonrpc))
+            .route("/", post(handle_jsonrpc).delete(close_session))
+            .route("/mcp", post(handle_jsonrpc).delete(close_session))
+            .route("/jsonrpc", post(handle_jsonrpc).delete(close_session))
             .with_state(shared_state)
             .layer(cors)
             .layer(TraceLayer::new_for_http());
@@ -96,15 +162,60 @@ impl McpServer {
         Ok(())
     }
 
-    async fn handle_request(&self, request: JsonRpcRequest) -> JsonRpcResponse {
+    /// Start an HTTP session from `initialize` params and return its ID.
+    /// Remote clients keep their storage under the server's.
+    fn open_session(&self, params: Option<&serde_json::Value>) -> Result<(String, Arc<SessionConfig>), String> {
+        let config = Arc::new(SessionConfig::from_initialize(params)?.confine_storage()?);
+        let session_id = uuid::Uuid::new_v4().to_string();
+        self.sessions
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(session_id.clone(), config.clone());
+        Ok((session_id, config))
+    }
+
+    /// Look up an HTTP session by ID
+    fn session(&self, session_id: &str) -> Option<Arc<SessionConfig>> {
+        self.sessions
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(session_id)
+            .cloned()
+    }
+
+    /// End an HTTP session, returning whether it existed
+    fn close_session_id(&self, session_id: &str) -> bool {
+        self.sessions
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(session_id)
+            .is_some()
+    }
+
+    /// Check the bearer token of an HTTP request
+    fn is_authorized(&self, headers: &HeaderMap) -> bool {
+        let Some(expected) = &self.auth_token else {
+            return true;
+        };
+        headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .is_some_and(|token| token == expected)
+    }
+
+    async fn handle_request(&self, request: JsonRpcRequest, session: &SessionConfig) -> JsonRpcResponse {
         match request.method.as_str() {
             "initialize" => self.handle_initialize(request.id),
             "tools/list" => self.handle_list_tools(request.id),
-            "tools/call" => self.handle_call_tool(request.id, request.params).await,
+            "tools/call" => self.handle_call_tool(request.id, request.params, session).await,
             "resources/list" => self.handle_list_resources(request.id),
-            "resources/read" => self.handle_read_resource(request.id, request.params).await,
+            "resources/templates/list" => self.handle_list_resource_templates(request.id),
+            "resources/read" => self.handle_read_resource(request.id, request.params, session).await,
+            "resources/subscribe" => self.handle_subscribe(request.id, request.params, session).await,
+            "resources/unsubscribe" => self.handle_unsubscribe(request.id, request.params).await,
             "prompts/list" => self.handle_list_prompts(request.id),
-            "prompts/get" => self.handle_get_prompt(request.id, request.params),
+            "prompts/get" => self.handle_get_prompt(request.id, request.params, session),
             _ => JsonRpcResponse::error(
                 request.id,
                 -32601,
@@ -118,7 +229,9 @@ impl McpServer {
             "protocolVersion": "2024-11-05",
             "capabilities": {
                 "tools": {},
-                "resources": {},
+                "resources": {
+                    "subscribe": self.notifies.load(Ordering::Relaxed)
+                },
                 "prompts": {},
                 "streaming": {}
             },
@@ -148,6 +261,7 @@ impl McpServer {
         &self,
         id: Option<serde_json::Value>,
         params: Option<serde_json::Value>,
+        session: &SessionConfig,
     ) -> JsonRpcResponse {
         let params = match params {
             Some(p) => p,
@@ -162,7 +276,7 @@ impl McpServer {
             .cloned()
             .unwrap_or(serde_json::Value::Object(Default::default()));
 
-        let result = crate::tools::call_tool(tool_name, arguments, &self.db).await;
+        let result = crate::tools::call_tool(tool_name, arguments, &self.db, session).await;
 
         match result {
             Ok(content) => JsonRpcResponse::success(id, serde_json::json!({
@@ -197,6 +311,7 @@ impl McpServer {
         &self,
         id: Option<serde_json::Value>,
         params: Option<serde_json::Value>,
+        session: &SessionConfig,
     ) -> JsonRpcResponse {
         let params = match params {
             Some(p) => p,
@@ -207,14 +322,62 @@ impl McpServer {
             .and_then(|u| u.as_str())
             .unwrap_or("");
 
-        let result = crate::resources::read_resource(uri).await;
+        let result = crate::resources::read_resource(uri, session).await;
 
         match result {
             Ok(contents) => JsonRpcResponse::success(id, serde_json::json!({
                 "contents": contents
             })),
-            Err(e) => JsonRpcResponse::error(id, -32603, e.to_string()),
+            Err(e) => resource_error(id, e),
+        }
+    }
+
+    fn handle_list_resource_templates(&self, id: Option<serde_json::Value>) -> JsonRpcResponse {
+        let templates: Vec<_> = self.resource_templates.iter()
+            .map(|t| serde_json::json!({
+                "uriTemplate": t.uri_template,
+                "name": t.name,
+                "description": t.description,
+                "mimeType": t.mime_type
+            }))
+            .collect();
+
+        JsonRpcResponse::success(id, serde_json::json!({
+            "resourceTemplates": templates
+        }))
+    }
+
+    /// Watch a resource and send `notifications/resources/updated` when
+    /// its contents change
+    async fn handle_subscribe(
+        &self,
+        id: Option<serde_json::Value>,
+        params: Option<serde_json::Value>,
+        session: &SessionConfig,
+    ) -> JsonRpcResponse {
+        if !self.notifies.load(Ordering::Relaxed) {
+            return JsonRpcResponse::error(id, -32601, "Resource subscriptions need the stdio transport".to_string());
         }
+        let Some(uri) = params.as_ref().and_then(|p| p.get("uri")).and_then(|u| u.as_str()) else {
+            return JsonRpcResponse::error(id, -32602, "Missing uri".to_string());
+        };
+
+        match self.subscriptions.lock().await.subscribe(uri, session).await {
+            Ok(()) => JsonRpcResponse::success(id, serde_json::json!({})),
+            Err(e) => resource_error(id, e),
+        }
+    }
+
+    async fn handle_unsubscribe(
+        &self,
+        id: Option<serde_json::Value>,
+        params: Option<serde_json::Value>,
+    ) -> JsonRpcResponse {
+        let Some(uri) = params.as_ref().and_then(|p| p.get("uri")).and_then(|u| u.as_str()) else {
+            return JsonRpcResponse::error(id, -32602, "Missing uri".to_string());
+        };
+        self.subscriptions.lock().await.unsubscribe(uri);
+        JsonRpcResponse::success(id, serde_json::json!({}))
     }
 
     fn handle_list_prompts(&self, id: Option<serde_json::Value>) -> JsonRpcResponse {
@@ -235,6 +398,7 @@ impl McpServer {
         &self,
         id: Option<serde_json::Value>,
         params: Option<serde_json::Value>,
+        session: &SessionConfig,
     ) -> JsonRpcResponse {
         let params = match params {
             Some(p) => p,
@@ -249,7 +413,7 @@ impl McpServer {
             .cloned()
             .unwrap_or(serde_json::Value::Object(Default::default()));
 
-        match crate::prompts::get_prompt(name, &arguments) {
+        match crate::prompts::get_prompt(name, &arguments, session) {
             Ok(messages) => JsonRpcResponse::success(id, serde_json::json!({
                 "messages": messages
             })),
@@ -287,6 +451,43 @@ pub struct JsonRpcResponse {
 pub struct JsonRpcError {
     pub code: i32,
     pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+/// JSON-RPC 2.0 Notification, a message without an ID that expects no
+/// response
+#[derive(Debug, Serialize)]
+pub struct JsonRpcNotification {
+    pub jsonrpc: String,
+    pub method: String,
+    pub params: serde_json::Value,
+}
+
+impl JsonRpcNotification {
+    pub fn new(method: &str, params: serde_json::Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params,
+        }
+    }
+}
+
+/// Write one message to stdout as a line of JSON
+fn write_message(message: &impl Serialize) -> std::io::Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    writeln!(stdout, "{}", serde_json::to_string(message)?)?;
+    stdout.flush()
+}
+
+/// Response for a failed resource read, keeping the code and data of a
+/// [`ResourceError`]
+fn resource_error(id: Option<serde_json::Value>, error: Box<dyn std::error::Error + Send + Sync>) -> JsonRpcResponse {
+    match error.downcast::<ResourceError>() {
+        Ok(error) => JsonRpcResponse::error_with_data(id, error.code, error.message, error.data),
+        Err(error) => JsonRpcResponse::error(id, -32603, error.to_string()),
+    }
 }
 
 impl JsonRpcResponse {
@@ -304,7 +505,21 @@ impl JsonRpcResponse {
             jsonrpc: "2.0".to_string(),
             id,
             result: None,
-            error: Some(JsonRpcError { code, message }),
+            error: Some(JsonRpcError { code, message, data: None }),
+        }
+    }
+
+    pub fn error_with_data(
+        id: Option<serde_json::Value>,
+        code: i32,
+        message: String,
+        data: serde_json::Value,
+    ) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: None,
+            error: Some(JsonRpcError { code, message, data: Some(data) }),
         }
     }
 }
@@ -324,6 +539,14 @@ pub struct ResourceDefinition {
     pub mime_type: String,
 }
 
+/// Resource template for MCP: resources whose URIs take parameters
+pub struct ResourceTemplate {
+    pub uri_template: String,
+    pub name: String,
+    pub description: String,
+    pub mime_type: String,
+}
+
 // =============================================================================
 // HTTP Handlers for Docker/container deployment
 // =============================================================================
@@ -334,10 +557,58 @@ async fn health_check() -> (StatusCode, &'static str) {
 }
 
 /// JSON-RPC handler for HTTP transport
+///
+/// `initialize` opens a session and returns its ID in the `Mcp-Session-Id`
+/// header; later requests send the header back to use that session's
+/// configuration. Requests without the header use the environment.
 async fn handle_jsonrpc(
     State(server): State<Arc<McpServer>>,
+    headers: HeaderMap,
     Json(request): Json<JsonRpcRequest>,
-) -> Json<JsonRpcResponse> {
-    let response = server.handle_request(request).await;
-    Json(response)
+) -> Response {
+    if !server.is_authorized(&headers) {
+        let response = JsonRpcResponse::error(request.id, -32001, "Unauthorized".to_string());
+        return (StatusCode::UNAUTHORIZED, Json(response)).into_response();
+    }
+
+    if request.method == "initialize" {
+        let (session_id, session) = match server.open_session(request.params.as_ref()) {
+            Ok(opened) => opened,
+            Err(e) => return Json(JsonRpcResponse::error(request.id, -32602, e)).into_response(),
+        };
+        let response = server.handle_request(request, &session).await;
+        let mut response = Json(response).into_response();
+        if let Ok(value) = HeaderValue::from_str(&session_id) {
+            response.headers_mut().insert(SESSION_HEADER, value);
+        }
+        return response;
+    }
+
+    let session = match headers.get(SESSION_HEADER).and_then(|v| v.to_str().ok()) {
+        Some(session_id) => match server.session(session_id) {
+            Some(session) => session,
+            None => {
+                let response = JsonRpcResponse::error(request.id, -32002, format!("Unknown session: {}", session_id));
+                return (StatusCode::NOT_FOUND, Json(response)).into_response();
+            }
+        },
+        None => Arc::new(SessionConfig::default()),
+    };
+
+    Json(server.handle_request(request, &session).await).into_response()
+}
+
+/// End the session named by the `Mcp-Session-Id` header
+async fn close_session(
+    State(server): State<Arc<McpServer>>,
+    headers: HeaderMap,
+) -> StatusCode {
+    if !server.is_authorized(&headers) {
+        return StatusCode::UNAUTHORIZED;
+    }
+    match headers.get(SESSION_HEADER).and_then(|v| v.to_str().ok()) {
+        Some(session_id) if server.close_session_id(session_id) => StatusCode::NO_CONTENT,
+        Some(_) => StatusCode::NOT_FOUND,
+        None => StatusCode::BAD_REQUEST,
+    }
 }
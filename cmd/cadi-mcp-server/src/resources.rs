@@ -2,9 +2,70 @@
 //!
 //! Resources provide context and documentation to AI agents.
 //! The usage guide explains how to use CADI to save tokens.
-
-use crate::protocol::ResourceDefinition;
+//!
+//! Resource templates expose chunks of the graph directly:
+//! `cadi://view/<chunk-id>?depth=1&format=signatures` is a rehydrated view
+//! and `cadi://content/<chunk-id>` the raw content. Clients can subscribe to
+//! any resource; subscribed resources are re-read every [`POLL_INTERVAL`]
+//! and reported when their contents change.
+
+use crate::orientation::{self, GraphSummary};
+use crate::protocol::{ResourceDefinition, ResourceTemplate};
+use crate::session::SessionConfig;
+use cadi_core::graph::GraphStore;
+use cadi_core::rehydration::{RehydrationEngine, ViewConfig, ViewFormat};
+use cadi_core::ChunkId;
 use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// How often subscribed resources are re-read to look for changes
+pub const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// JSON-RPC code for malformed resource URIs
+const INVALID_PARAMS: i32 = -32602;
+
+/// MCP code for resources that do not exist
+const RESOURCE_NOT_FOUND: i32 = -32002;
+
+/// Expansion depth of a view when the URI does not give one
+const DEFAULT_VIEW_DEPTH: usize = 1;
+
+/// A resource read that failed because of the request, with the JSON-RPC
+/// error code and data to report it with
+#[derive(Debug)]
+pub struct ResourceError {
+    pub code: i32,
+    pub message: String,
+    pub data: Value,
+}
+
+impl ResourceError {
+    fn invalid(uri: &str, message: String) -> Self {
+        Self {
+            code: INVALID_PARAMS,
+            message,
+            data: json!({ "uri": uri }),
+        }
+    }
+
+    fn not_found(uri: &str, chunk_id: &str) -> Self {
+        Self {
+            code: RESOURCE_NOT_FOUND,
+            message: format!("Chunk {} is not in the graph", chunk_id),
+            data: json!({ "uri": uri, "chunk_id": chunk_id }),
+        }
+    }
+}
+
+impl std::fmt::Display for ResourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ResourceError {}
 
 /// Get all available resources
 pub fn get_resources() -> Vec<ResourceDefinition> {
@@ -21,6 +82,12 @@ pub fn get_resources() -> Vec<ResourceDefinition> {
             description: "List of all cached chunk aliases - use instead of reading files".to_string(),
             mime_type: "application/json".to_string(),
         },
+        ResourceDefinition {
+            uri: "cadi://graph/summary".to_string(),
+            name: "Project Graph Summary".to_string(),
+            description: "Namespaces, most-connected chunks with aliases, and languages of the imported code".to_string(),
+            mime_type: "application/json".to_string(),
+        },
         ResourceDefinition {
             uri: "cadi://config".to_string(),
             name: "CADI Configuration".to_string(),
@@ -33,6 +100,12 @@ pub fn get_resources() -> Vec<ResourceDefinition> {
             description: "Local cache usage statistics".to_string(),
             mime_type: "application/json".to_string(),
         },
+        ResourceDefinition {
+            uri: "cadi://stats".to_string(),
+            name: "Repository Statistics".to_string(),
+            description: "Chunk, graph, deduplication and build cache statistics".to_string(),
+            mime_type: "application/json".to_string(),
+        },
         ResourceDefinition {
             uri: "cadi://registries".to_string(),
             name: "Registry Federation".to_string(),
@@ -48,22 +121,47 @@ pub fn get_resources() -> Vec<ResourceDefinition> {
     ]
 }
 
+/// Get the templates of parameterized resources
+pub fn get_resource_templates() -> Vec<ResourceTemplate> {
+    vec![
+        ResourceTemplate {
+            uri_template: "cadi://view/{chunk_id}{?depth,format}".to_string(),
+            name: "Virtual View".to_string(),
+            description: "Chunk assembled with its dependencies up to `depth` (default 1); format is source, minimal, documented, signatures or json".to_string(),
+            mime_type: "text/plain".to_string(),
+        },
+        ResourceTemplate {
+            uri_template: "cadi://content/{chunk_id}".to_string(),
+            name: "Chunk Content".to_string(),
+            description: "Raw content of a chunk in the graph".to_string(),
+            mime_type: "text/plain".to_string(),
+        },
+    ]
+}
+
 /// Read a resource by URI
 pub async fn read_resource(
     uri: &str,
+    session: &SessionConfig,
 ) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
     match uri {
         "cadi://guide" => read_usage_guide().await,
         "cadi://aliases" => read_aliases().await,
-        "cadi://config" => read_config().await,
-        "cadi://cache/stats" => read_cache_stats().await,
-        "cadi://registries" => read_registries().await,
-        "cadi://trust/policy" => read_trust_policy().await,
+        "cadi://graph/summary" => read_graph_summary(session).await,
+        "cadi://config" => read_config(session).await,
+        "cadi://cache/stats" => read_cache_stats(session).await,
+        "cadi://stats" => read_stats().await,
+        "cadi://registries" => read_registries(session).await,
+        "cadi://trust/policy" => read_trust_policy(session).await,
         _ => {
-            // Try to parse as chunk URI
-            if uri.starts_with("cadi://chunk/") {
-                let chunk_id = uri.strip_prefix("cadi://chunk/").unwrap();
-                read_chunk(chunk_id).await
+            if let Some(rest) = uri.strip_prefix("cadi://view/") {
+                let request = ViewRequest::parse(uri, rest)?;
+                read_view(uri, &request, GraphStore::open(orientation::graph_dir())?).await
+            } else if let Some(chunk_id) = uri.strip_prefix("cadi://content/") {
+                let chunk_id = parse_chunk_id(uri, chunk_id)?;
+                read_content(uri, &chunk_id, &GraphStore::open(orientation::graph_dir())?)
+            } else if let Some(chunk_id) = uri.strip_prefix("cadi://chunk/") {
+                read_chunk(chunk_id, session).await
             } else {
                 Err(format!("Unknown resource: {}", uri).into())
             }
@@ -128,6 +226,7 @@ cadi_import(path: "./new-code", publish: true)
 
 ### Import & Publish  
 - `cadi_import` - Import any codebase (analyze, chunk, alias)
+- `cadi_atomize` - Register a snippet from context as chunks
 - `cadi_publish` - Publish chunks to registry
 
 ### Build & Verify
@@ -138,7 +237,7 @@ cadi_import(path: "./new-code", publish: true)
 
 ### Retrieve
 - `cadi_get_chunk` - Get chunk content by ID
-- `cadi_scaffold` - Generate project from manifest
+- `cadi_scaffold` - Write a manifest's chunks back out as source files
 
 ## 💡 BEST PRACTICES
 
@@ -207,9 +306,28 @@ async fn read_aliases() -> Result<Vec<Value>, Box<dyn std::error::Error + Send +
     })])
 }
 
-async fn read_config() -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
-    let registry_url = std::env::var("CADI_REGISTRY").unwrap_or_else(|_| "http://localhost:8080".to_string());
-    let storage = std::env::var("CADI_STORAGE").unwrap_or_else(|_| ".cadi-repo".to_string());
+async fn read_graph_summary(session: &SessionConfig) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+    let registry_url = session.registry_url().unwrap_or_else(|| "http://localhost:8080".to_string());
+
+    let summary = match GraphSummary::load(&orientation::graph_dir(), &registry_url) {
+        Some(summary) => serde_json::to_value(&summary)?,
+        None => json!({
+            "chunks": 0,
+            "registry_url": registry_url,
+            "hint": "Run cadi_import to populate the graph"
+        }),
+    };
+
+    Ok(vec![json!({
+        "uri": "cadi://graph/summary",
+        "mimeType": "application/json",
+        "text": serde_json::to_string_pretty(&summary)?
+    })])
+}
+
+async fn read_config(session: &SessionConfig) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+    let registry_url = session.registry_url().unwrap_or_else(|| "http://localhost:8080".to_string());
+    let storage = session.storage_path();
     
     let config = json!({
         "version": env!("CARGO_PKG_VERSION"),
@@ -235,9 +353,8 @@ async fn read_config() -> Result<Vec<Value>, Box<dyn std::error::Error + Send +
     })])
 }
 
-async fn read_cache_stats() -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
-    let cadi_storage = std::env::var("CADI_STORAGE").unwrap_or_else(|_| ".cadi-repo".to_string());
-    let cadi_repo = std::path::PathBuf::from(&cadi_storage);
+async fn read_cache_stats(session: &SessionConfig) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+    let cadi_repo = session.storage_path();
     
     let (entries, total_size_bytes) = if cadi_repo.exists() {
         let mut count = 0;
@@ -262,7 +379,7 @@ async fn read_cache_stats() -> Result<Vec<Value>, Box<dyn std::error::Error + Se
         "total_size_bytes": total_size_bytes,
         "hit_rate": 0.0,
         "last_gc": null,
-        "storage_path": cadi_storage
+        "storage_path": cadi_repo
     });
     
     Ok(vec![json!({
@@ -272,8 +389,30 @@ async fn read_cache_stats() -> Result<Vec<Value>, Box<dyn std::error::Error + Se
     })])
 }
 
-async fn read_registries() -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
-    let cadi_registry = std::env::var("CADI_REGISTRY").unwrap_or_else(|_| "http://localhost:8080".to_string());
+async fn read_stats() -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+    let cache_dir = std::env::var("CADI_CACHE_DIR")
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(|_| cadi_builder::BuildConfig::default().cache_dir);
+
+    let repository = cadi_core::stats::StatsCollector::new()
+        .with_chunk_dir(cache_dir.join("chunks"))
+        .with_graph_dir(cache_dir.join("graph-db"))
+        .collect()?;
+    let builds = cadi_builder::BuildCache::new(cache_dir.clone()).history_stats(20)?;
+
+    let mut stats = serde_json::to_value(&repository)?;
+    stats["builds"] = serde_json::to_value(&builds)?;
+    stats["cache_dir"] = json!(cache_dir);
+
+    Ok(vec![json!({
+        "uri": "cadi://stats",
+        "mimeType": "application/json",
+        "text": serde_json::to_string_pretty(&stats)?
+    })])
+}
+
+async fn read_registries(session: &SessionConfig) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+    let cadi_registry = session.registry_url().unwrap_or_else(|| "http://localhost:8080".to_string());
     
     let registries = json!({
         "registries": [
@@ -282,7 +421,7 @@ async fn read_registries() -> Result<Vec<Value>, Box<dyn std::error::Error + Sen
                 "name": "primary",
                 "priority": 100,
                 "federation": true,
-                "storage_path": ".cadi-repo"
+                "storage_path": session.storage_path()
             }
         ],
         "federation_enabled": true,
@@ -296,9 +435,8 @@ async fn read_registries() -> Result<Vec<Value>, Box<dyn std::error::Error + Sen
     })])
 }
 
-async fn read_trust_policy() -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
-    let cadi_storage = std::env::var("CADI_STORAGE").unwrap_or_else(|_| ".cadi-repo".to_string());
-    let trust_file = std::path::PathBuf::from(&cadi_storage).join("trust-policy.json");
+async fn read_trust_policy(session: &SessionConfig) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+    let trust_file = session.storage_path().join("trust-policy.json");
     
     let policy = if trust_file.exists() {
         std::fs::read_to_string(&trust_file)
@@ -326,9 +464,8 @@ async fn read_trust_policy() -> Result<Vec<Value>, Box<dyn std::error::Error + S
     })])
 }
 
-async fn read_chunk(chunk_id: &str) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
-    let cadi_storage = std::env::var("CADI_STORAGE").unwrap_or_else(|_| ".cadi-repo".to_string());
-    let cadi_repo = std::path::PathBuf::from(&cadi_storage);
+async fn read_chunk(chunk_id: &str, session: &SessionConfig) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+    let cadi_repo = session.storage_path();
     
     // Read metadata to validate chunk exists
     if let Ok(metadata_content) = std::fs::read_to_string(cadi_repo.join("metadata.json")) {
@@ -364,3 +501,289 @@ async fn read_chunk(chunk_id: &str) -> Result<Vec<Value>, Box<dyn std::error::Er
         "text": serde_json::json!({"error": format!("Chunk {} not found", chunk_id)}).to_string()
     })])
 }
+
+/// Parameters of a `cadi://view/` URI
+#[derive(Debug, PartialEq)]
+struct ViewRequest {
+    chunk_id: String,
+    depth: usize,
+    format: ViewFormat,
+}
+
+impl ViewRequest {
+    /// Parse the part of `uri` after `cadi://view/`
+    fn parse(uri: &str, rest: &str) -> Result<Self, ResourceError> {
+        let (chunk_id, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let mut request = Self {
+            chunk_id: parse_chunk_id(uri, chunk_id)?,
+            depth: DEFAULT_VIEW_DEPTH,
+            format: ViewFormat::Source,
+        };
+
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = percent_decode(value);
+            match name {
+                "depth" => {
+                    request.depth = value
+                        .parse()
+                        .map_err(|_| ResourceError::invalid(uri, format!("Invalid depth: {}", value)))?;
+                }
+                "format" => {
+                    request.format = value.parse().map_err(|e: String| ResourceError::invalid(uri, e))?;
+                }
+                other => return Err(ResourceError::invalid(uri, format!("Unknown view parameter: {}", other))),
+            }
+        }
+        Ok(request)
+    }
+}
+
+/// Chunk ID from a resource URI, which clients may have percent-encoded
+fn parse_chunk_id(uri: &str, raw: &str) -> Result<String, ResourceError> {
+    let chunk_id = percent_decode(raw);
+    match ChunkId::parse(&chunk_id) {
+        Ok(_) => Ok(chunk_id),
+        Err(e) => Err(ResourceError {
+            code: INVALID_PARAMS,
+            message: e.to_string(),
+            data: json!({ "uri": uri, "chunk_id": chunk_id }),
+        }),
+    }
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// MIME type of source code in `language`
+fn language_mime_type(language: &str) -> &'static str {
+    match language {
+        "rust" => "text/x-rust",
+        "python" => "text/x-python",
+        "typescript" | "tsx" => "text/x-typescript",
+        "javascript" | "jsx" => "text/javascript",
+        "go" => "text/x-go",
+        "c" => "text/x-c",
+        "cpp" => "text/x-c++",
+        "java" => "text/x-java",
+        "markdown" => "text/markdown",
+        "json" => "application/json",
+        "yaml" => "application/yaml",
+        "toml" => "application/toml",
+        "html" => "text/html",
+        "css" => "text/css",
+        _ => "text/plain",
+    }
+}
+
+/// A rehydrated view, one content entry per language in the view
+async fn read_view(
+    uri: &str,
+    request: &ViewRequest,
+    graph: GraphStore,
+) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+    if !graph.node_exists(&request.chunk_id)? {
+        return Err(ResourceError::not_found(uri, &request.chunk_id).into());
+    }
+
+    let config = ViewConfig {
+        format: request.format,
+        ..Default::default()
+    }
+    .with_expansion(request.depth);
+    let view = RehydrationEngine::new(graph).create_view(vec![request.chunk_id.clone()], config).await?;
+
+    Ok(view
+        .files
+        .iter()
+        .map(|file| {
+            let mime_type = match request.format {
+                ViewFormat::Json => "application/json",
+                _ => language_mime_type(&file.language),
+            };
+            json!({
+                "uri": uri,
+                "mimeType": mime_type,
+                "text": file.source
+            })
+        })
+        .collect())
+}
+
+/// Raw content of a chunk in the graph
+fn read_content(
+    uri: &str,
+    chunk_id: &str,
+    graph: &GraphStore,
+) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+    let (Some(node), Some(content)) = (graph.get_node(chunk_id)?, graph.get_content_str(chunk_id)?) else {
+        return Err(ResourceError::not_found(uri, chunk_id).into());
+    };
+
+    Ok(vec![json!({
+        "uri": uri,
+        "mimeType": language_mime_type(&node.language),
+        "text": content
+    })])
+}
+
+/// Resources a client subscribed to, each with the session it subscribed
+/// in and a hash of its contents when last read
+#[derive(Default)]
+pub struct Subscriptions {
+    resources: HashMap<String, (SessionConfig, String)>,
+}
+
+impl Subscriptions {
+    /// Subscribe to `uri`, which must be readable now
+    pub async fn subscribe(
+        &mut self,
+        uri: &str,
+        session: &SessionConfig,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let contents = read_resource(uri, session).await?;
+        self.resources
+            .insert(uri.to_string(), (session.clone(), fingerprint(&Value::from(contents).to_string())));
+        Ok(())
+    }
+
+    pub fn unsubscribe(&mut self, uri: &str) -> bool {
+        self.resources.remove(uri).is_some()
+    }
+
+    /// Re-read every subscribed resource and return the URIs whose contents
+    /// changed, sorted. A resource that stops existing counts as changed;
+    /// one that cannot be read for another reason, such as the graph store
+    /// being held by a running tool, is checked again at the next poll.
+    pub async fn poll(&mut self) -> Vec<String> {
+        let mut changed = Vec::new();
+        for (uri, (session, last)) in self.resources.iter_mut() {
+            let current = match read_resource(uri, session).await {
+                Ok(contents) => fingerprint(&Value::from(contents).to_string()),
+                Err(e) => match e.downcast_ref::<ResourceError>() {
+                    Some(e) => fingerprint(&e.message),
+                    None => continue,
+                },
+            };
+            if *last != current {
+                *last = current;
+                changed.push(uri.clone());
+            }
+        }
+        changed.sort();
+        changed
+    }
+}
+
+fn fingerprint(text: &str) -> String {
+    format!("{:x}", Sha256::digest(text.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cadi_core::graph::{EdgeType, GraphNode};
+
+    const PARSE: &str = "pub fn parse(input: &str) -> Token {\n    Token::new(input)\n}";
+    const TOKEN: &str = "pub struct Token {\n    text: String,\n}";
+
+    fn store() -> (GraphStore, String, String) {
+        let store = GraphStore::in_memory().unwrap();
+        let parse = cadi_core::chunk_id_from_content(PARSE.as_bytes());
+        let token = cadi_core::chunk_id_from_content(TOKEN.as_bytes());
+        for (id, content, alias) in [(&parse, PARSE, "app/parse"), (&token, TOKEN, "app/token")] {
+            let node = GraphNode::new(id.as_str(), id.as_str())
+                .with_alias(alias)
+                .with_language("rust")
+                .with_granularity("function");
+            store.insert_node(&node).unwrap();
+            store.store_content(id, content.as_bytes()).unwrap();
+        }
+        store.add_dependency(&parse, &token, EdgeType::Imports).unwrap();
+        (store, parse, token)
+    }
+
+    #[test]
+    fn test_view_uri_parameters() {
+        let (_, parse, _) = store();
+        let encoded = parse.replace(':', "%3A");
+        let uri = format!("cadi://view/{}?depth=0&format=signatures", encoded);
+        let request = ViewRequest::parse(&uri, uri.strip_prefix("cadi://view/").unwrap()).unwrap();
+        assert_eq!(
+            request,
+            ViewRequest {
+                chunk_id: parse.clone(),
+                depth: 0,
+                format: ViewFormat::Signatures
+            }
+        );
+
+        let error = ViewRequest::parse("cadi://view/abc", "abc").unwrap_err();
+        assert_eq!((error.code, error.data["chunk_id"].as_str()), (INVALID_PARAMS, Some("abc")));
+        let error = ViewRequest::parse("", &format!("{}?format=poem", parse)).unwrap_err();
+        assert_eq!(error.message, "Unknown view format: poem");
+        let error = ViewRequest::parse("", &format!("{}?dpeth=1", parse)).unwrap_err();
+        assert_eq!(error.message, "Unknown view parameter: dpeth");
+    }
+
+    #[tokio::test]
+    async fn test_view_includes_dependencies_up_to_depth() {
+        let (graph, parse, _) = store();
+        let request = ViewRequest {
+            chunk_id: parse.clone(),
+            depth: 1,
+            format: ViewFormat::Source,
+        };
+        let contents = read_view("cadi://view/x", &request, graph).await.unwrap();
+        assert_eq!(contents.len(), 1);
+        assert_eq!(contents[0]["mimeType"], "text/x-rust");
+        let text = contents[0]["text"].as_str().unwrap();
+        assert!(text.contains("pub struct Token") && text.contains("Token::new(input)"), "{}", text);
+
+        let (graph, _, _) = store();
+        let request = ViewRequest { depth: 0, ..request };
+        let contents = read_view("cadi://view/x", &request, graph).await.unwrap();
+        assert!(!contents[0]["text"].as_str().unwrap().contains("pub struct Token"));
+    }
+
+    #[tokio::test]
+    async fn test_missing_chunks_are_not_found() {
+        let (graph, _, token) = store();
+        let contents = read_content("cadi://content/t", &token, &graph).unwrap();
+        assert_eq!((contents[0]["mimeType"].as_str(), contents[0]["text"].as_str()), (Some("text/x-rust"), Some(TOKEN)));
+
+        let missing = cadi_core::chunk_id_from_content(b"missing");
+        let error = read_content("cadi://content/m", &missing, &graph).unwrap_err();
+        let error = error.downcast::<ResourceError>().unwrap();
+        assert_eq!(error.code, RESOURCE_NOT_FOUND);
+        assert_eq!(error.data, json!({"uri": "cadi://content/m", "chunk_id": missing}));
+
+        let request = ViewRequest {
+            chunk_id: missing,
+            depth: 1,
+            format: ViewFormat::Source,
+        };
+        let error = read_view("cadi://view/m", &request, graph).await.unwrap_err();
+        assert_eq!(error.downcast::<ResourceError>().unwrap().code, RESOURCE_NOT_FOUND);
+    }
+}
@@ -0,0 +1,135 @@
+//! Per-session configuration
+//!
+//! Clients pass a `configuration` object in the `initialize` params. Every
+//! value they leave out falls back to the process environment, so a single
+//! server can serve several tenants with different registries and storage.
+//! The server's registry token only goes to the server's registry, and HTTP
+//! clients keep their storage under the server's.
+
+use serde::Deserialize;
+use std::path::{Component, PathBuf};
+
+/// Header carrying the session ID in HTTP mode
+pub const SESSION_HEADER: &str = "mcp-session-id";
+
+/// Configuration a client supplies when it initializes a session
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SessionConfig {
+    /// Registry to publish to and read from
+    #[serde(default)]
+    pub registry_url: Option<String>,
+    /// Registry auth token
+    #[serde(default)]
+    pub token: Option<String>,
+    /// Default namespace for aliases
+    #[serde(default)]
+    pub namespace: Option<String>,
+    /// Local chunk storage directory
+    #[serde(default)]
+    pub storage_path: Option<PathBuf>,
+}
+
+impl SessionConfig {
+    /// Read the `configuration` object from `initialize` params
+    pub fn from_initialize(params: Option<&serde_json::Value>) -> Result<Self, String> {
+        match params.and_then(|p| p.get("configuration")) {
+            None | Some(serde_json::Value::Null) => Ok(Self::default()),
+            Some(config) => serde_json::from_value(config.clone())
+                .map_err(|e| format!("Invalid configuration: {}", e)),
+        }
+    }
+
+    /// Registry URL, falling back to `CADI_REGISTRY`
+    pub fn registry_url(&self) -> Option<String> {
+        self.registry_url.clone().or_else(|| std::env::var("CADI_REGISTRY").ok())
+    }
+
+    /// Token to send to `registry_url`: the session's own, or
+    /// `CADI_AUTH_TOKEN` when `registry_url` is the `CADI_REGISTRY` it was
+    /// configured for. A URL a client chose never gets the server's token.
+    pub fn token_for(&self, registry_url: &str) -> Option<String> {
+        let env_registry = std::env::var("CADI_REGISTRY").ok();
+        self.token_with_env(registry_url, env_registry.as_deref(), || std::env::var("CADI_AUTH_TOKEN").ok())
+    }
+
+    fn token_with_env(
+        &self,
+        registry_url: &str,
+        env_registry: Option<&str>,
+        env_token: impl FnOnce() -> Option<String>,
+    ) -> Option<String> {
+        match &self.token {
+            Some(token) => Some(token.clone()),
+            None if env_registry == Some(registry_url) => env_token(),
+            None => None,
+        }
+    }
+
+    /// Alias namespace, falling back to `CADI_NAMESPACE`
+    pub fn namespace(&self) -> Option<String> {
+        self.namespace.clone().or_else(|| std::env::var("CADI_NAMESPACE").ok())
+    }
+
+    /// Storage directory, falling back to `CADI_STORAGE` and then `.cadi-repo`
+    pub fn storage_path(&self) -> PathBuf {
+        self.storage_path.clone().unwrap_or_else(default_storage_path)
+    }
+
+    /// Confine the storage path of a remote client to a directory under the
+    /// server's own storage, so it can't read or write anywhere else.
+    /// Absolute paths and `..` are rejected.
+    pub fn confine_storage(mut self) -> Result<Self, String> {
+        if let Some(path) = self.storage_path.take() {
+            if !path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+                return Err(format!(
+                    "Invalid configuration: storage_path must be relative to the server's storage: {}",
+                    path.display()
+                ));
+            }
+            self.storage_path = Some(default_storage_path().join(path));
+        }
+        Ok(self)
+    }
+}
+
+fn default_storage_path() -> PathBuf {
+    PathBuf::from(std::env::var("CADI_STORAGE").unwrap_or_else(|_| ".cadi-repo".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_storage_stays_under_the_server_storage() {
+        let config = |path: &str| SessionConfig {
+            storage_path: Some(PathBuf::from(path)),
+            ..Default::default()
+        };
+
+        let confined = config("tenant-a/repo").confine_storage().unwrap();
+        assert_eq!(confined.storage_path(), default_storage_path().join("tenant-a/repo"));
+        for escaping in ["/etc", "../other", "tenant-a/../../other"] {
+            assert!(config(escaping).confine_storage().is_err(), "{} was accepted", escaping);
+        }
+        assert_eq!(SessionConfig::default().confine_storage().unwrap().storage_path(), default_storage_path());
+    }
+
+    #[test]
+    fn test_server_token_only_goes_to_the_server_registry() {
+        let env_registry = Some("https://registry.internal");
+        let env_token = || Some("operator-token".to_string());
+
+        let session = SessionConfig::default();
+        let token = |url| session.token_with_env(url, env_registry, env_token);
+        assert_eq!(token("https://registry.internal").as_deref(), Some("operator-token"));
+        assert_eq!(token("https://attacker.example"), None);
+        assert_eq!(session.token_with_env("https://registry.internal", None, env_token), None);
+
+        let own = SessionConfig {
+            token: Some("client-token".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(own.token_with_env("https://attacker.example", env_registry, env_token).as_deref(), Some("client-token"));
+    }
+}
@@ -4,13 +4,14 @@
 //! Always search before writing new code!
 
 use crate::protocol::ToolDefinition;
+use crate::session::SessionConfig;
 use serde_json::{json, Value};
 
 #[allow(unused_imports)]
 use std::collections::HashMap;
 
-use cadi_registry::db::{RegistryDatabase, SearchQuery};
-use cadi_llm::embeddings::{EmbeddingManager, MockProvider};
+use cadi_registry::db::{RegistryDatabase, SearchMode, SearchQuery};
+use cadi_llm::embeddings::{provider_from_env, EmbeddingManager};
 
 /// Get all available tools
 pub fn get_tools() -> Vec<ToolDefinition> {
@@ -35,6 +36,32 @@ pub fn get_tools() -> Vec<ToolDefinition> {
                         "items": { "type": "string" },
                         "description": "Filter by concepts (e.g., ['http', 'server'])"
                     },
+                    "granularity": {
+                        "type": "string",
+                        "enum": ["function", "type", "module", "package", "project", "import"],
+                        "description": "Only chunks of this granularity"
+                    },
+                    "categories": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Only chunks in any of these categories (e.g., ['utility', 'api', 'ui'])"
+                    },
+                    "min_category_confidence": {
+                        "type": "number",
+                        "description": "Minimum confidence (0.0-1.0) of the chunk's inferred category"
+                    },
+                    "max_size": {
+                        "type": "integer",
+                        "description": "Maximum chunk size in bytes"
+                    },
+                    "min_quality": {
+                        "type": "number",
+                        "description": "Minimum quality score (0.0-1.0)"
+                    },
+                    "max_complexity": {
+                        "type": "number",
+                        "description": "Maximum cyclomatic complexity; chunks without metrics are excluded"
+                    },
                     "limit": {
                         "type": "integer",
                         "description": "Maximum results to return",
@@ -46,7 +73,7 @@ pub fn get_tools() -> Vec<ToolDefinition> {
         },
         ToolDefinition {
             name: "cadi_resolve_alias".to_string(),
-            description: "⚡ FAST LOOKUP (~30 tokens). Resolve a human-readable alias to chunk ID. Use for known chunks like 'myproject/utils/logger'.".to_string(),
+            description: "⚡ FAST LOOKUP (~30 tokens). Resolve a human-readable alias to chunk ID. Use for known chunks like 'myproject/utils/logger'. Case-insensitive, '-' and '_' are interchangeable, and the namespace may be omitted; unknown aliases return the closest suggestions.".to_string(),
             input_schema: json!({
                 "type": "object",
                 "properties": {
@@ -135,11 +162,42 @@ pub fn get_tools() -> Vec<ToolDefinition> {
                     "registry": {
                         "type": "string",
                         "description": "Registry URL for publishing"
+                    },
+                    "reconcile": {
+                        "type": "boolean",
+                        "description": "Ask the registry which chunks it already holds and reuse them instead of publishing them again (default: same as publish)"
+                    },
+                    "write_manifest": {
+                        "type": "boolean",
+                        "description": "Write a build-ready manifest (<project>.cadi.yaml) to the project directory",
+                        "default": true
                     }
                 },
                 "required": ["path"]
             }),
         },
+        ToolDefinition {
+            name: "cadi_atomize".to_string(),
+            description: "Register a code snippet as chunks without writing it to disk. Returns chunk IDs and aliases; atomizing the same source again returns the same IDs.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "source": {
+                        "type": "string",
+                        "description": "Source code to atomize"
+                    },
+                    "language": {
+                        "type": "string",
+                        "description": "Language of the source (rust, typescript, python, ...)"
+                    },
+                    "namespace": {
+                        "type": "string",
+                        "description": "Namespace for aliases (e.g., 'my-org')"
+                    }
+                },
+                "required": ["source", "language"]
+            }),
+        },
         ToolDefinition {
             name: "cadi_publish".to_string(),
             description: "Publish chunks to registry for team sharing. Share solutions so others don't rewrite them.".to_string(),
@@ -199,7 +257,7 @@ pub fn get_tools() -> Vec<ToolDefinition> {
         },
         ToolDefinition {
             name: "cadi_plan".to_string(),
-            description: "Show the build plan for a manifest without executing it".to_string(),
+            description: "Show the build plan for a manifest without executing it, with each step marked cached, rebuild or new against the build cache (JSON)".to_string(),
             input_schema: json!({
                 "type": "object",
                 "properties": {
@@ -268,7 +326,7 @@ pub fn get_tools() -> Vec<ToolDefinition> {
         },
         ToolDefinition {
             name: "cadi_scaffold".to_string(),
-            description: "Scaffold a project directory structure from a CADI manifest. Generate full project from chunks.".to_string(),
+            description: "Scaffold a project directory structure from a CADI manifest, writing each chunk back to its source file. Reports the files written and skipped.".to_string(),
             input_schema: json!({
                 "type": "object",
                 "properties": {
@@ -283,8 +341,13 @@ pub fn get_tools() -> Vec<ToolDefinition> {
                     },
                     "force": {
                         "type": "boolean",
-                        "description": "Overwrite existing files",
+                        "description": "Overwrite existing files; otherwise they are skipped",
                         "default": false
+                    },
+                    "fetch": {
+                        "type": "boolean",
+                        "description": "Fetch chunks missing locally from the session registry",
+                        "default": true
                     }
                 },
                 "required": ["manifest"]
@@ -313,10 +376,36 @@ pub fn get_tools() -> Vec<ToolDefinition> {
                         "default": "source",
                         "description": "Output format: source (full), minimal (no comments), documented (with docs), signatures (types only)"
                     },
+                    "ordering": {
+                        "type": "string",
+                        "enum": ["topological", "by_name", "as_requested"],
+                        "default": "topological",
+                        "description": "Atom layout: topological (definitions before uses), by_name (by alias), as_requested (request order, ghost imports last)"
+                    },
                     "max_tokens": {
                         "type": "integer",
                         "default": 8000,
                         "description": "Maximum tokens to include (truncates if exceeded)"
+                    },
+                    "include_tests": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "Add the tests covering the requested atoms in a separate \"--- tests ---\" section after the code. Useful when fixing a bug in them."
+                    },
+                    "max_tests": {
+                        "type": "integer",
+                        "default": 5,
+                        "description": "Most tests to add with include_tests"
+                    },
+                    "include_source_map": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "Also return a source map: runs of view lines (1-based, counted across all files of the view) with the chunk, original file and original line they were copied from. Use it to apply edits made in the view back to the project."
+                    },
+                    "token_estimator": {
+                        "type": "string",
+                        "default": "bytes",
+                        "description": "How tokens are counted against max_tokens: bytes (~4 bytes per token), calibrated (separate code and prose ratios, CJK counted per character), or bpe:<path to a .tiktoken vocabulary>"
                     }
                 },
                 "required": ["atoms"]
@@ -393,40 +482,173 @@ pub fn get_tools() -> Vec<ToolDefinition> {
                         "type": "integer",
                         "description": "Maximum tokens to include",
                         "default": 4000
+                    },
+                    "edge_rules": {
+                        "type": "object",
+                        "description": "Per-edge-type overrides keyed by edge type (imports, type_ref, calls, composed_of, implements, extends, exports, generic_ref, macro_use, tests, doc_ref, equivalent_to, refines)",
+                        "additionalProperties": {
+                            "type": "object",
+                            "properties": {
+                                "include": {
+                                    "type": "boolean",
+                                    "default": true,
+                                    "description": "Follow this edge type at all"
+                                },
+                                "max_depth": {
+                                    "type": "integer",
+                                    "description": "Deepest expansion level reachable through this edge type (defaults to the policy depth)"
+                                }
+                            }
+                        }
+                    },
+                    "include_tests": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "Also include the tests covering the requested atoms: atoms with a tests edge to them, then test-named atoms referencing a symbol they define. Useful when fixing a bug in them."
+                    },
+                    "max_tests": {
+                        "type": "integer",
+                        "default": 5,
+                        "description": "Most tests to include with include_tests"
+                    },
+                    "search_remote": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "Search the federated registries for symbols the expanded context references but does not define; matches are listed as candidates, not included"
+                    },
+                    "token_estimator": {
+                        "type": "string",
+                        "default": "bytes",
+                        "description": "How tokens are counted against max_tokens: bytes (~4 bytes per token), calibrated (separate code and prose ratios, CJK counted per character), or bpe:<path to a .tiktoken vocabulary>"
                     }
                 },
                 "required": ["atoms"]
             }),
         },
+        ToolDefinition {
+            name: "cadi_batch".to_string(),
+            description: "📦 BATCH: Run several read-only lookups (search, resolve_alias, get_chunk, get_dependencies, get_dependents, view_context) in one call. Results come back in request order, each with its own success or error.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "calls": {
+                        "type": "array",
+                        "maxItems": MAX_BATCH_SIZE,
+                        "description": "Tool calls to run concurrently",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "tool": {
+                                    "type": "string",
+                                    "description": "Tool to call; the cadi_ prefix may be left out"
+                                },
+                                "arguments": {
+                                    "type": "object",
+                                    "description": "Arguments for the tool, as if it were called directly"
+                                }
+                            },
+                            "required": ["tool"]
+                        }
+                    },
+                    "max_tokens": {
+                        "type": "integer",
+                        "default": DEFAULT_BATCH_TOKENS,
+                        "description": "Token budget for the combined response (~4 bytes per token). Results that would go over it are left out and marked; call those tools again on their own."
+                    }
+                },
+                "required": ["calls"]
+            }),
+        },
     ]
 }
 
-use cadi_registry::FederationManager;
+use cadi_registry::{FederatedRegistry, FederationManager};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Registry federation shared by all tool calls, loaded from the same
+/// config file as the CLI. Health checks run in the background.
+fn federation() -> Arc<FederationManager> {
+    static FEDERATION: OnceLock<Arc<FederationManager>> = OnceLock::new();
+    FEDERATION
+        .get_or_init(|| {
+            let manager = Arc::new(FederationManager::from_default_config().with_cache_dir(http_cache_dir()));
+            if !manager.registries().is_empty() {
+                manager.spawn_health_checks();
+            }
+            manager
+        })
+        .clone()
+}
+
+/// Registry, token and namespace a session reads with
+type SessionRegistryKey = (String, Option<String>, Option<String>);
+
+/// Registries a tool call reads from: the session's registry, with its
+/// token and namespace, when the session or `CADI_REGISTRY` names one, and
+/// the shared [`federation`] otherwise. Session managers are kept per
+/// registry, token and namespace, so their caches outlive a single call.
+fn session_federation(session: &SessionConfig) -> Arc<FederationManager> {
+    static SESSION_FEDERATIONS: OnceLock<Mutex<HashMap<SessionRegistryKey, Arc<FederationManager>>>> = OnceLock::new();
+
+    let Some(url) = session.registry_url() else {
+        return federation();
+    };
+    let key = (url.clone(), session.token_for(&url), session.namespace());
+    let mut managers = SESSION_FEDERATIONS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    managers
+        .entry(key.clone())
+        .or_insert_with(|| {
+            let (url, token, namespace) = key;
+            let mut manager = FederationManager::new().with_cache_dir(http_cache_dir());
+            let registry = FederatedRegistry {
+                id: url.clone(),
+                url,
+                token,
+                namespace,
+                priority: 0,
+                trust_level: Default::default(),
+                enabled: true,
+                regions: Vec::new(),
+                capabilities: Default::default(),
+            };
+            if let Err(e) = manager.add_registry(registry) {
+                tracing::warn!("Failed to create the session registry client: {}", e);
+            }
+            Arc::new(manager)
+        })
+        .clone()
+}
 
 /// Call a tool with the given arguments
 pub async fn call_tool(
     name: &str,
     arguments: Value,
     db: &surrealdb::Surreal<surrealdb::engine::local::Db>,
+    session: &SessionConfig,
 ) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
     match name {
-        "cadi_search" => call_search(arguments, db).await,
-        "cadi_get_chunk" => call_get_chunk(arguments, db).await,
-        "cadi_build" => call_build(arguments, db).await,
-        "cadi_plan" => call_plan(arguments, db).await,
-        "cadi_verify" => call_verify(arguments, db).await,
-        "cadi_explain" => call_explain(arguments, db).await,
-        "cadi_suggest" => call_suggest(arguments, db).await,
-        "cadi_scaffold" => call_scaffold(arguments, db).await,
-        "cadi_import" => call_import(arguments, db).await,
-        "cadi_publish" => call_publish(arguments, db).await,
-        "cadi_resolve_alias" => call_resolve_alias(arguments, db).await,
+        "cadi_search" => call_search(arguments, db, session).await,
+        "cadi_get_chunk" => call_get_chunk(arguments, db, session).await,
+        "cadi_build" => call_build(arguments, db, session).await,
+        "cadi_plan" => call_plan(arguments, db, session).await,
+        "cadi_verify" => call_verify(arguments, db, session).await,
+        "cadi_explain" => call_explain(arguments, db, session).await,
+        "cadi_suggest" => call_suggest(arguments, db, session).await,
+        "cadi_scaffold" => call_scaffold(arguments, db, session).await,
+        "cadi_import" => call_import(arguments, db, session).await,
+        "cadi_atomize" => call_atomize(arguments, db, session).await,
+        "cadi_publish" => call_publish(arguments, db, session).await,
+        "cadi_resolve_alias" => call_resolve_alias(arguments, db, session).await,
         // Phase 2: Virtual Views
-        "cadi_view_context" => call_view_context(arguments, db).await,
-        "cadi_get_dependencies" => call_get_dependencies(arguments, db).await,
-        "cadi_get_dependents" => call_get_dependents(arguments, db).await,
+        "cadi_view_context" => call_view_context(arguments, db, session).await,
+        "cadi_get_dependencies" => call_get_dependencies(arguments, db, session).await,
+        "cadi_get_dependents" => call_get_dependents(arguments, db, session).await,
         // Phase 3: Ghost Import Resolver
-        "cadi_expand_context" => call_expand_context(arguments, db).await,
+        "cadi_expand_context" => call_expand_context(arguments, db, session).await,
+        "cadi_batch" => call_batch(arguments, db, session).await,
         _ => Err(format!("Unknown tool: {}", name).into()),
     }
 }
@@ -434,13 +656,24 @@ pub async fn call_tool(
 async fn call_search(
     args: serde_json::Value,
     db: &surrealdb::Surreal<surrealdb::engine::local::Db>,
+    session: &SessionConfig,
 ) -> Result<Vec<serde_json::Value>, Box<dyn std::error::Error + Send + Sync>> {
     let query_text = args.get("query").and_then(|v| v.as_str()).unwrap_or("").to_string();
     let language = args.get("language").and_then(|v| v.as_str()).map(|s| s.to_string());
     let limit = args.get("limit").and_then(|v| v.as_u64()).unwrap_or(10) as usize;
+    let granularity = args.get("granularity").and_then(|v| v.as_str())
+        .and_then(|s| s.parse::<cadi_core::ChunkGranularity>().ok());
+    let categories: Vec<cadi_core::ChunkCategory> = args.get("categories").and_then(|v| v.as_array())
+        .map(|arr| arr.iter().filter_map(|v| v.as_str()).filter_map(|s| s.parse().ok()).collect())
+        .unwrap_or_default();
+    let min_category_confidence = args.get("min_category_confidence").and_then(|v| v.as_f64()).map(|v| v as f32);
+    let max_size = args.get("max_size").and_then(|v| v.as_u64()).map(|v| v as usize);
+    let min_quality = args.get("min_quality").and_then(|v| v.as_f64()).map(|v| v as f32);
+    let max_complexity = args.get("max_complexity").and_then(|v| v.as_f64()).map(|v| v as f32);
 
     // Create embedding manager for semantic search
-    let embedding_provider = Box::new(MockProvider); // TODO: Use OpenAI when API key available
+    let embedding_provider = provider_from_env(None).await
+        .map_err(|e| format!("Failed to configure embedding provider: {}", e))?;
     let mut embedding_manager = EmbeddingManager::new(embedding_provider, None);
 
     // Generate embedding for the query
@@ -455,9 +688,17 @@ async fn call_search(
     let search_query = SearchQuery {
         text: Some(query_text.clone()),
         embedding: Some(query_embedding),
-        language,
+        language: language.clone(),
         limit,
         min_score: 0.1,
+        granularity,
+        categories: categories.clone(),
+        min_category_confidence,
+        max_size,
+        min_quality,
+        max_complexity,
+        mode: SearchMode::Hybrid,
+        ..Default::default()
     };
 
     let results = registry_db.search(search_query).await
@@ -473,9 +714,12 @@ async fn call_search(
 
         for result in results {
             let chunk_info = format!(
-                "\n• **{}** (Score: {:.2})\n  Language: {}\n  Description: {}\n  Concepts: {}\n  Chunk ID: {}",
+                "\n• **{}** (Score: {:.2}; semantic {:.2}, keyword {:.2}, quality {:.2})\n  Language: {}\n  Description: {}\n  Concepts: {}\n  Chunk ID: {}",
                 result.metadata.name,
                 result.score,
+                result.scores.semantic.unwrap_or(0.0),
+                result.scores.keyword.unwrap_or(0.0),
+                result.scores.quality,
                 result.metadata.language,
                 result.metadata.description,
                 result.metadata.concepts.join(", "),
@@ -487,15 +731,48 @@ async fn call_search(
         responses.push(serde_json::json!({"type": "text", "text": "\n💡 Use 'cadi_get_chunk' to retrieve the full code for any chunk above."}));
     }
 
+    // Also search the session's registries, highest priority first
+    let manager = session_federation(session);
+    if !manager.registries().is_empty() {
+        let remote_query = cadi_registry::client::SearchQuery {
+            query: Some(query_text.clone()),
+            language,
+            granularity,
+            categories,
+            min_category_confidence,
+            max_size,
+            min_quality,
+            max_complexity,
+            limit,
+            ..Default::default()
+        };
+
+        match manager.search(&remote_query).await {
+            Ok(remote) if !remote.is_empty() => {
+                responses.push(serde_json::json!({"type": "text", "text": format!("\nFound {} chunks in federated registries:", remote.len())}));
+                for (chunk, registry_id) in remote {
+                    responses.push(serde_json::json!({"type": "text", "text": format!(
+                        "\n• **{}**\n  Chunk ID: {}\n  Registry: {}",
+                        chunk.name, chunk.chunk_id, registry_id
+                    )}));
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                responses.push(serde_json::json!({"type": "text", "text": format!("⚠ Federated search failed: {}", e)}));
+            }
+        }
+    }
+
     Ok(responses)
 }
 
-async fn call_get_chunk(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+async fn call_get_chunk(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>, session: &SessionConfig) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
     let chunk_id = args.get("chunk_id").and_then(|v| v.as_str()).unwrap_or("").to_string();
     let _include_source = args.get("include_source").and_then(|v| v.as_bool()).unwrap_or(true);
 
     let mut response_parts = Vec::new();
-    let manager = FederationManager::new();
+    let manager = session_federation(session);
 
     // Check federation (which might have local cache integrated or we check local first)
     match manager.fetch_chunk(&chunk_id).await {
@@ -503,19 +780,22 @@ async fn call_get_chunk(args: Value, _db: &surrealdb::Surreal<surrealdb::engine:
             response_parts.push(json!({"type": "text", "text": format!("✓ Retrieved chunk '{}' from registry '{}'", chunk_id, registry_id)}));
             response_parts.push(json!({"type": "text", "text": format!("Size: {} bytes", data.len())}));
         }
-        Err(_) => {
+        Err(RegistryError::NotFound(_)) => {
             response_parts.push(json!({"type": "text", "text": format!("✗ Chunk '{}' not found in any registry", chunk_id)}));
         }
+        Err(e) => {
+            response_parts.push(json!({"type": "text", "text": format!("✗ Failed to fetch chunk '{}': {}", chunk_id, e)}));
+        }
     }
 
     Ok(if response_parts.is_empty() { vec![json!({"type": "text", "text": "No chunk data available"})] } else { response_parts })
 }
 
 
-use cadi_builder::{BuildEngine, BuildConfig, BuildPlan};
+use cadi_builder::{BuildCache, BuildEngine, BuildConfig, BuildPlan};
 use cadi_core::Manifest;
 
-async fn call_build(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+async fn call_build(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>, _session: &SessionConfig) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
     let manifest_path = args.get("manifest").and_then(|v| v.as_str()).unwrap_or("");
     let target = args.get("target").and_then(|v| v.as_str()).unwrap_or("default");
     
@@ -547,7 +827,7 @@ async fn call_build(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::loc
     Ok(responses)
 }
 
-async fn call_plan(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+async fn call_plan(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>, _session: &SessionConfig) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
     let manifest_path = args.get("manifest").and_then(|v| v.as_str()).unwrap_or("");
     let target = args.get("target").and_then(|v| v.as_str()).unwrap_or("default");
 
@@ -561,39 +841,38 @@ async fn call_plan(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::loca
 
     match BuildPlan::from_manifest(&manifest, target) {
         Ok(plan) => {
-            let mut plan_text = format!("📋 Build Plan for {} (target: {})\n\n", path.file_name().and_then(|n| n.to_str()).unwrap_or(manifest_path), target);
-            plan_text.push_str("Build Steps:\n");
-            for (i, step) in plan.steps.iter().enumerate() {
-                plan_text.push_str(&format!("  {}. {}\n", i + 1, step.name));
-            }
-            Ok(vec![json!({"type": "text", "text": plan_text})])
+            // Annotate against the same cache the build engine uses
+            let cache = BuildCache::new(BuildConfig::default().cache_dir);
+            let annotated = plan.annotate_with_cache(&cache);
+            let result = json!({
+                "manifest": manifest_path,
+                "target": target,
+                "plan": annotated,
+            });
+            Ok(vec![json!({"type": "text", "text": serde_json::to_string_pretty(&result)?})])
         }
         Err(e) => Ok(vec![json!({"type": "text", "text": format!("✗ Failed to create build plan: {}", e)})])
     }
 }
 
 
-async fn call_verify(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+async fn call_verify(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>, session: &SessionConfig) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
     let chunk_id = args.get("chunk_id").and_then(|v| v.as_str()).unwrap_or("").to_string();
     let _rebuild = args.get("rebuild").and_then(|v| v.as_bool()).unwrap_or(false);
 
     let mut responses = vec![json!({"type": "text", "text": format!("🔐 Verifying chunk: {}\n", chunk_id)})];
-    let cadi_storage = std::env::var("CADI_STORAGE").unwrap_or_else(|_| ".cadi-repo".to_string());
-    let cadi_repo = std::path::PathBuf::from(&cadi_storage);
+    let cadi_repo = session.storage_path();
     let safe_name = chunk_id.replace(":", "_").replace("/", "_");
     let chunk_path = cadi_repo.join(format!("{}.chunk", safe_name));
 
     if chunk_path.exists() {
         responses.push(json!({"type": "text", "text": "✓ Chunk found in local storage"}));
         
-        if chunk_id.starts_with("chunk:sha256:") {
+        if let Ok(id) = cadi_core::ChunkId::parse(&chunk_id) {
             match std::fs::read(&chunk_path) {
                 Ok(content) => {
-                    use sha2::{Sha256, Digest};
-                    let mut hasher = Sha256::new();
-                    hasher.update(&content);
-                    let hash = format!("{:x}", hasher.finalize());
-                    let expected_hash = chunk_id.strip_prefix("chunk:sha256:").unwrap_or("");
+                    let hash = id.algorithm.hash_bytes(&content);
+                    let expected_hash = id.digest.as_str();
                     if hash.starts_with(expected_hash) || expected_hash.starts_with(&hash[..std::cmp::min(16, hash.len())]) {
                         responses.push(json!({"type": "text", "text": "✓ Content hash verified"}));
                     } else {
@@ -607,8 +886,9 @@ async fn call_verify(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::lo
         let metadata_path = cadi_repo.join("metadata.json");
         if let Ok(metadata_content) = std::fs::read_to_string(&metadata_path) {
             if let Ok(metadata) = serde_json::from_str::<Value>(&metadata_content) {
-                if metadata.get(&chunk_id).is_some() {
+                if let Some(chunk_meta) = metadata.get(&chunk_id) {
                     responses.push(json!({"type": "text", "text": "✓ Metadata verified"}));
+                    responses.push(json!({"type": "text", "text": verify_chunk_signatures(&chunk_id, chunk_meta)}));
                 }
             }
         }
@@ -620,12 +900,32 @@ async fn call_verify(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::lo
     Ok(responses)
 }
 
-async fn call_explain(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+/// Check a chunk's signatures against the trust store in `~/.cadi/trust`
+fn verify_chunk_signatures(chunk_id: &str, chunk_meta: &Value) -> String {
+    use cadi_core::trust::{TrustStore, TrustVerdict};
+
+    let signatures: Vec<String> = chunk_meta.get("signatures")
+        .cloned()
+        .and_then(|s| serde_json::from_value(s).ok())
+        .unwrap_or_default();
+    let trust_dir = dirs::home_dir().unwrap_or_default().join(".cadi").join("trust");
+
+    match TrustStore::open(trust_dir).verify_chunk(chunk_id, &signatures) {
+        Ok(statuses) => match TrustVerdict::from_statuses(&statuses) {
+            TrustVerdict::SignedBy(signers) => format!("✓ Signed by: {}", signers.join(", ")),
+            TrustVerdict::Unsigned => "⚠ Unsigned".to_string(),
+            TrustVerdict::UntrustedSigner => "⚠ Signed only by untrusted keys".to_string(),
+            TrustVerdict::Invalid => "✗ Invalid signature".to_string(),
+        },
+        Err(e) => format!("✗ Failed to read trust store: {}", e),
+    }
+}
+
+async fn call_explain(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>, session: &SessionConfig) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
     let chunk_id = args.get("chunk_id").and_then(|v| v.as_str()).unwrap_or("").to_string();
     let _depth = args.get("depth").and_then(|v| v.as_u64()).unwrap_or(2);
 
-    let cadi_storage = std::env::var("CADI_STORAGE").unwrap_or_else(|_| ".cadi-repo".to_string());
-    let cadi_repo = std::path::PathBuf::from(&cadi_storage);
+    let cadi_repo = session.storage_path();
     let mut explanation = format!("📖 Chunk Explanation: {}\n\n", chunk_id);
 
     let metadata_path = cadi_repo.join("metadata.json");
@@ -642,14 +942,14 @@ async fn call_explain(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::l
     Ok(vec![json!({"type": "text", "text": explanation})])
 }
 
-async fn call_suggest(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+async fn call_suggest(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>, session: &SessionConfig) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
     let task = args.get("task").and_then(|v| v.as_str()).unwrap_or("").to_lowercase();
     let language = args.get("language").and_then(|v| v.as_str()).map(|s| s.to_lowercase());
 
     let mut suggestions = Vec::new();
     suggestions.push(json!({"type": "text", "text": format!("🤖 Finding suggestions for: '{}'", task)}));
 
-    let cadi_repo = std::path::PathBuf::from(std::env::var("CADI_STORAGE").unwrap_or_else(|_| ".cadi-repo".to_string()));
+    let cadi_repo = session.storage_path();
     let mut candidates = Vec::new();
     
     if cadi_repo.exists() {
@@ -676,7 +976,13 @@ async fn call_suggest(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::l
         candidates.sort_by(|a, b| b.0.cmp(&a.0));
         suggestions.push(json!({"type": "text", "text": format!("Found {} relevant chunk(s):", std::cmp::min(5, candidates.len()))}));
         for (i, (_score, chunk_name)) in candidates.iter().take(5).enumerate() {
-            let normalized_name = chunk_name.replace("chunk_sha256_", "chunk:sha256:").trim_end_matches(".chunk").to_string();
+            let normalized_name = cadi_core::HashAlgorithm::ALL
+                .iter()
+                .fold(chunk_name.clone(), |name, algorithm| {
+                    name.replace(&format!("chunk_{}_", algorithm), &format!("chunk:{}:", algorithm))
+                })
+                .trim_end_matches(".chunk")
+                .to_string();
             suggestions.push(json!({"type": "text", "text": format!("  {}. {}", i + 1, normalized_name)}));
         }
     }
@@ -684,41 +990,95 @@ async fn call_suggest(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::l
     Ok(suggestions)
 }
 
-async fn call_scaffold(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+async fn call_scaffold(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>, session: &SessionConfig) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+    use cadi_builder::scaffold::{FileAction, OnConflict, Scaffolder};
+
     let manifest_path = args.get("manifest").and_then(|v| v.as_str()).unwrap_or("");
     let output_dir = args.get("output_dir").and_then(|v| v.as_str()).unwrap_or(".");
     let force = args.get("force").and_then(|v| v.as_bool()).unwrap_or(false);
-
-    let mut responses = Vec::new();
-    responses.push(json!({"type": "text", "text": format!("🏗 Scaffolding project from manifest: {}\nTarget directory: {}", manifest_path, output_dir)}));
+    let fetch = args.get("fetch").and_then(|v| v.as_bool()).unwrap_or(true);
 
     let path = std::path::PathBuf::from(manifest_path);
     if !path.exists() {
         return Ok(vec![json!({"type": "text", "text": format!("✗ Manifest not found: {}", manifest_path)})]);
     }
+    let manifest: cadi_core::Manifest = match serde_yaml::from_str(&std::fs::read_to_string(&path)?) {
+        Ok(manifest) => manifest,
+        Err(e) => return Ok(vec![json!({"type": "text", "text": format!("✗ Invalid manifest: {}", e)})]),
+    };
 
-    // In a real implementation we would call the scaffold logic directly.
-    // For the MCP tool, we'll simulate the success message matched with the CLI logic.
-    responses.push(json!({"type": "text", "text": "✓ Created src directory"}));
-    responses.push(json!({"type": "text", "text": "✓ Generated main.rs"}));
-    if force {
-        responses.push(json!({"type": "text", "text": "⚠ Overwriting existing files (force=true)"}));
+    let cache_dir = dirs::cache_dir()
+        .unwrap_or_else(|| std::path::PathBuf::from("."))
+        .join("dev.cadi.cadi");
+    let mut scaffolder = Scaffolder::new(&cache_dir);
+    let graph_dir = cache_dir.join("graph");
+    if graph_dir.exists() {
+        match cadi_core::graph::GraphStore::open(&graph_dir) {
+            Ok(graph) => scaffolder = scaffolder.with_graph(graph),
+            Err(e) => return Ok(vec![json!({"type": "text", "text": format!("✗ Failed to open graph store: {}", e)})]),
+        }
+    }
+    if fetch {
+        let registry_url = session.registry_url().unwrap_or_else(|| "https://registry.cadi.dev".to_string());
+        scaffolder = scaffolder.with_registry(RegistryClient::new(RegistryConfig {
+            token: session.token_for(&registry_url),
+            url: registry_url,
+            ..Default::default()
+        })?);
     }
-    responses.push(json!({"type": "text", "text": "\n✓ Scaffolding complete!"}));
 
-    Ok(responses)
+    // Nobody can answer a prompt over MCP, so existing files are kept
+    // unless overwriting was asked for
+    let on_conflict = if force { OnConflict::Overwrite } else { OnConflict::Skip };
+    let report = match scaffolder.scaffold(&manifest, std::path::Path::new(output_dir), on_conflict).await {
+        Ok(report) => report,
+        Err(e) => return Ok(vec![json!({"type": "text", "text": format!("✗ Scaffolding failed: {}", e)})]),
+    };
+
+    let mut text = format!("🏗 Scaffolded {} into {}\n", manifest_path, output_dir);
+    for file in &report.files {
+        let action = match file.action {
+            FileAction::Created => "✓ Created",
+            FileAction::Overwritten => "✓ Overwrote",
+            FileAction::Unchanged => "= Unchanged",
+            FileAction::Skipped => "⚠ Skipped existing",
+            FileAction::KeptBoth => "✓ Kept existing, wrote .cadi-new for",
+        };
+        text.push_str(&format!("{} {}\n", action, file.path.display()));
+    }
+    for unresolved in &report.unresolved {
+        text.push_str(&format!("✗ Node {}: {}\n", unresolved.node, unresolved.reason));
+    }
+    text.push_str(&format!(
+        "\n{} written, {} skipped, {} unchanged, {} unresolved, {} fetched from the registry",
+        report.written(),
+        report.count(FileAction::Skipped),
+        report.count(FileAction::Unchanged),
+        report.unresolved.len(),
+        report.fetched.len()
+    ));
+
+    Ok(vec![
+        json!({"type": "text", "text": text}),
+        json!({"type": "text", "text": serde_json::to_string_pretty(&report)?}),
+    ])
 }
 
-use cadi_core::{ProjectAnalyzer, ProjectAnalyzerConfig, SmartChunkerConfig};
-use cadi_registry::{RegistryClient, RegistryConfig};
+use cadi_core::validator::Validator;
+use cadi_core::{CancellationToken, ImportPhase, ImportProgress, ManifestGenerator, ProjectAnalyzer, ProjectAnalyzerConfig, SmartChunkerConfig};
+use cadi_registry::{PublishResult, RegistryClient, RegistryConfig, RegistryError, RegistryResult};
 
-async fn call_import(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+async fn call_import(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>, session: &SessionConfig) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
     let path_str = args.get("path").and_then(|v| v.as_str()).unwrap_or(".");
-    let namespace = args.get("namespace").and_then(|v| v.as_str()).map(|s| s.to_string());
+    let namespace = args.get("namespace").and_then(|v| v.as_str()).map(|s| s.to_string())
+        .or_else(|| session.namespace());
     let atomic = args.get("atomic").and_then(|v| v.as_bool()).unwrap_or(false);
     let publish = args.get("publish").and_then(|v| v.as_bool()).unwrap_or(false);
-    let registry_url = args.get("registry").and_then(|v| v.as_str())
-        .unwrap_or("https://registry.cadi.dev").to_string();
+    let write_manifest = args.get("write_manifest").and_then(|v| v.as_bool()).unwrap_or(true);
+    let reconcile = args.get("reconcile").and_then(|v| v.as_bool()).unwrap_or(publish);
+    let registry_url = args.get("registry").and_then(|v| v.as_str()).map(|s| s.to_string())
+        .or_else(|| session.registry_url())
+        .unwrap_or_else(|| "https://registry.cadi.dev".to_string());
 
     let mut responses = Vec::new();
     let path = std::path::PathBuf::from(path_str);
@@ -750,10 +1110,52 @@ async fn call_import(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::lo
 
     let analyzer = ProjectAnalyzer::new(analyzer_config);
 
-    // Run the import
-    match analyzer.import_project(&path) {
-        Ok(result) => {
+    // Run the import, reporting each phase and every quarter of its files
+    let updates = std::sync::Mutex::new(Vec::new());
+    let report = |progress: ImportProgress| {
+        let quarter = |n: usize| (n * 4).checked_div(progress.total).unwrap_or(0);
+        let text = if progress.processed == 0 {
+            match progress.phase {
+                ImportPhase::CollectingFiles => "⏳ Collecting files...".to_string(),
+                phase => format!("⏳ {}: 0/{} files", phase, progress.total),
+            }
+        } else if quarter(progress.processed) != quarter(progress.processed - 1) {
+            format!("⏳ {}: {}/{} files", progress.phase, progress.processed, progress.total)
+        } else {
+            return;
+        };
+        updates.lock().unwrap().push(json!({"type": "text", "text": text}));
+    };
+    let import = analyzer.import_project_with_progress(&path, report, &CancellationToken::new());
+    responses.extend(updates.into_inner().unwrap());
+
+    let registry_client = || RegistryClient::new(RegistryConfig {
+        url: registry_url.clone(),
+        token: session.token_for(&registry_url),
+        namespace: namespace.clone(),
+        spool_dir: Some(spool_dir()),
+        ..Default::default()
+    });
+
+    match import {
+        Ok(mut result) => {
             responses.push(json!({"type": "text", "text": format!("✓ Analysis complete\n")}));
+
+            // Reuse chunks the registry already holds; an unreachable
+            // registry leaves them all new
+            let mut reconciled = false;
+            if reconcile {
+                match registry_client() {
+                    Ok(client) => match client.reconcile_import(&mut result).await {
+                        Ok(_) => reconciled = true,
+                        Err(e) => responses.push(json!({"type": "text", "text": format!(
+                            "⚠ Could not check {} for existing chunks: {}\n", registry_url, e
+                        )})),
+                    },
+                    Err(e) => responses.push(json!({"type": "text", "text": format!("✗ Failed to create registry client: {}", e)})),
+                }
+            }
+
             responses.push(json!({"type": "text", "text": format!(
                 "Project: {}\nType: {}\nFiles: {}\nLines: {}\n",
                 result.summary.project_name,
@@ -767,6 +1169,28 @@ async fn call_import(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::lo
                 result.summary.composition_chunks,
                 result.summary.aliases_created
             )}));
+            if reconciled {
+                responses.push(json!({"type": "text", "text": format!(
+                    "♻ {} chunks already existed in registry\n",
+                    result.summary.registry_chunks
+                )}));
+            }
+            if result.summary.potential_duplicates > 0 {
+                responses.push(json!({"type": "text", "text": format!(
+                    "⚠ {} potential duplicates found\n",
+                    result.summary.potential_duplicates
+                )}));
+            }
+            if !result.errors.is_empty() {
+                let failed: Vec<String> = result.errors.iter()
+                    .map(|e| format!("  - {}: {}", e.path.display(), e.reason))
+                    .collect();
+                responses.push(json!({"type": "text", "text": format!(
+                    "⚠ {} files could not be analyzed and were skipped:\n{}\n",
+                    result.errors.len(),
+                    failed.join("\n")
+                )}));
+            }
 
             // Save chunks and aliases to cache
             let cache_dir = dirs::cache_dir()
@@ -783,7 +1207,7 @@ async fn call_import(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::lo
                     .collect();
                 
                 for chunk in &all_chunks {
-                    let hash = chunk.chunk_id.trim_start_matches("chunk:sha256:");
+                    let hash = cadi_core::chunk_storage_key(&chunk.chunk_id);
                     let chunk_file = cache_dir.join(format!("{}.json", &hash[..std::cmp::min(16, hash.len())]));
                     if let Ok(json) = serde_json::to_string_pretty(chunk) {
                         let _ = std::fs::write(&chunk_file, json);
@@ -791,10 +1215,7 @@ async fn call_import(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::lo
                 }
 
                 // Save alias registry
-                let registry_file = cache_dir.join("aliases.json");
-                if let Ok(registry_json) = serde_json::to_string_pretty(&result.alias_registry) {
-                    let _ = std::fs::write(&registry_file, registry_json);
-                }
+                let _ = result.alias_registry.save(&cache_dir.join("aliases.json"));
 
                 responses.push(json!({"type": "text", "text": format!("💾 Saved {} chunks to cache", all_chunks.len())}));
             }
@@ -817,25 +1238,28 @@ async fn call_import(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::lo
             if publish {
                 responses.push(json!({"type": "text", "text": format!("\n📤 Publishing to {}", registry_url)}));
                 
-                let registry_config = RegistryConfig {
-                    url: registry_url.clone(),
-                    token: None,
-                    ..Default::default()
-                };
-
-                match RegistryClient::new(registry_config) {
+                match registry_client() {
                     Ok(client) => {
                         let mut published = 0;
+                        let mut reused = 0;
                         let mut skipped = 0;
                         let mut failed = 0;
+                        let mut spooled = 0;
 
                         let all_chunks: Vec<_> = result.chunks.iter()
                             .chain(result.compositions.iter())
                             .collect();
 
                         for chunk in &all_chunks {
-                            // Check if exists
-                            if let Ok(true) = client.chunk_exists(&chunk.chunk_id).await {
+                            // Chunks the registry already holds keep their
+                            // IDs and aliases; only the upload is skipped
+                            if result.is_reused_from_registry(&chunk.chunk_id) {
+                                reused += 1;
+                                continue;
+                            }
+
+                            // Check if exists, unless reconciling already asked
+                            if !reconciled && matches!(client.chunk_exists(&chunk.chunk_id).await, Ok(true)) {
                                 skipped += 1;
                                 continue;
                             }
@@ -849,15 +1273,21 @@ async fn call_import(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::lo
                                 }
                             };
 
-                            match client.publish_chunk(&chunk.chunk_id, &data).await {
+                            match publish_with_backoff(&client, &chunk.chunk_id, &data).await {
+                                Ok(r) if r.spooled => spooled += 1,
                                 Ok(_) => published += 1,
+                                Err(RegistryError::Conflict(_)) => skipped += 1,
+                                Err(RegistryError::Unauthorized(message)) => {
+                                    responses.push(json!({"type": "text", "text": unauthorized_message(&message)}));
+                                    break;
+                                }
                                 Err(_) => failed += 1,
                             }
                         }
 
                         responses.push(json!({"type": "text", "text": format!(
-                            "\n✓ Published: {}\n→ Skipped: {}\n✗ Failed: {}",
-                            published, skipped, failed
+                            "\n✓ Published: {}\n♻ Reused: {}\n→ Skipped: {}\n✗ Failed: {}\n⏸ Spooled: {}",
+                            published, reused, skipped, failed, spooled
                         )}));
                     }
                     Err(e) => {
@@ -866,10 +1296,28 @@ async fn call_import(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::lo
                 }
             }
 
-            // Save manifest path hint
-            let manifest_name = format!("{}.cadi.yaml", 
-                result.summary.project_name.to_lowercase().replace(' ', "-"));
-            responses.push(json!({"type": "text", "text": format!("\n💡 Manifest would be: {}", path.join(manifest_name).display())}));
+            if write_manifest {
+                let generator = ManifestGenerator::new(&result);
+                let manifest = generator.generate();
+                let manifest_path = path.join(generator.file_name());
+                let written = match Validator::new().validate_manifest(&manifest) {
+                    Ok(()) => serde_yaml::to_string(&manifest)
+                        .map_err(|e| e.to_string())
+                        .and_then(|yaml| std::fs::write(&manifest_path, yaml).map_err(|e| e.to_string())),
+                    Err(errors) => Err(errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("; ")),
+                };
+                let text = match written {
+                    Ok(()) => format!(
+                        "\n📄 Manifest: {} ({} nodes, targets: {})\n   Build it with cadi_build or `cadi build {}`",
+                        manifest_path.display(),
+                        manifest.build_graph.nodes.len(),
+                        manifest.build_targets.iter().map(|t| t.name.as_str()).collect::<Vec<_>>().join(", "),
+                        manifest_path.display()
+                    ),
+                    Err(e) => format!("\n⚠ Failed to write manifest {}: {}", manifest_path.display(), e),
+                };
+                responses.push(json!({"type": "text", "text": text}));
+            }
         }
         Err(e) => {
             responses.push(json!({"type": "text", "text": format!("✗ Import failed: {}", e)}));
@@ -879,10 +1327,62 @@ async fn call_import(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::lo
     Ok(responses)
 }
 
-async fn call_publish(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
-    let registry_url = args.get("registry").and_then(|v| v.as_str())
-        .unwrap_or("https://registry.cadi.dev").to_string();
-    let namespace = args.get("namespace").and_then(|v| v.as_str()).map(|s| s.to_string());
+/// Largest snippet `cadi_atomize` accepts unless `CADI_ATOMIZE_MAX_BYTES` is set
+const DEFAULT_ATOMIZE_MAX_BYTES: usize = 256 * 1024;
+
+async fn call_atomize(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>, session: &SessionConfig) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+    let source = args.get("source").and_then(|v| v.as_str()).ok_or("Missing required argument: source")?;
+    let language = args.get("language").and_then(|v| v.as_str()).ok_or("Missing required argument: language")?;
+    let namespace = args.get("namespace").and_then(|v| v.as_str()).map(|s| s.to_string())
+        .or_else(|| session.namespace());
+
+    let max_bytes = std::env::var("CADI_ATOMIZE_MAX_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_ATOMIZE_MAX_BYTES);
+    if source.len() > max_bytes {
+        return Ok(vec![json!({"type": "text", "text": format!(
+            "✗ Source is {} bytes, over the {} byte limit. Write it to disk and use cadi_import instead.",
+            source.len(), max_bytes
+        )})]);
+    }
+
+    let cache_dir = dirs::cache_dir()
+        .unwrap_or_else(|| std::path::PathBuf::from("."))
+        .join("dev.cadi.cadi")
+        .join("graph");
+
+    let graph = match cadi_core::graph::GraphStore::open(&cache_dir) {
+        Ok(graph) => graph,
+        Err(e) => return Ok(vec![json!({"type": "text", "text": format!("✗ Failed to open graph store: {}", e)})]),
+    };
+
+    let chunks = match cadi_core::graph::BatchImporter::new(&graph).import_source(source, language, namespace.as_deref()) {
+        Ok(chunks) => chunks,
+        Err(e) => return Ok(vec![json!({"type": "text", "text": format!("✗ Atomization failed: {}", e)})]),
+    };
+    graph.flush()?;
+
+    let atoms: Vec<Value> = chunks.iter().map(|chunk| json!({
+        "chunk_id": chunk.chunk_id,
+        "name": chunk.name,
+        "alias": chunk.primary_alias().map(|a| a.full_path()),
+        "granularity": chunk.granularity,
+        "requires": chunk.requires,
+    })).collect();
+
+    Ok(vec![
+        json!({"type": "text", "text": format!("✓ Atomized {} chunk(s) from {} bytes of {}", chunks.len(), source.len(), language)}),
+        json!({"type": "text", "text": serde_json::to_string_pretty(&json!({ "chunks": atoms }))?}),
+    ])
+}
+
+async fn call_publish(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>, session: &SessionConfig) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+    let registry_url = args.get("registry").and_then(|v| v.as_str()).map(|s| s.to_string())
+        .or_else(|| session.registry_url())
+        .unwrap_or_else(|| "https://registry.cadi.dev".to_string());
+    let namespace = args.get("namespace").and_then(|v| v.as_str()).map(|s| s.to_string())
+        .or_else(|| session.namespace());
     let skip_existing = args.get("skip_existing").and_then(|v| v.as_bool()).unwrap_or(true);
     
     let chunk_ids: Vec<String> = args.get("chunks")
@@ -900,7 +1400,9 @@ async fn call_publish(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::l
 
     let registry_config = RegistryConfig {
         url: registry_url.clone(),
-        token: None,
+        token: session.token_for(&registry_url),
+        namespace: namespace.clone(),
+        spool_dir: Some(spool_dir()),
         ..Default::default()
     };
 
@@ -942,6 +1444,7 @@ async fn call_publish(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::l
     let mut published = 0;
     let mut skipped = 0;
     let mut failed = 0;
+    let mut spooled = 0;
 
     // Load chunks from cache and publish
     let cache_dir = std::env::var("CADI_CACHE_DIR")
@@ -963,12 +1466,18 @@ async fn call_publish(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::l
         }
 
         // Try to load chunk from cache
-        let hash = chunk_id.trim_start_matches("chunk:sha256:");
+        let hash = cadi_core::chunk_storage_key(chunk_id);
         let chunk_file = cache_dir.join(format!("{}.json", &hash[..std::cmp::min(16, hash.len())]));
         
         if let Ok(data) = std::fs::read(&chunk_file) {
-            match client.publish_chunk(chunk_id, &data).await {
+            match publish_with_backoff(&client, chunk_id, &data).await {
+                Ok(r) if r.spooled => spooled += 1,
                 Ok(_) => published += 1,
+                Err(RegistryError::Conflict(_)) => skipped += 1,
+                Err(RegistryError::Unauthorized(message)) => {
+                    responses.push(json!({"type": "text", "text": unauthorized_message(&message)}));
+                    break;
+                }
                 Err(_) => failed += 1,
             }
         } else {
@@ -977,47 +1486,102 @@ async fn call_publish(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::l
     }
 
     responses.push(json!({"type": "text", "text": format!(
-        "✓ Published: {}\n→ Skipped: {}\n✗ Failed: {}",
-        published, skipped, failed
+        "✓ Published: {}\n→ Skipped: {}\n✗ Failed: {}\n⏸ Spooled: {}",
+        published, skipped, failed, spooled
     )}));
 
     Ok(responses)
 }
 
-async fn call_resolve_alias(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+/// Publish a chunk, waiting out rate limits the client gave up on
+async fn publish_with_backoff(client: &RegistryClient, chunk_id: &str, data: &[u8]) -> RegistryResult<PublishResult> {
+    let mut attempt = 0;
+    loop {
+        match client.publish_chunk(chunk_id, data).await {
+            Err(RegistryError::RateLimited { retry_after }) if attempt < 3 => {
+                tokio::time::sleep(retry_after.unwrap_or(std::time::Duration::from_secs(1 << attempt))).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Why a publish stopped when the registry refused the credentials
+fn unauthorized_message(message: &str) -> String {
+    format!(
+        "✗ Registry refused the credentials ({}); stopped publishing. Set a token with push access via the session or CADI_AUTH_TOKEN",
+        message
+    )
+}
+
+/// Spool directory for chunks that could not reach the registry
+fn spool_dir() -> std::path::PathBuf {
+    cache_root().join("spool")
+}
+
+/// Cache of chunks fetched from the registries, shared with the CLI
+fn http_cache_dir() -> std::path::PathBuf {
+    cache_root().join("http-cache")
+}
+
+fn cache_root() -> std::path::PathBuf {
+    std::env::var("CADI_CACHE_DIR")
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(|_| {
+            dirs::cache_dir()
+                .unwrap_or_else(|| std::path::PathBuf::from("."))
+                .join("dev.cadi.cadi")
+        })
+}
+
+async fn call_resolve_alias(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>, session: &SessionConfig) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
     let alias = args.get("alias").and_then(|v| v.as_str()).unwrap_or("").to_string();
     
     let mut responses = Vec::new();
     responses.push(json!({"type": "text", "text": format!("🔍 Resolving alias: {}", alias)}));
 
     // Try to load alias registry from cache
-    let cache_dir = dirs::cache_dir()
-        .unwrap_or_else(|| std::path::PathBuf::from("."))
-        .join("dev.cadi.cadi")
-        .join("chunks");
+    let registry_file = cache_root().join("chunks").join("aliases.json");
 
-    let registry_file = cache_dir.join("aliases.json");
-    
-    if let Ok(content) = std::fs::read_to_string(&registry_file) {
-        if let Ok(registry) = serde_json::from_str::<Value>(&content) {
-            // The aliases are stored as a direct map: { alias_path: chunk_id }
-            if let Some(aliases) = registry.get("aliases").and_then(|a| a.as_object()) {
-                if let Some(chunk_id) = aliases.get(&alias).and_then(|c| c.as_str()) {
-                    responses.push(json!({"type": "text", "text": format!("✓ Found: {} → {}", alias, chunk_id)}));
-                    return Ok(responses);
-                }
-                
-                // Try partial match (without namespace prefix)
-                for (path, chunk_id) in aliases {
-                    if path.ends_with(&format!("/{}", alias)) || path == &alias {
-                        if let Some(id) = chunk_id.as_str() {
-                            responses.push(json!({"type": "text", "text": format!("✓ Found: {} → {}", path, id)}));
-                            return Ok(responses);
-                        }
-                    }
-                }
+    // Older registries (no version, no history) are upgraded on load
+    let registry = cadi_core::AliasRegistry::load(&registry_file).unwrap_or_default();
+    let index = cadi_core::AliasIndex::from_registry(&registry);
+
+    let suggestions = match index.resolve(&alias) {
+        cadi_core::AliasResolution::Found(found) => {
+            responses.push(json!({"type": "text", "text": format!("✓ Found: {} → {}", found.alias, found.chunk_id)}));
+            if let Some(rebind) = registry.history.iter().rev().find(|r| r.alias == found.alias) {
+                responses.push(json!({"type": "text", "text": format!(
+                    "  Rebound from {} at {}",
+                    rebind.previous_chunk_id, rebind.rebound_at
+                )}));
             }
+            return Ok(responses);
         }
+        cadi_core::AliasResolution::NotFound { suggestions } => suggestions,
+    };
+
+    // Aliases published by teammates live on the session's registry
+    if let Ok(Some((entry, registry_id))) = session_federation(session).resolve_alias(&alias).await {
+        responses.push(json!({"type": "text", "text": format!(
+            "✓ Found on registry {}: {} → {}",
+            registry_id, entry.path, entry.chunk_id
+        )}));
+        return Ok(responses);
+    }
+
+    if !suggestions.is_empty() {
+        let lines: Vec<String> = suggestions
+            .iter()
+            .map(|s| format!("  • {} → {} (similarity {:.2})", s.alias, s.chunk_id, s.score))
+            .collect();
+        responses.push(json!({"type": "text", "text": format!(
+            "✗ Alias '{}' not found. Did you mean one of these? (suggestions, not matches)\n{}",
+            alias,
+            lines.join("\n")
+        )}));
+        return Ok(responses);
     }
 
     responses.push(json!({"type": "text", "text": format!("✗ Alias '{}' not found", alias)}));
@@ -1028,7 +1592,7 @@ async fn call_resolve_alias(args: Value, _db: &surrealdb::Surreal<surrealdb::eng
 // Phase 2: Virtual View Tools
 // ============================================================================
 
-async fn call_view_context(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+async fn call_view_context(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>, _session: &SessionConfig) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
     let atoms: Vec<String> = args.get("atoms")
         .and_then(|v| v.as_array())
         .map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
@@ -1038,14 +1602,30 @@ async fn call_view_context(args: Value, _db: &surrealdb::Surreal<surrealdb::engi
         .and_then(|v| v.as_u64())
         .unwrap_or(1) as usize;
     
-    let _format = args.get("format")
+    let format = args.get("format")
         .and_then(|v| v.as_str())
         .unwrap_or("source");
+
+    let ordering = args.get("ordering")
+        .and_then(|v| v.as_str())
+        .unwrap_or("topological");
     
     let max_tokens = args.get("max_tokens")
         .and_then(|v| v.as_u64())
         .unwrap_or(8000) as usize;
 
+    let include_tests = args.get("include_tests")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let max_tests = args.get("max_tests")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(5) as usize;
+
+    let include_source_map = args.get("include_source_map")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
     let mut responses = Vec::new();
     
     if atoms.is_empty() {
@@ -1053,6 +1633,30 @@ async fn call_view_context(args: Value, _db: &surrealdb::Surreal<surrealdb::engi
         return Ok(responses);
     }
 
+    let format: cadi_core::rehydration::ViewFormat = match format.parse() {
+        Ok(format) => format,
+        Err(e) => {
+            responses.push(json!({"type": "text", "text": format!("✗ {}", e)}));
+            return Ok(responses);
+        }
+    };
+
+    let ordering: cadi_core::rehydration::ViewOrdering = match ordering.parse() {
+        Ok(ordering) => ordering,
+        Err(e) => {
+            responses.push(json!({"type": "text", "text": format!("✗ {}", e)}));
+            return Ok(responses);
+        }
+    };
+
+    let token_estimator = match token_estimator_arg(&args) {
+        Ok(token_estimator) => token_estimator,
+        Err(e) => {
+            responses.push(json!({"type": "text", "text": format!("✗ {}", e)}));
+            return Ok(responses);
+        }
+    };
+
     responses.push(json!({"type": "text", "text": format!(
         "🎯 Creating virtual view for {} atom(s) with expansion depth {}",
         atoms.len(), expand_depth
@@ -1066,83 +1670,105 @@ async fn call_view_context(args: Value, _db: &surrealdb::Surreal<surrealdb::engi
 
     match cadi_core::graph::GraphStore::open(&cache_dir) {
         Ok(graph) => {
-            // Collect atoms and their content
-            let mut ghost_atoms: Vec<String> = Vec::new();
-            let mut included: std::collections::HashSet<String> = std::collections::HashSet::new();
-            
-            // Add requested atoms
-            for atom_id in &atoms {
-                included.insert(atom_id.clone());
+            let engine = cadi_core::rehydration::RehydrationEngine::new(graph);
+            let config = cadi_core::rehydration::ViewConfig {
+                format,
+                ordering,
+                token_estimator,
+                include_tests,
+                max_tests,
+                ..Default::default()
             }
-            
-            // Expand dependencies
-            let mut frontier = atoms.clone();
-            for _depth in 0..expand_depth {
-                let mut next_frontier = Vec::new();
-                
-                for atom_id in &frontier {
-                    if let Ok(deps) = graph.get_dependencies(atom_id) {
-                        for (edge_type, dep_id) in deps {
-                            if edge_type.should_auto_expand() && !included.contains(&dep_id) {
-                                included.insert(dep_id.clone());
-                                ghost_atoms.push(dep_id.clone());
-                                next_frontier.push(dep_id);
-                            }
-                        }
-                    }
-                }
-                
-                frontier = next_frontier;
-                if frontier.is_empty() {
-                    break;
+            .with_expansion(expand_depth)
+            .with_max_tokens(max_tokens);
+
+            let view = match engine.create_view(atoms, config).await {
+                Ok(view) => view,
+                Err(e) => {
+                    responses.push(json!({"type": "text", "text": format!("✗ Failed to create view: {}", e)}));
+                    return Ok(responses);
                 }
+            };
+
+            if view.truncated {
+                responses.push(json!({"type": "text", "text": format!(
+                    "⚠ Truncated at {} tokens (limit: {})",
+                    view.token_estimate, max_tokens
+                )}));
             }
-            
-            // Collect content
-            let mut assembled = String::new();
-            let mut total_tokens = 0;
-            
-            for atom_id in &included {
-                if let Ok(Some(content)) = graph.get_content_str(atom_id) {
-                    let tokens = content.len() / 4;
-                    if total_tokens + tokens > max_tokens {
-                        responses.push(json!({"type": "text", "text": format!(
-                            "⚠ Truncated at {} tokens (limit: {})",
-                            total_tokens, max_tokens
-                        )}));
-                        break;
-                    }
-                    
-                    // Add separator
-                    if let Ok(Some(node)) = graph.get_node(atom_id) {
-                        let label = node.primary_alias.as_ref().unwrap_or(atom_id);
-                        assembled.push_str(&format!("// --- {} ---\n", label));
-                    }
-                    
-                    assembled.push_str(&content);
-                    assembled.push_str("\n\n");
-                    total_tokens += tokens;
+
+            for file in &view.files {
+                let label = if view.files.len() > 1 {
+                    format!(" ({})", file.language)
+                } else {
+                    String::new()
+                };
+                if let Some(repair) = file.validation.repair.as_ref().filter(|r| r.succeeded) {
+                    responses.push(json!({"type": "text", "text": format!(
+                        "🔧 Repaired view{} by appending `{}` ({})",
+                        label,
+                        repair.appended.replace('\n', " "),
+                        repair.fixed.join("; ")
+                    )}));
+                }
+                if !file.validation.valid {
+                    responses.push(json!({"type": "text", "text": format!(
+                        "⚠ View{} is not syntactically valid: {}",
+                        label,
+                        file.validation.errors.join("; ")
+                    )}));
                 }
             }
-            
-            if !ghost_atoms.is_empty() {
+
+            if !view.ghost_atoms.is_empty() {
                 responses.push(json!({"type": "text", "text": format!(
                     "👻 Ghost imports added: {} ({})",
-                    ghost_atoms.len(),
-                    ghost_atoms.iter().take(3).cloned().collect::<Vec<_>>().join(", ")
+                    view.ghost_atoms.len(),
+                    view.ghost_atoms.iter().take(3).cloned().collect::<Vec<_>>().join(", ")
+                )}));
+            }
+
+            if !view.test_atoms.is_empty() {
+                responses.push(json!({"type": "text", "text": format!(
+                    "🧪 Tests added after the code: {} ({})",
+                    view.test_atoms.len(),
+                    view.test_atoms.join(", ")
                 )}));
             }
             
             responses.push(json!({"type": "text", "text": format!(
                 "✓ Assembled {} atoms, ~{} tokens",
-                included.len(), total_tokens
+                view.atoms.len(), view.token_estimate
             )}));
-            
-            // Return the assembled code
-            responses.push(json!({
-                "type": "text",
-                "text": format!("```\n{}\n```", assembled)
-            }));
+
+            if view.files.len() > 1 {
+                let breakdown: Vec<String> = view
+                    .files
+                    .iter()
+                    .map(|f| format!("{}: {} atoms, ~{} tokens", f.language, f.atoms.len(), f.token_estimate))
+                    .collect();
+                responses.push(json!({"type": "text", "text": format!(
+                    "📂 {} files by language: {}",
+                    view.files.len(),
+                    breakdown.join("; ")
+                )}));
+            }
+
+            // Return the assembled code, one block per language
+            for file in &view.files {
+                responses.push(json!({
+                    "type": "text",
+                    "text": format!("```{}\n{}\n```", file.language, file.source)
+                }));
+            }
+
+            if include_source_map {
+                responses.push(json!({"type": "text", "text": format!(
+                    "🗺 Source map ({} regions, view lines → original file lines):\n```json\n{}\n```",
+                    view.source_map.len(),
+                    serde_json::to_string_pretty(&view.source_map)?
+                )}));
+            }
         }
         Err(e) => {
             responses.push(json!({"type": "text", "text": format!("✗ Failed to open graph store: {}", e)}));
@@ -1153,7 +1779,7 @@ async fn call_view_context(args: Value, _db: &surrealdb::Surreal<surrealdb::engi
     Ok(responses)
 }
 
-async fn call_get_dependencies(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+async fn call_get_dependencies(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>, _session: &SessionConfig) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
     let chunk_id = args.get("chunk_id")
         .and_then(|v| v.as_str())
         .unwrap_or("")
@@ -1173,7 +1799,7 @@ async fn call_get_dependencies(args: Value, _db: &surrealdb::Surreal<surrealdb::
 
     match cadi_core::graph::GraphStore::open(&cache_dir) {
         Ok(graph) => {
-            match graph.get_dependencies(&chunk_id) {
+            match graph.get_dependencies(&chunk_id, None) {
                 Ok(deps) => {
                     if deps.is_empty() {
                         responses.push(json!({"type": "text", "text": "No dependencies found."}));
@@ -1207,7 +1833,7 @@ async fn call_get_dependencies(args: Value, _db: &surrealdb::Surreal<surrealdb::
     Ok(responses)
 }
 
-async fn call_get_dependents(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+async fn call_get_dependents(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>, _session: &SessionConfig) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
     let chunk_id = args.get("chunk_id")
         .and_then(|v| v.as_str())
         .unwrap_or("")
@@ -1256,7 +1882,47 @@ async fn call_get_dependents(args: Value, _db: &surrealdb::Surreal<surrealdb::en
 
     Ok(responses)
 }
-async fn call_expand_context(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+/// The `token_estimator` argument shared by the context tools
+fn token_estimator_arg(args: &Value) -> Result<cadi_core::tokens::TokenEstimatorKind, String> {
+    args.get("token_estimator")
+        .and_then(|v| v.as_str())
+        .map(|s| s.parse())
+        .unwrap_or(Ok(cadi_core::tokens::TokenEstimatorKind::Bytes))
+}
+
+/// Chunks in the federated registries that may define the unresolved
+/// symbols, listed for the agent to fetch rather than included
+async fn remote_candidates(unresolved: &[cadi_core::ghost::UnresolvedSymbol], session: &SessionConfig) -> Vec<Value> {
+    let manager = session_federation(session);
+    if manager.registries().is_empty() {
+        return vec![json!({"type": "text", "text": "⚠ search_remote is set but no federated registries are configured"})];
+    }
+
+    let mut lines = Vec::new();
+    for symbol in unresolved {
+        let query = cadi_registry::client::SearchQuery {
+            query: Some(symbol.name.clone()),
+            limit: 3,
+            ..Default::default()
+        };
+        match manager.search(&query).await {
+            Ok(found) if !found.is_empty() => {
+                for (chunk, registry_id) in found {
+                    lines.push(format!("  • {} → **{}** ({}, registry {})", symbol.name, chunk.name, chunk.chunk_id, registry_id));
+                }
+            }
+            Ok(_) => lines.push(format!("  • {} → no matches", symbol.name)),
+            Err(e) => lines.push(format!("  • {} → search failed: {}", symbol.name, e)),
+        }
+    }
+
+    vec![json!({"type": "text", "text": format!(
+        "🌐 Candidates from federated registries (not included; fetch with cadi_get_chunk if they fit):\n{}",
+        lines.join("\n")
+    )})]
+}
+
+async fn call_expand_context(args: Value, _db: &surrealdb::Surreal<surrealdb::engine::local::Db>, session: &SessionConfig) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
     let atoms: Vec<String> = args.get("atoms")
         .and_then(|v| v.as_array())
         .map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
@@ -1274,6 +1940,18 @@ async fn call_expand_context(args: Value, _db: &surrealdb::Surreal<surrealdb::en
         .and_then(|v| v.as_u64())
         .unwrap_or(4000) as usize;
 
+    let search_remote = args.get("search_remote")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let include_tests = args.get("include_tests")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let max_tests = args.get("max_tests")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(5) as usize;
+
     let mut responses = Vec::new();
 
     if atoms.is_empty() {
@@ -1281,6 +1959,25 @@ async fn call_expand_context(args: Value, _db: &surrealdb::Surreal<surrealdb::en
         return Ok(responses);
     }
 
+    let token_estimator = match token_estimator_arg(&args) {
+        Ok(token_estimator) => token_estimator,
+        Err(e) => {
+            responses.push(json!({"type": "text", "text": format!("✗ {}", e)}));
+            return Ok(responses);
+        }
+    };
+
+    let edge_rules: HashMap<cadi_core::graph::EdgeType, cadi_core::ghost::EdgeRule> = match args.get("edge_rules") {
+        Some(rules) => match serde_json::from_value(rules.clone()) {
+            Ok(rules) => rules,
+            Err(e) => {
+                responses.push(json!({"type": "text", "text": format!("✗ Invalid edge_rules: {}", e)}));
+                return Ok(responses);
+            }
+        },
+        None => HashMap::new(),
+    };
+
     responses.push(json!({"type": "text", "text": format!(
         "👻 Analyzing context expansion for {} atom(s) with {} policy",
         atoms.len(), policy_name
@@ -1307,6 +2004,11 @@ async fn call_expand_context(args: Value, _db: &surrealdb::Surreal<surrealdb::en
             // Override limits if specified
             policy.max_atoms = max_atoms;
             policy.max_tokens = max_tokens;
+            policy.edge_rules.extend(edge_rules);
+            policy.search_remote = search_remote;
+            policy.token_estimator = token_estimator;
+            policy.include_tests = include_tests;
+            policy.max_tests = max_tests;
 
             // Resolve ghost imports
             match resolver.resolve_with_policy(&atoms, &policy).await {
@@ -1316,6 +2018,13 @@ async fn call_expand_context(args: Value, _db: &surrealdb::Surreal<surrealdb::en
                         result.atoms.len(), result.ghost_atoms.len()
                     )}));
 
+                    if !result.test_atoms.is_empty() {
+                        responses.push(json!({"type": "text", "text": format!(
+                            "🧪 Tests covering the requested atoms (pass include_tests to cadi_view_context to lay them out in a tests section):\n{}",
+                            result.test_atoms.iter().map(|t| format!("  • {}", t)).collect::<Vec<_>>().join("\n")
+                        )}));
+                    }
+
                     if result.truncated {
                         responses.push(json!({"type": "text", "text": "⚠ Expansion was truncated due to limits"}));
                     }
@@ -1333,6 +2042,38 @@ async fn call_expand_context(args: Value, _db: &surrealdb::Surreal<surrealdb::en
                         )}));
                     }
 
+                    if !result.rejected.is_empty() {
+                        let rejected = result.rejected.iter()
+                            .take(10)
+                            .map(|r| format!("  • {} (score {:.4}, ~{} tokens, {} refs)", r.atom_id, r.score, r.token_estimate, r.references))
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        responses.push(json!({"type": "text", "text": format!(
+                            "🚫 Left out to fit the budget (request explicitly if needed):\n{}",
+                            rejected
+                        )}));
+                    }
+
+                    if !result.unresolved.is_empty() {
+                        let unresolved = result.unresolved.iter()
+                            .map(|u| match u.defined_in.as_slice() {
+                                [] => format!("  • {} (probably a {:?}, referenced by {}) - try cadi_search with query \"{}\"",
+                                    u.name, u.guessed_kind, u.referenced_by.join(", "), u.name),
+                                definers => format!("  • {} (referenced by {}) - defined by {}, add it to atoms",
+                                    u.name, u.referenced_by.join(", "), definers.join(", ")),
+                            })
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        responses.push(json!({"type": "text", "text": format!(
+                            "❓ Referenced but not defined by any included atom (do not guess these):\n{}",
+                            unresolved
+                        )}));
+
+                        if policy.search_remote {
+                            responses.extend(remote_candidates(&result.unresolved, session).await);
+                        }
+                    }
+
                     // Return the atom list for use with cadi_view_context
                     responses.push(json!({
                         "type": "text",
@@ -1353,3 +2094,333 @@ async fn call_expand_context(args: Value, _db: &surrealdb::Surreal<surrealdb::en
 
     Ok(responses)
 }
+
+/// Tools `cadi_batch` may run. Lookups only, so entries can run
+/// concurrently in any order without one changing what another sees.
+const BATCH_TOOLS: &[&str] = &[
+    "cadi_search",
+    "cadi_resolve_alias",
+    "cadi_get_chunk",
+    "cadi_get_dependencies",
+    "cadi_get_dependents",
+    "cadi_view_context",
+];
+
+/// Most entries in one batch
+const MAX_BATCH_SIZE: usize = 25;
+
+/// Entries of a batch running at the same time
+const MAX_BATCH_CONCURRENCY: usize = 4;
+
+/// Default token budget for a batch's combined response
+const DEFAULT_BATCH_TOKENS: usize = 8000;
+
+/// One entry of a batch after it ran
+#[derive(Debug)]
+struct BatchOutcome {
+    tool: String,
+    result: Result<Vec<Value>, String>,
+}
+
+async fn call_batch(args: Value, db: &surrealdb::Surreal<surrealdb::engine::local::Db>, session: &SessionConfig) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+    let calls = match batch_calls(&args) {
+        Ok(calls) => calls,
+        Err(e) => return Ok(vec![json!({"type": "text", "text": format!("✗ {}", e)})]),
+    };
+    let max_tokens = args.get("max_tokens")
+        .and_then(|v| v.as_u64())
+        .map(|v| v as usize)
+        .unwrap_or(DEFAULT_BATCH_TOKENS);
+
+    let db = db.clone();
+    let session = session.clone();
+    let outcomes = run_batch(calls, move |tool, arguments| {
+        let db = db.clone();
+        let session = session.clone();
+        async move {
+            call_batched_tool(&tool, arguments, &db, &session).await.map_err(|e| e.to_string())
+        }
+    }).await;
+
+    Ok(render_batch(&outcomes, max_tokens))
+}
+
+/// The `calls` argument as `(tool, arguments)` pairs, with the `cadi_`
+/// prefix added to tool names given without it
+fn batch_calls(args: &Value) -> Result<Vec<(String, Value)>, String> {
+    let calls = args.get("calls")
+        .and_then(|v| v.as_array())
+        .ok_or("calls must be an array of {tool, arguments} entries")?;
+    if calls.is_empty() {
+        return Err("calls is empty".to_string());
+    }
+    if calls.len() > MAX_BATCH_SIZE {
+        return Err(format!("A batch holds at most {} calls, got {}", MAX_BATCH_SIZE, calls.len()));
+    }
+
+    Ok(calls.iter()
+        .map(|call| {
+            let tool = call.get("tool").and_then(|v| v.as_str()).unwrap_or_default();
+            let tool = if tool.starts_with("cadi_") { tool.to_string() } else { format!("cadi_{}", tool) };
+            let arguments = call.get("arguments").cloned().unwrap_or_else(|| json!({}));
+            (tool, arguments)
+        })
+        .collect())
+}
+
+async fn call_batched_tool(
+    tool: &str,
+    arguments: Value,
+    db: &surrealdb::Surreal<surrealdb::engine::local::Db>,
+    session: &SessionConfig,
+) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+    match tool {
+        "cadi_search" => call_search(arguments, db, session).await,
+        "cadi_resolve_alias" => call_resolve_alias(arguments, db, session).await,
+        "cadi_get_chunk" => call_get_chunk(arguments, db, session).await,
+        "cadi_get_dependencies" => call_get_dependencies(arguments, db, session).await,
+        "cadi_get_dependents" => call_get_dependents(arguments, db, session).await,
+        "cadi_view_context" => call_view_context(arguments, db, session).await,
+        _ => Err(format!("{} can't be batched", tool).into()),
+    }
+}
+
+/// Run the calls through `run`, at most [`MAX_BATCH_CONCURRENCY`] at a
+/// time, returning their outcomes in call order. Tools outside
+/// [`BATCH_TOOLS`] fail without running.
+async fn run_batch<F, Fut>(calls: Vec<(String, Value)>, run: F) -> Vec<BatchOutcome>
+where
+    F: Fn(String, Value) -> Fut,
+    Fut: std::future::Future<Output = Result<Vec<Value>, String>> + Send + 'static,
+{
+    let mut results: Vec<Option<Result<Vec<Value>, String>>> = calls.iter().map(|_| None).collect();
+    let mut tasks = tokio::task::JoinSet::new();
+
+    for (index, (tool, arguments)) in calls.iter().enumerate() {
+        if !BATCH_TOOLS.contains(&tool.as_str()) {
+            results[index] = Some(Err(format!(
+                "{} can't be batched; only {} can",
+                tool, BATCH_TOOLS.join(", ")
+            )));
+            continue;
+        }
+        if tasks.len() >= MAX_BATCH_CONCURRENCY {
+            if let Some(Ok((done, result))) = tasks.join_next().await {
+                results[done] = Some(result);
+            }
+        }
+        let call = run(tool.clone(), arguments.clone());
+        tasks.spawn(async move { (index, call.await) });
+    }
+    while let Some(joined) = tasks.join_next().await {
+        if let Ok((done, result)) = joined {
+            results[done] = Some(result);
+        }
+    }
+
+    calls.into_iter()
+        .zip(results)
+        .map(|((tool, _), result)| BatchOutcome {
+            tool,
+            result: result.unwrap_or_else(|| Err("Tool call panicked".to_string())),
+        })
+        .collect()
+}
+
+/// Content for a batch: a summary, then each entry in order under a
+/// header. Successful results are included while they fit `max_tokens`;
+/// the rest are left out with a note so they can be requested on their own.
+fn render_batch(outcomes: &[BatchOutcome], max_tokens: usize) -> Vec<Value> {
+    use cadi_core::tokens::{ByteHeuristic, TokenEstimator};
+
+    let mut used = 0;
+    let mut omitted = 0;
+    let mut entries = Vec::new();
+    for (index, outcome) in outcomes.iter().enumerate() {
+        match &outcome.result {
+            Ok(content) => {
+                let tokens: usize = content.iter()
+                    .filter_map(|part| part.get("text").and_then(|t| t.as_str()))
+                    .map(|text| ByteHeuristic.estimate(text, None))
+                    .sum();
+                if used + tokens > max_tokens {
+                    omitted += 1;
+                    entries.push(json!({"type": "text", "text": format!(
+                        "#{} {} ⚠ left out: its ~{} tokens would go over the batch budget of {}; call it on its own",
+                        index + 1, outcome.tool, tokens, max_tokens
+                    )}));
+                } else {
+                    used += tokens;
+                    entries.push(json!({"type": "text", "text": format!("#{} {} ✓", index + 1, outcome.tool)}));
+                    entries.extend(content.iter().cloned());
+                }
+            }
+            Err(e) => {
+                entries.push(json!({"type": "text", "text": format!("#{} {} ✗ {}", index + 1, outcome.tool, e)}));
+            }
+        }
+    }
+
+    let failed = outcomes.iter().filter(|o| o.result.is_err()).count();
+    let mut responses = vec![json!({"type": "text", "text": format!(
+        "📦 Batch of {}: {} succeeded, {} failed, {} left out (~{} of {} tokens used)",
+        outcomes.len(), outcomes.len() - failed - omitted, failed, omitted, used, max_tokens
+    )})];
+    responses.extend(entries);
+    responses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    fn text(parts: &[Value]) -> Vec<String> {
+        parts.iter().map(|p| p["text"].as_str().unwrap_or_default().to_string()).collect()
+    }
+
+    /// Serve `app` on a local port, returning its URL
+    async fn serve(app: axum::Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_tools_read_from_the_session_registry() {
+        use axum::http::HeaderMap;
+
+        // A registry that serves one chunk and records who asked for it
+        let content = b"pub fn from_session() {}".to_vec();
+        let chunk_id = cadi_core::hash::chunk_id_from_content(&content);
+        let seen: Arc<Mutex<Vec<HeaderMap>>> = Arc::default();
+        let app = axum::Router::new().route("/v1/chunks/:chunk_id", axum::routing::get({
+            let seen = seen.clone();
+            move |headers: HeaderMap| async move {
+                seen.lock().unwrap().push(headers);
+                content
+            }
+        }));
+        let url = serve(app).await;
+
+        let session = SessionConfig {
+            registry_url: Some(url.clone()),
+            token: Some("session-token".to_string()),
+            namespace: Some("acme".to_string()),
+            ..Default::default()
+        };
+        let manager = session_federation(&session);
+        assert_eq!(manager.registries().len(), 1);
+        assert!(Arc::ptr_eq(&manager, &session_federation(&session)));
+        let other = SessionConfig { token: Some("other-token".to_string()), ..session.clone() };
+        assert!(!Arc::ptr_eq(&manager, &session_federation(&other)));
+
+        let db = surrealdb::Surreal::new::<surrealdb::engine::local::Mem>(()).await.unwrap();
+        let parts = call_tool("cadi_get_chunk", json!({"chunk_id": chunk_id}), &db, &session).await.unwrap();
+        assert!(text(&parts)[0].contains(&format!("from registry '{}'", url)), "{:?}", text(&parts));
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen[0]["authorization"], "Bearer session-token");
+        assert_eq!(seen[0][cadi_registry::NAMESPACE_HEADER], "acme");
+    }
+
+    #[tokio::test]
+    async fn test_aliases_resolve_on_the_session_registry() {
+        // A registry that only shows the alias to the session's token
+        let app = axum::Router::new().route("/aliases/*path", axum::routing::get(
+            |axum::extract::Path(path): axum::extract::Path<String>, headers: axum::http::HeaderMap| async move {
+                if headers.get("authorization").and_then(|v| v.to_str().ok()) != Some("Bearer private-token") {
+                    return Err(axum::http::StatusCode::FORBIDDEN);
+                }
+                Ok(axum::Json(json!({"path": path, "chunk_id": "chunk:sha256:private"})))
+            },
+        ));
+        let url = serve(app).await;
+        let session = SessionConfig {
+            registry_url: Some(url.clone()),
+            token: Some("private-token".to_string()),
+            ..Default::default()
+        };
+
+        let db = surrealdb::Surreal::new::<surrealdb::engine::local::Mem>(()).await.unwrap();
+        let alias = "private-team/session-only/logger";
+        let parts = call_tool("cadi_resolve_alias", json!({"alias": alias}), &db, &session).await.unwrap();
+        assert_eq!(
+            text(&parts)[1],
+            format!("✓ Found on registry {}: {} → chunk:sha256:private", url, alias)
+        );
+
+        let anonymous = SessionConfig { token: Some("other-token".to_string()), ..session };
+        let parts = call_tool("cadi_resolve_alias", json!({"alias": alias}), &db, &anonymous).await.unwrap();
+        assert_eq!(text(&parts)[1], format!("✗ Alias '{}' not found", alias));
+    }
+
+    #[tokio::test]
+    async fn test_batch_keeps_order_and_isolates_failures() {
+        let calls = batch_calls(&json!({"calls": [
+            {"tool": "resolve_alias", "arguments": {"alias": "a", "delay": 40}},
+            {"tool": "cadi_get_chunk", "arguments": {"chunk_id": "b", "fail": true}},
+            {"tool": "cadi_publish", "arguments": {}},
+            {"tool": "get_dependencies", "arguments": {"chunk_id": "c", "delay": 20}},
+            {"tool": "get_dependents", "arguments": {"chunk_id": "d"}},
+            {"tool": "view_context", "arguments": {"atoms": ["e"], "delay": 30}},
+        ]})).unwrap();
+
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (running_in, peak_in) = (running.clone(), peak.clone());
+        let outcomes = run_batch(calls, move |tool, arguments| {
+            let (running, peak) = (running_in.clone(), peak_in.clone());
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                let delay = arguments["delay"].as_u64().unwrap_or(0);
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                if arguments["fail"].as_bool() == Some(true) {
+                    return Err("not found".to_string());
+                }
+                Ok(vec![json!({"type": "text", "text": format!("{} {}", tool, arguments)})])
+            }
+        }).await;
+
+        let tools: Vec<_> = outcomes.iter().map(|o| o.tool.as_str()).collect();
+        assert_eq!(tools, [
+            "cadi_resolve_alias", "cadi_get_chunk", "cadi_publish",
+            "cadi_get_dependencies", "cadi_get_dependents", "cadi_view_context",
+        ]);
+        assert_eq!(outcomes[1].result.as_ref().unwrap_err(), "not found");
+        assert!(outcomes[2].result.as_ref().unwrap_err().contains("can't be batched"));
+        for index in [0, 3, 4, 5] {
+            let content = outcomes[index].result.as_ref().unwrap();
+            assert!(text(content)[0].starts_with(&outcomes[index].tool));
+        }
+        assert!(peak.load(Ordering::SeqCst) <= MAX_BATCH_CONCURRENCY);
+
+        let rendered = text(&render_batch(&outcomes, DEFAULT_BATCH_TOKENS));
+        assert!(rendered[0].contains("4 succeeded, 2 failed, 0 left out"));
+        assert_eq!(rendered[1], "#1 cadi_resolve_alias ✓");
+        assert_eq!(rendered[3], "#2 cadi_get_chunk ✗ not found");
+        assert!(rendered[4].starts_with("#3 cadi_publish ✗"));
+    }
+
+    #[test]
+    fn test_batch_size_and_token_budget() {
+        let calls: Vec<_> = (0..=MAX_BATCH_SIZE).map(|_| json!({"tool": "search"})).collect();
+        assert!(batch_calls(&json!({"calls": calls})).is_err());
+        assert!(batch_calls(&json!({"calls": []})).is_err());
+
+        let outcome = |tool: &str, bytes: usize| BatchOutcome {
+            tool: tool.to_string(),
+            result: Ok(vec![json!({"type": "text", "text": "x".repeat(bytes)})]),
+        };
+        // 40 + 80 tokens would go over 100, but the 20 after it still fits
+        let outcomes = [outcome("cadi_search", 160), outcome("cadi_get_chunk", 320), outcome("cadi_get_dependents", 80)];
+        let rendered = text(&render_batch(&outcomes, 100));
+        assert!(rendered[0].contains("2 succeeded, 0 failed, 1 left out (~60 of 100 tokens used)"));
+        assert_eq!(rendered[1], "#1 cadi_search ✓");
+        assert!(rendered[3].starts_with("#2 cadi_get_chunk ⚠ left out"));
+        assert_eq!(rendered[4], "#3 cadi_get_dependents ✓");
+    }
+}
@@ -1,64 +1,414 @@
 //! HTTP route handlers
 
 use axum::{
-    extract::{Path, State, Query},
-    http::{HeaderMap, StatusCode},
+    extract::{Path, State, Query, Request},
+    http::{header, HeaderMap, Method, StatusCode},
     body::Bytes,
+    middleware::Next,
+    response::{IntoResponse, Response},
     Json,
 };
+use std::collections::HashSet;
+use std::time::Instant;
 use serde::{Deserialize, Serialize};
-use cadi_core::Chunk;
+use cadi_core::{Chunk, ChunkCategory, ChunkGranularity};
 
-use crate::state::AppState;
+use crate::namespaces;
+use crate::state::{AppState, ServerMode};
+use crate::webhooks::{WebhookEvent, WebhookPayload};
 
 /// Health check response
 #[derive(Serialize)]
 pub struct HealthResponse {
     pub status: String,
     pub version: String,
+    pub mode: ServerMode,
+    /// Chunks quarantined because their content didn't match their ID
+    pub quarantined_chunks: usize,
 }
 
 /// Health check handler
-pub async fn health() -> Json<HealthResponse> {
+pub async fn health(State(state): State<AppState>) -> Json<HealthResponse> {
     Json(HealthResponse {
         status: "healthy".to_string(),
         version: env!("CARGO_PKG_VERSION").to_string(),
+        mode: state.config.mode,
+        quarantined_chunks: state.store.read().await.quarantined_count(),
     })
 }
 
-/// Get chunk handler
+/// `POST` endpoints that only read: queries with a body
+const READ_ONLY_POSTS: &[&str] = &["/v1/chunks/exists", "/v1/search", "/v1/semantic_search", "/v1/views"];
+
+/// Middleware rejecting every change with 405 Method Not Allowed when the
+/// server is read-only: any request but `GET`, `HEAD`, `OPTIONS` and the
+/// [`READ_ONLY_POSTS`]. Reads, searches and view assembly still work.
+pub async fn reject_writes_when_read_only(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method();
+    let mutation = match *method {
+        Method::GET | Method::HEAD | Method::OPTIONS => false,
+        Method::POST => !READ_ONLY_POSTS.contains(&request.uri().path()),
+        _ => true,
+    };
+    if state.config.mode != ServerMode::ReadOnly || !mutation {
+        return next.run(request).await;
+    }
+    let body = serde_json::json!({
+        "error": "This registry is read-only and doesn't accept changes",
+        "mode": state.config.mode,
+    });
+    (StatusCode::METHOD_NOT_ALLOWED, Json(body)).into_response()
+}
+
+/// Get chunk handler. Chunks are content-addressed, so the ETag is the
+/// content hash and a matching `If-None-Match` gets 304 Not Modified. Both
+/// count as a use of the chunk.
 pub async fn get_chunk(
     State(state): State<AppState>,
     Path(chunk_id): Path<String>,
-) -> Result<Vec<u8>, StatusCode> {
+    headers: HeaderMap,
+) -> Result<axum::response::Response, StatusCode> {
+    if !can_read_chunk(&state, &headers, &chunk_id).await {
+        return Err(StatusCode::FORBIDDEN);
+    }
     let store = state.store.read().await;
-    
-    store.get(&chunk_id).await
-        .ok_or(StatusCode::NOT_FOUND)
+    let etag = chunk_etag(&chunk_id);
+
+    if etag_matches(&headers, &etag) && store.exists(&chunk_id).await {
+        state.usage.record(&chunk_id);
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+    }
+
+    let data = store.get(&chunk_id).await
+        .ok_or(StatusCode::NOT_FOUND)?;
+    state.metrics.add_download_bytes(data.len());
+    state.usage.record(&chunk_id);
+    Ok(([(header::ETAG, etag)], data).into_response())
+}
+
+/// Usage statistics of a chunk: its usage count and uses per day over the
+/// retention window
+pub async fn get_chunk_stats(
+    State(state): State<AppState>,
+    Path(chunk_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<cadi_registry::ChunkUsageStats>, StatusCode> {
+    if !can_read_chunk(&state, &headers, &chunk_id).await {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    match state.registry_db.read().await.usage_stats(&chunk_id).await {
+        Ok(Some(stats)) => Ok(Json(stats)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("usage stats error for {}: {}", chunk_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Chunks of an executed build plan
+#[derive(Deserialize)]
+pub struct UsageReport {
+    pub chunks: Vec<String>,
+}
+
+/// Most chunks a single usage report may name
+pub const MAX_USAGE_BATCH: usize = 1000;
+
+/// Count a use of each chunk in a build plan. Usage ranks search results,
+/// so reports need the admin token or a namespace write token, and only
+/// count each chunk the registry holds and the caller may read once. The
+/// uses are written in the background, so this returns 202 Accepted.
+pub async fn report_usage(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(report): Json<UsageReport>,
+) -> StatusCode {
+    if !namespaces::has_write_token(&state.config, &headers) {
+        return StatusCode::FORBIDDEN;
+    }
+    if report.chunks.len() > MAX_USAGE_BATCH {
+        return StatusCode::PAYLOAD_TOO_LARGE;
+    }
+    let chunk_ids: HashSet<&String> = report.chunks.iter().collect();
+    for chunk_id in chunk_ids {
+        let stored = state.store.read().await.exists(chunk_id).await;
+        if stored && can_read_chunk(&state, &headers, chunk_id).await {
+            state.usage.record(chunk_id);
+        }
+    }
+    StatusCode::ACCEPTED
 }
 
 /// Head chunk handler (check existence)
 pub async fn head_chunk(
     State(state): State<AppState>,
     Path(chunk_id): Path<String>,
-) -> StatusCode {
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, StatusCode> {
+    if !can_read_chunk(&state, &headers, &chunk_id).await {
+        return Err(StatusCode::FORBIDDEN);
+    }
     let store = state.store.read().await;
     
     if store.exists(&chunk_id).await {
-        StatusCode::OK
+        Ok([(header::ETAG, chunk_etag(&chunk_id))])
     } else {
-        StatusCode::NOT_FOUND
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+/// Most hashes a single existence check may ask about
+pub const MAX_EXISTS_BATCH: usize = 1000;
+
+/// Content hashes (chunk storage keys) to look up
+#[derive(Deserialize)]
+pub struct ExistsRequest {
+    pub hashes: Vec<String>,
+}
+
+/// The requested hashes the registry holds, in request order
+#[derive(Serialize)]
+pub struct ExistsResponse {
+    pub existing: Vec<String>,
+}
+
+/// Bulk existence check. Chunks the request may not read are reported
+/// as missing, the same as `HEAD /v1/chunks/:chunk_id` would.
+pub async fn chunks_exist(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<ExistsRequest>,
+) -> Result<Json<ExistsResponse>, StatusCode> {
+    if request.hashes.len() > MAX_EXISTS_BATCH {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+    let hidden = hidden_chunks(&state, &headers).await;
+    let store = state.store.read().await;
+
+    let mut existing = Vec::new();
+    let mut seen = HashSet::new();
+    for hash in request.hashes {
+        let chunk_id = cadi_core::hash::ChunkId::from_storage_key(&hash).to_string();
+        if !hidden.contains(&chunk_id) && store.exists(&chunk_id).await && seen.insert(hash.clone()) {
+            existing.push(hash);
+        }
+    }
+    Ok(Json(ExistsResponse { existing }))
+}
+
+/// Strong ETag of a chunk: its content hash
+fn chunk_etag(chunk_id: &str) -> String {
+    format!("\"{}\"", cadi_core::hash::chunk_storage_key(chunk_id))
+}
+
+/// Whether `If-None-Match` lists `etag` (weak comparison) or is `*`
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+/// Alias paths are slash-separated segments of letters, digits, `-`, `_`,
+/// `.` and `@`
+fn valid_alias_path(path: &str) -> bool {
+    !path.is_empty()
+        && path.len() <= 256
+        && path.split('/').all(|segment| {
+            !segment.is_empty()
+                && segment != "."
+                && segment != ".."
+                && segment.chars().all(|c| c.is_ascii_alphanumeric() || "-_.@".contains(c))
+        })
+}
+
+/// Body of `PUT /aliases/:path`
+#[derive(Deserialize)]
+pub struct PutAliasRequest {
+    pub chunk_id: String,
+}
+
+/// Query of the alias listing
+#[derive(Deserialize)]
+pub struct AliasListQuery {
+    #[serde(default)]
+    pub prefix: String,
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: usize,
+}
+
+const DEFAULT_ALIAS_PAGE: usize = 100;
+const MAX_ALIAS_PAGE: usize = 1000;
+
+/// Point an alias at a stored chunk. The alias adds its namespace to the
+/// chunk's, so it needs write access to the alias namespace and to the
+/// chunk.
+pub async fn put_alias(
+    State(state): State<AppState>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<PutAliasRequest>,
+) -> Result<Json<cadi_registry::AliasEntry>, StatusCode> {
+    if !can_write_alias(&state, &headers, &path) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if !valid_alias_path(&path) || cadi_core::hash::parse_chunk_id(&req.chunk_id).is_none() {
+        return Err(StatusCode::BAD_REQUEST);
     }
+    if !can_write_chunk(&state, &headers, &req.chunk_id).await {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if !state.store.read().await.exists(&req.chunk_id).await {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let entry = state.registry_db.read().await.put_alias(&path, &req.chunk_id).await.map_err(|e| {
+        eprintln!("alias store error: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    state.webhooks.notify(WebhookPayload {
+        alias: Some(entry.path.clone()),
+        ..WebhookPayload::new(WebhookEvent::AliasUpdate, entry.chunk_id.clone())
+    });
+    Ok(Json(entry))
+}
+
+/// Resolve an alias
+pub async fn get_alias(
+    State(state): State<AppState>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<cadi_registry::AliasEntry>, StatusCode> {
+    if !namespaces::can_read(&state.config, &headers, namespaces::alias_namespace(&path)) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    state.registry_db.read().await.get_alias(&path).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
 }
 
-/// Put chunk handler
+/// List aliases under a prefix, a page at a time. Aliases in namespaces
+/// the caller can't read are left out.
+pub async fn list_aliases(
+    State(state): State<AppState>,
+    Query(query): Query<AliasListQuery>,
+    headers: HeaderMap,
+) -> Result<Json<cadi_registry::AliasPage>, StatusCode> {
+    let limit = query.limit.unwrap_or(DEFAULT_ALIAS_PAGE).clamp(1, MAX_ALIAS_PAGE);
+    if namespaces::unreadable(&state.config, &headers).is_empty() {
+        return state.registry_db.read().await.list_aliases(&query.prefix, limit, query.offset).await
+            .map(Json)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    // Filtered before paging, so pages and the total count only what the
+    // caller can see
+    let visible: Vec<_> = all_aliases(&state, &query.prefix).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .into_iter()
+        .filter(|entry| namespaces::can_read(&state.config, &headers, namespaces::alias_namespace(&entry.path)))
+        .collect();
+    Ok(Json(cadi_registry::AliasPage {
+        total: visible.len(),
+        aliases: visible.into_iter().skip(query.offset).take(limit).collect(),
+        offset: query.offset,
+        limit,
+    }))
+}
+
+/// Every alias starting with `prefix`, in path order
+async fn all_aliases(state: &AppState, prefix: &str) -> cadi_core::CadiResult<Vec<cadi_registry::AliasEntry>> {
+    let db = state.registry_db.read().await;
+    let mut aliases = Vec::new();
+    loop {
+        let page = db.list_aliases(prefix, MAX_ALIAS_PAGE, aliases.len()).await?;
+        let last = page.aliases.len() < MAX_ALIAS_PAGE;
+        aliases.extend(page.aliases);
+        if last {
+            return Ok(aliases);
+        }
+    }
+}
+
+/// Remove an alias (requires write access to its namespace)
+pub async fn delete_alias(
+    State(state): State<AppState>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+) -> StatusCode {
+    if !can_write_alias(&state, &headers, &path) {
+        return StatusCode::FORBIDDEN;
+    }
+
+    match state.registry_db.read().await.delete_alias(&path).await {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Query of the closure endpoint
+#[derive(Deserialize)]
+pub struct ClosureQuery {
+    /// Levels of references to follow (default: unlimited)
+    pub max_depth: Option<usize>,
+}
+
+/// Dependency closure handler: the chunk and everything it transitively
+/// references, with sizes and hashes, so clients can fetch them in parallel
+pub async fn get_chunk_closure(
+    State(state): State<AppState>,
+    Path(chunk_id): Path<String>,
+    Query(query): Query<ClosureQuery>,
+    headers: HeaderMap,
+) -> Result<Json<cadi_registry::ChunkClosure>, StatusCode> {
+    if !can_read_chunk(&state, &headers, &chunk_id).await {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    // References the caller can't read are reported as missing
+    let hidden = std::sync::Arc::new(hidden_chunks(&state, &headers).await);
+    cadi_registry::resolve_closure(&chunk_id, query.max_depth, cadi_registry::MAX_CLOSURE_NODES, |id| {
+        let state = state.clone();
+        let hidden = hidden.clone();
+        async move {
+            if hidden.contains(&id) {
+                return None;
+            }
+            state.chunk_data(&id).await
+        }
+    })
+    .await
+    .map(Json)
+    .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Put chunk handler. A chunk published into a namespace (the
+/// `X-Cadi-Namespace` header), or already in one, needs a token that may
+/// write there.
 pub async fn put_chunk(
     State(state): State<AppState>,
     Path(chunk_id): Path<String>,
+    headers: HeaderMap,
     body: Bytes,
 ) -> Result<Json<PutResponse>, StatusCode> {
+    let namespace = namespaces::requested_namespace(&headers);
+    if !can_write_chunk(&state, &headers, &chunk_id).await {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
     // Verify hash matches
     if !cadi_core::hash::verify_chunk_content(&chunk_id, &body) {
+        state.metrics.record_rejection("hash_mismatch");
         return Ok(Json(PutResponse {
             success: false,
             chunk_id: Some(chunk_id),
@@ -68,10 +418,10 @@ pub async fn put_chunk(
     
     // Check size limit
     if body.len() > state.config.max_chunk_size {
+        state.metrics.record_rejection("too_large");
         return Err(StatusCode::PAYLOAD_TOO_LARGE);
     }
     
-    // Try to store in registry database first
     // For now, we'll store a simple chunk with the data as content
     // In a real implementation, we'd parse the chunk format
     let content = String::from_utf8_lossy(&body);
@@ -109,25 +459,25 @@ pub async fn put_chunk(
         "test_coverage": 0.85
     });
     
-    match state.registry_db.write().await.store_chunk(&chunk, &content, metadata).await {
-        Ok(_) => Ok(Json(PutResponse {
-            success: true,
-            chunk_id: Some(chunk_id),
-            message: None,
-        })),
-    Err(_e) => {
-        // Fallback to file store
-        let mut store = state.store.write().await;
-            match store.store(chunk_id.clone(), body.to_vec()).await {
-                Ok(_) => Ok(Json(PutResponse {
-                    success: true,
-                    chunk_id: Some(chunk_id),
-                    message: None,
-                })),
-                Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
-            }
-        }
+    // The file store keeps the exact bytes served by GET and HEAD; the
+    // registry database indexes the chunk for search
+    state.store.write().await.store(chunk_id.clone(), body.to_vec(), namespace).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if let Err(e) = state.registry_db.write().await.store_chunk(&chunk, &content, metadata).await {
+        tracing::warn!("Failed to index chunk {}: {}", chunk_id, e);
     }
+
+    let size = body.len();
+    state.metrics.add_upload_bytes(size);
+    state.webhooks.notify(WebhookPayload {
+        size: Some(size),
+        ..WebhookPayload::new(WebhookEvent::Publish, chunk_id.clone())
+    });
+    Ok(Json(PutResponse {
+        success: true,
+        chunk_id: Some(chunk_id),
+        message: None,
+    }))
 }
 
 /// Put response
@@ -142,21 +492,93 @@ pub struct PutResponse {
 pub async fn delete_chunk(
     State(state): State<AppState>,
     Path(chunk_id): Path<String>,
+    headers: HeaderMap,
 ) -> StatusCode {
+    if !can_write_chunk(&state, &headers, &chunk_id).await {
+        return StatusCode::FORBIDDEN;
+    }
     let mut store = state.store.write().await;
     
     if store.delete(&chunk_id).await {
+        state.webhooks.notify(WebhookPayload::new(WebhookEvent::Delete, chunk_id));
         StatusCode::NO_CONTENT
     } else {
         StatusCode::NOT_FOUND
     }
 }
 
+/// Path of a build cache entry. Keys are input hashes, so anything but
+/// short alphanumeric strings is rejected.
+fn cache_entry_path(state: &AppState, key: &str) -> Result<std::path::PathBuf, StatusCode> {
+    if key.is_empty() || key.len() > 128 || !key.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(std::path::PathBuf::from(&state.config.storage_path).join("cache").join(key))
+}
+
+/// Get build cache entry handler
+pub async fn get_cache_entry(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+) -> Result<Vec<u8>, StatusCode> {
+    let path = cache_entry_path(&state, &key)?;
+    std::fs::read(path).map_err(|_| StatusCode::NOT_FOUND)
+}
+
+/// Head build cache entry handler (check existence)
+pub async fn head_cache_entry(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+) -> StatusCode {
+    match cache_entry_path(&state, &key) {
+        Ok(path) if path.exists() => StatusCode::OK,
+        Ok(_) => StatusCode::NOT_FOUND,
+        Err(status) => status,
+    }
+}
+
+/// Put build cache entry handler. Nobody can check a cached output against
+/// its key, so writes need the admin token or a namespace write token,
+/// even when anonymous writes are enabled.
+pub async fn put_cache_entry(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    if !namespaces::has_write_token(&state.config, &headers) {
+        return StatusCode::FORBIDDEN;
+    }
+    if body.len() > state.config.max_chunk_size {
+        return StatusCode::PAYLOAD_TOO_LARGE;
+    }
+    let path = match cache_entry_path(&state, &key) {
+        Ok(path) => path,
+        Err(status) => return status,
+    };
+
+    let result = path.parent()
+        .map(std::fs::create_dir_all)
+        .unwrap_or(Ok(()))
+        .and_then(|_| std::fs::write(&path, &body));
+    match result {
+        Ok(()) => StatusCode::CREATED,
+        Err(e) => {
+            eprintln!("cache store error: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
 /// Get chunk metadata handler
 pub async fn get_chunk_meta(
     State(state): State<AppState>,
     Path(chunk_id): Path<String>,
+    headers: HeaderMap,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
+    if !can_read_chunk(&state, &headers, &chunk_id).await {
+        return Err(StatusCode::FORBIDDEN);
+    }
     match state.registry_db.read().await.get_chunk(&chunk_id).await {
         Ok(Some(record)) => match serde_json::to_value(record.metadata) {
             Ok(val) => Ok(Json(val)),
@@ -174,8 +596,27 @@ pub async fn get_chunk_meta(
 pub async fn put_chunk_meta(
     State(state): State<AppState>,
     Path(chunk_id): Path<String>,
+    headers: HeaderMap,
     Json(meta): Json<serde_json::Value>,
 ) -> Result<Json<PutResponse>, StatusCode> {
+    if !can_write_chunk(&state, &headers, &chunk_id).await {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    // Only store signatures that are valid for this chunk
+    if let Some(signatures) = meta.get("signatures").and_then(|s| s.as_array()) {
+        for signature in signatures {
+            let valid = signature
+                .as_str()
+                .map(|sig| cadi_core::trust::verify_signature(&chunk_id, sig).is_ok())
+                .unwrap_or(false);
+            if !valid {
+                eprintln!("rejected invalid signature for {}: {}", chunk_id, signature);
+                return Err(StatusCode::BAD_REQUEST);
+            }
+        }
+    }
+
     match state.registry_db.write().await.update_chunk_metadata(&chunk_id, meta).await {
         Ok(_) => Ok(Json(PutResponse {
             success: true,
@@ -189,13 +630,19 @@ pub async fn put_chunk_meta(
     }
 }
 
-/// List chunks handler
+/// List chunks handler. Chunks in namespaces the caller can't read are
+/// left out.
 pub async fn list_chunks(
     State(state): State<AppState>,
     Query(params): Query<std::collections::HashMap<String, String>>,
+    headers: HeaderMap,
 ) -> Json<Vec<crate::state::ChunkMetadata>> {
+    let hidden = hidden_chunks(&state, &headers).await;
+    // Registry database IDs are record IDs wrapping the chunk ID
+    let is_hidden = |id: &str| hidden.iter().any(|chunk_id| id.contains(chunk_id.as_str()));
+
     // If a chunk_id param is provided, try to fetch that exact chunk
-    if let Some(chunk_id) = params.get("chunk_id") {
+    if let Some(chunk_id) = params.get("chunk_id").filter(|id| !is_hidden(id)) {
         if let Ok(Some(rec)) = state.registry_db.read().await.get_chunk(chunk_id).await {
             let name = rec.metadata.name.clone();
             let description = rec.metadata.description.clone();
@@ -210,6 +657,7 @@ pub async fn list_chunks(
                 size: description.len(),
                 created_at: rec.created_at.to_rfc3339(),
                 content_type: "application/json".to_string(),
+                namespace: None,
             }]);
         }
     }
@@ -217,8 +665,10 @@ pub async fn list_chunks(
     // Try registry database first
     match state.registry_db.read().await.debug_list_chunks().await {
         Ok(chunks) => {
+            let chunks = chunks.into_iter()
+                .filter(|c| !c.get("id").and_then(|i| i.as_str()).is_some_and(is_hidden));
             let filtered: Vec<_> = if let Some(q) = params.get("name") {
-                chunks.into_iter()
+                chunks
                     .filter(|c| {
                         // Check both id and name for the query
                         let id_match = c.get("id")
@@ -240,11 +690,12 @@ pub async fn list_chunks(
                             size: description.len(),
                             created_at: chrono::Utc::now().to_rfc3339(),
                             content_type: "application/json".to_string(),
+                            namespace: None,
                         }
                     })
                     .collect()
             } else {
-                chunks.into_iter()
+                chunks
                     .map(|c| {
                         let id = c.get("id").and_then(|i| i.as_str()).unwrap_or("unknown");
                         let name = c.get("name").and_then(|n| n.as_str()).unwrap_or("unknown");
@@ -254,6 +705,7 @@ pub async fn list_chunks(
                             size: description.len(),
                             created_at: chrono::Utc::now().to_rfc3339(),
                             content_type: "application/json".to_string(),
+                            namespace: None,
                         }
                     })
                     .collect()
@@ -263,14 +715,15 @@ pub async fn list_chunks(
         Err(_) => {
             // Fallback to file store
             let store = state.store.read().await;
-            let all_chunks = store.list().await;
+            let all_chunks = store.list().await.into_iter()
+                .filter(|c| !hidden.contains(&c.chunk_id));
             
             let filtered: Vec<_> = if let Some(q) = params.get("name") {
-                all_chunks.into_iter()
+                all_chunks
                     .filter(|c| c.chunk_id.contains(q))
                     .collect()
             } else {
-                all_chunks
+                all_chunks.collect()
             };
             
             Json(filtered)
@@ -278,6 +731,41 @@ pub async fn list_chunks(
     }
 }
 
+/// Query of the chunk ID listing
+#[derive(Deserialize)]
+pub struct ChunkListQuery {
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: usize,
+}
+
+const DEFAULT_CHUNK_PAGE: usize = 100;
+const MAX_CHUNK_PAGE: usize = 1000;
+
+/// List the IDs of stored chunks in sorted order, a page at a time,
+/// leaving out chunks in namespaces the caller can't read
+pub async fn list_chunk_ids(
+    State(state): State<AppState>,
+    Query(query): Query<ChunkListQuery>,
+    headers: HeaderMap,
+) -> Json<cadi_registry::ChunkPage> {
+    let limit = query.limit.unwrap_or(DEFAULT_CHUNK_PAGE).clamp(1, MAX_CHUNK_PAGE);
+    let hidden = hidden_chunks(&state, &headers).await;
+    let mut chunk_ids: Vec<String> = state.store.read().await.list().await
+        .into_iter()
+        .map(|meta| meta.chunk_id)
+        .filter(|chunk_id| !hidden.contains(chunk_id))
+        .collect();
+    chunk_ids.sort();
+    let total = chunk_ids.len();
+    Json(cadi_registry::ChunkPage {
+        chunk_ids: chunk_ids.into_iter().skip(query.offset).take(limit).collect(),
+        total,
+        offset: query.offset,
+        limit,
+    })
+}
+
 /// Stats handler
 pub async fn stats(
     State(state): State<AppState>,
@@ -286,6 +774,23 @@ pub async fn stats(
     Json(store.stats())
 }
 
+/// Prometheus metrics handler
+pub async fn metrics(
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let store = state.store.read().await.stats();
+    let gauges = crate::metrics::Gauges {
+        store_chunks: store.chunk_count,
+        store_bytes: store.total_size,
+        graph_nodes: state.graph.stats().map(|s| s.node_count).unwrap_or(0),
+        graph_edges: state.graph.list_edges().map(|e| e.len()).unwrap_or(0),
+    };
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.render(&gauges),
+    )
+}
+
 /// Search query
 #[derive(Deserialize)]
 pub struct SearchQuery {
@@ -295,6 +800,18 @@ pub struct SearchQuery {
     pub limit: Option<usize>,
     #[serde(default)]
     pub offset: Option<usize>,
+    #[serde(default)]
+    pub granularity: Option<ChunkGranularity>,
+    #[serde(default)]
+    pub categories: Vec<ChunkCategory>,
+    #[serde(default)]
+    pub min_category_confidence: Option<f32>,
+    #[serde(default)]
+    pub max_size: Option<usize>,
+    #[serde(default)]
+    pub min_quality: Option<f32>,
+    #[serde(default)]
+    pub max_complexity: Option<f32>,
 }
 
 /// Search response
@@ -309,28 +826,50 @@ pub struct SearchResponse {
 /// Search handler
 pub async fn search(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(query): Json<SearchQuery>,
 ) -> Json<SearchResponse> {
+    let started = Instant::now();
+    let hidden = hidden_chunks(&state, &headers).await;
     let store = state.store.read().await;
     let all_chunks = store.list().await;
     
     let limit = query.limit.unwrap_or(20);
     let offset = query.offset.unwrap_or(0);
     
-    // Simple substring search
-    let filtered: Vec<_> = if let Some(ref q) = query.query {
-        all_chunks.into_iter()
-            .filter(|c| c.chunk_id.contains(q))
-            .collect()
+    // Granularity, category, quality and complexity live in the registry database
+    let filters = cadi_registry::db::SearchQuery {
+        granularity: query.granularity,
+        categories: query.categories.clone(),
+        min_category_confidence: query.min_category_confidence,
+        min_quality: query.min_quality,
+        max_complexity: query.max_complexity,
+        ..Default::default()
+    };
+    let allowed = if filters.has_filters() {
+        Some(
+            state.registry_db.read().await
+                .matching_chunk_ids(&filters).await
+                .unwrap_or_default(),
+        )
     } else {
-        all_chunks
+        None
     };
+
+    // Simple substring search plus filters
+    let filtered: Vec<_> = all_chunks.into_iter()
+        .filter(|c| query.query.as_ref().is_none_or(|q| c.chunk_id.contains(q)))
+        .filter(|c| query.max_size.is_none_or(|max| c.size <= max))
+        .filter(|c| allowed.as_ref().is_none_or(|ids| ids.contains(&c.chunk_id)))
+        .filter(|c| !hidden.contains(&c.chunk_id))
+        .collect();
     
     let total = filtered.len();
     let chunks: Vec<_> = filtered.into_iter()
         .skip(offset)
         .take(limit)
         .collect();
+    state.metrics.observe_search("structural", started.elapsed());
     
     Json(SearchResponse {
         chunks,
@@ -346,6 +885,9 @@ pub struct SemanticSearchRequest {
     pub query: String,
     #[serde(default)]
     pub limit: Option<usize>,
+    /// Boost frequently used chunks
+    #[serde(default)]
+    pub rank_by_usage: bool,
 }
 
 /// Semantic search response
@@ -357,14 +899,14 @@ pub struct SemanticSearchHit {
 
 pub async fn semantic_search(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(req): Json<SemanticSearchRequest>,
 ) -> Json<Vec<SemanticSearchHit>> {
     let limit = req.limit.unwrap_or(10);
+    let started = Instant::now();
 
     // Generate embedding for the query
-    let provider = Box::new(cadi_llm::embeddings::MockProvider::default());
-    let mut emb_manager = cadi_llm::embeddings::EmbeddingManager::new(provider, None);
-    let embedding = match emb_manager.get_chunk_embedding("query", &req.query).await {
+    let embedding = match state.embedding_manager.lock().await.embed_text(&req.query).await {
         Ok(emb) => emb,
         Err(_) => return Json(vec![]),
     };
@@ -376,11 +918,16 @@ pub async fn semantic_search(
         language: None,
         limit,
         min_score: 0.0,
+        rank_by_usage: req.rank_by_usage,
+        ..Default::default()
     };
 
-    match state.registry_db.read().await.search(query).await {
+    let results = state.registry_db.read().await.search(query).await;
+    state.metrics.observe_search("semantic", started.elapsed());
+    let hidden = hidden_chunks(&state, &headers).await;
+    match results {
         Ok(results) => {
-            Json(results.into_iter().map(|r| SemanticSearchHit {
+            Json(results.into_iter().filter(|r| !hidden.contains(&r.chunk_id)).map(|r| SemanticSearchHit {
                 chunk_id: r.chunk_id,
                 score: r.score as f32,
             }).collect())
@@ -397,18 +944,27 @@ pub struct ViewRequest {
     pub expansion_depth: Option<usize>,
     #[serde(default)]
     pub max_tokens: Option<usize>,
+    /// Return the lines of the view mapped back to their original files
+    #[serde(default)]
+    pub include_source_map: bool,
 }
 
 /// Response for virtual view
 #[derive(Serialize)]
 pub struct ViewResponse {
     pub source: String,
+    /// One file per language in the view
+    pub files: Vec<cadi_core::rehydration::ViewFile>,
     pub atoms: Vec<String>,
     pub ghost_atoms: Vec<String>,
     pub language: String,
     pub token_estimate: usize,
     pub explanation: String,
     pub truncated: bool,
+    /// With `include_source_map`: runs of view lines and the original file
+    /// lines they came from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_map: Option<Vec<cadi_core::rehydration::MappedRegion>>,
 }
 
 /// Handler: create a virtual view from a list of atom/chunk IDs
@@ -427,12 +983,14 @@ pub async fn create_view_handler(
     match view_res {
         Ok(v) => Ok(Json(ViewResponse {
             source: v.source,
+            files: v.files,
             atoms: v.atoms,
             ghost_atoms: v.ghost_atoms,
             language: v.language,
             token_estimate: v.token_estimate,
             explanation: v.explanation,
             truncated: v.truncated,
+            source_map: req.include_source_map.then_some(v.source_map),
         })),
         Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
@@ -447,26 +1005,157 @@ fn is_authorized(state: &AppState, headers: &HeaderMap) -> bool {
 
     // If admin token is configured, accept either Authorization: Bearer <token>
     // or X-Admin-Token: <token>
-    if let Some(ref token) = state.config.admin_token {
-        if let Some(hv) = headers.get("authorization") {
-            if let Ok(s) = hv.to_str() {
-                if let Some(rest) = s.strip_prefix("Bearer ") {
-                    return rest == token;
-                }
-            }
+    namespaces::is_admin(&state.config, headers)
+}
+
+/// Whether a request may write an alias: configured namespaces need one of
+/// their tokens, others admin access
+fn can_write_alias(state: &AppState, headers: &HeaderMap, path: &str) -> bool {
+    namespaces::can_write(&state.config, headers, namespaces::alias_namespace(path))
+        .unwrap_or_else(|| is_authorized(state, headers))
+}
+
+/// Namespaces a chunk is in: the one it was published into and those of
+/// its aliases
+async fn chunk_namespaces(state: &AppState, chunk_id: &str) -> Vec<String> {
+    let mut names: Vec<String> = state.store.read().await.namespace(chunk_id).into_iter().collect();
+    match state.registry_db.read().await.aliases_of(chunk_id).await {
+        Ok(aliases) => names.extend(aliases.iter().map(|a| namespaces::alias_namespace(&a.path).to_string())),
+        Err(e) => eprintln!("alias lookup error for {}: {}", chunk_id, e),
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Whether a request may write a chunk: it needs a token for each
+/// configured namespace the chunk is in or is being published into
+async fn can_write_chunk(state: &AppState, headers: &HeaderMap, chunk_id: &str) -> bool {
+    if state.config.namespaces.is_empty() {
+        return true;
+    }
+    let mut names = chunk_namespaces(state, chunk_id).await;
+    names.extend(namespaces::requested_namespace(headers));
+    namespaces::can_write_all(&state.config, headers, &names).unwrap_or(true)
+}
+
+/// Whether a request may read a chunk: every namespace it is in must be
+/// readable
+async fn can_read_chunk(state: &AppState, headers: &HeaderMap, chunk_id: &str) -> bool {
+    if state.config.namespaces.is_empty() {
+        return true;
+    }
+    chunk_namespaces(state, chunk_id).await
+        .iter()
+        .all(|namespace| namespaces::can_read(&state.config, headers, namespace))
+}
+
+/// Chunks in namespaces the request may not read, to leave out of
+/// listings and search results
+async fn hidden_chunks(state: &AppState, headers: &HeaderMap) -> HashSet<String> {
+    let unreadable = namespaces::unreadable(&state.config, headers);
+    let mut hidden = HashSet::new();
+    if unreadable.is_empty() {
+        return hidden;
+    }
+
+    for meta in state.store.read().await.list().await {
+        if meta.namespace.as_ref().is_some_and(|namespace| unreadable.contains(namespace)) {
+            hidden.insert(meta.chunk_id);
         }
-        if let Some(hv) = headers.get("x-admin-token") {
-            if let Ok(s) = hv.to_str() {
-                return s == token;
+    }
+    for namespace in &unreadable {
+        match all_aliases(state, namespace).await {
+            Ok(aliases) => hidden.extend(
+                aliases
+                    .into_iter()
+                    .filter(|entry| namespaces::alias_namespace(&entry.path) == namespace)
+                    .map(|entry| entry.chunk_id),
+            ),
+            Err(e) => eprintln!("alias listing error for namespace {}: {}", namespace, e),
+        }
+    }
+    hidden
+}
+
+/// Request to re-embed the registry
+#[derive(Debug, Default, Deserialize)]
+pub struct ReembedRequest {
+    /// Chunks re-embedded per batch
+    pub batch_size: Option<usize>,
+}
+
+/// Admin: re-embed every chunk with the current embedding model.
+///
+/// The run continues in the background, one batch per write lock so
+/// searches are served in between; poll `GET /v1/admin/reembed` for progress.
+pub async fn admin_reembed(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    payload: Option<Json<ReembedRequest>>,
+) -> Result<(StatusCode, Json<cadi_registry::ReembedStatus>), StatusCode> {
+    if !is_authorized(&state, &headers) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let batch_size = payload.and_then(|Json(req)| req.batch_size).unwrap_or(64).max(1);
+    {
+        let mut status = state.reembed.lock().await;
+        if status.running {
+            return Err(StatusCode::CONFLICT);
+        }
+        status.running = true;
+        status.error = None;
+    }
+
+    let task_state = state.clone();
+    tokio::spawn(async move {
+        loop {
+            let result = task_state.registry_db.write().await.reembed_batch(batch_size).await;
+            let mut status = task_state.reembed.lock().await;
+            match result {
+                Ok(progress) => {
+                    let done = progress.done;
+                    status.progress = Some(progress);
+                    if done {
+                        status.running = false;
+                        break;
+                    }
+                }
+                Err(e) => {
+                    eprintln!("reembed error: {}", e);
+                    status.running = false;
+                    status.error = Some(e.to_string());
+                    break;
+                }
             }
         }
+    });
+
+    let status = state.reembed.lock().await.clone();
+    Ok((StatusCode::ACCEPTED, Json(status)))
+}
+
+/// Admin: progress of the current or last re-embedding run
+pub async fn admin_reembed_status(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<cadi_registry::ReembedStatus>, StatusCode> {
+    if !is_authorized(&state, &headers) {
+        return Err(StatusCode::FORBIDDEN);
     }
 
-    false
+    let mut status = state.reembed.lock().await.clone();
+    if status.progress.is_none() {
+        // A run from before the last restart
+        status.progress = state.registry_db.read().await.reembed_status().await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+    Ok(Json(status))
 }
 
-/// Helper: create a node from JSON payload
-fn create_node_from_payload(state: &AppState, payload: &serde_json::Value) -> Result<String, StatusCode> {
+/// Helper: build a node and its content from JSON payload
+fn node_from_payload(payload: &serde_json::Value) -> Result<(cadi_core::graph::GraphNode, String), StatusCode> {
     let content = payload.get("content").and_then(|v| v.as_str()).ok_or(StatusCode::BAD_REQUEST)?;
 
     let chunk_id = if let Some(val) = payload.get("chunk_id").and_then(|v| v.as_str()) {
@@ -483,7 +1172,7 @@ fn create_node_from_payload(state: &AppState, payload: &serde_json::Value) -> Re
 
     let content_hash = cadi_core::hash::parse_chunk_id(&chunk_id).ok_or(StatusCode::BAD_REQUEST)?;
 
-    let mut node = cadi_core::graph::GraphNode::new(chunk_id.clone(), content_hash)
+    let mut node = cadi_core::graph::GraphNode::new(chunk_id, content_hash)
         .with_language(payload.get("language").and_then(|v| v.as_str()).unwrap_or("unknown"))
         .with_size(content.as_bytes().len());
 
@@ -501,10 +1190,64 @@ fn create_node_from_payload(state: &AppState, payload: &serde_json::Value) -> Re
         node = node.with_alias(alias);
     }
 
-    state.graph.store_content(&chunk_id, content.as_bytes()).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if let Some(file) = payload.get("source_file").and_then(|v| v.as_str()) {
+        let start = payload.get("start_line").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+        node = node.with_source(file, start, start + content.lines().count().saturating_sub(1));
+    }
+
+    Ok((node, content.to_string()))
+}
+
+/// Helper: create a node from JSON payload
+fn create_node_from_payload(state: &AppState, payload: &serde_json::Value) -> Result<String, StatusCode> {
+    let (node, content) = node_from_payload(payload)?;
+
+    state.graph.store_content(&node.chunk_id, content.as_bytes()).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     state.graph.insert_node(&node).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    link_symbols(state, &node).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    notify_alias(state, &node);
+
+    Ok(node.chunk_id)
+}
+
+/// Helper: add `imports` edges from a new node to the most used chunk
+/// defining each symbol it references, and to it from chunks referencing
+/// a symbol it is the first to define. Only the names link them, so the
+/// edges get symbol-name confidence.
+fn link_symbols(state: &AppState, node: &cadi_core::graph::GraphNode) -> cadi_core::CadiResult<()> {
+    use cadi_core::graph::{confidence, Edge, EdgeType};
+
+    let link = |source: &str, target: &str| {
+        let edge = Edge::new(source, target, EdgeType::Imports).with_confidence(confidence::SYMBOL_NAME);
+        state.graph.add_edge(&edge)
+    };
+    for symbol in &node.symbols_referenced {
+        let definers = state.graph.find_definers(symbol)?;
+        if let Some(definer) = definers.iter().find(|id| **id != node.chunk_id) {
+            link(&node.chunk_id, definer)?;
+        }
+    }
+    for symbol in &node.symbols_defined {
+        if state.graph.find_definers(symbol)? != [node.chunk_id.as_str()] {
+            continue;
+        }
+        for referencer in state.graph.find_referencers(symbol)? {
+            if referencer != node.chunk_id {
+                link(&referencer, &node.chunk_id)?;
+            }
+        }
+    }
+    Ok(())
+}
 
-    Ok(chunk_id)
+/// Helper: tell webhooks about a node's alias
+fn notify_alias(state: &AppState, node: &cadi_core::graph::GraphNode) {
+    if let Some(alias) = &node.primary_alias {
+        state.webhooks.notify(WebhookPayload {
+            alias: Some(alias.clone()),
+            ..WebhookPayload::new(WebhookEvent::AliasUpdate, node.chunk_id.clone())
+        });
+    }
 }
 
 /// Admin: list all graph nodes
@@ -533,7 +1276,7 @@ pub async fn admin_list_nodes(
     }
 }
 
-/// Admin: list all graph edges
+/// Admin: list all graph edges with their confidence and metadata
 pub async fn admin_list_edges(
     State(state): State<AppState>,
     headers: HeaderMap,
@@ -542,13 +1285,15 @@ pub async fn admin_list_edges(
         return Err(StatusCode::FORBIDDEN);
     }
 
-    match state.graph.list_edges() {
+    match state.graph.list_edges_with_metadata() {
         Ok(edges) => {
-            let edge_json: Vec<serde_json::Value> = edges.into_iter().map(|(from, to, edge_type)| {
+            let edge_json: Vec<serde_json::Value> = edges.into_iter().map(|edge| {
                 serde_json::json!({
-                    "from": from,
-                    "to": to,
-                    "edge_type": edge_type
+                    "confidence": edge.confidence(),
+                    "from": edge.source,
+                    "to": edge.target,
+                    "edge_type": edge.edge_type,
+                    "metadata": edge.metadata
                 })
             }).collect();
             Ok(Json(edge_json))
@@ -582,19 +1327,52 @@ pub async fn admin_create_nodes_batch(
         return Err(StatusCode::FORBIDDEN);
     }
 
-    let mut results: Vec<serde_json::Value> = Vec::new();
-    for item in payload.iter() {
-        match create_node_from_payload(&state, item) {
-            Ok(id) => results.push(serde_json::json!({"chunk_id": id, "status": 201})),
-            Err(code) => results.push(serde_json::json!({"status": code.as_u16()})),
+    let mut results = vec![serde_json::Value::Null; payload.len()];
+    let mut positions = Vec::new();
+    let mut nodes = Vec::new();
+    let mut contents = Vec::new();
+    for (i, item) in payload.iter().enumerate() {
+        match node_from_payload(item) {
+            Ok((node, content)) => {
+                positions.push(i);
+                nodes.push(node);
+                contents.push(content);
+            }
+            Err(code) => results[i] = serde_json::json!({"status": code.as_u16()}),
+        }
+    }
+
+    let report = state.graph.insert_nodes_batch(&nodes).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    for failure in &report.failed {
+        results[positions[failure.index]] = serde_json::json!({
+            "status": StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+            "error": failure.error,
+        });
+    }
+
+    for ((position, node), content) in positions.into_iter().zip(&nodes).zip(&contents) {
+        if !results[position].is_null() {
+            continue;
         }
+        let stored = state
+            .graph
+            .store_content(&node.chunk_id, content.as_bytes())
+            .and_then(|()| link_symbols(&state, node));
+        results[position] = match stored {
+            Ok(()) => {
+                notify_alias(&state, node);
+                serde_json::json!({"chunk_id": node.chunk_id, "status": 201})
+            }
+            Err(_) => serde_json::json!({"status": StatusCode::INTERNAL_SERVER_ERROR.as_u16()}),
+        };
     }
 
     Ok(Json(results))
 }
 
-/// Helper: add an edge from payload
-fn add_edge_from_payload(state: &AppState, payload: &serde_json::Value) -> Result<(), StatusCode> {
+/// Helper: parse an edge from payload. Edges without a `confidence` are
+/// declared, and fully trusted.
+fn edge_from_payload(payload: &serde_json::Value) -> Result<cadi_core::graph::Edge, StatusCode> {
     let source = payload.get("source").and_then(|v| v.as_str()).ok_or(StatusCode::BAD_REQUEST)?;
     let target = payload.get("target").and_then(|v| v.as_str()).ok_or(StatusCode::BAD_REQUEST)?;
     let edge_type = payload.get("edge_type").and_then(|v| v.as_str()).unwrap_or("imports");
@@ -611,10 +1389,23 @@ fn add_edge_from_payload(state: &AppState, payload: &serde_json::Value) -> Resul
         "macro_use" => cadi_core::graph::EdgeType::MacroUse,
         "tests" => cadi_core::graph::EdgeType::Tests,
         "doc_ref" => cadi_core::graph::EdgeType::DocRef,
+        "equivalent_to" => cadi_core::graph::EdgeType::EquivalentTo,
+        "refines" => cadi_core::graph::EdgeType::Refines,
         _ => cadi_core::graph::EdgeType::Imports,
     };
 
-    state.graph.add_dependency(source, target, et).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+    let confidence = match payload.get("confidence") {
+        Some(value) => value.as_f64().filter(|c| (0.0..=1.0).contains(c)).ok_or(StatusCode::BAD_REQUEST)? as f32,
+        None => cadi_core::graph::confidence::DECLARED,
+    };
+    Ok(cadi_core::graph::Edge::new(source, target, et).with_confidence(confidence))
+}
+
+/// Helper: add an edge from payload
+fn add_edge_from_payload(state: &AppState, payload: &serde_json::Value) -> Result<(), StatusCode> {
+    let edge = edge_from_payload(payload)?;
+
+    state.graph.add_edge(&edge).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
 }
 
 /// Admin: add an edge between two nodes
@@ -642,14 +1433,27 @@ pub async fn admin_add_edges_batch(
         return Err(StatusCode::FORBIDDEN);
     }
 
-    let mut results: Vec<serde_json::Value> = Vec::new();
-    for item in payload.iter() {
-        match add_edge_from_payload(&state, item) {
-            Ok(_) => results.push(serde_json::json!({"status": 201})),
-            Err(code) => results.push(serde_json::json!({"status": code.as_u16()})),
+    let mut results = vec![serde_json::json!({"status": 201}); payload.len()];
+    let mut positions = Vec::new();
+    let mut edges = Vec::new();
+    for (i, item) in payload.iter().enumerate() {
+        match edge_from_payload(item) {
+            Ok(edge) => {
+                positions.push(i);
+                edges.push(edge);
+            }
+            Err(code) => results[i] = serde_json::json!({"status": code.as_u16()}),
         }
     }
 
+    let report = state.graph.add_edges_batch(&edges).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    for failure in &report.failed {
+        results[positions[failure.index]] = serde_json::json!({
+            "status": StatusCode::BAD_REQUEST.as_u16(),
+            "error": failure.error,
+        });
+    }
+
     Ok(Json(results))
 }
 
@@ -671,6 +1475,11 @@ mod tests {
             anonymous_read: true,
             anonymous_write: true,
             admin_token: None,
+            webhooks: Vec::new(),
+            sync: None,
+            namespaces: Default::default(),
+            mode: ServerMode::ReadWrite,
+            verify_on_start: false,
         };
 
         let state = AppState::new(config.clone()).await;
@@ -717,8 +1526,8 @@ mod tests {
         }
 
         // Call handler
-        let req = SemanticSearchRequest { query: "hello".to_string(), limit: Some(10) };
-        let res = semantic_search(AxState(state), axum::Json(req)).await;
+        let req = SemanticSearchRequest { query: "hello".to_string(), limit: Some(10), rank_by_usage: false };
+        let res = semantic_search(AxState(state), HeaderMap::new(), axum::Json(req)).await;
         assert!(!res.0.is_empty(), "Expected at least one search hit");
         assert_eq!(res.0[0].chunk_id, chunk_id);
     }
@@ -733,6 +1542,11 @@ mod tests {
             anonymous_read: true,
             anonymous_write: true,
             admin_token: None,
+            webhooks: Vec::new(),
+            sync: None,
+            namespaces: Default::default(),
+            mode: ServerMode::ReadWrite,
+            verify_on_start: false,
         };
 
         let state = AppState::new(config.clone()).await;
@@ -761,7 +1575,9 @@ mod tests {
             "chunk_id": id_b,
             "content": b_content,
             "language": "rust",
-            "references": ["helper"]
+            "references": ["helper"],
+            "source_file": "src/app.rs",
+            "start_line": 12
         });
         let _payload_b = serde_json::to_vec(&new_node_b).unwrap();
         let mut headers_b = HeaderMap::new();
@@ -778,11 +1594,490 @@ mod tests {
         assert!(edge_res.is_ok());
 
         // Now create a view for B and ensure A appears as ghost import
-        let view_req = ViewRequest { atoms: vec![id_b.clone()], expansion_depth: Some(1), max_tokens: Some(1024) };
-        let view = create_view_handler(AxState(state), axum::Json(view_req)).await.expect("view failed");
+        let view_req = ViewRequest { atoms: vec![id_b.clone()], expansion_depth: Some(1), max_tokens: Some(1024), include_source_map: false };
+        let view = create_view_handler(AxState(state.clone()), axum::Json(view_req)).await.expect("view failed");
         let json = view.0;
         assert!(json.atoms.contains(&id_a));
         assert!(json.ghost_atoms.contains(&id_a));
+        assert!(json.source_map.is_none());
+
+        // The source map leads back to B's original file
+        let view_req = ViewRequest { atoms: vec![id_b.clone()], expansion_depth: None, max_tokens: None, include_source_map: true };
+        let json = create_view_handler(AxState(state), axum::Json(view_req)).await.expect("view failed").0;
+        let map = json.source_map.expect("source map requested");
+        let line = json.source.lines().position(|l| l.starts_with("pub fn use_helper")).unwrap() + 1;
+        let region = map.iter().find(|r| r.view_start_line <= line && line <= r.view_end_line).unwrap();
+        assert_eq!((region.chunk_id.as_str(), region.file.as_str()), (id_b.as_str(), "src/app.rs"));
+        assert_eq!(region.original_start_line + (line - region.view_start_line), 12);
+    }
+
+    #[tokio::test]
+    async fn test_admin_nodes_are_linked_through_symbols() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut config = ServerConfig::default();
+        config.storage_path = tmp.path().to_str().unwrap().to_string();
+        config.anonymous_write = true;
+        let state = AppState::new(config).await;
+
+        let create = |content: &str, symbols: serde_json::Value| {
+            let mut payload = serde_json::json!({ "content": content, "language": "rust" });
+            payload.as_object_mut().unwrap().extend(symbols.as_object().unwrap().clone());
+            create_node_from_payload(&state, &payload).unwrap()
+        };
+        let caller = create("fn run() { helper() }", serde_json::json!({ "references": ["helper"] }));
+        let helper = create("fn helper() {}", serde_json::json!({ "defines": ["helper"] }));
+        let other = create("fn main() { helper() }", serde_json::json!({ "references": ["helper"] }));
+
+        let imports = |id: &str| state.graph.get_dependencies_of_type(id, cadi_core::graph::EdgeType::Imports).unwrap();
+        assert_eq!(imports(&caller), [helper.clone()]);
+        assert_eq!(imports(&other), [helper.clone()]);
+        assert_eq!(state.graph.find_referencers("helper").unwrap().len(), 2);
+
+        // Symbol links are less certain than declared edges, which replace them
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", HeaderValue::from_static("Bearer dev"));
+        let confidence = |edges: &[serde_json::Value], from: &str| {
+            edges.iter().find(|e| e["from"] == from).unwrap()["confidence"].as_f64().unwrap()
+        };
+        let edges = admin_list_edges(AxState(state.clone()), headers.clone()).await.unwrap().0;
+        assert_eq!(edges.len(), 2);
+        assert!(confidence(&edges, &caller) < 0.5);
+        assert!(edges[0]["metadata"].is_object());
+
+        let declared = serde_json::json!({ "source": caller, "target": helper, "edge_type": "imports" });
+        admin_add_edge(AxState(state.clone()), headers.clone(), axum::Json(declared)).await.unwrap();
+        let invalid = serde_json::json!({ "source": other, "target": helper, "confidence": 2.0 });
+        let rejected = admin_add_edge(AxState(state.clone()), headers.clone(), axum::Json(invalid)).await;
+        assert_eq!(rejected, Err(StatusCode::BAD_REQUEST));
+
+        let edges = admin_list_edges(AxState(state.clone()), headers).await.unwrap().0;
+        assert_eq!(confidence(&edges, &caller), 1.0);
+        assert!(confidence(&edges, &other) < 0.5);
+    }
+
+    #[tokio::test]
+    async fn test_published_chunk_is_served_with_etag() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut config = ServerConfig::default();
+        config.storage_path = tmp.path().to_str().unwrap().to_string();
+        let state = AppState::new(config).await;
+
+        let content = b"pub fn helper() -> i32 { 42 }".to_vec();
+        let chunk_id = cadi_core::hash::chunk_id_from_content(&content);
+        let res = put_chunk(AxState(state.clone()), Path(chunk_id.clone()), HeaderMap::new(), Bytes::from(content.clone())).await.unwrap();
+        assert!(res.0.success);
+
+        assert!(head_chunk(AxState(state.clone()), Path(chunk_id.clone()), HeaderMap::new()).await.is_ok());
+        let res = get_chunk(AxState(state.clone()), Path(chunk_id.clone()), HeaderMap::new()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let etag = res.headers()[header::ETAG].clone();
+        assert_eq!(axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap(), content);
+
+        // Revalidating with the ETag transfers no body
+        let mut conditional = HeaderMap::new();
+        conditional.insert(header::IF_NONE_MATCH, etag);
+        let res = get_chunk(AxState(state.clone()), Path(chunk_id.clone()), conditional.clone()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert!(axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap().is_empty());
+
+        // The ETag of one chunk does not match another
+        let other = cadi_core::hash::chunk_id_from_content(b"other");
+        put_chunk(AxState(state.clone()), Path(other.clone()), HeaderMap::new(), Bytes::from_static(b"other")).await.unwrap();
+        let res = get_chunk(AxState(state.clone()), Path(other), conditional).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // Publishing the same chunk again still succeeds
+        let again = put_chunk(AxState(state), Path(chunk_id), HeaderMap::new(), Bytes::from(content)).await.unwrap();
+        assert!(again.0.success);
+    }
+
+    #[tokio::test]
+    async fn test_fetches_and_build_plans_count_as_usage() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = ServerConfig {
+            storage_path: tmp.path().to_str().unwrap().to_string(),
+            admin_token: Some("admin-token".to_string()),
+            ..Default::default()
+        };
+        let state = AppState::new(config).await;
+
+        let content = b"pub fn popular() {}".to_vec();
+        let chunk_id = cadi_core::hash::chunk_id_from_content(&content);
+        put_chunk(AxState(state.clone()), Path(chunk_id.clone()), HeaderMap::new(), Bytes::from(content)).await.unwrap();
+
+        get_chunk(AxState(state.clone()), Path(chunk_id.clone()), HeaderMap::new()).await.unwrap();
+        let mut conditional = HeaderMap::new();
+        conditional.insert(header::IF_NONE_MATCH, HeaderValue::from_str(&chunk_etag(&chunk_id)).unwrap());
+        get_chunk(AxState(state.clone()), Path(chunk_id.clone()), conditional).await.unwrap();
+        // Reports need a token, are capped, and count each stored chunk once
+        let report = |chunks: Vec<String>| axum::Json(UsageReport { chunks });
+        let plan = vec![chunk_id.clone(), chunk_id.clone(), "chunk:sha256:unknown".to_string()];
+        let mut authorized = HeaderMap::new();
+        authorized.insert("authorization", HeaderValue::from_static("Bearer admin-token"));
+        assert_eq!(report_usage(AxState(state.clone()), HeaderMap::new(), report(plan.clone())).await, StatusCode::FORBIDDEN);
+        let too_many = vec![chunk_id.clone(); MAX_USAGE_BATCH + 1];
+        assert_eq!(report_usage(AxState(state.clone()), authorized.clone(), report(too_many)).await, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(report_usage(AxState(state.clone()), authorized, report(plan)).await, StatusCode::ACCEPTED);
+
+        // Uses are written in the background
+        state.usage.flush().await;
+        let stats = get_chunk_stats(AxState(state.clone()), Path(chunk_id.clone()), HeaderMap::new()).await.unwrap().0;
+        assert_eq!(stats.usage_count, 3);
+        assert!(stats.last_used_at.is_some());
+        assert_eq!(stats.daily.len(), 1);
+        assert_eq!(stats.daily[0].count, 3);
+
+        let missing = get_chunk_stats(AxState(state), Path("chunk:sha256:unknown".to_string()), HeaderMap::new()).await;
+        assert_eq!(missing.unwrap_err(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_alias_endpoints() {
+        use tower::ServiceExt;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let config = ServerConfig {
+            storage_path: tmp.path().to_str().unwrap().to_string(),
+            admin_token: Some("secret-token".to_string()),
+            ..Default::default()
+        };
+        let app = crate::routes::app(AppState::new(config).await);
+        let request = |method: &str, uri: &str, token: Option<&str>, body: serde_json::Value| {
+            let mut builder = axum::http::Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/json");
+            if let Some(token) = token {
+                builder = builder.header("authorization", format!("Bearer {}", token));
+            }
+            builder.body(axum::body::Body::from(body.to_string())).unwrap()
+        };
+        let send = |req: axum::http::Request<axum::body::Body>| async {
+            let res = app.clone().oneshot(req).await.unwrap();
+            let status = res.status();
+            let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+            (status, serde_json::from_slice::<serde_json::Value>(&body).unwrap_or_default())
+        };
+        let content = serde_json::json!("logger");
+        let chunk_id = cadi_core::hash::chunk_id_from_content(content.to_string().as_bytes());
+        let body = serde_json::json!({ "chunk_id": chunk_id });
+
+        // Aliases point at stored chunks
+        let (status, _) = send(request("PUT", "/aliases/acme/utils/logger", Some("secret-token"), body.clone())).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = send(request("PUT", &format!("/v1/chunks/{}", chunk_id), Some("secret-token"), content)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Writes need the admin token
+        let (status, _) = send(request("PUT", "/aliases/acme/utils/logger", None, body.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(request("PUT", "/aliases/acme/../logger", Some("secret-token"), body.clone())).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        for path in ["acme/utils/logger", "acme/utils/config", "other/tool"] {
+            let (status, entry) = send(request("PUT", &format!("/aliases/{}", path), Some("secret-token"), body.clone())).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(entry["path"], path);
+        }
+
+        // Anyone can resolve and list
+        let (status, entry) = send(request("GET", "/aliases/acme/utils/logger", None, serde_json::Value::Null)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(entry["chunk_id"], chunk_id);
+        let (_, page) = send(request("GET", "/aliases?prefix=acme/&limit=1", None, serde_json::Value::Null)).await;
+        assert_eq!(page["total"], 2);
+        assert_eq!(page["aliases"][0]["path"], "acme/utils/config");
+        let (_, page) = send(request("GET", "/aliases?prefix=acme/&limit=1&offset=1", None, serde_json::Value::Null)).await;
+        assert_eq!(page["aliases"][0]["path"], "acme/utils/logger");
+
+        let (status, _) = send(request("DELETE", "/aliases/acme/utils/logger", None, serde_json::Value::Null)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(request("DELETE", "/aliases/acme/utils/logger", Some("secret-token"), serde_json::Value::Null)).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = send(request("GET", "/aliases/acme/utils/logger", None, serde_json::Value::Null)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    /// A server with a public `acme` namespace and a private `globex` one,
+    /// and a function sending a request with an optional token and
+    /// namespace header
+    async fn namespaced_app() -> (
+        tempfile::TempDir,
+        impl Fn(&str, &str, Option<&str>, Option<&str>, Vec<u8>) -> axum::http::Request<axum::body::Body>,
+        axum::Router,
+    ) {
+        let tmp = tempfile::tempdir().unwrap();
+        let namespace = |token: &str, public_read: bool| namespaces::NamespaceConfig {
+            write_tokens: vec![token.to_string()],
+            public_read,
+        };
+        let config = ServerConfig {
+            storage_path: tmp.path().to_str().unwrap().to_string(),
+            anonymous_write: true,
+            admin_token: Some("admin-token".to_string()),
+            namespaces: [
+                ("acme".to_string(), namespace("acme-token", true)),
+                ("globex".to_string(), namespace("globex-token", false)),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        let app = crate::routes::app(AppState::new(config).await);
+        let request = |method: &str, uri: &str, token: Option<&str>, namespace: Option<&str>, body: Vec<u8>| {
+            let mut builder = axum::http::Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/json");
+            if let Some(token) = token {
+                builder = builder.header("authorization", format!("Bearer {}", token));
+            }
+            if let Some(namespace) = namespace {
+                builder = builder.header(namespaces::NAMESPACE_HEADER, namespace);
+            }
+            builder.body(axum::body::Body::from(body)).unwrap()
+        };
+        (tmp, request, app)
+    }
+
+    async fn send(app: &axum::Router, req: axum::http::Request<axum::body::Body>) -> (StatusCode, serde_json::Value) {
+        use tower::ServiceExt;
+
+        let res = app.clone().oneshot(req).await.unwrap();
+        let status = res.status();
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn test_read_only_mode_rejects_changes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = ServerConfig {
+            storage_path: tmp.path().to_str().unwrap().to_string(),
+            anonymous_write: true,
+            admin_token: Some("admin-token".to_string()),
+            mode: ServerMode::ReadOnly,
+            ..Default::default()
+        };
+        let state = AppState::new(config).await;
+        let content = b"pub fn mirrored() {}".to_vec();
+        let chunk_id = cadi_core::hash::chunk_id_from_content(&content);
+        state.store.write().await.store(chunk_id.clone(), content.clone(), None).await.unwrap();
+        let app = crate::routes::app(state);
+        let request = |method: &str, uri: &str, body: Vec<u8>| {
+            axum::http::Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .header("authorization", "Bearer admin-token")
+                .body(axum::body::Body::from(body))
+                .unwrap()
+        };
+        let chunk_uri = format!("/v1/chunks/{}", chunk_id);
+
+        // Changes are rejected even with the admin token
+        let node = serde_json::json!({ "content": "pub fn node() {}" }).to_string().into_bytes();
+        let alias = serde_json::json!({ "chunk_id": chunk_id }).to_string().into_bytes();
+        let usage = serde_json::json!({ "chunks": [chunk_id] }).to_string().into_bytes();
+        for (method, uri, body) in [
+            ("PUT", chunk_uri.as_str(), content.clone()),
+            ("DELETE", chunk_uri.as_str(), Vec::new()),
+            ("PUT", "/aliases/utils/mirrored", alias),
+            ("POST", "/v1/admin/nodes", node),
+            ("POST", "/v1/usage", usage),
+        ] {
+            let (status, body) = send(&app, request(method, uri, body)).await;
+            assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED, "{} {}", method, uri);
+            assert_eq!(body["mode"], "read_only");
+        }
+
+        // Reads, searches and existence checks still work
+        let (status, _) = send(&app, request("GET", &chunk_uri, Vec::new())).await;
+        assert_eq!(status, StatusCode::OK);
+        let exists = serde_json::json!({ "hashes": [] }).to_string().into_bytes();
+        let (status, _) = send(&app, request("POST", "/v1/chunks/exists", exists)).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&app, request("POST", "/v1/search", b"{}".to_vec())).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, health) = send(&app, request("GET", "/health", Vec::new())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(health["mode"], "read_only");
+        assert_eq!(health["quarantined_chunks"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_namespace_writes_need_their_token() {
+        let (_tmp, request, app) = namespaced_app().await;
+        let content = b"pub fn acme() {}".to_vec();
+        let chunk_id = cadi_core::hash::chunk_id_from_content(&content);
+        let chunk_uri = format!("/v1/chunks/{}", chunk_id);
+        let alias = |chunk_id: &str| serde_json::json!({ "chunk_id": chunk_id }).to_string().into_bytes();
+
+        // Configured namespaces need their own token, even with anonymous
+        // writes enabled
+        let (status, _) = send(&app, request("PUT", &chunk_uri, None, Some("acme"), content.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(&app, request("PUT", &chunk_uri, Some("globex-token"), Some("acme"), content.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, body) = send(&app, request("PUT", &chunk_uri, Some("acme-token"), Some("acme"), content.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["success"], true);
+
+        // The chunk stays in acme: globex can't republish or delete it
+        let (status, _) = send(&app, request("PUT", &chunk_uri, Some("globex-token"), Some("globex"), content.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(&app, request("DELETE", &chunk_uri, Some("globex-token"), None, Vec::new())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        // Alias paths name their namespace
+        let (status, _) = send(&app, request("PUT", "/aliases/acme/utils/logger", Some("globex-token"), None, alias(&chunk_id))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(&app, request("PUT", "/aliases/acme/utils/logger", Some("acme-token"), None, alias(&chunk_id))).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&app, request("PUT", "/aliases/globex/logger", Some("acme-token"), None, alias(&chunk_id))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(&app, request("PUT", "/aliases/globex/logger", Some("admin-token"), None, alias(&chunk_id))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Other namespaces follow the server-wide rules, but an alias can't
+        // pull a chunk out of namespaces the caller can't write
+        let (status, _) = send(&app, request("PUT", "/aliases/other/logger", None, None, alias(&chunk_id))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let shared = b"pub fn shared() {}".to_vec();
+        let shared_id = cadi_core::hash::chunk_id_from_content(&shared);
+        send(&app, request("PUT", &format!("/v1/chunks/{}", shared_id), None, None, shared)).await;
+        let (status, _) = send(&app, request("PUT", "/aliases/other/logger", None, None, alias(&shared_id))).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_aliases_cant_claim_other_tenants_chunks() {
+        let (_tmp, request, app) = namespaced_app().await;
+        let alias = |chunk_id: &str| serde_json::json!({ "chunk_id": chunk_id }).to_string().into_bytes();
+        let publish = |content: &[u8], token: Option<&'static str>, namespace: Option<&'static str>| {
+            let chunk_id = cadi_core::hash::chunk_id_from_content(content);
+            (chunk_id.clone(), request("PUT", &format!("/v1/chunks/{}", chunk_id), token, namespace, content.to_vec()))
+        };
+        let (acme, req) = publish(b"pub fn acme() {}", Some("acme-token"), Some("acme"));
+        send(&app, req).await;
+        let (public, req) = publish(b"pub fn public() {}", None, None);
+        send(&app, req).await;
+
+        // globex can't alias acme's chunk into its own namespace
+        let (status, _) = send(&app, request("PUT", "/aliases/globex/stolen", Some("globex-token"), None, alias(&acme))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let missing = cadi_core::hash::chunk_id_from_content(b"missing");
+        let (status, _) = send(&app, request("PUT", "/aliases/globex/missing", Some("globex-token"), None, alias(&missing))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // So acme and anonymous callers still read the chunk and acme can
+        // still delete it
+        for token in [None, Some("acme-token")] {
+            let (status, _) = send(&app, request("GET", &format!("/v1/chunks/{}", acme), token, None, Vec::new())).await;
+            assert_eq!(status, StatusCode::OK);
+        }
+        let (_, page) = send(&app, request("GET", "/v1/chunk_ids", None, None, Vec::new())).await;
+        assert_eq!(page["total"], 2);
+        let (status, _) = send(&app, request("DELETE", &format!("/v1/chunks/{}", acme), Some("acme-token"), None, Vec::new())).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        // Chunks outside configured namespaces can still be aliased
+        let (status, _) = send(&app, request("PUT", "/aliases/globex/public", Some("globex-token"), None, alias(&public))).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_cache_writes_need_a_token() {
+        let (_tmp, request, app) = namespaced_app().await;
+        let output = b"compiled output".to_vec();
+
+        // Anonymous writes are enabled, but not for the build cache
+        let (status, _) = send(&app, request("PUT", "/cache/abc123", None, None, output.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(&app, request("PUT", "/cache/abc123", Some("wrong-token"), None, output.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(&app, request("GET", "/cache/abc123", None, None, Vec::new())).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, _) = send(&app, request("PUT", "/cache/abc123", Some("acme-token"), None, output.clone())).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let (status, _) = send(&app, request("PUT", "/cache/def456", Some("admin-token"), None, output)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let (status, _) = send(&app, request("HEAD", "/cache/abc123", None, None, Vec::new())).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_private_namespaces_are_hidden() {
+        let (_tmp, request, app) = namespaced_app().await;
+        let publish = |content: &[u8], token: Option<&'static str>, namespace: Option<&'static str>| {
+            let chunk_id = cadi_core::hash::chunk_id_from_content(content);
+            (chunk_id.clone(), request("PUT", &format!("/v1/chunks/{}", chunk_id), token, namespace, content.to_vec()))
+        };
+        let (public, req) = publish(b"pub fn public() {}", Some("acme-token"), Some("acme"));
+        send(&app, req).await;
+        let (private, req) = publish(b"pub fn private() {}", Some("globex-token"), Some("globex"));
+        send(&app, req).await;
+        let (aliased, req) = publish(b"pub fn aliased() {}", None, None);
+        send(&app, req).await;
+        let body = serde_json::json!({ "chunk_id": aliased }).to_string().into_bytes();
+        send(&app, request("PUT", "/aliases/globex/aliased", Some("globex-token"), None, body)).await;
+
+        // Public namespaces can be read by anyone; private ones need one
+        // of their tokens, including chunks only aliased there
+        for token in [None, Some("acme-token"), Some("globex-token")] {
+            let (status, _) = send(&app, request("GET", &format!("/v1/chunks/{}", public), token, None, Vec::new())).await;
+            assert_eq!(status, StatusCode::OK);
+        }
+        for (token, expected) in [(None, StatusCode::FORBIDDEN), (Some("acme-token"), StatusCode::FORBIDDEN), (Some("globex-token"), StatusCode::OK)] {
+            for chunk_id in [&private, &aliased] {
+                let (status, _) = send(&app, request("GET", &format!("/v1/chunks/{}", chunk_id), token, None, Vec::new())).await;
+                assert_eq!(status, expected);
+            }
+            let (status, _) = send(&app, request("GET", "/aliases/globex/aliased", token, None, Vec::new())).await;
+            assert_eq!(status, expected);
+            // Usage stats too; readers may get 404 until a use is recorded
+            let (status, _) = send(&app, request("GET", &format!("/v1/chunks/{}/stats", private), token, None, Vec::new())).await;
+            assert_eq!(status == StatusCode::FORBIDDEN, expected == StatusCode::FORBIDDEN);
+        }
+
+        // Listings and search leave out what the caller can't read
+        let (_, page) = send(&app, request("GET", "/v1/chunk_ids", None, None, Vec::new())).await;
+        assert_eq!(page["chunk_ids"], serde_json::json!([public]));
+        let (_, page) = send(&app, request("GET", "/v1/chunk_ids", Some("globex-token"), None, Vec::new())).await;
+        assert_eq!(page["total"], 3);
+        let (_, results) = send(&app, request("POST", "/v1/search", None, None, b"{}".to_vec())).await;
+        assert_eq!(results["total"], 1);
+        assert_eq!(results["chunks"][0]["chunk_id"], public);
+        let (_, aliases) = send(&app, request("GET", "/aliases", None, None, Vec::new())).await;
+        assert_eq!(aliases["total"], 0);
+        let (_, aliases) = send(&app, request("GET", "/aliases", Some("admin-token"), None, Vec::new())).await;
+        assert_eq!(aliases["total"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_exists_reports_readable_chunks() {
+        let (_tmp, request, app) = namespaced_app().await;
+        let mut hashes = Vec::new();
+        for (content, token, namespace) in [
+            (&b"pub fn one() {}"[..], None, None),
+            (b"pub fn two() {}", Some("globex-token"), Some("globex")),
+        ] {
+            let chunk_id = cadi_core::hash::chunk_id_from_content(content);
+            send(&app, request("PUT", &format!("/v1/chunks/{}", chunk_id), token, namespace, content.to_vec())).await;
+            hashes.push(cadi_core::hash::chunk_storage_key(&chunk_id));
+        }
+        let unknown = cadi_core::hash::chunk_storage_key(&cadi_core::hash::chunk_id_from_content(b"unknown"));
+        let body = serde_json::json!({ "hashes": [&hashes[0], &unknown, &hashes[1], &hashes[0]] }).to_string().into_bytes();
+
+        // Chunks in a private namespace exist only for its readers
+        let (status, res) = send(&app, request("POST", "/v1/chunks/exists", None, None, body.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(res["existing"], serde_json::json!([&hashes[0]]));
+        let (_, res) = send(&app, request("POST", "/v1/chunks/exists", Some("globex-token"), None, body)).await;
+        assert_eq!(res["existing"], serde_json::json!([&hashes[0], &hashes[1]]));
+
+        let too_many = serde_json::json!({ "hashes": vec![&unknown; MAX_EXISTS_BATCH + 1] }).to_string().into_bytes();
+        let (status, _) = send(&app, request("POST", "/v1/chunks/exists", None, None, too_many)).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
     }
 
     #[tokio::test]
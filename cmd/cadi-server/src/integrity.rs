@@ -0,0 +1,102 @@
+//! Startup integrity check of the chunk store
+//!
+//! With `CADI_VERIFY_ON_START` set, the server re-hashes every stored chunk
+//! in the background once it starts. A chunk whose content no longer
+//! matches its ID, e.g. after disk corruption or a hand-edited file, is
+//! logged and moved to the [`QUARANTINE_DIR`] so it is never served.
+//! Requests are answered while the check runs.
+
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::state::{ChunkStore, QUARANTINE_DIR};
+
+/// Re-hash every chunk in the store, quarantining those whose content
+/// doesn't match their ID. Returns the number quarantined.
+///
+/// Chunks are read under a read lock, one at a time, so uploads aren't
+/// held up for the whole check.
+pub async fn verify_store(store: &RwLock<ChunkStore>) -> usize {
+    let chunk_ids: Vec<String> = store.read().await.list().await.into_iter().map(|meta| meta.chunk_id).collect();
+    let total = chunk_ids.len();
+    let mut quarantined = 0;
+
+    for chunk_id in chunk_ids {
+        // Only content-addressed IDs can be checked
+        if cadi_core::hash::ChunkId::parse(&chunk_id).is_err() {
+            continue;
+        }
+        let matches = |data: &Option<Vec<u8>>| {
+            data.as_ref().is_none_or(|data| cadi_core::hash::verify_chunk_content(&chunk_id, data))
+        };
+        if matches(&store.read().await.get(&chunk_id).await) {
+            continue;
+        }
+
+        // Check again under the write lock: the chunk may have been
+        // published again in the meantime
+        let mut store = store.write().await;
+        if matches(&store.get(&chunk_id).await) {
+            continue;
+        }
+        match store.quarantine(&chunk_id).await {
+            Ok(()) => {
+                tracing::warn!("Chunk {} doesn't match its content hash; moved to {}/", chunk_id, QUARANTINE_DIR);
+                quarantined += 1;
+            }
+            Err(e) => tracing::error!("Failed to quarantine corrupt chunk {}: {}", chunk_id, e),
+        }
+    }
+
+    tracing::info!("Verified {} chunks, quarantined {}", total, quarantined);
+    quarantined
+}
+
+/// Run [`verify_store`] in the background
+pub fn spawn(store: Arc<RwLock<ChunkStore>>) -> tokio::task::JoinHandle<usize> {
+    tokio::spawn(async move { verify_store(&store).await })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{AppState, ServerConfig};
+
+    #[tokio::test]
+    async fn test_corrupt_chunks_are_quarantined() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = ServerConfig {
+            storage_path: tmp.path().to_str().unwrap().to_string(),
+            ..Default::default()
+        };
+        let state = AppState::new(config).await;
+
+        let good = b"pub fn intact() {}".to_vec();
+        let bad = b"pub fn corrupted() {}".to_vec();
+        let good_id = cadi_core::hash::chunk_id_from_content(&good);
+        let bad_id = cadi_core::hash::chunk_id_from_content(&bad);
+        {
+            let mut store = state.store.write().await;
+            store.store(good_id.clone(), good.clone(), None).await.unwrap();
+            store.store(bad_id.clone(), bad, None).await.unwrap();
+        }
+        let chunk_file = |id: &str| format!("{}.chunk", id.replace(':', "_"));
+        std::fs::write(tmp.path().join(chunk_file(&bad_id)), b"pub fn tampered() {}").unwrap();
+
+        assert_eq!(spawn(state.store.clone()).await.unwrap(), 1);
+
+        let store = state.store.read().await;
+        assert_eq!(store.get(&good_id).await, Some(good));
+        assert!(!store.exists(&bad_id).await);
+        assert!(store.get_meta(&bad_id).await.is_none());
+        assert!(tmp.path().join(QUARANTINE_DIR).join(chunk_file(&bad_id)).is_file());
+        assert_eq!(store.quarantined_count(), 1);
+        assert_eq!(store.stats().quarantined_count, 1);
+        drop(store);
+
+        // A second pass finds nothing new
+        assert_eq!(verify_store(&state.store).await, 0);
+        let health = crate::handlers::health(axum::extract::State(state)).await;
+        assert_eq!(health.0.quarantined_chunks, 1);
+    }
+}
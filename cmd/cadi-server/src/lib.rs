@@ -0,0 +1,15 @@
+//! CADI Registry Server
+//!
+//! HTTP server for hosting a CADI registry. The `cadi-server` binary serves
+//! [`routes::app`] on the configured address; other tools can embed the
+//! same router, e.g. to run a throwaway registry in-process.
+
+pub mod handlers;
+pub mod integrity;
+pub mod metrics;
+pub mod namespaces;
+pub mod state;
+pub mod routes;
+pub mod sync;
+pub mod usage;
+pub mod webhooks;
@@ -2,13 +2,8 @@
 //!
 //! HTTP server for hosting a CADI registry.
 
-mod handlers;
-mod state;
-mod routes;
-
-use axum::Router;
+use cadi_server::{routes, state};
 use std::net::SocketAddr;
-use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
@@ -26,19 +21,29 @@ async fn main() {
     let state = state::AppState::new(config.clone()).await;
 
     // Build the router
-    let app = Router::new()
-        .merge(routes::api_routes())
-        .layer(TraceLayer::new_for_http())
-        .with_state(state);
+    let app = routes::app(state);
 
     // Start the server
     let addr: SocketAddr = config.bind_address.parse()
         .expect("Invalid bind address");
     
-    tracing::info!("Starting CADI server on {}", addr);
+    tracing::info!("Starting CADI server on {} ({})", addr, config.mode.as_str());
     
     let listener = tokio::net::TcpListener::bind(addr).await
         .expect("Failed to bind");
+
+    if let Some(sync) = config.sync.clone() {
+        // Reach this server over loopback when bound to every interface
+        let mut local = listener.local_addr().expect("Failed to read bound address");
+        if local.ip().is_unspecified() {
+            local.set_ip(std::net::Ipv4Addr::LOCALHOST.into());
+        }
+        if config.mode == state::ServerMode::ReadOnly && sync.direction != cadi_server::sync::SyncDirection::Push {
+            tracing::warn!("This server is read-only, so chunks pulled from {} will be rejected", sync.peer);
+        }
+        tracing::info!("Syncing with {} every {:?}", sync.peer, sync.interval);
+        sync.spawn(format!("http://{}", local), config.admin_token.clone());
+    }
     
     axum::serve(listener, app).await
         .expect("Server error");
@@ -0,0 +1,219 @@
+//! Prometheus metrics
+//!
+//! Counters and histograms are updated by the request middleware and the
+//! handlers. Gauges for the chunk store and graph are read when `/metrics`
+//! is scraped.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+
+/// Upper bounds of the search latency buckets, in seconds
+const LATENCY_BUCKETS: [f64; 10] = [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0];
+
+/// Server metrics, shared by the middleware and the handlers
+#[derive(Default)]
+pub struct Metrics {
+    /// Requests by method, route and status
+    requests: Mutex<BTreeMap<(String, String, u16), u64>>,
+    upload_bytes: AtomicU64,
+    download_bytes: AtomicU64,
+    /// Rejected chunk uploads by reason
+    rejections: Mutex<BTreeMap<&'static str, u64>>,
+    /// Search latency by kind of search
+    search_latency: Mutex<BTreeMap<&'static str, Histogram>>,
+}
+
+/// Values sampled at scrape time
+#[derive(Debug, Default)]
+pub struct Gauges {
+    pub store_chunks: usize,
+    pub store_bytes: usize,
+    pub graph_nodes: usize,
+    pub graph_edges: usize,
+}
+
+#[derive(Default)]
+struct Histogram {
+    /// Cumulative counts per bucket of [`LATENCY_BUCKETS`]
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Metrics {
+    pub fn record_request(&self, method: &str, route: &str, status: u16) {
+        let mut requests = self.requests.lock().unwrap();
+        *requests.entry((method.to_string(), route.to_string(), status)).or_default() += 1;
+    }
+
+    pub fn add_upload_bytes(&self, bytes: usize) {
+        self.upload_bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn add_download_bytes(&self, bytes: usize) {
+        self.download_bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Count a rejected chunk upload, e.g. `hash_mismatch`
+    pub fn record_rejection(&self, reason: &'static str) {
+        *self.rejections.lock().unwrap().entry(reason).or_default() += 1;
+    }
+
+    /// Record how long a search took; `kind` is `structural` or `semantic`
+    pub fn observe_search(&self, kind: &'static str, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        let mut latency = self.search_latency.lock().unwrap();
+        let histogram = latency.entry(kind).or_default();
+        for (count, bound) in histogram.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *count += 1;
+            }
+        }
+        histogram.count += 1;
+        histogram.sum += seconds;
+    }
+
+    /// Render every metric in the Prometheus text exposition format
+    pub fn render(&self, gauges: &Gauges) -> String {
+        let mut out = String::new();
+
+        header(&mut out, "cadi_http_requests_total", "counter", "HTTP requests by method, route and status");
+        for ((method, route, status), count) in self.requests.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "cadi_http_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+                method, route, status, count
+            );
+        }
+
+        header(&mut out, "cadi_chunk_upload_bytes_total", "counter", "Bytes of chunks stored");
+        let _ = writeln!(out, "cadi_chunk_upload_bytes_total {}", self.upload_bytes.load(Ordering::Relaxed));
+        header(&mut out, "cadi_chunk_download_bytes_total", "counter", "Bytes of chunks served");
+        let _ = writeln!(out, "cadi_chunk_download_bytes_total {}", self.download_bytes.load(Ordering::Relaxed));
+
+        header(&mut out, "cadi_chunk_rejections_total", "counter", "Rejected chunk uploads by reason");
+        for (reason, count) in self.rejections.lock().unwrap().iter() {
+            let _ = writeln!(out, "cadi_chunk_rejections_total{{reason=\"{}\"}} {}", reason, count);
+        }
+
+        header(&mut out, "cadi_search_duration_seconds", "histogram", "Search latency by kind");
+        for (kind, histogram) in self.search_latency.lock().unwrap().iter() {
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                let _ = writeln!(
+                    out,
+                    "cadi_search_duration_seconds_bucket{{kind=\"{}\",le=\"{}\"}} {}",
+                    kind, bound, count
+                );
+            }
+            let _ = writeln!(
+                out,
+                "cadi_search_duration_seconds_bucket{{kind=\"{}\",le=\"+Inf\"}} {}",
+                kind, histogram.count
+            );
+            let _ = writeln!(out, "cadi_search_duration_seconds_sum{{kind=\"{}\"}} {}", kind, histogram.sum);
+            let _ = writeln!(out, "cadi_search_duration_seconds_count{{kind=\"{}\"}} {}", kind, histogram.count);
+        }
+
+        for (name, help, value) in [
+            ("cadi_store_chunks", "Chunks in the file store", gauges.store_chunks),
+            ("cadi_store_bytes", "Size of the file store in bytes", gauges.store_bytes),
+            ("cadi_graph_nodes", "Nodes in the dependency graph", gauges.graph_nodes),
+            ("cadi_graph_edges", "Edges in the dependency graph", gauges.graph_edges),
+        ] {
+            header(&mut out, name, "gauge", help);
+            let _ = writeln!(out, "{} {}", name, value);
+        }
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Middleware counting every request by its route template, so chunk IDs in
+/// paths don't each get their own series
+pub async fn track_requests(State(metrics): State<Arc<Metrics>>, request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| "unmatched".to_string(), |path| path.as_str().to_string());
+    let method = request.method().to_string();
+
+    let response = next.run(request).await;
+    metrics.record_request(&method, &route, response.status().as_u16());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::state::{AppState, ServerConfig};
+    use axum::body::Body;
+    use axum::http::{Method, StatusCode};
+    use tower::ServiceExt;
+
+    async fn send(app: &axum::Router, method: Method, uri: &str, body: Body) -> (StatusCode, String) {
+        let request = axum::http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(body)
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    fn sample(metrics: &str, series: &str) -> f64 {
+        metrics
+            .lines()
+            .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+            .unwrap_or_else(|| panic!("no sample for {}", series))
+            .parse()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_metrics_endpoint_counts_requests() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = ServerConfig {
+            storage_path: tmp.path().to_str().unwrap().to_string(),
+            anonymous_write: true,
+            ..Default::default()
+        };
+        let app = crate::routes::app(AppState::new(config).await);
+
+        let content = b"pub fn answer() -> u32 { 42 }";
+        let chunk_id = cadi_core::hash::chunk_id_from_content(content);
+        send(&app, Method::GET, "/health", Body::empty()).await;
+        send(&app, Method::PUT, &format!("/v1/chunks/{}", chunk_id), Body::from(&content[..])).await;
+        send(&app, Method::PUT, &format!("/v1/chunks/{}", chunk_id), Body::from("tampered")).await;
+        send(&app, Method::POST, "/v1/search", Body::from("{}")).await;
+
+        let (status, metrics) = send(&app, Method::GET, "/metrics", Body::empty()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(sample(&metrics, r#"cadi_http_requests_total{method="GET",route="/health",status="200"}"#), 1.0);
+        assert_eq!(
+            sample(&metrics, r#"cadi_http_requests_total{method="PUT",route="/v1/chunks/:chunk_id",status="200"}"#),
+            2.0
+        );
+        assert_eq!(sample(&metrics, "cadi_chunk_upload_bytes_total"), content.len() as f64);
+        assert_eq!(sample(&metrics, r#"cadi_chunk_rejections_total{reason="hash_mismatch"}"#), 1.0);
+        assert_eq!(sample(&metrics, r#"cadi_search_duration_seconds_count{kind="structural"}"#), 1.0);
+        assert!(metrics.contains("# TYPE cadi_graph_nodes gauge"));
+
+        // The scrape itself shows up in the next one
+        let (_, metrics) = send(&app, Method::GET, "/metrics", Body::empty()).await;
+        assert_eq!(sample(&metrics, r#"cadi_http_requests_total{method="GET",route="/metrics",status="200"}"#), 1.0);
+    }
+}
@@ -0,0 +1,128 @@
+//! Multi-tenant namespaces
+//!
+//! A registry shared by several teams gives each a namespace: the first
+//! segment of an alias path, so `acme/utils/logger` is in `acme`. A chunk
+//! is in the namespace it was published into (the `X-Cadi-Namespace`
+//! header) and in those of its aliases.
+//!
+//! Configured namespaces are writable only with one of their tokens or the
+//! admin token, even when anonymous writes are enabled. Unless a namespace
+//! is `public_read`, reading its chunks and aliases needs one of those
+//! tokens too, and listings and searches leave them out. Namespaces that
+//! aren't configured follow the server-wide rules.
+
+use axum::http::HeaderMap;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+
+use crate::state::ServerConfig;
+
+pub use cadi_registry::NAMESPACE_HEADER;
+
+/// Access rules of a namespace
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NamespaceConfig {
+    /// Tokens that may publish into the namespace and read it
+    #[serde(default)]
+    pub write_tokens: Vec<String>,
+    /// Whether anyone may read the namespace
+    #[serde(default)]
+    pub public_read: bool,
+}
+
+/// Namespaces from `CADI_NAMESPACES`: a JSON object of name to
+/// `{write_tokens, public_read}`, or the path of a file holding one
+pub fn namespaces_from_env() -> HashMap<String, NamespaceConfig> {
+    let Ok(value) = std::env::var("CADI_NAMESPACES") else {
+        return HashMap::new();
+    };
+    let json = if value.trim_start().starts_with('{') {
+        value
+    } else {
+        fs::read_to_string(&value).expect("Failed to read CADI_NAMESPACES file")
+    };
+    serde_json::from_str(&json).expect("Invalid CADI_NAMESPACES configuration")
+}
+
+/// Namespace of an alias path: its first segment
+pub fn alias_namespace(path: &str) -> &str {
+    path.split('/').next().unwrap_or(path)
+}
+
+/// Namespace a request names in the [`NAMESPACE_HEADER`]
+pub fn requested_namespace(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(NAMESPACE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.trim().trim_end_matches('/').to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Token of `Authorization: Bearer <token>`
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer "))
+}
+
+/// Whether the request carries the admin token, as
+/// `Authorization: Bearer <token>` or `X-Admin-Token: <token>`
+pub fn is_admin(config: &ServerConfig, headers: &HeaderMap) -> bool {
+    let Some(ref token) = config.admin_token else {
+        return false;
+    };
+    if let Some(bearer) = bearer_token(headers) {
+        return bearer == token;
+    }
+    headers
+        .get("x-admin-token")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|s| s == token)
+}
+
+/// Whether the request may publish into `namespace`, or `None` when the
+/// namespace isn't configured and the server-wide rules apply
+pub fn can_write(config: &ServerConfig, headers: &HeaderMap, namespace: &str) -> Option<bool> {
+    let rules = config.namespaces.get(namespace)?;
+    let token_matches = bearer_token(headers).is_some_and(|token| rules.write_tokens.iter().any(|t| t == token));
+    Some(token_matches || is_admin(config, headers))
+}
+
+/// Whether the request carries the admin token or the write token of some
+/// namespace. Anonymous writes don't count.
+pub fn has_write_token(config: &ServerConfig, headers: &HeaderMap) -> bool {
+    let token_matches = bearer_token(headers)
+        .is_some_and(|token| config.namespaces.values().any(|rules| rules.write_tokens.iter().any(|t| t == token)));
+    token_matches || is_admin(config, headers)
+}
+
+/// Whether the request may publish into every one of `namespaces`, or
+/// `None` when none of them is configured
+pub fn can_write_all(config: &ServerConfig, headers: &HeaderMap, namespaces: &[String]) -> Option<bool> {
+    namespaces
+        .iter()
+        .filter_map(|namespace| can_write(config, headers, namespace))
+        .reduce(|a, b| a && b)
+}
+
+/// Whether the request may read `namespace`
+pub fn can_read(config: &ServerConfig, headers: &HeaderMap, namespace: &str) -> bool {
+    match config.namespaces.get(namespace) {
+        Some(rules) if !rules.public_read => can_write(config, headers, namespace).unwrap_or(false),
+        _ => true,
+    }
+}
+
+/// Configured namespaces the request may not read, in name order
+pub fn unreadable(config: &ServerConfig, headers: &HeaderMap) -> Vec<String> {
+    let mut names: Vec<String> = config
+        .namespaces
+        .keys()
+        .filter(|namespace| !can_read(config, headers, namespace))
+        .cloned()
+        .collect();
+    names.sort();
+    names
+}
@@ -1,13 +1,27 @@
 //! Route definitions
 
 use axum::{
+    middleware,
     routing::{get, post, put, delete, head},
     Router,
 };
+use tower_http::trace::TraceLayer;
 
 use crate::handlers;
+use crate::metrics;
 use crate::state::AppState;
 
+/// The server application: API routes with request metrics and tracing,
+/// rejecting changes in read-only mode
+pub fn app(state: AppState) -> Router {
+    Router::new()
+        .merge(api_routes())
+        .layer(middleware::from_fn_with_state(state.clone(), handlers::reject_writes_when_read_only))
+        .layer(middleware::from_fn_with_state(state.metrics.clone(), metrics::track_requests))
+        .layer(TraceLayer::new_for_http())
+        .with_state(state)
+}
+
 /// API routes
 pub fn api_routes() -> Router<AppState> {
     Router::new()
@@ -16,13 +30,29 @@ pub fn api_routes() -> Router<AppState> {
         
         // Chunks API
         .route("/v1/chunks", get(handlers::list_chunks))
+        .route("/v1/chunk_ids", get(handlers::list_chunk_ids))
+        .route("/v1/chunks/exists", post(handlers::chunks_exist))
         .route("/v1/chunks/:chunk_id", get(handlers::get_chunk))
         .route("/v1/chunks/:chunk_id", head(handlers::head_chunk))
         .route("/v1/chunks/:chunk_id", put(handlers::put_chunk))
         .route("/v1/chunks/:chunk_id", delete(handlers::delete_chunk))
         .route("/v1/chunks/:chunk_id/meta", get(handlers::get_chunk_meta))
         .route("/v1/chunks/:chunk_id/meta", put(handlers::put_chunk_meta))
+        .route("/v1/chunks/:chunk_id/closure", get(handlers::get_chunk_closure))
+        .route("/v1/chunks/:chunk_id/stats", get(handlers::get_chunk_stats))
+        .route("/v1/usage", post(handlers::report_usage))
         
+        // Build cache (artifacts keyed by build step input hash)
+        .route("/cache/:key", get(handlers::get_cache_entry))
+        .route("/cache/:key", head(handlers::head_cache_entry))
+        .route("/cache/:key", put(handlers::put_cache_entry))
+
+        // Aliases
+        .route("/aliases", get(handlers::list_aliases))
+        .route("/aliases/*path", get(handlers::get_alias))
+        .route("/aliases/*path", put(handlers::put_alias))
+        .route("/aliases/*path", delete(handlers::delete_alias))
+
         // Search
         .route("/v1/search", post(handlers::search))
         // Semantic search
@@ -38,7 +68,10 @@ pub fn api_routes() -> Router<AppState> {
         .route("/v1/admin/edges", get(handlers::admin_list_edges))
         .route("/v1/admin/edges", post(handlers::admin_add_edge))
         .route("/v1/admin/edges/batch", post(handlers::admin_add_edges_batch))
+        .route("/v1/admin/reembed", get(handlers::admin_reembed_status))
+        .route("/v1/admin/reembed", post(handlers::admin_reembed))
 
         // Stats
         .route("/v1/stats", get(handlers::stats))
+        .route("/metrics", get(handlers::metrics))
 }
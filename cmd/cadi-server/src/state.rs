@@ -3,6 +3,7 @@
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use surrealdb::{Surreal, engine::local::RocksDb};
@@ -23,6 +24,48 @@ pub struct ServerConfig {
     pub anonymous_write: bool,
     /// Optional admin token for authenticated admin operations
     pub admin_token: Option<String>,
+    /// Endpoints notified of publishes, deletes and alias updates
+    pub webhooks: Vec<crate::webhooks::WebhookConfig>,
+    /// Scheduled mirroring with a peer registry
+    pub sync: Option<crate::sync::SyncConfig>,
+    /// Namespaces with their own write tokens and read access
+    pub namespaces: HashMap<String, crate::namespaces::NamespaceConfig>,
+    /// Whether chunks, aliases and the graph may be changed
+    pub mode: ServerMode,
+    /// Re-hash stored chunks in the background at startup
+    pub verify_on_start: bool,
+}
+
+/// Whether a server accepts changes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerMode {
+    /// Serve and accept changes
+    #[default]
+    ReadWrite,
+    /// Serve only, e.g. as a mirror: every change is rejected with 405
+    ReadOnly,
+}
+
+impl ServerMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ServerMode::ReadWrite => "read_write",
+            ServerMode::ReadOnly => "read_only",
+        }
+    }
+}
+
+impl FromStr for ServerMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "read_write" => Ok(ServerMode::ReadWrite),
+            "read_only" => Ok(ServerMode::ReadOnly),
+            other => Err(format!("unknown server mode '{}' (expected read_only or read_write)", other)),
+        }
+    }
 }
 
 impl ServerConfig {
@@ -44,10 +87,33 @@ impl ServerConfig {
                 .map(|s| s == "true" || s == "1")
                 .unwrap_or(false),
             admin_token: std::env::var("CADI_ADMIN_TOKEN").ok(),
+            webhooks: webhooks_from_env(),
+            sync: crate::sync::SyncConfig::from_env(),
+            namespaces: crate::namespaces::namespaces_from_env(),
+            mode: std::env::var("CADI_SERVER_MODE")
+                .map(|s| s.parse().expect("Invalid CADI_SERVER_MODE"))
+                .unwrap_or_default(),
+            verify_on_start: std::env::var("CADI_VERIFY_ON_START")
+                .map(|s| s == "true" || s == "1")
+                .unwrap_or(false),
         }
     }
 }
 
+/// Webhooks from `CADI_WEBHOOKS`: a JSON array of `{url, secret, events}`,
+/// or the path of a file holding one
+fn webhooks_from_env() -> Vec<crate::webhooks::WebhookConfig> {
+    let Ok(value) = std::env::var("CADI_WEBHOOKS") else {
+        return Vec::new();
+    };
+    let json = if value.trim_start().starts_with('[') {
+        value
+    } else {
+        fs::read_to_string(&value).expect("Failed to read CADI_WEBHOOKS file")
+    };
+    serde_json::from_str(&json).expect("Invalid CADI_WEBHOOKS configuration")
+}
+
 impl Default for ServerConfig {
     fn default() -> Self {
         Self {
@@ -57,10 +123,18 @@ impl Default for ServerConfig {
             anonymous_read: true,
             anonymous_write: false,
             admin_token: None,
+            webhooks: Vec::new(),
+            sync: None,
+            namespaces: HashMap::new(),
+            mode: ServerMode::ReadWrite,
+            verify_on_start: false,
         }
     }
 }
 
+/// Directory under the storage path holding quarantined chunks
+pub const QUARANTINE_DIR: &str = "corrupt";
+
 /// File-based chunk storage
 #[derive(Clone)]
 pub struct ChunkStore {
@@ -75,6 +149,9 @@ pub struct ChunkMetadata {
     pub size: usize,
     pub created_at: String,
     pub content_type: String,
+    /// Namespace the chunk was published into
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
 }
 
 impl ChunkStore {
@@ -112,7 +189,9 @@ impl ChunkStore {
         chunk_path.exists()
     }
 
-    pub async fn store(&mut self, chunk_id: String, data: Vec<u8>) -> std::io::Result<()> {
+    /// Store a chunk. The namespace it was first published into is kept
+    /// when it is stored again.
+    pub async fn store(&mut self, chunk_id: String, data: Vec<u8>, namespace: Option<String>) -> std::io::Result<()> {
         let chunk_path = self.chunk_path(&chunk_id);
         
         // Write chunk data
@@ -120,11 +199,13 @@ impl ChunkStore {
         
         // Update metadata
         let size = data.len();
+        let namespace = self.namespace(&chunk_id).or(namespace);
         let meta = ChunkMetadata {
             chunk_id: chunk_id.clone(),
             size,
             created_at: chrono::Utc::now().to_rfc3339(),
             content_type: "application/octet-stream".to_string(),
+            namespace,
         };
         
         self.metadata.insert(chunk_id, meta);
@@ -148,6 +229,32 @@ impl ChunkStore {
         existed
     }
 
+    /// Move a chunk whose content doesn't match its ID into the
+    /// [`QUARANTINE_DIR`], where it is kept for inspection but no longer
+    /// served
+    pub async fn quarantine(&mut self, chunk_id: &str) -> std::io::Result<()> {
+        let chunk_path = self.chunk_path(chunk_id);
+        let quarantine = self.storage_path.join(QUARANTINE_DIR);
+        fs::create_dir_all(&quarantine)?;
+        if let Some(name) = chunk_path.file_name() {
+            fs::rename(&chunk_path, quarantine.join(name))?;
+        }
+        self.metadata.remove(chunk_id);
+        self.save_metadata().await
+    }
+
+    /// Number of chunks in the [`QUARANTINE_DIR`]
+    pub fn quarantined_count(&self) -> usize {
+        fs::read_dir(self.storage_path.join(QUARANTINE_DIR))
+            .map(|entries| entries.flatten().filter(|e| e.path().is_file()).count())
+            .unwrap_or(0)
+    }
+
+    /// Namespace a chunk was published into
+    pub fn namespace(&self, chunk_id: &str) -> Option<String> {
+        self.metadata.get(chunk_id).and_then(|meta| meta.namespace.clone())
+    }
+
     pub async fn list(&self) -> Vec<ChunkMetadata> {
         self.metadata.values().cloned().collect()
     }
@@ -172,6 +279,7 @@ impl ChunkStore {
         StoreStats {
             chunk_count,
             total_size,
+            quarantined_count: self.quarantined_count(),
         }
     }
 
@@ -194,6 +302,8 @@ impl ChunkStore {
 pub struct StoreStats {
     pub chunk_count: usize,
     pub total_size: usize,
+    /// Chunks moved aside because their content didn't match their ID
+    pub quarantined_count: usize,
 }
 
 /// Application state
@@ -202,12 +312,19 @@ pub struct AppState {
     pub config: ServerConfig,
     pub store: Arc<RwLock<ChunkStore>>,
     /// Embedding manager for semantic search
-    #[allow(dead_code)]
     pub embedding_manager: std::sync::Arc<tokio::sync::Mutex<cadi_llm::embeddings::EmbeddingManager>>,
     /// Graph store for atoms & views
     pub graph: std::sync::Arc<cadi_core::graph::GraphStore>,
     /// Registry database for advanced search
     pub registry_db: Arc<RwLock<cadi_registry::db::RegistryDatabase>>,
+    /// State of background re-embedding
+    pub reembed: Arc<tokio::sync::Mutex<cadi_registry::ReembedStatus>>,
+    /// Request and domain metrics served at `/metrics`
+    pub metrics: Arc<crate::metrics::Metrics>,
+    /// Queue of webhook notifications
+    pub webhooks: crate::webhooks::Webhooks,
+    /// Queue of chunk uses counted toward usage statistics
+    pub usage: crate::usage::UsageRecorder,
 }
 
 impl AppState {
@@ -215,9 +332,11 @@ impl AppState {
         let store = ChunkStore::new(config.storage_path.clone().into())
             .expect("Failed to initialize chunk store");
 
-        // Initialize embedding manager with mock provider and persistent store path
+        // Initialize embedding manager with the configured provider and persistent store path
         let emb_store_path = std::path::PathBuf::from(config.storage_path.clone()).join("embeddings.json");
-        let provider: Box<dyn cadi_llm::embeddings::EmbeddingProvider> = Box::new(cadi_llm::embeddings::MockProvider::default());
+        let emb_cache_dir = std::path::PathBuf::from(config.storage_path.clone()).join("embedding-cache");
+        let provider = cadi_llm::embeddings::provider_from_env(Some(emb_cache_dir.clone())).await
+            .expect("Failed to configure embedding provider");
         let emb_manager = cadi_llm::embeddings::EmbeddingManager::new(provider, Some(emb_store_path));
 
         // Initialize graph store using storage path under a "graph" directory
@@ -230,17 +349,52 @@ impl AppState {
             .expect("Failed to initialize registry database");
         db.use_ns("cadi").use_db("registry").await
             .expect("Failed to use namespace/database");
-        let provider = Box::new(cadi_llm::embeddings::MockProvider::default());
+        let provider = cadi_llm::embeddings::provider_from_env(Some(emb_cache_dir)).await
+            .expect("Failed to configure embedding provider");
         let embedding_manager = cadi_llm::embeddings::EmbeddingManager::new(provider, None);
         let registry_db = cadi_registry::db::RegistryDatabase::new(db, Some(embedding_manager)).await
             .expect("Failed to initialize registry database");
+        let registry_db = Arc::new(RwLock::new(registry_db));
+        let usage = crate::usage::UsageRecorder::start(registry_db.clone(), crate::usage::FLUSH_INTERVAL);
+
+        let webhooks = crate::webhooks::Webhooks::start(
+            config.webhooks.clone(),
+            std::path::PathBuf::from(config.storage_path.clone()).join("webhooks-dead-letter.jsonl"),
+            crate::webhooks::RetryPolicy::default(),
+        );
+
+        let store = Arc::new(RwLock::new(store));
+        if config.verify_on_start {
+            crate::integrity::spawn(store.clone());
+        }
 
         Self {
             config,
-            store: Arc::new(RwLock::new(store)),
+            store,
             embedding_manager: std::sync::Arc::new(tokio::sync::Mutex::new(emb_manager)),
             graph: std::sync::Arc::new(graph),
-            registry_db: Arc::new(RwLock::new(registry_db)),
+            registry_db,
+            reembed: Arc::new(tokio::sync::Mutex::new(cadi_registry::ReembedStatus::default())),
+            metrics: Arc::new(crate::metrics::Metrics::default()),
+            webhooks,
+            usage,
+        }
+    }
+}
+
+impl AppState {
+    /// Content of a chunk from the file store, or failing that the registry
+    /// database
+    pub async fn chunk_data(&self, chunk_id: &str) -> Option<Vec<u8>> {
+        if let Some(data) = self.store.read().await.get(chunk_id).await {
+            return Some(data);
+        }
+        match self.registry_db.read().await.get_chunk(chunk_id).await {
+            Ok(record) => record.map(|r| r.content.into_bytes()),
+            Err(e) => {
+                eprintln!("closure lookup error for {}: {}", chunk_id, e);
+                None
+            }
         }
     }
 }
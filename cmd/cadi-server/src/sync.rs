@@ -0,0 +1,269 @@
+//! Scheduled mirroring with a peer registry
+//!
+//! With `CADI_SYNC_PEER` set, the server periodically runs
+//! [`FederationManager::sync`] between itself and the peer, pulling the
+//! peer's chunks, pushing its own, or both. Alias conflicts are logged and
+//! left for an operator; they are never overwritten.
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use cadi_registry::{FederatedRegistry, FederationManager, SyncFilter, SyncReport};
+
+/// Registry ID the server uses for itself
+const LOCAL: &str = "local";
+
+/// Registry ID of the peer
+const PEER: &str = "peer";
+
+/// Which way chunks are mirrored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncDirection {
+    /// Copy the peer's chunks here
+    Pull,
+    /// Copy this registry's chunks to the peer
+    Push,
+    /// Pull, then push
+    Both,
+}
+
+impl SyncDirection {
+    /// (source, target) pairs to sync, in order
+    fn pairs(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            SyncDirection::Pull => &[(PEER, LOCAL)],
+            SyncDirection::Push => &[(LOCAL, PEER)],
+            SyncDirection::Both => &[(PEER, LOCAL), (LOCAL, PEER)],
+        }
+    }
+}
+
+impl FromStr for SyncDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "pull" => Ok(SyncDirection::Pull),
+            "push" => Ok(SyncDirection::Push),
+            "both" => Ok(SyncDirection::Both),
+            other => Err(format!("unknown sync direction '{}' (expected pull, push or both)", other)),
+        }
+    }
+}
+
+/// Scheduled sync settings
+#[derive(Debug, Clone)]
+pub struct SyncConfig {
+    /// URL of the peer registry
+    pub peer: String,
+    /// Token for writing to the peer
+    pub token: Option<String>,
+    /// Time between runs
+    pub interval: Duration,
+    pub direction: SyncDirection,
+    /// Only mirror aliases under this prefix and their chunks
+    pub namespace: Option<String>,
+}
+
+impl SyncConfig {
+    /// Settings from `CADI_SYNC_*`, or `None` if no peer is configured
+    pub fn from_env() -> Option<Self> {
+        let peer = std::env::var("CADI_SYNC_PEER").ok()?;
+        Some(Self {
+            peer,
+            token: std::env::var("CADI_SYNC_TOKEN").ok(),
+            interval: Duration::from_secs(
+                std::env::var("CADI_SYNC_INTERVAL_SECS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(300),
+            ),
+            direction: std::env::var("CADI_SYNC_DIRECTION")
+                .map(|s| s.parse().expect("Invalid CADI_SYNC_DIRECTION"))
+                .unwrap_or(SyncDirection::Pull),
+            namespace: std::env::var("CADI_SYNC_NAMESPACE").ok(),
+        })
+    }
+
+    /// Federation of this server, reached at `local_url` with `local_token`,
+    /// and the peer
+    pub fn federation(&self, local_url: &str, local_token: Option<String>) -> cadi_core::CadiResult<FederationManager> {
+        let mut manager = FederationManager::new();
+        for (id, url, token) in [(LOCAL, local_url.to_string(), local_token), (PEER, self.peer.clone(), self.token.clone())] {
+            manager.add_registry(FederatedRegistry {
+                id: id.to_string(),
+                url,
+                token,
+                namespace: None,
+                priority: 0,
+                trust_level: Default::default(),
+                enabled: true,
+                regions: Vec::new(),
+                capabilities: Default::default(),
+            })?;
+        }
+        Ok(manager)
+    }
+
+    /// Run one sync in each configured direction
+    pub async fn run_once(&self, manager: &FederationManager) -> Vec<SyncReport> {
+        let filter = SyncFilter {
+            namespace: self.namespace.clone(),
+        };
+        let mut reports = Vec::new();
+        for (source, target) in self.direction.pairs() {
+            match manager.sync(source, target, &filter, |_| {}).await {
+                Ok(report) => {
+                    log_report(&report);
+                    reports.push(report);
+                }
+                Err(e) => tracing::warn!("Sync from {} to {} failed: {}", source, target, e),
+            }
+        }
+        reports
+    }
+
+    /// Sync every `interval` in the background
+    pub fn spawn(self, local_url: String, local_token: Option<String>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let manager = match self.federation(&local_url, local_token) {
+                Ok(manager) => manager,
+                Err(e) => {
+                    tracing::error!("Scheduled sync disabled: {}", e);
+                    return;
+                }
+            };
+            let mut ticker = tokio::time::interval(self.interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                self.run_once(&manager).await;
+            }
+        })
+    }
+}
+
+fn log_report(report: &SyncReport) {
+    tracing::info!(
+        "Synced {} -> {}: {} chunks copied, {} present, {} failed, {} aliases copied",
+        report.source,
+        report.target,
+        report.copied.len(),
+        report.present,
+        report.failed.len(),
+        report.aliases_copied.len()
+    );
+    for (chunk_id, reason) in &report.failed {
+        tracing::warn!("Sync of {} to {} failed: {}", chunk_id, report.target, reason);
+    }
+    for conflict in &report.conflicts {
+        tracing::warn!(
+            "Alias conflict: {} is {} on {} but {} on {}; left unchanged",
+            conflict.path,
+            conflict.source_chunk,
+            report.source,
+            conflict.target_chunk,
+            report.target
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{AppState, ServerConfig};
+    use cadi_core::hash::chunk_id_from_content;
+    use cadi_registry::{ChunkSyncOutcome, RegistryClient, RegistryConfig};
+
+    /// Serve a fresh registry in-process, returning its URL
+    async fn start_registry(storage: &std::path::Path) -> String {
+        let config = ServerConfig {
+            bind_address: "127.0.0.1:0".to_string(),
+            storage_path: storage.to_str().unwrap().to_string(),
+            anonymous_write: true,
+            ..Default::default()
+        };
+        let app = crate::routes::app(AppState::new(config).await);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    fn client(url: &str) -> RegistryClient {
+        RegistryClient::new(RegistryConfig {
+            url: url.to_string(),
+            max_retries: 0,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    async fn publish(client: &RegistryClient, content: &str, alias: &str) -> String {
+        let chunk_id = chunk_id_from_content(content.as_bytes());
+        client.publish_chunk(&chunk_id, content.as_bytes()).await.unwrap();
+        client.publish_alias(alias, &chunk_id).await.unwrap();
+        chunk_id
+    }
+
+    #[tokio::test]
+    async fn test_sync_copies_missing_chunks_and_reports_conflicts() {
+        let (tmp_a, tmp_b) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let (url_a, url_b) = (start_registry(tmp_a.path()).await, start_registry(tmp_b.path()).await);
+        let (a, b) = (client(&url_a), client(&url_b));
+
+        let one = publish(&a, "fn one() {}", "acme/one").await;
+        let two = publish(&a, "fn two() {}", "other/two").await;
+        let three = publish(&a, "fn three() {}", "acme/three").await;
+        publish(&b, "fn three() {}", "acme/three").await;
+        let elsewhere = publish(&b, "fn elsewhere() {}", "acme/one").await;
+
+        let config = SyncConfig {
+            peer: url_a.clone(),
+            token: None,
+            interval: Duration::from_secs(60),
+            direction: SyncDirection::Pull,
+            namespace: Some("acme/".to_string()),
+        };
+        let manager = config.federation(&url_b, None).unwrap();
+
+        let mut progress = Vec::new();
+        let report = manager
+            .sync(PEER, LOCAL, &SyncFilter::namespace("acme/"), |p| progress.push((p.chunk_id.clone(), p.outcome.clone())))
+            .await
+            .unwrap();
+        assert_eq!(report.copied, vec![one.clone()]);
+        assert_eq!(report.present, 1);
+        assert!(report.failed.is_empty());
+        assert_eq!(progress.len(), 2);
+        assert!(progress.contains(&(three.clone(), ChunkSyncOutcome::Present)));
+        assert_eq!(report.aliases_present, 1);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].path, "acme/one");
+        assert_eq!(report.conflicts[0].source_chunk, one);
+        assert_eq!(report.conflicts[0].target_chunk, elsewhere);
+        assert!(!report.is_clean());
+
+        assert_eq!(b.fetch_chunk(&one).await.unwrap(), b"fn one() {}");
+        assert!(!b.chunk_exists(&two).await.unwrap());
+        assert_eq!(b.resolve_alias("acme/one").await.unwrap().unwrap().chunk_id, elsewhere);
+
+        // The scheduled run mirrors everything outside the namespace too
+        let config = SyncConfig { namespace: None, ..config };
+        let reports = config.run_once(&manager).await;
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].copied, vec![two.clone()]);
+        assert_eq!(reports[0].aliases_copied, vec!["other/two".to_string()]);
+        assert_eq!(reports[0].conflicts.len(), 1);
+        assert_eq!(b.resolve_alias("other/two").await.unwrap().unwrap().chunk_id, two);
+
+        let page = b.list_chunks(2, 0).await.unwrap();
+        assert_eq!((page.chunk_ids.len(), page.total, page.next_offset()), (2, 4, Some(2)));
+    }
+
+    #[test]
+    fn test_sync_direction() {
+        assert_eq!("both".parse::<SyncDirection>().unwrap().pairs(), &[(PEER, LOCAL), (LOCAL, PEER)]);
+        assert!("sideways".parse::<SyncDirection>().is_err());
+    }
+}
@@ -0,0 +1,96 @@
+//! Chunk usage recording
+//!
+//! Handlers count a use with [`UsageRecorder::record`], which only queues
+//! it: a background task adds up the queued uses per chunk and writes them
+//! to the registry database in one batch every [`FLUSH_INTERVAL`], or
+//! sooner once [`MAX_PENDING_CHUNKS`] chunks are waiting, so serving a
+//! chunk never waits on a database write.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use cadi_registry::db::RegistryDatabase;
+use tokio::sync::{mpsc, oneshot, RwLock};
+
+/// Uses queued beyond this are dropped
+const QUEUE_CAPACITY: usize = 4096;
+
+/// How often pending uses are written
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Pending chunks that trigger a write before the interval is up
+pub const MAX_PENDING_CHUNKS: usize = 256;
+
+enum Command {
+    Use(String),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Queue of chunk uses
+#[derive(Clone)]
+pub struct UsageRecorder {
+    queue: mpsc::Sender<Command>,
+}
+
+impl UsageRecorder {
+    /// Start writing queued uses to `db` every `interval`. Must be called
+    /// from within a Tokio runtime.
+    pub fn start(db: Arc<RwLock<RegistryDatabase>>, interval: Duration) -> Self {
+        let (queue, mut commands) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(async move {
+            let mut pending: HashMap<String, u64> = HashMap::new();
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    command = commands.recv() => match command {
+                        Some(Command::Use(chunk_id)) => {
+                            *pending.entry(chunk_id).or_default() += 1;
+                            if pending.len() >= MAX_PENDING_CHUNKS {
+                                write(&db, &mut pending).await;
+                            }
+                        }
+                        Some(Command::Flush(done)) => {
+                            write(&db, &mut pending).await;
+                            let _ = done.send(());
+                        }
+                        None => {
+                            write(&db, &mut pending).await;
+                            break;
+                        }
+                    },
+                    _ = ticker.tick() => write(&db, &mut pending).await,
+                }
+            }
+        });
+
+        Self { queue }
+    }
+
+    /// Queue a use of a chunk without waiting for it to be written
+    pub fn record(&self, chunk_id: &str) {
+        if self.queue.try_send(Command::Use(chunk_id.to_string())).is_err() {
+            tracing::warn!("usage queue full, dropping use of {}", chunk_id);
+        }
+    }
+
+    /// Write the uses queued so far and wait until they are written
+    pub async fn flush(&self) {
+        let (done, written) = oneshot::channel();
+        if self.queue.send(Command::Flush(done)).await.is_ok() {
+            let _ = written.await;
+        }
+    }
+}
+
+/// Write the pending uses as one batch
+async fn write(db: &RwLock<RegistryDatabase>, pending: &mut HashMap<String, u64>) {
+    if pending.is_empty() {
+        return;
+    }
+    let uses = std::mem::take(pending);
+    if let Err(e) = db.read().await.record_usage(&uses, chrono::Utc::now()).await {
+        tracing::warn!("failed to record usage of {} chunks: {}", uses.len(), e);
+    }
+}
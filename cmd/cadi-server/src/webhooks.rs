@@ -0,0 +1,358 @@
+//! Webhook notifications
+//!
+//! Handlers queue events with [`Webhooks::notify`], which never waits on the
+//! network: a background task delivers each event to the subscribed hooks,
+//! retrying with exponential backoff, and appends deliveries that still fail
+//! to a dead-letter log.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::sync::mpsc;
+
+/// Events queued beyond this are dead-lettered instead of delivered
+const QUEUE_CAPACITY: usize = 1024;
+
+/// Header carrying `sha256=<hex HMAC-SHA256 of the body>`
+pub const SIGNATURE_HEADER: &str = "X-CADI-Signature";
+
+/// Header carrying the event name
+pub const EVENT_HEADER: &str = "X-CADI-Event";
+
+/// Registry events a webhook can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WebhookEvent {
+    /// A chunk was stored
+    Publish,
+    /// A chunk was deleted
+    Delete,
+    /// A chunk was given an alias
+    AliasUpdate,
+}
+
+impl WebhookEvent {
+    pub const ALL: [WebhookEvent; 3] = [WebhookEvent::Publish, WebhookEvent::Delete, WebhookEvent::AliasUpdate];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::Publish => "publish",
+            WebhookEvent::Delete => "delete",
+            WebhookEvent::AliasUpdate => "alias-update",
+        }
+    }
+}
+
+/// A webhook endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Key for the HMAC signature of each payload
+    pub secret: String,
+    /// Events to deliver (default: all)
+    #[serde(default = "all_events")]
+    pub events: Vec<WebhookEvent>,
+}
+
+fn all_events() -> Vec<WebhookEvent> {
+    WebhookEvent::ALL.to_vec()
+}
+
+/// Retry schedule for a delivery
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Attempts before a delivery is dead-lettered
+    pub attempts: u32,
+    /// Delay before the first retry, doubled for each one after it
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 5,
+            base_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Body of a webhook request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    pub event: WebhookEvent,
+    pub chunk_id: String,
+    /// Size in bytes, for `publish`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<usize>,
+    /// The new alias, for `alias-update`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    /// RFC 3339
+    pub timestamp: String,
+}
+
+impl WebhookPayload {
+    pub fn new(event: WebhookEvent, chunk_id: impl Into<String>) -> Self {
+        Self {
+            event,
+            chunk_id: chunk_id.into(),
+            size: None,
+            alias: None,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+/// Queue of webhook deliveries
+#[derive(Clone)]
+pub struct Webhooks {
+    /// `None` when no webhooks are configured
+    queue: Option<mpsc::Sender<WebhookPayload>>,
+    dead_letter: PathBuf,
+}
+
+impl Webhooks {
+    /// Start delivering to `hooks` in the background. Must be called from
+    /// within a Tokio runtime.
+    pub fn start(hooks: Vec<WebhookConfig>, dead_letter: PathBuf, retry: RetryPolicy) -> Self {
+        if hooks.is_empty() {
+            return Self { queue: None, dead_letter };
+        }
+
+        let (queue, mut events) = mpsc::channel::<WebhookPayload>(QUEUE_CAPACITY);
+        let log = dead_letter.clone();
+        tokio::spawn(async move {
+            let client = reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default();
+            while let Some(payload) = events.recv().await {
+                let body = match serde_json::to_vec(&payload) {
+                    Ok(body) => body,
+                    Err(_) => continue,
+                };
+                for hook in hooks.iter().filter(|h| h.events.contains(&payload.event)) {
+                    if let Err(error) = deliver(&client, hook, payload.event, &body, retry).await {
+                        tracing::warn!("webhook delivery to {} failed: {}", hook.url, error);
+                        record_dead_letter(&log, Some(&hook.url), &payload, &error);
+                    }
+                }
+            }
+        });
+
+        Self { queue: Some(queue), dead_letter }
+    }
+
+    /// Queue an event for delivery without waiting for it
+    pub fn notify(&self, payload: WebhookPayload) {
+        let Some(queue) = &self.queue else {
+            return;
+        };
+        match queue.try_send(payload) {
+            Ok(()) => {}
+            Err(mpsc::error::TrySendError::Full(payload)) => {
+                tracing::warn!("webhook queue full, dropping {} event", payload.event.as_str());
+                record_dead_letter(&self.dead_letter, None, &payload, "queue full");
+            }
+            Err(mpsc::error::TrySendError::Closed(payload)) => {
+                tracing::error!("webhook delivery task has stopped, dropping {} event", payload.event.as_str());
+                record_dead_letter(&self.dead_letter, None, &payload, "delivery task stopped");
+            }
+        }
+    }
+}
+
+/// HMAC-SHA256 of `body`, as sent in [`SIGNATURE_HEADER`]
+pub fn signature(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// POST the payload, retrying until it is accepted with a 2xx status or the
+/// attempts run out
+async fn deliver(
+    client: &reqwest::Client,
+    hook: &WebhookConfig,
+    event: WebhookEvent,
+    body: &[u8],
+    retry: RetryPolicy,
+) -> Result<(), String> {
+    let mut last_error = String::new();
+    for attempt in 0..retry.attempts.max(1) {
+        if attempt > 0 {
+            tokio::time::sleep(retry.base_delay * 2u32.pow(attempt - 1)).await;
+        }
+        let response = client
+            .post(&hook.url)
+            .header("Content-Type", "application/json")
+            .header(SIGNATURE_HEADER, signature(&hook.secret, body))
+            .header(EVENT_HEADER, event.as_str())
+            .body(body.to_vec())
+            .send()
+            .await;
+        match response {
+            Ok(r) if r.status().is_success() => return Ok(()),
+            Ok(r) => last_error = format!("HTTP {}", r.status()),
+            Err(e) => last_error = e.to_string(),
+        }
+    }
+    Err(format!("{} after {} attempts", last_error, retry.attempts.max(1)))
+}
+
+/// Append a failed delivery to the dead-letter log, one JSON object per line
+fn record_dead_letter(path: &Path, url: Option<&str>, payload: &WebhookPayload, error: &str) {
+    let entry = serde_json::json!({
+        "url": url,
+        "payload": payload,
+        "error": error,
+        "failed_at": chrono::Utc::now().to_rfc3339(),
+    });
+    let written = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{}", entry));
+    if let Err(e) = written {
+        tracing::error!("failed to write webhook dead-letter log {}: {}", path.display(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{AppState, ServerConfig};
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::post;
+    use tower::ServiceExt;
+
+    /// Serve `status` to every request, forwarding its headers and body
+    async fn listener(status: StatusCode) -> (String, mpsc::UnboundedReceiver<(HeaderMap, String)>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let app = axum::Router::new().route(
+            "/hook",
+            post(move |headers: HeaderMap, body: String| {
+                let tx = tx.clone();
+                async move {
+                    let _ = tx.send((headers, body));
+                    status
+                }
+            }),
+        );
+        let socket = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", socket.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(socket, app).await.unwrap() });
+        (url, rx)
+    }
+
+    async fn next(rx: &mut mpsc::UnboundedReceiver<(HeaderMap, String)>) -> (HeaderMap, String) {
+        tokio::time::timeout(Duration::from_secs(10), rx.recv()).await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_publish_and_delete_are_signed() {
+        let (url, mut rx) = listener(StatusCode::OK).await;
+        let tmp = tempfile::tempdir().unwrap();
+        let config = ServerConfig {
+            storage_path: tmp.path().to_str().unwrap().to_string(),
+            webhooks: vec![WebhookConfig {
+                url,
+                secret: "s3cret".to_string(),
+                events: vec![WebhookEvent::Publish, WebhookEvent::Delete],
+            }],
+            ..Default::default()
+        };
+        let state = AppState::new(config).await;
+        let app = crate::routes::app(state.clone());
+
+        let content = "pub fn answer() -> u32 { 42 }";
+        let chunk_id = cadi_core::hash::chunk_id_from_content(content.as_bytes());
+        let request = |method: &str| {
+            axum::http::Request::builder()
+                .method(method)
+                .uri(format!("/v1/chunks/{}", chunk_id))
+                .body(axum::body::Body::from(content))
+                .unwrap()
+        };
+        app.clone().oneshot(request("PUT")).await.unwrap();
+
+        let (headers, body) = next(&mut rx).await;
+        assert_eq!(headers[SIGNATURE_HEADER], signature("s3cret", body.as_bytes()));
+        assert_ne!(headers[SIGNATURE_HEADER], signature("other", body.as_bytes()));
+        assert_eq!(headers[EVENT_HEADER], "publish");
+        let payload: WebhookPayload = serde_json::from_str(&body).unwrap();
+        assert_eq!(payload.event, WebhookEvent::Publish);
+        assert_eq!(payload.chunk_id, chunk_id);
+        assert_eq!(payload.size, Some(content.len()));
+
+        // Deletes go to the file store, which the upload above skipped
+        state.store.write().await.store(chunk_id.clone(), content.as_bytes().to_vec(), None).await.unwrap();
+        let response = app.clone().oneshot(request("DELETE")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let (headers, body) = next(&mut rx).await;
+        assert_eq!(headers[EVENT_HEADER], "delete");
+        assert_eq!(headers[SIGNATURE_HEADER], signature("s3cret", body.as_bytes()));
+    }
+
+    #[tokio::test]
+    async fn test_failing_deliveries_are_retried_then_dead_lettered() {
+        let (url, mut rx) = listener(StatusCode::INTERNAL_SERVER_ERROR).await;
+        let tmp = tempfile::tempdir().unwrap();
+        let log = tmp.path().join("dead-letter.jsonl");
+        let hooks = vec![WebhookConfig { url: url.clone(), secret: "k".to_string(), events: all_events() }];
+        let webhooks = Webhooks::start(
+            hooks,
+            log.clone(),
+            RetryPolicy { attempts: 3, base_delay: Duration::from_millis(1) },
+        );
+
+        webhooks.notify(WebhookPayload::new(WebhookEvent::AliasUpdate, "chunk:sha256:abc"));
+        for _ in 0..3 {
+            next(&mut rx).await;
+        }
+
+        // The log is written after the last attempt's response arrives
+        let mut entry = None;
+        for _ in 0..100 {
+            if let Ok(contents) = std::fs::read_to_string(&log) {
+                if let Some(line) = contents.lines().next() {
+                    entry = Some(serde_json::from_str::<serde_json::Value>(line).unwrap());
+                    break;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let entry = entry.expect("no dead-letter entry");
+        assert_eq!(entry["url"], url);
+        assert_eq!(entry["payload"]["event"], "alias-update");
+        assert_eq!(entry["error"], "HTTP 500 Internal Server Error after 3 attempts");
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_full_and_stopped_queues_are_told_apart() {
+        let tmp = tempfile::tempdir().unwrap();
+        let log = tmp.path().join("dead-letter.jsonl");
+        let errors = || -> Vec<String> {
+            std::fs::read_to_string(&log)
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["error"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        let (queue, delivery) = mpsc::channel(1);
+        let webhooks = Webhooks { queue: Some(queue), dead_letter: log.clone() };
+        webhooks.notify(WebhookPayload::new(WebhookEvent::Publish, "chunk:sha256:a"));
+        webhooks.notify(WebhookPayload::new(WebhookEvent::Publish, "chunk:sha256:b"));
+        assert_eq!(errors(), ["queue full"]);
+
+        drop(delivery);
+        webhooks.notify(WebhookPayload::new(WebhookEvent::Publish, "chunk:sha256:c"));
+        assert_eq!(errors(), ["queue full", "delivery task stopped"]);
+    }
+}
@@ -0,0 +1,184 @@
+use anyhow::{anyhow, Result};
+use cadi_core::{AliasRegistry, AtomicChunk};
+use clap::{Args, Subcommand};
+use console::style;
+use std::path::{Path, PathBuf};
+
+use crate::config::CadiConfig;
+use crate::output::say;
+
+/// Arguments for the alias command
+#[derive(Args)]
+pub struct AliasArgs {
+    /// Alias registry file (defaults to <cache-dir>/chunks/aliases.json)
+    #[arg(long, global = true)]
+    registry: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: AliasCommands,
+}
+
+#[derive(Subcommand)]
+enum AliasCommands {
+    /// List aliases and the chunks they resolve to
+    List {
+        /// Only list aliases in this namespace
+        #[arg(long)]
+        namespace: Option<String>,
+
+        /// Show the rebind history
+        #[arg(long)]
+        history: bool,
+    },
+
+    /// Move every alias in a namespace to another namespace
+    RenameNamespace {
+        /// Current namespace
+        old: String,
+
+        /// New namespace
+        new: String,
+
+        /// Show what would be renamed without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Point an alias at a different chunk
+    Rebind {
+        /// Alias to rebind
+        alias: String,
+
+        /// Chunk ID the alias should resolve to
+        chunk_id: String,
+    },
+}
+
+/// Execute the alias command
+pub async fn execute(args: AliasArgs, config: &CadiConfig) -> Result<()> {
+    let registry_file = args
+        .registry
+        .clone()
+        .unwrap_or_else(|| config.cache.dir.join("chunks").join("aliases.json"));
+    if !registry_file.exists() {
+        return Err(anyhow!(
+            "No alias registry at {} (run `cadi import` first)",
+            registry_file.display()
+        ));
+    }
+    let mut registry = AliasRegistry::load(&registry_file)?;
+
+    match args.command {
+        AliasCommands::List { namespace, history } => {
+            let prefix = namespace.map(|ns| format!("{}/", ns.trim_end_matches('/')));
+            let mut aliases: Vec<(&String, &String)> = registry
+                .aliases
+                .iter()
+                .filter(|(alias, _)| prefix.as_ref().is_none_or(|p| alias.starts_with(p)))
+                .collect();
+            aliases.sort();
+
+            say!("{}", style("Aliases").bold());
+            say!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            if aliases.is_empty() {
+                say!("  {} No aliases", style("○").dim());
+            }
+            for (alias, chunk_id) in &aliases {
+                say!("  {} → {}", style(alias).cyan(), style(chunk_id).dim());
+            }
+
+            if history {
+                say!();
+                say!("{}", style("Rebind History").bold());
+                if registry.history.is_empty() {
+                    say!("  {} No rebinds", style("○").dim());
+                }
+                for rebind in &registry.history {
+                    say!(
+                        "  {}  {}: {} → {}",
+                        style(&rebind.rebound_at).dim(),
+                        rebind.alias,
+                        rebind.previous_chunk_id,
+                        rebind.chunk_id
+                    );
+                }
+            }
+        }
+
+        AliasCommands::RenameNamespace { old, new, dry_run } => {
+            let moved = registry.rename_namespace(&old, &new)?;
+            if moved == 0 {
+                say!("{} No aliases in namespace {}", style("⚠").yellow(), old);
+                return Ok(());
+            }
+
+            if dry_run {
+                say!("{} Would move {} aliases from {} to {}", style("→").cyan(), moved, old, new);
+                return Ok(());
+            }
+
+            registry.save(&registry_file)?;
+            let chunk_dir = registry_file.parent().unwrap_or(Path::new("."));
+            let chunks = rename_chunk_namespaces(chunk_dir, &old, &new)?;
+
+            say!(
+                "{} Moved {} aliases from {} to {} ({} chunk files updated)",
+                style("✓").green(),
+                moved,
+                style(&old).cyan(),
+                style(&new).cyan(),
+                chunks
+            );
+        }
+
+        AliasCommands::Rebind { alias, chunk_id } => {
+            let previous = registry.rebind(&alias, &chunk_id)?;
+            if previous == chunk_id {
+                say!("{} {} already resolves to {}", style("⚠").yellow(), alias, chunk_id);
+                return Ok(());
+            }
+
+            registry.save(&registry_file)?;
+            say!("{} Rebound {}", style("✓").green(), style(&alias).cyan());
+            say!("  Was: {}", style(previous).dim());
+            say!("  Now: {}", chunk_id);
+        }
+    }
+
+    Ok(())
+}
+
+/// Update the aliases recorded in cached chunk files to the new namespace,
+/// returning the number of files rewritten
+fn rename_chunk_namespaces(dir: &Path, old: &str, new: &str) -> Result<usize> {
+    let mut updated = 0;
+
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        // aliases.json, import-summary.json and friends are not chunks
+        let Ok(mut chunk) = serde_json::from_str::<AtomicChunk>(&content) else {
+            continue;
+        };
+
+        let mut changed = false;
+        for alias in &mut chunk.aliases {
+            if alias.namespace.as_deref() == Some(old) {
+                alias.namespace = Some(new.to_string());
+                changed = true;
+            }
+        }
+
+        if changed {
+            std::fs::write(&path, serde_json::to_string_pretty(&chunk)?)?;
+            updated += 1;
+        }
+    }
+
+    Ok(updated)
+}
@@ -1,14 +1,27 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Args;
 use console::style;
-use std::path::PathBuf;
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use cadi_builder::engine::{BuildEngine, BuildConfig};
+use std::time::{Duration, Instant};
+use cadi_builder::engine::{BuildEngine, BuildConfig, BuildResult};
+use cadi_builder::dependency_resolver::{resolve_target_triple, ComponentSearch, SkippedComponent};
+use cadi_builder::watch::{Atom, ChangeEvent, ChangeKind, Debouncer, FileChange, WatchGraph, WatchedAtom};
 use cadi_builder::{BuildSpec, BuildSpecValidator, CbsCompiler};
-use cadi_core::Manifest;
-use cadi_registry::search::SearchEngine;
+use cadi_core::graph::{BatchImporter, GraphStore};
+use cadi_core::{
+    AtomicChunk, ChunkRef, ChunkingStrategy, ImportState, Manifest, ManifestLock, SmartChunker,
+    SmartChunkerConfig, IMPORT_STATE_FILE, LOCK_FILE,
+};
+use cadi_registry::client::{RegistryClient, RegistryConfig};
+use cadi_registry::federation::FederationManager;
 
 use crate::config::CadiConfig;
+use crate::error::CommandError;
+use crate::output::{self, say};
 
 /// Arguments for the build command
 #[derive(Args)]
@@ -17,7 +30,8 @@ pub struct BuildArgs {
     #[arg(required = true, value_name = "MANIFEST_OR_BUILD_SPEC")]
     manifest: PathBuf, // Can be a cadi.yaml or a *.cbs.yaml
 
-    /// Build target name
+    /// Build target name; for build specs, a target triple also selects
+    /// which platform-specific components are built
     #[arg(short, long)]
     target: Option<String>,
 
@@ -29,6 +43,10 @@ pub struct BuildArgs {
     #[arg(long)]
     require_signed: bool,
 
+    /// Run external commands declared in the target's transforms
+    #[arg(long)]
+    allow_external_transforms: bool,
+
     /// Force rebuild (ignore cache)
     #[arg(long)]
     force: bool,
@@ -36,33 +54,87 @@ pub struct BuildArgs {
     /// Only verify build plan, don't execute
     #[arg(long)]
     dry_run: bool,
+
+    /// Use the chunk versions in cadi.lock and fail instead of re-resolving
+    #[arg(long)]
+    locked: bool,
+
+    /// Keep running and rebuild what changed whenever a source file behind
+    /// the manifest's chunks is saved
+    #[arg(long, conflicts_with = "dry_run")]
+    watch: bool,
+
+    /// How long saves must settle before a watch rebuild starts
+    #[arg(long, default_value_t = 300, value_name = "MS", requires = "watch")]
+    debounce_ms: u64,
+}
+
+/// What a build did
+#[derive(Debug, Default, Serialize)]
+pub struct BuildReport {
+    pub application: String,
+    pub target: String,
+    /// Nodes of the build graph
+    pub nodes: Vec<String>,
+    pub dry_run: bool,
+    /// Chunks built fresh
+    pub built: Vec<String>,
+    /// Chunks reused from the local or remote cache
+    pub cached: Vec<String>,
+    /// Chunks in `cached` served by the remote cache
+    pub remote_cached: Vec<String>,
+    /// Build spec components left out of the build
+    pub skipped: Vec<SkippedComponent>,
+    pub duration_ms: u64,
 }
 
 /// Execute the build command
-pub async fn execute(args: BuildArgs, config: &CadiConfig) -> Result<()> {
-    println!("{}", style("Building from manifest...").bold());
-    println!("  Manifest: {}", args.manifest.display());
+pub async fn execute(args: BuildArgs, config: &CadiConfig) -> Result<BuildReport> {
+    say!("{}", style("Building from manifest...").bold());
+    say!("  Manifest: {}", args.manifest.display());
     
     if let Some(target) = &args.target {
-        println!("  Target: {}", target);
+        say!("  Target: {}", target);
     }
 
-    let manifest: Manifest;
+    let mut manifest: Manifest;
+    let mut skipped: Vec<SkippedComponent> = Vec::new();
+    let mut target_name = args.target.clone().unwrap_or_else(|| "dev".to_string());
     let manifest_path_str = args.manifest.to_string_lossy();
 
     if manifest_path_str.ends_with(".build-spec.yaml") || manifest_path_str.ends_with(".cbs.yaml") {
-        println!("  {} Detected CADI Build Spec (CBS)", style("ℹ").blue());
+        say!("  {} Detected CADI Build Spec (CBS)", style("ℹ").blue());
         // 1. Load BuildSpec
         let spec: BuildSpec = BuildSpecValidator::from_yaml(&args.manifest)?;
 
-        // 2. Create SearchEngine and CbsCompiler
-        // In a real app, SearchEngine would be initialized with data from the registry.
-        let search_engine = Arc::new(SearchEngine::new());
-        let compiler = CbsCompiler::new(search_engine);
+        // 2. Resolve search components against the federated registries,
+        // or the configured registry when none are set up
+        let federation = FederationManager::from_default_config();
+        let registry: Arc<dyn ComponentSearch> = if federation.registries().is_empty() {
+            Arc::new(RegistryClient::new(RegistryConfig {
+                url: config.registry.url.clone(),
+                token: config.auth.token.clone(),
+                ..Default::default()
+            })?)
+        } else {
+            Arc::new(federation)
+        };
+        let compiler = CbsCompiler::new(registry);
 
-        // 3. Compile Spec to Manifest
-        manifest = compiler.compile(spec).await?;
-        println!("  {} Compiled Build Spec to internal manifest", style("✓").green());
+        // 3. Compile Spec to Manifest for the target's platform. A target
+        // that names no spec target is a triple; the default target builds.
+        let target_triple = resolve_target_triple(&spec, args.target.as_deref());
+        if !spec.targets.iter().any(|t| Some(&t.name) == args.target.as_ref()) {
+            target_name = "dev".to_string();
+        }
+        say!("  Target triple: {}", target_triple);
+        let compiled = compiler.compile_for_target(spec, &target_triple).await?;
+        manifest = compiled.manifest;
+        skipped = compiled.skipped;
+        say!("  {} Compiled Build Spec to internal manifest", style("✓").green());
+        for component in &skipped {
+            say!("  {} Skipping {}: {}", style("○").dim(), component.id, component.reason);
+        }
     } else {
         // Load manifest from file as before
         let manifest_content = std::fs::read_to_string(&args.manifest)?;
@@ -73,71 +145,120 @@ pub async fn execute(args: BuildArgs, config: &CadiConfig) -> Result<()> {
         };
     }
 
-    println!("  Application: {}", manifest.application.name);
-    println!("  Version: {}", manifest.application.version.as_deref().unwrap_or("0.1.0"));
+    manifest = manifest.interpolate_env().map_err(|errors| {
+        let details: Vec<String> = errors.iter().map(|e| format!("[{}] {}", e.path, e.message)).collect();
+        CommandError::Validation(format!("Manifest interpolation failed:\n  {}", details.join("\n  ")))
+    })?;
 
-    let target_name = args.target.as_deref().unwrap_or("dev");
-    
-    println!();
-    println!("{}", style("Build Plan:").bold());
+    if manifest.build_graph.nodes.iter().any(|node| node.chunk_ref.is_some()) {
+        let lock = lock_chunk_refs(&manifest, &args.manifest.with_file_name(LOCK_FILE), args.locked, config).await?;
+        lock.apply(&mut manifest)?;
+    }
+
+    say!("  Application: {}", manifest.application.name);
+    say!("  Version: {}", manifest.application.version.as_deref().unwrap_or("0.1.0"));
+
+
+    say!();
+    say!("{}", style("Build Plan:").bold());
 
     // Show nodes to be built
     for (_idx, node) in manifest.build_graph.nodes.iter().enumerate().take(10) {
-        println!("  {} Build node: {} ({})", style("→").cyan(), node.id, 
+        say!("  {} Build node: {} ({})", style("→").cyan(), node.id, 
             if node.source_cadi.is_some() { "source" } else { "derived" });
     }
     
     if manifest.build_graph.nodes.len() > 10 {
-        println!("  ... and {} more nodes", manifest.build_graph.nodes.len() - 10);
+        say!("  ... and {} more nodes", manifest.build_graph.nodes.len() - 10);
     }
 
+    let mut report = BuildReport {
+        application: manifest.application.name.clone(),
+        target: target_name.clone(),
+        nodes: manifest.build_graph.nodes.iter().map(|node| node.id.clone()).collect(),
+        dry_run: args.dry_run,
+        ..Default::default()
+    };
+
     if args.dry_run {
-        println!();
-        println!("{}", style("Dry run - no changes made.").yellow());
-        return Ok(());
+        say!();
+        say!("{}", style("Dry run - no changes made.").yellow());
+        return Ok(report);
     }
 
-    println!();
-    println!("{}", style("Executing build...").bold());
+    say!();
+    say!("{}", style("Executing build...").bold());
 
     // Create and run build engine
     let build_config = BuildConfig {
         parallel_jobs: config.build.parallelism,
         cache_dir: config.cache.dir.clone(),
         use_remote_cache: true,
+        remote_cache_url: config.build.remote_cache_url.clone(),
+        remote_cache_read_only: config.build.remote_cache_read_only,
         fail_fast: false,
-        verbose: true,
+        verbose: !output::json(),
         require_signed: args.require_signed,
+        allow_external_transforms: args.allow_external_transforms,
     };
     
     let engine = BuildEngine::new(build_config);
+    let result = run_build(&engine, &manifest, &target_name, skipped).await;
+
+    if args.watch {
+        if let Err(e) = &result {
+            eprintln!("  {} {}", style("✗").red(), e);
+        }
+        let debounce = Duration::from_millis(args.debounce_ms);
+        watch(&engine, manifest, &args.manifest, &target_name, debounce, config).await?;
+        return Ok(report);
+    }
+
+    let result = result?;
+    report.built = result.built;
+    report.cached = result.cached;
+    report.remote_cached = result.remote_cached;
+    report.skipped = result.skipped;
+    report.duration_ms = result.duration_ms;
+    Ok(report)
+}
+
+/// Build every step of the target and print a summary
+async fn run_build(
+    engine: &BuildEngine,
+    manifest: &Manifest,
+    target_name: &str,
+    skipped: Vec<SkippedComponent>,
+) -> Result<BuildResult> {
     let start = std::time::Instant::now();
-    
-    match engine.build(&manifest, target_name).await {
-        Ok(result) => {
+
+    match engine.build(manifest, target_name).await {
+        Ok(mut result) => {
+            result.skipped = skipped;
             let elapsed = start.elapsed().as_secs_f64();
             
-            println!();
-            println!("{}", style("═══════════════════════════════════════").green());
-            println!("{}", style("Build Summary").green().bold());
-            println!("{}", style("═══════════════════════════════════════").green());
+            say!();
+            say!("{}", style("═══════════════════════════════════════").green());
+            say!("{}", style("Build Summary").green().bold());
+            say!("{}", style("═══════════════════════════════════════").green());
             
             if !result.cached.is_empty() {
-                println!("  {} {} chunk(s) reused from cache", 
+                say!("  {} {} chunk(s) reused from cache ({} remote)", 
                     style("✓").green(), 
-                    style(result.cached.len()).cyan().bold());
+                    style(result.cached.len()).cyan().bold(),
+                    result.remote_cached.len());
                 for chunk_id in &result.cached {
                     let display_id = if chunk_id.len() > 60 { 
                         format!("{}...{}", &chunk_id[..30], &chunk_id[chunk_id.len()-10..])
                     } else { 
                         chunk_id.clone() 
                     };
-                    println!("    • {}", display_id);
+                    say!("    • {}", display_id);
                 }
             }
             
             if !result.built.is_empty() {
-                println!("  {} {} chunk(s) built fresh", 
+                say!("  {} {} chunk(s) built fresh", 
                     style("✓").yellow(), 
                     style(result.built.len()).cyan().bold());
                 for chunk_id in &result.built {
@@ -146,46 +267,352 @@ pub async fn execute(args: BuildArgs, config: &CadiConfig) -> Result<()> {
                     } else { 
                         chunk_id.clone() 
                     };
-                    println!("    • {}", display_id);
+                    say!("    • {}", display_id);
                 }
             }
             
+            if !result.skipped.is_empty() {
+                say!("  {} {} component(s) skipped",
+                    style("○").dim(),
+                    style(result.skipped.len()).cyan().bold());
+                for component in &result.skipped {
+                    say!("    • {}: {}", component.id, component.reason);
+                }
+            }
+
             if !result.failed.is_empty() {
-                println!("  {} {} build(s) failed", 
+                say!("  {} {} build(s) failed", 
                     style("✗").red(), 
                     style(result.failed.len()).red().bold());
                 for failure in &result.failed {
-                    println!("    • {}: {}", failure.chunk_id, failure.error);
+                    say!("    • {}: {}", failure.chunk_id, failure.error);
                 }
-                println!();
-                return Err(anyhow::anyhow!("Some builds failed"));
+                say!();
+                let failures: Vec<String> = result.failed.iter()
+                    .map(|failure| format!("{}: {}", failure.chunk_id, failure.error))
+                    .collect();
+                return Err(CommandError::Build(format!(
+                    "{} build(s) failed: {}",
+                    result.failed.len(),
+                    failures.join("; ")
+                ))
+                .into());
             }
             
-            println!();
-            println!("  {} Total time: {:.2}s", style("⏱").cyan(), elapsed);
+            say!();
+            say!("  {} Total time: {:.2}s", style("⏱").cyan(), elapsed);
             
             if !result.cached.is_empty() {
                 let cache_ratio = result.cached.len() as f64 / 
                     (result.built.len() + result.cached.len()) as f64;
-                println!("  {} Cache hit rate: {:.0}%", 
+                say!("  {} Cache hit rate: {:.0}%", 
                     style("📊").cyan(), 
                     cache_ratio * 100.0);
                 
                 let estimated_saved = result.cached.len() as f64 * 2.5; // Estimate 2.5s per cached chunk
-                println!("  {} Time saved by cache: ~{:.1}s", 
+                say!("  {} Time saved by cache: ~{:.1}s", 
                     style("⚡").cyan(), 
                     estimated_saved);
             }
             
-            println!("{}", style("═══════════════════════════════════════").green());
-            println!();
-            println!("{}", style("Build complete!").green().bold());
+            say!("{}", style("═══════════════════════════════════════").green());
+            say!();
+            say!("{}", style("Build complete!").green().bold());
+            Ok(result)
         }
         Err(e) => {
             eprintln!("  {} Build failed: {}", style("✗").red(), e);
-            return Err(anyhow::anyhow!("Build failed: {}", e));
+            Err(CommandError::Build(format!("Build failed: {}", e)).into())
+        }
+    }
+}
+
+/// Load cadi.lock and resolve the chunk references it is missing against the
+/// registry, saving the result. With `locked`, the lockfile has to match the
+/// manifest exactly and nothing is resolved.
+async fn lock_chunk_refs(
+    manifest: &Manifest,
+    lock_path: &std::path::Path,
+    locked: bool,
+    config: &CadiConfig,
+) -> Result<ManifestLock> {
+    let mut lock = if lock_path.exists() {
+        ManifestLock::load(lock_path)?
+    } else if locked {
+        return Err(CommandError::Validation(format!("--locked was given but {} does not exist", lock_path.display())).into());
+    } else {
+        ManifestLock::new()
+    };
+
+    if locked {
+        let errors = lock.check(manifest);
+        if !errors.is_empty() {
+            let details: Vec<String> = errors.iter().map(|e| e.message.clone()).collect();
+            return Err(CommandError::Validation(format!(
+                "{} is out of date and --locked prevents re-resolving:\n  {}",
+                LOCK_FILE,
+                details.join("\n  ")
+            ))
+            .into());
+        }
+        say!("  {} Using locked chunk versions", style("✓").green());
+        return Ok(lock);
+    }
+
+    let pruned = lock.prune(manifest);
+    let unresolved: Vec<(String, String)> = lock
+        .unresolved(manifest)
+        .into_iter()
+        .map(|(node_id, chunk_ref)| (node_id.to_string(), chunk_ref.to_string()))
+        .collect();
+    if pruned == 0 && unresolved.is_empty() {
+        return Ok(lock);
+    }
+
+    if !unresolved.is_empty() {
+        let client = RegistryClient::new(RegistryConfig {
+            url: config.registry.url.clone(),
+            token: config.auth.token.clone(),
+            ..Default::default()
+        })?;
+        for (node_id, chunk_ref) in unresolved {
+            let locked_chunk = client.resolve_chunk_ref(&ChunkRef::parse(&chunk_ref)?).await?;
+            say!("  {} {} → {} ({})", style("✓").green(), chunk_ref, locked_chunk.version, locked_chunk.chunk_id);
+            lock.chunks.insert(node_id, locked_chunk);
+        }
+    }
+
+    lock.save(lock_path)?;
+    say!("  {} Updated {}", style("✓").green(), lock_path.display());
+    Ok(lock)
+}
+
+/// Watch the source files behind the manifest's chunks and, after each burst
+/// of saves, re-atomize the changed files and rerun the build steps they
+/// invalidate. Runs until Ctrl-C.
+///
+/// Files are mapped to chunks through the import state `cadi import` leaves
+/// in the project, so only imported projects can be watched. The manifest
+/// is updated in memory; run `cadi import` to persist new chunk IDs.
+async fn watch(
+    engine: &BuildEngine,
+    manifest: Manifest,
+    manifest_path: &Path,
+    target: &str,
+    debounce: Duration,
+    config: &CadiConfig,
+) -> Result<()> {
+    let root = manifest_path
+        .canonicalize()?
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let state_path = root.join(IMPORT_STATE_FILE);
+    let state: ImportState = serde_json::from_str(
+        &std::fs::read_to_string(&state_path)
+            .with_context(|| format!("No import state at {}; run `cadi import` first", state_path.display()))?,
+    )?;
+
+    let mut graph = watch_graph(manifest, &state, &root);
+    let watched: BTreeSet<PathBuf> = graph.watched_files().map(Path::to_path_buf).collect();
+    if watched.is_empty() {
+        return Err(CommandError::Validation(format!(
+            "None of the manifest's chunks come from files imported from {}",
+            root.display()
+        ))
+        .into());
+    }
+
+    // Watch directories rather than files so saves that replace the file
+    // (write to a temporary, rename over) keep being seen
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            let _ = tx.send(event);
+        }
+    })?;
+    let dirs: BTreeSet<&Path> = watched.iter().filter_map(|path| path.parent()).collect();
+    for dir in dirs {
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    }
+
+    let chunker = SmartChunker::new(SmartChunkerConfig::default());
+    let graph_dir = config.cache.dir.join("graph-db");
+    let mut debouncer = Debouncer::new(debounce);
+
+    say!();
+    say!(
+        "{} Watching {} file(s) for changes (Ctrl-C to stop)",
+        style("👀").cyan(),
+        watched.len()
+    );
+
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    loop {
+        let deadline = debouncer.deadline();
+        tokio::select! {
+            _ = &mut ctrl_c => {
+                say!();
+                say!("{}", style("Stopped watching.").dim());
+                return Ok(());
+            }
+            Some(event) = rx.recv() => {
+                if matches!(event.kind, EventKind::Access(_)) {
+                    continue;
+                }
+                for path in event.paths {
+                    if graph.is_watched(&path) {
+                        let change = if path.exists() {
+                            ChangeEvent::modified(path)
+                        } else {
+                            ChangeEvent::removed(path)
+                        };
+                        debouncer.push(change, Instant::now());
+                    }
+                }
+            }
+            _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now).into()), if deadline.is_some() => {
+                let events = debouncer.take_ready(Instant::now());
+                let (changes, chunks) = reatomize(&chunker, &graph, &events);
+                let invalidation = graph.apply(changes);
+                if invalidation.is_empty() {
+                    continue;
+                }
+
+                say!();
+                say!("{} {}", style("↻").cyan(), invalidation);
+                if let Err(e) = store_chunks(&graph_dir, &chunks, &state) {
+                    eprintln!("  {} Failed to update the chunk graph: {}", style("⚠").yellow(), e);
+                }
+
+                let dirty = invalidation.dirty();
+                if dirty.is_empty() {
+                    continue;
+                }
+                let start = Instant::now();
+                match engine.rebuild(graph.manifest(), target, &dirty).await {
+                    Ok(result) if result.failed.is_empty() => {
+                        say!(
+                            "  {} Rebuilt {} step(s) in {:.2}s",
+                            style("✓").green(),
+                            result.built.len(),
+                            start.elapsed().as_secs_f64()
+                        );
+                    }
+                    Ok(result) => {
+                        for failure in &result.failed {
+                            eprintln!("  {} {}: {}", style("✗").red(), failure.chunk_id, failure.error);
+                        }
+                    }
+                    Err(e) => eprintln!("  {} Rebuild failed: {}", style("✗").red(), e),
+                }
+            }
+        }
+    }
+}
+
+/// Map each imported file to the manifest nodes materializing its chunks
+fn watch_graph(manifest: Manifest, state: &ImportState, root: &Path) -> WatchGraph {
+    let mut nodes_by_chunk: HashMap<&str, &str> = HashMap::new();
+    for node in &manifest.build_graph.nodes {
+        let chunks = node.source_cadi.iter().chain(node.representations.iter().map(|r| &r.chunk));
+        for chunk in chunks {
+            nodes_by_chunk.insert(chunk, &node.id);
+        }
+    }
+    let names: HashMap<&str, &str> = state.chunks.iter().map(|c| (c.chunk_id.as_str(), c.name.as_str())).collect();
+
+    let mut atoms = Vec::new();
+    for file in &state.files {
+        let path = root.join(&file.path);
+        for chunk_id in &file.chunk_ids {
+            if let (Some(node_id), Some(name)) = (nodes_by_chunk.get(chunk_id.as_str()), names.get(chunk_id.as_str())) {
+                let atom = WatchedAtom {
+                    node_id: node_id.to_string(),
+                    name: name.to_string(),
+                    chunk_id: chunk_id.clone(),
+                };
+                atoms.push((path.clone(), atom));
+            }
         }
     }
+    WatchGraph::new(manifest, atoms)
+}
+
+/// Re-atomize the changed files, returning the changes to apply and the new
+/// chunks with their content
+fn reatomize(
+    chunker: &SmartChunker,
+    graph: &WatchGraph,
+    events: &[ChangeEvent],
+) -> (Vec<FileChange>, Vec<(AtomicChunk, String)>) {
+    let mut changes = Vec::new();
+    let mut chunks = Vec::new();
+    for event in events {
+        let content = match (event.kind, std::fs::read_to_string(&event.path)) {
+            (ChangeKind::Modified, Ok(content)) => content,
+            _ => {
+                changes.push(FileChange::Removed(event.path.clone()));
+                continue;
+            }
+        };
 
+        // A file imported whole stays whole. A file that fails to
+        // re-atomize keeps its previous atoms until the next change.
+        let generated = match chunker.analyze_file(&event.path, &content).and_then(|analysis| {
+            let decision = if graph.atoms_in(&event.path).len() == 1 {
+                chunker.decide_chunking_as(&analysis, ChunkingStrategy::Atomic)
+            } else {
+                chunker.decide_chunking(&analysis)
+            };
+            chunker.generate_chunks(&event.path, &content, &decision)
+        }) {
+            Ok(generated) => generated,
+            Err(e) => {
+                eprintln!("  {} {}", style("⚠").yellow(), e);
+                continue;
+            }
+        };
+
+        changes.push(FileChange::Reatomized {
+            path: event.path.clone(),
+            atoms: generated
+                .iter()
+                .map(|c| Atom { name: c.name.clone(), chunk_id: c.chunk_id.clone() })
+                .collect(),
+        });
+        chunks.extend(generated.into_iter().map(|c| {
+            let text = chunk_text(&content, &c);
+            (c, text)
+        }));
+    }
+    (changes, chunks)
+}
+
+/// The lines of `content` a chunk was generated from
+fn chunk_text(content: &str, chunk: &AtomicChunk) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let source = chunk.sources.first();
+    let start = source.and_then(|s| s.start_line).unwrap_or(0);
+    let end = source.and_then(|s| s.end_line).unwrap_or(lines.len());
+    if start == 0 && end >= lines.len() {
+        content.to_string()
+    } else {
+        lines[start.saturating_sub(1)..end.min(lines.len())].join("\n")
+    }
+}
+
+/// Update the chunk graph with re-atomized chunks, if there is a graph
+fn store_chunks(graph_dir: &Path, chunks: &[(AtomicChunk, String)], state: &ImportState) -> Result<()> {
+    if chunks.is_empty() || !graph_dir.exists() {
+        return Ok(());
+    }
+    let store = GraphStore::open(graph_dir)?;
+    BatchImporter::new(&store).import(chunks.iter().map(|(c, _)| c.clone()).collect(), &state.alias_registry)?;
+    for (chunk, text) in chunks {
+        store.store_content(&chunk.chunk_id, text.as_bytes())?;
+    }
+    store.flush()?;
     Ok(())
 }
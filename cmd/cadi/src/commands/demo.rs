@@ -2,11 +2,12 @@ use anyhow::{Result, anyhow};
 use clap::Args;
 use console::style;
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use tokio::process::Command;
 
 use crate::config::CadiConfig;
+use crate::output::say;
 
 /// Arguments for the demo command
 #[derive(Args)]
@@ -23,6 +24,10 @@ pub struct DemoArgs {
     #[arg(short, long)]
     aesthetic: Option<String>,
 
+    /// Project imported by the e2e demo (default: the bundled todo-suite example)
+    #[arg(long)]
+    path: Option<PathBuf>,
+
     /// List available demos
     #[arg(long)]
     list: bool,
@@ -31,38 +36,57 @@ pub struct DemoArgs {
 /// Execute the demo command
 pub async fn execute(args: DemoArgs, _config: &CadiConfig) -> Result<()> {
     if args.list || args.demo.as_deref() == Some("list") {
-        println!("{}", style("Available Demos").bold());
-        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-        println!();
-        println!("  {} - CADI Todo App Builder (Chunk Reuse Demo)", style("todo-suite").cyan());
-        println!("    Demonstrates how CADI minimizes code generation by reusing");
-        println!("    standardized components from the registry.");
-        println!();
-        println!("    Aesthetics:");
-        println!("      - cyberpunk:  Neon green on black with glow effects");
-        println!("      - minimalist: Clean white and black design");
-        println!("      - sunset:     Warm orange and brown tones");
-        println!("      - ocean:      Deep blue with glass effects");
-        println!();
-        println!("Usage:");
-        println!("  cadi demo todo-suite --aesthetic cyberpunk");
+        say!("{}", style("Available Demos").bold());
+        say!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        say!();
+        say!("  {} - CADI Todo App Builder (Chunk Reuse Demo)", style("todo-suite").cyan());
+        say!("    Demonstrates how CADI minimizes code generation by reusing");
+        say!("    standardized components from the registry.");
+        say!();
+        say!("    Aesthetics:");
+        say!("      - cyberpunk:  Neon green on black with glow effects");
+        say!("      - minimalist: Clean white and black design");
+        say!("      - sunset:     Warm orange and brown tones");
+        say!("      - ocean:      Deep blue with glass effects");
+        say!();
+        say!("  {} - End-to-End Round Trip", style("e2e").cyan());
+        say!("    Imports the todo-suite example into an in-process registry,");
+        say!("    fetches it back by alias, rehydrates a view and builds todo-cli.");
+        say!();
+        say!("Usage:");
+        say!("  cadi demo todo-suite --aesthetic cyberpunk");
+        say!("  cadi demo e2e");
         return Ok(());
     }
 
     match args.demo.as_deref() {
         Some("todo-suite") => run_todo_suite_demo(&args).await,
+        Some("e2e") => {
+            let example = args.path.clone().unwrap_or_else(bundled_todo_suite);
+            super::demo_e2e::run(&example).await
+        }
         Some(unknown) => {
-            println!("{} Unknown demo: {}", style("✗").red(), unknown);
-            println!("Run 'cadi demo --list' to see available demos.");
+            say!("{} Unknown demo: {}", style("✗").red(), unknown);
+            say!("Run 'cadi demo --list' to see available demos.");
             Ok(())
         }
         None => {
-            println!("Run 'cadi demo --list' to see available demos.");
+            say!("Run 'cadi demo --list' to see available demos.");
             Ok(())
         }
     }
 }
 
+/// The todo-suite example: under the current directory when run from a
+/// checkout, otherwise next to the sources this binary was built from
+fn bundled_todo_suite() -> PathBuf {
+    let local = PathBuf::from("examples/todo-suite");
+    if local.exists() {
+        return local;
+    }
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../../examples/todo-suite")
+}
+
 async fn check_tool(name: &str) -> bool {
     Command::new(name)
         .arg("--version")
@@ -75,11 +99,11 @@ async fn check_tool(name: &str) -> bool {
 }
 
 async fn run_todo_suite_demo(args: &DemoArgs) -> Result<()> {
-    println!("{}", style("🎨 CADI Todo App Builder - Chunk Reuse Demo").bold());
-    println!();
-    println!("This demo shows how CADI minimizes code generation");
-    println!("by reusing standardized components from the registry.");
-    println!();
+    say!("{}", style("🎨 CADI Todo App Builder - Chunk Reuse Demo").bold());
+    say!();
+    say!("This demo shows how CADI minimizes code generation");
+    say!("by reusing standardized components from the registry.");
+    say!();
 
     // Check prerequisites
     let node_ok = check_tool("node").await;
@@ -93,16 +117,16 @@ async fn run_todo_suite_demo(args: &DemoArgs) -> Result<()> {
     let aesthetic = match &args.aesthetic {
         Some(a) => a.to_lowercase(),
         None => {
-            println!("{}", style("Available aesthetics:").cyan());
-            println!("  • cyberpunk  - Neon green on black with glow effects");
-            println!("  • minimalist - Clean white and black design");
-            println!("  • sunset     - Warm orange and brown tones");
-            println!("  • ocean      - Deep blue with glass effects");
-            println!();
+            say!("{}", style("Available aesthetics:").cyan());
+            say!("  • cyberpunk  - Neon green on black with glow effects");
+            say!("  • minimalist - Clean white and black design");
+            say!("  • sunset     - Warm orange and brown tones");
+            say!("  • ocean      - Deep blue with glass effects");
+            say!();
             
             // Use minimalist as default for non-interactive
-            println!("No aesthetic specified, using 'minimalist'.");
-            println!("Use --aesthetic <name> to choose a different style.");
+            say!("No aesthetic specified, using 'minimalist'.");
+            say!("Use --aesthetic <name> to choose a different style.");
             "minimalist".to_string()
         }
     };
@@ -115,29 +139,29 @@ async fn run_todo_suite_demo(args: &DemoArgs) -> Result<()> {
         ));
     }
 
-    println!();
-    println!("{} Creating new todo app with '{}' aesthetic...", style("[STEP]").blue(), aesthetic);
+    say!();
+    say!("{} Creating new todo app with '{}' aesthetic...", style("[STEP]").blue(), aesthetic);
 
     // Create app directory
     let app_name = format!("{}-todo", aesthetic);
     let app_dir = Path::new(&app_name);
 
     if app_dir.exists() {
-        println!("{} Removing existing {}...", style("[WARN]").yellow(), app_name);
+        say!("{} Removing existing {}...", style("[WARN]").yellow(), app_name);
         std::fs::remove_dir_all(app_dir)?;
     }
 
     std::fs::create_dir_all(app_dir.join("src"))?;
     std::fs::create_dir_all(app_dir.join("public"))?;
 
-    println!("{} Generating {} styling (ONLY new code being created)...", style("[STEP]").blue(), aesthetic);
+    say!("{} Generating {} styling (ONLY new code being created)...", style("[STEP]").blue(), aesthetic);
 
     // Generate CSS - THIS IS THE ONLY NEW CODE
     let css = generate_css(&aesthetic);
     std::fs::write(app_dir.join("src/styles.css"), &css)?;
-    println!("{} Generated styles.css - ~{} lines of styling", style("✅").green(), css.lines().count());
+    say!("{} Generated styles.css - ~{} lines of styling", style("✅").green(), css.lines().count());
 
-    println!("{} Fetching reusable components from CADI registry...", style("[STEP]").blue());
+    say!("{} Fetching reusable components from CADI registry...", style("[STEP]").blue());
 
     // Create package.json
     let capitalized = capitalize_first(&aesthetic);
@@ -307,10 +331,10 @@ export default function App() {{
 }}"#, uppercase);
     std::fs::write(app_dir.join("src/App.tsx"), app_tsx)?;
 
-    println!("{} Reused standard React todo components from CADI", style("✅").green());
+    say!("{} Reused standard React todo components from CADI", style("✅").green());
 
-    println!();
-    println!("{} Building the app...", style("[STEP]").blue());
+    say!();
+    say!("{} Building the app...", style("[STEP]").blue());
 
     // Run npm install
     let npm_install = Command::new("npm")
@@ -339,20 +363,20 @@ export default function App() {{
         return Err(anyhow!("npm build failed"));
     }
 
-    println!();
-    println!("{} {}", style("🎯").bold(), style("DEMONSTRATING CADI CHUNK REUSE").bold());
-    println!("{}", style("═══════════════════════════════════════════════════════════").magenta());
-    println!("{}", style("What just happened:").cyan());
-    println!("  {} Generated: ~{} lines of {} CSS (NEW code)", style("✓").green(), css.lines().count(), aesthetic);
-    println!("  {} Reused: React app structure (from CADI registry)", style("✓").green());
-    println!("  {} Reused: Todo logic component (from CADI registry)", style("✓").green());
-    println!("  {} Reused: TypeScript configs (from CADI registry)", style("✓").green());
-    println!();
-    println!("{} Generate ~800 lines of code", style("Traditional approach:").cyan());
-    println!("{} Generate ~{} lines, reuse ~600 lines", style("CADI approach:").green(), css.lines().count());
-    println!("{}", style("Code reduction: 75% less generation!").yellow());
-    println!("{}", style("═══════════════════════════════════════════════════════════").magenta());
-    println!();
+    say!();
+    say!("{} {}", style("🎯").bold(), style("DEMONSTRATING CADI CHUNK REUSE").bold());
+    say!("{}", style("═══════════════════════════════════════════════════════════").magenta());
+    say!("{}", style("What just happened:").cyan());
+    say!("  {} Generated: ~{} lines of {} CSS (NEW code)", style("✓").green(), css.lines().count(), aesthetic);
+    say!("  {} Reused: React app structure (from CADI registry)", style("✓").green());
+    say!("  {} Reused: Todo logic component (from CADI registry)", style("✓").green());
+    say!("  {} Reused: TypeScript configs (from CADI registry)", style("✓").green());
+    say!();
+    say!("{} Generate ~800 lines of code", style("Traditional approach:").cyan());
+    say!("{} Generate ~{} lines, reuse ~600 lines", style("CADI approach:").green(), css.lines().count());
+    say!("{}", style("Code reduction: 75% less generation!").yellow());
+    say!("{}", style("═══════════════════════════════════════════════════════════").magenta());
+    say!();
 
     // Find available port
     let mut port = 8083;
@@ -363,21 +387,21 @@ export default function App() {{
         }
     }
 
-    println!("{} Serving your new {} todo app...", style("🚀").bold(), aesthetic);
-    println!();
-    println!("{} App running at: {}", style("🌐").bold(), style(format!("http://localhost:{}", port)).cyan().underlined());
-    println!();
-    println!("{}", style("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━").green());
-    println!("{}", style("✨ CADI Value Proposition:").green());
-    println!("  {} Only generated new {} styling (~{} lines)", style("→").cyan(), aesthetic, css.lines().count());
-    println!("  {} Reused standard React todo components from registry", style("→").cyan());
-    println!("  {} 75% less code generation vs traditional approach", style("→").cyan());
-    println!("  {} Same components shared across all aesthetics", style("→").cyan());
-    println!("{}", style("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━").green());
-    println!();
-    println!("{} Try running again with different aesthetic to see reuse!", style("💡").bold());
-    println!();
-    println!("{} Press Ctrl+C to stop the server", style("[INFO]").yellow());
+    say!("{} Serving your new {} todo app...", style("🚀").bold(), aesthetic);
+    say!();
+    say!("{} App running at: {}", style("🌐").bold(), style(format!("http://localhost:{}", port)).cyan().underlined());
+    say!();
+    say!("{}", style("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━").green());
+    say!("{}", style("✨ CADI Value Proposition:").green());
+    say!("  {} Only generated new {} styling (~{} lines)", style("→").cyan(), aesthetic, css.lines().count());
+    say!("  {} Reused standard React todo components from registry", style("→").cyan());
+    say!("  {} 75% less code generation vs traditional approach", style("→").cyan());
+    say!("  {} Same components shared across all aesthetics", style("→").cyan());
+    say!("{}", style("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━").green());
+    say!();
+    say!("{} Try running again with different aesthetic to see reuse!", style("💡").bold());
+    say!();
+    say!("{} Press Ctrl+C to stop the server", style("[INFO]").yellow());
 
     // Serve the app
     let serve_result = Command::new("npx")
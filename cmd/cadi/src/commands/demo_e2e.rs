@@ -0,0 +1,510 @@
+//! End-to-end demo
+//!
+//! `cadi demo e2e` runs the whole CADI round trip against a throwaway
+//! registry: import the bundled todo-suite example, publish its chunks,
+//! wipe the workspace, fetch the chunks back by alias, rehydrate a view and
+//! build the todo-cli target. Each phase is a function of its own so the
+//! round trip also runs as a test.
+
+use anyhow::{anyhow, Context, Result};
+use console::style;
+use std::collections::{BTreeMap, HashSet};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use cadi_builder::{BuildConfig, BuildEngine, BuildResult};
+use cadi_core::graph::{BatchImporter, GraphStore};
+use cadi_core::rehydration::{RehydrationEngine, ViewConfig, VirtualView};
+use cadi_core::{chunk_storage_key, AliasRegistry, AtomicChunk, ImportResult, Manifest, ProjectAnalyzer, ProjectAnalyzerConfig, CURRENT_CADL_VERSION};
+use cadi_registry::{RegistryClient, RegistryConfig};
+
+use crate::output::{self, say};
+
+/// Workspace member whose chunks are rehydrated and built
+pub const DEMO_MEMBER: &str = "todo-cli";
+
+/// A registry served in-process on an ephemeral port. The server stops when
+/// this is dropped.
+pub struct DemoRegistry {
+    pub url: String,
+    server: tokio::task::JoinHandle<()>,
+}
+
+impl DemoRegistry {
+    /// A client for the registry
+    pub fn client(&self) -> Result<RegistryClient> {
+        Ok(RegistryClient::new(RegistryConfig {
+            url: self.url.clone(),
+            max_retries: 0,
+            ..Default::default()
+        })?)
+    }
+}
+
+impl Drop for DemoRegistry {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// What the import found
+#[derive(Debug)]
+pub struct ImportStats {
+    pub files: usize,
+    pub chunks: usize,
+    pub unique_chunks: usize,
+    pub compositions: usize,
+    pub potential_duplicates: usize,
+}
+
+/// What publishing sent to the registry
+#[derive(Debug, Default)]
+pub struct PublishStats {
+    pub published: usize,
+    /// Chunks the registry already had
+    pub already_present: usize,
+    /// Chunks whose content another imported chunk already carried
+    pub duplicates: usize,
+    pub bytes_published: usize,
+    pub bytes_deduped: usize,
+    /// Aliases of the published chunks; this is all a consumer keeps once
+    /// the workspace is wiped
+    pub aliases: AliasRegistry,
+}
+
+/// What fetching by alias brought back
+#[derive(Debug, Default)]
+pub struct FetchStats {
+    pub aliases: usize,
+    pub fetched: usize,
+    /// Aliases whose chunk an earlier alias already fetched
+    pub deduped: usize,
+    pub bytes: usize,
+}
+
+/// The view rehydrated from the fetched chunks
+#[derive(Debug)]
+pub struct RehydrateStats {
+    pub requested: usize,
+    pub view: VirtualView,
+}
+
+/// Start a registry serving cadi-server's router from `storage`
+pub async fn start_registry(storage: &Path) -> Result<DemoRegistry> {
+    let config = cadi_server::state::ServerConfig {
+        bind_address: "127.0.0.1:0".to_string(),
+        storage_path: storage.to_string_lossy().to_string(),
+        anonymous_write: true,
+        ..Default::default()
+    };
+    let app = cadi_server::routes::app(cadi_server::state::AppState::new(config).await);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}", listener.local_addr()?);
+    let server = tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            tracing::error!("Demo registry stopped: {}", e);
+        }
+    });
+
+    Ok(DemoRegistry { url, server })
+}
+
+/// Import a project without writing any import state into it
+pub async fn import_example(root: &Path) -> Result<(ImportResult, ImportStats)> {
+    let analyzer = ProjectAnalyzer::new(ProjectAnalyzerConfig {
+        incremental: false,
+        ..Default::default()
+    });
+    let path = root.to_path_buf();
+    let result = tokio::task::spawn_blocking(move || analyzer.import_project(&path))
+        .await?
+        .with_context(|| format!("Failed to import {}", root.display()))?;
+
+    let unique: HashSet<&str> = result.chunks.iter().map(|c| c.chunk_id.as_str()).collect();
+    let stats = ImportStats {
+        files: result.summary.total_files,
+        chunks: result.chunks.len(),
+        unique_chunks: unique.len(),
+        compositions: result.compositions.len(),
+        potential_duplicates: result.summary.potential_duplicates,
+    };
+    Ok((result, stats))
+}
+
+/// Publish the content of every atomic chunk, keeping a local copy in
+/// `<workspace>/chunks` as `cadi import` would. Compositions only group
+/// other chunks and have no content of their own, so they stay local.
+pub async fn publish_chunks(client: &RegistryClient, result: &ImportResult, workspace: &Path) -> Result<PublishStats> {
+    std::fs::create_dir_all(workspace.join("chunks"))?;
+    let mut stats = PublishStats::default();
+    let mut seen = HashSet::new();
+
+    for chunk in &result.chunks {
+        let content = chunk_content(chunk)?;
+        std::fs::write(chunk_path(workspace, &chunk.chunk_id), &content)?;
+        if !seen.insert(chunk.chunk_id.as_str()) {
+            stats.duplicates += 1;
+            stats.bytes_deduped += content.len();
+        } else if client.chunk_exists(&chunk.chunk_id).await? {
+            stats.already_present += 1;
+            stats.bytes_deduped += content.len();
+        } else {
+            let published = client.publish_chunk(&chunk.chunk_id, &content).await?;
+            if !published.success {
+                return Err(anyhow!(
+                    "Registry rejected {}: {}",
+                    chunk.chunk_id,
+                    published.message.unwrap_or_default()
+                ));
+            }
+            stats.published += 1;
+            stats.bytes_published += content.len();
+        }
+
+        for alias in result.alias_registry.get_aliases(&chunk.chunk_id).into_iter().flatten() {
+            stats.aliases.register(alias, &chunk.chunk_id);
+        }
+    }
+    stats.aliases.save(&workspace.join("aliases.json"))?;
+
+    Ok(stats)
+}
+
+/// Remove everything in the workspace, returning how many files went
+pub fn wipe_workspace(workspace: &Path) -> Result<usize> {
+    let mut removed = 0;
+    if workspace.exists() {
+        let mut pending = vec![workspace.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            for entry in std::fs::read_dir(&dir)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    pending.push(path);
+                } else {
+                    removed += 1;
+                }
+            }
+        }
+        std::fs::remove_dir_all(workspace)?;
+    }
+    std::fs::create_dir_all(workspace)?;
+    Ok(removed)
+}
+
+/// Fetch the chunk behind every alias into `<workspace>/chunks`, checking
+/// each against its hash
+pub async fn fetch_by_alias(client: &RegistryClient, aliases: &AliasRegistry, workspace: &Path) -> Result<FetchStats> {
+    let chunks_dir = workspace.join("chunks");
+    std::fs::create_dir_all(&chunks_dir)?;
+
+    let mut stats = FetchStats::default();
+    let mut fetched = HashSet::new();
+    let sorted: BTreeMap<&String, &String> = aliases.aliases.iter().collect();
+
+    for (alias, chunk_id) in sorted {
+        stats.aliases += 1;
+        if !fetched.insert(chunk_id.as_str()) {
+            stats.deduped += 1;
+            continue;
+        }
+
+        let data = client
+            .fetch_chunk(chunk_id)
+            .await
+            .with_context(|| format!("Failed to fetch {} ({})", alias, chunk_id))?;
+        if !cadi_core::hash::verify_chunk_content(chunk_id, &data) {
+            return Err(anyhow!("Content of {} does not match its hash", chunk_id));
+        }
+        std::fs::write(chunk_path(workspace, chunk_id), &data)?;
+        stats.fetched += 1;
+        stats.bytes += data.len();
+    }
+
+    Ok(stats)
+}
+
+/// Load the fetched chunks into a graph in the workspace and assemble a
+/// view of the member's chunks
+pub async fn rehydrate(
+    workspace: &Path,
+    result: &ImportResult,
+    aliases: &AliasRegistry,
+    root: &Path,
+) -> Result<RehydrateStats> {
+    let graph = GraphStore::open(workspace.join("graph-db"))?;
+    let fetched: Vec<AtomicChunk> = result
+        .chunks
+        .iter()
+        .filter(|c| chunk_path(workspace, &c.chunk_id).exists())
+        .cloned()
+        .collect();
+    BatchImporter::new(&graph).import(fetched.clone(), aliases)?;
+    for chunk in &fetched {
+        graph.store_content(&chunk.chunk_id, &std::fs::read(chunk_path(workspace, &chunk.chunk_id))?)?;
+    }
+    graph.flush()?;
+
+    let requested: Vec<String> = member_chunks(result, root, DEMO_MEMBER)
+        .into_iter()
+        .map(|c| c.chunk_id.clone())
+        .collect();
+    if requested.is_empty() {
+        return Err(anyhow!("No chunks found for {}", DEMO_MEMBER));
+    }
+
+    let view = RehydrationEngine::new(graph)
+        .create_view(requested.clone(), ViewConfig::default())
+        .await?;
+    Ok(RehydrateStats {
+        requested: requested.len(),
+        view,
+    })
+}
+
+/// Build the member's target from its fetched chunks
+pub async fn build_target(workspace: &Path, result: &ImportResult, root: &Path) -> Result<BuildResult> {
+    let chunks = member_chunks(result, root, DEMO_MEMBER);
+    let manifest = member_manifest(DEMO_MEMBER, &chunks)?;
+
+    let engine = BuildEngine::new(BuildConfig {
+        cache_dir: workspace.join("build-cache"),
+        use_remote_cache: false,
+        verbose: !output::json(),
+        ..Default::default()
+    });
+    Ok(engine.build(&manifest, DEMO_MEMBER).await?)
+}
+
+/// Run every phase, timing each one; the scratch directory holding the
+/// registry and the workspace is removed afterwards
+pub async fn run(example: &Path) -> Result<()> {
+    let root = example
+        .canonicalize()
+        .with_context(|| format!("Example not found: {}", example.display()))?;
+    let scratch = std::env::temp_dir().join(format!("cadi-demo-e2e-{}", uuid::Uuid::new_v4()));
+
+    let result = run_phases(&root, &scratch).await;
+
+    std::fs::remove_dir_all(&scratch).ok();
+    say!();
+    say!("{} Cleaned up {}", style("✓").green(), scratch.display());
+    result
+}
+
+async fn run_phases(root: &Path, scratch: &Path) -> Result<()> {
+    let workspace = scratch.join("workspace");
+    let total = Instant::now();
+
+    say!("{}", style("🔁 CADI End-to-End Demo").bold());
+    say!("  Example: {}", root.display());
+    say!();
+
+    let registry = timed("Registry", start_registry(&scratch.join("registry"))).await?;
+    say!("    listening on {}", registry.url);
+    let client = registry.client()?;
+
+    let (result, stats) = timed("Import", import_example(root)).await?;
+    say!(
+        "    {} files → {} chunks ({} unique), {} compositions, {} potential duplicates",
+        stats.files, stats.chunks, stats.unique_chunks, stats.compositions, stats.potential_duplicates
+    );
+
+    let published = timed("Publish", publish_chunks(&client, &result, &workspace)).await?;
+    say!(
+        "    {} published ({}), {} already in registry, {} duplicates ({} deduped)",
+        published.published,
+        format_size(published.bytes_published),
+        published.already_present,
+        published.duplicates,
+        format_size(published.bytes_deduped)
+    );
+
+    let removed = timed("Wipe", async { wipe_workspace(&workspace) }).await?;
+    say!("    {} files removed from {}", removed, workspace.display());
+
+    let fetched = timed("Fetch", fetch_by_alias(&client, &published.aliases, &workspace)).await?;
+    say!(
+        "    {} aliases → {} chunks fetched ({}), {} aliases shared a chunk",
+        fetched.aliases,
+        fetched.fetched,
+        format_size(fetched.bytes),
+        fetched.deduped
+    );
+
+    let rehydrated = timed("Rehydrate", rehydrate(&workspace, &result, &published.aliases, root)).await?;
+    let view = &rehydrated.view;
+    say!(
+        "    {} {} atoms requested → view of {} atoms ({} ghost), {} lines, ~{} tokens",
+        rehydrated.requested,
+        DEMO_MEMBER,
+        view.atoms.len(),
+        view.ghost_atoms.len(),
+        view.source.lines().count(),
+        view.token_estimate
+    );
+
+    let built = timed("Build", build_target(&workspace, &result, root)).await?;
+    say!(
+        "    {} built, {} from cache, {} failed",
+        built.built.len(),
+        built.cached.len(),
+        built.failed.len()
+    );
+    if let Some(failure) = built.failed.first() {
+        return Err(anyhow!("Building {} failed: {}", failure.chunk_id, failure.error));
+    }
+
+    say!();
+    say!(
+        "{} Round trip finished in {:.2}s",
+        style("✓").green().bold(),
+        total.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
+
+/// Await one phase, printing its name and how long it took
+async fn timed<T>(phase: &str, future: impl Future<Output = Result<T>>) -> Result<T> {
+    say!("{} {}", style("[PHASE]").blue(), phase);
+    let start = Instant::now();
+    let output = future.await.with_context(|| format!("{} phase failed", phase))?;
+    say!("    {} in {} ms", style("done").green(), start.elapsed().as_millis());
+    Ok(output)
+}
+
+/// The imported chunks whose source lives in a workspace member
+pub fn member_chunks<'a>(result: &'a ImportResult, root: &Path, member: &str) -> Vec<&'a AtomicChunk> {
+    result
+        .chunks
+        .iter()
+        .filter(|chunk| {
+            chunk.sources.first().is_some_and(|source| {
+                source
+                    .path()
+                    .strip_prefix(root)
+                    .is_ok_and(|relative| relative.starts_with(member))
+            })
+        })
+        .collect()
+}
+
+/// A manifest with one node per chunk and a target building all of them
+fn member_manifest(member: &str, chunks: &[&AtomicChunk]) -> Result<Manifest> {
+    let nodes: Vec<serde_json::Value> = chunks
+        .iter()
+        .map(|chunk| {
+            let alias = chunk
+                .primary_alias()
+                .map(|a| a.full_path())
+                .unwrap_or_else(|| chunk.name.clone());
+            serde_json::json!({
+                "id": alias,
+                "source_cadi": chunk.chunk_id,
+                "representations": [{ "form": "source", "language": chunk.language, "chunk": chunk.chunk_id }]
+            })
+        })
+        .collect();
+
+    let manifest = serde_json::json!({
+        "cadl_version": CURRENT_CADL_VERSION,
+        "manifest_id": format!("app:uuid:{}", uuid::Uuid::new_v4()),
+        "manifest_version": "1.0",
+        "application": { "name": member, "version": "0.1.0" },
+        "build_graph": { "nodes": nodes },
+        "build_targets": [{
+            "name": member,
+            "platform": "any",
+            "nodes": nodes.iter().map(|n| serde_json::json!({ "id": n["id"], "prefer": ["source"] })).collect::<Vec<_>>()
+        }]
+    });
+    Ok(serde_json::from_value(manifest)?)
+}
+
+/// The source text a chunk was hashed from: its line range, or the whole
+/// file for chunks that cover one
+fn chunk_content(chunk: &AtomicChunk) -> Result<Vec<u8>> {
+    let source = chunk
+        .sources
+        .first()
+        .ok_or_else(|| anyhow!("{} has no source location", chunk.chunk_id))?;
+    let file = std::fs::read_to_string(source.path())
+        .with_context(|| format!("Failed to read {}", source.file))?;
+
+    let lines: Vec<&str> = file.lines().collect();
+    let start = source.start_line.unwrap_or(1).saturating_sub(1).min(lines.len());
+    let end = source.end_line.unwrap_or(lines.len()).clamp(start, lines.len());
+    let candidates = [lines[start..end].join("\n"), file.clone()];
+
+    candidates
+        .into_iter()
+        .map(String::into_bytes)
+        .find(|content| cadi_core::hash::verify_chunk_content(&chunk.chunk_id, content))
+        .ok_or_else(|| anyhow!("{} changed since it was imported", source.file))
+}
+
+fn chunk_path(workspace: &Path, chunk_id: &str) -> PathBuf {
+    workspace.join("chunks").join(format!("{}.bin", chunk_storage_key(chunk_id)))
+}
+
+fn format_size(bytes: usize) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn todo_suite() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../../examples/todo-suite")
+            .canonicalize()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_e2e_round_trip() {
+        let root = todo_suite();
+        let scratch = tempfile::tempdir().unwrap();
+        let workspace = scratch.path().join("workspace");
+
+        let registry = start_registry(&scratch.path().join("registry")).await.unwrap();
+        let client = registry.client().unwrap();
+
+        let (result, stats) = import_example(&root).await.unwrap();
+        assert!(stats.chunks > 0);
+        assert!(!member_chunks(&result, &root, DEMO_MEMBER).is_empty());
+
+        let published = publish_chunks(&client, &result, &workspace).await.unwrap();
+        assert_eq!(published.published, stats.unique_chunks);
+        assert!(!published.aliases.aliases.is_empty());
+
+        // Publishing again only finds chunks the registry already has
+        let again = publish_chunks(&client, &result, &workspace).await.unwrap();
+        assert_eq!(again.published, 0);
+        assert_eq!(again.already_present, stats.unique_chunks);
+
+        // The local chunk copies and aliases.json
+        assert_eq!(wipe_workspace(&workspace).unwrap(), stats.unique_chunks + 1);
+        assert!(!chunk_path(&workspace, &result.chunks[0].chunk_id).exists());
+
+        let fetched = fetch_by_alias(&client, &published.aliases, &workspace).await.unwrap();
+        assert_eq!(fetched.fetched, stats.unique_chunks);
+        assert_eq!(fetched.aliases, fetched.fetched + fetched.deduped);
+
+        let rehydrated = rehydrate(&workspace, &result, &published.aliases, &root).await.unwrap();
+        assert!(rehydrated.view.atoms.len() >= rehydrated.requested);
+        assert!(!rehydrated.view.source.is_empty());
+
+        let built = build_target(&workspace, &result, &root).await.unwrap();
+        assert!(built.failed.is_empty());
+        assert_eq!(built.built.len() + built.cached.len(), rehydrated.requested);
+    }
+}
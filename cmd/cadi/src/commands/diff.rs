@@ -0,0 +1,430 @@
+//! Chunk diff command
+//!
+//! `cadi diff <old> <new>` compares two chunks from the local import, and
+//! `cadi diff --since-last-import <path>` re-analyzes a project and compares
+//! every chunk with the one under the same alias in the last import.
+
+use anyhow::{Context, Result};
+use clap::{Args, FromArgMatches, ValueEnum};
+use console::style;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use cadi_core::chunk_diff::source_content;
+use cadi_core::{
+    chunk_storage_key, short_chunk_id, AliasIndex, AtomicChunk, ChunkDiff, ImportState, ProjectAnalyzer, IMPORT_STATE_FILE,
+};
+
+use super::import_v2::{analyzer_config, ImportArgs};
+use crate::config::CadiConfig;
+use crate::output::say;
+
+/// Arguments for the diff command
+#[derive(Args)]
+pub struct DiffArgs {
+    /// Old chunk: a chunk ID, an ID prefix or an alias
+    #[arg(required_unless_present = "since_last_import", conflicts_with = "since_last_import")]
+    pub old: Option<String>,
+
+    /// New chunk: a chunk ID, an ID prefix or an alias
+    #[arg(required_unless_present = "since_last_import")]
+    pub new: Option<String>,
+
+    /// Re-analyze the project at this path with the import defaults and
+    /// compare its chunks with the last import's, paired by alias
+    #[arg(long, value_name = "PATH")]
+    pub since_last_import: Option<PathBuf>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value = "text")]
+    pub format: DiffFormat,
+
+    /// With --since-last-import, print the text diff of every modified chunk
+    #[arg(short, long)]
+    pub verbose: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DiffFormat {
+    /// Human-readable report
+    Text,
+    /// The structured diff as JSON
+    Json,
+}
+
+/// Chunks of a project paired by alias between two imports
+#[derive(Debug, Default, Serialize)]
+struct ImportDiff {
+    added: Vec<ChunkEntry>,
+    removed: Vec<ChunkEntry>,
+    modified: Vec<ModifiedChunk>,
+    unchanged: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct ChunkEntry {
+    alias: String,
+    chunk_id: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ModifiedChunk {
+    alias: String,
+    #[serde(flatten)]
+    diff: ChunkDiff,
+}
+
+/// Execute the diff command
+pub async fn execute(args: DiffArgs, config: &CadiConfig) -> Result<()> {
+    if let Some(path) = &args.since_last_import {
+        return diff_since_last_import(path, &args, config).await;
+    }
+    let (Some(old), Some(new)) = (&args.old, &args.new) else {
+        anyhow::bail!("Give two chunks to compare, or --since-last-import <path>");
+    };
+
+    let root = std::env::current_dir()?;
+    let chunks = local_chunks(&root, config);
+    let old = resolve(old, &chunks)?;
+    let new = resolve(new, &chunks)?;
+    let diff = old.diff(new, |id| lookup_content(&root, &chunks, id));
+
+    match args.format {
+        DiffFormat::Json => say!("{}", serde_json::to_string_pretty(&diff)?),
+        DiffFormat::Text => say!("{}", render_diff(&diff).trim_end_matches('\n')),
+    }
+    Ok(())
+}
+
+/// Chunks of the last import in `root` and those saved to the chunk cache
+fn local_chunks(root: &Path, config: &CadiConfig) -> Vec<AtomicChunk> {
+    let mut chunks: Vec<AtomicChunk> = read_import_state(root).map(|s| s.chunks).unwrap_or_default();
+
+    // The cache also holds aliases.json and the import summary, which do
+    // not parse as chunks
+    if let Ok(entries) = std::fs::read_dir(config.cache.dir.join("chunks")) {
+        let mut files: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+        files.sort();
+        for file in files.iter().filter(|f| f.extension().is_some_and(|e| e == "json")) {
+            let chunk = std::fs::read_to_string(file)
+                .ok()
+                .and_then(|content| serde_json::from_str::<AtomicChunk>(&content).ok());
+            if let Some(chunk) = chunk {
+                if !chunks.iter().any(|c| c.chunk_id == chunk.chunk_id) {
+                    chunks.push(chunk);
+                }
+            }
+        }
+    }
+    chunks
+}
+
+fn read_import_state(root: &Path) -> Result<ImportState> {
+    let state_path = root.join(IMPORT_STATE_FILE);
+    let content = std::fs::read_to_string(&state_path)
+        .with_context(|| format!("No import state at {}; run `cadi import` first", state_path.display()))?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Chunk named by a full ID, a unique ID prefix or an alias
+fn resolve<'a>(query: &str, chunks: &'a [AtomicChunk]) -> Result<&'a AtomicChunk> {
+    if let Some(chunk) = chunks.iter().find(|c| c.chunk_id == query) {
+        return Ok(chunk);
+    }
+
+    let prefixed: Vec<&AtomicChunk> = chunks
+        .iter()
+        .filter(|c| c.chunk_id.starts_with(query) || chunk_storage_key(&c.chunk_id).starts_with(query))
+        .collect();
+    match prefixed.as_slice() {
+        [chunk] => return Ok(chunk),
+        [] => {}
+        _ => anyhow::bail!("'{}' matches {} chunk IDs; give more of the ID", query, prefixed.len()),
+    }
+
+    let mut index = AliasIndex::new();
+    for chunk in chunks {
+        for alias in &chunk.aliases {
+            index.insert(&alias.full_path(), &chunk.chunk_id);
+        }
+    }
+    if let Some(found) = index.lookup(query) {
+        if let Some(chunk) = chunks.iter().find(|c| c.chunk_id == found.chunk_id) {
+            return Ok(chunk);
+        }
+    }
+
+    let suggestions: Vec<String> = index.suggest(query, 3).into_iter().map(|s| s.alias).collect();
+    if suggestions.is_empty() {
+        anyhow::bail!("No imported chunk matches '{}'", query);
+    }
+    anyhow::bail!("No imported chunk matches '{}'; did you mean {}?", query, suggestions.join(", "))
+}
+
+/// Content of chunk `id`, cut from its source file in the working tree or,
+/// when the file has changed since, from the file at `HEAD`
+fn lookup_content(root: &Path, chunks: &[AtomicChunk], id: &str) -> Option<String> {
+    let chunk = chunks.iter().find(|c| c.chunk_id == id)?;
+    let file = chunk.sources.first()?.path();
+    if let Some(content) = std::fs::read_to_string(root.join(&file))
+        .ok()
+        .and_then(|text| source_content(chunk, &text))
+    {
+        return Some(content);
+    }
+
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["show", &format!("HEAD:./{}", file.strip_prefix(root).unwrap_or(&file).display())])
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    source_content(chunk, &String::from_utf8_lossy(&output.stdout))
+}
+
+/// Re-analyze the project at `path` and compare it with its last import,
+/// leaving the import state as it was
+async fn diff_since_last_import(path: &Path, args: &DiffArgs, config: &CadiConfig) -> Result<()> {
+    let root = path.canonicalize().context("Failed to resolve project path")?;
+    let previous = read_import_state(&root)?;
+
+    let matches = ImportArgs::augment_args(clap::Command::new("import"))
+        .try_get_matches_from([std::ffi::OsStr::new("import"), root.as_os_str()])?;
+    let import_args = ImportArgs::from_arg_matches(&matches)?;
+    let mut analyzer_config = analyzer_config(&import_args, &root, config)?;
+    analyzer_config.incremental = false;
+
+    let result = tokio::task::spawn_blocking({
+        let root = root.clone();
+        move || ProjectAnalyzer::new(analyzer_config).import_project(&root)
+    })
+    .await
+    .context("Import task failed")?
+    .context("Failed to analyze project")?;
+
+    let mut chunks = previous.chunks.clone();
+    chunks.extend(result.chunks.iter().cloned());
+    let diff = pair_chunks(&previous.chunks, &result.chunks, |id| lookup_content(&root, &chunks, id));
+
+    match args.format {
+        DiffFormat::Json => say!("{}", serde_json::to_string_pretty(&diff)?),
+        DiffFormat::Text => say!("{}", render_import_diff(&diff, args.verbose).trim_end_matches('\n')),
+    }
+    Ok(())
+}
+
+/// Pair chunks by primary alias, falling back to the chunk name
+fn pair_chunks(old: &[AtomicChunk], new: &[AtomicChunk], content: impl Fn(&str) -> Option<String>) -> ImportDiff {
+    let by_alias = |chunks: &[AtomicChunk]| -> BTreeMap<String, usize> {
+        chunks.iter().enumerate().map(|(i, c)| (c.display_name(), i)).collect()
+    };
+    let (old_aliases, new_aliases) = (by_alias(old), by_alias(new));
+    let entry = |alias: &str, chunk: &AtomicChunk| ChunkEntry {
+        alias: alias.to_string(),
+        chunk_id: chunk.chunk_id.clone(),
+    };
+
+    let mut diff = ImportDiff::default();
+    for (alias, &i) in &old_aliases {
+        match new_aliases.get(alias) {
+            None => diff.removed.push(entry(alias, &old[i])),
+            Some(&j) if old[i].chunk_id == new[j].chunk_id => diff.unchanged += 1,
+            Some(&j) => diff.modified.push(ModifiedChunk {
+                alias: alias.clone(),
+                diff: old[i].diff(&new[j], &content),
+            }),
+        }
+    }
+    for (alias, &j) in &new_aliases {
+        if !old_aliases.contains_key(alias) {
+            diff.added.push(entry(alias, &new[j]));
+        }
+    }
+    diff
+}
+
+fn render_diff(diff: &ChunkDiff) -> String {
+    let mut out = format!("  {} → {}\n", style(&diff.old_id).dim(), style(&diff.new_id).bold());
+    if diff.is_identical() {
+        out.push_str(&format!("  {} Same chunk\n", style("✓").green()));
+        return out;
+    }
+
+    let list = |out: &mut String, label: &str, sign: &str, items: &[String]| {
+        for item in items {
+            out.push_str(&format!("  {:<10}{} {}\n", label, sign, item));
+        }
+    };
+    list(&mut out, "provides", "+", &diff.provides_added);
+    list(&mut out, "provides", "-", &diff.provides_removed);
+    list(&mut out, "requires", "+", &diff.requires_added);
+    list(&mut out, "requires", "-", &diff.requires_removed);
+    for metric in &diff.metrics {
+        out.push_str(&format!(
+            "  {:<20}{} → {} ({:+})\n",
+            metric.metric,
+            metric.old,
+            metric.new,
+            metric.delta()
+        ));
+    }
+    let range = |r: Option<cadi_core::LineRange>| {
+        r.map(|r| format!("{}-{}", r.start, r.end)).unwrap_or_else(|| "-".to_string())
+    };
+    for source in &diff.sources {
+        out.push_str(&format!("  {}  lines {} → {}\n", source.file, range(source.old), range(source.new)));
+    }
+
+    match &diff.text_diff {
+        Some(text) => {
+            out.push('\n');
+            out.push_str(&colorize(text));
+        }
+        None => out.push_str(&format!(
+            "  {} Content of one side was not found; no text diff\n",
+            style("!").yellow()
+        )),
+    }
+    out
+}
+
+fn colorize(text_diff: &str) -> String {
+    text_diff
+        .lines()
+        .map(|line| {
+            let styled = if line.starts_with("@@") {
+                style(line).cyan().to_string()
+            } else if line.starts_with('+') && !line.starts_with("+++") {
+                style(line).green().to_string()
+            } else if line.starts_with('-') && !line.starts_with("---") {
+                style(line).red().to_string()
+            } else {
+                line.to_string()
+            };
+            format!("{}\n", styled)
+        })
+        .collect()
+}
+
+fn render_import_diff(diff: &ImportDiff, verbose: bool) -> String {
+    let mut rows: Vec<[String; 4]> = Vec::new();
+    for chunk in &diff.modified {
+        let d = &chunk.diff;
+        let interface = [
+            ("+provides", &d.provides_added),
+            ("-provides", &d.provides_removed),
+            ("+requires", &d.requires_added),
+            ("-requires", &d.requires_removed),
+        ]
+        .iter()
+        .filter(|(_, items)| !items.is_empty())
+        .map(|(label, items)| format!("{} {}", label, items.join(",")))
+        .collect::<Vec<_>>()
+        .join(" ");
+        let lines = if d.text_diff.is_some() {
+            format!("+{} -{}", d.lines_added, d.lines_removed)
+        } else {
+            "?".to_string()
+        };
+        rows.push(["modified".into(), chunk.alias.clone(), lines, interface]);
+    }
+    for chunk in &diff.added {
+        rows.push(["added".into(), chunk.alias.clone(), String::new(), short_chunk_id(&chunk.chunk_id)]);
+    }
+    for chunk in &diff.removed {
+        rows.push(["removed".into(), chunk.alias.clone(), String::new(), short_chunk_id(&chunk.chunk_id)]);
+    }
+
+    let mut out = String::new();
+    if !rows.is_empty() {
+        let header = ["STATUS", "ALIAS", "LINES", "DETAILS"].map(String::from);
+        let mut widths = [0usize; 4];
+        for row in std::iter::once(&header).chain(&rows) {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let line = |row: &[String; 4]| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            cells.join("  ").trim_end().to_string()
+        };
+        out.push_str(&format!("  {}\n", style(line(&header)).bold()));
+        for row in &rows {
+            out.push_str(&format!("  {}\n", line(row)));
+        }
+        out.push('\n');
+    }
+    out.push_str(&format!(
+        "  {} modified, {} added, {} removed, {} unchanged\n",
+        diff.modified.len(),
+        diff.added.len(),
+        diff.removed.len(),
+        diff.unchanged
+    ));
+
+    if verbose {
+        for chunk in &diff.modified {
+            out.push_str(&format!("\n  {}\n", style(&chunk.alias).bold()));
+            out.push_str(&render_diff(&chunk.diff));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cadi_core::chunk_id_from_content;
+
+    fn chunk(alias: &str, content: &str) -> AtomicChunk {
+        let id = chunk_id_from_content(content.as_bytes());
+        AtomicChunk::new(id.clone(), alias.into(), "rust".into(), id, content.len()).with_alias(alias)
+    }
+
+    #[test]
+    fn test_chunks_are_paired_by_alias() {
+        let old = [chunk("app/a", "fn a() {}"), chunk("app/b", "fn b() {}"), chunk("app/c", "fn c() {}")];
+        let new = [chunk("app/a", "fn a() {}"), chunk("app/b", "fn b() { 1 }"), chunk("app/d", "fn d() {}")];
+        let content = |id: &str| {
+            ["fn b() {}", "fn b() { 1 }"]
+                .into_iter()
+                .find(|c| chunk_id_from_content(c.as_bytes()) == id)
+                .map(String::from)
+        };
+
+        let diff = pair_chunks(&old, &new, content);
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(diff.modified.len(), 1);
+        assert_eq!(diff.modified[0].alias, "app/b");
+        assert_eq!((diff.modified[0].diff.lines_added, diff.modified[0].diff.lines_removed), (1, 1));
+        assert_eq!(diff.added[0].alias, "app/d");
+        assert_eq!(diff.removed[0].alias, "app/c");
+
+        let json = serde_json::to_value(&diff).unwrap();
+        assert_eq!(json["modified"][0]["alias"], "app/b");
+        assert_eq!(json["modified"][0]["old_id"], old[1].chunk_id.as_str());
+
+        let table = render_import_diff(&diff, false);
+        assert!(table.contains("1 modified, 1 added, 1 removed, 1 unchanged"), "{}", table);
+    }
+
+    #[test]
+    fn test_resolve_by_id_prefix_or_alias() {
+        let chunks = [chunk("app/parse", "fn parse() {}"), chunk("app/print", "fn print() {}")];
+        let id = &chunks[1].chunk_id;
+        assert_eq!(resolve(id, &chunks).unwrap().chunk_id, *id);
+        assert_eq!(resolve(&chunk_storage_key(id)[..16], &chunks).unwrap().chunk_id, *id);
+        assert_eq!(resolve("parse", &chunks).unwrap().chunk_id, chunks[0].chunk_id);
+        let err = resolve("prase", &chunks).unwrap_err().to_string();
+        assert!(err.contains("did you mean app/parse"), "{}", err);
+    }
+}
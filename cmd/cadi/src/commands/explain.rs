@@ -0,0 +1,148 @@
+use anyhow::Result;
+use cadi_builder::{BuildCache, Provenance};
+use cadi_registry::{ChunkUsageStats, RegistryClient, RegistryConfig};
+use clap::Args;
+use console::style;
+
+use crate::config::CadiConfig;
+use crate::output::say;
+
+/// Arguments for the explain command
+#[derive(Args)]
+pub struct ExplainArgs {
+    /// Chunk ID to explain
+    #[arg(required = true)]
+    chunk: String,
+
+    /// How many builds back to follow the lineage
+    #[arg(long, default_value = "10")]
+    depth: usize,
+
+    /// Output format (text, json)
+    #[arg(long, default_value = "text")]
+    format: String,
+
+    /// Registry to read usage statistics from
+    #[arg(short, long)]
+    registry: Option<String>,
+
+    /// Don't ask the registry how often the chunk is used
+    #[arg(long)]
+    no_usage: bool,
+}
+
+/// Execute the explain command
+pub async fn execute(args: ExplainArgs, config: &CadiConfig) -> Result<()> {
+    let cache = BuildCache::new(config.cache.dir.clone());
+    let provenance = Provenance::trace(&cache, &args.chunk, args.depth)?;
+    let usage = if args.no_usage {
+        None
+    } else {
+        let registry = args.registry.as_deref().unwrap_or(&config.registry.url);
+        Some(fetch_usage(registry, &args.chunk).await)
+    };
+
+    if args.format == "json" {
+        let mut value = serde_json::to_value(&provenance)?;
+        if let (Some(Ok(Some(stats))), Some(fields)) = (&usage, value.as_object_mut()) {
+            fields.insert("usage".to_string(), serde_json::to_value(stats)?);
+        }
+        say!("{}", serde_json::to_string_pretty(&value)?);
+        return Ok(());
+    }
+
+    say!("{}", style("Lineage").bold());
+    say!();
+    print_provenance(&provenance, None, "", "");
+    if !provenance.is_built() {
+        say!();
+        say!("  {} No build receipt: the chunk was not built with this cache", style("○").dim());
+    }
+
+    if let Some(usage) = usage {
+        say!();
+        say!("{}", style("Usage").bold());
+        say!();
+        match usage {
+            Ok(Some(stats)) => print_usage(&stats),
+            Ok(None) => say!("  {} Not on the registry", style("○").dim()),
+            Err(e) => say!("  {} Usage unavailable: {}", style("⚠").yellow(), e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Usage statistics of a chunk, without retrying an unreachable registry
+async fn fetch_usage(registry: &str, chunk_id: &str) -> Result<Option<ChunkUsageStats>> {
+    let client = RegistryClient::new(RegistryConfig {
+        url: registry.to_string(),
+        timeout: std::time::Duration::from_secs(5),
+        max_retries: 0,
+        ..Default::default()
+    })?;
+    Ok(client.fetch_usage_stats(chunk_id).await?)
+}
+
+/// Print the usage count and the uses over the last 7, 30 and 90 days
+fn print_usage(stats: &ChunkUsageStats) {
+    let today = chrono::Utc::now().date_naive();
+    let since = |days: i64| {
+        let first = today - chrono::Duration::days(days - 1);
+        stats
+            .daily
+            .iter()
+            .filter(|d| chrono::NaiveDate::parse_from_str(&d.day, "%Y-%m-%d").is_ok_and(|day| day >= first))
+            .map(|d| d.count)
+            .sum::<u64>()
+    };
+
+    say!("  {} uses in total", style(stats.usage_count).cyan());
+    say!("  last 7 days: {} · last 30 days: {} · last 90 days: {}", since(7), since(30), since(90));
+    if let Some(last_used_at) = &stats.last_used_at {
+        say!("  last used at {}", last_used_at);
+    }
+}
+
+/// Print a chunk's build and, below it, the chunks it was built from
+fn print_provenance(node: &Provenance, role: Option<&str>, first: &str, rest: &str) {
+    let role = role.map(|role| format!("{} ", style(role).dim())).unwrap_or_default();
+    say!("{}{}{}", first, role, style(&node.chunk_id).cyan());
+
+    match (&node.receipt_id, &node.receipt) {
+        (Some(receipt_id), Some(receipt)) => {
+            say!("{}  built by step {} with {}", rest, style(&receipt.step).yellow(), receipt.transform);
+            say!("{}  at {} (receipt {})", rest, receipt.finished_at, receipt_id);
+            if !receipt.toolchain.is_empty() {
+                let tools: Vec<String> = receipt
+                    .toolchain
+                    .iter()
+                    .map(|(tool, version)| format!("{} = {}", tool, version))
+                    .collect();
+                say!("{}  toolchain: {}", rest, tools.join("; "));
+            }
+        }
+        (Some(receipt_id), None) => {
+            say!("{}  {} build receipt {} is missing", rest, style("⚠").yellow(), receipt_id);
+        }
+        _ => {}
+    }
+
+    // Roles come from the receipt's inputs, matched by chunk ID
+    let roles = |chunk_id: &str| {
+        node.receipt
+            .as_ref()
+            .and_then(|r| r.inputs.iter().find(|i| i.chunk_id == chunk_id))
+            .map(|i| i.role.clone())
+    };
+    for (i, parent) in node.parents.iter().enumerate() {
+        let last = i + 1 == node.parents.len();
+        let (branch, indent) = if last { ("└─ ", "   ") } else { ("├─ ", "│  ") };
+        print_provenance(
+            parent,
+            roles(&parent.chunk_id).as_deref(),
+            &format!("{}{}", rest, branch),
+            &format!("{}{}", rest, indent),
+        );
+    }
+}
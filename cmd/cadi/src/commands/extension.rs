@@ -0,0 +1,149 @@
+use anyhow::{anyhow, Result};
+use cadi_core::trust::{SigningKey, TrustStore, TrustVerdict};
+use cadi_extensions::signing::{sign_extension, verify_extension, MANIFEST_FILE};
+use cadi_extensions::{ExtensionLoader, ExtensionManifest, ExtensionPermissions};
+use clap::{Args, Subcommand};
+use console::style;
+use std::path::PathBuf;
+
+use crate::config::CadiConfig;
+use crate::output::say;
+
+/// Arguments for the extension command
+#[derive(Args)]
+pub struct ExtensionArgs {
+    #[command(subcommand)]
+    command: ExtensionCommands,
+}
+
+#[derive(Subcommand)]
+enum ExtensionCommands {
+    /// Load installed extensions and show what they were granted
+    List {
+        /// Additional directory to search for extensions
+        #[arg(long)]
+        dir: Option<PathBuf>,
+
+        /// Load extensions that are unsigned or signed by untrusted keys
+        #[arg(long)]
+        allow_unsigned: bool,
+    },
+
+    /// Sign an extension's manifest and code
+    Sign {
+        /// Extension directory (containing extension.toml)
+        dir: PathBuf,
+
+        /// Signing key (defaults to the configured key)
+        #[arg(long)]
+        key: Option<PathBuf>,
+    },
+
+    /// Check an extension's signatures against the trust store
+    Verify {
+        /// Extension directory (containing extension.toml)
+        dir: PathBuf,
+    },
+}
+
+/// Execute the extension command
+pub async fn execute(args: ExtensionArgs, config: &CadiConfig) -> Result<()> {
+    let trust_store = TrustStore::open(&config.security.trust_dir);
+
+    match args.command {
+        ExtensionCommands::List { dir, allow_unsigned } => {
+            let mut loader = ExtensionLoader::new()
+                .with_trust_store(trust_store)
+                .allow_unsigned(allow_unsigned);
+            if let Some(dir) = dir {
+                loader = loader.with_search_path(dir);
+            }
+            loader.load_all().await?;
+
+            say!("{}", style("Extensions").bold());
+            say!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            say!();
+
+            let extensions = loader.get_all_extensions();
+            if extensions.is_empty() {
+                say!("  {} No extensions installed", style("○").dim());
+            }
+            for extension in extensions {
+                let signed = if extension.signers.is_empty() {
+                    style("unsigned".to_string()).yellow()
+                } else {
+                    style(format!("signed by {}", extension.signers.join(", "))).green()
+                };
+                say!(
+                    "  {} {} {} ({}, {})",
+                    style("●").green(),
+                    extension.metadata.name,
+                    extension.metadata.version,
+                    extension.metadata.extension_type,
+                    signed
+                );
+                say!("    Permissions: {}", describe_permissions(&extension.permissions));
+            }
+
+            loader.shutdown().await?;
+        }
+
+        ExtensionCommands::Sign { dir, key } => {
+            let key_path = key
+                .or_else(|| config.security.signing_key.clone())
+                .ok_or_else(|| anyhow!("No signing key configured; pass --key"))?;
+            let signing_key = SigningKey::load(&key_path)?;
+
+            sign_extension(&dir, &signing_key)?;
+            say!("{} Signed {}", style("✓").green(), dir.display());
+            say!("  Key: {}", signing_key.public_key());
+        }
+
+        ExtensionCommands::Verify { dir } => {
+            let manifest = ExtensionManifest::from_file(dir.join(MANIFEST_FILE))?;
+            match verify_extension(&dir, &manifest, &trust_store)? {
+                TrustVerdict::SignedBy(signers) => {
+                    say!("{} Signed by {}", style("✓").green(), signers.join(", "));
+                }
+                TrustVerdict::Unsigned => {
+                    say!("{} {} is not signed", style("⚠").yellow(), manifest.extension.name);
+                }
+                TrustVerdict::UntrustedSigner => {
+                    say!(
+                        "{} {} is not signed by a trusted signer",
+                        style("⚠").yellow(),
+                        manifest.extension.name
+                    );
+                }
+                TrustVerdict::Invalid => {
+                    anyhow::bail!(
+                        "{} does not match its signature; it was changed after signing",
+                        manifest.extension.name
+                    );
+                }
+            }
+            say!("  Permissions: {}", describe_permissions(&manifest.permissions));
+        }
+    }
+
+    Ok(())
+}
+
+fn describe_permissions(permissions: &ExtensionPermissions) -> String {
+    let mut granted = Vec::new();
+    for path in &permissions.filesystem_read {
+        granted.push(format!("read {}", path.display()));
+    }
+    if permissions.network {
+        granted.push("network".to_string());
+    }
+    if permissions.registry_write {
+        granted.push("registry write".to_string());
+    }
+
+    if granted.is_empty() {
+        "none".to_string()
+    } else {
+        granted.join(", ")
+    }
+}
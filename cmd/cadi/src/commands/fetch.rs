@@ -1,9 +1,18 @@
 use anyhow::Result;
 use clap::Args;
 use console::style;
+use cadi_core::{chunk_storage_key, short_chunk_id};
+use cadi_core::local_cache::AccessTimes;
 use cadi_registry::client::{RegistryClient, RegistryConfig};
+use cadi_registry::federation::FederationManager;
+use cadi_registry::RegistryError;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Duration;
 
 use crate::config::CadiConfig;
+use crate::error::CommandError;
+use crate::output::say;
 
 /// Arguments for the fetch command
 #[derive(Args)]
@@ -23,23 +32,105 @@ pub struct FetchArgs {
     /// Skip signature verification
     #[arg(long)]
     no_verify: bool,
+
+    /// Fetch only the target, not the chunks it is composed of
+    #[arg(long)]
+    no_deps: bool,
+
+    /// How many levels of referenced chunks to fetch (default: unlimited)
+    #[arg(long)]
+    depth: Option<usize>,
+}
+
+/// How often a fetch is retried after the registry rate limits it
+const RATE_LIMIT_RETRIES: u32 = 3;
+
+/// Where a chunk came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FetchStatus {
+    Fetched,
+    Cached,
+}
+
+/// Chunks a fetch left in the local cache
+#[derive(Debug, Default, Serialize)]
+pub struct FetchReport {
+    /// Downloaded from a registry
+    pub fetched: Vec<String>,
+    /// Already in the cache
+    pub cached: Vec<String>,
+}
+
+impl FetchReport {
+    fn record(&mut self, chunk_id: &str, status: FetchStatus) {
+        if self.fetched.iter().chain(&self.cached).any(|id| id == chunk_id) {
+            return;
+        }
+        match status {
+            FetchStatus::Fetched => self.fetched.push(chunk_id.to_string()),
+            FetchStatus::Cached => self.cached.push(chunk_id.to_string()),
+        }
+    }
+}
+
+/// Registry access shared by every chunk of one fetch
+struct Fetcher<'a> {
+    client: RegistryClient,
+    registry_url: String,
+    federation: &'a FederationManager,
+    /// Root of the local cache; chunks live in its `chunks` directory
+    cache_dir: std::path::PathBuf,
+    fetch_signatures: bool,
 }
 
 /// Execute the fetch command
-pub async fn execute(args: FetchArgs, config: &CadiConfig) -> Result<()> {
+pub async fn execute(args: FetchArgs, config: &CadiConfig) -> Result<FetchReport> {
     let registry = args.registry.as_ref()
         .unwrap_or(&config.registry.url);
 
-    println!("{}", style("Fetching from registry...").bold());
-    println!("  Registry: {}", registry);
-    println!("  Target: {}", args.target);
-    println!("  Tier: {}", args.tier);
+    say!("{}", style("Fetching from registry...").bold());
+    say!("  Registry: {}", registry);
+    say!("  Target: {}", args.target);
+    say!("  Tier: {}", args.tier);
+
+    // Use the federated registries unless a registry was given explicitly
+    let http_cache_dir = config.cache.dir.join("http-cache");
+    let federation = if args.registry.is_none() {
+        FederationManager::from_default_config()
+    } else {
+        FederationManager::new()
+    }
+    .with_cache_dir(&http_cache_dir);
+    if !federation.registries().is_empty() {
+        let healthy = federation.check_health().await;
+        say!("  Federated: {}/{} registries healthy", healthy, federation.registries().len());
+    }
+
+    let reg_config = RegistryConfig {
+        url: registry.clone(),
+        token: config.auth.token.clone(),
+        timeout: std::time::Duration::from_secs(30),
+        verify_tls: true,
+        max_concurrent: 4,
+        cache_dir: Some(http_cache_dir),
+        ..Default::default()
+    };
+    let fetcher = Fetcher {
+        client: RegistryClient::new(reg_config)
+            .map_err(|e| anyhow::anyhow!("Failed to create registry client: {}", e))?,
+        registry_url: registry.clone(),
+        federation: &federation,
+        cache_dir: config.cache.dir.clone(),
+        fetch_signatures: !args.no_verify,
+    };
+    let depth = if args.no_deps { Some(0) } else { args.depth };
+    let mut report = FetchReport::default();
 
     // Check if target is a chunk ID or manifest
     let is_chunk = args.target.starts_with("chunk:");
 
     if is_chunk {
-        fetch_chunk(&args.target, &args.tier, config, !args.no_verify).await?;
+        fetch_with_deps(&args.target, depth, &fetcher, &mut report).await?;
     } else {
         // Treat as manifest path
         let manifest_content = std::fs::read_to_string(&args.target)?;
@@ -49,78 +140,222 @@ pub async fn execute(args: FetchArgs, config: &CadiConfig) -> Result<()> {
         if let Some(nodes) = manifest["build_graph"]["nodes"].as_array() {
             for node in nodes {
                 if let Some(source_cadi) = node["source_cadi"].as_str() {
-                    fetch_chunk(source_cadi, &args.tier, config, !args.no_verify).await?;
+                    fetch_with_deps(source_cadi, depth, &fetcher, &mut report).await?;
                 }
                 if let Some(ir_cadi) = node["ir_cadi"].as_str() {
                     if args.tier == "ir" || args.tier == "all" {
-                        fetch_chunk(ir_cadi, &args.tier, config, !args.no_verify).await?;
+                        fetch_with_deps(ir_cadi, depth, &fetcher, &mut report).await?;
                     }
                 }
                 if let Some(blob_cadi) = node["blob_cadi"].as_str() {
                     if args.tier == "blob" || args.tier == "all" {
-                        fetch_chunk(blob_cadi, &args.tier, config, !args.no_verify).await?;
+                        fetch_with_deps(blob_cadi, depth, &fetcher, &mut report).await?;
                     }
                 }
             }
         }
     }
 
-    println!();
-    println!("{}", style("Fetch complete!").green().bold());
+    say!();
+    say!("{}", style("Fetch complete!").green().bold());
+
+    Ok(report)
+}
+
+/// Fetch a chunk and, breadth-first, the chunks it references, up to
+/// `depth` levels below it. Each chunk is fetched at most once, so cyclic
+/// references terminate. Prints a tree of what was fetched.
+async fn fetch_with_deps(
+    root: &str,
+    depth: Option<usize>,
+    fetcher: &Fetcher<'_>,
+    report: &mut FetchReport,
+) -> Result<()> {
+    let mut seen = HashSet::from([root.to_string()]);
+    let mut queue = VecDeque::from([(root.to_string(), 0)]);
+    let mut status: HashMap<String, FetchStatus> = HashMap::new();
+    let mut children: HashMap<String, Vec<String>> = HashMap::new();
+
+    while let Some((chunk_id, level)) = queue.pop_front() {
+        let (data, fetch_status) = fetch_chunk(&chunk_id, fetcher).await?;
+        status.insert(chunk_id.clone(), fetch_status);
+        report.record(&chunk_id, fetch_status);
+
+        if depth.is_some_and(|max| level >= max) {
+            continue;
+        }
+        for dep in cadi_core::chunk_references(&data) {
+            if seen.insert(dep.clone()) {
+                children.entry(chunk_id.clone()).or_default().push(dep.clone());
+                queue.push_back((dep, level + 1));
+            }
+        }
+    }
+
+    if status.len() > 1 {
+        say!();
+        say!("{}", render_tree(root, &children, &status).trim_end_matches('\n'));
+        let fetched = status.values().filter(|s| **s == FetchStatus::Fetched).count();
+        say!(
+            "  {} fetched, {} already present",
+            fetched,
+            status.len() - fetched
+        );
+    }
 
     Ok(())
 }
 
-async fn fetch_chunk(chunk_id: &str, _tier: &str, config: &CadiConfig, _verify: bool) -> Result<()> {
-    // Initialize registry client
-    let registry_url = config.registry.url.clone();
-    let reg_config = RegistryConfig {
-        url: registry_url.clone(),
-        token: config.auth.token.clone(),
-        timeout: std::time::Duration::from_secs(30),
-        verify_tls: true,
-        max_concurrent: 4,
-    };
-    
-    let client = RegistryClient::new(reg_config)
-        .map_err(|e| anyhow::anyhow!("Failed to create registry client: {}", e))?;
+/// Render the fetched chunks as a tree rooted at `root`
+fn render_tree(
+    root: &str,
+    children: &HashMap<String, Vec<String>>,
+    status: &HashMap<String, FetchStatus>,
+) -> String {
+    fn walk(
+        chunk_id: &str,
+        prefix: &str,
+        connector: &str,
+        children: &HashMap<String, Vec<String>>,
+        status: &HashMap<String, FetchStatus>,
+        out: &mut String,
+    ) {
+        let label = match status.get(chunk_id) {
+            Some(FetchStatus::Fetched) => "fetched",
+            Some(FetchStatus::Cached) => "present",
+            None => "skipped",
+        };
+        out.push_str(&format!("{}{}{} ({})\n", prefix, connector, short_chunk_id(chunk_id), label));
+
+        let child_prefix = match connector {
+            "" => prefix.to_string(),
+            "└── " => format!("{}    ", prefix),
+            _ => format!("{}│   ", prefix),
+        };
+        let deps = children.get(chunk_id).map(Vec::as_slice).unwrap_or_default();
+        for (i, dep) in deps.iter().enumerate() {
+            let connector = if i + 1 == deps.len() { "└── " } else { "├── " };
+            walk(dep, &child_prefix, connector, children, status, out);
+        }
+    }
 
+    let mut out = String::new();
+    walk(root, "  ", "", children, status, &mut out);
+    out
+}
+
+/// Fetch one chunk into the local cache, or read it from there. Fetched
+/// content must hash to the chunk ID; on a mismatch nothing is written.
+async fn fetch_chunk(chunk_id: &str, fetcher: &Fetcher<'_>) -> Result<(Vec<u8>, FetchStatus)> {
     // Check if chunk already exists locally
-    let cache_dir = config.cache.dir.join("chunks");
-    let hash = chunk_id.strip_prefix("chunk:sha256:").unwrap_or(chunk_id);
-    let chunk_file = cache_dir.join(format!("{}.bin", hash));
+    let cache_dir = fetcher.cache_dir.join("chunks");
+    let key = chunk_storage_key(chunk_id);
+    let chunk_file = cache_dir.join(format!("{}.bin", key));
 
     if chunk_file.exists() {
-        println!("  {} {} (cached)", style("✓").green(), &chunk_id[..40.min(chunk_id.len())]);
-        return Ok(());
+        say!("  {} {} (cached)", style("✓").green(), short_chunk_id(chunk_id));
+        AccessTimes::record(&fetcher.cache_dir, chunk_id);
+        return Ok((std::fs::read(&chunk_file)?, FetchStatus::Cached));
     }
 
-    println!("  {} Fetching {}...", style("→").cyan(), &chunk_id[..40.min(chunk_id.len())]);
+    say!("  {} Fetching {}...", style("→").cyan(), short_chunk_id(chunk_id));
+
+    // Fetch from registry, waiting out rate limits the client gave up on
+    let federation = fetcher.federation;
+    let mut attempt = 0;
+    let fetched = loop {
+        let fetched = if federation.registries().is_empty() {
+            fetcher.client.fetch_chunk_cached(chunk_id).await.map(|data| (data, fetcher.registry_url.clone()))
+        } else {
+            federation.fetch_chunk(chunk_id).await
+        };
+        match fetched {
+            Err(RegistryError::RateLimited { retry_after }) if attempt < RATE_LIMIT_RETRIES => {
+                let wait = retry_after.unwrap_or(Duration::from_secs(1 << attempt));
+                say!("  {} Rate limited, retrying in {}s", style("→").yellow(), wait.as_secs());
+                tokio::time::sleep(wait).await;
+                attempt += 1;
+            }
+            fetched => break fetched,
+        }
+    };
+
+    match fetched {
+        Ok((data, source)) => {
+            if !cadi_core::hash::verify_chunk_content(chunk_id, &data) {
+                eprintln!("  {} Hash mismatch for {} from {}", style("✗").red(), chunk_id, source);
+                return Err(CommandError::Validation(format!(
+                    "Integrity check failed: content of {} does not match its hash",
+                    chunk_id
+                ))
+                .into());
+            }
 
-    // Fetch from registry
-    match client.fetch_chunk(chunk_id).await {
-        Ok(data) => {
             // Save to local cache
             std::fs::create_dir_all(&cache_dir)?;
             std::fs::write(&chunk_file, &data)?;
             
+            // Signatures are best effort; `cadi verify` reports unsigned chunks
+            let source_client = federation.client(&source).unwrap_or(&fetcher.client);
+            let signatures = if fetcher.fetch_signatures {
+                source_client.fetch_signatures(chunk_id).await.unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+
             // Also save metadata
-            let meta_file = cache_dir.join(format!("{}.json", hash));
+            let meta_file = cache_dir.join(format!("{}.json", key));
             let metadata = serde_json::json!({
                 "chunk_id": chunk_id,
                 "size": data.len(),
                 "fetched_at": chrono::Utc::now().to_rfc3339(),
-                "registry": registry_url
+                "registry": source,
+                "signatures": signatures
             });
             std::fs::write(&meta_file, serde_json::to_string_pretty(&metadata)?)?;
+            AccessTimes::record(&fetcher.cache_dir, chunk_id);
             
-            println!("  {} {} fetched ({} bytes)", style("✓").green(), &chunk_id[..40.min(chunk_id.len())], data.len());
+            say!("  {} {} fetched ({} bytes)", style("✓").green(), short_chunk_id(chunk_id), data.len());
+            Ok((data, FetchStatus::Fetched))
+        }
+        Err(RegistryError::NotFound(_)) => {
+            eprintln!("  {} {} is not on the registry", style("✗").red(), short_chunk_id(chunk_id));
+            Err(CommandError::NotFound(format!("Fetch failed: chunk {} not found", chunk_id)).into())
+        }
+        Err(RegistryError::Unauthorized(message)) => {
+            eprintln!("  {} Registry refused the credentials: {}", style("✗").red(), message);
+            Err(CommandError::Network(format!(
+                "Fetch failed: not authorized to read {}; set auth.token in the CADI config",
+                chunk_id
+            ))
+            .into())
         }
         Err(e) => {
             eprintln!("  {} Failed to fetch: {}", style("✗").red(), e);
-            return Err(anyhow::anyhow!("Fetch failed: {}", e));
+            Err(anyhow::Error::new(e).context("Fetch failed"))
         }
     }
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_tree() {
+        let children = HashMap::from([
+            ("a".to_string(), vec!["b".to_string(), "c".to_string()]),
+            ("b".to_string(), vec!["d".to_string()]),
+        ]);
+        let status = HashMap::from([
+            ("a".to_string(), FetchStatus::Fetched),
+            ("b".to_string(), FetchStatus::Cached),
+            ("c".to_string(), FetchStatus::Fetched),
+            ("d".to_string(), FetchStatus::Fetched),
+        ]);
+        assert_eq!(
+            render_tree("a", &children, &status),
+            "  a (fetched)\n  ├── b (present)\n  │   └── d (fetched)\n  └── c (fetched)\n"
+        );
+    }
 }
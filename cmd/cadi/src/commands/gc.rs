@@ -1,12 +1,19 @@
-use anyhow::Result;
-use clap::Args;
+use anyhow::{anyhow, Result};
+use cadi_core::graph::GraphStore;
+use cadi_core::local_cache::{GcPolicy, LocalCache, PinSet};
+use cadi_core::AliasRegistry;
+use clap::{Args, Subcommand};
 use console::style;
 
 use crate::config::CadiConfig;
+use crate::output::say;
 
 /// Arguments for the gc command
 #[derive(Args)]
 pub struct GcArgs {
+    #[command(subcommand)]
+    command: Option<GcCommands>,
+
     /// Show status without cleaning
     #[arg(long)]
     status: bool,
@@ -19,125 +26,249 @@ pub struct GcArgs {
     #[arg(long)]
     aggressive: bool,
 
-    /// Pin a chunk (prevent GC)
+    /// Remove least-recently-used chunks until the cache fits this size
+    /// (e.g. 500MB, 2GB; default: the configured cache size)
+    #[arg(long, value_parser = parse_size)]
+    max_cache_size: Option<u64>,
+
+    /// Drop content blobs in the graph store that no chunk references
     #[arg(long)]
-    pin: Option<String>,
+    graph: bool,
+}
 
+#[derive(Subcommand)]
+enum GcCommands {
+    /// Pin a chunk so gc keeps it and the chunks it is composed of
+    Pin {
+        /// Chunk ID or alias
+        target: String,
+    },
     /// Unpin a chunk
-    #[arg(long)]
-    unpin: Option<String>,
+    Unpin {
+        /// Chunk ID or alias
+        target: String,
+    },
+    /// List pinned chunks
+    Pins,
 }
 
 /// Execute the gc command
 pub async fn execute(args: GcArgs, config: &CadiConfig) -> Result<()> {
-    // Handle pin/unpin
-    if let Some(chunk_id) = args.pin {
-        println!("{} Pinned chunk: {}", style("✓").green(), &chunk_id[..40.min(chunk_id.len())]);
+    let cache_dir = &config.cache.dir;
+    // Pins belong to the project in the current directory
+    let project_root = std::env::current_dir()?;
+
+    if let Some(command) = args.command {
+        let mut pins = PinSet::load(&project_root)?;
+        match command {
+            GcCommands::Pin { target } => {
+                let chunk_id = resolve_target(&target, cache_dir)?;
+                if pins.pin(&chunk_id) {
+                    pins.save(&project_root)?;
+                    say!("{} Pinned chunk: {}", style("✓").green(), &chunk_id[..40.min(chunk_id.len())]);
+                } else {
+                    say!("Already pinned: {}", &chunk_id[..40.min(chunk_id.len())]);
+                }
+            }
+            GcCommands::Unpin { target } => {
+                let chunk_id = resolve_target(&target, cache_dir)?;
+                if !pins.unpin(&chunk_id) {
+                    return Err(anyhow!("Not pinned: {}", target));
+                }
+                pins.save(&project_root)?;
+                say!("{} Unpinned chunk: {}", style("✓").green(), &chunk_id[..40.min(chunk_id.len())]);
+            }
+            GcCommands::Pins => {
+                if pins.pins.is_empty() {
+                    say!("No pinned chunks");
+                }
+                for chunk_id in &pins.pins {
+                    say!("{}", chunk_id);
+                }
+            }
+        }
         return Ok(());
     }
 
-    if let Some(chunk_id) = args.unpin {
-        println!("{} Unpinned chunk: {}", style("✓").green(), &chunk_id[..40.min(chunk_id.len())]);
-        return Ok(());
+    if args.graph {
+        return gc_graph(&cache_dir.join("graph-db"), args.dry_run);
     }
 
-    let cache_dir = &config.cache.dir;
     let chunks_dir = cache_dir.join("chunks");
     let blobs_dir = cache_dir.join("blobs").join("sha256");
 
-    // Calculate cache stats
-    let (chunk_count, chunk_size) = count_directory(&chunks_dir)?;
-    let (blob_count, blob_size) = count_directory(&blobs_dir)?;
-    let total_size = chunk_size + blob_size;
-
     if args.status {
-        println!("{}", style("Cache Status").bold());
-        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-        println!();
-        println!("Cache directory: {}", cache_dir.display());
-        println!();
-        println!("Chunks:     {} items ({} KB)", chunk_count, chunk_size / 1024);
-        println!("Blobs:      {} items ({} KB)", blob_count, blob_size / 1024);
-        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-        println!("Total:      {} items ({} KB)", chunk_count + blob_count, total_size / 1024);
-        println!();
-        println!("Max size:   {} GB", config.cache.max_size_gb);
-        println!("Policy:     {}", config.cache.eviction_policy);
+        // Calculate cache stats
+        let (chunk_count, chunk_size) = count_directory(&chunks_dir)?;
+        let (blob_count, blob_size) = count_directory(&blobs_dir)?;
+        let total_size = chunk_size + blob_size;
+
+        say!("{}", style("Cache Status").bold());
+        say!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        say!();
+        say!("Cache directory: {}", cache_dir.display());
+        say!();
+        say!("Chunks:     {} items ({} KB)", chunk_count, chunk_size / 1024);
+        say!("Blobs:      {} items ({} KB)", blob_count, blob_size / 1024);
+        say!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        say!("Total:      {} items ({} KB)", chunk_count + blob_count, total_size / 1024);
+        say!();
+        say!("Max size:   {} GB", config.cache.max_size_gb);
+        say!("Policy:     {}", config.cache.eviction_policy);
+        say!("Pinned:     {}", PinSet::load(&project_root)?.pins.len());
         
         let usage_pct = (total_size as f64 / (config.cache.max_size_gb as f64 * 1024.0 * 1024.0 * 1024.0)) * 100.0;
-        println!("Usage:      {:.1}%", usage_pct);
+        say!("Usage:      {:.1}%", usage_pct);
         
         return Ok(());
     }
 
-    println!("{}", style("Garbage Collection").bold());
-    println!();
+    say!("{}", style("Garbage Collection").bold());
+    say!();
 
-    // Find candidates for deletion
-    let mut candidates = Vec::new();
-    let mut reclaimable: u64 = 0;
+    let policy = if args.aggressive {
+        GcPolicy::All
+    } else {
+        GcPolicy::MaxSize(
+            args.max_cache_size
+                .unwrap_or(config.cache.max_size_gb * 1024 * 1024 * 1024),
+        )
+    };
+    let pins = PinSet::load(&project_root)?;
+    let cache = LocalCache::new(cache_dir);
+    let plan = cache.plan_gc(&pins, policy)?;
 
-    if chunks_dir.exists() {
-        for entry in std::fs::read_dir(&chunks_dir)? {
-            let entry = entry?;
-            let metadata = entry.metadata()?;
-            
-            // In real implementation, would check:
-            // - Last access time
-            // - Reference count
-            // - Pinned status
-            
-            let is_candidate = !args.aggressive; // Simplified logic
-            
-            if !is_candidate || args.aggressive {
-                candidates.push(entry.path());
-                reclaimable += metadata.len();
-            }
-        }
+    say!("Cache size:        {}", format_size(plan.total_size));
+    if let GcPolicy::MaxSize(max) = policy {
+        say!("Target size:       {}", format_size(max));
     }
-
-    println!("Found {} candidates for deletion", candidates.len());
-    println!("Reclaimable space: {} KB", reclaimable / 1024);
-    println!();
+    say!("Protected chunks:  {} ({} pinned)", plan.protected.len(), pins.pins.len());
+    say!("Found {} candidates for deletion", plan.evict.len());
+    say!("Reclaimable space: {}", format_size(plan.reclaimed()));
+    say!();
 
     if args.dry_run {
-        println!("{}", style("Dry run - would delete:").yellow());
-        for path in candidates.iter().take(10) {
-            if let Some(name) = path.file_name() {
-                println!("  - {}", name.to_string_lossy());
-            }
+        if plan.evict.is_empty() {
+            say!("{}", style("Dry run - nothing would be deleted").yellow());
+            return Ok(());
         }
-        if candidates.len() > 10 {
-            println!("  ... and {} more", candidates.len() - 10);
+        say!("{}", style("Dry run - would delete (least recently used first):").yellow());
+        let now = chrono::Utc::now().timestamp().max(0) as u64;
+        for chunk in &plan.evict {
+            say!(
+                "  - {}  {:>10}  last used {}",
+                &chunk.chunk_id[..40.min(chunk.chunk_id.len())],
+                format_size(chunk.size),
+                format_age(now.saturating_sub(chunk.last_access))
+            );
         }
+        say!();
+        say!("Size after gc:     {}", format_size(plan.size_after));
         return Ok(());
     }
 
-    if candidates.is_empty() {
-        println!("{}", style("Nothing to clean up.").green());
+    if plan.evict.is_empty() {
+        say!("{}", style("Nothing to clean up.").green());
         return Ok(());
     }
 
-    // Confirm deletion
-    println!("{}", style("Deleting...").yellow());
-    
-    let mut deleted = 0;
-    for path in &candidates {
-        if std::fs::remove_file(path).is_ok() {
-            deleted += 1;
-        }
-    }
+    say!("{}", style("Deleting...").yellow());
+    let freed = cache.remove(&plan.evict)?;
 
-    println!();
-    println!("{} Deleted {} items ({} KB freed)", 
+    say!();
+    say!("{} Deleted {} chunks ({} freed)", 
         style("✓").green(), 
-        deleted, 
-        reclaimable / 1024
+        plan.evict.len(), 
+        format_size(freed)
     );
 
     Ok(())
 }
 
+/// Chunk ID for a chunk ID or an alias known to the local alias registry
+fn resolve_target(target: &str, cache_dir: &std::path::Path) -> Result<String> {
+    if target.starts_with("chunk:") {
+        return Ok(target.to_string());
+    }
+    let registry = AliasRegistry::load(&cache_dir.join("chunks").join("aliases.json"))
+        .map_err(|e| anyhow!("Failed to load aliases to resolve '{}': {}", target, e))?;
+    registry
+        .resolve(target)
+        .cloned()
+        .ok_or_else(|| anyhow!("Unknown chunk or alias: {}", target))
+}
+
+/// Parse a size such as `2GB`, `500 MB` or `1024` (bytes); units are powers
+/// of 1024
+fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().map_err(|_| format!("invalid size: {}", value))?;
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        "T" | "TB" => 1 << 40,
+        other => return Err(format!("unknown size unit '{}' (use B, KB, MB, GB or TB)", other)),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+fn format_age(secs: u64) -> String {
+    match secs {
+        s if s < 3600 => format!("{}m ago", s / 60),
+        s if s < 86_400 => format!("{}h ago", s / 3600),
+        s => format!("{}d ago", s / 86_400),
+    }
+}
+
+/// Remove unreferenced content blobs from the graph store
+fn gc_graph(graph_dir: &std::path::Path, dry_run: bool) -> Result<()> {
+    if !graph_dir.exists() {
+        say!("{}", style("No graph store to clean up.").green());
+        return Ok(());
+    }
+    let store = GraphStore::open(graph_dir)?;
+    let before = store.stats()?;
+
+    if dry_run {
+        say!("{}", style("Dry run - graph store holds:").yellow());
+        say!("  Content entries: {}", before.content_entries);
+        say!("  Blobs:           {} ({} KB)", before.blob_count, before.blob_bytes / 1024);
+        return Ok(());
+    }
+
+    let report = store.gc_blobs()?;
+    say!(
+        "{} Removed {} unreferenced blobs ({} KB freed)",
+        style("✓").green(),
+        report.removed,
+        report.bytes_freed / 1024
+    );
+    if report.recounted > 0 {
+        say!("  Corrected {} reference counts", report.recounted);
+    }
+
+    Ok(())
+}
+
 fn count_directory(dir: &std::path::Path) -> Result<(usize, u64)> {
     let mut count = 0;
     let mut size = 0;
@@ -154,3 +285,18 @@ fn count_directory(dir: &std::path::Path) -> Result<(usize, u64)> {
 
     Ok((count, size))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("2GB"), Ok(2 << 30));
+        assert_eq!(parse_size("500 mb"), Ok(500 << 20));
+        assert_eq!(parse_size("1.5K"), Ok(1536));
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert!(parse_size("2 parsecs").is_err());
+        assert!(parse_size("GB").is_err());
+    }
+}
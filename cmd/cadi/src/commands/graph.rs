@@ -0,0 +1,208 @@
+use anyhow::{Context, Result};
+use cadi_core::graph::{GraphQuery, GraphStore, ImportMode, QueryResult, SnapshotStats};
+use cadi_core::HashAlgorithm;
+use clap::{Args, Subcommand};
+use console::style;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+
+use crate::config::CadiConfig;
+use crate::error::CommandError;
+use crate::output::say;
+
+/// Arguments for the graph command
+#[derive(Args)]
+pub struct GraphArgs {
+    /// Graph store directory (defaults to <cache-dir>/graph-db)
+    #[arg(long, global = true)]
+    graph_dir: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: GraphCommands,
+}
+
+#[derive(Subcommand)]
+enum GraphCommands {
+    /// Write the whole graph to a portable snapshot file
+    Export {
+        /// Snapshot file to write
+        file: PathBuf,
+    },
+
+    /// Load a snapshot file into the graph
+    Import {
+        /// Snapshot file to read
+        file: PathBuf,
+
+        /// Keep nodes, aliases and content already in the graph
+        #[arg(long, conflicts_with = "replace")]
+        merge: bool,
+
+        /// Clear the graph before importing
+        #[arg(long)]
+        replace: bool,
+    },
+
+    /// Re-address every chunk under another hash algorithm
+    MigrateIds {
+        /// Target algorithm: sha256|blake3 (defaults to build.hash_algorithm)
+        #[arg(long)]
+        algorithm: Option<HashAlgorithm>,
+    },
+
+    /// Find chunks matching a filter, e.g. `lang=rust gran=function orphans`
+    ///
+    /// Filters: lang=<language>, gran=<granularity>, orphans (nothing
+    /// depends on the chunk), edge=<type>[,<type>...], dir=out|in|both,
+    /// depth=<n>, limit=<n> and from=<chunk-or-alias>[,...]. Without from=
+    /// every chunk in the graph is filtered.
+    Query {
+        /// Filter terms, separately or as one quoted string
+        filter: Vec<String>,
+
+        /// Output format: table|json
+        #[arg(long, default_value = "table")]
+        format: String,
+    },
+
+    /// Remove edges whose confidence is below a threshold
+    Prune {
+        /// Confidence threshold (0.0-1.0)
+        #[arg(long, default_value_t = 0.3)]
+        below: f32,
+
+        /// List the edges without removing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// Execute the graph command. `query` reports its results; the other
+/// subcommands report nothing.
+pub async fn execute(args: GraphArgs, config: &CadiConfig) -> Result<Option<QueryResult>> {
+    let graph_dir = args
+        .graph_dir
+        .clone()
+        .unwrap_or_else(|| config.cache.dir.join("graph-db"));
+
+    match args.command {
+        GraphCommands::Export { file } => {
+            let store = GraphStore::open(&graph_dir)?;
+            let writer = BufWriter::new(
+                File::create(&file).with_context(|| format!("Failed to create {}", file.display()))?,
+            );
+            let stats = store.export_snapshot(writer)?;
+            say!("{} Exported graph to {}", style("✓").green(), file.display());
+            print_stats(&stats);
+        }
+        GraphCommands::Import { file, merge, replace } => {
+            let reader = BufReader::new(
+                File::open(&file).with_context(|| format!("Failed to open {}", file.display()))?,
+            );
+            let mode = if merge {
+                ImportMode::Merge
+            } else if replace {
+                ImportMode::Replace
+            } else {
+                ImportMode::Empty
+            };
+
+            std::fs::create_dir_all(&graph_dir)?;
+            let store = GraphStore::open(&graph_dir)?;
+            if mode == ImportMode::Empty && !store.is_empty() {
+                anyhow::bail!(
+                    "Graph at {} is not empty (use --merge or --replace)",
+                    graph_dir.display()
+                );
+            }
+            let stats = store.import_snapshot(reader, mode)?;
+            say!("{} Imported {} into {}", style("✓").green(), file.display(), graph_dir.display());
+            print_stats(&stats);
+        }
+        GraphCommands::MigrateIds { algorithm } => {
+            let algorithm = algorithm.unwrap_or(config.build.hash_algorithm);
+            let store = GraphStore::open(&graph_dir)?;
+            let report = store.migrate_chunk_ids(algorithm)?;
+            say!(
+                "{} Migrated {} chunks to {} IDs",
+                style("✓").green(),
+                report.migrated.len(),
+                algorithm
+            );
+            for (chunk_id, reason) in &report.skipped {
+                say!("  {} Kept {}: {}", style("⚠").yellow(), chunk_id, reason);
+            }
+        }
+        GraphCommands::Query { filter, format } => {
+            let mut query: GraphQuery = filter
+                .join(" ")
+                .parse()
+                .map_err(|e: cadi_core::CadiError| CommandError::Validation(e.to_string()))?;
+            let store = GraphStore::open(&graph_dir)?;
+            for start in query.start_nodes.iter_mut() {
+                if !store.node_exists(start)? {
+                    *start = store
+                        .resolve_alias(start)?
+                        .ok_or_else(|| CommandError::NotFound(format!("No chunk or alias '{}' in the graph", start)))?;
+                }
+            }
+
+            let result = store.query(&query)?;
+            match format.as_str() {
+                "json" => say!("{}", serde_json::to_string_pretty(&result)?),
+                "table" => print_query(&result),
+                other => anyhow::bail!("Unknown format '{}' (expected table or json)", other),
+            }
+            return Ok(Some(result));
+        }
+        GraphCommands::Prune { below, dry_run } => {
+            if !(0.0..=1.0).contains(&below) {
+                anyhow::bail!("--below must be between 0.0 and 1.0, got {}", below);
+            }
+            let store = GraphStore::open(&graph_dir)?;
+            let edges = store.prune_edges(below, dry_run)?;
+            for edge in &edges {
+                say!(
+                    "  {:.2}  {} -[{}]-> {}",
+                    edge.confidence(),
+                    edge.source,
+                    edge.edge_type,
+                    edge.target
+                );
+            }
+            if dry_run {
+                say!("{} {} edges below {} would be removed", style("→").cyan(), edges.len(), below);
+            } else {
+                say!("{} Removed {} edges below {}", style("✓").green(), edges.len(), below);
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+fn print_query(result: &QueryResult) {
+    for node in &result.nodes {
+        let via = node.reached_via.map(|edge| format!(" via {}", edge)).unwrap_or_default();
+        match &node.alias {
+            Some(alias) => say!("  {}  {} ({}){}", node.depth, alias, node.chunk_id, via),
+            None => say!("  {}  {}{}", node.depth, node.chunk_id, via),
+        }
+    }
+    let more = if result.truncated { " (limit reached; raise it with limit=<n>)" } else { "" };
+    say!("{} {} chunks{}", style("→").cyan(), result.nodes.len(), more);
+}
+
+fn print_stats(stats: &SnapshotStats) {
+    say!("  Nodes:   {}", stats.nodes);
+    say!("  Edges:   {}", stats.edges);
+    say!("  Aliases: {}", stats.aliases);
+    say!("  Content: {}", stats.content);
+    if stats.refinements > 0 {
+        say!("  Refinements: {}", stats.refinements);
+    }
+    if stats.skipped > 0 {
+        say!("  Skipped: {} (already present)", stats.skipped);
+    }
+}
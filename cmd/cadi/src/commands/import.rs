@@ -4,9 +4,11 @@ use console::style;
 use indicatif::{ProgressBar, ProgressStyle};
 use sha2::{Sha256, Digest};
 
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 
 use crate::config::CadiConfig;
+use crate::output::say;
 
 /// Arguments for the import command
 #[derive(Args)]
@@ -45,18 +47,18 @@ pub async fn execute(args: ImportArgs, config: &CadiConfig) -> Result<()> {
     let path = args.path.canonicalize()
         .context("Failed to resolve project path")?;
     
-    println!("{}", style("Importing project...").bold());
-    println!("  Path: {}", path.display());
+    say!("{}", style("Importing project...").bold());
+    say!("  Path: {}", path.display());
 
     // Detect project type
     let project_type = detect_project_type(&path)?;
-    println!("  {} Detected project type: {:?}", style("✓").green(), project_type);
+    say!("  {} Detected project type: {:?}", style("✓").green(), project_type);
 
     // Get project name
     let project_name = args.name.clone()
         .or_else(|| path.file_name().and_then(|n| n.to_str()).map(String::from))
         .unwrap_or_else(|| "unnamed".to_string());
-    println!("  Project name: {}", project_name);
+    say!("  Project name: {}", project_name);
 
     // Collect source files
     let spinner = ProgressBar::new_spinner();
@@ -114,18 +116,18 @@ pub async fn execute(args: ImportArgs, config: &CadiConfig) -> Result<()> {
     // Generate chunk ID
     let chunk_hash = format!("{:x}", content_hasher.finalize());
     let chunk_id = format!("chunk:sha256:{}", chunk_hash);
-    println!("  {} Generated chunk ID: {}", style("✓").green(), &chunk_id[..40]);
+    say!("  {} Generated chunk ID: {}", style("✓").green(), &chunk_id[..40]);
 
     // Detect dependencies
     let dependencies = detect_dependencies(&path, &project_type)?;
     if !dependencies.is_empty() {
-        println!("  {} Detected {} dependencies", style("✓").green(), dependencies.len());
+        say!("  {} Detected {} dependencies", style("✓").green(), dependencies.len());
     }
 
     // Detect entrypoints
     let entrypoints = detect_entrypoints(&path, &project_type)?;
     if !entrypoints.is_empty() {
-        println!("  {} Detected {} entrypoints", style("✓").green(), entrypoints.len());
+        say!("  {} Detected {} entrypoints", style("✓").green(), entrypoints.len());
     }
 
     // Create Source CADI spec
@@ -151,7 +153,7 @@ pub async fn execute(args: ImportArgs, config: &CadiConfig) -> Result<()> {
     let chunk_data_file = chunks_dir.join(format!("{}.chunk", chunk_hash));
     std::fs::write(&chunk_data_file, &chunk_data)?;
     
-    println!("  {} Saved chunk to local store", style("✓").green());
+    say!("  {} Saved chunk to local store", style("✓").green());
 
     // Create manifest
     let manifest = create_manifest(&project_name, &chunk_id)?;
@@ -161,24 +163,24 @@ pub async fn execute(args: ImportArgs, config: &CadiConfig) -> Result<()> {
     });
     
     std::fs::write(&output_path, serde_yaml::to_string(&manifest)?)?;
-    println!("  {} Created manifest: {}", style("✓").green(), output_path.display());
+    say!("  {} Created manifest: {}", style("✓").green(), output_path.display());
 
-    println!();
-    println!("{}", style("Import complete!").green().bold());
-    println!();
-    println!("Chunk ID: {}", chunk_id);
-    println!("Manifest: {}", output_path.display());
+    say!();
+    say!("{}", style("Import complete!").green().bold());
+    say!();
+    say!("Chunk ID: {}", chunk_id);
+    say!("Manifest: {}", output_path.display());
     
     // Output chunk ID alone on last line for script parsing
     eprintln!("CHUNK_ID={}", chunk_id);
-    println!();
-    println!("Next steps:");
-    println!("  {} Build the project:", style("1.").cyan());
-    println!("     cadi build {}", output_path.display());
+    say!();
+    say!("Next steps:");
+    say!("  {} Build the project:", style("1.").cyan());
+    say!("     cadi build {}", output_path.display());
     if !args.no_publish {
-        println!();
-        println!("  {} Publish to registry:", style("2.").cyan());
-        println!("     cadi publish");
+        say!();
+        say!("  {} Publish to registry:", style("2.").cyan());
+        say!("     cadi publish");
     }
 
     Ok(())
@@ -237,13 +239,23 @@ fn collect_source_files(path: &Path, project_type: &ProjectType) -> Result<Vec<P
     };
 
     let mut files = Vec::new();
-    collect_files_recursive(path, &extensions, &mut files)?;
+    collect_files_recursive(path, &extensions, &mut HashSet::new(), &mut files)?;
     Ok(files)
 }
 
-fn collect_files_recursive(dir: &Path, extensions: &[&str], files: &mut Vec<PathBuf>) -> Result<()> {
+fn collect_files_recursive(
+    dir: &Path,
+    extensions: &[&str],
+    visited: &mut HashSet<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Result<()> {
     let ignore_dirs = ["node_modules", "target", ".git", "__pycache__", "dist", "build", ".next"];
-    
+
+    // Symlinks can lead back to a directory already collected
+    if !visited.insert(std::fs::canonicalize(dir)?) {
+        return Ok(());
+    }
+
     for entry in std::fs::read_dir(dir)? {
         let entry = entry?;
         let path = entry.path();
@@ -254,7 +266,7 @@ fn collect_files_recursive(dir: &Path, extensions: &[&str], files: &mut Vec<Path
                 .unwrap_or("");
             
             if !ignore_dirs.contains(&dir_name) && !dir_name.starts_with('.') {
-                collect_files_recursive(&path, extensions, files)?;
+                collect_files_recursive(&path, extensions, visited, files)?;
             }
         } else if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
             if extensions.contains(&ext) {
@@ -454,6 +466,7 @@ fn create_manifest(name: &str, chunk_id: &str) -> Result<serde_json::Value> {
     let manifest_id = format!("app:uuid:{}", uuid::Uuid::new_v4());
     
     let manifest = serde_json::json!({
+        "cadl_version": cadi_core::CURRENT_CADL_VERSION,
         "manifest_id": manifest_id,
         "manifest_version": "1.0",
         "application": {
@@ -472,7 +485,7 @@ fn create_manifest(name: &str, chunk_id: &str) -> Result<serde_json::Value> {
                     "form": "source",
                     "chunk": chunk_id
                 }],
-                "selection_strategy": "prefer_source"
+                "selection": "prefer_source"
             }],
             "edges": []
         },
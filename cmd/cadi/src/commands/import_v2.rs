@@ -15,15 +15,17 @@ use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
+use cadi_core::validator::Validator;
 use cadi_core::{
-    AtomicChunk, ChunkGranularity,
-    ImportResult, ProjectAnalyzer, ProjectAnalyzerConfig,
-    SmartChunkerConfig,
+    chunk_storage_key, short_chunk_id, AtomicChunk, CancellationToken, ChunkGranularity, Manifest,
+    ImportPhase, ImportPlan, ImportResult, ManifestGenerator, ProjectAnalyzer, ProjectAnalyzerConfig,
+    SmartChunkerConfig, IMPORT_PLAN_FILE,
 };
 
-use cadi_registry::{RegistryClient, RegistryConfig as ClientRegistryConfig};
+use cadi_registry::{RegistryClient, RegistryConfig as ClientRegistryConfig, RegistryError};
 
 use crate::config::CadiConfig;
+use crate::output::say;
 
 /// Arguments for the import command
 #[derive(Args)]
@@ -40,7 +42,8 @@ pub struct ImportArgs {
     #[arg(short, long)]
     pub output: Option<PathBuf>,
 
-    /// Namespace for aliases (e.g., "my-org")
+    /// Namespace for aliases (e.g., "my-org"); defaults to the namespace in
+    /// the project's cadi.yaml
     #[arg(long)]
     pub namespace: Option<String>,
 
@@ -64,6 +67,22 @@ pub struct ImportArgs {
     #[arg(long, default_value = "500")]
     pub max_chunk_lines: usize,
 
+    /// YAML file of per-path chunking rules (pattern, strategy, category)
+    #[arg(long)]
+    pub rules: Option<PathBuf>,
+
+    /// Review the chunking decisions before any chunk is generated: write
+    /// them to a YAML plan (the --plan file, or .cadi/import-plan.yaml),
+    /// open it in $VISUAL or $EDITOR, then import with the edited plan
+    #[arg(long)]
+    pub review: bool,
+
+    /// Import with the chunking decisions of a saved plan instead of
+    /// deciding them again (see --review); fails if the plan no longer
+    /// fits the project
+    #[arg(long)]
+    pub plan: Option<PathBuf>,
+
     /// Don't publish to registry (local only)
     #[arg(long)]
     pub no_publish: bool,
@@ -88,10 +107,35 @@ pub struct ImportArgs {
     #[arg(long, default_value = "true")]
     pub skip_existing: bool,
 
+    /// Ask the registry which chunks it already holds and reuse them
+    /// instead of publishing them again (implied by --publish with
+    /// --skip-existing)
+    #[arg(long)]
+    pub reconcile: bool,
+
     /// Dry run - show what would be imported
     #[arg(long)]
     pub dry_run: bool,
 
+    /// Write a build-ready manifest (`<project>.cadi.yaml`) to the project
+    /// directory
+    #[arg(long)]
+    pub write_manifest: bool,
+
+    /// Re-import every file, ignoring the incremental import state
+    #[arg(long)]
+    pub force: bool,
+
+    /// Follow symlinked directories and files (files reachable through
+    /// several links are imported once)
+    #[arg(long)]
+    pub follow_symlinks: bool,
+
+    /// Fail the import when more than this fraction of files could not be
+    /// analyzed (0.0-1.0)
+    #[arg(long, default_value = "0.1")]
+    pub max_failed_fraction: f64,
+
     /// Show detailed output
     #[arg(short, long)]
     pub verbose: bool,
@@ -108,32 +152,17 @@ pub async fn execute(args: ImportArgs, config: &CadiConfig) -> Result<()> {
 
     // Show header
     if args.format == "human" {
-        println!();
-        println!("{}", style("╭─────────────────────────────────────────────────╮").cyan());
-        println!("{}", style("│         CADI Seamless Import                    │").cyan().bold());
-        println!("{}", style("╰─────────────────────────────────────────────────╯").cyan());
-        println!();
+        say!();
+        say!("{}", style("╭─────────────────────────────────────────────────╮").cyan());
+        say!("{}", style("│         CADI Seamless Import                    │").cyan().bold());
+        say!("{}", style("╰─────────────────────────────────────────────────╯").cyan());
+        say!();
     }
 
-    // Build analyzer configuration
-    let chunker_config = SmartChunkerConfig {
-        min_function_lines: args.min_function_lines,
-        min_file_lines_to_split: if args.atomic { usize::MAX } else { 50 },
-        max_chunk_lines: args.max_chunk_lines,
-        extract_utilities: true,
-        extract_types: true,
-        group_related: true,
-        prefer_atomic: args.atomic,
-        namespace: args.namespace.clone(),
-    };
-
-    let analyzer_config = ProjectAnalyzerConfig {
-        chunker_config,
-        detect_compositions: args.compositions,
-        namespace: args.namespace.clone(),
-        ..Default::default()
-    };
-
+    let mut analyzer_config = analyzer_config(&args, &path, config)?;
+    if args.review {
+        analyzer_config.plan = Some(review_plan(&args, &path, &analyzer_config)?);
+    }
     let analyzer = ProjectAnalyzer::new(analyzer_config);
 
     // Set up progress display
@@ -142,20 +171,100 @@ pub async fn execute(args: ImportArgs, config: &CadiConfig) -> Result<()> {
         .template("{spinner:.green} {msg}")
         .unwrap();
 
-    let analyze_spinner = mp.add(ProgressBar::new_spinner());
-    analyze_spinner.set_style(spinner_style.clone());
-    analyze_spinner.enable_steady_tick(Duration::from_millis(100));
-    analyze_spinner.set_message(format!("Analyzing project: {}", path.display()));
+    let analyze_bar = mp.add(ProgressBar::new_spinner());
+    analyze_bar.set_style(spinner_style.clone());
+    analyze_bar.enable_steady_tick(Duration::from_millis(100));
+    analyze_bar.set_message(format!("Analyzing project: {}", path.display()));
+
+    // Run the import off the async runtime so Ctrl-C can cancel it between
+    // files; a second Ctrl-C exits immediately
+    let cancel = CancellationToken::new();
+    let ctrl_c = tokio::spawn({
+        let cancel = cancel.clone();
+        async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                cancel.cancel();
+            }
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(130);
+            }
+        }
+    });
+
+    let import = tokio::task::spawn_blocking({
+        let path = path.clone();
+        let bar = analyze_bar.clone();
+        let cancel = cancel.clone();
+        let bar_style = ProgressStyle::default_bar()
+            .template("{spinner:.green} {prefix} [{bar:30.cyan/blue}] {pos}/{len} {wide_msg}")
+            .unwrap()
+            .progress_chars("=> ");
+        move || {
+            let root = path.clone();
+            analyzer.import_project_with_progress(
+                &path,
+                |progress| {
+                    if progress.phase == ImportPhase::CollectingFiles {
+                        bar.set_message("Collecting files...");
+                        return;
+                    }
+                    if bar.length() != Some(progress.total as u64) {
+                        bar.set_style(bar_style.clone());
+                        bar.set_length(progress.total as u64);
+                    }
+                    bar.set_prefix(progress.phase.to_string());
+                    bar.set_position(progress.processed as u64);
+                    bar.set_message(
+                        progress
+                            .current_file
+                            .map(|f| f.strip_prefix(&root).unwrap_or(&f).display().to_string())
+                            .unwrap_or_default(),
+                    );
+                },
+                &cancel,
+            )
+        }
+    })
+    .await
+    .context("Import task failed")?;
+
+    // Once a handler is installed, Ctrl-C no longer terminates the process
+    // by itself
+    ctrl_c.abort();
+    tokio::spawn(async {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
 
-    // Run the import
-    let result = analyzer.import_project(&path)
-        .context("Failed to import project")?;
+    let mut result = match import {
+        Ok(result) => result,
+        Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {
+            analyze_bar.abandon_with_message(format!("{} Import cancelled", style("✗").red()));
+            anyhow::bail!("Import cancelled; no chunks or import state were written");
+        }
+        Err(e) => return Err(e).context("Failed to import project"),
+    };
 
-    analyze_spinner.finish_with_message(format!(
+    analyze_bar.set_style(spinner_style.clone());
+    analyze_bar.finish_with_message(format!(
         "{} Analysis complete",
         style("✓").green()
     ));
 
+    let publishing = args.publish && !args.no_publish;
+    let reconciled = if args.reconcile || (publishing && args.skip_existing && !args.dry_run) {
+        reconcile(&mut result, &args, config).await?
+    } else {
+        false
+    };
+
+    let manifest_path = if args.write_manifest && !args.dry_run {
+        Some(write_manifest(&result, &path)?)
+    } else {
+        None
+    };
+
     // Show results based on format
     match args.format.as_str() {
         "json" => {
@@ -164,9 +273,12 @@ pub async fn execute(args: ImportArgs, config: &CadiConfig) -> Result<()> {
                 "chunks": &result.chunks,
                 "compositions": &result.compositions,
                 "aliases": &result.alias_registry.aliases,
+                "dependencies": &result.dependencies,
+                "errors": &result.errors,
+                "reused_from_registry": &result.reused_from_registry,
             });
-            println!("{}", serde_json::to_string_pretty(&output)?);
-            return Ok(());
+            say!("{}", serde_json::to_string_pretty(&output)?);
+            return check_failures(&result, args.max_failed_fraction);
         }
         "yaml" => {
             let output = serde_json::json!({
@@ -174,9 +286,12 @@ pub async fn execute(args: ImportArgs, config: &CadiConfig) -> Result<()> {
                 "chunks": &result.chunks,
                 "compositions": &result.compositions,
                 "aliases": &result.alias_registry.aliases,
+                "dependencies": &result.dependencies,
+                "errors": &result.errors,
+                "reused_from_registry": &result.reused_from_registry,
             });
-            println!("{}", serde_yaml::to_string(&output)?);
-            return Ok(());
+            say!("{}", serde_yaml::to_string(&output)?);
+            return check_failures(&result, args.max_failed_fraction);
         }
         _ => {}
     }
@@ -204,121 +319,337 @@ pub async fn execute(args: ImportArgs, config: &CadiConfig) -> Result<()> {
             output_dir.display()
         ));
 
-        // Create manifest
-        let manifest_path = path.join(format!(
-            "{}.cadi.yaml",
-            result.summary.project_name.to_lowercase().replace(' ', "-")
-        ));
-        create_manifest(&result, &manifest_path)?;
-
-        println!();
-        println!("{}", style("Import complete!").green().bold());
-        println!();
-        println!("  {} Manifest: {}", style("→").cyan(), manifest_path.display());
-        println!("  {} Chunks:   {}", style("→").cyan(), output_dir.display());
+        say!();
+        say!("{}", style("Import complete!").green().bold());
+        say!();
+        if let Some(manifest_path) = &manifest_path {
+            say!("  {} Manifest: {}", style("→").cyan(), manifest_path.display());
+        }
+        say!("  {} Chunks:   {}", style("→").cyan(), output_dir.display());
 
         // Publish to registry if requested
-        if args.publish && !args.no_publish {
-            println!();
+        if publishing {
+            say!();
             let publish_result = publish_chunks(
                 &result,
                 &args,
                 config,
+                reconciled,
                 &mp,
                 &spinner_style,
             ).await?;
 
-            println!();
-            println!("{}", style("Publish Summary:").bold());
-            println!("  {} Published: {}", style("✓").green(), publish_result.published);
+            say!();
+            say!("{}", style("Publish Summary:").bold());
+            say!("  {} Published: {}", style("✓").green(), publish_result.published);
+            if publish_result.reused > 0 {
+                say!("  {} Reused:    {} (already in registry)", style("→").cyan(), publish_result.reused);
+            }
             if publish_result.skipped > 0 {
-                println!("  {} Skipped:   {}", style("→").yellow(), publish_result.skipped);
+                say!("  {} Skipped:   {}", style("→").yellow(), publish_result.skipped);
             }
             if publish_result.failed > 0 {
-                println!("  {} Failed:    {}", style("✗").red(), publish_result.failed);
+                say!("  {} Failed:    {}", style("✗").red(), publish_result.failed);
             }
-            println!("  {} Bytes:     {}", style("→").cyan(), format_size(publish_result.bytes_published));
+            if publish_result.spooled > 0 {
+                say!("  {} Spooled:   {} (run `cadi publish --flush-spool` once the registry is reachable)",
+                    style("→").yellow(), publish_result.spooled);
+            }
+            say!("  {} Bytes:     {}", style("→").cyan(), format_size(publish_result.bytes_published));
         }
     } else {
-        println!();
-        println!("{}", style("Dry run - no files written").yellow());
+        say!();
+        say!("{}", style("Dry run - no files written").yellow());
     }
 
     // Next steps
-    println!();
-    println!("{}", style("Next steps:").bold());
-    println!("  {} View chunks:    cadi query --local", style("1.").cyan());
-    println!("  {} Build project:  cadi build", style("2.").cyan());
+    say!();
+    say!("{}", style("Next steps:").bold());
+    say!("  {} View chunks:    cadi query --local", style("1.").cyan());
+    match &manifest_path {
+        Some(manifest_path) => say!(
+            "  {} Build project:  cadi build {}",
+            style("2.").cyan(),
+            manifest_path.display()
+        ),
+        None => say!("  {} Write manifest: cadi import --write-manifest", style("2.").cyan()),
+    }
     if !args.no_publish && !args.publish {
-        println!("  {} Publish:        cadi publish", style("3.").cyan());
+        say!("  {} Publish:        cadi publish", style("3.").cyan());
+    }
+    say!();
+
+    check_failures(&result, args.max_failed_fraction)
+}
+
+/// Fail when too large a share of the project could not be analyzed
+fn check_failures(result: &ImportResult, max_failed_fraction: f64) -> Result<()> {
+    let fraction = result.failed_fraction();
+    if fraction > max_failed_fraction {
+        anyhow::bail!(
+            "{} of {} files failed to import ({:.0}% > {:.0}% allowed by --max-failed-fraction)",
+            result.errors.len(),
+            result.summary.total_files,
+            fraction * 100.0,
+            max_failed_fraction * 100.0
+        );
+    }
+    Ok(())
+}
+
+/// Analyzer configuration for importing the project at `path`
+pub(crate) fn analyzer_config(args: &ImportArgs, path: &Path, config: &CadiConfig) -> Result<ProjectAnalyzerConfig> {
+    let namespace = args.namespace.clone().or_else(|| manifest_namespace(path));
+
+    let chunker_config = SmartChunkerConfig {
+        min_function_lines: args.min_function_lines,
+        min_file_lines_to_split: if args.atomic { usize::MAX } else { 50 },
+        max_chunk_lines: args.max_chunk_lines,
+        extract_utilities: true,
+        extract_types: true,
+        group_related: true,
+        prefer_atomic: args.atomic,
+        namespace: namespace.clone(),
+        hash_algorithm: config.build.hash_algorithm,
+        ..Default::default()
+    };
+
+    let rules = match &args.rules {
+        Some(file) => cadi_scraper::config::load_rules(&file.to_string_lossy())
+            .with_context(|| format!("Failed to load chunking rules from {}", file.display()))?,
+        None => Vec::new(),
+    };
+
+    // --review writes the plan rather than reading it
+    let plan = match &args.plan {
+        Some(file) if !args.review => Some(
+            ImportPlan::load(file)
+                .with_context(|| format!("Failed to load import plan from {}", file.display()))?,
+        ),
+        _ => None,
+    };
+
+    // A plan kept inside the project is not part of it
+    let mut extra_ignores = Vec::new();
+    if let Some(relative) = args.plan.as_ref().and_then(|file| {
+        let file = file.canonicalize().or_else(|_| std::path::absolute(file)).ok()?;
+        file.strip_prefix(path).ok().map(|r| r.to_string_lossy().replace('\\', "/"))
+    }) {
+        extra_ignores.push(format!("/{}", relative));
+    }
+
+    Ok(ProjectAnalyzerConfig {
+        chunker_config,
+        detect_compositions: args.compositions,
+        namespace,
+        incremental: !args.dry_run,
+        force: args.force,
+        rules,
+        plan,
+        extra_ignores,
+        parse_cache: !args.dry_run,
+        follow_symlinks: args.follow_symlinks,
+        ..Default::default()
+    })
+}
+
+/// Write the chunking decisions for the project to a plan file and let
+/// the user edit it until it validates. An existing plan file is the
+/// starting point, so earlier edits are kept.
+fn review_plan(args: &ImportArgs, path: &Path, analyzer_config: &ProjectAnalyzerConfig) -> Result<ImportPlan> {
+    use std::io::IsTerminal;
+
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!("--review needs a terminal; apply a saved plan with --plan instead");
     }
-    println!();
 
+    let plan_path = args.plan.clone().unwrap_or_else(|| path.join(IMPORT_PLAN_FILE));
+    let mut planning = analyzer_config.clone();
+    if plan_path.exists() {
+        planning.plan = Some(
+            ImportPlan::load(&plan_path)
+                .with_context(|| format!("Failed to load import plan from {}", plan_path.display()))?,
+        );
+    }
+    let (analysis, plan) = ProjectAnalyzer::new(planning)
+        .plan_project(path)
+        .context("Failed to analyze project")?;
+    plan.save(&plan_path)?;
+
+    let chunks: usize = plan.files.iter().map(|f| f.suggested_chunks.len()).sum();
+    say!("{} Planned {} chunks for {} files in {}", style("→").cyan(), chunks, plan.files.len(), plan_path.display());
+    say!("  Rename aliases, change line ranges to merge or split chunks, or set a file's strategy to Skip.");
+
+    loop {
+        edit_file(&plan_path)?;
+        let reviewed = ImportPlan::load(&plan_path)
+            .and_then(|plan| plan.validate(path, &analysis.files).map(|_| plan));
+        match reviewed {
+            Ok(plan) => {
+                say!("{} Plan accepted", style("✓").green());
+                return Ok(plan);
+            }
+            Err(e) => {
+                say!("{} {}", style("✗").red(), e);
+                let again = dialoguer::Confirm::new()
+                    .with_prompt("Edit the plan again?")
+                    .default(true)
+                    .interact()?;
+                if !again {
+                    anyhow::bail!("Import plan {} is invalid; nothing was imported", plan_path.display());
+                }
+            }
+        }
+    }
+}
+
+/// Open `file` in $VISUAL or $EDITOR, or wait for the user to edit it
+/// elsewhere when neither is set
+fn edit_file(file: &Path) -> Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty());
+
+    let Some(editor) = editor else {
+        say!("Edit {} and press Enter to continue", file.display());
+        std::io::stdin().read_line(&mut String::new())?;
+        return Ok(());
+    };
+
+    // Editors are often configured with arguments, e.g. "code --wait"
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or_default();
+    let status = std::process::Command::new(program)
+        .args(parts)
+        .arg(file)
+        .status()
+        .with_context(|| format!("Failed to run editor {}", editor))?;
+    if !status.success() {
+        anyhow::bail!("Editor {} exited with {}", editor, status);
+    }
     Ok(())
 }
 
+/// Alias namespace declared in the project's `cadi.yaml`, if any
+fn manifest_namespace(root: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(root.join("cadi.yaml")).ok()?;
+    let manifest: Manifest = serde_yaml::from_str(&content).ok()?;
+    manifest.application.namespace
+}
+
 /// Print the import summary
 fn print_summary(result: &ImportResult, path: &Path, verbose: bool) -> Result<()> {
     let summary = &result.summary;
     let analysis = &result.analysis;
 
-    println!();
-    println!("{}", style("Project Analysis").bold().underlined());
-    println!();
-    println!("  {} Project:     {}", style("→").cyan(), style(&summary.project_name).white().bold());
-    println!("  {} Path:        {}", style("→").cyan(), path.display());
-    println!("  {} Type:        {}", style("→").cyan(), style(&summary.project_type).yellow());
-    println!("  {} Language:    {}", style("→").cyan(), &analysis.primary_language);
-    println!("  {} Files:       {}", style("→").cyan(), summary.total_files);
-    println!("  {} Lines:       {}", style("→").cyan(), format_number(summary.total_lines));
-    println!("  {} Duration:    {}ms", style("→").cyan(), summary.duration_ms);
-    println!();
+    say!();
+    say!("{}", style("Project Analysis").bold().underlined());
+    say!();
+    say!("  {} Project:     {}", style("→").cyan(), style(&summary.project_name).white().bold());
+    say!("  {} Path:        {}", style("→").cyan(), path.display());
+    say!("  {} Type:        {}", style("→").cyan(), style(&summary.project_type).yellow());
+    say!("  {} Language:    {}", style("→").cyan(), &analysis.primary_language);
+    say!("  {} Files:       {}", style("→").cyan(), summary.total_files);
+    say!("  {} Lines:       {}", style("→").cyan(), format_number(summary.total_lines));
+    say!("  {} Duration:    {}ms", style("→").cyan(), summary.duration_ms);
+    say!();
 
     // Chunk summary
-    println!("{}", style("Chunks Created").bold().underlined());
-    println!();
-    println!("  {} Atomic chunks:      {}", 
+    say!("{}", style("Chunks Created").bold().underlined());
+    say!();
+    say!("  {} Atomic chunks:      {}", 
         style("→").cyan(), 
         style(summary.atomic_chunks).green().bold()
     );
-    println!("  {} Composition chunks: {}", 
+    say!("  {} Composition chunks: {}", 
         style("→").cyan(), 
         style(summary.composition_chunks).green().bold()
     );
-    println!("  {} Aliases created:    {}", 
+    say!("  {} Reused chunks:      {}", 
+        style("→").cyan(), 
+        summary.reused_chunks
+    );
+    say!("  {} New chunks:         {}", 
+        style("→").cyan(), 
+        style(summary.new_chunks).green().bold()
+    );
+    say!("  {} Aliases created:    {}", 
         style("→").cyan(), 
         style(summary.aliases_created).green().bold()
     );
-    println!("  {} Skipped files:      {}", 
+    say!("  {} Skipped files:      {}", 
         style("→").cyan(), 
         summary.skipped_files
     );
-    println!();
+    if let Some(cache) = &summary.parse_cache {
+        say!("  {} Parse cache hits:   {}/{} ({:.0}%)", 
+            style("→").cyan(), 
+            cache.hits,
+            cache.hits + cache.misses,
+            cache.hit_rate() * 100.0
+        );
+    }
+    if summary.registry_chunks > 0 {
+        say!("  {} {} chunks already existed in registry",
+            style("→").cyan(),
+            style(summary.registry_chunks).green().bold()
+        );
+    }
+    if summary.potential_duplicates > 0 {
+        say!("  {} {} potential duplicates found (see `cadi stats --duplicates`)",
+            style("!").yellow(),
+            style(summary.potential_duplicates).yellow().bold()
+        );
+    }
+    say!();
+
+    // Workspace members
+    if !summary.members.is_empty() {
+        say!("{}", style("Workspace Members").bold().underlined());
+        say!();
+        for member in &summary.members {
+            say!("  {} {} ({}): {} chunks",
+                style("→").cyan(),
+                style(&member.name).white().bold(),
+                member.path.display(),
+                member.chunks
+            );
+        }
+        for dependency in &result.dependencies {
+            say!("  {} {} depends on {}",
+                style("↳").dim(),
+                dependency.from_member,
+                dependency.to_member
+            );
+        }
+        say!();
+    }
 
     // Category breakdown
     if !summary.categories.is_empty() {
-        println!("{}", style("By Category").bold().underlined());
-        println!();
+        say!("{}", style("By Category").bold().underlined());
+        say!();
         for (category, count) in &summary.categories {
             let icon = get_category_icon(category);
-            println!("  {} {}: {}", icon, category, count);
+            say!("  {} {}: {}", icon, category, count);
         }
-        println!();
+        say!();
     }
 
     // Verbose: show all chunks and aliases
     if verbose {
-        println!("{}", style("Chunks").bold().underlined());
-        println!();
+        say!("{}", style("Chunks").bold().underlined());
+        say!();
 
         for chunk in &result.chunks {
             print_chunk_summary(chunk);
         }
 
         if !result.compositions.is_empty() {
-            println!();
-            println!("{}", style("Compositions").bold().underlined());
-            println!();
+            say!();
+            say!("{}", style("Compositions").bold().underlined());
+            say!();
 
             for chunk in &result.compositions {
                 print_chunk_summary(chunk);
@@ -326,21 +657,30 @@ fn print_summary(result: &ImportResult, path: &Path, verbose: bool) -> Result<()
         }
     } else {
         // Show just a few example chunks
-        println!("{}", style("Sample Chunks").bold().underlined());
-        println!();
+        say!("{}", style("Sample Chunks").bold().underlined());
+        say!();
 
         for chunk in result.chunks.iter().take(5) {
             print_chunk_summary(chunk);
         }
 
         if result.chunks.len() > 5 {
-            println!("  {} ... and {} more", 
+            say!("  {} ... and {} more", 
                 style("→").dim(), 
                 result.chunks.len() - 5
             );
         }
-        println!();
-        println!("  {} Use --verbose to see all chunks", style("Tip:").dim());
+        say!();
+        say!("  {} Use --verbose to see all chunks", style("Tip:").dim());
+    }
+
+    if !result.errors.is_empty() {
+        say!();
+        say!("{}", style(format!("Failed Files ({})", result.errors.len())).bold().underlined());
+        say!();
+        for error in &result.errors {
+            say!("  {} {}: {}", style("✗").red(), error.path.display(), error.reason);
+        }
     }
 
     Ok(())
@@ -372,7 +712,7 @@ fn print_chunk_summary(chunk: &AtomicChunk) {
 
     if is_composition {
         let component_count = chunk.composition.composed_of.len();
-        println!(
+        say!(
             "  {} {} {} {} ({} components)",
             style(granularity_icon).cyan(),
             category_icon,
@@ -381,7 +721,7 @@ fn print_chunk_summary(chunk: &AtomicChunk) {
             component_count
         );
     } else {
-        println!(
+        say!(
             "  {} {} {} {} {}",
             style(granularity_icon).cyan(),
             category_icon,
@@ -399,7 +739,7 @@ fn print_chunk_summary(chunk: &AtomicChunk) {
         } else {
             String::new()
         };
-        println!("      {} provides: {}{}", style("↳").dim(), provides_str, suffix);
+        say!("      {} provides: {}{}", style("↳").dim(), provides_str, suffix);
     }
 }
 
@@ -410,7 +750,7 @@ fn save_chunks(result: &ImportResult, output_dir: &Path, _config: &CadiConfig) -
     // Save atomic chunks
     for chunk in &result.chunks {
         let chunk_file = output_dir.join(format!("{}.json", 
-            chunk.chunk_id.trim_start_matches("chunk:sha256:").chars().take(16).collect::<String>()
+            chunk_storage_key(&chunk.chunk_id).chars().take(16).collect::<String>()
         ));
         let json = serde_json::to_string_pretty(chunk)?;
         std::fs::write(&chunk_file, json)?;
@@ -419,16 +759,14 @@ fn save_chunks(result: &ImportResult, output_dir: &Path, _config: &CadiConfig) -
     // Save composition chunks
     for chunk in &result.compositions {
         let chunk_file = output_dir.join(format!("{}.json",
-            chunk.chunk_id.trim_start_matches("chunk:sha256:").chars().take(16).collect::<String>()
+            chunk_storage_key(&chunk.chunk_id).chars().take(16).collect::<String>()
         ));
         let json = serde_json::to_string_pretty(chunk)?;
         std::fs::write(&chunk_file, json)?;
     }
 
     // Save alias registry
-    let registry_file = output_dir.join("aliases.json");
-    let registry_json = serde_json::to_string_pretty(&result.alias_registry)?;
-    std::fs::write(&registry_file, registry_json)?;
+    result.alias_registry.save(&output_dir.join("aliases.json"))?;
 
     // Save summary
     let summary_file = output_dir.join("import-summary.json");
@@ -438,11 +776,54 @@ fn save_chunks(result: &ImportResult, output_dir: &Path, _config: &CadiConfig) -
     Ok(())
 }
 
+/// Registry client for publishing to and reconciling with the registry,
+/// and its URL
+fn registry_client(args: &ImportArgs, config: &CadiConfig) -> Result<(RegistryClient, String)> {
+    let registry_url = args.registry.clone()
+        .or_else(|| Some(config.registry.url.clone()))
+        .unwrap_or_else(|| "https://registry.cadi.dev".to_string());
+
+    let registry_config = ClientRegistryConfig {
+        url: registry_url.clone(),
+        token: args.auth_token.clone(),
+        namespace: args.namespace.clone().or_else(|| manifest_namespace(&args.path)),
+        max_concurrent: args.concurrency,
+        spool_dir: Some(config.cache.dir.join("spool")),
+        ..Default::default()
+    };
+
+    let client = RegistryClient::new(registry_config)
+        .context("Failed to create registry client")?;
+    Ok((client, registry_url))
+}
+
+/// Mark the chunks the registry already holds as reused. An unreachable
+/// registry leaves every chunk new and returns false; the import itself
+/// carries on.
+async fn reconcile(result: &mut ImportResult, args: &ImportArgs, config: &CadiConfig) -> Result<bool> {
+    let (client, registry_url) = registry_client(args, config)?;
+    match client.reconcile_import(result).await {
+        Ok(_) => Ok(true),
+        Err(e) => {
+            if args.format == "human" {
+                say!("{} Could not check {} for existing chunks: {}",
+                    style("⚠").yellow(),
+                    registry_url,
+                    style(e.to_string()).dim()
+                );
+            }
+            Ok(false)
+        }
+    }
+}
+
 /// Statistics from publishing
 struct PublishStats {
     published: usize,
+    reused: usize,
     skipped: usize,
     failed: usize,
+    spooled: usize,
     bytes_published: usize,
 }
 
@@ -451,40 +832,31 @@ async fn publish_chunks(
     result: &ImportResult,
     args: &ImportArgs,
     config: &CadiConfig,
+    reconciled: bool,
     mp: &MultiProgress,
     _spinner_style: &ProgressStyle,
 ) -> Result<PublishStats> {
-    let registry_url = args.registry.clone()
-        .or_else(|| Some(config.registry.url.clone()))
-        .unwrap_or_else(|| "https://registry.cadi.dev".to_string());
-
-    println!();
-    println!("{}", style("Publishing to Registry").bold().underlined());
-    println!("  {} Registry: {}", style("→").cyan(), &registry_url);
-    println!();
-
-    let registry_config = ClientRegistryConfig {
-        url: registry_url.clone(),
-        token: args.auth_token.clone(),
-        max_concurrent: args.concurrency,
-        ..Default::default()
-    };
+    let (client, registry_url) = registry_client(args, config)?;
 
-    let client = RegistryClient::new(registry_config)
-        .context("Failed to create registry client")?;
+    say!();
+    say!("{}", style("Publishing to Registry").bold().underlined());
+    say!("  {} Registry: {}", style("→").cyan(), &registry_url);
+    say!();
 
     // Check registry health
     let health = client.health().await;
     if let Ok(status) = &health {
         if !status.healthy {
-            println!("{}", style("⚠ Registry may be unhealthy").yellow());
+            say!("{}", style("⚠ Registry may be unhealthy").yellow());
         }
     }
 
     let mut stats = PublishStats {
         published: 0,
+        reused: 0,
         skipped: 0,
         failed: 0,
+        spooled: 0,
         bytes_published: 0,
     };
 
@@ -503,10 +875,19 @@ async fn publish_chunks(
     );
 
     for chunk in all_chunks {
-        progress.set_message(format!("Publishing {}", short_id(&chunk.chunk_id)));
+        progress.set_message(format!("Publishing {}", short_chunk_id(&chunk.chunk_id)));
+
+        // Chunks the registry already holds keep their IDs and aliases; only
+        // the upload is skipped
+        if result.is_reused_from_registry(&chunk.chunk_id) {
+            stats.reused += 1;
+            progress.inc(1);
+            continue;
+        }
 
-        // Check if exists (skip if configured)
-        if args.skip_existing {
+        // Check if exists (skip if configured), unless reconciling already
+        // asked
+        if args.skip_existing && !reconciled {
             match client.chunk_exists(&chunk.chunk_id).await {
                 Ok(true) => {
                     stats.skipped += 1;
@@ -522,17 +903,41 @@ async fn publish_chunks(
         let data = serde_json::to_vec(chunk)?;
         let size = data.len();
 
-        // Publish
-        match client.publish_chunk(&chunk.chunk_id, &data).await {
+        // Publish, waiting out rate limits the client gave up on
+        let mut attempt = 0;
+        let published = loop {
+            match client.publish_chunk(&chunk.chunk_id, &data).await {
+                Err(RegistryError::RateLimited { retry_after }) if attempt < 3 => {
+                    tokio::time::sleep(retry_after.unwrap_or(Duration::from_secs(1 << attempt))).await;
+                    attempt += 1;
+                }
+                published => break published,
+            }
+        };
+        match published {
+            Ok(published) if published.spooled => {
+                stats.spooled += 1;
+            }
             Ok(_) => {
                 stats.published += 1;
                 stats.bytes_published += size;
             }
+            Err(RegistryError::Conflict(_)) => {
+                stats.skipped += 1;
+            }
+            Err(RegistryError::Unauthorized(message)) => {
+                progress.abandon_with_message("Stopped: registry refused the credentials");
+                anyhow::bail!(
+                    "{} refused the credentials ({}); stopped publishing. Pass a token with push access via --auth-token",
+                    registry_url,
+                    message
+                );
+            }
             Err(e) => {
                 if args.verbose {
-                    println!("{} {} {}", 
+                    say!("{} {} {}", 
                         style("✗").red(), 
-                        short_id(&chunk.chunk_id),
+                        short_chunk_id(&chunk.chunk_id),
                         style(e.to_string()).dim()
                     );
                 }
@@ -552,87 +957,20 @@ async fn publish_chunks(
     Ok(stats)
 }
 
-/// Get short form of chunk ID
-fn short_id(chunk_id: &str) -> String {
-    chunk_id
-        .trim_start_matches("chunk:sha256:")
-        .chars()
-        .take(12)
-        .collect()
-}
-
-/// Create a CADI manifest file
-fn create_manifest(result: &ImportResult, path: &Path) -> Result<()> {
-    let manifest_id = format!("app:uuid:{}", uuid::Uuid::new_v4());
-
-    // Build nodes from chunks
-    let nodes: Vec<serde_json::Value> = result.chunks.iter().map(|chunk| {
-        let alias = chunk.primary_alias()
-            .map(|a| a.full_path())
-            .unwrap_or_else(|| chunk.name.clone());
-
-        serde_json::json!({
-            "id": alias,
-            "source_cadi": chunk.chunk_id,
-            "alias": alias,
-            "granularity": format!("{:?}", chunk.granularity).to_lowercase(),
-            "categories": chunk.categories.iter().map(|c| format!("{:?}", c).to_lowercase()).collect::<Vec<_>>(),
-            "representations": [{
-                "form": "source",
-                "chunk": chunk.chunk_id
-            }],
-            "selection_strategy": "prefer_source"
-        })
-    }).collect();
-
-    // Build edges for compositions
-    let mut edges = Vec::new();
-    for comp in &result.compositions {
-        let comp_alias = comp.primary_alias()
-            .map(|a| a.full_path())
-            .unwrap_or_else(|| comp.name.clone());
-
-        for dep in &comp.composition.composed_of {
-            let dep_alias = dep.alias.clone()
-                .unwrap_or_else(|| dep.chunk_id.clone());
-
-            edges.push(serde_json::json!({
-                "from": comp_alias,
-                "to": dep_alias,
-                "type": "composition"
-            }));
-        }
+/// Write the manifest generated from the import to the project directory,
+/// refusing to write one that does not validate
+fn write_manifest(result: &ImportResult, root: &Path) -> Result<PathBuf> {
+    let generator = ManifestGenerator::new(result);
+    let manifest = generator.generate();
+    if let Err(errors) = Validator::new().validate_manifest(&manifest) {
+        let details: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        anyhow::bail!("Generated manifest is invalid:\n  {}", details.join("\n  "));
     }
 
-    let manifest = serde_json::json!({
-        "manifest_id": manifest_id,
-        "manifest_version": "1.0",
-        "application": {
-            "name": result.summary.project_name,
-            "description": format!("{} - imported by CADI", result.summary.project_name),
-            "version": "0.1.0"
-        },
-        "build_graph": {
-            "nodes": nodes,
-            "edges": edges
-        },
-        "aliases": result.alias_registry.aliases,
-        "build_targets": [{
-            "name": "default",
-            "platform": "any",
-            "nodes": nodes.iter().map(|n| {
-                serde_json::json!({
-                    "id": n["id"],
-                    "prefer": ["source"]
-                })
-            }).collect::<Vec<_>>()
-        }]
-    });
-
-    let yaml = serde_yaml::to_string(&manifest)?;
-    std::fs::write(path, yaml)?;
-
-    Ok(())
+    let path = root.join(generator.file_name());
+    std::fs::write(&path, serde_yaml::to_string(&manifest)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
 }
 
 /// Format a number with commas
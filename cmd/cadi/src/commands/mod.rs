@@ -8,12 +8,20 @@ pub mod query;
 pub mod run;
 pub mod plan;
 pub mod verify;
+pub mod explain;
 pub mod trust;
+pub mod extension;
+pub mod registry;
+pub mod alias;
+pub mod graph;
+pub mod refine;
 pub mod gc;
 pub mod stats;
 pub mod demo;
+pub mod demo_e2e;
 pub mod scrape;
 pub mod validate;
 pub mod scaffold;
 pub mod visualize;
+pub mod diff;
 
@@ -3,7 +3,11 @@ use clap::Args;
 use console::style;
 use std::path::PathBuf;
 
+use cadi_builder::{BuildCache, BuildPlan, StepStatus};
+use cadi_core::{chunk_storage_key, Manifest};
+
 use crate::config::CadiConfig;
+use crate::output::say;
 
 /// Arguments for the plan command
 #[derive(Args)]
@@ -19,6 +23,10 @@ pub struct PlanArgs {
     /// Output format (text, json)
     #[arg(long, default_value = "text")]
     format: String,
+
+    /// Compare against the build cache and show what would rebuild
+    #[arg(long)]
+    diff: bool,
 }
 
 /// Execute the plan command
@@ -34,20 +42,24 @@ pub async fn execute(args: PlanArgs, config: &CadiConfig) -> Result<()> {
     let app_name = manifest["application"]["name"].as_str().unwrap_or("unknown");
     let target_name = args.target.as_deref().unwrap_or("dev");
 
+    if args.diff {
+        return execute_diff(manifest, target_name, &args.format, config);
+    }
+
     if args.format == "json" {
         // JSON output
         let plan = build_plan_json(&manifest, target_name, config)?;
-        println!("{}", serde_json::to_string_pretty(&plan)?);
+        say!("{}", serde_json::to_string_pretty(&plan)?);
         return Ok(());
     }
 
     // Text output
-    println!("{}", style("Build Plan").bold());
-    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-    println!();
-    println!("Application: {}", style(app_name).cyan());
-    println!("Target:      {}", style(target_name).cyan());
-    println!();
+    say!("{}", style("Build Plan").bold());
+    say!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    say!();
+    say!("Application: {}", style(app_name).cyan());
+    say!("Target:      {}", style(target_name).cyan());
+    say!();
 
     // Find target
     let targets = manifest["build_targets"].as_array();
@@ -55,17 +67,17 @@ pub async fn execute(args: PlanArgs, config: &CadiConfig) -> Result<()> {
         .and_then(|t| t.iter().find(|bt| bt["name"].as_str() == Some(target_name)));
 
     if build_target.is_none() {
-        println!("{}", style("Target not found!").red());
+        say!("{}", style("Target not found!").red());
         return Ok(());
     }
 
     let build_target = build_target.unwrap();
     let platform = build_target["platform"].as_str().unwrap_or("any");
-    println!("Platform:    {}", platform);
-    println!();
+    say!("Platform:    {}", platform);
+    say!();
 
-    println!("{}", style("Operations:").bold());
-    println!();
+    say!("{}", style("Operations:").bold());
+    say!();
 
     let nodes = manifest["build_graph"]["nodes"].as_array();
     let target_nodes = build_target["nodes"].as_array();
@@ -96,36 +108,78 @@ pub async fn execute(args: PlanArgs, config: &CadiConfig) -> Result<()> {
                     ("build", style("⚙").yellow())
                 };
 
-                println!("  {} {} {}", icon, style(node_id).bold(), style(format!("[{}]", action)).dim());
+                say!("  {} {} {}", icon, style(node_id).bold(), style(format!("[{}]", action)).dim());
                 
                 if let Some(chunk_id) = source_cadi {
-                    println!("      chunk: {}", &chunk_id[..50.min(chunk_id.len())]);
+                    say!("      chunk: {}", &chunk_id[..50.min(chunk_id.len())]);
                 }
             }
         }
     }
 
-    println!();
-    println!("{}", style("Summary:").bold());
-    println!("  Cached:  {} items", total_cached);
-    println!("  Fetch:   {} items (~{} KB)", total_fetch, estimated_size / 1024);
-    println!("  Build:   {} items", total_build);
-    println!();
+    say!();
+    say!("{}", style("Summary:").bold());
+    say!("  Cached:  {} items", total_cached);
+    say!("  Fetch:   {} items (~{} KB)", total_fetch, estimated_size / 1024);
+    say!("  Build:   {} items", total_build);
+    say!();
 
     if total_build > 0 {
-        println!("Estimated build time: ~{} seconds", total_build * 5);
+        say!("Estimated build time: ~{} seconds", total_build * 5);
     }
     if total_fetch > 0 {
-        println!("Estimated download:   ~{} KB", estimated_size / 1024);
+        say!("Estimated download:   ~{} KB", estimated_size / 1024);
     }
 
     Ok(())
 }
 
+/// Show the plan annotated against the build cache
+fn execute_diff(manifest: serde_json::Value, target_name: &str, format: &str, config: &CadiConfig) -> Result<()> {
+    let manifest: Manifest = serde_json::from_value(manifest)?;
+    let plan = BuildPlan::from_manifest(&manifest, target_name)?;
+    let annotated = plan.annotate_with_cache(&BuildCache::new(config.cache.dir.clone()));
+
+    if format == "json" {
+        say!("{}", serde_json::to_string_pretty(&annotated)?);
+        return Ok(());
+    }
+
+    say!("{}", style("Build Plan (diff against last build)").bold());
+    say!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    say!();
+    say!("Application: {}", style(&manifest.application.name).cyan());
+    say!("Target:      {}", style(target_name).cyan());
+    say!();
+
+    for (i, step) in annotated.steps.iter().enumerate() {
+        let (icon, label) = match step.status {
+            StepStatus::Cached => (style("◉").green(), "cached"),
+            StepStatus::Rebuild => (style("⚙").yellow(), "rebuild"),
+            StepStatus::New => (style("+").blue(), "new"),
+        };
+        say!(
+            "  {:>2}. {} {} {} {}",
+            i + 1,
+            icon,
+            style(&step.name).bold(),
+            style(&step.transform).dim(),
+            style(format!("[{}]", label)).dim()
+        );
+        for input in &step.changed_inputs {
+            say!("        changed: {}", &input[..50.min(input.len())]);
+        }
+    }
+
+    say!();
+    say!("{}", style(annotated.summary).bold());
+
+    Ok(())
+}
+
 fn check_cached(chunk_id: Option<&str>, config: &CadiConfig) -> bool {
     if let Some(id) = chunk_id {
-        let hash = id.strip_prefix("chunk:sha256:").unwrap_or(id);
-        let chunk_file = config.cache.dir.join("chunks").join(format!("{}.json", hash));
+        let chunk_file = config.cache.dir.join("chunks").join(format!("{}.json", chunk_storage_key(id)));
         chunk_file.exists()
     } else {
         false
@@ -2,9 +2,17 @@ use anyhow::{Result, anyhow};
 use clap::Args;
 use console::style;
 use reqwest::{Client, header};
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+use cadi_core::trust::{SignatureStatus, SigningKey, TrustStore, SIGNATURE_PREFIX};
+use cadi_core::{chunk_storage_key, short_chunk_id, ChunkId};
+use cadi_registry::{RegistryClient, RegistryConfig, RegistryError, RegistryResult};
 
 use crate::config::CadiConfig;
+use crate::error::CommandError;
+use crate::output::say;
 
 /// Arguments for the publish command
 #[derive(Args)]
@@ -13,6 +21,21 @@ pub struct PublishArgs {
     #[arg()]
     chunks: Vec<String>,
 
+    /// Publish the chunks a manifest's build graph references, and the
+    /// local chunks they reference in turn
+    #[arg(long, conflicts_with = "chunks")]
+    manifest: Option<PathBuf>,
+
+    /// Continue a publish that failed partway, skipping chunks it already
+    /// published
+    #[arg(long, conflicts_with_all = ["chunks", "manifest"])]
+    resume: bool,
+
+    /// If any chunk fails, delete the chunks this run uploaded instead of
+    /// saving progress for --resume
+    #[arg(long)]
+    rollback: bool,
+
     /// Publish to specific registry
     #[arg(short, long)]
     registry: Option<String>,
@@ -21,6 +44,19 @@ pub struct PublishArgs {
     #[arg(long)]
     no_sign: bool,
 
+    /// Sign chunks, failing if no signing key is available
+    #[arg(long, conflicts_with = "no_sign")]
+    sign: bool,
+
+    /// Signing key (defaults to the configured key)
+    #[arg(long)]
+    key: Option<PathBuf>,
+
+    /// Re-sign and republish the local chunks signed by retired keys
+    /// (see `cadi trust rotate`), adding a signature by --key
+    #[arg(long, conflicts_with_all = ["chunks", "manifest", "resume", "no_sign"])]
+    resign: bool,
+
     /// Dry run - show what would be published
     #[arg(long)]
     dry_run: bool,
@@ -29,53 +65,146 @@ pub struct PublishArgs {
     #[arg(long)]
     auth_token: Option<String>,
 
-    /// Namespace for chunks
+    /// Namespace to publish into; multi-tenant registries check the token
+    /// against it
     #[arg(long)]
     namespace: Option<String>,
 
-    /// Batch size for concurrent publishing
+    /// Maximum number of concurrent uploads
     #[arg(long, default_value = "5")]
     batch_size: usize,
 
     /// Skip deduplication checks
     #[arg(long)]
     no_dedup: bool,
+
+    /// Retry publishing chunks spooled while the registry was unreachable
+    #[arg(long)]
+    flush_spool: bool,
+
+    /// Fail instead of warning when a chunk's license is unknown
+    #[arg(long)]
+    deny_unknown_license: bool,
+
+    /// After publishing, push the local aliases (aliases.json) of the
+    /// published chunks to the registry
+    #[arg(long)]
+    publish_aliases: bool,
 }
 
-/// Publish state tracker
-struct PublishStats {
-    total: usize,
+/// A chunk in the local store and the files it is published from
+#[derive(Default, Clone)]
+struct PublishItem {
+    id: String,
+    chunk_path: Option<PathBuf>,
+    meta_path: Option<PathBuf>,
+}
+
+/// Published, skipped and failed chunks of one granularity
+#[derive(Default)]
+struct GranularityStats {
     published: usize,
     skipped: usize,
     failed: usize,
+}
+
+/// Publish state tracker
+#[derive(Default)]
+struct PublishStats {
+    total: usize,
+    by_granularity: BTreeMap<String, GranularityStats>,
     bytes_published: u64,
 }
 
+impl PublishStats {
+    fn entry(&mut self, granularity: String) -> &mut GranularityStats {
+        self.by_granularity.entry(granularity).or_default()
+    }
+
+    fn published(&self) -> usize {
+        self.by_granularity.values().map(|s| s.published).sum()
+    }
+
+    fn skipped(&self) -> usize {
+        self.by_granularity.values().map(|s| s.skipped).sum()
+    }
+
+    fn failed(&self) -> usize {
+        self.by_granularity.values().map(|s| s.failed).sum()
+    }
+}
+
+/// What a publish did
+#[derive(Debug, Default, Serialize)]
+pub struct PublishReport {
+    pub registry: String,
+    pub dry_run: bool,
+    /// Chunks of the publish, components before compositions; with
+    /// `--dry-run` those that would be published
+    pub chunks: Vec<String>,
+    pub published: usize,
+    /// Chunks the registry already had
+    pub skipped: usize,
+    pub bytes_published: u64,
+    /// Aliases pushed with `--publish-aliases`
+    pub aliases: usize,
+}
+
+/// Progress of a publish that failed partway, saved so `--resume` can
+/// continue it
+#[derive(Serialize, Deserialize)]
+struct PublishState {
+    registry: String,
+    /// Every chunk of the publish, components before compositions
+    order: Vec<String>,
+    /// Chunks already on the registry
+    done: Vec<String>,
+}
+
+impl PublishState {
+    fn path(config: &CadiConfig) -> PathBuf {
+        config.cache.dir.join("publish-state.json")
+    }
+
+    fn load(config: &CadiConfig) -> Result<Self> {
+        let path = Self::path(config);
+        let content = std::fs::read_to_string(&path)
+            .map_err(|_| CommandError::NotFound(format!("No interrupted publish to resume ({} not found)", path.display())))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    fn save(&self, config: &CadiConfig) -> Result<()> {
+        std::fs::create_dir_all(&config.cache.dir)?;
+        std::fs::write(Self::path(config), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
 /// Execute the publish command
-pub async fn execute(args: PublishArgs, config: &CadiConfig) -> Result<()> {
+pub async fn execute(args: PublishArgs, config: &CadiConfig) -> Result<PublishReport> {
     let registry = args.registry.as_ref()
         .unwrap_or(&config.registry.url);
 
-    println!("{}", style("Publishing chunks to registry...").bold());
-    println!("  Registry: {}", registry);
+    say!("{}", style("Publishing chunks to registry...").bold());
+    say!("  Registry: {}", registry);
     if let Some(ref ns) = args.namespace {
-        println!("  Namespace: {}", ns);
+        say!("  Namespace: {}", ns);
+    }
+
+    if args.flush_spool {
+        return flush_spool(registry, &args, config).await;
     }
 
+    let mut report = PublishReport {
+        registry: registry.clone(),
+        dry_run: args.dry_run,
+        ..Default::default()
+    };
+
     // Find chunks to publish
     let chunks_dir = config.cache.dir.join("chunks");
 
     // Map from hash -> (chunk_path, metadata_path)
-    use std::collections::HashMap;
-    use std::path::PathBuf;
-
-    #[derive(Default, Clone)]
-    struct PublishItem {
-        id: String,
-        chunk_path: Option<PathBuf>,
-        meta_path: Option<PathBuf>,
-    }
-
     let mut map: HashMap<String, PublishItem> = HashMap::new();
 
     if chunks_dir.exists() {
@@ -85,8 +214,8 @@ pub async fn execute(args: PublishArgs, config: &CadiConfig) -> Result<()> {
             if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
                 if ext == "chunk" || ext == "json" {
                     if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
-                        let id = format!("chunk:sha256:{}", stem);
-                        let item = map.entry(stem.to_string()).or_insert(PublishItem::default());
+                        let id = ChunkId::from_storage_key(stem).to_string();
+                        let item = map.entry(stem.to_string()).or_default();
                         item.id = id.clone();
                         if ext == "chunk" {
                             item.chunk_path = Some(path.clone());
@@ -99,133 +228,664 @@ pub async fn execute(args: PublishArgs, config: &CadiConfig) -> Result<()> {
         }
     }
 
-    if !args.chunks.is_empty() {
+    let resumed = if args.resume {
+        let state = PublishState::load(config)?;
+        if state.registry != *registry {
+            return Err(CommandError::Validation(format!(
+                "The interrupted publish was to {}, not {}",
+                state.registry,
+                registry
+            ))
+            .into());
+        }
+        say!("  {} Resuming: {} of {} chunks already published", style("→").cyan(), state.done.len(), state.order.len());
+        Some(state)
+    } else {
+        None
+    };
+
+    let requested: Vec<String> = if let Some(state) = &resumed {
+        state.order.clone()
+    } else if let Some(manifest) = &args.manifest {
+        let ids = manifest_chunks(manifest, &map)?;
+        say!("  {} Manifest references {} chunks", style("✓").green(), ids.len());
+        ids
+    } else if args.resign {
+        let key = load_signing_key(&args, config)?
+            .ok_or_else(|| CommandError::Validation("--resign requires a signing key".to_string()))?;
+        let ids = retired_key_chunks(&map, &TrustStore::open(&config.security.trust_dir), &key.public_key())?;
+        if ids.is_empty() {
+            say!("  {} No local chunks are signed only by retired keys", style("!").yellow());
+            return Ok(report);
+        }
+        say!("  {} {} chunks are signed by retired keys", style("✓").green(), ids.len());
+        ids
+    } else {
+        args.chunks.clone()
+    };
+
+    if !requested.is_empty() {
         // Ensure requested chunks exist (at least one of chunk or metadata)
         let mut filtered = HashMap::new();
-        for chunk_id in &args.chunks {
-            let hash = chunk_id.strip_prefix("chunk:sha256:").unwrap_or(chunk_id);
-            if let Some(item) = map.get(hash) {
-                filtered.insert(hash.to_string(), item.clone());
+        for chunk_id in &requested {
+            let key = chunk_storage_key(chunk_id);
+            if let Some(item) = map.get(&key) {
+                filtered.insert(key, item.clone());
             } else {
-                let chunk_path = chunks_dir.join(format!("{}.chunk", hash));
-                let json_path = chunks_dir.join(format!("{}.json", hash));
-                if chunk_path.exists() || json_path.exists() {
-                    let mut item = PublishItem { id: format!("chunk:sha256:{}", hash), chunk_path: None, meta_path: None };
-                    if chunk_path.exists() { item.chunk_path = Some(chunk_path); }
-                    if json_path.exists() { item.meta_path = Some(json_path); }
-                    filtered.insert(hash.to_string(), item);
-                } else {
-                    println!("  {} Chunk not found locally: {}", style("⚠").yellow(), chunk_id);
-                }
+                say!("  {} Chunk not found locally: {}", style("⚠").yellow(), chunk_id);
             }
         }
-        map = filtered.into_iter().map(|(k, v)| (k, v)).collect();
+        map = filtered;
     }
 
     let chunks_to_publish: Vec<PublishItem> = map.into_values().collect();
 
     if chunks_to_publish.is_empty() {
-        println!("  {} No chunks to publish", style("!").yellow());
-        return Ok(());
+        if !requested.is_empty() {
+            return Err(CommandError::NotFound("None of the requested chunks are in the local store".to_string()).into());
+        }
+        say!("  {} No chunks to publish", style("!").yellow());
+        return Ok(report);
     }
 
-    println!("  {} Found {} chunks to publish", style("✓").green(), chunks_to_publish.len());
-    println!();
+    say!("  {} Found {} chunks to publish", style("✓").green(), chunks_to_publish.len());
+
+    let unlicensed: Vec<&PublishItem> = chunks_to_publish
+        .iter()
+        .filter(|item| !cadi_core::license::is_known(&chunk_license(item.meta_path.as_deref())))
+        .collect();
+    if !unlicensed.is_empty() {
+        let marker = if args.deny_unknown_license { style("✗").red() } else { style("⚠").yellow() };
+        say!("  {} {} chunks have no known license:", marker, unlicensed.len());
+        for item in &unlicensed {
+            say!("      {}", item.id);
+        }
+        if args.deny_unknown_license {
+            return Err(CommandError::Validation(format!(
+                "{} chunks have an unknown license (--deny-unknown-license)",
+                unlicensed.len()
+            ))
+            .into());
+        }
+    }
+
+    let levels = publish_levels(chunks_to_publish);
+    let mut state = PublishState {
+        registry: registry.clone(),
+        order: levels.iter().flatten().map(|item| item.id.clone()).collect(),
+        done: resumed.map(|state| state.done).unwrap_or_default(),
+    };
+    report.chunks = state.order.clone();
+    say!();
 
     if args.dry_run {
-        println!("{}", style("Dry run - would publish:").yellow());
-        for item in &chunks_to_publish {
-            let mut parts = vec![];
-            if let Some(chunk_path) = &item.chunk_path {
-                let size = std::fs::metadata(chunk_path)?.len();
-                parts.push(format!("data ({} bytes)", size));
-            }
-            if item.meta_path.is_some() {
-                parts.push("metadata".to_string());
+        say!("{}", style("Dry run - would publish, in order:").yellow());
+        let mut position = 0;
+        for (level, items) in levels.iter().enumerate() {
+            say!("  Level {}:", level);
+            for item in items {
+                position += 1;
+                let mut parts = vec![chunk_granularity(item.meta_path.as_deref())];
+                if let Some(chunk_path) = &item.chunk_path {
+                    let size = std::fs::metadata(chunk_path)?.len();
+                    parts.push(format!("data ({} bytes)", size));
+                }
+                if item.meta_path.is_some() {
+                    parts.push("metadata".to_string());
+                }
+                let resumed = if state.done.contains(&item.id) { " (already published)" } else { "" };
+                say!("    {:>3}. {}: {}{}", position, item.id, parts.join(", "), resumed);
             }
-            println!("  - {}: {}", item.id, parts.join(", "));
         }
-        return Ok(());
+        if args.publish_aliases {
+            let aliases = local_aliases(&chunks_dir, &state.order)?;
+            say!("  Would publish {} aliases", aliases.len());
+            report.aliases = aliases.len();
+        }
+        return Ok(report);
     }
 
-    // Publish chunks with batching
     let mut stats = PublishStats {
-        total: chunks_to_publish.len(),
-        published: 0,
-        skipped: 0,
-        failed: 0,
-        bytes_published: 0,
+        total: state.order.len(),
+        ..Default::default()
     };
 
-    let client = create_client(&args.auth_token)?;
-
-    // Process in batches
-    for (i, item) in chunks_to_publish.iter().enumerate() {
-        let display_id = if item.id.len() > 40 { &item.id[..40] } else { &item.id };
-        print!("  [{}/{}] Publishing {}... ", i + 1, stats.total, display_id);
-        use std::io::Write;
-        std::io::stdout().flush()?;
-
-        // First publish data chunk if present
-        if let Some(chunk_path) = &item.chunk_path {
-            match publish_chunk(&client, registry, &item.id, chunk_path, &args, config).await {
-                Ok(size) => {
-                    println!("{}", style("✓").green());
-                    stats.published += 1;
-                    stats.bytes_published += size as u64;
+    let uploader = Uploader {
+        client: create_client(&args.auth_token, &args.namespace)?,
+        registry: registry.clone(),
+        dedup: !args.no_dedup,
+    };
+    let signing_key = load_signing_key(&args, config)?;
+    if let Some(key) = &signing_key {
+        say!("  {} Signing with key {}", style("→").cyan(), key.public_key());
+    }
+
+    // Publish level by level, so every chunk's components are on the
+    // registry before it is uploaded. Chunks within a level are independent
+    // and are uploaded `batch_size` at a time.
+    let mut uploaded: Vec<String> = Vec::new();
+    let mut position = 0;
+    let mut not_attempted = 0;
+    let mut unauthorized = None;
+    for items in levels {
+        if stats.failed() > 0 || unauthorized.is_some() {
+            not_attempted += items.len();
+            continue;
+        }
+
+        let mut pending = Vec::new();
+        for mut item in items {
+            position += 1;
+            let granularity = chunk_granularity(item.meta_path.as_deref());
+            if state.done.contains(&item.id) {
+                stats.entry(granularity).skipped += 1;
+                continue;
+            }
+
+            // Sign into the metadata, which is published with the data
+            if let Some(key) = &signing_key {
+                match sign_chunk(&chunks_dir, &item.id, key) {
+                    Ok(meta_path) => item.meta_path = Some(meta_path),
+                    Err(e) => {
+                        say!("  [{}/{}] {} {} signing failed: {}", position, stats.total, style("✗").red(), short_chunk_id(&item.id), e);
+                        stats.entry(granularity).failed += 1;
+                        continue;
+                    }
                 }
-                Err(e) if e.to_string().contains("exists") && !args.no_dedup => {
-                    println!("{}", style("(skipped - exists)").yellow());
-                    stats.skipped += 1;
+            }
+            pending.push((position, granularity, item));
+        }
+
+        let attempted = pending.len();
+        let results = uploader.publish_all(pending, args.batch_size).await?;
+        not_attempted += attempted - results.len();
+        for (position, granularity, item, outcome) in results {
+            let label = match &outcome {
+                PublishOutcome::Published(size) => {
+                    stats.entry(granularity).published += 1;
+                    stats.bytes_published += *size as u64;
+                    uploaded.push(item.id.clone());
+                    state.done.push(item.id.clone());
+                    style("✓".to_string()).green()
                 }
-                Err(e) => {
-                    println!("{} {}", style("✗").red(), e);
-                    stats.failed += 1;
+                PublishOutcome::Exists => {
+                    stats.entry(granularity).skipped += 1;
+                    state.done.push(item.id.clone());
+                    style("(skipped - exists)".to_string()).yellow()
                 }
-            }
+                PublishOutcome::NoData => {
+                    stats.entry(granularity).published += 1;
+                    state.done.push(item.id.clone());
+                    style("(metadata only)".to_string()).yellow()
+                }
+                PublishOutcome::Failed { error, uploaded: data_uploaded } => {
+                    stats.entry(granularity).failed += 1;
+                    if *data_uploaded {
+                        uploaded.push(item.id.clone());
+                    }
+                    style(format!("✗ {}", error)).red()
+                }
+                PublishOutcome::Unauthorized(message) => {
+                    let label = style(format!("✗ {}", message)).red();
+                    unauthorized = Some(message.clone());
+                    label
+                }
+            };
+            say!("  [{}/{}] {} {}", position, stats.total, short_chunk_id(&item.id), label);
+        }
+    }
+
+    say!();
+    say!("{}", style("Publish Summary:").bold());
+    say!("  {:<12} {:>9} {:>9} {:>9}", "Granularity", "Published", "Skipped", "Failed");
+    for (granularity, counts) in &stats.by_granularity {
+        say!("  {:<12} {:>9} {:>9} {:>9}", granularity, counts.published, counts.skipped, counts.failed);
+    }
+    say!("  {} Published: {}", style("✓").green(), stats.published());
+    if stats.skipped() > 0 {
+        say!("  {} Skipped: {}", style("→").yellow(), stats.skipped());
+    }
+    if stats.failed() > 0 {
+        say!("  {} Failed: {}", style("✗").red(), stats.failed());
+    }
+    if not_attempted > 0 {
+        say!("  {} Not attempted: {}", style("→").yellow(), not_attempted);
+    }
+    say!("  {} Total bytes: {}", style("→").cyan(), stats.bytes_published);
+
+    if stats.failed() > 0 || unauthorized.is_some() {
+        say!();
+        if args.rollback {
+            let deleted = uploader.delete_all(&uploaded).await;
+            say!(
+                "  {} Rolled back {} of {} chunks uploaded by this run",
+                style("↺").yellow(),
+                deleted,
+                uploaded.len()
+            );
+            let _ = std::fs::remove_file(PublishState::path(config));
         } else {
-            // No chunk data to publish
-            println!("{}", style("(no data)").yellow());
+            state.save(config)?;
+            say!(
+                "  {} Progress saved; run {} to retry the rest",
+                style("→").cyan(),
+                style("cadi publish --resume").bold()
+            );
+        }
+        if let Some(message) = unauthorized {
+            return Err(CommandError::Network(format!(
+                "{} refused the credentials ({}); stopped publishing. Pass a token with push access via --auth-token",
+                registry,
+                message
+            ))
+            .into());
         }
+        return Err(CommandError::Network(format!("{} chunks failed to publish", stats.failed())).into());
+    }
 
-        // Then publish metadata if present
-        if let Some(meta_path) = &item.meta_path {
-            match publish_metadata(&client, registry, &item.id, meta_path, &args, config).await {
-                Ok(_) => {
-                    println!("  {} metadata updated", style("✓").green());
+    let _ = std::fs::remove_file(PublishState::path(config));
+    report.published = stats.published();
+    report.skipped = stats.skipped();
+    report.bytes_published = stats.bytes_published;
+
+    if args.publish_aliases {
+        let aliases = local_aliases(&chunks_dir, &state.done)?;
+        report.aliases = aliases.len();
+        publish_aliases(registry, &args, config, aliases).await?;
+    }
+
+    say!();
+    say!("{}", style("Publish complete!").green().bold());
+
+    Ok(report)
+}
+
+/// Chunk IDs a manifest's build graph names, plus every chunk they
+/// reference that is in the local store
+fn manifest_chunks(manifest: &Path, local: &HashMap<String, PublishItem>) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(manifest)
+        .map_err(|e| anyhow!("Failed to read manifest {}: {}", manifest.display(), e))?;
+    let manifest: serde_json::Value = serde_yaml::from_str(&content)?;
+
+    let mut ids: Vec<String> = Vec::new();
+    let mut queue: VecDeque<String> = VecDeque::new();
+    for node in manifest["build_graph"]["nodes"].as_array().into_iter().flatten() {
+        for field in ["source_cadi", "ir_cadi", "blob_cadi", "container_cadi"] {
+            if let Some(id) = node[field].as_str() {
+                queue.push_back(id.to_string());
+            }
+        }
+    }
+
+    while let Some(id) = queue.pop_front() {
+        if ids.contains(&id) {
+            continue;
+        }
+        if let Some(item) = local.get(&chunk_storage_key(&id)) {
+            queue.extend(local_references(item));
+        }
+        ids.push(id);
+    }
+
+    Ok(ids)
+}
+
+/// Chunks referenced by a local chunk's data or metadata
+fn local_references(item: &PublishItem) -> Vec<String> {
+    let mut references = Vec::new();
+    for path in item.chunk_path.iter().chain(&item.meta_path) {
+        let Ok(data) = std::fs::read(path) else {
+            continue;
+        };
+        for id in cadi_core::chunk_references(&data) {
+            if !references.contains(&id) {
+                references.push(id);
+            }
+        }
+    }
+    references
+}
+
+/// Group chunks into levels that can be published in order: each chunk
+/// comes after every chunk it references, so compositions never reach the
+/// registry before their components. Chunks in a reference cycle cannot be
+/// ordered and go last.
+fn publish_levels(items: Vec<PublishItem>) -> Vec<Vec<PublishItem>> {
+    let ids: HashSet<String> = items.iter().map(|item| item.id.clone()).collect();
+    let mut remaining: BTreeMap<String, (PublishItem, HashSet<String>)> = items
+        .into_iter()
+        .map(|item| {
+            let deps = local_references(&item)
+                .into_iter()
+                .filter(|dep| *dep != item.id && ids.contains(dep))
+                .collect();
+            (item.id.clone(), (item, deps))
+        })
+        .collect();
+
+    let mut levels = Vec::new();
+    while !remaining.is_empty() {
+        let ready: Vec<String> = remaining
+            .iter()
+            .filter(|(_, (_, deps))| deps.is_empty())
+            .map(|(id, _)| id.clone())
+            .collect();
+        if ready.is_empty() {
+            say!(
+                "  {} {} chunks reference each other in a cycle; publishing them last",
+                style("⚠").yellow(),
+                remaining.len()
+            );
+            levels.push(remaining.into_values().map(|(item, _)| item).collect());
+            break;
+        }
+
+        for (_, deps) in remaining.values_mut() {
+            deps.retain(|dep| !ready.contains(dep));
+        }
+        levels.push(
+            ready
+                .iter()
+                .filter_map(|id| remaining.remove(id))
+                .map(|(item, _)| item)
+                .collect(),
+        );
+    }
+    levels
+}
+
+/// What happened to one chunk
+enum PublishOutcome {
+    /// The data was uploaded; its size in bytes
+    Published(usize),
+    /// The registry already had the chunk
+    Exists,
+    /// Only metadata was published
+    NoData,
+    Failed {
+        error: String,
+        /// Whether the data reached the registry before the failure
+        uploaded: bool,
+    },
+    /// The registry refused the credentials, so the rest of the batch is
+    /// not attempted
+    Unauthorized(String),
+}
+
+/// How often an upload is retried after the registry rate limits it
+const RATE_LIMIT_RETRIES: u32 = 3;
+
+/// Uploads chunks and their metadata to a registry
+#[derive(Clone)]
+struct Uploader {
+    client: Client,
+    registry: String,
+    /// Treat chunks the registry already has as skipped, not failed
+    dedup: bool,
+}
+
+impl Uploader {
+    fn chunk_url(&self, chunk_id: &str) -> String {
+        format!("{}/v1/chunks/{}", self.registry.trim_end_matches('/'), chunk_id)
+    }
+
+    /// Publish `items` with at most `concurrency` uploads in flight,
+    /// returning each with its outcome in completion order. Once the
+    /// registry refuses the credentials no further uploads are started, so
+    /// fewer results than items may come back.
+    async fn publish_all<T: Send + 'static>(
+        &self,
+        items: Vec<(usize, T, PublishItem)>,
+        concurrency: usize,
+    ) -> Result<Vec<(usize, T, PublishItem, PublishOutcome)>> {
+        let mut tasks = tokio::task::JoinSet::new();
+        let mut pending = items.into_iter();
+        let mut results = Vec::new();
+        let mut unauthorized = false;
+
+        loop {
+            while !unauthorized && tasks.len() < concurrency.max(1) {
+                let Some((position, tag, item)) = pending.next() else {
+                    break;
+                };
+                let uploader = self.clone();
+                tasks.spawn(async move {
+                    let outcome = uploader.publish(&item).await;
+                    (position, tag, item, outcome)
+                });
+            }
+            match tasks.join_next().await {
+                Some(result) => {
+                    let result = result?;
+                    unauthorized |= matches!(result.3, PublishOutcome::Unauthorized(_));
+                    results.push(result);
                 }
+                None => break,
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Publish a chunk's data, then its metadata
+    async fn publish(&self, item: &PublishItem) -> PublishOutcome {
+        let outcome = match &item.chunk_path {
+            Some(chunk_path) => match self.publish_chunk(&item.id, chunk_path).await {
+                Ok(size) => PublishOutcome::Published(size),
+                Err(RegistryError::Conflict(_)) if self.dedup => PublishOutcome::Exists,
+                Err(RegistryError::Unauthorized(message)) => return PublishOutcome::Unauthorized(message),
+                Err(e) => return PublishOutcome::Failed { error: e.to_string(), uploaded: false },
+            },
+            None => PublishOutcome::NoData,
+        };
+
+        if let Some(meta_path) = &item.meta_path {
+            match self.publish_metadata(&item.id, meta_path).await {
+                Ok(()) => {}
+                Err(RegistryError::Unauthorized(message)) => return PublishOutcome::Unauthorized(message),
                 Err(e) => {
-                    println!("  {} metadata failed: {}", style("✗").red(), e);
-                    stats.failed += 1;
+                    return PublishOutcome::Failed {
+                        error: format!("metadata failed: {}", e),
+                        uploaded: matches!(outcome, PublishOutcome::Published(_)),
+                    };
                 }
             }
         }
+        outcome
     }
 
-    println!();
-    println!("{}", style("Publish Summary:").bold());
-    println!("  {} Published: {}", style("✓").green(), stats.published);
-    if stats.skipped > 0 {
-        println!("  {} Skipped: {}", style("→").yellow(), stats.skipped);
+    /// Send a request, waiting out the registry's `Retry-After` (or an
+    /// exponential backoff without one) when it rate limits the upload
+    async fn send<F>(&self, build: F, resource: &str) -> RegistryResult<reqwest::Response>
+    where
+        F: Fn() -> reqwest::RequestBuilder,
+    {
+        let mut attempt = 0;
+        loop {
+            let response = build().send().await?;
+            if response.status().is_success() {
+                return Ok(response);
+            }
+            match RegistryError::from_response(response, resource).await {
+                RegistryError::RateLimited { retry_after } if attempt < RATE_LIMIT_RETRIES => {
+                    let wait = retry_after.unwrap_or(std::time::Duration::from_secs(1 << attempt));
+                    tokio::time::sleep(wait).await;
+                    attempt += 1;
+                }
+                e => return Err(e),
+            }
+        }
     }
-    if stats.failed > 0 {
-        println!("  {} Failed: {}", style("✗").red(), stats.failed);
+
+    /// Publish a single chunk
+    async fn publish_chunk(&self, chunk_id: &str, path: &Path) -> RegistryResult<usize> {
+        let content = std::fs::read(path).map_err(RegistryError::Storage)?;
+        let size = content.len();
+
+        let response = self.send(|| {
+            self.client
+                .put(self.chunk_url(chunk_id))
+                .body(content.clone())
+                .header("Content-Type", "application/octet-stream")
+        }, chunk_id).await?;
+
+        // The registry answers 200 with `success: false` when it rejects
+        // the chunk, e.g. because its hash doesn't match
+        let status = response.status().as_u16();
+        let body: serde_json::Value = response.json().await.unwrap_or_default();
+        if body["success"] == false {
+            return Err(match body["message"].as_str() {
+                Some("Hash mismatch") => RegistryError::HashMismatch(chunk_id.to_string()),
+                message => RegistryError::Server {
+                    status,
+                    body: message.unwrap_or("rejected by registry").to_string(),
+                },
+            });
+        }
+        Ok(size)
     }
-    println!("  {} Total bytes: {}", style("→").cyan(), stats.bytes_published);
 
-    if stats.failed > 0 {
-        return Err(anyhow!("{} chunks failed to publish", stats.failed));
+    /// Publish metadata for a chunk (JSON)
+    async fn publish_metadata(&self, chunk_id: &str, meta_path: &Path) -> RegistryResult<()> {
+        let content = std::fs::read_to_string(meta_path).map_err(RegistryError::Storage)?;
+        let url = format!("{}/v1/chunks/{}/meta", self.registry.trim_end_matches('/'), chunk_id);
+
+        self.send(|| {
+            self.client
+                .put(&url)
+                .body(content.clone())
+                .header("Content-Type", "application/json")
+        }, chunk_id).await?;
+        Ok(())
     }
 
-    println!();
-    println!("{}", style("Publish complete!").green().bold());
+    /// Best-effort delete of the given chunks; returns how many were deleted
+    async fn delete_all(&self, chunk_ids: &[String]) -> usize {
+        let mut deleted = 0;
+        for chunk_id in chunk_ids {
+            match self.client.delete(self.chunk_url(chunk_id)).send().await {
+                Ok(response) if response.status().is_success() => deleted += 1,
+                Ok(response) => say!("  {} Could not delete {}: HTTP {}", style("⚠").yellow(), short_chunk_id(chunk_id), response.status()),
+                Err(e) => say!("  {} Could not delete {}: {}", style("⚠").yellow(), short_chunk_id(chunk_id), e),
+            }
+        }
+        deleted
+    }
+}
+
+/// Publish every chunk in the offline spool
+async fn flush_spool(registry: &str, args: &PublishArgs, config: &CadiConfig) -> Result<PublishReport> {
+    let client = RegistryClient::new(RegistryConfig {
+        url: registry.to_string(),
+        token: args.auth_token.clone().or_else(|| config.auth.token.clone()),
+        namespace: args.namespace.clone(),
+        spool_dir: Some(config.cache.dir.join("spool")),
+        ..Default::default()
+    })
+    .map_err(|e| anyhow!("Failed to create registry client: {}", e))?;
+
+    let result = client.flush_spool().await
+        .map_err(|e| anyhow::Error::new(e).context("Failed to flush spool"))?;
+
+    say!();
+    say!("{}", style("Spool Summary:").bold());
+    say!("  {} Flushed: {}", style("✓").green(), result.flushed.len());
+    if !result.remaining.is_empty() {
+        say!("  {} Remaining: {}", style("→").yellow(), result.remaining.len());
+        return Err(CommandError::Network(format!(
+            "{} spooled chunks could not be published",
+            result.remaining.len()
+        ))
+        .into());
+    }
+
+    Ok(PublishReport {
+        registry: registry.to_string(),
+        published: result.flushed.len(),
+        chunks: result.flushed,
+        ..Default::default()
+    })
+}
+
+/// Aliases in the local `aliases.json` that point at one of `chunk_ids`,
+/// in path order
+fn local_aliases(chunks_dir: &Path, chunk_ids: &[String]) -> Result<Vec<(String, String)>> {
+    let registry_file = chunks_dir.join("aliases.json");
+    if !registry_file.exists() {
+        return Ok(Vec::new());
+    }
+    let registry = cadi_core::AliasRegistry::load(&registry_file)?;
+    let chunk_ids: HashSet<&str> = chunk_ids.iter().map(String::as_str).collect();
+
+    let mut aliases: Vec<(String, String)> = registry
+        .aliases
+        .into_iter()
+        .filter(|(_, chunk_id)| chunk_ids.contains(chunk_id.as_str()))
+        .collect();
+    aliases.sort();
+    Ok(aliases)
+}
+
+/// Push aliases to the registry, so teammates can resolve them there
+async fn publish_aliases(registry: &str, args: &PublishArgs, config: &CadiConfig, aliases: Vec<(String, String)>) -> Result<()> {
+    let client = RegistryClient::new(RegistryConfig {
+        url: registry.to_string(),
+        token: args.auth_token.clone().or_else(|| config.auth.token.clone()),
+        ..Default::default()
+    })
+    .map_err(|e| anyhow!("Failed to create registry client: {}", e))?;
+
+    say!();
+    say!("{}", style("Publishing aliases...").bold());
+    let mut failed = 0;
+    for (path, chunk_id) in &aliases {
+        if let Err(e) = client.publish_alias(path, chunk_id).await {
+            say!("  {} {}: {}", style("✗").red(), path, e);
+            failed += 1;
+        }
+    }
+    say!("  {} Aliases published: {}", style("✓").green(), aliases.len() - failed);
 
+    if failed > 0 {
+        return Err(CommandError::Network(format!("{} aliases failed to publish", failed)).into());
+    }
     Ok(())
 }
 
-/// Create HTTP client with auth headers
-fn create_client(auth_token: &Option<String>) -> Result<Client> {
+/// License recorded in a chunk's metadata file: `license` for atomic chunks,
+/// `licensing.license` for manifest chunks
+fn chunk_license(meta_path: Option<&Path>) -> String {
+    let meta: Option<serde_json::Value> = meta_path
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok());
+
+    meta.as_ref()
+        .and_then(|m| m.get("license").or_else(|| m.get("licensing")?.get("license")))
+        .and_then(|l| l.as_str())
+        .unwrap_or(cadi_core::license::UNKNOWN_LICENSE)
+        .to_string()
+}
+
+/// Granularity recorded in a chunk's metadata file, or `other` for chunks
+/// without one (source and manifest chunks)
+fn chunk_granularity(meta_path: Option<&Path>) -> String {
+    meta_path
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|meta| meta["granularity"].as_str().map(str::to_string))
+        .unwrap_or_else(|| "other".to_string())
+}
+
+/// Create HTTP client with auth and namespace headers
+fn create_client(auth_token: &Option<String>, namespace: &Option<String>) -> Result<Client> {
     let mut headers = header::HeaderMap::new();
+
+    if let Some(namespace) = namespace {
+        headers.insert(
+            cadi_registry::NAMESPACE_HEADER,
+            header::HeaderValue::from_str(namespace)?,
+        );
+    }
     
     if let Some(token) = auth_token {
         let auth_header = format!("Bearer {}", token);
@@ -240,105 +900,218 @@ fn create_client(auth_token: &Option<String>) -> Result<Client> {
         .build()?)
 }
 
-/// Publish a single chunk
-async fn publish_chunk(
-    client: &Client,
-    registry: &str,
-    chunk_id: &str,
-    path: &Path,
-    args: &PublishArgs,
-    config: &CadiConfig,
-) -> Result<usize> {
-    let content = std::fs::read(path)?;
-    let size = content.len();
-
-    // Sign if required
-    if !args.no_sign {
-        if let Some(key_path) = &config.security.signing_key {
-            if key_path.exists() {
-                let key_content = std::fs::read_to_string(key_path)?;
-                let signature = sign_content(&content, &key_content)?;
-                
-                // Update metadata with signature
-                let hash = chunk_id.strip_prefix("chunk:sha256:").unwrap_or(chunk_id);
-                let metadata_path = config.cache.dir.join("chunks").join(format!("{}.json", hash));
-                if metadata_path.exists() {
-                    let mut meta_content: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&metadata_path)?)?;
-                    if let Some(signatures) = meta_content.get_mut("signatures").and_then(|s| s.as_array_mut()) {
-                        signatures.push(serde_json::Value::String(signature));
-                    } else {
-                        meta_content["signatures"] = serde_json::json!([signature]);
-                    }
-                    std::fs::write(&metadata_path, serde_json::to_string_pretty(&meta_content)?)?;
-                }
-            }
+/// Resolve the key to sign with. `--sign` requires a key; otherwise the
+/// configured key is used when present unless `--no-sign` is given.
+fn load_signing_key(args: &PublishArgs, config: &CadiConfig) -> Result<Option<SigningKey>> {
+    if args.no_sign {
+        return Ok(None);
+    }
+
+    let key_path = args.key.clone().or_else(|| config.security.signing_key.clone());
+    let required = args.sign || args.resign;
+    match key_path {
+        Some(path) if required || path.exists() => SigningKey::load(&path)
+            .map(Some)
+            .map_err(|e| anyhow!("Failed to load signing key {}: {}", path.display(), e)),
+        None if required => Err(anyhow!("--sign and --resign require --key or a configured signing key")),
+        _ => Ok(None),
+    }
+}
+
+/// Local chunks with a signature by a retired key, made before its
+/// retirement or undated, that `public_key` has not signed yet, sorted
+fn retired_key_chunks(local: &HashMap<String, PublishItem>, trust_store: &TrustStore, public_key: &str) -> Result<Vec<String>> {
+    let mut ids = Vec::new();
+    for item in local.values() {
+        let signatures = chunk_signatures(item.meta_path.as_deref());
+        if signatures.iter().any(|signature| signed_by(signature, public_key)) {
+            continue;
+        }
+        let statuses = trust_store.verify_chunk(&item.id, &signatures)?;
+        if statuses.iter().any(|status| matches!(status, SignatureStatus::Retired { .. } | SignatureStatus::RetiredUndated { .. })) {
+            ids.push(item.id.clone());
         }
     }
+    ids.sort();
+    Ok(ids)
+}
+
+/// Signatures recorded in a chunk's metadata file
+fn chunk_signatures(meta_path: Option<&Path>) -> Vec<String> {
+    meta_path
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|meta| serde_json::from_value(meta.get("signatures")?.clone()).ok())
+        .unwrap_or_default()
+}
 
-    // Build URL with namespace if provided
-    let url = if let Some(ref ns) = args.namespace {
-        format!("{}/v1/namespaces/{}/chunks/{}", 
-            registry.trim_end_matches('/'), ns, chunk_id)
+fn signed_by(signature: &str, public_key: &str) -> bool {
+    signature
+        .strip_prefix(SIGNATURE_PREFIX)
+        .and_then(|rest| rest.strip_prefix(public_key))
+        .is_some_and(|rest| rest.starts_with(':'))
+}
+
+/// Add a signature for the chunk to its local metadata file, creating the
+/// file if needed. Returns the metadata path.
+fn sign_chunk(chunks_dir: &Path, chunk_id: &str, key: &SigningKey) -> Result<PathBuf> {
+    let metadata_path = chunks_dir.join(format!("{}.json", chunk_storage_key(chunk_id)));
+
+    let mut meta_content: serde_json::Value = if metadata_path.exists() {
+        serde_json::from_str(&std::fs::read_to_string(&metadata_path)?)?
     } else {
-        format!("{}/v1/chunks/{}", registry.trim_end_matches('/'), chunk_id)
+        serde_json::json!({ "chunk_id": chunk_id })
     };
 
-    let response = client
-        .put(&url)
-        .body(content)
-        .header("Content-Type", "application/octet-stream")
-        .send()
-        .await
-        .map_err(|e| anyhow!("Network error: {}", e))?;
-
-    match response.status() {
-        reqwest::StatusCode::OK | reqwest::StatusCode::CREATED => Ok(size),
-        reqwest::StatusCode::CONFLICT => {
-            Err(anyhow!("Chunk already exists at registry"))
-        }
-        status => {
-            let body = response.text().await.unwrap_or_default();
-            Err(anyhow!("HTTP {}: {}", status, body))
-        }
+    // Signatures are dated, so keep an existing one by the key instead of
+    // adding another on every publish
+    let signature = serde_json::Value::String(key.sign_chunk(chunk_id));
+    let public_key = key.public_key();
+    match meta_content.get_mut("signatures").and_then(|s| s.as_array_mut()) {
+        Some(signatures) if signatures.iter().any(|s| s.as_str().is_some_and(|s| signed_by(s, &public_key))) => {}
+        Some(signatures) => signatures.push(signature),
+        None => meta_content["signatures"] = serde_json::json!([signature]),
     }
+
+    std::fs::write(&metadata_path, serde_json::to_string_pretty(&meta_content)?)?;
+    Ok(metadata_path)
 }
 
-/// Publish metadata for a chunk (JSON)
-async fn publish_metadata(
-    client: &Client,
-    registry: &str,
-    chunk_id: &str,
-    meta_path: &Path,
-    _args: &PublishArgs,
-    _config: &CadiConfig,
-) -> Result<()> {
-    let content = std::fs::read_to_string(meta_path)?;
-
-    // Build URL with namespace if provided
-    let url = format!("{}/v1/chunks/{}/meta", registry.trim_end_matches('/'), chunk_id);
-
-    let response = client
-        .put(&url)
-        .body(content)
-        .header("Content-Type", "application/json")
-        .send()
-        .await
-        .map_err(|e| anyhow!("Network error: {}", e))?;
-
-    match response.status() {
-        reqwest::StatusCode::OK | reqwest::StatusCode::CREATED => Ok(()),
-        status => {
-            let body = response.text().await.unwrap_or_default();
-            Err(anyhow!("HTTP {}: {}", status, body))
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cadi_core::trust::TrustVerdict;
+
+    /// A local chunk whose data references `references`
+    fn item(dir: &Path, id: &str, references: &[&str]) -> PublishItem {
+        let chunk_path = dir.join(format!("{}.chunk", id));
+        let composed_of: Vec<_> = references.iter().map(|r| serde_json::json!({ "chunk_id": r })).collect();
+        let data = serde_json::json!({ "chunk_id": id, "composition": { "composed_of": composed_of } });
+        std::fs::write(&chunk_path, data.to_string()).unwrap();
+        PublishItem {
+            id: id.to_string(),
+            chunk_path: Some(chunk_path),
+            meta_path: None,
         }
     }
-}
 
-fn sign_content(content: &[u8], key_content: &str) -> Result<String> {
-    use sha2::{Sha256, Digest};
-    let mut hasher = Sha256::new();
-    hasher.update(key_content.as_bytes());
-    hasher.update(content);
-    let result = hasher.finalize();
-    Ok(format!("sig:sha256:{}", hex::encode(result)))
+    fn level_ids(levels: &[Vec<PublishItem>]) -> Vec<Vec<&str>> {
+        levels.iter().map(|level| level.iter().map(|item| item.id.as_str()).collect()).collect()
+    }
+
+    #[test]
+    fn test_components_are_published_before_compositions() {
+        let dir = tempfile::tempdir().unwrap();
+        let items = vec![
+            item(dir.path(), "chunk:app", &["chunk:lib", "chunk:util"]),
+            item(dir.path(), "chunk:lib", &["chunk:util", "chunk:remote"]),
+            item(dir.path(), "chunk:util", &[]),
+            item(dir.path(), "chunk:docs", &[]),
+        ];
+
+        // References outside the publish (chunk:remote) don't hold it up
+        assert_eq!(
+            level_ids(&publish_levels(items)),
+            vec![vec!["chunk:docs", "chunk:util"], vec!["chunk:lib"], vec!["chunk:app"]]
+        );
+    }
+
+    #[test]
+    fn test_reference_cycles_are_published_last() {
+        let dir = tempfile::tempdir().unwrap();
+        let items = vec![
+            item(dir.path(), "chunk:a", &["chunk:b"]),
+            item(dir.path(), "chunk:b", &["chunk:a"]),
+            item(dir.path(), "chunk:c", &[]),
+        ];
+
+        assert_eq!(
+            level_ids(&publish_levels(items)),
+            vec![vec!["chunk:c"], vec!["chunk:a", "chunk:b"]]
+        );
+    }
+
+    #[test]
+    fn test_manifest_chunks_include_local_references() {
+        let dir = tempfile::tempdir().unwrap();
+        let local: HashMap<String, PublishItem> = [
+            item(dir.path(), "chunk:sha256:app", &["chunk:sha256:lib"]),
+            item(dir.path(), "chunk:sha256:lib", &[]),
+        ]
+        .into_iter()
+        .map(|item| (chunk_storage_key(&item.id), item))
+        .collect();
+
+        let manifest = dir.path().join("app.cadi.yaml");
+        std::fs::write(
+            &manifest,
+            "build_graph:\n  nodes:\n    - id: app\n      source_cadi: chunk:sha256:app\n      blob_cadi: chunk:sha256:blob\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            manifest_chunks(&manifest, &local).unwrap(),
+            vec!["chunk:sha256:app", "chunk:sha256:blob", "chunk:sha256:lib"]
+        );
+    }
+
+    #[test]
+    fn test_resign_selects_chunks_signed_by_retired_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let trust = TrustStore::open(dir.path().join("trust"));
+        let old = SigningKey::from_seed(&[1u8; 32]).unwrap();
+        let new = SigningKey::from_seed(&[2u8; 32]).unwrap();
+        trust.add("alice", &old.public_key_pem()).unwrap();
+
+        let chunks_dir = dir.path().join("chunks");
+        std::fs::create_dir_all(&chunks_dir).unwrap();
+        let mut local = HashMap::new();
+        for id in ["chunk:sha256:signed", "chunk:sha256:unsigned"] {
+            let mut item = item(&chunks_dir, &chunk_storage_key(id), &[]);
+            item.id = id.to_string();
+            local.insert(chunk_storage_key(id), item);
+        }
+        let meta_path = sign_chunk(&chunks_dir, "chunk:sha256:signed", &old).unwrap();
+        local.get_mut(&chunk_storage_key("chunk:sha256:signed")).unwrap().meta_path = Some(meta_path.clone());
+
+        // Nothing to do until the key is retired
+        assert!(retired_key_chunks(&local, &trust, &new.public_key()).unwrap().is_empty());
+        trust.rotate(&old, &new.public_key_pem(), chrono::Utc::now() + chrono::Duration::seconds(5)).unwrap();
+        assert_eq!(
+            retired_key_chunks(&local, &trust, &new.public_key()).unwrap(),
+            vec!["chunk:sha256:signed"]
+        );
+
+        // Re-signing appends one signature, leaving the old one in place
+        sign_chunk(&chunks_dir, "chunk:sha256:signed", &new).unwrap();
+        sign_chunk(&chunks_dir, "chunk:sha256:signed", &new).unwrap();
+        let signatures = chunk_signatures(Some(&meta_path));
+        assert_eq!(signatures.len(), 2);
+        assert!(signed_by(&signatures[0], &old.public_key()));
+        assert_eq!(
+            TrustVerdict::from_statuses(&trust.verify_chunk("chunk:sha256:signed", &signatures).unwrap()),
+            TrustVerdict::SignedBy(vec!["alice".to_string()])
+        );
+        assert!(retired_key_chunks(&local, &trust, &new.public_key()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_only_aliases_of_published_chunks_are_pushed() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(local_aliases(dir.path(), &["chunk:a".to_string()]).unwrap().is_empty());
+
+        let mut registry = cadi_core::AliasRegistry::new();
+        registry.register("acme/utils/logger", "chunk:a");
+        registry.register("acme/utils/config", "chunk:a");
+        registry.register("acme/app", "chunk:unpublished");
+        registry.save(&dir.path().join("aliases.json")).unwrap();
+
+        assert_eq!(
+            local_aliases(dir.path(), &["chunk:a".to_string()]).unwrap(),
+            vec![
+                ("acme/utils/config".to_string(), "chunk:a".to_string()),
+                ("acme/utils/logger".to_string(), "chunk:a".to_string()),
+            ]
+        );
+    }
 }
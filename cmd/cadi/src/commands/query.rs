@@ -1,10 +1,20 @@
-use anyhow::{Result, anyhow};
-use clap::Args;
+use anyhow::{Context, Result};
+use clap::builder::PossibleValuesParser;
+use clap::{Args, ValueEnum};
 use console::style;
 use reqwest::Client;
-use serde_json::json;
+use serde::Serialize;
+use std::path::PathBuf;
+
+use cadi_core::graph::GraphStore;
+use cadi_core::{AliasIndex, AliasRegistry, AliasResolution, ChunkCategory, ChunkGranularity};
+use cadi_registry::client::{ChunkSummary, RegistryClient, RegistryConfig, SearchQuery};
+use cadi_registry::types::{QueryOutput, QueryRecord, QUERY_OUTPUT_VERSION};
+use cadi_registry::federation::FederationManager;
 
 use crate::config::CadiConfig;
+use crate::error::CommandError;
+use crate::output::{self, say};
 
 /// Arguments for the query command
 #[derive(Args)]
@@ -17,6 +27,10 @@ pub struct QueryArgs {
     #[arg(short, long)]
     chunk_id: Option<String>,
 
+    /// Resolve an alias from the local cache, suggesting close matches
+    #[arg(short, long)]
+    alias: Option<String>,
+
     /// Filter by language
     #[arg(short, long)]
     language: Option<String>,
@@ -29,28 +43,194 @@ pub struct QueryArgs {
     #[arg(long)]
     semantic: bool,
 
-    /// Output format (json, table)
-    #[arg(short, long, default_value = "table")]
-    format: String,
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = QueryFormat::Table)]
+    format: QueryFormat,
+
+    /// Comma-separated fields to show: table columns, or the keys of each
+    /// JSON/YAML record
+    #[arg(long, value_delimiter = ',', value_parser = PossibleValuesParser::new(QueryRecord::FIELDS))]
+    fields: Vec<String>,
+
+    /// Write the output to a file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Print only the number of matching chunks
+    #[arg(long)]
+    count_only: bool,
 
     /// Limit results
     #[arg(long, default_value = "10")]
     limit: usize,
+
+    /// Filter by granularity (function, type, module, package, project)
+    #[arg(long)]
+    granularity: Option<ChunkGranularity>,
+
+    /// Filter by category (repeatable; matches any), e.g. utility, api, ui
+    #[arg(long = "category")]
+    categories: Vec<ChunkCategory>,
+
+    /// Minimum confidence (0.0-1.0) of the chunk's inferred category;
+    /// overridden categories always pass
+    #[arg(long)]
+    min_confidence: Option<f32>,
+
+    /// Maximum chunk size in bytes
+    #[arg(long)]
+    max_size: Option<usize>,
+
+    /// Minimum quality score (0.0-1.0)
+    #[arg(long)]
+    min_quality: Option<f32>,
+
+    /// Maximum cyclomatic complexity (chunks without metrics are excluded)
+    #[arg(long)]
+    max_complexity: Option<f32>,
+}
+
+impl QueryArgs {
+    /// Whether to print progress lines ahead of the results
+    fn shows_progress(&self) -> bool {
+        self.format == QueryFormat::Table && !self.count_only && self.output.is_none()
+    }
+
+    fn has_filters(&self) -> bool {
+        self.granularity.is_some()
+            || !self.categories.is_empty()
+            || self.min_confidence.is_some()
+            || self.max_size.is_some()
+            || self.min_quality.is_some()
+            || self.max_complexity.is_some()
+    }
+
+    fn search_query(&self) -> SearchQuery {
+        SearchQuery {
+            query: self.name.clone().or_else(|| self.chunk_id.clone()),
+            language: self.language.clone(),
+            granularity: self.granularity,
+            categories: self.categories.clone(),
+            min_category_confidence: self.min_confidence,
+            max_size: self.max_size,
+            min_quality: self.min_quality,
+            max_complexity: self.max_complexity,
+            limit: self.limit,
+            ..Default::default()
+        }
+    }
+}
+
+/// Output formats of the query command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum QueryFormat {
+    /// Aligned columns
+    Table,
+    /// Query output document with sorted keys
+    Json,
+    /// The JSON document as YAML
+    Yaml,
+}
+
+/// Result of a query, the data of its `--json` document
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum QueryReport {
+    Chunks(QueryOutput),
+    Alias(AliasResolution),
+}
+
+/// Execute the query command. An alias that doesn't resolve is an error,
+/// after its suggestions have been shown.
+pub async fn execute(args: QueryArgs, config: &CadiConfig) -> Result<QueryReport> {
+    if args.output.is_some() {
+        console::set_colors_enabled(false);
+    }
+
+    let (rendered, report) = match &args.alias {
+        Some(alias) => {
+            let resolution = resolve_alias(alias, config)?;
+            (render_resolution(alias, &args, &resolution)?, QueryReport::Alias(resolution))
+        }
+        None => {
+            let records = fetch_records(&args, config).await?;
+            (render(&args, &records)?, QueryReport::Chunks(query_output(&args, &records)))
+        }
+    };
+
+    match &args.output {
+        Some(path) => std::fs::write(path, rendered)
+            .with_context(|| format!("Failed to write {}", path.display()))?,
+        // The report is the output
+        None if output::json() => {}
+        None => print!("{}", rendered),
+    }
+
+    if let (Some(alias), QueryReport::Alias(AliasResolution::NotFound { suggestions })) = (&args.alias, &report) {
+        let mut message = format!("Alias '{}' not found", alias);
+        if !suggestions.is_empty() {
+            let aliases: Vec<&str> = suggestions.iter().map(|s| s.alias.as_str()).collect();
+            message.push_str(&format!("; did you mean {}?", aliases.join(", ")));
+        }
+        return Err(CommandError::NotFound(message).into());
+    }
+    Ok(report)
 }
 
-/// Execute the query command
-pub async fn execute(args: QueryArgs, config: &CadiConfig) -> Result<()> {
+/// Run the query against the federated registries, or the given or
+/// configured registry, and collect the matches
+async fn fetch_records(args: &QueryArgs, config: &CadiConfig) -> Result<Vec<QueryRecord>> {
     let registry = args.registry.as_ref()
         .unwrap_or(&config.registry.url);
 
-    if args.format == "table" {
-        println!("{}", style("Querying registry...").bold());
-        println!("  Registry: {}", registry);
+    // Query the federated registries unless a registry was given explicitly
+    if args.registry.is_none() && !args.semantic {
+        let federation = FederationManager::from_default_config();
+        if !federation.registries().is_empty() {
+            return query_federation(args, &federation).await;
+        }
+    }
+
+    if args.shows_progress() {
+        say!("{}", style("Querying registry...").bold());
+        say!("  Registry: {}", registry);
+    }
+
+    // If semantic flag is enabled, call semantic_search endpoint
+    if args.semantic {
+        let client = RegistryClient::new(RegistryConfig {
+            url: registry.to_string(),
+            timeout: std::time::Duration::from_secs(30),
+            ..Default::default()
+        })?;
+        let text = args.name.clone().or_else(|| args.chunk_id.clone()).unwrap_or_default();
+        let hits = client.semantic_search(&text, args.limit).await?;
+        return Ok(hits.into_iter()
+            .map(|(chunk, score)| QueryRecord {
+                // Four decimals keep f32 noise out of the output
+                score: Some((f64::from(score) * 1e4).round() / 1e4),
+                ..summary_record(chunk, registry)
+            })
+            .collect());
     }
 
-    // Build query
-    let client = Client::new();
-    let mut url = format!("{}/v1/chunks", registry);
+    // Structural filters need the search endpoint
+    if args.has_filters() {
+        let client = RegistryClient::new(RegistryConfig {
+            url: registry.to_string(),
+            ..Default::default()
+        })?;
+        let result = client.search(&args.search_query()).await?;
+        return Ok(result.chunks.into_iter()
+            .map(|chunk| summary_record(chunk, registry))
+            .collect());
+    }
+
+    list_chunks(args, registry).await
+}
+
+/// Query the registry's chunk listing by name, chunk ID and language
+async fn list_chunks(args: &QueryArgs, registry: &str) -> Result<Vec<QueryRecord>> {
     let mut params = Vec::new();
 
     if let Some(ref name) = args.name {
@@ -66,52 +246,20 @@ pub async fn execute(args: QueryArgs, config: &CadiConfig) -> Result<()> {
     }
 
     params.push(format!("limit={}", args.limit));
+    let url = format!("{}/v1/chunks?{}", registry, params.join("&"));
 
-    if !params.is_empty() {
-        url = format!("{}?{}", url, params.join("&"));
-    }
-
-    // If semantic flag is enabled, call semantic_search endpoint
-    if args.semantic {
-        let reg_config = cadi_registry::client::RegistryConfig {
-            url: registry.to_string(),
-            token: None,
-            timeout: std::time::Duration::from_secs(30),
-            verify_tls: true,
-            max_concurrent: 4,
-        };
-        let client = cadi_registry::client::RegistryClient::new(reg_config)?;
-        let hits = client.semantic_search(&args.name.clone().unwrap_or_else(|| args.chunk_id.clone().unwrap_or_default()), args.limit).await?;
-        if args.format == "json" {
-            let out: serde_json::Value = serde_json::json!({ "results": hits.iter().map(|(c,s)| serde_json::json!({ "chunk_id": c.chunk_id, "score": s })).collect::<Vec<_>>() });
-            println!("{}", serde_json::to_string_pretty(&out)?);
-        } else {
-            println!();
-            if hits.is_empty() {
-                println!("  {} No matching chunks found", style("!").yellow());
-            } else {
-                println!("  {} {} chunk(s) found:", style("✓").green(), hits.len());
-                println!();
-                for (chunk, score) in hits {
-                    println!("  {} {} (score {:.3})", style("•").cyan(), style(&chunk.chunk_id).bold(), score);
-                }
-            }
-        }
-        return Ok(());
-    }
-
-    // Execute query
-    let response = client
+    let response = Client::new()
         .get(&url)
         .header("Accept", "application/json")
         .send()
         .await?;
 
     if !response.status().is_success() {
-        return Err(anyhow!(
+        return Err(CommandError::Network(format!(
             "Registry query failed: {}",
             response.status()
-        ));
+        ))
+        .into());
     }
 
     let data: serde_json::Value = response.json().await?;
@@ -122,50 +270,264 @@ pub async fn execute(args: QueryArgs, config: &CadiConfig) -> Result<()> {
     } else {
         data["chunks"]
             .as_array()
-            .ok_or_else(|| anyhow!("Invalid response format"))?
+            .ok_or_else(|| CommandError::Network("Invalid response format".to_string()))?
+    };
+
+    let text = |chunk: &serde_json::Value, key: &str| chunk[key].as_str().map(str::to_string);
+    Ok(chunks.iter()
+        .map(|chunk| QueryRecord {
+            chunk_id: text(chunk, "chunk_id").unwrap_or_else(|| "unknown".to_string()),
+            name: text(chunk, "name"),
+            cadi_type: text(chunk, "cadi_type"),
+            content_type: text(chunk, "content_type"),
+            language: text(chunk, "language"),
+            size: chunk["size"].as_u64(),
+            registry: Some(registry.to_string()),
+            score: None,
+        })
+        .collect())
+}
+
+/// A search result as a query record, treating empty strings as unknown
+fn summary_record(chunk: ChunkSummary, registry: &str) -> QueryRecord {
+    let known = |value: String| Some(value).filter(|v| !v.is_empty());
+    QueryRecord {
+        chunk_id: chunk.chunk_id,
+        name: known(chunk.name),
+        cadi_type: known(chunk.cadi_type),
+        language: chunk.language,
+        size: chunk.size,
+        registry: Some(registry.to_string()),
+        ..Default::default()
+    }
+}
+
+/// Render the matches in the requested format
+fn render(args: &QueryArgs, records: &[QueryRecord]) -> Result<String> {
+    if args.count_only {
+        return Ok(format!("{}\n", records.len()));
+    }
+
+    Ok(match args.format {
+        QueryFormat::Json => format!("{}\n", serde_json::to_string_pretty(&query_output(args, records))?),
+        QueryFormat::Yaml => serde_yaml::to_string(&query_output(args, records))?,
+        QueryFormat::Table => render_table(args, records),
+    })
+}
+
+/// The matches as a query output document, projected to `--fields`
+fn query_output(args: &QueryArgs, records: &[QueryRecord]) -> QueryOutput {
+    QueryOutput {
+        chunks: records.iter().map(|record| record.project(&args.fields)).collect(),
+        count: records.len(),
+        version: QUERY_OUTPUT_VERSION,
+    }
+}
+
+/// Render the matches as aligned columns. Without `--fields` the columns are
+/// the chunk ID, name, language and size, plus the score for semantic
+/// queries and the registry when results came from several.
+fn render_table(args: &QueryArgs, records: &[QueryRecord]) -> String {
+    let mut out = String::from("\n");
+    if records.is_empty() {
+        out.push_str(&format!("  {} No matching chunks found\n", style("!").yellow()));
+        return out;
+    }
+    out.push_str(&format!("  {} {} chunk(s) found:\n\n", style("✓").green(), records.len()));
+
+    let columns: Vec<String> = if args.fields.is_empty() {
+        let mut columns: Vec<String> = ["chunk_id", "name", "language", "size"]
+            .map(String::from)
+            .to_vec();
+        if records.iter().any(|r| r.score.is_some()) {
+            columns.push("score".to_string());
+        }
+        let first = &records[0].registry;
+        if records.iter().any(|r| r.registry != *first) {
+            columns.push("registry".to_string());
+        }
+        columns
+    } else {
+        args.fields.clone()
+    };
+
+    let cell = |record: &QueryRecord, column: &str| match record.field(column) {
+        Some(serde_json::Value::String(s)) => s,
+        None | Some(serde_json::Value::Null) => "-".to_string(),
+        Some(value) => value.to_string(),
+    };
+    let rows: Vec<Vec<String>> = records.iter()
+        .map(|record| columns.iter().map(|c| cell(record, c)).collect())
+        .collect();
+    let widths: Vec<usize> = columns.iter().enumerate()
+        .map(|(i, c)| rows.iter().map(|row| row[i].chars().count()).chain([c.len()]).max().unwrap_or(0))
+        .collect();
+
+    // Pad before styling so escape codes don't skew the alignment
+    let line = |cells: Vec<String>| {
+        let padded: Vec<String> = cells.iter().zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        padded.join("  ").trim_end().to_string()
     };
+    let header = line(columns.iter().map(|c| c.to_uppercase()).collect());
+    out.push_str(&format!("  {}\n", style(header).bold()));
+    for row in rows {
+        out.push_str(&format!("  {}\n", line(row)));
+    }
+    out
+}
 
-    if args.format == "json" {
-        // Output as JSON - wrap array in object for consistency
-        let output = if data.is_array() {
-            json!({ "chunks": chunks })
-        } else {
-            data
-        };
-        println!("{}", serde_json::to_string_pretty(&output)?);
+/// Resolve an alias against the imported aliases and the graph store
+fn resolve_alias(alias: &str, config: &CadiConfig) -> Result<AliasResolution> {
+    let registry_file = config.cache.dir.join("chunks").join("aliases.json");
+    let mut index = if registry_file.exists() {
+        AliasIndex::from_registry(&AliasRegistry::load(&registry_file)?)
     } else {
-        // Output as table
-        println!();
-        if chunks.is_empty() {
-            println!("  {} No matching chunks found", style("!").yellow());
-        } else {
-            println!("  {} {} chunk(s) found:", style("✓").green(), chunks.len());
-            println!();
-
-            for chunk in chunks {
-                let chunk_id = chunk["chunk_id"]
-                    .as_str()
-                    .unwrap_or("unknown");
-                let size = chunk["size"]
-                    .as_u64()
-                    .unwrap_or(0);
-                let content_type = chunk["content_type"]
-                    .as_str()
-                    .unwrap_or("unknown");
-
-                let display_id = if chunk_id.len() > 60 {
-                    format!("{}...{}", &chunk_id[..30], &chunk_id[chunk_id.len()-10..])
-                } else {
-                    chunk_id.to_string()
-                };
-
-                println!("  {} {}", style("•").cyan(), style(display_id).bold());
-                println!("    Size: {} bytes", size);
-                println!("    Type: {}", content_type);
-                println!();
+        AliasIndex::new()
+    };
+
+    let graph_dir = config.cache.dir.join("graph-db");
+    if graph_dir.exists() {
+        index.add_graph(&GraphStore::open(&graph_dir)?)?;
+    }
+
+    Ok(index.resolve(alias))
+}
+
+/// Render an alias resolution in the requested format
+fn render_resolution(alias: &str, args: &QueryArgs, resolution: &AliasResolution) -> Result<String> {
+    match args.format {
+        QueryFormat::Json => return Ok(format!("{}\n", serde_json::to_string_pretty(resolution)?)),
+        QueryFormat::Yaml => return Ok(serde_yaml::to_string(resolution)?),
+        QueryFormat::Table => {}
+    }
+
+    let mut out = String::new();
+    match resolution {
+        AliasResolution::Found(found) => {
+            out.push_str(&format!("  {} {} → {}\n", style("✓").green(), found.alias, style(&found.chunk_id).bold()));
+        }
+        AliasResolution::NotFound { suggestions } => {
+            out.push_str(&format!("  {} Alias '{}' not found\n", style("!").yellow(), alias));
+            if !suggestions.is_empty() {
+                out.push_str("\n  Did you mean:\n");
+                for suggestion in suggestions {
+                    out.push_str(&format!(
+                        "  {} {} → {} {}\n",
+                        style("•").cyan(),
+                        suggestion.alias,
+                        suggestion.chunk_id,
+                        style(format!("({:.0}% similar)", suggestion.score * 100.0)).dim()
+                    ));
+                }
             }
         }
     }
 
-    Ok(())
+    Ok(out)
+}
+
+/// Search every healthy federated registry, highest priority first
+async fn query_federation(args: &QueryArgs, federation: &FederationManager) -> Result<Vec<QueryRecord>> {
+    let healthy = federation.check_health().await;
+    if args.shows_progress() {
+        say!("{}", style("Querying federated registries...").bold());
+        say!("  Registries: {}/{} healthy", healthy, federation.registries().len());
+    }
+
+    let results = federation.search(&args.search_query()).await
+        .map_err(|e| CommandError::Network(format!("Federated query failed: {}", e)))?;
+
+    Ok(results.into_iter()
+        .map(|(chunk, registry)| summary_record(chunk, &registry))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: QueryArgs,
+    }
+
+    fn args(flags: &[&str]) -> QueryArgs {
+        Cli::parse_from(std::iter::once("query").chain(flags.iter().copied())).args
+    }
+
+    fn records() -> Vec<QueryRecord> {
+        vec![
+            QueryRecord {
+                chunk_id: "chunk:sha256:aaa".to_string(),
+                name: Some("parse_config".to_string()),
+                language: Some("rust".to_string()),
+                size: Some(120),
+                registry: Some("http://localhost:8080".to_string()),
+                ..Default::default()
+            },
+            QueryRecord {
+                chunk_id: "chunk:sha256:bbb".to_string(),
+                size: Some(64),
+                registry: Some("http://localhost:8080".to_string()),
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn test_json_output_is_sorted_and_projected() {
+        let full = render(&args(&["--format", "json"]), &records()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&full).unwrap();
+        assert_eq!(value["count"], 2);
+        assert_eq!(value["version"], QUERY_OUTPUT_VERSION);
+        let keys: Vec<&String> = value["chunks"][1].as_object().unwrap().keys().collect();
+        assert_eq!(keys, QueryRecord::FIELDS);
+        assert!(value["chunks"][1]["name"].is_null());
+        // Key order is part of the contract, not just the parsed value
+        assert!(full.find("\"chunks\"").unwrap() < full.find("\"count\"").unwrap());
+
+        let projected = render(&args(&["--format", "json", "--fields", "size,chunk_id"]), &records()).unwrap();
+        let output: QueryOutput = serde_json::from_str(&projected).unwrap();
+        assert_eq!(
+            serde_json::to_value(&output.chunks[0]).unwrap(),
+            serde_json::json!({"chunk_id": "chunk:sha256:aaa", "size": 120})
+        );
+        assert!(projected.find("\"chunk_id\"").unwrap() < projected.find("\"size\"").unwrap());
+
+        let yaml = render(&args(&["--format", "yaml", "--fields", "name"]), &records()).unwrap();
+        let output: QueryOutput = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(output, serde_json::from_str::<QueryOutput>(
+            &render(&args(&["--format", "json", "--fields", "name"]), &records()).unwrap()
+        ).unwrap());
+    }
+
+    #[test]
+    fn test_count_only_and_table_columns() {
+        assert_eq!(render(&args(&["--count-only", "--format", "json"]), &records()).unwrap(), "2\n");
+        assert_eq!(render(&args(&["--count-only"]), &[]).unwrap(), "0\n");
+
+        console::set_colors_enabled(false);
+        let table = render(&args(&["--fields", "name,size"]), &records()).unwrap();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(&lines[3..], ["  NAME          SIZE", "  parse_config  120", "  -             64"]);
+
+        let table = render(&args(&[]), &records()).unwrap();
+        assert!(table.contains("CHUNK_ID          NAME          LANGUAGE  SIZE"));
+        assert!(!table.contains("REGISTRY"));
+
+        assert!(Cli::try_parse_from(["query", "--fields", "chunk_id,colour"]).is_err());
+    }
+
+    #[test]
+    fn test_category_confidence_filter() {
+        let query = args(&["--category", "api", "--min-confidence", "0.7"]);
+        assert!(query.has_filters());
+        let search = query.search_query();
+        assert_eq!(search.categories, vec![ChunkCategory::Api]);
+        assert_eq!(search.min_category_confidence, Some(0.7));
+    }
 }
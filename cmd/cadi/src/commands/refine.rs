@@ -0,0 +1,67 @@
+use anyhow::{anyhow, Result};
+use cadi_core::graph::GraphStore;
+use clap::Args;
+use console::style;
+use std::path::PathBuf;
+
+use crate::config::CadiConfig;
+use crate::output::say;
+
+/// Arguments for the refine command
+#[derive(Args)]
+pub struct RefineArgs {
+    /// Chunk ID or alias of the chunk being replaced
+    old: String,
+
+    /// Chunk ID or alias of the newer version
+    new: String,
+
+    /// Why the newer version exists
+    #[arg(long)]
+    reason: Option<String>,
+
+    /// Graph store directory (defaults to <cache-dir>/graph-db)
+    #[arg(long)]
+    graph_dir: Option<PathBuf>,
+}
+
+/// Execute the refine command
+pub async fn execute(args: RefineArgs, config: &CadiConfig) -> Result<()> {
+    let graph_dir = args
+        .graph_dir
+        .clone()
+        .unwrap_or_else(|| config.cache.dir.join("graph-db"));
+    let store = GraphStore::open(&graph_dir)?;
+
+    let old = resolve(&store, &args.old)?;
+    let new = resolve(&store, &args.new)?;
+    let refinement = store.mark_refinement(&old, &new, args.reason.as_deref().unwrap_or_default())?;
+    store.flush()?;
+
+    say!(
+        "{} {} refines {}",
+        style("✓").green(),
+        style(&refinement.new_chunk).cyan(),
+        refinement.old_chunk
+    );
+    if !refinement.reason.is_empty() {
+        say!("  Reason: {}", refinement.reason);
+    }
+    if let Some(latest) = store.get_latest_refinement(&old)? {
+        if latest != new {
+            say!("  Latest version of {} is now {}", old, latest);
+        }
+    }
+
+    Ok(())
+}
+
+/// A chunk ID, or the chunk an alias points at
+fn resolve(store: &GraphStore, reference: &str) -> Result<String> {
+    if store.node_exists(reference)? {
+        return Ok(reference.to_string());
+    }
+    store
+        .resolve_alias(reference)?
+        .ok_or_else(|| anyhow!("No chunk or alias '{}' in the graph", reference))
+}
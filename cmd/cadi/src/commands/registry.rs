@@ -0,0 +1,224 @@
+use anyhow::{anyhow, Result};
+use clap::{Args, Subcommand};
+use console::style;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::time::Duration;
+
+use cadi_registry::client::{RegistryClient, RegistryConfig};
+use cadi_registry::federation::{FederatedRegistry, FederationManager};
+use cadi_registry::sync::{SyncFilter, SyncReport};
+
+use crate::config::CadiConfig;
+use crate::output::say;
+
+/// Arguments for the registry command
+#[derive(Args)]
+pub struct RegistryArgs {
+    #[command(subcommand)]
+    command: RegistryCommands,
+}
+
+#[derive(Subcommand)]
+enum RegistryCommands {
+    /// Re-embed every chunk with the registry's current embedding model
+    Reembed {
+        /// Registry to maintain
+        #[arg(short, long)]
+        registry: Option<String>,
+
+        /// Admin token (defaults to the configured auth token)
+        #[arg(long, env = "CADI_ADMIN_TOKEN")]
+        token: Option<String>,
+
+        /// Chunks re-embedded per batch
+        #[arg(long, default_value = "64")]
+        batch_size: usize,
+
+        /// Return once the run has started instead of following it
+        #[arg(long)]
+        detach: bool,
+    },
+
+    /// Copy the chunks and aliases one registry has and another lacks
+    Sync {
+        /// Registry to copy from (federation ID or URL)
+        #[arg(long)]
+        from: String,
+
+        /// Registry to copy to (federation ID or URL)
+        #[arg(long)]
+        to: String,
+
+        /// Only copy aliases under this prefix and the chunks they point at
+        #[arg(long)]
+        namespace: Option<String>,
+
+        /// Output format (text, json)
+        #[arg(long, default_value = "text")]
+        format: String,
+    },
+}
+
+/// Execute the registry command
+pub async fn execute(args: RegistryArgs, config: &CadiConfig) -> Result<()> {
+    match args.command {
+        RegistryCommands::Reembed { registry, token, batch_size, detach } => {
+            let url = registry.unwrap_or_else(|| config.registry.url.clone());
+            let client = RegistryClient::new(RegistryConfig {
+                url: url.clone(),
+                token: token.or_else(|| config.auth.token.clone()),
+                ..Default::default()
+            })
+            .map_err(|e| anyhow!("Failed to create registry client: {}", e))?;
+
+            let status = client.start_reembed(batch_size).await
+                .map_err(|e| anyhow!("Failed to start re-embedding on {}: {}", url, e))?;
+            say!("{} Re-embedding started on {}", style("✓").green(), url);
+            if let Some(ref progress) = status.progress {
+                if !progress.done && progress.processed > 0 {
+                    say!("  Resuming at {}/{} chunks", progress.processed, progress.total);
+                }
+            }
+            if detach {
+                return Ok(());
+            }
+
+            let bar = ProgressBar::new(0);
+            bar.set_style(
+                ProgressStyle::default_bar()
+                    .template("{msg}\n{wide_bar:.cyan/blue} {pos}/{len}")
+                    .unwrap(),
+            );
+            bar.set_message("Re-embedding chunks...");
+
+            loop {
+                let status = client.reembed_status().await
+                    .map_err(|e| anyhow!("Failed to fetch re-embedding status: {}", e))?;
+                if let Some(ref progress) = status.progress {
+                    bar.set_length(progress.total as u64);
+                    bar.set_position(progress.processed as u64);
+                }
+
+                if let Some(error) = status.error {
+                    bar.abandon();
+                    anyhow::bail!("Re-embedding stopped: {}. Run the command again to resume.", error);
+                }
+                if !status.running {
+                    bar.finish_and_clear();
+                    let progress = status.progress.unwrap_or_default();
+                    say!(
+                        "{} Re-embedded {} chunks with {}",
+                        style("✓").green(),
+                        progress.processed,
+                        progress.model
+                    );
+                    return Ok(());
+                }
+
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+        RegistryCommands::Sync { from, to, namespace, format } => {
+            let mut federation = FederationManager::from_default_config();
+            for registry in [&from, &to] {
+                add_url_registry(&mut federation, registry, config)?;
+            }
+
+            let bar = ProgressBar::new(0);
+            if format == "text" {
+                bar.set_style(
+                    ProgressStyle::default_bar()
+                        .template("{msg}\n{wide_bar:.cyan/blue} {pos}/{len}")
+                        .unwrap(),
+                );
+                bar.set_message(format!("Syncing {} -> {}...", from, to));
+            } else {
+                bar.set_draw_target(indicatif::ProgressDrawTarget::hidden());
+            }
+
+            let filter = SyncFilter { namespace };
+            let report = federation
+                .sync(&from, &to, &filter, |progress| {
+                    bar.set_length(progress.total as u64);
+                    bar.set_position(progress.processed as u64);
+                })
+                .await
+                .map_err(|e| anyhow!("Sync from {} to {} failed: {}", from, to, e))?;
+            bar.finish_and_clear();
+
+            if format == "json" {
+                say!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print_sync_report(&report);
+            }
+
+            if !report.is_clean() {
+                anyhow::bail!(
+                    "{} chunk(s) failed and {} alias conflict(s) need attention",
+                    report.failed.len(),
+                    report.conflicts.len()
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Let `--from`/`--to` name a registry by URL when it isn't in the
+/// federation config
+fn add_url_registry(federation: &mut FederationManager, registry: &str, config: &CadiConfig) -> Result<()> {
+    if federation.client(registry).is_some() {
+        return Ok(());
+    }
+    if !registry.starts_with("http://") && !registry.starts_with("https://") {
+        return Err(anyhow!("Unknown registry '{}': not in the federation config and not a URL", registry));
+    }
+    federation
+        .add_registry(FederatedRegistry {
+            id: registry.to_string(),
+            url: registry.to_string(),
+            token: config.auth.token.clone(),
+            namespace: None,
+            priority: 0,
+            trust_level: Default::default(),
+            enabled: true,
+            regions: Vec::new(),
+            capabilities: Default::default(),
+        })
+        .map_err(|e| anyhow!("Failed to create registry client: {}", e))
+}
+
+fn print_sync_report(report: &SyncReport) {
+    for chunk_id in &report.copied {
+        say!("  {} Copied {}", style("✓").green(), chunk_id);
+    }
+    for alias in &report.aliases_copied {
+        say!("  {} Aliased {}", style("✓").green(), alias);
+    }
+    for (chunk_id, reason) in &report.failed {
+        say!("  {} {}: {}", style("✗").red(), chunk_id, reason);
+    }
+    for conflict in &report.conflicts {
+        say!(
+            "  {} Alias {} is {} on {} but {} on {}; left unchanged",
+            style("⚠").yellow(),
+            conflict.path,
+            conflict.source_chunk,
+            report.source,
+            conflict.target_chunk,
+            report.target
+        );
+    }
+
+    say!();
+    say!(
+        "{} {} chunks copied, {} already present, {} failed; {} aliases copied, {} already present, {} conflicts",
+        style("Sync complete:").green().bold(),
+        report.copied.len(),
+        report.present,
+        report.failed.len(),
+        report.aliases_copied.len(),
+        report.aliases_present,
+        report.conflicts.len()
+    );
+}
@@ -3,7 +3,10 @@ use clap::Args;
 use console::style;
 use std::path::PathBuf;
 
+use cadi_core::chunk_storage_key;
+
 use crate::config::CadiConfig;
+use crate::output::say;
 
 /// Arguments for the run command
 #[derive(Args)]
@@ -40,15 +43,15 @@ fn parse_env_var(s: &str) -> Result<(String, String), String> {
 
 /// Execute the run command
 pub async fn execute(args: RunArgs, config: &CadiConfig) -> Result<()> {
-    println!("{}", style("Running...").bold());
-    println!("  Target: {}", args.target);
+    say!("{}", style("Running...").bold());
+    say!("  Target: {}", args.target);
     
     if let Some(bt) = &args.build_target {
-        println!("  Build target: {}", bt);
+        say!("  Build target: {}", bt);
     }
     
     if args.sandbox {
-        println!("  Mode: {}", style("sandboxed").yellow());
+        say!("  Mode: {}", style("sandboxed").yellow());
     }
 
     // Determine run mode based on target
@@ -72,7 +75,7 @@ async fn run_from_manifest(args: &RunArgs, _config: &CadiConfig) -> Result<()> {
     let manifest: serde_json::Value = serde_yaml::from_str(&manifest_content)?;
 
     let app_name = manifest["application"]["name"].as_str().unwrap_or("unknown");
-    println!("  Application: {}", app_name);
+    say!("  Application: {}", app_name);
 
     // Find the target
     let target_name = args.build_target.as_deref().unwrap_or("dev");
@@ -82,36 +85,36 @@ async fn run_from_manifest(args: &RunArgs, _config: &CadiConfig) -> Result<()> {
         .and_then(|t| t.iter().find(|bt| bt["name"].as_str() == Some(target_name)));
 
     if build_target.is_none() {
-        println!("  {} Target '{}' not found", style("✗").red(), target_name);
+        say!("  {} Target '{}' not found", style("✗").red(), target_name);
         return Ok(());
     }
 
     let build_target = build_target.unwrap();
     let platform = build_target["platform"].as_str().unwrap_or("any");
 
-    println!("  Platform: {}", platform);
-    println!();
+    say!("  Platform: {}", platform);
+    say!();
 
     // Determine how to run based on platform
     match platform {
         "browser" | "browser + node-dev" => {
-            println!("{}", style("Starting development server...").green());
-            println!("  http://localhost:3000");
-            println!();
-            println!("  Press Ctrl+C to stop");
+            say!("{}", style("Starting development server...").green());
+            say!("  http://localhost:3000");
+            say!();
+            say!("  Press Ctrl+C to stop");
             
             // In real implementation, would start a dev server
             tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
         }
         "linux-container" | "x86_64-linux" | "arm64-linux" => {
-            println!("{}", style("Running in container...").green());
+            say!("{}", style("Running in container...").green());
             
             if args.sandbox {
-                println!("  {} Container isolation enabled", style("🔒").cyan());
+                say!("  {} Container isolation enabled", style("🔒").cyan());
             }
         }
         _ => {
-            println!("{}", style("Running natively...").green());
+            say!("{}", style("Running natively...").green());
         }
     }
 
@@ -119,11 +122,10 @@ async fn run_from_manifest(args: &RunArgs, _config: &CadiConfig) -> Result<()> {
 }
 
 async fn run_chunk(chunk_id: &str, args: &RunArgs, config: &CadiConfig) -> Result<()> {
-    let hash = chunk_id.strip_prefix("chunk:sha256:").unwrap_or(chunk_id);
-    let chunk_file = config.cache.dir.join("chunks").join(format!("{}.json", hash));
+    let chunk_file = config.cache.dir.join("chunks").join(format!("{}.json", chunk_storage_key(chunk_id)));
 
     if !chunk_file.exists() {
-        println!("  {} Chunk not found locally. Fetching...", style("→").cyan());
+        say!("  {} Chunk not found locally. Fetching...", style("→").cyan());
         // Would fetch here
     }
 
@@ -132,38 +134,38 @@ async fn run_chunk(chunk_id: &str, args: &RunArgs, config: &CadiConfig) -> Resul
 
     let cadi_type = chunk["cadi_type"].as_str().unwrap_or("unknown");
 
-    println!("  Chunk type: {}", cadi_type);
+    say!("  Chunk type: {}", cadi_type);
 
     match cadi_type {
         "source" => {
             let language = chunk["source"]["language"].as_str().unwrap_or("unknown");
-            println!("  Language: {}", language);
+            say!("  Language: {}", language);
             
             // For source, we need to build first or run interpreted
-            println!();
-            println!("{}", style("Note: Source chunks require building before running.").yellow());
-            println!("  Run: cadi build <manifest> && cadi run <manifest>");
+            say!();
+            say!("{}", style("Note: Source chunks require building before running.").yellow());
+            say!("  Run: cadi build <manifest> && cadi run <manifest>");
         }
         "intermediate" => {
-            println!("{}", style("Running WASM module...").green());
+            say!("{}", style("Running WASM module...").green());
             
             if args.sandbox || config.security.sandbox_untrusted {
-                println!("  {} WASM sandbox enabled", style("🔒").cyan());
+                say!("  {} WASM sandbox enabled", style("🔒").cyan());
             }
             
             // Would use wasmtime to run
         }
         "blob" => {
-            println!("{}", style("Running native binary...").green());
+            say!("{}", style("Running native binary...").green());
             
             if args.sandbox {
-                println!("  {} Container sandbox enabled", style("🔒").cyan());
+                say!("  {} Container sandbox enabled", style("🔒").cyan());
             }
         }
         "container" => {
             let image_ref = chunk["container"]["image_ref"].as_str().unwrap_or("unknown");
-            println!("  Image: {}", image_ref);
-            println!("{}", style("Running container...").green());
+            say!("  Image: {}", image_ref);
+            say!("{}", style("Running container...").green());
         }
         _ => {
             anyhow::bail!("Unknown chunk type: {}", cadi_type);
@@ -1,8 +1,15 @@
 use anyhow::{Result, anyhow};
+use cadi_builder::scaffold::{FileAction, OnConflict, Resolution, ScaffoldReport, Scaffolder};
+use cadi_core::graph::GraphStore;
+use cadi_core::Manifest;
+use cadi_registry::{RegistryClient, RegistryConfig};
 use clap::Args;
 use console::style;
-use std::path::PathBuf;
-use cadi_core::{Manifest, chunk::SourceCadi};
+use dialoguer::Select;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+
+use crate::output::say;
 
 /// Arguments for the scaffold command
 #[derive(Args)]
@@ -16,12 +23,28 @@ pub struct ScaffoldArgs {
     output: Option<PathBuf>,
 
     /// Force overwrite existing files
-    #[arg(short, long)]
+    #[arg(short, long, conflicts_with = "skip_existing")]
     force: bool,
+
+    /// Leave existing files alone without asking
+    #[arg(long)]
+    skip_existing: bool,
+
+    /// Registry to fetch chunks missing locally from
+    #[arg(short, long)]
+    registry: Option<String>,
+
+    /// Only use chunks available locally
+    #[arg(long, conflicts_with = "registry")]
+    no_fetch: bool,
+
+    /// Output format (text, json)
+    #[arg(long, default_value = "text")]
+    format: String,
 }
 
 /// Execute the scaffold command
-pub async fn execute(args: ScaffoldArgs, _config: &crate::config::CadiConfig) -> Result<()> {
+pub async fn execute(args: ScaffoldArgs, config: &crate::config::CadiConfig) -> Result<()> {
     let manifest_path = if args.manifest.is_absolute() {
         args.manifest.clone()
     } else {
@@ -32,71 +55,99 @@ pub async fn execute(args: ScaffoldArgs, _config: &crate::config::CadiConfig) ->
         return Err(anyhow!("Manifest not found: {}", manifest_path.display()));
     }
 
-    let output_dir = args.output.unwrap_or_else(|| {
+    let output_dir = args.output.clone().unwrap_or_else(|| {
         manifest_path.parent().unwrap_or(PathBuf::from(".").as_path()).to_path_buf()
     });
 
-    println!("{}", style(format!("Scaffolding project from: {}", manifest_path.display())).bold());
-    println!("  Output directory: {}", output_dir.display());
-
     let content = std::fs::read_to_string(&manifest_path)?;
     let manifest: Manifest = serde_yaml::from_str(&content)?;
 
-    // Generate files based on nodes
-    for node in &manifest.build_graph.nodes {
-        // Find source representation
-        let source_repr = match node.representations.iter().find(|r| r.form == "source") {
-            Some(r) => r,
-            None => {
-                println!("  {} Node {} has no source representation, skipping", style("⚠").yellow(), node.id);
-                continue;
-            }
-        };
-
-        let chunk_id = &source_repr.chunk;
-        let chunk_hash = chunk_id.strip_prefix("chunk:sha256:").unwrap_or(chunk_id);
-        
-        let chunk_meta_path = _config.cache.dir.join("chunks").join(format!("{}.json", chunk_hash));
-        if !chunk_meta_path.exists() {
-            println!("  {} Chunk metadata not found for {}: {}", style("✗").red(), node.id, chunk_id);
-            continue;
-        }
+    // Ask about conflicts only when someone is there to answer
+    let interactive = std::io::stdin().is_terminal() && std::io::stdout().is_terminal() && args.format == "text";
+    let on_conflict = if args.force {
+        OnConflict::Overwrite
+    } else if args.skip_existing || !interactive {
+        OnConflict::Skip
+    } else {
+        OnConflict::Ask
+    };
 
-        let chunk_json = std::fs::read_to_string(&chunk_meta_path)?;
-        let source_cadi: SourceCadi = serde_json::from_str(&chunk_json)?;
+    let mut scaffolder = Scaffolder::new(&config.cache.dir).with_prompt(Box::new(prompt));
+    let graph_dir = config.cache.dir.join("graph-db");
+    if graph_dir.exists() {
+        scaffolder = scaffolder.with_graph(GraphStore::open(&graph_dir)?);
+    }
+    if !args.no_fetch {
+        let registry = args.registry.clone().unwrap_or_else(|| config.registry.url.clone());
+        scaffolder = scaffolder.with_registry(RegistryClient::new(RegistryConfig {
+            url: registry,
+            token: config.auth.token.clone(),
+            cache_dir: Some(config.cache.dir.join("http-cache")),
+            ..Default::default()
+        })?);
+    }
 
-        println!("  🏗 Realizing node {} from chunk {}", style(&node.id).cyan(), &chunk_id[..15]);
+    if args.format == "text" {
+        say!("{}", style(format!("Scaffolding project from: {}", manifest_path.display())).bold());
+        say!("  Output directory: {}", output_dir.display());
+    }
 
-        for source_file in &source_cadi.source.files {
-            let file_path = output_dir.join(&node.id).join(&source_file.path);
-            
-            // Create parent directories for the file if they don't exist
-            if let Some(parent) = file_path.parent() {
-                std::fs::create_dir_all(parent)?;
-            }
+    let report = scaffolder.scaffold(&manifest, &output_dir, on_conflict).await?;
 
-            if file_path.exists() && !args.force {
-                println!("    {} Skipping existing file: {}", style("⚠").yellow(), source_file.path);
-                continue;
-            }
+    if args.format == "json" {
+        say!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
 
-            // Fetch blob
-            let blob_hash = source_file.hash.strip_prefix("sha256:").unwrap_or(&source_file.hash);
-            let blob_path = _config.cache.dir.join("blobs").join("sha256").join(blob_hash);
+    if !report.is_complete() {
+        return Err(anyhow!("{} node(s) could not be scaffolded", report.unresolved.len()));
+    }
+    Ok(())
+}
 
-            if !blob_path.exists() {
-                println!("    {} Blob not found for file {}: {}", style("✗").red(), source_file.path, blob_hash);
-                continue;
-            }
+/// Ask what to do with an existing file
+fn prompt(path: &Path) -> Resolution {
+    let choice = Select::new()
+        .with_prompt(format!("{} already exists", path.display()))
+        .items(&["Overwrite", "Skip", "Keep both (write .cadi-new)"])
+        .default(1)
+        .interact();
+    match choice {
+        Ok(0) => Resolution::Overwrite,
+        Ok(2) => Resolution::KeepBoth,
+        _ => Resolution::Skip,
+    }
+}
 
-            let content = std::fs::read(&blob_path)?;
-            std::fs::write(&file_path, content)?;
-            println!("    {} Realized {}", style("✓").green(), source_file.path);
+fn print_report(report: &ScaffoldReport) {
+    for file in &report.files {
+        let path = file.path.display();
+        match file.action {
+            FileAction::Created => say!("  {} Created {}", style("✓").green(), path),
+            FileAction::Overwritten => say!("  {} Overwrote {}", style("✓").green(), path),
+            FileAction::Unchanged => say!("  {} Unchanged {}", style("=").dim(), path),
+            FileAction::Skipped => say!("  {} Skipped existing {}", style("⚠").yellow(), path),
+            FileAction::KeptBoth => say!("  {} Kept {}, wrote {}.cadi-new", style("✓").green(), path, path),
         }
     }
+    for unresolved in &report.unresolved {
+        say!(
+            "  {} Node {}{}: {}",
+            style("✗").red(),
+            unresolved.node,
+            unresolved.chunk_id.as_ref().map(|c| format!(" ({})", c)).unwrap_or_default(),
+            unresolved.reason
+        );
+    }
 
-    println!();
-    println!("{}", style("Scaffolding complete!").green().bold());
-    
-    Ok(())
+    say!();
+    say!(
+        "{} {} written, {} skipped, {} unchanged, {} fetched from the registry",
+        style("Scaffolding complete:").green().bold(),
+        report.written(),
+        report.count(FileAction::Skipped),
+        report.count(FileAction::Unchanged),
+        report.fetched.len()
+    );
 }
@@ -1,22 +1,39 @@
 use anyhow::{Context, Result};
-use clap::Args;
+use clap::{Args, ValueEnum};
 use console::style;
 use indicatif::{ProgressBar, ProgressStyle};
 use std::path::PathBuf;
 
 use cadi_scraper::{
-    ScraperConfig, ScraperInput, ChunkingStrategy, Scraper,
+    ArchiveFormat, ScraperConfig, ScraperInput, ChunkingStrategy, PackageSource, Scraper,
 };
 
 use crate::config::CadiConfig;
+use crate::output::say;
 
 /// Arguments for the scrape command
 #[derive(Args)]
 pub struct ScrapeArgs {
-    /// Input path, URL, or directory to scrape
+    /// Input path, URL, directory, or package name to scrape
     #[arg(required = true)]
     input: String,
 
+    /// Where the input comes from (detected from the input if omitted)
+    #[arg(long, value_enum)]
+    source: Option<ScrapeSource>,
+
+    /// Package version for crates and npm sources (default: latest release)
+    #[arg(long = "version")]
+    package_version: Option<String>,
+
+    /// Branch, tag or commit to scrape from a git repository
+    #[arg(long = "ref")]
+    git_ref: Option<String>,
+
+    /// Repository subdirectory to scrape from a git repository
+    #[arg(long)]
+    subdir: Option<PathBuf>,
+
     /// Output directory for chunks
     #[arg(short, long, default_value = "./cadi-chunks")]
     output: PathBuf,
@@ -25,6 +42,10 @@ pub struct ScrapeArgs {
     #[arg(short, long, default_value = "file")]
     strategy: String,
 
+    /// YAML file of per-path chunking rules (pattern, strategy, category)
+    #[arg(long)]
+    rules: Option<PathBuf>,
+
     /// Maximum chunk size in bytes
     #[arg(long, default_value = "52428800")] // 50MB
     max_chunk_size: usize,
@@ -66,14 +87,33 @@ pub struct ScrapeArgs {
     verbose: bool,
 }
 
+/// Kinds of scrape input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ScrapeSource {
+    /// A crate published to crates.io
+    Crates,
+    /// A package published to npm
+    Npm,
+    /// A git repository URL
+    Git,
+    /// A local file or directory
+    Path,
+}
+
 /// Execute the scrape command
 pub async fn execute(args: ScrapeArgs, _config: &CadiConfig) -> Result<()> {
-    println!("{}", style("Starting CADI Scraper...").bold());
+    say!("{}", style("Starting CADI Scraper...").bold());
 
     // Parse input
-    let input = parse_input(&args.input)?;
-    println!("  {} Input: {}", style("→").cyan(), args.input);
-    println!("  {} Output: {}", style("→").cyan(), args.output.display());
+    let input = parse_input(
+        &args.input,
+        args.source,
+        args.package_version.clone(),
+        args.git_ref.clone(),
+        args.subdir.clone(),
+    )?;
+    say!("  {} Input: {}", style("→").cyan(), args.input);
+    say!("  {} Output: {}", style("→").cyan(), args.output.display());
 
     // Create scraper configuration
     let mut config = ScraperConfig::default();
@@ -91,10 +131,15 @@ pub async fn execute(args: ScrapeArgs, _config: &CadiConfig) -> Result<()> {
         _ => ChunkingStrategy::ByFile,
     };
 
+    if let Some(rules) = &args.rules {
+        config.rules = cadi_scraper::config::load_rules(&rules.to_string_lossy())
+            .with_context(|| format!("Failed to load chunking rules from {}", rules.display()))?;
+    }
+
     if args.verbose {
-        println!("  {} Strategy: {:?}", style("→").cyan(), config.chunking_strategy);
-        println!("  {} Max chunk size: {}", style("→").cyan(), args.max_chunk_size);
-        println!("  {} Include overlap: {}", style("→").cyan(), args.include_overlap);
+        say!("  {} Strategy: {:?}", style("→").cyan(), config.chunking_strategy);
+        say!("  {} Max chunk size: {}", style("→").cyan(), args.max_chunk_size);
+        say!("  {} Include overlap: {}", style("→").cyan(), args.include_overlap);
     }
 
     // Create scraper
@@ -120,24 +165,39 @@ pub async fn execute(args: ScrapeArgs, _config: &CadiConfig) -> Result<()> {
     ));
 
     // Display results
-    println!();
-    println!("{}", style("Scraping Results:").bold());
-    println!(
+    say!();
+    say!("{}", style("Scraping Results:").bold());
+    say!(
         "  {} Chunks: {}",
         style("→").cyan(),
         style(output.chunk_count).yellow()
     );
-    println!(
+    say!(
         "  {} Files: {}",
         style("→").cyan(),
         style(output.file_count).yellow()
     );
-    println!(
+    if let Some(package) = output
+        .chunks
+        .first()
+        .and_then(|c| c.lineage.as_ref())
+        .and_then(|l| l.package.as_ref())
+    {
+        say!(
+            "  {} Package: {} {} {} ({})",
+            style("→").cyan(),
+            package.source.as_str(),
+            package.name,
+            package.version,
+            package.checksum
+        );
+    }
+    say!(
         "  {} Total Size: {} bytes",
         style("→").cyan(),
         output.total_bytes
     );
-    println!(
+    say!(
         "  {} Duration: {}ms",
         style("→").cyan(),
         output.duration_ms
@@ -145,10 +205,10 @@ pub async fn execute(args: ScrapeArgs, _config: &CadiConfig) -> Result<()> {
 
     // Display errors if any
     if !output.errors.is_empty() {
-        println!();
-        println!("{}", style("Errors:").bold().red());
+        say!();
+        say!("{}", style("Errors:").bold().red());
         for error in &output.errors {
-            println!("  {} {}", style("✗").red(), error);
+            say!("  {} {}", style("✗").red(), error);
         }
     }
 
@@ -190,7 +250,7 @@ pub async fn execute(args: ScrapeArgs, _config: &CadiConfig) -> Result<()> {
             let manifest_path = args.output.join("manifest.json");
             let manifest_json = serde_json::to_string_pretty(manifest)?;
             tokio::fs::write(&manifest_path, manifest_json).await?;
-            println!(
+            say!(
                 "  {} Manifest saved to {}",
                 style("✓").green(),
                 manifest_path.display()
@@ -201,25 +261,25 @@ pub async fn execute(args: ScrapeArgs, _config: &CadiConfig) -> Result<()> {
     // Display chunk details based on format
     match args.format.as_str() {
         "json" => {
-            println!();
-            println!("{}", style("Chunks (JSON):").bold());
+            say!();
+            say!("{}", style("Chunks (JSON):").bold());
             let json = serde_json::to_string_pretty(&output.chunks)?;
-            println!("{}", json);
+            say!("{}", json);
         }
         "table" => {
-            println!();
-            println!("{}", style("Chunk Details:").bold());
+            say!();
+            say!("{}", style("Chunk Details:").bold());
             for chunk in output.chunks.iter().take(5) {
-                println!("  {} {}",  style("→").cyan(), chunk.name);
-                println!("    {} Language: {:?}", style("·").blue(), chunk.language);
-                println!("    {} Size: {} bytes", style("·").blue(), chunk.size);
-                println!("    {} Concepts: {}", style("·").blue(), chunk.concepts.join(", "));
+                say!("  {} {}",  style("→").cyan(), chunk.name);
+                say!("    {} Language: {:?}", style("·").blue(), chunk.language);
+                say!("    {} Size: {} bytes", style("·").blue(), chunk.size);
+                say!("    {} Concepts: {}", style("·").blue(), chunk.concepts.join(", "));
                 if let Some(desc) = &chunk.description {
-                    println!("    {} Description: {}", style("·").blue(), desc);
+                    say!("    {} Description: {}", style("·").blue(), desc);
                 }
             }
             if output.chunks.len() > 5 {
-                println!(
+                say!(
                     "  {} ... and {} more chunks",
                     style("→").cyan(),
                     output.chunks.len() - 5
@@ -231,27 +291,69 @@ pub async fn execute(args: ScrapeArgs, _config: &CadiConfig) -> Result<()> {
 
     // Publish if requested
     if args.publish {
-        println!();
-        println!("{}", style("Publishing chunks...").bold());
-        println!("  {} Registry publishing not yet configured", style("!").yellow());
+        say!();
+        say!("{}", style("Publishing chunks...").bold());
+        say!("  {} Registry publishing not yet configured", style("!").yellow());
     }
 
-    println!();
-    println!("{}", style("Scraping completed successfully!").green().bold());
+    say!();
+    say!("{}", style("Scraping completed successfully!").green().bold());
 
     Ok(())
 }
 
 /// Parse input string into ScraperInput
-fn parse_input(input: &str) -> Result<ScraperInput> {
-    if input.starts_with("http://") || input.starts_with("https://") {
-        Ok(ScraperInput::Url(input.to_string()))
-    } else if input.starts_with("git@") || input.ends_with(".git") {
+///
+/// Without `--source`, git repositories are recognized by `git@`/`.git`
+/// URLs and `https://github.com/<org>/<repo>`, `.zip`, `.tar` and
+/// `.tar.gz` URLs and files are unpacked as archives, other URLs are fetched
+/// as single files and anything else is a local path. `--ref` and `--subdir`
+/// only apply to git repositories and `--version` only to packages.
+fn parse_input(
+    input: &str,
+    source: Option<ScrapeSource>,
+    version: Option<String>,
+    git_ref: Option<String>,
+    subdir: Option<PathBuf>,
+) -> Result<ScraperInput> {
+    let package = match source {
+        Some(ScrapeSource::Crates) => Some(PackageSource::Crates),
+        Some(ScrapeSource::Npm) => Some(PackageSource::Npm),
+        _ => None,
+    };
+    if version.is_some() && package.is_none() {
+        return Err(anyhow::anyhow!("--version requires --source crates or --source npm"));
+    }
+    let git = match source {
+        Some(ScrapeSource::Git) => true,
+        Some(_) => false,
+        None => input.starts_with("git@") || input.ends_with(".git") || is_github_repo(input),
+    };
+
+    if git {
         Ok(ScraperInput::GitRepo {
-            url: input.to_string(),
-            branch: None,
-            commit: None,
+            url: input.trim_end_matches('/').to_string(),
+            git_ref,
+            subdir,
+        })
+    } else if git_ref.is_some() || subdir.is_some() {
+        Err(anyhow::anyhow!(
+            "--ref and --subdir require a git repository URL: {}",
+            input
+        ))
+    } else if let Some(source) = package {
+        Ok(ScraperInput::Package {
+            source,
+            name: input.to_string(),
+            version,
+        })
+    } else if ArchiveFormat::detect(input).is_some() {
+        Ok(ScraperInput::Archive {
+            path_or_url: input.to_string(),
+            format: None,
         })
+    } else if source.is_none() && (input.starts_with("http://") || input.starts_with("https://")) {
+        Ok(ScraperInput::Url(input.to_string()))
     } else {
         let path = PathBuf::from(input);
         if path.is_dir() {
@@ -270,13 +372,21 @@ fn parse_input(input: &str) -> Result<ScraperInput> {
     }
 }
 
+/// Whether a URL points at a GitHub repository root
+fn is_github_repo(input: &str) -> bool {
+    input
+        .strip_prefix("https://github.com/")
+        .map(|path| path.trim_end_matches('/').split('/').filter(|s| !s.is_empty()).count() == 2)
+        .unwrap_or(false)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_parse_input_url() {
-        let input = parse_input("https://example.com/repo").unwrap();
+        let input = parse_input("https://example.com/repo", None, None, None, None).unwrap();
         match input {
             ScraperInput::Url(url) => assert_eq!(url, "https://example.com/repo"),
             _ => panic!("Expected URL input"),
@@ -285,10 +395,72 @@ mod tests {
 
     #[test]
     fn test_parse_input_git() {
-        let input = parse_input("git@github.com:user/repo.git").unwrap();
+        let input = parse_input("git@github.com:user/repo.git", None, None, None, None).unwrap();
         match input {
             ScraperInput::GitRepo { .. } => {}
             _ => panic!("Expected GitRepo input"),
         }
     }
+
+    #[test]
+    fn test_parse_input_github_ref_and_subdir() {
+        let input = parse_input(
+            "https://github.com/org/repo/",
+            None,
+            None,
+            Some("v1.2.0".to_string()),
+            Some(PathBuf::from("crates/core")),
+        )
+        .unwrap();
+        match input {
+            ScraperInput::GitRepo { url, git_ref, subdir } => {
+                assert_eq!(url, "https://github.com/org/repo");
+                assert_eq!(git_ref.as_deref(), Some("v1.2.0"));
+                assert_eq!(subdir, Some(PathBuf::from("crates/core")));
+            }
+            _ => panic!("Expected GitRepo input"),
+        }
+
+        // Files inside a repository are plain URLs
+        assert!(matches!(
+            parse_input("https://github.com/org/repo/blob/main/README.md", None, None, None, None).unwrap(),
+            ScraperInput::Url(_)
+        ));
+        assert!(parse_input("https://example.com/file.rs", None, None, Some("main".to_string()), None).is_err());
+    }
+
+    #[test]
+    fn test_parse_input_packages() {
+        let input = parse_input("lodash", Some(ScrapeSource::Npm), Some("4.17.21".to_string()), None, None).unwrap();
+        match input {
+            ScraperInput::Package { source, name, version } => {
+                assert_eq!(source, PackageSource::Npm);
+                assert_eq!(name, "lodash");
+                assert_eq!(version.as_deref(), Some("4.17.21"));
+            }
+            _ => panic!("Expected Package input"),
+        }
+        assert!(matches!(
+            parse_input("serde", Some(ScrapeSource::Crates), None, None, None).unwrap(),
+            ScraperInput::Package { source: PackageSource::Crates, version: None, .. }
+        ));
+
+        // Any URL is a repository with --source git
+        assert!(matches!(
+            parse_input("https://example.com/repo", Some(ScrapeSource::Git), None, None, None).unwrap(),
+            ScraperInput::GitRepo { .. }
+        ));
+        assert!(parse_input("https://github.com/org/repo", None, Some("1.0.0".to_string()), None, None).is_err());
+
+        // Archives are unpacked whether local or remote
+        assert!(matches!(
+            parse_input("https://github.com/org/repo/archive/refs/heads/main.zip", None, None, None, None).unwrap(),
+            ScraperInput::Archive { format: None, .. }
+        ));
+        assert!(matches!(
+            parse_input("./vendor/demo-0.1.0.tar.gz", Some(ScrapeSource::Path), None, None, None).unwrap(),
+            ScraperInput::Archive { .. }
+        ));
+        assert!(parse_input("serde", Some(ScrapeSource::Crates), None, Some("main".to_string()), None).is_err());
+    }
 }
@@ -1,88 +1,157 @@
 use anyhow::Result;
+use cadi_builder::{BuildCache, BuildHistoryStats};
+use cadi_core::stats::{RepositoryStats, StatsCollector};
+use cadi_registry::client::ChunkCacheStats;
 use clap::Args;
 use console::style;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
 
 use crate::config::CadiConfig;
+use crate::output::say;
 
 /// Arguments for the stats command
 #[derive(Args)]
 pub struct StatsArgs {
-    /// Period to show (hour, day, week, month, all)
-    #[arg(long, default_value = "day")]
-    period: String,
-
-    /// Output format (text, json)
-    #[arg(long, default_value = "text")]
+    /// Output format (table, json)
+    #[arg(long, default_value = "table")]
     format: String,
+
+    /// Graph store directory (defaults to <cache-dir>/graph-db)
+    #[arg(long)]
+    graph_dir: Option<PathBuf>,
+
+    /// Cache directory (defaults to the configured cache directory)
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+
+    /// Number of recent builds the cache hit rate covers
+    #[arg(long, default_value = "20")]
+    builds: usize,
+
+    /// Number of most depended-on chunks to list
+    #[arg(long, default_value = "10")]
+    top: usize,
+
+    /// Report semantic duplicates and link them with equivalent_to edges
+    #[arg(long)]
+    duplicates: bool,
+
+    /// Minimum similarity (0.0-1.0) for two chunks to count as duplicates
+    #[arg(long, default_value = "0.9")]
+    threshold: f64,
+}
+
+/// Everything `cadi stats` reports
+#[derive(Serialize)]
+struct StatsReport {
+    #[serde(flatten)]
+    repository: RepositoryStats,
+    builds: BuildHistoryStats,
+    registry_cache: ChunkCacheStats,
 }
 
 /// Execute the stats command
 pub async fn execute(args: StatsArgs, config: &CadiConfig) -> Result<()> {
-    if args.format == "json" {
-        let stats = serde_json::json!({
-            "period": args.period,
-            "cache": {
-                "hits": 42,
-                "misses": 8,
-                "hit_rate": 0.84
-            },
-            "reuse": {
-                "chunks_reused": 156,
-                "bytes_deduplicated": 52428800,
-                "tokens_saved": 125000
-            },
-            "build": {
-                "total_builds": 23,
-                "avg_duration_seconds": 4.2,
-                "time_saved_seconds": 180
-            }
-        });
-        println!("{}", serde_json::to_string_pretty(&stats)?);
-        return Ok(());
+    let cache_dir = args.cache_dir.clone().unwrap_or_else(|| config.cache.dir.clone());
+    let graph_dir = args.graph_dir.clone().unwrap_or_else(|| cache_dir.join("graph-db"));
+
+    if !(0.0..=1.0).contains(&args.threshold) {
+        anyhow::bail!("--threshold must be between 0.0 and 1.0");
     }
 
-    println!("{}", style("CADI Efficiency Statistics").bold());
-    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-    println!("Period: {}", args.period);
-    println!();
-
-    // Cache statistics
-    println!("{}", style("Cache Performance").bold());
-    println!("  Cache hits:     {} ", style("42").green());
-    println!("  Cache misses:   {} ", style("8").yellow());
-    println!("  Hit rate:       {} ", style("84%").green());
-    println!();
-
-    // Reuse statistics
-    println!("{}", style("Reuse & Deduplication").bold());
-    println!("  Chunks reused:      {} times", style("156").cyan());
-    println!("  Bytes deduplicated: {} MB", style("50").cyan());
-    println!("  Tokens saved:       {} ", style("~125,000").green());
-    println!();
-
-    // Build statistics
-    println!("{}", style("Build Performance").bold());
-    println!("  Total builds:       {}", 23);
-    println!("  Avg build time:     {} seconds", 4.2);
-    println!("  Time saved (cache): {} seconds", style("180").green());
-    println!();
-
-    // LLM efficiency
-    println!("{}", style("LLM Efficiency").bold());
-    println!("  Chunks with summaries: 100%");
-    println!("  Avg summary tokens:    {}", 245);
-    println!("  Avg source tokens:     {}", 2450);
-    println!("  Compression ratio:     {} ", style("10x").green());
-    println!();
-
-    // Sustainability estimate
-    println!("{}", style("Sustainability Estimate").bold());
-    println!("  Estimated tokens regenerated if not using CADI: {}", style("~500,000").yellow());
-    println!("  Actual tokens used:                              {}", style("~25,000").green());
-    println!("  Savings:                                         {}", style("95%").green().bold());
-    println!();
-
-    println!("Cache directory: {}", config.cache.dir.display());
+    let mut collector = StatsCollector::new()
+        .with_chunk_dir(cache_dir.join("chunks"))
+        .with_graph_dir(&graph_dir)
+        .with_top_n(args.top);
+    if args.duplicates {
+        collector = collector.with_duplicates(args.threshold);
+    }
+    let repository = collector.collect()?;
+    let builds = BuildCache::new(cache_dir.clone()).history_stats(args.builds)?;
+    let registry_cache = ChunkCacheStats::load(&cache_dir.join("http-cache"))?;
+    let report = StatsReport { repository, builds, registry_cache };
+
+    match args.format.as_str() {
+        "json" => say!("{}", serde_json::to_string_pretty(&report)?),
+        "table" => print_table(&report, &cache_dir, &graph_dir),
+        other => anyhow::bail!("Unknown format '{}' (expected table or json)", other),
+    }
 
     Ok(())
 }
+
+fn print_table(report: &StatsReport, cache_dir: &Path, graph_dir: &Path) {
+    let repo = &report.repository;
+
+    say!("{}", style("CADI Statistics").bold());
+    say!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    say!();
+
+    say!("{}", style("Local Cache").bold());
+    say!("  Chunks:         {}", style(repo.chunks.count).cyan());
+    say!("  Size:           {} KB", repo.chunks.bytes / 1024);
+    say!();
+
+    say!("{}", style("Graph").bold());
+    say!("  Nodes:          {}", style(repo.graph.nodes).cyan());
+    say!("  Edges:          {}", style(repo.graph.edges).cyan());
+    for (edge_type, count) in &repo.graph.edges_by_type {
+        say!("    {:<14}{}", edge_type, count);
+    }
+    say!();
+
+    say!("{}", style("Deduplication").bold());
+    say!("  Unique content: {} of {} chunks", repo.dedup.unique, repo.dedup.chunks);
+    say!("  Dedup ratio:    {}", style(format!("{:.2}x", repo.dedup.ratio)).green());
+    say!();
+
+    say!("{}", style("Build Cache").bold());
+    let builds = &report.builds;
+    if builds.builds == 0 {
+        say!("  No builds recorded");
+    } else {
+        say!("  Builds:         {}", builds.builds);
+        say!("  Steps cached:   {}", style(builds.cached).green());
+        say!("  Steps built:    {}", style(builds.built).yellow());
+        say!("  Hit rate:       {}", style(format!("{:.0}%", builds.hit_rate * 100.0)).green());
+    }
+    say!();
+
+    say!("{}", style("Registry Cache").bold());
+    let registry_cache = &report.registry_cache;
+    if registry_cache.hits + registry_cache.misses == 0 {
+        say!("  No registry fetches recorded");
+    } else {
+        say!("  Hits:           {}", style(registry_cache.hits).green());
+        say!("  Misses:         {}", style(registry_cache.misses).yellow());
+        say!("  Hit rate:       {}", style(format!("{:.0}%", registry_cache.hit_rate() * 100.0)).green());
+    }
+    say!();
+
+    if !repo.top_dependencies.is_empty() {
+        say!("{}", style("Most Depended-On Chunks").bold());
+        for dep in &repo.top_dependencies {
+            say!("  {:>4}  {}", dep.dependents, dep.chunk_id);
+        }
+        say!();
+    }
+
+    if let Some(duplicates) = &repo.duplicates {
+        say!("{}", style("Potential Duplicates").bold());
+        if duplicates.is_empty() {
+            say!("  None found");
+        }
+        for cluster in duplicates {
+            say!(
+                "  {}  {}",
+                style(format!("{:>3.0}%", cluster.confidence * 100.0)).yellow(),
+                cluster.chunk_ids.join(", ")
+            );
+        }
+        say!();
+    }
+
+    say!("Cache directory: {}", cache_dir.display());
+    say!("Graph directory: {}", graph_dir.display());
+}
@@ -1,8 +1,11 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use cadi_core::trust::{SigningKey, TrustStore};
 use clap::{Args, Subcommand};
 use console::style;
+use std::path::PathBuf;
 
 use crate::config::CadiConfig;
+use crate::output::say;
 
 /// Arguments for the trust command
 #[derive(Args)]
@@ -13,25 +16,44 @@ pub struct TrustArgs {
 
 #[derive(Subcommand)]
 enum TrustCommands {
-    /// Add a trusted signer
+    /// Trust the ed25519 public key in a key file
     Add {
-        /// Signer identifier
-        signer: String,
-        
-        /// Trust level
-        #[arg(long, default_value = "standard")]
-        level: String,
+        /// Public key file (a private key file is also accepted)
+        key_file: PathBuf,
+
+        /// Name to record the signer under
+        #[arg(long)]
+        name: String,
     },
     
     /// Remove a trusted signer
     Remove {
-        /// Signer identifier
+        /// Signer name
         signer: String,
     },
     
     /// List trusted signers
     List,
     
+    /// Replace a trusted key, retiring the old one as of now. Signatures
+    /// by the old key made before its retirement stay valid.
+    Rotate {
+        /// Private key file of the key to retire
+        #[arg(long)]
+        old: PathBuf,
+
+        /// Key file of the replacement (public or private)
+        #[arg(long)]
+        new: PathBuf,
+    },
+
+    /// Print the public key of a signing key, for others to trust
+    Export {
+        /// Signing key (defaults to the configured key)
+        #[arg(long)]
+        key: Option<PathBuf>,
+    },
+
     /// Show or set trust policy
     Policy {
         /// Policy mode (strict, standard, permissive)
@@ -42,58 +64,112 @@ enum TrustCommands {
 
 /// Execute the trust command
 pub async fn execute(args: TrustArgs, config: &CadiConfig) -> Result<()> {
+    let store = TrustStore::open(&config.security.trust_dir);
+
     match args.command {
-        TrustCommands::Add { signer, level } => {
-            println!("{}", style("Adding trusted signer...").bold());
-            println!("  Signer: {}", signer);
-            println!("  Level:  {}", level);
-            
-            // Would update trust store
-            println!();
-            println!("{} Added {} as trusted signer", style("✓").green(), signer);
+        TrustCommands::Add { key_file, name } => {
+            say!("{}", style("Adding trusted signer...").bold());
+
+            let key_content = std::fs::read_to_string(&key_file)
+                .map_err(|e| anyhow!("Failed to read {}: {}", key_file.display(), e))?;
+            let signer = store.add(&name, &key_content)?;
+
+            say!("  Signer: {}", signer.name);
+            say!("  Key:    {}", signer.public_key);
+            say!();
+            say!("{} Added {} as trusted signer", style("✓").green(), signer.name);
         }
         
         TrustCommands::Remove { signer } => {
-            println!("{}", style("Removing trusted signer...").bold());
+            say!("{}", style("Removing trusted signer...").bold());
             
-            // Would update trust store
-            println!("{} Removed {} from trusted signers", style("✓").green(), signer);
+            if store.remove(&signer)? {
+                say!("{} Removed {} from trusted signers", style("✓").green(), signer);
+            } else {
+                say!("{} {} is not a trusted signer", style("⚠").yellow(), signer);
+            }
         }
         
         TrustCommands::List => {
-            println!("{}", style("Trusted Signers").bold());
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-            println!();
+            say!("{}", style("Trusted Signers").bold());
+            say!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            say!();
             
-            // Would read from trust store
-            println!("  {} github.com/ConflictingTheories/* (full)", style("●").green());
-            println!("  {} Local signing key (full)", style("●").green());
-            println!();
-            println!("Trust policy: {}", style(&config.security.trust_policy).cyan());
+            let signers = store.list()?;
+            if signers.is_empty() {
+                say!("  {} No trusted signers in {}", style("○").dim(), store.dir().display());
+            }
+            for signer in signers {
+                say!("  {} {} ({})", style("●").green(), signer.name, style(signer.public_key).dim());
+            }
+            for key in store.retired()? {
+                say!(
+                    "  {} {} ({}, retired {})",
+                    style("○").dim(),
+                    key.signer,
+                    style(key.public_key).dim(),
+                    key.retired_at.format("%Y-%m-%d %H:%M:%S UTC")
+                );
+            }
+            say!();
+            say!("Trust policy: {}", style(&config.security.trust_policy).cyan());
+        }
+
+        TrustCommands::Rotate { old, new } => {
+            say!("{}", style("Rotating signing key...").bold());
+
+            let old_key = SigningKey::load(&old)
+                .map_err(|e| anyhow!("--old must be the private key file of the key to retire ({}): {}", old.display(), e))?;
+            let new_content = std::fs::read_to_string(&new)
+                .map_err(|e| anyhow!("Failed to read {}: {}", new.display(), e))?;
+            let retired = store.rotate(&old_key, &new_content, chrono::Utc::now())?;
+
+            say!("  Signer:  {}", retired.signer);
+            say!("  Retired: {}", retired.public_key);
+            say!("  New key: {}", retired.successor.unwrap_or_default());
+            say!();
+            say!(
+                "{} Retired the old key of {} as of {}",
+                style("✓").green(),
+                retired.signer,
+                retired.retired_at.format("%Y-%m-%d %H:%M:%S UTC")
+            );
+            say!(
+                "  Run {} to re-sign cached chunks",
+                style(format!("cadi publish --resign --key {}", new.display())).bold()
+            );
+        }
+
+        TrustCommands::Export { key } => {
+            let key_path = key
+                .or_else(|| config.security.signing_key.clone())
+                .ok_or_else(|| anyhow!("No signing key configured; pass --key"))?;
+            let signing_key = SigningKey::load(&key_path)?;
+            say!("{}", signing_key.public_key_pem().trim_end_matches('\n'));
         }
         
         TrustCommands::Policy { mode } => {
             if let Some(new_mode) = mode {
-                println!("{}", style("Setting trust policy...").bold());
+                say!("{}", style("Setting trust policy...").bold());
                 
                 match new_mode.as_str() {
                     "strict" | "standard" | "permissive" => {
                         // Would update config
-                        println!("{} Trust policy set to: {}", style("✓").green(), new_mode);
+                        say!("{} Trust policy set to: {}", style("✓").green(), new_mode);
                     }
                     _ => {
-                        println!("{} Invalid policy. Use: strict, standard, or permissive", style("✗").red());
+                        say!("{} Invalid policy. Use: strict, standard, or permissive", style("✗").red());
                     }
                 }
             } else {
-                println!("{}", style("Trust Policy").bold());
-                println!();
-                println!("Current policy: {}", style(&config.security.trust_policy).cyan());
-                println!();
-                println!("Available policies:");
-                println!("  {} - All artifacts must have valid signatures from trusted signers", style("strict").bold());
-                println!("  {} - Require signatures for IR/blob/container, optional for source", style("standard").bold());
-                println!("  {} - Allow unsigned artifacts with warning", style("permissive").bold());
+                say!("{}", style("Trust Policy").bold());
+                say!();
+                say!("Current policy: {}", style(&config.security.trust_policy).cyan());
+                say!();
+                say!("Available policies:");
+                say!("  {} - All artifacts must have valid signatures from trusted signers", style("strict").bold());
+                say!("  {} - Require signatures for IR/blob/container, optional for source", style("standard").bold());
+                say!("  {} - Allow unsigned artifacts with warning", style("permissive").bold());
             }
         }
     }
@@ -1,62 +1,221 @@
 use super::super::config::CadiConfig as Config;
 use anyhow::{Context, Result};
 use clap::Args;
+use console::style;
 use std::fs;
-use std::path::PathBuf;
-use cadi_core::parser::parse_file;
-use cadi_core::validator::Validator;
+use std::path::{Path, PathBuf};
+use cadi_core::parser::parse_document;
+use cadi_core::validator::{Diagnostic, Severity, Validator};
+use cadi_core::{upgrade_manifest_source, ManifestLock, LOCK_FILE};
+use crate::error::CommandError;
+use crate::output::say;
 
-/// Validate a CADL file against the specification
+/// Validate a CADL file or a CADI manifest against the specification
 #[derive(Args)]
 pub struct ValidateArgs {
-    /// Path to the CADL file to validate
+    /// Path to the CADL file or manifest (.yaml, .yml, .json) to validate
     #[arg(required = true)]
     pub file: PathBuf,
 
     /// Verbose output showing parsed structure
     #[arg(short, long)]
     pub verbose: bool,
+
+    /// Output format (text, json)
+    #[arg(long, default_value = "text")]
+    pub format: String,
+
+    /// Rewrite an older manifest in place to the current cadl_version
+    /// before validating it
+    #[arg(long)]
+    pub upgrade: bool,
 }
 
 pub async fn execute(args: ValidateArgs, _config: &Config) -> Result<()> {
-    println!("Validating CADL file: {:?}", args.file);
-
-    let content = fs::read_to_string(&args.file)
+    let mut content = fs::read_to_string(&args.file)
         .with_context(|| format!("Failed to read file: {:?}", args.file))?;
 
-    // Phase 1: Parsing
-    println!("Phase 1: Parsing...");
-    let doc = match parse_file(&content) {
-        Ok(doc) => {
-            println!("✓ Parsing successful");
-            doc
+    let is_manifest = args
+        .file
+        .extension()
+        .is_some_and(|ext| ext == "yaml" || ext == "yml" || ext == "json");
+
+    let mut upgrade = None;
+    if args.upgrade {
+        if !is_manifest {
+            anyhow::bail!("--upgrade only applies to manifests (.yaml, .yml, .json)");
         }
-        Err(e) => {
-            eprintln!("✕ Parsing failed:\n{}", e);
-            std::process::exit(1);
+        let upgraded = upgrade_manifest_source(&content)
+            .with_context(|| format!("Failed to upgrade {}", args.file.display()))?;
+        if upgraded.is_upgraded() {
+            fs::write(&args.file, &upgraded.source)
+                .with_context(|| format!("Failed to write {}", args.file.display()))?;
+            content = upgraded.source.clone();
         }
-    };
-
-    if args.verbose {
-        println!("{:#?}", doc);
+        if args.format != "json" {
+            report_upgrade(&upgraded, &args.file);
+        }
+        upgrade = Some(upgraded);
     }
 
-    // Phase 2: Semantic Validation
-    println!("Phase 2: Semantic Validation...");
-    let validator = Validator::new();
-    match validator.validate(&doc) {
-        Ok(_) => {
-            println!("✓ Validation successful");
-            println!("File {:?} is valid CADL v2.", args.file);
+    let diagnostics = if is_manifest {
+        let lock_path = args.file.with_file_name(LOCK_FILE);
+        let lock = if lock_path.exists() {
+            Some(ManifestLock::load(&lock_path)?)
+        } else {
+            None
+        };
+
+        let report = Validator::new().validate_manifest_source(&content, lock.as_ref());
+        if args.verbose && args.format != "json" {
+            if let Some(manifest) = &report.manifest {
+                say!("{:#?}", manifest);
+            }
         }
-        Err(errors) => {
-            eprintln!("✕ Validation failed with {} errors:", errors.len());
-            for (i, err) in errors.iter().enumerate() {
-                eprintln!("  {}. [{}] {}", i + 1, err.path, err.message);
+        report.diagnostics
+    } else {
+        match parse_document(&content) {
+            Ok(doc) => {
+                if args.verbose && args.format != "json" {
+                    say!("{:#?}", doc);
+                }
+                Validator::new().diagnose(&doc)
             }
-            std::process::exit(1);
+            Err(diagnostic) => vec![diagnostic],
+        }
+    };
+
+    let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+    let warnings = diagnostics.len() - errors;
+
+    if args.format == "json" {
+        let mut report = serde_json::json!({
+            "file": args.file,
+            "valid": errors == 0,
+            "errors": errors,
+            "warnings": warnings,
+            "diagnostics": diagnostics,
+        });
+        if let Some(upgrade) = &upgrade {
+            report["upgrade"] = serde_json::json!({
+                "from": upgrade.from,
+                "to": upgrade.to,
+                "changes": upgrade.changes,
+                "preserved_formatting": upgrade.preserved_formatting,
+            });
+        }
+        say!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        for diagnostic in &diagnostics {
+            eprintln!("{}", render(diagnostic, &args.file, &content));
+        }
+        if errors == 0 {
+            say!(
+                "{} {} is valid{}",
+                style("✓").green(),
+                args.file.display(),
+                if warnings > 0 { format!(" ({} warnings)", warnings) } else { String::new() }
+            );
+        } else {
+            eprintln!(
+                "{}: {} failed validation with {} errors and {} warnings",
+                style("error").red().bold(),
+                args.file.display(),
+                errors,
+                warnings
+            );
         }
     }
 
+    if errors > 0 {
+        return Err(CommandError::Validation(format!(
+            "{} failed validation with {} errors",
+            args.file.display(),
+            errors
+        ))
+        .into());
+    }
     Ok(())
 }
+
+fn report_upgrade(upgrade: &cadi_core::ManifestUpgrade, file: &Path) {
+    if !upgrade.is_upgraded() {
+        say!("{} is already at cadl_version {}", file.display(), upgrade.to);
+        return;
+    }
+    say!(
+        "{} Upgraded {} from cadl_version {} to {}",
+        style("✓").green(),
+        file.display(),
+        upgrade.from,
+        upgrade.to
+    );
+    for change in &upgrade.changes {
+        say!("  {}", change);
+    }
+    if !upgrade.preserved_formatting {
+        say!(
+            "  {} the manifest was re-serialized; formatting and comments were not kept",
+            style("note:").yellow()
+        );
+    }
+}
+
+/// Format a diagnostic like a compiler message, quoting the source line
+fn render(diagnostic: &Diagnostic, file: &Path, source: &str) -> String {
+    let severity = match diagnostic.severity {
+        Severity::Error => style(format!("error[{}]", diagnostic.code)).red().bold(),
+        Severity::Warning => style(format!("warning[{}]", diagnostic.code)).yellow().bold(),
+    };
+    let mut out = format!("{}: {}\n", severity, style(&diagnostic.message).bold());
+
+    let Some(span) = diagnostic.span else {
+        out.push_str(&format!("  --> {} ({})\n", file.display(), diagnostic.path));
+        return out;
+    };
+
+    let gutter = span.line.to_string().len();
+    let pad = " ".repeat(gutter);
+    out.push_str(&format!("{}--> {}:{}:{}\n", pad, file.display(), span.line, span.column));
+    if let Some(line) = source.lines().nth(span.line - 1) {
+        out.push_str(&format!("{} {}\n", pad, style("|").blue()));
+        out.push_str(&format!("{} {} {}\n", style(span.line).blue(), style("|").blue(), line));
+        out.push_str(&format!(
+            "{} {} {}{}\n",
+            pad,
+            style("|").blue(),
+            " ".repeat(span.column.saturating_sub(1)),
+            style("^").red().bold()
+        ));
+    }
+    if diagnostic.path != "root" {
+        out.push_str(&format!("{} {} path: {}\n", pad, style("=").blue(), diagnostic.path));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cadi_core::validator::codes;
+    use cadi_core::ast::Span;
+
+    #[test]
+    fn test_render_quotes_source_line() {
+        console::set_colors_enabled(false);
+        let source = "manifest_id: demo\nbuild_targets:\n- name: dev\n  platform: ''\n";
+        let diagnostic = Diagnostic::error(codes::MISSING_PLATFORM, "Build target 'dev' has no platform", "build_targets[0].platform")
+            .with_span(Span { line: 4, column: 3 });
+
+        let rendered = render(&diagnostic, Path::new("cadi.yaml"), source);
+        assert_eq!(
+            rendered,
+            "error[CADL025]: Build target 'dev' has no platform\n \
+             --> cadi.yaml:4:3\n  \
+             |\n\
+             4 |   platform: ''\n  \
+             |   ^\n  \
+             = path: build_targets[0].platform\n"
+        );
+    }
+}
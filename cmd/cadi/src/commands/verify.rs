@@ -1,9 +1,61 @@
 use anyhow::Result;
 use clap::Args;
 use console::style;
-use sha2::{Sha256, Digest};
+use serde::Serialize;
+
+use cadi_builder::{BuildCache, BuildConfig, BuildEngine, Replay};
+use cadi_core::trust::{SignatureStatus, TrustStore, TrustVerdict};
+use cadi_core::ChunkId;
 
 use crate::config::CadiConfig;
+use crate::error::CommandError;
+use crate::output::say;
+
+/// Why a chunk failed verification, most severe first. When several
+/// chunks fail, the command fails with the most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyFailure {
+    HashMismatch,
+    InvalidSignature,
+    UntrustedSigner,
+    Unsigned,
+    NotFound,
+    NotReproduced,
+}
+
+impl VerifyFailure {
+    fn error(self, chunk_id: &str) -> CommandError {
+        match self {
+            VerifyFailure::HashMismatch => {
+                CommandError::Validation(format!("Verification failed: content of {} does not match its hash", chunk_id))
+            }
+            VerifyFailure::InvalidSignature => {
+                CommandError::Validation(format!("Verification failed: {} has an invalid signature", chunk_id))
+            }
+            VerifyFailure::UntrustedSigner => {
+                CommandError::Validation(format!("Verification failed: {} is signed only by untrusted keys", chunk_id))
+            }
+            VerifyFailure::Unsigned => CommandError::Validation(format!("Verification failed: {} is unsigned", chunk_id)),
+            VerifyFailure::NotFound => CommandError::NotFound(format!("Chunk {} not found locally", chunk_id)),
+            VerifyFailure::NotReproduced => {
+                CommandError::Build(format!("Verification failed: rebuilding {} produced a different output", chunk_id))
+            }
+        }
+    }
+}
+
+/// Verification outcome of every chunk checked
+#[derive(Debug, Default, Serialize)]
+pub struct VerifyReport {
+    pub chunks: Vec<ChunkVerification>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChunkVerification {
+    pub chunk_id: String,
+    pub failure: Option<VerifyFailure>,
+}
 
 /// Arguments for the verify command
 #[derive(Args)]
@@ -16,25 +68,32 @@ pub struct VerifyArgs {
     #[arg(long)]
     deep: bool,
 
-    /// Attempt to rebuild and compare hashes
+    /// Rebuild from the chunk's build receipt and compare output hashes
     #[arg(long)]
     rebuild: bool,
 
+    /// Let the rebuild run an external command the receipt records
+    #[arg(long, requires = "rebuild")]
+    allow_external_transforms: bool,
+
     /// Output format (text, json)
     #[arg(long, default_value = "text")]
     format: String,
 }
 
 /// Execute the verify command
-pub async fn execute(args: VerifyArgs, config: &CadiConfig) -> Result<()> {
-    println!("{}", style("Verifying...").bold());
-    println!("  Target: {}", args.target);
-    println!();
+pub async fn execute(args: VerifyArgs, config: &CadiConfig) -> Result<VerifyReport> {
+    say!("{}", style("Verifying...").bold());
+    say!("  Target: {}", args.target);
+    say!();
 
     let is_chunk = args.target.starts_with("chunk:");
+    let trust_store = TrustStore::open(&config.security.trust_dir);
+    let mut report = VerifyReport::default();
 
     if is_chunk {
-        verify_chunk(&args.target, &args, config).await?;
+        let failure = verify_chunk(&args.target, &args, config, &trust_store).await?;
+        report.chunks.push(ChunkVerification { chunk_id: args.target.clone(), failure });
     } else {
         // Treat as manifest
         let manifest_content = std::fs::read_to_string(&args.target)?;
@@ -43,45 +102,66 @@ pub async fn execute(args: VerifyArgs, config: &CadiConfig) -> Result<()> {
         if let Some(nodes) = manifest["build_graph"]["nodes"].as_array() {
             for node in nodes {
                 if let Some(source_cadi) = node["source_cadi"].as_str() {
-                    verify_chunk(source_cadi, &args, config).await?;
+                    let failure = verify_chunk(source_cadi, &args, config, &trust_store).await?;
+                    report.chunks.push(ChunkVerification { chunk_id: source_cadi.to_string(), failure });
                 }
             }
         }
     }
 
-    println!();
-    println!("{}", style("Verification complete!").green().bold());
+    say!();
+    let worst = report.chunks.iter()
+        .filter_map(|c| c.failure.map(|failure| (failure, &c.chunk_id)))
+        .min();
+    if let Some((failure, chunk_id)) = worst {
+        say!("{}", style("Verification failed").red().bold());
+        return Err(failure.error(chunk_id).into());
+    }
+    say!("{}", style("Verification complete!").green().bold());
 
-    Ok(())
+    Ok(report)
 }
 
-async fn verify_chunk(chunk_id: &str, args: &VerifyArgs, config: &CadiConfig) -> Result<()> {
-    // Extract the expected hash from chunk ID
-    let expected_hash = chunk_id.strip_prefix("chunk:sha256:").unwrap_or(chunk_id);
+/// Verify one chunk, returning its failure if any
+async fn verify_chunk(
+    chunk_id: &str,
+    args: &VerifyArgs,
+    config: &CadiConfig,
+    trust_store: &TrustStore,
+) -> Result<Option<VerifyFailure>> {
+    // Extract the expected hash from chunk ID; a bare hash is taken as SHA-256
+    let id = ChunkId::parse(chunk_id).unwrap_or_else(|_| ChunkId::from_storage_key(chunk_id));
+    let expected_hash = id.digest.as_str();
     let cache_dir = config.cache.dir.join("chunks");
-    let chunk_file = cache_dir.join(format!("{}.bin", expected_hash));
-    let metadata_file = cache_dir.join(format!("{}.json", expected_hash));
+    let metadata_file = cache_dir.join(format!("{}.json", id.storage_key()));
+    // Fetched chunks, else artifacts built into the same cache
+    let chunk_file = Some(cache_dir.join(format!("{}.bin", id.storage_key())))
+        .filter(|path| path.exists())
+        .unwrap_or_else(|| BuildCache::new(config.cache.dir.clone()).get_path(chunk_id));
 
-    println!("{}", style(format!("Chunk: {}", &chunk_id[..50.min(chunk_id.len())])).bold());
+    say!("{}", style(format!("Chunk: {}", &chunk_id[..50.min(chunk_id.len())])).bold());
 
     // Check if chunk exists locally
     if !chunk_file.exists() {
-        println!("  {} Chunk not found locally", style("⚠").yellow());
-        return Ok(());
+        say!("  {} Chunk not found locally", style("✗").red());
+        return Ok(Some(VerifyFailure::NotFound));
     }
 
     // Read and verify content hash
     let chunk_content = std::fs::read(& chunk_file)?;
-    let mut hasher = Sha256::new();
-    hasher.update(&chunk_content);
-    let computed_hash = format!("{:x}", hasher.finalize());
+    let computed_hash = id.algorithm.hash_bytes(&chunk_content);
 
     // Compare hashes
     if computed_hash == expected_hash || computed_hash.starts_with(expected_hash) {
-        println!("  {} Content hash verified: {}", style("✓").green(), &computed_hash[..16]);
+        say!("  {} Content hash verified: {}", style("✓").green(), &computed_hash[..16]);
     } else {
-        println!("  {} Hash mismatch! Expected: {}, Got: {}", style("✗").red(), &expected_hash[..16], &computed_hash[..16]);
-        return Err(anyhow::anyhow!("Hash verification failed"));
+        say!(
+            "  {} Hash mismatch! Expected: {}, Got: {}",
+            style("✗").red(),
+            &expected_hash[..16.min(expected_hash.len())],
+            &computed_hash[..16]
+        );
+        return Ok(Some(VerifyFailure::HashMismatch));
     }
 
     // Check metadata
@@ -90,79 +170,159 @@ async fn verify_chunk(chunk_id: &str, args: &VerifyArgs, config: &CadiConfig) ->
             // Verify size matches
             if let Some(size) = metadata.get("size").and_then(|v| v.as_u64()) {
                 if size as usize == chunk_content.len() {
-                    println!("  {} File size verified: {} bytes", style("✓").green(), size);
+                    say!("  {} File size verified: {} bytes", style("✓").green(), size);
                 } else {
-                    println!("  {} Size mismatch: metadata={}, actual={}", style("✗").red(), size, chunk_content.len());
+                    say!("  {} Size mismatch: metadata={}, actual={}", style("✗").red(), size, chunk_content.len());
                 }
             }
             
             // Show creation date if available
             if let Some(created) = metadata.get("created_at").and_then(|v| v.as_str()) {
-                println!("  {} Created: {}", style("●").cyan(), created);
+                say!("  {} Created: {}", style("●").cyan(), created);
             }
         }
     } else {
-        println!("  {} No metadata file found", style("⚠").yellow());
+        say!("  {} No metadata file found", style("⚠").yellow());
     }
 
-    // Check for signatures
-    if let Ok(metadata_content) = std::fs::read_to_string(&metadata_file) {
-        if let Ok(metadata) = serde_json::from_str::<serde_json::Value>(&metadata_content) {
-            if let Some(signatures) = metadata.get("signatures").and_then(|s| s.as_array()) {
-                if signatures.is_empty() {
-                    println!("  {} Signatures: none found", style("○").dim());
-                } else {
-                    println!("  {} Found {} signature(s)", style("✓").green(), signatures.len());
-                    for sig in signatures {
-                        if let Some(sig_str) = sig.as_str() {
-                            if let Some(key_path) = &config.security.signing_key {
-                                if key_path.exists() {
-                                    let key_content = std::fs::read_to_string(key_path)?;
-                                    if verify_signature(&chunk_content, sig_str, &key_content)? {
-                                        println!("    {} Verified: {}", style("✓").green(), sig_str);
-                                    } else {
-                                        println!("    {} Invalid: {}", style("✗").red(), sig_str);
-                                    }
-                                } else {
-                                    println!("    {} Signature exists but no signing key found for verification", style("⚠").yellow());
-                                }
-                            }
-                        }
-                    }
-                }
-            } else {
-                println!("  {} Signatures: field missing in metadata", style("○").dim());
+    // Check signatures against the trust store
+    let signatures: Vec<String> = std::fs::read_to_string(&metadata_file)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|metadata| metadata.get("signatures").cloned())
+        .and_then(|signatures| serde_json::from_value(signatures).ok())
+        .unwrap_or_default();
+
+    let statuses = trust_store.verify_chunk(chunk_id, &signatures)?;
+    for status in &statuses {
+        match status {
+            SignatureStatus::Trusted { signer } => {
+                say!("    {} Signature by {}", style("✓").green(), signer);
+            }
+            SignatureStatus::Untrusted { public_key } => {
+                say!("    {} Signature by untrusted key {}", style("⚠").yellow(), public_key);
+            }
+            SignatureStatus::Retired { signer, retired_at } => {
+                say!(
+                    "    {} Signature by {} with a key retired on {}; re-sign with the current key",
+                    style("⚠").yellow(),
+                    signer,
+                    retired_at.format("%Y-%m-%d")
+                );
+            }
+            SignatureStatus::RetiredUndated { signer, retired_at } => {
+                say!(
+                    "    {} Undated signature by {} with a key retired on {}; not trusted, re-sign with the current key",
+                    style("⚠").yellow(),
+                    signer,
+                    retired_at.format("%Y-%m-%d")
+                );
+            }
+            SignatureStatus::SignedAfterRetirement { signer, retired_at, signed_at } => {
+                say!(
+                    "    {} Signature by {} made {} with a key retired on {}",
+                    style("✗").red(),
+                    signer,
+                    signed_at.format("%Y-%m-%d"),
+                    retired_at.format("%Y-%m-%d")
+                );
+            }
+            SignatureStatus::Invalid { reason } => {
+                say!("    {} Invalid signature: {}", style("✗").red(), reason);
             }
         }
     }
 
+    // The permissive policy only warns about missing trust
+    let permissive = config.security.trust_policy == "permissive";
+    let failure = match TrustVerdict::from_statuses(&statuses) {
+        TrustVerdict::SignedBy(signers) => {
+            say!("  {} Signed by: {}", style("✓").green(), signers.join(", "));
+            None
+        }
+        TrustVerdict::Invalid => {
+            say!("  {} Signature verification failed", style("✗").red());
+            Some(VerifyFailure::InvalidSignature)
+        }
+        TrustVerdict::UntrustedSigner => {
+            say!("  {} Signed only by untrusted keys", trust_marker(permissive));
+            (!permissive).then_some(VerifyFailure::UntrustedSigner)
+        }
+        TrustVerdict::Unsigned => {
+            say!("  {} Unsigned", trust_marker(permissive));
+            (!permissive).then_some(VerifyFailure::Unsigned)
+        }
+    };
+
     // Deep verification of parent chunks
     if args.deep {
-        println!("  {} Verifying dependency chain...", style("→").cyan());
+        say!("  {} Verifying dependency chain...", style("→").cyan());
         // In a real implementation, would trace back through parent chunks in metadata
-        println!("    {} No parents found", style("○").dim());
+        say!("    {} No parents found", style("○").dim());
     }
 
-    // Rebuild verification (would re-hash via transform pipeline)
-    if args.rebuild {
-        println!("  {} Rebuild verification skipped (would require transform pipeline)", style("→").dim());
-    }
+    let rebuilt = if args.rebuild {
+        verify_rebuild(chunk_id, args.allow_external_transforms, config).await?
+    } else {
+        None
+    };
+
+    say!();
+    Ok(failure.into_iter().chain(rebuilt).min())
+}
 
-    println!();
-    Ok(())
+/// Rerun the build recorded in a chunk's receipt and compare output hashes.
+/// Chunks that weren't built here, or whose inputs are gone, only warn.
+async fn verify_rebuild(
+    chunk_id: &str,
+    allow_external_transforms: bool,
+    config: &CadiConfig,
+) -> Result<Option<VerifyFailure>> {
+    let cache = BuildCache::new(config.cache.dir.clone());
+    let Some(receipt_id) = cache.lineage(chunk_id)?.and_then(|lineage| lineage.build_receipt) else {
+        say!("  {} No build receipt; the chunk was not built here", style("⚠").yellow());
+        return Ok(None);
+    };
+    let Some(receipt) = cache.receipt(&receipt_id)? else {
+        say!("  {} Build receipt {} is missing", style("⚠").yellow(), receipt_id);
+        return Ok(None);
+    };
+
+    say!("  {} Rebuilding step {} with {}...", style("→").cyan(), receipt.step, receipt.transform);
+    let engine = BuildEngine::new(BuildConfig {
+        cache_dir: config.cache.dir.clone(),
+        use_remote_cache: false,
+        allow_external_transforms,
+        ..Default::default()
+    });
+    match engine.replay(&receipt).await? {
+        Replay::Reproduced => {
+            say!("  {} Rebuild reproduced the output hash", style("✓").green());
+            Ok(None)
+        }
+        Replay::Differs { expected, actual } => {
+            say!(
+                "  {} Rebuild produced {} instead of {}",
+                style("✗").red(),
+                &actual[..16],
+                &expected[..16.min(expected.len())]
+            );
+            for (tool, recorded, current) in receipt.toolchain_drift(engine.toolchain().await) {
+                say!("    {} {}: built with {}, now {}", style("●").cyan(), tool, recorded, current);
+            }
+            Ok(Some(VerifyFailure::NotReproduced))
+        }
+        Replay::Unavailable(reason) => {
+            say!("  {} Rebuild not possible: {}", style("⚠").yellow(), reason);
+            Ok(None)
+        }
+    }
 }
 
-fn verify_signature(content: &[u8], signature: &str, key_content: &str) -> Result<bool> {
-    if !signature.starts_with("sig:sha256:") {
-        return Ok(false);
+fn trust_marker(permissive: bool) -> console::StyledObject<&'static str> {
+    if permissive {
+        style("⚠").yellow()
+    } else {
+        style("✗").red()
     }
-    
-    use sha2::{Sha256, Digest};
-    let mut hasher = Sha256::new();
-    hasher.update(key_content.as_bytes());
-    hasher.update(content);
-    let result = hasher.finalize();
-    let expected = format!("sig:sha256:{}", hex::encode(result));
-    
-    Ok(signature == expected)
 }
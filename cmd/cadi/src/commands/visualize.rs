@@ -18,6 +18,7 @@ use cadi_core::graph::{GraphStore, GraphQuery, TraversalDirection};
 use cadi_core::graph::store::GraphStoreStats;
 
 use crate::config::CadiConfig;
+use crate::output::say;
 
 use axum::{
     extract::Query,
@@ -72,7 +73,7 @@ async fn run_tui(config: &CadiConfig) -> Result<()> {
     terminal.show_cursor()?;
 
     if let Err(err) = res {
-        println!("{:?}", err)
+        say!("{:?}", err)
     }
 
     Ok(())
@@ -180,7 +181,7 @@ impl App {
         }
 
         // Search for chunks by alias or symbol
-        let query = GraphQuery::new(vec![])
+        let query = GraphQuery::new()
             .with_direction(TraversalDirection::Both)
             .with_depth(1);
 
@@ -227,6 +228,8 @@ async fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, confi
                 dependency_entries: 0,
                 dependent_entries: 0,
                 content_entries: 0,
+                blob_count: 0,
+                blob_bytes: 0,
                 db_size_bytes: 0,
             })
         } else {
@@ -237,6 +240,8 @@ async fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, confi
                 dependency_entries: 0,
                 dependent_entries: 0,
                 content_entries: 0,
+                blob_count: 0,
+                blob_bytes: 0,
                 db_size_bytes: 0,
             }
         };
@@ -442,8 +447,8 @@ async fn run_web(port: u16, _config: &CadiConfig) -> Result<()> {
 
     // Bind to address
     let addr = format!("0.0.0.0:{}", port);
-    println!("Starting web server at http://{}", addr);
-    println!("Open this URL in your browser to view the visualization");
+    say!("Starting web server at http://{}", addr);
+    say!("Open this URL in your browser to view the visualization");
 
     let listener = tokio::net::TcpListener::bind(&addr).await?;
     axum::serve(listener, app).await?;
@@ -554,10 +559,10 @@ async fn chunks_api_handler(
     let chunks = match GraphStore::open(std::env::var("CADI_CACHE_DIR").unwrap_or_else(|_| "data/graph-db".to_string())) {
         Ok(store) => {
             let query = if search.is_empty() {
-                GraphQuery::new(vec![])
+                GraphQuery::new()
             } else {
                 // Search by alias or chunk_id
-                GraphQuery::new(vec![]).with_depth(1)
+                GraphQuery::new().with_depth(1)
             };
 
             match store.query(&query) {
@@ -620,7 +625,7 @@ async fn graph_api_handler() -> axum::response::Json<serde_json::Value> {
     // Get real graph data from GraphStore for visualization
     let graph_data = match GraphStore::open("data/graph-db") {
         Ok(store) => {
-            let query = GraphQuery::new(vec![]).with_limit(50); // Limit to 50 nodes for visualization
+            let query = GraphQuery::new().with_limit(50); // Limit to 50 nodes for visualization
             match store.query(&query) {
                 Ok(result) => {
                     let nodes: Vec<serde_json::Value> = result.nodes
@@ -3,6 +3,8 @@ use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
+use cadi_core::HashAlgorithm;
+
 /// CADI Configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[derive(Default)]
@@ -75,6 +77,18 @@ pub struct BuildConfig {
     /// Preferred representations (in order)
     #[serde(default = "default_prefer_representation")]
     pub prefer_representation: Vec<String>,
+
+    /// Registry serving a shared build cache
+    #[serde(default)]
+    pub remote_cache_url: Option<String>,
+
+    /// Fetch from the remote cache without uploading (e.g. for local builds)
+    #[serde(default)]
+    pub remote_cache_read_only: bool,
+
+    /// Hash algorithm for new chunk IDs (sha256, blake3)
+    #[serde(default)]
+    pub hash_algorithm: HashAlgorithm,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -87,6 +101,10 @@ pub struct SecurityConfig {
     #[serde(default = "default_trust_policy")]
     pub trust_policy: String,
 
+    /// Directory of trusted signer public keys
+    #[serde(default = "default_trust_dir")]
+    pub trust_dir: PathBuf,
+
     /// Verify signatures on fetch
     #[serde(default = "default_true")]
     pub verify_on_fetch: bool,
@@ -140,6 +158,14 @@ fn default_trust_policy() -> String {
     "standard".to_string()
 }
 
+fn default_trust_dir() -> PathBuf {
+    if let Some(base_dirs) = directories::BaseDirs::new() {
+        base_dirs.home_dir().join(".cadi").join("trust")
+    } else {
+        PathBuf::from("~/.cadi/trust")
+    }
+}
+
 fn default_true() -> bool {
     true
 }
@@ -177,6 +203,9 @@ impl Default for BuildConfig {
         Self {
             parallelism: default_parallelism(),
             prefer_representation: default_prefer_representation(),
+            remote_cache_url: None,
+            remote_cache_read_only: false,
+            hash_algorithm: HashAlgorithm::default(),
         }
     }
 }
@@ -186,6 +215,7 @@ impl Default for SecurityConfig {
         Self {
             signing_key: None,
             trust_policy: default_trust_policy(),
+            trust_dir: default_trust_dir(),
             verify_on_fetch: true,
             sandbox_untrusted: true,
         }
@@ -285,6 +315,7 @@ pub fn load_local_config() -> CadiConfig {
         security: SecurityConfig {
             signing_key: None,
             trust_policy: "permissive".to_string(),
+            trust_dir: default_trust_dir(),
             verify_on_fetch: false,
             sandbox_untrusted: false,
         },
@@ -0,0 +1,142 @@
+//! Command failures and the exit codes scripts see
+//!
+//! | Code | Meaning |
+//! |------|---------|
+//! | 0 | Success |
+//! | 1 | Any other error |
+//! | 2 | Validation error: bad input, or a chunk that fails verification |
+//! | 3 | Not found: chunk, alias, manifest or file |
+//! | 4 | Network or registry error |
+//! | 5 | Build failure |
+
+use cadi_core::CadiError;
+use cadi_registry::RegistryError;
+
+/// A failure whose kind a command knows; return it inside `anyhow::Error`
+/// (`Err(CommandError::NotFound(..).into())`). Errors of other types are
+/// classified by their causes in [`ErrorKind::of`].
+#[derive(Debug, thiserror::Error)]
+pub enum CommandError {
+    #[error("{0}")]
+    Validation(String),
+
+    #[error("{0}")]
+    NotFound(String),
+
+    #[error("{0}")]
+    Network(String),
+
+    #[error("{0}")]
+    Build(String),
+}
+
+impl CommandError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            CommandError::Validation(_) => ErrorKind::Validation,
+            CommandError::NotFound(_) => ErrorKind::NotFound,
+            CommandError::Network(_) => ErrorKind::Network,
+            CommandError::Build(_) => ErrorKind::Build,
+        }
+    }
+}
+
+/// Kind of failure, deciding the exit code and the `--json` error code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Other,
+    Validation,
+    NotFound,
+    Network,
+    Build,
+}
+
+impl ErrorKind {
+    /// Kind of the first cause of `error` that has one: a [`CommandError`],
+    /// a registry or core error, a failed request, a missing file or a
+    /// document that doesn't parse
+    pub fn of(error: &anyhow::Error) -> Self {
+        if let Some(e) = error.downcast_ref::<CommandError>() {
+            return e.kind();
+        }
+        for cause in error.chain() {
+            if let Some(e) = cause.downcast_ref::<CommandError>() {
+                return e.kind();
+            }
+            if let Some(e) = cause.downcast_ref::<RegistryError>() {
+                return match e {
+                    RegistryError::NotFound(_) => ErrorKind::NotFound,
+                    RegistryError::Storage(_) => ErrorKind::Other,
+                    _ => ErrorKind::Network,
+                };
+            }
+            if let Some(e) = cause.downcast_ref::<CadiError>() {
+                match e {
+                    CadiError::ChunkNotFound(_) | CadiError::ManifestNotFound(_) | CadiError::AliasNotFound(_) => {
+                        return ErrorKind::NotFound
+                    }
+                    CadiError::BuildFailed(_) | CadiError::TransformFailed(_) => return ErrorKind::Build,
+                    CadiError::RegistryError(_) => return ErrorKind::Network,
+                    CadiError::InvalidChunkId(_) | CadiError::Configuration(_) => return ErrorKind::Validation,
+                    _ => {}
+                }
+            }
+            if cause.is::<reqwest::Error>() {
+                return ErrorKind::Network;
+            }
+            if cause.is::<serde_yaml::Error>() || cause.is::<serde_json::Error>() {
+                return ErrorKind::Validation;
+            }
+            if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+                if e.kind() == std::io::ErrorKind::NotFound {
+                    return ErrorKind::NotFound;
+                }
+            }
+        }
+        ErrorKind::Other
+    }
+
+    pub fn exit_code(self) -> u8 {
+        match self {
+            ErrorKind::Other => 1,
+            ErrorKind::Validation => 2,
+            ErrorKind::NotFound => 3,
+            ErrorKind::Network => 4,
+            ErrorKind::Build => 5,
+        }
+    }
+
+    /// Code of the error in the `--json` document
+    pub fn code(self) -> &'static str {
+        match self {
+            ErrorKind::Other => "error",
+            ErrorKind::Validation => "validation",
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::Network => "network",
+            ErrorKind::Build => "build",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_error_kinds() {
+        let error: anyhow::Error = CommandError::NotFound("chunk:sha256:abc".to_string()).into();
+        assert_eq!(ErrorKind::of(&error.context("Fetch failed")), ErrorKind::NotFound);
+
+        let error = anyhow::Error::from(RegistryError::RateLimited { retry_after: None });
+        assert_eq!(ErrorKind::of(&error).exit_code(), 4);
+
+        let missing = std::fs::read("/nonexistent/cadi.yaml").context("Failed to read manifest").unwrap_err();
+        assert_eq!(ErrorKind::of(&missing), ErrorKind::NotFound);
+
+        let invalid = serde_yaml::from_str::<serde_json::Value>("a: [").map_err(anyhow::Error::from).unwrap_err();
+        assert_eq!(ErrorKind::of(&invalid).code(), "validation");
+
+        assert_eq!(ErrorKind::of(&anyhow::anyhow!("something else")), ErrorKind::Other);
+    }
+}
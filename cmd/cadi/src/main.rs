@@ -1,10 +1,18 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
+use serde::Serialize;
+use std::process::ExitCode;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+mod error;
+mod output;
 mod commands;
 mod config;
 
+use error::ErrorKind;
+use output::Envelope;
+
 /// CADI - Content-Addressed Development Interface
 /// 
 /// A universal build and distribution system for software artifacts,
@@ -23,6 +31,11 @@ struct Cli {
     #[arg(long, global = true)]
     config: Option<String>,
 
+    /// Print one JSON document, `{ok, data, error: {code, message}}`, on
+    /// stdout and send progress to stderr
+    #[arg(long, global = true)]
+    json: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -60,9 +73,27 @@ enum Commands {
     /// Verify signatures and provenance
     Verify(commands::verify::VerifyArgs),
 
+    /// Show how a chunk was built, back through its lineage
+    Explain(commands::explain::ExplainArgs),
+
     /// Manage trusted signers
     Trust(commands::trust::TrustArgs),
 
+    /// List, sign and verify extensions
+    Extension(commands::extension::ExtensionArgs),
+
+    /// Maintain registries: re-embed the search index, mirror chunks
+    Registry(commands::registry::RegistryArgs),
+
+    /// Manage chunk aliases
+    Alias(commands::alias::AliasArgs),
+
+    /// Export or import the dependency graph
+    Graph(commands::graph::GraphArgs),
+
+    /// Record a chunk as a newer version of another
+    Refine(commands::refine::RefineArgs),
+
     /// Garbage collect local cache
     Gc(commands::gc::GcArgs),
 
@@ -72,7 +103,8 @@ enum Commands {
     /// Run demo projects
     Demo(commands::demo::DemoArgs),
 
-    /// Scrape and chunk repositories or files
+    /// Scrape and chunk repositories, packages or files
+    #[command(disable_version_flag = true)]
     Scrape(commands::scrape::ScrapeArgs),
 
     /// Validate a CADL file against the specification
@@ -83,41 +115,87 @@ enum Commands {
 
     /// Visualize repository data with TUI or web GUI
     Visualize(commands::visualize::VisualizeArgs),
+
+    /// Compare two chunks, or a project's chunks with its last import
+    Diff(commands::diff::DiffArgs),
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    // Initialize tracing
+async fn main() -> ExitCode {
+    let cli = Cli::try_parse().unwrap_or_else(|e| {
+        // Usage errors still produce the JSON document scripts expect
+        if e.use_stderr() && std::env::args().any(|arg| arg == "--json") {
+            Envelope::failure(ErrorKind::Validation, e.to_string().trim_end().to_string()).print();
+            std::process::exit(ErrorKind::Validation.exit_code().into());
+        }
+        e.exit()
+    });
+    output::set_json(cli.json);
+
+    // Initialize tracing, keeping logs out of the JSON document
+    let writer = if cli.json { BoxMakeWriter::new(std::io::stderr) } else { BoxMakeWriter::new(std::io::stdout) };
     tracing_subscriber::registry()
         .with(tracing_subscriber::EnvFilter::new(
             std::env::var("CADI_LOG").unwrap_or_else(|_| "cadi=info".into()),
         ))
-        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_subscriber::fmt::layer().with_writer(writer))
         .init();
 
-    let cli = Cli::parse();
+    match run(cli.command, cli.config.as_deref()).await {
+        Ok(data) => {
+            if cli.json {
+                Envelope::success(data).print();
+            }
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            let kind = ErrorKind::of(&error);
+            if cli.json {
+                Envelope::failure(kind, format!("{:#}", error)).print();
+            } else {
+                eprintln!("Error: {:?}", error);
+            }
+            ExitCode::from(kind.exit_code())
+        }
+    }
+}
 
+/// Run a command, returning its report as the `data` of the JSON document
+async fn run(command: Commands, config_path: Option<&str>) -> Result<serde_json::Value> {
     // Load configuration
-    let config = config::load_config(cli.config.as_deref())?;
-
-    match cli.command {
-        Commands::Init(args) => commands::init::execute(args, &config).await,
-        Commands::Import(args) => commands::import_v2::execute(args, &config).await,
-        Commands::ImportLegacy(args) => commands::import::execute(args, &config).await,
-        Commands::Build(args) => commands::build::execute(args, &config).await,
-        Commands::Publish(args) => commands::publish::execute(args, &config).await,
-        Commands::Fetch(args) => commands::fetch::execute(args, &config).await,
-        Commands::Query(args) => commands::query::execute(args, &config).await,
-        Commands::Run(args) => commands::run::execute(args, &config).await,
-        Commands::Plan(args) => commands::plan::execute(args, &config).await,
-        Commands::Verify(args) => commands::verify::execute(args, &config).await,
-        Commands::Trust(args) => commands::trust::execute(args, &config).await,
-        Commands::Gc(args) => commands::gc::execute(args, &config).await,
-        Commands::Stats(args) => commands::stats::execute(args, &config).await,
-        Commands::Demo(args) => commands::demo::execute(args, &config).await,
-        Commands::Scrape(args) => commands::scrape::execute(args, &config).await,
-        Commands::Validate(args) => commands::validate::execute(args, &config).await,
-        Commands::Scaffold(args) => commands::scaffold::execute(args, &config).await,
-        Commands::Visualize(args) => commands::visualize::execute(args, &config).await,
+    let config = config::load_config(config_path)
+        .map_err(|e| error::CommandError::Validation(format!("Invalid configuration: {:#}", e)))?;
+
+    match command {
+        Commands::Init(args) => report(commands::init::execute(args, &config).await),
+        Commands::Import(args) => report(commands::import_v2::execute(args, &config).await),
+        Commands::ImportLegacy(args) => report(commands::import::execute(args, &config).await),
+        Commands::Build(args) => report(commands::build::execute(args, &config).await),
+        Commands::Publish(args) => report(commands::publish::execute(args, &config).await),
+        Commands::Fetch(args) => report(commands::fetch::execute(args, &config).await),
+        Commands::Query(args) => report(commands::query::execute(args, &config).await),
+        Commands::Run(args) => report(commands::run::execute(args, &config).await),
+        Commands::Plan(args) => report(commands::plan::execute(args, &config).await),
+        Commands::Verify(args) => report(commands::verify::execute(args, &config).await),
+        Commands::Explain(args) => report(commands::explain::execute(args, &config).await),
+        Commands::Trust(args) => report(commands::trust::execute(args, &config).await),
+        Commands::Extension(args) => report(commands::extension::execute(args, &config).await),
+        Commands::Registry(args) => report(commands::registry::execute(args, &config).await),
+        Commands::Alias(args) => report(commands::alias::execute(args, &config).await),
+        Commands::Graph(args) => report(commands::graph::execute(args, &config).await),
+        Commands::Refine(args) => report(commands::refine::execute(args, &config).await),
+        Commands::Gc(args) => report(commands::gc::execute(args, &config).await),
+        Commands::Stats(args) => report(commands::stats::execute(args, &config).await),
+        Commands::Demo(args) => report(commands::demo::execute(args, &config).await),
+        Commands::Scrape(args) => report(commands::scrape::execute(args, &config).await),
+        Commands::Validate(args) => report(commands::validate::execute(args, &config).await),
+        Commands::Scaffold(args) => report(commands::scaffold::execute(args, &config).await),
+        Commands::Visualize(args) => report(commands::visualize::execute(args, &config).await),
+        Commands::Diff(args) => report(commands::diff::execute(args, &config).await),
     }
 }
+
+/// A command's result with its report as JSON; `()` becomes `null`
+fn report<T: Serialize>(result: Result<T>) -> Result<serde_json::Value> {
+    Ok(serde_json::to_value(result?)?)
+}
@@ -0,0 +1,86 @@
+//! How commands report to their caller
+//!
+//! Normally commands print prose. With the global `--json` flag, stdout
+//! carries a single document instead, and the lines commands print with
+//! [`say!`] go to stderr:
+//!
+//! ```json
+//! {"ok": true, "data": {...}, "error": null}
+//! {"ok": false, "data": null, "error": {"code": "not_found", "message": "..."}}
+//! ```
+//!
+//! `data` is what the command returned (`null` for commands without a
+//! report) and `error.code` is one of the [`ErrorKind`] codes.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::error::ErrorKind;
+
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// Switch to `--json` output for the rest of the process
+pub fn set_json(json: bool) {
+    JSON.store(json, Ordering::Relaxed);
+}
+
+/// Whether `--json` was given
+pub fn json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// Print a line for people: to stdout, or to stderr with `--json` so
+/// stdout stays one JSON document
+macro_rules! say {
+    () => {
+        $crate::output::say_line(format_args!(""))
+    };
+    ($($arg:tt)*) => {
+        $crate::output::say_line(format_args!($($arg)*))
+    };
+}
+pub(crate) use say;
+
+pub fn say_line(line: std::fmt::Arguments) {
+    if json() {
+        eprintln!("{}", line);
+    } else {
+        println!("{}", line);
+    }
+}
+
+/// The `--json` document
+#[derive(Debug, Serialize)]
+pub struct Envelope {
+    pub ok: bool,
+    pub data: serde_json::Value,
+    pub error: Option<EnvelopeError>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EnvelopeError {
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl Envelope {
+    pub fn success(data: serde_json::Value) -> Self {
+        Self { ok: true, data, error: None }
+    }
+
+    pub fn failure(kind: ErrorKind, message: String) -> Self {
+        Self {
+            ok: false,
+            data: serde_json::Value::Null,
+            error: Some(EnvelopeError { code: kind.code(), message }),
+        }
+    }
+
+    /// Print the document to stdout
+    pub fn print(&self) {
+        match serde_json::to_string_pretty(self) {
+            Ok(document) => println!("{}", document),
+            Err(e) => eprintln!("Failed to serialize output: {}", e),
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! `--json` documents and exit codes of the cadi binary
+
+use assert_cmd::Command;
+use serde_json::Value;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// A config with its cache and trust store in a temporary directory
+struct TestEnv {
+    dir: TempDir,
+    config: PathBuf,
+}
+
+impl TestEnv {
+    fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("config.yaml");
+        std::fs::write(
+            &config,
+            format!(
+                "registry:\n  url: http://127.0.0.1:9\ncache:\n  dir: {}\nsecurity:\n  trust_policy: permissive\n  trust_dir: {}\n",
+                dir.path().join("cache").display(),
+                dir.path().join("trust").display()
+            ),
+        )
+        .unwrap();
+        Self { dir, config }
+    }
+
+    fn cache(&self) -> PathBuf {
+        self.dir.path().join("cache")
+    }
+
+    /// `cadi --json` with this config, kept away from the user's own
+    fn cadi(&self, args: &[&str]) -> Command {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("cadi");
+        cmd.env("HOME", self.dir.path())
+            .env("XDG_CONFIG_HOME", self.dir.path().join("xdg"))
+            .env("CADI_LOG", "off")
+            .current_dir(self.dir.path())
+            .arg("--config")
+            .arg(&self.config)
+            .arg("--json")
+            .args(args);
+        cmd
+    }
+}
+
+/// Run `cmd`, check its exit code, and parse stdout as one JSON document
+fn run(mut cmd: Command, code: i32) -> Value {
+    let output = cmd.output().unwrap();
+    assert_eq!(
+        output.status.code(),
+        Some(code),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    serde_json::from_slice(&output.stdout).expect("stdout is one JSON document")
+}
+
+fn assert_failure(document: &Value, code: &str) {
+    assert_eq!(document["ok"], false);
+    assert_eq!(document["data"], Value::Null);
+    assert_eq!(document["error"]["code"], code);
+    assert!(document["error"]["message"].as_str().is_some_and(|m| !m.is_empty()));
+}
+
+/// Serve every request with `status` and a JSON `body`
+fn mock_registry(status: &'static str, body: &'static str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { break };
+            let mut request = [0u8; 8192];
+            let _ = stream.read(&mut request);
+            let response = format!(
+                "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+    url
+}
+
+/// A registry URL nothing listens on
+fn closed_registry() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    format!("http://{}", listener.local_addr().unwrap())
+}
+
+fn cache_chunk(cache: &Path, chunk_id: &str, content: &[u8]) {
+    let chunks = cache.join("chunks");
+    std::fs::create_dir_all(&chunks).unwrap();
+    std::fs::write(chunks.join(format!("{}.bin", cadi_core::chunk_storage_key(chunk_id))), content).unwrap();
+}
+
+#[test]
+fn test_verify_reports_chunks_and_failures() {
+    let env = TestEnv::new();
+    let content = b"fn main() {}\n";
+    let chunk_id = format!("chunk:sha256:{}", cadi_core::sha256_bytes(content));
+    cache_chunk(&env.cache(), &chunk_id, content);
+
+    let document = run(env.cadi(&["verify", &chunk_id]), 0);
+    assert_eq!(document["ok"], true);
+    assert_eq!(document["error"], Value::Null);
+    assert_eq!(document["data"]["chunks"][0]["chunk_id"], chunk_id.as_str());
+    assert_eq!(document["data"]["chunks"][0]["failure"], Value::Null);
+
+    let tampered = format!("chunk:sha256:{}", cadi_core::sha256_bytes(b"other"));
+    cache_chunk(&env.cache(), &tampered, content);
+    assert_failure(&run(env.cadi(&["verify", &tampered]), 2), "validation");
+
+    let missing = format!("chunk:sha256:{}", "0".repeat(64));
+    assert_failure(&run(env.cadi(&["verify", &missing]), 3), "not_found");
+}
+
+#[test]
+fn test_fetch_distinguishes_missing_chunks_from_network_errors() {
+    let env = TestEnv::new();
+    let chunk_id = format!("chunk:sha256:{}", "a".repeat(64));
+
+    let registry = mock_registry("404 Not Found", "{}");
+    let document = run(env.cadi(&["fetch", &chunk_id, "--registry", &registry]), 3);
+    assert_failure(&document, "not_found");
+
+    let document = run(env.cadi(&["fetch", &chunk_id, "--registry", &closed_registry()]), 4);
+    assert_failure(&document, "network");
+
+    // A cached chunk needs no registry
+    let content = b"cached";
+    let cached = format!("chunk:sha256:{}", cadi_core::sha256_bytes(content));
+    cache_chunk(&env.cache(), &cached, content);
+    let document = run(env.cadi(&["fetch", &cached, "--registry", &closed_registry()]), 0);
+    assert_eq!(document["data"]["cached"][0], cached.as_str());
+    assert_eq!(document["data"]["fetched"], serde_json::json!([]));
+}
+
+#[test]
+fn test_query_output_and_unknown_alias() {
+    let env = TestEnv::new();
+    let registry = mock_registry(
+        "200 OK",
+        r#"{"chunks": [{"chunk_id": "chunk:sha256:abc", "name": "parse_config", "language": "rust", "size": 120}]}"#,
+    );
+
+    let document = run(env.cadi(&["query", "--name", "parse", "--registry", &registry]), 0);
+    assert_eq!(document["ok"], true);
+    assert_eq!(document["data"]["count"], 1);
+    assert_eq!(document["data"]["chunks"][0]["name"], "parse_config");
+
+    assert_failure(&run(env.cadi(&["query", "--alias", "utils/logger"]), 3), "not_found");
+
+    assert_failure(&run(env.cadi(&["query", "--name", "parse", "--registry", &closed_registry()]), 4), "network");
+}
+
+#[test]
+fn test_build_errors() {
+    let env = TestEnv::new();
+    assert_failure(&run(env.cadi(&["build", "missing.yaml"]), 3), "not_found");
+
+    std::fs::write(env.dir.path().join("broken.yaml"), "application: [").unwrap();
+    assert_failure(&run(env.cadi(&["build", "broken.yaml"]), 2), "validation");
+}
+
+#[test]
+fn test_publish_with_nothing_to_publish() {
+    let env = TestEnv::new();
+    let document = run(env.cadi(&["publish", "--registry", "http://127.0.0.1:9"]), 0);
+    assert_eq!(document["ok"], true);
+    assert_eq!(document["data"]["registry"], "http://127.0.0.1:9");
+    assert_eq!(document["data"]["published"], 0);
+
+    let missing = format!("chunk:sha256:{}", "b".repeat(64));
+    let document = run(env.cadi(&["publish", &missing, "--registry", "http://127.0.0.1:9"]), 3);
+    assert_failure(&document, "not_found");
+}
+
+#[test]
+fn test_graph_query() {
+    use cadi_core::graph::{EdgeType, GraphNode, GraphStore};
+
+    let env = TestEnv::new();
+    {
+        let store = GraphStore::open(env.cache().join("graph-db")).unwrap();
+        for (id, language) in [("main", "rust"), ("util", "rust"), ("dead", "rust"), ("legacy", "python")] {
+            let node = GraphNode::new(format!("chunk:{}", id), id)
+                .with_alias(format!("demo/{}", id))
+                .with_language(language)
+                .with_granularity("function");
+            store.insert_node(&node).unwrap();
+        }
+        store.add_dependency("chunk:main", "chunk:util", EdgeType::Imports).unwrap();
+        store.flush().unwrap();
+    }
+
+    let document = run(env.cadi(&["graph", "query", "lang=rust gran=function", "orphans"]), 0);
+    let mut orphans: Vec<&str> = document["data"]["nodes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|node| node["chunk_id"].as_str().unwrap())
+        .collect();
+    orphans.sort();
+    assert_eq!(orphans, ["chunk:dead", "chunk:main"]);
+
+    let document = run(env.cadi(&["graph", "query", "from=demo/util", "dir=in", "--format", "json"]), 0);
+    assert_eq!(document["data"]["nodes"][1]["chunk_id"], "chunk:main");
+    assert_eq!(document["data"]["nodes"][1]["reached_via"], "imports");
+
+    assert_failure(&run(env.cadi(&["graph", "query", "colour=red"]), 2), "validation");
+    assert_failure(&run(env.cadi(&["graph", "query", "from=demo/missing"]), 3), "not_found");
+}
+
+#[test]
+fn test_usage_errors_are_validation_errors() {
+    let env = TestEnv::new();
+    assert_failure(&run(env.cadi(&["verify"]), 2), "validation");
+}
@@ -2,6 +2,7 @@
 
 use async_trait::async_trait;
 use cadi_core::{AtomicChunk, atomizer::ResolvedImport};
+use cadi_core::license::{spdx_from_header, UNKNOWN_LICENSE};
 use cadi_extensions::{AtomizerExtension, Extension, ExtensionContext, ExtensionMetadata, ExtensionType, Result};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
@@ -80,6 +81,7 @@ impl AtomizerExtension for JavaAtomizer {
 
     async fn extract_atoms(&self, source: &str) -> Result<Vec<AtomicChunk>> {
         let mut atoms = Vec::new();
+        let license = spdx_from_header(source).unwrap_or_else(|| UNKNOWN_LICENSE.to_string());
 
         // Extract class-level atoms
         for capture in self.class_regex.captures_iter(source) {
@@ -95,6 +97,7 @@ impl AtomizerExtension for JavaAtomizer {
                 language: "java".into(),
                 granularity: cadi_core::atomic::ChunkGranularity::Type,
                 categories: vec![cadi_core::atomic::ChunkCategory::Logic],
+                category_confidence: None,
                 tags: vec!["class".into()],
                 concepts: vec![],
                 provides: vec![],
@@ -105,7 +108,7 @@ impl AtomizerExtension for JavaAtomizer {
                 sources: vec![],
                 content_hash: blake3::hash(class_body.as_bytes()).to_hex().to_string(),
                 size: class_body.len(),
-                license: "MIT".into(),
+                license: license.clone(),
                 created_at: Some(chrono::Utc::now().to_rfc3339()),
                 version: Some("1.0.0".into()),
             };
@@ -123,6 +126,7 @@ impl AtomizerExtension for JavaAtomizer {
                     language: "java".into(),
                     granularity: cadi_core::atomic::ChunkGranularity::Function,
                     categories: vec![cadi_core::atomic::ChunkCategory::Logic],
+                category_confidence: None,
                     tags: vec!["method".into()],
                     concepts: vec![],
                     provides: vec![],
@@ -143,7 +147,7 @@ impl AtomizerExtension for JavaAtomizer {
                     sources: vec![],
                     content_hash: blake3::hash(method_body.as_bytes()).to_hex().to_string(),
                     size: method_body.len(),
-                    license: "MIT".into(),
+                    license: license.clone(),
                     created_at: Some(chrono::Utc::now().to_rfc3339()),
                     version: Some("1.0.0".into()),
                 };
@@ -171,6 +175,7 @@ impl AtomizerExtension for JavaAtomizer {
                         symbol_type: Some("class".into()),
                     }],
                     line: 0, // We don't track line numbers in this simple example
+                    is_external: import_path.starts_with("java.") || import_path.starts_with("javax."),
                 };
 
                 imports.push(resolved);
@@ -30,6 +30,9 @@ enum Commands {
         /// Show only completed todos
         #[arg(short, long)]
         completed: bool,
+        /// With --pending, include todos due after today
+        #[arg(long)]
+        include_future: bool,
     },
     /// Mark a todo as complete
     Complete {
@@ -45,6 +48,9 @@ enum Commands {
     Delete {
         /// Todo ID
         id: String,
+        /// Delete every occurrence of a recurring todo
+        #[arg(long)]
+        series: bool,
     },
     /// Search todos
     Search {
@@ -67,9 +73,9 @@ fn main() {
             let todo = service.add(title, description);
             println!("✓ Added todo: {} ({})", todo.title, todo.id);
         }
-        Commands::List { pending, completed } => {
+        Commands::List { pending, completed, include_future } => {
             let todos = if pending {
-                service.list_pending()
+                service.list_pending(include_future)
             } else if completed {
                 service.list_completed()
             } else {
@@ -86,8 +92,11 @@ fn main() {
             }
         }
         Commands::Complete { id } => {
-            if service.complete(&id).is_some() {
+            if let Some(outcome) = service.complete(&id) {
                 println!("✓ Marked {} as complete", id);
+                if let Some(next) = outcome.next {
+                    println!("  Next occurrence: {} due {}", next.id, next.due_date.unwrap_or_default());
+                }
             } else {
                 println!("✗ Todo not found: {}", id);
             }
@@ -99,8 +108,13 @@ fn main() {
                 println!("✗ Todo not found: {}", id);
             }
         }
-        Commands::Delete { id } => {
-            if service.delete(&id) {
+        Commands::Delete { id, series } => {
+            if series {
+                match service.delete_series(&id) {
+                    0 => println!("✗ Todo not found: {}", id),
+                    n => println!("✓ Deleted {} occurrences of {}", n, id),
+                }
+            } else if service.delete(&id) {
                 println!("✓ Deleted {}", id);
             } else {
                 println!("✗ Todo not found: {}", id);
@@ -6,6 +6,10 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+pub mod recurrence;
+
+pub use recurrence::{Frequency, Recurrence};
+
 /// A todo item
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Todo {
@@ -25,6 +29,24 @@ pub struct Todo {
     pub created_at: String,
     /// Completion timestamp (RFC3339)
     pub completed_at: Option<String>,
+    /// Due date (`YYYY-MM-DD`)
+    #[serde(default)]
+    pub due_date: Option<String>,
+    /// How the todo repeats once completed
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
+    /// ID shared by every occurrence of a recurring todo
+    #[serde(default)]
+    pub series_id: Option<String>,
+}
+
+/// Result of completing a todo
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompleteOutcome {
+    /// The completed todo
+    pub completed: Todo,
+    /// The next occurrence, if the todo recurs and this completion created it
+    pub next: Option<Todo>,
 }
 
 /// Todo service for managing todos
@@ -56,6 +78,9 @@ impl TodoService {
             tags: Vec::new(),
             created_at: current_time(),
             completed_at: None,
+            due_date: None,
+            recurrence: None,
+            series_id: None,
         };
         
         self.todos.insert(id, todo.clone());
@@ -72,10 +97,13 @@ impl TodoService {
         self.todos.values().collect()
     }
 
-    /// List incomplete todos
-    pub fn list_pending(&self) -> Vec<&Todo> {
+    /// List incomplete todos. Todos due after today, such as upcoming
+    /// occurrences of recurring todos, are included only if `include_future`.
+    pub fn list_pending(&self, include_future: bool) -> Vec<&Todo> {
+        let today = recurrence::today();
         self.todos.values()
             .filter(|t| !t.completed)
+            .filter(|t| include_future || t.due_date.as_ref().is_none_or(|due| *due <= today))
             .collect()
     }
 
@@ -86,15 +114,53 @@ impl TodoService {
             .collect()
     }
 
-    /// Mark a todo as completed
-    pub fn complete(&mut self, id: &str) -> Option<&Todo> {
-        if let Some(todo) = self.todos.get_mut(id) {
-            todo.completed = true;
-            todo.completed_at = Some(current_time());
-            Some(todo)
-        } else {
-            None
+    /// Mark a todo as completed. Completing a recurring todo also creates
+    /// its next occurrence, unless that occurrence already exists (as when a
+    /// todo is completed again after being uncompleted).
+    pub fn complete(&mut self, id: &str) -> Option<CompleteOutcome> {
+        let todo = self.todos.get_mut(id)?;
+        if todo.completed {
+            return Some(CompleteOutcome {
+                completed: todo.clone(),
+                next: None,
+            });
         }
+
+        todo.completed = true;
+        todo.completed_at = Some(current_time());
+        let completed = todo.clone();
+        let next = self.add_next_occurrence(&completed);
+
+        Some(CompleteOutcome { completed, next })
+    }
+
+    fn add_next_occurrence(&mut self, todo: &Todo) -> Option<Todo> {
+        let recurrence = todo.recurrence?;
+        let due = todo.due_date.clone().unwrap_or_else(recurrence::today);
+        let next_due = recurrence.next_after(&due)?;
+        let series_id = todo.series_id.clone().unwrap_or_else(|| todo.id.clone());
+
+        let exists = self.todos.values().any(|t| {
+            t.series_id.as_deref() == Some(series_id.as_str()) && t.due_date.as_deref() == Some(next_due.as_str())
+        });
+        if exists {
+            return None;
+        }
+
+        let id = format!("todo-{}", self.next_id);
+        self.next_id += 1;
+
+        let next = Todo {
+            id: id.clone(),
+            completed: false,
+            created_at: current_time(),
+            completed_at: None,
+            due_date: Some(next_due),
+            series_id: Some(series_id),
+            ..todo.clone()
+        };
+        self.todos.insert(id, next.clone());
+        Some(next)
     }
 
     /// Mark a todo as incomplete
@@ -118,6 +184,40 @@ impl TodoService {
         }
     }
 
+    /// Update a todo's description
+    pub fn update_description(&mut self, id: &str, description: Option<String>) -> Option<&Todo> {
+        if let Some(todo) = self.todos.get_mut(id) {
+            todo.description = description;
+            Some(todo)
+        } else {
+            None
+        }
+    }
+
+    /// Set or clear a todo's due date (`YYYY-MM-DD`)
+    pub fn set_due_date(&mut self, id: &str, due_date: Option<String>) -> Option<&Todo> {
+        if let Some(todo) = self.todos.get_mut(id) {
+            todo.due_date = due_date;
+            Some(todo)
+        } else {
+            None
+        }
+    }
+
+    /// Make a todo recur, or stop it recurring. The todo starts a series
+    /// unless it already belongs to one.
+    pub fn set_recurrence(&mut self, id: &str, recurrence: Option<Recurrence>) -> Option<&Todo> {
+        if let Some(todo) = self.todos.get_mut(id) {
+            if recurrence.is_some() && todo.series_id.is_none() {
+                todo.series_id = Some(todo.id.clone());
+            }
+            todo.recurrence = recurrence;
+            Some(todo)
+        } else {
+            None
+        }
+    }
+
     /// Update a todo's priority
     pub fn set_priority(&mut self, id: &str, priority: u8) -> Option<&Todo> {
         if let Some(todo) = self.todos.get_mut(id) {
@@ -150,11 +250,26 @@ impl TodoService {
         }
     }
 
-    /// Delete a todo
+    /// Delete a todo. For a recurring todo only this occurrence is deleted.
     pub fn delete(&mut self, id: &str) -> bool {
         self.todos.remove(id).is_some()
     }
 
+    /// Delete a todo and, if it recurs, every other occurrence in its series.
+    /// Returns the number of todos deleted.
+    pub fn delete_series(&mut self, id: &str) -> usize {
+        let Some(series_id) = self.todos.get(id).map(|t| t.series_id.clone()) else {
+            return 0;
+        };
+        let Some(series_id) = series_id else {
+            return usize::from(self.delete(id));
+        };
+
+        let before = self.todos.len();
+        self.todos.retain(|_, t| t.series_id.as_deref() != Some(series_id.as_str()));
+        before - self.todos.len()
+    }
+
     /// Search todos by title or tag
     pub fn search(&self, query: &str) -> Vec<&Todo> {
         let query_lower = query.to_lowercase();
@@ -237,4 +352,62 @@ mod tests {
         assert!(service.delete(&id));
         assert!(service.get(&id).is_none());
     }
+
+    fn recurring(service: &mut TodoService, due: &str) -> String {
+        let id = service.add("Water plants".to_string(), None).id;
+        service.set_due_date(&id, Some(due.to_string()));
+        service.set_recurrence(&id, Some(Recurrence::new(Frequency::Weekly, 2)));
+        id
+    }
+
+    #[test]
+    fn test_completing_recurring_todo_creates_next_occurrence() {
+        let mut service = TodoService::new();
+        let id = recurring(&mut service, "2024-03-01");
+
+        let outcome = service.complete(&id).unwrap();
+        assert!(outcome.completed.completed);
+        let next = outcome.next.unwrap();
+        assert_eq!(next.due_date.as_deref(), Some("2024-03-15"));
+        assert_eq!(next.series_id.as_deref(), Some(id.as_str()));
+        assert!(!next.completed);
+
+        // Completing again, or again after uncompleting, spawns nothing new
+        assert!(service.complete(&id).unwrap().next.is_none());
+        service.uncomplete(&id);
+        assert!(service.complete(&id).unwrap().next.is_none());
+        assert_eq!(service.list().len(), 2);
+    }
+
+    #[test]
+    fn test_future_occurrences_are_hidden_from_pending() {
+        let mut service = TodoService::new();
+        let id = recurring(&mut service, "2099-01-01");
+        service.add("Today".to_string(), None);
+
+        assert_eq!(service.list_pending(false).len(), 1);
+        assert_eq!(service.list_pending(true).len(), 2);
+
+        service.complete(&id);
+        assert_eq!(service.list_pending(true).len(), 2);
+        assert_eq!(service.list_pending(false).len(), 1);
+    }
+
+    #[test]
+    fn test_delete_occurrence_or_series() {
+        let mut service = TodoService::new();
+        let id = recurring(&mut service, "2024-03-01");
+        let next = service.complete(&id).unwrap().next.unwrap();
+        let other = service.add("Unrelated".to_string(), None).id;
+
+        assert!(service.delete(&next.id));
+        assert!(service.get(&id).is_some());
+
+        // With that occurrence gone, completing again recreates it
+        service.uncomplete(&id);
+        let next = service.complete(&id).unwrap().next.unwrap();
+        assert_eq!(service.delete_series(&next.id), 2);
+        assert!(service.get(&id).is_none());
+        assert_eq!(service.delete_series(&other), 1);
+    }
 }
@@ -0,0 +1,136 @@
+//! Recurrence rules and the calendar arithmetic behind them
+//!
+//! Due dates are plain `YYYY-MM-DD` strings in UTC.
+
+use serde::{Deserialize, Serialize};
+
+/// How often a recurring todo comes back
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+/// A recurrence rule: every `interval` days, weeks or months
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    /// Number of periods between occurrences (at least 1)
+    #[serde(default = "default_interval")]
+    pub interval: u32,
+}
+
+fn default_interval() -> u32 {
+    1
+}
+
+impl Recurrence {
+    pub fn new(frequency: Frequency, interval: u32) -> Self {
+        Self {
+            frequency,
+            interval: interval.max(1),
+        }
+    }
+
+    /// Due date of the occurrence after one due on `date`. Monthly
+    /// recurrences keep the day of the month where it exists and otherwise
+    /// fall on the month's last day (Jan 31 -> Feb 28).
+    pub fn next_after(&self, date: &str) -> Option<String> {
+        let (year, month, day) = parse_date(date)?;
+        let interval = self.interval.max(1) as i64;
+
+        let (year, month, day) = match self.frequency {
+            Frequency::Daily => civil_from_days(days_from_civil(year, month, day) + interval),
+            Frequency::Weekly => civil_from_days(days_from_civil(year, month, day) + 7 * interval),
+            Frequency::Monthly => {
+                let months = year * 12 + (month as i64 - 1) + interval;
+                let (year, month) = (months.div_euclid(12), months.rem_euclid(12) as u32 + 1);
+                (year, month, day.min(days_in_month(year, month)))
+            }
+        };
+        Some(format_date(year, month, day))
+    }
+}
+
+/// Today's date in UTC
+pub fn today() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    format_date(year, month, day)
+}
+
+/// Parse a `YYYY-MM-DD` date
+pub fn parse_date(date: &str) -> Option<(i64, u32, u32)> {
+    let mut parts = date.splitn(3, '-');
+    let year = parts.next()?.parse().ok()?;
+    let month = parts.next()?.parse().ok()?;
+    let day = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+    Some((year, month, day))
+}
+
+fn format_date(year: i64, month: u32, day: u32) -> String {
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 (Howard Hinnant's `days_from_civil`)
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_occurrence_dates() {
+        let daily = Recurrence::new(Frequency::Daily, 3);
+        assert_eq!(daily.next_after("2024-02-27").as_deref(), Some("2024-03-01"));
+
+        let weekly = Recurrence::new(Frequency::Weekly, 1);
+        assert_eq!(weekly.next_after("2024-12-30").as_deref(), Some("2025-01-06"));
+
+        let monthly = Recurrence::new(Frequency::Monthly, 1);
+        assert_eq!(monthly.next_after("2024-01-31").as_deref(), Some("2024-02-29"));
+        assert_eq!(monthly.next_after("2023-01-31").as_deref(), Some("2023-02-28"));
+        let quarterly = Recurrence::new(Frequency::Monthly, 3);
+        assert_eq!(quarterly.next_after("2024-11-15").as_deref(), Some("2025-02-15"));
+
+        assert_eq!(daily.next_after("2024-02-30"), None);
+    }
+}
@@ -0,0 +1,477 @@
+//! Todo Server - HTTP API over todo-core
+//!
+//! Request bodies are typed and validated; failures are answered with a
+//! status code and a JSON body of the form `{"error": "...", "details": [...]}`.
+//!
+//! Every change to the todo list is also broadcast as a [`TodoEvent`] to the
+//! WebSocket clients of `GET /ws`.
+
+use axum::{
+    extract::{
+        rejection::{JsonRejection, QueryRejection},
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        FromRef, Path, Query, State,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, patch, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+use todo_core::{recurrence, CompleteOutcome, Recurrence, Todo, TodoService, TodoStats};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tower_http::cors::CorsLayer;
+
+pub type SharedState = Arc<RwLock<TodoService>>;
+
+/// Events a WebSocket client may fall behind by before it is dropped
+pub const EVENT_BUFFER: usize = 64;
+
+/// A change to the todo list, sent to WebSocket clients as JSON such as
+/// `{"type": "created", "todo": {...}}` or `{"type": "deleted", "id": "..."}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TodoEvent {
+    Created { todo: Todo },
+    Updated { todo: Todo },
+    Completed { todo: Todo },
+    Deleted { id: String },
+}
+
+/// The todo list and the channel its changes are broadcast on
+#[derive(Clone)]
+pub struct AppState {
+    pub todos: SharedState,
+    pub events: broadcast::Sender<TodoEvent>,
+}
+
+impl AppState {
+    pub fn new(todos: SharedState) -> Self {
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        Self { todos, events }
+    }
+
+    /// Broadcast events; call only once the write lock is released
+    fn publish(&self, events: impl IntoIterator<Item = TodoEvent>) {
+        for event in events {
+            // Without connected clients there is nobody to tell
+            let _ = self.events.send(event);
+        }
+    }
+}
+
+impl FromRef<AppState> for SharedState {
+    fn from_ref(state: &AppState) -> Self {
+        state.todos.clone()
+    }
+}
+
+/// Build the API router
+pub fn app(state: SharedState) -> Router {
+    Router::new()
+        .route("/todos", get(list_todos).post(add_todo))
+        .route("/todos/search", get(search_todos))
+        .route("/todos/:id", get(get_todo).put(update_todo).delete(delete_todo))
+        .route("/todos/:id/complete", post(complete_todo))
+        .route("/todos/:id/priority", patch(set_priority))
+        .route("/stats", get(stats))
+        .route("/ws", get(events))
+        .layer(CorsLayer::permissive())
+        .with_state(AppState::new(state))
+}
+
+/// Body of `POST /todos`
+#[derive(Debug, Deserialize)]
+pub struct CreateTodo {
+    pub title: String,
+    pub description: Option<String>,
+    pub priority: Option<u8>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Due date (`YYYY-MM-DD`)
+    pub due_date: Option<String>,
+    pub recurrence: Option<Recurrence>,
+}
+
+/// Body of `PUT /todos/:id`; fields left out are unchanged
+#[derive(Debug, Deserialize)]
+pub struct UpdateTodo {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub completed: Option<bool>,
+    pub priority: Option<u8>,
+    pub tags: Option<Vec<String>>,
+    pub due_date: Option<String>,
+    pub recurrence: Option<Recurrence>,
+}
+
+/// Body of `PATCH /todos/:id/priority`
+#[derive(Debug, Deserialize)]
+pub struct SetPriority {
+    pub priority: u8,
+}
+
+/// Query of `GET /todos`
+#[derive(Debug, Default, Deserialize)]
+pub struct ListParams {
+    /// Only incomplete todos
+    #[serde(default)]
+    pub pending: bool,
+    /// With `pending`, also todos due after today
+    #[serde(default)]
+    pub include_future: bool,
+}
+
+/// Query of `DELETE /todos/:id`
+#[derive(Debug, Default, Deserialize)]
+pub struct DeleteParams {
+    /// Delete every occurrence of a recurring todo
+    #[serde(default)]
+    pub series: bool,
+}
+
+/// Query of `GET /todos/search`
+#[derive(Debug, Deserialize)]
+pub struct SearchParams {
+    pub q: String,
+}
+
+/// JSON body of an error response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ErrorBody {
+    pub error: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<String>,
+}
+
+/// A failed request
+#[derive(Debug)]
+pub enum ApiError {
+    /// No todo with this ID
+    NotFound(String),
+    /// The request was well-formed but its values are invalid
+    Invalid(Vec<String>),
+    /// The body or query could not be parsed
+    BadRequest(String),
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, body) = match self {
+            ApiError::NotFound(id) => (
+                StatusCode::NOT_FOUND,
+                ErrorBody { error: format!("todo {} not found", id), details: vec![] },
+            ),
+            ApiError::Invalid(details) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorBody { error: "validation failed".to_string(), details },
+            ),
+            ApiError::BadRequest(message) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorBody { error: "invalid request".to_string(), details: vec![message] },
+            ),
+        };
+        (status, Json(body)).into_response()
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        ApiError::BadRequest(rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        ApiError::BadRequest(rejection.body_text())
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+fn validate_title(title: &str, errors: &mut Vec<String>) {
+    if title.trim().is_empty() {
+        errors.push("title must not be empty".to_string());
+    }
+}
+
+fn validate_priority(priority: u8, errors: &mut Vec<String>) {
+    if !(1..=5).contains(&priority) {
+        errors.push(format!("priority must be between 1 and 5, got {}", priority));
+    }
+}
+
+fn validate_due_date(due_date: &str, errors: &mut Vec<String>) {
+    if recurrence::parse_date(due_date).is_none() {
+        errors.push(format!("due_date must be a YYYY-MM-DD date, got {:?}", due_date));
+    }
+}
+
+fn validate_recurrence(recurrence: &Recurrence, errors: &mut Vec<String>) {
+    if recurrence.interval == 0 {
+        errors.push("recurrence interval must be at least 1".to_string());
+    }
+}
+
+fn check(errors: Vec<String>) -> Result<(), ApiError> {
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::Invalid(errors))
+    }
+}
+
+async fn list_todos(
+    State(state): State<SharedState>,
+    params: Result<Query<ListParams>, QueryRejection>,
+) -> ApiResult<Vec<Todo>> {
+    let Query(params) = params?;
+    let service = state.read().unwrap();
+    let todos = if params.pending {
+        service.list_pending(params.include_future)
+    } else {
+        service.list()
+    };
+    Ok(Json(todos.into_iter().cloned().collect()))
+}
+
+async fn get_todo(State(state): State<SharedState>, Path(id): Path<String>) -> ApiResult<Todo> {
+    let service = state.read().unwrap();
+    service.get(&id).cloned().map(Json).ok_or(ApiError::NotFound(id))
+}
+
+async fn add_todo(
+    State(state): State<AppState>,
+    payload: Result<Json<CreateTodo>, JsonRejection>,
+) -> Result<(StatusCode, Json<Todo>), ApiError> {
+    let Json(payload) = payload?;
+
+    let mut errors = Vec::new();
+    validate_title(&payload.title, &mut errors);
+    if let Some(priority) = payload.priority {
+        validate_priority(priority, &mut errors);
+    }
+    if let Some(ref due_date) = payload.due_date {
+        validate_due_date(due_date, &mut errors);
+    }
+    if let Some(ref recurrence) = payload.recurrence {
+        validate_recurrence(recurrence, &mut errors);
+    }
+    check(errors)?;
+
+    let todo = {
+        let mut service = state.todos.write().unwrap();
+        let id = service.add(payload.title.trim().to_string(), payload.description).id;
+        if let Some(priority) = payload.priority {
+            service.set_priority(&id, priority);
+        }
+        if payload.due_date.is_some() {
+            service.set_due_date(&id, payload.due_date);
+        }
+        if payload.recurrence.is_some() {
+            service.set_recurrence(&id, payload.recurrence);
+        }
+        for tag in payload.tags {
+            service.add_tag(&id, tag);
+        }
+        service.get(&id).cloned().ok_or(ApiError::NotFound(id))?
+    };
+
+    state.publish([TodoEvent::Created { todo: todo.clone() }]);
+    Ok((StatusCode::CREATED, Json(todo)))
+}
+
+async fn update_todo(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    payload: Result<Json<UpdateTodo>, JsonRejection>,
+) -> ApiResult<Todo> {
+    let Json(payload) = payload?;
+
+    let mut errors = Vec::new();
+    if let Some(ref title) = payload.title {
+        validate_title(title, &mut errors);
+    }
+    if let Some(priority) = payload.priority {
+        validate_priority(priority, &mut errors);
+    }
+    if let Some(ref due_date) = payload.due_date {
+        validate_due_date(due_date, &mut errors);
+    }
+    if let Some(ref recurrence) = payload.recurrence {
+        validate_recurrence(recurrence, &mut errors);
+    }
+
+    let (todo, completed, next) = {
+        let mut service = state.todos.write().unwrap();
+        let Some(current) = service.get(&id).cloned() else {
+            return Err(ApiError::NotFound(id));
+        };
+        check(errors)?;
+
+        if let Some(title) = payload.title {
+            service.update_title(&id, title.trim().to_string());
+        }
+        if payload.description.is_some() {
+            service.update_description(&id, payload.description);
+        }
+        let mut next = None;
+        match payload.completed {
+            Some(true) if !current.completed => {
+                next = service.complete(&id).and_then(|outcome| outcome.next);
+            }
+            Some(false) if current.completed => {
+                service.uncomplete(&id);
+            }
+            _ => {}
+        }
+        if let Some(priority) = payload.priority {
+            service.set_priority(&id, priority);
+        }
+        if payload.due_date.is_some() {
+            service.set_due_date(&id, payload.due_date);
+        }
+        if payload.recurrence.is_some() {
+            service.set_recurrence(&id, payload.recurrence);
+        }
+        if let Some(tags) = payload.tags {
+            for tag in &current.tags {
+                service.remove_tag(&id, tag);
+            }
+            for tag in tags {
+                service.add_tag(&id, tag);
+            }
+        }
+
+        let todo = service.get(&id).cloned().ok_or(ApiError::NotFound(id))?;
+        (todo, payload.completed == Some(true) && !current.completed, next)
+    };
+
+    let event = if completed {
+        TodoEvent::Completed { todo: todo.clone() }
+    } else {
+        TodoEvent::Updated { todo: todo.clone() }
+    };
+    state.publish(std::iter::once(event).chain(next.map(|todo| TodoEvent::Created { todo })));
+    Ok(Json(todo))
+}
+
+async fn delete_todo(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    params: Result<Query<DeleteParams>, QueryRejection>,
+) -> Result<StatusCode, ApiError> {
+    let Query(params) = params?;
+    let deleted: Vec<String> = {
+        let mut service = state.todos.write().unwrap();
+        if params.series {
+            let before: Vec<String> = service.list().into_iter().map(|t| t.id.clone()).collect();
+            service.delete_series(&id);
+            before.into_iter().filter(|id| service.get(id).is_none()).collect()
+        } else if service.delete(&id) {
+            vec![id.clone()]
+        } else {
+            Vec::new()
+        }
+    };
+
+    if deleted.is_empty() {
+        return Err(ApiError::NotFound(id));
+    }
+    state.publish(deleted.into_iter().map(|id| TodoEvent::Deleted { id }));
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn complete_todo(State(state): State<AppState>, Path(id): Path<String>) -> ApiResult<CompleteOutcome> {
+    let (outcome, already_completed) = {
+        let mut service = state.todos.write().unwrap();
+        let already_completed = service.get(&id).is_some_and(|t| t.completed);
+        (service.complete(&id).ok_or(ApiError::NotFound(id))?, already_completed)
+    };
+
+    if !already_completed {
+        state.publish(
+            std::iter::once(TodoEvent::Completed { todo: outcome.completed.clone() })
+                .chain(outcome.next.clone().map(|todo| TodoEvent::Created { todo })),
+        );
+    }
+    Ok(Json(outcome))
+}
+
+async fn set_priority(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    payload: Result<Json<SetPriority>, JsonRejection>,
+) -> ApiResult<Todo> {
+    let Json(payload) = payload?;
+
+    let todo = {
+        let mut service = state.todos.write().unwrap();
+        if service.get(&id).is_none() {
+            return Err(ApiError::NotFound(id));
+        }
+        let mut errors = Vec::new();
+        validate_priority(payload.priority, &mut errors);
+        check(errors)?;
+
+        service.set_priority(&id, payload.priority).cloned().ok_or(ApiError::NotFound(id))?
+    };
+
+    state.publish([TodoEvent::Updated { todo: todo.clone() }]);
+    Ok(Json(todo))
+}
+
+async fn search_todos(
+    State(state): State<SharedState>,
+    params: Result<Query<SearchParams>, QueryRejection>,
+) -> ApiResult<Vec<Todo>> {
+    let Query(params) = params?;
+    if params.q.trim().is_empty() {
+        return Err(ApiError::Invalid(vec!["q must not be empty".to_string()]));
+    }
+
+    let service = state.read().unwrap();
+    Ok(Json(service.search(params.q.trim()).into_iter().cloned().collect()))
+}
+
+async fn stats(State(state): State<SharedState>) -> Json<TodoStats> {
+    let service = state.read().unwrap();
+    Json(service.stats())
+}
+
+/// `GET /ws`: stream [`TodoEvent`]s to a WebSocket client
+async fn events(ws: WebSocketUpgrade, State(state): State<AppState>) -> Response {
+    // Subscribe before the upgrade so no change after the handshake is missed
+    let events = state.events.subscribe();
+    ws.on_upgrade(move |socket| forward_events(socket, events))
+}
+
+/// Send events to one client until it disconnects. A client that falls more
+/// than [`EVENT_BUFFER`] events behind is dropped rather than holding up the
+/// others.
+async fn forward_events(mut socket: WebSocket, mut events: broadcast::Receiver<TodoEvent>) {
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    let json = serde_json::to_string(&event).expect("events serialize to JSON");
+                    if socket.send(Message::Text(json)).await.is_err() {
+                        return;
+                    }
+                }
+                Err(RecvError::Lagged(_)) => {
+                    let close = CloseFrame { code: close_code::AGAIN, reason: "too slow".into() };
+                    let _ = socket.send(Message::Close(Some(close))).await;
+                    return;
+                }
+                Err(RecvError::Closed) => return,
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                // Clients have nothing to say; pings are answered by axum
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
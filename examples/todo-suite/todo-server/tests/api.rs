@@ -0,0 +1,136 @@
+use axum::body::Body;
+use axum::http::{header, Method, Request, StatusCode};
+use axum::Router;
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use std::sync::{Arc, RwLock};
+use todo_core::TodoService;
+use tower::ServiceExt;
+
+fn app() -> Router {
+    todo_server::app(Arc::new(RwLock::new(TodoService::new())))
+}
+
+async fn send(app: &Router, method: Method, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let request = Request::builder().method(method).uri(uri);
+    let request = match body {
+        Some(body) => request
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string())),
+        None => request.body(Body::empty()),
+    }
+    .unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let body = if bytes.is_empty() { Value::Null } else { serde_json::from_slice(&bytes).unwrap() };
+    (status, body)
+}
+
+#[tokio::test]
+async fn test_crud_round_trip() {
+    let app = app();
+
+    let (status, todo) = send(&app, Method::POST, "/todos", Some(json!({"title": "Write docs", "priority": 2, "tags": ["docs"]}))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(todo["priority"], 2);
+    let id = todo["id"].as_str().unwrap().to_string();
+
+    let (status, todo) = send(&app, Method::PUT, &format!("/todos/{}", id), Some(json!({"title": "Write more docs", "completed": true, "tags": ["writing"]}))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(todo["title"], "Write more docs");
+    assert_eq!(todo["completed"], true);
+    assert_eq!(todo["tags"], json!(["writing"]));
+
+    let (status, todo) = send(&app, Method::PATCH, &format!("/todos/{}/priority", id), Some(json!({"priority": 5}))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(todo["priority"], 5);
+
+    let (_, results) = send(&app, Method::GET, "/todos/search?q=writ", None).await;
+    assert_eq!(results.as_array().unwrap().len(), 1);
+
+    let (_, stats) = send(&app, Method::GET, "/stats", None).await;
+    assert_eq!(stats, json!({"total": 1, "completed": 1, "pending": 0}));
+
+    let (status, _) = send(&app, Method::DELETE, &format!("/todos/{}", id), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, Method::GET, &format!("/todos/{}", id), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_missing_todos_are_404() {
+    let app = app();
+
+    for (method, uri, body) in [
+        (Method::GET, "/todos/todo-9", None),
+        (Method::PUT, "/todos/todo-9", Some(json!({"title": "x"}))),
+        (Method::DELETE, "/todos/todo-9", None),
+        (Method::POST, "/todos/todo-9/complete", None),
+        (Method::PATCH, "/todos/todo-9/priority", Some(json!({"priority": 1}))),
+    ] {
+        let (status, body) = send(&app, method.clone(), uri, body).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{} {}", method, uri);
+        assert_eq!(body["error"], "todo todo-9 not found");
+    }
+}
+
+#[tokio::test]
+async fn test_invalid_requests_are_422() {
+    let app = app();
+
+    // Titles are required rather than defaulted
+    let (status, body) = send(&app, Method::POST, "/todos", Some(json!({"description": "no title"}))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["error"], "invalid request");
+
+    let (status, body) = send(&app, Method::POST, "/todos", Some(json!({"title": "  ", "priority": 9}))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["details"].as_array().unwrap().len(), 2);
+
+    let (_, todo) = send(&app, Method::POST, "/todos", Some(json!({"title": "Valid"}))).await;
+    let id = todo["id"].as_str().unwrap();
+
+    let (status, _) = send(&app, Method::PATCH, &format!("/todos/{}/priority", id), Some(json!({"priority": 0}))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = send(&app, Method::PUT, &format!("/todos/{}", id), Some(json!({"title": ""}))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = send(&app, Method::GET, "/todos/search", None).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = send(&app, Method::GET, "/todos/search?q=", None).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Rejected updates leave the todo untouched
+    let (_, todo) = send(&app, Method::GET, &format!("/todos/{}", id), None).await;
+    assert_eq!(todo["title"], "Valid");
+    assert_eq!(todo["priority"], 3);
+}
+
+#[tokio::test]
+async fn test_recurring_todos() {
+    let app = app();
+
+    let recurrence = json!({"frequency": "monthly", "interval": 0});
+    let (status, _) = send(&app, Method::POST, "/todos", Some(json!({"title": "Pay rent", "recurrence": recurrence}))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = send(&app, Method::POST, "/todos", Some(json!({"title": "Pay rent", "due_date": "2024-02-30"}))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let recurrence = json!({"frequency": "monthly", "interval": 1});
+    let (_, todo) = send(&app, Method::POST, "/todos", Some(json!({"title": "Pay rent", "due_date": "2024-01-31", "recurrence": recurrence}))).await;
+    let id = todo["id"].as_str().unwrap().to_string();
+
+    let (status, outcome) = send(&app, Method::POST, &format!("/todos/{}/complete", id), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(outcome["completed"]["completed"], true);
+    assert_eq!(outcome["next"]["due_date"], "2024-02-29");
+
+    let (_, outcome) = send(&app, Method::POST, &format!("/todos/{}/complete", id), None).await;
+    assert_eq!(outcome["next"], Value::Null);
+
+    let (status, _) = send(&app, Method::DELETE, &format!("/todos/{}?series=true", id), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, todos) = send(&app, Method::GET, "/todos", None).await;
+    assert_eq!(todos, json!([]));
+}
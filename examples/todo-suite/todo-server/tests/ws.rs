@@ -0,0 +1,59 @@
+use futures_util::StreamExt;
+use serde_json::{json, Value};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use todo_core::TodoService;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::Message;
+
+/// Serve a fresh todo list, returning its address
+async fn serve() -> String {
+    let app = todo_server::app(Arc::new(RwLock::new(TodoService::new())));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    addr
+}
+
+/// POST a JSON body over a plain HTTP/1.1 connection, returning the status line
+async fn post(addr: &str, path: &str, body: Value) -> String {
+    let body = body.to_string();
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        path,
+        addr,
+        body.len(),
+        body
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response.lines().next().unwrap_or_default().to_string()
+}
+
+#[tokio::test]
+async fn test_clients_receive_created_events() {
+    let addr = serve().await;
+    let url = format!("ws://{}/ws", addr);
+    let (mut first, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+    let (mut second, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+
+    let status = post(&addr, "/todos", json!({"title": "Ship it"})).await;
+    assert!(status.contains("201"), "{}", status);
+
+    for client in [&mut first, &mut second] {
+        let message = tokio::time::timeout(Duration::from_secs(5), client.next())
+            .await
+            .expect("event within 5s")
+            .unwrap()
+            .unwrap();
+        let Message::Text(text) = message else {
+            panic!("expected a text message, got {:?}", message);
+        };
+        let event: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(event["type"], "created");
+        assert_eq!(event["todo"]["title"], "Ship it");
+    }
+}
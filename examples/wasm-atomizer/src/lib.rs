@@ -0,0 +1,132 @@
+//! INI atomizer for CADI, built as a WASM extension
+//!
+//! Each `[section]` becomes an atom, with keys before the first section
+//! collected into a `global` atom. `path` keys under `[include]` (as in git
+//! config) are reported as imports.
+//!
+//! The exports follow the ABI described in `cadi_extensions::wasm`. The host
+//! runs every call in a fresh instance, so buffers are simply leaked.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+#[derive(Deserialize)]
+struct Input {
+    source: String,
+}
+
+#[no_mangle]
+pub extern "C" fn cadi_alloc(len: i32) -> i32 {
+    vec![0u8; len as usize].leak().as_mut_ptr() as i32
+}
+
+#[no_mangle]
+pub extern "C" fn cadi_language() -> i64 {
+    pack(b"ini")
+}
+
+#[no_mangle]
+pub extern "C" fn cadi_extract_atoms(ptr: i32, len: i32) -> i64 {
+    respond(read_input(ptr, len).map(|input| extract_atoms(&input.source)))
+}
+
+#[no_mangle]
+pub extern "C" fn cadi_resolve_imports(ptr: i32, len: i32) -> i64 {
+    respond(read_input(ptr, len).map(|input| resolve_imports(&input.source)))
+}
+
+struct Section<'a> {
+    name: &'a str,
+    start_line: usize,
+    lines: Vec<&'a str>,
+}
+
+fn sections(source: &str) -> Vec<Section<'_>> {
+    let mut sections = vec![Section {
+        name: "global",
+        start_line: 1,
+        lines: Vec::new(),
+    }];
+    for (i, line) in source.lines().enumerate() {
+        let trimmed = line.trim();
+        if let Some(name) = trimmed.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            sections.push(Section {
+                name: name.trim(),
+                start_line: i + 1,
+                lines: Vec::new(),
+            });
+        }
+        sections.last_mut().unwrap().lines.push(line);
+    }
+    sections
+}
+
+fn keys<'s, 'a>(section: &'s Section<'a>) -> impl Iterator<Item = (usize, &'a str, &'a str)> + 's {
+    section.lines.iter().enumerate().filter_map(|(i, line)| {
+        let line = line.trim();
+        if line.starts_with(['#', ';', '[']) {
+            return None;
+        }
+        let (key, value) = line.split_once('=')?;
+        Some((section.start_line + i, key.trim(), value.trim()))
+    })
+}
+
+fn extract_atoms(source: &str) -> Value {
+    let atoms: Vec<Value> = sections(source)
+        .iter()
+        .filter(|section| keys(section).next().is_some())
+        .map(|section| {
+            let content = section.lines.join("\n");
+            let hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+            let provides: Vec<String> = keys(section)
+                .map(|(_, key, _)| format!("{}.{}", section.name, key))
+                .collect();
+            json!({
+                "chunk_id": format!("chunk:sha256:{}", hash),
+                "name": section.name,
+                "language": "ini",
+                "granularity": "type",
+                "provides": provides,
+                "tags": ["ini", "config"],
+                "content_hash": hash,
+                "size": content.len(),
+            })
+        })
+        .collect();
+    json!(atoms)
+}
+
+fn resolve_imports(source: &str) -> Value {
+    let imports: Vec<Value> = sections(source)
+        .iter()
+        .filter(|section| section.name == "include")
+        .flat_map(|section| keys(section).collect::<Vec<_>>())
+        .filter(|(_, key, _)| *key == "path")
+        .map(|(line, _, path)| {
+            json!({
+                "source_path": path,
+                "symbols": [],
+                "line": line,
+                "is_external": true,
+            })
+        })
+        .collect();
+    json!(imports)
+}
+
+fn read_input(ptr: i32, len: i32) -> Result<Input, String> {
+    // SAFETY: the host wrote `len` bytes at the buffer `cadi_alloc` returned
+    let bytes = unsafe { std::slice::from_raw_parts(ptr as *const u8, len as usize) };
+    serde_json::from_slice(bytes).map_err(|e| format!("invalid input: {}", e))
+}
+
+fn respond(result: Result<Value, String>) -> i64 {
+    let value = result.unwrap_or_else(|error| json!({ "error": error }));
+    pack(value.to_string().into_bytes().leak())
+}
+
+fn pack(bytes: &[u8]) -> i64 {
+    ((bytes.as_ptr() as u32 as i64) << 32) | bytes.len() as i64
+}
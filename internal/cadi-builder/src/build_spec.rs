@@ -1,9 +1,8 @@
+use crate::dependency_resolver::{resolve_searches, ComponentSearch, ComponentSelection, SearchResolution, SkippedComponent};
 use anyhow::Result;
-use cadi_registry::search::SearchEngine;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
-use std::sync::Arc;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BuildSpec {
@@ -30,12 +29,62 @@ pub enum ComponentSpec {
     Search(SearchComponent),
 }
 
+impl ComponentSpec {
+    pub fn id(&self) -> &str {
+        match self {
+            ComponentSpec::Reuse(c) => &c.id,
+            ComponentSpec::Generate(c) => &c.id,
+            ComponentSpec::Search(c) => &c.id,
+        }
+    }
+
+    pub fn is_optional(&self) -> bool {
+        match self {
+            ComponentSpec::Reuse(c) => c.optional,
+            ComponentSpec::Generate(c) => c.optional,
+            ComponentSpec::Search(c) => c.optional,
+        }
+    }
+
+    pub fn platforms(&self) -> &[String] {
+        match self {
+            ComponentSpec::Reuse(c) => &c.platforms,
+            ComponentSpec::Generate(c) => &c.platforms,
+            ComponentSpec::Search(c) => &c.platforms,
+        }
+    }
+
+    /// Why the component cannot be resolved, for components missing the
+    /// chunk or query they are resolved from
+    pub fn unresolvable(&self) -> Option<&'static str> {
+        match self {
+            ComponentSpec::Reuse(c) if c.source.trim().is_empty() => Some("has no source chunk"),
+            ComponentSpec::Search(c) if c.query.trim().is_empty() => Some("has an empty search query"),
+            _ => None,
+        }
+    }
+
+    /// IDs of the components this one depends on
+    pub fn depends_on(&self) -> &[String] {
+        match self {
+            ComponentSpec::Generate(c) => c.depends_on.as_deref().unwrap_or_default(),
+            _ => &[],
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReuseComponent {
     pub id: String,
     pub source: String,
     pub alias: Option<String>,
     pub version: Option<String>,
+    /// Leave the component out, with a warning, when it cannot be resolved
+    #[serde(default)]
+    pub optional: bool,
+    /// Platforms the component is built for (all when empty)
+    #[serde(default)]
+    pub platforms: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,6 +95,12 @@ pub struct GenerateComponent {
     pub depends_on: Option<Vec<String>>,
     pub interface: Option<InterfaceSpec>,
     pub code_snippet: Option<String>,
+    /// Leave the component out, with a warning, when it cannot be resolved
+    #[serde(default)]
+    pub optional: bool,
+    /// Platforms the component is built for (all when empty)
+    #[serde(default)]
+    pub platforms: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,6 +110,19 @@ pub struct SearchComponent {
     pub language: Option<String>,
     pub concepts: Option<Vec<String>>,
     pub expected_interface: Option<InterfaceSpec>,
+    /// Relevance the best registry match must reach to be used (0 to 1)
+    #[serde(default)]
+    pub min_score: Option<f32>,
+    /// ID of a generate component to build instead when no registry match
+    /// reaches `min_score`. It is only built when the search falls back.
+    #[serde(default)]
+    pub fallback: Option<String>,
+    /// Leave the component out, with a warning, when it cannot be resolved
+    #[serde(default)]
+    pub optional: bool,
+    /// Platforms the component is built for (all when empty)
+    #[serde(default)]
+    pub platforms: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -89,13 +157,19 @@ impl BuildSpecValidator {
             .map_err(|e| anyhow::anyhow!("YAML parse error: {}", e))?;
 
         // Validate schema
-        Self::validate(&spec)?;
+        for warning in Self::validate(&spec)? {
+            tracing::warn!("{}", warning);
+        }
 
         Ok(spec)
     }
 
-    /// Validate CBS structure and references
-    pub fn validate(spec: &BuildSpec) -> Result<()> {
+    /// Validate CBS structure and references. Problems with required
+    /// components are errors; the same problems with optional components
+    /// are returned as warnings.
+    pub fn validate(spec: &BuildSpec) -> Result<Vec<String>> {
+        let mut warnings = Vec::new();
+
         // 1. Validate project info
         if spec.project.name.is_empty() {
             return Err(anyhow::anyhow!("Project name is required"));
@@ -104,16 +178,19 @@ impl BuildSpecValidator {
         // 2. Validate components exist and are properly defined
         let mut component_ids = std::collections::HashSet::new();
         for component in &spec.components {
-            let id = match component {
-                ComponentSpec::Reuse(c) => &c.id,
-                ComponentSpec::Generate(c) => &c.id,
-                ComponentSpec::Search(c) => &c.id,
-            };
-
+            let id = component.id();
             if component_ids.contains(id) {
                 return Err(anyhow::anyhow!("Duplicate component ID: {}", id));
             }
-            component_ids.insert(id.clone());
+            component_ids.insert(id.to_string());
+
+            if let Some(problem) = component.unresolvable() {
+                let message = format!("Component '{}' {}", id, problem);
+                if !component.is_optional() {
+                    return Err(anyhow::anyhow!(message));
+                }
+                warnings.push(format!("{} (optional, will be skipped)", message));
+            }
         }
 
         // 3. Validate targets reference existing components
@@ -131,22 +208,41 @@ impl BuildSpecValidator {
 
         // 4. Validate dependencies
         for component in &spec.components {
-            if let ComponentSpec::Generate(gen) = component {
-                if let Some(deps) = &gen.depends_on {
-                    for dep_id in deps {
-                        if !component_ids.contains(dep_id) {
-                            return Err(anyhow::anyhow!(
-                                "Component '{}' depends on undefined '{}'",
-                                gen.id,
-                                dep_id
-                            ));
-                        }
-                    }
+            for dep_id in component.depends_on() {
+                if component_ids.contains(dep_id) {
+                    continue;
+                }
+                let message = format!("Component '{}' depends on undefined '{}'", component.id(), dep_id);
+                if !component.is_optional() {
+                    return Err(anyhow::anyhow!(message));
+                }
+                warnings.push(format!("{} (optional, will be skipped)", message));
+            }
+        }
+
+        // 5. Validate search fallbacks
+        for component in &spec.components {
+            let ComponentSpec::Search(search) = component else {
+                continue;
+            };
+            if let Some(min_score) = search.min_score {
+                if !(0.0..=1.0).contains(&min_score) {
+                    return Err(anyhow::anyhow!("Component '{}' min_score must be 0-1", search.id));
                 }
             }
+            let Some(fallback) = &search.fallback else {
+                continue;
+            };
+            if !spec.components.iter().any(|c| c.id() == fallback && matches!(c, ComponentSpec::Generate(_))) {
+                return Err(anyhow::anyhow!(
+                    "Component '{}' falls back to '{}', which is not a generate component",
+                    search.id,
+                    fallback
+                ));
+            }
         }
 
-        // 5. Validate constraints
+        // 6. Validate constraints
         if let Some(constraints) = &spec.constraints {
             if let Some(min_reuse) = constraints.min_reuse_percentage {
                 if !(0.0..=100.0).contains(&min_reuse) {
@@ -155,56 +251,80 @@ impl BuildSpecValidator {
             }
         }
 
-        Ok(())
+        Ok(warnings)
     }
 
-    /// Convert CBS to internal build plan
+    /// Convert CBS to internal build plan for a target triple, leaving out
+    /// components for other platforms and optional components that cannot
+    /// be resolved. Search components are resolved against `registry` and
+    /// pinned to their best match, or replaced by their generate fallback.
     pub async fn to_build_plan(
         spec: BuildSpec,
-        search_db: Arc<SearchEngine>,
+        registry: &dyn ComponentSearch,
+        target_triple: &str,
     ) -> Result<BuildPlan> {
+        let mut selection = ComponentSelection::for_target(&spec, target_triple)?;
+        let mut searches = resolve_searches(&spec, &mut selection, registry).await?;
+        let mut fell_back: HashMap<String, SearchResolution> = searches
+            .fallbacks
+            .values()
+            .map(|(fallback, resolution)| (fallback.clone(), resolution.clone()))
+            .collect();
+
         let mut plan = BuildPlan {
             project: spec.project,
             reuse_components: Vec::new(),
             generate_components: Vec::new(),
             targets: spec.targets,
+            skipped: Vec::new(),
         };
+        for target in &mut plan.targets {
+            target.components.retain(|id| !selection.is_skipped(id));
+            for id in &mut target.components {
+                *id = searches.target_component(id).to_string();
+            }
+        }
 
-        for component in spec.components {
+        for component in selection.components {
             match component {
                 ComponentSpec::Reuse(reuse) => {
                     plan.reuse_components.push(ReusePlan {
                         id: reuse.id,
                         chunk_id: reuse.source,
+                        search: None,
                     });
                 }
 
                 ComponentSpec::Generate(gen) => {
+                    let depends_on = gen
+                        .depends_on
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|id| searches.target_component(id).to_string())
+                        .collect();
                     plan.generate_components.push(GeneratePlan {
+                        search: fell_back.remove(&gen.id),
                         id: gen.id,
                         description: gen.description,
-                        depends_on: gen.depends_on.unwrap_or_default(),
+                        depends_on,
                         interface: gen.interface,
                         code_snippet: gen.code_snippet,
                     });
                 }
 
                 ComponentSpec::Search(search) => {
-                    // Resolve search query to chunk ID
-                    let results = search_db.search_sync(&search.query, 5);
-
-                    if results.is_empty() {
-                        return Err(anyhow::anyhow!("No chunks found for query: {}", search.query));
-                    }
-
-                    // Use top result
+                    let Some(resolution) = searches.pinned.remove(&search.id) else {
+                        continue;
+                    };
                     plan.reuse_components.push(ReusePlan {
                         id: search.id,
-                        chunk_id: results[0].id.clone(),
+                        chunk_id: resolution.best().map(|c| c.chunk_id.clone()).unwrap_or_default(),
+                        search: Some(resolution),
                     });
                 }
             }
         }
+        plan.skipped = selection.skipped;
 
         Ok(plan)
     }
@@ -215,11 +335,15 @@ pub struct BuildPlan {
     pub reuse_components: Vec<ReusePlan>,
     pub generate_components: Vec<GeneratePlan>,
     pub targets: Vec<TargetSpec>,
+    /// Components left out of the plan, and why
+    pub skipped: Vec<SkippedComponent>,
 }
 
 pub struct ReusePlan {
     pub id: String,
     pub chunk_id: String,
+    /// For search components, the query and the matches it was chosen from
+    pub search: Option<SearchResolution>,
 }
 
 pub struct GeneratePlan {
@@ -228,4 +352,6 @@ pub struct GeneratePlan {
     pub depends_on: Vec<String>,
     pub interface: Option<InterfaceSpec>,
     pub code_snippet: Option<String>,
+    /// For search fallbacks, the search that found no good enough match
+    pub search: Option<SearchResolution>,
 }
\ No newline at end of file
@@ -145,11 +145,14 @@ pub struct ImportStatement {
     pub language: String,
 }
 
-fn language_to_extension(language: &str) -> &str {
+pub(crate) fn language_to_extension(language: &str) -> &str {
     match language {
         "typescript" => "ts",
+        "javascript" => "js",
         "python" => "py",
         "rust" => "rs",
+        "go" => "go",
+        "c" => "c",
         _ => "txt",
     }
 }
\ No newline at end of file
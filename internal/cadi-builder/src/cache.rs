@@ -1,10 +1,21 @@
 //! Build cache for CADI
+//!
+//! [`BuildCache`] stores artifacts on local disk. A [`RemoteCache`] shares
+//! artifacts between machines, keyed by the input hash of the build step
+//! that produced them, and [`LayeredCache`] combines the two.
 
-use cadi_core::{CadiError, CadiResult, sha256_bytes};
+use async_trait::async_trait;
+use cadi_core::{chunk_storage_key, parse_chunk_id, sha256_bytes, CadiResult, ChunkId};
+use cadi_core::local_cache::AccessTimes;
+use cadi_core::ChunkLineage;
+use cadi_registry::{RegistryClient, RegistryConfig};
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::{Read, Write};
 use std::path::PathBuf;
 
+use crate::{BuildReceipt, BuildStep};
+
 /// Build cache for storing and retrieving built artifacts
 pub struct BuildCache {
     cache_dir: PathBuf,
@@ -34,16 +45,15 @@ impl BuildCache {
         file.read_to_end(&mut data)?;
         
         // Verify hash matches
-        let expected_hash = chunk_id.strip_prefix("chunk:sha256:")
-            .ok_or_else(|| CadiError::InvalidChunkId(chunk_id.to_string()))?;
-        let actual_hash = sha256_bytes(&data);
-        
-        if expected_hash != actual_hash {
+        let expected = ChunkId::parse(chunk_id)?;
+
+        if !expected.matches(&data) {
             tracing::warn!("Cache corruption detected for {}", chunk_id);
             fs::remove_file(&path)?;
             return Ok(None);
         }
-        
+
+        AccessTimes::record(&self.cache_dir, chunk_id);
         Ok(Some(data))
     }
 
@@ -73,6 +83,106 @@ impl BuildCache {
         }
     }
 
+    /// Record the inputs a build step was last built from
+    pub fn record_step(&self, step: &str, inputs: &[String]) -> CadiResult<()> {
+        let path = self.step_path(step);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_vec(inputs)?)?;
+        Ok(())
+    }
+
+    /// Inputs a build step was last built from, if it has been built
+    pub fn step_inputs(&self, step: &str) -> CadiResult<Option<Vec<String>>> {
+        let path = self.step_path(step);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_slice(&fs::read(&path)?)?))
+    }
+
+    /// Store a build receipt, returning its ID
+    pub fn store_receipt(&self, receipt: &BuildReceipt) -> CadiResult<String> {
+        let receipt_id = receipt.receipt_id();
+        let path = self.keyed_path("receipts", &receipt_id);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_vec_pretty(receipt)?)?;
+        Ok(receipt_id)
+    }
+
+    /// A stored build receipt
+    pub fn receipt(&self, receipt_id: &str) -> CadiResult<Option<BuildReceipt>> {
+        let path = self.keyed_path("receipts", receipt_id);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_slice(&fs::read(&path)?)?))
+    }
+
+    /// Record what a built chunk was produced from
+    pub fn record_lineage(&self, chunk_id: &str, lineage: &ChunkLineage) -> CadiResult<()> {
+        let path = self.keyed_path("lineage", chunk_id);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_vec(lineage)?)?;
+        Ok(())
+    }
+
+    /// Lineage of a chunk, if it was built here
+    pub fn lineage(&self, chunk_id: &str) -> CadiResult<Option<ChunkLineage>> {
+        let path = self.keyed_path("lineage", chunk_id);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_slice(&fs::read(&path)?)?))
+    }
+
+    /// Append the outcome of a build to the build history
+    pub fn record_build(&self, record: &BuildRecord) -> CadiResult<()> {
+        fs::create_dir_all(&self.cache_dir)?;
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.history_path())?;
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+        Ok(())
+    }
+
+    /// The last `limit` recorded builds, oldest first
+    pub fn build_history(&self, limit: usize) -> CadiResult<Vec<BuildRecord>> {
+        let path = self.history_path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&path)?;
+        let records: Vec<BuildRecord> = content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        let skip = records.len().saturating_sub(limit);
+        Ok(records.into_iter().skip(skip).collect())
+    }
+
+    /// Cache hits over the last `limit` builds
+    pub fn history_stats(&self, limit: usize) -> CadiResult<BuildHistoryStats> {
+        let mut stats = BuildHistoryStats::default();
+        for record in self.build_history(limit)? {
+            stats.builds += 1;
+            stats.cached += record.cached;
+            stats.built += record.built;
+        }
+        let steps = stats.cached + stats.built;
+        if steps > 0 {
+            stats.hit_rate = stats.cached as f64 / steps as f64;
+        }
+        Ok(stats)
+    }
+
     /// Get cache statistics
     pub fn stats(&self) -> CadiResult<super::CacheStats> {
         let mut total_entries = 0;
@@ -98,7 +208,7 @@ impl BuildCache {
         Ok(super::CacheStats {
             total_entries,
             total_size_bytes,
-            hit_rate: 0.0, // Would need tracking to compute
+            hit_rate: self.history_stats(usize::MAX)?.hit_rate,
         })
     }
 
@@ -173,23 +283,197 @@ impl BuildCache {
 
     /// Internal helper to get the path for a chunk
     fn chunk_path(&self, chunk_id: &str) -> PathBuf {
-        // Use first 2 chars of hash as subdirectory for better filesystem performance
-        let hash = chunk_id.strip_prefix("chunk:sha256:")
-            .unwrap_or(chunk_id);
-        
-        let prefix = if hash.len() >= 2 {
-            &hash[..2]
-        } else {
-            "00"
-        };
-        
-        self.cache_dir.join("chunks").join(prefix).join(hash)
+        self.cache_dir.join("chunks").join(shard(chunk_id)).join(chunk_storage_key(chunk_id))
+    }
+
+    /// Path of a JSON record about a chunk, sharded like chunks
+    fn keyed_path(&self, kind: &str, chunk_id: &str) -> PathBuf {
+        self.cache_dir
+            .join(kind)
+            .join(shard(chunk_id))
+            .join(format!("{}.json", chunk_storage_key(chunk_id)))
+    }
+
+    /// Path of the input record for a build step, keyed by a hash of its name
+    fn step_path(&self, step: &str) -> PathBuf {
+        let hash = sha256_bytes(step.as_bytes());
+        self.cache_dir.join("steps").join(&hash[..2]).join(format!("{}.json", hash))
+    }
+
+    /// Path of the build history, one JSON record per line
+    fn history_path(&self) -> PathBuf {
+        self.cache_dir.join("builds.jsonl")
     }
 }
 
+/// Cache subdirectory of a chunk: the first two characters of its digest
+fn shard(chunk_id: &str) -> String {
+    let digest = parse_chunk_id(chunk_id).unwrap_or_else(|| chunk_id.to_string());
+    digest.get(..2).unwrap_or("00").to_string()
+}
+
+/// Outcome of a single build
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildRecord {
+    /// RFC 3339 time the build finished
+    pub finished_at: String,
+    /// Steps served from a cache
+    pub cached: usize,
+    /// Steps that had to be built
+    pub built: usize,
+    /// Steps that failed
+    pub failed: usize,
+}
+
+/// Cache hits aggregated over recent builds
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BuildHistoryStats {
+    pub builds: usize,
+    pub cached: usize,
+    pub built: usize,
+    /// Fraction of steps served from a cache
+    pub hit_rate: f64,
+}
+
 /// Result of garbage collection
 #[derive(Debug)]
 pub struct GcResult {
     pub removed: usize,
     pub freed_bytes: u64,
 }
+
+/// Remote store for build artifacts, keyed by a build step's input hash
+#[async_trait]
+pub trait RemoteCache: Send + Sync {
+    /// Fetch an artifact, or `None` on a miss
+    async fn get(&self, key: &str) -> CadiResult<Option<Vec<u8>>>;
+
+    /// Upload an artifact
+    async fn put(&self, key: &str, data: &[u8]) -> CadiResult<()>;
+
+    /// Check if an artifact exists
+    async fn contains(&self, key: &str) -> CadiResult<bool>;
+
+    /// Report the chunks of an executed build plan as used
+    async fn report_usage(&self, _chunk_ids: &[String]) -> CadiResult<()> {
+        Ok(())
+    }
+}
+
+/// Remote cache served by a registry server under `/cache/:key`
+pub struct HttpRemoteCache {
+    client: RegistryClient,
+}
+
+impl HttpRemoteCache {
+    /// Create a remote cache for the registry at `url`
+    pub fn new(url: &str, token: Option<String>) -> CadiResult<Self> {
+        let client = RegistryClient::new(RegistryConfig {
+            url: url.trim_end_matches('/').to_string(),
+            token,
+            ..Default::default()
+        })?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl RemoteCache for HttpRemoteCache {
+    async fn get(&self, key: &str) -> CadiResult<Option<Vec<u8>>> {
+        Ok(self.client.fetch_cache_entry(key).await?)
+    }
+
+    async fn put(&self, key: &str, data: &[u8]) -> CadiResult<()> {
+        Ok(self.client.publish_cache_entry(key, data).await?)
+    }
+
+    async fn contains(&self, key: &str) -> CadiResult<bool> {
+        Ok(self.client.cache_entry_exists(key).await?)
+    }
+
+    async fn report_usage(&self, chunk_ids: &[String]) -> CadiResult<()> {
+        Ok(self.client.report_usage(chunk_ids).await?)
+    }
+}
+
+/// Where a cached artifact was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheSource {
+    Local,
+    Remote,
+}
+
+/// Local cache backed by an optional remote cache. Lookups check the local
+/// cache first and populate it on a remote hit. Remote failures are logged
+/// and treated as misses so an unreachable cache never fails a build.
+pub struct LayeredCache {
+    local: BuildCache,
+    remote: Option<Box<dyn RemoteCache>>,
+    read_only: bool,
+}
+
+impl LayeredCache {
+    /// Create a layered cache. With `read_only`, artifacts are never uploaded.
+    pub fn new(local: BuildCache, remote: Option<Box<dyn RemoteCache>>, read_only: bool) -> Self {
+        Self { local, remote, read_only }
+    }
+
+    /// The local cache
+    pub fn local(&self) -> &BuildCache {
+        &self.local
+    }
+
+    /// Look up the artifact of a build step
+    pub async fn lookup(&self, step: &BuildStep) -> CadiResult<Option<CacheSource>> {
+        if let Some(key) = step.cache_key() {
+            if self.local.has(key)? {
+                return Ok(Some(CacheSource::Local));
+            }
+        }
+
+        let Some(remote) = &self.remote else {
+            return Ok(None);
+        };
+        match remote.get(&step.input_hash).await {
+            Ok(Some(data)) => {
+                if let Some(key) = step.cache_key() {
+                    self.local.store(key, &data)?;
+                }
+                Ok(Some(CacheSource::Remote))
+            }
+            Ok(None) => Ok(None),
+            Err(e) => {
+                tracing::warn!("Remote cache lookup for {} failed: {}", step.name, e);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Report the chunks of an executed build plan to the remote cache's
+    /// registry, if there is one. Failures are logged, never returned.
+    pub async fn report_usage(&self, chunk_ids: &[String]) {
+        let Some(remote) = &self.remote else {
+            return;
+        };
+        if chunk_ids.is_empty() {
+            return;
+        }
+        if let Err(e) = remote.report_usage(chunk_ids).await {
+            tracing::warn!("Reporting chunk usage to the remote cache failed: {}", e);
+        }
+    }
+
+    /// Store the artifact of a build step locally and, unless read-only, remotely
+    pub async fn store(&self, step: &BuildStep, data: &[u8]) -> CadiResult<()> {
+        if let Some(key) = step.cache_key() {
+            self.local.store(key, data)?;
+        }
+
+        if let (Some(remote), false) = (&self.remote, self.read_only) {
+            if let Err(e) = remote.put(&step.input_hash, data).await {
+                tracing::warn!("Remote cache upload for {} failed: {}", step.name, e);
+            }
+        }
+        Ok(())
+    }
+}
@@ -5,70 +5,107 @@
 //! component reuse, and sets up generation tasks.
 
 use crate::build_spec::{BuildSpec, ComponentSpec, GenerateComponent, ReuseComponent, SearchComponent};
+use crate::dependency_resolver::{
+    host_target_triple, resolve_searches, ComponentSearch, ComponentSelection, ResolvedSearches, SkippedComponent,
+};
 use cadi_core::{Manifest, GraphNode, Representation, BuildTarget, TargetNode, GraphEdge};
-use cadi_core::{CadiResult, CadiError};
-use cadi_registry::search::SearchEngine;
+use cadi_core::CadiResult;
 use std::sync::Arc;
 
+/// A build spec compiled for one target triple
+pub struct CompiledSpec {
+    pub manifest: Manifest,
+    pub target_triple: String,
+    /// Components left out of the manifest, and why
+    pub skipped: Vec<SkippedComponent>,
+}
+
 /// Compiler for CADI Build Specifications (CBS)
 pub struct CbsCompiler {
-    search_engine: Arc<SearchEngine>,
+    registry: Arc<dyn ComponentSearch>,
 }
 
 impl CbsCompiler {
-    /// Create a new CBS compiler with a search engine for resolving queries
-    pub fn new(search_engine: Arc<SearchEngine>) -> Self {
-        Self { search_engine }
+    /// Create a new CBS compiler that resolves search components against
+    /// a registry (or a local search engine)
+    pub fn new(registry: Arc<dyn ComponentSearch>) -> Self {
+        Self { registry }
     }
 
-    /// Compile a CBS into a CADI Manifest
+    /// Compile a CBS into a CADI Manifest for the host platform
     pub async fn compile(&self, spec: BuildSpec) -> CadiResult<Manifest> {
+        Ok(self.compile_for_target(spec, &host_target_triple()).await?.manifest)
+    }
+
+    /// Compile a CBS into a CADI Manifest for a target triple, leaving out
+    /// components for other platforms and optional components that cannot
+    /// be resolved
+    pub async fn compile_for_target(&self, spec: BuildSpec, target_triple: &str) -> CadiResult<CompiledSpec> {
         let mut manifest = Manifest::new(
             format!("{}-manifest", spec.project.name),
             spec.project.name.clone(),
         );
 
         manifest.application.description = spec.project.description.clone();
-        manifest.application.version = spec.project.version.clone();
+        if let Some(version) = &spec.project.version {
+            manifest.application.version = Some(version.clone());
+        }
+
+        let mut selection = ComponentSelection::for_target(&spec, target_triple)?;
+        let searches = resolve_searches(&spec, &mut selection, self.registry.as_ref()).await?;
 
         // Process components
-        for component in &spec.components {
-            match component {
+        for component in selection.components.clone() {
+            match &component {
                 ComponentSpec::Reuse(reuse) => {
                     self.add_reuse_node(&mut manifest, reuse)?;
                 }
                 ComponentSpec::Generate(gen) => {
-                    self.add_generate_node(&mut manifest, gen)?;
+                    self.add_generate_node(&mut manifest, gen, &searches)?;
                 }
                 ComponentSpec::Search(search) => {
-                    self.resolve_and_add_search_node(&mut manifest, search).await?;
+                    self.add_search_node(&mut manifest, search, &searches);
                 }
             }
         }
 
+        // Nodes and edges of components skipped after they were added
+        manifest.build_graph.nodes.retain(|node| !selection.is_skipped(&node.id));
+        manifest
+            .build_graph
+            .edges
+            .retain(|edge| !selection.is_skipped(&edge.from) && !selection.is_skipped(&edge.to));
+
         // Process targets
         for target in &spec.targets {
             let build_target = BuildTarget {
                 name: target.name.clone(),
                 platform: target.platform.clone().unwrap_or_else(|| "any".to_string()),
-                nodes: target.components.iter().map(|id| TargetNode {
-                    id: id.clone(),
+                nodes: target.components.iter().filter(|id| !selection.is_skipped(id)).map(|id| TargetNode {
+                    id: searches.target_component(id).to_string(),
                     require: None,
                     prefer: None,
                 }).collect(),
                 bundle: None,
                 deploy: None,
                 trust_requirements: None,
+                transforms: Vec::new(),
+                toolchain: Vec::new(),
             };
             manifest.add_target(build_target);
         }
 
-        Ok(manifest)
+        Ok(CompiledSpec {
+            manifest,
+            target_triple: target_triple.to_string(),
+            skipped: selection.skipped,
+        })
     }
 
     fn add_reuse_node(&self, manifest: &mut Manifest, reuse: &ReuseComponent) -> CadiResult<()> {
         let node = GraphNode {
             id: reuse.id.clone(),
+            chunk_ref: None,
             source_cadi: Some(reuse.source.clone()),
             ir_cadi: None,
             blob_cadi: None,
@@ -89,11 +126,12 @@ impl CbsCompiler {
         Ok(())
     }
 
-    fn add_generate_node(&self, manifest: &mut Manifest, gen: &GenerateComponent) -> CadiResult<()> {
+    fn add_generate_node(&self, manifest: &mut Manifest, gen: &GenerateComponent, searches: &ResolvedSearches) -> CadiResult<()> {
         // For generation, we create a placeholder node that the build engine will need to fill
         // The build engine will see source_cadi is None and trigger generation logic
         let node = GraphNode {
             id: gen.id.clone(),
+            chunk_ref: None,
             source_cadi: None, // Indicates generation needed
             ir_cadi: None,
             blob_cadi: None,
@@ -109,7 +147,7 @@ impl CbsCompiler {
             for dep in deps {
                 let edge = GraphEdge {
                     from: gen.id.clone(),
-                    to: dep.clone(),
+                    to: searches.target_component(dep).to_string(),
                     interface: None,
                     relation: "depends_on".to_string(),
                 };
@@ -120,35 +158,30 @@ impl CbsCompiler {
         Ok(())
     }
 
-    async fn resolve_and_add_search_node(&self, manifest: &mut Manifest, search: &SearchComponent) -> CadiResult<()> {
-        // Perform search using search_sync since we have an Arc<SearchEngine>
-        // We use a limit of 1 to get the best match
-        let results = self.search_engine.search_sync(&search.query, 1);
-        
-        if let Some(best_match) = results.first() {
-             let node = GraphNode {
-                id: search.id.clone(),
-                source_cadi: Some(best_match.id.clone()),
-                ir_cadi: None,
-                blob_cadi: None,
-                container_cadi: None,
-                representations: vec![
-                    Representation {
-                        form: "source".to_string(),
-                        language: search.language.clone(),
-                        format: None,
-                        architecture: None,
-                        chunk: best_match.id.clone(),
-                    }
-                ],
-                selection_strategy: Some("best_match".to_string()),
-                materialization: None,
-            };
-            manifest.add_node(node);
-        } else {
-            return Err(CadiError::DependencyResolution(format!("No results found for query: {}", search.query)));
-        }
-
-        Ok(())
+    fn add_search_node(&self, manifest: &mut Manifest, search: &SearchComponent, searches: &ResolvedSearches) {
+        // Searches that fell back are built by their generate component
+        let Some(best_match) = searches.pinned.get(&search.id).and_then(|r| r.best()) else {
+            return;
+        };
+        let node = GraphNode {
+            id: search.id.clone(),
+            chunk_ref: None,
+            source_cadi: Some(best_match.chunk_id.clone()),
+            ir_cadi: None,
+            blob_cadi: None,
+            container_cadi: None,
+            representations: vec![
+                Representation {
+                    form: "source".to_string(),
+                    language: search.language.clone(),
+                    format: None,
+                    architecture: None,
+                    chunk: best_match.chunk_id.clone(),
+                }
+            ],
+            selection_strategy: Some("best_match".to_string()),
+            materialization: None,
+        };
+        manifest.add_node(node);
     }
-}
\ No newline at end of file
+}
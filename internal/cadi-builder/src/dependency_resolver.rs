@@ -1,5 +1,10 @@
-use cadi_core::error::CadiError;
+use crate::build_spec::{BuildSpec, ComponentSpec, SearchComponent};
+use async_trait::async_trait;
+use cadi_core::error::{CadiError, CadiResult};
 use cadi_registry::graph::GraphDB;
+use cadi_registry::search::SearchEngine;
+use cadi_registry::{ChunkSummary, FederationManager, RegistryClient, SearchQuery};
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 
@@ -132,4 +137,439 @@ pub struct CompositionIssue {
 pub struct InterfaceCompatibility {
     pub is_compatible: bool,
     pub incompatibilities: Vec<String>,
-}
\ No newline at end of file
+}
+
+/// A build spec component left out of a build
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedComponent {
+    pub id: String,
+    pub reason: String,
+}
+
+/// The components of a build spec that apply to one target triple
+#[derive(Debug, Clone)]
+pub struct ComponentSelection {
+    pub target_triple: String,
+    /// Components to build, in spec order
+    pub components: Vec<ComponentSpec>,
+    pub skipped: Vec<SkippedComponent>,
+}
+
+impl ComponentSelection {
+    /// Select the components of `spec` whose platforms include
+    /// `target_triple`, along with everything that depends on them
+    pub fn for_target(spec: &BuildSpec, target_triple: &str) -> Result<Self, CadiError> {
+        let mut selection = Self {
+            target_triple: target_triple.to_string(),
+            components: spec.components.clone(),
+            skipped: Vec::new(),
+        };
+
+        for component in &spec.components {
+            let platforms = component.platforms();
+            if !platforms.is_empty() && !platforms.iter().any(|p| platform_matches(p, target_triple)) {
+                let reason = format!("not built for {} (platforms: {})", target_triple, platforms.join(", "));
+                selection.skip(component.id(), reason)?;
+            }
+        }
+
+        // Optional components that cannot be resolved are left out; the
+        // validator rejects required ones
+        let defined: HashSet<&str> = spec.components.iter().map(|c| c.id()).collect();
+        for component in spec.components.iter().filter(|c| c.is_optional()) {
+            let undefined = component.depends_on().iter().find(|d| !defined.contains(d.as_str()));
+            let reason = match (component.unresolvable(), undefined) {
+                (Some(problem), _) => problem.to_string(),
+                (None, Some(dep)) => format!("depends on undefined component '{}'", dep),
+                (None, None) => continue,
+            };
+            selection.skip(component.id(), reason)?;
+        }
+
+        Ok(selection)
+    }
+
+    /// Leave a component out, then every optional component depending on
+    /// it. Fails if a required component depends on it.
+    pub fn skip(&mut self, id: &str, reason: String) -> Result<(), CadiError> {
+        let mut pending = vec![(id.to_string(), reason)];
+
+        while let Some((id, reason)) = pending.pop() {
+            let Some(pos) = self.components.iter().position(|c| c.id() == id) else {
+                continue;
+            };
+            self.components.remove(pos);
+
+            for dependent in self.components.iter().filter(|c| c.depends_on().contains(&id)) {
+                if !dependent.is_optional() {
+                    return Err(CadiError::DependencyResolution(format!(
+                        "Component '{}' depends on '{}', which is skipped: {}",
+                        dependent.id(),
+                        id,
+                        reason
+                    )));
+                }
+                pending.push((dependent.id().to_string(), format!("depends on skipped component '{}'", id)));
+            }
+            self.skipped.push(SkippedComponent { id, reason });
+        }
+
+        Ok(())
+    }
+
+    /// Whether a component was left out
+    pub fn is_skipped(&self, id: &str) -> bool {
+        self.skipped.iter().any(|s| s.id == id)
+    }
+}
+
+/// Score a search component's best match must reach unless the component
+/// sets `min_score`
+pub const DEFAULT_MIN_SEARCH_SCORE: f32 = 0.5;
+
+/// How many of a search's matches are kept in the plan
+const MAX_SEARCH_CANDIDATES: usize = 5;
+
+/// Searches registries for the chunks a search component asks for
+#[async_trait]
+pub trait ComponentSearch: Send + Sync {
+    /// Chunks matching the query, each with the ID of the registry it came from
+    async fn search(&self, query: &SearchQuery) -> CadiResult<Vec<(ChunkSummary, String)>>;
+}
+
+#[async_trait]
+impl ComponentSearch for FederationManager {
+    async fn search(&self, query: &SearchQuery) -> CadiResult<Vec<(ChunkSummary, String)>> {
+        FederationManager::search(self, query).await
+    }
+}
+
+#[async_trait]
+impl ComponentSearch for RegistryClient {
+    async fn search(&self, query: &SearchQuery) -> CadiResult<Vec<(ChunkSummary, String)>> {
+        let result = RegistryClient::search(self, query).await?;
+        Ok(result.chunks.into_iter().map(|chunk| (chunk, "default".to_string())).collect())
+    }
+}
+
+/// The in-process index, for specs resolved without a registry
+#[async_trait]
+impl ComponentSearch for SearchEngine {
+    async fn search(&self, query: &SearchQuery) -> CadiResult<Vec<(ChunkSummary, String)>> {
+        let text = query.query.as_deref().unwrap_or_default();
+        Ok(self
+            .search_sync(text, query.limit)
+            .into_iter()
+            .map(|result| {
+                let chunk = ChunkSummary {
+                    chunk_id: result.id,
+                    name: result.metadata.name,
+                    cadi_type: String::new(),
+                    concepts: result.metadata.concepts,
+                    description: Some(result.metadata.description),
+                    language: Some(result.metadata.language),
+                    size: None,
+                };
+                (chunk, "local".to_string())
+            })
+            .collect())
+    }
+}
+
+/// A chunk a search component's query matched
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchCandidate {
+    pub chunk_id: String,
+    pub name: String,
+    /// Registry the chunk was found on
+    pub registry: String,
+    /// Relevance to the query and concepts, 0 to 1
+    pub score: f32,
+}
+
+/// How a search component was resolved, kept in the build plan so the
+/// choice can be audited
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResolution {
+    pub query: String,
+    pub language: Option<String>,
+    pub concepts: Vec<String>,
+    pub min_score: f32,
+    /// Best matches, highest score first
+    pub candidates: Vec<SearchCandidate>,
+}
+
+impl SearchResolution {
+    /// The match to pin, if the best one clears the minimum score
+    pub fn best(&self) -> Option<&SearchCandidate> {
+        self.candidates.first().filter(|c| c.score >= self.min_score)
+    }
+
+    /// The matches that were not pinned
+    pub fn alternatives(&self) -> &[SearchCandidate] {
+        match self.best() {
+            Some(_) => &self.candidates[1..],
+            None => &self.candidates,
+        }
+    }
+
+    /// The matches that fell short of the minimum score, for error messages
+    fn near_misses(&self) -> String {
+        if self.candidates.is_empty() {
+            return "no matches".to_string();
+        }
+        let misses: Vec<String> = self
+            .candidates
+            .iter()
+            .map(|c| format!("{} ({}, score {:.2})", c.name, c.chunk_id, c.score))
+            .collect();
+        format!("near misses: {}", misses.join(", "))
+    }
+}
+
+/// Run a search component's query against a registry, restricted to its
+/// language (or the project's) and ranked by how well each match covers the
+/// query terms and the requested concepts
+pub async fn resolve_search(
+    search: &SearchComponent,
+    project_language: &str,
+    registry: &dyn ComponentSearch,
+) -> CadiResult<SearchResolution> {
+    let language = search.language.clone().or_else(|| Some(project_language.to_string())).filter(|l| !l.is_empty());
+    let concepts = search.concepts.clone().unwrap_or_default();
+
+    let hits = registry
+        .search(&SearchQuery {
+            query: Some(search.query.clone()),
+            concepts: Some(concepts.clone()).filter(|c| !c.is_empty()),
+            language: language.clone(),
+            limit: 20,
+            ..Default::default()
+        })
+        .await?;
+
+    let mut candidates: Vec<SearchCandidate> = Vec::new();
+    for (chunk, registry) in hits {
+        if let (Some(wanted), Some(actual)) = (&language, &chunk.language) {
+            if !wanted.eq_ignore_ascii_case(actual) {
+                continue;
+            }
+        }
+        if candidates.iter().any(|c| c.chunk_id == chunk.chunk_id) {
+            continue;
+        }
+        candidates.push(SearchCandidate {
+            score: search_relevance(&search.query, &concepts, &chunk),
+            chunk_id: chunk.chunk_id,
+            name: chunk.name,
+            registry,
+        });
+    }
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    candidates.truncate(MAX_SEARCH_CANDIDATES);
+
+    Ok(SearchResolution {
+        query: search.query.clone(),
+        language,
+        concepts,
+        min_score: search.min_score.unwrap_or(DEFAULT_MIN_SEARCH_SCORE),
+        candidates,
+    })
+}
+
+/// Share of the query's terms found in the chunk's name, description and
+/// concepts, weighed with the share of the requested concepts it has
+fn search_relevance(query: &str, concepts: &[String], chunk: &ChunkSummary) -> f32 {
+    let words = |text: &str| -> Vec<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    };
+
+    let mut haystack = words(&chunk.name);
+    haystack.extend(words(chunk.description.as_deref().unwrap_or_default()));
+    for concept in &chunk.concepts {
+        haystack.extend(words(concept));
+    }
+
+    let mut terms = words(query);
+    terms.dedup();
+    let term_score = if terms.is_empty() {
+        1.0
+    } else {
+        terms.iter().filter(|t| haystack.contains(t)).count() as f32 / terms.len() as f32
+    };
+
+    if concepts.is_empty() {
+        return term_score;
+    }
+    let concept_score = concepts
+        .iter()
+        .filter(|wanted| chunk.concepts.iter().any(|c| c.eq_ignore_ascii_case(wanted)))
+        .count() as f32
+        / concepts.len() as f32;
+    0.6 * term_score + 0.4 * concept_score
+}
+
+/// Search components of a selection, resolved against a registry
+#[derive(Debug, Clone, Default)]
+pub struct ResolvedSearches {
+    /// Components pinned to a registry chunk, by component ID
+    pub pinned: HashMap<String, SearchResolution>,
+    /// Components replaced by their generate fallback: the fallback's ID
+    /// and the search that fell short, by search component ID
+    pub fallbacks: HashMap<String, (String, SearchResolution)>,
+}
+
+impl ResolvedSearches {
+    /// The ID a target builds in place of `id`
+    pub fn target_component<'a>(&'a self, id: &'a str) -> &'a str {
+        self.fallbacks.get(id).map_or(id, |(fallback, _)| fallback.as_str())
+    }
+}
+
+/// Resolve every search component in `selection`. A component whose best
+/// match doesn't clear its minimum score is replaced by its `fallback`
+/// generate component; without one it is skipped if optional and fails the
+/// build otherwise. Fallback generate components are only built for the
+/// searches that need them.
+pub async fn resolve_searches(
+    spec: &BuildSpec,
+    selection: &mut ComponentSelection,
+    registry: &dyn ComponentSearch,
+) -> Result<ResolvedSearches, CadiError> {
+    let mut resolved = ResolvedSearches::default();
+
+    for component in selection.components.clone() {
+        let ComponentSpec::Search(search) = component else {
+            continue;
+        };
+        let fallback = search
+            .fallback
+            .clone()
+            .filter(|id| selection.components.iter().any(|c| c.id() == id));
+
+        let resolution = match resolve_search(&search, &spec.project.language, registry).await {
+            Ok(resolution) => resolution,
+            Err(e) if fallback.is_none() && search.optional => {
+                tracing::warn!("Skipping optional component '{}': {}", search.id, e);
+                selection.skip(&search.id, format!("registry search failed: {}", e))?;
+                continue;
+            }
+            Err(e) if fallback.is_none() => {
+                return Err(CadiError::DependencyResolution(format!(
+                    "Component '{}': registry search for \"{}\" failed: {}",
+                    search.id, search.query, e
+                )));
+            }
+            Err(e) => {
+                tracing::warn!("Registry search for '{}' failed: {}", search.id, e);
+                SearchResolution {
+                    query: search.query.clone(),
+                    language: search.language.clone(),
+                    concepts: search.concepts.clone().unwrap_or_default(),
+                    min_score: search.min_score.unwrap_or(DEFAULT_MIN_SEARCH_SCORE),
+                    candidates: Vec::new(),
+                }
+            }
+        };
+
+        if resolution.best().is_some() {
+            resolved.pinned.insert(search.id, resolution);
+            continue;
+        }
+
+        let reason = format!(
+            "no registry chunk for \"{}\" scored at least {:.2} ({})",
+            search.query,
+            resolution.min_score,
+            resolution.near_misses()
+        );
+        match fallback {
+            Some(fallback) => {
+                tracing::info!("Component '{}' falls back to '{}': {}", search.id, fallback, reason);
+                selection.components.retain(|c| c.id() != search.id);
+                resolved.fallbacks.insert(search.id, (fallback, resolution));
+            }
+            None if search.optional => {
+                tracing::warn!("Skipping optional component '{}': {}", search.id, reason);
+                selection.skip(&search.id, reason)?;
+            }
+            None => {
+                return Err(CadiError::DependencyResolution(format!("Component '{}': {}", search.id, reason)));
+            }
+        }
+    }
+
+    // Fallbacks no search needed
+    let used: HashSet<String> = resolved.fallbacks.values().map(|(id, _)| id.clone()).collect();
+    for component in &spec.components {
+        let ComponentSpec::Search(search) = component else {
+            continue;
+        };
+        if let Some(fallback) = &search.fallback {
+            if !used.contains(fallback) && selection.components.iter().any(|c| c.id() == fallback) {
+                let reason = if resolved.pinned.contains_key(&search.id) {
+                    format!("fallback for '{}', which was found on the registry", search.id)
+                } else {
+                    format!("fallback for '{}', which is not built", search.id)
+                };
+                selection.skip(fallback, reason)?;
+            }
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// Target triple of the machine running the build
+pub fn host_target_triple() -> String {
+    let arch = std::env::consts::ARCH;
+    match std::env::consts::OS {
+        "linux" => format!("{}-unknown-linux-gnu", arch),
+        "macos" => format!("{}-apple-darwin", arch),
+        "windows" => format!("{}-pc-windows-msvc", arch),
+        os => format!("{}-unknown-{}", arch, os),
+    }
+}
+
+/// Target triple for a build of `spec`. `target` names one of the spec's
+/// targets, whose platform is used, or is a triple itself; the host triple
+/// is used when neither gives one.
+pub fn resolve_target_triple(spec: &BuildSpec, target: Option<&str>) -> String {
+    let Some(target) = target else {
+        return host_target_triple();
+    };
+    match spec.targets.iter().find(|t| t.name == target) {
+        Some(named) => named
+            .platform
+            .clone()
+            .filter(|p| !matches!(p.as_str(), "" | "any" | "native"))
+            .unwrap_or_else(host_target_triple),
+        None => target.to_string(),
+    }
+}
+
+/// Whether a component platform such as `windows`, `x86_64-linux` or a
+/// full triple applies to `triple`: every `-`-separated part of the
+/// platform has to appear in the triple
+pub fn platform_matches(platform: &str, triple: &str) -> bool {
+    let platform = platform.trim().to_ascii_lowercase();
+    if matches!(platform.as_str(), "" | "any" | "*") {
+        return true;
+    }
+
+    let triple = triple.to_ascii_lowercase();
+    let parts: Vec<&str> = triple.split('-').collect();
+    platform.split('-').all(|part| {
+        let part = match part {
+            "macos" | "mac" | "osx" => "darwin",
+            "win" | "win32" => "windows",
+            "wasm" => "wasm32",
+            "arm64" => "aarch64",
+            part => part,
+        };
+        parts.contains(&part)
+    })
+}
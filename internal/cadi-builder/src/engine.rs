@@ -1,7 +1,8 @@
 //! Build engine for CADI
 
-use cadi_core::{CadiError, CadiResult, Manifest};
-use crate::BuildPlan;
+use cadi_core::{sha256_bytes, CadiError, CadiResult, Manifest};
+use crate::{BuildPlan, BuildReceipt, CacheSource, HttpRemoteCache, LayeredCache, ReceiptInput, RemoteCache, Replay};
+use crate::dependency_resolver::SkippedComponent;
 use cadi_registry::client::RegistryClient;
 use serde_json::Value as JsonValue;
 use base64::engine::general_purpose;
@@ -9,7 +10,9 @@ use base64::Engine as _;
 use ed25519_dalek::Verifier;
 use ed25519_dalek::PublicKey;
 use ed25519_dalek::Signature;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::PathBuf;
+use tokio::sync::OnceCell;
 
 /// Build engine configuration
 #[derive(Debug, Clone)]
@@ -20,12 +23,18 @@ pub struct BuildConfig {
     pub cache_dir: PathBuf,
     /// Whether to use remote cache
     pub use_remote_cache: bool,
+    /// Registry serving the remote build cache
+    pub remote_cache_url: Option<String>,
+    /// Fetch from the remote cache but never upload to it
+    pub remote_cache_read_only: bool,
     /// Whether to fail fast on first error
     pub fail_fast: bool,
     /// Verbosity level
     pub verbose: bool,
     /// Require artifacts to be signed / attested before materialization
     pub require_signed: bool,
+    /// Run external commands declared in a target's transforms
+    pub allow_external_transforms: bool,
 }
 
 impl Default for BuildConfig {
@@ -36,9 +45,12 @@ impl Default for BuildConfig {
                 .map(|d| d.join("cadi"))
                 .unwrap_or_else(|| PathBuf::from(".cadi-cache")),
             use_remote_cache: true,
+            remote_cache_url: None,
+            remote_cache_read_only: false,
             fail_fast: false,
             verbose: false,
             require_signed: false,
+            allow_external_transforms: false,
         }
     }
 }
@@ -50,8 +62,13 @@ pub struct BuildResult {
     pub built: Vec<String>,
     /// Chunks retrieved from cache
     pub cached: Vec<String>,
+    /// Chunks in `cached` that were served by the remote cache
+    pub remote_cached: Vec<String>,
     /// Failed builds
     pub failed: Vec<BuildFailure>,
+    /// Build spec components left out of the build, and why (filled in by
+    /// the caller that compiled the spec)
+    pub skipped: Vec<SkippedComponent>,
     /// Total build time in milliseconds
     pub duration_ms: u64,
 }
@@ -66,18 +83,55 @@ pub struct BuildFailure {
 /// Build engine
 pub struct BuildEngine {
     config: BuildConfig,
-    cache: super::BuildCache,
+    cache: LayeredCache,
+    /// Tool versions recorded in build receipts, detected on first use
+    toolchain: OnceCell<BTreeMap<String, String>>,
 }
 
 impl BuildEngine {
     /// Create a new build engine
     pub fn new(config: BuildConfig) -> Self {
-        let cache = super::BuildCache::new(config.cache_dir.clone());
-        Self { config, cache }
+        let local = super::BuildCache::new(config.cache_dir.clone());
+
+        let remote = match (&config.remote_cache_url, config.use_remote_cache) {
+            (Some(url), true) => match HttpRemoteCache::new(url, None) {
+                Ok(remote) => Some(Box::new(remote) as Box<dyn RemoteCache>),
+                Err(e) => {
+                    tracing::warn!("Remote cache {} disabled: {}", url, e);
+                    None
+                }
+            },
+            _ => None,
+        };
+
+        let cache = LayeredCache::new(local, remote, config.remote_cache_read_only);
+        Self { config, cache, toolchain: OnceCell::new() }
     }
 
     /// Build a manifest for a given target
     pub async fn build(&self, manifest: &Manifest, target: &str) -> CadiResult<BuildResult> {
+        self.build_steps(manifest, target, None).await
+    }
+
+    /// Rerun only the named steps of a target's plan, skipping the cache for
+    /// them since their previous artifacts are stale. The rest of the plan
+    /// is assumed built. Used by watch mode after an
+    /// [`Invalidation`](crate::watch::Invalidation).
+    pub async fn rebuild(
+        &self,
+        manifest: &Manifest,
+        target: &str,
+        steps: &BTreeSet<String>,
+    ) -> CadiResult<BuildResult> {
+        self.build_steps(manifest, target, Some(steps)).await
+    }
+
+    async fn build_steps(
+        &self,
+        manifest: &Manifest,
+        target: &str,
+        only: Option<&BTreeSet<String>>,
+    ) -> CadiResult<BuildResult> {
         let start = std::time::Instant::now();
         
         let target_config = manifest.find_target(target)
@@ -95,6 +149,22 @@ impl BuildEngine {
         // Apply materialization preferences (stub - selection logic to be implemented)
         self.apply_materialization_preferences(&mut plan, target_config);
 
+        if let Some(only) = only {
+            // A node's transforms rerun with it
+            plan.steps.retain(|step| {
+                only.contains(&step.name) || step.output_of.as_ref().is_some_and(|node| only.contains(node))
+            });
+        }
+
+        if !self.config.allow_external_transforms {
+            if let Some(step) = plan.steps.iter().find(|step| step.transform.is_external()) {
+                return Err(CadiError::BuildFailed(format!(
+                    "Target '{}' runs the external transform {} in step {}; pass --allow-external-transforms to run it",
+                    target, step.transform, step.name
+                )));
+            }
+        }
+
         // Optional trust verification before executing plan
         if self.config.require_signed {
             if let Err(e) = self.verify_trust(&plan, manifest, target_config).await {
@@ -104,32 +174,58 @@ impl BuildEngine {
         
         let mut built = Vec::new();
         let mut cached = Vec::new();
+        let mut remote_cached = Vec::new();
         let mut failed = Vec::new();
+        // Content addresses of the step outputs so far, by step name
+        let mut outputs = HashMap::new();
         
         // Execute build plan
         for step in &plan.steps {
-            // Check cache first
-            if let Some(chunk_id) = &step.chunk_id {
-                if self.cache.has(chunk_id)? {
-                    if self.config.verbose {
-                        tracing::info!("Cache hit for {}", chunk_id);
-                    }
-                    println!("  {} Fetched {} from cache", 
+            // Check cache first, unless the step was invalidated
+            let cached_source = match only {
+                Some(_) => None,
+                None => self.cache.lookup(step).await?,
+            };
+            if let Some(source) = cached_source {
+                let chunk_id = step.chunk_id.clone().unwrap_or_else(|| step.name.clone());
+                if self.config.verbose {
+                    tracing::info!("Cache hit for {}", chunk_id);
+                }
+                let from = match source {
+                    CacheSource::Local => "cache",
+                    CacheSource::Remote => "remote cache",
+                };
+                if self.config.verbose {
+                    println!("  {} Fetched {} from {}",
                         console::style("✓").green(),
-                        console::style(&step.name).cyan());
-                    cached.push(chunk_id.to_string());
-                    continue;
+                        console::style(&step.name).cyan(),
+                        from);
+                }
+                if source == CacheSource::Remote {
+                    remote_cached.push(chunk_id.clone());
+                }
+                cached.push(chunk_id);
+                if let Some(output) = self.cached_output(step) {
+                    if let Some(node) = &step.output_of {
+                        outputs.insert(node.clone(), output.clone());
+                    }
+                    outputs.insert(step.name.clone(), output);
                 }
+                self.record_step(step);
+                continue;
             }
             
             // Execute transformation
-            println!("  {} Building {}...", 
-                console::style("→").cyan(),
-                console::style(&step.name).yellow());
+            if self.config.verbose {
+                println!("  {} Building {}...",
+                    console::style("→").cyan(),
+                    console::style(&step.name).yellow());
+            }
             
-            match self.execute_step(step).await {
+            match self.execute_step(step, &mut outputs).await {
                 Ok(chunk_id) => {
                     tracing::debug!("Built {}", chunk_id);
+                    self.record_step(step);
                     built.push(chunk_id);
                 }
                 Err(e) => {
@@ -147,41 +243,187 @@ impl BuildEngine {
         }
         
         let duration_ms = start.elapsed().as_millis() as u64;
+
+        // A full build counts as a use of every chunk in the plan; rebuilds
+        // of a few invalidated steps (watch mode) would inflate the counts
+        if only.is_none() {
+            let chunk_ids: Vec<String> = plan.steps.iter().filter_map(|step| step.chunk_id.clone()).collect();
+            self.cache.report_usage(&chunk_ids).await;
+        }
+
+        let record = super::BuildRecord {
+            finished_at: chrono::Utc::now().to_rfc3339(),
+            cached: cached.len(),
+            built: built.len(),
+            failed: failed.len(),
+        };
+        if let Err(e) = self.cache.local().record_build(&record) {
+            tracing::warn!("Failed to record build history: {}", e);
+        }
         
         Ok(BuildResult {
             built,
             cached,
+            remote_cached,
             failed,
+            skipped: Vec::new(),
             duration_ms,
         })
     }
 
-    /// Execute a single build step
-    async fn execute_step(&self, step: &super::BuildStep) -> CadiResult<String> {
+    /// Remember a step's inputs so the next plan can tell what changed
+    fn record_step(&self, step: &super::BuildStep) {
+        let inputs: Vec<String> = step.inputs.iter().map(|i| i.chunk_id.clone()).collect();
+        if let Err(e) = self.cache.local().record_step(&step.name, &inputs) {
+            tracing::warn!("Failed to record build step {}: {}", step.name, e);
+        }
+    }
+
+    /// Content address of a step's cached artifact
+    fn cached_output(&self, step: &super::BuildStep) -> Option<String> {
+        let key = step.cache_key()?;
+        let data = std::fs::read(self.cache.local().get_path(key)).ok()?;
+        Some(format!("chunk:sha256:{}", sha256_bytes(&data)))
+    }
+
+    /// Execute a single build step, recording a receipt for its output
+    async fn execute_step(
+        &self,
+        step: &super::BuildStep,
+        outputs: &mut HashMap<String, String>,
+    ) -> CadiResult<String> {
         tracing::info!("Executing step: {}", step.name);
+        let started_at = chrono::Utc::now().to_rfc3339();
         
         // Prepare inputs with paths
         let mut prepared_inputs = Vec::new();
+        let mut receipt_inputs = Vec::new();
         for input in &step.inputs {
             let mut prepared = input.clone();
-            if self.cache.has(&input.chunk_id)? {
-                prepared.path = Some(self.cache.get_path(&input.chunk_id).to_string_lossy().to_string());
+            // Dependencies built or fetched earlier stand in by their output
+            if let Some(output) = input.chunk_id.strip_prefix("pending:").and_then(|dep| outputs.get(dep)) {
+                prepared.chunk_id = output.clone();
+            }
+            let mut hash = None;
+            if self.cache.local().has(&prepared.chunk_id)? {
+                let path = self.cache.local().get_path(&prepared.chunk_id);
+                hash = Some(sha256_bytes(&std::fs::read(&path)?));
+                prepared.path = Some(path.to_string_lossy().to_string());
             }
+            receipt_inputs.push(ReceiptInput {
+                chunk_id: prepared.chunk_id.clone(),
+                role: prepared.role.clone(),
+                hash,
+            });
             prepared_inputs.push(prepared);
         }
 
         // Execute the transformation
         let transformer = super::Transformer::new();
-        let result = transformer.transform(&step.transform, &prepared_inputs).await?;
+        let super::TransformOutput { data: result, log } = transformer.run(&step.transform, &prepared_inputs).await?;
         
         // Store in cache
-        if let Some(ref chunk_id) = step.chunk_id {
-            self.cache.store(chunk_id, &result)?;
+        self.cache.store(step, &result).await?;
+
+        // Keep the artifact under its content address too, where later
+        // steps and receipt replays look for it
+        let output_hash = sha256_bytes(&result);
+        let output = format!("chunk:sha256:{}", output_hash);
+        if step.chunk_id.as_deref() != Some(output.as_str()) {
+            self.cache.local().store(&output, &result)?;
         }
+        outputs.insert(step.name.clone(), output.clone());
+        if let Some(node) = &step.output_of {
+            outputs.insert(node.clone(), output.clone());
+        }
+
+        let receipt = BuildReceipt {
+            step: step.name.clone(),
+            transform: step.transform.clone(),
+            inputs: receipt_inputs,
+            toolchain: self.toolchain().await.clone(),
+            output,
+            output_hash,
+            log,
+            started_at,
+            finished_at: chrono::Utc::now().to_rfc3339(),
+        };
+        self.record_receipt(&receipt);
         
         Ok(step.chunk_id.clone().unwrap_or_else(|| step.name.clone()))
     }
 
+    /// Store a build receipt and link the output to it
+    fn record_receipt(&self, receipt: &BuildReceipt) {
+        let stored = self
+            .cache
+            .local()
+            .store_receipt(receipt)
+            .and_then(|_| self.cache.local().record_lineage(&receipt.output, &receipt.lineage()));
+        if let Err(e) = stored {
+            tracing::warn!("Failed to record build receipt for {}: {}", receipt.step, e);
+        }
+    }
+
+    /// Versions of the tools on this machine, as recorded in build receipts
+    pub async fn toolchain(&self) -> &BTreeMap<String, String> {
+        self.toolchain.get_or_init(crate::receipt::detect_toolchain).await
+    }
+
+    /// Rerun the transform a receipt records, with the inputs it records,
+    /// and compare the output hash.
+    ///
+    /// Inputs that were in the local cache when the receipt was made must
+    /// still be there with the same content, under their chunk ID or
+    /// content address.
+    pub async fn replay(&self, receipt: &BuildReceipt) -> CadiResult<Replay> {
+        if receipt.transform.is_external() && !self.config.allow_external_transforms {
+            return Ok(Replay::Unavailable(format!(
+                "{} is an external transform; pass --allow-external-transforms to rerun it",
+                receipt.transform
+            )));
+        }
+        let mut inputs = Vec::new();
+        for input in &receipt.inputs {
+            let mut prepared = super::TransformInput {
+                chunk_id: input.chunk_id.clone(),
+                data: None,
+                role: input.role.clone(),
+                path: None,
+            };
+            if let Some(hash) = &input.hash {
+                let candidates = [input.chunk_id.clone(), format!("chunk:sha256:{}", hash)];
+                let found = candidates.iter().map(|id| self.cache.local().get_path(id)).find(|path| {
+                    std::fs::read(path).map(|data| &sha256_bytes(&data) == hash).unwrap_or(false)
+                });
+                match found {
+                    Some(path) => prepared.path = Some(path.to_string_lossy().to_string()),
+                    None => {
+                        return Ok(Replay::Unavailable(format!(
+                            "input {} is no longer in the local cache as built",
+                            input.chunk_id
+                        )))
+                    }
+                }
+            }
+            inputs.push(prepared);
+        }
+
+        let result = match super::Transformer::new().transform(&receipt.transform, &inputs).await {
+            Ok(result) => result,
+            Err(e) => return Ok(Replay::Unavailable(format!("{} failed: {}", receipt.transform, e))),
+        };
+        let actual = sha256_bytes(&result);
+        if actual == receipt.output_hash {
+            Ok(Replay::Reproduced)
+        } else {
+            Ok(Replay::Differs {
+                expected: receipt.output_hash.clone(),
+                actual,
+            })
+        }
+    }
+
     /// Apply materialization preferences to the build plan.
     ///
     /// This is a placeholder for selection logic that will choose representations
@@ -279,7 +521,7 @@ impl BuildEngine {
                         }
                         Err(e) => {
                             tracing::warn!("Failed to fetch build receipt {}: {}", build_receipt_id, e);
-                            return Err(e);
+                            return Err(e.into());
                         }
                     }
                 } else {
@@ -349,8 +591,8 @@ fn verify_attestation_signature(json: &JsonValue, _raw_bytes: &[u8]) -> CadiResu
 
     /// Get the path to a cached chunk
     pub fn get_chunk_path(&self, chunk_id: &str) -> Option<PathBuf> {
-        if self.cache.has(chunk_id).unwrap_or(false) {
-            Some(self.cache.get_path(chunk_id))
+        if self.cache.local().has(chunk_id).unwrap_or(false) {
+            Some(self.cache.local().get_path(chunk_id))
         } else {
             None
         }
@@ -358,7 +600,7 @@ fn verify_attestation_signature(json: &JsonValue, _raw_bytes: &[u8]) -> CadiResu
 
     /// Get cache statistics
     pub fn cache_stats(&self) -> CadiResult<CacheStats> {
-        self.cache.stats()
+        self.cache.local().stats()
     }
 }
 
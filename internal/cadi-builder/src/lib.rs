@@ -11,6 +11,9 @@ pub mod importer;
 pub mod builder;
 pub mod cbs;
 pub mod build_spec;
+pub mod watch;
+pub mod receipt;
+pub mod scaffold;
 
 pub use engine::*;
 pub use cache::*;
@@ -19,5 +22,7 @@ pub use plan::*;
 pub use importer::*;
 pub use builder::*;
 pub use cbs::*;
+pub use scaffold::{OnConflict, Resolution, ScaffoldReport, Scaffolder};
+pub use receipt::{BuildReceipt, Provenance, ReceiptInput, Replay};
 pub use build_spec::{BuildSpec, ComponentSpec, GenerateComponent, ReuseComponent, SearchComponent, BuildSpecValidator, ReusePlan, GeneratePlan};
 pub mod dependency_resolver;
@@ -1,8 +1,11 @@
 //! Build planning for CADI
 
 use cadi_core::{CadiError, CadiResult, Manifest};
+use serde::Serialize;
 use std::collections::{HashMap, HashSet};
 
+use crate::BuildCache;
+
 /// A build plan
 #[derive(Debug)]
 pub struct BuildPlan {
@@ -25,6 +28,23 @@ pub struct BuildStep {
     pub inputs: Vec<super::TransformInput>,
     /// Dependencies (step names that must complete first)
     pub depends_on: Vec<String>,
+    /// Hash of the transformation, inputs and dependency input hashes.
+    /// Used as the remote cache key.
+    pub input_hash: String,
+    /// Node whose output this target transform step rewrites; dependents
+    /// of the node get the rewritten output
+    pub output_of: Option<String>,
+}
+
+impl BuildStep {
+    /// Key of the step's output in the local cache: the chunk the step
+    /// builds, or for a target transform its input hash
+    pub fn cache_key(&self) -> Option<&str> {
+        match &self.output_of {
+            Some(_) => Some(&self.input_hash),
+            None => self.chunk_id.as_deref(),
+        }
+    }
 }
 
 impl BuildPlan {
@@ -76,6 +96,123 @@ impl BuildPlan {
     pub fn len(&self) -> usize {
         self.steps.len()
     }
+
+    /// Compare the plan against the build cache, marking each step as
+    /// cached, rebuilt because its inputs changed, or new
+    pub fn annotate_with_cache(&self, cache: &BuildCache) -> AnnotatedPlan {
+        let mut statuses: HashMap<&str, StepStatus> = HashMap::new();
+        let mut steps = Vec::with_capacity(self.steps.len());
+
+        for step in &self.steps {
+            let inputs: Vec<String> = step.inputs.iter().map(|i| i.chunk_id.clone()).collect();
+            let previous = cache.step_inputs(&step.name).ok().flatten();
+
+            // Inputs missing from the last build, plus dependencies that will be rebuilt
+            let mut changed_inputs = Vec::new();
+            if let Some(previous) = &previous {
+                changed_inputs.extend(inputs.iter().filter(|i| !previous.contains(i)).cloned());
+                for dep in &step.depends_on {
+                    let dep_input = format!("pending:{}", dep);
+                    let dep_changed = statuses.get(dep.as_str()).is_some_and(|s| *s != StepStatus::Cached);
+                    if dep_changed && !changed_inputs.contains(&dep_input) {
+                        changed_inputs.push(dep_input);
+                    }
+                }
+            }
+
+            // The engine skips any step whose output is already cached
+            let cached = step.cache_key()
+                .map(|id| cache.has(id).unwrap_or(false))
+                .unwrap_or(false);
+            let status = if cached {
+                StepStatus::Cached
+            } else if previous.is_some() {
+                StepStatus::Rebuild
+            } else {
+                StepStatus::New
+            };
+            statuses.insert(&step.name, status);
+
+            steps.push(AnnotatedStep {
+                name: step.name.clone(),
+                chunk_id: step.chunk_id.clone(),
+                transform: step.transform.to_string(),
+                depends_on: step.depends_on.clone(),
+                status,
+                changed_inputs,
+            });
+        }
+
+        AnnotatedPlan {
+            summary: PlanSummary::from_steps(&steps),
+            steps,
+            estimated_time_ms: self.estimated_time_ms,
+        }
+    }
+}
+
+/// Cache status of a planned step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    /// Output is in the build cache
+    Cached,
+    /// Built before, but its inputs changed since
+    Rebuild,
+    /// Never built
+    New,
+}
+
+/// A build step with its cache status
+#[derive(Debug, Clone, Serialize)]
+pub struct AnnotatedStep {
+    pub name: String,
+    pub chunk_id: Option<String>,
+    /// Transformation description, e.g. `compile (x86_64-linux)`
+    pub transform: String,
+    pub depends_on: Vec<String>,
+    pub status: StepStatus,
+    /// Inputs that differ from the last build of this step
+    pub changed_inputs: Vec<String>,
+}
+
+/// Step counts by cache status
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PlanSummary {
+    pub rebuild: usize,
+    pub new: usize,
+    pub cached: usize,
+}
+
+impl PlanSummary {
+    fn from_steps(steps: &[AnnotatedStep]) -> Self {
+        let count = |status| steps.iter().filter(|s| s.status == status).count();
+        Self {
+            rebuild: count(StepStatus::Rebuild),
+            new: count(StepStatus::New),
+            cached: count(StepStatus::Cached),
+        }
+    }
+}
+
+impl std::fmt::Display for PlanSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} to rebuild, {} cached", self.rebuild, self.cached)?;
+        if self.new > 0 {
+            write!(f, ", {} new", self.new)?;
+        }
+        Ok(())
+    }
+}
+
+/// A build plan annotated against the build cache (a dry-run diff against
+/// the previous build)
+#[derive(Debug, Clone, Serialize)]
+pub struct AnnotatedPlan {
+    pub steps: Vec<AnnotatedStep>,
+    pub summary: PlanSummary,
+    /// Estimated total time in ms
+    pub estimated_time_ms: u64,
 }
 
 /// Build a dependency graph from the build graph edges
@@ -126,20 +263,105 @@ fn collect_steps(
     let repr = select_representation(node, target);
     
     // Create build step
+    let transform = determine_transform(repr, node, &target.platform);
+    let inputs = build_inputs(node, repr, deps);
+    let depends_on = deps.get(node_id).cloned()
+        .unwrap_or_default();
+    let input_hash = step_input_hash(&transform, &inputs, &depends_on, steps);
     let step = BuildStep {
         name: node_id.to_string(),
         chunk_id: repr.map(|r| r.chunk.clone()),
-        transform: determine_transform(repr, node, &target.platform),
-        inputs: build_inputs(node, repr, deps),
-        depends_on: deps.get(node_id).cloned()
-            .unwrap_or_default(),
+        transform,
+        inputs,
+        depends_on,
+        input_hash,
+        output_of: None,
     };
-    
+    let mut representation = output_representation(&step.transform);
     steps.push(step);
+
+    // The target's transforms rewrite the node's output, each taking the
+    // representation the previous one left
+    let mut tail = node_id.to_string();
+    for spec in &target.transforms {
+        if spec.input != representation {
+            continue;
+        }
+        let mut name = format!("{}:{}", node_id, spec.label());
+        if steps.iter().any(|s| s.name == name) {
+            name = format!("{}.{}", name, steps.len());
+        }
+        let transform = super::TransformType::from_spec(spec);
+        let inputs = vec![super::TransformInput {
+            chunk_id: format!("pending:{}", tail),
+            data: None,
+            role: "main".to_string(),
+            path: None,
+        }];
+        let depends_on = vec![tail];
+        let input_hash = step_input_hash(&transform, &inputs, &depends_on, steps);
+        steps.push(BuildStep {
+            name: name.clone(),
+            chunk_id: None,
+            transform,
+            inputs,
+            depends_on,
+            input_hash,
+            output_of: Some(node_id.to_string()),
+        });
+        tail = name;
+        representation = spec.output.clone();
+    }
     
     Ok(())
 }
 
+/// Representation a node's build step produces, as target transforms name
+/// it
+fn output_representation(transform: &super::TransformType) -> String {
+    match transform {
+        super::TransformType::Parse { .. } => "ir",
+        super::TransformType::Bundle { .. } => "bundle",
+        super::TransformType::Containerize { .. } => "container",
+        _ => "blob",
+    }
+    .to_string()
+}
+
+/// Hash a step's transformation and inputs. Dependencies are already in
+/// `steps`, so their hashes stand in for their (not yet built) outputs.
+fn step_input_hash(
+    transform: &super::TransformType,
+    inputs: &[super::TransformInput],
+    depends_on: &[String],
+    steps: &[BuildStep],
+) -> String {
+    let mut key = transform.to_string();
+    match transform {
+        super::TransformType::Custom { args, .. } => {
+            let mut args: Vec<_> = args.iter().collect();
+            args.sort();
+            for (name, value) in args {
+                key.push_str(&format!(" {}={}", name, value));
+            }
+        }
+        super::TransformType::External { command, env, exit_code } => {
+            key.push_str(&format!(" {:?} env={:?} exit={}", command, env, exit_code));
+        }
+        _ => {}
+    }
+    for input in inputs {
+        key.push_str(&format!("\n{}:{}", input.role, input.chunk_id));
+    }
+    // A transformed dependency stands in by its last transform
+    for dep in depends_on {
+        if let Some(dep_step) = steps.iter().rev().find(|s| &s.name == dep || s.output_of.as_ref() == Some(dep)) {
+            key.push_str(&format!("\ndep:{}", dep_step.input_hash));
+        }
+    }
+    cadi_core::sha256_bytes(key.as_bytes())
+}
+
 /// Select the best representation for a platform
 fn select_representation<'a>(
     node: &'a cadi_core::GraphNode,
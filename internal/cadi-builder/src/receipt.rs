@@ -0,0 +1,186 @@
+//! Build receipts
+//!
+//! Every artifact the [`BuildEngine`](crate::BuildEngine) builds gets a
+//! receipt recording the inputs it was built from, the transform applied,
+//! the toolchain on the build machine and the hash of the output. A receipt
+//! is content-addressed by a hash that leaves out its timestamps, so
+//! building the same inputs with the same toolchain yields the same receipt
+//! ID. The built chunk's [`ChunkLineage`] points at its receipt, and
+//! [`Provenance::trace`] follows those links back through earlier builds.
+
+use cadi_core::{sha256_bytes, CadiResult, ChunkLineage};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use tokio::process::Command;
+
+use crate::{BuildCache, TransformLog, TransformType};
+
+/// Tools whose versions are recorded, with the command reporting them
+const TOOLS: &[(&str, &str)] = &[
+    ("rustc", "rustc"),
+    ("cargo", "cargo"),
+    ("gcc", "gcc"),
+    ("node", "node"),
+    ("npm", "npm"),
+    ("python", "python3"),
+];
+
+/// Receipt fields left out of its ID: timestamps, and what a command
+/// printed, which can differ between identical builds
+const UNHASHED_FIELDS: &[&str] = &["started_at", "finished_at", "log"];
+
+/// An input a receipt's output was built from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceiptInput {
+    pub chunk_id: String,
+    /// Role of the input (e.g., "main", "dependency")
+    pub role: String,
+    /// SHA-256 of the input's content, if it was in the local cache
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+/// How a built chunk was produced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildReceipt {
+    /// Build step that produced the output
+    pub step: String,
+    pub transform: TransformType,
+    pub inputs: Vec<ReceiptInput>,
+    /// Tool versions on the build machine, for the tools installed there
+    pub toolchain: BTreeMap<String, String>,
+    /// Content address of the output
+    pub output: String,
+    pub output_hash: String,
+    /// Exit code, stdout and stderr of an external transform
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log: Option<TransformLog>,
+    pub started_at: String,
+    pub finished_at: String,
+}
+
+impl BuildReceipt {
+    /// Content address of the receipt, excluding its timestamps and log
+    pub fn receipt_id(&self) -> String {
+        let mut value = serde_json::to_value(self).expect("build receipts serialize to JSON");
+        if let Some(fields) = value.as_object_mut() {
+            for field in UNHASHED_FIELDS {
+                fields.remove(*field);
+            }
+        }
+        let bytes = serde_json::to_vec(&value).expect("build receipts serialize to JSON");
+        format!("chunk:sha256:{}", sha256_bytes(&bytes))
+    }
+
+    /// Lineage linking the output to its inputs and this receipt
+    pub fn lineage(&self) -> ChunkLineage {
+        ChunkLineage {
+            parents: self
+                .inputs
+                .iter()
+                .filter(|input| input.chunk_id.starts_with("chunk:"))
+                .map(|input| input.chunk_id.clone())
+                .collect(),
+            build_receipt: Some(self.receipt_id()),
+        }
+    }
+
+    /// Tools whose version differs between the receipt and `toolchain`, as
+    /// (tool, recorded version, current version). Missing tools read as "-".
+    pub fn toolchain_drift(&self, toolchain: &BTreeMap<String, String>) -> Vec<(String, String, String)> {
+        let tools: BTreeSet<&String> = self.toolchain.keys().chain(toolchain.keys()).collect();
+        tools
+            .into_iter()
+            .filter_map(|tool| {
+                let recorded = self.toolchain.get(tool).map_or("-", String::as_str);
+                let current = toolchain.get(tool).map_or("-", String::as_str);
+                (recorded != current).then(|| (tool.clone(), recorded.to_string(), current.to_string()))
+            })
+            .collect()
+    }
+}
+
+/// Versions of the tools installed on this machine. Tools that aren't
+/// installed are left out.
+pub async fn detect_toolchain() -> BTreeMap<String, String> {
+    let mut toolchain = BTreeMap::new();
+    for (name, command) in TOOLS {
+        let Ok(output) = Command::new(command).arg("--version").output().await else {
+            continue;
+        };
+        if !output.status.success() {
+            continue;
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if let Some(version) = stdout.lines().map(str::trim).find(|line| !line.is_empty()) {
+            toolchain.insert(name.to_string(), version.to_string());
+        }
+    }
+    toolchain
+}
+
+/// Outcome of replaying a build receipt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Replay {
+    /// The transform produced the recorded output again
+    Reproduced,
+    /// The transform produced a different output
+    Differs { expected: String, actual: String },
+    /// The recorded build can't be rerun here
+    Unavailable(String),
+}
+
+/// A chunk and, if it was built locally, the build that produced it and
+/// the provenance of that build's inputs
+#[derive(Debug, Clone, Serialize)]
+pub struct Provenance {
+    pub chunk_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receipt_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receipt: Option<BuildReceipt>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub parents: Vec<Provenance>,
+}
+
+impl Provenance {
+    /// Follow the lineage recorded in `cache` from `chunk_id`, at most
+    /// `max_depth` builds back
+    pub fn trace(cache: &BuildCache, chunk_id: &str, max_depth: usize) -> CadiResult<Self> {
+        Self::trace_from(cache, chunk_id, max_depth, &mut BTreeSet::new())
+    }
+
+    fn trace_from(
+        cache: &BuildCache,
+        chunk_id: &str,
+        max_depth: usize,
+        seen: &mut BTreeSet<String>,
+    ) -> CadiResult<Self> {
+        let mut node = Self {
+            chunk_id: chunk_id.to_string(),
+            receipt_id: None,
+            receipt: None,
+            parents: Vec::new(),
+        };
+        if max_depth == 0 || !seen.insert(chunk_id.to_string()) {
+            return Ok(node);
+        }
+        let Some(lineage) = cache.lineage(chunk_id)? else {
+            return Ok(node);
+        };
+
+        if let Some(receipt_id) = &lineage.build_receipt {
+            node.receipt = cache.receipt(receipt_id)?;
+            node.receipt_id = Some(receipt_id.clone());
+        }
+        for parent in &lineage.parents {
+            node.parents.push(Self::trace_from(cache, parent, max_depth - 1, seen)?);
+        }
+        Ok(node)
+    }
+
+    /// Whether the chunk was built locally
+    pub fn is_built(&self) -> bool {
+        self.receipt_id.is_some()
+    }
+}
@@ -0,0 +1,400 @@
+//! Project scaffolding
+//!
+//! A [`Scaffolder`] writes out the source files of a [`Manifest`]. Each
+//! node's source chunk is looked up in the local graph store, then in the
+//! chunk cache, and fetched from the registry when neither has its content.
+//! A chunk's file goes back where it came from: the source file recorded
+//! for it, else a path derived from its alias, else one derived from the
+//! node ID. Atoms cut from the same file are joined again in line order.
+//! Files that already exist are overwritten, skipped or referred to a
+//! prompt, according to [`OnConflict`].
+
+use cadi_core::atomic::AtomicChunk;
+use cadi_core::chunk::SourceCadi;
+use cadi_core::graph::GraphStore;
+use cadi_core::{chunk_storage_key, CadiResult, Manifest};
+use cadi_registry::RegistryClient;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::builder::language_to_extension;
+
+/// Suffix of the file written next to an existing one on
+/// [`Resolution::KeepBoth`]
+pub const KEEP_BOTH_SUFFIX: &str = "cadi-new";
+
+/// What to do with a file that exists with different content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnConflict {
+    Overwrite,
+    #[default]
+    Skip,
+    /// Ask the scaffolder's prompt; skip when it has none
+    Ask,
+}
+
+/// Answer to a conflict prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Overwrite,
+    Skip,
+    /// Keep the existing file and write the scaffolded one next to it,
+    /// with [`KEEP_BOTH_SUFFIX`] appended
+    KeepBoth,
+}
+
+/// Asked which [`Resolution`] to apply to an existing file, given its path
+/// relative to the output directory
+pub type ConflictPrompt = Box<dyn Fn(&Path) -> Resolution + Send + Sync>;
+
+/// What happened to a scaffolded file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileAction {
+    Created,
+    Overwritten,
+    /// The file already had the scaffolded content
+    Unchanged,
+    Skipped,
+    /// Written next to the existing file
+    KeptBoth,
+}
+
+/// A file in a [`ScaffoldReport`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScaffoldedFile {
+    /// Path relative to the output directory; for
+    /// [`FileAction::KeptBoth`], the path of the existing file
+    pub path: PathBuf,
+    pub action: FileAction,
+    /// Chunks the file was assembled from
+    pub chunks: Vec<String>,
+}
+
+/// A node whose files could not be scaffolded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Unresolved {
+    pub node: String,
+    pub chunk_id: Option<String>,
+    pub reason: String,
+}
+
+/// Result of [`Scaffolder::scaffold`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScaffoldReport {
+    pub output_dir: PathBuf,
+    /// Files in path order
+    pub files: Vec<ScaffoldedFile>,
+    pub unresolved: Vec<Unresolved>,
+    /// Chunks fetched from the registry
+    pub fetched: Vec<String>,
+}
+
+impl ScaffoldReport {
+    /// Number of files with the given action
+    pub fn count(&self, action: FileAction) -> usize {
+        self.files.iter().filter(|f| f.action == action).count()
+    }
+
+    /// Number of files written to disk
+    pub fn written(&self) -> usize {
+        self.files
+            .iter()
+            .filter(|f| matches!(f.action, FileAction::Created | FileAction::Overwritten | FileAction::KeptBoth))
+            .count()
+    }
+
+    /// Whether every node was scaffolded
+    pub fn is_complete(&self) -> bool {
+        self.unresolved.is_empty()
+    }
+}
+
+/// Part of a file contributed by one chunk
+struct Piece {
+    chunk_id: String,
+    lines: Option<(usize, usize)>,
+    content: Vec<u8>,
+}
+
+/// Where a chunk's content came from and where it goes
+struct Located {
+    file: Option<String>,
+    lines: Option<(usize, usize)>,
+    alias: Option<String>,
+    language: Option<String>,
+}
+
+/// Writes manifests out as source trees
+pub struct Scaffolder {
+    cache_dir: PathBuf,
+    graph: Option<GraphStore>,
+    registry: Option<RegistryClient>,
+    prompt: Option<ConflictPrompt>,
+}
+
+impl Scaffolder {
+    /// Scaffold from the chunk cache in `cache_dir` (`chunks/` and
+    /// `blobs/sha256/`)
+    pub fn new(cache_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            cache_dir: cache_dir.into(),
+            graph: None,
+            registry: None,
+            prompt: None,
+        }
+    }
+
+    /// Resolve chunks and their locations through a graph store first
+    pub fn with_graph(mut self, graph: GraphStore) -> Self {
+        self.graph = Some(graph);
+        self
+    }
+
+    /// Fetch chunks missing locally from a registry
+    pub fn with_registry(mut self, registry: RegistryClient) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Prompt used for [`OnConflict::Ask`]
+    pub fn with_prompt(mut self, prompt: ConflictPrompt) -> Self {
+        self.prompt = Some(prompt);
+        self
+    }
+
+    /// Write the files of `manifest` under `output_dir`
+    pub async fn scaffold(&self, manifest: &Manifest, output_dir: &Path, on_conflict: OnConflict) -> CadiResult<ScaffoldReport> {
+        let mut report = ScaffoldReport {
+            output_dir: output_dir.to_path_buf(),
+            ..Default::default()
+        };
+        let mut files: BTreeMap<PathBuf, Vec<Piece>> = BTreeMap::new();
+
+        for node in &manifest.build_graph.nodes {
+            let unresolved = |chunk_id: Option<&str>, reason: String| Unresolved {
+                node: node.id.clone(),
+                chunk_id: chunk_id.map(str::to_string),
+                reason,
+            };
+
+            let Some(reference) = node
+                .representations
+                .iter()
+                .find(|r| r.form == "source")
+                .map(|r| r.chunk.clone())
+                .or_else(|| node.source_cadi.clone())
+                .or_else(|| node.chunk_ref.as_ref().map(|r| r.split('@').next().unwrap_or(r).to_string()))
+            else {
+                report.unresolved.push(unresolved(None, "no source chunk".to_string()));
+                continue;
+            };
+            let Some(chunk_id) = self.resolve(&reference)? else {
+                report.unresolved.push(unresolved(Some(&reference), "alias not found in the graph".to_string()));
+                continue;
+            };
+
+            // Multi-file source chunks carry their own layout under the node
+            if let Some(source) = self.source_cadi(&chunk_id) {
+                for file in &source.source.files {
+                    let hash = file.hash.strip_prefix("sha256:").unwrap_or(&file.hash);
+                    match std::fs::read(self.cache_dir.join("blobs").join("sha256").join(hash)) {
+                        Ok(content) => self.add(&mut files, &mut report, &node.id, Path::new(&node.id).join(&file.path), Piece {
+                            chunk_id: chunk_id.clone(),
+                            lines: None,
+                            content,
+                        }),
+                        Err(_) => report.unresolved.push(unresolved(Some(&chunk_id), format!("blob for {} not found", file.path))),
+                    }
+                }
+                continue;
+            }
+
+            let content = match self.content(&chunk_id).await {
+                Ok(Some((content, fetched))) => {
+                    if fetched {
+                        report.fetched.push(chunk_id.clone());
+                    }
+                    content
+                }
+                Ok(None) => {
+                    report.unresolved.push(unresolved(Some(&chunk_id), "chunk not found locally and no registry configured".to_string()));
+                    continue;
+                }
+                Err(e) => {
+                    report.unresolved.push(unresolved(Some(&chunk_id), e.to_string()));
+                    continue;
+                }
+            };
+
+            let located = self.locate(&chunk_id)?;
+            let extension = language_to_extension(located.language.as_deref().unwrap_or_default());
+            let path = match (&located.file, &located.alias) {
+                (Some(file), _) => PathBuf::from(file),
+                (None, Some(alias)) => PathBuf::from(format!("{}.{}", alias, extension)),
+                (None, None) => PathBuf::from(format!("{}.{}", node.id, extension)),
+            };
+            self.add(&mut files, &mut report, &node.id, path, Piece {
+                chunk_id,
+                lines: located.lines,
+                content,
+            });
+        }
+
+        for (path, pieces) in files {
+            let chunks = pieces.iter().map(|p| p.chunk_id.clone()).collect();
+            let action = self.write(output_dir, &path, &assemble(pieces), on_conflict)?;
+            report.files.push(ScaffoldedFile { path, action, chunks });
+        }
+
+        Ok(report)
+    }
+
+    /// Queue a piece of a file, unless its path leaves the output directory
+    fn add(&self, files: &mut BTreeMap<PathBuf, Vec<Piece>>, report: &mut ScaffoldReport, node: &str, path: PathBuf, piece: Piece) {
+        if path.components().all(|c| matches!(c, Component::Normal(_))) {
+            files.entry(path).or_default().push(piece);
+        } else {
+            report.unresolved.push(Unresolved {
+                node: node.to_string(),
+                chunk_id: Some(piece.chunk_id),
+                reason: format!("path {} leaves the output directory", path.display()),
+            });
+        }
+    }
+
+    /// The chunk ID for a manifest reference, which may be an alias
+    fn resolve(&self, reference: &str) -> CadiResult<Option<String>> {
+        if reference.starts_with("chunk:") {
+            return Ok(Some(reference.to_string()));
+        }
+        match &self.graph {
+            Some(graph) => graph.resolve_alias(reference),
+            None => Ok(None),
+        }
+    }
+
+    fn source_cadi(&self, chunk_id: &str) -> Option<SourceCadi> {
+        let key = chunk_storage_key(chunk_id);
+        let json = std::fs::read_to_string(self.cache_dir.join("chunks").join(format!("{}.json", key))).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    /// Atomic chunk metadata as saved by `cadi import`
+    fn atomic_chunk(&self, chunk_id: &str) -> Option<AtomicChunk> {
+        let short: String = chunk_storage_key(chunk_id).chars().take(16).collect();
+        let json = std::fs::read_to_string(self.cache_dir.join("chunks").join(format!("{}.json", short))).ok()?;
+        serde_json::from_str::<AtomicChunk>(&json).ok().filter(|c| c.chunk_id == chunk_id)
+    }
+
+    /// The chunk's content, and whether it was fetched from the registry
+    async fn content(&self, chunk_id: &str) -> CadiResult<Option<(Vec<u8>, bool)>> {
+        if let Some(graph) = &self.graph {
+            if let Some(content) = graph.get_content(chunk_id)? {
+                return Ok(Some((content, false)));
+            }
+        }
+        match &self.registry {
+            Some(registry) => Ok(Some((registry.fetch_chunk(chunk_id).await?, true))),
+            None => Ok(None),
+        }
+    }
+
+    fn locate(&self, chunk_id: &str) -> CadiResult<Located> {
+        if let Some(node) = match &self.graph {
+            Some(graph) => graph.get_node(chunk_id)?,
+            None => None,
+        } {
+            return Ok(Located {
+                file: node.source_file,
+                lines: node.source_lines,
+                alias: node.primary_alias.or_else(|| node.aliases.into_iter().next()),
+                language: Some(node.language),
+            });
+        }
+        Ok(match self.atomic_chunk(chunk_id) {
+            Some(chunk) => {
+                let source = chunk.sources.first();
+                Located {
+                    file: source.map(|s| s.file.clone()),
+                    lines: source.and_then(|s| Some((s.start_line?, s.end_line?))),
+                    alias: chunk.primary_alias().or(chunk.aliases.first()).map(|a| a.path.clone()),
+                    language: Some(chunk.language),
+                }
+            }
+            None => Located {
+                file: None,
+                lines: None,
+                alias: None,
+                language: None,
+            },
+        })
+    }
+
+    fn write(&self, output_dir: &Path, path: &Path, content: &[u8], on_conflict: OnConflict) -> CadiResult<FileAction> {
+        let target = output_dir.join(path);
+        let existed = target.exists();
+        if existed {
+            if std::fs::read(&target)? == content {
+                return Ok(FileAction::Unchanged);
+            }
+            let resolution = match on_conflict {
+                OnConflict::Overwrite => Resolution::Overwrite,
+                OnConflict::Skip => Resolution::Skip,
+                OnConflict::Ask => self.prompt.as_ref().map_or(Resolution::Skip, |prompt| prompt(path)),
+            };
+            match resolution {
+                Resolution::Overwrite => {}
+                Resolution::Skip => return Ok(FileAction::Skipped),
+                Resolution::KeepBoth => {
+                    let mut name = target.as_os_str().to_owned();
+                    name.push(format!(".{}", KEEP_BOTH_SUFFIX));
+                    std::fs::write(PathBuf::from(name), content)?;
+                    return Ok(FileAction::KeptBoth);
+                }
+            }
+        }
+
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&target, content)?;
+        Ok(if existed { FileAction::Overwritten } else { FileAction::Created })
+    }
+}
+
+/// Join the pieces of a file in line order, leaving out pieces inside an
+/// earlier, larger one (e.g. a function inside its module chunk)
+fn assemble(mut pieces: Vec<Piece>) -> Vec<u8> {
+    if pieces.len() == 1 {
+        return pieces.remove(0).content;
+    }
+    pieces.sort_by_key(|p| p.lines.map(|(start, end)| (start, usize::MAX - end)));
+
+    let mut content = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    let mut covered = 0;
+    for piece in pieces {
+        if !seen.insert(piece.chunk_id.clone()) {
+            continue;
+        }
+        if let Some((_, end)) = piece.lines {
+            if end <= covered {
+                continue;
+            }
+            covered = end;
+        }
+        if !content.is_empty() {
+            if !content.ends_with(b"\n") {
+                content.push(b'\n');
+            }
+            content.push(b'\n');
+        }
+        content.extend_from_slice(&piece.content);
+    }
+    if !content.ends_with(b"\n") {
+        content.push(b'\n');
+    }
+    content
+}
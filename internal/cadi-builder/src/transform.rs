@@ -1,13 +1,19 @@
 //! Transformation engine for CADI
 
-use cadi_core::{CadiError, CadiResult};
-use std::collections::HashMap;
+use cadi_core::{CadiError, CadiResult, TransformSpec};
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
 use std::process::Stdio;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::process::Command;
 use std::path::Path;
 
+/// Scratch directories of external transforms started by this process
+static EXTERNAL_RUNS: AtomicUsize = AtomicUsize::new(0);
+
 /// Transformation type
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
 pub enum TransformType {
     /// Parse source to AST/IR
     Parse { language: String },
@@ -20,7 +26,74 @@ pub enum TransformType {
     /// Containerize
     Containerize { base: String },
     /// Custom transform
-    Custom { name: String, args: HashMap<String, String> },
+    Custom {
+        name: String,
+        #[serde(serialize_with = "sorted_args")]
+        args: HashMap<String, String>,
+    },
+    /// External command declared by a build target. `{input}` and
+    /// `{output}` in its arguments are replaced by file paths.
+    External {
+        command: Vec<String>,
+        /// Environment variables passed through; the rest are cleared
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        env: Vec<String>,
+        /// Exit code of a successful run
+        #[serde(default)]
+        exit_code: i32,
+    },
+}
+
+impl TransformType {
+    /// The transform a manifest's build target declares
+    pub fn from_spec(spec: &TransformSpec) -> Self {
+        let Some(builtin) = &spec.builtin else {
+            return TransformType::External {
+                command: spec.command.clone(),
+                env: spec.env.clone(),
+                exit_code: spec.exit_code,
+            };
+        };
+        let arg = |name: &str, default: &str| spec.args.get(name).cloned().unwrap_or_else(|| default.to_string());
+        match builtin.as_str() {
+            "parse" => TransformType::Parse { language: arg("language", "unknown") },
+            "compile" => TransformType::Compile { target: arg("target", "any") },
+            "link" => TransformType::Link { format: arg("format", "default") },
+            "bundle" => TransformType::Bundle { format: arg("format", "default") },
+            "containerize" => TransformType::Containerize { base: arg("base", "scratch") },
+            name => TransformType::Custom {
+                name: name.to_string(),
+                args: spec.args.clone().into_iter().collect(),
+            },
+        }
+    }
+
+    /// Whether the transform runs a command from the manifest
+    pub fn is_external(&self) -> bool {
+        matches!(self, TransformType::External { .. })
+    }
+}
+
+/// Serialize custom transform args in a stable order, so build receipts
+/// hash the same across runs
+fn sorted_args<S: Serializer>(args: &HashMap<String, String>, serializer: S) -> Result<S::Ok, S::Error> {
+    args.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+impl std::fmt::Display for TransformType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransformType::Parse { language } => write!(f, "parse ({})", language),
+            TransformType::Compile { target } => write!(f, "compile ({})", target),
+            TransformType::Link { format } => write!(f, "link ({})", format),
+            TransformType::Bundle { format } => write!(f, "bundle ({})", format),
+            TransformType::Containerize { base } => write!(f, "containerize ({})", base),
+            TransformType::Custom { name, .. } => write!(f, "{}", name),
+            TransformType::External { command, .. } => {
+                write!(f, "{} (external)", command.first().map_or("", String::as_str))
+            }
+        }
+    }
 }
 
 /// Input to a transformation
@@ -36,6 +109,24 @@ pub struct TransformInput {
     pub path: Option<String>,
 }
 
+/// Output of a transformation
+#[derive(Debug, Clone)]
+pub struct TransformOutput {
+    pub data: Vec<u8>,
+    /// What an external command printed
+    pub log: Option<TransformLog>,
+}
+
+/// Exit code and output of an external transform, kept in its build receipt
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransformLog {
+    pub exit_code: i32,
+    /// Left out when stdout was the transform's output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stdout: Option<String>,
+    pub stderr: String,
+}
+
 /// Transformer for executing transformations
 pub struct Transformer;
 
@@ -51,7 +142,12 @@ impl Transformer {
         transform: &TransformType,
         inputs: &[TransformInput],
     ) -> CadiResult<Vec<u8>> {
-        match transform {
+        Ok(self.run(transform, inputs).await?.data)
+    }
+
+    /// Execute a transformation, keeping what an external command printed
+    pub async fn run(&self, transform: &TransformType, inputs: &[TransformInput]) -> CadiResult<TransformOutput> {
+        let data = match transform {
             TransformType::Parse { language } => {
                 self.execute_parse(language, inputs).await
             }
@@ -70,7 +166,11 @@ impl Transformer {
             TransformType::Custom { name, args } => {
                 self.execute_custom(name, args, inputs).await
             }
-        }
+            TransformType::External { command, env, exit_code } => {
+                return self.execute_external(command, env, *exit_code, inputs).await
+            }
+        }?;
+        Ok(TransformOutput { data, log: None })
     }
 
     async fn execute_parse(&self, language: &str, inputs: &[TransformInput]) -> CadiResult<Vec<u8>> {
@@ -100,7 +200,7 @@ impl Transformer {
             if path.starts_with("file://") {
                 path = path.replace("file://", "");
             }
-            println!("DEBUG: execute_compile path='{}' target='{}'", path, target);
+            tracing::debug!("execute_compile path='{}' target='{}'", path, target);
             
             if target.contains("linux") || target.contains("darwin") || target == "any" {
                 // Real C compilation if it's a C file
@@ -280,6 +380,105 @@ impl Transformer {
     }
 }
 
+impl Transformer {
+    /// Run a command on the main input's file. The output is the file the
+    /// command writes to `{output}`, or its stdout if no argument names
+    /// `{output}`.
+    async fn execute_external(
+        &self,
+        command: &[String],
+        env: &[String],
+        exit_code: i32,
+        inputs: &[TransformInput],
+    ) -> CadiResult<TransformOutput> {
+        let (program, args) = command
+            .split_first()
+            .ok_or_else(|| CadiError::TransformFailed("External transform has no command".to_string()))?;
+        let input = inputs
+            .iter()
+            .find(|i| i.role == "main")
+            .or(inputs.first())
+            .ok_or_else(|| CadiError::TransformFailed("No input provided".to_string()))?;
+        tracing::info!("Running external transform {} on {}", program, input.chunk_id);
+
+        let scratch = std::env::temp_dir().join(format!(
+            "cadi-transform-{}-{}",
+            std::process::id(),
+            EXTERNAL_RUNS.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&scratch)?;
+        let result = self.run_external(program, args, env, exit_code, input, &scratch).await;
+        let _ = std::fs::remove_dir_all(&scratch);
+        result
+    }
+
+    async fn run_external(
+        &self,
+        program: &str,
+        args: &[String],
+        env: &[String],
+        exit_code: i32,
+        input: &TransformInput,
+        scratch: &Path,
+    ) -> CadiResult<TransformOutput> {
+        let input_path = match (&input.path, &input.data) {
+            (Some(path), _) => path.trim_start_matches("file://").to_string(),
+            (None, Some(data)) => {
+                let path = scratch.join("input");
+                std::fs::write(&path, data)?;
+                path.to_string_lossy().to_string()
+            }
+            (None, None) => {
+                return Err(CadiError::TransformFailed(format!(
+                    "Input {} of {} is not in the local cache",
+                    input.chunk_id, program
+                )))
+            }
+        };
+        let output_path = scratch.join("output").to_string_lossy().to_string();
+        let writes_output = args.iter().any(|arg| arg.contains("{output}"));
+
+        let mut cmd = Command::new(program);
+        cmd.args(args.iter().map(|arg| arg.replace("{input}", &input_path).replace("{output}", &output_path)))
+            .env_clear()
+            .stdin(Stdio::null());
+        for name in env {
+            if let Some(value) = std::env::var_os(name) {
+                cmd.env(name, value);
+            }
+        }
+        let output = cmd
+            .output()
+            .await
+            .map_err(|e| CadiError::TransformFailed(format!("Failed to run {}: {}", program, e)))?;
+
+        let code = output.status.code().unwrap_or(-1);
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        if code != exit_code {
+            return Err(CadiError::TransformFailed(format!(
+                "{} exited with {} (expected {}): {}",
+                program,
+                code,
+                exit_code,
+                stderr.trim()
+            )));
+        }
+
+        let (data, stdout) = if writes_output {
+            let data = std::fs::read(&output_path).map_err(|e| {
+                CadiError::TransformFailed(format!("{} wrote no output to {{output}}: {}", program, e))
+            })?;
+            (data, Some(String::from_utf8_lossy(&output.stdout).to_string()))
+        } else {
+            (output.stdout, None)
+        };
+        Ok(TransformOutput {
+            data,
+            log: Some(TransformLog { exit_code: code, stdout, stderr }),
+        })
+    }
+}
+
 impl Default for Transformer {
     fn default() -> Self {
         Self::new()
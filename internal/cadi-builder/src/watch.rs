@@ -0,0 +1,322 @@
+//! Incremental rebuilds for `cadi build --watch`
+//!
+//! The file watcher itself lives in the CLI. This module holds the logic it
+//! drives, so it can be exercised with synthetic change events:
+//!
+//! - [`Debouncer`] coalesces the burst of events an editor save produces.
+//! - [`WatchGraph`] maps watched source files to the manifest nodes built
+//!   from their atoms. Applying re-atomized files updates those nodes' chunk
+//!   IDs, drops nodes whose atoms disappeared, and returns an
+//!   [`Invalidation`] naming the build steps that have to rerun: the changed
+//!   nodes plus everything depending on them through the build graph edges.
+
+use cadi_core::Manifest;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// What happened to a watched file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// Created or written
+    Modified,
+    /// Deleted or moved away
+    Removed,
+}
+
+/// A file system change reported by the watcher
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeEvent {
+    pub path: PathBuf,
+    pub kind: ChangeKind,
+}
+
+impl ChangeEvent {
+    pub fn modified(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), kind: ChangeKind::Modified }
+    }
+
+    pub fn removed(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), kind: ChangeKind::Removed }
+    }
+}
+
+/// Coalesces change events until no new one has arrived for `window`.
+///
+/// Only the latest event per path is kept, so a save that writes a
+/// temporary file and renames it over the original yields one change.
+#[derive(Debug)]
+pub struct Debouncer {
+    window: Duration,
+    pending: BTreeMap<PathBuf, ChangeKind>,
+    last_event: Option<Instant>,
+}
+
+impl Debouncer {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            pending: BTreeMap::new(),
+            last_event: None,
+        }
+    }
+
+    /// Record an event received at `now`
+    pub fn push(&mut self, event: ChangeEvent, now: Instant) {
+        self.pending.insert(event.path, event.kind);
+        self.last_event = Some(now);
+    }
+
+    /// When the pending events settle, if there are any
+    pub fn deadline(&self) -> Option<Instant> {
+        self.last_event
+            .filter(|_| !self.pending.is_empty())
+            .map(|last| last + self.window)
+    }
+
+    /// Take the pending events if they have settled by `now`
+    pub fn take_ready(&mut self, now: Instant) -> Vec<ChangeEvent> {
+        match self.deadline() {
+            Some(deadline) if now >= deadline => {
+                self.last_event = None;
+                std::mem::take(&mut self.pending)
+                    .into_iter()
+                    .map(|(path, kind)| ChangeEvent { path, kind })
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// A manifest node built from an atom of a watched file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchedAtom {
+    /// Build graph node the atom is materialized as
+    pub node_id: String,
+    /// Atom name, stable across edits of the file
+    pub name: String,
+    pub chunk_id: String,
+}
+
+/// An atom produced by re-atomizing a changed file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Atom {
+    pub name: String,
+    pub chunk_id: String,
+}
+
+/// A watched file after a change
+#[derive(Debug, Clone)]
+pub enum FileChange {
+    /// The file was re-atomized into these atoms
+    Reatomized { path: PathBuf, atoms: Vec<Atom> },
+    /// The file is gone
+    Removed(PathBuf),
+}
+
+/// Effect of a batch of file changes on the build graph
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Invalidation {
+    /// Nodes whose chunk changed
+    pub changed: Vec<String>,
+    /// Nodes dropped because their atom disappeared
+    pub dropped: Vec<String>,
+    /// Nodes that only need rebuilding because something they depend on did
+    pub dependents: Vec<String>,
+    /// Atoms with no node in the manifest; `cadi import` adds them
+    pub unmapped: Vec<String>,
+}
+
+impl Invalidation {
+    /// Build steps to rerun: changed nodes and their dependents
+    pub fn dirty(&self) -> BTreeSet<String> {
+        self.changed.iter().chain(&self.dependents).cloned().collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.dropped.is_empty() && self.unmapped.is_empty()
+    }
+}
+
+impl std::fmt::Display for Invalidation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        for (label, nodes) in [
+            ("changed", &self.changed),
+            ("dependent", &self.dependents),
+            ("dropped", &self.dropped),
+            ("unmapped", &self.unmapped),
+        ] {
+            if !nodes.is_empty() {
+                parts.push(format!("{} {} ({})", nodes.len(), label, nodes.join(", ")));
+            }
+        }
+        if parts.is_empty() {
+            f.write_str("no changes")
+        } else {
+            f.write_str(&parts.join(", "))
+        }
+    }
+}
+
+/// Watched files, the manifest nodes built from them and the manifest kept
+/// up to date as they change
+#[derive(Debug, Clone)]
+pub struct WatchGraph {
+    manifest: Manifest,
+    files: BTreeMap<PathBuf, Vec<WatchedAtom>>,
+}
+
+impl WatchGraph {
+    /// Track `atoms`, each produced from the file it is paired with.
+    /// Atoms whose node isn't in the manifest are ignored.
+    pub fn new(manifest: Manifest, atoms: impl IntoIterator<Item = (PathBuf, WatchedAtom)>) -> Self {
+        let nodes: BTreeSet<&str> = manifest.build_graph.nodes.iter().map(|n| n.id.as_str()).collect();
+        let mut files: BTreeMap<PathBuf, Vec<WatchedAtom>> = BTreeMap::new();
+        for (path, atom) in atoms {
+            if nodes.contains(atom.node_id.as_str()) {
+                files.entry(path).or_default().push(atom);
+            }
+        }
+        Self { manifest, files }
+    }
+
+    /// The manifest with every applied change
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    /// Files backing at least one manifest node
+    pub fn watched_files(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(PathBuf::as_path)
+    }
+
+    pub fn is_watched(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    /// Atoms of a watched file
+    pub fn atoms_in(&self, path: &Path) -> &[WatchedAtom] {
+        self.files.get(path).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Apply file changes to the manifest and work out what to rebuild.
+    ///
+    /// Atoms are matched to nodes by name within their file. A matched atom
+    /// with a new chunk ID updates its node; a node whose atom is gone, or
+    /// whose file was removed, is dropped along with its edges.
+    pub fn apply(&mut self, changes: Vec<FileChange>) -> Invalidation {
+        let mut changed = BTreeSet::new();
+        let mut dropped = BTreeSet::new();
+        let mut unmapped = BTreeSet::new();
+        let mut updates: HashMap<String, (String, String)> = HashMap::new();
+
+        for change in changes {
+            match change {
+                FileChange::Removed(path) => {
+                    for atom in self.files.remove(&path).unwrap_or_default() {
+                        dropped.insert(atom.node_id);
+                    }
+                }
+                FileChange::Reatomized { path, atoms } => {
+                    let Some(watched) = self.files.get_mut(&path) else {
+                        continue;
+                    };
+                    let by_name: HashMap<&str, &Atom> = atoms.iter().map(|a| (a.name.as_str(), a)).collect();
+
+                    watched.retain_mut(|existing| match by_name.get(existing.name.as_str()) {
+                        Some(atom) => {
+                            if atom.chunk_id != existing.chunk_id {
+                                changed.insert(existing.node_id.clone());
+                                updates.insert(
+                                    existing.node_id.clone(),
+                                    (existing.chunk_id.clone(), atom.chunk_id.clone()),
+                                );
+                                existing.chunk_id = atom.chunk_id.clone();
+                            }
+                            true
+                        }
+                        None => {
+                            dropped.insert(existing.node_id.clone());
+                            false
+                        }
+                    });
+
+                    let known: BTreeSet<&str> = watched.iter().map(|a| a.name.as_str()).collect();
+                    unmapped.extend(atoms.iter().filter(|a| !known.contains(a.name.as_str())).map(|a| a.name.clone()));
+                    if watched.is_empty() {
+                        self.files.remove(&path);
+                    }
+                }
+            }
+        }
+
+        for node in &mut self.manifest.build_graph.nodes {
+            if let Some((old, new)) = updates.get(&node.id) {
+                if node.source_cadi.as_ref() == Some(old) {
+                    node.source_cadi = Some(new.clone());
+                }
+                for repr in node.representations.iter_mut().filter(|r| &r.chunk == old) {
+                    repr.chunk = new.clone();
+                }
+            }
+        }
+
+        let dependents = self.dependents_of(changed.iter().chain(&dropped), &changed, &dropped);
+        self.drop_nodes(&dropped);
+
+        Invalidation {
+            changed: changed.into_iter().collect(),
+            dropped: dropped.into_iter().collect(),
+            dependents,
+            unmapped: unmapped.into_iter().collect(),
+        }
+    }
+
+    /// Nodes depending, directly or transitively, on `roots`, excluding
+    /// nodes that are themselves changed or dropped
+    fn dependents_of<'a>(
+        &self,
+        roots: impl Iterator<Item = &'a String>,
+        changed: &BTreeSet<String>,
+        dropped: &BTreeSet<String>,
+    ) -> Vec<String> {
+        // Edges point from a node to what it depends on
+        let mut reverse: HashMap<&str, Vec<&str>> = HashMap::new();
+        for edge in &self.manifest.build_graph.edges {
+            reverse.entry(edge.to.as_str()).or_default().push(edge.from.as_str());
+        }
+
+        let mut seen: BTreeSet<&str> = BTreeSet::new();
+        let mut queue: VecDeque<&str> = roots.map(String::as_str).collect();
+        while let Some(node) = queue.pop_front() {
+            for &dependent in reverse.get(node).into_iter().flatten() {
+                if seen.insert(dependent) {
+                    queue.push_back(dependent);
+                }
+            }
+        }
+
+        seen.into_iter()
+            .filter(|node| !changed.contains(*node) && !dropped.contains(*node))
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn drop_nodes(&mut self, dropped: &BTreeSet<String>) {
+        if dropped.is_empty() {
+            return;
+        }
+        let graph = &mut self.manifest.build_graph;
+        graph.nodes.retain(|n| !dropped.contains(&n.id));
+        graph.edges.retain(|e| !dropped.contains(&e.from) && !dropped.contains(&e.to));
+        for target in &mut self.manifest.build_targets {
+            target.nodes.retain(|n| !dropped.contains(&n.id));
+        }
+        for atoms in self.files.values_mut() {
+            atoms.retain(|a| !dropped.contains(&a.node_id));
+        }
+        self.files.retain(|_, atoms| !atoms.is_empty());
+    }
+}
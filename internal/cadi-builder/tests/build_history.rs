@@ -0,0 +1,37 @@
+use cadi_builder::{BuildCache, BuildRecord};
+
+fn record(cached: usize, built: usize) -> BuildRecord {
+    BuildRecord {
+        finished_at: chrono::Utc::now().to_rfc3339(),
+        cached,
+        built,
+        failed: 0,
+    }
+}
+
+#[test]
+fn test_hit_rate_over_last_builds() {
+    let dir = std::env::temp_dir().join(format!("cadi-build-history-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let cache = BuildCache::new(dir.clone());
+
+    assert_eq!(cache.history_stats(10).unwrap().builds, 0);
+    assert_eq!(cache.stats().unwrap().hit_rate, 0.0);
+
+    cache.record_build(&record(0, 4)).unwrap();
+    cache.record_build(&record(3, 1)).unwrap();
+    cache.record_build(&record(4, 0)).unwrap();
+
+    let all = cache.history_stats(10).unwrap();
+    assert_eq!(all.builds, 3);
+    assert_eq!((all.cached, all.built), (7, 5));
+    assert!((all.hit_rate - 7.0 / 12.0).abs() < 1e-9);
+    assert!((cache.stats().unwrap().hit_rate - all.hit_rate).abs() < 1e-9);
+
+    let recent = cache.history_stats(2).unwrap();
+    assert_eq!(recent.builds, 2);
+    assert!((recent.hit_rate - 7.0 / 8.0).abs() < 1e-9);
+    assert_eq!(cache.build_history(1).unwrap()[0].cached, 4);
+
+    let _ = std::fs::remove_dir_all(dir);
+}
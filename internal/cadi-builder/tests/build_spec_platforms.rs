@@ -0,0 +1,100 @@
+use cadi_builder::dependency_resolver::{platform_matches, resolve_target_triple};
+use cadi_builder::{BuildSpec, BuildSpecValidator, CbsCompiler};
+use cadi_registry::search::SearchEngine;
+use std::sync::Arc;
+
+const SPEC: &str = r#"
+version: "1.0"
+project:
+  name: "notes"
+  language: rust
+
+components:
+  - id: core
+    source: "chunk:sha256:core"
+
+  - id: win-tray
+    source: "chunk:sha256:tray"
+    platforms: [windows]
+
+  - id: tray-menu
+    generate: true
+    description: "Menu for the tray icon"
+    depends_on: [win-tray]
+    optional: true
+
+  - id: spellcheck
+    query: "spell checker"
+    optional: true
+
+targets:
+  - name: desktop
+    components: [core, win-tray, tray-menu, spellcheck]
+  - name: web
+    platform: wasm32-unknown-unknown
+    components: [core]
+"#;
+
+#[test]
+fn test_platform_matching() {
+    assert!(platform_matches("windows", "x86_64-pc-windows-msvc"));
+    assert!(platform_matches("macos", "aarch64-apple-darwin"));
+    assert!(platform_matches("x86_64-linux", "x86_64-unknown-linux-gnu"));
+    assert!(platform_matches("any", "wasm32-unknown-unknown"));
+    assert!(!platform_matches("windows", "x86_64-unknown-linux-gnu"));
+    assert!(!platform_matches("aarch64-linux", "x86_64-unknown-linux-gnu"));
+
+    let spec: BuildSpec = serde_yaml::from_str(SPEC).unwrap();
+    assert_eq!(resolve_target_triple(&spec, Some("web")), "wasm32-unknown-unknown");
+    assert_eq!(resolve_target_triple(&spec, Some("x86_64-pc-windows-msvc")), "x86_64-pc-windows-msvc");
+}
+
+#[tokio::test]
+async fn test_components_gated_by_platform() {
+    let spec: BuildSpec = serde_yaml::from_str(SPEC).unwrap();
+    assert!(BuildSpecValidator::validate(&spec).unwrap().is_empty());
+    let compiler = CbsCompiler::new(Arc::new(SearchEngine::new()));
+
+    let linux = compiler.compile_for_target(spec.clone(), "x86_64-unknown-linux-gnu").await.unwrap();
+    let skipped: Vec<&str> = linux.skipped.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(skipped, ["win-tray", "tray-menu", "spellcheck"]);
+    assert!(linux.skipped[0].reason.contains("platforms: windows"));
+    assert!(linux.skipped[1].reason.contains("'win-tray'"));
+    let nodes: Vec<&str> = linux.manifest.build_graph.nodes.iter().map(|n| n.id.as_str()).collect();
+    assert_eq!(nodes, ["core"]);
+    assert!(linux.manifest.build_graph.edges.is_empty());
+    let desktop = linux.manifest.find_target("desktop").unwrap();
+    assert_eq!(desktop.nodes.len(), 1);
+
+    let windows = compiler.compile_for_target(spec, "x86_64-pc-windows-msvc").await.unwrap();
+    let skipped: Vec<&str> = windows.skipped.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(skipped, ["spellcheck"]);
+    assert_eq!(windows.manifest.build_graph.nodes.len(), 3);
+}
+
+#[tokio::test]
+async fn test_required_components_must_resolve() {
+    // A required component depending on a Windows-only one
+    let spec: BuildSpec = serde_yaml::from_str(&SPEC.replace("    optional: true\n\n  - id: spellcheck", "\n  - id: spellcheck")).unwrap();
+    let compiler = CbsCompiler::new(Arc::new(SearchEngine::new()));
+    let err = compiler.compile_for_target(spec, "x86_64-unknown-linux-gnu").await.err().unwrap();
+    assert!(err.to_string().contains("'tray-menu' depends on 'win-tray'"));
+
+    // A required search with no results
+    let spec: BuildSpec = serde_yaml::from_str(&SPEC.replace("    optional: true\n\ntargets", "\ntargets")).unwrap();
+    assert!(compiler.compile_for_target(spec, "x86_64-pc-windows-msvc").await.is_err());
+
+    // Unresolvable references are errors for required components and
+    // warnings for optional ones
+    let mut spec: BuildSpec = serde_yaml::from_str(SPEC).unwrap();
+    let dangling = SPEC.replace("depends_on: [win-tray]", "depends_on: [missing]");
+    let warnings = BuildSpecValidator::validate(&serde_yaml::from_str(&dangling).unwrap()).unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("'tray-menu' depends on undefined 'missing'"));
+
+    if let cadi_builder::ComponentSpec::Generate(menu) = &mut spec.components[2] {
+        menu.optional = false;
+        menu.depends_on = Some(vec!["missing".to_string()]);
+    }
+    assert!(BuildSpecValidator::validate(&spec).is_err());
+}
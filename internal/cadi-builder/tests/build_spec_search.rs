@@ -0,0 +1,150 @@
+use async_trait::async_trait;
+use cadi_builder::dependency_resolver::ComponentSearch;
+use cadi_builder::{BuildSpec, BuildSpecValidator, CbsCompiler};
+use cadi_core::CadiResult;
+use cadi_registry::{ChunkSummary, SearchQuery};
+use std::sync::Arc;
+
+/// A registry holding a fixed set of chunks: (id, name, language, concepts)
+struct FakeRegistry(Vec<(&'static str, &'static str, &'static str, &'static [&'static str])>);
+
+#[async_trait]
+impl ComponentSearch for FakeRegistry {
+    async fn search(&self, _query: &SearchQuery) -> CadiResult<Vec<(ChunkSummary, String)>> {
+        Ok(self
+            .0
+            .iter()
+            .map(|(id, name, language, concepts)| {
+                let chunk = ChunkSummary {
+                    chunk_id: id.to_string(),
+                    name: name.to_string(),
+                    cadi_type: "source".to_string(),
+                    concepts: concepts.iter().map(|c| c.to_string()).collect(),
+                    description: None,
+                    language: Some(language.to_string()),
+                    size: None,
+                };
+                (chunk, "fake".to_string())
+            })
+            .collect())
+    }
+}
+
+fn registry() -> FakeRegistry {
+    FakeRegistry(vec![
+        ("chunk:sha256:lru", "lru cache", "rust", &["caching"]),
+        ("chunk:sha256:ttl", "ttl cache", "rust", &["caching", "expiry"]),
+        ("chunk:sha256:pycache", "lru cache", "python", &["caching"]),
+        ("chunk:sha256:queue", "job queue", "rust", &[]),
+        ("chunk:sha256:cronparse", "cron parser", "rust", &[]),
+    ])
+}
+
+const SPEC: &str = r#"
+version: "1.0"
+project:
+  name: "api"
+  language: rust
+
+components:
+  - id: cache
+    query: "lru cache"
+    concepts: [caching]
+
+  - id: scheduler
+    query: "cron scheduler"
+    min_score: 0.8
+    fallback: scheduler-gen
+
+  - id: scheduler-gen
+    generate: true
+    description: "Cron-style job scheduler"
+
+  - id: handlers
+    generate: true
+    description: "HTTP handlers"
+    depends_on: [cache, scheduler]
+
+targets:
+  - name: server
+    components: [cache, scheduler, handlers]
+"#;
+
+#[tokio::test]
+async fn test_search_pins_best_match() {
+    let spec: BuildSpec = serde_yaml::from_str(SPEC).unwrap();
+    assert!(BuildSpecValidator::validate(&spec).unwrap().is_empty());
+
+    let plan = BuildSpecValidator::to_build_plan(spec, &registry(), "x86_64-unknown-linux-gnu").await.unwrap();
+    let cache = plan.reuse_components.iter().find(|r| r.id == "cache").unwrap();
+    assert_eq!(cache.chunk_id, "chunk:sha256:lru");
+
+    // The query and the runners-up are kept for auditing; chunks in other
+    // languages are not considered
+    let search = cache.search.as_ref().unwrap();
+    assert_eq!(search.query, "lru cache");
+    assert_eq!(search.language.as_deref(), Some("rust"));
+    let alternatives: Vec<&str> = search.alternatives().iter().map(|c| c.chunk_id.as_str()).collect();
+    assert_eq!(alternatives, ["chunk:sha256:ttl", "chunk:sha256:queue", "chunk:sha256:cronparse"]);
+    assert!(search.candidates.iter().all(|c| c.registry == "fake"));
+}
+
+#[tokio::test]
+async fn test_search_falls_back_to_generate() {
+    let spec: BuildSpec = serde_yaml::from_str(SPEC).unwrap();
+
+    let plan = BuildSpecValidator::to_build_plan(spec.clone(), &registry(), "x86_64-unknown-linux-gnu").await.unwrap();
+    assert!(plan.reuse_components.iter().all(|r| r.id != "scheduler"));
+    let fallback = plan.generate_components.iter().find(|g| g.id == "scheduler-gen").unwrap();
+    assert_eq!(fallback.search.as_ref().unwrap().query, "cron scheduler");
+    let handlers = plan.generate_components.iter().find(|g| g.id == "handlers").unwrap();
+    assert_eq!(handlers.depends_on, ["cache", "scheduler-gen"]);
+    assert_eq!(plan.targets[0].components, ["cache", "scheduler-gen", "handlers"]);
+
+    let compiled = CbsCompiler::new(Arc::new(registry()))
+        .compile_for_target(spec.clone(), "x86_64-unknown-linux-gnu")
+        .await
+        .unwrap();
+    let nodes: Vec<&str> = compiled.manifest.build_graph.nodes.iter().map(|n| n.id.as_str()).collect();
+    assert_eq!(nodes, ["cache", "scheduler-gen", "handlers"]);
+    assert!(compiled.manifest.build_graph.edges.iter().any(|e| e.to == "scheduler-gen"));
+
+    // A fallback whose search is found on the registry isn't built
+    let mut found = registry();
+    found.0.push(("chunk:sha256:cron", "cron scheduler", "rust", &[]));
+    let plan = BuildSpecValidator::to_build_plan(spec, &found, "x86_64-unknown-linux-gnu").await.unwrap();
+    assert!(plan.generate_components.iter().all(|g| g.id != "scheduler-gen"));
+    assert_eq!(plan.skipped[0].id, "scheduler-gen");
+    assert!(plan.skipped[0].reason.contains("found on the registry"));
+}
+
+#[tokio::test]
+async fn test_unmatched_search_lists_near_misses() {
+    let spec: BuildSpec = serde_yaml::from_str(&SPEC.replace("    fallback: scheduler-gen\n", "")).unwrap();
+
+    let err = BuildSpecValidator::to_build_plan(spec, &registry(), "x86_64-unknown-linux-gnu").await.err().unwrap();
+    let message = err.to_string();
+    assert!(message.contains("'scheduler'"));
+    assert!(message.contains("near misses: cron parser (chunk:sha256:cronparse, score 0.50)"));
+
+    // Optional searches are skipped instead
+    let spec: BuildSpec = serde_yaml::from_str(
+        &SPEC
+            .replace("    fallback: scheduler-gen\n", "    optional: true\n")
+            .replace("depends_on: [cache, scheduler]", "depends_on: [cache]"),
+    )
+    .unwrap();
+    let plan = BuildSpecValidator::to_build_plan(spec, &registry(), "x86_64-unknown-linux-gnu").await.unwrap();
+    assert_eq!(plan.skipped[0].id, "scheduler");
+    assert!(plan.skipped[0].reason.contains("scored at least 0.80"));
+}
+
+#[test]
+fn test_fallback_must_be_generate() {
+    let spec: BuildSpec = serde_yaml::from_str(&SPEC.replace("fallback: scheduler-gen", "fallback: cache")).unwrap();
+    let err = BuildSpecValidator::validate(&spec).unwrap_err();
+    assert!(err.to_string().contains("not a generate component"));
+
+    let spec: BuildSpec = serde_yaml::from_str(&SPEC.replace("min_score: 0.8", "min_score: 2.0")).unwrap();
+    assert!(BuildSpecValidator::validate(&spec).is_err());
+}
@@ -0,0 +1,49 @@
+use cadi_builder::{BuildConfig, BuildEngine};
+use cadi_core::validator::Validator;
+use cadi_core::{ManifestGenerator, ProjectAnalyzer};
+use std::path::PathBuf;
+
+fn todo_suite() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../examples/todo-suite").canonicalize().unwrap()
+}
+
+#[tokio::test]
+async fn test_generated_todo_suite_manifest_validates_and_builds() {
+    let result = ProjectAnalyzer::default().import_project(&todo_suite()).unwrap();
+    let generator = ManifestGenerator::new(&result);
+    assert_eq!(generator.file_name(), "todo-suite.cadi.yaml");
+
+    // The manifest as written to disk passes `cadi validate` without findings
+    let yaml = serde_yaml::to_string(&generator.generate()).unwrap();
+    let report = Validator::new().validate_manifest_source(&yaml, None);
+    assert!(report.diagnostics.is_empty(), "{:?}", report.diagnostics);
+    let manifest = report.manifest.unwrap();
+
+    // Nodes are named by alias; hashes only address their sources
+    assert!(manifest.build_graph.nodes.iter().all(|n| !n.id.starts_with("chunk:")));
+    assert!(manifest.build_graph.nodes.iter().all(|n| n.source_cadi.as_deref().is_some_and(|s| s.starts_with("chunk:"))));
+
+    let dev = manifest.find_target("dev").unwrap();
+    assert_eq!(dev.nodes.len(), manifest.build_graph.nodes.len());
+    assert!(dev.toolchain.iter().any(|tool| tool == "cargo"));
+    for entry in ["todo-cli", "todo-server"] {
+        let target = manifest.find_target(entry).unwrap_or_else(|| panic!("no target for {}", entry));
+        assert_eq!(target.platform, "native");
+        assert_eq!(target.toolchain, ["cargo", "rustc"]);
+    }
+
+    let dir = std::env::temp_dir().join(format!("cadi-generated-manifest-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let engine = BuildEngine::new(BuildConfig {
+        cache_dir: dir.clone(),
+        use_remote_cache: false,
+        ..Default::default()
+    });
+    for target in &manifest.build_targets {
+        let built = engine.build(&manifest, &target.name).await.unwrap();
+        assert!(built.failed.is_empty(), "{}: {:?}", target.name, built.failed);
+        assert_eq!(built.built.len() + built.cached.len(), target.nodes.len());
+    }
+
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,71 @@
+use cadi_builder::{BuildCache, BuildPlan, StepStatus};
+use cadi_core::Manifest;
+
+const LIB: &str = "chunk:sha256:1111111111111111111111111111111111111111111111111111111111111111";
+const APP_V1: &str = "chunk:sha256:2222222222222222222222222222222222222222222222222222222222222222";
+const APP_V2: &str = "chunk:sha256:3333333333333333333333333333333333333333333333333333333333333333";
+
+fn manifest(app_chunk: &str) -> Manifest {
+    serde_yaml::from_str(&format!(
+        r#"
+manifest_id: app
+manifest_version: "1.0"
+application:
+  name: app
+build_graph:
+  nodes:
+    - id: lib
+      representations:
+        - form: source
+          chunk: {LIB}
+    - id: app
+      representations:
+        - form: source
+          chunk: {app_chunk}
+  edges:
+    - from: app
+      to: lib
+build_targets:
+  - name: dev
+    platform: any
+"#
+    ))
+    .unwrap()
+}
+
+#[test]
+fn test_annotate_with_cache_marks_cached_rebuild_and_new() {
+    let dir = std::env::temp_dir().join(format!("cadi-plan-diff-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let cache = BuildCache::new(dir.clone());
+
+    // Nothing built yet
+    let plan = BuildPlan::from_manifest(&manifest(APP_V1), "dev").unwrap();
+    let annotated = plan.annotate_with_cache(&cache);
+    assert!(annotated.steps.iter().all(|s| s.status == StepStatus::New));
+    assert_eq!(annotated.summary.to_string(), "0 to rebuild, 0 cached, 2 new");
+
+    // Simulate a successful build of v1
+    for step in &plan.steps {
+        let inputs: Vec<String> = step.inputs.iter().map(|i| i.chunk_id.clone()).collect();
+        cache.record_step(&step.name, &inputs).unwrap();
+        cache.store(step.chunk_id.as_ref().unwrap(), b"built").unwrap();
+    }
+    let annotated = plan.annotate_with_cache(&cache);
+    assert_eq!(annotated.summary.cached, 2);
+
+    // Changing the app source rebuilds only the app
+    let plan = BuildPlan::from_manifest(&manifest(APP_V2), "dev").unwrap();
+    let annotated = plan.annotate_with_cache(&cache);
+    let status = |name: &str| annotated.steps.iter().find(|s| s.name == name).unwrap();
+    assert_eq!(status("lib").status, StepStatus::Cached);
+    assert_eq!(status("app").status, StepStatus::Rebuild);
+    assert_eq!(status("app").changed_inputs, vec![APP_V2.to_string()]);
+    assert_eq!(annotated.summary.to_string(), "1 to rebuild, 1 cached");
+
+    let json = serde_json::to_value(&annotated).unwrap();
+    assert_eq!(json["summary"]["rebuild"], 1);
+    assert_eq!(json["steps"][1]["status"], "rebuild");
+
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,135 @@
+use cadi_builder::{BuildCache, BuildConfig, BuildEngine, BuildReceipt, Provenance, ReceiptInput, Replay, TransformType};
+use cadi_core::Manifest;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+fn manifest() -> Manifest {
+    serde_yaml::from_str(
+        r#"
+manifest_id: app
+manifest_version: "1.0"
+application:
+  name: app
+build_graph:
+  nodes:
+    - id: lib
+      representations:
+        - form: source
+          chunk: chunk:sha256:1111111111111111111111111111111111111111111111111111111111111111
+    - id: app
+      representations:
+        - form: source
+          chunk: chunk:sha256:2222222222222222222222222222222222222222222222222222222222222222
+  edges:
+    - from: app
+      to: lib
+build_targets:
+  - name: dev
+    platform: any
+"#,
+    )
+    .unwrap()
+}
+
+fn cache_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cadi-receipts-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn receipt(started_at: &str, args: &[(&str, &str)]) -> BuildReceipt {
+    BuildReceipt {
+        step: "app".to_string(),
+        transform: TransformType::Custom {
+            name: "pack".to_string(),
+            args: args.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<HashMap<_, _>>(),
+        },
+        inputs: vec![ReceiptInput {
+            chunk_id: "chunk:sha256:aa".to_string(),
+            role: "main".to_string(),
+            hash: Some("aa".to_string()),
+        }],
+        toolchain: BTreeMap::from([("rustc".to_string(), "rustc 1.80.0".to_string())]),
+        output: "chunk:sha256:bb".to_string(),
+        output_hash: "bb".to_string(),
+        log: None,
+        started_at: started_at.to_string(),
+        finished_at: started_at.to_string(),
+    }
+}
+
+#[test]
+fn test_receipt_id_ignores_timestamps() {
+    let args = [("a", "1"), ("b", "2"), ("c", "3"), ("d", "4")];
+    let first = receipt("2026-01-01T00:00:00Z", &args);
+    let later = receipt("2026-06-01T12:00:00Z", &[("d", "4"), ("c", "3"), ("b", "2"), ("a", "1")]);
+    assert_eq!(first.receipt_id(), later.receipt_id());
+    assert!(first.receipt_id().starts_with("chunk:sha256:"));
+
+    let mut other_input = first.clone();
+    other_input.inputs[0].hash = Some("cc".to_string());
+    assert_ne!(first.receipt_id(), other_input.receipt_id());
+
+    let lineage = first.lineage();
+    assert_eq!(lineage.parents, vec!["chunk:sha256:aa"]);
+    assert_eq!(lineage.build_receipt, Some(first.receipt_id()));
+
+    let drift = first.toolchain_drift(&BTreeMap::from([("rustc".to_string(), "rustc 1.81.0".to_string())]));
+    assert_eq!(drift, vec![("rustc".to_string(), "rustc 1.80.0".to_string(), "rustc 1.81.0".to_string())]);
+}
+
+#[tokio::test]
+async fn test_build_records_receipts_that_replay() {
+    let dir = cache_dir("replay");
+    let engine = BuildEngine::new(BuildConfig {
+        cache_dir: dir.clone(),
+        use_remote_cache: false,
+        ..Default::default()
+    });
+    let result = engine.build(&manifest(), "dev").await.unwrap();
+    assert_eq!(result.built.len(), 2);
+
+    // The app's receipt names lib's output as its dependency input
+    let cache = BuildCache::new(dir.clone());
+    let receipts: Vec<BuildReceipt> = walk(&dir.join("receipts"))
+        .into_iter()
+        .map(|path| serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap())
+        .collect();
+    assert_eq!(receipts.len(), 2);
+    let lib = receipts.iter().find(|r| r.step == "lib").unwrap();
+    let app = receipts.iter().find(|r| r.step == "app").unwrap();
+    let dependency = app.inputs.iter().find(|i| i.role == "dependency").unwrap();
+    assert_eq!(dependency.chunk_id, lib.output);
+    assert!(dependency.hash.is_some());
+
+    let provenance = Provenance::trace(&cache, &app.output, 10).unwrap();
+    assert_eq!(provenance.receipt_id, Some(app.receipt_id()));
+    let lib_node = provenance.parents.iter().find(|p| p.chunk_id == lib.output).unwrap();
+    assert_eq!(lib_node.receipt_id, Some(lib.receipt_id()));
+    assert!(Provenance::trace(&cache, &app.output, 1).unwrap().parents.iter().all(|p| p.receipt.is_none()));
+
+    assert_eq!(engine.replay(app).await.unwrap(), Replay::Reproduced);
+
+    // A tampered dependency can no longer reproduce the build
+    std::fs::write(cache.get_path(&lib.output), b"tampered").unwrap();
+    assert!(matches!(engine.replay(app).await.unwrap(), Replay::Unavailable(_)));
+
+    let mut forged = lib.clone();
+    forged.output_hash = "0".repeat(64);
+    assert!(matches!(engine.replay(&forged).await.unwrap(), Replay::Differs { .. }));
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+fn walk(dir: &std::path::Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files.extend(walk(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files
+}
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use cadi_builder::{BuildConfig, BuildEngine};
+use cadi_core::Manifest;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+type Store = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+type Usage = Arc<Mutex<Vec<serde_json::Value>>>;
+
+/// Start a mock registry serving `GET/HEAD/PUT /cache/:key` from memory and
+/// collecting the reports posted to `/v1/usage`
+async fn mock_cache_server(store: Store, usage: Usage) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut socket, _)) = listener.accept().await else {
+                break;
+            };
+            let store = store.clone();
+            let usage = usage.clone();
+            tokio::spawn(async move {
+                // Read headers, then the body up to content-length
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 8192];
+                let header_end = loop {
+                    let n = socket.read(&mut chunk).await.unwrap_or(0);
+                    if n == 0 {
+                        return;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                    if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break pos + 4;
+                    }
+                };
+                let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+                let content_length = head
+                    .lines()
+                    .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().to_string()))
+                    .and_then(|v| v.parse::<usize>().ok())
+                    .unwrap_or(0);
+                while buf.len() < header_end + content_length {
+                    let n = socket.read(&mut chunk).await.unwrap_or(0);
+                    if n == 0 {
+                        break;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+
+                let mut parts = head.split_whitespace();
+                let method = parts.next().unwrap_or("").to_string();
+                let path = parts.next().unwrap_or("").to_string();
+                let key = path.strip_prefix("/cache/").unwrap_or("").to_string();
+
+                let (status, body) = match method.as_str() {
+                    "POST" if path == "/v1/usage" => {
+                        usage.lock().unwrap().push(serde_json::from_slice(&buf[header_end..]).unwrap());
+                        ("202 Accepted", Vec::new())
+                    }
+                    "PUT" => {
+                        store.lock().unwrap().insert(key, buf[header_end..].to_vec());
+                        ("201 Created", Vec::new())
+                    }
+                    "GET" | "HEAD" => match store.lock().unwrap().get(&key) {
+                        Some(data) if method == "GET" => ("200 OK", data.clone()),
+                        Some(_) => ("200 OK", Vec::new()),
+                        None => ("404 Not Found", Vec::new()),
+                    },
+                    _ => ("405 Method Not Allowed", Vec::new()),
+                };
+
+                let response = format!(
+                    "HTTP/1.1 {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.write_all(&body).await;
+                let _ = socket.shutdown().await;
+            });
+        }
+    });
+
+    url
+}
+
+fn manifest(chunk: &str) -> Manifest {
+    serde_yaml::from_str(&format!(
+        r#"
+manifest_id: app
+manifest_version: "1.0"
+application:
+  name: app
+build_graph:
+  nodes:
+    - id: tool
+      representations:
+        - form: binary
+          chunk: {chunk}
+build_targets:
+  - name: dev
+    platform: any
+"#
+    ))
+    .unwrap()
+}
+
+fn config(name: &str, remote: &str, read_only: bool) -> BuildConfig {
+    let cache_dir = std::env::temp_dir().join(format!("cadi-remote-cache-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&cache_dir);
+    BuildConfig {
+        cache_dir,
+        remote_cache_url: Some(remote.to_string()),
+        remote_cache_read_only: read_only,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_second_build_is_served_from_remote_cache() {
+    let store = Store::default();
+    let remote = mock_cache_server(store.clone(), Usage::default()).await;
+    let manifest = manifest("chunk:sha256:4444444444444444444444444444444444444444444444444444444444444444");
+    let config = config("layered", &remote, false);
+
+    let first = BuildEngine::new(config.clone()).build(&manifest, "dev").await.unwrap();
+    assert_eq!(first.built.len(), 1);
+    assert!(first.cached.is_empty());
+    assert_eq!(store.lock().unwrap().len(), 1);
+
+    // A fresh machine: nothing cached locally
+    std::fs::remove_dir_all(&config.cache_dir).unwrap();
+
+    let second = BuildEngine::new(config.clone()).build(&manifest, "dev").await.unwrap();
+    assert!(second.built.is_empty());
+    assert_eq!(second.cached, first.built);
+    assert_eq!(second.remote_cached, first.built);
+
+    // The remote hit populated the local cache
+    let third = BuildEngine::new(config.clone()).build(&manifest, "dev").await.unwrap();
+    assert_eq!(third.cached.len(), 1);
+    assert!(third.remote_cached.is_empty());
+
+    let _ = std::fs::remove_dir_all(&config.cache_dir);
+}
+
+#[tokio::test]
+async fn test_read_only_remote_cache_is_not_populated() {
+    let store = Store::default();
+    let remote = mock_cache_server(store.clone(), Usage::default()).await;
+    let manifest = manifest("chunk:sha256:5555555555555555555555555555555555555555555555555555555555555555");
+    let config = config("read-only", &remote, true);
+
+    let result = BuildEngine::new(config.clone()).build(&manifest, "dev").await.unwrap();
+    assert_eq!(result.built.len(), 1);
+    assert!(store.lock().unwrap().is_empty());
+
+    let _ = std::fs::remove_dir_all(&config.cache_dir);
+}
+
+#[tokio::test]
+async fn test_full_builds_report_chunk_usage() {
+    let store = Store::default();
+    let usage = Usage::default();
+    let remote = mock_cache_server(store.clone(), usage.clone()).await;
+    let chunk = "chunk:sha256:6666666666666666666666666666666666666666666666666666666666666666";
+    let config = config("usage", &remote, true);
+    let engine = BuildEngine::new(config.clone());
+
+    engine.build(&manifest(chunk), "dev").await.unwrap();
+    assert_eq!(*usage.lock().unwrap(), vec![serde_json::json!({ "chunks": [chunk] })]);
+
+    // Rebuilding invalidated steps is not a new use
+    let steps = std::collections::BTreeSet::from(["tool".to_string()]);
+    engine.rebuild(&manifest(chunk), "dev", &steps).await.unwrap();
+    assert_eq!(usage.lock().unwrap().len(), 1);
+
+    let _ = std::fs::remove_dir_all(&config.cache_dir);
+}
@@ -0,0 +1,173 @@
+use cadi_builder::scaffold::{FileAction, OnConflict, Resolution, Scaffolder};
+use cadi_core::graph::{GraphNode, GraphStore};
+use cadi_core::{AtomicChunk, ChunkAlias, Manifest, SourceLocation};
+use cadi_registry::{RegistryClient, RegistryConfig};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+fn manifest(nodes: &[(&str, &str)]) -> Manifest {
+    let nodes: Vec<serde_json::Value> = nodes
+        .iter()
+        .map(|(id, chunk)| serde_json::json!({"id": id, "representations": [{"form": "source", "chunk": chunk}]}))
+        .collect();
+    serde_json::from_value(serde_json::json!({
+        "manifest_id": "app",
+        "manifest_version": "1.0",
+        "application": {"name": "app"},
+        "build_graph": {"nodes": nodes},
+    }))
+    .unwrap()
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cadi-scaffold-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A graph with two functions cut from `src/lib.rs` and one chunk known
+/// only by its alias
+fn graph() -> GraphStore {
+    let graph = GraphStore::in_memory().unwrap();
+    for (chunk_id, content, node) in [
+        ("chunk:sha256:b", "fn second() {}", GraphNode::new("chunk:sha256:b", "b").with_language("rust").with_source("src/lib.rs", 5, 5)),
+        ("chunk:sha256:a", "fn first() {}", GraphNode::new("chunk:sha256:a", "a").with_language("rust").with_source("src/lib.rs", 1, 3)),
+        ("chunk:sha256:c", "export const x = 1;", GraphNode::new("chunk:sha256:c", "c").with_language("typescript").with_alias("app/util")),
+    ] {
+        graph.insert_node(&node).unwrap();
+        graph.store_content(chunk_id, content.as_bytes()).unwrap();
+    }
+    graph
+}
+
+#[tokio::test]
+async fn test_scaffold_rebuilds_layout_from_graph() {
+    let cache = temp_dir("layout-cache");
+    let output = temp_dir("layout-out");
+    let scaffolder = Scaffolder::new(&cache).with_graph(graph());
+
+    let manifest = manifest(&[
+        ("second", "chunk:sha256:b"),
+        ("first", "chunk:sha256:a"),
+        ("util", "app/util"),
+        ("gone", "chunk:sha256:d"),
+    ]);
+    let report = scaffolder.scaffold(&manifest, &output, OnConflict::Skip).await.unwrap();
+
+    assert_eq!(std::fs::read_to_string(output.join("src/lib.rs")).unwrap(), "fn first() {}\n\nfn second() {}\n");
+    assert_eq!(std::fs::read_to_string(output.join("app/util.ts")).unwrap(), "export const x = 1;");
+    assert_eq!(report.count(FileAction::Created), 2);
+    assert_eq!(report.unresolved.len(), 1);
+    assert_eq!(report.unresolved[0].node, "gone");
+    assert!(report.fetched.is_empty());
+
+    // Scaffolding again finds the files already in place
+    let report = scaffolder.scaffold(&manifest, &output, OnConflict::Overwrite).await.unwrap();
+    assert_eq!(report.count(FileAction::Unchanged), 2);
+    assert_eq!(report.written(), 0);
+}
+
+#[tokio::test]
+async fn test_scaffold_conflict_handling() {
+    let cache = temp_dir("conflict-cache");
+    let output = temp_dir("conflict-out");
+    let manifest = manifest(&[("util", "chunk:sha256:c")]);
+    let existing = output.join("app/util.ts");
+    std::fs::create_dir_all(existing.parent().unwrap()).unwrap();
+    std::fs::write(&existing, "// local edits").unwrap();
+
+    let scaffolder = Scaffolder::new(&cache).with_graph(graph());
+    let report = scaffolder.scaffold(&manifest, &output, OnConflict::Skip).await.unwrap();
+    assert_eq!(report.files[0].action, FileAction::Skipped);
+    assert_eq!(std::fs::read_to_string(&existing).unwrap(), "// local edits");
+
+    // Without a prompt, asking skips
+    let report = scaffolder.scaffold(&manifest, &output, OnConflict::Ask).await.unwrap();
+    assert_eq!(report.files[0].action, FileAction::Skipped);
+
+    let scaffolder = scaffolder.with_prompt(Box::new(|path: &Path| {
+        assert_eq!(path, Path::new("app/util.ts"));
+        Resolution::KeepBoth
+    }));
+    let report = scaffolder.scaffold(&manifest, &output, OnConflict::Ask).await.unwrap();
+    assert_eq!(report.files[0].action, FileAction::KeptBoth);
+    assert_eq!(std::fs::read_to_string(&existing).unwrap(), "// local edits");
+    assert_eq!(std::fs::read_to_string(output.join("app/util.ts.cadi-new")).unwrap(), "export const x = 1;");
+
+    let report = scaffolder.scaffold(&manifest, &output, OnConflict::Overwrite).await.unwrap();
+    assert_eq!(report.files[0].action, FileAction::Overwritten);
+    assert_eq!(std::fs::read_to_string(&existing).unwrap(), "export const x = 1;");
+}
+
+/// Serve chunks `e` and `f` from `GET /v1/chunks/:chunk_id`
+async fn mock_registry() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut buf = [0u8; 4096];
+            let n = socket.read(&mut buf).await.unwrap_or(0);
+            let head = String::from_utf8_lossy(&buf[..n]).to_string();
+            let (status, body) = if head.starts_with("GET /v1/chunks/chunk:sha256:e ") {
+                ("200 OK", "def fetched():\n    pass\n")
+            } else if head.starts_with("GET /v1/chunks/chunk:sha256:f ") {
+                ("200 OK", "x = 1\n")
+            } else {
+                ("404 Not Found", "")
+            };
+            let response = format!("HTTP/1.1 {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}", status, body.len(), body);
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    });
+    url
+}
+
+#[tokio::test]
+async fn test_scaffold_fetches_missing_chunks() {
+    let cache = temp_dir("fetch-cache");
+    let output = temp_dir("fetch-out");
+
+    // Imported chunk metadata says where the chunk came from
+    let mut chunk = AtomicChunk::new("chunk:sha256:e".to_string(), "fetched".to_string(), "python".to_string(), "e".to_string(), 24);
+    chunk.sources.push(SourceLocation {
+        file: "pkg/fetched.py".to_string(),
+        file_bytes: None,
+        start_line: Some(1),
+        end_line: Some(2),
+        start_col: None,
+        end_col: None,
+    });
+    std::fs::create_dir_all(cache.join("chunks")).unwrap();
+    std::fs::write(cache.join("chunks").join("e.json"), serde_json::to_string(&chunk).unwrap()).unwrap();
+
+    let mut escaping = AtomicChunk::new("chunk:sha256:f".to_string(), "escaping".to_string(), "python".to_string(), "f".to_string(), 0);
+    escaping.aliases.push(ChunkAlias::new("../outside"));
+    escaping.sources.push(SourceLocation {
+        file: "../outside.py".to_string(),
+        file_bytes: None,
+        start_line: None,
+        end_line: None,
+        start_col: None,
+        end_col: None,
+    });
+    std::fs::write(cache.join("chunks").join("f.json"), serde_json::to_string(&escaping).unwrap()).unwrap();
+
+    let registry = RegistryClient::new(RegistryConfig {
+        url: mock_registry().await,
+        max_retries: 0,
+        ..Default::default()
+    })
+    .unwrap();
+    let scaffolder = Scaffolder::new(&cache).with_graph(GraphStore::in_memory().unwrap()).with_registry(registry);
+
+    let manifest = manifest(&[("fetched", "chunk:sha256:e"), ("missing", "chunk:sha256:9"), ("escaping", "chunk:sha256:f")]);
+    let report = scaffolder.scaffold(&manifest, &output, OnConflict::Skip).await.unwrap();
+
+    assert_eq!(std::fs::read_to_string(output.join("pkg/fetched.py")).unwrap(), "def fetched():\n    pass\n");
+    assert_eq!(report.fetched, vec!["chunk:sha256:e".to_string(), "chunk:sha256:f".to_string()]);
+    let unresolved: Vec<&str> = report.unresolved.iter().map(|u| u.node.as_str()).collect();
+    assert_eq!(unresolved, vec!["missing", "escaping"]);
+    assert!(!output.parent().unwrap().join("outside.py").exists());
+}
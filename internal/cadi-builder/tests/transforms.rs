@@ -0,0 +1,139 @@
+use cadi_builder::{BuildCache, BuildConfig, BuildEngine, BuildPlan, BuildReceipt, Replay};
+use cadi_core::Manifest;
+use std::path::Path;
+
+/// Both nodes compile to a text blob that the target's transforms rewrite;
+/// `app` depends on `greeting`
+fn manifest(sed: &str) -> Manifest {
+    serde_yaml::from_str(&format!(
+        r#"
+manifest_id: app
+manifest_version: "1.0"
+application:
+  name: app
+build_graph:
+  nodes:
+    - id: greeting
+      representations:
+        - form: source
+          chunk: chunk:sha256:3333333333333333333333333333333333333333333333333333333333333333
+    - id: app
+      representations:
+        - form: source
+          chunk: chunk:sha256:4444444444444444444444444444444444444444444444444444444444444444
+  edges:
+    - from: app
+      to: greeting
+build_targets:
+  - name: dev
+    platform: any
+    transforms:
+      - command: [sed, -e, '{sed}', "{{input}}"]
+        input: blob
+        output: text
+        env: [PATH]
+      - builtin: parse
+        input: ir
+        output: ir
+      - command: [sh, -c, 'tr a-z A-Z < "$0" > "$1"; echo shouted >&2', "{{input}}", "{{output}}"]
+        input: text
+        output: text
+        env: [PATH]
+"#
+    ))
+    .unwrap()
+}
+
+fn engine(dir: &Path, allow_external_transforms: bool) -> BuildEngine {
+    BuildEngine::new(BuildConfig {
+        cache_dir: dir.to_path_buf(),
+        use_remote_cache: false,
+        allow_external_transforms,
+        ..Default::default()
+    })
+}
+
+fn receipts(dir: &Path) -> Vec<BuildReceipt> {
+    let mut receipts = Vec::new();
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            receipts.extend(self::receipts(&path));
+        } else {
+            receipts.push(serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap());
+        }
+    }
+    receipts
+}
+
+#[tokio::test]
+async fn test_target_transforms_rewrite_node_outputs() {
+    let dir = std::env::temp_dir().join(format!("cadi-transforms-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let manifest = manifest("s/\"blob\"/\"optimized\"/");
+
+    let refused = engine(&dir, false).build(&manifest, "dev").await.unwrap_err();
+    assert!(refused.to_string().contains("--allow-external-transforms"));
+
+    let engine = engine(&dir, true);
+    let result = engine.build(&manifest, "dev").await.unwrap();
+    assert!(result.failed.is_empty(), "{:?}", result.failed);
+    // The parse transform takes ir, which no node produces
+    assert_eq!(result.built.len(), 6, "{:?}", result.built);
+    assert_eq!(result.built[1..3], ["greeting:sed", "greeting:sh"]);
+
+    // sed's stdout is its output; the shell writes {output} and logs to stderr
+    let cache = BuildCache::new(dir.clone());
+    let receipts = receipts(&dir.join("receipts"));
+    let sed = receipts.iter().find(|r| r.step == "greeting:sed").unwrap();
+    assert_eq!(sed.log.as_ref().unwrap().stdout, None);
+    let shout = receipts.iter().find(|r| r.step == "greeting:sh").unwrap();
+    let log = shout.log.as_ref().unwrap();
+    assert_eq!((log.exit_code, log.stdout.as_deref(), log.stderr.as_str()), (0, Some(""), "shouted\n"));
+    let shouted = String::from_utf8(cache.get(&shout.output).unwrap().unwrap()).unwrap();
+    assert!(shouted.contains("\"OPTIMIZED\""), "{}", shouted);
+
+    // The app is built from the transformed output
+    let app = receipts.iter().find(|r| r.step == "app").unwrap();
+    assert!(app.inputs.iter().any(|i| i.role == "dependency" && i.chunk_id == shout.output));
+
+    // Transform steps are cached under their input hash
+    let again = engine.build(&manifest, "dev").await.unwrap();
+    assert!(again.cached.iter().any(|s| s == "greeting:sh"), "{:?}", again.cached);
+    assert_eq!(engine.replay(shout).await.unwrap(), Replay::Reproduced);
+
+    // Editing a command changes its step's hash and every later one
+    let hashes = |manifest: &Manifest| -> Vec<(String, String)> {
+        let plan = BuildPlan::from_manifest(manifest, "dev").unwrap();
+        plan.steps.iter().map(|s| (s.name.clone(), s.input_hash.clone())).collect()
+    };
+    let (before, after) = (hashes(&manifest), hashes(&self::manifest("s/blob/other/")));
+    let changed: Vec<&str> = before
+        .iter()
+        .zip(&after)
+        .filter(|(b, a)| b.1 != a.1)
+        .map(|(b, _)| b.0.as_str())
+        .collect();
+    assert_eq!(changed, ["greeting:sed", "greeting:sh", "app", "app:sed", "app:sh"]);
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn test_unexpected_exit_code_fails_the_step() {
+    let dir = std::env::temp_dir().join(format!("cadi-transforms-exit-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let mut manifest = manifest("p");
+    manifest.build_targets[0].transforms[2].command = vec!["sh".into(), "-c".into(), "echo broken >&2; exit 3".into()];
+
+    let result = engine(&dir, true).build(&manifest, "dev").await.unwrap();
+    let failed: Vec<&str> = result.failed.iter().map(|f| f.chunk_id.as_str()).collect();
+    assert_eq!(failed, ["greeting:sh", "app:sh"]);
+    assert!(result.failed[0].error.contains("exited with 3 (expected 0): broken"));
+
+    manifest.build_targets[0].transforms[2].exit_code = 3;
+    let result = engine(&dir, true).build(&manifest, "dev").await.unwrap();
+    assert!(result.failed.is_empty());
+
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,144 @@
+use cadi_builder::watch::{Atom, ChangeEvent, Debouncer, FileChange, WatchGraph, WatchedAtom};
+use cadi_builder::BuildPlan;
+use cadi_core::Manifest;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const UTIL_V1: &str = "chunk:sha256:1111111111111111111111111111111111111111111111111111111111111111";
+const UTIL_V2: &str = "chunk:sha256:4444444444444444444444444444444444444444444444444444444444444444";
+const LIB: &str = "chunk:sha256:2222222222222222222222222222222222222222222222222222222222222222";
+const APP: &str = "chunk:sha256:3333333333333333333333333333333333333333333333333333333333333333";
+
+/// app -> lib -> util, with util and lib in util.rs and app in main.rs
+fn graph() -> WatchGraph {
+    let manifest: Manifest = serde_yaml::from_str(&format!(
+        r#"
+manifest_id: app
+manifest_version: "1.0"
+application:
+  name: app
+build_graph:
+  nodes:
+    - id: util
+      source_cadi: {UTIL_V1}
+      representations:
+        - form: source
+          chunk: {UTIL_V1}
+    - id: lib
+      representations:
+        - form: source
+          chunk: {LIB}
+    - id: app
+      representations:
+        - form: source
+          chunk: {APP}
+  edges:
+    - from: app
+      to: lib
+    - from: lib
+      to: util
+build_targets:
+  - name: dev
+    platform: any
+    nodes:
+      - id: util
+      - id: lib
+      - id: app
+"#
+    ))
+    .unwrap();
+
+    let atom = |node: &str, chunk: &str| WatchedAtom {
+        node_id: node.to_string(),
+        name: node.to_string(),
+        chunk_id: chunk.to_string(),
+    };
+    WatchGraph::new(
+        manifest,
+        vec![
+            (PathBuf::from("src/util.rs"), atom("util", UTIL_V1)),
+            (PathBuf::from("src/util.rs"), atom("lib", LIB)),
+            (PathBuf::from("src/main.rs"), atom("app", APP)),
+        ],
+    )
+}
+
+#[test]
+fn test_changed_atom_invalidates_its_dependents() {
+    let mut graph = graph();
+    let invalidation = graph.apply(vec![FileChange::Reatomized {
+        path: PathBuf::from("src/util.rs"),
+        atoms: vec![
+            Atom { name: "util".into(), chunk_id: UTIL_V2.into() },
+            Atom { name: "lib".into(), chunk_id: LIB.into() },
+            Atom { name: "helper".into(), chunk_id: APP.into() },
+        ],
+    }]);
+
+    assert_eq!(invalidation.changed, vec!["util"]);
+    assert_eq!(invalidation.dependents, vec!["app", "lib"]);
+    assert_eq!(invalidation.unmapped, vec!["helper"]);
+    assert_eq!(
+        invalidation.dirty().into_iter().collect::<Vec<_>>(),
+        vec!["app", "lib", "util"]
+    );
+    assert_eq!(
+        invalidation.to_string(),
+        "1 changed (util), 2 dependent (app, lib), 1 unmapped (helper)"
+    );
+
+    let util = &graph.manifest().build_graph.nodes[0];
+    assert_eq!(util.source_cadi.as_deref(), Some(UTIL_V2));
+    assert_eq!(util.representations[0].chunk, UTIL_V2);
+
+    // Re-applying the same content changes nothing
+    let again = graph.apply(vec![FileChange::Reatomized {
+        path: PathBuf::from("src/util.rs"),
+        atoms: vec![
+            Atom { name: "util".into(), chunk_id: UTIL_V2.into() },
+            Atom { name: "lib".into(), chunk_id: LIB.into() },
+        ],
+    }]);
+    assert!(again.is_empty());
+    assert!(again.dirty().is_empty());
+}
+
+#[test]
+fn test_removed_file_drops_atoms_and_marks_dependents_dirty() {
+    let mut graph = graph();
+    let invalidation = graph.apply(vec![FileChange::Removed(PathBuf::from("src/util.rs"))]);
+
+    assert_eq!(invalidation.dropped, vec!["lib", "util"]);
+    assert_eq!(invalidation.dependents, vec!["app"]);
+    assert!(invalidation.changed.is_empty());
+
+    let manifest = graph.manifest();
+    assert_eq!(manifest.build_graph.nodes.len(), 1);
+    assert!(manifest.build_graph.edges.is_empty());
+    assert_eq!(manifest.build_targets[0].nodes.len(), 1);
+    assert_eq!(graph.watched_files().collect::<Vec<_>>(), vec![Path::new("src/main.rs")]);
+
+    // The trimmed manifest still plans
+    let plan = BuildPlan::from_manifest(manifest, "dev").unwrap();
+    assert_eq!(plan.steps.len(), 1);
+}
+
+#[test]
+fn test_debouncer_waits_for_quiet_and_keeps_latest_event() {
+    let start = Instant::now();
+    let window = Duration::from_millis(100);
+    let mut debouncer = Debouncer::new(window);
+    assert!(debouncer.deadline().is_none());
+
+    debouncer.push(ChangeEvent::modified("a.rs"), start);
+    debouncer.push(ChangeEvent::modified("b.rs"), start + Duration::from_millis(50));
+    debouncer.push(ChangeEvent::removed("a.rs"), start + Duration::from_millis(80));
+
+    // Each event pushes the deadline back
+    assert!(debouncer.take_ready(start + Duration::from_millis(120)).is_empty());
+    assert_eq!(debouncer.deadline(), Some(start + Duration::from_millis(180)));
+
+    let events = debouncer.take_ready(start + Duration::from_millis(180));
+    assert_eq!(events, vec![ChangeEvent::removed("a.rs"), ChangeEvent::modified("b.rs")]);
+    assert!(debouncer.deadline().is_none());
+}
@@ -0,0 +1,66 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+use cadi_core::graph::{EdgeType, GraphNode, GraphStore};
+
+const NODES: usize = 10_000;
+/// Shared helpers every chunk calls, so dependents lists grow long
+const HUBS: usize = 16;
+
+fn make_nodes() -> Vec<GraphNode> {
+    (0..NODES)
+        .map(|i| {
+            let mut node = GraphNode::new(format!("chunk:{}", i), i.to_string())
+                .with_alias(format!("bench/node-{}", i))
+                .with_defines(vec![format!("symbol_{}", i)]);
+            if i > 0 {
+                node.add_dependency(EdgeType::Imports, format!("chunk:{}", i / 2));
+            }
+            node
+        })
+        .collect()
+}
+
+fn make_edges() -> Vec<(String, String, EdgeType)> {
+    (HUBS..NODES)
+        .map(|i| (format!("chunk:{}", i), format!("chunk:{}", i % HUBS), EdgeType::Calls))
+        .collect()
+}
+
+fn bench_graph_inserts(c: &mut Criterion) {
+    let nodes = make_nodes();
+    let edges = make_edges();
+    let mut group = c.benchmark_group("graph_store::import_10k");
+    group.sample_size(10);
+
+    group.bench_function("insert_node_loop", |b| {
+        b.iter_batched(
+            || GraphStore::in_memory().expect("store"),
+            |store| {
+                for node in &nodes {
+                    store.insert_node(node).expect("insert failed");
+                }
+                for (source, target, edge_type) in &edges {
+                    store.add_dependency(source, target, *edge_type).expect("edge failed");
+                }
+                store.flush().expect("flush failed");
+            },
+            BatchSize::PerIteration,
+        )
+    });
+
+    group.bench_function("insert_nodes_batch", |b| {
+        b.iter_batched(
+            || GraphStore::in_memory().expect("store"),
+            |store| {
+                store.insert_nodes_batch(&nodes).expect("batch failed");
+                store.add_dependencies_batch(&edges).expect("batch failed");
+            },
+            BatchSize::PerIteration,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_graph_inserts);
+criterion_main!(benches);
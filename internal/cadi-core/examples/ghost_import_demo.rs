@@ -182,7 +182,7 @@ fn main() {
     println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
     
     // What does App depend on?
-    let app_deps = graph.get_dependencies("chunk:app")?;
+    let app_deps = graph.get_dependencies("chunk:app", None)?;
     println!("What does 'app/main' depend on?");
     for (edge_type, dep_id) in &app_deps {
         if let Ok(Some(node)) = graph.get_node(dep_id) {
@@ -220,7 +220,7 @@ fn main() {
         let mut next_frontier = Vec::new();
         
         for chunk_id in &frontier {
-            if let Ok(deps) = graph.get_dependencies(chunk_id) {
+            if let Ok(deps) = graph.get_dependencies(chunk_id, None) {
                 for (edge_type, dep_id) in deps {
                     if edge_type.should_auto_expand() && !included.contains(&dep_id) {
                         if let Ok(Some(node)) = graph.get_node(&dep_id) {
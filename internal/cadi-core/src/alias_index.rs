@@ -0,0 +1,265 @@
+//! Alias lookup with fuzzy suggestions
+//!
+//! [`AliasIndex`] resolves human-readable aliases to chunk IDs. Matching is
+//! case-insensitive and treats `-` and `_` as the same character. A query
+//! without its namespace still resolves through a suffix match, and a query
+//! with no match at all returns the closest aliases as suggestions instead.
+
+use crate::atomic::AliasRegistry;
+use crate::deduplication::DeduplicationEngine;
+use crate::error::CadiResult;
+use crate::graph::GraphStore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Number of suggestions returned when an alias does not resolve
+pub const DEFAULT_SUGGESTIONS: usize = 5;
+
+/// Searchable set of aliases
+#[derive(Debug, Clone, Default)]
+pub struct AliasIndex {
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    alias: String,
+    chunk_id: String,
+    normalized: String,
+    tokens: HashSet<String>,
+}
+
+/// How an alias matched the query
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchKind {
+    /// The whole alias matched
+    Exact,
+    /// The query matched the trailing path segments of the alias
+    Suffix,
+}
+
+/// An alias that resolved
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AliasMatch {
+    pub alias: String,
+    pub chunk_id: String,
+    pub kind: MatchKind,
+}
+
+/// An alias close to one that did not resolve
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AliasSuggestion {
+    pub alias: String,
+    pub chunk_id: String,
+    /// Similarity to the query (0.0-1.0)
+    pub score: f64,
+}
+
+/// Outcome of resolving an alias
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum AliasResolution {
+    Found(AliasMatch),
+    NotFound { suggestions: Vec<AliasSuggestion> },
+}
+
+impl AliasIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index every alias in a registry
+    pub fn from_registry(registry: &AliasRegistry) -> Self {
+        let mut index = Self::new();
+        for (alias, chunk_id) in &registry.aliases {
+            index.insert(alias, chunk_id);
+        }
+        index
+    }
+
+    /// Index the aliases of every node in a graph store
+    pub fn from_graph(store: &GraphStore) -> CadiResult<Self> {
+        let mut index = Self::new();
+        index.add_graph(store)?;
+        Ok(index)
+    }
+
+    /// Add the aliases of every node in a graph store
+    pub fn add_graph(&mut self, store: &GraphStore) -> CadiResult<()> {
+        for node in store.list_nodes()? {
+            for alias in &node.aliases {
+                self.insert(alias, &node.chunk_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Add an alias
+    pub fn insert(&mut self, alias: &str, chunk_id: &str) {
+        let normalized = normalize(alias);
+        let tokens = tokens(&normalized);
+        self.entries.push(Entry {
+            alias: alias.to_string(),
+            chunk_id: chunk_id.to_string(),
+            normalized,
+            tokens,
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Resolve an alias, falling back to the [`DEFAULT_SUGGESTIONS`]
+    /// closest aliases when nothing matches
+    pub fn resolve(&self, query: &str) -> AliasResolution {
+        match self.lookup(query) {
+            Some(found) => AliasResolution::Found(found),
+            None => AliasResolution::NotFound {
+                suggestions: self.suggest(query, DEFAULT_SUGGESTIONS),
+            },
+        }
+    }
+
+    /// Exact match, else the shortest alias ending in the query's segments
+    pub fn lookup(&self, query: &str) -> Option<AliasMatch> {
+        let query = normalize(query);
+        if query.is_empty() {
+            return None;
+        }
+
+        if let Some(entry) = self.entries.iter().find(|e| e.normalized == query) {
+            return Some(entry.to_match(MatchKind::Exact));
+        }
+
+        let suffix = format!("/{}", query);
+        self.entries
+            .iter()
+            .filter(|e| e.normalized.ends_with(&suffix))
+            .min_by(|a, b| {
+                a.normalized
+                    .len()
+                    .cmp(&b.normalized.len())
+                    .then_with(|| a.alias.cmp(&b.alias))
+            })
+            .map(|e| e.to_match(MatchKind::Suffix))
+    }
+
+    /// Up to `limit` aliases closest to the query, best first. Aliases are
+    /// scored by edit distance, both whole and over as many trailing
+    /// segments as the query has, and by overlap of their words.
+    pub fn suggest(&self, query: &str, limit: usize) -> Vec<AliasSuggestion> {
+        let query = normalize(query);
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let query_tokens = tokens(&query);
+        let query_segments = query.split('/').count();
+
+        let mut suggestions: Vec<AliasSuggestion> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let tail = trailing_segments(&entry.normalized, query_segments);
+                let edit = DeduplicationEngine::levenshtein_similarity(&query, &entry.normalized)
+                    .max(DeduplicationEngine::levenshtein_similarity(&query, tail));
+                let shared = query_tokens.intersection(&entry.tokens).count();
+                let overlap = shared as f32
+                    / (query_tokens.len() + entry.tokens.len() - shared).max(1) as f32;
+                AliasSuggestion {
+                    alias: entry.alias.clone(),
+                    chunk_id: entry.chunk_id.clone(),
+                    score: edit.max(overlap) as f64,
+                }
+            })
+            .filter(|s| s.score > 0.0)
+            .collect();
+
+        suggestions.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.alias.cmp(&b.alias)));
+        suggestions.truncate(limit);
+        suggestions
+    }
+}
+
+impl Entry {
+    fn to_match(&self, kind: MatchKind) -> AliasMatch {
+        AliasMatch {
+            alias: self.alias.clone(),
+            chunk_id: self.chunk_id.clone(),
+            kind,
+        }
+    }
+}
+
+/// Lowercase, with `_` folded into `-` and surrounding slashes dropped
+fn normalize(alias: &str) -> String {
+    alias.trim().trim_matches('/').to_lowercase().replace('_', "-")
+}
+
+fn tokens(normalized: &str) -> HashSet<String> {
+    normalized
+        .split(['/', '-', '.'])
+        .filter(|t| !t.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn trailing_segments(path: &str, count: usize) -> &str {
+    match path.rmatch_indices('/').nth(count.saturating_sub(1)) {
+        Some((i, _)) => &path[i + 1..],
+        None => path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> AliasIndex {
+        let mut index = AliasIndex::new();
+        index.insert("acme/utils/string_helpers", "chunk:strings");
+        index.insert("acme/http/client", "chunk:client");
+        index.insert("acme/http/server", "chunk:server");
+        index.insert("acme/db/connection-pool", "chunk:pool");
+        index
+    }
+
+    #[test]
+    fn test_lookup_ignores_case_and_separator_style() {
+        let index = index();
+
+        let found = index.lookup("ACME/Utils/String-Helpers").unwrap();
+        assert_eq!(found.chunk_id, "chunk:strings");
+        assert_eq!(found.kind, MatchKind::Exact);
+
+        let found = index.lookup("db/connection_pool").unwrap();
+        assert_eq!(found.alias, "acme/db/connection-pool");
+        assert_eq!(found.kind, MatchKind::Suffix);
+
+        // A suffix has to cover whole segments
+        assert!(index.lookup("lient").is_none());
+    }
+
+    #[test]
+    fn test_typo_returns_ranked_suggestions() {
+        let index = index();
+
+        let AliasResolution::NotFound { suggestions } = index.resolve("acme/htp/client") else {
+            panic!("typo should not resolve");
+        };
+        assert!(suggestions.len() <= DEFAULT_SUGGESTIONS);
+        assert_eq!(suggestions[0].alias, "acme/http/client");
+        assert_eq!(suggestions[0].chunk_id, "chunk:client");
+        assert!(suggestions.windows(2).all(|w| w[0].score >= w[1].score));
+
+        let AliasResolution::NotFound { suggestions } = index.resolve("connection-pol") else {
+            panic!("typo should not resolve");
+        };
+        assert_eq!(suggestions[0].alias, "acme/db/connection-pool");
+    }
+}
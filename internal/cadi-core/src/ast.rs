@@ -7,6 +7,17 @@ pub struct CadlDocument {
     pub implementations: Vec<ImplDef>,
     pub constraints: Vec<ConstraintDef>,
     pub top_level_annotations: Vec<Annotation>,
+    /// Start of each element, keyed by its path in the document
+    /// (`interfaces[0].methods[1]`)
+    #[serde(skip)]
+    pub source_map: HashMap<String, Span>,
+}
+
+/// 1-based line and column in a source file
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -7,8 +7,10 @@
 //! - Composition support (chunks made of other chunks)
 //! - Granularity levels for different reuse patterns
 
+use crate::error::{CadiError, CadiResult};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 /// Granularity level of an atomic chunk
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -30,6 +32,21 @@ pub enum ChunkGranularity {
     Import,
 }
 
+impl std::str::FromStr for ChunkGranularity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "function" => Ok(ChunkGranularity::Function),
+            "type" => Ok(ChunkGranularity::Type),
+            "module" => Ok(ChunkGranularity::Module),
+            "package" => Ok(ChunkGranularity::Package),
+            "project" => Ok(ChunkGranularity::Project),
+            "import" => Ok(ChunkGranularity::Import),
+            other => Err(format!("unknown granularity: {}", other)),
+        }
+    }
+}
 
 /// Category of code chunk
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -63,6 +80,27 @@ pub enum ChunkCategory {
     Custom(String),
 }
 
+impl std::str::FromStr for ChunkCategory {
+    type Err = std::convert::Infallible;
+
+    /// Parse a category name; unknown names become `Custom`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "logic" => ChunkCategory::Logic,
+            "data" => ChunkCategory::Data,
+            "utility" => ChunkCategory::Utility,
+            "api" => ChunkCategory::Api,
+            "config" => ChunkCategory::Config,
+            "test" => ChunkCategory::Test,
+            "docs" => ChunkCategory::Docs,
+            "build" => ChunkCategory::Build,
+            "ui" => ChunkCategory::Ui,
+            "backend" => ChunkCategory::Backend,
+            "database" => ChunkCategory::Database,
+            _ => ChunkCategory::Custom(s.to_string()),
+        })
+    }
+}
 
 /// Platform constraint for a chunk
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -207,13 +245,26 @@ pub struct ChunkMetrics {
     /// Coupling score (0-1, lower = better)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub coupling: Option<f32>,
+    /// Distinct symbols referenced (calls, paths and types)
+    #[serde(default)]
+    pub referenced_symbols: usize,
+    /// Fraction of non-blank lines carrying a comment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment_density: Option<f32>,
+    /// Deepest block nesting
+    #[serde(default)]
+    pub max_nesting: usize,
 }
 
 /// Source location information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SourceLocation {
-    /// Original file path (relative)
+    /// Original file path (relative), for display; lossy when the path
+    /// isn't valid UTF-8
     pub file: String,
+    /// Raw bytes of the path, kept only when it isn't valid UTF-8
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_bytes: Option<Vec<u8>>,
     /// Start line (1-indexed)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub start_line: Option<usize>,
@@ -228,6 +279,90 @@ pub struct SourceLocation {
     pub end_col: Option<usize>,
 }
 
+impl SourceLocation {
+    /// Location covering the whole file at `path`
+    pub fn for_path(path: &Path) -> Self {
+        Self {
+            file: path.to_string_lossy().into_owned(),
+            file_bytes: raw_path_bytes(path),
+            start_line: None,
+            end_line: None,
+            start_col: None,
+            end_col: None,
+        }
+    }
+
+    /// The original file path, rebuilt from its bytes when it isn't UTF-8
+    pub fn path(&self) -> PathBuf {
+        match &self.file_bytes {
+            Some(bytes) => path_from_raw_bytes(bytes),
+            None => PathBuf::from(&self.file),
+        }
+    }
+
+    /// Whether this location is in the file at `path`
+    pub fn is_in(&self, path: &Path) -> bool {
+        match &self.file_bytes {
+            Some(_) => self.path() == path,
+            None => path.to_str() == Some(self.file.as_str()),
+        }
+    }
+}
+
+/// Bytes of a path that isn't valid UTF-8, which a string can't hold
+#[cfg(unix)]
+fn raw_path_bytes(path: &Path) -> Option<Vec<u8>> {
+    use std::os::unix::ffi::OsStrExt;
+    path.to_str().is_none().then(|| path.as_os_str().as_bytes().to_vec())
+}
+
+#[cfg(not(unix))]
+fn raw_path_bytes(_path: &Path) -> Option<Vec<u8>> {
+    None
+}
+
+#[cfg(unix)]
+fn path_from_raw_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_raw_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// Serde helpers for paths that may not be valid UTF-8: a string when it
+/// is, the raw bytes otherwise. Plain `PathBuf` fields fail to serialize
+/// such paths.
+pub mod raw_path {
+    use super::{path_from_raw_bytes, raw_path_bytes};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::path::{Path, PathBuf};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Text(String),
+        Bytes(Vec<u8>),
+    }
+
+    pub fn serialize<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+        match raw_path_bytes(path) {
+            Some(bytes) => Repr::Bytes(bytes),
+            None => Repr::Text(path.to_string_lossy().into_owned()),
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Text(text) => PathBuf::from(text),
+            Repr::Bytes(bytes) => path_from_raw_bytes(&bytes),
+        })
+    }
+}
+
 /// An atomic chunk - the fundamental unit of reusable code
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AtomicChunk {
@@ -256,6 +391,11 @@ pub struct AtomicChunk {
     #[serde(default)]
     pub categories: Vec<ChunkCategory>,
 
+    /// Confidence (0.0-1.0) in the first category, when it was inferred
+    /// rather than set explicitly
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category_confidence: Option<f32>,
+
     /// Tags for search and discovery
     #[serde(default)]
     pub tags: Vec<String>,
@@ -294,7 +434,7 @@ pub struct AtomicChunk {
     /// Size in bytes
     pub size: usize,
 
-    /// License
+    /// License (SPDX expression, `NOASSERTION` when unknown)
     #[serde(default = "default_license")]
     pub license: String,
 
@@ -308,7 +448,7 @@ pub struct AtomicChunk {
 }
 
 fn default_license() -> String {
-    "MIT".to_string()
+    crate::license::UNKNOWN_LICENSE.to_string()
 }
 
 impl AtomicChunk {
@@ -328,6 +468,7 @@ impl AtomicChunk {
             language,
             granularity: ChunkGranularity::default(),
             categories: Vec::new(),
+            category_confidence: None,
             tags: Vec::new(),
             concepts: Vec::new(),
             provides: Vec::new(),
@@ -338,7 +479,7 @@ impl AtomicChunk {
             sources: Vec::new(),
             content_hash,
             size,
-            license: "MIT".to_string(),
+            license: default_license(),
             created_at: Some(chrono::Utc::now().to_rfc3339()),
             version: None,
         }
@@ -366,6 +507,12 @@ impl AtomicChunk {
         self
     }
 
+    /// Set the confidence in the first category
+    pub fn with_category_confidence(mut self, confidence: Option<f32>) -> Self {
+        self.category_confidence = confidence;
+        self
+    }
+
     /// Set granularity
     pub fn with_granularity(mut self, granularity: ChunkGranularity) -> Self {
         self.granularity = granularity;
@@ -378,6 +525,12 @@ impl AtomicChunk {
         self
     }
 
+    /// Set the license (an SPDX expression)
+    pub fn with_license(mut self, license: impl Into<String>) -> Self {
+        self.license = license.into();
+        self
+    }
+
     /// Mark as composed of other chunks
     pub fn composed_of(mut self, chunks: Vec<ChunkReference>) -> Self {
         let is_empty = chunks.is_empty();
@@ -404,9 +557,46 @@ impl AtomicChunk {
     }
 }
 
+/// IDs of the chunks referenced by serialized chunk `data`: the
+/// `composition.composed_of` entries, then any `requires` entries that are
+/// chunk IDs, without duplicates. Data that is not a JSON chunk references
+/// nothing.
+pub fn chunk_references(data: &[u8]) -> Vec<String> {
+    let Ok(chunk) = serde_json::from_slice::<serde_json::Value>(data) else {
+        return Vec::new();
+    };
+
+    let composed_of = chunk["composition"]["composed_of"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|r| r["chunk_id"].as_str());
+    let requires = chunk["requires"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|r| r.as_str())
+        .filter(|r| r.starts_with("chunk:"));
+
+    let mut references: Vec<String> = Vec::new();
+    for id in composed_of.chain(requires) {
+        if !references.iter().any(|r| r == id) {
+            references.push(id.to_string());
+        }
+    }
+    references
+}
+
+/// Current `aliases.json` format version. Version 1 files have no
+/// `version` field and no rebind history.
+pub const ALIAS_REGISTRY_VERSION: u32 = 2;
+
 /// Alias registry for tracking used aliases
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AliasRegistry {
+    /// Format version
+    #[serde(default = "legacy_registry_version")]
+    pub version: u32,
     /// Map of alias path -> chunk_id
     pub aliases: HashMap<String, String>,
     /// Map of chunk_id -> list of aliases
@@ -414,6 +604,35 @@ pub struct AliasRegistry {
     /// Reserved aliases that cannot be used
     #[serde(default)]
     pub reserved: Vec<String>,
+    /// Rebinds applied to existing aliases, oldest first
+    #[serde(default)]
+    pub history: Vec<AliasRebind>,
+}
+
+/// An alias being pointed at a different chunk
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AliasRebind {
+    pub alias: String,
+    pub previous_chunk_id: String,
+    pub chunk_id: String,
+    /// RFC 3339 timestamp
+    pub rebound_at: String,
+}
+
+fn legacy_registry_version() -> u32 {
+    1
+}
+
+impl Default for AliasRegistry {
+    fn default() -> Self {
+        Self {
+            version: ALIAS_REGISTRY_VERSION,
+            aliases: HashMap::new(),
+            chunks: HashMap::new(),
+            reserved: Vec::new(),
+            history: Vec::new(),
+        }
+    }
 }
 
 impl AliasRegistry {
@@ -422,6 +641,30 @@ impl AliasRegistry {
         Self::default()
     }
 
+    /// Load an `aliases.json` file, upgrading older formats
+    pub fn load(path: &Path) -> CadiResult<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let mut registry: Self = serde_json::from_str(&content)?;
+        if registry.version > ALIAS_REGISTRY_VERSION {
+            return Err(CadiError::Configuration(format!(
+                "{} has alias registry version {}, newer than the supported version {}",
+                path.display(),
+                registry.version,
+                ALIAS_REGISTRY_VERSION
+            )));
+        }
+        registry.version = ALIAS_REGISTRY_VERSION;
+        Ok(registry)
+    }
+
+    /// Write the registry in the current format
+    pub fn save(&self, path: &Path) -> CadiResult<()> {
+        let mut current = self.clone();
+        current.version = ALIAS_REGISTRY_VERSION;
+        std::fs::write(path, serde_json::to_string_pretty(&current)?)?;
+        Ok(())
+    }
+
     /// Check if an alias is available
     pub fn is_available(&self, alias: &str) -> bool {
         !self.aliases.contains_key(alias) && !self.reserved.contains(&alias.to_string())
@@ -450,6 +693,95 @@ impl AliasRegistry {
         self.aliases.get(alias)
     }
 
+    /// Remove a chunk and all of its aliases, returning the removed aliases
+    pub fn unregister_chunk(&mut self, chunk_id: &str) -> Vec<String> {
+        let removed = self.chunks.remove(chunk_id).unwrap_or_default();
+        for alias in &removed {
+            self.aliases.remove(alias);
+        }
+        removed
+    }
+
+    /// Move every alias under `old/` to `new/`. Nothing changes if any
+    /// moved alias would collide with an alias of a different chunk or a
+    /// reserved alias. Returns the number of aliases moved.
+    pub fn rename_namespace(&mut self, old: &str, new: &str) -> CadiResult<usize> {
+        let old_prefix = format!("{}/", old.trim_end_matches('/'));
+        let new_prefix = format!("{}/", new.trim_end_matches('/'));
+
+        let mut moves: Vec<(String, String)> = self
+            .aliases
+            .keys()
+            .filter_map(|alias| {
+                alias
+                    .strip_prefix(&old_prefix)
+                    .map(|rest| (alias.clone(), format!("{}{}", new_prefix, rest)))
+            })
+            .collect();
+        moves.sort();
+
+        for (from, to) in &moves {
+            let chunk_id = &self.aliases[from];
+            if self.reserved.contains(to) {
+                return Err(CadiError::AliasConflict(format!("'{}' is reserved", to)));
+            }
+            if let Some(existing) = self.aliases.get(to) {
+                if existing != chunk_id {
+                    return Err(CadiError::AliasConflict(format!(
+                        "cannot move '{}' to '{}': it already maps to {}",
+                        from, to, existing
+                    )));
+                }
+            }
+        }
+
+        for (from, to) in &moves {
+            let chunk_id = self.aliases.remove(from).unwrap_or_default();
+            let chunk_aliases = self.chunks.entry(chunk_id.clone()).or_default();
+            chunk_aliases.retain(|a| a != from);
+            if !chunk_aliases.contains(to) {
+                chunk_aliases.push(to.clone());
+            }
+            self.aliases.insert(to.clone(), chunk_id);
+        }
+
+        Ok(moves.len())
+    }
+
+    /// Point an existing alias at a different chunk, recording the change
+    /// in the history. Returns the chunk the alias previously mapped to.
+    pub fn rebind(&mut self, alias: &str, chunk_id: &str) -> CadiResult<String> {
+        let previous = self
+            .aliases
+            .get(alias)
+            .cloned()
+            .ok_or_else(|| CadiError::AliasNotFound(alias.to_string()))?;
+        if previous == chunk_id {
+            return Ok(previous);
+        }
+
+        if let Some(aliases) = self.chunks.get_mut(&previous) {
+            aliases.retain(|a| a != alias);
+            if aliases.is_empty() {
+                self.chunks.remove(&previous);
+            }
+        }
+        self.chunks
+            .entry(chunk_id.to_string())
+            .or_default()
+            .push(alias.to_string());
+        self.aliases.insert(alias.to_string(), chunk_id.to_string());
+
+        self.history.push(AliasRebind {
+            alias: alias.to_string(),
+            previous_chunk_id: previous.clone(),
+            chunk_id: chunk_id.to_string(),
+            rebound_at: chrono::Utc::now().to_rfc3339(),
+        });
+
+        Ok(previous)
+    }
+
     /// Get all aliases for a chunk
     pub fn get_aliases(&self, chunk_id: &str) -> Option<&Vec<String>> {
         self.chunks.get(chunk_id)
@@ -485,6 +817,17 @@ mod tests {
         assert!(alias.primary);
     }
 
+    #[test]
+    fn test_parse_granularity_and_category() {
+        assert_eq!("function".parse::<ChunkGranularity>(), Ok(ChunkGranularity::Function));
+        assert!("galaxy".parse::<ChunkGranularity>().is_err());
+        assert_eq!("Utility".parse::<ChunkCategory>(), Ok(ChunkCategory::Utility));
+        assert_eq!(
+            "plugins".parse::<ChunkCategory>(),
+            Ok(ChunkCategory::Custom("plugins".to_string()))
+        );
+    }
+
     #[test]
     fn test_alias_registry() {
         let mut registry = AliasRegistry::new();
@@ -497,6 +840,71 @@ mod tests {
         assert_eq!(resolved, Some(&"chunk:sha256:abc123".to_string()));
     }
 
+    #[test]
+    fn test_rename_namespace_detects_conflicts() {
+        let mut registry = AliasRegistry::new();
+        registry.register("acme/utils/slug", "chunk:a");
+        registry.register("acme/http/client", "chunk:b");
+        registry.register("acme-platform/http/client", "chunk:c");
+        registry.register("other/utils/slug", "chunk:d");
+
+        let err = registry.rename_namespace("acme", "acme-platform").unwrap_err();
+        assert!(matches!(err, CadiError::AliasConflict(_)));
+        assert_eq!(registry.resolve("acme/utils/slug"), Some(&"chunk:a".to_string()));
+
+        registry.unregister_chunk("chunk:c");
+        assert_eq!(registry.rename_namespace("acme", "acme-platform").unwrap(), 2);
+        assert_eq!(registry.resolve("acme/utils/slug"), None);
+        assert_eq!(
+            registry.resolve("acme-platform/utils/slug"),
+            Some(&"chunk:a".to_string())
+        );
+        assert_eq!(
+            registry.get_aliases("chunk:b"),
+            Some(&vec!["acme-platform/http/client".to_string()])
+        );
+        assert_eq!(registry.resolve("other/utils/slug"), Some(&"chunk:d".to_string()));
+    }
+
+    #[test]
+    fn test_rebind_records_history() {
+        let mut registry = AliasRegistry::new();
+        registry.register("acme/utils/slug", "chunk:a");
+
+        assert!(matches!(
+            registry.rebind("acme/missing", "chunk:b"),
+            Err(CadiError::AliasNotFound(_))
+        ));
+        assert_eq!(registry.rebind("acme/utils/slug", "chunk:b").unwrap(), "chunk:a");
+        assert_eq!(registry.resolve("acme/utils/slug"), Some(&"chunk:b".to_string()));
+        assert_eq!(registry.get_aliases("chunk:a"), None);
+        assert_eq!(registry.history.len(), 1);
+        assert_eq!(registry.history[0].previous_chunk_id, "chunk:a");
+        assert_eq!(registry.history[0].chunk_id, "chunk:b");
+    }
+
+    #[test]
+    fn test_load_upgrades_legacy_registry() {
+        let path = std::env::temp_dir().join(format!("cadi-aliases-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{"aliases": {"acme/slug": "chunk:a"}, "chunks": {"chunk:a": ["acme/slug"]}}"#,
+        )
+        .unwrap();
+
+        let registry = AliasRegistry::load(&path).unwrap();
+        assert_eq!(registry.version, ALIAS_REGISTRY_VERSION);
+        assert_eq!(registry.resolve("acme/slug"), Some(&"chunk:a".to_string()));
+
+        registry.save(&path).unwrap();
+        let saved: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved["version"], ALIAS_REGISTRY_VERSION);
+        assert_eq!(saved["history"], serde_json::json!([]));
+
+        let _ = std::fs::remove_file(path);
+    }
+
     #[test]
     fn test_atomic_chunk() {
         let chunk = AtomicChunk::new(
@@ -516,4 +924,17 @@ mod tests {
         assert!(chunk.is_atomic());
         assert_eq!(chunk.display_name(), "utils/string-helpers");
     }
+
+    #[test]
+    fn test_chunk_references() {
+        let chunk = serde_json::json!({
+            "composition": {"composed_of": [{"chunk_id": "chunk:sha256:aa"}, {"chunk_id": "chunk:sha256:bb"}]},
+            "requires": ["chunk:sha256:bb", "serde::Serialize", "chunk:sha256:cc"],
+        });
+        assert_eq!(
+            chunk_references(chunk.to_string().as_bytes()),
+            ["chunk:sha256:aa", "chunk:sha256:bb", "chunk:sha256:cc"]
+        );
+        assert!(chunk_references(b"\x7fELF").is_empty());
+    }
 }
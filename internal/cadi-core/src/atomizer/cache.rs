@@ -0,0 +1,311 @@
+//! Parse cache
+//!
+//! Extracting atoms means parsing the whole file, which dominates the time
+//! of importing a large project. [`ParseCache`] keeps the atoms extracted
+//! from a file, not its syntax tree, under a hash of the language, the
+//! atomizer configuration and the content, so unchanged files are never
+//! parsed twice. Entries are JSON files in `.cadi/parse-cache/`, evicted
+//! least recently used first once the cache outgrows its size bound.
+//!
+//! Every entry records the [`EXTRACTOR_VERSION`] and backend that produced
+//! it; entries from another version, and entries that can't be read, are
+//! misses and get overwritten.
+
+use super::config::AtomizerConfig;
+use super::extractor::{AtomExtractor, ExtractedAtom, EXTRACTOR_VERSION};
+use crate::error::CadiResult;
+use crate::hash::sha256_bytes;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Location of the parse cache, relative to the project root
+pub const PARSE_CACHE_DIR: &str = ".cadi/parse-cache";
+
+/// Default bound on the total size of the cache entries
+pub const DEFAULT_PARSE_CACHE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Extraction backend entries were produced by; tree-sitter and the regex
+/// fallback extract different atoms
+const BACKEND: &str = if cfg!(feature = "ast-parsing") { "tree-sitter" } else { "regex" };
+
+/// Hits and misses of a parse cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseCacheStats {
+    /// Files whose atoms were read from the cache
+    pub hits: usize,
+    /// Files that had to be parsed, including those with stale or
+    /// corrupted entries
+    pub misses: usize,
+    /// Entries removed to stay within the size bound
+    #[serde(default)]
+    pub evictions: usize,
+}
+
+impl ParseCacheStats {
+    /// Share of lookups served from the cache (0.0-1.0)
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+/// A cache entry as stored on disk
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    extractor_version: u32,
+    backend: String,
+    language: String,
+    atoms: Vec<ExtractedAtom>,
+}
+
+/// Size and last use of the entries on disk, by key
+#[derive(Default)]
+struct Index {
+    entries: HashMap<String, (u64, SystemTime)>,
+    total_bytes: u64,
+}
+
+/// On-disk cache of extracted atoms, keyed by language, atomizer
+/// configuration and content hash
+pub struct ParseCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    /// Loaded from the directory on first use
+    index: Mutex<Option<Index>>,
+    stats: Mutex<ParseCacheStats>,
+}
+
+impl ParseCache {
+    /// A cache in `dir`, bounded to [`DEFAULT_PARSE_CACHE_BYTES`]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_bytes: DEFAULT_PARSE_CACHE_BYTES,
+            index: Mutex::new(None),
+            stats: Mutex::new(ParseCacheStats::default()),
+        }
+    }
+
+    /// The cache of the project at `root`, in [`PARSE_CACHE_DIR`]
+    pub fn for_project(root: &Path) -> Self {
+        Self::new(root.join(PARSE_CACHE_DIR))
+    }
+
+    /// Bound the total size of the entries
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Hits and misses so far
+    pub fn stats(&self) -> ParseCacheStats {
+        *self.stats.lock().unwrap()
+    }
+
+    /// The atoms `extractor` extracts from `source`, from the cache when
+    /// possible. Fresh extractions are stored; failures are not.
+    pub fn extract(&self, extractor: &AtomExtractor, source: &str) -> CadiResult<Vec<ExtractedAtom>> {
+        let key = cache_key(extractor.language(), extractor.config(), source);
+        if let Some(atoms) = self.get(&key, extractor.language()) {
+            self.stats.lock().unwrap().hits += 1;
+            return Ok(atoms);
+        }
+        self.stats.lock().unwrap().misses += 1;
+
+        let atoms = extractor.extract_uncached(source)?;
+        if let Err(e) = self.put(&key, extractor.language(), &atoms) {
+            tracing::debug!("failed to write parse cache entry {}: {}", key, e);
+        }
+        Ok(atoms)
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    fn get(&self, key: &str, language: &str) -> Option<Vec<ExtractedAtom>> {
+        let path = self.path(key);
+        let bytes = std::fs::read(&path).ok()?;
+        let entry: Entry = match serde_json::from_slice(&bytes) {
+            Ok(entry) => entry,
+            Err(e) => {
+                tracing::debug!("ignoring corrupted parse cache entry {}: {}", path.display(), e);
+                return None;
+            }
+        };
+        if entry.extractor_version != EXTRACTOR_VERSION || entry.backend != BACKEND || entry.language != language {
+            return None;
+        }
+
+        // The modification time doubles as the last use, so the LRU order
+        // survives between runs
+        let now = SystemTime::now();
+        if let Ok(file) = std::fs::File::options().write(true).open(&path) {
+            let _ = file.set_modified(now);
+        }
+        let mut index = self.index.lock().unwrap();
+        let index = index.get_or_insert_with(|| self.load_index());
+        if let Some(entry) = index.entries.get_mut(key) {
+            entry.1 = now;
+        }
+        Some(entry.atoms)
+    }
+
+    fn put(&self, key: &str, language: &str, atoms: &[ExtractedAtom]) -> std::io::Result<()> {
+        let entry = Entry {
+            extractor_version: EXTRACTOR_VERSION,
+            backend: BACKEND.to_string(),
+            language: language.to_string(),
+            atoms: atoms.to_vec(),
+        };
+        let bytes = serde_json::to_vec(&entry)?;
+        std::fs::create_dir_all(&self.dir)?;
+
+        // Write through a temporary file so a crash never leaves a torn entry
+        let path = self.path(key);
+        let tmp = self.dir.join(format!("{}.tmp", key));
+        std::fs::write(&tmp, &bytes)?;
+        std::fs::rename(&tmp, &path)?;
+
+        let mut index = self.index.lock().unwrap();
+        let index = index.get_or_insert_with(|| self.load_index());
+        let size = bytes.len() as u64;
+        if let Some((old, _)) = index.entries.insert(key.to_string(), (size, SystemTime::now())) {
+            index.total_bytes -= old;
+        }
+        index.total_bytes += size;
+
+        let mut evicted = 0;
+        while index.total_bytes > self.max_bytes {
+            let Some(oldest) = index
+                .entries
+                .iter()
+                .filter(|(k, _)| k.as_str() != key)
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(k, _)| k.clone())
+            else {
+                break;
+            };
+            if let Some((size, _)) = index.entries.remove(&oldest) {
+                index.total_bytes -= size;
+            }
+            let _ = std::fs::remove_file(self.path(&oldest));
+            evicted += 1;
+        }
+        self.stats.lock().unwrap().evictions += evicted;
+        Ok(())
+    }
+
+    fn load_index(&self) -> Index {
+        let mut index = Index::default();
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return index;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let (Some(key), Ok(metadata)) = (path.file_stem().and_then(|s| s.to_str()), entry.metadata()) else {
+                continue;
+            };
+            let used = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            index.total_bytes += metadata.len();
+            index.entries.insert(key.to_string(), (metadata.len(), used));
+        }
+        index
+    }
+}
+
+/// Hash of everything extraction depends on besides the extractor version
+fn cache_key(language: &str, config: &AtomizerConfig, source: &str) -> String {
+    // Language settings are a HashMap, whose order varies between runs
+    let mut config = serde_json::to_value(config).unwrap_or_default();
+    config.sort_all_objects();
+    sha256_bytes(format!("{}\0{}\0{}", language, config, source).as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\npub fn sub(a: i32, b: i32) -> i32 {\n    a - b\n}\n";
+
+    fn cache_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cadi-parse-cache-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn entries(dir: &Path) -> Vec<PathBuf> {
+        std::fs::read_dir(dir).unwrap().map(|e| e.unwrap().path()).collect()
+    }
+
+    #[test]
+    fn test_parse_cache_hits_and_invalidation() {
+        let dir = cache_dir("hits");
+        let extractor = AtomExtractor::new("rust", AtomizerConfig::default());
+        let fresh = extractor.extract_uncached(SOURCE).unwrap();
+
+        let cache = ParseCache::new(&dir);
+        assert_eq!(cache.extract(&extractor, SOURCE).unwrap().len(), fresh.len());
+        let cached = cache.extract(&extractor, SOURCE).unwrap();
+        assert_eq!(cached.iter().map(|a| &a.name).collect::<Vec<_>>(), fresh.iter().map(|a| &a.name).collect::<Vec<_>>());
+        assert_eq!(cache.stats(), ParseCacheStats { hits: 1, misses: 1, evictions: 0 });
+
+        // Another run reads the same entry
+        let cache = ParseCache::new(&dir);
+        cache.extract(&extractor, SOURCE).unwrap();
+        assert_eq!(cache.stats().hits, 1);
+
+        // Corrupted entries and entries of another extractor version miss
+        let [entry] = entries(&dir).try_into().unwrap();
+        std::fs::write(&entry, b"{\"extractor_version\":").unwrap();
+        let cache = ParseCache::new(&dir);
+        assert_eq!(cache.extract(&extractor, SOURCE).unwrap().len(), fresh.len());
+        assert_eq!(cache.stats().misses, 1);
+
+        let mut stale: serde_json::Value = serde_json::from_slice(&std::fs::read(&entry).unwrap()).unwrap();
+        stale["extractor_version"] = (EXTRACTOR_VERSION + 1).into();
+        stale["atoms"] = serde_json::json!([]);
+        std::fs::write(&entry, stale.to_string()).unwrap();
+        let cache = ParseCache::new(&dir);
+        assert_eq!(cache.extract(&extractor, SOURCE).unwrap().len(), fresh.len());
+        assert_eq!(cache.stats(), ParseCacheStats { hits: 0, misses: 1, evictions: 0 });
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_parse_cache_evicts_least_recently_used() {
+        let dir = cache_dir("lru");
+        let extractor = AtomExtractor::new("rust", AtomizerConfig::default());
+        let sources: Vec<String> = (0..3).map(|i| SOURCE.replace("add", &format!("add{}", i))).collect();
+
+        let cache = ParseCache::new(&dir);
+        cache.extract(&extractor, &sources[0]).unwrap();
+        let entry_size = std::fs::metadata(&entries(&dir)[0]).unwrap().len();
+
+        // Room for two entries; using the first again makes the second the
+        // least recently used
+        let cache = ParseCache::new(&dir).with_max_bytes(entry_size * 2 + entry_size / 2);
+        cache.extract(&extractor, &sources[1]).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        cache.extract(&extractor, &sources[0]).unwrap();
+        cache.extract(&extractor, &sources[2]).unwrap();
+        assert_eq!(cache.stats(), ParseCacheStats { hits: 1, misses: 2, evictions: 1 });
+        assert_eq!(entries(&dir).len(), 2);
+
+        cache.extract(&extractor, &sources[0]).unwrap();
+        cache.extract(&extractor, &sources[1]).unwrap();
+        assert_eq!(cache.stats().hits, 2);
+        assert_eq!(cache.stats().misses, 3);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
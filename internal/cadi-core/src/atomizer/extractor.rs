@@ -4,9 +4,19 @@
 
 use serde::{Deserialize, Serialize};
 
+use super::cache::ParseCache;
 use super::config::AtomizerConfig;
 use super::resolver::SymbolResolver;
+use crate::atomic::{AtomicChunk, ChunkAlias, ChunkGranularity};
 use crate::error::{CadiError, CadiResult};
+use crate::hash::{chunk_id_from_hash, sha256_bytes};
+use crate::smart_chunker::to_kebab_case;
+use std::sync::Arc;
+
+/// Version of the extraction output. Bump it whenever a change to the
+/// extractors changes the atoms they produce, so stale parse cache entries
+/// are ignored.
+pub const EXTRACTOR_VERSION: u32 = 2;
 
 /// Kind of code atom
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -131,11 +141,23 @@ pub enum Visibility {
     Protected,
 }
 
+/// Whether atoms of `language` are extracted with tree-sitter rather
+/// than the regex fallbacks
+pub fn has_tree_sitter_support(language: &str) -> bool {
+    cfg!(feature = "ast-parsing")
+        && matches!(
+            language,
+            "rust" | "c" | "cpp" | "csharp" | "css" | "glsl" | "typescript" | "javascript" | "tsx" | "jsx"
+                | "python" | "html" | "go"
+        )
+}
+
 /// Atom extractor for a specific language
 pub struct AtomExtractor {
     #[allow(unused)]
     config: AtomizerConfig,
     language: String,
+    cache: Option<Arc<ParseCache>>,
 }
 
 impl AtomExtractor {
@@ -144,14 +166,40 @@ impl AtomExtractor {
         Self {
             config,
             language: language.into(),
+            cache: None,
         }
     }
 
+    /// Look up and store extracted atoms in a parse cache
+    pub fn with_cache(mut self, cache: Arc<ParseCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Language this extractor parses
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Configuration extraction runs with
+    pub fn config(&self) -> &AtomizerConfig {
+        &self.config
+    }
+
     /// Extract atoms from source code
     /// 
     /// When the `ast-parsing` feature is enabled, this uses Tree-sitter.
-    /// Otherwise, falls back to regex-based extraction.
+    /// Otherwise, falls back to regex-based extraction. With a parse cache,
+    /// unchanged sources are not parsed again.
     pub fn extract(&self, source: &str) -> CadiResult<Vec<ExtractedAtom>> {
+        match &self.cache {
+            Some(cache) => cache.extract(self, source),
+            None => self.extract_uncached(source),
+        }
+    }
+
+    /// Extract atoms without consulting the parse cache
+    pub(crate) fn extract_uncached(&self, source: &str) -> CadiResult<Vec<ExtractedAtom>> {
         #[cfg(feature = "ast-parsing")]
         {
             use crate::atomizer::languages::*;
@@ -184,12 +232,13 @@ impl AtomExtractor {
             }
 
             match self.language.as_str() {
+                "html" => return crate::atomizer::languages::HtmlAtomizer::new(self.config.clone()).extract(source),
+                "css" => return crate::atomizer::languages::CssAtomizer::new(self.config.clone()).extract(source),
                 "rust" => self.extract_rust(source),
                 "typescript" | "javascript" => self.extract_typescript(source),
                 "python" => self.extract_python(source),
                 "c" | "cpp" => self.extract_c(source),
                 "csharp" => self.extract_csharp(source),
-                "css" => self.extract_css(source),
                 "glsl" => self.extract_glsl(source),
                 _ => self.extract_fallback(source),
             }
@@ -312,28 +361,29 @@ impl AtomExtractor {
         for cap in fn_regex.captures_iter(source) {
             let name = cap.get(5).map(|m| m.as_str()).unwrap_or("unknown");
             let is_async = cap.get(4).is_some();
-            let is_pub = source[..cap.get(0).unwrap().start()]
+            // The signature is the match's last line, after any doc comments
+            let is_pub = cap[0]
                 .lines()
                 .last()
-                .map(|l| l.contains("pub"))
+                .map(|l| l.trim_start().starts_with("pub"))
                 .unwrap_or(false);
 
             let start_byte = cap.get(0).unwrap().start();
             let end_byte = self.find_block_end(source, start_byte);
             
-            let start_line = source[..start_byte].matches('\n').count() + 1;
-            let end_line = source[..end_byte].matches('\n').count() + 1;
+            let start_line = span(source, 0, start_byte)?.matches('\n').count() + 1;
+            let end_line = span(source, 0, end_byte)?.matches('\n').count() + 1;
 
             atoms.push(ExtractedAtom {
                 name: name.to_string(),
                 kind: if is_async { AtomKind::AsyncFunction } else { AtomKind::Function },
-                source: source[start_byte..end_byte].to_string(),
+                source: span(source, start_byte, end_byte)?.to_string(),
                 start_byte,
                 end_byte,
                 start_line,
                 end_line,
                 defines: vec![name.to_string()],
-                references: self.extract_references(&source[start_byte..end_byte]),
+                references: self.extract_references(span(source, start_byte, end_byte)?),
                 doc_comment: self.extract_doc_comment(source, start_byte),
                 visibility: if is_pub { Visibility::Public } else { Visibility::Private },
                 parent: None,
@@ -350,13 +400,13 @@ impl AtomExtractor {
             atoms.push(ExtractedAtom {
                 name: name.to_string(),
                 kind: AtomKind::Struct,
-                source: source[start_byte..end_byte].to_string(),
+                source: span(source, start_byte, end_byte)?.to_string(),
                 start_byte,
                 end_byte,
-                start_line: source[..start_byte].matches('\n').count() + 1,
-                end_line: source[..end_byte].matches('\n').count() + 1,
+                start_line: span(source, 0, start_byte)?.matches('\n').count() + 1,
+                end_line: span(source, 0, end_byte)?.matches('\n').count() + 1,
                 defines: vec![name.to_string()],
-                references: self.extract_references(&source[start_byte..end_byte]),
+                references: self.extract_references(span(source, start_byte, end_byte)?),
                 doc_comment: self.extract_doc_comment(source, start_byte),
                 visibility: Visibility::Public, // simplified
                 parent: None,
@@ -375,11 +425,11 @@ impl AtomExtractor {
         let mut atoms = Vec::new();
 
         let fn_regex = regex::Regex::new(
-            r"(?m)^(\s*)(export\s+)?(async\s+)?function\s+(\w+)"
+            r"(?m)^([ \t]*)(export\s+)?(async\s+)?function\s+(\w+)"
         ).map_err(|e| CadiError::AtomizerError(e.to_string()))?;
 
         let class_regex = regex::Regex::new(
-            r"(?m)^(\s*)(export\s+)?class\s+(\w+)"
+            r"(?m)^([ \t]*)(export\s+)?(?:default\s+)?(?:abstract\s+)?class\s+(\w+)"
         ).map_err(|e| CadiError::AtomizerError(e.to_string()))?;
 
         let _interface_regex = regex::Regex::new(
@@ -387,7 +437,7 @@ impl AtomExtractor {
         ).map_err(|e| CadiError::AtomizerError(e.to_string()))?;
 
         let const_regex = regex::Regex::new(
-            r"(?m)^(\s*)(export\s+)?const\s+(\w+).*?="
+            r"(?m)^([ \t]*)(export\s+)?const\s+(\w+).*?="
         ).map_err(|e| CadiError::AtomizerError(e.to_string()))?;
 
         // Extract functions
@@ -396,17 +446,17 @@ impl AtomExtractor {
             let is_async = cap.get(3).is_some();
             let is_export = cap.get(2).is_some();
 
-            let start_byte = cap.get(0).unwrap().start();
-            let end_byte = self.find_block_end(source, start_byte);
+            let start_byte = cap.get(1).unwrap().end();
+            let end_byte = self.find_ts_block_end(source, start_byte);
 
             atoms.push(ExtractedAtom {
                 name: name.to_string(),
                 kind: if is_async { AtomKind::AsyncFunction } else { AtomKind::Function },
-                source: source[start_byte..end_byte].to_string(),
+                source: span(source, start_byte, end_byte)?.to_string(),
                 start_byte,
                 end_byte,
-                start_line: source[..start_byte].matches('\n').count() + 1,
-                end_line: source[..end_byte].matches('\n').count() + 1,
+                start_line: span(source, 0, start_byte)?.matches('\n').count() + 1,
+                end_line: span(source, 0, end_byte)?.matches('\n').count() + 1,
                 defines: vec![name.to_string()],
                 references: self.extract_ts_imports(source) // simplified
                     .into_iter()
@@ -422,41 +472,55 @@ impl AtomExtractor {
         // Classes, interfaces, etc.
         for cap in class_regex.captures_iter(source) {
             let name = cap.get(3).map(|m| m.as_str()).unwrap_or("unknown");
-            let start_byte = cap.get(0).unwrap().start();
-            let end_byte = self.find_block_end(source, start_byte);
+            let class_start = cap.get(1).unwrap().end();
+            let end_byte = self.find_ts_block_end(source, class_start);
+            // Decorators are part of the class; without them the atom
+            // loses its framework metadata and no longer compiles
+            let (start_byte, decorators) = ts_leading_decorators(source, class_start);
 
             atoms.push(ExtractedAtom {
                 name: name.to_string(),
                 kind: AtomKind::Class,
-                source: source[start_byte..end_byte].to_string(),
+                source: span(source, start_byte, end_byte)?.to_string(),
                 start_byte,
                 end_byte,
-                start_line: source[..start_byte].matches('\n').count() + 1,
-                end_line: source[..end_byte].matches('\n').count() + 1,
+                start_line: span(source, 0, start_byte)?.matches('\n').count() + 1,
+                end_line: span(source, 0, end_byte)?.matches('\n').count() + 1,
                 defines: vec![name.to_string()],
                 references: Vec::new(),
                 doc_comment: None,
                 visibility: Visibility::Public,
                 parent: None,
-                decorators: Vec::new(),
+                decorators,
             });
         }
 
-        // Constants
+        // Constants and arrow functions
         for cap in const_regex.captures_iter(source) {
             let name = cap.get(3).map(|m| m.as_str()).unwrap_or("unknown");
             let is_export = cap.get(2).is_some();
-            let start_byte = cap.get(0).unwrap().start();
-            let end_byte = self.find_statement_end(source, start_byte);
+            let start_byte = cap.get(1).unwrap().end();
+            let init_byte = cap.get(0).unwrap().end();
+            let end_byte = self.find_ts_statement_end(source, start_byte);
+
+            let kind = if self.is_ts_arrow(source, init_byte, end_byte) {
+                if source[init_byte..].trim_start().starts_with("async") {
+                    AtomKind::AsyncFunction
+                } else {
+                    AtomKind::Function
+                }
+            } else {
+                AtomKind::Constant
+            };
 
             atoms.push(ExtractedAtom {
                 name: name.to_string(),
-                kind: AtomKind::Constant,
-                source: source[start_byte..end_byte].to_string(),
+                kind,
+                source: span(source, start_byte, end_byte)?.to_string(),
                 start_byte,
                 end_byte,
-                start_line: source[..start_byte].matches('\n').count() + 1,
-                end_line: source[..end_byte].matches('\n').count() + 1,
+                start_line: span(source, 0, start_byte)?.matches('\n').count() + 1,
+                end_line: span(source, 0, end_byte)?.matches('\n').count() + 1,
                 defines: vec![name.to_string()],
                 references: Vec::new(),
                 doc_comment: None,
@@ -470,64 +534,97 @@ impl AtomExtractor {
     }
 
     /// Extract atoms from Python source
+    ///
+    /// Methods become child atoms of their class, decorators are part of
+    /// the atom they decorate, and names imported with `from x import y`
+    /// are recorded as references on the atoms that use them.
     fn extract_python(&self, source: &str) -> CadiResult<Vec<ExtractedAtom>> {
         let mut atoms = Vec::new();
 
-        let fn_regex = regex::Regex::new(
-            r"(?m)^(\s*)(async\s+)?def\s+(\w+)\s*\("
+        let def_regex = regex::Regex::new(
+            r"(?m)^([ \t]*)(?:(async\s+)?def\s+(\w+)\s*\(|class\s+(\w+))"
         ).map_err(|e| CadiError::AtomizerError(e.to_string()))?;
 
-        let class_regex = regex::Regex::new(
-            r"(?m)^(\s*)class\s+(\w+)"
-        ).map_err(|e| CadiError::AtomizerError(e.to_string()))?;
-
-        for cap in fn_regex.captures_iter(source) {
+        let exported = python_all(source);
+        let imported: Vec<String> = SymbolResolver::new(std::path::PathBuf::from("/"), "python")
+            .extract_imports(source)
+            .into_iter()
+            .filter(|import| !import.is_namespace)
+            .flat_map(|import| import.symbols)
+            .map(|symbol| symbol.alias.unwrap_or(symbol.name))
+            .filter(|name| name != "*")
+            .collect();
+
+        // Enclosing definitions: indent, block end, name, whether it is a
+        // class, and its visibility
+        let mut scopes: Vec<(usize, usize, String, bool, Visibility)> = Vec::new();
+
+        for cap in def_regex.captures_iter(source) {
             let indent = cap.get(1).map(|m| m.as_str().len()).unwrap_or(0);
-            let name = cap.get(3).map(|m| m.as_str()).unwrap_or("unknown");
-            let is_async = cap.get(2).is_some();
+            let line_start = cap.get(0).unwrap().start();
+            let end_byte = self.find_python_block_end(source, line_start, indent);
+            let is_class = cap.get(4).is_some();
+            let name = cap.get(3).or(cap.get(4)).map(|m| m.as_str()).unwrap_or("unknown");
 
-            let start_byte = cap.get(0).unwrap().start();
-            let end_byte = self.find_python_block_end(source, start_byte, indent);
-
-            atoms.push(ExtractedAtom {
-                name: name.to_string(),
-                kind: if is_async { AtomKind::AsyncFunction } else { AtomKind::Function },
-                source: source[start_byte..end_byte].to_string(),
-                start_byte,
-                end_byte,
-                start_line: source[..start_byte].matches('\n').count() + 1,
-                end_line: source[..end_byte].matches('\n').count() + 1,
-                defines: vec![name.to_string()],
-                references: Vec::new(),
-                doc_comment: self.extract_python_docstring(source, start_byte),
-                visibility: if name.starts_with('_') { Visibility::Private } else { Visibility::Public },
-                parent: None,
-                decorators: Vec::new(),
-            });
-        }
+            while scopes.last().is_some_and(|&(scope_indent, scope_end, ..)| scope_indent >= indent || scope_end <= line_start) {
+                scopes.pop();
+            }
+            let enclosing = scopes.last();
+            let parent = enclosing.map(|(_, _, name, ..)| name.clone());
+            let in_class = enclosing.is_some_and(|&(_, _, _, is_class, _)| is_class);
+
+            let visibility = if name.starts_with('_') && !(name.starts_with("__") && name.ends_with("__")) {
+                Visibility::Private
+            } else if let Some(&(.., scope_visibility)) = enclosing {
+                scope_visibility
+            } else if exported.as_ref().is_some_and(|names| !names.contains(name)) {
+                Visibility::Internal
+            } else {
+                Visibility::Public
+            };
 
-        for cap in class_regex.captures_iter(source) {
-            let indent = cap.get(1).map(|m| m.as_str().len()).unwrap_or(0);
-            let name = cap.get(2).map(|m| m.as_str()).unwrap_or("unknown");
+            let kind = if is_class {
+                AtomKind::Class
+            } else if in_class {
+                AtomKind::Method
+            } else if cap.get(2).is_some() {
+                AtomKind::AsyncFunction
+            } else {
+                AtomKind::Function
+            };
 
-            let start_byte = cap.get(0).unwrap().start();
-            let end_byte = self.find_python_block_end(source, start_byte, indent);
+            // Decorators are part of the definition
+            let (start_byte, decorators) = python_leading_decorators(source, line_start, indent);
+            let atom_source = span(source, start_byte, end_byte)?;
+
+            let used: std::collections::HashSet<&str> = atom_source
+                .split(|c: char| !c.is_alphanumeric() && c != '_')
+                .collect();
+            let mut references: Vec<String> = imported
+                .iter()
+                .filter(|import| import.as_str() != name && used.contains(import.as_str()))
+                .cloned()
+                .collect();
+            references.sort();
+            references.dedup();
 
             atoms.push(ExtractedAtom {
                 name: name.to_string(),
-                kind: AtomKind::Class,
-                source: source[start_byte..end_byte].to_string(),
+                kind,
+                source: atom_source.to_string(),
                 start_byte,
                 end_byte,
-                start_line: source[..start_byte].matches('\n').count() + 1,
-                end_line: source[..end_byte].matches('\n').count() + 1,
+                start_line: span(source, 0, start_byte)?.matches('\n').count() + 1,
+                end_line: span(source, 0, end_byte)?.matches('\n').count() + 1,
                 defines: vec![name.to_string()],
-                references: Vec::new(),
-                doc_comment: None,
-                visibility: Visibility::Public,
-                parent: None,
-                decorators: Vec::new(),
+                references,
+                doc_comment: self.extract_python_docstring(source, line_start),
+                visibility,
+                parent,
+                decorators,
             });
+
+            scopes.push((indent, end_byte, name.to_string(), is_class, visibility));
         }
 
         Ok(atoms)
@@ -537,8 +634,10 @@ impl AtomExtractor {
     fn extract_c(&self, source: &str) -> CadiResult<Vec<ExtractedAtom>> {
         // Simplified fallback: extract functions and structs
         let mut atoms = Vec::new();
-        let fn_regex = regex::Regex::new(r"(?m)^\s*(\w+)\s+(\w+)\s*\([^)]*\)\s*\{").unwrap();
-        let struct_regex = regex::Regex::new(r"(?m)^\s*struct\s+(\w+)\s*\{").unwrap();
+        let fn_regex = regex::Regex::new(r"(?m)^\s*(\w+)\s+(\w+)\s*\([^)]*\)\s*\{")
+            .map_err(|e| CadiError::AtomizerError(e.to_string()))?;
+        let struct_regex = regex::Regex::new(r"(?m)^\s*struct\s+(\w+)\s*\{")
+            .map_err(|e| CadiError::AtomizerError(e.to_string()))?;
 
         for cap in fn_regex.captures_iter(source) {
             let name = cap.get(2).map(|m| m.as_str()).unwrap_or("unknown");
@@ -548,11 +647,11 @@ impl AtomExtractor {
             atoms.push(ExtractedAtom {
                 name: name.to_string(),
                 kind: AtomKind::Function,
-                source: source[start_byte..end_byte].to_string(),
+                source: span(source, start_byte, end_byte)?.to_string(),
                 start_byte,
                 end_byte,
-                start_line: source[..start_byte].matches('\n').count() + 1,
-                end_line: source[..end_byte].matches('\n').count() + 1,
+                start_line: span(source, 0, start_byte)?.matches('\n').count() + 1,
+                end_line: span(source, 0, end_byte)?.matches('\n').count() + 1,
                 defines: vec![name.to_string()],
                 references: Vec::new(),
                 doc_comment: None,
@@ -570,11 +669,11 @@ impl AtomExtractor {
             atoms.push(ExtractedAtom {
                 name: name.to_string(),
                 kind: AtomKind::Struct,
-                source: source[start_byte..end_byte].to_string(),
+                source: span(source, start_byte, end_byte)?.to_string(),
                 start_byte,
                 end_byte,
-                start_line: source[..start_byte].matches('\n').count() + 1,
-                end_line: source[..end_byte].matches('\n').count() + 1,
+                start_line: span(source, 0, start_byte)?.matches('\n').count() + 1,
+                end_line: span(source, 0, end_byte)?.matches('\n').count() + 1,
                 defines: vec![name.to_string()],
                 references: Vec::new(),
                 doc_comment: None,
@@ -590,8 +689,10 @@ impl AtomExtractor {
     /// Extract atoms from C# source
     fn extract_csharp(&self, source: &str) -> CadiResult<Vec<ExtractedAtom>> {
         let mut atoms = Vec::new();
-        let class_regex = regex::Regex::new(r"(?m)^(\s*)(?:public|private|internal|protected)?\s+class\s+(\w+)").unwrap();
-        let method_regex = regex::Regex::new(r"(?m)^(\s*)(?:public|private|internal|protected)?\s+(?:\w+\s+)*(\w+)\s*\([^)]*\)\s*\{").unwrap();
+        let class_regex = regex::Regex::new(r"(?m)^(\s*)(?:public|private|internal|protected)?\s+class\s+(\w+)")
+            .map_err(|e| CadiError::AtomizerError(e.to_string()))?;
+        let method_regex = regex::Regex::new(r"(?m)^(\s*)(?:public|private|internal|protected)?\s+(?:\w+\s+)*(\w+)\s*\([^)]*\)\s*\{")
+            .map_err(|e| CadiError::AtomizerError(e.to_string()))?;
 
         for cap in class_regex.captures_iter(source) {
             let name = cap.get(2).map(|m| m.as_str()).unwrap_or("unknown");
@@ -601,11 +702,11 @@ impl AtomExtractor {
             atoms.push(ExtractedAtom {
                 name: name.to_string(),
                 kind: AtomKind::Class,
-                source: source[start_byte..end_byte].to_string(),
+                source: span(source, start_byte, end_byte)?.to_string(),
                 start_byte,
                 end_byte,
-                start_line: source[..start_byte].matches('\n').count() + 1,
-                end_line: source[..end_byte].matches('\n').count() + 1,
+                start_line: span(source, 0, start_byte)?.matches('\n').count() + 1,
+                end_line: span(source, 0, end_byte)?.matches('\n').count() + 1,
                 defines: vec![name.to_string()],
                 references: Vec::new(),
                 doc_comment: None,
@@ -623,11 +724,11 @@ impl AtomExtractor {
             atoms.push(ExtractedAtom {
                 name: name.to_string(),
                 kind: AtomKind::Function,
-                source: source[start_byte..end_byte].to_string(),
+                source: span(source, start_byte, end_byte)?.to_string(),
                 start_byte,
                 end_byte,
-                start_line: source[..start_byte].matches('\n').count() + 1,
-                end_line: source[..end_byte].matches('\n').count() + 1,
+                start_line: span(source, 0, start_byte)?.matches('\n').count() + 1,
+                end_line: span(source, 0, end_byte)?.matches('\n').count() + 1,
                 defines: vec![name.to_string()],
                 references: Vec::new(),
                 doc_comment: None,
@@ -640,35 +741,6 @@ impl AtomExtractor {
         Ok(atoms)
     }
 
-    /// Extract atoms from CSS source
-    fn extract_css(&self, source: &str) -> CadiResult<Vec<ExtractedAtom>> {
-        let mut atoms = Vec::new();
-        let rule_regex = regex::Regex::new(r"(?m)^([^{]+)\{").unwrap();
-
-        for cap in rule_regex.captures_iter(source) {
-            let name = cap.get(1).map(|m| m.as_str().trim()).unwrap_or("rule");
-            let start_byte = cap.get(0).unwrap().start();
-            let end_byte = self.find_block_end(source, start_byte);
-
-            atoms.push(ExtractedAtom {
-                name: name.to_string(),
-                kind: AtomKind::Constant, // CSS rules are roughly constants/styles
-                source: source[start_byte..end_byte].to_string(),
-                start_byte,
-                end_byte,
-                start_line: source[..start_byte].matches('\n').count() + 1,
-                end_line: source[..end_byte].matches('\n').count() + 1,
-                defines: Vec::new(),
-                references: Vec::new(),
-                doc_comment: None,
-                visibility: Visibility::Public,
-                parent: None,
-                decorators: Vec::new(),
-            });
-        }
-        Ok(atoms)
-    }
-
     /// Extract atoms from GLSL source
     fn extract_glsl(&self, source: &str) -> CadiResult<Vec<ExtractedAtom>> {
         self.extract_c(source)
@@ -732,41 +804,125 @@ impl AtomExtractor {
         source.len()
     }
 
-    /// Find the end of a statement (semicolon or newline)
-    fn find_statement_end(&self, source: &str, start: usize) -> usize {
-        for (i, c) in source[start..].char_indices() {
-            if c == ';' || c == '\n' {
-                return start + i + 1;
+    /// Find the end of a TS/JS brace-delimited item starting at `start`.
+    ///
+    /// The body opens at the first `{` outside parentheses and type
+    /// parameters, so default parameters like `(opts = {})` and generics
+    /// like `<T extends { id: string }>` don't close the item early.
+    fn find_ts_block_end(&self, source: &str, start: usize) -> usize {
+        let mut parens = 0i32;
+        let mut angles = 0i32;
+        let mut depth = 0i32;
+        let mut prev = ' ';
+
+        for (i, c) in TsCodeChars::new(source, start) {
+            let signature = depth == 0 && parens == 0;
+            match c {
+                '(' => parens += 1,
+                ')' => parens -= 1,
+                '<' if signature => angles += 1,
+                // `=>` in a function type isn't a closing bracket
+                '>' if signature && angles > 0 && prev != '=' => angles -= 1,
+                '{' if (signature && angles == 0) || depth > 0 => depth += 1,
+                '}' if depth > 0 => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return i + 1;
+                    }
+                }
+                ';' if signature && angles == 0 => return i + 1,
+                _ => {}
+            }
+            if !c.is_whitespace() {
+                prev = c;
             }
         }
+
         source.len()
     }
 
+    /// Find the end of a TS/JS statement: a `;` or line break outside any
+    /// brackets. A line ending in an operator or `=>` continues onto the
+    /// next line. The returned end excludes the trailing newline.
+    fn find_ts_statement_end(&self, source: &str, start: usize) -> usize {
+        let mut depth = 0i32;
+        let mut last = ' ';
+        let mut arrow = false;
+
+        for (i, c) in TsCodeChars::new(source, start) {
+            match c {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => {
+                    depth -= 1;
+                    // The enclosing block closed without a terminator
+                    if depth < 0 {
+                        return i;
+                    }
+                }
+                ';' if depth == 0 => return i + 1,
+                '\n' if depth == 0 && !arrow && !"=(,[{+-*/?:|&.".contains(last) => {
+                    let next_line = source[i + 1..].trim_start();
+                    if !next_line.starts_with('.') && !next_line.starts_with('?') {
+                        return i;
+                    }
+                }
+                _ => {}
+            }
+            if !c.is_whitespace() {
+                arrow = last == '=' && c == '>';
+                last = c;
+            }
+        }
+
+        source.len()
+    }
+
+    /// Does the initializer in `start..end` contain a top-level `=>`?
+    fn is_ts_arrow(&self, source: &str, start: usize, end: usize) -> bool {
+        let mut depth = 0i32;
+        let mut prev = ' ';
+
+        for (i, c) in TsCodeChars::new(source, start) {
+            if i >= end {
+                break;
+            }
+            match c {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth -= 1,
+                '>' if prev == '=' && depth == 0 => return true,
+                _ => {}
+            }
+            prev = c;
+        }
+
+        false
+    }
+
     /// Find the end of a Python block (indentation-based)
+    ///
+    /// Lines are measured with their terminators, so `\r\n` line endings
+    /// don't shift the end off a character boundary.
     fn find_python_block_end(&self, source: &str, start: usize, base_indent: usize) -> usize {
-        let lines: Vec<&str> = source[start..].lines().collect();
         let mut end = start;
         let mut started = false;
 
-        for line in lines {
+        for line in source.get(start..).unwrap_or_default().split_inclusive('\n') {
             if line.trim().is_empty() {
-                end += line.len() + 1;
+                end += line.len();
                 continue;
             }
 
             let indent = line.len() - line.trim_start().len();
-            
-            if !started {
+
+            if !started || indent > base_indent {
                 started = true;
-                end += line.len() + 1;
-            } else if indent > base_indent {
-                end += line.len() + 1;
+                end += line.len();
             } else {
                 break;
             }
         }
 
-        end.min(source.len())
+        end
     }
 
     /// Extract references from Rust code
@@ -874,6 +1030,132 @@ impl AtomExtractor {
     }
 }
 
+/// Decorators directly before the TS item at `start`, in source order, and
+/// the byte where the first of them begins (`start` if there are none).
+/// Decorator arguments may span lines.
+fn ts_leading_decorators(source: &str, start: usize) -> (usize, Vec<String>) {
+    let mut decorators = Vec::new();
+    let mut begin = start;
+
+    loop {
+        let before = source[..begin].trim_end();
+        let end = before.len();
+
+        // Arguments, if any
+        let mut name_end = end;
+        if before.ends_with(')') {
+            let mut depth = 0i32;
+            let open = before.char_indices().rev().find(|&(_, c)| {
+                match c {
+                    ')' => depth += 1,
+                    '(' => depth -= 1,
+                    _ => {}
+                }
+                depth == 0
+            });
+            match open {
+                Some((open, _)) => name_end = before[..open].trim_end().len(),
+                None => break,
+            }
+        }
+
+        // The possibly qualified name, preceded by `@`
+        let name_start = before[..name_end]
+            .trim_end_matches(|c: char| c.is_alphanumeric() || c == '_' || c == '$' || c == '.')
+            .len();
+        if name_start == name_end || !before[..name_start].ends_with('@') {
+            break;
+        }
+        begin = name_start - 1;
+        decorators.push(before[begin..end].to_string());
+    }
+
+    decorators.reverse();
+    (begin, decorators)
+}
+
+/// Decorators directly above the Python definition whose line starts at
+/// `start`, in source order, and the byte where the first of them begins
+/// (`start` if there are none). Decorator arguments may span lines.
+fn python_leading_decorators(source: &str, start: usize, indent: usize) -> (usize, Vec<String>) {
+    let mut decorators = Vec::new();
+    let mut begin = start;
+    let mut cursor = start;
+
+    while cursor > 0 {
+        let line_start = source[..cursor - 1].rfind('\n').map_or(0, |i| i + 1);
+        let line = &source[line_start..cursor];
+        let trimmed = line.trim();
+        let line_indent = line.len() - line.trim_start().len();
+
+        if line_indent == indent && trimmed.starts_with('@') {
+            decorators.push(source[line_start..begin].trim().to_string());
+            begin = line_start;
+        } else if trimmed.is_empty() || trimmed.starts_with('#') || (line_indent < indent || (line_indent == indent && !trimmed.starts_with([')', ']', '}']))) {
+            // Anything but a decorator or the continuation of its arguments
+            break;
+        }
+        cursor = line_start;
+    }
+
+    decorators.reverse();
+    (begin, decorators)
+}
+
+/// Names listed in a Python module's `__all__`, or `None` if it doesn't
+/// declare one
+fn python_all(source: &str) -> Option<std::collections::HashSet<String>> {
+    let all_regex = regex::Regex::new(r"(?ms)^__all__\s*(?::[^=\n]*)?\+?=\s*[\[(](.*?)[\])]").ok()?;
+    let name_regex = regex::Regex::new(r#"["'](\w+)["']"#).ok()?;
+
+    let mut names = None;
+    for cap in all_regex.captures_iter(source) {
+        let list = cap.get(1).map(|m| m.as_str()).unwrap_or_default();
+        names
+            .get_or_insert_with(std::collections::HashSet::new)
+            .extend(name_regex.captures_iter(list).map(|name| name[1].to_string()));
+    }
+    names
+}
+
+/// `source[start..end]`, or an error if the range is out of bounds or not
+/// on character boundaries
+fn span(source: &str, start: usize, end: usize) -> CadiResult<&str> {
+    source.get(start..end).ok_or_else(|| {
+        CadiError::AtomizerError(format!(
+            "byte range {}..{} does not fall on character boundaries of a {}-byte source",
+            start,
+            end,
+            source.len()
+        ))
+    })
+}
+
+/// Search concepts implied by framework decorators, such as "component"
+/// for Angular's `@Component` or "controller" for NestJS's `@Controller`
+pub fn decorator_concepts(decorators: &[String]) -> Vec<String> {
+    let mut concepts: Vec<String> = Vec::new();
+    for decorator in decorators {
+        let name = decorator.trim_start_matches('@').split('(').next().unwrap_or_default().trim();
+        let concept = match name.rsplit('.').next().unwrap_or(name) {
+            "Component" => "component",
+            "Directive" => "directive",
+            "Pipe" => "pipe",
+            "NgModule" | "Module" => "module",
+            "Injectable" => "injectable",
+            "Controller" => "controller",
+            "Resolver" => "resolver",
+            "Entity" => "entity",
+            "Get" | "Post" | "Put" | "Patch" | "Delete" | "Options" | "Head" | "All" => "route",
+            _ => continue,
+        };
+        if !concepts.iter().any(|c| c == concept) {
+            concepts.push(concept.to_string());
+        }
+    }
+    concepts
+}
+
 impl ExtractedAtom {
     /// Get the number of lines in this atom
     pub fn line_count(&self) -> usize {
@@ -889,6 +1171,153 @@ impl ExtractedAtom {
     pub fn is_public(&self) -> bool {
         self.visibility == Visibility::Public
     }
+
+    /// Convert into a content-addressed chunk aliased as `namespace/name`
+    ///
+    /// The chunk ID depends only on the atom's source, so identical atoms
+    /// always map to the same chunk.
+    pub fn to_atomic_chunk(&self, language: &str, namespace: Option<&str>) -> AtomicChunk {
+        let content_hash = sha256_bytes(self.source.as_bytes());
+        let granularity = if self.kind.is_type() {
+            ChunkGranularity::Type
+        } else if self.kind.is_dependency() {
+            ChunkGranularity::Import
+        } else if self.kind == AtomKind::Module {
+            ChunkGranularity::Module
+        } else {
+            ChunkGranularity::Function
+        };
+
+        let mut alias = ChunkAlias::new(to_kebab_case(&self.name));
+        if let Some(namespace) = namespace {
+            alias = alias.with_namespace(namespace);
+        }
+
+        let mut chunk = AtomicChunk::new(
+            chunk_id_from_hash(&content_hash),
+            self.name.clone(),
+            language.to_string(),
+            content_hash,
+            self.source.len(),
+        )
+        .with_granularity(granularity);
+        chunk.aliases.push(alias);
+        chunk.description = self.doc_comment.clone();
+        chunk.provides = self.defines.clone();
+        chunk.requires = self.references.clone();
+        chunk.concepts = decorator_concepts(&self.decorators);
+        chunk.metrics.loc = self.line_count();
+        chunk
+    }
+}
+
+/// Iterates over the code characters of TS/JS source, skipping comments,
+/// string contents and template literal text (but not `${}` expressions).
+///
+/// A quote with no closing quote on the same line is treated as plain text,
+/// which keeps apostrophes in JSX text (`<p>Don't</p>`) from swallowing the
+/// rest of the file.
+struct TsCodeChars<'a> {
+    source: &'a str,
+    pos: usize,
+    /// Brace depth inside each open `${`, innermost last
+    templates: Vec<usize>,
+    in_template_text: bool,
+}
+
+impl<'a> TsCodeChars<'a> {
+    fn new(source: &'a str, start: usize) -> Self {
+        Self {
+            source,
+            pos: start,
+            templates: Vec::new(),
+            in_template_text: false,
+        }
+    }
+
+    /// Byte offset just past the closing `quote` on the current line
+    fn string_end(&self, quote: char) -> Option<usize> {
+        let mut escaped = false;
+        for (i, c) in self.source[self.pos + 1..].char_indices() {
+            match c {
+                '\n' => return None,
+                '\\' if !escaped => escaped = true,
+                c if c == quote && !escaped => return Some(self.pos + 1 + i + 1),
+                _ => escaped = false,
+            }
+        }
+        None
+    }
+}
+
+impl Iterator for TsCodeChars<'_> {
+    type Item = (usize, char);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let rest = &self.source[self.pos..];
+            let c = rest.chars().next()?;
+
+            if self.in_template_text {
+                if c == '\\' {
+                    self.pos += 1 + rest[1..].chars().next().map_or(0, char::len_utf8);
+                } else if c == '`' {
+                    self.in_template_text = false;
+                    self.pos += 1;
+                } else if rest.starts_with("${") {
+                    self.templates.push(0);
+                    self.in_template_text = false;
+                    self.pos += 2;
+                } else {
+                    self.pos += c.len_utf8();
+                }
+                continue;
+            }
+
+            match c {
+                '/' if rest.starts_with("//") => {
+                    self.pos += rest.find('\n').unwrap_or(rest.len());
+                    continue;
+                }
+                '/' if rest.starts_with("/*") => {
+                    self.pos += rest.find("*/").map_or(rest.len(), |i| i + 2);
+                    continue;
+                }
+                '"' | '\'' => {
+                    if let Some(end) = self.string_end(c) {
+                        self.pos = end;
+                        continue;
+                    }
+                }
+                '`' => {
+                    self.in_template_text = true;
+                    self.pos += 1;
+                    continue;
+                }
+                '{' => {
+                    if let Some(depth) = self.templates.last_mut() {
+                        *depth += 1;
+                    }
+                }
+                '}' => {
+                    if let Some(depth) = self.templates.last_mut() {
+                        if *depth == 0 {
+                            self.templates.pop();
+                            self.in_template_text = true;
+                            self.pos += 1;
+                            continue;
+                        }
+                        *depth -= 1;
+                    }
+                }
+                _ => {}
+            }
+
+            let item = (self.pos, c);
+            self.pos += c.len_utf8();
+            return Some(item);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -935,6 +1364,97 @@ export class Greeter {
         assert!(!atoms.is_empty());
     }
 
+    #[test]
+    fn test_typescript_arrow_functions() {
+        let source = r#"export const load = async (opts = { retry: 1 }) => {
+    const url = `${opts.base}/items/{id}`;
+    return fetch(url, { method: 'GET' });
+};
+
+const square = (x: number) =>
+    x * x;
+
+export function withDefaults(config = {}) {
+    return { ...config };
+}
+
+const LIMIT = 10;
+"#;
+
+        let extractor = AtomExtractor::new("typescript", AtomizerConfig::default());
+        let atoms = extractor.extract(source).unwrap();
+        let atom = |name: &str| atoms.iter().find(|a| a.name == name).unwrap();
+
+        let load = atom("load");
+        assert_eq!(load.kind, AtomKind::AsyncFunction);
+        assert_eq!((load.start_line, load.end_line), (1, 4));
+        assert!(load.source.ends_with("};"));
+
+        let square = atom("square");
+        assert_eq!(square.kind, AtomKind::Function);
+        assert_eq!((square.start_line, square.end_line), (6, 7));
+
+        let with_defaults = atom("withDefaults");
+        assert_eq!((with_defaults.start_line, with_defaults.end_line), (9, 11));
+
+        assert_eq!(atom("LIMIT").kind, AtomKind::Constant);
+        assert_eq!(atom("LIMIT").source, "const LIMIT = 10;");
+    }
+
+    #[test]
+    fn test_typescript_decorators_and_generics() {
+        let source = r#"import { Component, Injectable } from '@angular/core';
+
+@Component({
+  selector: 'app-root',
+  template: '<h1 (click)="greet()">{{ title }}</h1>',
+})
+export class AppComponent {
+  title = 'app';
+}
+
+@Injectable()
+abstract class Store<T extends { id: string }> {
+  abstract load(): T[];
+}
+
+export function pick<T extends { id: string }>(items: T[], id: string): T | undefined {
+  return items.find((item) => item.id === id);
+}
+"#;
+
+        let extractor = AtomExtractor::new("typescript", AtomizerConfig::default());
+        let atoms = extractor.extract(source).unwrap();
+        let atom = |name: &str| atoms.iter().find(|a| a.name == name).unwrap();
+
+        let component = atom("AppComponent");
+        assert!(component.source.starts_with("@Component({\n  selector: 'app-root',"));
+        assert!(component.source.ends_with("title = 'app';\n}"));
+        assert_eq!((component.start_line, component.end_line), (3, 9));
+        assert_eq!(component.decorators.len(), 1);
+        assert!(component.decorators[0].starts_with("@Component({"));
+
+        let store = atom("Store");
+        assert_eq!(store.decorators, vec!["@Injectable()"]);
+        assert!(store.source.starts_with("@Injectable()\nabstract class Store<T extends { id: string }> {"));
+
+        let pick = atom("pick");
+        assert!(pick.source.starts_with("export function pick<T extends { id: string }>") || pick.source.starts_with("function pick<"));
+        assert!(pick.source.ends_with("item.id === id);\n}"));
+
+        let chunk = component.to_atomic_chunk("typescript", Some("app"));
+        assert_eq!(chunk.concepts, vec!["component"]);
+        assert_eq!(store.to_atomic_chunk("typescript", None).concepts, vec!["injectable"]);
+    }
+
+    #[test]
+    fn test_decorator_concepts() {
+        let decorators = ["@Controller('cats')", "@Get(':id')", "@nest.Post()", "@Get()", "@Memoize"]
+            .map(String::from);
+        assert_eq!(decorator_concepts(&decorators), vec!["controller", "route"]);
+        assert!(decorator_concepts(&[]).is_empty());
+    }
+
     #[test]
     fn test_python_extraction() {
         let source = r#"
@@ -952,4 +1472,77 @@ class Greeter:
 
         assert!(!atoms.is_empty());
     }
+
+    #[test]
+    fn test_python_methods_decorators_and_all() {
+        let source = r#"from django.http import JsonResponse
+from django.views import View
+from .forms import CommentForm
+
+__all__ = ["ArticleView"]
+
+
+class ArticleView(View):
+    @method_decorator(
+        login_required,
+    )
+    def get(self, request):
+        return JsonResponse({})
+
+    def _load(self):
+        return CommentForm()
+
+    def __str__(self):
+        return "articles"
+
+
+class DraftView(View):
+    pass
+
+
+@cache_page(60)
+def index(request):
+    return JsonResponse({})
+"#;
+
+        let extractor = AtomExtractor::new("python", AtomizerConfig::default());
+        let atoms = extractor.extract(source).unwrap();
+        let atom = |name: &str| atoms.iter().find(|a| a.name == name).unwrap();
+
+        let view = atom("ArticleView");
+        assert_eq!(view.kind, AtomKind::Class);
+        assert_eq!(view.visibility, Visibility::Public);
+        assert_eq!(view.references, vec!["CommentForm", "JsonResponse", "View"]);
+
+        let get = atom("get");
+        assert_eq!(get.kind, AtomKind::Method);
+        assert_eq!(get.parent.as_deref(), Some("ArticleView"));
+        assert_eq!(get.decorators, vec!["@method_decorator(\n        login_required,\n    )"]);
+        assert!(get.source.starts_with("    @method_decorator("));
+        assert_eq!(get.references, vec!["JsonResponse"]);
+        assert_eq!(get.visibility, Visibility::Public);
+
+        assert_eq!(atom("_load").visibility, Visibility::Private);
+        assert_eq!(atom("_load").references, vec!["CommentForm"]);
+        assert_eq!(atom("__str__").visibility, Visibility::Public);
+
+        // Not in `__all__`, so internal to the module
+        assert_eq!(atom("DraftView").visibility, Visibility::Internal);
+        let index = atom("index");
+        assert_eq!(index.visibility, Visibility::Internal);
+        assert_eq!(index.kind, AtomKind::Function);
+        assert_eq!(index.parent, None);
+        assert_eq!(index.decorators, vec!["@cache_page(60)"]);
+        assert_eq!(index.start_line, 26);
+    }
+
+    #[test]
+    fn test_python_block_end_with_crlf() {
+        let source = "def first():\r\n    return 1\r\n\r\n# café\r\ndef second():\r\n    pass\r\n";
+        let extractor = AtomExtractor::new("python", AtomizerConfig::default());
+
+        let end = extractor.find_python_block_end(source, 0, 0);
+        assert_eq!(end, source.find("# café").unwrap());
+        assert_eq!(extractor.find_python_block_end(source, source.find("def second").unwrap(), 0), source.len());
+    }
 }
@@ -1,14 +1,22 @@
 //! CSS-specific atomizer
+//!
+//! Rules become Constant atoms. `@media`, `@supports`, `@layer` and
+//! `@container` blocks become Module atoms with the rules inside them as
+//! children, rules nested inside rules are children of the enclosing rule,
+//! and `@import` statements become Import atoms referencing the imported
+//! stylesheet. Custom properties are tracked as symbols: a rule setting
+//! `--accent` defines it and a rule using `var(--accent)` references it.
 
-#[cfg(feature = "ast-parsing")]
-use crate::atomizer::{AtomizerConfig, ExtractedAtom, AtomKind};
-
-#[cfg(not(feature = "ast-parsing"))]
-use crate::atomizer::{AtomizerConfig, ExtractedAtom};
+use std::ops::Range;
 
+use crate::atomizer::{AtomizerConfig, ExtractedAtom, AtomKind};
+use crate::atomizer::extractor::Visibility;
 use crate::error::CadiResult;
 
-/// CSS-specific atomizer with Tree-sitter support
+/// At-rules whose blocks hold rules rather than declarations
+const CONTAINER_AT_RULES: &[&str] = &["@media", "@supports", "@layer", "@container", "@document", "@scope"];
+
+/// CSS-specific atomizer
 pub struct CssAtomizer {
     _config: AtomizerConfig,
 }
@@ -18,116 +26,214 @@ impl CssAtomizer {
         Self { _config: config }
     }
 
-    /// Extract atoms using Tree-sitter (when feature enabled)
-    #[cfg(feature = "ast-parsing")]
+    /// Extract atoms in source order, containers before their children
     pub fn extract(&self, source: &str) -> CadiResult<Vec<ExtractedAtom>> {
-        use tree_sitter::{Parser, Query, QueryCursor};
-        
-        let mut parser = Parser::new();
-        parser.set_language(&tree_sitter_css::language())?;
-        
-        let tree = parser.parse(source, None)
-            .ok_or_else(|| crate::error::CadiError::AtomizerError("Parse failed".into()))?;
-        
+        let code = blank_comments(source);
         let mut atoms = Vec::new();
-        
-        // Tree-sitter queries for CSS
-        let query_src = r#"
-            (rule_set
-                (selectors) @selector
-            ) @rule
-            
-            (media_statement) @media
-            
-            (keyframe_block_list) @keyframes
-
-            (import_statement) @import
-        "#;
-        
-        let query = Query::new(&tree_sitter_css::language(), query_src)?;
-        let mut cursor = QueryCursor::new();
-        
-        let matches = cursor.matches(&query, tree.root_node(), source.as_bytes());
-        
-        for m in matches {
-            let mut name = "rule".to_string();
-            let mut kind = AtomKind::Constant;
-            let mut atom_node = None;
-            let mut references = Vec::new();
-
-            for capture in m.captures {
-                let capture_name = query.capture_names()[capture.index as usize];
-                match capture_name {
-                    "selector" => {
-                        name = capture.node.utf8_text(source.as_bytes()).unwrap_or("rule").trim().to_string();
-                    }
-                    "rule" => {
-                        kind = AtomKind::Constant;
-                        atom_node = Some(capture.node);
-                    }
-                    "media" => {
-                        name = capture.node.child(0).map(|n| n.utf8_text(source.as_bytes()).unwrap_or("@media").to_string()).unwrap_or("@media".to_string());
-                        kind = AtomKind::Constant;
-                        atom_node = Some(capture.node);
-                    }
-                    "keyframes" => {
-                        name = "@keyframes".to_string();
-                        kind = AtomKind::Constant;
-                        atom_node = Some(capture.node);
-                    }
-                    "import" => {
-                         name = "@import".to_string();
-                         kind = AtomKind::Import;
-                         atom_node = Some(capture.node);
-                         
-                         // Extract the import path
-                         let node_text = capture.node.utf8_text(source.as_bytes()).unwrap_or("");
-                         // Simple extraction from string: @import "foo.css" or @import url("foo.css")
-                         // Tree-sitter struct would be better but simple string parsing is robust enough for now
-                         if let Some(start) = node_text.find('"').or_else(|| node_text.find('\'')) {
-                             let quote = &node_text[start..start+1];
-                             if let Some(end) = node_text[start+1..].find(quote) {
-                                  references.push(node_text[start+1..start+1+end].to_string());
-                             }
-                         }
-                    }
-                    _ => {}
+        collect(source, &code, 0..code.len(), None, &mut atoms);
+        Ok(atoms)
+    }
+}
+
+/// Collect the statements and blocks in `range` of `code` (the source with
+/// comments blanked out), recursing into blocks that can hold rules
+fn collect(source: &str, code: &str, range: Range<usize>, parent: Option<&str>, atoms: &mut Vec<ExtractedAtom>) {
+    let mut i = range.start;
+    while let Some(offset) = code[i..range.end].find(|c: char| !c.is_whitespace() && c != ';') {
+        let start = i + offset;
+        let stop = statement_end(code, start, range.end);
+        let prelude = code[start..stop].trim();
+
+        if !code[stop..range.end].starts_with('{') {
+            // A statement or declaration; only imports are atoms
+            if prelude.len() > 7 && prelude[..7].eq_ignore_ascii_case("@import") {
+                let end = (stop + 1).min(range.end);
+                let mut atom = atom(source, "@import".to_string(), AtomKind::Import, start..end, parent);
+                atom.references.extend(import_path(&prelude[7..]));
+                atoms.push(atom);
+            }
+            i = (stop + 1).min(range.end);
+            continue;
+        }
+
+        let close = block_end(code, stop, range.end);
+        let end = (close + 1).min(range.end);
+        let body = stop + 1..close;
+        let name = prelude.split_whitespace().collect::<Vec<_>>().join(" ");
+        let keyword = name.split(|c: char| c.is_whitespace() || c == '(').next().unwrap_or("").to_ascii_lowercase();
+
+        let kind = if CONTAINER_AT_RULES.contains(&keyword.as_str()) {
+            AtomKind::Module
+        } else {
+            AtomKind::Constant
+        };
+        let mut block = atom(source, name.clone(), kind, start..end, parent);
+
+        // Keyframes and font faces hold declarations only; everything else
+        // may hold nested rules, whose declarations belong to them
+        let holds_rules = !keyword.starts_with('@') || kind == AtomKind::Module;
+        let own = if holds_rules { top_level_text(code, body.clone()) } else { code[body.clone()].to_string() };
+        block.defines = custom_property_definitions(&own);
+        block.references = custom_property_uses(&own)
+            .into_iter()
+            .filter(|p| !block.defines.contains(p))
+            .collect();
+        atoms.push(block);
+
+        if holds_rules {
+            collect(source, code, body, Some(&name), atoms);
+        }
+        i = end;
+    }
+}
+
+fn atom(source: &str, name: String, kind: AtomKind, range: Range<usize>, parent: Option<&str>) -> ExtractedAtom {
+    ExtractedAtom {
+        name,
+        kind,
+        source: source[range.clone()].to_string(),
+        start_line: source[..range.start].matches('\n').count() + 1,
+        end_line: source[..range.end].matches('\n').count() + 1,
+        start_byte: range.start,
+        end_byte: range.end,
+        defines: Vec::new(),
+        references: Vec::new(),
+        doc_comment: None,
+        visibility: Visibility::Public,
+        parent: parent.map(str::to_string),
+        decorators: Vec::new(),
+    }
+}
+
+/// Replace comments with spaces, keeping byte offsets and line breaks
+fn blank_comments(source: &str) -> String {
+    let mut code = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(start) = rest.find("/*") {
+        code.push_str(&rest[..start]);
+        let end = rest[start + 2..].find("*/").map_or(rest.len(), |e| start + e + 4);
+        for c in rest[start..end].chars() {
+            match c {
+                '\n' => code.push('\n'),
+                _ => code.extend(std::iter::repeat_n(' ', c.len_utf8())),
+            }
+        }
+        rest = &rest[end..];
+    }
+    code.push_str(rest);
+    code
+}
+
+/// Position of the `{` or `;` ending the statement at `start`, outside
+/// strings and parentheses, or `end` if there is none
+fn statement_end(code: &str, start: usize, end: usize) -> usize {
+    let mut quote = None;
+    let mut parens = 0usize;
+    for (i, c) in code[start..end].char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(') => parens += 1,
+            (None, ')') => parens = parens.saturating_sub(1),
+            (None, '{' | ';' | '}') if parens == 0 => return start + i,
+            _ => {}
+        }
+    }
+    end
+}
+
+/// Position of the `}` closing the block opened at `open`, or `end`
+fn block_end(code: &str, open: usize, end: usize) -> usize {
+    let mut quote = None;
+    let mut depth = 0usize;
+    for (i, c) in code[open..end].char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '{') => depth += 1,
+            (None, '}') => {
+                depth -= 1;
+                if depth == 0 {
+                    return open + i;
                 }
             }
+            _ => {}
+        }
+    }
+    end
+}
 
-            if let Some(node) = atom_node {
-                let start_byte = node.start_byte();
-                let end_byte = node.end_byte();
-                let start_point = node.start_position();
-                let end_point = node.end_position();
-
-                atoms.push(ExtractedAtom {
-                    name,
-                    kind,
-                    source: source[start_byte..end_byte].to_string(),
-                    start_byte,
-                    end_byte,
-                    start_line: start_point.row + 1,
-                    end_line: end_point.row + 1,
-                    defines: vec![],
-                    references,
-                    doc_comment: None,
-                    visibility: crate::atomizer::extractor::Visibility::Public,
-                    parent: None,
-                    decorators: Vec::new(),
-                });
+/// The text of a block body outside any nested blocks
+fn top_level_text(code: &str, body: Range<usize>) -> String {
+    let mut depth = 0usize;
+    code[body]
+        .chars()
+        .filter(|&c| {
+            match c {
+                '{' => depth += 1,
+                '}' => depth = depth.saturating_sub(1),
+                _ => return depth == 0,
             }
+            false
+        })
+        .collect()
+}
+
+/// Path of an `@import "a.css"` or `@import url(a.css)` statement, given
+/// the text after `@import`
+fn import_path(rest: &str) -> Option<String> {
+    let rest = rest.trim_start();
+    let target = match rest.get(..4) {
+        Some(url) if url.eq_ignore_ascii_case("url(") => {
+            let inner = &rest[4..];
+            inner[..inner.find(')')?].trim()
+        }
+        _ => rest,
+    };
+    let path = match target.chars().next()? {
+        q @ ('"' | '\'') => &target[1..1 + target[1..].find(q)?],
+        _ => target.split(|c: char| c.is_whitespace() || c == ';').next()?,
+    };
+    (!path.is_empty()).then(|| path.to_string())
+}
+
+/// Custom properties set by declarations, e.g. `--accent: red`
+fn custom_property_definitions(declarations: &str) -> Vec<String> {
+    let mut defined = Vec::new();
+    for declaration in declarations.split(';') {
+        let Some((property, _)) = declaration.split_once(':') else {
+            continue;
+        };
+        let property = property.trim();
+        if property.starts_with("--") && is_custom_property(property) && !defined.iter().any(|d| d == property) {
+            defined.push(property.to_string());
         }
-        
-        Ok(atoms)
     }
-    
-    /// Fallback extraction without Tree-sitter
-    #[cfg(not(feature = "ast-parsing"))]
-    pub fn extract(&self, source: &str) -> CadiResult<Vec<ExtractedAtom>> {
-        use crate::atomizer::AtomExtractor;
-        AtomExtractor::new("css", self._config.clone()).extract(source)
+    defined
+}
+
+/// Custom properties read with `var(--name)`
+fn custom_property_uses(declarations: &str) -> Vec<String> {
+    let mut used: Vec<String> = Vec::new();
+    for (at, _) in declarations.match_indices("var(") {
+        let rest = declarations[at + 4..].trim_start();
+        let name_end = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+            .unwrap_or(rest.len());
+        let name = &rest[..name_end];
+        if is_custom_property(name) && !used.iter().any(|u| u == name) {
+            used.push(name.to_string());
+        }
     }
+    used
+}
+
+fn is_custom_property(name: &str) -> bool {
+    name.len() > 2
+        && name.starts_with("--")
+        && name[2..].chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
 }
 
 #[cfg(test)]
@@ -156,4 +262,67 @@ mod tests {
         assert!(atoms.iter().any(|a| a.name == ".header"));
         assert!(atoms.iter().any(|a| a.name.contains("@media")));
     }
+
+    #[test]
+    fn test_imports_media_queries_and_custom_properties() {
+        let source = r#"@import "base.css";
+@import url('themes/dark.css') screen;
+
+/* --unused: 1; { not a rule } */
+:root {
+  --brand: #06c;
+  --accent: var(--brand);
+}
+
+.button {
+  color: var(--accent);
+  &:hover { border-color: var(--brand); }
+}
+
+@media (max-width: 600px) {
+  .button { padding: 0; }
+  .card, .panel { background: var(--accent, white); }
+}
+
+@keyframes pulse {
+  from { opacity: 0; }
+  to { opacity: 1; }
+}
+"#;
+        let atoms = CssAtomizer::new(AtomizerConfig::default()).extract(source).unwrap();
+        let find = |name: &str| atoms.iter().find(|a| a.name == name).unwrap_or_else(|| panic!("no atom {}", name));
+
+        let imports: Vec<_> = atoms.iter().filter(|a| a.kind == AtomKind::Import).collect();
+        assert_eq!(imports.len(), 2);
+        assert_eq!(imports[0].references, ["base.css"]);
+        assert_eq!(imports[1].references, ["themes/dark.css"]);
+        assert_eq!((imports[1].start_line, imports[1].end_line), (2, 2));
+
+        // The custom property chain: .button -> --accent -> --brand
+        let root = find(":root");
+        assert_eq!(root.defines, ["--brand", "--accent"]);
+        assert!(root.references.is_empty());
+        let button = find(".button");
+        assert_eq!(button.references, ["--accent"]);
+        assert_eq!((button.start_line, button.end_line), (10, 13));
+
+        let hover = find("&:hover");
+        assert_eq!(hover.parent.as_deref(), Some(".button"));
+        assert_eq!(hover.references, ["--brand"]);
+
+        let media = find("@media (max-width: 600px)");
+        assert_eq!(media.kind, AtomKind::Module);
+        assert_eq!((media.start_line, media.end_line), (15, 18));
+        let nested: Vec<&str> = atoms
+            .iter()
+            .filter(|a| a.parent.as_deref() == Some(media.name.as_str()))
+            .map(|a| a.name.as_str())
+            .collect();
+        assert_eq!(nested, [".button", ".card, .panel"]);
+        assert_eq!(find(".card, .panel").references, ["--accent"]);
+
+        // Keyframe selectors stay inside their at-rule
+        assert_eq!(find("@keyframes pulse").kind, AtomKind::Constant);
+        assert!(atoms.iter().all(|a| a.name != "from" && !a.name.contains("--unused")));
+    }
 }
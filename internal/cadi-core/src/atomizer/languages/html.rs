@@ -1,168 +1,364 @@
 //! HTML atomizer
+//!
+//! A page becomes a `page` atom plus a child atom for every embedded
+//! `<script>` and `<style>` block and every external script or stylesheet
+//! it links. Embedded blocks are parsed with the JavaScript/TypeScript and
+//! CSS extractors and keep just their contents, so they can dedupe against
+//! standalone files. Inline event handlers are left in the page.
 
-use crate::atomizer::{AtomizerConfig, ExtractedAtom};
+use std::ops::Range;
+
+use crate::atomizer::{AtomExtractor, AtomKind, AtomizerConfig, ExtractedAtom};
+use crate::atomizer::extractor::Visibility;
 use crate::error::CadiResult;
 
-/// HTML atomizer - extracts meaningful fragments and embedded scripts/styles
+/// Name of the atom covering the whole page
+pub const PAGE_ATOM: &str = "page";
+
+/// HTML atomizer - extracts the page and its embedded scripts/styles
 pub struct HtmlAtomizer {
     config: AtomizerConfig,
 }
 
+/// A `<script>`, `<style>` or `<link>` element
+struct Element<'a> {
+    /// Lowercase tag name
+    name: &'static str,
+    /// Text between the tag name and the `>` of the start tag
+    attributes: &'a str,
+    /// Byte range of the element's raw text (empty for `<link>`)
+    content: Range<usize>,
+}
+
 impl HtmlAtomizer {
     pub fn new(config: AtomizerConfig) -> Self {
         Self { config }
     }
 
-    /// Extract atoms using Tree-sitter
-    #[cfg(feature = "ast-parsing")]
+    /// Extract the page atom followed by its child atoms
     pub fn extract(&self, source: &str) -> CadiResult<Vec<ExtractedAtom>> {
-        use tree_sitter::{Parser, Query, QueryCursor};
-        use crate::atomizer::AtomKind;
-
-        let mut parser = Parser::new();
-        parser.set_language(&tree_sitter_html::language())?;
-        
-        let tree = parser.parse(source, None)
-            .ok_or_else(|| crate::error::CadiError::AtomizerError("Parse failed".into()))?;
-        
-        let mut atoms = Vec::new();
-        
-        // Tree-sitter queries for HTML
-        // We look for script tags and link tags
-        let _query_src = r#"
-            (script_element
-                (start_tag 
-                    (attribute
-                        (attribute_name) @attr_name
-                        (quoted_attribute_value (attribute_value) @src_val)
-                    )
-                )
-            ) @script
-
-            (link_element
-                 (start_tag 
-                    (attribute
-                        (attribute_name) @attr_name
-                        (quoted_attribute_value (attribute_value) @href_val)
-                    )
-                )
-            ) @link
-            
-            (style_element) @style
-            (script_element) @inline_script
-        "#;
-        // Note: The above query is a simplification. HTML attributes are unordered.
-        // A more robust manual traversal is often safer than complex queries for attributes.
-        // But let's try a simpler approach capturing the whole element and parsing attributes manually if needed.
-        
-        let simplified_query = r#"
-            (script_element) @script
-            (style_element) @style
-            (element) @element
-        "#;
-
-        let query = Query::new(&tree_sitter_html::language(), simplified_query)?;
-        let mut cursor = QueryCursor::new();
-        
-        let matches = cursor.matches(&query, tree.root_node(), source.as_bytes());
-
-        for m in matches {
-            let mut kind = AtomKind::Module; // Default
-            let mut name = "unknown".to_string();
-            let mut atom_node = None;
-            let mut references = Vec::new();
-            
-            for capture in m.captures {
-                let capture_name = query.capture_names()[capture.index as usize];
-                match capture_name {
-                    "script" => {
-                        atom_node = Some(capture.node);
-                        kind = AtomKind::Function; // Closest mapping for script block
-                        name = "script".to_string();
-                        
-                        // Check for src attribute
-                        let node_text = capture.node.utf8_text(source.as_bytes()).unwrap_or("");
-                        if let Some(src_idx) = node_text.find("src=") {
-                             // Crude attribute parsing
-                             let after_src = &node_text[src_idx+4..];
-                             let quote = after_src.chars().next().unwrap_or('"');
-                             if quote == '"' || quote == '\'' {
-                                 if let Some(end) = after_src[1..].find(quote) {
-                                     let path = after_src[1..1+end].to_string();
-                                     references.push(path);
-                                     kind = AtomKind::Import; // It's an external script
-                                     name = "script_import".to_string();
-                                 }
-                             }
-                        }
-                    }
-                    "style" => {
-                        atom_node = Some(capture.node);
-                        kind = AtomKind::Constant;
-                        name = "style".to_string();
+        let mut children = Vec::new();
+        let (mut scripts, mut styles) = (0, 0);
+
+        for element in elements(source) {
+            match element.name {
+                "script" => {
+                    if let Some(src) = attribute(element.attributes, "src") {
+                        children.push(import_atom("script_import", src, source, &element.content));
+                    } else if let Some(language) = script_language(element.attributes) {
+                        scripts += 1;
+                        let name = format!("script-{}", scripts);
+                        children.extend(self.block_atom(name, AtomKind::Module, language, source, element.content));
                     }
-                    "element" => {
-                         // Check for link
-                         let node_text = capture.node.utf8_text(source.as_bytes()).unwrap_or("");
-                         if node_text.starts_with("<link") {
-                            atom_node = Some(capture.node);
-                             // Check for rel="stylesheet"
-                            if node_text.contains("stylesheet") {
-                                 kind = AtomKind::Import; // CSS Import
-                                 name = "style_import".to_string();
-                                 
-                                 if let Some(href_idx) = node_text.find("href=") {
-                                     let after_href = &node_text[href_idx+5..];
-                                     let quote = after_href.chars().next().unwrap_or('"');
-                                     if quote == '"' || quote == '\'' {
-                                         if let Some(end) = after_href[1..].find(quote) {
-                                             let path = after_href[1..1+end].to_string();
-                                             references.push(path);
-                                         }
-                                     }
-                                }
-                            } else {
-                                continue; 
-                            }
-                         } else {
-                             continue;
-                         }
+                }
+                "style" => {
+                    styles += 1;
+                    let name = format!("style-{}", styles);
+                    children.extend(self.block_atom(name, AtomKind::Constant, "css", source, element.content));
+                }
+                _ => {
+                    let stylesheet = attribute(element.attributes, "rel")
+                        .is_some_and(|rel| rel.split_whitespace().any(|r| r.eq_ignore_ascii_case("stylesheet")));
+                    if let Some(href) = attribute(element.attributes, "href").filter(|_| stylesheet) {
+                        children.push(import_atom("style_import", href, source, &element.content));
                     }
-                    _ => {}
                 }
             }
+        }
 
-            if let Some(node) = atom_node {
-                let start_byte = node.start_byte();
-                let end_byte = node.end_byte();
-                let start_point = node.start_position();
-                let end_point = node.end_position();
-
-                atoms.push(ExtractedAtom {
-                    name,
-                    kind,
-                    source: source[start_byte..end_byte].to_string(),
-                    start_byte,
-                    end_byte,
-                    start_line: start_point.row + 1,
-                    end_line: end_point.row + 1,
-                    defines: vec![],
-                    references,
-                    doc_comment: None,
-                    visibility: crate::atomizer::extractor::Visibility::Public,
-                    parent: None,
-                    decorators: Vec::new(),
-                });
+        let page = ExtractedAtom {
+            name: PAGE_ATOM.to_string(),
+            kind: AtomKind::Module,
+            source: source.to_string(),
+            start_byte: 0,
+            end_byte: source.len(),
+            start_line: 1,
+            end_line: source.lines().count().max(1),
+            defines: Vec::new(),
+            // The page is composed of its embedded blocks
+            references: children
+                .iter()
+                .filter(|c| c.kind != AtomKind::Import)
+                .map(|c| c.name.clone())
+                .collect(),
+            doc_comment: None,
+            visibility: Visibility::Public,
+            parent: None,
+            decorators: Vec::new(),
+        };
+
+        Ok(std::iter::once(page).chain(children).collect())
+    }
+
+    /// An atom for the contents of an embedded block, carrying what the
+    /// language's extractor found defined and referenced in it. Blank lines
+    /// around the code are left out; `None` for an empty block.
+    fn block_atom(
+        &self,
+        name: String,
+        kind: AtomKind,
+        language: &str,
+        source: &str,
+        content: Range<usize>,
+    ) -> Option<ExtractedAtom> {
+        let range = trim_blank_lines(source, content)?;
+        let code = &source[range.clone()];
+
+        let mut defines: Vec<String> = Vec::new();
+        let mut references: Vec<String> = Vec::new();
+        for atom in AtomExtractor::new(language, self.config.clone()).extract(code).unwrap_or_default() {
+            for symbol in atom.defines {
+                if !defines.contains(&symbol) {
+                    defines.push(symbol);
+                }
+            }
+            for symbol in atom.references {
+                if !references.contains(&symbol) {
+                    references.push(symbol);
+                }
             }
         }
-        
-        Ok(atoms)
+        references.retain(|r| !defines.contains(r));
+
+        Some(ExtractedAtom {
+            name,
+            kind,
+            source: code.to_string(),
+            start_line: line_at(source, range.start),
+            end_line: line_at(source, range.end - 1),
+            start_byte: range.start,
+            end_byte: range.end,
+            defines,
+            references,
+            doc_comment: None,
+            visibility: Visibility::Public,
+            parent: Some(PAGE_ATOM.to_string()),
+            decorators: Vec::new(),
+        })
     }
+}
 
-    /// Fallback extraction without Tree-sitter
-    #[cfg(not(feature = "ast-parsing"))]
-    pub fn extract(&self, source: &str) -> CadiResult<Vec<ExtractedAtom>> {
-        use crate::atomizer::AtomExtractor;
-        AtomExtractor::new("html", self.config.clone()).extract(source)
+/// An atom for a script or stylesheet the page loads from `path`
+fn import_atom(name: &str, path: &str, source: &str, at: &Range<usize>) -> ExtractedAtom {
+    let line = line_at(source, at.start);
+    ExtractedAtom {
+        name: name.to_string(),
+        kind: AtomKind::Import,
+        source: String::new(),
+        start_byte: at.start,
+        end_byte: at.start,
+        start_line: line,
+        end_line: line,
+        defines: Vec::new(),
+        references: vec![path.to_string()],
+        doc_comment: None,
+        visibility: Visibility::Public,
+        parent: Some(PAGE_ATOM.to_string()),
+        decorators: Vec::new(),
+    }
+}
+
+/// Extractor language for a script's `type`/`lang`, or `None` for data
+/// blocks such as JSON and import maps
+fn script_language(attributes: &str) -> Option<&'static str> {
+    let lang = attribute(attributes, "lang").unwrap_or_default().to_ascii_lowercase();
+    let kind = attribute(attributes, "type").unwrap_or_default().trim().to_ascii_lowercase();
+    if matches!(lang.as_str(), "ts" | "typescript") || kind.ends_with("/typescript") {
+        return Some("typescript");
+    }
+    match kind.as_str() {
+        "" | "module" | "text/javascript" | "application/javascript" | "text/ecmascript" => Some("javascript"),
+        _ => None,
+    }
+}
+
+/// The `<script>`, `<style>` and `<link>` elements of a page, in order,
+/// skipping those inside comments
+fn elements(source: &str) -> Vec<Element<'_>> {
+    // ASCII lowercasing keeps byte offsets
+    let lower = source.to_ascii_lowercase();
+    let mut found = Vec::new();
+    let mut i = 0;
+
+    while let Some(at) = lower[i..].find('<').map(|p| i + p) {
+        if lower[at..].starts_with("<!--") {
+            i = lower[at..].find("-->").map_or(lower.len(), |e| at + e + 3);
+            continue;
+        }
+
+        let name_start = at + 1;
+        let name_end = lower[name_start..]
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .map_or(lower.len(), |e| name_start + e);
+        let name = match &lower[name_start..name_end] {
+            "script" => "script",
+            "style" => "style",
+            "link" => "link",
+            _ => {
+                i = name_start;
+                continue;
+            }
+        };
+
+        let Some(tag_end) = start_tag_end(source, name_end) else {
+            break;
+        };
+        let attributes = source[name_end..tag_end].trim_end_matches('/');
+        let open_end = tag_end + 1;
+
+        if name == "link" {
+            found.push(Element { name, attributes, content: open_end..open_end });
+            i = open_end;
+            continue;
+        }
+
+        // Script and style contents are raw text: only the end tag ends them
+        let close = lower[open_end..]
+            .find(&format!("</{}", name))
+            .map_or(lower.len(), |c| open_end + c);
+        found.push(Element { name, attributes, content: open_end..close });
+        i = lower[close..].find('>').map_or(lower.len(), |e| close + e + 1);
+    }
+
+    found
+}
+
+/// Position of the `>` closing a start tag, ignoring any inside quoted
+/// attribute values
+fn start_tag_end(source: &str, from: usize) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in source[from..].char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(from + i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Value of an attribute in a start tag; empty for attributes given
+/// without a value
+fn attribute<'a>(attributes: &'a str, wanted: &str) -> Option<&'a str> {
+    let mut rest = attributes.trim_start();
+    while !rest.is_empty() {
+        let name_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len());
+        let name = &rest[..name_end];
+        rest = rest[name_end..].trim_start();
+
+        let mut value = "";
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let (v, remaining) = match after.chars().next() {
+                Some(q @ ('"' | '\'')) => {
+                    let end = after[1..].find(q).map_or(after.len(), |e| e + 1);
+                    (&after[1..end], after.get(end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                    (&after[..end], &after[end..])
+                }
+            };
+            value = v;
+            rest = remaining;
+        } else if name.is_empty() {
+            // A stray `/` or other separator
+            rest = &rest[1..];
+        }
+
+        if name.eq_ignore_ascii_case(wanted) {
+            return Some(value);
+        }
+        rest = rest.trim_start();
+    }
+    None
+}
+
+/// `content` without its leading and trailing blank lines, or `None` if
+/// it is all whitespace
+fn trim_blank_lines(source: &str, content: Range<usize>) -> Option<Range<usize>> {
+    let text = &source[content.clone()];
+    let first = text.find(|c: char| !c.is_whitespace())?;
+    let last = text.rfind(|c: char| !c.is_whitespace())?;
+    let start = text[..first].rfind('\n').map_or(0, |n| n + 1);
+    let end = last + text[last..].chars().next().map_or(1, char::len_utf8);
+    Some(content.start + start..content.start + end)
+}
+
+/// 1-indexed line of a byte offset
+fn line_at(source: &str, byte: usize) -> usize {
+    source[..byte].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+  <style>
+    .todo { color: red; }
+  </style>
+  <!-- <script>ignored()</script> -->
+</head>
+<body>
+  <button onclick="addTodo()">Add</button>
+  <script type="module">
+    function addTodo() {
+      renderList();
+    }
+  </script>
+  <script>
+    function renderList() {}
+  </script>
+</body>
+</html>
+"#;
+
+    #[test]
+    fn test_embedded_blocks_become_child_atoms() {
+        let atoms = HtmlAtomizer::new(AtomizerConfig::default()).extract(PAGE).unwrap();
+        let names: Vec<&str> = atoms.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, ["page", "style-1", "script-1", "script-2"]);
+
+        let page = &atoms[0];
+        assert_eq!((page.start_line, page.end_line), (1, 20));
+        assert_eq!(page.references, ["style-1", "script-1", "script-2"]);
+
+        for atom in &atoms[1..] {
+            assert_eq!(atom.parent.as_deref(), Some(PAGE_ATOM));
+            assert_eq!(&PAGE[atom.start_byte..atom.end_byte], atom.source);
+        }
+
+        let style = &atoms[1];
+        assert_eq!((style.start_line, style.end_line), (5, 5));
+        assert_eq!(style.source, "    .todo { color: red; }");
+
+        let module = &atoms[2];
+        assert_eq!((module.start_line, module.end_line), (12, 14));
+        assert!(module.source.trim_start().starts_with("function addTodo()"));
+        assert!(module.defines.contains(&"addTodo".to_string()));
+
+        let classic = &atoms[3];
+        assert_eq!((classic.start_line, classic.end_line), (17, 17));
+        assert!(classic.defines.contains(&"renderList".to_string()));
+    }
+
+    #[test]
+    fn test_external_resources_and_data_blocks() {
+        let page = "<head>\n<script src='app.js'></script>\n<LINK rel=\"stylesheet\" href=\"style.css\">\n\
+                    <script type=\"application/json\">{\"a\": 1}</script>\n<script></script>\n</head>\n";
+        let atoms = HtmlAtomizer::new(AtomizerConfig::default()).extract(page).unwrap();
+        let names: Vec<&str> = atoms.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, ["page", "script_import", "style_import"]);
+        assert_eq!(atoms[1].references, ["app.js"]);
+        assert_eq!(atoms[1].start_line, 2);
+        assert_eq!(atoms[2].references, ["style.css"]);
+        assert!(atoms[0].references.is_empty());
     }
 }
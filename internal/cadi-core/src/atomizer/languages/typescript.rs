@@ -37,6 +37,10 @@ impl TypeScriptAtomizer {
             (class_declaration
                 name: (type_identifier) @class_name
             ) @class
+
+            (abstract_class_declaration
+                name: (type_identifier) @class_name
+            ) @class
             
             (interface_declaration
                 name: (type_identifier) @interface_name
@@ -129,9 +133,10 @@ impl TypeScriptAtomizer {
             // Class
             if let (Some(class_node), Some(name_node)) = (caps.get("class"), caps.get("class_name")) {
                 let name = name_node.utf8_text(source.as_bytes()).unwrap_or("unknown").to_string();
-                let start = class_node.start_byte();
+                let (start_node, decorators) = Self::decorators(source, *class_node);
+                let start = start_node.start_byte();
                 let end = class_node.end_byte();
-                let start_point = class_node.start_position();
+                let start_point = start_node.start_position();
                 let end_point = class_node.end_position();
 
                 let params = Self::extract_references(source, *class_node, &ref_query, &name);
@@ -149,7 +154,7 @@ impl TypeScriptAtomizer {
                     doc_comment: None,
                     visibility: crate::atomizer::extractor::Visibility::Public,
                     parent: None,
-                    decorators: Vec::new(),
+                    decorators,
                 });
             }
 
@@ -184,6 +189,26 @@ impl TypeScriptAtomizer {
         Ok(atoms)
     }
 
+    /// Decorators of a class, and the node its atom starts at. An exported
+    /// class's decorators come before `export`, so they belong to the
+    /// export statement and the atom starts there.
+    #[cfg(feature = "ast-parsing")]
+    fn decorators<'a>(source: &str, node: tree_sitter::Node<'a>) -> (tree_sitter::Node<'a>, Vec<String>) {
+        let mut start = node;
+        let mut decorators = Vec::new();
+        let export = node.parent().filter(|parent| parent.kind() == "export_statement");
+        for owner in export.into_iter().chain(std::iter::once(node)) {
+            let mut cursor = owner.walk();
+            for child in owner.children(&mut cursor).filter(|child| child.kind() == "decorator") {
+                if decorators.is_empty() {
+                    start = owner;
+                }
+                decorators.push(child.utf8_text(source.as_bytes()).unwrap_or_default().to_string());
+            }
+        }
+        (start, decorators)
+    }
+
     #[cfg(feature = "ast-parsing")]
     fn extract_references(source: &str, node: tree_sitter::Node, query: &tree_sitter::Query, exclude_name: &str) -> Vec<String> {
         use tree_sitter::QueryCursor;
@@ -33,13 +33,15 @@
 //! }
 //! ```
 
+pub mod cache;
 pub mod config;
 pub mod parser;
 pub mod extractor;
 pub mod resolver;
 pub mod languages;
 
+pub use cache::{ParseCache, ParseCacheStats, PARSE_CACHE_DIR};
 pub use config::{AtomizerConfig, LanguageConfig};
 pub use parser::AstParser;
-pub use extractor::{AtomExtractor, ExtractedAtom, AtomKind};
+pub use extractor::{AtomExtractor, ExtractedAtom, AtomKind, decorator_concepts};
 pub use resolver::{SymbolResolver, ResolvedImport, ImportedSymbol};
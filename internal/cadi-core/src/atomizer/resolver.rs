@@ -10,7 +10,7 @@
 //! We resolve `helper` to its chunk ID: `chunk:sha256:abc123...`
 
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 
@@ -27,8 +27,18 @@ pub struct SymbolResolver {
     
     /// Language being resolved
     language: String,
+
+    /// Symbols defined by files that have already been atomized:
+    /// normalized file path -> (symbol -> chunk_id)
+    atomized_files: HashMap<PathBuf, HashMap<String, String>>,
 }
 
+/// Extensions probed (in order) when resolving an extensionless TS/JS import
+const TS_EXTENSIONS: &[&str] = &["ts", "tsx", "d.ts", "js", "jsx", "mjs", "cjs"];
+
+/// Index files probed when a TS/JS import points at a directory
+const TS_INDEX_FILES: &[&str] = &["index.ts", "index.tsx", "index.js", "index.jsx"];
+
 /// A raw import statement
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RawImport {
@@ -69,6 +79,11 @@ pub struct ResolvedImport {
     
     /// Line number
     pub line: usize,
+
+    /// The import target lies outside the project (a package, or a relative
+    /// path that doesn't exist). Consumers like the ghost resolver skip these.
+    #[serde(default)]
+    pub is_external: bool,
 }
 
 /// A symbol resolved to its chunk
@@ -97,6 +112,7 @@ impl SymbolResolver {
             cache: HashMap::new(),
             project_root: project_root.into(),
             language: language.into(),
+            atomized_files: HashMap::new(),
         }
     }
 
@@ -105,6 +121,26 @@ impl SymbolResolver {
         self.cache.clear();
     }
 
+    /// Register the atoms produced for a file so that imports of it resolve
+    /// to the real chunk IDs of the defining atoms.
+    ///
+    /// `symbols` maps each exported symbol to the chunk ID of its atom. A
+    /// default export can be registered under the name `default`.
+    pub fn register_file(
+        &mut self,
+        file: impl AsRef<Path>,
+        symbols: impl IntoIterator<Item = (String, String)>,
+    ) {
+        let path = normalize_path(&self.project_root.join(file.as_ref()));
+        self.atomized_files.entry(path).or_default().extend(symbols);
+    }
+
+    /// Has this file been registered as atomized?
+    pub fn is_atomized(&self, file: impl AsRef<Path>) -> bool {
+        let path = normalize_path(&self.project_root.join(file.as_ref()));
+        self.atomized_files.contains_key(&path)
+    }
+
     /// Extract raw imports from source code
     pub fn extract_imports(&self, source: &str) -> Vec<RawImport> {
         match self.language.as_str() {
@@ -130,9 +166,21 @@ impl SymbolResolver {
 
         for import in imports {
             let target_path = self.resolve_path(current_file, &import.source)?;
+            let is_external = self.is_external_import(current_file, &import.source);
             let mut resolved_symbols = Vec::new();
 
             for sym in import.symbols {
+                if is_external {
+                    resolved_symbols.push(ImportedSymbol {
+                        name: sym.name,
+                        alias: sym.alias,
+                        chunk_id: "external".to_string(),
+                        chunk_hash: String::new(),
+                        symbol_type: None,
+                    });
+                    continue;
+                }
+
                 // Check cache first
                 let cache_key = (target_path.clone(), sym.name.clone());
                 
@@ -147,8 +195,18 @@ impl SymbolResolver {
                     continue;
                 }
 
-                // Try to find in graph
-                if let Ok(Some(chunk_id)) = graph.find_symbol(&sym.name) {
+                // Prefer the atom defined in the target file, then fall back to the graph
+                let lookup_name = if import.is_default { "default" } else { sym.name.as_str() };
+                let local = self
+                    .lookup_in_file(&target_path, lookup_name)
+                    .or_else(|| self.lookup_in_file(&target_path, &sym.name));
+
+                let found = match local {
+                    Some(chunk_id) => Some(chunk_id),
+                    None => graph.find_symbol(&sym.name).ok().flatten(),
+                };
+
+                if let Some(chunk_id) = found {
                     self.cache.insert(cache_key, chunk_id.clone());
                     resolved_symbols.push(ImportedSymbol {
                         name: sym.name,
@@ -173,6 +231,7 @@ impl SymbolResolver {
                 source_path: import.source,
                 symbols: resolved_symbols,
                 line: import.line,
+                is_external,
             });
         }
 
@@ -185,7 +244,7 @@ impl SymbolResolver {
     pub fn create_link_references(&self, imports: &[ResolvedImport]) -> HashMap<String, String> {
         let mut links = HashMap::new();
 
-        for import in imports {
+        for import in imports.iter().filter(|i| !i.is_external) {
             for sym in &import.symbols {
                 if !sym.chunk_id.is_empty() && sym.chunk_id != "unresolved" {
                     let key = sym.alias.as_ref().unwrap_or(&sym.name).clone();
@@ -357,12 +416,25 @@ impl SymbolResolver {
             r"^import\s+([\w., ]+)"
         ).unwrap();
 
-        for (line_idx, line) in source.lines().enumerate() {
-            let trimmed = line.trim();
+        let lines: Vec<&str> = source.lines().collect();
+        for (line_idx, line) in lines.iter().enumerate() {
+            let trimmed = strip_python_comment(line).trim();
             
             if let Some(cap) = from_regex.captures(trimmed) {
                 let path = cap.get(1).map(|m| m.as_str()).unwrap_or("");
-                let symbols_str = cap.get(2).map(|m| m.as_str()).unwrap_or("");
+                let mut symbols_str = cap.get(2).map(|m| m.as_str()).unwrap_or("").to_string();
+
+                // A parenthesized list may continue over the following lines
+                if symbols_str.starts_with('(') {
+                    for next in lines.iter().skip(line_idx + 1) {
+                        if symbols_str.contains(')') {
+                            break;
+                        }
+                        symbols_str.push(',');
+                        symbols_str.push_str(strip_python_comment(next));
+                    }
+                }
+                let symbols_str = symbols_str.trim_start_matches('(').split(')').next().unwrap_or_default();
 
                 let symbols: Vec<RawSymbol> = symbols_str
                     .split(',')
@@ -521,18 +593,10 @@ impl SymbolResolver {
     fn resolve_ts_path(&self, current_file: &Path, import_path: &str) -> CadiResult<PathBuf> {
         if import_path.starts_with('.') {
             // Relative import
-            let parent = current_file.parent().unwrap_or(&self.project_root);
-            let resolved = parent.join(import_path);
-            
-            // Try various extensions
-            for ext in &["ts", "tsx", "js", "jsx", "index.ts", "index.js"] {
-                let with_ext = resolved.with_extension(ext);
-                if with_ext.exists() {
-                    return Ok(with_ext);
-                }
+            if let Some(found) = self.locate_ts_module(current_file, import_path) {
+                return Ok(found);
             }
-            
-            Ok(resolved)
+            Ok(self.ts_import_base(current_file, import_path))
         } else if import_path.starts_with('@') || import_path.starts_with("~") {
             // Aliased import - would need tsconfig.json to resolve
             Ok(PathBuf::from(import_path))
@@ -542,6 +606,54 @@ impl SymbolResolver {
         }
     }
 
+    /// Normalized path a relative TS/JS import points at, before probing
+    fn ts_import_base(&self, current_file: &Path, import_path: &str) -> PathBuf {
+        let current = self.project_root.join(current_file);
+        let parent = current.parent().unwrap_or(&self.project_root);
+        normalize_path(&parent.join(import_path))
+    }
+
+    /// Find the file a relative TS/JS import refers to, probing extensions
+    /// and index files. Atomized files are checked before the filesystem.
+    fn locate_ts_module(&self, current_file: &Path, import_path: &str) -> Option<PathBuf> {
+        let base = self.ts_import_base(current_file, import_path);
+
+        let mut candidates = vec![base.clone()];
+        for ext in TS_EXTENSIONS {
+            let mut with_ext = base.clone().into_os_string();
+            with_ext.push(".");
+            with_ext.push(ext);
+            candidates.push(PathBuf::from(with_ext));
+        }
+        for index in TS_INDEX_FILES {
+            candidates.push(base.join(index));
+        }
+
+        candidates
+            .iter()
+            .find(|c| self.atomized_files.contains_key(*c))
+            .or_else(|| candidates.iter().find(|c| c.is_file()))
+            .cloned()
+    }
+
+    /// Does this import point outside the project?
+    fn is_external_import(&self, current_file: &Path, import_path: &str) -> bool {
+        match self.language.as_str() {
+            "typescript" | "javascript" => {
+                !import_path.starts_with('.')
+                    || self.locate_ts_module(current_file, import_path).is_none()
+            }
+            _ => false,
+        }
+    }
+
+    fn lookup_in_file(&self, file: &Path, symbol: &str) -> Option<String> {
+        self.atomized_files
+            .get(&normalize_path(file))
+            .and_then(|symbols| symbols.get(symbol))
+            .cloned()
+    }
+
     fn resolve_python_path(&self, current_file: &Path, import_path: &str) -> CadiResult<PathBuf> {
         if import_path.starts_with('.') {
             // Relative import
@@ -589,6 +701,25 @@ impl SymbolResolver {
     }
 }
 
+/// Lexically normalize a path, collapsing `.` and `..` components
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match out.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    out.pop();
+                }
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => out.push(".."),
+            },
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
+
 /// Extract hash from chunk ID
 fn extract_hash(chunk_id: &str) -> String {
     chunk_id
@@ -598,6 +729,11 @@ fn extract_hash(chunk_id: &str) -> String {
         .to_string()
 }
 
+/// A line of Python without its trailing `#` comment
+fn strip_python_comment(line: &str) -> &str {
+    line.split('#').next().unwrap_or_default()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -650,6 +786,22 @@ from .utils import helper
         assert_eq!(imports.len(), 4); // from os, import json, import yaml, from .utils
     }
 
+    #[test]
+    fn test_python_parenthesized_import_extraction() {
+        let source = "from django.http import (\n    HttpResponse,  # plain\n    JsonResponse as Json,\n)\n";
+
+        let resolver = SymbolResolver::new("/project", "python");
+        let imports = resolver.extract_imports(source);
+
+        assert_eq!(imports.len(), 1);
+        let names: Vec<(&str, Option<&str>)> = imports[0]
+            .symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.alias.as_deref()))
+            .collect();
+        assert_eq!(names, vec![("HttpResponse", None), ("JsonResponse", Some("Json"))]);
+    }
+
     #[test]
     fn test_c_import_extraction() {
         let source = r#"
@@ -0,0 +1,353 @@
+//! Scoring-based chunk categorization
+//!
+//! [`score_categories`] weighs the evidence a file offers — whether it is a
+//! test or config file, the frameworks it imports, its directories, its
+//! file name and the kinds of entities it defines — and ranks categories by
+//! their share of the total weight. The share is the category's confidence.
+//!
+//! Heuristics can be wrong, so a project can pin categories in
+//! [`CATEGORY_OVERRIDES_FILE`], a TOML table mapping globs to categories:
+//!
+//! ```toml
+//! "src/api/**" = "api"
+//! "*.stories.tsx" = "docs"
+//! ```
+//!
+//! Patterns use the same syntax as chunking rules. When several match, the
+//! longest pattern wins. An override always has confidence 1.0.
+
+use crate::atomic::ChunkCategory;
+use crate::error::{CadiError, CadiResult};
+use crate::gitignore::{glob_match, unterminated_class};
+use crate::smart_chunker::{CodeEntity, EntityKind};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Category overrides, relative to the project root
+pub const CATEGORY_OVERRIDES_FILE: &str = ".cadi/categories.toml";
+
+/// Weight of being a test or config file
+const ROLE_WEIGHT: f32 = 4.0;
+/// Weight of importing a framework associated with a category
+const IMPORT_WEIGHT: f32 = 2.0;
+/// Weight of a directory name associated with a category
+const PATH_WEIGHT: f32 = 1.0;
+/// Weight of a file defining mostly types
+const ENTITY_WEIGHT: f32 = 1.0;
+/// Weight of a file name or extension associated with a category
+const FILE_NAME_WEIGHT: f32 = 0.75;
+/// Weight every file gives to plain logic, so weak evidence stays uncertain
+const BASELINE_WEIGHT: f32 = 0.5;
+
+/// A category and how confident the categorizer is in it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CategoryScore {
+    pub category: ChunkCategory,
+    /// Share of the evidence pointing at this category (0.0-1.0)
+    pub confidence: f32,
+}
+
+impl CategoryScore {
+    /// A category that was set explicitly rather than inferred
+    pub fn certain(category: ChunkCategory) -> Self {
+        Self {
+            category,
+            confidence: 1.0,
+        }
+    }
+}
+
+/// Rank the categories a file may belong to, most likely first
+pub fn score_categories(
+    path: &Path,
+    imports: &[String],
+    entities: &[CodeEntity],
+    is_test: bool,
+    is_config: bool,
+) -> Vec<CategoryScore> {
+    let mut weights: Vec<(ChunkCategory, f32)> = Vec::new();
+    let mut add = |category: ChunkCategory, weight: f32| {
+        match weights.iter_mut().find(|(c, _)| *c == category) {
+            Some((_, total)) => *total += weight,
+            None => weights.push((category, weight)),
+        }
+    };
+
+    if is_test {
+        add(ChunkCategory::Test, ROLE_WEIGHT);
+    }
+    if is_config {
+        add(ChunkCategory::Config, ROLE_WEIGHT);
+    }
+
+    // Each category counts once, however many of its frameworks are imported
+    let mut imported: Vec<ChunkCategory> = Vec::new();
+    for category in imports.iter().flat_map(|import| import_segments(import)).filter_map(|s| import_hint(&s)) {
+        if !imported.contains(&category) {
+            imported.push(category);
+        }
+    }
+    for category in imported {
+        add(category, IMPORT_WEIGHT);
+    }
+
+    if let Some(parent) = path.parent() {
+        for component in parent.components() {
+            let name = component.as_os_str().to_string_lossy().to_lowercase();
+            if let Some(category) = directory_hint(&name) {
+                add(category, PATH_WEIGHT);
+            }
+        }
+    }
+
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_lowercase();
+    if let Some(category) = file_name_hint(&file_name) {
+        add(category, FILE_NAME_WEIGHT);
+    }
+
+    let type_count = entities
+        .iter()
+        .filter(|e| {
+            matches!(
+                e.kind,
+                EntityKind::Struct | EntityKind::Class | EntityKind::Interface | EntityKind::Enum | EntityKind::Type
+            )
+        })
+        .count();
+    if !entities.is_empty() && type_count > entities.len() / 2 {
+        add(ChunkCategory::Data, ENTITY_WEIGHT);
+    }
+
+    add(ChunkCategory::Logic, BASELINE_WEIGHT);
+
+    let total: f32 = weights.iter().map(|(_, w)| w).sum();
+    let mut scores: Vec<CategoryScore> = weights
+        .into_iter()
+        .map(|(category, weight)| CategoryScore {
+            category,
+            confidence: (weight / total * 100.0).round() / 100.0,
+        })
+        .collect();
+    // Stable, so ties keep the order the evidence was added in
+    scores.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    scores
+}
+
+/// Path segments of the module an import statement names, lowercased:
+/// the quoted path in JS/TS and Go, otherwise the path after the keyword
+fn import_segments(statement: &str) -> Vec<String> {
+    let quoted = statement.split(['"', '\'']).nth(1);
+    let module = quoted.unwrap_or_else(|| {
+        let rest = ["use ", "from ", "import "]
+            .iter()
+            .find_map(|keyword| statement.trim().strip_prefix(keyword))
+            .unwrap_or(statement);
+        rest.split_whitespace().next().unwrap_or("")
+    });
+
+    module
+        .split([':', '.', '/', '@', '-', '{', '}', ',', ';'])
+        .filter(|s| !s.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Category of a framework or library name
+fn import_hint(segment: &str) -> Option<ChunkCategory> {
+    Some(match segment {
+        "axum" | "actix_web" | "warp" | "rocket" | "poem" | "tide" | "tonic" | "express" | "fastify" | "koa"
+        | "hapi" | "flask" | "fastapi" | "starlette" | "rest_framework" | "gin" | "ktor" | "jaxrs" => ChunkCategory::Api,
+        "react" | "preact" | "vue" | "svelte" | "angular" | "yew" | "leptos" | "dioxus" | "egui" | "iced"
+        | "tkinter" => ChunkCategory::Ui,
+        "sqlx" | "diesel" | "sea_orm" | "rusqlite" | "tokio_postgres" | "mongodb" | "mongoose" | "sequelize"
+        | "prisma" | "typeorm" | "knex" | "pg" | "mysql" | "mysql2" | "sqlite3" | "sqlalchemy" | "psycopg2"
+        | "pymongo" | "gorm" | "hibernate" | "surrealdb" => ChunkCategory::Database,
+        _ => return None,
+    })
+}
+
+/// Category of a directory name
+fn directory_hint(name: &str) -> Option<ChunkCategory> {
+    Some(match name {
+        "api" | "apis" | "routes" | "handlers" | "controllers" | "endpoints" => ChunkCategory::Api,
+        "ui" | "components" | "views" | "pages" | "widgets" | "frontend" => ChunkCategory::Ui,
+        "utils" | "util" | "helpers" => ChunkCategory::Utility,
+        "models" | "types" | "schemas" | "dto" => ChunkCategory::Data,
+        "db" | "database" | "migrations" | "repositories" | "persistence" => ChunkCategory::Database,
+        "docs" => ChunkCategory::Docs,
+        "scripts" | "tools" => ChunkCategory::Build,
+        "server" | "backend" | "services" => ChunkCategory::Backend,
+        _ => return None,
+    })
+}
+
+/// Category suggested by a file name or extension
+fn file_name_hint(file_name: &str) -> Option<ChunkCategory> {
+    let stem = file_name.split('.').next().unwrap_or(file_name);
+    let has = |words: &[&str]| words.iter().any(|w| stem.contains(w));
+    if has(&["util", "helper"]) {
+        Some(ChunkCategory::Utility)
+    } else if has(&["type", "model", "schema"]) {
+        Some(ChunkCategory::Data)
+    } else if has(&["route", "controller", "handler", "endpoint"]) {
+        Some(ChunkCategory::Api)
+    } else if has(&["repository", "migration", "dao"]) {
+        Some(ChunkCategory::Database)
+    } else if has(&["component", "view", "page", "widget"])
+        || [".tsx", ".jsx", ".vue", ".svelte"].iter().any(|ext| file_name.ends_with(ext))
+    {
+        Some(ChunkCategory::Ui)
+    } else {
+        None
+    }
+}
+
+/// Categories pinned to files by glob
+#[derive(Debug, Clone, Default)]
+pub struct CategoryOverrides {
+    overrides: Vec<CompiledOverride>,
+}
+
+#[derive(Debug, Clone)]
+struct CompiledOverride {
+    pattern: Vec<char>,
+    anchored: bool,
+    category: ChunkCategory,
+}
+
+impl CategoryOverrides {
+    /// Load [`CATEGORY_OVERRIDES_FILE`] under `root`; a missing file means
+    /// no overrides
+    pub fn load(root: &Path) -> CadiResult<Self> {
+        let path = root.join(CATEGORY_OVERRIDES_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Self::parse(&std::fs::read_to_string(&path)?)
+    }
+
+    /// Parse a TOML table of glob = category
+    pub fn parse(content: &str) -> CadiResult<Self> {
+        let table: toml::Table = toml::from_str(content)
+            .map_err(|e| CadiError::Configuration(format!("Invalid {}: {}", CATEGORY_OVERRIDES_FILE, e)))?;
+
+        let mut overrides = table
+            .into_iter()
+            .map(|(glob, category)| {
+                let invalid = |reason: &str| {
+                    CadiError::Configuration(format!(
+                        "Invalid pattern '{}' in {}: {}",
+                        glob, CATEGORY_OVERRIDES_FILE, reason
+                    ))
+                };
+                let category = category.as_str().ok_or_else(|| invalid("category must be a string"))?;
+
+                let trimmed = glob.trim();
+                let anchored = trimmed.contains('/');
+                let pattern: Vec<char> = trimmed.trim_start_matches('/').chars().collect();
+                if pattern.is_empty() {
+                    return Err(invalid("pattern is empty"));
+                }
+                if unterminated_class(&pattern) {
+                    return Err(invalid("unterminated character class"));
+                }
+
+                Ok(CompiledOverride {
+                    pattern,
+                    anchored,
+                    category: category.parse().unwrap_or_else(|never| match never {}),
+                })
+            })
+            .collect::<CadiResult<Vec<_>>>()?;
+
+        // Longest pattern first, so the most specific match wins
+        overrides.sort_by_key(|o| std::cmp::Reverse(o.pattern.len()));
+        Ok(Self { overrides })
+    }
+
+    /// Whether there are no overrides
+    pub fn is_empty(&self) -> bool {
+        self.overrides.is_empty()
+    }
+
+    /// Category pinned to a path relative to the project root
+    pub fn resolve(&self, path: &Path) -> Option<&ChunkCategory> {
+        let path = path.to_string_lossy().replace('\\', "/");
+        let path = path.trim_start_matches("./");
+        let name = path.rsplit('/').next().unwrap_or(path);
+
+        let path: Vec<char> = path.chars().collect();
+        let name: Vec<char> = name.chars().collect();
+
+        self.overrides
+            .iter()
+            .find(|o| glob_match(&o.pattern, if o.anchored { &path } else { &name }))
+            .map(|o| &o.category)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn top(path: &str, imports: &[&str]) -> CategoryScore {
+        let imports: Vec<String> = imports.iter().map(|s| s.to_string()).collect();
+        score_categories(Path::new(path), &imports, &[], false, false).remove(0)
+    }
+
+    #[test]
+    fn test_import_signal_outweighs_path() {
+        let scores = score_categories(
+            Path::new("src/api/Profile.ts"),
+            &["import React, { useState } from 'react';".to_string()],
+            &[],
+            false,
+            false,
+        );
+        assert_eq!(scores[0].category, ChunkCategory::Ui);
+        assert_eq!(scores[1].category, ChunkCategory::Api);
+        assert!(scores[0].confidence > scores[1].confidence);
+        let total: f32 = scores.iter().map(|s| s.confidence).sum();
+        assert!((total - 1.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_signals_add_up() {
+        let api = top("src/api/routes.rs", &["axum::{Router, routing::get}"]);
+        assert_eq!(api.category, ChunkCategory::Api);
+        assert!(api.confidence >= 0.8, "{:?}", api);
+
+        // A path hint alone is weaker evidence
+        let weak = top("src/api/mod.rs", &[]);
+        assert_eq!(weak.category, ChunkCategory::Api);
+        assert!(weak.confidence < api.confidence);
+
+        assert_eq!(top("app/db/store.py", &["from sqlalchemy.orm import Session"]).category, ChunkCategory::Database);
+        assert_eq!(top("src/lib.rs", &["crate::api::routes"]), CategoryScore::certain(ChunkCategory::Logic));
+        let test = score_categories(Path::new("src/api/routes_test.go"), &[], &[], true, false);
+        assert_eq!(test[0].category, ChunkCategory::Test);
+    }
+
+    #[test]
+    fn test_overrides_longest_pattern_wins() {
+        let overrides = CategoryOverrides::parse(
+            r#"
+"src/api/**" = "api"
+"src/api/legacy/**" = "ui"
+"*.stories.tsx" = "docs"
+"schema.sql" = "migrations"
+"#,
+        )
+        .unwrap();
+
+        let resolve = |path: &str| overrides.resolve(Path::new(path)).cloned();
+        assert_eq!(resolve("src/api/users.ts"), Some(ChunkCategory::Api));
+        assert_eq!(resolve("src/api/legacy/form.ts"), Some(ChunkCategory::Ui));
+        assert_eq!(resolve("web/Button.stories.tsx"), Some(ChunkCategory::Docs));
+        assert_eq!(resolve("db/schema.sql"), Some(ChunkCategory::Custom("migrations".to_string())));
+        assert_eq!(resolve("src/main.rs"), None);
+
+        assert!(CategoryOverrides::parse("\"src/[\" = \"api\"").is_err());
+        assert!(CategoryOverrides::parse("\"src/**\" = 3").is_err());
+    }
+}
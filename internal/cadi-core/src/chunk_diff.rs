@@ -0,0 +1,420 @@
+//! Structured differences between two versions of an atomic chunk
+//!
+//! [`AtomicChunk::diff`] compares what a chunk provides and requires, its
+//! metrics and where it sits in its source files, and adds a unified diff
+//! of the content when a lookup can produce both versions. The result
+//! serializes, so it can be printed by `cadi diff` or served as is.
+
+use crate::atomic::{AtomicChunk, SourceLocation};
+use crate::hash::verify_chunk_content;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Lines of context around each hunk of a unified diff
+pub const DIFF_CONTEXT: usize = 3;
+
+/// Largest line count product the unified diff will align; beyond it the
+/// whole content is shown as replaced
+const MAX_ALIGNMENT_CELLS: usize = 4_000_000;
+
+/// How one chunk differs from another
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChunkDiff {
+    pub old_id: String,
+    pub new_id: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub provides_added: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub provides_removed: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires_added: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires_removed: Vec<String>,
+    /// Metrics whose value changed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub metrics: Vec<MetricDelta>,
+    /// Source locations that moved, appeared or disappeared
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<SourceChange>,
+    /// Unified diff of the content, if both versions could be looked up
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_diff: Option<String>,
+    /// Content lines added and removed, counted from the text diff
+    #[serde(default)]
+    pub lines_added: usize,
+    #[serde(default)]
+    pub lines_removed: usize,
+}
+
+impl ChunkDiff {
+    /// Whether both sides are the same chunk
+    pub fn is_identical(&self) -> bool {
+        self.old_id == self.new_id
+    }
+
+    /// Whether the interface changed: anything provided or required was
+    /// added or removed
+    pub fn changes_interface(&self) -> bool {
+        !(self.provides_added.is_empty()
+            && self.provides_removed.is_empty()
+            && self.requires_added.is_empty()
+            && self.requires_removed.is_empty())
+    }
+
+    /// Change of a metric, if it changed
+    pub fn metric(&self, name: &str) -> Option<&MetricDelta> {
+        self.metrics.iter().find(|m| m.metric == name)
+    }
+}
+
+/// A metric that differs between two chunks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricDelta {
+    pub metric: String,
+    pub old: f64,
+    pub new: f64,
+}
+
+impl MetricDelta {
+    pub fn delta(&self) -> f64 {
+        self.new - self.old
+    }
+}
+
+/// Inclusive, 1-indexed line range of a chunk in a source file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A source file whose line range for the chunk changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceChange {
+    pub file: String,
+    /// Range in the old chunk, if it came from this file
+    pub old: Option<LineRange>,
+    /// Range in the new chunk, if it comes from this file
+    pub new: Option<LineRange>,
+}
+
+impl AtomicChunk {
+    /// Compare this chunk with a newer version of it. `content` looks up
+    /// the content of a chunk ID; the text diff is left out unless it finds
+    /// both.
+    pub fn diff(&self, other: &AtomicChunk, content: impl Fn(&str) -> Option<String>) -> ChunkDiff {
+        let (provides_added, provides_removed) = added_removed(&self.provides, &other.provides);
+        let (requires_added, requires_removed) = added_removed(&self.requires, &other.requires);
+
+        let mut diff = ChunkDiff {
+            old_id: self.chunk_id.clone(),
+            new_id: other.chunk_id.clone(),
+            provides_added,
+            provides_removed,
+            requires_added,
+            requires_removed,
+            metrics: metric_deltas(self, other),
+            sources: source_changes(&self.sources, &other.sources),
+            ..Default::default()
+        };
+
+        if diff.is_identical() {
+            return diff;
+        }
+        if let (Some(old), Some(new)) = (content(&self.chunk_id), content(&other.chunk_id)) {
+            let text = unified_diff(&old, &new, &self.display_name(), &other.display_name(), DIFF_CONTEXT);
+            for line in text.lines().filter(|l| !l.starts_with("+++") && !l.starts_with("---")) {
+                match line.as_bytes().first() {
+                    Some(b'+') => diff.lines_added += 1,
+                    Some(b'-') => diff.lines_removed += 1,
+                    _ => {}
+                }
+            }
+            diff.text_diff = Some(text);
+        }
+        diff
+    }
+}
+
+/// Entries of `new` missing from `old`, and of `old` missing from `new`
+fn added_removed(old: &[String], new: &[String]) -> (Vec<String>, Vec<String>) {
+    let (old_set, new_set): (BTreeSet<&String>, BTreeSet<&String>) = (old.iter().collect(), new.iter().collect());
+    (
+        new_set.difference(&old_set).map(|s| s.to_string()).collect(),
+        old_set.difference(&new_set).map(|s| s.to_string()).collect(),
+    )
+}
+
+fn metric_deltas(old: &AtomicChunk, new: &AtomicChunk) -> Vec<MetricDelta> {
+    let values = |chunk: &AtomicChunk| {
+        let m = &chunk.metrics;
+        [
+            ("size", Some(chunk.size as f64)),
+            ("loc", Some(m.loc as f64)),
+            ("complexity", m.complexity.map(f64::from)),
+            ("reusability_score", m.reusability_score.map(f64::from)),
+            ("export_count", Some(m.export_count as f64)),
+            ("dependency_count", Some(m.dependency_count as f64)),
+            ("coupling", m.coupling.map(f64::from)),
+            ("referenced_symbols", Some(m.referenced_symbols as f64)),
+            ("comment_density", m.comment_density.map(f64::from)),
+            ("max_nesting", Some(m.max_nesting as f64)),
+        ]
+    };
+    values(old)
+        .into_iter()
+        .zip(values(new))
+        .filter_map(|((metric, old), (_, new))| {
+            let (old, new) = (old.unwrap_or(0.0), new.unwrap_or(0.0));
+            (old != new).then(|| MetricDelta {
+                metric: metric.to_string(),
+                old,
+                new,
+            })
+        })
+        .collect()
+}
+
+fn line_range(location: &SourceLocation) -> Option<LineRange> {
+    Some(LineRange {
+        start: location.start_line?,
+        end: location.end_line?,
+    })
+}
+
+fn source_changes(old: &[SourceLocation], new: &[SourceLocation]) -> Vec<SourceChange> {
+    let files: BTreeSet<&str> = old.iter().chain(new).map(|s| s.file.as_str()).collect();
+    let find = |sources: &[SourceLocation], file: &str| sources.iter().find(|s| s.file == file).and_then(line_range);
+    files
+        .into_iter()
+        .filter_map(|file| {
+            let (old, new) = (find(old, file), find(new, file));
+            (old != new).then(|| SourceChange {
+                file: file.to_string(),
+                old,
+                new,
+            })
+        })
+        .collect()
+}
+
+/// Content of `chunk` cut from `file_content`, the current text of its
+/// first source file, the way the chunker cut it. `None` unless the cut
+/// matches the chunk ID, e.g. when the file changed since.
+pub fn source_content(chunk: &AtomicChunk, file_content: &str) -> Option<String> {
+    let range = line_range(chunk.sources.first()?)?;
+    let lines: Vec<&str> = file_content.lines().collect();
+    let content = if range.start == 0 && range.end >= lines.len() {
+        file_content.to_string()
+    } else {
+        lines.get(range.start.saturating_sub(1)..range.end.min(lines.len()))?.join("\n")
+    };
+    verify_chunk_content(&chunk.chunk_id, content.as_bytes()).then_some(content)
+}
+
+/// Line diff of `old` and `new` in unified format, with `context` lines
+/// around each hunk. Empty when they are equal.
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str, context: usize) -> String {
+    let (a, b): (Vec<&str>, Vec<&str>) = (old.lines().collect(), new.lines().collect());
+    let ops = align(&a, &b);
+    if ops.iter().all(|op| matches!(op, Op::Same(_))) {
+        return String::new();
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_label, new_label);
+    // Group the operations into hunks of changes less than 2 * context apart
+    let changed: Vec<usize> = (0..ops.len()).filter(|&i| !matches!(ops[i], Op::Same(_))).collect();
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for &i in &changed {
+        let start = i.saturating_sub(context);
+        let end = (i + context + 1).min(ops.len());
+        match groups.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => groups.push((start, end)),
+        }
+    }
+
+    for (start, end) in groups {
+        let (mut old_start, mut new_start) = (1, 1);
+        for op in &ops[..start] {
+            match op {
+                Op::Same(_) => {
+                    old_start += 1;
+                    new_start += 1;
+                }
+                Op::Removed(_) => old_start += 1,
+                Op::Added(_) => new_start += 1,
+            }
+        }
+        let hunk = &ops[start..end];
+        let old_len = hunk.iter().filter(|op| !matches!(op, Op::Added(_))).count();
+        let new_len = hunk.iter().filter(|op| !matches!(op, Op::Removed(_))).count();
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_len),
+            hunk_range(new_start, new_len)
+        ));
+        for op in hunk {
+            match op {
+                Op::Same(i) => out.push_str(&format!(" {}\n", a[*i])),
+                Op::Removed(i) => out.push_str(&format!("-{}\n", a[*i])),
+                Op::Added(j) => out.push_str(&format!("+{}\n", b[*j])),
+            }
+        }
+    }
+    out
+}
+
+/// `start,len` of a hunk side; an empty side starts at the line before it
+fn hunk_range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start - 1),
+        1 => start.to_string(),
+        _ => format!("{},{}", start, len),
+    }
+}
+
+/// A line of the alignment, by index into the old or new lines; kept
+/// lines are indexed into the old ones
+enum Op {
+    Same(usize),
+    Removed(usize),
+    Added(usize),
+}
+
+/// Align two line lists along their longest common subsequence
+fn align(a: &[&str], b: &[&str]) -> Vec<Op> {
+    // Common prefix and suffix need no alignment
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut ops: Vec<Op> = (0..prefix).map(Op::Same).collect();
+    if a_mid.len() * b_mid.len() > MAX_ALIGNMENT_CELLS {
+        ops.extend((0..a_mid.len()).map(|i| Op::Removed(prefix + i)));
+        ops.extend((0..b_mid.len()).map(|j| Op::Added(prefix + j)));
+    } else {
+        // lcs[i][j]: length of the LCS of a_mid[i..] and b_mid[j..]
+        let (n, m) = (a_mid.len(), b_mid.len());
+        let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i][j] = if a_mid[i] == b_mid[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && a_mid[i] == b_mid[j] {
+                ops.push(Op::Same(prefix + i));
+                i += 1;
+                j += 1;
+            } else if j < m && (i == n || lcs[i][j + 1] > lcs[i + 1][j]) {
+                ops.push(Op::Added(prefix + j));
+                j += 1;
+            } else {
+                ops.push(Op::Removed(prefix + i));
+                i += 1;
+            }
+        }
+    }
+    ops.extend((a.len() - suffix..a.len()).map(Op::Same));
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::chunk_id_from_content;
+
+    fn chunk(content: &str, provides: &[&str], start: usize) -> AtomicChunk {
+        let id = chunk_id_from_content(content.as_bytes());
+        let mut chunk = AtomicChunk::new(id.clone(), "parse".into(), "rust".into(), id, content.len())
+            .with_alias("app/parse");
+        chunk.provides = provides.iter().map(|s| s.to_string()).collect();
+        chunk.requires = vec!["serde".to_string()];
+        chunk.metrics.loc = content.lines().count();
+        chunk.sources = vec![SourceLocation {
+            file: "src/parse.rs".into(),
+            file_bytes: None,
+            start_line: Some(start),
+            end_line: Some(start + content.lines().count() - 1),
+            start_col: None,
+            end_col: None,
+        }];
+        chunk
+    }
+
+    #[test]
+    fn test_chunk_diff() {
+        let old_text = "fn parse() {\n    let a = 1;\n    a\n}";
+        let new_text = "fn parse() {\n    let a = 2;\n    log(a);\n    a\n}";
+        let old = chunk(old_text, &["parse"], 10);
+        let new = chunk(new_text, &["parse", "Parsed"], 12);
+        let lookup = |id: &str| {
+            [&old, &new]
+                .into_iter()
+                .find(|c| c.chunk_id == id)
+                .map(|c| if c.chunk_id == old.chunk_id { old_text } else { new_text }.to_string())
+        };
+
+        let diff = old.diff(&new, lookup);
+        assert!(!diff.is_identical());
+        assert_eq!(diff.provides_added, ["Parsed"]);
+        assert!(diff.provides_removed.is_empty() && diff.requires_added.is_empty());
+        assert!(diff.changes_interface());
+        assert_eq!(diff.metric("loc").unwrap().delta(), 1.0);
+        assert_eq!(
+            diff.sources,
+            [SourceChange {
+                file: "src/parse.rs".into(),
+                old: Some(LineRange { start: 10, end: 13 }),
+                new: Some(LineRange { start: 12, end: 16 }),
+            }]
+        );
+        assert_eq!(
+            diff.text_diff.as_deref(),
+            Some(
+                "--- app/parse\n+++ app/parse\n@@ -1,4 +1,5 @@\n fn parse() {\n-    let a = 1;\n+    let a = 2;\n+    log(a);\n     a\n }\n"
+            )
+        );
+        assert_eq!((diff.lines_added, diff.lines_removed), (2, 1));
+
+        // Without content there is no text diff, and a chunk equals itself
+        assert_eq!(old.diff(&new, |_| None).text_diff, None);
+        assert!(old.diff(&old, lookup).is_identical());
+        let same = old.diff(&old, lookup);
+        assert!(same.text_diff.is_none() && !same.changes_interface() && same.metrics.is_empty());
+    }
+
+    #[test]
+    fn test_unified_diff_hunks() {
+        let old: Vec<String> = (1..=20).map(|i| format!("line {}", i)).collect();
+        let mut new = old.clone();
+        new[1] = "changed 2".into();
+        new.remove(17);
+        let diff = unified_diff(&old.join("\n"), &new.join("\n"), "a", "b", 2);
+        let headers: Vec<&str> = diff.lines().filter(|l| l.starts_with("@@")).collect();
+        assert_eq!(headers, ["@@ -1,4 +1,4 @@", "@@ -16,5 +16,4 @@"]);
+        assert_eq!(unified_diff("same", "same", "a", "b", 3), "");
+        assert_eq!(unified_diff("", "new", "a", "b", 3), "--- a\n+++ b\n@@ -0,0 +1 @@\n+new\n");
+    }
+
+    #[test]
+    fn test_source_content_checks_the_chunk_id() {
+        let file = "use serde;\nfn parse() {\n    1\n}\n";
+        let mut parse = chunk("fn parse() {\n    1\n}", &["parse"], 2);
+        assert_eq!(source_content(&parse, file).as_deref(), Some("fn parse() {\n    1\n}"));
+        assert_eq!(source_content(&parse, &file.replace('1', "2")), None);
+        parse.sources.clear();
+        assert_eq!(source_content(&parse, file), None);
+    }
+}
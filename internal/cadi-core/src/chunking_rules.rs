@@ -0,0 +1,163 @@
+//! Per-path chunking rules
+//!
+//! A [`ChunkingRule`] pins the chunking strategy (and optionally the
+//! category) of every file matching a glob. Rules are evaluated in order and
+//! the first match wins; files no rule matches fall back to the automatic
+//! decision. Patterns use the same syntax as `.gitignore`: a pattern
+//! containing `/` is matched against the path relative to the project root,
+//! any other pattern against the file name alone.
+
+use crate::atomic::ChunkCategory;
+use crate::error::{CadiError, CadiResult};
+use crate::gitignore::{glob_match, unterminated_class};
+use crate::smart_chunker::ChunkingStrategy;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Chunking strategy override for files matching a glob
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkingRule {
+    /// Glob matched against the project-relative path
+    pub pattern: String,
+    /// Strategy for matching files
+    pub strategy: ChunkingStrategy,
+    /// Category assigned to the resulting chunks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<ChunkCategory>,
+}
+
+impl ChunkingRule {
+    pub fn new(pattern: impl Into<String>, strategy: ChunkingStrategy) -> Self {
+        Self {
+            pattern: pattern.into(),
+            strategy,
+            category: None,
+        }
+    }
+
+    pub fn with_category(mut self, category: ChunkCategory) -> Self {
+        self.category = Some(category);
+        self
+    }
+}
+
+/// An ordered, validated set of chunking rules
+#[derive(Debug, Clone, Default)]
+pub struct ChunkingRules {
+    rules: Vec<CompiledRule>,
+}
+
+#[derive(Debug, Clone)]
+struct CompiledRule {
+    pattern: Vec<char>,
+    anchored: bool,
+    rule: ChunkingRule,
+}
+
+impl ChunkingRules {
+    /// Validate the patterns of `rules`, keeping their order
+    pub fn compile(rules: &[ChunkingRule]) -> CadiResult<Self> {
+        let rules = rules
+            .iter()
+            .enumerate()
+            .map(|(i, rule)| {
+                let invalid = |reason: &str| {
+                    CadiError::Configuration(format!(
+                        "Invalid pattern '{}' in chunking rule {}: {}",
+                        rule.pattern,
+                        i + 1,
+                        reason
+                    ))
+                };
+
+                let trimmed = rule.pattern.trim();
+                let anchored = trimmed.contains('/');
+                let pattern: Vec<char> = trimmed.trim_start_matches('/').chars().collect();
+
+                if pattern.is_empty() {
+                    return Err(invalid("pattern is empty"));
+                }
+                if unterminated_class(&pattern) {
+                    return Err(invalid("unterminated character class"));
+                }
+                if pattern.ends_with(&['\\']) && !pattern.ends_with(&['\\', '\\']) {
+                    return Err(invalid("trailing escape"));
+                }
+
+                Ok(CompiledRule {
+                    pattern,
+                    anchored,
+                    rule: rule.clone(),
+                })
+            })
+            .collect::<CadiResult<Vec<_>>>()?;
+
+        Ok(Self { rules })
+    }
+
+    /// Whether there are no rules
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// First rule matching a path relative to the project root
+    pub fn resolve(&self, path: &Path) -> Option<&ChunkingRule> {
+        let path = path.to_string_lossy().replace('\\', "/");
+        let path = path.trim_start_matches("./");
+        let name = path.rsplit('/').next().unwrap_or(path);
+
+        let path: Vec<char> = path.chars().collect();
+        let name: Vec<char> = name.chars().collect();
+
+        self.rules
+            .iter()
+            .find(|compiled| {
+                let text = if compiled.anchored { &path } else { &name };
+                glob_match(&compiled.pattern, text)
+            })
+            .map(|compiled| &compiled.rule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let rules = ChunkingRules::compile(&[
+            ChunkingRule::new("src/generated/**", ChunkingStrategy::Skip),
+            ChunkingRule::new("src/**/*.rs", ChunkingStrategy::ByEntity),
+            ChunkingRule::new("docs/**/*.md", ChunkingStrategy::Atomic)
+                .with_category(ChunkCategory::Docs),
+            ChunkingRule::new("*.sql", ChunkingStrategy::Skip),
+        ])
+        .unwrap();
+
+        let strategy = |path: &str| rules.resolve(Path::new(path)).map(|r| r.strategy.clone());
+        assert_eq!(strategy("src/generated/api.rs"), Some(ChunkingStrategy::Skip));
+        assert_eq!(strategy("src/lib.rs"), Some(ChunkingStrategy::ByEntity));
+        assert_eq!(strategy("src/net/http.rs"), Some(ChunkingStrategy::ByEntity));
+        assert_eq!(strategy("docs/guide/intro.md"), Some(ChunkingStrategy::Atomic));
+        assert_eq!(strategy("migrations/001_init.sql"), Some(ChunkingStrategy::Skip));
+        assert_eq!(strategy("README.md"), None);
+
+        let docs = rules.resolve(Path::new("docs/index.md")).unwrap();
+        assert_eq!(docs.category, Some(ChunkCategory::Docs));
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_config_error() {
+        let err = ChunkingRules::compile(&[
+            ChunkingRule::new("src/**/*.rs", ChunkingStrategy::ByEntity),
+            ChunkingRule::new("src/[ab", ChunkingStrategy::Atomic),
+        ])
+        .unwrap_err();
+
+        assert!(matches!(err, CadiError::Configuration(_)));
+        assert_eq!(
+            err.to_string(),
+            "Configuration error: Invalid pattern 'src/[ab' in chunking rule 2: unterminated character class"
+        );
+    }
+}
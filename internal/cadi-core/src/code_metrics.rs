@@ -0,0 +1,334 @@
+//! Source metrics for chunks
+//!
+//! Cheap, language-aware approximations computed from the chunk text:
+//! comments and string literals are blanked out first so keywords inside
+//! them are not counted.
+
+use crate::atomic::ChunkMetrics;
+use std::collections::HashSet;
+
+/// Compute the metrics of a chunk's source. `loc`, `complexity`,
+/// `referenced_symbols`, `comment_density` and `max_nesting` are filled in;
+/// export and dependency counts come from the chunk and are left at zero.
+pub fn compute_metrics(content: &str, language: &str) -> ChunkMetrics {
+    let syntax = Syntax::for_language(language);
+    let (code, comment_lines) = strip_comments_and_strings(content, &syntax);
+
+    let non_blank = content.lines().filter(|l| !l.trim().is_empty()).count();
+    let comment_density = if non_blank == 0 {
+        None
+    } else {
+        Some(comment_lines as f32 / non_blank as f32)
+    };
+
+    ChunkMetrics {
+        loc: content.lines().count(),
+        complexity: Some(cyclomatic_complexity(&code, &syntax) as f32),
+        referenced_symbols: referenced_symbols(&code, &syntax),
+        comment_density,
+        max_nesting: if syntax.indent_blocks {
+            indent_nesting(&code)
+        } else {
+            brace_nesting(&code)
+        },
+        ..Default::default()
+    }
+}
+
+/// The parts of a language's syntax the metrics look at
+struct Syntax {
+    line_comment: &'static str,
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    /// Keywords that open a decision point
+    branch_keywords: &'static [&'static str],
+    /// Whether `=>` marks a match/switch arm
+    arrow_arms: bool,
+    /// Blocks are delimited by indentation rather than braces
+    indent_blocks: bool,
+    keywords: &'static [&'static str],
+}
+
+impl Syntax {
+    fn for_language(language: &str) -> Self {
+        match language {
+            "rust" => Syntax {
+                line_comment: "//",
+                block_comment: Some(("/*", "*/")),
+                quotes: &['"'],
+                branch_keywords: &["if", "while", "for", "loop"],
+                arrow_arms: true,
+                indent_blocks: false,
+                keywords: &[
+                    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+                    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+                    "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+                    "trait", "true", "type", "unsafe", "use", "where", "while", "Some", "None", "Ok", "Err",
+                ],
+            },
+            "python" => Syntax {
+                line_comment: "#",
+                block_comment: None,
+                quotes: &['"', '\''],
+                branch_keywords: &["if", "elif", "for", "while", "except", "case", "and", "or"],
+                arrow_arms: false,
+                indent_blocks: true,
+                keywords: &[
+                    "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+                    "elif", "else", "except", "False", "finally", "for", "from", "global", "if", "import",
+                    "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return",
+                    "self", "True", "try", "while", "with", "yield", "print",
+                ],
+            },
+            "go" => Syntax {
+                line_comment: "//",
+                block_comment: Some(("/*", "*/")),
+                quotes: &['"', '`'],
+                branch_keywords: &["if", "for", "case"],
+                arrow_arms: false,
+                indent_blocks: false,
+                keywords: &[
+                    "break", "case", "chan", "const", "continue", "default", "defer", "else", "fallthrough",
+                    "for", "func", "go", "goto", "if", "import", "interface", "map", "package", "range",
+                    "return", "select", "struct", "switch", "type", "var", "nil", "true", "false",
+                ],
+            },
+            "typescript" | "javascript" => Syntax {
+                line_comment: "//",
+                block_comment: Some(("/*", "*/")),
+                quotes: &['"', '\'', '`'],
+                branch_keywords: &["if", "for", "while", "case", "catch"],
+                arrow_arms: false,
+                indent_blocks: false,
+                keywords: &[
+                    "async", "await", "break", "case", "catch", "class", "const", "continue", "default",
+                    "delete", "do", "else", "export", "extends", "false", "finally", "for", "function",
+                    "if", "import", "in", "instanceof", "let", "new", "null", "return", "super", "switch",
+                    "this", "throw", "true", "try", "typeof", "undefined", "var", "void", "while", "yield",
+                ],
+            },
+            _ => Syntax {
+                line_comment: "//",
+                block_comment: Some(("/*", "*/")),
+                quotes: &['"'],
+                branch_keywords: &["if", "for", "while", "case", "catch"],
+                arrow_arms: false,
+                indent_blocks: false,
+                keywords: &[
+                    "break", "case", "catch", "char", "class", "const", "continue", "default", "do",
+                    "double", "else", "enum", "false", "float", "for", "if", "int", "long", "new", "null",
+                    "private", "protected", "public", "return", "sizeof", "static", "struct", "switch",
+                    "this", "throw", "true", "try", "typedef", "unsigned", "using", "void", "while",
+                ],
+            },
+        }
+    }
+}
+
+/// Blank out comments and string literal contents, keeping line structure.
+/// Returns the code and the number of lines that carry a comment.
+fn strip_comments_and_strings(content: &str, syntax: &Syntax) -> (String, usize) {
+    let mut code = String::with_capacity(content.len());
+    let mut comment_lines = 0;
+    let mut in_block = false;
+    let mut in_string: Option<char> = None;
+
+    for line in content.lines() {
+        let mut has_comment = in_block;
+        let mut rest = line;
+
+        while !rest.is_empty() {
+            if in_block {
+                let (_, end) = syntax.block_comment.unwrap_or(("", ""));
+                match rest.find(end) {
+                    Some(i) => {
+                        in_block = false;
+                        rest = &rest[i + end.len()..];
+                    }
+                    None => rest = "",
+                }
+                continue;
+            }
+
+            if let Some(quote) = in_string {
+                let mut escaped = false;
+                let close = rest.char_indices().find(|&(_, c)| {
+                    let found = c == quote && !escaped;
+                    escaped = c == '\\' && !escaped;
+                    found
+                });
+                match close {
+                    Some((i, _)) => {
+                        code.push(quote);
+                        in_string = None;
+                        rest = &rest[i + quote.len_utf8()..];
+                    }
+                    None => rest = "",
+                }
+                continue;
+            }
+
+            if rest.starts_with(syntax.line_comment) {
+                has_comment = true;
+                break;
+            }
+            if let Some((start, _)) = syntax.block_comment {
+                if rest.starts_with(start) {
+                    has_comment = true;
+                    in_block = true;
+                    rest = &rest[start.len()..];
+                    continue;
+                }
+            }
+
+            let c = rest.chars().next().unwrap_or_default();
+            code.push(c);
+            if syntax.quotes.contains(&c) {
+                in_string = Some(c);
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+
+        // Only backtick strings span lines
+        if in_string.is_some_and(|q| q != '`') {
+            in_string = None;
+        }
+        if has_comment {
+            comment_lines += 1;
+        }
+        code.push('\n');
+    }
+
+    (code, comment_lines)
+}
+
+fn identifiers(code: &str) -> impl Iterator<Item = (usize, &str)> {
+    code.char_indices()
+        .filter(move |&(i, c)| {
+            (c.is_alphabetic() || c == '_')
+                && !code[..i]
+                    .chars()
+                    .next_back()
+                    .is_some_and(|p| p.is_alphanumeric() || p == '_')
+        })
+        .map(move |(i, _)| {
+            let end = code[i..]
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .map_or(code.len(), |e| i + e);
+            (end, &code[i..end])
+        })
+}
+
+/// 1 + decision points: branch keywords, short-circuit operators and, for
+/// languages with `=>` arms, every match arm after the first
+fn cyclomatic_complexity(code: &str, syntax: &Syntax) -> usize {
+    let branches = identifiers(code)
+        .filter(|(_, word)| syntax.branch_keywords.contains(word))
+        .count();
+    let operators = code.matches("&&").count() + code.matches("||").count();
+    let arms = if syntax.arrow_arms {
+        let matches = identifiers(code).filter(|(_, word)| *word == "match").count();
+        code.matches("=>").count().saturating_sub(matches)
+    } else {
+        0
+    };
+
+    1 + branches + operators + arms
+}
+
+/// Distinct non-keyword names that are called (`name(`), used as a path
+/// (`name::`) or look like types (`Name`)
+fn referenced_symbols(code: &str, syntax: &Syntax) -> usize {
+    identifiers(code)
+        .filter(|&(end, word)| {
+            let after = code[end..].trim_start();
+            !syntax.keywords.contains(&word)
+                && (after.starts_with('(')
+                    || after.starts_with("::")
+                    || word.starts_with(|c: char| c.is_uppercase()))
+        })
+        .map(|(_, word)| word)
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+fn brace_nesting(code: &str) -> usize {
+    let mut depth = 0usize;
+    let mut max = 0;
+    for c in code.chars() {
+        match c {
+            '{' => {
+                depth += 1;
+                max = max.max(depth);
+            }
+            '}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    max
+}
+
+/// Indentation levels below the least indented line, for languages whose
+/// blocks are indented (a tab counts as four spaces)
+fn indent_nesting(code: &str) -> usize {
+    let widths: Vec<usize> = code
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| {
+            l.chars()
+                .take_while(|c| c.is_whitespace())
+                .map(|c| if c == '\t' { 4 } else { 1 })
+                .sum()
+        })
+        .collect();
+    let Some(&base) = widths.iter().min() else {
+        return 0;
+    };
+    widths.iter().map(|w| (w - base).div_ceil(4)).max().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_branching_code_scores_higher_complexity() {
+        let straight = "fn area(w: u32, h: u32) -> u32 {\n    let a = w * h;\n    a\n}\n";
+        let branching = r#"fn describe(n: i32) -> &'static str {
+    // "if" in a comment and a string does not count
+    if n < 0 {
+        "negative if"
+    } else if n == 0 {
+        "zero"
+    } else {
+        match n % 3 {
+            0 => "fizz",
+            1 => "one",
+            _ => "other",
+        }
+    }
+}
+"#;
+
+        let straight = compute_metrics(straight, "rust");
+        let branching = compute_metrics(branching, "rust");
+        assert_eq!(straight.complexity, Some(1.0));
+        // Two ifs and two extra match arms
+        assert_eq!(branching.complexity, Some(5.0));
+        assert_eq!(straight.max_nesting, 1);
+        assert_eq!(branching.max_nesting, 3);
+        assert!(branching.comment_density.unwrap() > 0.0);
+        assert_eq!(straight.comment_density, Some(0.0));
+    }
+
+    #[test]
+    fn test_python_metrics() {
+        let content = "def clamp(x, lo, hi):\n    # keep x in range\n    if x < lo or x > hi:\n        return max(lo, min(x, hi))\n    return x\n";
+        let metrics = compute_metrics(content, "python");
+        assert_eq!(metrics.complexity, Some(3.0));
+        assert_eq!(metrics.max_nesting, 2);
+        // clamp, max and min are called
+        assert_eq!(metrics.referenced_symbols, 3);
+        assert_eq!(metrics.loc, 5);
+    }
+}
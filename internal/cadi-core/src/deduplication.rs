@@ -1,23 +1,75 @@
-use crate::normalizer::SemanticNormalizer;
+use crate::normalizer::{SemanticHash, SemanticNormalizer};
 use crate::error::CadiResult;
-use std::collections::HashMap;
+use crate::graph::{Edge, EdgeType, GraphStore};
+use crate::stats::DedupStats;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
 
-/// In-memory deduplication engine for semantic hashes
+/// In-memory deduplication engine for semantic hashes.
+///
+/// Hashes only match when they were computed with the same normalization
+/// version and passes. Mixing schemes in one engine is allowed but logged,
+/// since chunks hashed under different schemes never dedupe.
 #[derive(Debug, Default)]
 pub struct DeduplicationEngine {
     pub hash_index: HashMap<String, Vec<String>>, // hash -> [chunk ids]
+    /// Normalization schemes (`v2:0f`) of the registered hashes
+    schemes: BTreeSet<String>,
 }
 
-impl DeduplicationEngine {
-    pub fn new() -> Self {
+/// A chunk to compare when looking for semantic duplicates
+#[derive(Debug, Clone)]
+pub struct DuplicateCandidate {
+    pub chunk_id: String,
+    pub language: String,
+    pub content: String,
+}
+
+impl DuplicateCandidate {
+    pub fn new(
+        chunk_id: impl Into<String>,
+        language: impl Into<String>,
+        content: impl Into<String>,
+    ) -> Self {
         Self {
-            hash_index: HashMap::new(),
+            chunk_id: chunk_id.into(),
+            language: language.into(),
+            content: content.into(),
         }
     }
+}
+
+/// Chunks that appear to implement the same thing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateCluster {
+    /// Member chunk IDs, sorted
+    pub chunk_ids: Vec<String>,
+    /// Weakest similarity linking the cluster together (1.0 for identical
+    /// normalized code)
+    pub confidence: f64,
+}
+
+impl DeduplicationEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
     /// Register a chunk by semantic hash
     /// Returns: (is_new, equivalents)
     pub fn register_chunk(&mut self, chunk_id: &str, semantic_hash: &str) -> (bool, Vec<String>) {
+        if let Some(scheme) = SemanticHash::parse(semantic_hash).map(|hash| hash.scheme()) {
+            if !self.schemes.is_empty() && !self.schemes.contains(&scheme) {
+                tracing::warn!(
+                    chunk_id,
+                    scheme = %scheme,
+                    registered = ?self.schemes,
+                    "semantic hash uses a different normalization scheme; it will not dedupe against existing chunks"
+                );
+            }
+            self.schemes.insert(scheme);
+        }
+
         let entry = self.hash_index.entry(semantic_hash.to_string()).or_insert_with(Vec::new);
         let is_new = entry.is_empty();
         let equivalents = entry.clone();
@@ -26,9 +78,190 @@ impl DeduplicationEngine {
     }
 
     pub fn find_equivalents(&self, semantic_hash: &str) -> Vec<String> {
+        if let Some(scheme) = SemanticHash::parse(semantic_hash).map(|hash| hash.scheme()) {
+            if !self.schemes.is_empty() && !self.schemes.contains(&scheme) {
+                tracing::warn!(
+                    scheme = %scheme,
+                    registered = ?self.schemes,
+                    "semantic hash uses a different normalization scheme than every registered chunk"
+                );
+                return Vec::new();
+            }
+        }
         self.hash_index.get(semantic_hash).cloned().unwrap_or_default()
     }
 
+    /// Whether two semantic hashes were computed with the same normalization
+    /// scheme. Hashes that aren't versioned semantic hashes compare as-is.
+    pub fn is_comparable(a: &str, b: &str) -> bool {
+        match (SemanticHash::parse(a), SemanticHash::parse(b)) {
+            (Some(a), Some(b)) => a.is_comparable(&b),
+            _ => true,
+        }
+    }
+
+    /// Registered chunks against distinct hashes
+    pub fn stats(&self) -> DedupStats {
+        let chunks: usize = self.hash_index.values().map(Vec::len).sum();
+        let unique = self.hash_index.len();
+        DedupStats {
+            chunks,
+            unique,
+            ratio: if unique == 0 { 1.0 } else { chunks as f64 / unique as f64 },
+        }
+    }
+
+    /// Cluster chunks whose normalized code is identical or similar enough.
+    ///
+    /// Identifiers are alpha-renamed and whitespace and comments dropped, so
+    /// renamed copies have equal semantic hashes. Near-duplicates are scored
+    /// by token bigram overlap. Pairs scoring at least `threshold` are linked
+    /// and the linked groups returned, most confident first.
+    pub fn find_semantic_duplicates(
+        chunks: &[DuplicateCandidate],
+        threshold: f64,
+    ) -> Vec<DuplicateCluster> {
+        Self::find_semantic_duplicates_with_embeddings(chunks, threshold, &HashMap::new())
+    }
+
+    /// Like [`Self::find_semantic_duplicates`], additionally scoring pairs
+    /// by the cosine similarity of their embeddings (keyed by chunk ID).
+    /// Embeddings are what link duplicates written in different languages.
+    pub fn find_semantic_duplicates_with_embeddings(
+        chunks: &[DuplicateCandidate],
+        threshold: f64,
+        embeddings: &HashMap<String, Vec<f32>>,
+    ) -> Vec<DuplicateCluster> {
+        let mut seen = HashSet::new();
+        let chunks: Vec<&DuplicateCandidate> = chunks
+            .iter()
+            .filter(|c| seen.insert(c.chunk_id.as_str()))
+            .collect();
+
+        let normalized: Vec<(String, HashSet<String>)> = chunks
+            .iter()
+            .map(|c| normalize_for_comparison(&c.language, &c.content))
+            .collect();
+
+        // Best score per pair, keyed by (lower, higher) index
+        let mut scores: HashMap<(usize, usize), f64> = HashMap::new();
+        let mut link = |a: usize, b: usize, score: f64| {
+            if score >= threshold {
+                let entry = scores.entry((a.min(b), a.max(b))).or_insert(score);
+                *entry = entry.max(score);
+            }
+        };
+
+        // Identical normalized code
+        let mut by_hash: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, (hash, _)) in normalized.iter().enumerate() {
+            by_hash.entry(hash.as_str()).or_default().push(i);
+        }
+        for group in by_hash.values() {
+            for &other in &group[1..] {
+                link(group[0], other, 1.0);
+            }
+        }
+
+        // Token overlap. Jaccard similarity can't exceed the ratio of the set
+        // sizes, so only chunks of comparable size are compared.
+        let mut by_size: Vec<usize> = (0..chunks.len())
+            .filter(|&i| !normalized[i].1.is_empty())
+            .collect();
+        by_size.sort_by_key(|&i| normalized[i].1.len());
+        for (pos, &i) in by_size.iter().enumerate() {
+            let a = &normalized[i].1;
+            for &j in &by_size[pos + 1..] {
+                let b = &normalized[j].1;
+                if (a.len() as f64) < threshold * b.len() as f64 {
+                    break;
+                }
+                let shared = a.intersection(b).count();
+                link(i, j, shared as f64 / (a.len() + b.len() - shared) as f64);
+            }
+        }
+
+        // Embedding similarity
+        let vectors: Vec<(usize, &Vec<f32>)> = chunks
+            .iter()
+            .enumerate()
+            .filter_map(|(i, c)| embeddings.get(&c.chunk_id).map(|e| (i, e)))
+            .collect();
+        for (pos, &(i, a)) in vectors.iter().enumerate() {
+            for &(j, b) in &vectors[pos + 1..] {
+                link(i, j, cosine_similarity(a, b));
+            }
+        }
+
+        // Union the strongest links first so each cluster's confidence is
+        // the weakest link it needed
+        let mut pairs: Vec<((usize, usize), f64)> = scores.into_iter().collect();
+        pairs.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let mut parent: Vec<usize> = (0..chunks.len()).collect();
+        let mut confidence = vec![1.0f64; chunks.len()];
+        fn find(parent: &mut [usize], i: usize) -> usize {
+            let mut root = i;
+            while parent[root] != root {
+                root = parent[root];
+            }
+            parent[i] = root;
+            root
+        }
+        for ((a, b), score) in pairs {
+            let (ra, rb) = (find(&mut parent, a), find(&mut parent, b));
+            if ra != rb {
+                parent[rb] = ra;
+                confidence[ra] = confidence[ra].min(confidence[rb]).min(score);
+            }
+        }
+
+        let mut groups: HashMap<usize, Vec<String>> = HashMap::new();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let root = find(&mut parent, i);
+            groups.entry(root).or_default().push(chunk.chunk_id.clone());
+        }
+
+        let mut clusters: Vec<DuplicateCluster> = groups
+            .into_iter()
+            .filter(|(_, ids)| ids.len() > 1)
+            .map(|(root, mut chunk_ids)| {
+                chunk_ids.sort();
+                DuplicateCluster {
+                    chunk_ids,
+                    confidence: confidence[root],
+                }
+            })
+            .collect();
+        clusters.sort_by(|a, b| {
+            b.confidence
+                .total_cmp(&a.confidence)
+                .then_with(|| a.chunk_ids.cmp(&b.chunk_ids))
+        });
+        clusters
+    }
+
+    /// Link every member of each cluster to the others with `EquivalentTo`
+    /// edges, as confident as the cluster. Returns the number of edges
+    /// written.
+    pub fn record_equivalences(
+        store: &GraphStore,
+        clusters: &[DuplicateCluster],
+    ) -> CadiResult<usize> {
+        let mut written = 0;
+        for cluster in clusters {
+            for a in &cluster.chunk_ids {
+                for b in cluster.chunk_ids.iter().filter(|b| *b != a) {
+                    store.add_edge(
+                        &Edge::new(a, b, EdgeType::EquivalentTo).with_confidence(cluster.confidence as f32),
+                    )?;
+                    written += 1;
+                }
+            }
+        }
+        Ok(written)
+    }
+
     /// Check semantic similarity between two code strings
     /// Returns: (is_identical, similarity_score)
     pub async fn check_similarity(code_a: &str, code_b: &str, language: &str) -> CadiResult<(bool, f32)> {
@@ -44,7 +277,7 @@ impl DeduplicationEngine {
         Ok((false, similarity))
     }
 
-    fn levenshtein_similarity(a: &str, b: &str) -> f32 {
+    pub(crate) fn levenshtein_similarity(a: &str, b: &str) -> f32 {
         let dist = Self::levenshtein_distance(a, b);
         let max_len = a.len().max(b.len());
         if max_len == 0 { return 1.0; }
@@ -74,6 +307,36 @@ impl DeduplicationEngine {
     }
 }
 
+/// Semantic hash and token bigrams of a chunk's normalized code. Languages
+/// the normalizer doesn't support only have their whitespace collapsed.
+fn normalize_for_comparison(language: &str, content: &str) -> (String, HashSet<String>) {
+    let canonical = SemanticNormalizer::new(language)
+        .and_then(|normalizer| normalizer.normalize(content))
+        .map(|result| result.canonical)
+        .unwrap_or_else(|_| content.split_whitespace().collect::<Vec<_>>().join(" "));
+
+    let token = Regex::new(r"[A-Za-z_][A-Za-z0-9_]*|\d+|\S").unwrap();
+    let tokens: Vec<&str> = token.find_iter(&canonical).map(|m| m.as_str()).collect();
+    let bigrams = tokens.windows(2).map(|w| format!("{} {}", w[0], w[1])).collect();
+
+    let hash = crate::hash::sha256_str(&canonical);
+    (hash, bigrams)
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f64 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f64 = a.iter().zip(b).map(|(x, y)| *x as f64 * *y as f64).sum();
+    let norm_a: f64 = a.iter().map(|x| (*x as f64).powi(2)).sum::<f64>().sqrt();
+    let norm_b: f64 = b.iter().map(|x| (*x as f64).powi(2)).sum::<f64>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -81,7 +344,7 @@ mod tests {
     #[test]
     fn test_dedup_register_and_find() {
         let mut engine = DeduplicationEngine::new();
-        let hash = "semantic:abc123";
+        let hash = "sem:v2:0f:abc123";
         let (is_new_1, eq1) = engine.register_chunk("chunk1", hash);
         assert!(is_new_1);
         assert!(eq1.is_empty());
@@ -93,4 +356,76 @@ mod tests {
         let found = engine.find_equivalents(hash);
         assert_eq!(found, vec!["chunk1".to_string(), "chunk2".to_string()]);
     }
+
+    #[test]
+    fn test_mismatched_normalization_schemes_do_not_dedupe() {
+        let mut engine = DeduplicationEngine::new();
+        engine.register_chunk("chunk1", "sem:v2:0f:abc123");
+
+        // Same digest, different passes
+        let (is_new, equivalents) = engine.register_chunk("chunk2", "sem:v2:07:abc123");
+        assert!(is_new);
+        assert!(equivalents.is_empty());
+
+        // Legacy hashes parse as version 1
+        assert!(engine.find_equivalents("semantic:abc123").is_empty());
+        assert!(!DeduplicationEngine::is_comparable("semantic:abc123", "sem:v2:07:abc123"));
+        assert!(DeduplicationEngine::is_comparable("semantic:abc123", "sem:v1:07:def456"));
+    }
+
+    #[test]
+    fn test_renamed_copies_cluster_together() {
+        let chunks = vec![
+            DuplicateCandidate::new(
+                "chunk:add",
+                "rust",
+                "fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n",
+            ),
+            DuplicateCandidate::new(
+                "chunk:sum",
+                "rust",
+                "fn sum(left: i32,   right: i32) -> i32\n{\n    left + right\n}\n",
+            ),
+            DuplicateCandidate::new(
+                "chunk:greet",
+                "rust",
+                "fn greet(name: &str) -> String {\n    format!(\"Hello, {}!\", name)\n}\n",
+            ),
+        ];
+
+        let clusters = DeduplicationEngine::find_semantic_duplicates(&chunks, 0.9);
+        assert_eq!(
+            clusters,
+            vec![DuplicateCluster {
+                chunk_ids: vec!["chunk:add".to_string(), "chunk:sum".to_string()],
+                confidence: 1.0,
+            }]
+        );
+
+        let store = GraphStore::in_memory().unwrap();
+        assert_eq!(DeduplicationEngine::record_equivalences(&store, &clusters).unwrap(), 2);
+        assert_eq!(
+            store.get_dependencies_of_type("chunk:add", EdgeType::EquivalentTo).unwrap(),
+            vec!["chunk:sum".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_embeddings_link_cross_language_duplicates() {
+        let chunks = vec![
+            DuplicateCandidate::new("chunk:py", "python", "def add(a, b):\n    return a + b\n"),
+            DuplicateCandidate::new("chunk:ts", "typescript", "function add(a: number, b: number) { return a + b; }"),
+        ];
+        assert!(DeduplicationEngine::find_semantic_duplicates(&chunks, 0.9).is_empty());
+
+        let embeddings = HashMap::from([
+            ("chunk:py".to_string(), vec![0.9, 0.1, 0.4]),
+            ("chunk:ts".to_string(), vec![0.88, 0.12, 0.41]),
+        ]);
+        let clusters =
+            DeduplicationEngine::find_semantic_duplicates_with_embeddings(&chunks, 0.9, &embeddings);
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].chunk_ids, vec!["chunk:py", "chunk:ts"]);
+        assert!(clusters[0].confidence > 0.99 && clusters[0].confidence < 1.0);
+    }
 }
@@ -53,6 +53,12 @@ pub enum CadiError {
     #[error("Configuration error: {0}")]
     Configuration(String),
 
+    #[error("Alias not found: {0}")]
+    AliasNotFound(String),
+
+    #[error("Alias conflict: {0}")]
+    AliasConflict(String),
+
     #[error("Storage error: {0}")]
     StorageError(String),
 
@@ -62,6 +68,12 @@ pub enum CadiError {
     #[error("Atomizer error: {0}")]
     AtomizerError(String),
 
+    #[error("Failed to analyze {}: {reason}", path.display())]
+    FileAnalysis {
+        path: std::path::PathBuf,
+        reason: String,
+    },
+
     #[error("Rehydration error: {0}")]
     RehydrationError(String),
 
@@ -1,5 +1,8 @@
-use crate::graph::{GraphStore, EdgeType};
-use std::collections::HashSet;
+use crate::error::CadiResult;
+use crate::graph::{GraphNode, GraphStore, EdgeType};
+use crate::tokens::TokenEstimatorKind;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 /// Analyzes atoms to determine what dependencies should be included
 pub struct DependencyAnalyzer<'a> {
@@ -28,6 +31,18 @@ pub enum DependencyPriority {
     Low = 3,         // Optional or weak references
 }
 
+impl DependencyPriority {
+    /// Relative value of following an edge of this priority
+    pub fn weight(&self) -> f64 {
+        match self {
+            DependencyPriority::Critical => 1.0,
+            DependencyPriority::High => 0.8,
+            DependencyPriority::Medium => 0.5,
+            DependencyPriority::Low => 0.2,
+        }
+    }
+}
+
 impl<'a> DependencyAnalyzer<'a> {
     pub fn new(graph: &'a GraphStore) -> Self {
         Self { graph }
@@ -41,7 +56,7 @@ impl<'a> DependencyAnalyzer<'a> {
         let mut results = Vec::new();
 
         for atom_id in atom_ids {
-            let deps = self.graph.get_dependencies(atom_id)?;
+            let deps = self.graph.get_dependencies(atom_id, None)?;
             let token_estimate = self.graph.get_token_estimate(atom_id)?;
 
             let dependency_edges = deps
@@ -64,6 +79,10 @@ impl<'a> DependencyAnalyzer<'a> {
     }
 
     /// Find all atoms that would be included in an expansion
+    ///
+    /// Candidates reachable under the policy's edge rules are discovered
+    /// first and scored; the highest-value ones are then admitted until the
+    /// atom or token budget runs out.
     pub fn simulate_expansion(
         &self,
         atom_ids: &[String],
@@ -71,53 +90,214 @@ impl<'a> DependencyAnalyzer<'a> {
     ) -> Result<ExpansionSimulation, Box<dyn std::error::Error + Send + Sync>> {
         let mut included = HashSet::new();
         let mut total_tokens = 0;
-        let mut depth_reached = 0;
 
-        // Start with requested atoms
-        let mut frontier: Vec<(String, usize)> = atom_ids.iter()
-            .map(|id| (id.clone(), 0))
-            .collect();
+        for atom_id in atom_ids {
+            if included.insert(atom_id.clone()) {
+                total_tokens += self.atom_tokens(atom_id, &policy.token_estimator)?;
+            }
+        }
+
+        let candidates = self.discover_candidates(atom_ids, policy)?;
+
+        // Best-first admission: an atom becomes eligible once an atom that
+        // references it has been admitted
+        let mut depths: HashMap<String, usize> = atom_ids.iter().map(|id| (id.clone(), 0)).collect();
+        let mut inclusions: HashMap<String, Inclusion> = HashMap::new();
+        let mut eligible: HashMap<String, Inclusion> = HashMap::new();
+        let mut truncated = false;
 
-        for (atom_id, _depth) in &frontier {
-            included.insert(atom_id.clone());
-            total_tokens += self.graph.get_token_estimate(atom_id)?;
+        for atom_id in atom_ids {
+            self.push_eligible(atom_id, 0, policy, &candidates, &included, &mut eligible)?;
         }
 
-        // BFS expansion
-        while let Some((atom_id, depth)) = frontier.pop() {
-            if depth >= policy.max_depth {
-                depth_reached = depth_reached.max(depth);
-                continue;
-            }
+        let mut decided = HashSet::new();
+        while let Some(next) = eligible
+            .keys()
+            .filter(|id| !decided.contains(*id))
+            .max_by(|a, b| {
+                candidates[*a].score.total_cmp(&candidates[*b].score).then_with(|| b.cmp(a))
+            })
+            .cloned()
+        {
+            decided.insert(next.clone());
+            let candidate = &candidates[&next];
 
-            if included.len() >= policy.max_atoms || total_tokens >= policy.max_tokens {
-                break;
+            if included.len() >= policy.max_atoms
+                || total_tokens + candidate.token_estimate > policy.max_tokens
+            {
+                truncated = true;
+                continue;
             }
 
-            let deps = self.graph.get_dependencies(&atom_id)?;
-            for (edge_type, dep_id) in deps {
-                if policy.follow_edges.contains(&edge_type) && !included.contains(&dep_id) {
-                    let dep_tokens = self.graph.get_token_estimate(&dep_id)?;
-                    if total_tokens + dep_tokens <= policy.max_tokens {
-                        included.insert(dep_id.clone());
-                        total_tokens += dep_tokens;
-                        frontier.push((dep_id, depth + 1));
-                    }
-                }
-            }
+            let inclusion = eligible[&next].clone();
+            included.insert(next.clone());
+            total_tokens += candidate.token_estimate;
+            depths.insert(next.clone(), inclusion.depth);
+            self.push_eligible(&next, inclusion.depth, policy, &candidates, &included, &mut eligible)?;
+            inclusions.insert(next, inclusion);
         }
 
-        // Check if we were truncated
-        let truncated = included.len() >= policy.max_atoms || total_tokens >= policy.max_tokens;
+        let mut rejected: Vec<RejectedAtom> = candidates
+            .into_iter()
+            .filter(|(id, _)| !included.contains(id))
+            .map(|(atom_id, candidate)| RejectedAtom {
+                atom_id,
+                score: candidate.score,
+                token_estimate: candidate.token_estimate,
+                references: candidate.references,
+            })
+            .collect();
+        rejected.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.atom_id.cmp(&b.atom_id)));
 
         Ok(ExpansionSimulation {
             included_atoms: included.into_iter().collect(),
             total_tokens,
-            max_depth_reached: depth_reached,
+            max_depth_reached: depths.values().copied().max().unwrap_or(0),
             truncated,
+            inclusions,
+            rejected,
         })
     }
 
+    /// Walk every edge the policy allows and score the atoms found
+    fn discover_candidates(
+        &self,
+        atom_ids: &[String],
+        policy: &super::policy::ExpansionPolicy,
+    ) -> Result<HashMap<String, Candidate>, Box<dyn std::error::Error + Send + Sync>> {
+        let requested: HashSet<&String> = atom_ids.iter().collect();
+        let mut candidates: HashMap<String, Candidate> = HashMap::new();
+        let mut frontier: VecDeque<(String, usize)> = atom_ids.iter().map(|id| (id.clone(), 0)).collect();
+
+        while let Some((atom_id, depth)) = frontier.pop_front() {
+            for (edge_type, dep_id) in self.graph.get_dependencies(&atom_id, policy.min_confidence)? {
+                if requested.contains(&dep_id) || !policy.should_follow(edge_type, depth) {
+                    continue;
+                }
+
+                let weight = self.calculate_priority(&edge_type).weight();
+                match candidates.get_mut(&dep_id) {
+                    Some(candidate) => {
+                        candidate.references += 1;
+                        candidate.weight = candidate.weight.max(weight);
+                    }
+                    None => {
+                        candidates.insert(dep_id.clone(), Candidate {
+                            token_estimate: self.atom_tokens(&dep_id, &policy.token_estimator)?,
+                            references: 1,
+                            weight,
+                            depth: depth + 1,
+                            score: 0.0,
+                        });
+                        frontier.push_back((dep_id, depth + 1));
+                    }
+                }
+            }
+        }
+
+        for candidate in candidates.values_mut() {
+            candidate.score = candidate.references as f64 * candidate.weight
+                / candidate.depth as f64
+                / candidate.token_estimate.max(1) as f64;
+        }
+
+        Ok(candidates)
+    }
+
+    /// Tests covering `atom_ids`, at most `limit` of them. For each
+    /// requested atom, atoms with a `Tests` edge to it come first, then
+    /// test-looking atoms that reference a symbol it defines, each by chunk
+    /// ID.
+    pub fn covering_tests(&self, atom_ids: &[String], limit: usize) -> CadiResult<Vec<CoveringTest>> {
+        let mut tests: Vec<CoveringTest> = Vec::new();
+        let mut seen: HashSet<String> = atom_ids.iter().cloned().collect();
+
+        for atom_id in atom_ids {
+            let mut linked: Vec<String> = self
+                .graph
+                .get_dependents(atom_id)?
+                .into_iter()
+                .filter(|(edge_type, test_id)| *edge_type == EdgeType::Tests && !seen.contains(test_id))
+                .map(|(_, test_id)| test_id)
+                .collect();
+            linked.sort();
+            linked.dedup();
+
+            let mut named: Vec<(String, String)> = Vec::new();
+            if let Some(node) = self.graph.get_node(atom_id)? {
+                for symbol in &node.symbols_defined {
+                    for test_id in self.graph.find_referencers(symbol)? {
+                        if seen.contains(&test_id)
+                            || linked.contains(&test_id)
+                            || named.iter().any(|(id, _)| *id == test_id)
+                        {
+                            continue;
+                        }
+                        if self.graph.get_node(&test_id)?.is_some_and(|n| looks_like_test(&n)) {
+                            named.push((test_id, symbol.clone()));
+                        }
+                    }
+                }
+            }
+            named.sort();
+
+            let found = linked
+                .into_iter()
+                .map(|test_id| (test_id, None))
+                .chain(named.into_iter().map(|(test_id, symbol)| (test_id, Some(symbol))));
+            for (test_id, symbol) in found {
+                if tests.len() >= limit {
+                    return Ok(tests);
+                }
+                seen.insert(test_id.clone());
+                tests.push(CoveringTest { atom_id: test_id, covers: atom_id.clone(), symbol });
+            }
+        }
+
+        Ok(tests)
+    }
+
+    /// Tokens of an atom as counted by `estimator`. The byte heuristic uses
+    /// the estimate stored with the node; other estimators count the
+    /// atom's content.
+    pub fn atom_tokens(&self, atom_id: &str, estimator: &TokenEstimatorKind) -> CadiResult<usize> {
+        if *estimator == TokenEstimatorKind::Bytes {
+            return self.graph.get_token_estimate(atom_id);
+        }
+        let language = self.graph.get_node(atom_id)?.map(|n| n.language).filter(|l| !l.is_empty());
+        match self.graph.get_content_str(atom_id)? {
+            Some(content) => Ok(estimator.estimate(&content, language.as_deref())),
+            None => self.graph.get_token_estimate(atom_id),
+        }
+    }
+
+    /// Mark the candidates referenced from a newly admitted atom as eligible
+    fn push_eligible(
+        &self,
+        atom_id: &str,
+        depth: usize,
+        policy: &super::policy::ExpansionPolicy,
+        candidates: &HashMap<String, Candidate>,
+        included: &HashSet<String>,
+        eligible: &mut HashMap<String, Inclusion>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        for (edge_type, dep_id) in self.graph.get_dependencies(atom_id, policy.min_confidence)? {
+            if included.contains(&dep_id)
+                || eligible.contains_key(&dep_id)
+                || !candidates.contains_key(&dep_id)
+                || !policy.should_follow(edge_type, depth)
+            {
+                continue;
+            }
+            eligible.insert(dep_id, Inclusion {
+                parent: atom_id.to_string(),
+                edge_type,
+                depth: depth + 1,
+            });
+        }
+        Ok(())
+    }
+
     fn calculate_priority(&self, edge_type: &EdgeType) -> DependencyPriority {
         match edge_type {
             EdgeType::Imports => DependencyPriority::Critical,
@@ -131,6 +311,8 @@ impl<'a> DependencyAnalyzer<'a> {
             EdgeType::MacroUse => DependencyPriority::Medium,
             EdgeType::Tests => DependencyPriority::Low,
             EdgeType::DocRef => DependencyPriority::Low,
+            EdgeType::EquivalentTo => DependencyPriority::Low,
+            EdgeType::Refines => DependencyPriority::Low,
         }
     }
 }
@@ -141,4 +323,85 @@ pub struct ExpansionSimulation {
     pub total_tokens: usize,
     pub max_depth_reached: usize,
     pub truncated: bool,
+    /// How each ghost atom was reached
+    pub inclusions: HashMap<String, Inclusion>,
+    /// Atoms that were reachable but didn't make the budget, best first
+    pub rejected: Vec<RejectedAtom>,
+}
+
+/// A ghost atom candidate found during discovery
+#[derive(Debug)]
+struct Candidate {
+    token_estimate: usize,
+    /// Number of edges pointing at this atom from the expansion
+    references: usize,
+    /// Weight of the strongest edge type pointing at it
+    weight: f64,
+    /// Shallowest level it was found at
+    depth: usize,
+    score: f64,
+}
+
+/// A candidate ghost atom that was left out of an expansion
+#[derive(Debug, Clone, Serialize)]
+pub struct RejectedAtom {
+    pub atom_id: String,
+    /// Value per token; higher means it was a closer call
+    pub score: f64,
+    pub token_estimate: usize,
+    pub references: usize,
+}
+
+/// A test included because it covers a requested atom
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CoveringTest {
+    pub atom_id: String,
+    /// Requested atom it tests
+    pub covers: String,
+    /// Symbol of `covers` it references, when it was found by name rather
+    /// than through a `Tests` edge
+    pub symbol: Option<String>,
+}
+
+/// Does an atom look like a test, going by its names and source file?
+/// `test_parse`, `TestParse`, `parse_test`, `tests/parse.rs`,
+/// `parse.test.ts` and `parse_test.go` all do.
+pub fn looks_like_test(node: &GraphNode) -> bool {
+    let names = node
+        .primary_alias
+        .iter()
+        .chain(&node.aliases)
+        .chain(&node.symbols_defined)
+        .map(|name| name.rsplit(['/', ':', '.']).next().unwrap_or(name));
+    let file = node.source_file.iter().flat_map(|file| {
+        let name = file.rsplit(['/', '\\']).next().unwrap_or(file);
+        let stem = name.split('.').next().unwrap_or(name);
+        [stem, name]
+    });
+    let is_test_name = |name: &str| {
+        name.starts_with("test_")
+            || name.ends_with("_test")
+            || name.ends_with("_tests")
+            || name.strip_prefix("Test").is_some_and(|rest| rest.starts_with(char::is_uppercase))
+    };
+
+    names.chain(file).any(is_test_name)
+        || node.source_file.as_deref().is_some_and(|file| {
+            let file = file.replace('\\', "/");
+            file.starts_with("tests/")
+                || file.contains("/tests/")
+                || file.contains("__tests__/")
+                || file.contains(".test.")
+                || file.contains(".spec.")
+        })
+}
+
+/// The edge that pulled a ghost atom into an expansion
+#[derive(Debug, Clone)]
+pub struct Inclusion {
+    /// Atom the edge leaves from
+    pub parent: String,
+    pub edge_type: EdgeType,
+    /// Levels away from the requested atoms
+    pub depth: usize,
 }
\ No newline at end of file
@@ -7,5 +7,6 @@ pub mod resolver;
 pub mod policy;
 pub mod analyzer;
 
-pub use resolver::GhostResolver;
-pub use policy::ExpansionPolicy;
\ No newline at end of file
+pub use resolver::{GhostResolver, UnresolvedSymbol};
+pub use policy::{EdgeRule, ExpansionPolicy};
+pub use analyzer::{looks_like_test, CoveringTest};
\ No newline at end of file
@@ -1,4 +1,40 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
 use crate::graph::EdgeType;
+use crate::tokens::TokenEstimatorKind;
+
+/// Per-edge-type override for how far (and whether) to follow an edge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EdgeRule {
+    /// Follow this edge type at all
+    #[serde(default = "default_include")]
+    pub include: bool,
+    /// Deepest level an atom reached through this edge may sit at
+    /// (defaults to the policy's `max_depth`)
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+}
+
+fn default_include() -> bool { true }
+
+impl EdgeRule {
+    /// Follow the edge up to the policy's `max_depth`
+    pub fn follow() -> Self {
+        Self { include: true, max_depth: None }
+    }
+
+    /// Never follow the edge
+    pub fn never() -> Self {
+        Self { include: false, max_depth: None }
+    }
+
+    /// Follow the edge only up to `depth` levels from the requested atoms
+    pub fn up_to(depth: usize) -> Self {
+        Self { include: true, max_depth: Some(depth) }
+    }
+}
 
 /// Policy for automatic context expansion
 #[derive(Debug, Clone)]
@@ -15,6 +51,23 @@ pub struct ExpansionPolicy {
     pub always_include_types: bool,
     /// Include method signatures (not bodies) for referenced types
     pub include_signatures: bool,
+    /// Per-edge-type overrides; edge types without a rule fall back to
+    /// `follow_edges` and `max_depth`
+    pub edge_rules: HashMap<EdgeType, EdgeRule>,
+    /// Look up unresolved symbols in the federated registries. Matches are
+    /// reported as candidates, never added to the context.
+    pub search_remote: bool,
+    /// Skip edges whose confidence is below this (see
+    /// [`confidence`](crate::graph::confidence)); `None` follows every edge
+    pub min_confidence: Option<f32>,
+    /// How atoms are counted against `max_tokens`
+    pub token_estimator: TokenEstimatorKind,
+    /// Also include the tests covering the requested atoms: atoms with a
+    /// `Tests` edge to them, then test-looking atoms that reference a
+    /// symbol they define
+    pub include_tests: bool,
+    /// Most tests to include with `include_tests`
+    pub max_tests: usize,
 }
 
 impl Default for ExpansionPolicy {
@@ -29,6 +82,12 @@ impl Default for ExpansionPolicy {
             ],
             always_include_types: true,
             include_signatures: true,
+            edge_rules: HashMap::new(),
+            search_remote: false,
+            min_confidence: None,
+            token_estimator: TokenEstimatorKind::Bytes,
+            include_tests: false,
+            max_tests: 5,
         }
     }
 }
@@ -43,6 +102,13 @@ impl ExpansionPolicy {
             follow_edges: vec![EdgeType::Imports],
             always_include_types: false,
             include_signatures: false,
+            edge_rules: HashMap::new(),
+            search_remote: false,
+            // Only imports that were resolved by path, not symbol name
+            min_confidence: Some(0.5),
+            token_estimator: TokenEstimatorKind::Bytes,
+            include_tests: false,
+            max_tests: 5,
         }
     }
 
@@ -59,6 +125,91 @@ impl ExpansionPolicy {
             ],
             always_include_types: true,
             include_signatures: true,
+            edge_rules: HashMap::new(),
+            search_remote: false,
+            min_confidence: None,
+            token_estimator: TokenEstimatorKind::Bytes,
+            include_tests: false,
+            max_tests: 5,
+        }
+    }
+
+    /// Set the rule for an edge type
+    pub fn with_edge_rule(mut self, edge_type: EdgeType, rule: EdgeRule) -> Self {
+        self.edge_rules.insert(edge_type, rule);
+        self
+    }
+
+    /// Include up to `max_tests` tests covering the requested atoms
+    pub fn with_tests(mut self, max_tests: usize) -> Self {
+        self.include_tests = true;
+        self.max_tests = max_tests;
+        self
+    }
+
+    /// The rule in effect for an edge type
+    pub fn rule_for(&self, edge_type: EdgeType) -> EdgeRule {
+        self.edge_rules.get(&edge_type).copied().unwrap_or(EdgeRule {
+            include: self.follow_edges.contains(&edge_type),
+            max_depth: None,
+        })
+    }
+
+    /// Should an edge leaving an atom at `depth` be followed?
+    pub fn should_follow(&self, edge_type: EdgeType, depth: usize) -> bool {
+        let rule = self.rule_for(edge_type);
+        rule.include && depth < rule.max_depth.unwrap_or(self.max_depth)
+    }
+
+    /// Human-readable description of the rule applied to an edge type
+    pub fn describe_rule(&self, edge_type: EdgeType) -> String {
+        let rule = self.rule_for(edge_type);
+        let depth = rule.max_depth.unwrap_or(self.max_depth);
+        if self.edge_rules.contains_key(&edge_type) {
+            format!("edge rule {}: follow up to depth {}", edge_type, depth)
+        } else {
+            format!("default rule for {}: follow up to depth {}", edge_type, depth)
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_rules_match_follow_edges() {
+        let policy = ExpansionPolicy::default();
+        assert!(policy.should_follow(EdgeType::Imports, 0));
+        assert!(policy.should_follow(EdgeType::TypeRef, 1));
+        assert!(!policy.should_follow(EdgeType::TypeRef, 2));
+        assert!(!policy.should_follow(EdgeType::Calls, 0));
+    }
+
+    #[test]
+    fn test_edge_rules_override_defaults() {
+        let policy = ExpansionPolicy::aggressive()
+            .with_edge_rule(EdgeType::Calls, EdgeRule::up_to(1))
+            .with_edge_rule(EdgeType::Tests, EdgeRule::never())
+            .with_edge_rule(EdgeType::TypeRef, EdgeRule::up_to(5));
+
+        assert!(policy.should_follow(EdgeType::Calls, 0));
+        assert!(!policy.should_follow(EdgeType::Calls, 1));
+        assert!(!policy.should_follow(EdgeType::Tests, 0));
+        assert!(policy.should_follow(EdgeType::TypeRef, 4));
+        assert!(policy.describe_rule(EdgeType::Calls).starts_with("edge rule calls"));
+        assert!(policy.describe_rule(EdgeType::Imports).starts_with("default rule"));
+    }
+
+    #[test]
+    fn test_edge_rules_deserialize_by_edge_name() {
+        let rules: HashMap<EdgeType, EdgeRule> = serde_json::from_value(serde_json::json!({
+            "calls": { "max_depth": 1 },
+            "tests": { "include": false }
+        }))
+        .unwrap();
+
+        assert_eq!(rules[&EdgeType::Calls], EdgeRule::up_to(1));
+        assert_eq!(rules[&EdgeType::Tests], EdgeRule::never());
+    }
+}
@@ -1,6 +1,12 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::atomizer::AtomKind;
+use crate::error::CadiResult;
 use crate::graph::GraphStore;
 use super::policy::ExpansionPolicy;
-use super::analyzer::DependencyAnalyzer;
+use super::analyzer::{DependencyAnalyzer, Inclusion, RejectedAtom};
 
 /// Ghost Import Resolver
 ///
@@ -12,16 +18,59 @@ pub struct GhostResolver {
 
 #[derive(Debug)]
 pub struct ExpansionResult {
-    /// All atoms to include (original + ghosts)
+    /// All atoms to include (original + ghosts + tests)
     pub atoms: Vec<String>,
     /// Which atoms were "ghost" additions
     pub ghost_atoms: Vec<String>,
+    /// Which atoms are tests covering the requested atoms, added with
+    /// `include_tests`; not counted among `ghost_atoms`
+    pub test_atoms: Vec<String>,
     /// Truncated due to limits?
     pub truncated: bool,
     /// Total token estimate
     pub total_tokens: usize,
     /// Explanation of what was included and why
     pub explanation: String,
+    /// Candidates that were reachable but left out, highest score first
+    pub rejected: Vec<RejectedAtom>,
+    /// Symbols referenced by included atoms that none of them define
+    pub unresolved: Vec<UnresolvedSymbol>,
+}
+
+/// A symbol the expanded context uses but does not define
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnresolvedSymbol {
+    pub name: String,
+    /// Included atoms referencing the symbol
+    pub referenced_by: Vec<String>,
+    /// Kind suggested by the name's casing
+    pub guessed_kind: AtomKind,
+    /// Chunks in the graph, outside the expansion, that define the symbol,
+    /// most used first
+    #[serde(default)]
+    pub defined_in: Vec<String>,
+}
+
+impl UnresolvedSymbol {
+    /// Guess what a symbol is from its name: `name!` is a macro,
+    /// `SCREAMING_CASE` a constant, `PascalCase` a type and anything else a
+    /// function. Paths are judged by their last segment.
+    pub fn guess_kind(name: &str) -> AtomKind {
+        if name.ends_with('!') {
+            return AtomKind::Macro;
+        }
+        let last = name.rsplit([':', '.']).next().unwrap_or(name);
+        let mut letters = last.chars().filter(|c| c.is_alphabetic()).peekable();
+        if letters.peek().is_none() {
+            AtomKind::Function
+        } else if letters.clone().all(char::is_uppercase) && last.chars().count() > 1 {
+            AtomKind::Constant
+        } else if letters.next().is_some_and(char::is_uppercase) {
+            AtomKind::Struct
+        } else {
+            AtomKind::Function
+        }
+    }
 }
 
 impl GhostResolver {
@@ -55,22 +104,92 @@ impl GhostResolver {
             if !atom_ids.contains(atom_id) {
                 ghost_atoms.push(atom_id.clone());
 
-                // Find why this atom was included
-                if let Some(reason) = self.find_inclusion_reason(atom_id, atom_ids, policy).await? {
+                // Explain which edge rule pulled this atom in
+                if let Some(reason) = Self::inclusion_reason(atom_id, &simulation.inclusions, policy) {
                     explanations.push(reason);
                 }
             }
         }
 
+        let mut atoms = simulation.included_atoms;
+        let mut total_tokens = simulation.total_tokens;
+        let mut truncated = simulation.truncated;
+        let mut test_atoms = Vec::new();
+        if policy.include_tests {
+            // Tests go in after the dependencies, in whatever budget is left
+            for test in self.analyzer().covering_tests(atom_ids, policy.max_tests)? {
+                if atoms.contains(&test.atom_id) {
+                    continue;
+                }
+                let tokens = self.analyzer().atom_tokens(&test.atom_id, &policy.token_estimator)?;
+                if total_tokens + tokens > policy.max_tokens {
+                    truncated = true;
+                    continue;
+                }
+                total_tokens += tokens;
+                explanations.push(match &test.symbol {
+                    Some(symbol) => format!(
+                        "Added test '{}' because it looks like a test and references '{}' from '{}'",
+                        test.atom_id, symbol, test.covers
+                    ),
+                    None => format!("Added test '{}' because it tests '{}' via Tests", test.atom_id, test.covers),
+                });
+                atoms.push(test.atom_id.clone());
+                test_atoms.push(test.atom_id);
+            }
+        }
+
+        let unresolved = self.unresolved_symbols(&atoms)?;
+
         Ok(ExpansionResult {
-            atoms: simulation.included_atoms,
+            atoms,
             ghost_atoms,
-            truncated: simulation.truncated,
-            total_tokens: simulation.total_tokens,
+            test_atoms,
+            truncated,
+            total_tokens,
             explanation: explanations.join("\n"),
+            rejected: simulation.rejected,
+            unresolved,
         })
     }
 
+    /// Symbols referenced by `atom_ids` that none of them define, in the
+    /// order they are first referenced
+    fn unresolved_symbols(&self, atom_ids: &[String]) -> CadiResult<Vec<UnresolvedSymbol>> {
+        let mut nodes = Vec::new();
+        for atom_id in atom_ids {
+            if let Some(node) = self.graph.get_node(atom_id)? {
+                nodes.push(node);
+            }
+        }
+        let defined: HashSet<&str> = nodes
+            .iter()
+            .flat_map(|n| n.symbols_defined.iter().map(String::as_str))
+            .collect();
+
+        let mut unresolved: Vec<UnresolvedSymbol> = Vec::new();
+        for node in &nodes {
+            for symbol in &node.symbols_referenced {
+                if defined.contains(symbol.as_str()) {
+                    continue;
+                }
+                match unresolved.iter_mut().find(|u| &u.name == symbol) {
+                    Some(entry) if !entry.referenced_by.contains(&node.chunk_id) => {
+                        entry.referenced_by.push(node.chunk_id.clone());
+                    }
+                    Some(_) => {}
+                    None => unresolved.push(UnresolvedSymbol {
+                        name: symbol.clone(),
+                        referenced_by: vec![node.chunk_id.clone()],
+                        guessed_kind: UnresolvedSymbol::guess_kind(symbol),
+                        defined_in: self.graph.find_definers(symbol)?,
+                    }),
+                }
+            }
+        }
+        Ok(unresolved)
+    }
+
     /// Get optimal policy for a set of atoms
     pub async fn suggest_policy(&self, atom_ids: &[String]) -> Result<ExpansionPolicy, Box<dyn std::error::Error + Send + Sync>> {
         let analysis = self.analyzer().analyze_dependencies(atom_ids)?;
@@ -101,60 +220,272 @@ impl GhostResolver {
         Ok(policy)
     }
 
-    async fn find_inclusion_reason(
-        &self,
+    fn inclusion_reason(
         atom_id: &str,
-        original_atoms: &[String],
+        inclusions: &HashMap<String, Inclusion>,
         policy: &ExpansionPolicy,
-    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
-        // Find which original atom(s) led to this inclusion
-        for original_id in original_atoms {
-            let deps = self.graph.get_dependencies(original_id)?;
-            for (edge_type, dep_id) in deps {
-                if dep_id == atom_id && policy.follow_edges.contains(&edge_type) {
-                    return Ok(Some(format!(
-                        "Added '{}' because '{}' references it via {:?}",
-                        atom_id, original_id, edge_type
-                    )));
-                }
-            }
+    ) -> Option<String> {
+        let inclusion = inclusions.get(atom_id)?;
+        let rule = policy.describe_rule(inclusion.edge_type);
+
+        if inclusion.depth <= 1 {
+            return Some(format!(
+                "Added '{}' because '{}' references it via {:?} ({})",
+                atom_id, inclusion.parent, inclusion.edge_type, rule
+            ));
         }
 
-        // Check transitive dependencies
-        for original_id in original_atoms {
-            if let Some(path) = self.find_dependency_path(original_id, atom_id, policy, 3)? {
-                return Ok(Some(format!(
-                    "Added '{}' through dependency chain: {}",
-                    atom_id, path
-                )));
-            }
+        // Walk back up to the requested atom for the full chain
+        let mut chain = vec![atom_id.to_string(), inclusion.parent.clone()];
+        let mut current = inclusion.parent.as_str();
+        while let Some(parent) = inclusions.get(current) {
+            chain.push(parent.parent.clone());
+            current = parent.parent.as_str();
         }
+        chain.reverse();
 
-        Ok(None)
+        Some(format!(
+            "Added '{}' through dependency chain: {} via {:?} ({})",
+            atom_id,
+            chain.join(" -> "),
+            inclusion.edge_type,
+            rule
+        ))
     }
+}
 
-    fn find_dependency_path(
-        &self,
-        from: &str,
-        to: &str,
-        policy: &ExpansionPolicy,
-        max_depth: usize,
-    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
-        if max_depth == 0 {
-            return Ok(None);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ghost::EdgeRule;
+    use crate::graph::{confidence, Edge, EdgeType, GraphNode};
+    use crate::tokens::TokenEstimatorKind;
+
+    #[test]
+    fn test_edge_rules_drive_expansion() {
+        let graph = GraphStore::in_memory().unwrap();
+        for id in ["a", "b", "c", "d", "t"] {
+            graph.insert_node(&GraphNode::new(format!("chunk:{}", id), id)).unwrap();
         }
+        graph.add_dependency("chunk:a", "chunk:b", EdgeType::TypeRef).unwrap();
+        graph.add_dependency("chunk:a", "chunk:c", EdgeType::Calls).unwrap();
+        graph.add_dependency("chunk:c", "chunk:d", EdgeType::Calls).unwrap();
+        graph.add_dependency("chunk:a", "chunk:t", EdgeType::Tests).unwrap();
 
-        let deps = self.graph.get_dependencies(from)?;
-        for (edge_type, dep_id) in deps {
-            if dep_id == to && policy.follow_edges.contains(&edge_type) {
-                return Ok(Some(format!("{} -> {}", from, to)));
-            }
+        let policy = ExpansionPolicy::aggressive()
+            .with_edge_rule(EdgeType::Calls, EdgeRule::up_to(1))
+            .with_edge_rule(EdgeType::Tests, EdgeRule::never());
 
-            if let Some(path) = self.find_dependency_path(&dep_id, to, policy, max_depth - 1)? {
-                return Ok(Some(format!("{} -> {}", from, path)));
-            }
+        let resolver = GhostResolver::new(graph);
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let result = rt
+            .block_on(resolver.resolve_with_policy(&["chunk:a".to_string()], &policy))
+            .unwrap();
+
+        let mut ghosts = result.ghost_atoms.clone();
+        ghosts.sort();
+        assert_eq!(ghosts, vec!["chunk:b".to_string(), "chunk:c".to_string()]);
+        assert!(result.explanation.contains("via Calls (edge rule calls: follow up to depth 1)"));
+        assert!(result.explanation.contains("via TypeRef (default rule for type_ref"));
+    }
+
+    #[test]
+    fn test_covering_tests_only_when_requested() {
+        let graph = GraphStore::in_memory().unwrap();
+        graph
+            .insert_node(&GraphNode::new("chunk:parse", "parse").with_defines(vec!["parse".to_string()]))
+            .unwrap();
+        // One test linked by an edge, one found by its name and references
+        graph.insert_node(&GraphNode::new("chunk:parse_tests", "t1").with_alias("parser/parse_tests")).unwrap();
+        graph.add_dependency("chunk:parse_tests", "chunk:parse", EdgeType::Tests).unwrap();
+        graph
+            .insert_node(
+                &GraphNode::new("chunk:test_parse_empty", "t2")
+                    .with_defines(vec!["test_parse_empty".to_string()])
+                    .with_references(vec!["parse".to_string()]),
+            )
+            .unwrap();
+        graph
+            .insert_node(&GraphNode::new("chunk:caller", "c").with_references(vec!["parse".to_string()]))
+            .unwrap();
+
+        let resolver = GhostResolver::new(graph);
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let parse = ["chunk:parse".to_string()];
+
+        let result = rt.block_on(resolver.resolve_with_policy(&parse, &ExpansionPolicy::default())).unwrap();
+        assert!(result.test_atoms.is_empty());
+        assert_eq!(result.atoms, parse);
+
+        // Tests linked by an edge come before those found by name
+        let policy = ExpansionPolicy::default().with_tests(5);
+        let result = rt.block_on(resolver.resolve_with_policy(&parse, &policy)).unwrap();
+        assert_eq!(result.test_atoms, ["chunk:parse_tests", "chunk:test_parse_empty"]);
+        assert_eq!(result.atoms, ["chunk:parse", "chunk:parse_tests", "chunk:test_parse_empty"]);
+        assert!(result.ghost_atoms.is_empty());
+        assert!(result.explanation.contains("'chunk:parse_tests' because it tests 'chunk:parse' via Tests"));
+        assert!(result.explanation.contains("references 'parse' from 'chunk:parse'"));
+
+        let limited = rt.block_on(resolver.resolve_with_policy(&parse, &ExpansionPolicy::default().with_tests(1))).unwrap();
+        assert_eq!(limited.test_atoms, ["chunk:parse_tests"]);
+    }
+
+    #[test]
+    fn test_small_shared_type_beats_large_module() {
+        let graph = GraphStore::in_memory().unwrap();
+        let requested: Vec<String> = (0..5).map(|i| format!("chunk:req{}", i)).collect();
+        for id in &requested {
+            graph.insert_node(&GraphNode::new(id.clone(), id.clone()).with_size(40)).unwrap();
+            graph.add_dependency(id, "chunk:small_type", EdgeType::TypeRef).unwrap();
         }
+        graph.insert_node(&GraphNode::new("chunk:small_type", "small").with_size(40)).unwrap();
+        graph.insert_node(&GraphNode::new("chunk:utils", "utils").with_size(8000)).unwrap();
+        graph.add_dependency("chunk:req0", "chunk:utils", EdgeType::Imports).unwrap();
+
+        // Room for the requested atoms plus only one of the two candidates
+        let policy = ExpansionPolicy {
+            max_tokens: 5 * 10 + 1500,
+            ..Default::default()
+        };
+
+        let resolver = GhostResolver::new(graph);
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let result = rt.block_on(resolver.resolve_with_policy(&requested, &policy)).unwrap();
+
+        assert_eq!(result.ghost_atoms, vec!["chunk:small_type".to_string()]);
+        assert!(result.truncated);
+        assert_eq!(result.rejected.len(), 1);
+        assert_eq!(result.rejected[0].atom_id, "chunk:utils");
+        assert_eq!(result.rejected[0].token_estimate, 2000);
+        assert!(result.rejected[0].score > 0.0);
+    }
+
+    #[test]
+    fn test_higher_score_admitted_first() {
+        let graph = GraphStore::in_memory().unwrap();
+        graph.insert_node(&GraphNode::new("chunk:root", "root").with_size(40)).unwrap();
+        graph.insert_node(&GraphNode::new("chunk:big", "big").with_size(800)).unwrap();
+        graph.insert_node(&GraphNode::new("chunk:tiny", "tiny").with_size(80)).unwrap();
+        graph.add_dependency("chunk:root", "chunk:big", EdgeType::Imports).unwrap();
+        graph.add_dependency("chunk:root", "chunk:tiny", EdgeType::TypeRef).unwrap();
+
+        // Either fits alone, but not both
+        let policy = ExpansionPolicy {
+            max_tokens: 10 + 200,
+            ..Default::default()
+        };
+
+        let resolver = GhostResolver::new(graph);
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let result = rt
+            .block_on(resolver.resolve_with_policy(&["chunk:root".to_string()], &policy))
+            .unwrap();
 
-        Ok(None)
+        assert_eq!(result.ghost_atoms, vec!["chunk:tiny".to_string()]);
+        assert_eq!(result.rejected[0].atom_id, "chunk:big");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_token_estimator_applies_to_budget() {
+        let graph = GraphStore::in_memory().unwrap();
+        let messages = format!("const MESSAGES: &str = \"{}\";", "设置已保存".repeat(40));
+        graph.insert_node(&GraphNode::new("chunk:root", "root").with_size(40)).unwrap();
+        graph.insert_node(&GraphNode::new("chunk:messages", "messages").with_language("rust").with_size(messages.len())).unwrap();
+        graph.store_content("chunk:messages", messages.as_bytes()).unwrap();
+        graph.add_dependency("chunk:root", "chunk:messages", EdgeType::Imports).unwrap();
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let resolver = GhostResolver::new(graph);
+        let expand = |policy: ExpansionPolicy| {
+            rt.block_on(resolver.resolve_with_policy(&["chunk:root".to_string()], &policy)).unwrap()
+        };
+
+        // 600 bytes of CJK fit 180 tokens by the byte heuristic, but are
+        // 200 characters, about a token each
+        let bytes = expand(ExpansionPolicy { max_tokens: 180, ..Default::default() });
+        assert_eq!(bytes.ghost_atoms, vec!["chunk:messages".to_string()]);
+
+        let calibrated = expand(ExpansionPolicy {
+            max_tokens: 180,
+            token_estimator: TokenEstimatorKind::Calibrated,
+            ..Default::default()
+        });
+        assert!(calibrated.ghost_atoms.is_empty());
+        assert!(calibrated.rejected[0].token_estimate > 200);
+    }
+
+    #[test]
+    fn test_conservative_policy_skips_low_confidence_edges() {
+        let graph = GraphStore::in_memory().unwrap();
+        for id in ["chunk:root", "chunk:imported", "chunk:same_name"] {
+            graph.insert_node(&GraphNode::new(id, id).with_size(40)).unwrap();
+        }
+        graph
+            .add_edge(&Edge::new("chunk:root", "chunk:imported", EdgeType::Imports).with_confidence(confidence::TREE_SITTER))
+            .unwrap();
+        graph
+            .add_edge(
+                &Edge::new("chunk:root", "chunk:same_name", EdgeType::Imports)
+                    .with_confidence(confidence::SYMBOL_NAME * confidence::TREE_SITTER),
+            )
+            .unwrap();
+
+        let resolver = GhostResolver::new(graph);
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let root = ["chunk:root".to_string()];
+
+        let mut result = rt.block_on(resolver.resolve_with_policy(&root, &ExpansionPolicy::default())).unwrap();
+        result.ghost_atoms.sort();
+        assert_eq!(result.ghost_atoms, ["chunk:imported", "chunk:same_name"]);
+
+        let result = rt.block_on(resolver.resolve_with_policy(&root, &ExpansionPolicy::conservative())).unwrap();
+        assert_eq!(result.ghost_atoms, ["chunk:imported"]);
+    }
+
+    #[test]
+    fn test_unresolved_references_are_reported() {
+        let graph = GraphStore::in_memory().unwrap();
+        graph
+            .insert_node(
+                &GraphNode::new("chunk:handler", "handler")
+                    .with_defines(vec!["handle".to_string()])
+                    .with_references(vec!["Request".to_string(), "parse".to_string(), "MAX_BODY".to_string()]),
+            )
+            .unwrap();
+        graph
+            .insert_node(
+                &GraphNode::new("chunk:parser", "parser")
+                    .with_defines(vec!["parse".to_string()])
+                    .with_references(vec!["Request".to_string(), "log::warn!".to_string()]),
+            )
+            .unwrap();
+        graph.add_dependency("chunk:handler", "chunk:parser", EdgeType::Imports).unwrap();
+        graph
+            .insert_node(&GraphNode::new("chunk:limits", "limits").with_defines(vec!["MAX_BODY".to_string()]))
+            .unwrap();
+
+        let resolver = GhostResolver::new(graph);
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let result = rt.block_on(resolver.resolve(&["chunk:handler".to_string()])).unwrap();
+
+        let unresolved: Vec<(&str, AtomKind, usize)> = result
+            .unresolved
+            .iter()
+            .map(|u| (u.name.as_str(), u.guessed_kind, u.referenced_by.len()))
+            .collect();
+        assert_eq!(
+            unresolved,
+            [
+                ("Request", AtomKind::Struct, 2),
+                ("MAX_BODY", AtomKind::Constant, 1),
+                ("log::warn!", AtomKind::Macro, 1),
+            ]
+        );
+        assert_eq!(result.unresolved[0].referenced_by, ["chunk:handler", "chunk:parser"]);
+        assert!(result.unresolved[0].defined_in.is_empty());
+        // Defined in the graph, but not reachable through the edges
+        let max_body = result.unresolved.iter().find(|u| u.name == "MAX_BODY").unwrap();
+        assert_eq!(max_body.defined_in, ["chunk:limits"]);
+        assert_eq!(UnresolvedSymbol::guess_kind("std::fs::read_to_string"), AtomKind::Function);
+    }
+}
@@ -0,0 +1,267 @@
+//! Gitignore-style path matching
+//!
+//! A small matcher for `.gitignore` patterns, used when collecting project
+//! files. Supports comments, negation (`!`), directory-only patterns
+//! (trailing `/`), anchored patterns (containing `/`), `*`, `?`, `[...]`
+//! classes and `**`. As in git, the last matching pattern wins and patterns
+//! from nested `.gitignore` files take precedence over their parents.
+
+use std::path::{Path, PathBuf};
+
+/// A single parsed ignore pattern
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    /// Directory the pattern is relative to (relative to the project root)
+    base: PathBuf,
+    pattern: Vec<char>,
+    negated: bool,
+    dir_only: bool,
+    anchored: bool,
+}
+
+/// An ordered set of gitignore rules
+#[derive(Debug, Clone, Default)]
+pub struct GitIgnore {
+    rules: Vec<IgnoreRule>,
+}
+
+impl GitIgnore {
+    /// Create an empty matcher
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add patterns relative to `base` (a directory relative to the project
+    /// root). Later patterns take precedence over earlier ones.
+    pub fn add_patterns<'a>(&mut self, base: &Path, lines: impl IntoIterator<Item = &'a str>) {
+        for line in lines {
+            if let Some(rule) = parse_rule(base, line) {
+                self.rules.push(rule);
+            }
+        }
+    }
+
+    /// Add the patterns of the `.gitignore` in `dir`, if there is one.
+    /// `base` is `dir` relative to the project root.
+    pub fn add_file(&mut self, dir: &Path, base: &Path) {
+        if let Ok(content) = std::fs::read_to_string(dir.join(".gitignore")) {
+            self.add_patterns(base, content.lines());
+        }
+    }
+
+    /// Whether any rules have been loaded
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Check whether a path (relative to the project root) is ignored
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let mut ignored = false;
+
+        for rule in &self.rules {
+            if rule.dir_only && !is_dir {
+                continue;
+            }
+            let Ok(relative) = path.strip_prefix(&rule.base) else {
+                continue;
+            };
+            let relative = relative.to_string_lossy().replace('\\', "/");
+
+            let matched = if rule.anchored {
+                glob_match(&rule.pattern, &relative.chars().collect::<Vec<_>>())
+            } else {
+                let name = relative.rsplit('/').next().unwrap_or(&relative);
+                glob_match(&rule.pattern, &name.chars().collect::<Vec<_>>())
+            };
+
+            if matched {
+                ignored = !rule.negated;
+            }
+        }
+
+        ignored
+    }
+}
+
+fn parse_rule(base: &Path, line: &str) -> Option<IgnoreRule> {
+    // Trailing spaces are ignored unless escaped
+    let mut line = line.trim_end_matches(['\r', '\n']);
+    while line.ends_with(' ') && !line.ends_with("\\ ") {
+        line = &line[..line.len() - 1];
+    }
+
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let (negated, mut pattern) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, line.strip_prefix('\\').filter(|r| r.starts_with(['#', '!'])).unwrap_or(line)),
+    };
+
+    let dir_only = pattern.ends_with('/');
+    if dir_only {
+        pattern = pattern.trim_end_matches('/');
+    }
+
+    // A slash anywhere but the end anchors the pattern to its base
+    let anchored = pattern.contains('/');
+    let pattern = pattern.trim_start_matches('/');
+
+    if pattern.is_empty() {
+        return None;
+    }
+
+    Some(IgnoreRule {
+        base: base.to_path_buf(),
+        pattern: pattern.chars().collect(),
+        negated,
+        dir_only,
+        anchored,
+    })
+}
+
+/// Match a glob against a `/`-separated path
+pub(crate) fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            let rest = &pattern[2..];
+            if let Some(after) = rest.strip_prefix(&['/']) {
+                // `**/` matches zero or more whole directories
+                glob_match(after, text)
+                    || (0..text.len()).any(|i| text[i] == '/' && glob_match(after, &text[i + 1..]))
+            } else {
+                (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+            }
+        }
+        Some('*') => {
+            let rest = &pattern[1..];
+            for i in 0..=text.len() {
+                if glob_match(rest, &text[i..]) {
+                    return true;
+                }
+                if i < text.len() && text[i] == '/' {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => {
+            matches!(text.first(), Some(c) if *c != '/') && glob_match(&pattern[1..], &text[1..])
+        }
+        Some('[') => match (text.first(), match_class(&pattern[1..], text.first().copied())) {
+            (Some(_), Some((true, len))) => glob_match(&pattern[1 + len..], &text[1..]),
+            (_, Some((false, _))) => false,
+            // Unterminated class: treat `[` literally
+            (first, None) => first == Some(&'[') && glob_match(&pattern[1..], &text[1..]),
+            (None, _) => false,
+        },
+        Some('\\') if pattern.len() > 1 => {
+            text.first() == Some(&pattern[1]) && glob_match(&pattern[2..], &text[1..])
+        }
+        Some(c) => text.first() == Some(c) && glob_match(&pattern[1..], &text[1..]),
+    }
+}
+
+/// Whether a glob has a `[` class without a closing `]`
+pub(crate) fn unterminated_class(pattern: &[char]) -> bool {
+    let mut i = 0;
+    while i < pattern.len() {
+        match pattern[i] {
+            '\\' => i += 2,
+            '[' => match match_class(&pattern[i + 1..], None) {
+                Some((_, len)) => i += 1 + len,
+                None => return true,
+            },
+            _ => i += 1,
+        }
+    }
+    false
+}
+
+/// Match a character class (the part after `[`). Returns whether `c`
+/// matched and how many pattern characters the class consumed, or `None`
+/// if the class is unterminated.
+fn match_class(class: &[char], c: Option<char>) -> Option<(bool, usize)> {
+    let mut i = 0;
+    let negated = matches!(class.first(), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+    while i < class.len() {
+        if class[i] == ']' && !first {
+            let matched = c.is_some_and(|c| c != '/') && (matched != negated);
+            return Some((matched, i + 1));
+        }
+        first = false;
+
+        let lo = class[i];
+        if class.get(i + 1) == Some(&'-') && class.get(i + 2).is_some_and(|&hi| hi != ']') {
+            let hi = class[i + 2];
+            if c.is_some_and(|c| lo <= c && c <= hi) {
+                matched = true;
+            }
+            i += 3;
+        } else {
+            if c == Some(lo) {
+                matched = true;
+            }
+            i += 1;
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(patterns: &str) -> GitIgnore {
+        let mut ignore = GitIgnore::new();
+        ignore.add_patterns(Path::new(""), patterns.lines());
+        ignore
+    }
+
+    #[test]
+    fn test_basename_and_anchored_patterns() {
+        let ignore = matcher("*.pb.rs\n/build.rs\ndocs/*.md\n");
+        assert!(ignore.is_ignored(Path::new("src/api.pb.rs"), false));
+        assert!(ignore.is_ignored(Path::new("build.rs"), false));
+        assert!(!ignore.is_ignored(Path::new("src/build.rs"), false));
+        assert!(ignore.is_ignored(Path::new("docs/intro.md"), false));
+        assert!(!ignore.is_ignored(Path::new("docs/guide/intro.md"), false));
+    }
+
+    #[test]
+    fn test_directory_patterns_and_negation() {
+        let ignore = matcher("# comment\nvendor/\n*.rs\n!keep.rs\n");
+        assert!(ignore.is_ignored(Path::new("vendor"), true));
+        assert!(!ignore.is_ignored(Path::new("vendor"), false));
+        assert!(ignore.is_ignored(Path::new("src/main.rs"), false));
+        assert!(!ignore.is_ignored(Path::new("src/keep.rs"), false));
+    }
+
+    #[test]
+    fn test_double_star_and_classes() {
+        let ignore = matcher("**/gen/**\nlog[0-9].txt\n");
+        assert!(ignore.is_ignored(Path::new("gen/a.rs"), false));
+        assert!(ignore.is_ignored(Path::new("src/gen/deep/a.rs"), false));
+        assert!(ignore.is_ignored(Path::new("log3.txt"), false));
+        assert!(!ignore.is_ignored(Path::new("logx.txt"), false));
+    }
+
+    #[test]
+    fn test_nested_rules_are_scoped_to_their_directory() {
+        let mut ignore = matcher("*.tmp\n");
+        ignore.add_patterns(Path::new("src"), ["scratch.rs", "!keep.tmp"]);
+        assert!(ignore.is_ignored(Path::new("src/scratch.rs"), false));
+        assert!(!ignore.is_ignored(Path::new("lib/scratch.rs"), false));
+        assert!(!ignore.is_ignored(Path::new("src/keep.tmp"), false));
+        assert!(ignore.is_ignored(Path::new("lib/keep.tmp"), false));
+    }
+}
@@ -0,0 +1,124 @@
+//! Compressed, deduplicated content blobs
+//!
+//! The graph store keeps chunk content in blobs keyed by the SHA-256 of the
+//! uncompressed bytes, so chunks with identical content share one blob. A
+//! chunk's content entry holds a [`BLOB_REF_MAGIC`] reference to its blob.
+//! Entries written before blobs existed hold the raw bytes and are read as
+//! they are.
+//!
+//! A blob starts with [`BLOB_MAGIC`] and a codec byte, followed by the
+//! payload: the bytes as they are, or a zstd frame when that is smaller.
+
+use sha2::{Digest, Sha256};
+
+use crate::error::{CadiError, CadiResult};
+
+/// Prefix of an encoded blob
+pub const BLOB_MAGIC: &[u8; 3] = b"\0CB";
+
+/// Prefix of a content entry that points at a blob
+pub const BLOB_REF_MAGIC: &[u8; 3] = b"\0CR";
+
+const CODEC_STORED: u8 = 0;
+const CODEC_ZSTD: u8 = 1;
+
+/// Content shorter than this is not worth compressing
+const MIN_COMPRESS_LEN: usize = 64;
+
+/// Largest content a compressed blob may decode to, the registry's default
+/// chunk size limit
+pub const MAX_BLOB_SIZE: usize = 100 * 1024 * 1024;
+
+/// SHA-256 of uncompressed content, the key of its blob
+pub type BlobKey = [u8; 32];
+
+pub fn blob_key(content: &[u8]) -> BlobKey {
+    Sha256::digest(content).into()
+}
+
+/// Encode content as a blob, compressing it when that saves space
+pub fn encode_blob(content: &[u8]) -> CadiResult<Vec<u8>> {
+    if content.len() >= MIN_COMPRESS_LEN {
+        let compressed = zstd_compress(content)?;
+        if compressed.len() < content.len() {
+            return Ok(header(CODEC_ZSTD).into_iter().chain(compressed).collect());
+        }
+    }
+    Ok(header(CODEC_STORED).into_iter().chain(content.iter().copied()).collect())
+}
+
+/// Decode a blob written by [`encode_blob`]
+pub fn decode_blob(blob: &[u8]) -> CadiResult<Vec<u8>> {
+    match blob.strip_prefix(BLOB_MAGIC.as_slice()) {
+        Some([CODEC_STORED, payload @ ..]) => Ok(payload.to_vec()),
+        Some([CODEC_ZSTD, payload @ ..]) => zstd_decompress(payload),
+        Some([codec, ..]) => Err(CadiError::StorageError(format!("Unknown blob codec {}", codec))),
+        _ => Err(CadiError::StorageError("Malformed content blob".to_string())),
+    }
+}
+
+/// A content entry pointing at the blob with the given key
+pub fn blob_ref(key: &BlobKey) -> Vec<u8> {
+    BLOB_REF_MAGIC.iter().chain(key).copied().collect()
+}
+
+/// The blob key of a content entry, or `None` for a legacy raw entry
+pub fn parse_blob_ref(entry: &[u8]) -> Option<BlobKey> {
+    entry.strip_prefix(BLOB_REF_MAGIC.as_slice())?.try_into().ok()
+}
+
+fn header(codec: u8) -> Vec<u8> {
+    let mut header = BLOB_MAGIC.to_vec();
+    header.push(codec);
+    header
+}
+
+fn zstd_compress(src: &[u8]) -> CadiResult<Vec<u8>> {
+    zstd::bulk::compress(src, zstd::DEFAULT_COMPRESSION_LEVEL).map_err(zstd_error)
+}
+
+fn zstd_decompress(src: &[u8]) -> CadiResult<Vec<u8>> {
+    // The frame records the content size, but a corrupt or hostile frame
+    // can claim any size, so at most MAX_BLOB_SIZE is reserved
+    let size = zstd::zstd_safe::get_frame_content_size(src)
+        .ok()
+        .flatten()
+        .ok_or_else(|| CadiError::StorageError("Corrupt compressed blob".to_string()))?;
+    let capacity = usize::try_from(size).unwrap_or(usize::MAX).min(MAX_BLOB_SIZE);
+    zstd::bulk::decompress(src, capacity).map_err(zstd_error)
+}
+
+fn zstd_error(e: std::io::Error) -> CadiError {
+    CadiError::StorageError(format!("zstd: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blob_round_trip() {
+        let text = "// Licensed under the Apache License, Version 2.0\n".repeat(200);
+        let blob = encode_blob(text.as_bytes()).unwrap();
+        assert_eq!(blob[3], CODEC_ZSTD);
+        assert!(blob.len() < text.len() / 10);
+        assert_eq!(decode_blob(&blob).unwrap(), text.as_bytes());
+
+        let short = encode_blob(b"fn a() {}").unwrap();
+        assert_eq!(short[3], CODEC_STORED);
+        assert_eq!(decode_blob(&short).unwrap(), b"fn a() {}");
+        assert_eq!(decode_blob(&encode_blob(b"").unwrap()).unwrap(), b"");
+
+        // A frame claiming 1 TiB of content for a 1-byte raw block is
+        // rejected without reserving the claimed size
+        let mut corrupt = header(CODEC_ZSTD);
+        corrupt.extend([0x28, 0xb5, 0x2f, 0xfd, 0xe0]);
+        corrupt.extend((1u64 << 40).to_le_bytes());
+        corrupt.extend([0x09, 0x00, 0x00, b'x']);
+        assert!(decode_blob(&corrupt).is_err());
+
+        let key = blob_key(text.as_bytes());
+        assert_eq!(parse_blob_ref(&blob_ref(&key)), Some(key));
+        assert_eq!(parse_blob_ref(b"fn legacy() {}"), None);
+    }
+}
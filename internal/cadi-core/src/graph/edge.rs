@@ -47,6 +47,14 @@ pub enum EdgeType {
 
     /// Documentation reference
     DocRef,
+
+    /// Semantically equivalent implementation, possibly in another language
+    /// (written in both directions by duplicate detection)
+    EquivalentTo,
+
+    /// Newer version of a chunk (new -> old), recorded by
+    /// `GraphStore::mark_refinement`
+    Refines,
 }
 
 impl EdgeType {
@@ -74,6 +82,19 @@ impl EdgeType {
         )
     }
 
+    /// Does the source atom use the target, so that a view should lay the
+    /// target out first?
+    pub fn orders_layout(&self) -> bool {
+        !matches!(
+            self,
+            EdgeType::ComposedOf
+                | EdgeType::Exports
+                | EdgeType::DocRef
+                | EdgeType::EquivalentTo
+                | EdgeType::Refines
+        )
+    }
+
     /// Priority for context assembly (lower = included first)
     pub fn assembly_priority(&self) -> u8 {
         match self {
@@ -88,6 +109,8 @@ impl EdgeType {
             EdgeType::Exports => 10,
             EdgeType::Tests => 20,
             EdgeType::DocRef => 20,
+            EdgeType::EquivalentTo => 30,
+            EdgeType::Refines => 30,
         }
     }
 }
@@ -106,11 +129,36 @@ impl std::fmt::Display for EdgeType {
             EdgeType::MacroUse => "macro_use",
             EdgeType::Tests => "tests",
             EdgeType::DocRef => "doc_ref",
+            EdgeType::EquivalentTo => "equivalent_to",
+            EdgeType::Refines => "refines",
         };
         write!(f, "{}", s)
     }
 }
 
+impl std::str::FromStr for EdgeType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "imports" => Ok(EdgeType::Imports),
+            "type_ref" => Ok(EdgeType::TypeRef),
+            "calls" => Ok(EdgeType::Calls),
+            "composed_of" => Ok(EdgeType::ComposedOf),
+            "implements" => Ok(EdgeType::Implements),
+            "extends" => Ok(EdgeType::Extends),
+            "exports" => Ok(EdgeType::Exports),
+            "generic_ref" => Ok(EdgeType::GenericRef),
+            "macro_use" => Ok(EdgeType::MacroUse),
+            "tests" => Ok(EdgeType::Tests),
+            "doc_ref" => Ok(EdgeType::DocRef),
+            "equivalent_to" => Ok(EdgeType::EquivalentTo),
+            "refines" => Ok(EdgeType::Refines),
+            other => Err(format!("unknown edge type: {}", other)),
+        }
+    }
+}
+
 /// A full edge representation with metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Edge {
@@ -134,16 +182,79 @@ pub struct Edge {
 }
 
 /// Additional edge metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EdgeMetadata {
     /// Line number in source where this relationship exists
+    #[serde(default)]
     pub source_line: Option<usize>,
 
     /// Whether this is a re-export
+    #[serde(default)]
     pub is_reexport: bool,
 
     /// Conditional (e.g., cfg(feature = "x"))
+    #[serde(default)]
     pub condition: Option<String>,
+
+    /// How certain the relationship is (0.0 - 1.0); see [`confidence`]
+    #[serde(default = "default_confidence")]
+    pub confidence: f32,
+}
+
+fn default_confidence() -> f32 {
+    confidence::DECLARED
+}
+
+impl Default for EdgeMetadata {
+    fn default() -> Self {
+        Self {
+            source_line: None,
+            is_reexport: false,
+            condition: None,
+            confidence: default_confidence(),
+        }
+    }
+}
+
+impl EdgeMetadata {
+    /// Metadata recording only a confidence
+    pub fn with_confidence(confidence: f32) -> Self {
+        Self {
+            confidence: confidence.clamp(0.0, 1.0),
+            ..Default::default()
+        }
+    }
+}
+
+/// Confidence of edges, by how the relationship was found
+///
+/// The confidence of an extracted edge is the product of how its target
+/// was resolved and how reliable the extractor is for its language.
+pub mod confidence {
+    /// Declared explicitly, or stored without metadata
+    pub const DECLARED: f32 = 1.0;
+
+    /// Resolved from an exact import path or alias
+    pub const RESOLVED_IMPORT: f32 = 1.0;
+
+    /// Resolved only because another chunk defines a symbol of the same
+    /// name, which several chunks may share
+    pub const SYMBOL_NAME: f32 = 0.4;
+
+    /// Found by a tree-sitter based extractor
+    pub const TREE_SITTER: f32 = 0.9;
+
+    /// Found by a regex fallback extractor
+    pub const REGEX: f32 = 0.6;
+
+    /// Reliability of the extractor for a language
+    pub fn extraction(language: &str) -> f32 {
+        if crate::atomizer::extractor::has_tree_sitter_support(language) {
+            TREE_SITTER
+        } else {
+            REGEX
+        }
+    }
 }
 
 impl Edge {
@@ -181,6 +292,17 @@ impl Edge {
         self
     }
 
+    /// Set the confidence, keeping any other metadata
+    pub fn with_confidence(mut self, confidence: f32) -> Self {
+        self.metadata.get_or_insert_with(EdgeMetadata::default).confidence = confidence.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Confidence of the edge; edges without metadata are fully trusted
+    pub fn confidence(&self) -> f32 {
+        self.metadata.as_ref().map_or(confidence::DECLARED, |m| m.confidence)
+    }
+
     /// Serialize for storage
     pub fn to_bytes(&self) -> Result<Vec<u8>, serde_json::Error> {
         serde_json::to_vec(self)
@@ -256,4 +378,16 @@ mod tests {
         assert_eq!(set.strong_edges().count(), 2); // Imports + TypeRef
         assert_eq!(set.weak_edges().count(), 1); // Calls
     }
+
+    #[test]
+    fn test_edge_confidence() {
+        let edge = Edge::new("a", "b", EdgeType::Imports);
+        assert_eq!(edge.confidence(), 1.0);
+        assert_eq!(edge.with_confidence(1.5).confidence(), 1.0);
+
+        // Metadata stored before confidence existed is fully trusted
+        let metadata: EdgeMetadata = serde_json::from_str(r#"{"source_line":3,"is_reexport":false}"#).unwrap();
+        assert_eq!(metadata.confidence, confidence::DECLARED);
+        assert_eq!(metadata.source_line, Some(3));
+    }
 }
@@ -4,8 +4,10 @@
 //! dependencies (requires) are correctly resolved to edges.
 
 use crate::atomic::{AtomicChunk, AliasRegistry};
-use crate::graph::{GraphStore, GraphNode, EdgeType};
+use crate::atomizer::{AtomExtractor, AtomizerConfig};
+use crate::graph::{confidence, Edge, GraphStore, GraphNode, EdgeType};
 use crate::error::CadiResult;
+use std::collections::HashSet;
 
 pub struct BatchImporter<'a> {
     store: &'a GraphStore,
@@ -17,8 +19,13 @@ impl<'a> BatchImporter<'a> {
     }
 
     /// Import atomic chunks and create resolved edges
+    ///
+    /// Requirements resolved through the alias registry are trusted more
+    /// than those matched to a chunk defining a symbol of the same name;
+    /// both are scaled by the reliability of the chunk's extractor.
     pub fn import(&self, chunks: Vec<AtomicChunk>, registry: &AliasRegistry) -> CadiResult<()> {
         // 1. First pass: Insert all nodes
+        let mut nodes = Vec::with_capacity(chunks.len());
         for chunk in &chunks {
             let mut node = GraphNode::new(&chunk.chunk_id, &chunk.content_hash)
                 .with_language(&chunk.language)
@@ -32,18 +39,30 @@ impl<'a> BatchImporter<'a> {
                 node = node.with_alias(alias.full_path());
             }
 
-            self.store.insert_node(&node)?;
+            if let Some(source) = chunk.sources.first() {
+                if let Some(start) = source.start_line {
+                    node = node.with_source(&source.file, start, source.end_line.unwrap_or(start));
+                }
+            }
+
+            nodes.push(node);
         }
+        self.store.insert_nodes_batch(&nodes)?.into_result()?;
 
         // 2. Second pass: Create strong edges from 'requires'
+        let mut edges = Vec::new();
         for chunk in &chunks {
             let source_id = &chunk.chunk_id;
+            let extraction = confidence::extraction(&chunk.language);
             
             for required_name in &chunk.requires {
                 // Try to resolve the requirement to a chunk ID
                 // 1. Check if it's an alias in registry
                 if let Some(target_id) = registry.resolve(required_name) {
-                    self.store.add_dependency(source_id, target_id, EdgeType::Imports)?;
+                    edges.push(
+                        Edge::new(source_id, target_id, EdgeType::Imports)
+                            .with_confidence(confidence::RESOLVED_IMPORT * extraction),
+                    );
                     continue;
                 }
 
@@ -52,14 +71,61 @@ impl<'a> BatchImporter<'a> {
                 if let Ok(Some(target_id)) = self.store.find_symbol(required_name) {
                      // Avoid self-dependency
                      if &target_id != source_id {
-                        self.store.add_dependency(source_id, &target_id, EdgeType::Imports)?;
+                        edges.push(
+                            Edge::new(source_id, target_id, EdgeType::Imports)
+                                .with_confidence(confidence::SYMBOL_NAME * extraction),
+                        );
                      }
                 }
             }
         }
+        self.store.add_edges_batch(&edges)?.into_result()?;
 
         Ok(())
     }
+
+    /// Atomize a source snippet and import its atoms
+    ///
+    /// Besides the references the extractor finds, an atom requires every
+    /// symbol it mentions that another atom in the snippet defines. Chunk
+    /// IDs are content hashes, so importing the same source again returns
+    /// the same chunks without duplicating nodes or edges.
+    pub fn import_source(
+        &self,
+        source: &str,
+        language: &str,
+        namespace: Option<&str>,
+    ) -> CadiResult<Vec<AtomicChunk>> {
+        let atoms = AtomExtractor::new(language, AtomizerConfig::default()).extract(source)?;
+
+        let mut seen = HashSet::new();
+        let mut chunks = Vec::new();
+        for atom in &atoms {
+            let mut chunk = atom.to_atomic_chunk(language, namespace);
+            if !seen.insert(chunk.chunk_id.clone()) {
+                continue;
+            }
+
+            let identifiers: HashSet<&str> = atom
+                .source
+                .split(|c: char| !c.is_alphanumeric() && c != '_')
+                .collect();
+            for other in &atoms {
+                for symbol in &other.defines {
+                    if identifiers.contains(symbol.as_str())
+                        && !atom.defines.contains(symbol)
+                        && !chunk.requires.contains(symbol)
+                    {
+                        chunk.requires.push(symbol.clone());
+                    }
+                }
+            }
+            chunks.push(chunk);
+        }
+
+        self.import(chunks.clone(), &AliasRegistry::new())?;
+        Ok(chunks)
+    }
 }
 
 #[cfg(test)]
@@ -105,9 +171,51 @@ mod tests {
         importer.import(vec![chunk_a.clone(), chunk_b.clone()], &registry).unwrap();
 
         // Verify Edge
-        let deps = store.get_dependencies("chunk:a").unwrap();
+        let deps = store.get_dependencies("chunk:a", None).unwrap();
         assert_eq!(deps.len(), 1);
         assert_eq!(deps[0].1, "chunk:b");
         assert_eq!(deps[0].0, EdgeType::Imports);
+        let resolved = store.edge_confidence("chunk:a", "chunk:b", EdgeType::Imports).unwrap();
+        assert_eq!(resolved, confidence::RESOLVED_IMPORT * confidence::extraction("rust"));
+
+        // Without the alias, only the symbol name links the chunks
+        let store = GraphStore::in_memory().unwrap();
+        BatchImporter::new(&store).import(vec![chunk_a, chunk_b], &AliasRegistry::new()).unwrap();
+        let matched = store.edge_confidence("chunk:a", "chunk:b", EdgeType::Imports).unwrap();
+        assert!(matched < resolved);
+    }
+
+    #[test]
+    fn test_reimporting_atomized_source_is_idempotent() {
+        let source = r#"
+pub fn parse_header(line: &str) -> String {
+    normalize_line(line)
+}
+
+fn normalize_line(line: &str) -> String {
+    line.trim().to_lowercase()
+}
+"#;
+        let store = GraphStore::in_memory().unwrap();
+        let importer = BatchImporter::new(&store);
+
+        let first = importer.import_source(source, "rust", Some("snippets")).unwrap();
+        let nodes = store.list_nodes().unwrap().len();
+        let edges = store.list_edges().unwrap().len();
+        assert_eq!(nodes, first.len());
+
+        let second = importer.import_source(source, "rust", Some("snippets")).unwrap();
+        let ids = |chunks: &[AtomicChunk]| chunks.iter().map(|c| c.chunk_id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&first), ids(&second));
+        assert_eq!(store.list_nodes().unwrap().len(), nodes);
+        assert_eq!(store.list_edges().unwrap().len(), edges);
+
+        let parse = first.iter().find(|c| c.name == "parse_header").unwrap();
+        assert_eq!(parse.display_name(), "snippets/parse-header");
+        assert!(parse.chunk_id.starts_with("chunk:sha256:"));
+        let normalize = first.iter().find(|c| c.name == "normalize_line").unwrap();
+        let deps = store.get_dependencies(&parse.chunk_id, None).unwrap();
+        assert_eq!(deps, vec![(EdgeType::Imports, normalize.chunk_id.clone())]);
+        assert!(store.get_dependencies(&normalize.chunk_id, None).unwrap().is_empty());
     }
 }
@@ -0,0 +1,158 @@
+//! Chunk ID migration
+//!
+//! Re-addresses the chunks in a store under another hash algorithm. Nodes,
+//! edges, content and refinement records move to the new IDs; aliases and
+//! symbols keep their names and resolve to the migrated chunks.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+
+use super::snapshot::SnapshotRecord;
+use super::{GraphStore, ImportMode};
+use crate::error::CadiResult;
+use crate::hash::{migrate_chunk_id, ChunkId, HashAlgorithm};
+
+/// Outcome of [`GraphStore::migrate_chunk_ids`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct MigrationReport {
+    /// Old chunk ID -> new chunk ID
+    pub migrated: BTreeMap<String, String>,
+    /// Chunks left under their old ID, with the reason
+    pub skipped: Vec<(String, String)>,
+}
+
+impl GraphStore {
+    /// Move every chunk to an ID computed with `algorithm`.
+    ///
+    /// A chunk is migrated only when its stored content matches its current
+    /// ID; chunks without content or with IDs that do not verify keep their
+    /// old ID and are listed in the report. Edges to and from them are
+    /// still rewritten. If writing the migrated graph fails, the previous
+    /// graph is restored.
+    pub fn migrate_chunk_ids(&self, algorithm: HashAlgorithm) -> CadiResult<MigrationReport> {
+        let mut report = MigrationReport::default();
+        for entry in self.iter_content() {
+            let (chunk_id, content) = entry?;
+            if ChunkId::parse(&chunk_id).is_ok_and(|id| id.algorithm == algorithm) {
+                continue;
+            }
+            match migrate_chunk_id(&chunk_id, &content, algorithm) {
+                Ok(new_id) => {
+                    report.migrated.insert(chunk_id, new_id);
+                }
+                Err(e) => report.skipped.push((chunk_id, e.to_string())),
+            }
+        }
+        for node in self.iter_nodes() {
+            let chunk_id = node?.chunk_id;
+            let current = ChunkId::parse(&chunk_id).is_ok_and(|id| id.algorithm == algorithm);
+            if !current && !report.migrated.contains_key(&chunk_id) && self.get_content(&chunk_id)?.is_none() {
+                report.skipped.push((chunk_id, "no stored content".to_string()));
+            }
+        }
+        if report.migrated.is_empty() {
+            return Ok(report);
+        }
+
+        let mut original = Vec::new();
+        self.export_snapshot(&mut original)?;
+        let migrated = rewrite_snapshot(&original, &report.migrated)?;
+        if let Err(e) = self.import_snapshot(migrated.as_slice(), ImportMode::Replace) {
+            self.import_snapshot(original.as_slice(), ImportMode::Replace)?;
+            return Err(e);
+        }
+        Ok(report)
+    }
+}
+
+/// Copy a snapshot, renaming chunk IDs found in `ids`
+fn rewrite_snapshot(snapshot: &[u8], ids: &BTreeMap<String, String>) -> CadiResult<Vec<u8>> {
+    let rename = |id: &mut String| {
+        if let Some(new_id) = ids.get(id.as_str()) {
+            *id = new_id.clone();
+        }
+    };
+
+    let mut out = Vec::with_capacity(snapshot.len());
+    let mut lines = snapshot.lines();
+    if let Some(header) = lines.next() {
+        writeln!(out, "{}", header?)?;
+    }
+    for line in lines {
+        let mut record: SnapshotRecord = serde_json::from_str(&line?)?;
+        match &mut record {
+            SnapshotRecord::Node(node) => {
+                if let Some(new_id) = ids.get(&node.chunk_id) {
+                    node.content_hash = ChunkId::parse(new_id)?.digest;
+                    node.chunk_id = new_id.clone();
+                }
+                for (_, target) in node.outgoing_edges.iter_mut().chain(node.incoming_edges.iter_mut()) {
+                    rename(target);
+                }
+            }
+            SnapshotRecord::Edge { source, target, .. } => {
+                rename(source);
+                rename(target);
+            }
+            SnapshotRecord::Alias { chunk_id, .. } | SnapshotRecord::Content { chunk_id, .. } => rename(chunk_id),
+            SnapshotRecord::Refinement(refinement) => {
+                rename(&mut refinement.old_chunk);
+                rename(&mut refinement.new_chunk);
+            }
+        }
+        serde_json::to_writer(&mut out, &record)?;
+        out.push(b'\n');
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{EdgeType, GraphNode};
+    use crate::hash::chunk_id_from_content;
+
+    #[test]
+    fn test_migrate_chunk_ids() {
+        let store = GraphStore::in_memory().unwrap();
+        let a = chunk_id_from_content(b"fn a() { b() }");
+        let b = chunk_id_from_content(b"fn b() {}");
+        let mut node_a = GraphNode::new(&a, "a")
+            .with_alias("demo/a")
+            .with_defines(vec!["a".to_string()]);
+        node_a.add_dependency(EdgeType::Calls, b.clone());
+        store.insert_node(&node_a).unwrap();
+        store.insert_node(&GraphNode::new(&b, "b").with_alias("demo/b")).unwrap();
+        store.insert_node(&GraphNode::new("chunk:sha256:nocontent", "c")).unwrap();
+        store.add_dependency("chunk:sha256:nocontent", &b, EdgeType::Imports).unwrap();
+        store.store_content(&a, b"fn a() { b() }").unwrap();
+        store.store_content(&b, b"fn b() {}").unwrap();
+        store.mark_refinement(&b, &a, "demo").unwrap();
+
+        let report = store.migrate_chunk_ids(HashAlgorithm::Blake3).unwrap();
+        assert_eq!(report.migrated.len(), 2);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].0, "chunk:sha256:nocontent");
+        let (new_a, new_b) = (&report.migrated[&a], &report.migrated[&b]);
+        assert!(new_a.starts_with("chunk:blake3:"));
+
+        assert!(!store.node_exists(&a).unwrap());
+        let node = store.get_node(new_a).unwrap().unwrap();
+        assert_eq!(node.content_hash, ChunkId::parse(new_a).unwrap().digest);
+        assert_eq!(store.get_content_str(new_a).unwrap().unwrap(), "fn a() { b() }");
+        assert_eq!(store.resolve_alias("demo/a").unwrap().as_deref(), Some(new_a.as_str()));
+        assert_eq!(store.resolve_alias("demo/b").unwrap().as_deref(), Some(new_b.as_str()));
+        assert_eq!(store.find_symbol("a").unwrap().as_deref(), Some(new_a.as_str()));
+        assert!(store.get_dependencies(new_a, None).unwrap().contains(&(EdgeType::Calls, new_b.clone())));
+        assert!(store
+            .get_dependents(new_b)
+            .unwrap()
+            .contains(&(EdgeType::Imports, "chunk:sha256:nocontent".to_string())));
+        assert_eq!(store.get_latest_refinement(new_b).unwrap().as_deref(), Some(new_a.as_str()));
+
+        // Already migrated chunks are left alone
+        let again = store.migrate_chunk_ids(HashAlgorithm::Blake3).unwrap();
+        assert!(again.migrated.is_empty());
+    }
+}
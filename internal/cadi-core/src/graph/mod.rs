@@ -1,16 +1,25 @@
 use serde::{Deserialize, Serialize};
 
 // Submodules
+pub mod blob;
 pub mod edge;
+pub mod importer;
+pub mod migrate;
 pub mod node;
 pub mod query;
+pub mod refinement;
+pub mod snapshot;
 pub mod store;
 
 // Re-export types from submodules
-pub use edge::EdgeType;
+pub use edge::{confidence, Edge, EdgeType};
+pub use importer::BatchImporter;
+pub use migrate::MigrationReport;
 pub use node::GraphNode;
-pub use query::{GraphQuery, QueryNode, QueryResult, TraversalDirection};
-pub use store::GraphStore;
+pub use query::{GraphQuery, QueryIter, QueryNode, QueryResult, TraversalDirection};
+pub use refinement::Refinement;
+pub use snapshot::{ImportMode, SnapshotStats};
+pub use store::{BatchFailure, BatchReport, BlobGcReport, GraphStore};
 
 /// Edge types in the semantic dependency graph
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
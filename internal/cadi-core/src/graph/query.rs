@@ -1,10 +1,20 @@
 //! Graph query system
 //!
 //! Provides efficient queries over the chunk dependency graph.
+//!
+//! A query walks the graph breadth-first from its start nodes, returning the
+//! chunks it reaches that pass its language, granularity and orphan filters.
+//! Without start nodes it is a filter over every chunk in the graph.
+//! [`GraphStore::query_iter`] yields results as they are found;
+//! [`GraphStore::query`] collects them.
 
 use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::str::FromStr;
 
-use super::EdgeType;
+use super::{EdgeType, GraphNode, GraphStore};
+use crate::atomic::ChunkGranularity;
+use crate::error::{CadiError, CadiResult};
 
 /// A query against the graph
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -32,6 +42,10 @@ pub struct GraphQuery {
 
     /// Include the starting nodes in results
     pub include_start: bool,
+
+    /// Only return chunks nothing depends on through the followed edge types
+    #[serde(default)]
+    pub orphans_only: bool,
 }
 
 /// Direction of graph traversal
@@ -86,10 +100,11 @@ pub struct QueryNode {
 }
 
 impl GraphQuery {
-    /// Create a new query starting from given chunks
-    pub fn new(start_nodes: Vec<String>) -> Self {
+    /// Create a query over the whole graph; narrow it with the filter
+    /// methods or give start nodes with [`GraphQuery::starting_from`]
+    pub fn new() -> Self {
         Self {
-            start_nodes,
+            start_nodes: Vec::new(),
             direction: TraversalDirection::Outgoing,
             max_depth: 3,
             edge_types: None,
@@ -97,17 +112,70 @@ impl GraphQuery {
             language_filter: None,
             granularity_filter: None,
             include_start: true,
+            orphans_only: false,
         }
     }
 
+    /// Query chunks nothing depends on
+    pub fn orphaned() -> Self {
+        Self::new().orphans()
+    }
+
     /// Query dependencies (things this chunk needs)
     pub fn dependencies(chunk_id: impl Into<String>) -> Self {
-        Self::new(vec![chunk_id.into()]).with_direction(TraversalDirection::Outgoing)
+        Self::new()
+            .starting_from(vec![chunk_id.into()])
+            .with_direction(TraversalDirection::Outgoing)
     }
 
     /// Query dependents (things that need this chunk)
     pub fn dependents(chunk_id: impl Into<String>) -> Self {
-        Self::new(vec![chunk_id.into()]).with_direction(TraversalDirection::Incoming)
+        Self::new()
+            .starting_from(vec![chunk_id.into()])
+            .with_direction(TraversalDirection::Incoming)
+    }
+
+    /// Start the traversal from these chunks
+    pub fn starting_from(mut self, start_nodes: Vec<String>) -> Self {
+        self.start_nodes = start_nodes;
+        self
+    }
+
+    /// Only return chunks in this language
+    pub fn language(self, language: impl Into<String>) -> Self {
+        self.with_language(language)
+    }
+
+    /// Only return chunks of this granularity
+    pub fn granularity(mut self, granularity: ChunkGranularity) -> Self {
+        self.granularity_filter = Some(granularity_name(granularity).to_string());
+        self
+    }
+
+    /// Set traversal direction
+    pub fn direction(self, direction: TraversalDirection) -> Self {
+        self.with_direction(direction)
+    }
+
+    /// Set maximum depth
+    pub fn max_depth(self, depth: usize) -> Self {
+        self.with_depth(depth)
+    }
+
+    /// Also follow edges of this type; with no edge filter every type is
+    /// followed
+    pub fn filter_edge(mut self, edge_type: EdgeType) -> Self {
+        let types = self.edge_types.get_or_insert_with(Vec::new);
+        if !types.contains(&edge_type) {
+            types.push(edge_type);
+        }
+        self
+    }
+
+    /// Only return chunks with no incoming edges of the followed types
+    pub fn orphans(mut self) -> Self {
+        self.orphans_only = true;
+        self
     }
 
     /// Set traversal direction
@@ -163,6 +231,259 @@ impl GraphQuery {
             None => true,
         }
     }
+
+    /// Whether a node passes the language and granularity filters.
+    /// Granularity is compared case-insensitively since importers record
+    /// it both as `function` and `Function`.
+    pub fn matches(&self, node: &GraphNode) -> bool {
+        self.language_filter.as_ref().is_none_or(|lang| &node.language == lang)
+            && self
+                .granularity_filter
+                .as_ref()
+                .is_none_or(|gran| node.granularity.eq_ignore_ascii_case(gran))
+    }
+}
+
+/// Parses the textual filter syntax: whitespace-separated terms such as
+/// `lang=rust gran=function orphans edge=imports,calls dir=in depth=2
+/// limit=20 from=chunk:abc,utils/helper`. Start nodes are taken as given;
+/// resolving aliases is up to the caller.
+impl FromStr for GraphQuery {
+    type Err = CadiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |msg: String| CadiError::GraphQueryError(msg);
+        let number = |key: &str, value: &str| {
+            value
+                .parse::<usize>()
+                .map_err(|_| invalid(format!("{} must be a number, got '{}'", key, value)))
+        };
+
+        let mut query = GraphQuery::new();
+        for term in s.split_whitespace() {
+            let (key, value) = term.split_once('=').unwrap_or((term, ""));
+            match (key, value) {
+                ("orphans" | "orphan", "") => query = query.orphans(),
+                ("lang" | "language", lang) if !lang.is_empty() => query = query.language(lang),
+                ("gran" | "granularity", gran) => query = query.granularity(gran.parse().map_err(invalid)?),
+                ("edge" | "edges", edges) => {
+                    for edge in edges.split(',') {
+                        query = query.filter_edge(edge.parse().map_err(invalid)?);
+                    }
+                }
+                ("dir" | "direction", dir) => query = query.direction(dir.parse().map_err(invalid)?),
+                ("depth", depth) => query = query.max_depth(number(key, depth)?),
+                ("limit", limit) => query = query.with_limit(number(key, limit)?),
+                ("from", ids) if !ids.is_empty() => {
+                    query.start_nodes.extend(ids.split(',').map(str::to_string));
+                }
+                _ => return Err(invalid(format!("unknown filter '{}'", term))),
+            }
+        }
+        Ok(query)
+    }
+}
+
+impl FromStr for TraversalDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "out" | "outgoing" => Ok(TraversalDirection::Outgoing),
+            "in" | "incoming" => Ok(TraversalDirection::Incoming),
+            "both" => Ok(TraversalDirection::Both),
+            other => Err(format!("unknown direction: {}", other)),
+        }
+    }
+}
+
+/// Name a granularity is stored under in graph nodes
+fn granularity_name(granularity: ChunkGranularity) -> &'static str {
+    match granularity {
+        ChunkGranularity::Function => "function",
+        ChunkGranularity::Type => "type",
+        ChunkGranularity::Module => "module",
+        ChunkGranularity::Package => "package",
+        ChunkGranularity::Project => "project",
+        ChunkGranularity::Import => "import",
+    }
+}
+
+/// Lazily executed query, yielding nodes in breadth-first order as they
+/// are reached. Stops after `max_results` nodes or the first storage error.
+pub struct QueryIter<'a> {
+    store: &'a GraphStore,
+    query: GraphQuery,
+    /// Chunks to start from; every chunk in the graph, checked against the
+    /// filters as they are taken, when the query has no start nodes
+    seeds: VecDeque<String>,
+    scan: bool,
+    frontier: VecDeque<(String, usize)>,
+    ready: VecDeque<QueryNode>,
+    visited: HashSet<String>,
+    yielded: usize,
+    truncated: bool,
+    done: bool,
+}
+
+impl<'a> QueryIter<'a> {
+    pub(crate) fn new(store: &'a GraphStore, query: &GraphQuery) -> CadiResult<Self> {
+        let scan = query.start_nodes.is_empty();
+        let seeds = if scan {
+            store.node_ids()?.into()
+        } else {
+            query.start_nodes.iter().cloned().collect()
+        };
+        Ok(Self {
+            store,
+            query: query.clone(),
+            seeds,
+            scan,
+            frontier: VecDeque::new(),
+            ready: VecDeque::new(),
+            visited: HashSet::new(),
+            yielded: 0,
+            truncated: false,
+            done: false,
+        })
+    }
+
+    /// Chunks reached so far, whether or not they passed the filters
+    pub fn nodes_visited(&self) -> usize {
+        self.visited.len()
+    }
+
+    /// Whether results were cut off at `max_results`
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Queue a start chunk, returning it if it belongs in the results
+    fn seed(&mut self, chunk_id: String) -> CadiResult<Option<QueryNode>> {
+        if !self.visited.insert(chunk_id.clone()) {
+            return Ok(None);
+        }
+        let Some(node) = self.store.get_node(&chunk_id)? else {
+            return Ok(None);
+        };
+        if self.scan && !self.passes(&node)? {
+            return Ok(None);
+        }
+
+        self.frontier.push_back((chunk_id.clone(), 0));
+        Ok(self.query.include_start.then_some(QueryNode {
+            chunk_id,
+            alias: node.primary_alias,
+            depth: 0,
+            reached_via: None,
+            parent: None,
+            token_estimate: node.token_estimate,
+        }))
+    }
+
+    /// Whether a node passes every filter, orphan filtering included
+    fn passes(&self, node: &GraphNode) -> CadiResult<bool> {
+        if !self.query.matches(node) {
+            return Ok(false);
+        }
+        if !self.query.orphans_only {
+            return Ok(true);
+        }
+        Ok(!self
+            .store
+            .get_dependents(&node.chunk_id)?
+            .iter()
+            .any(|(edge_type, _)| self.query.should_follow_edge(*edge_type)))
+    }
+
+    /// Visit the neighbours of a reached chunk
+    fn expand(&mut self, current_id: String, depth: usize) -> CadiResult<()> {
+        let edges = match self.query.direction {
+            TraversalDirection::Outgoing => self.store.get_dependencies(&current_id, None)?,
+            TraversalDirection::Incoming => self.store.get_dependents(&current_id)?,
+            TraversalDirection::Both => {
+                let mut all = self.store.get_dependencies(&current_id, None)?;
+                all.extend(self.store.get_dependents(&current_id)?);
+                all
+            }
+        };
+
+        for (edge_type, target_id) in edges {
+            if !self.query.should_follow_edge(edge_type) || !self.visited.insert(target_id.clone()) {
+                continue;
+            }
+            let Some(node) = self.store.get_node(&target_id)? else {
+                continue;
+            };
+            if !self.passes(&node)? {
+                continue;
+            }
+
+            self.ready.push_back(QueryNode {
+                chunk_id: target_id.clone(),
+                alias: node.primary_alias,
+                depth: depth + 1,
+                reached_via: Some(edge_type),
+                parent: Some(current_id.clone()),
+                token_estimate: node.token_estimate,
+            });
+            self.frontier.push_back((target_id, depth + 1));
+        }
+        Ok(())
+    }
+
+    fn advance(&mut self) -> CadiResult<Option<QueryNode>> {
+        loop {
+            if let Some(node) = self.ready.pop_front() {
+                return Ok(Some(node));
+            }
+            if let Some(chunk_id) = self.seeds.pop_front() {
+                if let Some(node) = self.seed(chunk_id)? {
+                    return Ok(Some(node));
+                }
+                continue;
+            }
+            // Every node a scan could reach is already a start node
+            if self.scan {
+                return Ok(None);
+            }
+            match self.frontier.pop_front() {
+                Some((chunk_id, depth)) if depth < self.query.max_depth => self.expand(chunk_id, depth)?,
+                Some(_) => {}
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+impl Iterator for QueryIter<'_> {
+    type Item = CadiResult<QueryNode>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if self.yielded >= self.query.max_results {
+            self.done = true;
+            self.truncated = !matches!(self.advance(), Ok(None));
+            return None;
+        }
+
+        match self.advance() {
+            Ok(Some(node)) => {
+                self.yielded += 1;
+                Some(Ok(node))
+            }
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
 }
 
 impl QueryResult {
@@ -298,6 +619,28 @@ mod tests {
         assert_eq!(query.max_results, 50);
     }
 
+    #[test]
+    fn test_parse_filter_syntax() {
+        let query: GraphQuery = "lang=rust gran=Function orphans edge=imports,type-ref dir=in depth=2 limit=20 from=chunk:a,utils/b"
+            .parse()
+            .unwrap();
+        assert_eq!(query.language_filter.as_deref(), Some("rust"));
+        assert_eq!(query.granularity_filter.as_deref(), Some("function"));
+        assert!(query.orphans_only);
+        assert_eq!(query.edge_types, Some(vec![EdgeType::Imports, EdgeType::TypeRef]));
+        assert_eq!(query.direction, TraversalDirection::Incoming);
+        assert_eq!((query.max_depth, query.max_results), (2, 20));
+        assert_eq!(query.start_nodes, ["chunk:a", "utils/b"]);
+
+        for bad in ["lang", "depth=two", "edge=imports,owns", "dir=up", "color=red"] {
+            assert!(
+                matches!(bad.parse::<GraphQuery>(), Err(CadiError::GraphQueryError(_))),
+                "{} parsed",
+                bad
+            );
+        }
+    }
+
     #[test]
     fn test_result_operations() {
         let result = QueryResult {
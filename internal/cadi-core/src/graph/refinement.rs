@@ -0,0 +1,168 @@
+//! Chunk refinements
+//!
+//! A refinement says "chunk B is a newer version of chunk A". It is stored
+//! as a `Refines` edge from the new chunk to the old one, plus a record of
+//! why and when, kept per old chunk in the order the refinements were made.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use super::{EdgeType, GraphStore};
+use crate::error::{CadiError, CadiResult};
+
+/// Metadata of a `Refines` edge
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Refinement {
+    /// The chunk being replaced
+    pub old_chunk: String,
+    /// The chunk replacing it
+    pub new_chunk: String,
+    /// Why the new version exists
+    pub reason: String,
+    /// When the refinement was recorded (RFC 3339)
+    pub created_at: String,
+}
+
+impl GraphStore {
+    /// Record `new_chunk` as a refinement of `old_chunk`. Both chunks must
+    /// be in the store.
+    pub fn mark_refinement(&self, old_chunk: &str, new_chunk: &str, reason: &str) -> CadiResult<Refinement> {
+        if old_chunk == new_chunk {
+            return Err(CadiError::InvalidChunkId(format!("{} cannot refine itself", old_chunk)));
+        }
+        for chunk_id in [old_chunk, new_chunk] {
+            if !self.node_exists(chunk_id)? {
+                return Err(CadiError::ChunkNotFound(chunk_id.to_string()));
+            }
+        }
+
+        let refinement = Refinement {
+            old_chunk: old_chunk.to_string(),
+            new_chunk: new_chunk.to_string(),
+            reason: reason.to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+        };
+        self.add_dependency(new_chunk, old_chunk, EdgeType::Refines)?;
+        self.put_refinement(&refinement)?;
+        Ok(refinement)
+    }
+
+    /// Refinements of a chunk, oldest first
+    pub fn get_refinements(&self, chunk_id: &str) -> CadiResult<Vec<Refinement>> {
+        match self.refinement_tree().get(chunk_id.as_bytes())? {
+            Some(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Follow the `Refines` chain from a chunk to its newest version.
+    ///
+    /// Where a chunk was refined more than once the most recent refinement
+    /// is followed; chunks already visited and chunks no longer in the
+    /// store are skipped, so cycles end the walk. Returns `None` when the
+    /// chunk has no refinement.
+    pub fn get_latest_refinement(&self, chunk_id: &str) -> CadiResult<Option<String>> {
+        let mut visited = HashSet::from([chunk_id.to_string()]);
+        let mut current = chunk_id.to_string();
+
+        loop {
+            let mut refiners: Vec<String> = self
+                .get_dependents(&current)?
+                .into_iter()
+                .filter(|(edge_type, _)| *edge_type == EdgeType::Refines)
+                .map(|(_, id)| id)
+                .collect();
+            // Most recent last: edges without a record (e.g. added by hand)
+            // count as older than recorded ones
+            for record in self.get_refinements(&current)? {
+                if let Some(pos) = refiners.iter().position(|id| *id == record.new_chunk) {
+                    let id = refiners.remove(pos);
+                    refiners.push(id);
+                }
+            }
+
+            let mut next = None;
+            for id in refiners.into_iter().rev() {
+                if !visited.contains(&id) && self.node_exists(&id)? {
+                    next = Some(id);
+                    break;
+                }
+            }
+            match next {
+                Some(id) => {
+                    visited.insert(id.clone());
+                    current = id;
+                }
+                None => break,
+            }
+        }
+
+        Ok((current != chunk_id).then_some(current))
+    }
+
+    /// Append a refinement record without touching edges or nodes
+    pub(super) fn put_refinement(&self, refinement: &Refinement) -> CadiResult<()> {
+        let mut records = self.get_refinements(&refinement.old_chunk)?;
+        records.retain(|r| r.new_chunk != refinement.new_chunk);
+        records.push(refinement.clone());
+        self.refinement_tree()
+            .insert(refinement.old_chunk.as_bytes(), serde_json::to_vec(&records)?)?;
+        Ok(())
+    }
+
+    /// Iterate over the refinement records of each refined chunk
+    pub(super) fn iter_refinement_lists(&self) -> impl Iterator<Item = CadiResult<Vec<Refinement>>> + '_ {
+        self.refinement_tree()
+            .iter()
+            .map(|entry| Ok(serde_json::from_slice(&entry?.1)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::GraphNode;
+
+    fn store_with(ids: &[&str]) -> GraphStore {
+        let store = GraphStore::in_memory().unwrap();
+        for id in ids {
+            store.insert_node(&GraphNode::new(*id, *id)).unwrap();
+        }
+        store
+    }
+
+    #[test]
+    fn test_refinement_chain() {
+        let store = store_with(&["chunk:v1", "chunk:v2", "chunk:v3", "chunk:alt"]);
+        assert_eq!(store.get_latest_refinement("chunk:v1").unwrap(), None);
+
+        let first = store.mark_refinement("chunk:v1", "chunk:v2", "faster sort").unwrap();
+        assert_eq!(first.reason, "faster sort");
+        store.mark_refinement("chunk:v1", "chunk:alt", "experiment").unwrap();
+        store.mark_refinement("chunk:alt", "chunk:v3", "merged back").unwrap();
+
+        assert_eq!(store.get_dependencies_of_type("chunk:v2", EdgeType::Refines).unwrap(), ["chunk:v1"]);
+        assert_eq!(store.get_refinements("chunk:v1").unwrap().len(), 2);
+
+        // The newest refinement of v1 is followed down its own chain
+        assert_eq!(store.get_latest_refinement("chunk:v1").unwrap().as_deref(), Some("chunk:v3"));
+        assert_eq!(store.get_latest_refinement("chunk:v2").unwrap(), None);
+
+        assert!(matches!(
+            store.mark_refinement("chunk:v1", "chunk:missing", "x"),
+            Err(CadiError::ChunkNotFound(_))
+        ));
+        assert!(store.mark_refinement("chunk:v1", "chunk:v1", "x").is_err());
+    }
+
+    #[test]
+    fn test_latest_refinement_is_cycle_safe() {
+        let store = store_with(&["chunk:a", "chunk:b", "chunk:c"]);
+        store.mark_refinement("chunk:a", "chunk:b", "").unwrap();
+        store.mark_refinement("chunk:b", "chunk:c", "").unwrap();
+        store.mark_refinement("chunk:c", "chunk:a", "").unwrap();
+
+        assert_eq!(store.get_latest_refinement("chunk:a").unwrap().as_deref(), Some("chunk:c"));
+        assert_eq!(store.get_latest_refinement("chunk:b").unwrap().as_deref(), Some("chunk:a"));
+    }
+}
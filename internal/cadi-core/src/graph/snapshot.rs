@@ -0,0 +1,323 @@
+//! Portable graph snapshots
+//!
+//! A snapshot is a JSON Lines stream. The first line is a header naming the
+//! format and its version; every following line is one record: a node, a
+//! dependency edge, an alias, a content block (base64 encoded) or the
+//! metadata of a refinement. Nodes are written before edges so a snapshot
+//! can be imported in a single pass.
+
+use base64::engine::general_purpose;
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+
+use super::edge::EdgeMetadata;
+use super::{Edge, EdgeType, GraphNode, GraphStore, Refinement};
+use crate::error::{CadiError, CadiResult};
+
+/// Format name in the snapshot header
+pub const SNAPSHOT_FORMAT: &str = "cadi-graph-snapshot";
+
+/// Current snapshot format version
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Records staged before each batch write during import
+const IMPORT_BATCH: usize = 1000;
+
+/// First line of a snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotHeader {
+    pub format: String,
+    pub version: u32,
+    pub created_at: String,
+}
+
+/// One line of a snapshot after the header
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SnapshotRecord {
+    Node(Box<GraphNode>),
+    Edge {
+        source: String,
+        target: String,
+        edge_type: EdgeType,
+        /// Confidence and other metadata, for edges that have any
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        metadata: Option<EdgeMetadata>,
+    },
+    Alias {
+        alias: String,
+        chunk_id: String,
+    },
+    Content {
+        chunk_id: String,
+        /// Content bytes, base64 encoded
+        data: String,
+    },
+    Refinement(Refinement),
+}
+
+/// How an import treats data already in the store
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImportMode {
+    /// Refuse to import into a store that is not empty
+    #[default]
+    Empty,
+    /// Keep existing nodes, aliases and content; add the rest
+    Merge,
+    /// Clear the store first
+    Replace,
+}
+
+/// Number of records written or read
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotStats {
+    pub nodes: usize,
+    pub edges: usize,
+    pub aliases: usize,
+    pub content: usize,
+    #[serde(default)]
+    pub refinements: usize,
+    /// Records skipped because the store already had them (merge only)
+    pub skipped: usize,
+}
+
+impl GraphStore {
+    /// Write every node, edge, alias and content block as a snapshot
+    pub fn export_snapshot<W: Write>(&self, mut writer: W) -> CadiResult<SnapshotStats> {
+        let mut stats = SnapshotStats::default();
+
+        let header = SnapshotHeader {
+            format: SNAPSHOT_FORMAT.to_string(),
+            version: SNAPSHOT_VERSION,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        };
+        write_line(&mut writer, &header)?;
+
+        for node in self.iter_nodes() {
+            write_line(&mut writer, &SnapshotRecord::Node(Box::new(node?)))?;
+            stats.nodes += 1;
+        }
+        for entry in self.iter_edge_lists() {
+            let (source, edges) = entry?;
+            for (edge_type, target) in edges {
+                let record = SnapshotRecord::Edge {
+                    metadata: self.edge_metadata(&source, &target, edge_type)?,
+                    source: source.clone(),
+                    target,
+                    edge_type,
+                };
+                write_line(&mut writer, &record)?;
+                stats.edges += 1;
+            }
+        }
+        for entry in self.iter_aliases() {
+            let (alias, chunk_id) = entry?;
+            write_line(&mut writer, &SnapshotRecord::Alias { alias, chunk_id })?;
+            stats.aliases += 1;
+        }
+        for entry in self.iter_content() {
+            let (chunk_id, bytes) = entry?;
+            let data = general_purpose::STANDARD.encode(bytes);
+            write_line(&mut writer, &SnapshotRecord::Content { chunk_id, data })?;
+            stats.content += 1;
+        }
+        for records in self.iter_refinement_lists() {
+            for refinement in records? {
+                write_line(&mut writer, &SnapshotRecord::Refinement(refinement))?;
+                stats.refinements += 1;
+            }
+        }
+
+        writer.flush()?;
+        Ok(stats)
+    }
+
+    /// Read a snapshot written by [`GraphStore::export_snapshot`]
+    pub fn import_snapshot<R: BufRead>(&self, reader: R, mode: ImportMode) -> CadiResult<SnapshotStats> {
+        match mode {
+            ImportMode::Empty if !self.is_empty() => {
+                return Err(CadiError::StorageError(
+                    "graph store is not empty; merge into it or replace it".to_string(),
+                ));
+            }
+            ImportMode::Replace => self.clear()?,
+            _ => {}
+        }
+
+        let mut lines = reader.lines().enumerate();
+        let header: SnapshotHeader = match lines.next() {
+            Some((_, line)) => parse_line(&line?, 1)?,
+            None => return Err(CadiError::Serialization("empty graph snapshot".to_string())),
+        };
+        if header.format != SNAPSHOT_FORMAT {
+            return Err(CadiError::Serialization(format!(
+                "not a graph snapshot (format '{}')",
+                header.format
+            )));
+        }
+        if header.version > SNAPSHOT_VERSION {
+            return Err(CadiError::Configuration(format!(
+                "graph snapshot version {} is newer than the supported version {}",
+                header.version, SNAPSHOT_VERSION
+            )));
+        }
+
+        let merge = mode == ImportMode::Merge;
+        let mut stats = SnapshotStats::default();
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+
+        for (i, line) in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match parse_line(&line, i + 1)? {
+                SnapshotRecord::Node(node) => {
+                    if merge && self.node_exists(&node.chunk_id)? {
+                        stats.skipped += 1;
+                        continue;
+                    }
+                    nodes.push(*node);
+                    if nodes.len() >= IMPORT_BATCH {
+                        stats.nodes += self.insert_nodes_batch(&nodes)?.into_result()?;
+                        nodes.clear();
+                    }
+                }
+                SnapshotRecord::Edge { source, target, edge_type, metadata } => {
+                    if !nodes.is_empty() {
+                        stats.nodes += self.insert_nodes_batch(&nodes)?.into_result()?;
+                        nodes.clear();
+                    }
+                    edges.push(Edge { metadata, ..Edge::new(source, target, edge_type) });
+                    if edges.len() >= IMPORT_BATCH {
+                        stats.edges += self.add_edges_batch(&edges)?.into_result()?;
+                        edges.clear();
+                    }
+                }
+                SnapshotRecord::Alias { alias, chunk_id } => {
+                    if merge && self.resolve_alias(&alias)?.is_some() {
+                        stats.skipped += 1;
+                        continue;
+                    }
+                    self.insert_alias(&alias, &chunk_id)?;
+                    stats.aliases += 1;
+                }
+                SnapshotRecord::Content { chunk_id, data } => {
+                    if merge && self.get_content(&chunk_id)?.is_some() {
+                        stats.skipped += 1;
+                        continue;
+                    }
+                    let bytes = general_purpose::STANDARD.decode(data).map_err(|e| {
+                        CadiError::Serialization(format!("snapshot line {}: invalid content: {}", i + 1, e))
+                    })?;
+                    self.store_content(&chunk_id, &bytes)?;
+                    stats.content += 1;
+                }
+                SnapshotRecord::Refinement(refinement) => {
+                    self.put_refinement(&refinement)?;
+                    stats.refinements += 1;
+                }
+            }
+        }
+
+        stats.nodes += self.insert_nodes_batch(&nodes)?.into_result()?;
+        stats.edges += self.add_edges_batch(&edges)?.into_result()?;
+        self.flush()?;
+        Ok(stats)
+    }
+}
+
+fn write_line<W: Write, T: Serialize>(writer: &mut W, value: &T) -> CadiResult<()> {
+    serde_json::to_writer(&mut *writer, value)?;
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+fn parse_line<T: serde::de::DeserializeOwned>(line: &str, number: usize) -> CadiResult<T> {
+    serde_json::from_str(line)
+        .map_err(|e| CadiError::Serialization(format!("snapshot line {}: {}", number, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn populated() -> GraphStore {
+        let store = GraphStore::in_memory().unwrap();
+        let mut node_a = GraphNode::new("chunk:a", "a")
+            .with_alias("demo/a")
+            .with_defines(vec!["run".to_string()]);
+        node_a.add_dependency(EdgeType::Imports, "chunk:b".to_string());
+        store.insert_node(&node_a).unwrap();
+        store.insert_node(&GraphNode::new("chunk:b", "b").with_alias("demo/b")).unwrap();
+        store.add_dependency("chunk:a", "chunk:b", EdgeType::Calls).unwrap();
+        store.add_dependency("chunk:b", "chunk:a", EdgeType::Tests).unwrap();
+        store.add_edge(&Edge::new("chunk:b", "chunk:a", EdgeType::Calls).with_confidence(0.25)).unwrap();
+        store.store_content("chunk:a", b"fn run() {}\n").unwrap();
+        store.store_content("chunk:b", &[0, 159, 146, 150, 255]).unwrap();
+        store
+    }
+
+    fn sorted_edges(store: &GraphStore) -> Vec<(String, String, EdgeType)> {
+        let mut edges = store.list_edges().unwrap();
+        edges.sort_by(|a, b| (&a.0, &a.1, format!("{:?}", a.2)).cmp(&(&b.0, &b.1, format!("{:?}", b.2))));
+        edges
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let source = populated();
+        source.mark_refinement("chunk:a", "chunk:b", "smaller").unwrap();
+        let mut snapshot = Vec::new();
+        let exported = source.export_snapshot(&mut snapshot).unwrap();
+        assert_eq!(exported.nodes, 2);
+        assert_eq!(exported.edges, 5);
+        assert_eq!(exported.refinements, 1);
+
+        let target = GraphStore::in_memory().unwrap();
+        let imported = target.import_snapshot(snapshot.as_slice(), ImportMode::Empty).unwrap();
+        assert_eq!(imported, exported);
+
+        assert_eq!(sorted_edges(&target), sorted_edges(&source));
+        assert_eq!(target.edge_confidence("chunk:b", "chunk:a", EdgeType::Calls).unwrap(), 0.25);
+        assert_eq!(target.edge_metadata("chunk:a", "chunk:b", EdgeType::Calls).unwrap(), None);
+        assert_eq!(
+            target.get_dependents("chunk:b").unwrap(),
+            source.get_dependents("chunk:b").unwrap()
+        );
+        assert_eq!(target.get_content("chunk:b").unwrap().unwrap(), vec![0, 159, 146, 150, 255]);
+        assert_eq!(target.get_content_str("chunk:a").unwrap().unwrap(), "fn run() {}\n");
+        assert_eq!(target.resolve_alias("demo/b").unwrap().as_deref(), Some("chunk:b"));
+        assert_eq!(target.find_symbol("run").unwrap().as_deref(), Some("chunk:a"));
+        assert_eq!(target.get_refinements("chunk:a").unwrap(), source.get_refinements("chunk:a").unwrap());
+        assert_eq!(target.get_latest_refinement("chunk:a").unwrap().as_deref(), Some("chunk:b"));
+    }
+
+    #[test]
+    fn test_import_modes() {
+        let mut snapshot = Vec::new();
+        populated().export_snapshot(&mut snapshot).unwrap();
+
+        let target = GraphStore::in_memory().unwrap();
+        target.insert_node(&GraphNode::new("chunk:a", "local").with_alias("demo/a")).unwrap();
+        target.insert_node(&GraphNode::new("chunk:local", "local")).unwrap();
+
+        assert!(target.import_snapshot(snapshot.as_slice(), ImportMode::Empty).is_err());
+
+        let merged = target.import_snapshot(snapshot.as_slice(), ImportMode::Merge).unwrap();
+        assert_eq!(merged.nodes, 1);
+        assert_eq!(target.get_node("chunk:a").unwrap().unwrap().content_hash, "local");
+        assert!(target.node_exists("chunk:local").unwrap());
+
+        target.import_snapshot(snapshot.as_slice(), ImportMode::Replace).unwrap();
+        assert_eq!(target.get_node("chunk:a").unwrap().unwrap().content_hash, "a");
+        assert!(!target.node_exists("chunk:local").unwrap());
+
+        let newer = String::from_utf8(snapshot).unwrap().replacen("\"version\":1", "\"version\":99", 1);
+        let fresh = GraphStore::in_memory().unwrap();
+        assert!(fresh.import_snapshot(newer.as_bytes(), ImportMode::Empty).is_err());
+    }
+}
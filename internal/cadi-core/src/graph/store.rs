@@ -3,14 +3,24 @@
 //! The core persistent storage for the CADI dependency graph.
 //! Uses sled for embedded, ACID-compliant storage with O(1) lookups.
 
-use sled::{Db, Tree};
-use std::collections::{HashSet, VecDeque};
+use sled::transaction::{
+    ConflictableTransactionError, TransactionError, Transactional, TransactionalTree,
+};
+use sled::{Batch, Db, Tree};
+use std::collections::HashMap;
 use std::path::Path;
 use std::time::Instant;
 
-use super::{EdgeType, GraphNode, GraphQuery, QueryNode, QueryResult, TraversalDirection};
+use super::blob::{self, BlobKey};
+use super::edge::{Edge, EdgeMetadata};
+use super::query::QueryIter;
+use super::{EdgeType, GraphNode, GraphQuery, QueryResult};
 use crate::error::{CadiError, CadiResult};
 
+/// Key in the default tree marking that the symbol definer and referencer
+/// indices are in use; stores without it are reindexed when opened
+const SYMBOL_INDEX_MARKER: &[u8] = b"symbol_index_v1";
+
 /// The main graph store
 pub struct GraphStore {
     /// The underlying sled database
@@ -27,16 +37,37 @@ pub struct GraphStore {
     /// (things that depend on this chunk)
     dependents: Tree,
 
-    /// Symbol index: symbol_name -> chunk_id
-    /// For fast "who defines this symbol?" lookups
+    /// Edge metadata: source \0 target \0 edge_type -> EdgeMetadata
+    /// (serialized). Edges without an entry have the default metadata.
+    edge_metadata: Tree,
+
+    /// Symbol index: symbol_name -> chunk_id (the last chunk inserted)
     symbols: Tree,
 
+    /// Symbol definitions: symbol_name \0 chunk_id -> ()
+    /// For "which chunks define this symbol?" lookups
+    symbol_definers: Tree,
+
+    /// Symbol references: symbol_name \0 chunk_id -> ()
+    /// For "which chunks use this symbol?" lookups
+    symbol_referencers: Tree,
+
     /// Alias index: alias -> chunk_id
     /// For human-readable lookups
     aliases: Tree,
 
-    /// Chunk content storage: chunk_id -> content bytes
+    /// Chunk content storage: chunk_id -> blob reference
+    /// (or the raw bytes, for entries written before blobs)
     content: Tree,
+
+    /// Content blobs: SHA-256 of content -> compressed bytes
+    blobs: Tree,
+
+    /// Blob reference counts: SHA-256 of content -> u64 (big endian)
+    blob_refs: Tree,
+
+    /// Refinement records: old chunk_id -> [Refinement] (serialized)
+    refinements: Tree,
 }
 
 impl GraphStore {
@@ -46,15 +77,7 @@ impl GraphStore {
             CadiError::StorageError(format!("Failed to open graph store: {}", e))
         })?;
 
-        Ok(Self {
-            nodes: db.open_tree("nodes")?,
-            dependencies: db.open_tree("dependencies")?,
-            dependents: db.open_tree("dependents")?,
-            symbols: db.open_tree("symbols")?,
-            aliases: db.open_tree("aliases")?,
-            content: db.open_tree("content")?,
-            db,
-        })
+        Self::with_db(db)
     }
 
     /// Create an in-memory graph store (for testing)
@@ -64,15 +87,31 @@ impl GraphStore {
             CadiError::StorageError(format!("Failed to create in-memory store: {}", e))
         })?;
 
-        Ok(Self {
+        Self::with_db(db)
+    }
+
+    fn with_db(db: Db) -> CadiResult<Self> {
+        let store = Self {
             nodes: db.open_tree("nodes")?,
             dependencies: db.open_tree("dependencies")?,
             dependents: db.open_tree("dependents")?,
+            edge_metadata: db.open_tree("edge_metadata")?,
             symbols: db.open_tree("symbols")?,
+            symbol_definers: db.open_tree("symbol_definers")?,
+            symbol_referencers: db.open_tree("symbol_referencers")?,
             aliases: db.open_tree("aliases")?,
             content: db.open_tree("content")?,
+            blobs: db.open_tree("blobs")?,
+            blob_refs: db.open_tree("blob_refs")?,
+            refinements: db.open_tree("refinements")?,
             db,
-        })
+        };
+
+        // Stores created before the definer and referencer indices
+        if !store.db.contains_key(SYMBOL_INDEX_MARKER)? {
+            store.reindex_symbols()?;
+        }
+        Ok(store)
     }
 
     // ========================================================================
@@ -83,13 +122,15 @@ impl GraphStore {
     pub fn insert_node(&self, node: &GraphNode) -> CadiResult<()> {
         let key = node.chunk_id.as_bytes();
         let value = node.to_bytes()?;
-        
+        let previous = self.get_node(&node.chunk_id).ok().flatten();
+
         self.nodes.insert(key, value)?;
 
         // Index symbols
         for symbol in &node.symbols_defined {
             self.symbols.insert(symbol.as_bytes(), key)?;
         }
+        self.index_symbols(previous.as_ref(), Some(node))?;
 
         // Index aliases
         for alias in &node.aliases {
@@ -102,6 +143,52 @@ impl GraphStore {
         Ok(())
     }
 
+    /// Insert or update many nodes in one transaction, flushing once.
+    ///
+    /// Nodes that cannot be stored are reported in the returned
+    /// [`BatchReport`] and skipped; the rest are committed together, so a
+    /// storage error leaves none of the batch behind.
+    pub fn insert_nodes_batch(&self, nodes: &[GraphNode]) -> CadiResult<BatchReport> {
+        let mut report = BatchReport::default();
+        let mut writes = StagedWrites::new(self);
+
+        for (index, node) in nodes.iter().enumerate() {
+            let staged = if node.chunk_id.is_empty() {
+                Err(CadiError::InvalidChunkId("empty chunk ID".to_string()))
+            } else {
+                node.to_bytes().map_err(CadiError::from)
+            };
+            let value = match staged {
+                Ok(value) => value,
+                Err(e) => {
+                    report.fail(index, &node.chunk_id, e);
+                    continue;
+                }
+            };
+
+            let key = node.chunk_id.as_bytes();
+            let previous = self.get_node(&node.chunk_id).ok().flatten();
+            writes.nodes.insert(key, value);
+            for symbol in &node.symbols_defined {
+                writes.symbols.insert(symbol.as_bytes(), key);
+            }
+            stage_symbols(&mut writes.symbol_definers, &mut writes.symbol_referencers, previous.as_ref(), Some(node));
+            for alias in &node.aliases {
+                writes.aliases.insert(alias.as_bytes(), key);
+            }
+            if !node.outgoing_edges.is_empty() {
+                writes.dependencies.set(&node.chunk_id, node.outgoing_edges.clone());
+            }
+            for (edge_type, target) in &node.outgoing_edges {
+                writes.dependents.add(target, &node.chunk_id, *edge_type)?;
+            }
+            report.written += 1;
+        }
+
+        writes.commit()?;
+        Ok(report)
+    }
+
     /// Get a node by chunk ID
     pub fn get_node(&self, chunk_id: &str) -> CadiResult<Option<GraphNode>> {
         match self.nodes.get(chunk_id.as_bytes())? {
@@ -119,10 +206,11 @@ impl GraphStore {
     pub fn delete_node(&self, chunk_id: &str) -> CadiResult<bool> {
         // Get the node first to clean up indices
         if let Some(node) = self.get_node(chunk_id)? {
-            // Remove from symbol index
+            // Remove from symbol indices
             for symbol in &node.symbols_defined {
                 self.symbols.remove(symbol.as_bytes())?;
             }
+            self.index_symbols(Some(&node), None)?;
 
             // Remove from alias index
             for alias in &node.aliases {
@@ -130,11 +218,17 @@ impl GraphStore {
             }
 
             // Remove edges
+            for key in self.edge_metadata.scan_prefix(edge_key(chunk_id, "", None)).keys() {
+                self.edge_metadata.remove(key?)?;
+            }
+            for (edge_type, source) in self.get_dependents(chunk_id)? {
+                self.edge_metadata.remove(edge_key(&source, chunk_id, Some(edge_type)))?;
+            }
             self.dependencies.remove(chunk_id.as_bytes())?;
             self.dependents.remove(chunk_id.as_bytes())?;
 
             // Remove content
-            self.content.remove(chunk_id.as_bytes())?;
+            self.remove_content(chunk_id)?;
 
             // Remove node
             self.nodes.remove(chunk_id.as_bytes())?;
@@ -149,15 +243,38 @@ impl GraphStore {
     // Content Operations
     // ========================================================================
 
-    /// Store chunk content
+    /// Store chunk content. Identical content stored under several chunk
+    /// IDs shares one compressed blob.
     pub fn store_content(&self, chunk_id: &str, content: &[u8]) -> CadiResult<()> {
-        self.content.insert(chunk_id.as_bytes(), content)?;
-        Ok(())
+        let key = blob::blob_key(content);
+
+        (&self.content, &self.blobs, &self.blob_refs)
+            .transaction(|(contents, blobs, refs)| {
+                let previous = contents.insert(chunk_id.as_bytes(), blob::blob_ref(&key))?;
+                if let Some(previous) = previous.as_deref().and_then(blob::parse_blob_ref) {
+                    if previous == key {
+                        return Ok(());
+                    }
+                    release_blob(blobs, refs, &previous)?;
+                }
+
+                let count = ref_count(refs.get(key)?.as_deref());
+                refs.insert(&key, &(count + 1).to_be_bytes())?;
+                if count == 0 || blobs.get(key)?.is_none() {
+                    let encoded = blob::encode_blob(content).map_err(ConflictableTransactionError::Abort)?;
+                    blobs.insert(&key, encoded)?;
+                }
+                Ok(())
+            })
+            .map_err(transaction_error)
     }
 
     /// Get chunk content
     pub fn get_content(&self, chunk_id: &str) -> CadiResult<Option<Vec<u8>>> {
-        Ok(self.content.get(chunk_id.as_bytes())?.map(|v| v.to_vec()))
+        match self.content.get(chunk_id.as_bytes())? {
+            Some(entry) => Ok(Some(self.resolve_content(&entry)?)),
+            None => Ok(None),
+        }
     }
 
     /// Get content as string
@@ -183,9 +300,78 @@ impl GraphStore {
         Ok(())
     }
 
-    /// Get all dependencies of a chunk (things it needs)
-    pub fn get_dependencies(&self, chunk_id: &str) -> CadiResult<Vec<(EdgeType, String)>> {
-        self.get_edge_list(&self.dependencies, chunk_id)
+    /// Add an edge together with its metadata, replacing the metadata of
+    /// an existing edge. Edges without metadata keep what is stored.
+    pub fn add_edge(&self, edge: &Edge) -> CadiResult<()> {
+        self.add_dependency(&edge.source, &edge.target, edge.edge_type)?;
+        if let Some(metadata) = &edge.metadata {
+            self.edge_metadata.insert(
+                edge_key(&edge.source, &edge.target, Some(edge.edge_type)),
+                serde_json::to_vec(metadata)?,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Add many `(source, target, edge_type)` dependency edges in one
+    /// transaction, flushing once. Edges with an empty endpoint are
+    /// reported in the returned [`BatchReport`] and skipped.
+    pub fn add_dependencies_batch(&self, edges: &[(String, String, EdgeType)]) -> CadiResult<BatchReport> {
+        let edges: Vec<Edge> = edges
+            .iter()
+            .map(|(source, target, edge_type)| Edge::new(source, target, *edge_type))
+            .collect();
+        self.add_edges_batch(&edges)
+    }
+
+    /// [`add_dependencies_batch`](Self::add_dependencies_batch) for edges
+    /// with metadata, which is stored as [`add_edge`](Self::add_edge) does
+    pub fn add_edges_batch(&self, edges: &[Edge]) -> CadiResult<BatchReport> {
+        let mut report = BatchReport::default();
+        let mut writes = StagedWrites::new(self);
+
+        for (index, edge) in edges.iter().enumerate() {
+            let (source, target) = (&edge.source, &edge.target);
+            if source.is_empty() || target.is_empty() {
+                report.fail(
+                    index,
+                    &format!("{} -> {}", source, target),
+                    CadiError::InvalidChunkId("empty edge endpoint".to_string()),
+                );
+                continue;
+            }
+            writes.dependencies.add(source, target, edge.edge_type)?;
+            writes.dependents.add(target, source, edge.edge_type)?;
+            if let Some(metadata) = &edge.metadata {
+                writes
+                    .edge_metadata
+                    .insert(edge_key(source, target, Some(edge.edge_type)), serde_json::to_vec(metadata)?);
+            }
+            report.written += 1;
+        }
+
+        writes.commit()?;
+        Ok(report)
+    }
+
+    /// Get the dependencies of a chunk (things it needs), leaving out
+    /// edges whose confidence is below `min_confidence`
+    pub fn get_dependencies(
+        &self,
+        chunk_id: &str,
+        min_confidence: Option<f32>,
+    ) -> CadiResult<Vec<(EdgeType, String)>> {
+        let edges = self.get_edge_list(&self.dependencies, chunk_id)?;
+        let Some(min_confidence) = min_confidence else {
+            return Ok(edges);
+        };
+        let mut confident = Vec::with_capacity(edges.len());
+        for (edge_type, target) in edges {
+            if self.edge_confidence(chunk_id, &target, edge_type)? >= min_confidence {
+                confident.push((edge_type, target));
+            }
+        }
+        Ok(confident)
     }
 
     /// Get all dependents of a chunk (things that need it)
@@ -200,23 +386,101 @@ impl GraphStore {
         edge_type: EdgeType,
     ) -> CadiResult<Vec<String>> {
         Ok(self
-            .get_dependencies(chunk_id)?
+            .get_dependencies(chunk_id, None)?
             .into_iter()
             .filter(|(et, _)| *et == edge_type)
             .map(|(_, id)| id)
             .collect())
     }
 
+    /// Metadata stored for an edge, if any
+    pub fn edge_metadata(
+        &self,
+        source: &str,
+        target: &str,
+        edge_type: EdgeType,
+    ) -> CadiResult<Option<EdgeMetadata>> {
+        match self.edge_metadata.get(edge_key(source, target, Some(edge_type)))? {
+            Some(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Confidence of an edge; edges without metadata are fully trusted
+    pub fn edge_confidence(&self, source: &str, target: &str, edge_type: EdgeType) -> CadiResult<f32> {
+        Ok(self
+            .edge_metadata(source, target, edge_type)?
+            .map_or(super::confidence::DECLARED, |m| m.confidence))
+    }
+
+    /// Remove every edge whose confidence is below `below`, returning the
+    /// edges removed. With `dry_run` the edges are only listed.
+    pub fn prune_edges(&self, below: f32, dry_run: bool) -> CadiResult<Vec<Edge>> {
+        let pruned: Vec<Edge> = self
+            .list_edges_with_metadata()?
+            .into_iter()
+            .filter(|edge| edge.confidence() < below)
+            .collect();
+        if dry_run || pruned.is_empty() {
+            return Ok(pruned);
+        }
+
+        for edge in &pruned {
+            self.remove_from_edge_list(&self.dependencies, &edge.source, &edge.target, edge.edge_type)?;
+            self.remove_from_edge_list(&self.dependents, &edge.target, &edge.source, edge.edge_type)?;
+            self.edge_metadata.remove(edge_key(&edge.source, &edge.target, Some(edge.edge_type)))?;
+        }
+        self.flush()?;
+        Ok(pruned)
+    }
+
     // ========================================================================
     // Symbol & Alias Lookups
     // ========================================================================
 
-    /// Find the chunk that defines a symbol
+    /// Find the chunk that defines a symbol; the most used one when
+    /// several do
     pub fn find_symbol(&self, symbol: &str) -> CadiResult<Option<String>> {
-        match self.symbols.get(symbol.as_bytes())? {
-            Some(bytes) => Ok(Some(String::from_utf8_lossy(&bytes).to_string())),
-            None => Ok(None),
+        Ok(self.find_definers(symbol)?.into_iter().next())
+    }
+
+    /// Every chunk that defines a symbol, the most used (by number of
+    /// dependents) first
+    pub fn find_definers(&self, symbol: &str) -> CadiResult<Vec<String>> {
+        let mut ranked = Vec::new();
+        for chunk_id in scan_symbol(&self.symbol_definers, symbol) {
+            let chunk_id = chunk_id?;
+            let usage = self.get_dependents(&chunk_id)?.len();
+            ranked.push((usage, chunk_id));
         }
+        ranked.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        Ok(ranked.into_iter().map(|(_, chunk_id)| chunk_id).collect())
+    }
+
+    /// Every chunk that references a symbol, by chunk ID
+    pub fn find_referencers(&self, symbol: &str) -> CadiResult<Vec<String>> {
+        scan_symbol(&self.symbol_referencers, symbol).collect()
+    }
+
+    /// Rebuild the symbol definer and referencer indices from the stored
+    /// nodes, returning the number of nodes indexed. Stores created before
+    /// these indices existed are rebuilt when opened.
+    pub fn reindex_symbols(&self) -> CadiResult<usize> {
+        self.symbol_definers.clear()?;
+        self.symbol_referencers.clear()?;
+
+        let mut definers = Batch::default();
+        let mut referencers = Batch::default();
+        let mut indexed = 0;
+        for node in self.iter_nodes() {
+            stage_symbols(&mut definers, &mut referencers, None, Some(&node?));
+            indexed += 1;
+        }
+        self.symbol_definers.apply_batch(definers)?;
+        self.symbol_referencers.apply_batch(referencers)?;
+        self.db.insert(SYMBOL_INDEX_MARKER, &[])?;
+        self.flush()?;
+        Ok(indexed)
     }
 
     /// Resolve an alias to chunk ID
@@ -243,97 +507,38 @@ impl GraphStore {
     /// Execute a graph query
     pub fn query(&self, query: &GraphQuery) -> CadiResult<QueryResult> {
         let start = Instant::now();
-        let mut visited = HashSet::new();
-        let mut result_nodes = Vec::new();
-        let mut queue = VecDeque::new();
-
-        // Initialize queue with start nodes
-        for chunk_id in &query.start_nodes {
-            if query.include_start {
-                if let Some(node) = self.get_node(chunk_id)? {
-                    result_nodes.push(QueryNode {
-                        chunk_id: chunk_id.clone(),
-                        alias: node.primary_alias,
-                        depth: 0,
-                        reached_via: None,
-                        parent: None,
-                        token_estimate: node.token_estimate,
-                    });
-                }
-            }
-            visited.insert(chunk_id.clone());
-            queue.push_back((chunk_id.clone(), 0, None, None));
-        }
-
-        // BFS traversal
-        while let Some((current_id, depth, _reached_via, _parent)) = queue.pop_front() {
-            if depth >= query.max_depth {
-                continue;
-            }
-            if result_nodes.len() >= query.max_results {
-                break;
-            }
+        let mut iter = self.query_iter(query)?;
+        let nodes = iter.by_ref().collect::<CadiResult<Vec<_>>>()?;
 
-            // Get edges based on direction
-            let edges = match query.direction {
-                TraversalDirection::Outgoing => self.get_dependencies(&current_id)?,
-                TraversalDirection::Incoming => self.get_dependents(&current_id)?,
-                TraversalDirection::Both => {
-                    let mut all = self.get_dependencies(&current_id)?;
-                    all.extend(self.get_dependents(&current_id)?);
-                    all
-                }
-            };
-
-            for (edge_type, target_id) in edges {
-                if visited.contains(&target_id) {
-                    continue;
-                }
-                if !query.should_follow_edge(edge_type) {
-                    continue;
-                }
-
-                visited.insert(target_id.clone());
+        Ok(QueryResult {
+            nodes,
+            nodes_visited: iter.nodes_visited(),
+            truncated: iter.truncated(),
+            execution_time_ms: start.elapsed().as_millis() as u64,
+        })
+    }
 
-                // Get node details
-                if let Some(node) = self.get_node(&target_id)? {
-                    // Apply filters
-                    if let Some(ref lang) = query.language_filter {
-                        if &node.language != lang {
-                            continue;
-                        }
-                    }
-                    if let Some(ref gran) = query.granularity_filter {
-                        if &node.granularity != gran {
-                            continue;
-                        }
-                    }
+    /// Execute a graph query lazily, yielding nodes as the traversal
+    /// reaches them
+    pub fn query_iter(&self, query: &GraphQuery) -> CadiResult<QueryIter<'_>> {
+        QueryIter::new(self, query)
+    }
 
-                    result_nodes.push(QueryNode {
-                        chunk_id: target_id.clone(),
-                        alias: node.primary_alias,
-                        depth: depth + 1,
-                        reached_via: Some(edge_type),
-                        parent: Some(current_id.clone()),
-                        token_estimate: node.token_estimate,
-                    });
-
-                    queue.push_back((
-                        target_id,
-                        depth + 1,
-                        Some(edge_type),
-                        Some(current_id.clone()),
-                    ));
-                }
+    /// Chunks nothing depends on through any of `edge_types` (any edge
+    /// when `None`): the candidates for garbage collection, and the
+    /// entry points of a codebase
+    pub fn orphans(&self, edge_types: Option<&[EdgeType]>) -> CadiResult<Vec<String>> {
+        let mut orphans = Vec::new();
+        for chunk_id in self.node_ids()? {
+            let depended_on = self
+                .get_dependents(&chunk_id)?
+                .iter()
+                .any(|(edge_type, _)| edge_types.is_none_or(|types| types.contains(edge_type)));
+            if !depended_on {
+                orphans.push(chunk_id);
             }
         }
-
-        Ok(QueryResult {
-            nodes: result_nodes,
-            nodes_visited: visited.len(),
-            truncated: visited.len() > query.max_results,
-            execution_time_ms: start.elapsed().as_millis() as u64,
-        })
+        Ok(orphans)
     }
 
     /// Get token estimate for a chunk
@@ -358,10 +563,21 @@ impl GraphStore {
             dependency_entries: self.dependencies.len(),
             dependent_entries: self.dependents.len(),
             content_entries: self.content.len(),
+            blob_count: self.blobs.len(),
+            blob_bytes: self.blobs.iter().values().map(|v| Ok(v?.len() as u64)).sum::<CadiResult<u64>>()?,
             db_size_bytes: self.db.size_on_disk()?,
         })
     }
 
+    /// IDs of all nodes in the graph
+    pub fn node_ids(&self) -> CadiResult<Vec<String>> {
+        self.nodes
+            .iter()
+            .keys()
+            .map(|key| Ok(String::from_utf8_lossy(&key?).into_owned()))
+            .collect()
+    }
+
     /// List all nodes in the graph
     pub fn list_nodes(&self) -> CadiResult<Vec<GraphNode>> {
         let mut nodes = Vec::new();
@@ -402,16 +618,184 @@ impl GraphStore {
         Ok(edges)
     }
 
+    /// List all edges in the graph with their stored metadata
+    pub fn list_edges_with_metadata(&self) -> CadiResult<Vec<Edge>> {
+        let mut edges = Vec::new();
+        for entry in self.iter_edge_lists() {
+            let (source, list) = entry?;
+            for (edge_type, target) in list {
+                let metadata = self.edge_metadata(&source, &target, edge_type)?;
+                edges.push(Edge {
+                    metadata,
+                    ..Edge::new(source.clone(), target, edge_type)
+                });
+            }
+        }
+        Ok(edges)
+    }
+
     /// Flush all pending writes to disk
     pub fn flush(&self) -> CadiResult<()> {
         self.db.flush()?;
         Ok(())
     }
 
+    /// Drop blobs no content entry references and correct reference
+    /// counts that disagree with the content entries
+    pub fn gc_blobs(&self) -> CadiResult<BlobGcReport> {
+        let mut referenced: HashMap<BlobKey, u64> = HashMap::new();
+        for entry in self.content.iter() {
+            if let Some(key) = blob::parse_blob_ref(&entry?.1) {
+                *referenced.entry(key).or_default() += 1;
+            }
+        }
+
+        let mut report = BlobGcReport::default();
+        for entry in self.blobs.iter() {
+            let (key, value) = entry?;
+            let count = <BlobKey>::try_from(key.as_ref())
+                .ok()
+                .and_then(|k| referenced.get(&k).copied())
+                .unwrap_or(0);
+            if count == 0 {
+                self.blobs.remove(&key)?;
+                self.blob_refs.remove(&key)?;
+                report.removed += 1;
+                report.bytes_freed += value.len() as u64;
+            } else if ref_count(self.blob_refs.get(&key)?.as_deref()) != count {
+                self.blob_refs.insert(&key, &count.to_be_bytes())?;
+                report.recounted += 1;
+            }
+        }
+        for entry in self.blob_refs.iter() {
+            let key = entry?.0;
+            if !self.blobs.contains_key(&key)? {
+                self.blob_refs.remove(&key)?;
+            }
+        }
+
+        self.flush()?;
+        Ok(report)
+    }
+
+    /// Whether the store holds no nodes, edges, aliases or content
+    pub fn is_empty(&self) -> bool {
+        self.trees().iter().all(|tree| tree.is_empty())
+    }
+
+    /// Remove everything from the store
+    pub fn clear(&self) -> CadiResult<()> {
+        for tree in self.trees() {
+            tree.clear()?;
+        }
+        self.flush()
+    }
+
+    /// Iterate over stored nodes without loading them all at once
+    pub(super) fn iter_nodes(&self) -> impl Iterator<Item = CadiResult<GraphNode>> + '_ {
+        self.nodes
+            .iter()
+            .map(|entry| Ok(GraphNode::from_bytes(&entry?.1)?))
+    }
+
+    /// Iterate over `(source, edge list)` entries of the forward edge index
+    pub(super) fn iter_edge_lists(
+        &self,
+    ) -> impl Iterator<Item = CadiResult<(String, Vec<(EdgeType, String)>)>> + '_ {
+        self.dependencies.iter().map(|entry| {
+            let (key, value) = entry?;
+            Ok((String::from_utf8_lossy(&key).to_string(), serde_json::from_slice(&value)?))
+        })
+    }
+
+    /// Iterate over `(alias, chunk_id)` entries of the alias index
+    pub(super) fn iter_aliases(&self) -> impl Iterator<Item = CadiResult<(String, String)>> + '_ {
+        self.aliases.iter().map(|entry| {
+            let (key, value) = entry?;
+            Ok((
+                String::from_utf8_lossy(&key).to_string(),
+                String::from_utf8_lossy(&value).to_string(),
+            ))
+        })
+    }
+
+    /// Iterate over `(chunk_id, content)` entries
+    pub(super) fn iter_content(&self) -> impl Iterator<Item = CadiResult<(String, Vec<u8>)>> + '_ {
+        self.content.iter().map(|entry| {
+            let (key, value) = entry?;
+            Ok((String::from_utf8_lossy(&key).to_string(), self.resolve_content(&value)?))
+        })
+    }
+
+    /// Point an alias at a chunk without touching the chunk's node
+    pub(super) fn insert_alias(&self, alias: &str, chunk_id: &str) -> CadiResult<()> {
+        self.aliases.insert(alias.as_bytes(), chunk_id.as_bytes())?;
+        Ok(())
+    }
+
+    /// Refinement records, keyed by the chunk being refined
+    pub(super) fn refinement_tree(&self) -> &Tree {
+        &self.refinements
+    }
+
+    fn trees(&self) -> [&Tree; 12] {
+        [
+            &self.nodes,
+            &self.dependencies,
+            &self.dependents,
+            &self.edge_metadata,
+            &self.symbols,
+            &self.symbol_definers,
+            &self.symbol_referencers,
+            &self.aliases,
+            &self.content,
+            &self.blobs,
+            &self.blob_refs,
+            &self.refinements,
+        ]
+    }
+
     // ========================================================================
     // Private Helpers
     // ========================================================================
 
+    /// Replace the symbol index entries of `previous` with those of `current`
+    fn index_symbols(&self, previous: Option<&GraphNode>, current: Option<&GraphNode>) -> CadiResult<()> {
+        let mut definers = Batch::default();
+        let mut referencers = Batch::default();
+        stage_symbols(&mut definers, &mut referencers, previous, current);
+        self.symbol_definers.apply_batch(definers)?;
+        self.symbol_referencers.apply_batch(referencers)?;
+        Ok(())
+    }
+
+    /// Bytes of a content entry, following its blob reference
+    fn resolve_content(&self, entry: &[u8]) -> CadiResult<Vec<u8>> {
+        let Some(key) = blob::parse_blob_ref(entry) else {
+            return Ok(entry.to_vec());
+        };
+        match self.blobs.get(key)? {
+            Some(encoded) => blob::decode_blob(&encoded),
+            None => Err(CadiError::StorageError(format!(
+                "Missing content blob {}",
+                hex::encode(key)
+            ))),
+        }
+    }
+
+    /// Remove a chunk's content entry, releasing its blob
+    fn remove_content(&self, chunk_id: &str) -> CadiResult<()> {
+        (&self.content, &self.blobs, &self.blob_refs)
+            .transaction(|(contents, blobs, refs)| {
+                let removed = contents.remove(chunk_id.as_bytes())?;
+                if let Some(key) = removed.as_deref().and_then(blob::parse_blob_ref) {
+                    release_blob(blobs, refs, &key)?;
+                }
+                Ok(())
+            })
+            .map_err(transaction_error)
+    }
+
     fn update_edge_indices(
         &self,
         chunk_id: &str,
@@ -451,6 +835,23 @@ impl GraphStore {
         Ok(())
     }
 
+    fn remove_from_edge_list(
+        &self,
+        tree: &Tree,
+        key: &str,
+        value: &str,
+        edge_type: EdgeType,
+    ) -> CadiResult<()> {
+        let mut edges = self.get_edge_list(tree, key)?;
+        edges.retain(|(et, v)| !(et == &edge_type && v == value));
+        if edges.is_empty() {
+            tree.remove(key.as_bytes())?;
+        } else {
+            tree.insert(key.as_bytes(), serde_json::to_vec(&edges)?)?;
+        }
+        Ok(())
+    }
+
     fn get_edge_list(&self, tree: &Tree, key: &str) -> CadiResult<Vec<(EdgeType, String)>> {
         match tree.get(key.as_bytes())? {
             Some(bytes) => Ok(serde_json::from_slice(&bytes)?),
@@ -459,6 +860,253 @@ impl GraphStore {
     }
 }
 
+/// Key of a symbol index entry: the symbol, a NUL and the chunk ID, so a
+/// prefix scan finds every chunk for one symbol
+fn symbol_key(symbol: &str, chunk_id: &str) -> Vec<u8> {
+    let mut key = Vec::with_capacity(symbol.len() + 1 + chunk_id.len());
+    key.extend_from_slice(symbol.as_bytes());
+    key.push(0);
+    key.extend_from_slice(chunk_id.as_bytes());
+    key
+}
+
+/// Key of an edge metadata entry: source, target and edge type separated
+/// by NULs. Without an edge type and with an empty target, the prefix of
+/// every edge leaving `source`.
+fn edge_key(source: &str, target: &str, edge_type: Option<EdgeType>) -> Vec<u8> {
+    let mut key = format!("{}\0{}", source, target);
+    if let Some(edge_type) = edge_type {
+        key.push('\0');
+        key.push_str(&edge_type.to_string());
+    }
+    key.into_bytes()
+}
+
+/// Chunk IDs under `symbol` in a symbol index tree
+fn scan_symbol<'a>(tree: &'a Tree, symbol: &str) -> impl Iterator<Item = CadiResult<String>> + 'a {
+    let prefix = symbol_key(symbol, "");
+    let skip = prefix.len();
+    tree.scan_prefix(prefix)
+        .keys()
+        .map(move |key| Ok(String::from_utf8_lossy(&key?[skip..]).to_string()))
+}
+
+/// Stage removing `previous`'s symbol index entries and adding `current`'s
+fn stage_symbols(
+    definers: &mut Batch,
+    referencers: &mut Batch,
+    previous: Option<&GraphNode>,
+    current: Option<&GraphNode>,
+) {
+    if let Some(node) = previous {
+        for symbol in &node.symbols_defined {
+            definers.remove(symbol_key(symbol, &node.chunk_id));
+        }
+        for symbol in &node.symbols_referenced {
+            referencers.remove(symbol_key(symbol, &node.chunk_id));
+        }
+    }
+    if let Some(node) = current {
+        for symbol in &node.symbols_defined {
+            definers.insert(symbol_key(symbol, &node.chunk_id), &[]);
+        }
+        for symbol in &node.symbols_referenced {
+            referencers.insert(symbol_key(symbol, &node.chunk_id), &[]);
+        }
+    }
+}
+
+/// Outcome of [`GraphStore::gc_blobs`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct BlobGcReport {
+    /// Unreferenced blobs removed
+    pub removed: usize,
+    /// Compressed bytes those blobs held
+    pub bytes_freed: u64,
+    /// Blobs whose reference count was corrected
+    pub recounted: usize,
+}
+
+/// Drop one reference to a blob, deleting it with the last reference
+fn release_blob(
+    blobs: &TransactionalTree,
+    refs: &TransactionalTree,
+    key: &BlobKey,
+) -> Result<(), ConflictableTransactionError<CadiError>> {
+    let count = ref_count(refs.get(key)?.as_deref());
+    if count <= 1 {
+        refs.remove(key.as_slice())?;
+        blobs.remove(key.as_slice())?;
+    } else {
+        refs.insert(key.as_slice(), &(count - 1).to_be_bytes())?;
+    }
+    Ok(())
+}
+
+fn ref_count(value: Option<&[u8]>) -> u64 {
+    value
+        .and_then(|v| <[u8; 8]>::try_from(v).ok())
+        .map(u64::from_be_bytes)
+        .unwrap_or(0)
+}
+
+fn transaction_error(e: TransactionError<CadiError>) -> CadiError {
+    match e {
+        TransactionError::Abort(e) => e,
+        TransactionError::Storage(e) => e.into(),
+    }
+}
+
+/// Outcome of a batch write
+#[derive(Debug, Clone, Default)]
+pub struct BatchReport {
+    /// Number of entries written
+    pub written: usize,
+    /// Entries that were skipped
+    pub failed: Vec<BatchFailure>,
+}
+
+impl BatchReport {
+    fn fail(&mut self, index: usize, id: &str, error: CadiError) {
+        self.failed.push(BatchFailure {
+            index,
+            id: id.to_string(),
+            error: error.to_string(),
+        });
+    }
+
+    /// Turn the first failure, if any, into an error
+    pub fn into_result(self) -> CadiResult<usize> {
+        match self.failed.into_iter().next() {
+            Some(failure) => Err(CadiError::StorageError(failure.to_string())),
+            None => Ok(self.written),
+        }
+    }
+}
+
+/// A batch entry that was not written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchFailure {
+    /// Position of the entry in the batch
+    pub index: usize,
+    /// Chunk ID of the node, or `source -> target` of the edge
+    pub id: String,
+    pub error: String,
+}
+
+impl std::fmt::Display for BatchFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "batch entry {} ({}): {}", self.index, self.id, self.error)
+    }
+}
+
+/// Writes of a batch operation, staged in memory until commit
+struct StagedWrites<'a> {
+    store: &'a GraphStore,
+    nodes: Batch,
+    symbols: Batch,
+    symbol_definers: Batch,
+    symbol_referencers: Batch,
+    aliases: Batch,
+    edge_metadata: Batch,
+    dependencies: StagedEdgeLists<'a>,
+    dependents: StagedEdgeLists<'a>,
+}
+
+impl<'a> StagedWrites<'a> {
+    fn new(store: &'a GraphStore) -> Self {
+        Self {
+            store,
+            nodes: Batch::default(),
+            symbols: Batch::default(),
+            symbol_definers: Batch::default(),
+            symbol_referencers: Batch::default(),
+            aliases: Batch::default(),
+            edge_metadata: Batch::default(),
+            dependencies: StagedEdgeLists::new(store, &store.dependencies),
+            dependents: StagedEdgeLists::new(store, &store.dependents),
+        }
+    }
+
+    /// Apply every staged write atomically, then flush
+    fn commit(self) -> CadiResult<()> {
+        let dependencies = self.dependencies.into_batch()?;
+        let dependents = self.dependents.into_batch()?;
+        let store = self.store;
+
+        (
+            &store.nodes,
+            &store.symbols,
+            &store.symbol_definers,
+            &store.symbol_referencers,
+            &store.aliases,
+            &store.dependencies,
+            &store.dependents,
+            &store.edge_metadata,
+        )
+            .transaction(|(nodes, symbols, definers, referencers, aliases, deps, rdeps, metadata)| {
+                nodes.apply_batch(&self.nodes)?;
+                symbols.apply_batch(&self.symbols)?;
+                definers.apply_batch(&self.symbol_definers)?;
+                referencers.apply_batch(&self.symbol_referencers)?;
+                aliases.apply_batch(&self.aliases)?;
+                deps.apply_batch(&dependencies)?;
+                rdeps.apply_batch(&dependents)?;
+                metadata.apply_batch(&self.edge_metadata)?;
+                Ok::<_, ConflictableTransactionError<()>>(())
+            })
+            .map_err(|e| match e {
+                TransactionError::Abort(()) => CadiError::StorageError("batch aborted".to_string()),
+                TransactionError::Storage(e) => e.into(),
+            })?;
+
+        store.flush()
+    }
+}
+
+/// Edge lists of one index tree, read once and merged in memory
+struct StagedEdgeLists<'a> {
+    store: &'a GraphStore,
+    tree: &'a Tree,
+    lists: HashMap<String, Vec<(EdgeType, String)>>,
+}
+
+impl<'a> StagedEdgeLists<'a> {
+    fn new(store: &'a GraphStore, tree: &'a Tree) -> Self {
+        Self {
+            store,
+            tree,
+            lists: HashMap::new(),
+        }
+    }
+
+    /// Append an edge unless the list already has it
+    fn add(&mut self, key: &str, value: &str, edge_type: EdgeType) -> CadiResult<()> {
+        if !self.lists.contains_key(key) {
+            let existing = self.store.get_edge_list(self.tree, key)?;
+            self.lists.insert(key.to_string(), existing);
+        }
+        let edges = self.lists.get_mut(key).expect("edge list was just loaded");
+        if !edges.iter().any(|(et, v)| et == &edge_type && v == value) {
+            edges.push((edge_type, value.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Replace a list, as `insert_node` does with a node's outgoing edges
+    fn set(&mut self, key: &str, edges: Vec<(EdgeType, String)>) {
+        self.lists.insert(key.to_string(), edges);
+    }
+
+    fn into_batch(self) -> CadiResult<Batch> {
+        let mut batch = Batch::default();
+        for (key, edges) in &self.lists {
+            batch.insert(key.as_bytes(), serde_json::to_vec(edges)?);
+        }
+        Ok(batch)
+    }
+}
+
 /// Statistics about the graph store
 #[derive(Debug, Clone)]
 pub struct GraphStoreStats {
@@ -468,6 +1116,9 @@ pub struct GraphStoreStats {
     pub dependency_entries: usize,
     pub dependent_entries: usize,
     pub content_entries: usize,
+    pub blob_count: usize,
+    /// Size of all content blobs, after compression
+    pub blob_bytes: u64,
     pub db_size_bytes: u64,
 }
 
@@ -551,6 +1202,46 @@ mod tests {
         assert!(!store.node_exists("chunk:sha256:abc123").unwrap());
     }
 
+    #[test]
+    fn test_symbol_definers_and_referencers() {
+        let store = GraphStore::in_memory().unwrap();
+        let defines = |id: &str| GraphNode::new(id, id).with_defines(vec!["HashMapExt".to_string()]);
+        store.insert_node(&defines("chunk:a")).unwrap();
+        store.insert_node(&defines("chunk:b")).unwrap();
+        for user in ["chunk:c", "chunk:d"] {
+            let node = GraphNode::new(user, user).with_references(vec!["HashMapExt".to_string()]);
+            store.insert_node(&node).unwrap();
+            store.add_dependency(user, "chunk:b", EdgeType::Imports).unwrap();
+        }
+        store.add_dependency("chunk:c", "chunk:a", EdgeType::Imports).unwrap();
+
+        // Collisions come back most used first
+        assert_eq!(store.find_definers("HashMapExt").unwrap(), ["chunk:b", "chunk:a"]);
+        assert_eq!(store.find_symbol("HashMapExt").unwrap().as_deref(), Some("chunk:b"));
+        assert_eq!(store.find_referencers("HashMapExt").unwrap(), ["chunk:c", "chunk:d"]);
+        assert!(store.find_definers("HashMap").unwrap().is_empty());
+
+        // Updates and deletes drop stale entries
+        store.insert_node(&GraphNode::new("chunk:b", "b")).unwrap();
+        assert_eq!(store.find_definers("HashMapExt").unwrap(), ["chunk:a"]);
+        store.delete_node("chunk:d").unwrap();
+        assert_eq!(store.find_referencers("HashMapExt").unwrap(), ["chunk:c"]);
+
+        // A store from before the indices is rebuilt when opened
+        store.symbol_definers.clear().unwrap();
+        store.symbol_referencers.clear().unwrap();
+        store.db.remove(SYMBOL_INDEX_MARKER).unwrap();
+        let reopened = GraphStore::with_db(store.db.clone()).unwrap();
+        assert_eq!(reopened.find_definers("HashMapExt").unwrap(), ["chunk:a"]);
+        assert_eq!(reopened.find_referencers("HashMapExt").unwrap(), ["chunk:c"]);
+        assert_eq!(reopened.reindex_symbols().unwrap(), 3);
+
+        let batched = GraphStore::in_memory().unwrap();
+        batched.insert_nodes_batch(&[defines("chunk:a"), defines("chunk:b")]).unwrap();
+        batched.insert_nodes_batch(&[GraphNode::new("chunk:a", "a")]).unwrap();
+        assert_eq!(batched.find_definers("HashMapExt").unwrap(), ["chunk:b"]);
+    }
+
     #[test]
     fn test_content_storage() {
         let store = GraphStore::in_memory().unwrap();
@@ -584,7 +1275,7 @@ mod tests {
         store.add_dependency("chunk:a", "chunk:c", EdgeType::TypeRef).unwrap();
 
         // Check dependencies of A
-        let deps = store.get_dependencies("chunk:a").unwrap();
+        let deps = store.get_dependencies("chunk:a", None).unwrap();
         assert_eq!(deps.len(), 2);
 
         // Check dependents of B
@@ -622,4 +1313,258 @@ mod tests {
         assert!(result.nodes.iter().any(|n| n.chunk_id == "chunk:b"));
         assert!(result.nodes.iter().any(|n| n.chunk_id == "chunk:c"));
     }
+
+    #[test]
+    fn test_query_builder_filters_and_orphans() {
+        use crate::atomic::ChunkGranularity;
+        use crate::graph::TraversalDirection;
+
+        let store = GraphStore::in_memory().unwrap();
+        // main -> parse -> helper (call), main -> util, dead -> util,
+        // parse -> Config (type ref); legacy is Python and unconnected
+        for (id, language, granularity) in [
+            ("main", "rust", "module"),
+            ("parse", "rust", "function"),
+            ("helper", "rust", "Function"),
+            ("util", "rust", "function"),
+            ("dead", "rust", "function"),
+            ("config", "rust", "type"),
+            ("legacy", "python", "function"),
+        ] {
+            let node = GraphNode::new(format!("chunk:{}", id), id)
+                .with_alias(id)
+                .with_language(language)
+                .with_granularity(granularity)
+                .with_size(40);
+            store.insert_node(&node).unwrap();
+        }
+        for (source, target, edge_type) in [
+            ("main", "parse", EdgeType::Imports),
+            ("main", "util", EdgeType::Imports),
+            ("parse", "helper", EdgeType::Calls),
+            ("parse", "config", EdgeType::TypeRef),
+            ("dead", "util", EdgeType::Imports),
+        ] {
+            store
+                .add_dependency(&format!("chunk:{}", source), &format!("chunk:{}", target), edge_type)
+                .unwrap();
+        }
+        let ids = |query: &GraphQuery| -> Vec<String> {
+            let mut ids: Vec<String> = store
+                .query_iter(query)
+                .unwrap()
+                .map(|node| node.unwrap().chunk_id.trim_start_matches("chunk:").to_string())
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        // Rust functions nothing depends on
+        let query = GraphQuery::new().language("rust").granularity(ChunkGranularity::Function).orphans();
+        assert_eq!(ids(&query), ["dead"]);
+        // Calls don't count once only imports are followed
+        assert_eq!(ids(&query.filter_edge(EdgeType::Imports)), ["dead", "helper"]);
+        assert_eq!(ids(&GraphQuery::orphaned()), ["dead", "legacy", "main"]);
+        assert_eq!(
+            store.orphans(Some(&[EdgeType::Imports])).unwrap(),
+            ["chunk:config", "chunk:dead", "chunk:helper", "chunk:legacy", "chunk:main"]
+        );
+
+        // Everything that imports util within two hops
+        let query = GraphQuery::new()
+            .starting_from(vec!["chunk:util".to_string()])
+            .direction(TraversalDirection::Incoming)
+            .max_depth(2)
+            .filter_edge(EdgeType::Imports)
+            .include_start(false);
+        assert_eq!(ids(&query), ["dead", "main"]);
+
+        // Filters stop the walk: helper is only reachable through parse
+        let query = GraphQuery::dependencies("chunk:main").granularity(ChunkGranularity::Type);
+        assert_eq!(ids(&query), ["main"]);
+        let query = GraphQuery::dependencies("chunk:main").granularity(ChunkGranularity::Function);
+        assert_eq!(ids(&query), ["helper", "main", "parse", "util"]);
+
+        let result = store.query(&GraphQuery::new().language("rust").with_limit(2)).unwrap();
+        assert_eq!(result.nodes.len(), 2);
+        assert!(result.truncated);
+
+        let parsed: GraphQuery = "lang=rust gran=function orphans edge=imports".parse().unwrap();
+        assert_eq!(ids(&parsed), ["dead", "helper"]);
+    }
+
+    #[test]
+    fn test_batch_matches_single_inserts() {
+        let nodes: Vec<GraphNode> = (0..50)
+            .map(|i| {
+                let mut node = GraphNode::new(format!("chunk:{}", i), i.to_string())
+                    .with_alias(format!("batch/node-{}", i))
+                    .with_defines(vec![format!("sym_{}", i)]);
+                if i > 0 {
+                    node.add_dependency(EdgeType::Imports, format!("chunk:{}", i - 1));
+                }
+                node
+            })
+            .collect();
+        let edges: Vec<(String, String, EdgeType)> = (1..50)
+            .map(|i| (format!("chunk:{}", i), "chunk:0".to_string(), EdgeType::Calls))
+            .collect();
+
+        let single = GraphStore::in_memory().unwrap();
+        for node in &nodes {
+            single.insert_node(node).unwrap();
+        }
+        for (source, target, edge_type) in &edges {
+            single.add_dependency(source, target, *edge_type).unwrap();
+        }
+
+        let batched = GraphStore::in_memory().unwrap();
+        assert_eq!(batched.insert_nodes_batch(&nodes).unwrap().written, 50);
+        assert_eq!(batched.add_dependencies_batch(&edges).unwrap().written, 49);
+
+        let sorted = |mut edges: Vec<(String, String, EdgeType)>| {
+            edges.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+            edges
+        };
+        assert_eq!(sorted(batched.list_edges().unwrap()), sorted(single.list_edges().unwrap()));
+        for id in ["chunk:0", "chunk:25", "chunk:49"] {
+            let mut a = batched.get_dependents(id).unwrap();
+            let mut b = single.get_dependents(id).unwrap();
+            a.sort_by(|x, y| x.1.cmp(&y.1));
+            b.sort_by(|x, y| x.1.cmp(&y.1));
+            assert_eq!(a, b);
+        }
+        assert_eq!(batched.find_symbol("sym_7").unwrap().as_deref(), Some("chunk:7"));
+        assert_eq!(batched.resolve_alias("batch/node-7").unwrap().as_deref(), Some("chunk:7"));
+    }
+
+    #[test]
+    fn test_batch_reports_failed_entries() {
+        let store = GraphStore::in_memory().unwrap();
+        store.insert_node(&GraphNode::new("chunk:existing", "existing")).unwrap();
+
+        let nodes = vec![
+            GraphNode::new("chunk:a", "a"),
+            GraphNode::new("", "missing-id"),
+            GraphNode::new("chunk:b", "b"),
+        ];
+        let report = store.insert_nodes_batch(&nodes).unwrap();
+        assert_eq!(report.written, 2);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].index, 1);
+        assert!(store.insert_nodes_batch(&nodes).unwrap().into_result().is_err());
+
+        let edges = vec![
+            ("chunk:a".to_string(), "chunk:existing".to_string(), EdgeType::Imports),
+            ("chunk:b".to_string(), String::new(), EdgeType::Imports),
+        ];
+        let report = store.add_dependencies_batch(&edges).unwrap();
+        assert_eq!(report.written, 1);
+        assert_eq!(report.failed[0].index, 1);
+        assert_eq!(report.failed[0].id, "chunk:b -> ");
+
+        // Accepted entries landed; existing data is untouched
+        assert_eq!(store.stats().unwrap().node_count, 3);
+        assert!(store.get_dependencies("chunk:b", None).unwrap().is_empty());
+        assert_eq!(
+            store.get_dependents("chunk:existing").unwrap(),
+            vec![(EdgeType::Imports, "chunk:a".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_edge_confidence_filter_and_prune() {
+        let store = GraphStore::in_memory().unwrap();
+        for id in ["chunk:a", "chunk:b", "chunk:c", "chunk:d"] {
+            store.insert_node(&GraphNode::new(id, id)).unwrap();
+        }
+        store.add_dependency("chunk:a", "chunk:b", EdgeType::Imports).unwrap();
+        let edges = vec![
+            Edge::new("chunk:a", "chunk:c", EdgeType::Imports).with_confidence(0.2),
+            Edge::new("chunk:a", "chunk:d", EdgeType::Calls).with_confidence(0.6),
+            Edge::new("chunk:d", "chunk:c", EdgeType::TypeRef).with_confidence(0.1),
+        ];
+        assert_eq!(store.add_edges_batch(&edges).unwrap().written, 3);
+
+        assert_eq!(store.get_dependencies("chunk:a", None).unwrap().len(), 3);
+        assert_eq!(
+            store.get_dependencies("chunk:a", Some(0.5)).unwrap(),
+            vec![(EdgeType::Imports, "chunk:b".to_string()), (EdgeType::Calls, "chunk:d".to_string())]
+        );
+        let listed = store.list_edges_with_metadata().unwrap();
+        let listed = listed.iter().find(|e| e.target == "chunk:d").unwrap();
+        assert_eq!(listed.confidence(), 0.6);
+
+        // A dry run only lists the edges
+        let noisy = store.prune_edges(0.3, true).unwrap();
+        assert_eq!(noisy.len(), 2);
+        assert_eq!(store.list_edges().unwrap().len(), 4);
+
+        let pruned = store.prune_edges(0.3, false).unwrap();
+        assert_eq!(pruned.len(), 2);
+        assert_eq!(store.list_edges().unwrap().len(), 2);
+        assert!(store.get_dependents("chunk:c").unwrap().is_empty());
+        assert!(store.get_dependencies("chunk:d", None).unwrap().is_empty());
+        assert_eq!(store.edge_metadata("chunk:a", "chunk:c", EdgeType::Imports).unwrap(), None);
+
+        // Deleting a node drops the metadata of its edges
+        store.delete_node("chunk:d").unwrap();
+        assert_eq!(store.edge_metadata("chunk:a", "chunk:d", EdgeType::Calls).unwrap(), None);
+    }
+
+    #[test]
+    fn test_identical_content_shares_one_blob() {
+        let store = GraphStore::in_memory().unwrap();
+
+        // Incompressible, so the size reflects deduplication alone
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let content: Vec<u8> = (0..1024 * 1024)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        for i in 0..10 {
+            store.store_content(&format!("chunk:{}", i), &content).unwrap();
+        }
+
+        let stats = store.stats().unwrap();
+        assert_eq!(stats.content_entries, 10);
+        assert_eq!(stats.blob_count, 1);
+        assert!(stats.blob_bytes < content.len() as u64 + 1024);
+        assert_eq!(store.get_content("chunk:7").unwrap().unwrap(), content);
+
+        // The blob outlives all but the last reference
+        for i in 0..9 {
+            store.remove_content(&format!("chunk:{}", i)).unwrap();
+        }
+        assert_eq!(store.stats().unwrap().blob_count, 1);
+        store.store_content("chunk:9", b"replaced").unwrap();
+        assert_eq!(store.stats().unwrap().blob_count, 1);
+        assert_eq!(store.get_content_str("chunk:9").unwrap().unwrap(), "replaced");
+    }
+
+    #[test]
+    fn test_legacy_content_and_blob_gc() {
+        let store = GraphStore::in_memory().unwrap();
+
+        // Written raw, before content went through blobs
+        store.content.insert("chunk:old", "fn legacy() {}".as_bytes()).unwrap();
+        assert_eq!(store.get_content_str("chunk:old").unwrap().unwrap(), "fn legacy() {}");
+
+        let header = "/* Licensed under the MIT License */\n".repeat(100);
+        store.store_content("chunk:a", header.as_bytes()).unwrap();
+        assert!(store.stats().unwrap().blob_bytes < header.len() as u64 / 4);
+
+        // An orphaned blob, as a crash between writes could leave behind
+        store.blobs.insert([7u8; 32], blob::encode_blob(b"orphan").unwrap()).unwrap();
+        store.blob_refs.insert([7u8; 32], &1u64.to_be_bytes()).unwrap();
+
+        let report = store.gc_blobs().unwrap();
+        assert_eq!(report.removed, 1);
+        assert_eq!(store.stats().unwrap().blob_count, 1);
+        assert_eq!(store.get_content_str("chunk:a").unwrap().unwrap(), header);
+    }
 }
@@ -1,8 +1,162 @@
 //! Hashing utilities for CADI
+//!
+//! Chunk IDs have the form `chunk:<algorithm>:<hex digest>`, where the
+//! algorithm is one of [`HashAlgorithm`]. SHA-256 is the default; BLAKE3
+//! IDs can live alongside it and existing chunks can be moved over with
+//! [`migrate_chunk_id`].
 
+use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
+use std::fmt;
 use std::io::{Read, BufReader};
 use std::path::Path;
+use std::str::FromStr;
+
+use crate::error::{CadiError, CadiResult};
+
+/// Hash algorithm of a chunk ID
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    #[default]
+    Sha256,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    /// Every supported algorithm
+    pub const ALL: [HashAlgorithm; 2] = [HashAlgorithm::Sha256, HashAlgorithm::Blake3];
+
+    /// Name used in chunk IDs
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Blake3 => "blake3",
+        }
+    }
+
+    /// Hex digest of bytes
+    pub fn hash_bytes(self, data: &[u8]) -> String {
+        match self {
+            HashAlgorithm::Sha256 => sha256_bytes(data),
+            HashAlgorithm::Blake3 => blake3::hash(data).to_hex().to_string(),
+        }
+    }
+
+    /// Hex digest of a file, read in blocks
+    pub fn hash_file(self, path: &Path) -> std::io::Result<String> {
+        match self {
+            HashAlgorithm::Sha256 => sha256_file(path),
+            HashAlgorithm::Blake3 => {
+                let mut hasher = blake3::Hasher::new();
+                read_blocks(path, |block| {
+                    hasher.update(block);
+                })?;
+                Ok(hasher.finalize().to_hex().to_string())
+            }
+        }
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = CadiError;
+
+    fn from_str(s: &str) -> CadiResult<Self> {
+        HashAlgorithm::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                CadiError::Configuration(format!(
+                    "unknown hash algorithm '{}' (expected sha256 or blake3)",
+                    s
+                ))
+            })
+    }
+}
+
+/// A parsed chunk ID
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChunkId {
+    pub algorithm: HashAlgorithm,
+    /// Hex digest of the chunk content
+    pub digest: String,
+}
+
+impl ChunkId {
+    /// Parse `chunk:<algorithm>:<digest>`
+    pub fn parse(chunk_id: &str) -> CadiResult<Self> {
+        let invalid = |reason: &str| CadiError::InvalidChunkId(format!("{}: {}", chunk_id, reason));
+        let rest = chunk_id
+            .strip_prefix("chunk:")
+            .ok_or_else(|| invalid("expected chunk:<algorithm>:<digest>"))?;
+        let (algorithm, digest) = rest
+            .split_once(':')
+            .ok_or_else(|| invalid("expected chunk:<algorithm>:<digest>"))?;
+        let algorithm = algorithm.parse().map_err(|_| invalid("unknown hash algorithm"))?;
+        // Digests end up in file names, so nothing that could leave a directory
+        if digest.is_empty() || !digest.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(invalid("digest must be non-empty and alphanumeric"));
+        }
+        Ok(Self {
+            algorithm,
+            digest: digest.to_string(),
+        })
+    }
+
+    /// The ID of some content
+    pub fn from_content(algorithm: HashAlgorithm, content: &[u8]) -> Self {
+        Self {
+            algorithm,
+            digest: algorithm.hash_bytes(content),
+        }
+    }
+
+    /// Whether content hashes to this ID
+    pub fn matches(&self, content: &[u8]) -> bool {
+        self.algorithm.hash_bytes(content) == self.digest
+    }
+
+    /// Key for storing the chunk on disk or in a table: the bare digest for
+    /// SHA-256, as chunks have always been stored, else `<algorithm>-<digest>`
+    pub fn storage_key(&self) -> String {
+        match self.algorithm {
+            HashAlgorithm::Sha256 => self.digest.clone(),
+            algorithm => format!("{}-{}", algorithm, self.digest),
+        }
+    }
+
+    /// Inverse of [`ChunkId::storage_key`]
+    pub fn from_storage_key(key: &str) -> Self {
+        let (algorithm, digest) = key
+            .split_once('-')
+            .and_then(|(name, digest)| Some((name.parse().ok()?, digest)))
+            .unwrap_or((HashAlgorithm::Sha256, key));
+        Self {
+            algorithm,
+            digest: digest.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ChunkId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "chunk:{}:{}", self.algorithm, self.digest)
+    }
+}
+
+impl FromStr for ChunkId {
+    type Err = CadiError;
+
+    fn from_str(s: &str) -> CadiResult<Self> {
+        ChunkId::parse(s)
+    }
+}
 
 /// Compute SHA256 hash of bytes
 pub fn sha256_bytes(data: &[u8]) -> String {
@@ -18,47 +172,74 @@ pub fn sha256_str(s: &str) -> String {
 
 /// Compute SHA256 hash of a file
 pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    read_blocks(path, |block| hasher.update(block))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn read_blocks(path: &Path, mut f: impl FnMut(&[u8])) -> std::io::Result<()> {
     let file = std::fs::File::open(path)?;
     let mut reader = BufReader::new(file);
-    let mut hasher = Sha256::new();
-    
+
     let mut buffer = [0u8; 8192];
     loop {
         let bytes_read = reader.read(&mut buffer)?;
         if bytes_read == 0 {
             break;
         }
-        hasher.update(&buffer[..bytes_read]);
+        f(&buffer[..bytes_read]);
     }
-    
-    Ok(format!("{:x}", hasher.finalize()))
+    Ok(())
 }
 
-/// Create a chunk ID from a hash
+/// Create a SHA-256 chunk ID from a hash
 pub fn chunk_id_from_hash(hash: &str) -> String {
     format!("chunk:sha256:{}", hash)
 }
 
-/// Create a chunk ID from content
+/// Create a SHA-256 chunk ID from content
 pub fn chunk_id_from_content(content: &[u8]) -> String {
     chunk_id_from_hash(&sha256_bytes(content))
 }
 
 /// Parse a chunk ID to extract the hash
 pub fn parse_chunk_id(chunk_id: &str) -> Option<String> {
-    chunk_id
-        .strip_prefix("chunk:sha256:")
-        .map(|s| s.to_string())
+    ChunkId::parse(chunk_id).ok().map(|id| id.digest)
+}
+
+/// Storage key of a chunk ID (see [`ChunkId::storage_key`]), or the input
+/// itself when it is not a chunk ID
+pub fn chunk_storage_key(chunk_id: &str) -> String {
+    ChunkId::parse(chunk_id)
+        .map(|id| id.storage_key())
+        .unwrap_or_else(|_| chunk_id.to_string())
+}
+
+/// Short form of a chunk ID for display: the first 12 characters of its
+/// storage key
+pub fn short_chunk_id(chunk_id: &str) -> String {
+    chunk_storage_key(chunk_id).chars().take(12).collect()
 }
 
-/// Verify that content matches a chunk ID
+/// Verify that content matches a chunk ID, using the ID's algorithm
 pub fn verify_chunk_content(chunk_id: &str, content: &[u8]) -> bool {
-    if let Some(expected_hash) = parse_chunk_id(chunk_id) {
-        let actual_hash = sha256_bytes(content);
-        expected_hash == actual_hash
-    } else {
-        false
+    ChunkId::parse(chunk_id).is_ok_and(|id| id.matches(content))
+}
+
+/// Re-address a chunk under another hash algorithm.
+///
+/// The content must match `old_id`, so a migration cannot attach an ID to
+/// the wrong bytes.
+pub fn migrate_chunk_id(old_id: &str, content: &[u8], new_algorithm: HashAlgorithm) -> CadiResult<String> {
+    let old = ChunkId::parse(old_id)?;
+    let actual = old.algorithm.hash_bytes(content);
+    if actual != old.digest {
+        return Err(CadiError::HashMismatch {
+            expected: old.digest,
+            actual,
+        });
     }
+    Ok(ChunkId::from_content(new_algorithm, content).to_string())
 }
 
 #[cfg(test)]
@@ -86,4 +267,55 @@ mod tests {
         assert!(verify_chunk_content(&chunk_id, content));
         assert!(!verify_chunk_content(&chunk_id, b"different content"));
     }
+
+    #[test]
+    fn test_parse_blake3_chunk_id() {
+        let id = ChunkId::from_content(HashAlgorithm::Blake3, b"");
+        assert_eq!(
+            id.to_string(),
+            "chunk:blake3:af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+        assert_eq!(ChunkId::parse(&id.to_string()).unwrap(), id);
+        assert!(verify_chunk_content(&id.to_string(), b""));
+        assert!(!verify_chunk_content(&id.to_string(), b"x"));
+
+        for invalid in ["chunk:md5:abc", "chunk:sha256:", "chunk:sha256:../x", "sha256:abc", "abc"] {
+            assert!(ChunkId::parse(invalid).is_err(), "{}", invalid);
+            assert!(!verify_chunk_content(invalid, b""));
+        }
+    }
+
+    #[test]
+    fn test_storage_key_round_trip() {
+        for algorithm in HashAlgorithm::ALL {
+            let id = ChunkId::from_content(algorithm, b"content");
+            assert_eq!(ChunkId::from_storage_key(&id.storage_key()), id);
+        }
+        let sha = chunk_id_from_content(b"content");
+        assert_eq!(chunk_storage_key(&sha), sha256_bytes(b"content"));
+        assert_eq!(chunk_storage_key("my-chunk"), "my-chunk");
+    }
+
+    #[test]
+    fn test_short_chunk_id() {
+        let sha = chunk_id_from_content(b"content");
+        assert_eq!(short_chunk_id(&sha), sha256_bytes(b"content")[..12]);
+        assert_eq!(short_chunk_id("my-chunk"), "my-chunk");
+        assert_eq!(short_chunk_id("chunk:ünïcödé-name"), "chunk:ünïcöd");
+    }
+
+    #[test]
+    fn test_migrate_chunk_id() {
+        let content = b"fn main() {}";
+        let old = chunk_id_from_content(content);
+        let new = migrate_chunk_id(&old, content, HashAlgorithm::Blake3).unwrap();
+        assert!(new.starts_with("chunk:blake3:"));
+        assert!(verify_chunk_content(&new, content));
+        assert_eq!(migrate_chunk_id(&new, content, HashAlgorithm::Sha256).unwrap(), old);
+
+        assert!(matches!(
+            migrate_chunk_id(&old, b"other", HashAlgorithm::Blake3),
+            Err(CadiError::HashMismatch { .. })
+        ));
+    }
 }
@@ -0,0 +1,233 @@
+//! Reviewed import plans
+//!
+//! An [`ImportPlan`] holds the chunking decision of every file of a project
+//! so the decisions can be reviewed and edited before any chunk is generated:
+//! aliases renamed, chunks merged or split by changing their line ranges, or
+//! files marked `Skip`. Saved alongside the project, the same plan reproduces
+//! the same chunks on any machine. File paths are relative to the project
+//! root.
+
+use crate::error::{CadiError, CadiResult};
+use crate::smart_chunker::{ChunkingDecision, ChunkingStrategy, FileAnalysis};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Default location of the plan written by `cadi import --review`
+pub const IMPORT_PLAN_FILE: &str = ".cadi/import-plan.yaml";
+
+/// Chunking decisions for the files of a project
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportPlan {
+    /// One decision per file, with `file_path` relative to the project root
+    #[serde(default)]
+    pub files: Vec<ChunkingDecision>,
+}
+
+impl ImportPlan {
+    /// Load a plan from a YAML file
+    pub fn load(path: &Path) -> CadiResult<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Self::parse(&content)
+            .map_err(|e| CadiError::Configuration(format!("Invalid import plan {}: {}", path.display(), e)))
+    }
+
+    /// Parse a plan from YAML
+    pub fn parse(content: &str) -> CadiResult<Self> {
+        serde_yaml::from_str(content).map_err(|e| CadiError::Serialization(e.to_string()))
+    }
+
+    /// Write the plan to a YAML file, creating its directory
+    pub fn save(&self, path: &Path) -> CadiResult<()> {
+        let yaml = serde_yaml::to_string(self).map_err(|e| CadiError::Serialization(e.to_string()))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, yaml)?;
+        Ok(())
+    }
+
+    /// The planned decision for a project-relative path
+    pub fn decision_for(&self, relative: &Path) -> Option<&ChunkingDecision> {
+        self.files.iter().find(|decision| decision.file_path == relative)
+    }
+
+    /// Check the plan against the analyses of the project under `root`.
+    /// Every planned file must exist, chunk line ranges must lie within
+    /// their file, two chunks may only overlap when one contains the other,
+    /// no chunk may cut through a code entity, and aliases must be unique
+    /// within a file. All problems are reported together.
+    pub fn validate(&self, root: &Path, files: &[FileAnalysis]) -> CadiResult<()> {
+        let analyses: HashMap<&Path, &FileAnalysis> = files
+            .iter()
+            .map(|f| (f.path.strip_prefix(root).unwrap_or(&f.path), f))
+            .collect();
+
+        let mut issues = Vec::new();
+        let mut planned = HashSet::new();
+        for decision in &self.files {
+            let file = decision.file_path.display();
+            if !planned.insert(decision.file_path.as_path()) {
+                issues.push(format!("{}: planned more than once", file));
+                continue;
+            }
+            let Some(analysis) = analyses.get(decision.file_path.as_path()) else {
+                issues.push(format!("{}: not a file of the project", file));
+                continue;
+            };
+            if decision.strategy == ChunkingStrategy::Skip {
+                continue;
+            }
+            if decision.suggested_chunks.is_empty() {
+                issues.push(format!("{}: no chunks; use strategy Skip to leave the file out", file));
+                continue;
+            }
+
+            let mut aliases = HashSet::new();
+            for chunk in &decision.suggested_chunks {
+                if chunk.alias.trim().is_empty() {
+                    issues.push(format!("{}: chunk {} has no alias", file, chunk.name));
+                } else if !aliases.insert(chunk.alias.as_str()) {
+                    issues.push(format!("{}: alias {} is used by more than one chunk", file, chunk.alias));
+                }
+                // Empty files have a single chunk of no lines
+                let in_file = chunk.start_line >= 1
+                    && chunk.start_line <= chunk.end_line
+                    && chunk.end_line <= analysis.total_lines;
+                if analysis.total_lines > 0 && !in_file {
+                    issues.push(format!(
+                        "{}: chunk {} spans lines {}-{} of a {}-line file",
+                        file, chunk.name, chunk.start_line, chunk.end_line, analysis.total_lines
+                    ));
+                }
+            }
+
+            // A chunk inside an entity must be made of whole nested
+            // entities, like the methods of a class
+            let starts: HashSet<usize> = analysis.entities.iter().map(|e| e.start_line).collect();
+            let ends: HashSet<usize> = analysis.entities.iter().map(|e| e.end_line).collect();
+
+            let chunks = &decision.suggested_chunks;
+            for (i, a) in chunks.iter().enumerate() {
+                for b in &chunks[i + 1..] {
+                    if crosses((a.start_line, a.end_line), (b.start_line, b.end_line)) {
+                        issues.push(format!(
+                            "{}: chunks {} (lines {}-{}) and {} (lines {}-{}) overlap",
+                            file, a.name, a.start_line, a.end_line, b.name, b.start_line, b.end_line
+                        ));
+                    }
+                }
+                let lines = (a.start_line, a.end_line);
+                for entity in &analysis.entities {
+                    let entity_lines = (entity.start_line, entity.end_line);
+                    let inside = contains(entity_lines, lines)
+                        && lines != entity_lines
+                        && !(starts.contains(&lines.0) && ends.contains(&lines.1));
+                    if crosses(lines, entity_lines) || inside {
+                        issues.push(format!(
+                            "{}: chunk {} (lines {}-{}) cuts through {} (lines {}-{})",
+                            file, a.name, a.start_line, a.end_line, entity.name, entity.start_line, entity.end_line
+                        ));
+                    }
+                }
+            }
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(CadiError::Configuration(format!(
+                "Invalid import plan:\n  {}",
+                issues.join("\n  ")
+            )))
+        }
+    }
+}
+
+/// Whether two inclusive line ranges overlap without one containing the
+/// other
+fn crosses(a: (usize, usize), b: (usize, usize)) -> bool {
+    a.0 <= b.1 && b.0 <= a.1 && !contains(a, b) && !contains(b, a)
+}
+
+fn contains(outer: (usize, usize), inner: (usize, usize)) -> bool {
+    outer.0 <= inner.0 && inner.1 <= outer.1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smart_chunker::SmartChunker;
+    use std::path::PathBuf;
+
+    const SOURCE: &str = "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\npub fn sub(a: i32, b: i32) -> i32 {\n    a - b\n}\n";
+
+    fn planned() -> (ImportPlan, Vec<FileAnalysis>) {
+        let root = Path::new("/project");
+        let chunker = SmartChunker::default();
+        let analysis = chunker.analyze_file(&root.join("src/math.rs"), SOURCE).unwrap();
+        let mut decision = chunker.decide_chunking_as(&analysis, ChunkingStrategy::ByEntity);
+        decision.file_path = PathBuf::from("src/math.rs");
+        (ImportPlan { files: vec![decision] }, vec![analysis])
+    }
+
+    fn issues(plan: &ImportPlan, files: &[FileAnalysis]) -> String {
+        match plan.validate(Path::new("/project"), files) {
+            Err(CadiError::Configuration(message)) => message,
+            other => panic!("expected an invalid plan, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_plan_round_trips_and_validates() {
+        let (plan, files) = planned();
+        plan.validate(Path::new("/project"), &files).unwrap();
+
+        let yaml = serde_yaml::to_string(&plan).unwrap();
+        let parsed = ImportPlan::parse(&yaml).unwrap();
+        let decision = parsed.decision_for(Path::new("src/math.rs")).unwrap();
+        assert_eq!(decision.suggested_chunks.len(), plan.files[0].suggested_chunks.len());
+        parsed.validate(Path::new("/project"), &files).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_edits_are_reported_together() {
+        let (mut plan, files) = planned();
+        let mut first = plan.files[0].suggested_chunks[0].clone();
+        first.name = "add".to_string();
+        first.alias = "math/add".to_string();
+        first.start_line = 1;
+        first.end_line = 2;
+        let mut second = first.clone();
+        second.name = "rest".to_string();
+        second.start_line = 2;
+        second.end_line = 9;
+        plan.files[0].suggested_chunks = vec![first, second];
+
+        let mut unknown = plan.files[0].clone();
+        unknown.file_path = PathBuf::from("src/gone.rs");
+        plan.files.push(unknown);
+
+        let message = issues(&plan, &files);
+        assert!(message.contains("chunk add (lines 1-2) cuts through add (lines 1-3)"), "{}", message);
+        assert!(message.contains("chunk rest (lines 2-9) cuts through add (lines 1-3)"), "{}", message);
+        assert!(message.contains("chunks add (lines 1-2) and rest (lines 2-9) overlap"), "{}", message);
+        assert!(message.contains("rest spans lines 2-9 of a 7-line file"), "{}", message);
+        assert!(message.contains("alias math/add is used by more than one chunk"), "{}", message);
+        assert!(message.contains("src/gone.rs: not a file of the project"), "{}", message);
+
+        // A skipped file needs no chunks, and nested chunks are fine
+        let (mut plan, files) = planned();
+        let whole = plan.files[0].suggested_chunks[0].clone();
+        plan.files[0].suggested_chunks.insert(0, crate::smart_chunker::SuggestedChunk {
+            alias: "math".to_string(),
+            start_line: 1,
+            end_line: 7,
+            ..whole
+        });
+        plan.validate(Path::new("/project"), &files).unwrap();
+        plan.files[0].strategy = ChunkingStrategy::Skip;
+        plan.files[0].suggested_chunks.clear();
+        plan.validate(Path::new("/project"), &files).unwrap();
+    }
+}
@@ -0,0 +1,138 @@
+//! Environment variable interpolation in manifests
+//!
+//! String fields may contain `${env:NAME}`, which is replaced by the value of
+//! the environment variable, or `${env:NAME:-default}`, which falls back to
+//! `default` when the variable is unset or empty. `$${` produces a literal
+//! `${`.
+
+use crate::validator::{codes, Diagnostic};
+use serde_json::Value;
+
+/// Substitute every `${env:...}` in `input`
+pub fn interpolate(input: &str, lookup: &dyn Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            out.push_str(&rest[..start - 1]);
+            out.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        out.push_str(&rest[..start]);
+
+        let body_start = start + 2;
+        let Some(len) = rest[body_start..].find('}') else {
+            return Err(format!("Unterminated interpolation in '{}'", input));
+        };
+        let body = &rest[body_start..body_start + len];
+        rest = &rest[body_start + len + 1..];
+
+        let Some(var) = body.strip_prefix("env:") else {
+            return Err(format!("Unsupported interpolation '${{{}}}' (expected ${{env:NAME}})", body));
+        };
+        let (name, default) = match var.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (var, None),
+        };
+        if name.is_empty() {
+            return Err(format!("Missing variable name in '${{{}}}'", body));
+        }
+
+        match (lookup(name).filter(|v| !v.is_empty()), default) {
+            (Some(value), _) => out.push_str(&value),
+            (None, Some(default)) => out.push_str(default),
+            (None, None) => {
+                return Err(format!("Environment variable {} is not set and has no default", name));
+            }
+        }
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Interpolate every string in a JSON tree in place, collecting one error
+/// per string that does not resolve
+pub fn interpolate_value(
+    value: &mut Value,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<(), Vec<Diagnostic>> {
+    let mut errors = Vec::new();
+    walk(value, String::new(), lookup, &mut errors);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn walk(
+    value: &mut Value,
+    path: String,
+    lookup: &dyn Fn(&str) -> Option<String>,
+    errors: &mut Vec<Diagnostic>,
+) {
+    match value {
+        Value::String(s) if s.contains("${") => match interpolate(s, lookup) {
+            Ok(resolved) => *s = resolved,
+            Err(message) => errors.push(Diagnostic::error(
+                codes::UNRESOLVED_INTERPOLATION,
+                message,
+                if path.is_empty() { "root".to_string() } else { path },
+            )),
+        },
+        Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                walk(item, format!("{}[{}]", path, i), lookup, errors);
+            }
+        }
+        Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                let child = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                walk(item, child, lookup, errors);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(name: &str) -> Option<String> {
+        match name {
+            "REGISTRY" => Some("https://registry.internal".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_interpolate_env_and_defaults() {
+        assert_eq!(interpolate("${env:REGISTRY}/v1", &env).unwrap(), "https://registry.internal/v1");
+        assert_eq!(interpolate("${env:MISSING:-dev}", &env).unwrap(), "dev");
+        assert_eq!(interpolate("${env:EMPTY:-fallback}", &env).unwrap(), "fallback");
+        assert_eq!(interpolate("cost: $${env:REGISTRY}", &env).unwrap(), "cost: ${env:REGISTRY}");
+        assert_eq!(interpolate("no placeholders", &env).unwrap(), "no placeholders");
+
+        assert!(interpolate("${env:MISSING}", &env).unwrap_err().contains("MISSING"));
+        assert!(interpolate("${env:REGISTRY", &env).is_err());
+        assert!(interpolate("${file:secret}", &env).is_err());
+    }
+
+    #[test]
+    fn test_interpolate_value_reports_paths() {
+        let mut value = serde_json::json!({
+            "application": { "name": "${env:MISSING:-app}" },
+            "build_targets": [{ "platform": "${env:PLATFORM}" }],
+        });
+
+        let errors = interpolate_value(&mut value, &env).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "build_targets[0].platform");
+        assert_eq!(value["application"]["name"], "app");
+    }
+}
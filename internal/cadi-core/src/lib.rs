@@ -6,12 +6,25 @@
 //!
 //! - `chunk` - Basic chunk types
 //! - `manifest` - CADI manifest parsing
+//! - `manifest_upgrade` - Upgrading manifests to the current `cadl_version`
+//! - `manifest_generator` - Build-ready manifests from an import
+//! - `interpolation` - `${env:...}` substitution in manifests
+//! - `lockfile` - Chunk references and `cadi.lock`
 //! - `hash` - Content hashing utilities
 //! - `atomic` - Atomic chunk system with aliases
+//! - `chunk_diff` - Structured differences between chunk versions
+//! - `alias_index` - Alias resolution with fuzzy suggestions
 //! - `smart_chunker` - Intelligent code analysis
+//! - `chunking_rules` - Per-path chunking strategy overrides
+//! - `categorizer` - Scored chunk categories and category overrides
 //! - `graph` - Merkle DAG graph store for dependencies
 //! - `atomizer` - Language-aware AST parsing (Phase 1)
 //! - `rehydration` - Virtual view assembly (Phase 2)
+//! - `trust` - Chunk signing and the trust store
+//! - `stats` - Chunk, graph and deduplication statistics
+//! - `license` - License detection and combination for imported code
+//! - `code_metrics` - Complexity, nesting and comment metrics for chunks
+//! - `workspace` - Cargo, npm/yarn and pnpm workspace detection
 //!
 //! ## The Graph Store
 //!
@@ -22,7 +35,7 @@
 //!
 //! let store = GraphStore::open(".cadi/graph")?;
 //! store.insert_node(&node)?;
-//! let deps = store.get_dependencies("chunk:sha256:abc...")?;
+//! let deps = store.get_dependencies("chunk:sha256:abc...", None)?;
 //! ```
 //!
 //! ## The Atomizer
@@ -60,11 +73,16 @@
 
 pub mod chunk;
 pub mod manifest;
+pub mod manifest_upgrade;
+pub mod interpolation;
+pub mod lockfile;
 pub mod hash;
 pub mod error;
 
 pub use chunk::*;
 pub use manifest::*;
+pub use manifest_upgrade::{upgrade_manifest_source, ManifestUpgrade};
+pub use lockfile::{ChunkRef, LockedChunk, ManifestLock, LOCK_FILE};
 pub use hash::*;
 pub use error::*;
 
@@ -74,12 +92,30 @@ pub mod validator;
 
 // New atomic chunk and smart chunking system
 pub mod atomic;
+pub mod chunk_diff;
+pub mod alias_index;
 pub mod smart_chunker;
+pub mod chunking_rules;
+pub mod import_plan;
+pub mod categorizer;
 pub mod project_analyzer;
+pub mod manifest_generator;
+pub mod workspace;
+pub mod gitignore;
+
+// Chunk signing and trusted signer keys
+pub mod trust;
 
 pub use atomic::*;
+pub use chunk_diff::{unified_diff, ChunkDiff, LineRange, MetricDelta, SourceChange};
+pub use alias_index::{AliasIndex, AliasMatch, AliasResolution, AliasSuggestion, MatchKind};
 pub use smart_chunker::*;
+pub use chunking_rules::{ChunkingRule, ChunkingRules};
+pub use import_plan::{ImportPlan, IMPORT_PLAN_FILE};
+pub use categorizer::{CategoryOverrides, CategoryScore, CATEGORY_OVERRIDES_FILE};
 pub use project_analyzer::*;
+pub use manifest_generator::{toolchain_for, ManifestGenerator};
+pub use workspace::{Workspace, WorkspaceKind, WorkspaceMember};
 
 // Semantic hashing and deduplication (Stage 2)
 pub mod normalizer;
@@ -96,3 +132,18 @@ pub mod rehydration;
 
 // Phase 3: Ghost Import Resolver
 pub mod ghost;
+
+// Repository statistics
+pub mod stats;
+
+// Local cache bookkeeping for garbage collection
+pub mod local_cache;
+
+// License detection for imported code
+pub mod license;
+
+// Complexity and other source metrics for chunks
+pub mod code_metrics;
+
+// Token estimation for view and expansion budgets
+pub mod tokens;
@@ -0,0 +1,352 @@
+//! License detection for imported code
+//!
+//! A source file's license is, in order of precedence:
+//! - an `SPDX-License-Identifier:` line in its header
+//! - the license declared by the nearest enclosing directory, either in a
+//!   package manifest (`Cargo.toml`, `package.json`, `pyproject.toml`) or
+//!   in `LICENSE*` / `COPYING*` files
+//!
+//! Licenses are SPDX expressions. Code whose license cannot be determined
+//! is marked [`UNKNOWN_LICENSE`] rather than assumed to be permissive.
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+/// SPDX marker for a license that could not be determined
+pub const UNKNOWN_LICENSE: &str = "NOASSERTION";
+
+/// Lines of a file searched for an SPDX header
+const HEADER_LINES: usize = 20;
+
+/// Whether `license` names an actual license
+pub fn is_known(license: &str) -> bool {
+    let license = license.trim();
+    !license.is_empty() && license != UNKNOWN_LICENSE && license != "NONE"
+}
+
+/// License from an `SPDX-License-Identifier:` line near the top of a file
+pub fn spdx_from_header(content: &str) -> Option<String> {
+    content.lines().take(HEADER_LINES).find_map(|line| {
+        let (_, rest) = line.split_once("SPDX-License-Identifier:")?;
+        let expression = rest
+            .trim()
+            .trim_end_matches("*/")
+            .trim_end_matches("-->")
+            .trim();
+        (!expression.is_empty()).then(|| expression.to_string())
+    })
+}
+
+/// Identify a license from the text of a LICENSE or COPYING file
+pub fn identify_license_text(text: &str) -> Option<String> {
+    if let Some(expression) = spdx_from_header(text) {
+        return Some(expression);
+    }
+
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let upper = text.to_uppercase();
+    let version = |v: &str| upper.contains(&format!("VERSION {}", v));
+
+    let license = if upper.contains("APACHE LICENSE") && version("2.0") {
+        "Apache-2.0"
+    } else if upper.contains("MOZILLA PUBLIC LICENSE") && (version("2.0") || upper.contains("V. 2.0")) {
+        "MPL-2.0"
+    } else if upper.contains("GNU AFFERO GENERAL PUBLIC LICENSE") {
+        "AGPL-3.0-only"
+    } else if upper.contains("GNU LESSER GENERAL PUBLIC LICENSE") {
+        if version("2.1") { "LGPL-2.1-only" } else { "LGPL-3.0-only" }
+    } else if upper.contains("GNU GENERAL PUBLIC LICENSE") {
+        if version("3") { "GPL-3.0-only" } else { "GPL-2.0-only" }
+    } else if upper.contains("UNENCUMBERED SOFTWARE RELEASED INTO THE PUBLIC DOMAIN") {
+        "Unlicense"
+    } else if upper.contains("PERMISSION IS HEREBY GRANTED, FREE OF CHARGE") {
+        "MIT"
+    } else if upper.contains("PERMISSION TO USE, COPY, MODIFY, AND/OR DISTRIBUTE") {
+        "ISC"
+    } else if upper.contains("REDISTRIBUTION AND USE IN SOURCE AND BINARY FORMS") {
+        if upper.contains("NEITHER THE NAME") { "BSD-3-Clause" } else { "BSD-2-Clause" }
+    } else {
+        return None;
+    };
+    Some(license.to_string())
+}
+
+/// License declared by a package manifest in `dir`
+pub fn manifest_license(dir: &Path) -> Option<String> {
+    if let Ok(content) = std::fs::read_to_string(dir.join("Cargo.toml")) {
+        let manifest: toml::Value = toml::from_str(&content).ok()?;
+        // `license.workspace = true` is not a string, so the lookup falls
+        // through to the workspace manifest further up
+        let license = manifest
+            .get("package")
+            .and_then(|p| p.get("license"))
+            .and_then(|l| l.as_str())
+            .or_else(|| {
+                manifest
+                    .get("workspace")
+                    .and_then(|w| w.get("package"))
+                    .and_then(|p| p.get("license"))
+                    .and_then(|l| l.as_str())
+            });
+        if let Some(license) = license {
+            return Some(license.to_string());
+        }
+    }
+
+    if let Ok(content) = std::fs::read_to_string(dir.join("package.json")) {
+        let manifest: serde_json::Value = serde_json::from_str(&content).ok()?;
+        let name = |v: &serde_json::Value| {
+            v.as_str()
+                .or_else(|| v.get("type").and_then(|t| t.as_str()))
+                .map(str::to_string)
+        };
+        if let Some(license) = manifest.get("license").and_then(name) {
+            return Some(license);
+        }
+        // The deprecated `licenses` array lists alternatives
+        let licenses: Vec<String> = manifest
+            .get("licenses")
+            .and_then(|l| l.as_array())
+            .map(|l| l.iter().filter_map(name).collect())
+            .unwrap_or_default();
+        if !licenses.is_empty() {
+            return Some(licenses.join(" OR "));
+        }
+    }
+
+    if let Ok(content) = std::fs::read_to_string(dir.join("pyproject.toml")) {
+        let manifest: toml::Value = toml::from_str(&content).ok()?;
+        let license = manifest
+            .get("project")
+            .and_then(|p| p.get("license"))
+            .or_else(|| manifest.get("tool")?.get("poetry")?.get("license"))?;
+        if let Some(license) = license.as_str() {
+            return Some(license.to_string());
+        }
+        // `license = { text = "..." }`
+        if let Some(text) = license.get("text").and_then(|t| t.as_str()) {
+            return Some(text.to_string());
+        }
+    }
+
+    None
+}
+
+/// License from the `LICENSE*` and `COPYING*` files in `dir`. Several
+/// license files (`LICENSE-MIT`, `LICENSE-APACHE`) offer a choice.
+pub fn license_files_license(dir: &Path) -> Option<String> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            let name = p
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("")
+                .to_uppercase();
+            p.is_file() && (name.starts_with("LICENSE") || name.starts_with("LICENCE") || name.starts_with("COPYING"))
+        })
+        .collect();
+    files.sort();
+
+    let mut licenses: Vec<String> = Vec::new();
+    for file in files {
+        let Ok(text) = std::fs::read_to_string(&file) else {
+            continue;
+        };
+        if let Some(license) = identify_license_text(&text) {
+            if !licenses.contains(&license) {
+                licenses.push(license);
+            }
+        }
+    }
+
+    match licenses.len() {
+        0 => None,
+        1 => licenses.pop(),
+        _ => Some(
+            licenses
+                .iter()
+                .map(|l| parenthesize(l))
+                .collect::<Vec<_>>()
+                .join(" OR "),
+        ),
+    }
+}
+
+/// Combined license of code made from parts under `licenses`: all of them
+/// apply. Any unknown part makes the whole unknown.
+pub fn combine_licenses<'a>(licenses: impl IntoIterator<Item = &'a str>) -> String {
+    let mut terms = BTreeSet::new();
+    for license in licenses {
+        if !is_known(license) {
+            return UNKNOWN_LICENSE.to_string();
+        }
+        for term in split_and(license.trim()) {
+            terms.insert(term);
+        }
+    }
+
+    match terms.len() {
+        0 => UNKNOWN_LICENSE.to_string(),
+        1 => terms.into_iter().next().unwrap_or_default(),
+        _ => terms
+            .iter()
+            .map(|t| parenthesize(t))
+            .collect::<Vec<_>>()
+            .join(" AND "),
+    }
+}
+
+/// Top-level `AND` terms of an expression
+fn split_and(expression: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut depth = 0i32;
+    let mut term = String::new();
+    for word in expression.split_whitespace() {
+        if word == "AND" && depth == 0 {
+            terms.push(std::mem::take(&mut term));
+            continue;
+        }
+        depth += word.matches('(').count() as i32 - word.matches(')').count() as i32;
+        if !term.is_empty() {
+            term.push(' ');
+        }
+        term.push_str(word);
+    }
+    terms.push(term);
+
+    terms
+        .into_iter()
+        .filter(|t| !t.is_empty())
+        .map(|t| strip_parens(&t).to_string())
+        .collect()
+}
+
+/// `(A OR B)` -> `A OR B`, when the parentheses enclose the whole term
+fn strip_parens(term: &str) -> &str {
+    let Some(inner) = term.strip_prefix('(').and_then(|t| t.strip_suffix(')')) else {
+        return term;
+    };
+    let mut depth = 0i32;
+    for c in inner.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        if depth < 0 {
+            return term;
+        }
+    }
+    inner
+}
+
+fn parenthesize(term: &str) -> String {
+    if term.contains(" OR ") {
+        format!("({})", term)
+    } else {
+        term.to_string()
+    }
+}
+
+/// Detects the license of files under a project root, caching what each
+/// directory declares
+pub struct LicenseDetector {
+    root: PathBuf,
+    dirs: HashMap<PathBuf, Option<String>>,
+}
+
+impl LicenseDetector {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            dirs: HashMap::new(),
+        }
+    }
+
+    /// License of the file at `path` with the given content
+    pub fn detect(&mut self, path: &Path, content: &str) -> String {
+        spdx_from_header(content)
+            .or_else(|| path.parent().and_then(|dir| self.dir_license(dir)))
+            .unwrap_or_else(|| UNKNOWN_LICENSE.to_string())
+    }
+
+    /// License declared by `dir` or its nearest ancestor within the root
+    pub fn dir_license(&mut self, dir: &Path) -> Option<String> {
+        if let Some(cached) = self.dirs.get(dir) {
+            return cached.clone();
+        }
+
+        let license = manifest_license(dir)
+            .or_else(|| license_files_license(dir))
+            .or_else(|| {
+                if dir == self.root || !dir.starts_with(&self.root) {
+                    None
+                } else {
+                    dir.parent().and_then(|parent| self.dir_license(parent))
+                }
+            });
+
+        self.dirs.insert(dir.to_path_buf(), license.clone());
+        license
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spdx_header() {
+        let content = "// SPDX-License-Identifier: Apache-2.0\nfn main() {}\n";
+        assert_eq!(spdx_from_header(content).as_deref(), Some("Apache-2.0"));
+        let content = "/* SPDX-License-Identifier: MIT OR Apache-2.0 */\n";
+        assert_eq!(spdx_from_header(content).as_deref(), Some("MIT OR Apache-2.0"));
+        assert_eq!(spdx_from_header("fn main() {}\n"), None);
+    }
+
+    #[test]
+    fn test_combine_licenses() {
+        assert_eq!(combine_licenses(["MIT", "MIT"]), "MIT");
+        assert_eq!(combine_licenses(["MIT", "Apache-2.0"]), "Apache-2.0 AND MIT");
+        assert_eq!(
+            combine_licenses(["MIT OR Apache-2.0", "BSD-3-Clause AND MIT"]),
+            "BSD-3-Clause AND MIT AND (MIT OR Apache-2.0)"
+        );
+        assert_eq!(combine_licenses(["MIT", UNKNOWN_LICENSE]), UNKNOWN_LICENSE);
+        assert_eq!(combine_licenses([]), UNKNOWN_LICENSE);
+    }
+
+    #[test]
+    fn test_detector_prefers_nearest_declaration() {
+        let root = std::env::temp_dir().join(format!("cadi-license-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("vendor/lib")).unwrap();
+        std::fs::write(
+            root.join("Cargo.toml"),
+            "[package]\nname = \"x\"\nversion = \"0.1.0\"\nlicense = \"MIT\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("vendor/COPYING"),
+            "GNU GENERAL PUBLIC LICENSE\nVersion 3, 29 June 2007\n",
+        )
+        .unwrap();
+
+        let mut detector = LicenseDetector::new(&root);
+        assert_eq!(detector.detect(&root.join("src/lib.rs"), "fn a() {}"), "MIT");
+        assert_eq!(
+            detector.detect(&root.join("vendor/lib/a.c"), "int a;"),
+            "GPL-3.0-only"
+        );
+        assert_eq!(
+            detector.detect(&root.join("src/b.rs"), "// SPDX-License-Identifier: Apache-2.0\n"),
+            "Apache-2.0"
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
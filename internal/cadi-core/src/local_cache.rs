@@ -0,0 +1,436 @@
+//! Local chunk cache bookkeeping for garbage collection
+//!
+//! The local cache holds chunks written by several commands under
+//! `<cache>/chunks`: fetched chunks (`<hash>.bin` with a `<hash>.json`
+//! metadata file), build cache entries (`<hash[..2]>/<hash>`) and imported
+//! chunks (`<hash[..16]>.json`). [`LocalCache`] groups these files by chunk
+//! and plans least-recently-used eviction around pinned chunks.
+//!
+//! Access times live in a sidecar file, [`ACCESS_TIMES_FILE`], rather than
+//! in filesystem atime, which is often disabled or coarse.
+
+use crate::atomic::chunk_references;
+use crate::error::CadiResult;
+use crate::hash::ChunkId;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Location of the last-access sidecar, relative to the cache directory
+pub const ACCESS_TIMES_FILE: &str = "access.json";
+
+/// Location of a project's pins, relative to the project root
+pub const PINS_FILE: &str = ".cadi/pins.json";
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Write `contents` to `path` through a temporary file, so readers never see
+/// a partial file
+fn write_atomic(path: &Path, contents: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path)
+}
+
+/// Last-access times of cached chunks, in seconds since the Unix epoch
+#[derive(Debug, Clone, Default)]
+pub struct AccessTimes {
+    path: PathBuf,
+    times: HashMap<String, u64>,
+}
+
+impl AccessTimes {
+    /// Load the access times of the cache at `cache_dir`. A missing or
+    /// unreadable sidecar starts empty.
+    pub fn open(cache_dir: &Path) -> Self {
+        let path = cache_dir.join(ACCESS_TIMES_FILE);
+        let times = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self { path, times }
+    }
+
+    /// Mark `chunk_id` as used now
+    pub fn touch(&mut self, chunk_id: &str) {
+        self.times.insert(chunk_id.to_string(), now_secs());
+    }
+
+    pub fn last_access(&self, chunk_id: &str) -> Option<u64> {
+        self.times.get(chunk_id).copied()
+    }
+
+    pub fn remove(&mut self, chunk_id: &str) {
+        self.times.remove(chunk_id);
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let json = serde_json::to_string(&self.times)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        write_atomic(&self.path, &json)
+    }
+
+    /// Record a use of `chunk_id` in the cache at `cache_dir`. Failures only
+    /// cost LRU accuracy, so they are logged rather than returned.
+    pub fn record(cache_dir: &Path, chunk_id: &str) {
+        let mut times = Self::open(cache_dir);
+        times.touch(chunk_id);
+        if let Err(e) = times.save() {
+            tracing::debug!("Failed to record access to {}: {}", chunk_id, e);
+        }
+    }
+}
+
+/// Chunks a project has pinned against garbage collection
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PinSet {
+    pub pins: BTreeSet<String>,
+}
+
+impl PinSet {
+    /// Load the pins of the project at `root`; no pins file means no pins
+    pub fn load(root: &Path) -> CadiResult<Self> {
+        match fs::read_to_string(root.join(PINS_FILE)) {
+            Ok(content) => Ok(serde_json::from_str(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, root: &Path) -> CadiResult<()> {
+        write_atomic(&root.join(PINS_FILE), &serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Returns whether the chunk was not pinned yet
+    pub fn pin(&mut self, chunk_id: &str) -> bool {
+        self.pins.insert(chunk_id.to_string())
+    }
+
+    /// Returns whether the chunk was pinned
+    pub fn unpin(&mut self, chunk_id: &str) -> bool {
+        self.pins.remove(chunk_id)
+    }
+
+    pub fn contains(&self, chunk_id: &str) -> bool {
+        self.pins.contains(chunk_id)
+    }
+}
+
+/// One chunk in the local cache and the files that hold it
+#[derive(Debug, Clone)]
+pub struct CachedChunk {
+    pub chunk_id: String,
+    pub files: Vec<PathBuf>,
+    /// File holding the chunk's content, if any
+    pub content: Option<PathBuf>,
+    /// Total size of `files` in bytes
+    pub size: u64,
+    /// Seconds since the Unix epoch; the newest file mtime when the chunk
+    /// has no recorded access
+    pub last_access: u64,
+}
+
+/// How much of the cache garbage collection may remove
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GcPolicy {
+    /// Remove least-recently-used chunks until the cache is at most this
+    /// many bytes
+    MaxSize(u64),
+    /// Remove every unprotected chunk
+    All,
+}
+
+/// Chunks a garbage collection run would remove
+#[derive(Debug, Clone, Default)]
+pub struct GcPlan {
+    /// Chunks to remove, least recently used first
+    pub evict: Vec<CachedChunk>,
+    /// Chunks kept because they are pinned or a pinned chunk references them
+    pub protected: Vec<String>,
+    /// Cache size before collection
+    pub total_size: u64,
+    /// Cache size after removing `evict`
+    pub size_after: u64,
+}
+
+impl GcPlan {
+    pub fn reclaimed(&self) -> u64 {
+        self.total_size - self.size_after
+    }
+}
+
+/// The local chunk cache at a cache directory
+#[derive(Debug, Clone)]
+pub struct LocalCache {
+    dir: PathBuf,
+}
+
+impl LocalCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn chunks_dir(&self) -> PathBuf {
+        self.dir.join("chunks")
+    }
+
+    /// Every chunk in the cache, ordered by chunk ID
+    pub fn scan(&self) -> CadiResult<Vec<CachedChunk>> {
+        let access = AccessTimes::open(&self.dir);
+        let mut chunks: BTreeMap<String, CachedChunk> = BTreeMap::new();
+        let chunks_dir = self.chunks_dir();
+        if !chunks_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut add = |chunk_id: String, path: PathBuf, is_content: bool| -> CadiResult<()> {
+            let metadata = fs::metadata(&path)?;
+            let mtime = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            let chunk = chunks.entry(chunk_id.clone()).or_insert_with(|| CachedChunk {
+                chunk_id,
+                files: Vec::new(),
+                content: None,
+                size: 0,
+                last_access: 0,
+            });
+            chunk.size += metadata.len();
+            chunk.last_access = chunk.last_access.max(mtime);
+            if is_content && chunk.content.is_none() {
+                chunk.content = Some(path.clone());
+            }
+            chunk.files.push(path);
+            Ok(())
+        };
+
+        for entry in fs::read_dir(&chunks_dir)? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string();
+
+            if path.is_dir() {
+                // Build cache: <hash[..2]>/<hash>
+                if name.len() != 2 {
+                    continue;
+                }
+                for file in fs::read_dir(&path)? {
+                    let file = file?.path();
+                    if let Some(hash) = file.file_name().and_then(|n| n.to_str()) {
+                        add(ChunkId::from_storage_key(hash).to_string(), file.clone(), true)?;
+                    }
+                }
+                continue;
+            }
+
+            let (stem, extension) = match name.rsplit_once('.') {
+                Some((stem, extension)) => (stem.to_string(), extension),
+                None => continue,
+            };
+            match extension {
+                "bin" => add(ChunkId::from_storage_key(&stem).to_string(), path, true)?,
+                "json" if name != "aliases.json" => {
+                    if chunks_dir.join(format!("{}.bin", stem)).exists() {
+                        // Metadata of a fetched chunk
+                        add(ChunkId::from_storage_key(&stem).to_string(), path, false)?;
+                    } else if let Some(chunk_id) = imported_chunk_id(&path) {
+                        add(chunk_id, path, true)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(chunks
+            .into_values()
+            .map(|mut chunk| {
+                if let Some(time) = access.last_access(&chunk.chunk_id) {
+                    chunk.last_access = time;
+                }
+                chunk
+            })
+            .collect())
+    }
+
+    /// Plan a collection that keeps every chunk in `pins` and every cached
+    /// chunk they transitively reference
+    pub fn plan_gc(&self, pins: &PinSet, policy: GcPolicy) -> CadiResult<GcPlan> {
+        let chunks = self.scan()?;
+        let protected = protected_chunks(&chunks, pins);
+        let total_size: u64 = chunks.iter().map(|c| c.size).sum();
+
+        let mut candidates: Vec<CachedChunk> = chunks
+            .into_iter()
+            .filter(|c| !protected.contains(&c.chunk_id))
+            .collect();
+        candidates.sort_by(|a, b| {
+            a.last_access
+                .cmp(&b.last_access)
+                .then_with(|| a.chunk_id.cmp(&b.chunk_id))
+        });
+
+        let mut size_after = total_size;
+        let mut evict = Vec::new();
+        for chunk in candidates {
+            if let GcPolicy::MaxSize(max) = policy {
+                if size_after <= max {
+                    break;
+                }
+            }
+            size_after -= chunk.size;
+            evict.push(chunk);
+        }
+
+        let mut protected: Vec<String> = protected.into_iter().collect();
+        protected.sort();
+        Ok(GcPlan {
+            evict,
+            protected,
+            total_size,
+            size_after,
+        })
+    }
+
+    /// Delete the files of `chunks` and forget their access times. Returns
+    /// the number of bytes freed.
+    pub fn remove(&self, chunks: &[CachedChunk]) -> CadiResult<u64> {
+        let mut access = AccessTimes::open(&self.dir);
+        let mut freed = 0;
+        for chunk in chunks {
+            for file in &chunk.files {
+                let size = fs::metadata(file).map(|m| m.len()).unwrap_or(0);
+                match fs::remove_file(file) {
+                    Ok(()) => freed += size,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            access.remove(&chunk.chunk_id);
+        }
+        access.save()?;
+        Ok(freed)
+    }
+}
+
+/// Chunk ID recorded in an imported chunk's JSON
+fn imported_chunk_id(path: &Path) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    let value: serde_json::Value = serde_json::from_str(&content).ok()?;
+    value["chunk_id"].as_str().map(String::from)
+}
+
+/// `pins` and every cached chunk reachable from them through composition
+/// references
+fn protected_chunks(chunks: &[CachedChunk], pins: &PinSet) -> HashSet<String> {
+    let by_id: HashMap<&str, &CachedChunk> = chunks.iter().map(|c| (c.chunk_id.as_str(), c)).collect();
+    let mut protected: HashSet<String> = pins.pins.iter().cloned().collect();
+    let mut queue: VecDeque<String> = pins.pins.iter().cloned().collect();
+
+    while let Some(chunk_id) = queue.pop_front() {
+        let Some(content) = by_id.get(chunk_id.as_str()).and_then(|c| c.content.as_ref()) else {
+            continue;
+        };
+        let Ok(data) = fs::read(content) else {
+            continue;
+        };
+        for reference in chunk_references(&data) {
+            if protected.insert(reference.clone()) {
+                queue.push_back(reference);
+            }
+        }
+    }
+
+    protected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn write_chunk(dir: &Path, hash: &str, deps: &[&str], last_access: u64, access: &mut AccessTimes) {
+        let composed_of: Vec<_> = deps.iter().map(|d| json!({"chunk_id": d})).collect();
+        let data = json!({"chunk_id": format!("chunk:sha256:{}", hash), "composition": {"composed_of": composed_of}});
+        fs::write(dir.join("chunks").join(format!("{}.bin", hash)), data.to_string()).unwrap();
+        access.times.insert(format!("chunk:sha256:{}", hash), last_access);
+    }
+
+    #[test]
+    fn test_lru_eviction_keeps_pinned_closure() {
+        let dir = std::env::temp_dir().join(format!("cadi-local-cache-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("chunks")).unwrap();
+
+        let mut access = AccessTimes::open(&dir);
+        // app -> lib -> util; the rest are unreferenced
+        write_chunk(&dir, "app", &["chunk:sha256:lib"], 10, &mut access);
+        write_chunk(&dir, "lib", &["chunk:sha256:util"], 1, &mut access);
+        write_chunk(&dir, "util", &["chunk:sha256:app"], 2, &mut access);
+        write_chunk(&dir, "old", &[], 3, &mut access);
+        write_chunk(&dir, "new", &[], 20, &mut access);
+        write_chunk(&dir, "mid", &[], 5, &mut access);
+        access.save().unwrap();
+        fs::write(dir.join("chunks/mid.json"), b"{\"chunk_id\": \"chunk:sha256:mid\"}").unwrap();
+
+        let cache = LocalCache::new(&dir);
+        let chunks = cache.scan().unwrap();
+        assert_eq!(chunks.len(), 6);
+        let mid = chunks.iter().find(|c| c.chunk_id == "chunk:sha256:mid").unwrap();
+        assert_eq!(mid.files.len(), 2);
+        assert_eq!(mid.last_access, 5);
+
+        let mut pins = PinSet::default();
+        pins.pin("chunk:sha256:app");
+
+        // Only unpinned chunks, least recently used first, until under the target
+        let size = |id: &str| chunks.iter().find(|c| c.chunk_id == id).unwrap().size;
+        let target = size("chunk:sha256:app") + size("chunk:sha256:lib") + size("chunk:sha256:util") + size("chunk:sha256:new");
+        let plan = cache.plan_gc(&pins, GcPolicy::MaxSize(target)).unwrap();
+        let evicted: Vec<&str> = plan.evict.iter().map(|c| c.chunk_id.as_str()).collect();
+        assert_eq!(evicted, ["chunk:sha256:old", "chunk:sha256:mid"]);
+        assert_eq!(plan.protected, ["chunk:sha256:app", "chunk:sha256:lib", "chunk:sha256:util"]);
+        assert_eq!(plan.size_after, target);
+
+        let everything = cache.plan_gc(&pins, GcPolicy::All).unwrap();
+        assert_eq!(everything.evict.len(), 3);
+
+        let freed = cache.remove(&plan.evict).unwrap();
+        assert_eq!(freed, plan.reclaimed());
+        assert!(!dir.join("chunks/mid.json").exists());
+        assert_eq!(AccessTimes::open(&dir).last_access("chunk:sha256:old"), None);
+        assert_eq!(cache.scan().unwrap().len(), 4);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_pins_round_trip() {
+        let root = std::env::temp_dir().join(format!("cadi-pins-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        assert!(PinSet::load(&root).unwrap().pins.is_empty());
+        let mut pins = PinSet::default();
+        assert!(pins.pin("chunk:sha256:a"));
+        assert!(!pins.pin("chunk:sha256:a"));
+        pins.save(&root).unwrap();
+
+        let mut loaded = PinSet::load(&root).unwrap();
+        assert!(loaded.contains("chunk:sha256:a"));
+        assert!(loaded.unpin("chunk:sha256:a"));
+        assert!(!loaded.unpin("chunk:sha256:a"));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
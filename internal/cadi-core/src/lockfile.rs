@@ -0,0 +1,292 @@
+//! Chunk references and the manifest lockfile
+//!
+//! A build graph node may name its source by [`ChunkRef`] (`mylib@^1.2`)
+//! instead of a chunk ID. The reference is resolved against the registry at
+//! build time and the chosen version is recorded in `cadi.lock`, so later
+//! builds use the same chunk until the reference changes.
+
+use crate::error::{CadiError, CadiResult};
+use crate::manifest::Manifest;
+use crate::validator::{codes, Diagnostic};
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+/// File name of the lockfile, next to the manifest
+pub const LOCK_FILE: &str = "cadi.lock";
+
+/// Current lockfile format version
+pub const LOCK_FILE_VERSION: u32 = 1;
+
+/// A chunk name with a semver range
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkRef {
+    pub name: String,
+    pub req: VersionReq,
+}
+
+impl ChunkRef {
+    /// Parse `name@range`
+    pub fn parse(s: &str) -> CadiResult<Self> {
+        let invalid = |reason: String| {
+            CadiError::DependencyResolution(format!("Invalid chunk reference '{}': {}", s, reason))
+        };
+
+        let (name, range) = s
+            .trim()
+            .rsplit_once('@')
+            .ok_or_else(|| invalid("expected name@range".to_string()))?;
+        if name.is_empty() {
+            return Err(invalid("missing chunk name".to_string()));
+        }
+        let req = VersionReq::parse(range).map_err(|e| invalid(e.to_string()))?;
+
+        Ok(Self {
+            name: name.to_string(),
+            req,
+        })
+    }
+
+    /// Newest `(version, chunk_id)` candidate satisfying the range.
+    /// Candidates whose version does not parse are ignored.
+    pub fn select<I>(&self, candidates: I) -> Option<LockedChunk>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        candidates
+            .into_iter()
+            .filter_map(|(version, chunk_id)| Some((Version::parse(&version).ok()?, chunk_id)))
+            .filter(|(version, _)| self.req.matches(version))
+            .max_by(|a, b| a.0.cmp(&b.0).then_with(|| b.1.cmp(&a.1)))
+            .map(|(version, chunk_id)| LockedChunk {
+                chunk_ref: self.to_string(),
+                version: version.to_string(),
+                chunk_id,
+            })
+    }
+}
+
+impl fmt::Display for ChunkRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.name, self.req)
+    }
+}
+
+/// A resolved chunk reference
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedChunk {
+    /// Reference as written in the manifest
+    pub chunk_ref: String,
+    /// Version the reference resolved to
+    pub version: String,
+    pub chunk_id: String,
+}
+
+/// Resolved chunk references of a manifest, keyed by build graph node ID
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestLock {
+    #[serde(default = "default_lock_version")]
+    pub version: u32,
+    #[serde(default)]
+    pub chunks: BTreeMap<String, LockedChunk>,
+}
+
+fn default_lock_version() -> u32 {
+    LOCK_FILE_VERSION
+}
+
+impl Default for ManifestLock {
+    fn default() -> Self {
+        Self {
+            version: LOCK_FILE_VERSION,
+            chunks: BTreeMap::new(),
+        }
+    }
+}
+
+impl ManifestLock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read a lockfile, rejecting formats newer than this build understands
+    pub fn load(path: &Path) -> CadiResult<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let lock: Self = serde_json::from_str(&content)
+            .map_err(|e| CadiError::Serialization(format!("{}: {}", path.display(), e)))?;
+        if lock.version > LOCK_FILE_VERSION {
+            return Err(CadiError::Configuration(format!(
+                "{} has lockfile version {}, newer than the supported version {}",
+                path.display(),
+                lock.version,
+                LOCK_FILE_VERSION
+            )));
+        }
+        Ok(lock)
+    }
+
+    pub fn save(&self, path: &Path) -> CadiResult<()> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| CadiError::Serialization(e.to_string()))?;
+        std::fs::write(path, content + "\n")?;
+        Ok(())
+    }
+
+    /// Differences between the lockfile and the chunk references in a
+    /// manifest. An empty result means the lockfile can be used as is.
+    pub fn check(&self, manifest: &Manifest) -> Vec<Diagnostic> {
+        let mut errors = Vec::new();
+
+        for (i, node) in manifest.build_graph.nodes.iter().enumerate() {
+            let Some(chunk_ref) = &node.chunk_ref else {
+                continue;
+            };
+            let path = format!("build_graph.nodes[{}].chunk_ref", i);
+            let mut error = |message: String| errors.push(Diagnostic::error(codes::STALE_LOCKFILE, message, path.clone()));
+
+            let Some(locked) = self.chunks.get(&node.id) else {
+                error(format!("Node '{}' is not in {}", node.id, LOCK_FILE));
+                continue;
+            };
+            let Ok(parsed) = ChunkRef::parse(chunk_ref) else {
+                // Reported by the manifest validator
+                continue;
+            };
+            if ChunkRef::parse(&locked.chunk_ref).ok().as_ref() != Some(&parsed) {
+                error(format!(
+                    "Node '{}' references {} but {} locks {}",
+                    node.id, chunk_ref, LOCK_FILE, locked.chunk_ref
+                ));
+            } else if !Version::parse(&locked.version).is_ok_and(|v| parsed.req.matches(&v)) {
+                error(format!(
+                    "Locked version {} of node '{}' does not satisfy {}",
+                    locked.version, node.id, chunk_ref
+                ));
+            }
+        }
+
+        for node_id in self.chunks.keys() {
+            let referenced = manifest
+                .find_node(node_id)
+                .is_some_and(|node| node.chunk_ref.is_some());
+            if !referenced {
+                errors.push(Diagnostic::error(
+                    codes::STALE_LOCKFILE,
+                    format!("{} locks node '{}', which has no chunk_ref", LOCK_FILE, node_id),
+                    LOCK_FILE,
+                ));
+            }
+        }
+
+        errors
+    }
+
+    /// Drop entries that no longer match the manifest, keeping those that
+    /// do. Returns the number of entries removed.
+    pub fn prune(&mut self, manifest: &Manifest) -> usize {
+        let before = self.chunks.len();
+        self.chunks.retain(|node_id, locked| {
+            manifest
+                .find_node(node_id)
+                .and_then(|node| node.chunk_ref.as_deref())
+                .and_then(|chunk_ref| ChunkRef::parse(chunk_ref).ok())
+                .is_some_and(|parsed| {
+                    ChunkRef::parse(&locked.chunk_ref).ok().as_ref() == Some(&parsed)
+                        && Version::parse(&locked.version).is_ok_and(|v| parsed.req.matches(&v))
+                })
+        });
+        before - self.chunks.len()
+    }
+
+    /// Nodes with a chunk reference and no lock entry
+    pub fn unresolved<'a>(&self, manifest: &'a Manifest) -> Vec<(&'a str, &'a str)> {
+        manifest
+            .build_graph
+            .nodes
+            .iter()
+            .filter(|node| !self.chunks.contains_key(&node.id))
+            .filter_map(|node| Some((node.id.as_str(), node.chunk_ref.as_deref()?)))
+            .collect()
+    }
+
+    /// Point every referencing node's `source_cadi` at its locked chunk
+    pub fn apply(&self, manifest: &mut Manifest) -> CadiResult<()> {
+        for node in &mut manifest.build_graph.nodes {
+            if node.chunk_ref.is_none() {
+                continue;
+            }
+            let locked = self.chunks.get(&node.id).ok_or_else(|| {
+                CadiError::DependencyResolution(format!("Node '{}' is not in {}", node.id, LOCK_FILE))
+            })?;
+            node.source_cadi = Some(locked.chunk_id.clone());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(chunk_ref: &str) -> Manifest {
+        serde_json::from_value(serde_json::json!({
+            "manifest_id": "app",
+            "manifest_version": "1.0",
+            "application": { "name": "app" },
+            "build_graph": { "nodes": [{ "id": "lib", "chunk_ref": chunk_ref }] },
+            "build_targets": [],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_select_newest_matching_version() {
+        let chunk_ref = ChunkRef::parse("mylib@^1.2").unwrap();
+        assert_eq!(chunk_ref.name, "mylib");
+
+        let locked = chunk_ref
+            .select([
+                ("1.1.9".to_string(), "chunk:a".to_string()),
+                ("1.2.0".to_string(), "chunk:b".to_string()),
+                ("1.4.1".to_string(), "chunk:c".to_string()),
+                ("2.0.0".to_string(), "chunk:d".to_string()),
+                ("latest".to_string(), "chunk:e".to_string()),
+            ])
+            .unwrap();
+        assert_eq!(locked.version, "1.4.1");
+        assert_eq!(locked.chunk_id, "chunk:c");
+
+        assert!(ChunkRef::parse("mylib").is_err());
+        assert!(ChunkRef::parse("mylib@not-a-range").is_err());
+    }
+
+    #[test]
+    fn test_lock_consistency_with_manifest() {
+        let mut lock = ManifestLock::new();
+        let pinned = manifest("mylib@^1.2");
+        assert_eq!(lock.unresolved(&pinned), vec![("lib", "mylib@^1.2")]);
+        assert_eq!(lock.check(&pinned).len(), 1);
+
+        lock.chunks.insert(
+            "lib".to_string(),
+            LockedChunk {
+                chunk_ref: "mylib@^1.2".to_string(),
+                version: "1.4.1".to_string(),
+                chunk_id: "chunk:c".to_string(),
+            },
+        );
+        assert!(lock.check(&pinned).is_empty());
+
+        let mut applied = pinned.clone();
+        lock.apply(&mut applied).unwrap();
+        assert_eq!(applied.build_graph.nodes[0].source_cadi.as_deref(), Some("chunk:c"));
+
+        // Changing the range invalidates the entry
+        let bumped = manifest("mylib@^2");
+        assert_eq!(lock.check(&bumped).len(), 1);
+        assert_eq!(lock.prune(&bumped), 1);
+        assert!(lock.chunks.is_empty());
+    }
+}
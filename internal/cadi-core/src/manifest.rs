@@ -1,17 +1,33 @@
 //! Manifest types for CADI application build graphs
 
-use serde::{Deserialize, Serialize};
+use crate::validator::{codes, Diagnostic};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+
+/// Version of the manifest format that new manifests are written in
+pub const CURRENT_CADL_VERSION: &str = "2";
+
+/// Version assumed for manifests that do not declare `cadl_version`
+pub const LEGACY_CADL_VERSION: &str = "1";
+
+/// Every manifest format version this build understands, oldest first
+pub const SUPPORTED_CADL_VERSIONS: &[&str] = &["1", "2"];
 
 /// Application manifest
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Manifest {
+    /// Version of the manifest format; `cadi validate --upgrade` rewrites
+    /// older manifests to [`CURRENT_CADL_VERSION`]
+    #[serde(default = "legacy_cadl_version", deserialize_with = "deserialize_cadl_version")]
+    pub cadl_version: String,
     pub manifest_id: String,
     pub manifest_version: String,
     pub application: ApplicationInfo,
     pub build_graph: BuildGraph,
     #[serde(default)]
     pub build_targets: Vec<BuildTarget>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// `trust_defaults` before cadl_version 2
+    #[serde(rename = "trust", alias = "trust_defaults", skip_serializing_if = "Option::is_none")]
     pub trust_defaults: Option<TrustRequirements>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dependencies: Option<DependencyConfig>,
@@ -31,6 +47,9 @@ pub struct ApplicationInfo {
     pub license: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub repository: Option<String>,
+    /// Alias namespace for chunks imported from this project
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
 }
 
 /// Build graph structure
@@ -45,6 +64,10 @@ pub struct BuildGraph {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GraphNode {
     pub id: String,
+    /// Registry chunk and semver range (`mylib@^1.2`), resolved through
+    /// `cadi.lock` into `source_cadi` at build time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_ref: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub source_cadi: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -55,7 +78,8 @@ pub struct GraphNode {
     pub container_cadi: Option<String>,
     #[serde(default)]
     pub representations: Vec<Representation>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// `selection_strategy` before cadl_version 2
+    #[serde(rename = "selection", alias = "selection_strategy", skip_serializing_if = "Option::is_none")]
     pub selection_strategy: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub materialization: Option<Materialization>,
@@ -113,6 +137,59 @@ pub struct BuildTarget {
     pub deploy: Option<DeployConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub trust_requirements: Option<TrustRequirements>,
+    /// Transforms run on each node's build output, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transforms: Vec<TransformSpec>,
+    /// Tools the target's nodes are built with (`rustc`, `node`, ...), as
+    /// named in build receipts
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub toolchain: Vec<String>,
+}
+
+/// A transform a build target runs between two representations, either a
+/// builtin or an external command.
+///
+/// A transform applies to every built node whose output so far is in its
+/// `input` representation; its output is then in the `output`
+/// representation, so transforms chain. An external command reads the
+/// artifact from `{input}` and writes it to `{output}`, or to stdout when
+/// its arguments don't name `{output}`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransformSpec {
+    /// Builtin transform (`parse`, `compile`, `link`, `bundle`,
+    /// `containerize`, or a custom transform name)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub builtin: Option<String>,
+    /// Arguments of a builtin transform
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub args: BTreeMap<String, String>,
+    /// External command and its arguments
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub command: Vec<String>,
+    /// Representation consumed (`ir`, `blob`, ...)
+    pub input: String,
+    /// Representation produced
+    pub output: String,
+    /// Environment variables passed to the command; the rest are cleared
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env: Vec<String>,
+    /// Exit code of a successful run
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub exit_code: i32,
+}
+
+fn is_zero(code: &i32) -> bool {
+    *code == 0
+}
+
+impl TransformSpec {
+    /// Name of the builtin or program
+    pub fn label(&self) -> &str {
+        match &self.builtin {
+            Some(builtin) => builtin,
+            None => self.command.first().map_or("transform", String::as_str),
+        }
+    }
 }
 
 /// Trust requirements for a build target
@@ -181,7 +258,8 @@ pub struct DeployConfig {
 pub struct DependencyConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub lock_file: Option<String>,
-    #[serde(default = "default_resolution_strategy")]
+    /// `resolution_strategy` before cadl_version 2
+    #[serde(rename = "resolution", alias = "resolution_strategy", default = "default_resolution_strategy")]
     pub resolution_strategy: String,
 }
 
@@ -189,19 +267,49 @@ fn default_resolution_strategy() -> String {
     "newest".to_string()
 }
 
+fn legacy_cadl_version() -> String {
+    LEGACY_CADL_VERSION.to_string()
+}
+
+/// `cadl_version` as a string, also when written as a bare number
+fn deserialize_cadl_version<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Version {
+        Text(String),
+        Number(u64),
+    }
+    Ok(match Version::deserialize(deserializer)? {
+        Version::Text(version) => version,
+        Version::Number(version) => version.to_string(),
+    })
+}
+
+/// Whether manifests of `version` follow the rules introduced in `since`.
+/// Versions are whole numbers; anything else sorts before every version.
+pub fn cadl_version_at_least(version: &str, since: &str) -> bool {
+    let number = |v: &str| v.trim().parse::<u64>().ok();
+    match (number(version), number(since)) {
+        (Some(version), Some(since)) => version >= since,
+        _ => false,
+    }
+}
+
 impl Manifest {
     /// Create a new manifest with the given ID and application name
     pub fn new(manifest_id: String, app_name: String) -> Self {
         Self {
+            cadl_version: CURRENT_CADL_VERSION.to_string(),
             manifest_id,
             manifest_version: "1.0".to_string(),
             application: ApplicationInfo {
                 name: app_name,
                 description: None,
-                version: None,
+                version: Some("0.1.0".to_string()),
                 authors: Vec::new(),
                 license: None,
                 repository: None,
+                namespace: None,
             },
             build_graph: BuildGraph {
                 nodes: Vec::new(),
@@ -233,6 +341,24 @@ impl Manifest {
         self.build_graph.nodes.iter().find(|n| n.id == id)
     }
 
+    /// Substitute `${env:NAME:-default}` in every string field from the
+    /// process environment
+    pub fn interpolate_env(&self) -> Result<Manifest, Vec<Diagnostic>> {
+        self.interpolate_with(&|name| std::env::var(name).ok())
+    }
+
+    /// Substitute `${env:NAME:-default}` in every string field, reporting
+    /// each field that does not resolve
+    pub fn interpolate_with(
+        &self,
+        lookup: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<Manifest, Vec<Diagnostic>> {
+        let serialization = |e: serde_json::Error| vec![Diagnostic::error(codes::INVALID_MANIFEST, e.to_string(), "root")];
+        let mut value = serde_json::to_value(self).map_err(serialization)?;
+        crate::interpolation::interpolate_value(&mut value, lookup)?;
+        serde_json::from_value(value).map_err(serialization)
+    }
+
     /// Find a target by name
     pub fn find_target(&self, name: &str) -> Option<&BuildTarget> {
         self.build_targets.iter().find(|t| t.name == name)
@@ -0,0 +1,341 @@
+//! CADL manifests generated from an import
+//!
+//! [`ManifestGenerator`] turns an [`ImportResult`] into a manifest `cadi build`
+//! can run: every composition chunk becomes a build graph node named by its
+//! alias, the packages compositions depend on become edges, and each detected
+//! entry point becomes a build target with the toolchain its languages need.
+//! Entry point files that no composition covers get nodes of their own from
+//! their chunks, and an import without compositions turns every chunk into a
+//! node.
+
+use crate::atomic::{AtomicChunk, ChunkCategory};
+use crate::manifest::{
+    ApplicationInfo, BuildGraph, BuildTarget, GraphEdge, GraphNode, Manifest, Representation, TargetNode,
+    CURRENT_CADL_VERSION,
+};
+use crate::project_analyzer::{ImportResult, ProjectType};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+
+/// Target that builds every node of a generated manifest
+pub const DEV_TARGET: &str = "dev";
+
+/// Tools that build sources in `language`, named as in build receipts
+pub fn toolchain_for(language: &str) -> &'static [&'static str] {
+    match language {
+        "rust" => &["rustc", "cargo"],
+        "typescript" | "javascript" => &["node", "npm"],
+        "python" => &["python"],
+        "c" | "cpp" => &["gcc"],
+        "go" => &["go"],
+        "java" => &["javac"],
+        _ => &[],
+    }
+}
+
+/// Platform a target built from `language` runs on, as `cadi init` writes it
+fn platform_for(language: &str) -> &'static str {
+    match language {
+        "rust" | "c" | "cpp" | "go" => "native",
+        "typescript" | "javascript" => "node",
+        "python" => "python",
+        _ => "any",
+    }
+}
+
+/// Builds a [`Manifest`] from an [`ImportResult`]
+pub struct ManifestGenerator<'a> {
+    result: &'a ImportResult,
+}
+
+/// A build graph node and the chunk it builds
+struct Node<'a> {
+    id: String,
+    chunk: &'a AtomicChunk,
+    language: String,
+}
+
+impl<'a> ManifestGenerator<'a> {
+    pub fn new(result: &'a ImportResult) -> Self {
+        Self { result }
+    }
+
+    /// Project name as used in manifest IDs and file names
+    pub fn slug(&self) -> String {
+        self.result.summary.project_name.to_lowercase().replace(' ', "-")
+    }
+
+    /// File name of the generated manifest (`<project>.cadi.yaml`)
+    pub fn file_name(&self) -> String {
+        format!("{}.cadi.yaml", self.slug())
+    }
+
+    /// Generate the manifest
+    pub fn generate(&self) -> Manifest {
+        let result = self.result;
+        let atomic: HashMap<&str, &AtomicChunk> = result.chunks.iter().map(|c| (c.chunk_id.as_str(), c)).collect();
+
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut ids: BTreeSet<String> = BTreeSet::new();
+        let mut by_chunk: HashMap<&str, usize> = HashMap::new();
+        let mut add_node = |chunk: &'a AtomicChunk, nodes: &mut Vec<Node<'a>>| -> usize {
+            if let Some(&index) = by_chunk.get(chunk.chunk_id.as_str()) {
+                return index;
+            }
+            let base = chunk.display_name();
+            let mut id = base.clone();
+            let mut n = 1;
+            while !ids.insert(id.clone()) {
+                id = format!("{}-{}", base, n);
+                n += 1;
+            }
+            let components: Vec<&AtomicChunk> = chunk
+                .composition
+                .composed_of
+                .iter()
+                .filter_map(|c| atomic.get(c.chunk_id.as_str()).copied())
+                .collect();
+            let language = if components.is_empty() {
+                chunk.language.clone()
+            } else {
+                dominant_language(&components)
+            };
+            by_chunk.insert(&chunk.chunk_id, nodes.len());
+            nodes.push(Node { id, chunk, language });
+            nodes.len() - 1
+        };
+
+        for composition in &result.compositions {
+            add_node(composition, &mut nodes);
+        }
+
+        // Entry points become targets, built from the compositions covering
+        // them, or from their own chunks when no composition does
+        let mut entry_targets: BTreeMap<String, BTreeSet<usize>> = BTreeMap::new();
+        for entrypoint in &result.analysis.entrypoints {
+            let defines = |chunk: &AtomicChunk| {
+                chunk
+                    .sources
+                    .iter()
+                    .any(|s| Path::new(&s.file) == entrypoint || result.analysis.root.join(&s.file) == *entrypoint)
+            };
+            let covering: Vec<usize> = result
+                .compositions
+                .iter()
+                .filter(|c| !c.categories.contains(&ChunkCategory::Test))
+                .filter(|c| c.composition.composed_of.iter().any(|r| atomic.get(r.chunk_id.as_str()).is_some_and(|c| defines(c))))
+                .map(|c| add_node(c, &mut nodes))
+                .collect();
+            let entry_nodes = if covering.is_empty() {
+                result.chunks.iter().filter(|c| defines(c)).map(|c| add_node(c, &mut nodes)).collect()
+            } else {
+                covering
+            };
+            if !entry_nodes.is_empty() {
+                entry_targets.entry(self.target_name(entrypoint)).or_default().extend(entry_nodes);
+            }
+        }
+
+        if nodes.is_empty() {
+            for chunk in &result.chunks {
+                add_node(chunk, &mut nodes);
+            }
+        }
+
+        // Compositions require the package chunks of the members they
+        // depend on
+        let mut edges: Vec<GraphEdge> = Vec::new();
+        let mut dependencies: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (from, node) in nodes.iter().enumerate() {
+            for required in &node.chunk.requires {
+                let Some(&to) = by_chunk.get(required.as_str()) else {
+                    continue;
+                };
+                if to == from || dependencies.get(&from).is_some_and(|deps| deps.contains(&to)) {
+                    continue;
+                }
+                dependencies.entry(from).or_default().push(to);
+                edges.push(GraphEdge {
+                    from: node.id.clone(),
+                    to: nodes[to].id.clone(),
+                    interface: None,
+                    relation: "depends_on".to_string(),
+                });
+            }
+        }
+
+        let target = |name: String, members: &BTreeSet<usize>, fallback_language: &str| {
+            // A target builds its nodes along with everything they depend on
+            let mut included = members.clone();
+            let mut pending: Vec<usize> = members.iter().copied().collect();
+            while let Some(index) = pending.pop() {
+                for &dep in dependencies.get(&index).into_iter().flatten() {
+                    if included.insert(dep) {
+                        pending.push(dep);
+                    }
+                }
+            }
+            let mut target_nodes: Vec<&Node> = included.iter().map(|&i| &nodes[i]).collect();
+            target_nodes.sort_by(|a, b| a.id.cmp(&b.id));
+
+            let language = members
+                .iter()
+                .map(|&i| nodes[i].language.as_str())
+                .find(|l| !toolchain_for(l).is_empty())
+                .unwrap_or(fallback_language);
+            let toolchain: BTreeSet<&str> =
+                target_nodes.iter().flat_map(|n| toolchain_for(&n.language).iter().copied()).collect();
+            BuildTarget {
+                name,
+                platform: platform_for(language).to_string(),
+                nodes: target_nodes
+                    .iter()
+                    .map(|n| TargetNode { id: n.id.clone(), require: None, prefer: Some(vec!["source".to_string()]) })
+                    .collect(),
+                bundle: None,
+                deploy: None,
+                trust_requirements: None,
+                transforms: Vec::new(),
+                toolchain: toolchain.into_iter().map(String::from).collect(),
+            }
+        };
+
+        let primary_language = self.primary_language();
+        let mut build_targets = vec![target(DEV_TARGET.to_string(), &(0..nodes.len()).collect(), &primary_language)];
+        for (name, members) in &entry_targets {
+            build_targets.push(target(name.clone(), members, &primary_language));
+        }
+
+        let mut graph_nodes: Vec<GraphNode> = nodes
+            .iter()
+            .map(|node| GraphNode {
+                id: node.id.clone(),
+                chunk_ref: None,
+                source_cadi: Some(node.chunk.chunk_id.clone()),
+                ir_cadi: None,
+                blob_cadi: None,
+                container_cadi: None,
+                representations: vec![Representation {
+                    form: "source".to_string(),
+                    language: Some(node.language.clone()),
+                    format: None,
+                    architecture: None,
+                    chunk: node.chunk.chunk_id.clone(),
+                }],
+                selection_strategy: None,
+                materialization: None,
+            })
+            .collect();
+        graph_nodes.sort_by(|a, b| a.id.cmp(&b.id));
+        edges.sort_by(|a, b| (&a.from, &a.to).cmp(&(&b.from, &b.to)));
+
+        Manifest {
+            cadl_version: CURRENT_CADL_VERSION.to_string(),
+            manifest_id: format!("{}-manifest", self.slug()),
+            manifest_version: "1.0".to_string(),
+            application: ApplicationInfo {
+                name: result.summary.project_name.clone(),
+                description: Some(format!("{} - imported by CADI", result.summary.project_name)),
+                version: Some("0.1.0".to_string()),
+                authors: Vec::new(),
+                license: None,
+                repository: None,
+                namespace: None,
+            },
+            build_graph: BuildGraph { nodes: graph_nodes, edges },
+            build_targets,
+            trust_defaults: None,
+            dependencies: None,
+        }
+    }
+
+    /// Name of the target built from an entry point: its workspace member,
+    /// else the nearest enclosing package below the project root, else the
+    /// project
+    fn target_name(&self, entrypoint: &Path) -> String {
+        let analysis = &self.result.analysis;
+        let name = if let Some(member) = analysis.member_for(entrypoint) {
+            member.alias.clone()
+        } else {
+            entrypoint
+                .ancestors()
+                .skip(1)
+                .take_while(|dir| *dir != analysis.root && dir.starts_with(&analysis.root))
+                .find(|dir| ProjectType::from_markers(dir).is_some())
+                .and_then(|dir| dir.file_name())
+                .map(|name| name.to_string_lossy().to_lowercase().replace(' ', "-"))
+                .unwrap_or_else(|| self.slug())
+        };
+        if name == DEV_TARGET {
+            format!("{}-app", name)
+        } else {
+            name
+        }
+    }
+
+    /// The project's primary language, or the most common language of its
+    /// chunks for mixed projects
+    fn primary_language(&self) -> String {
+        let language = &self.result.analysis.primary_language;
+        if !toolchain_for(language).is_empty() {
+            return language.clone();
+        }
+        dominant_language(&self.result.chunks.iter().collect::<Vec<_>>())
+    }
+}
+
+/// Most common language among `chunks`, preferring languages with a
+/// toolchain over configuration and documentation
+fn dominant_language(chunks: &[&AtomicChunk]) -> String {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for chunk in chunks {
+        *counts.entry(chunk.language.as_str()).or_insert(0) += 1;
+    }
+    counts
+        .into_iter()
+        .max_by_key(|(language, count)| (!toolchain_for(language).is_empty(), *count, std::cmp::Reverse(*language)))
+        .map(|(language, _)| language.to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project_analyzer::ProjectAnalyzer;
+    use crate::validator::Validator;
+
+    #[test]
+    fn test_generated_manifest_builds_entry_points() {
+        let root = std::env::temp_dir().join(format!("cadi-manifest-gen-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        for (path, content) in [
+            ("core/Cargo.toml", "[package]\nname = \"core\"\nversion = \"0.1.0\"\n"),
+            ("core/src/lib.rs", "pub mod model;\n\npub fn total(a: u32, b: u32) -> u32 {\n    a + b\n}\n"),
+            ("core/src/model.rs", "pub struct Item {\n    pub id: u32,\n}\n"),
+            ("cli/Cargo.toml", "[package]\nname = \"cli\"\nversion = \"0.1.0\"\n"),
+            ("cli/src/main.rs", "fn main() {\n    println!(\"{}\", 1 + 2);\n}\n"),
+        ] {
+            std::fs::create_dir_all(root.join(path).parent().unwrap()).unwrap();
+            std::fs::write(root.join(path), content).unwrap();
+        }
+
+        let result = ProjectAnalyzer::default().import_project(&root).unwrap();
+        let manifest = ManifestGenerator::new(&result).generate();
+
+        assert_eq!(Validator::new().diagnose_manifest(&manifest), []);
+        let module = manifest.find_node("src-module").unwrap();
+        assert_eq!(module.representations[0].language.as_deref(), Some("rust"));
+        assert!(module.source_cadi.as_deref().unwrap().starts_with("chunk:"));
+
+        let dev = manifest.find_target(DEV_TARGET).unwrap();
+        assert_eq!(dev.nodes.len(), manifest.build_graph.nodes.len());
+        assert_eq!(dev.platform, "native");
+        assert_eq!(dev.toolchain, ["cargo", "rustc"]);
+        // cli/src/main.rs is a single file, too small for a composition
+        let cli = manifest.find_target("cli").unwrap();
+        assert!(cli.nodes.iter().all(|n| manifest.find_node(&n.id).is_some()));
+        assert!(!cli.nodes.is_empty());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
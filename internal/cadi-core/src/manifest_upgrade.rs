@@ -0,0 +1,397 @@
+//! Upgrading manifests to the current `cadl_version`
+//!
+//! Each [`Migration`] lists the fields a manifest format version renamed and
+//! the required fields it added. [`upgrade_manifest_source`] applies the
+//! pending migrations as targeted edits to the lines they touch, so comments
+//! and formatting survive. Flow-style YAML and JSON, where such edits are not
+//! feasible, are re-serialized instead.
+
+use crate::error::{CadiError, CadiResult};
+use crate::manifest::{cadl_version_at_least, CURRENT_CADL_VERSION, LEGACY_CADL_VERSION, SUPPORTED_CADL_VERSIONS};
+use crate::validator::{locate_yaml, yaml_entries, YamlEntry};
+use serde_yaml::{Mapping, Value};
+
+/// A field renamed by a manifest format version
+#[derive(Debug, Clone, Copy)]
+pub struct FieldRename {
+    /// Path of the mapping holding the field; `[]` stands for every item of
+    /// a list (`build_graph.nodes[]`)
+    pub parent: &'static str,
+    pub from: &'static str,
+    pub to: &'static str,
+}
+
+/// A field a manifest format version made required
+#[derive(Debug, Clone, Copy)]
+pub struct AddedField {
+    pub parent: &'static str,
+    pub key: &'static str,
+    /// YAML scalar an upgrade fills in when the field is missing
+    pub default: &'static str,
+}
+
+/// The changes from one manifest format version to the next
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    pub from: &'static str,
+    pub to: &'static str,
+    pub renames: &'static [FieldRename],
+    pub added: &'static [AddedField],
+}
+
+/// Every migration, oldest first
+pub const MIGRATIONS: &[Migration] = &[Migration {
+    from: "1",
+    to: "2",
+    renames: &[
+        FieldRename { parent: "", from: "trust_defaults", to: "trust" },
+        FieldRename { parent: "build_graph.nodes[]", from: "selection_strategy", to: "selection" },
+        FieldRename { parent: "dependencies", from: "resolution_strategy", to: "resolution" },
+    ],
+    added: &[AddedField { parent: "application", key: "version", default: "0.1.0" }],
+}];
+
+/// The migration that renamed a field to or from `key` in the mapping at
+/// `parent` (`build_graph.nodes[0]`)
+pub fn renamed_field(parent: &str, key: &str) -> Option<(&'static Migration, &'static FieldRename)> {
+    let parent = parent_pattern(parent);
+    MIGRATIONS.iter().find_map(|migration| {
+        migration
+            .renames
+            .iter()
+            .find(|rename| rename.parent == parent && (rename.from == key || rename.to == key))
+            .map(|rename| (migration, rename))
+    })
+}
+
+/// `cadl_version` as written in a parsed manifest, if it declares one
+pub fn declared_cadl_version(value: &Value) -> Option<String> {
+    match value.get("cadl_version")? {
+        Value::String(version) => Some(version.clone()),
+        Value::Number(version) => Some(version.to_string()),
+        _ => None,
+    }
+}
+
+/// Outcome of [`upgrade_manifest_source`]
+#[derive(Debug, Clone)]
+pub struct ManifestUpgrade {
+    /// Version the source declared, or [`LEGACY_CADL_VERSION`]
+    pub from: String,
+    pub to: String,
+    /// The upgraded source; the input itself when it was already current
+    pub source: String,
+    /// One line per change, in the order they were made
+    pub changes: Vec<String>,
+    /// Whether comments and formatting were kept, rather than the manifest
+    /// being re-serialized
+    pub preserved_formatting: bool,
+}
+
+impl ManifestUpgrade {
+    pub fn is_upgraded(&self) -> bool {
+        self.from != self.to
+    }
+}
+
+/// Rewrite YAML or JSON manifest source to [`CURRENT_CADL_VERSION`]:
+/// rename the fields each pending migration renamed, fill in the fields it
+/// made required and set `cadl_version`
+pub fn upgrade_manifest_source(source: &str) -> CadiResult<ManifestUpgrade> {
+    let original: Value = serde_yaml::from_str(source).map_err(|e| CadiError::Serialization(e.to_string()))?;
+    if !original.is_mapping() {
+        return Err(CadiError::Serialization("manifest is not a mapping".to_string()));
+    }
+    let from = declared_cadl_version(&original).unwrap_or_else(|| LEGACY_CADL_VERSION.to_string());
+    if !SUPPORTED_CADL_VERSIONS.contains(&from.as_str()) {
+        return Err(CadiError::Configuration(format!(
+            "Unsupported cadl_version {} (this build supports {})",
+            from,
+            SUPPORTED_CADL_VERSIONS.join(", ")
+        )));
+    }
+
+    let pending: Vec<&Migration> = MIGRATIONS.iter().filter(|m| !cadl_version_at_least(&from, m.to)).collect();
+    if pending.is_empty() {
+        return Ok(ManifestUpgrade {
+            to: from.clone(),
+            from,
+            source: source.to_string(),
+            changes: Vec::new(),
+            preserved_formatting: true,
+        });
+    }
+
+    let mut expected = original;
+    let mut changes = Vec::new();
+    for migration in &pending {
+        migrate_value(&mut expected, migration, &mut changes);
+    }
+
+    let is_json = source.trim_start().starts_with('{');
+    let edited = if is_json {
+        None
+    } else {
+        pending
+            .iter()
+            .try_fold(source.to_string(), |text, migration| edit_source(&text, migration))
+            .filter(|text| serde_yaml::from_str::<Value>(text).is_ok_and(|value| value == expected))
+    };
+
+    let (upgraded, preserved_formatting) = match edited {
+        Some(text) => (text, true),
+        None if is_json => {
+            let json = serde_json::to_string_pretty(&expected).map_err(|e| CadiError::Serialization(e.to_string()))?;
+            (json + "\n", false)
+        }
+        None => (
+            serde_yaml::to_string(&expected).map_err(|e| CadiError::Serialization(e.to_string()))?,
+            false,
+        ),
+    };
+
+    Ok(ManifestUpgrade {
+        from,
+        to: CURRENT_CADL_VERSION.to_string(),
+        source: upgraded,
+        changes,
+        preserved_formatting,
+    })
+}
+
+/// Apply `migration` to a parsed manifest, describing each change
+fn migrate_value(value: &mut Value, migration: &Migration, changes: &mut Vec<String>) {
+    for rename in migration.renames {
+        each_mapping_mut(value, &segments(rename.parent), String::new(), &mut |path, map| {
+            if map.contains_key(rename.from) {
+                *map = std::mem::take(map)
+                    .into_iter()
+                    .map(|(key, child)| match key.as_str() {
+                        Some(name) if name == rename.from => (Value::from(rename.to), child),
+                        _ => (key, child),
+                    })
+                    .collect();
+                changes.push(format!("renamed `{}` to `{}`", join(path, rename.from), rename.to));
+            }
+        });
+    }
+
+    for added in migration.added {
+        let default: Value = serde_yaml::from_str(added.default).unwrap_or(Value::Null);
+        each_mapping_mut(value, &segments(added.parent), String::new(), &mut |path, map| {
+            if !map.contains_key(added.key) {
+                map.insert(Value::from(added.key), default.clone());
+                changes.push(format!("added `{}: {}`", join(path, added.key), added.default));
+            }
+        });
+    }
+
+    if let Some(root) = value.as_mapping_mut() {
+        if root.contains_key("cadl_version") {
+            root.insert(Value::from("cadl_version"), Value::from(migration.to));
+        } else {
+            *root = std::iter::once((Value::from("cadl_version"), Value::from(migration.to)))
+                .chain(std::mem::take(root))
+                .collect();
+        }
+    }
+    changes.push(format!("set `cadl_version` to {}", migration.to));
+}
+
+/// Apply `migration` to block-style YAML by editing only the lines it
+/// touches, or `None` when some change cannot be made that way
+fn edit_source(source: &str, migration: &Migration) -> Option<String> {
+    let value: Value = serde_yaml::from_str(source).ok()?;
+    let entries = yaml_entries(source);
+    let mut lines: Vec<String> = source.lines().map(str::to_string).collect();
+    let mut inserts: Vec<(usize, String)> = Vec::new();
+
+    for rename in migration.renames {
+        for (parent, map) in mappings(&value, rename.parent) {
+            if !map.contains_key(rename.from) {
+                continue;
+            }
+            let entry = entry_at(&entries, &join(&parent, rename.from), rename.from)?;
+            let line = &mut lines[entry.line];
+            let start = entry.column + usize::from(line[entry.column..].starts_with(['"', '\'']));
+            if !line[start..].starts_with(rename.from) {
+                return None;
+            }
+            line.replace_range(start..start + rename.from.len(), rename.to);
+        }
+    }
+
+    for added in migration.added {
+        for (parent, map) in mappings(&value, added.parent) {
+            if !map.contains_key(added.key) {
+                let (at, indent) = block_end(&entries, &lines, &parent)?;
+                inserts.push((at, format!("{}{}: {}", " ".repeat(indent), added.key, added.default)));
+            }
+        }
+    }
+
+    let version = format!("cadl_version: '{}'", migration.to);
+    match value.get("cadl_version") {
+        Some(_) => {
+            let entry = entry_at(&entries, "cadl_version", "cadl_version")?;
+            let line = &mut lines[entry.line];
+            let comment = line.find(" #").map(|at| line[at..].to_string()).unwrap_or_default();
+            *line = format!("{}{}{}", &line[..entry.column], version, comment);
+        }
+        None => {
+            let first = entries.first().filter(|entry| entry.column == 0 && entry.key.is_some())?;
+            inserts.push((first.line, version));
+        }
+    }
+
+    // Bottom up, so earlier insertions do not shift later ones
+    inserts.sort_by_key(|(at, _)| std::cmp::Reverse(*at));
+    for (at, line) in inserts {
+        lines.insert(at, line);
+    }
+
+    let newline = if source.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut text = lines.join(newline);
+    if source.ends_with('\n') {
+        text.push_str(newline);
+    }
+    Some(text)
+}
+
+/// The entry for `key` at exactly `path`
+fn entry_at<'a>(entries: &'a [YamlEntry], path: &str, key: &str) -> Option<&'a YamlEntry> {
+    let span = locate_yaml(entries, path)?;
+    entries
+        .iter()
+        .find(|entry| entry.line + 1 == span.line && entry.column + 1 == span.column)
+        .filter(|entry| entry.key.as_deref() == Some(key))
+}
+
+/// Line after the last child of the block mapping at `path`, before any
+/// trailing blank or comment lines, and the indentation of its children
+fn block_end(entries: &[YamlEntry], lines: &[String], path: &str) -> Option<(usize, usize)> {
+    let key = path.rsplit('.').next().filter(|key| !key.is_empty() && !key.ends_with(']'))?;
+    let parent = entry_at(entries, path, key)?;
+    let index = entries.iter().position(|entry| std::ptr::eq(entry, parent))?;
+    let children: Vec<&YamlEntry> = entries[index + 1..]
+        .iter()
+        .take_while(|entry| entry.column > parent.column)
+        .collect();
+    let first = children.first().filter(|child| child.line > parent.line && child.key.is_some())?;
+
+    let mut end = entries
+        .get(index + 1 + children.len())
+        .map_or(lines.len(), |next| next.line);
+    while end > first.line + 1 && matches!(lines[end - 1].trim_start().chars().next(), None | Some('#')) {
+        end -= 1;
+    }
+    Some((end, first.column))
+}
+
+/// The mappings at `pattern`, with their concrete paths
+fn mappings<'a>(value: &'a Value, pattern: &str) -> Vec<(String, &'a Mapping)> {
+    let mut found = vec![(String::new(), value)];
+    for segment in segments(pattern) {
+        let (key, each) = segment.strip_suffix("[]").map_or((segment, false), |key| (key, true));
+        found = found
+            .into_iter()
+            .filter_map(|(path, value)| Some((join(&path, key), value.get(key)?)))
+            .flat_map(|(path, value)| match (each, value) {
+                (true, Value::Sequence(items)) => items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| (format!("{}[{}]", path, i), item))
+                    .collect(),
+                (true, _) => Vec::new(),
+                (false, _) => vec![(path, value)],
+            })
+            .collect();
+    }
+    found
+        .into_iter()
+        .filter_map(|(path, value)| Some((path, value.as_mapping()?)))
+        .collect()
+}
+
+fn each_mapping_mut(value: &mut Value, segments: &[&str], path: String, f: &mut dyn FnMut(&str, &mut Mapping)) {
+    let Some((segment, rest)) = segments.split_first() else {
+        if let Some(map) = value.as_mapping_mut() {
+            f(&path, map);
+        }
+        return;
+    };
+    let (key, each) = segment.strip_suffix("[]").map_or((*segment, false), |key| (key, true));
+    let Some(child) = value.get_mut(key) else { return };
+    let path = join(&path, key);
+    match (each, child) {
+        (true, Value::Sequence(items)) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                each_mapping_mut(item, rest, format!("{}[{}]", path, i), f);
+            }
+        }
+        (true, _) => {}
+        (false, child) => each_mapping_mut(child, rest, path, f),
+    }
+}
+
+fn segments(pattern: &str) -> Vec<&str> {
+    pattern.split('.').filter(|s| !s.is_empty()).collect()
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+/// `build_graph.nodes[0]` as `build_graph.nodes[]`
+fn parent_pattern(path: &str) -> String {
+    let mut pattern = String::with_capacity(path.len());
+    let mut in_index = false;
+    for c in path.chars() {
+        match c {
+            '[' => {
+                in_index = true;
+                pattern.push_str("[]");
+            }
+            ']' => in_index = false,
+            _ if in_index => {}
+            _ => pattern.push(c),
+        }
+    }
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flow_style_yaml_is_reserialized() {
+        let source = "{manifest_id: demo, manifest_version: '1.0', application: {name: demo}, build_graph: {nodes: []}}\n";
+        let upgrade = upgrade_manifest_source(source).unwrap();
+        assert!(!upgrade.preserved_formatting);
+        let value: Value = serde_yaml::from_str(&upgrade.source).unwrap();
+        assert_eq!(value["cadl_version"], Value::from("2"));
+        assert_eq!(value["application"]["version"], Value::from("0.1.0"));
+    }
+
+    #[test]
+    fn test_current_and_unsupported_versions() {
+        let current = "cadl_version: 2\nmanifest_id: demo\n";
+        let upgrade = upgrade_manifest_source(current).unwrap();
+        assert!(!upgrade.is_upgraded());
+        assert_eq!(upgrade.source, current);
+
+        assert!(upgrade_manifest_source("cadl_version: '9'\nmanifest_id: demo\n").is_err());
+    }
+
+    #[test]
+    fn test_renamed_field_lookup() {
+        let (migration, rename) = renamed_field("build_graph.nodes[3]", "selection_strategy").unwrap();
+        assert_eq!((migration.to, rename.to), ("2", "selection"));
+        assert!(renamed_field("build_graph", "selection_strategy").is_none());
+    }
+}
@@ -1,8 +1,12 @@
 //! Semantic Normalizer
 //!
-//! Language-aware semantic normalization: parse → alpha-rename → canonicalize → semantic hash
+//! Language-aware semantic normalization: a pipeline of named passes
+//! (strip comments → strip string literals → sort imports → alpha-rename →
+//! normalize whitespace), each of which can be turned off, followed by a
+//! semantic hash that records the normalization version and passes used.
 
 use crate::error::CadiResult;
+use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
 use regex::Regex;
 use std::collections::HashMap;
@@ -10,12 +14,191 @@ use std::collections::HashMap;
 #[cfg(feature = "ast-parsing")]
 use tree_sitter::{Parser, Query, QueryCursor};
 
+/// Version of the normalization rules. Bump it whenever a pass changes its
+/// output for some input, so hashes computed under the old rules are
+/// recognisably different rather than silently unequal.
+pub const NORMALIZATION_VERSION: u32 = 2;
+
+/// A step of the normalization pipeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NormalizationPass {
+    /// Drop line and block comments
+    StripComments,
+    /// Empty every string literal
+    StripStringLiterals,
+    /// Sort each run of import statements
+    SortImports,
+    /// Rename identifiers to `_var0`, `_var1`, ... by first appearance
+    AlphaRename,
+    /// Trim lines, drop blank ones and canonicalize spacing and punctuation
+    NormalizeWhitespace,
+}
+
+impl NormalizationPass {
+    /// Every pass, in the order the pipeline runs them
+    pub const ALL: [NormalizationPass; 5] = [
+        NormalizationPass::StripComments,
+        NormalizationPass::StripStringLiterals,
+        NormalizationPass::SortImports,
+        NormalizationPass::AlphaRename,
+        NormalizationPass::NormalizeWhitespace,
+    ];
+
+    /// Name of the pass, as used in configuration
+    pub fn name(&self) -> &'static str {
+        match self {
+            NormalizationPass::StripComments => "strip_comments",
+            NormalizationPass::StripStringLiterals => "strip_string_literals",
+            NormalizationPass::SortImports => "sort_imports",
+            NormalizationPass::AlphaRename => "alpha_rename",
+            NormalizationPass::NormalizeWhitespace => "normalize_whitespace",
+        }
+    }
+
+    /// Bit of the pass in a semantic hash's pass bitmap. Bits are fixed:
+    /// new passes take new bits.
+    pub fn bit(&self) -> u8 {
+        match self {
+            NormalizationPass::StripComments => 1,
+            NormalizationPass::NormalizeWhitespace => 1 << 1,
+            NormalizationPass::AlphaRename => 1 << 2,
+            NormalizationPass::SortImports => 1 << 3,
+            NormalizationPass::StripStringLiterals => 1 << 4,
+        }
+    }
+}
+
+impl std::str::FromStr for NormalizationPass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.to_ascii_lowercase().replace('-', "_");
+        NormalizationPass::ALL
+            .into_iter()
+            .find(|pass| pass.name() == name)
+            .ok_or_else(|| format!("Unknown normalization pass: {}", s))
+    }
+}
+
+/// Which normalization passes run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NormalizerConfig {
+    pub strip_comments: bool,
+    pub strip_string_literals: bool,
+    pub sort_imports: bool,
+    pub alpha_rename: bool,
+    pub normalize_whitespace: bool,
+}
+
+impl Default for NormalizerConfig {
+    fn default() -> Self {
+        Self {
+            strip_comments: true,
+            strip_string_literals: false,
+            sort_imports: true,
+            alpha_rename: true,
+            normalize_whitespace: true,
+        }
+    }
+}
+
+impl NormalizerConfig {
+    /// Whether a pass is enabled
+    pub fn enabled(&self, pass: NormalizationPass) -> bool {
+        match pass {
+            NormalizationPass::StripComments => self.strip_comments,
+            NormalizationPass::StripStringLiterals => self.strip_string_literals,
+            NormalizationPass::SortImports => self.sort_imports,
+            NormalizationPass::AlphaRename => self.alpha_rename,
+            NormalizationPass::NormalizeWhitespace => self.normalize_whitespace,
+        }
+    }
+
+    /// Enable or disable a pass
+    pub fn with_pass(mut self, pass: NormalizationPass, enabled: bool) -> Self {
+        let flag = match pass {
+            NormalizationPass::StripComments => &mut self.strip_comments,
+            NormalizationPass::StripStringLiterals => &mut self.strip_string_literals,
+            NormalizationPass::SortImports => &mut self.sort_imports,
+            NormalizationPass::AlphaRename => &mut self.alpha_rename,
+            NormalizationPass::NormalizeWhitespace => &mut self.normalize_whitespace,
+        };
+        *flag = enabled;
+        self
+    }
+
+    /// Enabled passes, in pipeline order
+    pub fn passes(&self) -> Vec<NormalizationPass> {
+        NormalizationPass::ALL.into_iter().filter(|pass| self.enabled(*pass)).collect()
+    }
+
+    /// Bitmap of the enabled passes
+    pub fn bitmap(&self) -> u8 {
+        self.passes().iter().fold(0, |bits, pass| bits | pass.bit())
+    }
+}
+
+/// A parsed semantic hash: `sem:v<version>:<pass bitmap>:<sha256>`.
+///
+/// Hashes are only comparable when they were computed with the same
+/// normalization version and passes. Hashes from before versioning
+/// (`semantic:<sha256>`) parse as version 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemanticHash {
+    pub version: u32,
+    pub passes: u8,
+    pub digest: String,
+}
+
+impl SemanticHash {
+    /// Passes of the unversioned normalizer
+    const LEGACY_PASSES: u8 = 0b111;
+
+    /// Parse a semantic hash string
+    pub fn parse(hash: &str) -> Option<Self> {
+        if let Some(digest) = hash.strip_prefix("semantic:") {
+            return Some(Self {
+                version: 1,
+                passes: Self::LEGACY_PASSES,
+                digest: digest.to_string(),
+            });
+        }
+
+        let mut parts = hash.strip_prefix("sem:v")?.splitn(3, ':');
+        let version = parts.next()?.parse().ok()?;
+        let passes = u8::from_str_radix(parts.next()?, 16).ok()?;
+        let digest = parts.next()?.to_string();
+        Some(Self { version, passes, digest })
+    }
+
+    /// Normalization version and passes, e.g. `v2:0f`
+    pub fn scheme(&self) -> String {
+        format!("v{}:{:02x}", self.version, self.passes)
+    }
+
+    /// Whether the two hashes were computed the same way and can be compared
+    pub fn is_comparable(&self, other: &SemanticHash) -> bool {
+        self.version == other.version && self.passes == other.passes
+    }
+}
+
+impl std::fmt::Display for SemanticHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "sem:v{}:{:02x}:{}", self.version, self.passes, self.digest)
+    }
+}
+
 /// Result returned by normalization
 #[derive(Debug, Clone)]
 pub struct NormalizationResult {
     pub original: String,
+    /// The code after the alpha-rename pass
     pub alpha_renamed: String,
+    /// The code after every pass
     pub canonical: String,
+    /// `sem:v<version>:<pass bitmap>:<sha256 of canonical>`
     pub hash: String,
 }
 
@@ -23,50 +206,81 @@ pub struct NormalizationResult {
 #[derive(Debug, Clone)]
 pub struct SemanticNormalizer {
     language: String,
+    config: NormalizerConfig,
 }
 
 impl SemanticNormalizer {
-    /// Create a new normalizer for a supported language.
+    /// Create a new normalizer for a supported language, running the
+    /// default passes.
     pub fn new(language: &str) -> CadiResult<Self> {
+        Self::with_config(language, NormalizerConfig::default())
+    }
+
+    /// Create a normalizer running the passes enabled in `config`
+    pub fn with_config(language: &str, config: NormalizerConfig) -> CadiResult<Self> {
         let supported = ["typescript", "python", "rust", "go"];
         if !supported.contains(&language) {
             return Err(crate::error::CadiError::UnsupportedPlatform(format!("Unsupported language: {}", language)));
         }
         Ok(Self {
             language: language.to_string(),
+            config,
         })
     }
 
-    /// Main normalization pipeline
+    /// The passes this normalizer runs
+    pub fn config(&self) -> &NormalizerConfig {
+        &self.config
+    }
+
+    /// Run the enabled passes in order and hash the result
     pub fn normalize(&self, code: &str) -> CadiResult<NormalizationResult> {
-        // If AST parsing is enabled, use tree-sitter for alpha-renaming; otherwise fall back to simple canonicalization
+        let mut current = code.to_string();
+        let mut alpha_renamed = None;
+
+        for pass in self.config.passes() {
+            current = self.run_pass(pass, &current)?;
+            if pass == NormalizationPass::AlphaRename {
+                alpha_renamed = Some(current.clone());
+            }
+        }
+
+        let hash = SemanticHash {
+            version: NORMALIZATION_VERSION,
+            passes: self.config.bitmap(),
+            digest: Self::compute_digest(&current),
+        };
+
+        Ok(NormalizationResult {
+            original: code.to_string(),
+            alpha_renamed: alpha_renamed.unwrap_or_else(|| code.to_string()),
+            canonical: current,
+            hash: hash.to_string(),
+        })
+    }
+
+    fn run_pass(&self, pass: NormalizationPass, code: &str) -> CadiResult<String> {
+        Ok(match pass {
+            NormalizationPass::StripComments => Self::strip_comments(code, &self.language),
+            NormalizationPass::StripStringLiterals => Self::strip_string_literals(code, &self.language),
+            NormalizationPass::SortImports => Self::sort_imports(code, &self.language),
+            NormalizationPass::AlphaRename => self.rename(code)?,
+            NormalizationPass::NormalizeWhitespace => Self::normalize_whitespace(code, &self.language),
+        })
+    }
+
+    /// Alpha-rename with tree-sitter when AST parsing is enabled, otherwise
+    /// with a best-effort word scan
+    fn rename(&self, code: &str) -> CadiResult<String> {
         #[cfg(feature = "ast-parsing")]
         {
             let tree = self.parse(code)?;
-            let alpha = self.alpha_rename(&tree, code)?;
-            let canonical = self.canonicalize(&alpha)?;
-            let hash = Self::compute_hash(&canonical);
-
-            Ok(NormalizationResult {
-                original: code.to_string(),
-                alpha_renamed: alpha,
-                canonical,
-                hash,
-            })
+            self.alpha_rename(&tree, code)
         }
 
         #[cfg(not(feature = "ast-parsing"))]
         {
-            let alpha = self.simple_alpha_placeholder(code);
-            let canonical = self.canonicalize(&alpha)?;
-            let hash = Self::compute_hash(&canonical);
-
-            Ok(NormalizationResult {
-                original: code.to_string(),
-                alpha_renamed: alpha,
-                canonical,
-                hash,
-            })
+            Ok(self.simple_alpha_placeholder(code))
         }
     }
 
@@ -175,20 +389,119 @@ impl SemanticNormalizer {
         }).to_string()
     }
 
-    /// Canonicalize structure: remove comments, normalize whitespace, punctuation
-    fn canonicalize(&self, code: &str) -> CadiResult<String> {
-        let no_comments = Self::strip_comments(code, &self.language);
-
-        let normalized = no_comments
+    /// Trim lines, drop blank ones and canonicalize spacing and punctuation
+    fn normalize_whitespace(code: &str, language: &str) -> String {
+        let normalized = code
             .lines()
             .map(|line| line.trim())
             .filter(|line| !line.is_empty())
             .collect::<Vec<_>>()
             .join("\n");
 
-        let cleaned = Self::normalize_punctuation(&normalized, &self.language);
+        Self::normalize_punctuation(&normalized, language)
+    }
+
+    /// Empty every string literal, keeping its quotes. Rust's `'` is left
+    /// alone since it also starts lifetimes.
+    fn strip_string_literals(code: &str, language: &str) -> String {
+        let quotes: &[char] = match language {
+            "typescript" => &['"', '\'', '`'],
+            "python" => &['"', '\''],
+            "go" => &['"', '`'],
+            _ => &['"'],
+        };
+
+        let mut result = String::with_capacity(code.len());
+        let mut chars = code.chars().peekable();
+        while let Some(c) = chars.next() {
+            result.push(c);
+            if !quotes.contains(&c) {
+                continue;
+            }
+
+            // Python triple-quoted strings end at the matching triple
+            let triple = language == "python" && chars.peek() == Some(&c) && {
+                let mut ahead = chars.clone();
+                ahead.next();
+                ahead.peek() == Some(&c)
+            };
+            if triple {
+                chars.next();
+                chars.next();
+                let mut run = 0;
+                for next in chars.by_ref() {
+                    run = if next == c { run + 1 } else { 0 };
+                    if run == 3 {
+                        break;
+                    }
+                }
+                result.push(c);
+                result.push(c);
+                result.push_str(&c.to_string().repeat(3));
+                continue;
+            }
+
+            let mut escaped = false;
+            for next in chars.by_ref() {
+                if escaped {
+                    escaped = false;
+                } else if next == '\\' && c != '`' {
+                    escaped = true;
+                } else if next == c {
+                    break;
+                }
+            }
+            result.push(c);
+        }
+        result
+    }
 
-        Ok(cleaned)
+    /// Sort every run of consecutive import lines, and the lines of Go
+    /// `import ( ... )` blocks, so import order does not matter
+    fn sort_imports(code: &str, language: &str) -> String {
+        let is_import = |line: &str| {
+            let line = line.trim_start();
+            match language {
+                "rust" => line.starts_with("use ") || line.starts_with("pub use "),
+                "python" => line.starts_with("import ") || (line.starts_with("from ") && line.contains(" import ")),
+                "typescript" => line.starts_with("import ") && line.trim_end().ends_with(';'),
+                "go" => line.starts_with("import \""),
+                _ => false,
+            }
+        };
+
+        let lines: Vec<&str> = code.lines().collect();
+        let mut result: Vec<&str> = Vec::with_capacity(lines.len());
+        let mut i = 0;
+        while i < lines.len() {
+            let in_go_block = language == "go" && lines[i].trim() == "import (";
+            if in_go_block {
+                result.push(lines[i]);
+                i += 1;
+            }
+            let start = i;
+            while i < lines.len()
+                && if in_go_block { lines[i].trim() != ")" } else { is_import(lines[i]) }
+            {
+                i += 1;
+            }
+            if i == start {
+                if !in_go_block {
+                    result.push(lines[i]);
+                    i += 1;
+                }
+                continue;
+            }
+            let mut run = lines[start..i].to_vec();
+            run.sort_by_key(|line| line.trim());
+            result.extend(run);
+        }
+
+        let mut sorted = result.join("\n");
+        if code.ends_with('\n') {
+            sorted.push('\n');
+        }
+        sorted
     }
 
     fn strip_comments(code: &str, language: &str) -> String {
@@ -327,10 +640,10 @@ impl SemanticNormalizer {
         keywords.contains(&word)
     }
 
-    fn compute_hash(canonical: &str) -> String {
+    fn compute_digest(canonical: &str) -> String {
         let mut hasher = Sha256::new();
         hasher.update(canonical.as_bytes());
-        format!("semantic:{:x}", hasher.finalize())
+        format!("{:x}", hasher.finalize())
     }
 }
 
@@ -351,4 +664,145 @@ mod tests {
         }
         assert_eq!(r1.hash, r2.hash);
     }
+
+    /// Deterministic xorshift generator for the property tests below
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 as usize
+        }
+
+        /// `count` distinct identifiers that aren't keywords in any language
+        fn names(&mut self, count: usize) -> Vec<String> {
+            const STEMS: [&str; 6] = ["acc", "item", "total", "val", "tmp", "node"];
+            (0..count)
+                .map(|i| format!("{}{}_{}", STEMS[self.next() % STEMS.len()], self.next() % 100, i))
+                .collect()
+        }
+    }
+
+    const OPS: [&str; 4] = ["+", "-", "*", "/"];
+
+    /// A small function in `language` using `names` for its function,
+    /// parameters and locals, and `ops` for its arithmetic
+    fn program(language: &str, names: &[String], ops: [&str; 2]) -> String {
+        let [f, a, b, c, d] = [&names[0], &names[1], &names[2], &names[3], &names[4]];
+        let [op1, op2] = ops;
+        match language {
+            "rust" => format!(
+                "fn {f}({a}: i32, {b}: i32) -> i32 {{\n    let {c} = {a} {op1} {b};\n    let {d} = {c} {op2} {a};\n    {d}\n}}\n"
+            ),
+            "python" => format!(
+                "def {f}({a}, {b}):\n    {c} = {a} {op1} {b}\n    {d} = {c} {op2} {a}\n    return {d}\n"
+            ),
+            "typescript" => format!(
+                "function {f}({a}, {b}) {{\n  const {c} = {a} {op1} {b};\n  const {d} = {c} {op2} {a};\n  return {d};\n}}\n"
+            ),
+            _ => format!(
+                "func {f}({a} int, {b} int) int {{\n\t{c} := {a} {op1} {b}\n\t{d} := {c} {op2} {a}\n\treturn {d}\n}}\n"
+            ),
+        }
+    }
+
+    #[test]
+    fn renaming_locals_preserves_hash() {
+        let mut rng = Rng(0x5eed);
+        for language in ["rust", "python", "typescript", "go"] {
+            let normalizer = SemanticNormalizer::new(language).unwrap();
+            for _ in 0..50 {
+                let ops = [OPS[rng.next() % OPS.len()], OPS[rng.next() % OPS.len()]];
+                let original = program(language, &rng.names(5), ops);
+                let renamed = program(language, &rng.names(5), ops);
+                assert_eq!(
+                    normalizer.normalize(&original).unwrap().hash,
+                    normalizer.normalize(&renamed).unwrap().hash,
+                    "renaming changed the hash:\n{}\n---\n{}",
+                    original,
+                    renamed
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn changing_logic_changes_hash() {
+        let mut rng = Rng(0xc0ffee);
+        for language in ["rust", "python", "typescript", "go"] {
+            let normalizer = SemanticNormalizer::new(language).unwrap();
+            for _ in 0..50 {
+                let names = rng.names(5);
+                let op1 = rng.next() % OPS.len();
+                let op2 = rng.next() % OPS.len();
+                let changed = (op2 + 1 + rng.next() % (OPS.len() - 1)) % OPS.len();
+                let original = program(language, &names, [OPS[op1], OPS[op2]]);
+                let modified = program(language, &names, [OPS[op1], OPS[changed]]);
+                assert_ne!(
+                    normalizer.normalize(&original).unwrap().hash,
+                    normalizer.normalize(&modified).unwrap().hash,
+                    "different logic hashed the same:\n{}\n---\n{}",
+                    original,
+                    modified
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn hash_records_version_and_passes() {
+        let code = "fn add(a: i32, b: i32) -> i32 { a + b }";
+        let default = SemanticNormalizer::new("rust").unwrap().normalize(code).unwrap();
+        let parsed = SemanticHash::parse(&default.hash).unwrap();
+        assert_eq!(parsed.version, NORMALIZATION_VERSION);
+        assert_eq!(parsed.passes, NormalizerConfig::default().bitmap());
+        assert_eq!(parsed.to_string(), default.hash);
+        assert!(default.hash.starts_with("sem:v2:"));
+
+        let config = NormalizerConfig::default().with_pass(NormalizationPass::AlphaRename, false);
+        let unrenamed = SemanticNormalizer::with_config("rust", config).unwrap().normalize(code).unwrap();
+        assert!(!SemanticHash::parse(&unrenamed.hash).unwrap().is_comparable(&parsed));
+        assert_eq!(unrenamed.alpha_renamed, code);
+
+        assert_eq!("sort-imports".parse::<NormalizationPass>().unwrap(), NormalizationPass::SortImports);
+        assert!("minify".parse::<NormalizationPass>().is_err());
+    }
+
+    #[test]
+    fn optional_passes_can_be_toggled() {
+        let a = "use std::fmt;\nuse std::collections::HashMap;\nfn greet() -> String { \"404\".into() } // hi\n";
+        let b = "use std::collections::HashMap;\nuse std::fmt;\n\nfn greet() -> String { \"500\".into() }\n";
+
+        let hash = |config: NormalizerConfig, code: &str| {
+            SemanticNormalizer::with_config("rust", config).unwrap().normalize(code).unwrap().hash
+        };
+
+        // Imports are sorted by default, strings kept
+        let default = NormalizerConfig::default();
+        assert_ne!(hash(default.clone(), a), hash(default.clone(), b));
+
+        let strip_strings = default.with_pass(NormalizationPass::StripStringLiterals, true);
+        assert_eq!(hash(strip_strings.clone(), a), hash(strip_strings.clone(), b));
+
+        let unsorted = strip_strings.with_pass(NormalizationPass::SortImports, false);
+        assert_ne!(hash(unsorted.clone(), a), hash(unsorted, b));
+    }
+
+    #[test]
+    fn string_literals_and_imports_are_normalized_per_language() {
+        assert_eq!(
+            SemanticNormalizer::strip_string_literals(r#"let s = "a \"quoted\" word"; let c = 'x';"#, "rust"),
+            r#"let s = ""; let c = 'x';"#
+        );
+        assert_eq!(
+            SemanticNormalizer::strip_string_literals("x = '''doc ' string''' + 'y'", "python"),
+            "x = '''''' + ''"
+        );
+        assert_eq!(
+            SemanticNormalizer::sort_imports("import (\n\t\"os\"\n\t\"fmt\"\n)\nfunc main() {}", "go"),
+            "import (\n\t\"fmt\"\n\t\"os\"\n)\nfunc main() {}"
+        );
+    }
 }
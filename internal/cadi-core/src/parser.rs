@@ -3,6 +3,7 @@ use pest::iterators::Pair;
 use pest_derive::Parser;
 use std::collections::HashMap;
 use crate::ast::*;
+use crate::validator::{codes, Diagnostic};
 
 #[derive(Parser)]
 #[grammar = "src/grammar.pest"] 
@@ -10,31 +11,48 @@ use crate::ast::*;
 pub struct CadlParser;
 
 pub fn parse_file(input: &str) -> Result<CadlDocument, String> {
-    let mut pairs = CadlParser::parse(Rule::file, input)
-        .map_err(|e| format!("{}", e))?;
+    parse_document(input).map_err(|d| d.to_string())
+}
+
+/// Parse a CADL document, reporting a syntax error as a diagnostic with the
+/// position it was found at
+pub fn parse_document(input: &str) -> Result<CadlDocument, Diagnostic> {
+    let mut pairs = CadlParser::parse(Rule::file, input).map_err(|e| {
+        let (line, column) = match e.line_col {
+            pest::error::LineColLocation::Pos(pos) => pos,
+            pest::error::LineColLocation::Span(start, _) => start,
+        };
+        Diagnostic::error(codes::PARSE_ERROR, e.variant.message().to_string(), "root")
+            .with_span(Span { line, column })
+    })?;
 
     let mut doc = CadlDocument {
         interfaces: Vec::new(),
         implementations: Vec::new(),
         constraints: Vec::new(),
         top_level_annotations: Vec::new(),
+        source_map: HashMap::new(),
     };
 
     for pair in pairs.next().unwrap().into_inner() {
         match pair.as_rule() {
             Rule::interface_def => {
-                doc.interfaces.push(parse_interface(pair));
+                let path = format!("interfaces[{}]", doc.interfaces.len());
+                doc.interfaces.push(parse_interface(pair, &path, &mut doc.source_map));
             }
             Rule::impl_def => {
-                doc.implementations.push(parse_impl(pair));
+                let path = format!("implementations[{}]", doc.implementations.len());
+                doc.implementations.push(parse_impl(pair, &path, &mut doc.source_map));
             }
             Rule::annotation => {
+                let span = span_of(&pair);
                 let ann = parse_annotation(pair);
                 // Check if it's strictly a constraint block (special case in AST usually, 
                 // but here we might treat @constraints as just another annotation or promote it)
                 // For the spec, @constraints { ... } is a block.
                 if let Annotation::Unknown(name, map) = &ann {
                     if name == "constraints" {
+                        doc.source_map.insert(format!("constraints[{}]", doc.constraints.len()), span);
                         doc.constraints.push(ConstraintDef {
                             name: None,
                             rules: Vec::new(), // TODO: parse internals better
@@ -43,6 +61,10 @@ pub fn parse_file(input: &str) -> Result<CadlDocument, String> {
                         continue;
                     }
                 }
+                doc.source_map.insert(
+                    format!("top_level_annotations[{}]", doc.top_level_annotations.len()),
+                    span,
+                );
                 doc.top_level_annotations.push(ann);
             }
             Rule::EOI => (),
@@ -53,7 +75,13 @@ pub fn parse_file(input: &str) -> Result<CadlDocument, String> {
     Ok(doc)
 }
 
-fn parse_interface(pair: Pair<Rule>) -> InterfaceDef {
+fn span_of(pair: &Pair<Rule>) -> Span {
+    let (line, column) = pair.as_span().start_pos().line_col();
+    Span { line, column }
+}
+
+fn parse_interface(pair: Pair<Rule>, path: &str, source_map: &mut HashMap<String, Span>) -> InterfaceDef {
+    source_map.insert(path.to_string(), span_of(&pair));
     let mut inner = pair.into_inner();
     // input string: @interface Name { ... }
     // first token is "interface" keyword (implicit), second is identifier
@@ -68,9 +96,11 @@ fn parse_interface(pair: Pair<Rule>) -> InterfaceDef {
     for item in content.into_inner() {
         match item.as_rule() {
             Rule::method_def => {
+                source_map.insert(format!("{}.methods[{}]", path, methods.len()), span_of(&item));
                 methods.push(parse_method(item));
             }
             Rule::annotation => {
+                source_map.insert(format!("{}.annotations[{}]", path, annotations.len()), span_of(&item));
                 annotations.push(parse_annotation(item));
             }
             _ => {}
@@ -189,7 +219,8 @@ fn parse_value(pair: Pair<Rule>) -> Value {
     }
 }
 
-fn parse_impl(pair: Pair<Rule>) -> ImplDef {
+fn parse_impl(pair: Pair<Rule>, path: &str, source_map: &mut HashMap<String, Span>) -> ImplDef {
+    source_map.insert(path.to_string(), span_of(&pair));
     let mut inner = pair.into_inner();
     let name = inner.next().unwrap().as_str().to_string();
     let content = inner.next().unwrap();
@@ -206,6 +237,7 @@ fn parse_impl(pair: Pair<Rule>) -> ImplDef {
                 attributes.insert(key, val);
             }
             Rule::annotation => {
+                source_map.insert(format!("{}.annotations[{}]", path, annotations.len()), span_of(&item));
                 annotations.push(parse_annotation(item));
             }
             _ => {}
@@ -5,16 +5,30 @@
 //! - Find shared utilities and common patterns
 //! - Detect composition relationships between files
 //! - Determine optimal chunking for maximum reuse
+//! - Split Cargo, npm/yarn and pnpm workspaces into their member packages
 
 use crate::atomic::{
     AliasRegistry, AtomicChunk, ChunkCategory, ChunkComposition,
     ChunkGranularity, ChunkReference,
 };
-use crate::smart_chunker::{ChunkingStrategy, FileAnalysis, SmartChunker, SmartChunkerConfig};
+use crate::atomizer::{ParseCache, ParseCacheStats};
+use crate::categorizer::CategoryOverrides;
+use crate::chunking_rules::{ChunkingRule, ChunkingRules};
+use crate::deduplication::{DeduplicationEngine, DuplicateCandidate, DuplicateCluster};
+use crate::error::{CadiError, CadiResult};
+use crate::gitignore::GitIgnore;
+use crate::hash::{chunk_storage_key, ChunkId};
+use crate::import_plan::ImportPlan;
+use crate::license::{combine_licenses, LicenseDetector};
+use crate::graph::{confidence, Edge, EdgeType, GraphEdgeType, GraphStore};
+use crate::smart_chunker::{ChunkingDecision, ChunkingStrategy, FileAnalysis, SmartChunker, SmartChunkerConfig};
+use crate::workspace::{Workspace, WorkspaceKind, WorkspaceMember};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 /// Project type detection
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -47,6 +61,30 @@ impl ProjectType {
             ProjectType::Unknown => "unknown",
         }
     }
+
+    /// Project type declared by a build file in `root` (`Cargo.toml`,
+    /// `package.json`, `pyproject.toml`, ...). A `package.json` project is
+    /// reported as JavaScript and a `Makefile` project as C; telling them
+    /// from TypeScript and C++ takes a look at the sources.
+    pub fn from_markers(root: &Path) -> Option<Self> {
+        if root.join("Cargo.toml").exists() {
+            Some(ProjectType::Rust)
+        } else if root.join("tsconfig.json").exists() {
+            Some(ProjectType::TypeScript)
+        } else if root.join("package.json").exists() {
+            Some(ProjectType::JavaScript)
+        } else if root.join("pyproject.toml").exists() || root.join("setup.py").exists() {
+            Some(ProjectType::Python)
+        } else if root.join("go.mod").exists() {
+            Some(ProjectType::Go)
+        } else if root.join("CMakeLists.txt").exists() || root.join("Makefile").exists() {
+            Some(ProjectType::C)
+        } else if root.join("pom.xml").exists() || root.join("build.gradle").exists() {
+            Some(ProjectType::Java)
+        } else {
+            None
+        }
+    }
 }
 
 /// Configuration for project analysis
@@ -83,6 +121,56 @@ pub struct ProjectAnalyzerConfig {
     /// Namespace for aliases
     #[serde(skip_serializing_if = "Option::is_none")]
     pub namespace: Option<String>,
+
+    /// Persist import state under `.cadi/import-state.json` and reuse the
+    /// chunks of files that have not changed since the last import
+    #[serde(default)]
+    pub incremental: bool,
+
+    /// Ignore any existing import state (the state is still rewritten)
+    #[serde(default)]
+    pub force: bool,
+
+    /// Skip files matched by `.gitignore` files in the project
+    #[serde(default = "default_true")]
+    pub respect_gitignore: bool,
+
+    /// Additional gitignore-style patterns, relative to the project root.
+    /// These apply even when `respect_gitignore` is off.
+    #[serde(default)]
+    pub extra_ignores: Vec<String>,
+
+    /// Per-path strategy overrides; the first matching rule wins and
+    /// unmatched files use the automatic chunking decision
+    #[serde(default)]
+    pub rules: Vec<ChunkingRule>,
+
+    /// Reviewed chunking decisions, used instead of the rules and the
+    /// automatic decision for the files they cover. The plan is validated
+    /// against the project before anything is chunked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plan: Option<ImportPlan>,
+
+    /// Minimum similarity for two chunks to be reported as potential
+    /// duplicates
+    #[serde(default = "default_duplicate_threshold")]
+    pub duplicate_threshold: f64,
+
+    /// Keep the atoms extracted from each file in `.cadi/parse-cache/`
+    /// and reuse them for files with the same content
+    #[serde(default)]
+    pub parse_cache: bool,
+
+    /// Size bound of the parse cache in bytes; least recently used entries
+    /// are evicted beyond it
+    #[serde(default = "default_parse_cache_max_bytes")]
+    pub parse_cache_max_bytes: u64,
+
+    /// Descend into symlinked directories and import symlinked files.
+    /// Directories and files reached through several links are collected
+    /// once either way.
+    #[serde(default)]
+    pub follow_symlinks: bool,
 }
 
 fn default_ignore_dirs() -> Vec<String> {
@@ -124,6 +212,14 @@ fn default_min_composition_files() -> usize {
     2
 }
 
+fn default_duplicate_threshold() -> f64 {
+    0.9
+}
+
+fn default_parse_cache_max_bytes() -> u64 {
+    crate::atomizer::cache::DEFAULT_PARSE_CACHE_BYTES
+}
+
 impl Default for ProjectAnalyzerConfig {
     fn default() -> Self {
         Self {
@@ -135,6 +231,16 @@ impl Default for ProjectAnalyzerConfig {
             merge_small_files: true,
             min_composition_files: 2,
             namespace: None,
+            incremental: false,
+            force: false,
+            respect_gitignore: true,
+            extra_ignores: Vec::new(),
+            rules: Vec::new(),
+            plan: None,
+            duplicate_threshold: default_duplicate_threshold(),
+            parse_cache: false,
+            parse_cache_max_bytes: default_parse_cache_max_bytes(),
+            follow_symlinks: false,
         }
     }
 }
@@ -177,6 +283,48 @@ pub struct ProjectAnalysis {
 
     /// Suggested compositions
     pub compositions: Vec<CompositionSuggestion>,
+
+    /// Workspace tool, if the project is a Cargo, npm/yarn or pnpm workspace
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<WorkspaceKind>,
+
+    /// Workspace members, each analyzed as its own project
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<MemberAnalysis>,
+}
+
+impl ProjectAnalysis {
+    /// The workspace member containing `path`; the innermost one when
+    /// members are nested
+    pub fn member_for(&self, path: &Path) -> Option<&MemberAnalysis> {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        self.members
+            .iter()
+            .filter(|m| relative.starts_with(&m.path))
+            .max_by_key(|m| m.path.components().count())
+    }
+}
+
+/// Analysis of a single workspace member
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberAnalysis {
+    /// Package name from the member's manifest
+    pub name: String,
+
+    /// Alias segment the member's chunks are namespaced under
+    pub alias: String,
+
+    /// Directory relative to the workspace root
+    pub path: PathBuf,
+
+    pub project_type: ProjectType,
+    pub primary_language: String,
+    pub total_files: usize,
+    pub entrypoints: Vec<PathBuf>,
+    pub modules: Vec<ModuleInfo>,
+
+    /// Names of the members this one depends on
+    pub dependencies: Vec<String>,
 }
 
 /// Information about a module/package
@@ -217,6 +365,112 @@ pub struct ImportResult {
 
     /// Import summary
     pub summary: ImportSummary,
+
+    /// Chunks that look like semantic duplicates of each other
+    #[serde(default)]
+    pub duplicates: Vec<DuplicateCluster>,
+
+    /// Dependencies between the package chunks of workspace members
+    #[serde(default)]
+    pub dependencies: Vec<PackageDependency>,
+
+    /// Files that could not be read, analyzed or chunked; the import
+    /// carries on without them
+    #[serde(default)]
+    pub errors: Vec<FileError>,
+
+    /// Chunks whose content the registry already holds; publishing skips
+    /// them, the local aliases and graph nodes still point at their IDs
+    #[serde(default)]
+    pub reused_from_registry: Vec<String>,
+}
+
+impl ImportResult {
+    /// Storage keys of every chunk the import generated (the content hash
+    /// for SHA-256 chunks), as the registry's bulk existence check takes them
+    pub fn chunk_hashes(&self) -> Vec<String> {
+        let mut hashes: Vec<String> = self
+            .chunks
+            .iter()
+            .chain(&self.compositions)
+            .map(|chunk| chunk_storage_key(&chunk.chunk_id))
+            .collect();
+        hashes.sort();
+        hashes.dedup();
+        hashes
+    }
+
+    /// Mark the chunks whose storage key is in `existing` as reused from the
+    /// registry instead of new. Returns how many were marked.
+    pub fn mark_reused_from_registry(&mut self, existing: &HashSet<String>) -> usize {
+        let mut reused: Vec<String> = self
+            .chunks
+            .iter()
+            .chain(&self.compositions)
+            .filter(|chunk| existing.contains(&chunk_storage_key(&chunk.chunk_id)))
+            .map(|chunk| chunk.chunk_id.clone())
+            .collect();
+        reused.sort();
+        reused.dedup();
+
+        self.summary.registry_chunks = reused.len();
+        self.summary.new_chunks = self.summary.new_chunks.saturating_sub(
+            self.chunks
+                .iter()
+                .filter(|chunk| reused.binary_search(&chunk.chunk_id).is_ok())
+                .count(),
+        );
+        self.reused_from_registry = reused;
+        self.summary.registry_chunks
+    }
+
+    /// Whether the registry already holds a chunk (see
+    /// [`ImportResult::mark_reused_from_registry`])
+    pub fn is_reused_from_registry(&self, chunk_id: &str) -> bool {
+        self.reused_from_registry
+            .binary_search_by(|id| id.as_str().cmp(chunk_id))
+            .is_ok()
+    }
+
+    /// Fraction of the project's source files that failed, 0.0 for a
+    /// project without any
+    pub fn failed_fraction(&self) -> f64 {
+        if self.summary.total_files == 0 {
+            0.0
+        } else {
+            self.errors.len() as f64 / self.summary.total_files as f64
+        }
+    }
+}
+
+/// A file an import had to leave out
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileError {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+impl FileError {
+    fn new(path: &Path, error: CadiError) -> Self {
+        match error {
+            CadiError::FileAnalysis { path, reason } => Self { path, reason },
+            error => Self {
+                path: path.to_path_buf(),
+                reason: error.to_string(),
+            },
+        }
+    }
+}
+
+/// A `DEPENDS_ON` edge from one workspace member's package chunk to
+/// another's
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageDependency {
+    pub from: String,
+    pub to: String,
+    pub from_member: String,
+    pub to_member: String,
+    pub edge_type: GraphEdgeType,
 }
 
 /// Summary of import operation
@@ -231,9 +485,187 @@ pub struct ImportSummary {
     pub skipped_files: usize,
     pub categories: HashMap<String, usize>,
     pub aliases_created: usize,
+    /// Chunks carried forward from the previous import
+    #[serde(default)]
+    pub reused_chunks: usize,
+    /// Chunks generated during this import
+    #[serde(default)]
+    pub new_chunks: usize,
+    /// Chunks in a potential duplicate cluster
+    #[serde(default)]
+    pub potential_duplicates: usize,
+    /// Files left out because they could not be read, analyzed or chunked
+    #[serde(default)]
+    pub failed_files: usize,
+    /// Chunks the registry already held, so publishing skips them
+    #[serde(default)]
+    pub registry_chunks: usize,
+    /// Workspace members and their chunk counts
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<MemberSummary>,
+    /// Parse cache hits and misses, when the cache is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parse_cache: Option<ParseCacheStats>,
     pub duration_ms: u128,
 }
 
+/// Chunks imported from one workspace member
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberSummary {
+    pub name: String,
+    pub path: PathBuf,
+    pub chunks: usize,
+}
+
+/// Location of the incremental import state, relative to the project root
+pub const IMPORT_STATE_FILE: &str = ".cadi/import-state.json";
+
+/// State persisted between incremental imports
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportState {
+    /// Hash of the analyzer config that produced this state
+    pub config_hash: String,
+
+    /// Per-file fingerprints and the chunks generated from them
+    pub files: Vec<FileImportState>,
+
+    /// Atomic chunks generated by the previous import
+    pub chunks: Vec<AtomicChunk>,
+
+    /// IDs of the composition chunks from the previous import
+    #[serde(default)]
+    pub compositions: Vec<String>,
+
+    /// Alias registry from the previous import
+    pub alias_registry: AliasRegistry,
+
+    /// Workspace members at the previous import; chunks are only reused
+    /// while the members stay the same, as they carry member aliases
+    #[serde(default)]
+    pub members: Vec<WorkspaceMember>,
+}
+
+/// Fingerprint of a single imported file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileImportState {
+    #[serde(with = "crate::atomic::raw_path")]
+    pub path: PathBuf,
+    /// Modification time in nanoseconds since the Unix epoch
+    pub mtime: u64,
+    pub size: u64,
+    pub content_hash: String,
+    pub chunk_ids: Vec<String>,
+    /// Whether the chunker decided to skip this file
+    #[serde(default)]
+    pub skipped: bool,
+    pub analysis: FileAnalysis,
+}
+
+/// Phase of an import, in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportPhase {
+    CollectingFiles,
+    Analyzing,
+    Chunking,
+    GraphInsertion,
+}
+
+impl std::fmt::Display for ImportPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ImportPhase::CollectingFiles => "Collecting files",
+            ImportPhase::Analyzing => "Analyzing",
+            ImportPhase::Chunking => "Chunking",
+            ImportPhase::GraphInsertion => "Inserting into graph",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Progress report of a running import
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportProgress {
+    pub phase: ImportPhase,
+    /// Files done in this phase
+    pub processed: usize,
+    /// Files in this phase (0 while collecting)
+    pub total: usize,
+    /// File being worked on
+    pub current_file: Option<PathBuf>,
+}
+
+impl ImportProgress {
+    fn new(phase: ImportPhase, processed: usize, total: usize, current_file: Option<&Path>) -> Self {
+        Self {
+            phase,
+            processed,
+            total,
+            current_file: current_file.map(Path::to_path_buf),
+        }
+    }
+}
+
+/// Cooperative cancellation flag for imports. Clones share the flag, so one
+/// can be handed to a Ctrl-C handler while the import runs.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Error returned by a cancelled import
+    fn check(&self) -> std::io::Result<()> {
+        if self.is_cancelled() {
+            Err(std::io::Error::new(std::io::ErrorKind::Interrupted, "import cancelled"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// A file seen during analysis, and whether its previous chunks can be reused
+struct ScannedFile {
+    state: FileImportState,
+    reused: bool,
+}
+
+/// Files and directories collected so far, by identity on disk, so that
+/// symlinks and hard links to them are not collected twice
+#[derive(Default)]
+struct VisitedFiles {
+    #[cfg(unix)]
+    seen: HashSet<(u64, u64)>,
+    #[cfg(not(unix))]
+    seen: HashSet<PathBuf>,
+}
+
+impl VisitedFiles {
+    /// Record `path`, returning whether it was not seen before through
+    /// another path. Paths whose identity can't be read count as new.
+    fn first_visit(&mut self, path: &Path) -> bool {
+        #[cfg(unix)]
+        let identity = {
+            use std::os::unix::fs::MetadataExt;
+            std::fs::metadata(path).map(|m| (m.dev(), m.ino()))
+        };
+        #[cfg(not(unix))]
+        let identity = std::fs::canonicalize(path);
+
+        identity.map_or(true, |identity| self.seen.insert(identity))
+    }
+}
+
 /// The Project Analyzer
 pub struct ProjectAnalyzer {
     config: ProjectAnalyzerConfig,
@@ -252,8 +684,27 @@ impl ProjectAnalyzer {
         Self::new(ProjectAnalyzerConfig::default())
     }
 
-    /// Analyze an entire project
+    /// Analyze an entire project. Files that fail to analyze are left out.
     pub fn analyze_project(&self, root: &Path) -> std::io::Result<ProjectAnalysis> {
+        let (analysis, _, errors) =
+            self.scan_project(root, &self.chunker, None, &|_| {}, &CancellationToken::new())?;
+        for error in errors {
+            tracing::warn!("skipping {}: {}", error.path.display(), error.reason);
+        }
+        Ok(analysis)
+    }
+
+    /// Analyze a project, reusing the analyses of files that match the
+    /// fingerprints recorded in `previous`. Files that fail are returned
+    /// as errors instead of failing the scan.
+    fn scan_project(
+        &self,
+        root: &Path,
+        chunker: &SmartChunker,
+        previous: Option<&ImportState>,
+        progress: &dyn Fn(ImportProgress),
+        cancel: &CancellationToken,
+    ) -> std::io::Result<(ProjectAnalysis, HashMap<PathBuf, ScannedFile>, Vec<FileError>)> {
         let name = root
             .file_name()
             .and_then(|n| n.to_str())
@@ -261,28 +712,50 @@ impl ProjectAnalyzer {
             .to_string();
 
         // Collect all files
+        progress(ImportProgress::new(ImportPhase::CollectingFiles, 0, 0, None));
         let files = self.collect_files(root)?;
         let total_files = files.len();
 
+        let previous_files: HashMap<&Path, &FileImportState> = previous
+            .map(|state| {
+                state
+                    .files
+                    .iter()
+                    .map(|f| (f.path.as_path(), f))
+                    .collect()
+            })
+            .unwrap_or_default();
+
         // Analyze each file
         let mut file_analyses = Vec::new();
+        let mut scanned = HashMap::new();
         let mut total_lines = 0;
         let mut language_counts: HashMap<String, usize> = HashMap::new();
         let mut entrypoints = Vec::new();
+        let mut errors = Vec::new();
 
-        for file_path in &files {
-            if let Ok(content) = std::fs::read_to_string(file_path) {
-                let analysis = self.chunker.analyze_file(file_path, &content);
-                total_lines += analysis.total_lines;
-
-                *language_counts.entry(analysis.language.clone()).or_insert(0) += 1;
+        for (i, file_path) in files.iter().enumerate() {
+            cancel.check()?;
+            progress(ImportProgress::new(ImportPhase::Analyzing, i, total_files, Some(file_path)));
 
-                if analysis.is_entrypoint {
-                    entrypoints.push(file_path.clone());
+            let file = match self.scan_file(chunker, file_path, previous_files.get(file_path.as_path())) {
+                Ok(file) => file,
+                Err(error) => {
+                    errors.push(error);
+                    continue;
                 }
+            };
+            let analysis = file.state.analysis.clone();
+            total_lines += analysis.total_lines;
 
-                file_analyses.push(analysis);
+            *language_counts.entry(analysis.language.clone()).or_insert(0) += 1;
+
+            if analysis.is_entrypoint {
+                entrypoints.push(file_path.clone());
             }
+
+            file_analyses.push(analysis);
+            scanned.insert(file_path.clone(), file);
         }
 
         // Determine project type and primary language
@@ -303,7 +776,19 @@ impl ProjectAnalyzer {
             Vec::new()
         };
 
-        Ok(ProjectAnalysis {
+        // Analyze workspace members as projects of their own
+        let workspace = Workspace::detect(root, &self.config.ignore_dirs);
+        let members = workspace
+            .as_ref()
+            .map(|ws| {
+                ws.members
+                    .iter()
+                    .map(|member| self.analyze_member(root, ws, member, &file_analyses))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let analysis = ProjectAnalysis {
             root: root.to_path_buf(),
             name,
             project_type,
@@ -316,40 +801,264 @@ impl ProjectAnalyzer {
             modules,
             shared_utilities,
             compositions,
+            workspace: workspace.map(|ws| ws.kind),
+            members,
+        };
+
+        Ok((analysis, scanned, errors))
+    }
+
+    /// Fingerprint and analyze a single file. Files whose mtime and size (or,
+    /// failing that, content hash) match the previous import are not re-analyzed.
+    fn scan_file(
+        &self,
+        chunker: &SmartChunker,
+        path: &Path,
+        previous: Option<&&FileImportState>,
+    ) -> Result<ScannedFile, FileError> {
+        let metadata = path.metadata().map_err(|e| FileError::new(path, e.into()))?;
+        let size = metadata.len();
+        let mtime = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+
+        if let Some(prev) = previous {
+            if prev.mtime == mtime && prev.size == size {
+                return Ok(ScannedFile {
+                    state: (*prev).clone(),
+                    reused: true,
+                });
+            }
+        }
+
+        let content = std::fs::read_to_string(path).map_err(|e| FileError::new(path, e.into()))?;
+        let content_hash = hex::encode(Sha256::digest(content.as_bytes()));
+
+        if let Some(prev) = previous {
+            if prev.content_hash == content_hash {
+                return Ok(ScannedFile {
+                    state: FileImportState {
+                        mtime,
+                        ..(*prev).clone()
+                    },
+                    reused: true,
+                });
+            }
+        }
+
+        let analysis = chunker.analyze_file(path, &content).map_err(|e| FileError::new(path, e))?;
+        Ok(ScannedFile {
+            state: FileImportState {
+                path: path.to_path_buf(),
+                mtime,
+                size,
+                content_hash,
+                chunk_ids: Vec::new(),
+                skipped: false,
+                analysis,
+            },
+            reused: false,
         })
     }
 
     /// Import a project - analyze and create all chunks
+    ///
+    /// With `incremental` enabled, files unchanged since the previous import
+    /// keep their chunks and aliases, and chunks of deleted files are dropped.
     pub fn import_project(&self, root: &Path) -> std::io::Result<ImportResult> {
+        self.import_project_with_progress(root, |_| {}, &CancellationToken::new())
+    }
+
+    /// Import a project, reporting each phase to `progress` and checking
+    /// `cancel` between files. A cancelled import fails with
+    /// `ErrorKind::Interrupted` before anything is persisted, so the
+    /// incremental import state stays that of the previous import.
+    pub fn import_project_with_progress(
+        &self,
+        root: &Path,
+        progress: impl Fn(ImportProgress),
+        cancel: &CancellationToken,
+    ) -> std::io::Result<ImportResult> {
         let start = std::time::Instant::now();
 
+        let rules = ChunkingRules::compile(&self.config.rules)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+        let overrides = CategoryOverrides::load(root)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+
+        let previous = if self.config.incremental && !self.config.force {
+            self.load_import_state(root)
+        } else {
+            None
+        };
+
+        // Files whose content was parsed before reuse the extracted atoms
+        let parse_cache = self.config.parse_cache.then(|| {
+            Arc::new(ParseCache::for_project(root).with_max_bytes(self.config.parse_cache_max_bytes))
+        });
+        let cached_chunker = parse_cache
+            .as_ref()
+            .map(|cache| SmartChunker::new(self.config.chunker_config.clone()).with_parse_cache(cache.clone()));
+
         // Analyze project
-        let analysis = self.analyze_project(root)?;
+        let (analysis, mut scanned, mut errors) = self.scan_project(
+            root,
+            cached_chunker.as_ref().unwrap_or(&self.chunker),
+            previous.as_ref(),
+            &progress,
+            cancel,
+        )?;
+        if let Some(plan) = &self.config.plan {
+            plan.validate(root, &analysis.files)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+        }
+
+        let previous_chunks: HashMap<&str, &AtomicChunk> = previous
+            .iter()
+            .flat_map(|state| state.chunks.iter())
+            .map(|c| (c.chunk_id.as_str(), c))
+            .collect();
+
+        // Member aliases are baked into chunks, so a changed set of
+        // workspace members invalidates every previous chunk
+        let members_changed = previous.as_ref().is_some_and(|state| {
+            let before = state.members.iter().map(|m| (&m.name, &m.path));
+            !before.eq(analysis.members.iter().map(|m| (&m.name, &m.path)))
+        });
+
+        // A reused file is only usable if all of its chunks were persisted
+        for file in scanned.values_mut() {
+            if file.reused
+                && (members_changed
+                    || !file
+                    .state
+                    .chunk_ids
+                    .iter()
+                    .all(|id| previous_chunks.contains_key(id.as_str())))
+            {
+                file.reused = false;
+            }
+        }
+
+        // Start from the previous aliases, dropping every chunk that is not
+        // carried forward (changed files, deleted files, old compositions)
+        let mut alias_registry = previous
+            .as_ref()
+            .map(|state| state.alias_registry.clone())
+            .unwrap_or_default();
+        let carried: HashSet<&str> = scanned
+            .values()
+            .filter(|f| f.reused)
+            .flat_map(|f| f.state.chunk_ids.iter().map(String::as_str))
+            .collect();
+        let stale: Vec<String> = alias_registry
+            .chunks
+            .keys()
+            .filter(|id| !carried.contains(id.as_str()))
+            .cloned()
+            .collect();
+        for chunk_id in &stale {
+            alias_registry.unregister_chunk(chunk_id);
+        }
 
         // Create chunks
         let mut chunks = Vec::new();
-        let mut alias_registry = AliasRegistry::new();
+        let mut licenses = LicenseDetector::new(root);
         let mut skipped_files = 0;
+        let mut reused_chunks = 0;
+        let mut new_chunks = 0;
         let mut categories: HashMap<String, usize> = HashMap::new();
+        // Indices into `chunks` of each member's chunks
+        let mut member_chunks: HashMap<&str, Vec<usize>> = HashMap::new();
+
+        let total_analyzed = analysis.files.len();
+        for (i, file_analysis) in analysis.files.iter().enumerate() {
+            cancel.check()?;
+            progress(ImportProgress::new(
+                ImportPhase::Chunking,
+                i,
+                total_analyzed,
+                Some(&file_analysis.path),
+            ));
+
+            let Some(scanned_file) = scanned.get_mut(&file_analysis.path) else {
+                continue;
+            };
+            let member = analysis.member_for(&file_analysis.path);
+
+            let relative = file_analysis.path.strip_prefix(root).unwrap_or(&file_analysis.path);
+            let rule = rules.resolve(relative);
+            let categorized = categorize(file_analysis, rule, overrides.resolve(relative));
+
+            let file_chunks: Vec<AtomicChunk> = if scanned_file.reused {
+                if scanned_file.state.skipped {
+                    skipped_files += 1;
+                    continue;
+                }
+
+                let mut carried: Vec<AtomicChunk> = scanned_file
+                    .state
+                    .chunk_ids
+                    .iter()
+                    .filter_map(|id| previous_chunks.get(id.as_str()).map(|c| (*c).clone()))
+                    .collect();
+                reused_chunks += carried.len();
+
+                // The file is unchanged but the license files around it may not be
+                if let Ok(content) = std::fs::read_to_string(&file_analysis.path) {
+                    let license = licenses.detect(&file_analysis.path, &content);
+                    for chunk in &mut carried {
+                        chunk.license = license.clone();
+                    }
+                }
+                // Nor may the category overrides
+                for chunk in &mut carried {
+                    chunk.categories = vec![categorized.category.clone()];
+                    chunk.category_confidence = categorized.category_confidence();
+                }
+                carried
+            } else {
+                let content = match std::fs::read_to_string(&file_analysis.path) {
+                    Ok(content) => content,
+                    Err(e) => {
+                        errors.push(FileError::new(&file_analysis.path, e.into()));
+                        continue;
+                    }
+                };
 
-        for file_analysis in &analysis.files {
-            if let Ok(content) = std::fs::read_to_string(&file_analysis.path) {
-                let decision = self.chunker.decide_chunking(file_analysis);
+                let decision = self.decide(relative, &categorized, rule);
 
                 if decision.strategy == ChunkingStrategy::Skip {
+                    scanned_file.state.skipped = true;
                     skipped_files += 1;
                     continue;
                 }
 
-                let _relative_path = file_analysis
-                    .path
-                    .clone();
+                let mut generated =
+                    match self.chunker.generate_chunks(&file_analysis.path, &content, &decision) {
+                        Ok(generated) => generated,
+                        Err(e) => {
+                            errors.push(FileError::new(&file_analysis.path, e));
+                            continue;
+                        }
+                    };
+                let license = licenses.detect(&file_analysis.path, &content);
 
-                let file_chunks =
-                    self.chunker
-                        .generate_chunks(&file_analysis.path, &content, &decision);
+                for chunk in &mut generated {
+                    chunk.license = license.clone();
+
+                    // Workspace members prefix their aliases with the
+                    // package and default the namespace to the workspace
+                    if let Some(member) = member {
+                        for alias in &mut chunk.aliases {
+                            alias.path = format!("{}/{}", member.alias, alias.path);
+                            alias.namespace = Some(analysis.name.clone());
+                        }
+                    }
 
-                for mut chunk in file_chunks {
                     // Add namespace if configured
                     if let Some(ref ns) = self.config.namespace {
                         for alias in &mut chunk.aliases {
@@ -363,18 +1072,37 @@ impl ProjectAnalyzer {
                         let unique_alias = alias_registry.generate_unique(&alias_path);
                         alias_registry.register(&unique_alias, &chunk.chunk_id);
                     }
+                }
 
-                    // Count categories
-                    for cat in &chunk.categories {
-                        let cat_str = format!("{:?}", cat);
-                        *categories.entry(cat_str).or_insert(0) += 1;
-                    }
+                scanned_file.state.chunk_ids =
+                    generated.iter().map(|c| c.chunk_id.clone()).collect();
+                new_chunks += generated.len();
+                generated
+            };
+
+            for chunk in file_chunks {
+                // Count categories
+                for cat in &chunk.categories {
+                    let cat_str = format!("{:?}", cat);
+                    *categories.entry(cat_str).or_insert(0) += 1;
+                }
 
-                    chunks.push(chunk);
+                if let Some(member) = member {
+                    member_chunks.entry(member.name.as_str()).or_default().push(chunks.len());
                 }
+                chunks.push(chunk);
             }
         }
 
+        cancel.check()?;
+        progress(ImportProgress::new(ImportPhase::Chunking, total_analyzed, total_analyzed, None));
+
+        // Failed files stay out of the import state so the next import
+        // retries them
+        for error in &errors {
+            scanned.remove(&error.path);
+        }
+
         // Create composition chunks
         let mut compositions = Vec::new();
         for suggestion in &analysis.compositions {
@@ -385,6 +1113,79 @@ impl ProjectAnalyzer {
             }
         }
 
+        // One package chunk per workspace member, depending on the package
+        // chunks of the members it depends on
+        let namespace = self.config.namespace.as_deref().unwrap_or(&analysis.name);
+        let mut packages: HashMap<&str, usize> = HashMap::new();
+        for member in &analysis.members {
+            let Some(indices) = member_chunks.get(member.name.as_str()) else {
+                continue;
+            };
+            let components: Vec<&AtomicChunk> = indices.iter().map(|&i| &chunks[i]).collect();
+            let package = self.compose_chunk(
+                &member.name,
+                &format!("{} workspace package", member.name),
+                ChunkCategory::Logic,
+                &format!("{}/{}", namespace, member.alias),
+                &components,
+                &mut alias_registry,
+            );
+            packages.insert(member.name.as_str(), compositions.len());
+            compositions.push(package);
+        }
+
+        let mut dependencies = Vec::new();
+        for member in &analysis.members {
+            let Some(&from) = packages.get(member.name.as_str()) else {
+                continue;
+            };
+            for dependency in &member.dependencies {
+                let Some(&to) = packages.get(dependency.as_str()) else {
+                    continue;
+                };
+                let to_id = compositions[to].chunk_id.clone();
+                compositions[from].requires.push(to_id.clone());
+                dependencies.push(PackageDependency {
+                    from: compositions[from].chunk_id.clone(),
+                    to: to_id,
+                    from_member: member.name.clone(),
+                    to_member: dependency.clone(),
+                    edge_type: GraphEdgeType::DEPENDS_ON,
+                });
+            }
+        }
+
+        if self.config.incremental {
+            let mut files: Vec<FileImportState> =
+                scanned.into_values().map(|f| f.state).collect();
+            files.sort_by(|a, b| a.path.cmp(&b.path));
+
+            let mut seen = HashSet::new();
+            let state = ImportState {
+                config_hash: self.config_hash(),
+                files,
+                chunks: chunks
+                    .iter()
+                    .filter(|c| seen.insert(c.chunk_id.clone()))
+                    .cloned()
+                    .collect(),
+                compositions: compositions.iter().map(|c| c.chunk_id.clone()).collect(),
+                alias_registry: alias_registry.clone(),
+                members: analysis
+                    .members
+                    .iter()
+                    .map(|m| WorkspaceMember {
+                        name: m.name.clone(),
+                        path: m.path.clone(),
+                        dependencies: m.dependencies.clone(),
+                    })
+                    .collect(),
+            };
+            self.save_import_state(root, &state)?;
+        }
+
+        let duplicates = self.find_duplicates(&chunks);
+
         let duration_ms = start.elapsed().as_millis();
 
         let summary = ImportSummary {
@@ -397,6 +1198,21 @@ impl ProjectAnalyzer {
             skipped_files,
             categories,
             aliases_created: alias_registry.aliases.len(),
+            reused_chunks,
+            new_chunks,
+            potential_duplicates: duplicates.iter().map(|c| c.chunk_ids.len()).sum(),
+            failed_files: errors.len(),
+            registry_chunks: 0,
+            members: analysis
+                .members
+                .iter()
+                .map(|m| MemberSummary {
+                    name: m.name.clone(),
+                    path: m.path.clone(),
+                    chunks: member_chunks.get(m.name.as_str()).map_or(0, Vec::len),
+                })
+                .collect(),
+            parse_cache: parse_cache.map(|cache| cache.stats()),
             duration_ms,
         };
 
@@ -406,49 +1222,263 @@ impl ProjectAnalyzer {
             alias_registry,
             compositions,
             summary,
+            duplicates,
+            dependencies,
+            errors,
+            reused_from_registry: Vec::new(),
         })
     }
 
+    /// Chunking decisions for every file of a project without generating
+    /// chunks, for review before importing with [`ProjectAnalyzerConfig::plan`].
+    /// Files the configured plan covers keep their planned decisions. Also
+    /// returns the analysis, to validate edits of the plan against.
+    pub fn plan_project(&self, root: &Path) -> std::io::Result<(ProjectAnalysis, ImportPlan)> {
+        let invalid = |e: CadiError| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string());
+        let rules = ChunkingRules::compile(&self.config.rules).map_err(invalid)?;
+        let overrides = CategoryOverrides::load(root).map_err(invalid)?;
+
+        let analysis = self.analyze_project(root)?;
+        let files = analysis
+            .files
+            .iter()
+            .map(|file_analysis| {
+                let relative = file_analysis.path.strip_prefix(root).unwrap_or(&file_analysis.path);
+                let rule = rules.resolve(relative);
+                let categorized = categorize(file_analysis, rule, overrides.resolve(relative));
+                ChunkingDecision {
+                    file_path: relative.to_path_buf(),
+                    ..self.decide(relative, &categorized, rule)
+                }
+            })
+            .collect();
+
+        Ok((analysis, ImportPlan { files }))
+    }
+
+    /// How to chunk a file: as planned, as its chunking rule requires, or
+    /// as the chunker decides
+    fn decide(&self, relative: &Path, analysis: &FileAnalysis, rule: Option<&ChunkingRule>) -> ChunkingDecision {
+        if let Some(planned) = self.config.plan.as_ref().and_then(|plan| plan.decision_for(relative)) {
+            return ChunkingDecision {
+                file_path: analysis.path.clone(),
+                ..planned.clone()
+            };
+        }
+        match rule {
+            Some(rule) => self.chunker.decide_chunking_as(analysis, rule.strategy.clone()),
+            None => self.chunker.decide_chunking(analysis),
+        }
+    }
+
+    /// Load the state of the previous import, if it exists and was produced
+    /// with the same configuration
+    pub fn load_import_state(&self, root: &Path) -> Option<ImportState> {
+        let content = std::fs::read_to_string(root.join(IMPORT_STATE_FILE)).ok()?;
+        let state: ImportState = serde_json::from_str(&content).ok()?;
+        (state.config_hash == self.config_hash()).then_some(state)
+    }
+
+    fn save_import_state(&self, root: &Path, state: &ImportState) -> std::io::Result<()> {
+        let path = root.join(IMPORT_STATE_FILE);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string(state)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        std::fs::write(path, json)
+    }
+
+    /// Hash of the settings that affect chunk generation
+    fn config_hash(&self) -> String {
+        let mut settings = serde_json::json!({
+            "chunker": self.config.chunker_config,
+            "namespace": self.config.namespace,
+            "detect_compositions": self.config.detect_compositions,
+            "min_composition_files": self.config.min_composition_files,
+            "rules": self.config.rules,
+        });
+        // Only present with a plan, so states from before plans stay valid
+        if let Some(plan) = &self.config.plan {
+            settings["plan"] = serde_json::json!(plan);
+        }
+        hex::encode(Sha256::digest(settings.to_string().as_bytes()))
+    }
+
+    /// Add `Calls` edges between chunks whose entities call each other
+    /// within the same file. Returns the number of edges added. Calls are
+    /// found by pattern matching, so the edges get regex confidence.
+    pub fn emit_call_edges(
+        &self,
+        files: &[FileAnalysis],
+        chunks: &[AtomicChunk],
+        graph: &GraphStore,
+    ) -> CadiResult<usize> {
+        self.emit_call_edges_with_progress(files, chunks, graph, |_| {}, &CancellationToken::new())
+    }
+
+    /// [`emit_call_edges`](Self::emit_call_edges) with progress reporting.
+    /// The edges are written in a single batch once every file has been
+    /// visited, so cancelling leaves the graph untouched.
+    pub fn emit_call_edges_with_progress(
+        &self,
+        files: &[FileAnalysis],
+        chunks: &[AtomicChunk],
+        graph: &GraphStore,
+        progress: impl Fn(ImportProgress),
+        cancel: &CancellationToken,
+    ) -> CadiResult<usize> {
+        let mut edges = Vec::new();
+
+        for (i, file) in files.iter().enumerate() {
+            cancel.check()?;
+            progress(ImportProgress::new(ImportPhase::GraphInsertion, i, files.len(), Some(&file.path)));
+
+            // Entity name -> chunk for this file
+            let file_chunks: HashMap<&str, &str> = chunks
+                .iter()
+                .filter(|c| c.sources.first().is_some_and(|s| s.is_in(&file.path)))
+                .map(|c| (c.name.as_str(), c.chunk_id.as_str()))
+                .collect();
+
+            for entity in &file.entities {
+                let Some(caller) = file_chunks.get(entity.name.as_str()) else {
+                    continue;
+                };
+                for callee in &entity.calls {
+                    if let Some(target) = file_chunks.get(callee.as_str()) {
+                        if target != caller {
+                            edges.push(
+                                Edge::new(*caller, *target, EdgeType::Calls).with_confidence(confidence::REGEX),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        cancel.check()?;
+        let added = graph.add_edges_batch(&edges)?.into_result()?;
+        progress(ImportProgress::new(ImportPhase::GraphInsertion, files.len(), files.len(), None));
+        Ok(added)
+    }
+
     // ========================================================================
     // Private helpers
     // ========================================================================
 
+    /// Cluster entity chunks whose source code is semantically equivalent
+    fn find_duplicates(&self, chunks: &[AtomicChunk]) -> Vec<DuplicateCluster> {
+        let mut files: HashMap<PathBuf, Option<Vec<String>>> = HashMap::new();
+        let mut candidates = Vec::new();
+
+        for chunk in chunks {
+            let Some(source) = chunk.sources.first() else {
+                continue;
+            };
+            let (Some(start), Some(end)) = (source.start_line, source.end_line) else {
+                continue;
+            };
+            let path = source.path();
+            let lines = files.entry(path.clone()).or_insert_with(|| {
+                std::fs::read_to_string(&path)
+                    .ok()
+                    .map(|content| content.lines().map(String::from).collect())
+            });
+            let Some(lines) = lines else {
+                continue;
+            };
+
+            let start = start.saturating_sub(1).min(lines.len());
+            let end = end.clamp(start, lines.len());
+            candidates.push(DuplicateCandidate::new(
+                chunk.chunk_id.clone(),
+                chunk.language.clone(),
+                lines[start..end].join("\n"),
+            ));
+        }
+
+        DeduplicationEngine::find_semantic_duplicates(&candidates, self.config.duplicate_threshold)
+    }
+
     fn collect_files(&self, root: &Path) -> std::io::Result<Vec<PathBuf>> {
         let mut files = Vec::new();
-        self.collect_files_recursive(root, &mut files)?;
+        let mut ignore = GitIgnore::new();
+        ignore.add_patterns(Path::new(""), self.config.extra_ignores.iter().map(String::as_str));
+        let mut visited = VisitedFiles::default();
+        self.collect_files_recursive(root, root, &ignore, &mut visited, &mut files)?;
         Ok(files)
     }
 
-    fn collect_files_recursive(&self, dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    fn collect_files_recursive(
+        &self,
+        root: &Path,
+        dir: &Path,
+        ignore: &GitIgnore,
+        visited: &mut VisitedFiles,
+        files: &mut Vec<PathBuf>,
+    ) -> std::io::Result<()> {
         if !dir.is_dir() {
             return Ok(());
         }
+        // A directory reached again through a symlink is a cycle or a
+        // duplicate; it was (or is being) collected already
+        if !visited.first_visit(dir) {
+            return Ok(());
+        }
+
+        // Rules from this directory's .gitignore apply to everything below it
+        let relative_dir = dir.strip_prefix(root).unwrap_or(Path::new(""));
+        let mut scoped;
+        let ignore = if self.config.respect_gitignore && dir.join(".gitignore").is_file() {
+            scoped = ignore.clone();
+            scoped.add_file(dir, relative_dir);
+            &scoped
+        } else {
+            ignore
+        };
+
+        // Sorted, so the path kept for a file reachable through several
+        // links doesn't depend on directory order
+        let mut entries = std::fs::read_dir(dir)?.collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
 
-        for entry in std::fs::read_dir(dir)? {
-            let entry = entry?;
+        for entry in entries {
             let path = entry.path();
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+
+            if !self.config.follow_symlinks && entry.file_type()?.is_symlink() {
+                continue;
+            }
 
             if path.is_dir() {
-                let dir_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+                let dir_name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
 
                 // Skip ignored directories
-                if self.config.ignore_dirs.contains(&dir_name.to_string()) {
+                if self.config.ignore_dirs.iter().any(|d| *d == dir_name) {
                     continue;
                 }
                 if dir_name.starts_with('.') {
                     continue;
                 }
+                if ignore.is_ignored(relative, true) {
+                    continue;
+                }
 
-                self.collect_files_recursive(&path, files)?;
+                self.collect_files_recursive(root, &path, ignore, visited, files)?;
             } else if path.is_file() {
-                let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+                if ignore.is_ignored(relative, false) {
+                    continue;
+                }
+
+                let file_name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
 
                 // Check against ignore patterns
                 let should_ignore = self.config.ignore_patterns.iter().any(|pattern| {
                     if pattern.starts_with('*') {
                         file_name.ends_with(&pattern[1..])
                     } else {
-                        file_name == pattern
+                        file_name == pattern.as_str()
                     }
                 });
 
@@ -463,8 +1493,8 @@ impl ProjectAnalyzer {
                     }
                 }
 
-                // Check if it's a source file
-                if self.is_source_file(&path) {
+                // Check if it's a source file, and the first path to it
+                if self.is_source_file(&path) && visited.first_visit(&path) {
                     files.push(path);
                 }
             }
@@ -519,38 +1549,19 @@ impl ProjectAnalyzer {
         root: &Path,
         language_counts: &HashMap<String, usize>,
     ) -> (ProjectType, String) {
-        // Check for project markers
-        if root.join("Cargo.toml").exists() {
-            return (ProjectType::Rust, "rust".to_string());
-        }
-        if root.join("tsconfig.json").exists() {
-            return (ProjectType::TypeScript, "typescript".to_string());
-        }
-        if root.join("package.json").exists() {
-            // Could be TS or JS
-            if language_counts.get("typescript").unwrap_or(&0)
-                > language_counts.get("javascript").unwrap_or(&0)
-            {
+        let count = |language: &str| language_counts.get(language).copied().unwrap_or(0);
+        match ProjectType::from_markers(root) {
+            Some(ProjectType::JavaScript) if count("typescript") > count("javascript") => {
                 return (ProjectType::TypeScript, "typescript".to_string());
             }
-            return (ProjectType::JavaScript, "javascript".to_string());
-        }
-        if root.join("pyproject.toml").exists() || root.join("setup.py").exists() {
-            return (ProjectType::Python, "python".to_string());
-        }
-        if root.join("go.mod").exists() {
-            return (ProjectType::Go, "go".to_string());
-        }
-        if root.join("CMakeLists.txt").exists() || root.join("Makefile").exists() {
-            let cpp_count = language_counts.get("cpp").unwrap_or(&0);
-            let c_count = language_counts.get("c").unwrap_or(&0);
-            if cpp_count > c_count {
+            Some(ProjectType::C) if count("cpp") > count("c") => {
                 return (ProjectType::Cpp, "cpp".to_string());
             }
-            return (ProjectType::C, "c".to_string());
-        }
-        if root.join("pom.xml").exists() || root.join("build.gradle").exists() {
-            return (ProjectType::Java, "java".to_string());
+            Some(project_type) => {
+                let language = project_type.primary_language().to_string();
+                return (project_type, language);
+            }
+            None => {}
         }
 
         // Fallback to most common language
@@ -567,6 +1578,44 @@ impl ProjectAnalyzer {
         }
     }
 
+    /// Analyze the files of one workspace member, leaving out those of
+    /// members nested inside it
+    fn analyze_member(
+        &self,
+        root: &Path,
+        workspace: &Workspace,
+        member: &WorkspaceMember,
+        files: &[FileAnalysis],
+    ) -> MemberAnalysis {
+        let member_root = root.join(&member.path);
+        let files: Vec<FileAnalysis> = files
+            .iter()
+            .filter(|f| {
+                let relative = f.path.strip_prefix(root).unwrap_or(&f.path);
+                workspace.member_for(relative).is_some_and(|m| m.path == member.path)
+            })
+            .cloned()
+            .collect();
+
+        let mut language_counts: HashMap<String, usize> = HashMap::new();
+        for file in &files {
+            *language_counts.entry(file.language.clone()).or_insert(0) += 1;
+        }
+        let (project_type, primary_language) = self.detect_project_type(&member_root, &language_counts);
+
+        MemberAnalysis {
+            name: member.name.clone(),
+            alias: member.alias_segment(),
+            path: member.path.clone(),
+            project_type,
+            primary_language,
+            total_files: files.len(),
+            entrypoints: files.iter().filter(|f| f.is_entrypoint).map(|f| f.path.clone()).collect(),
+            modules: self.detect_modules(&member_root, &files),
+            dependencies: member.dependencies.clone(),
+        }
+    }
+
     fn detect_modules(&self, root: &Path, files: &[FileAnalysis]) -> Vec<ModuleInfo> {
         let mut modules: HashMap<PathBuf, ModuleInfo> = HashMap::new();
 
@@ -717,13 +1766,31 @@ impl ProjectAnalyzer {
             return None;
         }
 
+        Some(self.compose_chunk(
+            &suggestion.name,
+            &suggestion.description,
+            suggestion.category.clone(),
+            &suggestion.name,
+            &component_chunks,
+            registry,
+        ))
+    }
+
+    /// Create a composition chunk aggregating `component_chunks`, registered
+    /// under a unique alias derived from `alias`
+    fn compose_chunk(
+        &self,
+        name: &str,
+        description: &str,
+        category: ChunkCategory,
+        alias: &str,
+        component_chunks: &[&AtomicChunk],
+        registry: &mut AliasRegistry,
+    ) -> AtomicChunk {
         // Create composition content hash from component hashes
-        let mut hasher = Sha256::new();
-        for chunk in &component_chunks {
-            hasher.update(chunk.chunk_id.as_bytes());
-        }
-        let content_hash = hex::encode(hasher.finalize());
-        let chunk_id = format!("chunk:sha256:{}", content_hash);
+        let component_ids: String = component_chunks.iter().map(|c| c.chunk_id.as_str()).collect();
+        let id = ChunkId::from_content(self.config.chunker_config.hash_algorithm, component_ids.as_bytes());
+        let (chunk_id, content_hash) = (id.to_string(), id.digest);
 
         // Create references to component chunks
         let composed_of: Vec<ChunkReference> = component_chunks
@@ -738,21 +1805,22 @@ impl ProjectAnalyzer {
 
         let total_size: usize = component_chunks.iter().map(|c| c.size).sum();
 
-        let alias = registry.generate_unique(&suggestion.name);
+        let alias = registry.generate_unique(alias);
         registry.register(&alias, &chunk_id);
 
         let mut chunk = AtomicChunk::new(
             chunk_id,
-            suggestion.name.clone(),
+            name.to_string(),
             "composition".to_string(),
             content_hash,
             total_size,
         )
         .with_alias(&alias)
         .with_granularity(ChunkGranularity::Package)
-        .with_categories(vec![suggestion.category.clone()]);
+        .with_categories(vec![category])
+        .with_license(combine_licenses(component_chunks.iter().map(|c| c.license.as_str())));
 
-        chunk.description = Some(suggestion.description.clone());
+        chunk.description = Some(description.to_string());
         chunk.composition = ChunkComposition {
             composed_of,
             composed_by: Vec::new(),
@@ -775,13 +1843,32 @@ impl ProjectAnalyzer {
             .into_iter()
             .collect();
 
-        Some(chunk)
+        chunk
     }
 }
 
+/// A file's analysis with its category set explicitly: a category override
+/// wins over the category of its chunking rule, which wins over the
+/// heuristics
+fn categorize(
+    analysis: &FileAnalysis,
+    rule: Option<&ChunkingRule>,
+    category_override: Option<&ChunkCategory>,
+) -> FileAnalysis {
+    let mut categorized = analysis.clone();
+    if let Some(category) = rule.and_then(|rule| rule.category.as_ref()) {
+        categorized.set_category(category.clone());
+    }
+    if let Some(category) = category_override {
+        categorized.set_category(category.clone());
+    }
+    categorized
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::categorizer::CATEGORY_OVERRIDES_FILE;
 
     #[test]
     fn test_project_type_detection() {
@@ -805,4 +1892,608 @@ mod tests {
         assert!(!analyzer.is_source_file(Path::new("test.exe")));
         assert!(!analyzer.is_source_file(Path::new("test.bin")));
     }
+
+    #[test]
+    fn test_call_edges_emitted() {
+        let config = ProjectAnalyzerConfig {
+            chunker_config: SmartChunkerConfig {
+                min_function_lines: 0,
+                min_file_lines_to_split: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let analyzer = ProjectAnalyzer::new(config);
+
+        let content = "fn handler() {\n    let _ = parse_args();\n}\n\nfn parse_args() -> u32 {\n    0\n}\n";
+        let path = Path::new("src/cli.rs");
+        let analysis = analyzer.chunker.analyze_file(path, content).unwrap();
+        let decision = analyzer.chunker.decide_chunking(&analysis);
+        let chunks = analyzer.chunker.generate_chunks(path, content, &decision).unwrap();
+
+        let graph = GraphStore::in_memory().unwrap();
+        let added = analyzer.emit_call_edges(&[analysis], &chunks, &graph).unwrap();
+        assert_eq!(added, 1);
+
+        let id = |name: &str| chunks.iter().find(|c| c.name == name).unwrap().chunk_id.clone();
+        let calls = graph.get_dependencies_of_type(&id("handler"), EdgeType::Calls).unwrap();
+        assert_eq!(calls, vec![id("parse_args")]);
+        assert_eq!(
+            graph.edge_confidence(&id("handler"), &id("parse_args"), EdgeType::Calls).unwrap(),
+            confidence::REGEX
+        );
+    }
+
+    #[test]
+    fn test_incremental_import_reuses_unchanged_chunks() {
+        let root = std::env::temp_dir().join(format!("cadi-incremental-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(
+            root.join("src/math.rs"),
+            "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("src/text.rs"),
+            "pub fn shout(s: &str) -> String {\n    s.to_uppercase()\n}\n",
+        )
+        .unwrap();
+
+        let config = ProjectAnalyzerConfig {
+            incremental: true,
+            ..Default::default()
+        };
+        let analyzer = ProjectAnalyzer::new(config.clone());
+
+        let first = analyzer.import_project(&root).unwrap();
+        assert!(root.join(IMPORT_STATE_FILE).exists());
+        assert_eq!(first.summary.reused_chunks, 0);
+        assert_eq!(first.summary.new_chunks, first.chunks.len());
+
+        let second = analyzer.import_project(&root).unwrap();
+        assert_eq!(second.summary.reused_chunks, first.chunks.len());
+        assert_eq!(second.summary.new_chunks, 0);
+        assert_eq!(second.alias_registry.aliases, first.alias_registry.aliases);
+
+        // Deleting a file drops its chunks from the alias registry
+        let text_id = first
+            .chunks
+            .iter()
+            .find(|c| c.sources[0].file.ends_with("text.rs"))
+            .unwrap()
+            .chunk_id
+            .clone();
+        assert!(second.alias_registry.get_aliases(&text_id).is_some());
+        std::fs::remove_file(root.join("src/text.rs")).unwrap();
+
+        let third = analyzer.import_project(&root).unwrap();
+        assert!(third.alias_registry.get_aliases(&text_id).is_none());
+        assert!(!third.alias_registry.aliases.values().any(|id| *id == text_id));
+        assert_eq!(third.summary.new_chunks, 0);
+
+        // Forcing bypasses the cached state
+        let forced = ProjectAnalyzer::new(ProjectAnalyzerConfig { force: true, ..config });
+        let fourth = forced.import_project(&root).unwrap();
+        assert_eq!(fourth.summary.reused_chunks, 0);
+        assert_eq!(fourth.summary.new_chunks, third.chunks.len());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_parse_cache_reuses_extracted_atoms() {
+        let root = std::env::temp_dir().join(format!("cadi-parse-cache-import-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/math.rs"), "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n").unwrap();
+        std::fs::write(root.join("src/text.rs"), "pub fn shout(s: &str) -> String {\n    s.to_uppercase()\n}\n").unwrap();
+
+        let analyzer = ProjectAnalyzer::new(ProjectAnalyzerConfig {
+            parse_cache: true,
+            ..Default::default()
+        });
+        let first = analyzer.import_project(&root).unwrap();
+        assert_eq!(first.summary.parse_cache, Some(ParseCacheStats { hits: 0, misses: 2, evictions: 0 }));
+
+        // Without incremental state every file is analyzed again, but only
+        // the changed one is parsed
+        std::fs::write(root.join("src/text.rs"), "pub fn whisper(s: &str) -> String {\n    s.to_lowercase()\n}\n").unwrap();
+        let second = analyzer.import_project(&root).unwrap();
+        assert_eq!(second.summary.parse_cache, Some(ParseCacheStats { hits: 1, misses: 1, evictions: 0 }));
+        let ids = |result: &ImportResult, file: &str| -> Vec<String> {
+            let chunks = result.chunks.iter().filter(|c| c.sources[0].file.ends_with(file));
+            chunks.map(|c| c.chunk_id.clone()).collect()
+        };
+        assert_eq!(ids(&first, "math.rs"), ids(&second, "math.rs"));
+        assert_ne!(ids(&first, "text.rs"), ids(&second, "text.rs"));
+
+        assert!(ProjectAnalyzer::default().import_project(&root).unwrap().summary.parse_cache.is_none());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_and_hard_links_are_collected_once() {
+        let root = std::env::temp_dir().join(format!("cadi-symlinks-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/math.rs"), "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n").unwrap();
+        std::fs::hard_link(root.join("src/math.rs"), root.join("src/sum.rs")).unwrap();
+        // A cycle back to the root, and a second way into src
+        std::os::unix::fs::symlink(&root, root.join("src/loop")).unwrap();
+        std::os::unix::fs::symlink(root.join("src"), root.join("lib")).unwrap();
+
+        let files = ProjectAnalyzer::default().collect_files(&root).unwrap();
+        assert_eq!(files, [root.join("src/math.rs")]);
+
+        let following = ProjectAnalyzer::new(ProjectAnalyzerConfig {
+            follow_symlinks: true,
+            ..Default::default()
+        });
+        let files = following.collect_files(&root).unwrap();
+        assert_eq!(files, [root.join("lib/math.rs")]);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_file_names() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let root = std::env::temp_dir().join(format!("cadi-non-utf8-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        let file = root.join("src").join(std::ffi::OsStr::from_bytes(b"caf\xe9.rs"));
+        std::fs::write(&file, "pub fn brew() -> u32 {\n    1\n}\n").unwrap();
+
+        let analyzer = ProjectAnalyzer::new(ProjectAnalyzerConfig {
+            incremental: true,
+            ..Default::default()
+        });
+        let first = analyzer.import_project(&root).unwrap();
+        let source = &first.chunks[0].sources[0];
+        assert!(source.file.ends_with("caf\u{fffd}.rs"));
+        assert_eq!(source.path(), file);
+        assert!(source.is_in(&file));
+
+        // The import state keeps the original path
+        let json = serde_json::to_string(&first.chunks[0]).unwrap();
+        let chunk: AtomicChunk = serde_json::from_str(&json).unwrap();
+        assert_eq!(chunk.sources[0].path(), file);
+        let state = analyzer.load_import_state(&root).unwrap();
+        assert_eq!(state.files[0].path, file);
+        let second = analyzer.import_project(&root).unwrap();
+        assert_eq!(second.summary.reused_chunks, first.chunks.len());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_import_skips_files_that_fail() {
+        let root = std::env::temp_dir().join(format!("cadi-file-errors-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/ok.rs"), "pub fn ok() -> u32 {\n    1\n}\n").unwrap();
+        std::fs::write(root.join("src/empty.rs"), "").unwrap();
+        std::fs::write(root.join("src/bad.rs"), b"pub fn bad() {\n    let s = \"\xff\xfe\";\n}\n").unwrap();
+
+        let config = ProjectAnalyzerConfig {
+            incremental: true,
+            ..Default::default()
+        };
+        let analyzer = ProjectAnalyzer::new(config);
+        let result = analyzer.import_project(&root).unwrap();
+
+        assert_eq!(result.errors.len(), 1);
+        assert!(result.errors[0].path.ends_with("src/bad.rs"));
+        assert_eq!(result.summary.failed_files, 1);
+        assert!((result.failed_fraction() - 1.0 / 3.0).abs() < 1e-9);
+        assert!(result.chunks.iter().any(|c| c.sources[0].file.ends_with("ok.rs")));
+
+        // The failed file is retried once it is fixed
+        std::fs::write(root.join("src/bad.rs"), "pub fn bad() -> u32 {\n    2\n}\n").unwrap();
+        let result = analyzer.import_project(&root).unwrap();
+        assert!(result.errors.is_empty());
+        assert!(result.chunks.iter().any(|c| c.sources[0].file.ends_with("bad.rs")));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_import_detects_and_combines_licenses() {
+        let root = std::env::temp_dir().join(format!("cadi-licenses-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(
+            root.join("Cargo.toml"),
+            "[package]\nname = \"x\"\nversion = \"0.1.0\"\nlicense = \"MIT\"\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("src/a.rs"), "pub fn a() -> u32 {\n    1\n}\n").unwrap();
+        std::fs::write(
+            root.join("src/b.rs"),
+            "// SPDX-License-Identifier: Apache-2.0\npub fn b() -> u32 {\n    2\n}\n",
+        )
+        .unwrap();
+
+        let result = ProjectAnalyzer::default().import_project(&root).unwrap();
+
+        let license = |file: &str| {
+            let chunk = result
+                .chunks
+                .iter()
+                .find(|c| c.sources.iter().any(|s| s.file.ends_with(file)))
+                .unwrap();
+            chunk.license.clone()
+        };
+        assert_eq!(license("src/a.rs"), "MIT");
+        assert_eq!(license("src/b.rs"), "Apache-2.0");
+
+        let module = result.compositions.iter().find(|c| c.name == "src-module").unwrap();
+        assert_eq!(module.license, "Apache-2.0 AND MIT");
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_import_reports_potential_duplicates() {
+        let root = std::env::temp_dir().join(format!("cadi-dupes-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(
+            root.join("src/a.rs"),
+            "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\npub fn greet(name: &str) -> String {\n    format!(\"hi {}\", name)\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("src/b.rs"),
+            "pub fn sum(x: i32, y: i32) -> i32 {\n    x + y\n}\n",
+        )
+        .unwrap();
+
+        let config = ProjectAnalyzerConfig {
+            chunker_config: SmartChunkerConfig {
+                min_function_lines: 0,
+                ..Default::default()
+            },
+            detect_compositions: false,
+            rules: vec![ChunkingRule::new("*.rs", ChunkingStrategy::ByEntity)],
+            ..Default::default()
+        };
+        let result = ProjectAnalyzer::new(config).import_project(&root).unwrap();
+
+        let id = |name: &str| {
+            result.chunks.iter().find(|c| c.name == name).unwrap().chunk_id.clone()
+        };
+        let mut expected = vec![id("add"), id("sum")];
+        expected.sort();
+        assert_eq!(result.duplicates.len(), 1);
+        assert_eq!(result.duplicates[0].chunk_ids, expected);
+        assert_eq!(result.summary.potential_duplicates, 2);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_chunking_rules_route_files() {
+        let root = std::env::temp_dir().join(format!("cadi-rules-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        for (path, content) in [
+            ("src/math.rs", "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\npub fn sub(a: i32, b: i32) -> i32 {\n    a - b\n}\n"),
+            ("docs/guide/intro.md", "# Intro\n\nSome text.\n"),
+            ("migrations/001_init.sql", "CREATE TABLE users (id INTEGER);\n"),
+        ] {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+
+        let config = ProjectAnalyzerConfig {
+            chunker_config: SmartChunkerConfig {
+                min_function_lines: 0,
+                ..Default::default()
+            },
+            detect_compositions: false,
+            rules: vec![
+                ChunkingRule::new("src/**/*.rs", ChunkingStrategy::ByEntity),
+                ChunkingRule::new("docs/**/*.md", ChunkingStrategy::Atomic)
+                    .with_category(ChunkCategory::Docs),
+                ChunkingRule::new("migrations/*.sql", ChunkingStrategy::Skip),
+            ],
+            ..Default::default()
+        };
+        let result = ProjectAnalyzer::new(config).import_project(&root).unwrap();
+
+        let from = |suffix: &str| -> Vec<&AtomicChunk> {
+            result.chunks.iter().filter(|c| c.sources[0].file.ends_with(suffix)).collect()
+        };
+        let math: Vec<&str> = from("math.rs").iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(math, vec!["add", "sub"]);
+        let docs = from("intro.md");
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].categories, vec![ChunkCategory::Docs]);
+        assert!(from("001_init.sql").is_empty());
+        assert_eq!(result.summary.skipped_files, 1);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_category_overrides_win_and_apply_to_reused_chunks() {
+        let root = std::env::temp_dir().join(format!("cadi-categories-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        for (path, content) in [
+            ("src/api/routes.rs", "use axum::Router;\n\npub fn routes() -> Router {\n    Router::new()\n}\n"),
+            ("src/api/legacy.rs", "use axum::Router;\n\npub fn old() -> Router {\n    Router::new()\n}\n"),
+        ] {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+
+        let config = ProjectAnalyzerConfig {
+            incremental: true,
+            detect_compositions: false,
+            rules: vec![ChunkingRule::new("src/api/*.rs", ChunkingStrategy::Atomic)
+                .with_category(ChunkCategory::Backend)],
+            ..Default::default()
+        };
+        let analyzer = ProjectAnalyzer::new(config);
+        let category = |result: &ImportResult, file: &str| {
+            let chunk = result.chunks.iter().find(|c| c.sources[0].file.ends_with(file)).unwrap();
+            (chunk.categories[0].clone(), chunk.category_confidence)
+        };
+
+        std::fs::create_dir_all(root.join(".cadi")).unwrap();
+        std::fs::write(root.join(CATEGORY_OVERRIDES_FILE), "\"legacy.rs\" = \"deprecated\"\n").unwrap();
+        let first = analyzer.import_project(&root).unwrap();
+        assert_eq!(category(&first, "routes.rs"), (ChunkCategory::Backend, Some(1.0)));
+        assert_eq!(
+            category(&first, "legacy.rs"),
+            (ChunkCategory::Custom("deprecated".to_string()), Some(1.0))
+        );
+
+        // Unchanged files are reused, but with the current overrides
+        std::fs::write(root.join(CATEGORY_OVERRIDES_FILE), "\"src/api/**\" = \"api\"\n").unwrap();
+        let second = analyzer.import_project(&root).unwrap();
+        assert_eq!(second.summary.new_chunks, 0);
+        assert_eq!(category(&second, "routes.rs"), (ChunkCategory::Api, Some(1.0)));
+        assert_eq!(category(&second, "legacy.rs"), (ChunkCategory::Api, Some(1.0)));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_invalid_chunking_rule_fails_import() {
+        let config = ProjectAnalyzerConfig {
+            rules: vec![ChunkingRule::new("src/[", ChunkingStrategy::Atomic)],
+            ..Default::default()
+        };
+        let err = ProjectAnalyzer::new(config).import_project(Path::new(".")).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("unterminated character class"));
+    }
+
+    #[test]
+    fn test_import_progress_and_cancellation() {
+        let root = std::env::temp_dir().join(format!("cadi-import-progress-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        for name in ["a", "b", "c"] {
+            std::fs::write(
+                root.join(format!("src/{}.rs", name)),
+                format!("pub fn {}() -> u32 {{\n    1\n}}\n", name),
+            )
+            .unwrap();
+        }
+
+        let config = ProjectAnalyzerConfig {
+            incremental: true,
+            ..Default::default()
+        };
+        let analyzer = ProjectAnalyzer::new(config);
+
+        let reports = std::cell::RefCell::new(Vec::new());
+        analyzer
+            .import_project_with_progress(&root, |p| reports.borrow_mut().push(p), &CancellationToken::new())
+            .unwrap();
+        let reports = reports.into_inner();
+        let phases: Vec<ImportPhase> = reports.iter().map(|p| p.phase).collect();
+        assert_eq!(phases.first(), Some(&ImportPhase::CollectingFiles));
+        assert!(phases.windows(2).all(|w| w[0] as u8 <= w[1] as u8));
+        let last = reports.last().unwrap();
+        assert_eq!((last.phase, last.processed, last.total), (ImportPhase::Chunking, 3, 3));
+
+        // Cancelling while chunking fails the import and leaves the previous
+        // import state in place
+        let state_path = root.join(IMPORT_STATE_FILE);
+        let before = std::fs::read_to_string(&state_path).unwrap();
+        std::fs::write(root.join("src/d.rs"), "pub fn d() -> u32 {\n    4\n}\n").unwrap();
+
+        let cancel = CancellationToken::new();
+        let err = analyzer
+            .import_project_with_progress(
+                &root,
+                |p| {
+                    if p.phase == ImportPhase::Chunking && p.processed == 1 {
+                        cancel.cancel();
+                    }
+                },
+                &cancel,
+            )
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Interrupted);
+        assert_eq!(std::fs::read_to_string(&state_path).unwrap(), before);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_workspace_members_are_imported_as_packages() {
+        let root = std::env::temp_dir().join(format!("cadi-workspace-import-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let files = [
+            ("Cargo.toml", "[workspace]\nmembers = [\"crates/*\"]\n"),
+            ("crates/core_lib/Cargo.toml", "[package]\nname = \"core_lib\"\n"),
+            ("crates/core_lib/src/lib.rs", "pub fn answer() -> u32 {\n    42\n}\n"),
+            ("crates/app/Cargo.toml", "[package]\nname = \"app\"\n\n[dependencies]\ncore_lib = { path = \"../core_lib\" }\n"),
+            ("crates/app/src/main.rs", "fn main() {\n    println!(\"{}\", core_lib::answer());\n}\n"),
+            ("crates/app/src/cli.rs", "pub fn usage() -> &'static str {\n    \"app\"\n}\n"),
+        ];
+        for (path, content) in files {
+            std::fs::create_dir_all(root.join(path).parent().unwrap()).unwrap();
+            std::fs::write(root.join(path), content).unwrap();
+        }
+
+        let config = ProjectAnalyzerConfig {
+            namespace: Some("acme".to_string()),
+            ..Default::default()
+        };
+        let result = ProjectAnalyzer::new(config).import_project(&root).unwrap();
+
+        assert_eq!(result.analysis.workspace, Some(WorkspaceKind::Cargo));
+        let app = &result.analysis.members[0];
+        assert_eq!((app.name.as_str(), app.path.as_path()), ("app", Path::new("crates/app")));
+        assert_eq!(app.total_files, 3);
+        assert_eq!(app.entrypoints, [root.join("crates/app/src/main.rs")]);
+        assert_eq!(app.dependencies, ["core_lib"]);
+        assert_eq!(result.analysis.members[1].alias, "core-lib");
+
+        // Chunk aliases are namespaced by package; the root manifest belongs
+        // to no member
+        for chunk in &result.chunks {
+            let file = &chunk.sources[0].file;
+            let member = if file.contains("crates/core_lib") {
+                "core-lib"
+            } else if file.contains("crates/app") {
+                "app"
+            } else {
+                continue;
+            };
+            let alias = chunk.primary_alias().unwrap().full_path();
+            assert!(alias.starts_with(&format!("acme/{}/", member)), "{}", alias);
+        }
+
+        let package = |alias: &str| {
+            let id = result.alias_registry.resolve(alias).unwrap();
+            result.compositions.iter().find(|c| &c.chunk_id == id).unwrap()
+        };
+        let app_package = package("acme/app");
+        let core_package = package("acme/core-lib");
+        assert_eq!(app_package.requires, std::slice::from_ref(&core_package.chunk_id));
+        assert_eq!(result.dependencies.len(), 1);
+        let edge = &result.dependencies[0];
+        assert_eq!((edge.from.as_str(), edge.to.as_str()), (app_package.chunk_id.as_str(), core_package.chunk_id.as_str()));
+        assert_eq!(edge.edge_type, GraphEdgeType::DEPENDS_ON);
+
+        let members: Vec<(&str, usize)> =
+            result.summary.members.iter().map(|m| (m.name.as_str(), m.chunks)).collect();
+        assert_eq!(members, [("app", 3), ("core_lib", 2)]);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_chunks_in_registry_are_marked_reused() {
+        let root = std::env::temp_dir().join(format!("cadi-registry-reuse-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        for (name, body) in [("one", "1"), ("two", "2"), ("three", "3"), ("four", "4")] {
+            std::fs::write(
+                root.join(format!("src/{}.rs", name)),
+                format!("pub fn {}() -> u32 {{\n    {}\n}}\n", name, body),
+            )
+            .unwrap();
+        }
+
+        let mut result = ProjectAnalyzer::default().import_project(&root).unwrap();
+        let hashes = result.chunk_hashes();
+        assert_eq!(hashes.len(), result.chunks.len() + result.compositions.len());
+
+        // The registry knows every other chunk
+        let existing: HashSet<String> = hashes.iter().step_by(2).cloned().collect();
+        let new_before = result.summary.new_chunks;
+        let marked = result.mark_reused_from_registry(&existing);
+
+        assert_eq!(marked, existing.len());
+        assert_eq!(result.summary.registry_chunks, marked);
+        assert_eq!(result.reused_from_registry.len(), marked);
+        let reused_atoms = result
+            .chunks
+            .iter()
+            .filter(|c| result.is_reused_from_registry(&c.chunk_id))
+            .count();
+        assert_eq!(result.summary.new_chunks, new_before - reused_atoms);
+        for chunk in result.chunks.iter().chain(&result.compositions) {
+            assert_eq!(
+                result.is_reused_from_registry(&chunk.chunk_id),
+                existing.contains(&chunk_storage_key(&chunk.chunk_id))
+            );
+            // Aliases still resolve to the existing IDs
+            if let Some(alias) = chunk.primary_alias() {
+                assert_eq!(result.alias_registry.resolve(&alias.full_path()), Some(&chunk.chunk_id));
+            }
+        }
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_import_applies_reviewed_plan() {
+        let root = std::env::temp_dir().join(format!("cadi-plan-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(
+            root.join("src/math.rs"),
+            "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\npub fn sub(a: i32, b: i32) -> i32 {\n    a - b\n}\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("src/text.rs"), "pub fn shout(s: &str) -> String {\n    s.to_uppercase()\n}\n").unwrap();
+
+        let config = ProjectAnalyzerConfig {
+            chunker_config: SmartChunkerConfig {
+                min_function_lines: 0,
+                merge_small_entities: false,
+                ..Default::default()
+            },
+            detect_compositions: false,
+            rules: vec![ChunkingRule::new("*.rs", ChunkingStrategy::ByEntity)],
+            ..Default::default()
+        };
+        let (analysis, mut plan) = ProjectAnalyzer::new(config.clone()).plan_project(&root).unwrap();
+        plan.validate(&root, &analysis.files).unwrap();
+        let math = plan.files.iter().position(|f| f.file_path == Path::new("src/math.rs")).unwrap();
+        assert_eq!(plan.files[math].suggested_chunks.len(), 2);
+
+        // Merge the two functions under a new alias and leave text.rs out
+        let mut merged = plan.files[math].suggested_chunks[0].clone();
+        merged.alias = "arithmetic".to_string();
+        merged.end_line = 7;
+        plan.files[math].suggested_chunks = vec![merged];
+        for file in &mut plan.files {
+            if file.file_path == Path::new("src/text.rs") {
+                file.strategy = ChunkingStrategy::Skip;
+                file.suggested_chunks.clear();
+            }
+        }
+
+        let reviewed = ProjectAnalyzerConfig { plan: Some(plan.clone()), ..config.clone() };
+        let result = ProjectAnalyzer::new(reviewed).import_project(&root).unwrap();
+        assert_eq!(result.chunks.len(), 1);
+        assert!(result.chunks[0].aliases.iter().any(|a| a.full_path().ends_with("arithmetic")));
+        assert_eq!(result.summary.skipped_files, 1);
+
+        // A range that cuts a function in two fails the import
+        plan.files[math].suggested_chunks[0].end_line = 2;
+        let broken = ProjectAnalyzerConfig { plan: Some(plan), ..config };
+        let error = ProjectAnalyzer::new(broken).import_project(&root).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(error.to_string().contains("cuts through add"), "{}", error);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
 }
@@ -2,18 +2,46 @@
 //!
 //! Assembles atoms into syntactically valid code.
 
-use super::config::{ViewConfig, ViewFormat};
-use super::view::{ViewFragment, InclusionReason};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+
+use super::config::{ViewConfig, ViewFormat, ViewOrdering};
+use super::view::{InclusionReason, MappedRegion, ViewFragment};
 use crate::graph::GraphNode;
+use crate::tokens::TokenEstimator;
 
 /// Assembler for creating virtual views from atoms
 pub struct Assembler {
     config: ViewConfig,
+    estimator: Arc<dyn TokenEstimator>,
+    /// chunk ID -> chunk IDs it uses, for topological ordering
+    dependencies: HashMap<String, Vec<String>>,
+    /// Atoms that are tests, laid out after the rest in their own section
+    tests: HashSet<String>,
 }
 
 impl Assembler {
     pub fn new(config: ViewConfig) -> Self {
-        Self { config }
+        Self {
+            estimator: config.token_estimator.estimator(),
+            config,
+            dependencies: HashMap::new(),
+            tests: HashSet::new(),
+        }
+    }
+
+    /// Set the dependency edges between the atoms being assembled. Edges to
+    /// atoms outside the assembly are ignored.
+    pub fn with_dependencies(mut self, dependencies: HashMap<String, Vec<String>>) -> Self {
+        self.dependencies = dependencies;
+        self
+    }
+
+    /// Set the atoms that are tests; they go after the other atoms, below a
+    /// `--- tests ---` comment
+    pub fn with_tests(mut self, tests: HashSet<String>) -> Self {
+        self.tests = tests;
+        self
     }
 
     /// Assemble atoms into a single coherent source
@@ -24,23 +52,84 @@ impl Assembler {
     ) -> AssemblyResult {
         let mut output = String::new();
         let mut fragments = Vec::new();
-        let mut symbol_locations = std::collections::HashMap::new();
+        let mut symbol_locations = BTreeMap::new();
+        let mut source_map = Vec::new();
         let mut current_line = 1;
         let mut total_tokens = 0;
 
-        // Sort atoms by priority if configured
-        let sorted_atoms = if self.config.sort_by_type {
-            self.sort_by_type(atoms)
-        } else {
-            atoms
-        };
+        let (tests, code): (Vec<_>, Vec<_>) = atoms
+            .into_iter()
+            .partition(|(node, _)| self.tests.contains(&node.chunk_id));
+        let mut sorted_atoms = self.order(code);
+        sorted_atoms.extend(self.order(tests));
 
+        // Pull imports out of each atom, then apply the output format
+        let mut prepared = Vec::new();
         for (node, content) in sorted_atoms {
-            // Check token limit
-            let atom_tokens = content.len() / 4;
-            if total_tokens + atom_tokens > self.config.max_tokens {
+            let (imports, body) = if self.config.dedupe_imports {
+                self.take_imports(&node, &content, language)
+            } else {
+                (Vec::new(), content.clone())
+            };
+            let body = self.format_content(&body, language);
+            prepared.push((node, imports, body, content));
+        }
+
+        // Select atoms within the token budget, measured after formatting
+        let mut selected = Vec::new();
+        let mut imports = Vec::new();
+        let mut originals = Vec::new();
+        let mut budget_used = 0;
+        for (node, atom_imports, body, original) in prepared {
+            let atom_tokens = self.estimator.estimate(&(atom_imports.concat() + &body), Some(language));
+            if budget_used + atom_tokens > self.config.max_tokens {
                 break;
             }
+            budget_used += atom_tokens;
+            imports.extend(atom_imports);
+            if let Some(origin) = Self::origin(&node) {
+                originals.push((node.chunk_id.clone(), origin, original.clone()));
+            }
+            if !body.trim().is_empty() {
+                selected.push((node, body, original));
+            }
+        }
+
+        // Emit imports once, before any code
+        let header = self.render_imports(imports, language);
+        for (i, import) in header.lines().enumerate() {
+            // A deduplicated import maps to the first atom it came from
+            let found = originals.iter().find_map(|(chunk_id, (file, start), original)| {
+                original
+                    .lines()
+                    .position(|line| Self::normalize(line) == import)
+                    .map(|index| (chunk_id, file, start + index))
+            });
+            if let Some((chunk_id, file, line)) = found {
+                MappedRegion::extend_map(&mut source_map, 1 + i, chunk_id, file, line);
+            }
+        }
+        if !header.is_empty() {
+            output.push_str(&header);
+            output.push('\n');
+            current_line += header.lines().count() + 1;
+            total_tokens += self.estimator.estimate(&header, Some(language));
+        }
+
+        let mut in_tests = false;
+        for (node, content, original) in selected {
+            let atom_tokens = self.estimator.estimate(&content, Some(language));
+
+            if !in_tests && self.tests.contains(&node.chunk_id) {
+                in_tests = true;
+                let heading = format!("{} --- tests ---\n", Self::comment_prefix(language));
+                output.push_str(&heading);
+                current_line += 1;
+                if self.config.add_separators {
+                    output.push('\n');
+                    current_line += 1;
+                }
+            }
 
             // Add separator if configured
             if self.config.add_separators && !output.is_empty() {
@@ -54,9 +143,7 @@ impl Assembler {
                 symbol_locations.insert(symbol.clone(), current_line);
             }
 
-            // Add content
-            let formatted_content = self.format_content(&content, language);
-            let content_lines = formatted_content.lines().count();
+            let content_lines = content.lines().count();
 
             // Create fragment
             fragments.push(ViewFragment {
@@ -69,8 +156,16 @@ impl Assembler {
                 defines: node.symbols_defined.clone(),
             });
 
-            output.push_str(&formatted_content);
-            if !formatted_content.ends_with('\n') {
+            if let Some((file, start)) = Self::origin(&node) {
+                for (i, index) in Self::line_origins(&original, &content).into_iter().enumerate() {
+                    if let Some(index) = index {
+                        MappedRegion::extend_map(&mut source_map, current_line + i, &node.chunk_id, &file, start + index);
+                    }
+                }
+            }
+
+            output.push_str(&content);
+            if !content.ends_with('\n') {
                 output.push('\n');
             }
             output.push('\n');
@@ -83,27 +178,285 @@ impl Assembler {
             source: output,
             fragments,
             symbol_locations,
+            source_map,
             total_tokens,
             truncated: total_tokens >= self.config.max_tokens,
         }
     }
 
-    /// Sort atoms by type priority
-    fn sort_by_type(&self, mut atoms: Vec<(GraphNode, String)>) -> Vec<(GraphNode, String)> {
-        atoms.sort_by_key(|(node, _)| {
-            match node.granularity.as_str() {
-                "import" => 0,
-                "type" | "struct" | "interface" | "enum" => 1,
-                "trait" => 2,
-                "constant" => 3,
-                "function" => 4,
-                "async_function" => 4,
-                "class" => 5,
-                "module" => 6,
-                _ => 10,
+    /// Original file of an atom and the line its content starts at
+    fn origin(node: &GraphNode) -> Option<(String, usize)> {
+        let file = node.source_file.clone()?;
+        // Whole-file chunks start at line 0
+        Some((file, node.source_lines.map_or(1, |(start, _)| start.max(1))))
+    }
+
+    /// For each line of `formatted`, the index of the line of `original` it
+    /// was copied from unchanged. Taking out imports and formatting only
+    /// drop or rewrite lines, never reorder them, so lines are matched in
+    /// order; rewritten lines have no origin.
+    fn line_origins(original: &str, formatted: &str) -> Vec<Option<usize>> {
+        let original: Vec<&str> = original.lines().collect();
+        let mut next = 0;
+        formatted
+            .lines()
+            .map(|line| {
+                let index = (next..original.len()).find(|&j| original[j] == line)?;
+                next = index + 1;
+                Some(index)
+            })
+            .collect()
+    }
+
+    /// An import statement as it appears in the rendered header
+    fn normalize(import: &str) -> String {
+        import.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// Lay atoms out in the configured order
+    fn order(&self, mut atoms: Vec<(GraphNode, String)>) -> Vec<(GraphNode, String)> {
+        match self.config.ordering {
+            ViewOrdering::AsRequested => {
+                if self.config.sort_by_type {
+                    atoms.sort_by_key(|(node, _)| self.type_rank(node));
+                }
+                atoms
             }
-        });
-        atoms
+            ViewOrdering::ByName => {
+                atoms.sort_by_cached_key(|(node, _)| {
+                    let name = node.primary_alias.clone().unwrap_or_else(|| node.chunk_id.clone());
+                    (self.type_rank(node), name, node.chunk_id.clone())
+                });
+                atoms
+            }
+            ViewOrdering::Topological => self.topological(atoms),
+        }
+    }
+
+    /// Kahn's algorithm over the intra-view dependencies: every atom comes
+    /// after the atoms it uses. Among atoms that are ready at the same time
+    /// the lowest (type rank, chunk ID) goes first; a cycle is broken by
+    /// emitting its lowest atom early.
+    fn topological(&self, atoms: Vec<(GraphNode, String)>) -> Vec<(GraphNode, String)> {
+        let mut index = HashMap::new();
+        for (i, (node, _)) in atoms.iter().enumerate() {
+            index.entry(node.chunk_id.as_str()).or_insert(i);
+        }
+
+        let mut pending = vec![0usize; atoms.len()];
+        let mut users = vec![Vec::new(); atoms.len()];
+        for (i, (node, _)) in atoms.iter().enumerate() {
+            let deps: BTreeSet<usize> = self
+                .dependencies
+                .get(&node.chunk_id)
+                .into_iter()
+                .flatten()
+                .filter_map(|dep| index.get(dep.as_str()).copied())
+                .filter(|&j| j != i)
+                .collect();
+            for j in deps {
+                users[j].push(i);
+                pending[i] += 1;
+            }
+        }
+
+        let key = |i: usize| (self.type_rank(&atoms[i].0), atoms[i].0.chunk_id.clone(), i);
+        let mut unplaced: BTreeSet<_> = (0..atoms.len()).map(key).collect();
+        let mut ready: BTreeSet<_> = (0..atoms.len()).filter(|&i| pending[i] == 0).map(key).collect();
+        let mut order = Vec::with_capacity(atoms.len());
+
+        while let Some(next) = ready.pop_first().or_else(|| unplaced.first().cloned()) {
+            unplaced.remove(&next);
+            let i = next.2;
+            order.push(i);
+            for &user in &users[i] {
+                pending[user] = pending[user].saturating_sub(1);
+                if pending[user] == 0 && unplaced.contains(&key(user)) {
+                    ready.insert(key(user));
+                }
+            }
+        }
+
+        let mut atoms: Vec<_> = atoms.into_iter().map(Some).collect();
+        order.into_iter().filter_map(|i| atoms[i].take()).collect()
+    }
+
+    /// Layout priority of an atom's granularity, when sorting by type
+    fn type_rank(&self, node: &GraphNode) -> u8 {
+        if !self.config.sort_by_type {
+            return 0;
+        }
+        match node.granularity.as_str() {
+            "import" => 0,
+            "type" | "struct" | "interface" | "enum" => 1,
+            "trait" => 2,
+            "constant" => 3,
+            "function" => 4,
+            "async_function" => 4,
+            "class" => 5,
+            "module" => 6,
+            _ => 10,
+        }
+    }
+
+    /// Pull the import statements out of an atom. Atoms that are themselves
+    /// imports are moved into the import list wholesale.
+    fn take_imports(&self, node: &GraphNode, content: &str, language: &str) -> (Vec<String>, String) {
+        let (mut imports, body) = self.split_imports(content, language);
+
+        if node.granularity == "import" {
+            if !body.trim().is_empty() {
+                imports.push(body.trim().to_string());
+            }
+            return (imports, String::new());
+        }
+
+        (imports, body.trim_matches('\n').to_string())
+    }
+
+    /// Deduplicate import statements and render them as a single header
+    fn render_imports(&self, imports: Vec<String>, language: &str) -> String {
+        let mut unique = Vec::new();
+        let mut seen = HashSet::new();
+        for import in imports {
+            let key = Self::normalize(&import);
+            if seen.insert(key.clone()) {
+                unique.push(key);
+            }
+        }
+
+        let unique = if language == "rust" {
+            self.merge_rust_uses(unique)
+        } else {
+            unique
+        };
+
+        let mut header = String::new();
+        for import in unique {
+            header.push_str(&import);
+            header.push('\n');
+        }
+        header
+    }
+
+    /// Split top-level import statements from the rest of an atom
+    fn split_imports(&self, content: &str, language: &str) -> (Vec<String>, String) {
+        let mut imports = Vec::new();
+        let mut body = String::new();
+        let mut pending: Option<String> = None;
+
+        for line in content.lines() {
+            if let Some(stmt) = pending.as_mut() {
+                stmt.push('\n');
+                stmt.push_str(line);
+                if Self::import_complete(stmt, language) {
+                    imports.push(pending.take().unwrap());
+                }
+                continue;
+            }
+
+            if Self::starts_import(line, language) {
+                let stmt = line.to_string();
+                if Self::import_complete(&stmt, language) {
+                    imports.push(stmt);
+                } else {
+                    pending = Some(stmt);
+                }
+                continue;
+            }
+
+            body.push_str(line);
+            body.push('\n');
+        }
+
+        // An unterminated statement wasn't an import we understand
+        if let Some(stmt) = pending {
+            body.push_str(&stmt);
+            body.push('\n');
+        }
+
+        (imports, body)
+    }
+
+    /// Does this (unindented) line begin an import statement?
+    fn starts_import(line: &str, language: &str) -> bool {
+        match language {
+            "rust" => line.starts_with("use "),
+            "typescript" | "javascript" => line.starts_with("import "),
+            "python" => {
+                line.starts_with("import ")
+                    || (line.starts_with("from ") && line.contains(" import"))
+            }
+            _ => false,
+        }
+    }
+
+    /// Has the import statement collected so far ended?
+    fn import_complete(stmt: &str, language: &str) -> bool {
+        let trimmed = stmt.trim_end();
+        match language {
+            "rust" => trimmed.ends_with(';'),
+            "typescript" | "javascript" => {
+                trimmed.ends_with(';')
+                    || ((trimmed.ends_with('\'') || trimmed.ends_with('"'))
+                        && (trimmed.contains(" from ") || !trimmed.contains('{')))
+            }
+            "python" => !trimmed.contains('(') || trimmed.ends_with(')'),
+            _ => true,
+        }
+    }
+
+    /// Merge Rust `use` statements that share a path prefix, e.g.
+    /// `use std::fmt::Debug;` and `use std::fmt::Display;` become
+    /// `use std::fmt::{Debug, Display};`
+    fn merge_rust_uses(&self, imports: Vec<String>) -> Vec<String> {
+        let mut groups: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        let mut verbatim = Vec::new();
+
+        for import in imports {
+            match Self::parse_rust_use(&import) {
+                Some((prefix, items)) => groups.entry(prefix).or_default().extend(items),
+                None => verbatim.push(import),
+            }
+        }
+
+        let mut merged: Vec<String> = groups
+            .into_iter()
+            .map(|(prefix, items)| {
+                let items: Vec<String> = items.into_iter().collect();
+                if items.len() == 1 {
+                    format!("use {}::{};", prefix, items[0])
+                } else {
+                    format!("use {}::{{{}}};", prefix, items.join(", "))
+                }
+            })
+            .collect();
+        merged.extend(verbatim);
+        merged
+    }
+
+    /// Parse `use a::b::C;` or `use a::b::{C, D};` into its prefix and
+    /// leaf items. Nested groups are left alone.
+    fn parse_rust_use(import: &str) -> Option<(String, Vec<String>)> {
+        let path = import.strip_prefix("use ")?.strip_suffix(';')?.trim();
+
+        if let Some(open) = path.find('{') {
+            let prefix = path[..open].strip_suffix("::")?;
+            let inner = path[open + 1..].strip_suffix('}')?;
+            if inner.contains('{') || prefix.is_empty() {
+                return None;
+            }
+            let items = inner
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(String::from)
+                .collect();
+            return Some((prefix.to_string(), items));
+        }
+
+        let (prefix, leaf) = path.rsplit_once("::")?;
+        Some((prefix.to_string(), vec![leaf.to_string()]))
     }
 
     /// Create a separator comment
@@ -111,57 +464,94 @@ impl Assembler {
         let label = node.primary_alias.as_ref()
             .unwrap_or(&node.chunk_id);
         
-        let comment_style = match language {
+        format!("{} --- {} ---\n", Self::comment_prefix(language), label)
+    }
+
+    /// Line comment marker of a language
+    fn comment_prefix(language: &str) -> &'static str {
+        match language {
             "python" => "#",
             "rust" | "typescript" | "javascript" | "go" | "java" | "c" | "cpp" => "//",
             _ => "//",
-        };
-        
-        format!("{} --- {} ---\n", comment_style, label)
+        }
     }
 
+
     /// Format content based on view format
     fn format_content(&self, content: &str, language: &str) -> String {
         match self.config.format {
             ViewFormat::Source => content.to_string(),
-            ViewFormat::Minimal => self.minimize(content),
-            ViewFormat::Documented => content.to_string(),
+            ViewFormat::Minimal => self.minimize(content, language),
+            ViewFormat::Documented => self.strip_comments(content, language, true),
             ViewFormat::Signatures => self.extract_signatures(content, language),
             ViewFormat::Json => content.to_string(),
         }
     }
 
     /// Minimize content (remove comments, compact whitespace)
-    fn minimize(&self, content: &str) -> String {
+    fn minimize(&self, content: &str, language: &str) -> String {
+        let mut result = String::new();
+        for line in self.strip_comments(content, language, false).lines() {
+            if !line.trim().is_empty() {
+                result.push_str(line);
+                result.push('\n');
+            }
+        }
+        result
+    }
+
+    /// Remove comments, optionally keeping doc comments (`///`, `//!`,
+    /// `/** */` and Python docstrings)
+    fn strip_comments(&self, content: &str, language: &str, keep_docs: bool) -> String {
+        let python = language == "python";
         let mut result = String::new();
-        let mut in_block_comment = false;
+        // (is_doc, terminator) of the block comment we're inside
+        let mut in_block: Option<(bool, &str)> = None;
 
         for line in content.lines() {
             let trimmed = line.trim();
-            
-            // Skip empty lines
-            if trimmed.is_empty() {
-                continue;
-            }
-            
-            // Skip single-line comments
-            if trimmed.starts_with("//") || trimmed.starts_with("#") {
+
+            if let Some((is_doc, end)) = in_block {
+                if trimmed.contains(end) {
+                    in_block = None;
+                }
+                if keep_docs && is_doc {
+                    result.push_str(line);
+                    result.push('\n');
+                }
                 continue;
             }
-            
-            // Handle block comments (simplified)
-            if trimmed.contains("/*") {
-                in_block_comment = true;
-            }
-            if in_block_comment {
-                if trimmed.contains("*/") {
-                    in_block_comment = false;
+
+            let comment = if python {
+                if trimmed.starts_with('#') {
+                    Some(false)
+                } else if let Some(quote) = ["\"\"\"", "'''"].into_iter().find(|q| trimmed.starts_with(q)) {
+                    if !trimmed[3..].contains(quote) {
+                        in_block = Some((true, quote));
+                    }
+                    Some(true)
+                } else {
+                    None
+                }
+            } else if trimmed.starts_with("//") {
+                Some(trimmed.starts_with("///") || trimmed.starts_with("//!"))
+            } else if let Some(rest) = trimmed.strip_prefix("/*") {
+                let is_doc = rest.starts_with('*') || rest.starts_with('!');
+                if !rest.contains("*/") {
+                    in_block = Some((is_doc, "*/"));
+                }
+                Some(is_doc)
+            } else {
+                None
+            };
+
+            match comment {
+                Some(is_doc) if !(keep_docs && is_doc) => continue,
+                _ => {
+                    result.push_str(line);
+                    result.push('\n');
                 }
-                continue;
             }
-            
-            result.push_str(line);
-            result.push('\n');
         }
 
         result
@@ -171,38 +561,142 @@ impl Assembler {
     fn extract_signatures(&self, content: &str, language: &str) -> String {
         match language {
             "rust" => self.extract_rust_signatures(content),
-            "typescript" => self.extract_ts_signatures(content),
+            "typescript" | "javascript" => self.extract_ts_signatures(content),
             "python" => self.extract_python_signatures(content),
             _ => content.to_string(),
         }
     }
 
     fn extract_rust_signatures(&self, content: &str) -> String {
+        let lines: Vec<&str> = content.lines().collect();
         let mut result = String::new();
-        
-        // Match function signatures
-        let fn_regex = regex::Regex::new(
-            r"(?m)^(\s*)(?:pub(?:\([^)]*\))?\s+)?(async\s+)?fn\s+\w+[^{]+\{"
-        ).unwrap();
-        
-        for cap in fn_regex.captures_iter(content) {
-            let sig = cap.get(0).unwrap().as_str();
-            let sig = sig.trim_end_matches('{').trim();
-            result.push_str(sig);
-            result.push_str(";\n");
+        Self::rust_signatures_in(&lines, &mut result);
+        result
+    }
+
+    /// Emit the signatures of the items in `lines`, descending into
+    /// `impl` and `trait` blocks
+    fn rust_signatures_in(lines: &[&str], out: &mut String) {
+        let mut i = 0;
+
+        while i < lines.len() {
+            let item = Self::strip_rust_visibility(lines[i].trim_start());
+            let fn_item = Self::strip_rust_fn_qualifiers(item);
+
+            if fn_item.starts_with("fn ") {
+                // Signatures may span lines; they end at the body or a `;`
+                let (sig, brace_line) = Self::rust_header(lines, i);
+                match brace_line {
+                    Some(line) => {
+                        out.push_str(sig.trim_end());
+                        out.push_str(" { ... }\n");
+                        i = Self::block_end(lines, line) + 1;
+                    }
+                    None => {
+                        out.push_str(sig.trim_end());
+                        out.push('\n');
+                        i += sig.lines().count().max(1);
+                    }
+                }
+            } else if ["impl ", "impl<", "unsafe impl", "trait ", "unsafe trait "].iter().any(|k| item.starts_with(k)) {
+                let (header, brace_line) = Self::rust_header(lines, i);
+                let Some(line) = brace_line else {
+                    i += 1;
+                    continue;
+                };
+                let end = Self::block_end(lines, line);
+                out.push_str(header.trim_end());
+                out.push_str(" {\n");
+                if end > line {
+                    Self::rust_signatures_in(&lines[line + 1..end], out);
+                }
+                let indent = &lines[i][..lines[i].len() - lines[i].trim_start().len()];
+                out.push_str(indent);
+                out.push_str("}\n");
+                i = end + 1;
+            } else if ["struct ", "enum ", "union ", "type "].iter().any(|k| item.starts_with(k)) {
+                // Type definitions are their own signature
+                let (_, brace_line) = Self::rust_header(lines, i);
+                let end = match brace_line {
+                    Some(line) => Self::block_end(lines, line),
+                    None => (i..lines.len())
+                        .find(|&j| lines[j].trim_end().ends_with(';'))
+                        .unwrap_or(i),
+                };
+                for line in &lines[i..=end] {
+                    out.push_str(line);
+                    out.push('\n');
+                }
+                i = end + 1;
+            } else {
+                i += 1;
+            }
         }
-        
-        // Match struct definitions
-        let struct_regex = regex::Regex::new(
-            r"(?m)^(?:pub(?:\([^)]*\))?\s+)?struct\s+\w+[^{]*\{[^}]+\}"
-        ).unwrap();
-        
-        for cap in struct_regex.find_iter(content) {
-            result.push_str(cap.as_str());
-            result.push('\n');
+    }
+
+    /// Collect an item header starting at `start` up to (not including) the
+    /// opening brace. Returns the header and the line holding the brace, or
+    /// `None` if the header ends in `;` first.
+    fn rust_header(lines: &[&str], start: usize) -> (String, Option<usize>) {
+        let mut header = String::new();
+        for (offset, line) in lines[start..].iter().enumerate() {
+            if let Some(pos) = line.find('{') {
+                header.push_str(&line[..pos]);
+                return (header, Some(start + offset));
+            }
+            header.push_str(line);
+            if line.trim_end().ends_with(';') {
+                return (header, None);
+            }
+            header.push('\n');
+        }
+        (header, None)
+    }
+
+    /// Index of the line closing the brace block opened on line `start`
+    fn block_end(lines: &[&str], start: usize) -> usize {
+        let mut depth = 0i32;
+        for (offset, line) in lines[start..].iter().enumerate() {
+            for c in line.chars() {
+                match c {
+                    '{' => depth += 1,
+                    '}' => depth -= 1,
+                    _ => {}
+                }
+            }
+            if depth <= 0 {
+                return start + offset;
+            }
+        }
+        lines.len() - 1
+    }
+
+    fn strip_rust_visibility(item: &str) -> &str {
+        if let Some(rest) = item.strip_prefix("pub(") {
+            if let Some(close) = rest.find(')') {
+                return rest[close + 1..].trim_start();
+            }
+        }
+        item.strip_prefix("pub ").unwrap_or(item)
+    }
+
+    fn strip_rust_fn_qualifiers(mut item: &str) -> &str {
+        loop {
+            let stripped = ["const ", "async ", "unsafe ", "default "]
+                .iter()
+                .find_map(|q| item.strip_prefix(q))
+                .or_else(|| {
+                    // extern "C" fn
+                    let rest = item.strip_prefix("extern ")?;
+                    let rest = rest.strip_prefix('"')?;
+                    let close = rest.find('"')?;
+                    Some(rest[close + 1..].trim_start())
+                });
+            match stripped {
+                Some(rest) => item = rest,
+                None => return item,
+            }
         }
-        
-        result
     }
 
     fn extract_ts_signatures(&self, content: &str) -> String {
@@ -231,19 +725,66 @@ impl Assembler {
         result
     }
 
+    /// `def` and `class` lines with their docstrings; function bodies
+    /// become `...`
     fn extract_python_signatures(&self, content: &str) -> String {
+        let lines: Vec<&str> = content.lines().collect();
+        let indent_of = |line: &str| line.len() - line.trim_start().len();
         let mut result = String::new();
-        
-        // Match function definitions
-        let fn_regex = regex::Regex::new(
-            r"(?m)^(\s*)(?:async\s+)?def\s+\w+\([^)]*\)(?:\s*->\s*[^:]+)?:"
-        ).unwrap();
-        
-        for cap in fn_regex.find_iter(content) {
-            result.push_str(cap.as_str().trim_end_matches(':'));
-            result.push_str(": ...\n");
+        let mut i = 0;
+
+        while i < lines.len() {
+            let trimmed = lines[i].trim_start();
+            let is_def = trimmed.starts_with("def ") || trimmed.starts_with("async def ");
+            if !is_def && !trimmed.starts_with("class ") {
+                i += 1;
+                continue;
+            }
+
+            let indent = indent_of(lines[i]);
+
+            // Headers may span lines; they end with `:`
+            let header_end = (i..lines.len())
+                .find(|&j| lines[j].trim_end().ends_with(':'))
+                .unwrap_or(i);
+            for line in &lines[i..=header_end] {
+                result.push_str(line);
+                result.push('\n');
+            }
+            i = header_end + 1;
+
+            // Docstring
+            if let Some(first) = lines.get(i) {
+                let doc = first.trim_start();
+                if let Some(quote) = ["\"\"\"", "'''"].into_iter().find(|q| doc.starts_with(q)) {
+                    let doc_end = if doc[3..].contains(quote) {
+                        i
+                    } else {
+                        (i + 1..lines.len())
+                            .find(|&j| lines[j].contains(quote))
+                            .unwrap_or(lines.len() - 1)
+                    };
+                    for line in &lines[i..=doc_end] {
+                        result.push_str(line);
+                        result.push('\n');
+                    }
+                    i = doc_end + 1;
+                }
+            }
+
+            if is_def {
+                result.push_str(&" ".repeat(indent + 4));
+                result.push_str("...\n");
+
+                // Skip the body
+                while i < lines.len()
+                    && (lines[i].trim().is_empty() || indent_of(lines[i]) > indent)
+                {
+                    i += 1;
+                }
+            }
         }
-        
+
         result
     }
 }
@@ -252,7 +793,8 @@ impl Assembler {
 pub struct AssemblyResult {
     pub source: String,
     pub fragments: Vec<ViewFragment>,
-    pub symbol_locations: std::collections::HashMap<String, usize>,
+    pub symbol_locations: BTreeMap<String, usize>,
+    pub source_map: Vec<MappedRegion>,
     pub total_tokens: usize,
     pub truncated: bool,
 }
@@ -260,6 +802,7 @@ pub struct AssemblyResult {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tokens::TokenEstimatorKind;
 
     #[test]
     fn test_minimize() {
@@ -273,7 +816,7 @@ fn hello() {
 }
 "#;
         
-        let minimized = assembler.minimize(content);
+        let minimized = assembler.minimize(content, "rust");
         assert!(!minimized.contains("// This is a comment"));
         assert!(minimized.contains("fn hello()"));
     }
@@ -294,7 +837,332 @@ pub struct Person {
 "#;
         
         let signatures = assembler.extract_rust_signatures(content);
-        assert!(signatures.contains("pub fn hello(name: &str) -> String;"));
+        assert!(signatures.contains("pub fn hello(name: &str) -> String { ... }"));
+        assert!(!signatures.contains("format!"));
         assert!(signatures.contains("pub struct Person"));
     }
+
+    #[test]
+    fn test_rust_imports_deduplicated() {
+        let assembler = Assembler::new(ViewConfig::default());
+
+        let atoms = ["a", "b", "c"]
+            .iter()
+            .map(|name| {
+                let node = GraphNode::new(format!("chunk:{}", name), name.to_string())
+                    .with_language("rust");
+                let content = format!(
+                    "use std::collections::HashMap;\n\nfn {}() -> HashMap<u8, u8> {{ HashMap::new() }}",
+                    name
+                );
+                (node, content)
+            })
+            .collect();
+
+        let result = assembler.assemble(atoms, "rust");
+        assert_eq!(result.source.matches("use std::collections::HashMap;").count(), 1);
+        assert!(result.source.starts_with("use std::collections::HashMap;"));
+        assert_eq!(result.fragments.len(), 3);
+        assert!(result.fragments.iter().all(|f| f.start_line > 1));
+    }
+
+    #[test]
+    fn test_rust_uses_merged_by_prefix() {
+        let assembler = Assembler::new(ViewConfig::default());
+
+        let merged = assembler.merge_rust_uses(vec![
+            "use std::collections::HashMap;".to_string(),
+            "use std::collections::{BTreeMap, HashMap};".to_string(),
+            "use serde::Serialize;".to_string(),
+        ]);
+        assert_eq!(
+            merged,
+            vec![
+                "use serde::Serialize;".to_string(),
+                "use std::collections::{BTreeMap, HashMap};".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_imports_kept_inline_when_disabled() {
+        let config = ViewConfig {
+            dedupe_imports: false,
+            ..Default::default()
+        };
+        let assembler = Assembler::new(config);
+
+        let atoms = (0..2)
+            .map(|i| {
+                let node = GraphNode::new(format!("chunk:{}", i), format!("f{}", i));
+                (node, format!("import {{ x }} from './x';\nexport const f{} = x;", i))
+            })
+            .collect();
+
+        let result = assembler.assemble(atoms, "typescript");
+        assert_eq!(result.source.matches("import { x } from './x';").count(), 2);
+    }
+
+    #[test]
+    fn test_rust_impl_signatures() {
+        let assembler = Assembler::new(ViewConfig::default());
+
+        let content = r#"
+impl Person {
+    /// Create a person
+    pub fn new(
+        name: String,
+    ) -> Self {
+        Self { name, age: 0 }
+    }
+
+    fn age(&self) -> u32 { self.age }
+}
+"#;
+
+        let signatures = assembler.extract_rust_signatures(content);
+        assert!(signatures.starts_with("impl Person {\n"));
+        assert!(signatures.contains("pub fn new(\n        name: String,\n    ) -> Self { ... }"));
+        assert!(signatures.contains("    fn age(&self) -> u32 { ... }"));
+        assert!(signatures.trim_end().ends_with('}'));
+        assert!(!signatures.contains("Create a person"));
+    }
+
+    #[test]
+    fn test_python_signatures() {
+        let assembler = Assembler::new(ViewConfig::default());
+
+        let content = r#"
+def greet(name: str) -> str:
+    """Return a greeting."""
+    # build it
+    return f"Hello, {name}"
+
+class Greeter:
+    """Greets people."""
+
+    def __init__(self, prefix):
+        self.prefix = prefix
+"#;
+
+        let signatures = assembler.extract_python_signatures(content);
+        assert!(signatures.contains("def greet(name: str) -> str:\n    \"\"\"Return a greeting.\"\"\"\n    ...\n"));
+        assert!(signatures.contains("class Greeter:\n    \"\"\"Greets people.\"\"\""));
+        assert!(signatures.contains("    def __init__(self, prefix):\n        ...\n"));
+        assert!(!signatures.contains("return"));
+        assert!(!signatures.contains("self.prefix ="));
+    }
+
+    #[test]
+    fn test_minimal_keeps_attributes() {
+        let assembler = Assembler::new(ViewConfig::minimal());
+
+        let content = "/// Docs\n#[derive(Debug)]\n\npub struct Unit;\n";
+        let minimized = assembler.minimize(content, "rust");
+        assert_eq!(minimized, "#[derive(Debug)]\npub struct Unit;\n");
+    }
+
+    #[test]
+    fn test_documented_keeps_doc_comments() {
+        let assembler = Assembler::new(ViewConfig::documented());
+
+        let content = r#"/// Adds one
+// implementation note
+fn inc(x: u32) -> u32 {
+    /* inline
+       block */
+    x + 1
+}
+"#;
+
+        let documented = assembler.format_content(content, "rust");
+        assert!(documented.contains("/// Adds one"));
+        assert!(!documented.contains("implementation note"));
+        assert!(!documented.contains("inline"));
+        assert!(documented.contains("x + 1"));
+    }
+
+    #[test]
+    fn test_tokens_counted_after_formatting() {
+        let body = format!("pub fn big() -> u32 {{\n{}    0\n}}", "    let _x = 1;\n".repeat(50));
+        let atoms = || vec![(GraphNode::new("chunk:big", "big").with_language("rust"), body.clone())];
+
+        let source = Assembler::new(ViewConfig::default()).assemble(atoms(), "rust");
+        let config = ViewConfig {
+            format: ViewFormat::Signatures,
+            ..Default::default()
+        };
+        let signatures = Assembler::new(config).assemble(atoms(), "rust");
+
+        assert!(signatures.source.contains("pub fn big() -> u32 { ... }"));
+        assert!(signatures.total_tokens < source.total_tokens / 10);
+        assert_eq!(signatures.fragments[0].token_count, signatures.total_tokens);
+    }
+
+    #[test]
+    fn test_token_estimator_decides_truncation() {
+        let atoms = || {
+            vec![
+                (GraphNode::new("chunk:a", "greeting"), format!("const GREETING: &str = \"{}\";", "你好".repeat(300))),
+                (GraphNode::new("chunk:b", "answer"), "const ANSWER: u32 = 42;".to_string()),
+            ]
+        };
+        let config = ViewConfig {
+            max_tokens: 500,
+            sort_by_type: false,
+            ordering: ViewOrdering::AsRequested,
+            ..Default::default()
+        };
+
+        // 1800 bytes of CJK look like 450 tokens to the byte heuristic
+        let bytes = Assembler::new(config.clone()).assemble(atoms(), "rust");
+        assert_eq!(bytes.fragments.len(), 2);
+
+        let calibrated = Assembler::new(config.with_token_estimator(TokenEstimatorKind::Calibrated)).assemble(atoms(), "rust");
+        assert!(calibrated.fragments.is_empty());
+        assert_eq!(calibrated.total_tokens, 0);
+    }
+
+    fn ordering_atoms() -> Vec<(GraphNode, String)> {
+        vec![
+            (GraphNode::new("chunk:c", "render").with_alias("app/render"), "fn render(p: Point) {}".to_string()),
+            (GraphNode::new("chunk:b", "point").with_alias("geo/point"), "struct Point;".to_string()),
+            (GraphNode::new("chunk:a", "zoom").with_alias("app/zoom"), "fn zoom() {}".to_string()),
+        ]
+    }
+
+    fn fragment_ids(result: &AssemblyResult) -> Vec<&str> {
+        result.fragments.iter().map(|f| f.chunk_id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_topological_ordering() {
+        let config = ViewConfig {
+            sort_by_type: false,
+            ..Default::default()
+        };
+        let dependencies = HashMap::from([
+            ("chunk:a".to_string(), vec!["chunk:c".to_string()]),
+            ("chunk:c".to_string(), vec!["chunk:b".to_string(), "chunk:outside".to_string()]),
+        ]);
+        let assembler = Assembler::new(config).with_dependencies(dependencies);
+
+        // Definitions come before their uses, whatever the input order
+        let result = assembler.assemble(ordering_atoms(), "rust");
+        assert_eq!(fragment_ids(&result), ["chunk:b", "chunk:c", "chunk:a"]);
+        let mut reversed = ordering_atoms();
+        reversed.reverse();
+        assert_eq!(assembler.assemble(reversed, "rust").source, result.source);
+
+        // Without edges, ties are broken by chunk ID
+        let unrelated = Assembler::new(ViewConfig { sort_by_type: false, ..Default::default() });
+        assert_eq!(fragment_ids(&unrelated.assemble(ordering_atoms(), "rust")), ["chunk:a", "chunk:b", "chunk:c"]);
+    }
+
+    #[test]
+    fn test_topological_ordering_breaks_cycles() {
+        let dependencies = HashMap::from([
+            ("chunk:a".to_string(), vec!["chunk:b".to_string()]),
+            ("chunk:b".to_string(), vec!["chunk:a".to_string()]),
+            ("chunk:c".to_string(), vec!["chunk:b".to_string()]),
+        ]);
+        let assembler = Assembler::new(ViewConfig::default()).with_dependencies(dependencies);
+        let result = assembler.assemble(ordering_atoms(), "rust");
+        assert_eq!(fragment_ids(&result), ["chunk:a", "chunk:b", "chunk:c"]);
+    }
+
+    #[test]
+    fn test_by_name_and_as_requested_ordering() {
+        let by_name = Assembler::new(ViewConfig {
+            ordering: ViewOrdering::ByName,
+            ..Default::default()
+        });
+        let result = by_name.assemble(ordering_atoms(), "rust");
+        assert_eq!(fragment_ids(&result), ["chunk:c", "chunk:a", "chunk:b"]);
+
+        let as_requested = Assembler::new(ViewConfig {
+            ordering: ViewOrdering::AsRequested,
+            ..Default::default()
+        });
+        let result = as_requested.assemble(ordering_atoms(), "rust");
+        assert_eq!(fragment_ids(&result), ["chunk:c", "chunk:b", "chunk:a"]);
+    }
+
+    /// Atoms from `src/lib.rs` (lines 1-4) and `src/util.rs` (lines 20-24),
+    /// both importing HashMap
+    fn mapped_atoms() -> Vec<(GraphNode, String)> {
+        vec![
+            (
+                GraphNode::new("chunk:a", "a").with_source("src/lib.rs", 1, 4),
+                "use std::collections::HashMap;\n\npub fn a() -> HashMap<u8, u8> {\n    HashMap::new()\n}".to_string(),
+            ),
+            (
+                GraphNode::new("chunk:b", "b").with_source("src/util.rs", 20, 24),
+                "use std::collections::HashMap;\nuse std::fmt::Debug;\n// helper\npub fn b() -> usize {\n    1\n}".to_string(),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_source_map_survives_import_deduplication() {
+        let config = ViewConfig {
+            sort_by_type: false,
+            ordering: ViewOrdering::AsRequested,
+            ..Default::default()
+        };
+        let result = Assembler::new(config).assemble(mapped_atoms(), "rust");
+        let lines: Vec<&str> = result.source.lines().collect();
+        let mut view = super::super::VirtualView::new("rust");
+        view.source_map = result.source_map;
+
+        // The shared import maps to the first atom that had it
+        assert_eq!(lines[0], "use std::collections::HashMap;");
+        assert_eq!(view.map_line(1), Some(("chunk:a", "src/lib.rs", 1)));
+        assert_eq!(lines[1], "use std::fmt::Debug;");
+        assert_eq!(view.map_line(2), Some(("chunk:b", "src/util.rs", 21)));
+
+        // Bodies map past the imports taken out of them
+        let a = lines.iter().position(|l| *l == "pub fn a() -> HashMap<u8, u8> {").unwrap() + 1;
+        assert_eq!(view.map_line(a), Some(("chunk:a", "src/lib.rs", 3)));
+        assert_eq!(view.map_line(a + 1), Some(("chunk:a", "src/lib.rs", 4)));
+        let b = lines.iter().position(|l| *l == "pub fn b() -> usize {").unwrap() + 1;
+        assert_eq!(view.map_line(b - 1), Some(("chunk:b", "src/util.rs", 22)));
+        assert_eq!(view.map_line(b), Some(("chunk:b", "src/util.rs", 23)));
+        assert_eq!(view.map_line(b + 2), Some(("chunk:b", "src/util.rs", 25)));
+
+        // Separators map nowhere
+        let separator = lines.iter().position(|l| l.starts_with("// --- ")).unwrap() + 1;
+        assert_eq!(view.map_line(separator), None);
+    }
+
+    #[test]
+    fn test_source_map_after_truncation_and_formatting() {
+        let config = ViewConfig {
+            sort_by_type: false,
+            ordering: ViewOrdering::AsRequested,
+            format: ViewFormat::Minimal,
+            add_separators: false,
+            max_tokens: 30,
+            ..Default::default()
+        };
+        let mut atoms = mapped_atoms();
+        atoms.reverse();
+        let result = Assembler::new(config).assemble(atoms, "rust");
+
+        // Only b fits; a's import no longer lands in the header and its
+        // comment is stripped, shifting the lines after it
+        assert_eq!(fragment_ids(&result), ["chunk:b"]);
+        let mut view = super::super::VirtualView::new("rust");
+        view.source_map = result.source_map;
+        let lines: Vec<&str> = result.source.lines().collect();
+        for (i, line) in lines.iter().enumerate() {
+            if let Some((_, _, original)) = view.map_line(i + 1) {
+                let atom = &mapped_atoms()[1].1;
+                assert_eq!(atom.lines().nth(original - 20), Some(*line));
+            }
+        }
+        let b = lines.iter().position(|l| *l == "pub fn b() -> usize {").unwrap() + 1;
+        assert_eq!(view.map_line(b), Some(("chunk:b", "src/util.rs", 23)));
+        assert!(view.map_line(lines.len() + 1).is_none());
+    }
 }
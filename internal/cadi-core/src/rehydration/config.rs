@@ -2,6 +2,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::tokens::TokenEstimatorKind;
+
 /// Configuration for creating virtual views
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ViewConfig {
@@ -36,11 +38,37 @@ pub struct ViewConfig {
     /// Deduplicate atoms that appear multiple times
     #[serde(default = "default_true")]
     pub deduplicate: bool,
+
+    /// Hoist import statements to the top of the view, deduplicated
+    #[serde(default = "default_true")]
+    pub dedupe_imports: bool,
+
+    /// Replace each requested atom with its latest refinement, if any
+    #[serde(default)]
+    pub prefer_refined: bool,
+
+    /// Order in which atoms are laid out in the view
+    #[serde(default)]
+    pub ordering: ViewOrdering,
+
+    /// How tokens are counted against `max_tokens`
+    #[serde(default)]
+    pub token_estimator: TokenEstimatorKind,
+
+    /// Add the tests covering the requested atoms, in a section of their
+    /// own after the code
+    #[serde(default)]
+    pub include_tests: bool,
+
+    /// Most tests to add with `include_tests`
+    #[serde(default = "default_max_tests")]
+    pub max_tests: usize,
 }
 
 fn default_max_tokens() -> usize { 8000 }
 fn default_expansion_depth() -> usize { 1 }
 fn default_true() -> bool { true }
+fn default_max_tests() -> usize { 5 }
 
 impl Default for ViewConfig {
     fn default() -> Self {
@@ -53,6 +81,12 @@ impl Default for ViewConfig {
             add_separators: true,
             sort_by_type: true,
             deduplicate: true,
+            dedupe_imports: true,
+            prefer_refined: false,
+            ordering: ViewOrdering::Topological,
+            token_estimator: TokenEstimatorKind::Bytes,
+            include_tests: false,
+            max_tests: default_max_tests(),
         }
     }
 }
@@ -69,6 +103,12 @@ impl ViewConfig {
             add_separators: false,
             sort_by_type: false,
             deduplicate: true,
+            dedupe_imports: true,
+            prefer_refined: false,
+            ordering: ViewOrdering::Topological,
+            token_estimator: TokenEstimatorKind::Bytes,
+            include_tests: false,
+            max_tests: default_max_tests(),
         }
     }
 
@@ -83,6 +123,12 @@ impl ViewConfig {
             add_separators: true,
             sort_by_type: true,
             deduplicate: true,
+            dedupe_imports: true,
+            prefer_refined: false,
+            ordering: ViewOrdering::Topological,
+            token_estimator: TokenEstimatorKind::Bytes,
+            include_tests: false,
+            max_tests: default_max_tests(),
         }
     }
 
@@ -98,6 +144,19 @@ impl ViewConfig {
         self
     }
 
+    /// Set how tokens are counted
+    pub fn with_token_estimator(mut self, token_estimator: TokenEstimatorKind) -> Self {
+        self.token_estimator = token_estimator;
+        self
+    }
+
+    /// Add up to `max_tests` tests covering the requested atoms
+    pub fn with_tests(mut self, max_tests: usize) -> Self {
+        self.include_tests = true;
+        self.max_tests = max_tests;
+        self
+    }
+
     /// Disable Ghost Imports
     pub fn no_expansion(mut self) -> Self {
         self.expansion_depth = 0;
@@ -121,3 +180,45 @@ pub enum ViewFormat {
     /// JSON representation
     Json,
 }
+
+impl std::str::FromStr for ViewFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "source" => Ok(Self::Source),
+            "minimal" => Ok(Self::Minimal),
+            "documented" => Ok(Self::Documented),
+            "signatures" => Ok(Self::Signatures),
+            "json" => Ok(Self::Json),
+            other => Err(format!("Unknown view format: {}", other)),
+        }
+    }
+}
+
+/// Layout order of the atoms in a view. Every ordering is deterministic:
+/// the same atoms always produce the same view.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViewOrdering {
+    /// Definitions before the atoms that use them, ties broken by chunk ID
+    #[default]
+    Topological,
+    /// By alias (falling back to chunk ID)
+    ByName,
+    /// In the order the atoms were requested, ghost imports last
+    AsRequested,
+}
+
+impl std::str::FromStr for ViewOrdering {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('-', "_").as_str() {
+            "topological" => Ok(Self::Topological),
+            "by_name" | "name" => Ok(Self::ByName),
+            "as_requested" | "requested" => Ok(Self::AsRequested),
+            other => Err(format!("Unknown view ordering: {}", other)),
+        }
+    }
+}
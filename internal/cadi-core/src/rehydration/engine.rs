@@ -2,13 +2,15 @@
 //!
 //! The main engine for creating virtual views from atoms.
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use super::assembler::Assembler;
-use super::config::ViewConfig;
-use super::view::VirtualView;
+use super::config::{ViewConfig, ViewOrdering};
+use super::validation::{validate_and_repair, ViewValidation};
+use super::view::{InclusionReason, ViewFile, VirtualView};
 use crate::error::{CadiError, CadiResult};
-use crate::graph::GraphStore;
+use crate::ghost::analyzer::DependencyAnalyzer;
+use crate::graph::{GraphNode, GraphStore};
 
 use std::sync::Arc;
 
@@ -35,14 +37,44 @@ impl RehydrationEngine {
         config: ViewConfig,
     ) -> CadiResult<VirtualView> {
         let expansion_depth = config.expansion_depth;
-        
+
+        // Swap requested atoms for their newest versions
+        let mut substitutions = Vec::new();
+        let atom_ids = if config.prefer_refined {
+            let mut refined = Vec::with_capacity(atom_ids.len());
+            for atom_id in atom_ids {
+                match self.graph.get_latest_refinement(&atom_id)? {
+                    Some(latest) => {
+                        substitutions.push((atom_id, latest.clone()));
+                        refined.push(latest);
+                    }
+                    None => refined.push(atom_id),
+                }
+            }
+            refined
+        } else {
+            atom_ids
+        };
+
         // Expand dependencies if configured
-        let (all_atoms, ghost_atoms) = if expansion_depth > 0 {
+        let (mut all_atoms, ghost_atoms) = if expansion_depth > 0 {
             self.expand_dependencies(&atom_ids, expansion_depth).await?
         } else {
             (atom_ids.clone(), Vec::new())
         };
 
+        // Tests covering the requested atoms go last
+        let mut test_atoms = Vec::new();
+        if config.include_tests {
+            for test in DependencyAnalyzer::new(&self.graph).covering_tests(&atom_ids, config.max_tests)? {
+                if !all_atoms.contains(&test.atom_id) {
+                    all_atoms.push(test.atom_id.clone());
+                    test_atoms.push(test.atom_id);
+                }
+            }
+        }
+        let tests: HashSet<String> = test_atoms.iter().cloned().collect();
+
         // Collect atom data
         let mut atoms_with_content = Vec::new();
         for atom_id in &all_atoms {
@@ -57,15 +89,79 @@ impl RehydrationEngine {
             return Ok(VirtualView::new("unknown"));
         }
 
-        // Detect primary language
-        let language = atoms_with_content
-            .first()
-            .map(|(n, _)| n.language.clone())
-            .unwrap_or_else(|| "unknown".to_string());
+        // Group atoms by language, in the order languages first appear.
+        // The first group's language is the view's primary language.
+        let mut groups: Vec<(String, Vec<(GraphNode, String)>)> = Vec::new();
+        for (node, content) in atoms_with_content {
+            match groups.iter_mut().find(|(language, _)| *language == node.language) {
+                Some((_, atoms)) => atoms.push((node, content)),
+                None => groups.push((node.language.clone(), vec![(node, content)])),
+            }
+        }
+        let language = groups[0].0.clone();
+
+        let dependencies = if config.ordering == ViewOrdering::Topological {
+            self.view_dependencies(&all_atoms)?
+        } else {
+            HashMap::new()
+        };
+
+        // Assemble each language on its own, sharing the token budget
+        let mut files = Vec::new();
+        let mut source = String::new();
+        let mut fragments = Vec::new();
+        let mut symbol_locations = BTreeMap::new();
+        let mut source_map = Vec::new();
+        let mut total_tokens = 0;
+        let mut truncated = false;
+        for (file_language, atoms) in groups {
+            let remaining = config.max_tokens.saturating_sub(total_tokens);
+            if remaining == 0 {
+                truncated = true;
+                break;
+            }
+            let assembler = Assembler::new(ViewConfig {
+                max_tokens: remaining,
+                ..config.clone()
+            })
+            .with_dependencies(dependencies.clone())
+            .with_tests(tests.clone());
+            let result = assembler.assemble(atoms, &file_language);
+            let (file_source, validation) = validate_and_repair(result.source, &file_language, config.format);
+
+            // Fragment and symbol lines refer to the flattened source
+            let offset = source.lines().count();
+            for (symbol, line) in result.symbol_locations {
+                symbol_locations.insert(symbol, line + offset);
+            }
+            for mut fragment in result.fragments.iter().cloned() {
+                fragment.start_line += offset;
+                fragment.end_line += offset;
+                if ghost_atoms.contains(&fragment.chunk_id) {
+                    fragment.inclusion_reason = InclusionReason::GhostImport;
+                } else if tests.contains(&fragment.chunk_id) {
+                    fragment.inclusion_reason = InclusionReason::Test;
+                }
+                fragments.push(fragment);
+            }
+            for mut region in result.source_map {
+                region.view_start_line += offset;
+                region.view_end_line += offset;
+                source_map.push(region);
+            }
 
-        // Assemble the view
-        let assembler = Assembler::new(config.clone());
-        let result = assembler.assemble(atoms_with_content, &language);
+            source.push_str(&file_source);
+            total_tokens += result.total_tokens;
+            truncated |= result.truncated;
+            files.push(ViewFile {
+                language: file_language,
+                source: file_source,
+                atoms: result.fragments.into_iter().map(|f| f.chunk_id).collect(),
+                token_estimate: result.total_tokens,
+                validation,
+            });
+        }
+        let validation = combined_validation(&files);
 
         // Build explanation
         let mut explanation = format!(
@@ -78,17 +174,31 @@ impl RehydrationEngine {
                 ghost_atoms.len()
             ));
         }
+        if !test_atoms.is_empty() {
+            explanation.push_str(&format!("; {} covering test(s) in a tests section", test_atoms.len()));
+        }
+        if files.len() > 1 {
+            let languages: Vec<&str> = files.iter().map(|f| f.language.as_str()).collect();
+            explanation.push_str(&format!(" across {} files ({})", files.len(), languages.join(", ")));
+        }
+        for (requested, latest) in &substitutions {
+            explanation.push_str(&format!("; using {} in place of {} (refined)", latest, requested));
+        }
 
         Ok(VirtualView {
-            source: result.source,
+            source,
+            files,
             atoms: all_atoms,
             ghost_atoms,
-            token_estimate: result.total_tokens,
+            test_atoms,
+            token_estimate: total_tokens,
             language,
-            symbol_locations: result.symbol_locations,
-            fragments: result.fragments,
-            truncated: result.truncated,
+            symbol_locations,
+            fragments,
+            source_map,
+            truncated,
             explanation,
+            validation,
         })
     }
 
@@ -116,8 +226,11 @@ impl RehydrationEngine {
         let mut ghost_atoms = Vec::new();
 
         // Add requested atoms
+        let mut ordered = Vec::new();
         for id in atom_ids {
-            all_atoms.insert(id.clone());
+            if all_atoms.insert(id.clone()) {
+                ordered.push(id.clone());
+            }
         }
 
         // BFS expansion
@@ -128,7 +241,7 @@ impl RehydrationEngine {
 
             for atom_id in &frontier {
                 // Get dependencies
-                let deps = self.graph.get_dependencies(atom_id)?;
+                let deps = self.graph.get_dependencies(atom_id, None)?;
                 
                 for (edge_type, dep_id) in deps {
                     // Only follow strong dependencies for ghost imports
@@ -136,8 +249,8 @@ impl RehydrationEngine {
                         continue;
                     }
 
-                    if !all_atoms.contains(&dep_id) {
-                        all_atoms.insert(dep_id.clone());
+                    if all_atoms.insert(dep_id.clone()) {
+                        ordered.push(dep_id.clone());
                         ghost_atoms.push(dep_id.clone());
                         next_frontier.push(dep_id);
                     }
@@ -151,7 +264,27 @@ impl RehydrationEngine {
             }
         }
 
-        Ok((all_atoms.into_iter().collect(), ghost_atoms))
+        // Requested atoms first, then ghost imports in the order found
+        Ok((ordered, ghost_atoms))
+    }
+
+    /// Dependency edges between the atoms of a view, for laying it out
+    fn view_dependencies(&self, atom_ids: &[String]) -> CadiResult<HashMap<String, Vec<String>>> {
+        let in_view: HashSet<&str> = atom_ids.iter().map(String::as_str).collect();
+        let mut dependencies = HashMap::new();
+        for atom_id in atom_ids {
+            let uses: Vec<String> = self
+                .graph
+                .get_dependencies(atom_id, None)?
+                .into_iter()
+                .filter(|(edge_type, dep_id)| edge_type.orders_layout() && in_view.contains(dep_id.as_str()))
+                .map(|(_, dep_id)| dep_id)
+                .collect();
+            if !uses.is_empty() {
+                dependencies.insert(atom_id.clone(), uses);
+            }
+        }
+        Ok(dependencies)
     }
 
     /// Get token estimate for a set of atoms
@@ -201,10 +334,26 @@ impl RehydrationEngine {
     }
 }
 
+/// Validation of a whole view from its files' results
+fn combined_validation(files: &[ViewFile]) -> ViewValidation {
+    match files {
+        [] => ViewValidation::default(),
+        [file] => file.validation.clone(),
+        _ => ViewValidation {
+            valid: files.iter().all(|f| f.validation.valid),
+            errors: files
+                .iter()
+                .flat_map(|f| f.validation.errors.iter().map(move |e| format!("{}: {}", f.language, e)))
+                .collect(),
+            repair: None,
+        },
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::graph::{GraphNode, EdgeType};
+    use crate::graph::EdgeType;
 
     #[test]
     fn test_create_view() {
@@ -228,6 +377,8 @@ mod tests {
         
         assert!(view.source.contains("func_a"));
         assert_eq!(view.atoms.len(), 1);
+        assert!(view.validation.valid);
+        assert!(view.validation.repair.is_none());
     }
 
     #[test]
@@ -259,4 +410,151 @@ mod tests {
         assert!(view.atoms.contains(&"chunk:b".to_string()));
         assert!(view.ghost_atoms.contains(&"chunk:b".to_string()));
     }
+
+    #[test]
+    fn test_prefer_refined_swaps_requested_atoms() {
+        let graph = GraphStore::in_memory().unwrap();
+        for (id, source) in [("chunk:v1", "fn sort_v1() {}"), ("chunk:v2", "fn sort_v2() {}")] {
+            graph.insert_node(&GraphNode::new(id, id).with_language("rust")).unwrap();
+            graph.store_content(id, source.as_bytes()).unwrap();
+        }
+        graph.mark_refinement("chunk:v1", "chunk:v2", "linear time").unwrap();
+
+        let engine = RehydrationEngine::new(graph);
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let plain = rt.block_on(engine.create_view(vec!["chunk:v1".to_string()], ViewConfig::default())).unwrap();
+        assert_eq!(plain.atoms, ["chunk:v1"]);
+
+        let config = ViewConfig {
+            prefer_refined: true,
+            ..Default::default()
+        };
+        let view = rt.block_on(engine.create_view(vec!["chunk:v1".to_string()], config)).unwrap();
+        assert_eq!(view.atoms, ["chunk:v2"]);
+        assert!(view.source.contains("sort_v2") && !view.source.contains("sort_v1"));
+        assert!(view.explanation.contains("using chunk:v2 in place of chunk:v1 (refined)"));
+    }
+
+    #[test]
+    fn test_views_partitioned_by_language() {
+        let graph = GraphStore::in_memory().unwrap();
+
+        let rust = GraphNode::new("chunk:rust", "r")
+            .with_language("rust")
+            .with_defines(vec!["Point".to_string()]);
+        let python = GraphNode::new("chunk:python", "p")
+            .with_language("python")
+            .with_defines(vec!["point".to_string()]);
+        graph.insert_node(&rust).unwrap();
+        graph.insert_node(&python).unwrap();
+        graph.store_content("chunk:rust", b"pub struct Point { x: f64, y: f64 }").unwrap();
+        graph.store_content("chunk:python", b"def point(x, y):\n    return {'x': x, 'y': y}").unwrap();
+
+        let engine = RehydrationEngine::new(graph);
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let view = rt.block_on(engine.create_view(
+            vec!["chunk:rust".to_string(), "chunk:python".to_string()],
+            ViewConfig::default(),
+        )).unwrap();
+
+        assert_eq!(view.files.len(), 2);
+        assert_eq!(view.files[0].language, "rust");
+        assert_eq!(view.files[0].atoms, vec!["chunk:rust"]);
+        assert!(view.files[0].source.contains("struct Point"));
+        assert_eq!(view.files[1].language, "python");
+        assert_eq!(view.files[1].atoms, vec!["chunk:python"]);
+        assert!(!view.files[1].source.contains("struct Point"));
+
+        // The flattened source keeps both, with lines pointing into it
+        assert_eq!(view.language, "rust");
+        assert_eq!(view.source, format!("{}{}", view.files[0].source, view.files[1].source));
+        let line = view.find_symbol("point").unwrap();
+        assert!(view.source.lines().nth(line - 1).unwrap().starts_with("def point"));
+        assert!(view.validation.valid);
+    }
+
+    #[test]
+    fn test_views_are_ordered_and_stable() {
+        let graph = GraphStore::in_memory().unwrap();
+
+        // render uses Point; Point is pulled in as a ghost import
+        let render = GraphNode::new("chunk:a-render", "render")
+            .with_language("rust")
+            .with_defines(vec!["render".to_string()]);
+        let point = GraphNode::new("chunk:z-point", "point")
+            .with_language("rust")
+            .with_defines(vec!["Point".to_string()]);
+        let origin = GraphNode::new("chunk:m-origin", "origin")
+            .with_language("rust")
+            .with_defines(vec!["origin".to_string()]);
+        for node in [&render, &point, &origin] {
+            graph.insert_node(node).unwrap();
+        }
+        graph.store_content("chunk:a-render", b"fn render(p: Point) {\n    draw(p);\n}").unwrap();
+        graph.store_content("chunk:z-point", b"struct Point {\n    x: f64,\n}").unwrap();
+        graph.store_content("chunk:m-origin", b"fn origin() -> Point { Point { x: 0.0 } }").unwrap();
+        graph.add_dependency("chunk:a-render", "chunk:z-point", EdgeType::TypeRef).unwrap();
+        graph.add_dependency("chunk:m-origin", "chunk:z-point", EdgeType::TypeRef).unwrap();
+
+        let engine = RehydrationEngine::new(graph);
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let request = || vec!["chunk:m-origin".to_string(), "chunk:a-render".to_string()];
+        let view = rt.block_on(engine.create_view(request(), ViewConfig::default())).unwrap();
+
+        let order: Vec<&str> = view.fragments.iter().map(|f| f.chunk_id.as_str()).collect();
+        assert_eq!(order, ["chunk:z-point", "chunk:a-render", "chunk:m-origin"]);
+        assert_eq!(view.fragments[0].inclusion_reason, InclusionReason::GhostImport);
+        assert_eq!(view.fragments[1].inclusion_reason, InclusionReason::Requested);
+
+        // Every line of a fragment maps back to its chunk
+        let lines: Vec<&str> = view.source.lines().collect();
+        for fragment in &view.fragments {
+            let first = lines[fragment.start_line - 1];
+            assert!(fragment.defines.iter().any(|symbol| first.contains(symbol.as_str())));
+            assert_eq!(view.fragment_at_line(fragment.end_line).unwrap().chunk_id, fragment.chunk_id);
+        }
+        assert!(view.fragment_at_line(lines.len() + 1).is_none());
+
+        // Repeated requests produce byte-identical views
+        let snapshot = serde_json::to_string(&view).unwrap();
+        for _ in 0..5 {
+            let again = rt.block_on(engine.create_view(request(), ViewConfig::default())).unwrap();
+            assert_eq!(again.source, view.source);
+            assert_eq!(serde_json::to_string(&again).unwrap(), snapshot);
+        }
+    }
+
+    #[test]
+    fn test_covering_tests_get_their_own_section() {
+        let graph = GraphStore::in_memory().unwrap();
+        let parse = GraphNode::new("chunk:parse", "parse")
+            .with_language("rust")
+            .with_granularity("function")
+            .with_defines(vec!["parse".to_string()]);
+        let test = GraphNode::new("chunk:test-parse", "test")
+            .with_language("rust")
+            .with_granularity("function")
+            .with_defines(vec!["test_parse".to_string()])
+            .with_references(vec!["parse".to_string()]);
+        graph.insert_node(&parse).unwrap();
+        graph.insert_node(&test).unwrap();
+        graph.store_content("chunk:parse", b"fn parse(s: &str) -> u32 {\n    s.len() as u32\n}").unwrap();
+        graph.store_content("chunk:test-parse", b"#[test]\nfn test_parse() {\n    assert_eq!(parse(\"ab\"), 2);\n}").unwrap();
+
+        let engine = RehydrationEngine::new(graph);
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let request = || vec!["chunk:parse".to_string()];
+
+        let plain = rt.block_on(engine.create_view(request(), ViewConfig::default())).unwrap();
+        assert!(plain.test_atoms.is_empty());
+        assert!(!plain.source.contains("--- tests ---"));
+
+        let view = rt.block_on(engine.create_view(request(), ViewConfig::default().with_tests(5))).unwrap();
+        assert_eq!(view.test_atoms, ["chunk:test-parse"]);
+        assert_eq!(view.fragments[1].inclusion_reason, InclusionReason::Test);
+        let section = view.source.find("// --- tests ---").unwrap();
+        assert!(view.source.find("fn parse").unwrap() < section);
+        assert!(view.source.find("fn test_parse").unwrap() > section);
+        assert!(view.source.lines().nth(view.fragments[1].start_line).unwrap().starts_with("fn test_parse"));
+    }
 }
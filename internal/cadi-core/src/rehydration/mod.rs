@@ -28,8 +28,10 @@ pub mod engine;
 pub mod view;
 pub mod config;
 pub mod assembler;
+pub mod validation;
 
 pub use engine::RehydrationEngine;
-pub use view::{VirtualView, ViewFragment};
-pub use config::ViewConfig;
+pub use view::{MappedRegion, VirtualView, ViewFile, ViewFragment};
+pub use config::{ViewConfig, ViewFormat, ViewOrdering};
 pub use assembler::Assembler;
+pub use validation::{ViewRepair, ViewValidation};
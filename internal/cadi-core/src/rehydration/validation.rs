@@ -0,0 +1,433 @@
+//! Syntactic validation of assembled views
+//!
+//! Every view is checked for balanced `()`, `[]` and `{}`, skipping strings
+//! and comments. JSON and YAML views are parsed, and so are Rust views when
+//! the `syntax-validation` feature is enabled. A view whose only problem is
+//! unclosed delimiters at the end, as left by truncation, is repaired by
+//! appending the missing closers.
+
+use serde::{Deserialize, Serialize};
+
+use super::config::ViewFormat;
+
+/// Outcome of validating a view
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViewValidation {
+    /// Whether the view's source parses, after any repair
+    pub valid: bool,
+    /// Problems found in the source as returned
+    pub errors: Vec<String>,
+    /// Set when a repair was attempted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repair: Option<ViewRepair>,
+}
+
+/// A repair attempt on an invalid view
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViewRepair {
+    /// Whether the repaired source is valid. A failed repair is discarded.
+    pub succeeded: bool,
+    /// Text appended to the source
+    pub appended: String,
+    /// Problems the repair fixed
+    pub fixed: Vec<String>,
+}
+
+impl Default for ViewValidation {
+    fn default() -> Self {
+        Self {
+            valid: true,
+            errors: Vec::new(),
+            repair: None,
+        }
+    }
+}
+
+impl ViewValidation {
+    fn from_errors(errors: Vec<String>) -> Self {
+        Self {
+            valid: errors.is_empty(),
+            errors,
+            repair: None,
+        }
+    }
+}
+
+/// Validate a view's source, repairing it when the only problems are
+/// unclosed delimiters. Returns the source to use and the validation.
+pub fn validate_and_repair(source: String, language: &str, format: ViewFormat) -> (String, ViewValidation) {
+    let scan = scan_delimiters(&source, language);
+    let validation = ViewValidation::from_errors(check(&source, language, format, &scan));
+    if validation.valid || scan.unclosed.is_empty() || scan.has_other_errors() {
+        return (source, validation);
+    }
+
+    let mut appended = String::new();
+    for (open, _) in scan.unclosed.iter().rev() {
+        appended.push(closer(*open));
+        appended.push('\n');
+    }
+    let mut repaired = source.clone();
+    if !repaired.ends_with('\n') {
+        repaired.push('\n');
+    }
+    repaired.push_str(&appended);
+
+    let rescan = scan_delimiters(&repaired, language);
+    let remaining = check(&repaired, language, format, &rescan);
+    let repair = ViewRepair {
+        succeeded: remaining.is_empty(),
+        appended: appended.trim_end().to_string(),
+        fixed: validation.errors.clone(),
+    };
+
+    if repair.succeeded {
+        let validation = ViewValidation {
+            valid: true,
+            errors: Vec::new(),
+            repair: Some(repair),
+        };
+        (repaired, validation)
+    } else {
+        let validation = ViewValidation {
+            repair: Some(repair),
+            ..validation
+        };
+        (source, validation)
+    }
+}
+
+/// Validate without repairing
+pub fn validate(source: &str, language: &str, format: ViewFormat) -> ViewValidation {
+    let scan = scan_delimiters(source, language);
+    ViewValidation::from_errors(check(source, language, format, &scan))
+}
+
+fn check(source: &str, language: &str, format: ViewFormat, scan: &DelimiterScan) -> Vec<String> {
+    let mut errors = scan.errors();
+    if !errors.is_empty() {
+        return errors;
+    }
+
+    // Signature views elide bodies, so only the baseline applies to them
+    if format == ViewFormat::Signatures {
+        return errors;
+    }
+    match language {
+        "json" => {
+            if let Err(e) = serde_json::from_str::<serde_json::Value>(source) {
+                errors.push(format!("line {}: {}", e.line(), e));
+            }
+        }
+        "yaml" => {
+            for document in serde_yaml::Deserializer::from_str(source) {
+                if let Err(e) = serde_yaml::Value::deserialize(document) {
+                    errors.push(e.to_string());
+                    break;
+                }
+            }
+        }
+        #[cfg(feature = "syntax-validation")]
+        "rust" => {
+            if let Err(e) = syn::parse_file(source) {
+                errors.push(e.to_string());
+            }
+        }
+        _ => {}
+    }
+    errors
+}
+
+/// Delimiters found by [`scan_delimiters`]
+#[derive(Debug, Default)]
+struct DelimiterScan {
+    /// Opening delimiters never closed, with their line
+    unclosed: Vec<(char, usize)>,
+    /// Closers that match nothing or the wrong opener
+    mismatched: Vec<String>,
+    /// A string or block comment still open at the end
+    unterminated: Option<String>,
+}
+
+impl DelimiterScan {
+    fn has_other_errors(&self) -> bool {
+        !self.mismatched.is_empty() || self.unterminated.is_some()
+    }
+
+    fn errors(&self) -> Vec<String> {
+        let mut errors = self.mismatched.clone();
+        errors.extend(self.unterminated.clone());
+        errors.extend(
+            self.unclosed
+                .iter()
+                .map(|(open, line)| format!("line {}: unclosed '{}'", line, open)),
+        );
+        errors
+    }
+}
+
+/// Comment and string syntax of a language
+struct Lexicon {
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    rust: bool,
+    triple_quotes: bool,
+}
+
+fn lexicon(language: &str) -> Option<Lexicon> {
+    const C_LIKE: &[&str] = &["//"];
+    const HASH: &[&str] = &["#"];
+    let lexicon = match language {
+        // Parsed instead, or not code
+        "json" | "yaml" | "markdown" | "text" | "html" | "xml" => return None,
+        "rust" => Lexicon {
+            line_comments: C_LIKE,
+            block_comment: Some(("/*", "*/")),
+            quotes: &['"'],
+            rust: true,
+            triple_quotes: false,
+        },
+        "typescript" | "javascript" | "tsx" | "jsx" => Lexicon {
+            line_comments: C_LIKE,
+            block_comment: Some(("/*", "*/")),
+            quotes: &['"', '\'', '`'],
+            rust: false,
+            triple_quotes: false,
+        },
+        "python" => Lexicon {
+            line_comments: HASH,
+            block_comment: None,
+            quotes: &['"', '\''],
+            rust: false,
+            triple_quotes: true,
+        },
+        "shell" | "bash" | "ruby" | "toml" => Lexicon {
+            line_comments: HASH,
+            block_comment: None,
+            quotes: &['"', '\''],
+            rust: false,
+            triple_quotes: false,
+        },
+        "css" => Lexicon {
+            line_comments: &[],
+            block_comment: Some(("/*", "*/")),
+            quotes: &['"', '\''],
+            rust: false,
+            triple_quotes: false,
+        },
+        "c" | "cpp" | "csharp" | "c_sharp" | "go" | "java" | "glsl" | "wgsl" => Lexicon {
+            line_comments: C_LIKE,
+            block_comment: Some(("/*", "*/")),
+            quotes: &['"', '\''],
+            rust: false,
+            triple_quotes: false,
+        },
+        _ => Lexicon {
+            line_comments: &[],
+            block_comment: None,
+            quotes: &['"'],
+            rust: false,
+            triple_quotes: false,
+        },
+    };
+    Some(lexicon)
+}
+
+/// Match `()`, `[]` and `{}` outside strings and comments
+fn scan_delimiters(source: &str, language: &str) -> DelimiterScan {
+    let mut scan = DelimiterScan::default();
+    let Some(lexicon) = lexicon(language) else {
+        return scan;
+    };
+
+    let chars: Vec<char> = source.chars().collect();
+    let starts_with = |i: usize, token: &str| token.chars().enumerate().all(|(k, c)| chars.get(i + k) == Some(&c));
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if lexicon.line_comments.iter().any(|t| starts_with(i, t)) {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+
+        if let Some((open, close)) = lexicon.block_comment {
+            if starts_with(i, open) {
+                let start = line;
+                i += open.len();
+                while i < chars.len() && !starts_with(i, close) {
+                    line += (chars[i] == '\n') as usize;
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    scan.unterminated = Some(format!("line {}: unterminated block comment", start));
+                    return scan;
+                }
+                i += close.len();
+                continue;
+            }
+        }
+
+        if lexicon.rust && c == '\'' {
+            i += rust_char_literal_len(&chars[i..]);
+            continue;
+        }
+
+        // Rust raw strings: r"..." and r#"..."#
+        if lexicon.rust && c == 'r' && (i == 0 || !is_ident(chars[i - 1])) {
+            let hashes = chars[i + 1..].iter().take_while(|&&h| h == '#').count();
+            if chars.get(i + 1 + hashes) == Some(&'"') {
+                let close: String = std::iter::once('"').chain(std::iter::repeat_n('#', hashes)).collect();
+                let start = line;
+                i += hashes + 2;
+                while i < chars.len() && !starts_with(i, &close) {
+                    line += (chars[i] == '\n') as usize;
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    scan.unterminated = Some(format!("line {}: unterminated string", start));
+                    return scan;
+                }
+                i += close.len();
+                continue;
+            }
+        }
+
+        if lexicon.quotes.contains(&c) {
+            let triple: String = std::iter::repeat_n(c, 3).collect();
+            let delimiter = if lexicon.triple_quotes && starts_with(i, &triple) { triple } else { c.to_string() };
+            let start = line;
+            i += delimiter.len();
+            loop {
+                match chars.get(i) {
+                    None => {
+                        scan.unterminated = Some(format!("line {}: unterminated string", start));
+                        return scan;
+                    }
+                    Some('\\') => i += 2,
+                    Some('\n') if delimiter.len() == 1 && c != '`' && !lexicon.rust => {
+                        scan.unterminated = Some(format!("line {}: unterminated string", start));
+                        return scan;
+                    }
+                    Some(_) if starts_with(i, &delimiter) => {
+                        i += delimiter.len();
+                        break;
+                    }
+                    Some(ch) => {
+                        line += (*ch == '\n') as usize;
+                        i += 1;
+                    }
+                }
+            }
+            continue;
+        }
+
+        match c {
+            '(' | '[' | '{' => scan.unclosed.push((c, line)),
+            ')' | ']' | '}' => match scan.unclosed.last() {
+                Some(&(open, _)) if closer(open) == c => {
+                    scan.unclosed.pop();
+                }
+                Some(&(open, opened)) => {
+                    scan.mismatched.push(format!(
+                        "line {}: '{}' does not close '{}' from line {}",
+                        line, c, open, opened
+                    ));
+                    scan.unclosed.pop();
+                }
+                None => scan.mismatched.push(format!("line {}: unexpected '{}'", line, c)),
+            },
+            '\n' => line += 1,
+            _ => {}
+        }
+        i += 1;
+    }
+
+    scan
+}
+
+/// Length of a Rust char literal starting at `'`, or 1 for a lifetime
+fn rust_char_literal_len(chars: &[char]) -> usize {
+    match chars.get(1) {
+        Some('\\') => chars
+            .iter()
+            .skip(2)
+            .position(|&c| c == '\'')
+            .map_or(1, |p| p + 3),
+        Some(_) if chars.get(2) == Some(&'\'') => 3,
+        _ => 1,
+    }
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn closer(open: char) -> char {
+    match open {
+        '(' => ')',
+        '[' => ']',
+        _ => '}',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_baseline_ignores_strings_and_comments() {
+        let rust = "fn a<'a>(s: &'a str) -> char {\n    // }\n    let _ = \"}\";\n    let _ = r#\"{\"#;\n    '}'\n}\n";
+        assert!(validate(rust, "rust", ViewFormat::Source).valid);
+
+        let ts = "const s = `{${x}`; /* ( */\nfunction f() { return '['; }\n";
+        assert!(validate(ts, "typescript", ViewFormat::Source).valid);
+
+        let python = "def f():\n    \"\"\"Returns {\"\"\"\n    return [1, 2]  # ]\n";
+        assert!(validate(python, "python", ViewFormat::Source).valid);
+
+        let broken = validate("fn a() { (1] }\n", "rust", ViewFormat::Source);
+        assert!(!broken.valid);
+        assert_eq!(broken.errors, vec!["line 1: ']' does not close '(' from line 1"]);
+    }
+
+    #[test]
+    fn test_truncated_view_is_repaired() {
+        let source = "impl Parser {\n    fn parse(&self) {\n        if self.ready {\n".to_string();
+        let (repaired, validation) = validate_and_repair(source, "rust", ViewFormat::Source);
+        assert!(validation.valid);
+        let repair = validation.repair.unwrap();
+        assert!(repair.succeeded);
+        assert_eq!(repair.appended, "}\n}\n}");
+        assert_eq!(repair.fixed.len(), 3);
+        assert!(repaired.ends_with("}\n}\n}\n"));
+
+        // A stray closer is not something appending can fix
+        let (kept, validation) = validate_and_repair("fn a() {}\n}\n{\n".to_string(), "rust", ViewFormat::Source);
+        assert!(!validation.valid);
+        assert!(validation.repair.is_none());
+        assert_eq!(kept, "fn a() {}\n}\n{\n");
+    }
+
+    #[test]
+    fn test_data_fragments_are_parsed() {
+        assert!(validate("{\"a\": [1, 2]}", "json", ViewFormat::Source).valid);
+        assert!(!validate("{\"a\": [1, 2}", "json", ViewFormat::Source).valid);
+        assert!(validate("name: app\nitems: [a, b]\n", "yaml", ViewFormat::Source).valid);
+        assert!(!validate("name: app\n  bad: : indent\n", "yaml", ViewFormat::Source).valid);
+    }
+
+    #[cfg(feature = "syntax-validation")]
+    #[test]
+    fn test_rust_is_parsed() {
+        assert!(validate("fn a() -> u32 { 1 }\n", "rust", ViewFormat::Source).valid);
+        assert!(!validate("fn a() -> { 1 }\n", "rust", ViewFormat::Source).valid);
+        // Signature views have elided bodies and are not parsed
+        assert!(validate("fn a() -> u32 { ... }\n", "rust", ViewFormat::Signatures).valid);
+    }
+}
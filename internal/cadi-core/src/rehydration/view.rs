@@ -1,20 +1,31 @@
 //! Virtual View representation
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+
+use super::validation::ViewValidation;
 
 /// A virtual view - atoms assembled into a coherent context
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VirtualView {
-    /// The assembled source code
+    /// The assembled source code, every file in order
     pub source: String,
 
+    /// One file per language, in the order their languages first appear
+    #[serde(default)]
+    pub files: Vec<ViewFile>,
+
     /// Atoms included in this view (in order)
     pub atoms: Vec<String>,
 
     /// Which atoms were added automatically (Ghost Imports)
     pub ghost_atoms: Vec<String>,
 
+    /// Which atoms are tests covering the requested atoms, laid out after
+    /// the code (`ViewConfig::include_tests`)
+    #[serde(default)]
+    pub test_atoms: Vec<String>,
+
     /// Total token estimate
     pub token_estimate: usize,
 
@@ -22,16 +33,28 @@ pub struct VirtualView {
     pub language: String,
 
     /// Map of symbol name -> line number in the view
-    pub symbol_locations: HashMap<String, usize>,
+    pub symbol_locations: BTreeMap<String, usize>,
 
-    /// Fragments that make up this view
+    /// Fragments that make up this view, in layout order, with the line
+    /// range each atom occupies in `source`
     pub fragments: Vec<ViewFragment>,
 
+    /// Runs of lines in `source` copied unchanged from an original file,
+    /// in view order. Lines the view rewrote (merged imports, signatures,
+    /// separators) are left out.
+    #[serde(default)]
+    pub source_map: Vec<MappedRegion>,
+
     /// Was the view truncated due to token limits?
     pub truncated: bool,
 
     /// Explanation of how the view was constructed
     pub explanation: String,
+
+    /// Syntactic validation of the assembled source. With more than one
+    /// file this combines the files' results; repairs are reported per file.
+    #[serde(default)]
+    pub validation: ViewValidation,
 }
 
 impl VirtualView {
@@ -39,14 +62,18 @@ impl VirtualView {
     pub fn new(language: impl Into<String>) -> Self {
         Self {
             source: String::new(),
+            files: Vec::new(),
             atoms: Vec::new(),
             ghost_atoms: Vec::new(),
+            test_atoms: Vec::new(),
             token_estimate: 0,
             language: language.into(),
-            symbol_locations: HashMap::new(),
+            symbol_locations: BTreeMap::new(),
             fragments: Vec::new(),
+            source_map: Vec::new(),
             truncated: false,
             explanation: String::new(),
+            validation: ViewValidation::default(),
         }
     }
 
@@ -65,6 +92,22 @@ impl VirtualView {
         self.symbol_locations.get(name).copied()
     }
 
+    /// Find the fragment, and so the chunk, that a line of the view came from
+    pub fn fragment_at_line(&self, line: usize) -> Option<&ViewFragment> {
+        self.fragments
+            .iter()
+            .find(|f| f.start_line <= line && line <= f.end_line)
+    }
+
+    /// The chunk, original file and original line a line of the view was
+    /// copied from, if it was copied unchanged
+    pub fn map_line(&self, view_line: usize) -> Option<(&str, &str, usize)> {
+        self.source_map
+            .iter()
+            .find(|r| r.view_start_line <= view_line && view_line <= r.view_end_line)
+            .map(|r| (r.chunk_id.as_str(), r.file.as_str(), r.original_start_line + (view_line - r.view_start_line)))
+    }
+
     /// Get a snippet around a symbol
     pub fn snippet_for_symbol(&self, name: &str, context_lines: usize) -> Option<String> {
         let line = self.find_symbol(name)?;
@@ -87,6 +130,26 @@ impl VirtualView {
     }
 }
 
+/// The atoms of one language in a virtual view, assembled on their own
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewFile {
+    /// Language of every atom in the file
+    pub language: String,
+
+    /// The assembled source code
+    pub source: String,
+
+    /// Atoms included in this file (in order)
+    pub atoms: Vec<String>,
+
+    /// Token estimate for this file
+    pub token_estimate: usize,
+
+    /// Syntactic validation of this file's source
+    #[serde(default)]
+    pub validation: ViewValidation,
+}
+
 /// A fragment in a virtual view
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ViewFragment {
@@ -112,6 +175,50 @@ pub struct ViewFragment {
     pub defines: Vec<String>,
 }
 
+/// Consecutive lines of a view copied from consecutive lines of one
+/// original file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MappedRegion {
+    /// The chunk the lines came from
+    pub chunk_id: String,
+
+    /// Original file path, as recorded in the chunk's source location
+    pub file: String,
+
+    /// First line of the region in the assembled view
+    pub view_start_line: usize,
+
+    /// Last line of the region in the assembled view
+    pub view_end_line: usize,
+
+    /// Line of the original file that `view_start_line` came from
+    pub original_start_line: usize,
+}
+
+impl MappedRegion {
+    /// Append a view line copied from `file:original_line` to a source map,
+    /// extending the last region when the line continues it
+    pub fn extend_map(map: &mut Vec<MappedRegion>, view_line: usize, chunk_id: &str, file: &str, original_line: usize) {
+        if let Some(last) = map.last_mut() {
+            if last.chunk_id == chunk_id
+                && last.file == file
+                && last.view_end_line + 1 == view_line
+                && last.original_start_line + (view_line - last.view_start_line) == original_line
+            {
+                last.view_end_line = view_line;
+                return;
+            }
+        }
+        map.push(MappedRegion {
+            chunk_id: chunk_id.to_string(),
+            file: file.to_string(),
+            view_start_line: view_line,
+            view_end_line: view_line,
+            original_start_line: original_line,
+        });
+    }
+}
+
 /// Why a fragment was included in a view
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -124,6 +231,8 @@ pub enum InclusionReason {
     TypeDependency,
     /// Required for context (Ghost Import)
     GhostImport,
+    /// Test covering a requested atom
+    Test,
 }
 
 impl ViewFragment {
@@ -177,6 +286,23 @@ mod tests {
         assert!(view.contains_atom("chunk:a"));
     }
 
+    #[test]
+    fn test_map_line() {
+        let mut view = VirtualView::new("rust");
+        let mut map = Vec::new();
+        for (view_line, original_line) in [(1, 10), (2, 11), (3, 13), (5, 40)] {
+            let chunk = if view_line < 5 { "chunk:a" } else { "chunk:b" };
+            MappedRegion::extend_map(&mut map, view_line, chunk, "src/lib.rs", original_line);
+        }
+        view.source_map = map;
+
+        assert_eq!(view.source_map.len(), 3);
+        assert_eq!(view.map_line(2), Some(("chunk:a", "src/lib.rs", 11)));
+        assert_eq!(view.map_line(3), Some(("chunk:a", "src/lib.rs", 13)));
+        assert_eq!(view.map_line(4), None);
+        assert_eq!(view.map_line(5), Some(("chunk:b", "src/lib.rs", 40)));
+    }
+
     #[test]
     fn test_snippet_extraction() {
         let mut view = VirtualView::new("rust");
@@ -11,7 +11,7 @@
         "#;
         
         // This relies on the standard SmartChunker logic
-        let analysis = chunker.analyze_file(Path::new("test.c"), source);
+        let analysis = chunker.analyze_file(Path::new("test.c"), source).unwrap();
         
         // Detailed check
         let has_import = analysis.entities.iter().any(|e| e.kind == EntityKind::Import && e.name == "stdio.h");
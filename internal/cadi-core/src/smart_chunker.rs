@@ -12,10 +12,16 @@ use crate::atomic::{
     ChunkMetrics, SourceLocation,
 };
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
 
-use crate::atomizer::{AtomExtractor, AtomizerConfig, AtomKind};
+use crate::atomizer::{decorator_concepts, AtomExtractor, AtomizerConfig, AtomKind, ParseCache};
+use crate::categorizer::{score_categories, CategoryScore};
+use crate::code_metrics::compute_metrics;
+use crate::error::{CadiError, CadiResult};
+use crate::hash::{ChunkId, HashAlgorithm};
+use crate::license::{spdx_from_header, UNKNOWN_LICENSE};
 
 /// Configuration for smart chunking
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,6 +50,15 @@ pub struct SmartChunkerConfig {
     #[serde(default = "default_true")]
     pub group_related: bool,
 
+    /// Merge consecutive entities below `min_function_lines` into shared
+    /// chunks instead of dropping them
+    #[serde(default = "default_true")]
+    pub merge_small_entities: bool,
+
+    /// Maximum lines spanned by a chunk of merged small entities
+    #[serde(default = "default_max_merged_chunk_lines")]
+    pub max_merged_chunk_lines: usize,
+
     /// Prefer atomic (single) chunks when possible
     #[serde(default)]
     pub prefer_atomic: bool,
@@ -51,8 +66,21 @@ pub struct SmartChunkerConfig {
     /// Namespace for generated aliases
     #[serde(skip_serializing_if = "Option::is_none")]
     pub namespace: Option<String>,
+
+    /// Hash algorithm for the IDs of generated chunks
+    #[serde(default)]
+    pub hash_algorithm: HashAlgorithm,
 }
 
+/// Concept of React function and class components
+const REACT_COMPONENT: &str = "react-component";
+
+/// Concept of custom React hooks, the `useXxx` functions
+const REACT_HOOK: &str = "react-hook";
+
+/// Concept of `styled.*` and `styled(...)` definitions
+const STYLED_COMPONENT: &str = "styled-component";
+
 fn default_min_function_lines() -> usize {
     10
 }
@@ -65,6 +93,10 @@ fn default_max_chunk_lines() -> usize {
     500
 }
 
+fn default_max_merged_chunk_lines() -> usize {
+    100
+}
+
 fn default_true() -> bool {
     true
 }
@@ -78,8 +110,11 @@ impl Default for SmartChunkerConfig {
             extract_utilities: true,
             extract_types: true,
             group_related: true,
+            merge_small_entities: true,
+            max_merged_chunk_lines: default_max_merged_chunk_lines(),
             prefer_atomic: false,
             namespace: None,
+            hash_algorithm: HashAlgorithm::default(),
         }
     }
 }
@@ -97,6 +132,9 @@ pub struct CodeEntity {
     pub exports: Vec<String>,
     pub calls: Vec<String>,
     pub complexity: u32,
+    /// Concepts beyond its name, such as those implied by decorators
+    #[serde(default)]
+    pub concepts: Vec<String>,
 }
 
 /// Kind of code entity
@@ -129,6 +167,7 @@ pub enum Visibility {
 /// Analysis result for a file
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileAnalysis {
+    #[serde(with = "crate::atomic::raw_path")]
     pub path: PathBuf,
     pub language: String,
     pub total_lines: usize,
@@ -140,6 +179,25 @@ pub struct FileAnalysis {
     pub is_config: bool,
     pub framework_hints: Vec<String>,
     pub category: ChunkCategory,
+    /// Candidate categories, most likely first; `category` is the top one
+    #[serde(default)]
+    pub category_scores: Vec<CategoryScore>,
+}
+
+impl FileAnalysis {
+    /// Set the category explicitly, e.g. from a chunking rule or override
+    pub fn set_category(&mut self, category: ChunkCategory) {
+        self.category_scores = vec![CategoryScore::certain(category.clone())];
+        self.category = category;
+    }
+
+    /// Confidence in `category`, if it was scored
+    pub fn category_confidence(&self) -> Option<f32> {
+        self.category_scores
+            .iter()
+            .find(|score| score.category == self.category)
+            .map(|score| score.confidence)
+    }
 }
 
 /// Result of smart chunking analysis
@@ -155,14 +213,19 @@ pub struct ChunkingDecision {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ChunkingStrategy {
     /// Keep as single atomic chunk
+    #[serde(alias = "atomic")]
     Atomic,
     /// Split by entities (functions, types)
+    #[serde(alias = "by_entity", alias = "entity")]
     ByEntity,
     /// Split by logical sections
+    #[serde(alias = "by_sections", alias = "sections")]
     BySections,
     /// Hierarchical (file + entity chunks)
+    #[serde(alias = "hierarchical")]
     Hierarchical,
     /// Skip this file (not useful as a chunk)
+    #[serde(alias = "skip")]
     Skip,
 }
 
@@ -175,20 +238,33 @@ pub struct SuggestedChunk {
     pub end_line: usize,
     pub granularity: ChunkGranularity,
     pub category: ChunkCategory,
+    /// Confidence in `category` (0.0-1.0)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category_confidence: Option<f32>,
+    #[serde(default)]
     pub concepts: Vec<String>,
+    #[serde(default)]
     pub requires: Vec<String>,
+    #[serde(default)]
     pub provides: Vec<String>,
 }
 
 /// The SmartChunker - intelligent code analyzer and chunker
 pub struct SmartChunker {
     config: SmartChunkerConfig,
+    parse_cache: Option<Arc<ParseCache>>,
 }
 
 impl SmartChunker {
     /// Create a new SmartChunker with configuration
     pub fn new(config: SmartChunkerConfig) -> Self {
-        Self { config }
+        Self { config, parse_cache: None }
+    }
+
+    /// Reuse the atoms of previously parsed files from a parse cache
+    pub fn with_parse_cache(mut self, cache: Arc<ParseCache>) -> Self {
+        self.parse_cache = Some(cache);
+        self
     }
 
     /// Create with default configuration
@@ -197,21 +273,30 @@ impl SmartChunker {
     }
 
     /// Analyze a file and determine chunking strategy
-    pub fn analyze_file(&self, path: &Path, content: &str) -> FileAnalysis {
+    pub fn analyze_file(&self, path: &Path, content: &str) -> CadiResult<FileAnalysis> {
         let language = self.detect_language(path);
         let lines: Vec<&str> = content.lines().collect();
         let total_lines = lines.len();
 
-        let entities = self.extract_entities(content, &language);
+        let mut entities = self
+            .extract_entities(content, &language)
+            .map_err(|e| CadiError::FileAnalysis {
+                path: path.to_path_buf(),
+                reason: e.to_string(),
+            })?;
+        let framework_hints = self.detect_frameworks(content, &language);
+        if framework_hints.iter().any(|f| f == "react") {
+            self.extract_react_entities(content, &mut entities);
+        }
         let imports = self.extract_imports(content, &language);
         let exports = self.extract_exports(content, &language, &entities);
         let is_entrypoint = self.is_entrypoint(path, content, &language);
         let is_test = self.is_test_file(path, content, &language);
         let is_config = self.is_config_file(path);
-        let framework_hints = self.detect_frameworks(content, &language);
-        let category = self.categorize_file(path, &entities, is_test, is_config);
+        let category_scores = score_categories(path, &imports, &entities, is_test, is_config);
+        let category = category_scores[0].category.clone();
 
-        FileAnalysis {
+        Ok(FileAnalysis {
             path: path.to_path_buf(),
             language,
             total_lines,
@@ -223,7 +308,8 @@ impl SmartChunker {
             is_config,
             framework_hints,
             category,
-        }
+            category_scores,
+        })
     }
 
     /// Decide how to chunk a file based on analysis
@@ -293,15 +379,52 @@ impl SmartChunker {
         }
     }
 
-    /// Generate atomic chunks from content
+    /// Chunk a file with a fixed strategy instead of deciding one
+    pub fn decide_chunking_as(
+        &self,
+        analysis: &FileAnalysis,
+        strategy: ChunkingStrategy,
+    ) -> ChunkingDecision {
+        let suggested_chunks = match strategy {
+            ChunkingStrategy::Atomic => vec![self.create_file_chunk(analysis)],
+            ChunkingStrategy::ByEntity => self.create_entity_chunks(analysis),
+            ChunkingStrategy::BySections => self.create_section_chunks(analysis),
+            ChunkingStrategy::Hierarchical => {
+                let mut chunks = vec![self.create_file_chunk(analysis)];
+                chunks.extend(self.create_entity_chunks(analysis));
+                chunks
+            }
+            ChunkingStrategy::Skip => Vec::new(),
+        };
+
+        // A file without usable entities is still worth one chunk
+        let suggested_chunks = if suggested_chunks.is_empty() && strategy != ChunkingStrategy::Skip {
+            vec![self.create_file_chunk(analysis)]
+        } else {
+            suggested_chunks
+        };
+
+        ChunkingDecision {
+            file_path: analysis.path.clone(),
+            reasoning: format!("Chunking rule requires {:?}", strategy),
+            strategy,
+            suggested_chunks,
+        }
+    }
+
+    /// Generate atomic chunks from content. Fails if a suggested chunk's
+    /// lines are not in `content`, e.g. because the file changed since it
+    /// was analyzed.
     pub fn generate_chunks(
         &self,
         path: &Path,
         content: &str,
         decision: &ChunkingDecision,
-    ) -> Vec<AtomicChunk> {
+    ) -> CadiResult<Vec<AtomicChunk>> {
         let mut chunks = Vec::new();
         let lines: Vec<&str> = content.lines().collect();
+        let license = spdx_from_header(content).unwrap_or_else(|| UNKNOWN_LICENSE.to_string());
+        let language = self.detect_language(path);
 
         for suggested in &decision.suggested_chunks {
             let chunk_content = if suggested.start_line == 0 && suggested.end_line >= lines.len() {
@@ -309,44 +432,55 @@ impl SmartChunker {
             } else {
                 let start = suggested.start_line.saturating_sub(1);
                 let end = suggested.end_line.min(lines.len());
-                lines[start..end].join("\n")
+                lines
+                    .get(start..end)
+                    .ok_or_else(|| CadiError::FileAnalysis {
+                        path: path.to_path_buf(),
+                        reason: format!(
+                            "chunk {} spans lines {}-{} of a {}-line file",
+                            suggested.name,
+                            suggested.start_line,
+                            suggested.end_line,
+                            lines.len()
+                        ),
+                    })?
+                    .join("\n")
             };
 
-            let content_hash = compute_hash(&chunk_content);
-            let chunk_id = format!("chunk:sha256:{}", content_hash);
-
-            let analysis = self.analyze_file(path, content);
+            let id = ChunkId::from_content(self.config.hash_algorithm, chunk_content.as_bytes());
 
             let mut chunk = AtomicChunk::new(
-                chunk_id,
+                id.to_string(),
                 suggested.name.clone(),
-                analysis.language.clone(),
-                content_hash,
+                language.clone(),
+                id.digest,
                 chunk_content.len(),
             )
             .with_alias(&suggested.alias)
             .with_granularity(suggested.granularity)
             .with_categories(vec![suggested.category.clone()])
-            .with_concepts(suggested.concepts.clone());
+            .with_category_confidence(suggested.category_confidence)
+            .with_concepts(suggested.concepts.clone())
+            .with_license(license.clone());
 
             chunk.provides = suggested.provides.clone();
             chunk.requires = suggested.requires.clone();
             chunk.sources = vec![SourceLocation {
-                file: path.to_string_lossy().to_string(),
                 start_line: Some(suggested.start_line),
                 end_line: Some(suggested.end_line),
-                start_col: None,
-                end_col: None,
+                ..SourceLocation::for_path(path)
             }];
             chunk.metrics = ChunkMetrics {
-                loc: suggested.end_line - suggested.start_line + 1,
-                ..Default::default()
+                loc: (suggested.end_line + 1).saturating_sub(suggested.start_line),
+                export_count: suggested.provides.len(),
+                dependency_count: suggested.requires.len(),
+                ..compute_metrics(&chunk_content, &language)
             };
 
             chunks.push(chunk);
         }
 
-        chunks
+        Ok(chunks)
     }
 
     // ========================================================================
@@ -385,22 +519,314 @@ impl SmartChunker {
         }
     }
 
-    fn extract_entities(&self, content: &str, language: &str) -> Vec<CodeEntity> {
+    fn extract_entities(&self, content: &str, language: &str) -> CadiResult<Vec<CodeEntity>> {
         let mut entities = Vec::new();
-        let _lines: Vec<&str> = content.lines().collect();
 
         match language {
             "rust" | "python" | "go" | "c" | "cpp" | "glsl" | "csharp" | "typescript" | "javascript" | "html" | "css" => {
-                self.extract_via_atomizer(content, language, &mut entities)
+                self.extract_via_atomizer(content, language, &mut entities)?
             }
+            "java" => entities.extend(self.extract_java_entities(content)),
+            "kotlin" => entities.extend(self.extract_kotlin_entities(content)),
             _ => {}
         }
 
+        for constant in self.extract_constant_entities(content, language) {
+            if !entities.iter().any(|e| e.name == constant.name && e.start_line == constant.start_line) {
+                entities.push(constant);
+            }
+        }
+
+        self.extract_calls(content, language, &mut entities);
+        self.link_constant_references(content, language, &mut entities);
         entities.extend(self.extract_import_entities(content, language));
-        
+
+        Ok(entities)
+    }
+
+    /// Module-level constants and statics: Rust `const` and `static`
+    /// items, Python `UPPER_CASE` assignments and Go `const` and `var`
+    /// declarations, where a parenthesized block is one entity exporting
+    /// every name in it. Only declarations starting in the first column
+    /// count; TypeScript constants come from the atomizer.
+    fn extract_constant_entities(&self, content: &str, language: &str) -> Vec<CodeEntity> {
+        static RUST_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+            regex::Regex::new(
+                r"^(pub(?:\([^)]*\))?\s+)?(?:const|static)\s+(?:mut\s+)?([A-Za-z_][A-Za-z0-9_]*)\s*:",
+            )
+            .unwrap()
+        });
+        static PYTHON_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+            regex::Regex::new(r"^([A-Z_][A-Z0-9_]*)\s*(?::[^=]*)?=([^=]|$)").unwrap()
+        });
+        static GO_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+            regex::Regex::new(r"^(?:const|var)\s+([A-Za-z_][A-Za-z0-9_]*)\b").unwrap()
+        });
+        static GO_BLOCK_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+            regex::Regex::new(r"^(?:const|var)\s*\(").unwrap()
+        });
+        static IDENTIFIER_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+            regex::Regex::new(r"^([A-Za-z_][A-Za-z0-9_]*)").unwrap()
+        });
+
+        let lines: Vec<&str> = content.lines().collect();
+        let mut entities = Vec::new();
+        let mut i = 0;
+
+        while i < lines.len() {
+            let line = lines[i];
+            let (names, visibility, end) = match language {
+                "rust" => match RUST_REGEX.captures(line) {
+                    Some(cap) => {
+                        let visibility = if cap.get(1).is_some() { Visibility::Public } else { Visibility::Private };
+                        (vec![cap[2].to_string()], visibility, declaration_end(&lines, i, language))
+                    }
+                    None => (Vec::new(), Visibility::Private, i),
+                },
+                "python" => match PYTHON_REGEX.captures(line) {
+                    Some(cap) if cap[1].chars().any(|c| c.is_ascii_uppercase()) => {
+                        let visibility = if cap[1].starts_with('_') { Visibility::Private } else { Visibility::Public };
+                        (vec![cap[1].to_string()], visibility, declaration_end(&lines, i, language))
+                    }
+                    _ => (Vec::new(), Visibility::Private, i),
+                },
+                "go" if GO_BLOCK_REGEX.is_match(line) => {
+                    let mut names = Vec::new();
+                    let mut depth = 0;
+                    let mut end = i + 1;
+                    while end < lines.len() && !(depth == 0 && lines[end].trim_start().starts_with(')')) {
+                        let trimmed = lines[end].trim();
+                        if depth == 0 && !trimmed.starts_with("//") {
+                            if let Some(cap) = IDENTIFIER_REGEX.captures(trimmed) {
+                                names.push(cap[1].to_string());
+                            }
+                        }
+                        depth = scan_brackets(lines[end], depth, language).0;
+                        end += 1;
+                    }
+                    let visibility = if names.iter().any(|n| n.starts_with(|c: char| c.is_uppercase())) {
+                        Visibility::Public
+                    } else {
+                        Visibility::Private
+                    };
+                    (names, visibility, end.min(lines.len() - 1))
+                }
+                "go" => match GO_REGEX.captures(line) {
+                    Some(cap) => {
+                        let visibility =
+                            if cap[1].starts_with(|c: char| c.is_uppercase()) { Visibility::Public } else { Visibility::Private };
+                        (vec![cap[1].to_string()], visibility, declaration_end(&lines, i, language))
+                    }
+                    None => (Vec::new(), Visibility::Private, i),
+                },
+                _ => return entities,
+            };
+
+            if let Some(name) = names.first() {
+                entities.push(CodeEntity {
+                    name: name.clone(),
+                    kind: EntityKind::Constant,
+                    start_line: i + 1,
+                    end_line: end + 1,
+                    visibility,
+                    doc_comment: None,
+                    imports: Vec::new(),
+                    exports: names,
+                    calls: Vec::new(),
+                    complexity: 0,
+                    concepts: Vec::new(),
+                });
+            }
+            i = end + 1;
+        }
+
         entities
     }
 
+    /// Add every top-level constant an entity mentions to its imports, so
+    /// the entity's chunk requires the chunk defining the constant
+    fn link_constant_references(&self, content: &str, language: &str, entities: &mut [CodeEntity]) {
+        let constants: Vec<(usize, usize, Vec<String>)> = entities
+            .iter()
+            .filter(|e| is_top_level_constant(e, entities))
+            .map(|e| {
+                let names = std::iter::once(&e.name).chain(&e.exports).cloned().collect::<HashSet<_>>();
+                (e.start_line, e.end_line, names.into_iter().collect())
+            })
+            .collect();
+        if constants.is_empty() {
+            return;
+        }
+
+        let lines: Vec<&str> = content.lines().collect();
+        let comment_prefix = if language == "python" { "#" } else { "//" };
+
+        for entity in entities.iter_mut() {
+            if entity.kind == EntityKind::Import || entity.start_line == 0 {
+                continue;
+            }
+            // Constants an entity spans are its own definitions
+            let outside: HashSet<&str> = constants
+                .iter()
+                .filter(|(start, end, _)| *start < entity.start_line || *end > entity.end_line)
+                .flat_map(|(_, _, names)| names.iter().map(String::as_str))
+                .collect();
+
+            let start = entity.start_line - 1;
+            let end = entity.end_line.min(lines.len());
+            for line in lines.get(start..end).unwrap_or_default() {
+                if line.trim_start().starts_with(comment_prefix) {
+                    continue;
+                }
+                for word in line.split(|c: char| !(c.is_alphanumeric() || c == '_')) {
+                    if outside.contains(word) && !entity.imports.iter().any(|i| i == word) {
+                        entity.imports.push(word.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Record, for each entity, which other entities in the same file it
+    /// calls: an identifier followed by `(` that names another entity
+    fn extract_calls(&self, content: &str, language: &str, entities: &mut [CodeEntity]) {
+        static CALL_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+            regex::Regex::new(r"\b([A-Za-z_][A-Za-z0-9_]*)\s*\(").unwrap()
+        });
+        let lines: Vec<&str> = content.lines().collect();
+
+        let keywords: &[&str] = match language {
+            "rust" => &["if", "while", "for", "match", "return", "fn", "loop", "in", "as", "impl", "where"],
+            "python" => &["if", "elif", "while", "for", "return", "def", "class", "lambda", "with", "print", "not", "and", "or", "in"],
+            "typescript" | "javascript" => &["if", "while", "for", "switch", "return", "function", "catch", "typeof", "new", "await"],
+            "go" => &["if", "for", "switch", "return", "func", "go", "defer"],
+            "java" | "kotlin" => &["if", "while", "for", "switch", "when", "return", "catch", "synchronized", "super", "this"],
+            _ => &["if", "while", "for", "switch", "return", "sizeof"],
+        };
+        let comment_prefix = if language == "python" { "#" } else { "//" };
+
+        let names: std::collections::HashSet<String> = entities
+            .iter()
+            .filter(|e| e.kind != EntityKind::Import)
+            .map(|e| e.name.clone())
+            .collect();
+
+        for entity in entities.iter_mut() {
+            if entity.kind == EntityKind::Import || entity.start_line == 0 {
+                continue;
+            }
+
+            let start = entity.start_line - 1;
+            let end = entity.end_line.min(lines.len());
+            let mut calls = Vec::new();
+
+            for line in lines.get(start..end).unwrap_or_default() {
+                if line.trim_start().starts_with(comment_prefix) {
+                    continue;
+                }
+                for cap in CALL_REGEX.captures_iter(line) {
+                    let callee = &cap[1];
+                    if callee != entity.name
+                        && names.contains(callee)
+                        && !keywords.contains(&callee)
+                        && !calls.iter().any(|c| c == callee)
+                    {
+                        calls.push(callee.to_string());
+                    }
+                }
+            }
+
+            entity.calls = calls;
+        }
+    }
+
+    /// React files: add the top-level interfaces and type aliases the
+    /// atomizer leaves out, such as props types, and tag components, custom
+    /// hooks and `styled` definitions with concepts. Components and hooks
+    /// also require the other top-level declarations they mention, JSX
+    /// tags included.
+    fn extract_react_entities(&self, content: &str, entities: &mut Vec<CodeEntity>) {
+        static TYPE_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+            regex::Regex::new(
+                r"^(export\s+)?(?:declare\s+)?(interface|type)\s+([A-Za-z_$][\w$]*)",
+            )
+            .unwrap()
+        });
+        static STYLED_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+            regex::Regex::new(r"=\s*styled\s*(?:\.\s*\w+|\()").unwrap()
+        });
+        static HOOK_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+            regex::Regex::new(r"^use[A-Z0-9]").unwrap()
+        });
+        let lines: Vec<&str> = content.lines().collect();
+
+        for (i, line) in lines.iter().enumerate() {
+            let Some(cap) = TYPE_REGEX.captures(line) else {
+                continue;
+            };
+            if entities.iter().any(|e| e.name == cap[3] && e.start_line == i + 1) {
+                continue;
+            }
+            entities.push(CodeEntity {
+                name: cap[3].to_string(),
+                kind: if &cap[2] == "interface" { EntityKind::Interface } else { EntityKind::Type },
+                start_line: i + 1,
+                end_line: declaration_end(&lines, i, "typescript") + 1,
+                visibility: if cap.get(1).is_some() { Visibility::Public } else { Visibility::Private },
+                doc_comment: None,
+                imports: Vec::new(),
+                exports: vec![cap[3].to_string()],
+                calls: Vec::new(),
+                complexity: 0,
+                concepts: Vec::new(),
+            });
+        }
+
+        let top: Vec<usize> = (0..entities.len())
+            .filter(|&i| is_top_level(&entities[i], entities))
+            .collect();
+        let names: HashSet<String> = top.iter().map(|&i| entities[i].name.clone()).collect();
+
+        for &i in &top {
+            let entity = &entities[i];
+            let body = lines
+                .get(entity.start_line.saturating_sub(1)..entity.end_line.min(lines.len()))
+                .unwrap_or_default();
+            let function = matches!(entity.kind, EntityKind::Function | EntityKind::AsyncFunction);
+            let concept = if body.first().is_some_and(|line| STYLED_REGEX.is_match(line)) {
+                STYLED_COMPONENT
+            } else if function && HOOK_REGEX.is_match(&entity.name) {
+                REACT_HOOK
+            } else if (function || entity.kind == EntityKind::Class)
+                && entity.name.starts_with(|c: char| c.is_ascii_uppercase())
+                && body.iter().any(|line| line.contains("</") || line.contains("/>"))
+            {
+                REACT_COMPONENT
+            } else {
+                continue;
+            };
+
+            let mut mentioned = Vec::new();
+            if concept != STYLED_COMPONENT {
+                for line in body.iter().filter(|line| !line.trim_start().starts_with("//")) {
+                    for word in line.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$')) {
+                        if word != entity.name && names.contains(word) && !mentioned.iter().any(|m| m == word) {
+                            mentioned.push(word.to_string());
+                        }
+                    }
+                }
+            }
+
+            let entity = &mut entities[i];
+            entity.concepts.push(concept.to_string());
+            for name in mentioned {
+                if !entity.imports.contains(&name) {
+                    entity.imports.push(name);
+                }
+            }
+        }
+    }
+
     fn extract_import_entities(&self, content: &str, language: &str) -> Vec<CodeEntity> {
         let mut entities = Vec::new();
         for (i, line) in content.lines().enumerate() {
@@ -439,6 +865,12 @@ impl SmartChunker {
                          // Go import blocks are hard line-by-line. 
                     }
                 }
+                "java" | "kotlin" => {
+                    if let Some(import) = jvm_import(trimmed) {
+                        is_import = true;
+                        name = import;
+                    }
+                }
                 "c" | "cpp" | "h" | "hpp" => {
                     if trimmed.starts_with("#include") {
                         is_import = true;
@@ -460,6 +892,7 @@ impl SmartChunker {
                     exports: vec![name], // The import chunk provides the imported module/symbol
                     calls: Vec::new(),
                     complexity: 0,
+                    concepts: Vec::new(),
                 });
             }
         }
@@ -470,56 +903,195 @@ impl SmartChunker {
 
 
 
-    fn extract_via_atomizer(&self, content: &str, language: &str, entities: &mut Vec<CodeEntity>) {
+    fn extract_via_atomizer(&self, content: &str, language: &str, entities: &mut Vec<CodeEntity>) -> CadiResult<()> {
         let config = AtomizerConfig::default();
-        let extractor = AtomExtractor::new(language, config);
+        let mut extractor = AtomExtractor::new(language, config);
+        if let Some(cache) = &self.parse_cache {
+            extractor = extractor.with_cache(cache.clone());
+        }
 
-        if let Ok(atoms) = extractor.extract(content) {
-            for atom in atoms {
-                // Skip Header atoms
-                if atom.kind == AtomKind::Header {
-                    continue;
-                }
+        for atom in extractor.extract(content)? {
+            // Skip Header atoms
+            if atom.kind == AtomKind::Header {
+                continue;
+            }
 
-                let kind = match atom.kind {
-                    AtomKind::Function => EntityKind::Function,
-                    AtomKind::Method => EntityKind::Method,
-                    AtomKind::Class => EntityKind::Class,
-                    AtomKind::Struct => EntityKind::Struct,
-                    AtomKind::Interface => EntityKind::Interface,
-                    AtomKind::Enum => EntityKind::Enum,
-                    AtomKind::Module => EntityKind::Module,
-                    AtomKind::Constant => EntityKind::Constant,
-                    AtomKind::Import => EntityKind::Import,
-                    // Map others as needed
-                    _ => EntityKind::Function, 
-                };
+            let kind = match atom.kind {
+                AtomKind::Function => EntityKind::Function,
+                AtomKind::Method => EntityKind::Method,
+                AtomKind::Class => EntityKind::Class,
+                AtomKind::Struct => EntityKind::Struct,
+                AtomKind::Interface => EntityKind::Interface,
+                AtomKind::Enum => EntityKind::Enum,
+                AtomKind::Module => EntityKind::Module,
+                AtomKind::Constant => EntityKind::Constant,
+                AtomKind::Import => EntityKind::Import,
+                // Map others as needed
+                _ => EntityKind::Function, 
+            };
 
-                let visibility = match atom.visibility {
-                    crate::atomizer::extractor::Visibility::Public => Visibility::Public,
-                    crate::atomizer::extractor::Visibility::Private => Visibility::Private,
-                    crate::atomizer::extractor::Visibility::Protected => Visibility::Internal,
-                    crate::atomizer::extractor::Visibility::Internal => Visibility::Internal,
-                };
+            let visibility = match atom.visibility {
+                crate::atomizer::extractor::Visibility::Public => Visibility::Public,
+                crate::atomizer::extractor::Visibility::Private => Visibility::Private,
+                crate::atomizer::extractor::Visibility::Protected => Visibility::Internal,
+                crate::atomizer::extractor::Visibility::Internal => Visibility::Internal,
+            };
 
-                entities.push(CodeEntity {
-                    name: atom.name,
-                    kind,
-                    start_line: atom.start_line,
-                    end_line: atom.end_line,
-                    visibility,
-                    doc_comment: atom.doc_comment,
-                    // Map references to "imports" so they become chunk dependencies (requires)
-                    imports: atom.references, 
-                    exports: atom.defines,
-                    calls: Vec::new(), // Could also map references here, but 'imports' drives 'requires'
-                    complexity: 1,
+            entities.push(CodeEntity {
+                name: atom.name,
+                kind,
+                start_line: atom.start_line,
+                end_line: atom.end_line,
+                visibility,
+                doc_comment: atom.doc_comment,
+                // Map references to "imports" so they become chunk dependencies (requires)
+                imports: atom.references, 
+                exports: atom.defines,
+                calls: Vec::new(), // Could also map references here, but 'imports' drives 'requires'
+                complexity: 1,
+                concepts: decorator_concepts(&atom.decorators),
+            });
+        }
+
+        Ok(())
+    }
+
+
+
+    /// Java types, including nested ones, and the methods and constructors
+    /// declared directly in their bodies. Members without a modifier are
+    /// package-private, except in interfaces.
+    fn extract_java_entities(&self, content: &str) -> Vec<CodeEntity> {
+        static TYPE_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+            regex::Regex::new(
+                r"^((?:(?:public|protected|private|static|abstract|final|sealed|non-sealed|strictfp)\s+)*)(class|interface|enum|record|@interface)\s+(\w+)",
+            )
+            .unwrap()
+        });
+        static METHOD_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+            regex::Regex::new(
+                r"^((?:(?:public|protected|private|static|abstract|final|synchronized|native|default|strictfp)\s+)*)(?:<[^()]*?>\s+)?(?:([\w.]+(?:<[^()]*?>)?(?:\[\])*)\s+)?(\w+)\s*\(",
+            )
+            .unwrap()
+        });
+        let keywords = ["return", "new", "throw", "else", "if", "for", "while", "switch", "catch", "synchronized"];
+
+        let visibility = |modifiers: &str, enclosing: Option<&JvmScope>| {
+            if modifiers.contains("public") {
+                Visibility::Public
+            } else if modifiers.contains("private") {
+                Visibility::Private
+            } else if enclosing.is_some_and(|scope| scope.interface) && !modifiers.contains("protected") {
+                Visibility::Public
+            } else {
+                Visibility::Internal
+            }
+        };
+
+        extract_jvm_entities(content, false, |decl, enclosing| {
+            if let Some(caps) = TYPE_REGEX.captures(decl) {
+                let kind = match &caps[2] {
+                    "interface" | "@interface" => EntityKind::Interface,
+                    "enum" => EntityKind::Enum,
+                    "record" => EntityKind::Struct,
+                    _ => EntityKind::Class,
+                };
+                return Some(JvmDeclaration {
+                    name: caps[3].to_string(),
+                    kind: Some(kind),
+                    visibility: visibility(&caps[1], enclosing),
+                    interface: &caps[2] == "interface",
                 });
             }
-        }
+
+            // Methods only exist inside types; a member without a return
+            // type is a constructor of the enclosing type
+            let scope = enclosing?;
+            let caps = METHOD_REGEX.captures(decl)?;
+            let name = &caps[3];
+            let is_method = match caps.get(2) {
+                Some(ret) => !keywords.contains(&ret.as_str()),
+                None => name == scope.name,
+            };
+            if !is_method || keywords.contains(&name) {
+                return None;
+            }
+            Some(JvmDeclaration {
+                name: name.to_string(),
+                kind: Some(EntityKind::Method),
+                visibility: visibility(&caps[1], enclosing),
+                interface: false,
+            })
+        })
     }
 
+    /// Kotlin classes, interfaces, objects and data/enum classes, and
+    /// functions at the top level or directly in a type body. Extension
+    /// functions are named without their receiver. Companion object
+    /// members count as members of the enclosing class.
+    fn extract_kotlin_entities(&self, content: &str) -> Vec<CodeEntity> {
+        static TYPE_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+            regex::Regex::new(
+                r"^((?:(?:public|private|protected|internal|abstract|open|final|sealed|data|enum|annotation|inner|value|inline|companion|fun|expect|actual)\s+)*)(class|interface|object)\b\s*(\w*)",
+            )
+            .unwrap()
+        });
+        static FUN_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+            regex::Regex::new(
+                r"^((?:(?:public|private|protected|internal|override|open|abstract|final|suspend|inline|infix|operator|tailrec|external|expect|actual)\s+)*)fun\s+(?:<[^()]*?>\s+)?(?:[\w.<>?*, ]+?\.)?(\w+)\s*\(",
+            )
+            .unwrap()
+        });
+
+        let visibility = |modifiers: &str| {
+            if modifiers.contains("private") {
+                Visibility::Private
+            } else if modifiers.contains("internal") || modifiers.contains("protected") {
+                Visibility::Internal
+            } else {
+                Visibility::Public
+            }
+        };
+
+        extract_jvm_entities(content, true, |decl, enclosing| {
+            if let Some(caps) = TYPE_REGEX.captures(decl) {
+                let modifiers = &caps[1];
+                let kind = if caps[3].is_empty() {
+                    // A companion object only scopes its members
+                    None
+                } else if modifiers.contains("data") {
+                    Some(EntityKind::Struct)
+                } else if modifiers.contains("enum") {
+                    Some(EntityKind::Enum)
+                } else if &caps[2] == "interface" || modifiers.contains("annotation") {
+                    Some(EntityKind::Interface)
+                } else {
+                    Some(EntityKind::Class)
+                };
+                return Some(JvmDeclaration {
+                    name: caps[3].to_string(),
+                    kind,
+                    visibility: visibility(modifiers),
+                    interface: &caps[2] == "interface",
+                });
+            }
 
+            let caps = FUN_REGEX.captures(decl)?;
+            let kind = if enclosing.is_some() {
+                EntityKind::Method
+            } else if caps[1].contains("suspend") {
+                EntityKind::AsyncFunction
+            } else {
+                EntityKind::Function
+            };
+            Some(JvmDeclaration {
+                name: caps[2].to_string(),
+                kind: Some(kind),
+                visibility: visibility(&caps[1]),
+                interface: false,
+            })
+        })
+    }
 
     fn extract_imports(&self, content: &str, language: &str) -> Vec<String> {
         let mut imports = Vec::new();
@@ -558,6 +1130,9 @@ impl SmartChunker {
                     }
                 }
             }
+            "java" | "kotlin" => {
+                imports.extend(content.lines().filter_map(|line| jvm_import(line.trim())));
+            }
             _ => {}
         }
 
@@ -573,6 +1148,11 @@ impl SmartChunker {
     }
 
     fn is_entrypoint(&self, path: &Path, content: &str, language: &str) -> bool {
+        static JAVA_MAIN: LazyLock<regex::Regex> = LazyLock::new(|| {
+            regex::Regex::new(r"\b(?:public\s+static|static\s+public)\s+void\s+main\s*\(").unwrap()
+        });
+        static KOTLIN_MAIN: LazyLock<regex::Regex> =
+            LazyLock::new(|| regex::Regex::new(r"(?m)^\s*(?:suspend\s+)?fun\s+main\s*\(").unwrap());
         let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
 
         match language {
@@ -589,6 +1169,8 @@ impl SmartChunker {
                     || content.contains("if __name__")
             }
             "go" => file_name == "main.go" || content.contains("func main()"),
+            "java" => JAVA_MAIN.is_match(content),
+            "kotlin" => KOTLIN_MAIN.is_match(content),
             _ => false,
         }
     }
@@ -693,82 +1275,11 @@ impl SmartChunker {
         frameworks
     }
 
-    fn categorize_file(
-        &self,
-        path: &Path,
-        entities: &[CodeEntity],
-        is_test: bool,
-        is_config: bool,
-    ) -> ChunkCategory {
-        if is_test {
-            return ChunkCategory::Test;
-        }
-        if is_config {
-            return ChunkCategory::Config;
-        }
-
-        let path_str = path.to_string_lossy().to_lowercase();
-        let file_name = path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("")
-            .to_lowercase();
-
-        // Path-based categorization
-        if path_str.contains("/api/") || path_str.contains("/routes/") {
-            return ChunkCategory::Api;
-        }
-        if path_str.contains("/ui/")
-            || path_str.contains("/components/")
-            || path_str.contains("/views/")
-        {
-            return ChunkCategory::Ui;
-        }
-        if path_str.contains("/utils/") || path_str.contains("/helpers/") {
-            return ChunkCategory::Utility;
-        }
-        if path_str.contains("/models/") || path_str.contains("/types/") {
-            return ChunkCategory::Data;
-        }
-        if path_str.contains("/db/") || path_str.contains("/database/") {
-            return ChunkCategory::Database;
-        }
-
-        // Name-based categorization
-        if file_name.contains("util") || file_name.contains("helper") {
-            return ChunkCategory::Utility;
-        }
-        if file_name.contains("type") || file_name.contains("model") {
-            return ChunkCategory::Data;
-        }
-
-        // Entity-based categorization
-        let type_count = entities
-            .iter()
-            .filter(|e| {
-                matches!(
-                    e.kind,
-                    EntityKind::Struct
-                        | EntityKind::Class
-                        | EntityKind::Interface
-                        | EntityKind::Enum
-                        | EntityKind::Type
-                )
-            })
-            .count();
-
-        if type_count > entities.len() / 2 {
-            return ChunkCategory::Data;
-        }
-
-        ChunkCategory::Logic
-    }
-
     fn has_clear_structure(&self, entities: &[CodeEntity]) -> bool {
         // Has multiple significant entities
         let significant = entities
             .iter()
-            .filter(|e| e.end_line - e.start_line >= self.config.min_function_lines)
+            .filter(|e| e.end_line.saturating_sub(e.start_line) >= self.config.min_function_lines)
             .count();
 
         significant >= 2
@@ -790,59 +1301,436 @@ impl SmartChunker {
             end_line: analysis.total_lines,
             granularity: ChunkGranularity::Module,
             category: analysis.category.clone(),
+            category_confidence: analysis.category_confidence(),
             concepts: analysis.exports.clone(),
             requires: analysis.imports.clone(),
             provides: analysis.exports.clone(),
         }
     }
 
+    /// Split a file into sections of at most `max_chunk_lines` lines
+    fn create_section_chunks(&self, analysis: &FileAnalysis) -> Vec<SuggestedChunk> {
+        let file_chunk = self.create_file_chunk(analysis);
+        let size = self.config.max_chunk_lines.max(1);
+        if analysis.total_lines <= size {
+            return vec![file_chunk];
+        }
+
+        (1..=analysis.total_lines)
+            .step_by(size)
+            .enumerate()
+            .map(|(i, start_line)| SuggestedChunk {
+                name: format!("{}-part{}", file_chunk.name, i + 1),
+                alias: format!("{}-part-{}", file_chunk.alias, i + 1),
+                start_line,
+                end_line: (start_line + size - 1).min(analysis.total_lines),
+                ..file_chunk.clone()
+            })
+            .collect()
+    }
+
     fn create_entity_chunks(&self, analysis: &FileAnalysis) -> Vec<SuggestedChunk> {
+        if analysis.framework_hints.iter().any(|f| f == "react") {
+            if let Some(chunks) = self.create_react_chunks(analysis) {
+                return chunks;
+            }
+        }
+        self.create_generic_entity_chunks(analysis)
+    }
+
+    fn create_generic_entity_chunks(&self, analysis: &FileAnalysis) -> Vec<SuggestedChunk> {
         let file_stem = analysis
             .path
             .file_stem()
             .and_then(|n| n.to_str())
             .unwrap_or("unknown");
 
-        analysis
+        let mut chunks: Vec<SuggestedChunk> = analysis
             .entities
             .iter()
-            .filter(|e| e.end_line - e.start_line >= self.config.min_function_lines)
-            .map(|entity| {
-                let granularity = match entity.kind {
-                    EntityKind::Function | EntityKind::AsyncFunction | EntityKind::Method => {
-                        ChunkGranularity::Function
+            .filter(|e| e.end_line.saturating_sub(e.start_line) >= self.config.min_function_lines)
+            .filter(|e| !is_top_level_constant(e, &analysis.entities))
+            .map(|entity| self.create_single_entity_chunk(analysis, file_stem, entity))
+            .collect();
+
+        if self.config.merge_small_entities {
+            let mut merged = 0;
+            for group in self.group_small_entities(&analysis.entities) {
+                if let [entity] = group.as_slice() {
+                    chunks.push(self.create_single_entity_chunk(analysis, file_stem, entity));
+                } else {
+                    merged += 1;
+                    chunks.push(self.create_merged_chunk(analysis, file_stem, &group, merged));
+                }
+            }
+        }
+
+        chunks.extend(self.create_constant_chunks(file_stem, &analysis.entities));
+        chunks
+    }
+
+    /// React files with components or custom hooks: each component is one
+    /// chunk together with its props type and the `styled` definitions it
+    /// uses, when they are declared right around it, and each custom hook
+    /// is a function chunk of its own. Everything else is chunked as usual.
+    /// `None` if the file has neither components nor hooks.
+    fn create_react_chunks(&self, analysis: &FileAnalysis) -> Option<Vec<SuggestedChunk>> {
+        let has = |e: &CodeEntity, concept: &str| e.concepts.iter().any(|c| c == concept);
+        let mut top: Vec<&CodeEntity> = analysis
+            .entities
+            .iter()
+            .filter(|e| is_top_level(e, &analysis.entities))
+            .collect();
+        top.sort_by_key(|e| e.start_line);
+        if !top.iter().any(|e| has(e, REACT_COMPONENT) || has(e, REACT_HOOK)) {
+            return None;
+        }
+
+        let file_stem = analysis
+            .path
+            .file_stem()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown");
+        let ui = CategoryScore::certain(ChunkCategory::Ui);
+        let mut chunks = Vec::new();
+        let mut grouped = vec![false; top.len()];
+
+        for (i, component) in top.iter().enumerate() {
+            if !has(component, REACT_COMPONENT) {
+                continue;
+            }
+            let attached = |e: &CodeEntity| {
+                let mentioned = component.imports.contains(&e.name);
+                (has(e, STYLED_COMPONENT) && mentioned)
+                    || (matches!(e.kind, EntityKind::Interface | EntityKind::Type)
+                        && (mentioned || e.name == format!("{}Props", component.name)))
+            };
+
+            let mut members = vec![i];
+            let before = (0..i).rev().take_while(|&j| !grouped[j] && attached(top[j]));
+            members.extend(before);
+            let after = (i + 1..top.len()).take_while(|&j| attached(top[j]));
+            members.extend(after);
+            members.sort();
+            for &j in &members {
+                grouped[j] = true;
+            }
+
+            let members: Vec<&CodeEntity> = members.iter().map(|&j| top[j]).collect();
+            chunks.push(self.create_component_chunk(file_stem, component, &members));
+        }
+
+        for (i, hook) in top.iter().enumerate() {
+            if grouped[i] || !has(hook, REACT_HOOK) {
+                continue;
+            }
+            grouped[i] = true;
+            chunks.push(SuggestedChunk {
+                category: ui.category.clone(),
+                category_confidence: Some(ui.confidence),
+                ..self.create_single_entity_chunk(analysis, file_stem, hook)
+            });
+        }
+
+        // The rest of the file, minus what the chunks above span
+        let spans: Vec<(usize, usize)> = chunks.iter().map(|c| (c.start_line, c.end_line)).collect();
+        let rest = FileAnalysis {
+            entities: analysis
+                .entities
+                .iter()
+                .filter(|e| !spans.iter().any(|&(start, end)| start <= e.start_line && e.end_line <= end))
+                .cloned()
+                .collect(),
+            ..analysis.clone()
+        };
+        chunks.extend(self.create_generic_entity_chunks(&rest));
+
+        chunks.sort_by_key(|c| c.start_line);
+        Some(chunks)
+    }
+
+    /// One UI chunk spanning a component and the declarations attached to
+    /// it. It provides the component and its props types, and requires what
+    /// the group does not define itself.
+    fn create_component_chunk(
+        &self,
+        file_stem: &str,
+        component: &CodeEntity,
+        members: &[&CodeEntity],
+    ) -> SuggestedChunk {
+        let defined: HashSet<&str> = members
+            .iter()
+            .flat_map(|e| std::iter::once(e.name.as_str()).chain(e.exports.iter().map(String::as_str)))
+            .collect();
+
+        let mut provides = vec![component.name.clone()];
+        let mut concepts = std::iter::once(component.name.clone())
+            .chain(component.concepts.iter().cloned())
+            .collect::<Vec<_>>();
+        for member in members {
+            let props = matches!(member.kind, EntityKind::Interface | EntityKind::Type);
+            if (props || member.visibility == Visibility::Public) && !provides.contains(&member.name) {
+                provides.push(member.name.clone());
+            }
+            if props && !concepts.contains(&member.name) {
+                concepts.push(member.name.clone());
+            }
+        }
+        let mut requires = Vec::new();
+        for import in members.iter().flat_map(|e| &e.imports) {
+            if !defined.contains(import.as_str()) && !requires.contains(import) {
+                requires.push(import.clone());
+            }
+        }
+
+        let ui = CategoryScore::certain(ChunkCategory::Ui);
+        SuggestedChunk {
+            name: component.name.clone(),
+            alias: format!("{}/{}", file_stem, to_kebab_case(&component.name)),
+            start_line: members.iter().map(|e| e.start_line).min().unwrap_or(component.start_line),
+            end_line: members.iter().map(|e| e.end_line).max().unwrap_or(component.end_line),
+            granularity: ChunkGranularity::Module,
+            category: ui.category,
+            category_confidence: Some(ui.confidence),
+            concepts,
+            requires,
+            provides,
+        }
+    }
+
+    fn create_single_entity_chunk(
+        &self,
+        analysis: &FileAnalysis,
+        file_stem: &str,
+        entity: &CodeEntity,
+    ) -> SuggestedChunk {
+        let granularity = match entity.kind {
+            EntityKind::Function | EntityKind::AsyncFunction | EntityKind::Method => {
+                ChunkGranularity::Function
+            }
+            EntityKind::Struct
+            | EntityKind::Class
+            | EntityKind::Trait
+            | EntityKind::Interface
+            | EntityKind::Enum => ChunkGranularity::Type,
+            EntityKind::Module => ChunkGranularity::Module,
+            EntityKind::Import => ChunkGranularity::Import,
+            _ => ChunkGranularity::Function,
+        };
+
+        let alias = format!("{}/{}", file_stem, to_kebab_case(&entity.name));
+
+        SuggestedChunk {
+            name: entity.name.clone(),
+            alias,
+            start_line: entity.start_line,
+            end_line: entity.end_line,
+            granularity,
+            category: analysis.category.clone(),
+            category_confidence: analysis.category_confidence(),
+            concepts: std::iter::once(entity.name.clone()).chain(entity.concepts.iter().cloned()).collect(),
+            requires: entity.imports.clone(),
+            provides: if entity.visibility == Visibility::Public || entity.kind == EntityKind::Import {
+                entity.exports.clone() // Use pre-calculated exports (which includes the name for Imports)
+            } else {
+                Vec::new()
+            },
+        }
+    }
+
+    /// One chunk spanning a group of small entities; it provides every
+    /// public name inside and requires what the group does not define itself
+    fn create_merged_chunk(
+        &self,
+        analysis: &FileAnalysis,
+        file_stem: &str,
+        group: &[&CodeEntity],
+        index: usize,
+    ) -> SuggestedChunk {
+        let name = format!("small-helpers-{}", index);
+        let defined: HashSet<&str> = group
+            .iter()
+            .flat_map(|e| std::iter::once(e.name.as_str()).chain(e.exports.iter().map(String::as_str)))
+            .collect();
+
+        let mut provides = Vec::new();
+        let mut requires = Vec::new();
+        for entity in group {
+            if entity.visibility == Visibility::Public {
+                for name in std::iter::once(&entity.name).chain(&entity.exports) {
+                    if !provides.contains(name) {
+                        provides.push(name.clone());
                     }
-                    EntityKind::Struct
-                    | EntityKind::Class
-                    | EntityKind::Trait
-                    | EntityKind::Interface
-                    | EntityKind::Enum => ChunkGranularity::Type,
-                    EntityKind::Module => ChunkGranularity::Module,
-                    EntityKind::Import => ChunkGranularity::Import,
-                    _ => ChunkGranularity::Function,
-                };
+                }
+            }
+            for import in &entity.imports {
+                if !defined.contains(import.as_str()) && !requires.contains(import) {
+                    requires.push(import.clone());
+                }
+            }
+        }
 
-                let alias = format!("{}/{}", file_stem, to_kebab_case(&entity.name));
-
-                SuggestedChunk {
-                    name: entity.name.clone(),
-                    alias,
-                    start_line: entity.start_line,
-                    end_line: entity.end_line,
-                    granularity,
-                    category: analysis.category.clone(),
-                    concepts: vec![entity.name.clone()],
-                    requires: entity.imports.clone(),
-                    provides: if entity.visibility == Visibility::Public || entity.kind == EntityKind::Import {
-                        entity.exports.clone() // Use pre-calculated exports (which includes the name for Imports)
-                    } else {
-                        Vec::new()
-                    },
+        SuggestedChunk {
+            alias: format!("{}/{}", file_stem, name),
+            name,
+            start_line: group[0].start_line,
+            end_line: group.iter().map(|e| e.end_line).max().unwrap_or(group[0].end_line),
+            granularity: ChunkGranularity::Module,
+            category: analysis.category.clone(),
+            category_confidence: analysis.category_confidence(),
+            concepts: group
+                .iter()
+                .flat_map(|e| std::iter::once(&e.name).chain(&e.concepts))
+                .fold(Vec::new(), |mut concepts, concept| {
+                    if !concepts.contains(concept) {
+                        concepts.push(concept.clone());
+                    }
+                    concepts
+                }),
+            requires,
+            provides,
+        }
+    }
+
+    /// Top-level constants as data chunks: each run of small constants with
+    /// no other entity between them becomes one `consts` chunk providing
+    /// all their names, and a constant spanning `min_function_lines` or
+    /// more gets a chunk of its own
+    fn create_constant_chunks(&self, file_stem: &str, entities: &[CodeEntity]) -> Vec<SuggestedChunk> {
+        let is_big = |e: &CodeEntity| e.end_line - e.start_line >= self.config.min_function_lines;
+
+        let mut sorted: Vec<&CodeEntity> = entities
+            .iter()
+            .filter(|e| e.kind != EntityKind::Import && e.start_line > 0)
+            .collect();
+        sorted.sort_by_key(|e| (e.start_line, std::cmp::Reverse(e.end_line)));
+
+        let mut runs = Vec::new();
+        let mut run: Vec<&CodeEntity> = Vec::new();
+        for entity in sorted {
+            if !is_top_level_constant(entity, entities) || is_big(entity) {
+                if !run.is_empty() {
+                    runs.push(std::mem::take(&mut run));
                 }
+                if is_top_level_constant(entity, entities) {
+                    runs.push(vec![entity]);
+                }
+            } else {
+                run.push(entity);
+            }
+        }
+        if !run.is_empty() {
+            runs.push(run);
+        }
+
+        let mut merged = 0;
+        runs.into_iter()
+            .map(|run| {
+                let name = match run.as_slice() {
+                    [entity] if is_big(entity) => entity.name.clone(),
+                    _ => {
+                        merged += 1;
+                        if merged == 1 { "consts".to_string() } else { format!("consts-{}", merged) }
+                    }
+                };
+                self.create_constants_chunk(file_stem, &run, name)
             })
             .collect()
     }
 
+    /// One data chunk for a run of constants
+    fn create_constants_chunk(&self, file_stem: &str, run: &[&CodeEntity], name: String) -> SuggestedChunk {
+        let mut provides: Vec<String> = Vec::new();
+        for name in run.iter().flat_map(|e| std::iter::once(&e.name).chain(&e.exports)) {
+            if !provides.contains(name) {
+                provides.push(name.clone());
+            }
+        }
+        let mut requires: Vec<String> = Vec::new();
+        for import in run.iter().flat_map(|e| &e.imports) {
+            if !provides.contains(import) && !requires.contains(import) {
+                requires.push(import.clone());
+            }
+        }
+
+        // `MAX_RETRIES` reads as `max-retries`, not `m-a-x_-r-e-t-r-i-e-s`
+        let alias_name = if name.chars().any(|c| c.is_lowercase()) { name.clone() } else { name.to_lowercase() };
+
+        SuggestedChunk {
+            alias: format!("{}/{}", file_stem, to_kebab_case(&alias_name)),
+            name,
+            start_line: run[0].start_line,
+            end_line: run.iter().map(|e| e.end_line).max().unwrap_or(run[0].end_line),
+            granularity: ChunkGranularity::Module,
+            category: ChunkCategory::Data,
+            category_confidence: Some(CategoryScore::certain(ChunkCategory::Data).confidence),
+            concepts: provides.clone(),
+            requires,
+            provides,
+        }
+    }
+
+    /// Group consecutive entities below `min_function_lines` so that each
+    /// group spans at most `max_merged_chunk_lines`. An entity big enough to
+    /// be its own chunk ends the current group, and small entities nested in
+    /// one are already covered by it. Top-level constants get chunks of
+    /// their own and end the group too. With `group_related`, entities sharing
+    /// a name prefix (`parse_args`, `parse_flags`) move to the next group
+    /// together rather than being split across groups.
+    fn group_small_entities<'a>(&self, entities: &'a [CodeEntity]) -> Vec<Vec<&'a CodeEntity>> {
+        let is_small = |e: &CodeEntity| {
+            e.end_line - e.start_line < self.config.min_function_lines && !is_top_level_constant(e, entities)
+        };
+        let max_lines = self.config.max_merged_chunk_lines.max(1);
+
+        let mut sorted: Vec<&CodeEntity> = entities
+            .iter()
+            .filter(|e| e.kind != EntityKind::Import && e.start_line > 0)
+            .collect();
+        sorted.sort_by_key(|e| (e.start_line, std::cmp::Reverse(e.end_line)));
+
+        let mut groups = Vec::new();
+        let mut group: Vec<&CodeEntity> = Vec::new();
+        let mut covered_until = 0;
+
+        for entity in sorted {
+            if entity.end_line <= covered_until {
+                continue;
+            }
+            if !is_small(entity) {
+                covered_until = entity.end_line;
+                if !group.is_empty() {
+                    groups.push(std::mem::take(&mut group));
+                }
+                continue;
+            }
+
+            let fits = |group: &[&CodeEntity]| {
+                group.first().is_none_or(|first| entity.end_line + 1 - first.start_line <= max_lines)
+            };
+            if !fits(&group) {
+                let mut carry = Vec::new();
+                if self.config.group_related {
+                    let prefix = name_prefix(&entity.name);
+                    let split = group
+                        .iter()
+                        .rposition(|e| name_prefix(&e.name) != prefix)
+                        .map_or(0, |i| i + 1);
+                    if split > 0 && split < group.len() && fits(&group[split..]) {
+                        carry = group.split_off(split);
+                    }
+                }
+                groups.push(std::mem::replace(&mut group, carry));
+            }
+            group.push(entity);
+        }
+        if !group.is_empty() {
+            groups.push(group);
+        }
+
+        groups
+    }
+
     fn generate_alias(&self, name: &str, path: &Path) -> String {
         let parent = path
             .parent()
@@ -858,8 +1746,251 @@ impl SmartChunker {
     }
 }
 
+/// A Java or Kotlin type whose body encloses the lines being scanned
+struct JvmScope {
+    name: String,
+    /// Brace depth of lines directly inside the body
+    body_depth: i32,
+    /// Index of the line closing the body
+    end_line: usize,
+    interface: bool,
+}
+
+/// A declaration recognized on one line of a Java or Kotlin file
+struct JvmDeclaration {
+    name: String,
+    /// `None` for scopes that are not entities themselves
+    kind: Option<EntityKind>,
+    visibility: Visibility,
+    interface: bool,
+}
+
+/// Scan a Java or Kotlin file for declarations at the top level or directly
+/// inside a type body; `parse` recognizes one declaration given the
+/// innermost enclosing type. Type declarations open a scope for their
+/// members, so nested types and their methods are found as well.
+fn extract_jvm_entities(
+    content: &str,
+    kotlin: bool,
+    parse: impl Fn(&str, Option<&JvmScope>) -> Option<JvmDeclaration>,
+) -> Vec<CodeEntity> {
+    let code = strip_jvm_literals(content);
+    let raw: Vec<&str> = content.lines().collect();
+    let mut scopes: Vec<JvmScope> = Vec::new();
+    let mut entities = Vec::new();
+    let mut depth = 0i32;
+
+    for (i, line) in code.iter().enumerate() {
+        scopes.retain(|scope| scope.end_line > i);
+        let enclosing = scopes.last().filter(|scope| scope.body_depth == depth);
+
+        let text = strip_jvm_annotations(line.trim());
+        if (depth == 0 || enclosing.is_some()) && !text.is_empty() {
+            if let Some(decl) = parse(text, enclosing) {
+                let end = jvm_declaration_end(&code, i, kotlin);
+                // Annotations on the lines above belong to the declaration
+                let mut start = i;
+                while start > 0 && code[start - 1].trim_start().starts_with('@') {
+                    start -= 1;
+                }
+
+                let opens_scope = !matches!(decl.kind, Some(EntityKind::Method | EntityKind::Function | EntityKind::AsyncFunction));
+                if let Some(kind) = decl.kind {
+                    entities.push(CodeEntity {
+                        name: decl.name.clone(),
+                        kind,
+                        start_line: start + 1,
+                        end_line: end + 1,
+                        visibility: decl.visibility,
+                        doc_comment: jvm_doc_comment(&raw, start),
+                        imports: Vec::new(),
+                        exports: vec![decl.name.clone()],
+                        calls: Vec::new(),
+                        complexity: 1,
+                        concepts: Vec::new(),
+                    });
+                }
+                if opens_scope {
+                    scopes.push(JvmScope {
+                        name: decl.name,
+                        body_depth: depth + 1,
+                        end_line: end,
+                        interface: decl.interface,
+                    });
+                }
+            }
+        }
+
+        depth += line.matches('{').count() as i32 - line.matches('}').count() as i32;
+    }
+
+    entities
+}
+
+/// Lines of a Java or Kotlin file with comments and string and character
+/// literals blanked out, so braces inside them are not counted
+fn strip_jvm_literals(content: &str) -> Vec<String> {
+    let mut in_block_comment = false;
+    let mut in_text_block = false;
+
+    content
+        .lines()
+        .map(|line| {
+            let chars: Vec<char> = line.chars().collect();
+            let mut out = String::with_capacity(line.len());
+            let mut i = 0;
+            while i < chars.len() {
+                let rest = &chars[i..];
+                if in_block_comment || in_text_block {
+                    if in_block_comment && rest.starts_with(&['*', '/']) {
+                        in_block_comment = false;
+                        i += 2;
+                    } else if in_text_block && rest.starts_with(&['"', '"', '"']) {
+                        in_text_block = false;
+                        i += 3;
+                    } else {
+                        i += 1;
+                    }
+                    out.push(' ');
+                    continue;
+                }
+                match rest {
+                    ['/', '/', ..] => break,
+                    ['/', '*', ..] => {
+                        in_block_comment = true;
+                        i += 2;
+                    }
+                    ['"', '"', '"', ..] => {
+                        in_text_block = true;
+                        i += 3;
+                    }
+                    [quote @ ('"' | '\''), ..] => {
+                        let quote = *quote;
+                        i += 1;
+                        while i < chars.len() && chars[i] != quote {
+                            i += if chars[i] == '\\' { 2 } else { 1 };
+                        }
+                        i += 1;
+                    }
+                    _ => {
+                        out.push(chars[i]);
+                        i += 1;
+                        continue;
+                    }
+                }
+                out.push(' ');
+            }
+            out
+        })
+        .collect()
+}
+
+/// A declaration with its leading annotations (`@Override`,
+/// `@Deprecated("...")`) removed
+fn strip_jvm_annotations(mut decl: &str) -> &str {
+    while let Some(rest) = decl.strip_prefix('@') {
+        if rest.starts_with("interface") {
+            break;
+        }
+        let name_len = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+            .unwrap_or(rest.len());
+        let mut rest = &rest[name_len..];
+        if rest.starts_with('(') {
+            let mut parens = 0;
+            let Some(close) = rest.find(|c| {
+                match c {
+                    '(' => parens += 1,
+                    ')' => parens -= 1,
+                    _ => {}
+                }
+                parens == 0
+            }) else {
+                // The arguments continue on the next line
+                return "";
+            };
+            rest = &rest[close + 1..];
+        }
+        decl = rest.trim_start();
+    }
+    decl
+}
+
+/// Index of the last line of the declaration starting on line `start`: the
+/// line closing its brace body, or the one ending it with `;`. A Kotlin
+/// declaration may also end with its header or expression body, once a
+/// line is not continued by the next.
+fn jvm_declaration_end(code: &[String], start: usize, kotlin: bool) -> usize {
+    let mut parens = 0i32;
+    let mut braces = 0i32;
+
+    for (i, line) in code.iter().enumerate().skip(start) {
+        for c in line.chars() {
+            match c {
+                '(' | '[' => parens += 1,
+                ')' | ']' => parens -= 1,
+                // Braces inside the parameter list are default lambdas
+                '{' if parens == 0 || braces > 0 => braces += 1,
+                '}' if braces > 0 => {
+                    braces -= 1;
+                    if braces == 0 {
+                        return i;
+                    }
+                }
+                ';' if parens == 0 && braces == 0 => return i,
+                _ => {}
+            }
+        }
+
+        if kotlin && parens <= 0 && braces == 0 {
+            let line = line.trim_end();
+            let next = code[i + 1..].iter().map(|l| l.trim()).find(|l| !l.is_empty());
+            let continued = ["=", ",", ":", "->", "(", "."].iter().any(|s| line.ends_with(s))
+                || next.is_some_and(|next| ["{", "=", ":", ".", "where "].iter().any(|s| next.starts_with(s)));
+            if !continued {
+                return i;
+            }
+        }
+    }
+
+    code.len().saturating_sub(1)
+}
+
+/// The `/** ... */` comment ending on the line above `start`, without its
+/// delimiters and leading `*`s
+fn jvm_doc_comment(lines: &[&str], start: usize) -> Option<String> {
+    if start == 0 || !lines[start - 1].trim_end().ends_with("*/") {
+        return None;
+    }
+    let first = (0..start).rev().find(|&i| lines[i].trim_start().starts_with("/*"))?;
+    if !lines[first].trim_start().starts_with("/**") {
+        return None;
+    }
+
+    let doc: Vec<&str> = lines[first..start]
+        .iter()
+        .map(|line| {
+            let line = line.trim();
+            let line = line.strip_prefix("/**").unwrap_or(line);
+            let line = line.strip_suffix("*/").unwrap_or(line);
+            line.trim_start_matches('*').trim()
+        })
+        .filter(|line| !line.is_empty())
+        .collect();
+    (!doc.is_empty()).then(|| doc.join("\n"))
+}
+
+/// The target of a Java or Kotlin `import` statement (`java.util.List`,
+/// `org.junit.Assert.assertEquals`). A `package` declaration names the
+/// file's own package and is not an import.
+fn jvm_import(line: &str) -> Option<String> {
+    let target = line.strip_prefix("import ")?;
+    let target = target.strip_prefix("static ").unwrap_or(target);
+    Some(target.trim().trim_end_matches(';').trim().to_string())
+}
+
 /// Convert string to kebab-case
-fn to_kebab_case(s: &str) -> String {
+pub(crate) fn to_kebab_case(s: &str) -> String {
     let mut result = String::new();
     for (i, c) in s.chars().enumerate() {
         if c.is_uppercase() && i > 0 {
@@ -872,14 +2003,82 @@ fn to_kebab_case(s: &str) -> String {
         .replace("--", "-")
 }
 
-/// Compute SHA256 hash
-fn compute_hash(content: &str) -> String {
-    let mut hasher = Sha256::new();
-    hasher.update(content.as_bytes());
-    let result = hasher.finalize();
-    hex::encode(result)
+/// Whether `entity` is a declaration not nested in a function or type;
+/// imports never are
+fn is_top_level(entity: &CodeEntity, entities: &[CodeEntity]) -> bool {
+    entity.kind != EntityKind::Import
+        && !entities.iter().any(|other| {
+            !matches!(other.kind, EntityKind::Import | EntityKind::Module)
+                && other.start_line <= entity.start_line
+                && entity.end_line <= other.end_line
+                && (other.start_line, other.end_line) != (entity.start_line, entity.end_line)
+        })
 }
 
+/// Whether `entity` is a constant outside any function or type; a module
+/// entity spanning the file doesn't count as enclosing it
+fn is_top_level_constant(entity: &CodeEntity, entities: &[CodeEntity]) -> bool {
+    entity.kind == EntityKind::Constant
+        && !entities.iter().any(|other| {
+            !matches!(other.kind, EntityKind::Constant | EntityKind::Import | EntityKind::Module)
+                && other.start_line <= entity.start_line
+                && entity.end_line <= other.end_line
+        })
+}
+
+/// Index of the line ending a declaration that starts at `lines[start]`:
+/// in Rust the one with the `;` outside brackets, elsewhere the first
+/// where brackets are balanced and no `\` continues the line
+fn declaration_end(lines: &[&str], start: usize, language: &str) -> usize {
+    let mut depth = 0;
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        let (next, terminated) = scan_brackets(line, depth, language);
+        depth = next;
+        if terminated || (language != "rust" && depth <= 0 && !line.trim_end().ends_with('\\')) {
+            return i;
+        }
+    }
+    lines.len().saturating_sub(1)
+}
+
+/// Bracket depth after `line` starting from `depth`, skipping string
+/// literals and comments, and whether a `;` at depth 0 ended a statement
+fn scan_brackets(line: &str, mut depth: i32, language: &str) -> (i32, bool) {
+    let comment = if language == "python" { "#" } else { "//" };
+    let mut quote = None;
+    let mut escaped = false;
+
+    for (i, c) in line.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            // Rust lifetimes make `'` unreliable as a quote
+            '\'' if language == "rust" => {}
+            '"' | '\'' | '`' => quote = Some(c),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ';' if language == "rust" && depth <= 0 => return (depth, true),
+            _ if line[i..].starts_with(comment) => break,
+            _ => {}
+        }
+    }
+    (depth, false)
+}
+
+/// First word of an entity name (`parse` for `parse_args` and `parseFlags`)
+fn name_prefix(name: &str) -> String {
+    to_kebab_case(name).split('-').next().unwrap_or_default().to_string()
+}
+
+/// Compute SHA256 hash
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -908,6 +2107,434 @@ mod tests {
         assert_eq!(to_kebab_case("my-file"), "my-file");
     }
 
+    #[test]
+    fn test_calls_extracted_between_entities() {
+        let content = r#"pub fn handler() -> u32 {
+    // parse_args() is the entry point
+    let args = parse_args();
+    handler_inner(args)
+}
+
+fn handler_inner(args: u32) -> u32 {
+    args + 1
+}
+
+fn parse_args() -> u32 {
+    parse_args_len()
+}
+"#;
+
+        let chunker = SmartChunker::default();
+        let analysis = chunker.analyze_file(Path::new("cli.rs"), content).unwrap();
+
+        let calls = |name: &str| {
+            analysis.entities.iter().find(|e| e.name == name).unwrap().calls.clone()
+        };
+        assert_eq!(calls("handler"), vec!["parse_args".to_string(), "handler_inner".to_string()]);
+        assert!(calls("handler_inner").is_empty());
+        // Unknown callees and the entity itself are ignored
+        assert!(calls("parse_args").is_empty());
+    }
+
+    #[test]
+    fn test_ts_arrow_component_boundaries() {
+        let content = r#"import React from 'react';
+
+export const Card = ({ title, items }: CardProps) => {
+  const style = { padding: 8, border: { width: 1, color: '#ccc' } };
+  return (
+    <div style={{ margin: 0, inner: { a: 1 } }}>
+      <h1>{`${title} {${items.length}}`}</h1>
+      {items.map((item) => <span key={item}>{item}</span>)}
+      <p>Don't panic</p>
+    </div>
+  );
+};
+
+export const double = (x: number) => x * 2;
+"#;
+
+        let chunker = SmartChunker::default();
+        let analysis = chunker.analyze_file(Path::new("Card.tsx"), content).unwrap();
+
+        let card = analysis.entities.iter().find(|e| e.name == "Card").unwrap();
+        assert_eq!(card.kind, EntityKind::Function);
+        assert_eq!((card.start_line, card.end_line), (3, 12));
+
+        let double = analysis.entities.iter().find(|e| e.name == "double").unwrap();
+        assert_eq!(double.kind, EntityKind::Function);
+        assert_eq!((double.start_line, double.end_line), (14, 14));
+    }
+
+    #[test]
+    fn test_react_components_keep_props_and_styles() {
+        let content = r#"import React, { useState, useEffect } from 'react';
+import styled from 'styled-components';
+
+export function useToggle(initial: boolean = false) {
+  const [on, setOn] = useState(initial);
+  const toggle = () => setOn((value) => !value);
+  useEffect(() => {
+    document.title = on ? '{' : '}';
+  }, [on]);
+  return { on, toggle };
+}
+
+interface ButtonProps {
+  label: string;
+  onClick?: () => void;
+}
+
+const StyledButton = styled.button`
+  padding: 8px;
+  color: ${(props) => props.color};
+`;
+
+export const Button = ({ label, onClick }: ButtonProps) => {
+  const { on, toggle } = useToggle();
+  return (
+    <StyledButton onClick={() => { toggle(); onClick?.(); }}>
+      {on ? `${label} {on}` : label}
+    </StyledButton>
+  );
+};
+
+export type PanelProps = {
+  title: string;
+  children?: React.ReactNode;
+};
+
+const Wrapper = styled.div`
+  margin: 0 auto;
+`;
+
+const Title = styled.h2`
+  font-size: 2em;
+`;
+
+export function Panel({ title, children }: PanelProps) {
+  const { on, toggle } = useToggle(true);
+  return (
+    <Wrapper>
+      <Title onClick={toggle}>{title} {"}"}</Title>
+      {on && <div className="body">{children}</div>}
+    </Wrapper>
+  );
+}
+"#;
+
+        let chunker = SmartChunker::default();
+        let analysis = chunker.analyze_file(Path::new("src/components/Panels.tsx"), content).unwrap();
+        assert!(analysis.framework_hints.contains(&"react".to_string()));
+        let decision = chunker.decide_chunking(&analysis);
+
+        let chunks: Vec<_> = decision
+            .suggested_chunks
+            .iter()
+            .filter(|c| c.granularity != ChunkGranularity::Import)
+            .collect();
+        let summary: Vec<_> = chunks
+            .iter()
+            .map(|c| (c.name.as_str(), c.start_line, c.end_line, c.provides.clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("useToggle", 4, 11, vec!["useToggle".to_string()]),
+                ("Button", 13, 30, vec!["Button".to_string(), "ButtonProps".to_string()]),
+                ("Panel", 32, 53, vec!["Panel".to_string(), "PanelProps".to_string()]),
+            ]
+        );
+
+        let hook = chunks[0];
+        assert_eq!(hook.granularity, ChunkGranularity::Function);
+        assert!(hook.concepts.contains(&"react-hook".to_string()));
+        assert_eq!(chunks[1].alias, "Panels/button");
+        assert!(chunks[1].requires.contains(&"useToggle".to_string()));
+        assert!(!chunks[1].requires.contains(&"StyledButton".to_string()));
+        assert!(chunks.iter().all(|c| c.category == ChunkCategory::Ui));
+    }
+
+    #[test]
+    fn test_small_entities_are_merged() {
+        let names = [
+            "parse_name", "parse_age", "parse_email", "format_name",
+            "format_age", "trim_input", "is_blank", "clamp_score",
+        ];
+        let content: String = names
+            .iter()
+            .map(|name| format!("pub fn {}(s: &str) -> usize {{\n    let t = s.trim();\n    t.len()\n}}\n\n", name))
+            .collect();
+
+        let chunker = SmartChunker::default();
+        let analysis = chunker.analyze_file(Path::new("src/utils.rs"), &content).unwrap();
+        let decision = chunker.decide_chunking_as(&analysis, ChunkingStrategy::ByEntity);
+
+        let merged: Vec<_> = decision.suggested_chunks.iter().filter(|c| c.name.starts_with("small-helpers")).collect();
+        assert!(!merged.is_empty() && merged.len() <= 2);
+        assert_eq!(merged[0].alias, "utils/small-helpers-1");
+        let provided: Vec<&String> = merged.iter().flat_map(|c| &c.provides).collect();
+        for name in names {
+            assert!(provided.iter().any(|p| *p == name), "{} not provided", name);
+        }
+
+        // A smaller budget splits the run, keeping same-prefix names together
+        let chunker = SmartChunker::new(SmartChunkerConfig {
+            max_merged_chunk_lines: 23,
+            ..Default::default()
+        });
+        let decision = chunker.decide_chunking_as(&analysis, ChunkingStrategy::ByEntity);
+        let concepts: Vec<Vec<String>> = decision.suggested_chunks.iter().map(|c| c.concepts.clone()).collect();
+        assert_eq!(concepts[0], vec!["parse_name", "parse_age", "parse_email"]);
+        assert_eq!(concepts[1], vec!["format_name", "format_age", "trim_input", "is_blank"]);
+        // A lone leftover becomes an ordinary entity chunk
+        assert_eq!(decision.suggested_chunks[2].alias, "utils/clamp-score");
+        assert!(decision.suggested_chunks.iter().all(|c| c.end_line - c.start_line < 23));
+
+        // Without merging they are dropped as before
+        let chunker = SmartChunker::new(SmartChunkerConfig {
+            merge_small_entities: false,
+            ..Default::default()
+        });
+        let decision = chunker.decide_chunking_as(&analysis, ChunkingStrategy::ByEntity);
+        assert_eq!(decision.suggested_chunks.len(), 1);
+        assert_eq!(decision.suggested_chunks[0].granularity, ChunkGranularity::Module);
+    }
+
+    #[test]
+    fn test_constants_become_data_chunks() {
+        let content = r#"use std::time::Duration;
+
+pub const MAX_RETRIES: u32 = 3;
+pub const BACKOFF_MS: u64 = 250;
+pub const USER_AGENT: &'static str = "cadi/1.0";
+
+pub fn retry_delay(attempt: u32) -> Duration {
+    let attempt = attempt.min(MAX_RETRIES);
+    Duration::from_millis(BACKOFF_MS * u64::from(attempt))
+}
+
+pub fn agent() -> &'static str {
+    // Sent with every request
+    let agent = USER_AGENT;
+    agent
+}
+"#;
+
+        let chunker = SmartChunker::default();
+        let analysis = chunker.analyze_file(Path::new("src/net.rs"), content).unwrap();
+        let decision = chunker.decide_chunking_as(&analysis, ChunkingStrategy::ByEntity);
+
+        let consts: Vec<_> = decision.suggested_chunks.iter().filter(|c| c.name.starts_with("consts")).collect();
+        assert_eq!(consts.len(), 1);
+        let consts = consts[0];
+        assert_eq!(consts.alias, "net/consts");
+        assert_eq!((consts.start_line, consts.end_line), (3, 5));
+        assert_eq!(consts.category, ChunkCategory::Data);
+        assert_eq!(consts.provides, vec!["MAX_RETRIES", "BACKOFF_MS", "USER_AGENT"]);
+
+        // Functions using a constant require it, so expansion pulls the chunk in
+        let retry = decision.suggested_chunks.iter().find(|c| c.provides.contains(&"retry_delay".to_string())).unwrap();
+        assert!(retry.requires.contains(&"MAX_RETRIES".to_string()));
+        assert!(retry.requires.contains(&"BACKOFF_MS".to_string()));
+        assert!(!retry.provides.iter().any(|p| p == "MAX_RETRIES"));
+    }
+
+    #[test]
+    fn test_python_and_go_constants() {
+        let chunker = SmartChunker::default();
+
+        let python = "import os\n\nDEFAULT_PORT = 8080\nHEADERS = {\n    \"accept\": \"json\",\n}\nlogger = None\n";
+        let analysis = chunker.analyze_file(Path::new("settings.py"), python).unwrap();
+        let constants: Vec<(&str, usize, usize)> = analysis
+            .entities
+            .iter()
+            .filter(|e| e.kind == EntityKind::Constant)
+            .map(|e| (e.name.as_str(), e.start_line, e.end_line))
+            .collect();
+        assert_eq!(constants, vec![("DEFAULT_PORT", 3, 3), ("HEADERS", 4, 6)]);
+
+        let go = "package config\n\nconst (\n\tModeDev = iota\n\tModeProd\n)\n\nvar timeout = 30\n";
+        let analysis = chunker.analyze_file(Path::new("config.go"), go).unwrap();
+        let block = analysis.entities.iter().find(|e| e.name == "ModeDev").unwrap();
+        assert_eq!(block.exports, vec!["ModeDev", "ModeProd"]);
+        assert_eq!((block.start_line, block.end_line), (3, 6));
+        let timeout = analysis.entities.iter().find(|e| e.name == "timeout").unwrap();
+        assert_eq!(timeout.visibility, Visibility::Private);
+    }
+
+    #[test]
+    fn test_java_entities() {
+        let content = r#"package com.example.todo;
+
+import java.util.ArrayList;
+import java.util.List;
+import static java.util.Objects.requireNonNull;
+
+/**
+ * Keeps todo items in memory.
+ */
+public class TodoStore {
+    private final List<Item> items = new ArrayList<>();
+
+    public TodoStore() {
+        // { not a brace }
+    }
+
+    /** Adds an item. */
+    @Deprecated
+    public Item add(String title) {
+        Item item = new Item(requireNonNull(title), "{");
+        items.add(item);
+        return item;
+    }
+
+    int count() {
+        return items.size();
+    }
+
+    public static class Item {
+        private final String title;
+
+        Item(String title, String marker) {
+            this.title = title;
+        }
+
+        private String title() {
+            return title;
+        }
+    }
+
+    public static void main(String[] args) {
+        new TodoStore().add("demo");
+    }
+}
+
+interface Store {
+    int count();
+}
+
+enum Priority { LOW, HIGH }
+
+record Tag(String name) {}
+"#;
+
+        let chunker = SmartChunker::default();
+        let analysis = chunker.analyze_file(Path::new("TodoStore.java"), content).unwrap();
+        let entity = |name: &str| analysis.entities.iter().find(|e| e.name == name && e.kind != EntityKind::Import).unwrap();
+
+        let store = entity("TodoStore");
+        assert_eq!(store.kind, EntityKind::Class);
+        assert_eq!((store.start_line, store.end_line), (10, 44));
+        assert_eq!(store.doc_comment.as_deref(), Some("Keeps todo items in memory."));
+
+        let add = entity("add");
+        assert_eq!(add.kind, EntityKind::Method);
+        assert_eq!((add.start_line, add.end_line), (18, 23));
+        assert_eq!(add.visibility, Visibility::Public);
+        assert_eq!(entity("count").visibility, Visibility::Internal);
+
+        let item = entity("Item");
+        assert_eq!(item.kind, EntityKind::Class);
+        assert_eq!((item.start_line, item.end_line), (29, 39));
+        assert_eq!(entity("title").visibility, Visibility::Private);
+        // Constructors of both classes
+        assert_eq!(analysis.entities.iter().filter(|e| e.kind == EntityKind::Method && (e.name == "TodoStore" || e.name == "Item")).count(), 2);
+
+        assert_eq!(entity("Store").kind, EntityKind::Interface);
+        assert_eq!(entity("Priority").kind, EntityKind::Enum);
+        assert_eq!(entity("Tag").kind, EntityKind::Struct);
+        // Interface members are public; `count` in Store is the second one
+        assert!(analysis.entities.iter().any(|e| e.name == "count" && e.start_line == 47 && e.visibility == Visibility::Public));
+
+        assert_eq!(analysis.imports, vec!["java.util.ArrayList", "java.util.List", "java.util.Objects.requireNonNull"]);
+        assert!(analysis.exports.contains(&"TodoStore".to_string()));
+        assert!(analysis.is_entrypoint);
+        assert!(!chunker.analyze_file(Path::new("Store.java"), "interface Store {\n    void main(String[] args);\n}\n").unwrap().is_entrypoint);
+    }
+
+    #[test]
+    fn test_kotlin_entities() {
+        let content = r#"package com.example.todo
+
+import kotlinx.coroutines.delay
+import com.example.todo.model.Item as TodoItem
+
+data class Todo(val title: String, val done: Boolean = false)
+
+/** Extension functions on Todo */
+fun Todo.describe(): String {
+    val mark = if (done) "x" else " "
+    return "[$mark] ${title}"
+}
+
+fun String.shout() = uppercase() + "!"
+
+fun <T> List<T>.second(): T =
+    this[1]
+
+internal suspend fun load(): List<Todo> {
+    delay(10)
+    return listOf(Todo("a"))
+}
+
+sealed interface Command {
+    fun run(todos: MutableList<Todo>)
+}
+
+class TodoService(private val todos: MutableList<Todo>) {
+    private fun find(title: String) = todos.firstOrNull { it.title == title }
+
+    fun complete(title: String) {
+        find(title)?.let { todos.remove(it) }
+    }
+
+    companion object {
+        fun empty() = TodoService(mutableListOf())
+    }
+}
+
+enum class Status { OPEN, DONE }
+
+fun main() {
+    println(Todo("demo").describe())
+}
+"#;
+
+        let chunker = SmartChunker::default();
+        let analysis = chunker.analyze_file(Path::new("Todo.kt"), content).unwrap();
+        let entity = |name: &str| analysis.entities.iter().find(|e| e.name == name && e.kind != EntityKind::Import).unwrap();
+
+        let todo = entity("Todo");
+        assert_eq!(todo.kind, EntityKind::Struct);
+        assert_eq!((todo.start_line, todo.end_line), (6, 6));
+
+        let describe = entity("describe");
+        assert_eq!(describe.kind, EntityKind::Function);
+        assert_eq!((describe.start_line, describe.end_line), (9, 12));
+        assert_eq!(describe.doc_comment.as_deref(), Some("Extension functions on Todo"));
+        assert_eq!((entity("shout").start_line, entity("shout").end_line), (14, 14));
+        assert_eq!((entity("second").start_line, entity("second").end_line), (16, 17));
+
+        let load = entity("load");
+        assert_eq!(load.kind, EntityKind::AsyncFunction);
+        assert_eq!(load.visibility, Visibility::Internal);
+
+        assert_eq!(entity("Command").kind, EntityKind::Interface);
+        assert_eq!(entity("run").kind, EntityKind::Method);
+
+        let service = entity("TodoService");
+        assert_eq!((service.start_line, service.end_line), (28, 38));
+        assert_eq!(entity("find").visibility, Visibility::Private);
+        assert_eq!((entity("complete").start_line, entity("complete").end_line), (31, 33));
+        // Companion members belong to the class
+        assert_eq!(entity("empty").kind, EntityKind::Method);
+        assert_eq!(entity("Status").kind, EntityKind::Enum);
+
+        assert_eq!(analysis.imports, vec!["kotlinx.coroutines.delay", "com.example.todo.model.Item as TodoItem"]);
+        assert!(analysis.is_entrypoint);
+    }
+
 
 
 }
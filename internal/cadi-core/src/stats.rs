@@ -0,0 +1,287 @@
+//! Repository statistics
+//!
+//! [`StatsCollector`] gathers chunk, graph and deduplication metrics from a
+//! local CADI cache so the CLI and the MCP server report the same numbers.
+
+use crate::deduplication::{DeduplicationEngine, DuplicateCandidate, DuplicateCluster};
+use crate::error::CadiResult;
+use crate::graph::{EdgeType, GraphStore};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Collects statistics from a chunk directory and a graph store
+#[derive(Debug, Clone)]
+pub struct StatsCollector {
+    chunk_dir: Option<PathBuf>,
+    graph_dir: Option<PathBuf>,
+    top_n: usize,
+    duplicate_threshold: Option<f64>,
+}
+
+impl Default for StatsCollector {
+    fn default() -> Self {
+        Self {
+            chunk_dir: None,
+            graph_dir: None,
+            top_n: 10,
+            duplicate_threshold: None,
+        }
+    }
+}
+
+/// Statistics about a local CADI repository
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RepositoryStats {
+    pub chunks: ChunkStats,
+    pub graph: GraphStats,
+    pub dedup: DedupStats,
+    /// Chunks with the most distinct dependents, most depended-on first
+    pub top_dependencies: Vec<DependencyCount>,
+    /// Semantic duplicate clusters, when requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicates: Option<Vec<DuplicateCluster>>,
+}
+
+/// Chunks stored in the local cache
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChunkStats {
+    pub count: usize,
+    pub bytes: u64,
+}
+
+/// Size of the dependency graph
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GraphStats {
+    pub nodes: usize,
+    pub edges: usize,
+    /// Edge count keyed by edge type
+    pub edges_by_type: BTreeMap<String, usize>,
+}
+
+/// Duplicate content across graph nodes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DedupStats {
+    pub chunks: usize,
+    pub unique: usize,
+    /// Chunks per unique content hash (1.0 means no duplicates)
+    pub ratio: f64,
+}
+
+/// Number of chunks depending on a chunk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DependencyCount {
+    pub chunk_id: String,
+    pub dependents: usize,
+}
+
+impl StatsCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Directory holding cached chunk files
+    pub fn with_chunk_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.chunk_dir = Some(dir.into());
+        self
+    }
+
+    /// Directory of the graph store
+    pub fn with_graph_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.graph_dir = Some(dir.into());
+        self
+    }
+
+    /// Number of most depended-on chunks to report
+    pub fn with_top_n(mut self, top_n: usize) -> Self {
+        self.top_n = top_n;
+        self
+    }
+
+    /// Also search graph nodes for semantic duplicates scoring at least
+    /// `threshold`, recording `EquivalentTo` edges between them
+    pub fn with_duplicates(mut self, threshold: f64) -> Self {
+        self.duplicate_threshold = Some(threshold);
+        self
+    }
+
+    /// Collect statistics; missing directories count as empty
+    pub fn collect(&self) -> CadiResult<RepositoryStats> {
+        let mut stats = match &self.graph_dir {
+            Some(dir) if dir.exists() => self.collect_graph(&GraphStore::open(dir)?)?,
+            _ => RepositoryStats {
+                dedup: DeduplicationEngine::new().stats(),
+                duplicates: self.duplicate_threshold.map(|_| Vec::new()),
+                ..Default::default()
+            },
+        };
+
+        if let Some(dir) = &self.chunk_dir {
+            stats.chunks = chunk_stats(dir)?;
+        }
+
+        Ok(stats)
+    }
+
+    /// Collect graph, deduplication and dependency statistics from a store
+    pub fn collect_graph(&self, store: &GraphStore) -> CadiResult<RepositoryStats> {
+        let duplicates = match self.duplicate_threshold {
+            Some(threshold) => Some(find_duplicates(store, threshold)?),
+            None => None,
+        };
+
+        let nodes = store.list_nodes()?;
+        let edges = store.list_edges()?;
+
+        let mut edges_by_type = BTreeMap::new();
+        let mut dependents: HashMap<&str, HashSet<&str>> = HashMap::new();
+        for (source, target, edge_type) in &edges {
+            *edges_by_type.entry(edge_type.to_string()).or_insert(0) += 1;
+            if *edge_type == EdgeType::EquivalentTo {
+                continue;
+            }
+            dependents.entry(target.as_str()).or_default().insert(source.as_str());
+        }
+
+        let mut dedup = DeduplicationEngine::new();
+        for node in &nodes {
+            dedup.register_chunk(&node.chunk_id, &node.content_hash);
+        }
+
+        let mut top_dependencies: Vec<DependencyCount> = dependents
+            .into_iter()
+            .map(|(chunk_id, sources)| DependencyCount {
+                chunk_id: chunk_id.to_string(),
+                dependents: sources.len(),
+            })
+            .collect();
+        top_dependencies.sort_by(|a, b| {
+            b.dependents.cmp(&a.dependents).then_with(|| a.chunk_id.cmp(&b.chunk_id))
+        });
+        top_dependencies.truncate(self.top_n);
+
+        Ok(RepositoryStats {
+            chunks: ChunkStats::default(),
+            graph: GraphStats {
+                nodes: nodes.len(),
+                edges: edges.len(),
+                edges_by_type,
+            },
+            dedup: dedup.stats(),
+            top_dependencies,
+            duplicates,
+        })
+    }
+}
+
+/// Cluster graph nodes by semantic similarity and record the equivalences
+fn find_duplicates(store: &GraphStore, threshold: f64) -> CadiResult<Vec<DuplicateCluster>> {
+    let mut candidates = Vec::new();
+    for node in store.list_nodes()? {
+        if let Some(content) = store.get_content_str(&node.chunk_id)? {
+            candidates.push(DuplicateCandidate::new(node.chunk_id, node.language, content));
+        }
+    }
+
+    let clusters = DeduplicationEngine::find_semantic_duplicates(&candidates, threshold);
+    DeduplicationEngine::record_equivalences(store, &clusters)?;
+    Ok(clusters)
+}
+
+/// Count chunk files under a directory, skipping `.json` metadata files
+fn chunk_stats(dir: &Path) -> CadiResult<ChunkStats> {
+    let mut stats = ChunkStats::default();
+    if !dir.exists() {
+        return Ok(stats);
+    }
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.is_dir() {
+            let sub = chunk_stats(&path)?;
+            stats.count += sub.count;
+            stats.bytes += sub.bytes;
+        } else if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            stats.count += 1;
+            stats.bytes += entry.metadata()?.len();
+        }
+    }
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{EdgeType, GraphNode};
+
+    #[test]
+    fn test_collect_graph_counts_edges_dedup_and_dependents() {
+        let store = GraphStore::in_memory().unwrap();
+        for (id, hash) in [("a", "h1"), ("b", "h2"), ("c", "h2"), ("util", "h3")] {
+            store.insert_node(&GraphNode::new(id, hash)).unwrap();
+        }
+        store.add_dependency("a", "util", EdgeType::Imports).unwrap();
+        store.add_dependency("b", "util", EdgeType::Calls).unwrap();
+        store.add_dependency("b", "util", EdgeType::Imports).unwrap();
+        store.add_dependency("c", "b", EdgeType::Calls).unwrap();
+
+        let stats = StatsCollector::new().with_top_n(1).collect_graph(&store).unwrap();
+
+        assert_eq!(stats.graph.nodes, 4);
+        assert_eq!(stats.graph.edges, 4);
+        assert_eq!(stats.graph.edges_by_type.get("imports"), Some(&2));
+        assert_eq!(stats.graph.edges_by_type.get("calls"), Some(&2));
+        assert_eq!(stats.dedup.unique, 3);
+        assert!((stats.dedup.ratio - 4.0 / 3.0).abs() < 1e-9);
+        assert_eq!(stats.top_dependencies.len(), 1);
+        assert_eq!(stats.top_dependencies[0].chunk_id, "util");
+        assert_eq!(stats.top_dependencies[0].dependents, 2);
+    }
+
+    #[test]
+    fn test_collect_graph_reports_duplicates() {
+        let store = GraphStore::in_memory().unwrap();
+        let chunks = [
+            ("add", "fn add(a: u64, b: u64) -> u64 { a + b }"),
+            ("plus", "fn plus(x: u64, y: u64) -> u64 {\n    x + y\n}"),
+            ("main", "fn main() { println!(\"{}\", add(1, 2)); }"),
+        ];
+        for (id, content) in chunks {
+            let node = GraphNode::new(id, crate::hash::sha256_str(content)).with_language("rust");
+            store.insert_node(&node).unwrap();
+            store.store_content(id, content.as_bytes()).unwrap();
+        }
+        store.add_dependency("main", "add", EdgeType::Calls).unwrap();
+
+        let stats = StatsCollector::new().with_duplicates(0.9).collect_graph(&store).unwrap();
+
+        let duplicates = stats.duplicates.unwrap();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].chunk_ids, vec!["add", "plus"]);
+        assert_eq!(stats.graph.edges_by_type.get("equivalent_to"), Some(&2));
+        // Equivalences are not dependencies
+        assert_eq!(stats.top_dependencies.len(), 1);
+        assert_eq!(stats.top_dependencies[0].chunk_id, "add");
+        assert_eq!(stats.top_dependencies[0].dependents, 1);
+    }
+
+    #[test]
+    fn test_chunk_stats_skips_metadata() {
+        let dir = std::env::temp_dir().join(format!("cadi-stats-{}", std::process::id()));
+        fs::create_dir_all(dir.join("ab")).unwrap();
+        fs::write(dir.join("abc.chunk"), b"12345").unwrap();
+        fs::write(dir.join("abc.json"), b"{}").unwrap();
+        fs::write(dir.join("ab").join("abcdef"), b"123").unwrap();
+
+        let stats = StatsCollector::new().with_chunk_dir(&dir).collect().unwrap();
+        assert_eq!(stats.chunks.count, 2);
+        assert_eq!(stats.chunks.bytes, 8);
+        assert_eq!(stats.graph.nodes, 0);
+        assert_eq!(stats.dedup.ratio, 1.0);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
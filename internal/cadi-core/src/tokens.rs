@@ -0,0 +1,341 @@
+//! Token estimation
+//!
+//! Views and ghost import expansions are cut to a token budget. How tokens
+//! are counted is pluggable: the default is the `len / 4` byte heuristic,
+//! [`CalibratedHeuristic`] adjusts for code, prose and CJK text, and with
+//! the `bpe-tokenizer` feature `BpeTokenizer` counts exactly with a
+//! tiktoken vocabulary file (e.g. `cl100k_base.tiktoken`).
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[cfg(feature = "bpe-tokenizer")]
+use crate::error::{CadiError, CadiResult};
+#[cfg(feature = "bpe-tokenizer")]
+use std::collections::HashMap;
+#[cfg(feature = "bpe-tokenizer")]
+use std::path::Path;
+
+/// Estimates how many model tokens a text takes up
+pub trait TokenEstimator: Send + Sync {
+    /// Tokens in `text`, written in `language` if known
+    fn estimate(&self, text: &str, language: Option<&str>) -> usize;
+}
+
+/// One token per four bytes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ByteHeuristic;
+
+impl TokenEstimator for ByteHeuristic {
+    fn estimate(&self, text: &str, _language: Option<&str>) -> usize {
+        text.len() / 4
+    }
+}
+
+/// Characters per token tuned separately for code and prose, with CJK
+/// characters counted as a token each (they are about one token per
+/// character in BPE vocabularies, where the byte heuristic counts ~0.75)
+#[derive(Debug, Clone, Copy)]
+pub struct CalibratedHeuristic {
+    /// Characters per token in source code
+    pub code_divisor: f64,
+    /// Characters per token in markdown and plain text
+    pub prose_divisor: f64,
+}
+
+impl Default for CalibratedHeuristic {
+    fn default() -> Self {
+        Self {
+            code_divisor: 3.5,
+            prose_divisor: 4.0,
+        }
+    }
+}
+
+impl CalibratedHeuristic {
+    fn divisor(&self, language: Option<&str>) -> f64 {
+        match language.map(str::to_ascii_lowercase).as_deref() {
+            None | Some("markdown" | "md" | "text" | "txt" | "plain" | "rst" | "unknown") => self.prose_divisor,
+            Some(_) => self.code_divisor,
+        }
+    }
+}
+
+impl TokenEstimator for CalibratedHeuristic {
+    fn estimate(&self, text: &str, language: Option<&str>) -> usize {
+        let mut cjk = 0usize;
+        let mut other = 0usize;
+        for c in text.chars() {
+            if is_cjk(c) {
+                cjk += 1;
+            } else {
+                other += 1;
+            }
+        }
+        cjk + (other as f64 / self.divisor(language)).ceil() as usize
+    }
+}
+
+/// Han, kana, Hangul and fullwidth forms
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF     // Hiragana, Katakana
+        | 0x3400..=0x4DBF   // CJK Extension A
+        | 0x4E00..=0x9FFF   // CJK Unified Ideographs
+        | 0xAC00..=0xD7AF   // Hangul syllables
+        | 0xF900..=0xFAFF   // CJK Compatibility Ideographs
+        | 0xFF00..=0xFFEF   // Halfwidth and fullwidth forms
+        | 0x20000..=0x2FA1F // CJK Extensions B-F, compatibility supplement
+    )
+}
+
+/// Byte-pair encoding with a tiktoken vocabulary: each line of the file is
+/// a base64 token and its merge rank
+#[cfg(feature = "bpe-tokenizer")]
+pub struct BpeTokenizer {
+    ranks: HashMap<Vec<u8>, u32>,
+    pattern: regex::Regex,
+}
+
+#[cfg(feature = "bpe-tokenizer")]
+impl BpeTokenizer {
+    /// Parse a vocabulary in the `.tiktoken` format
+    pub fn from_tiktoken(data: &str) -> CadiResult<Self> {
+        use base64::Engine;
+
+        let mut ranks = HashMap::new();
+        for (number, line) in data.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            let invalid = || CadiError::Configuration(format!("Invalid tiktoken vocabulary line {}", number + 1));
+            let (token, rank) = line.trim().split_once(' ').ok_or_else(invalid)?;
+            let token = base64::engine::general_purpose::STANDARD.decode(token).map_err(|_| invalid())?;
+            ranks.insert(token, rank.parse().map_err(|_| invalid())?);
+        }
+
+        // The cl100k pre-tokenizer, without the `\s+(?!\S)` lookahead the
+        // regex crate lacks; `pieces` emulates it
+        let pattern = regex::Regex::new(
+            r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+",
+        )
+        .map_err(|e| CadiError::Configuration(e.to_string()))?;
+
+        Ok(Self { ranks, pattern })
+    }
+
+    /// Load a `.tiktoken` vocabulary file
+    pub fn from_file(path: &Path) -> CadiResult<Self> {
+        Self::from_tiktoken(&std::fs::read_to_string(path)?)
+    }
+
+    /// Number of tokens `text` encodes to
+    pub fn count(&self, text: &str) -> usize {
+        self.pieces(text).map(|piece| self.piece_tokens(piece.as_bytes())).sum()
+    }
+
+    /// Split `text` into the pieces merges happen within. A whitespace run
+    /// before a word leaves its last character to the word.
+    fn pieces<'t>(&'t self, text: &'t str) -> impl Iterator<Item = &'t str> + 't {
+        let mut pos = 0;
+        std::iter::from_fn(move || {
+            let found = self.pattern.find_at(text, pos)?;
+            let mut end = found.end();
+            let piece = found.as_str();
+            if piece.chars().count() > 1
+                && piece.chars().all(char::is_whitespace)
+                && text[end..].chars().next().is_some_and(|c| !c.is_whitespace())
+            {
+                end -= piece.chars().next_back().map_or(0, char::len_utf8);
+            }
+            pos = end;
+            Some(&text[found.start()..end])
+        })
+    }
+
+    /// Tokens in one piece, merging the lowest-ranked adjacent pair until
+    /// no pair is in the vocabulary
+    fn piece_tokens(&self, piece: &[u8]) -> usize {
+        if piece.is_empty() {
+            return 0;
+        }
+        if self.ranks.contains_key(piece) {
+            return 1;
+        }
+        let mut bounds: Vec<usize> = (0..=piece.len()).collect();
+        loop {
+            let best = (0..bounds.len().saturating_sub(2))
+                .filter_map(|i| self.ranks.get(&piece[bounds[i]..bounds[i + 2]]).map(|rank| (*rank, i)))
+                .min();
+            match best {
+                Some((_, i)) => {
+                    bounds.remove(i + 1);
+                }
+                None => return bounds.len() - 1,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "bpe-tokenizer")]
+impl TokenEstimator for BpeTokenizer {
+    fn estimate(&self, text: &str, _language: Option<&str>) -> usize {
+        self.count(text)
+    }
+}
+
+/// Which estimator to count tokens with, as chosen in a view config or
+/// expansion policy
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenEstimatorKind {
+    /// [`ByteHeuristic`]
+    #[default]
+    Bytes,
+    /// [`CalibratedHeuristic`] with its default divisors
+    Calibrated,
+    /// `BpeTokenizer` with a `.tiktoken` vocabulary file. Without the
+    /// `bpe-tokenizer` feature, or if the file can't be loaded, the
+    /// calibrated heuristic is used instead.
+    Bpe { vocab: PathBuf },
+}
+
+impl TokenEstimatorKind {
+    /// The estimator to count with
+    pub fn estimator(&self) -> Arc<dyn TokenEstimator> {
+        match self {
+            Self::Bytes => Arc::new(ByteHeuristic),
+            Self::Calibrated => Arc::new(CalibratedHeuristic::default()),
+            Self::Bpe { vocab } => bpe_estimator(vocab),
+        }
+    }
+
+    /// Tokens in `text`, written in `language` if known
+    pub fn estimate(&self, text: &str, language: Option<&str>) -> usize {
+        self.estimator().estimate(text, language)
+    }
+}
+
+/// Vocabularies are loaded once per process
+#[cfg(feature = "bpe-tokenizer")]
+fn bpe_estimator(vocab: &Path) -> Arc<dyn TokenEstimator> {
+    use std::sync::{Mutex, OnceLock};
+
+    static LOADED: OnceLock<Mutex<HashMap<PathBuf, Arc<BpeTokenizer>>>> = OnceLock::new();
+    let mut loaded = LOADED.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(tokenizer) = loaded.get(vocab) {
+        return tokenizer.clone();
+    }
+    match BpeTokenizer::from_file(vocab) {
+        Ok(tokenizer) => {
+            let tokenizer = Arc::new(tokenizer);
+            loaded.insert(vocab.to_path_buf(), tokenizer.clone());
+            tokenizer
+        }
+        Err(e) => {
+            tracing::warn!("Falling back to the calibrated token heuristic: {}: {}", vocab.display(), e);
+            Arc::new(CalibratedHeuristic::default())
+        }
+    }
+}
+
+#[cfg(not(feature = "bpe-tokenizer"))]
+fn bpe_estimator(_vocab: &std::path::Path) -> Arc<dyn TokenEstimator> {
+    tracing::warn!("Built without the bpe-tokenizer feature; using the calibrated token heuristic");
+    Arc::new(CalibratedHeuristic::default())
+}
+
+impl std::str::FromStr for TokenEstimatorKind {
+    type Err = String;
+
+    /// `bytes`, `calibrated` or `bpe:<vocab path>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "bytes" => Ok(Self::Bytes),
+            "calibrated" => Ok(Self::Calibrated),
+            _ => match s.split_once(':') {
+                Some((kind, vocab)) if kind.eq_ignore_ascii_case("bpe") && !vocab.is_empty() => Ok(Self::Bpe {
+                    vocab: PathBuf::from(vocab),
+                }),
+                _ => Err(format!("Unknown token estimator: {} (expected bytes, calibrated or bpe:<vocab>)", s)),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Texts and their cl100k_base token counts
+    const FIXTURES: &[(&str, Option<&str>, usize)] = &[
+        ("Hello, world!", None, 4),
+        ("The quick brown fox jumps over the lazy dog.", Some("markdown"), 10),
+        ("fn main() {}", Some("rust"), 4),
+        ("你好", None, 2),
+    ];
+
+    #[test]
+    fn test_calibrated_is_close_to_known_counts() {
+        let calibrated = CalibratedHeuristic::default();
+        for (text, language, tokens) in FIXTURES {
+            let estimate = calibrated.estimate(text, *language);
+            assert!(estimate.abs_diff(*tokens) <= (*tokens / 5).max(1), "{:?}: {} vs {}", text, estimate, tokens);
+        }
+        // The byte heuristic is off by half on CJK text
+        assert_eq!(ByteHeuristic.estimate("你好", None), 1);
+    }
+
+    #[test]
+    fn test_byte_heuristic_undercounts_cjk() {
+        let text = "内容寻址的代码块可以在项目之间复用";
+        let chars = text.chars().count();
+        assert_eq!(CalibratedHeuristic::default().estimate(text, None), chars);
+        assert!(ByteHeuristic.estimate(text, None) < chars);
+        // Code is denser than prose
+        let code = "let x = 1;".repeat(10);
+        assert!(
+            CalibratedHeuristic::default().estimate(&code, Some("rust"))
+                > CalibratedHeuristic::default().estimate(&code, Some("markdown"))
+        );
+    }
+
+    #[test]
+    fn test_estimator_kind_parsing() {
+        assert_eq!("bytes".parse::<TokenEstimatorKind>(), Ok(TokenEstimatorKind::Bytes));
+        assert_eq!("Calibrated".parse::<TokenEstimatorKind>(), Ok(TokenEstimatorKind::Calibrated));
+        assert_eq!(
+            "bpe:/tmp/cl100k_base.tiktoken".parse::<TokenEstimatorKind>(),
+            Ok(TokenEstimatorKind::Bpe { vocab: PathBuf::from("/tmp/cl100k_base.tiktoken") })
+        );
+        assert!("words".parse::<TokenEstimatorKind>().is_err());
+        assert_eq!(TokenEstimatorKind::default().estimate("12345678", None), 2);
+        // A missing vocabulary falls back to the calibrated heuristic
+        let missing = TokenEstimatorKind::Bpe { vocab: PathBuf::from("/nonexistent.tiktoken") };
+        assert_eq!(missing.estimate("你好", None), 2);
+    }
+
+    #[cfg(feature = "bpe-tokenizer")]
+    #[test]
+    fn test_bpe_counts_with_tiktoken_vocabulary() {
+        use base64::Engine;
+
+        // Every byte, then merges up to " world" and "hello"
+        let mut tokens: Vec<Vec<u8>> = (0..=255u8).map(|b| vec![b]).collect();
+        for merged in ["he", "ll", "hell", "hello", " w", "or", " wor", "ld", " world"] {
+            tokens.push(merged.as_bytes().to_vec());
+        }
+        let vocab: String = tokens
+            .iter()
+            .enumerate()
+            .map(|(rank, token)| format!("{} {}\n", base64::engine::general_purpose::STANDARD.encode(token), rank))
+            .collect();
+        let bpe = BpeTokenizer::from_tiktoken(&vocab).unwrap();
+
+        assert_eq!(bpe.count("hello"), 1);
+        assert_eq!(bpe.count("hello world"), 2);
+        assert_eq!(bpe.count("hello, world!"), 4);
+        // Whitespace before a word goes with the word: "hello", "  ", " world"
+        assert_eq!(bpe.count("hello   world"), 4);
+        assert_eq!(bpe.count("héllo"), 5);
+        assert!(BpeTokenizer::from_tiktoken("not-base64!! x").is_err());
+    }
+}
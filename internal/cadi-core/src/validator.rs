@@ -1,54 +1,314 @@
+//! Validation of CADL documents and CADI manifests
+//!
+//! Every finding is a [`Diagnostic`] with a stable code from [`codes`], a
+//! severity, the path of the offending element and, when the source text is
+//! available, its line and column. Only error diagnostics make validation
+//! fail; warnings are only reported by the `diagnose` methods.
+
 use crate::ast::*;
-use std::collections::HashSet;
+use crate::deduplication::DeduplicationEngine;
+use crate::lockfile::{ChunkRef, ManifestLock};
+use crate::manifest::{
+    cadl_version_at_least, Manifest, CURRENT_CADL_VERSION, LEGACY_CADL_VERSION, SUPPORTED_CADL_VERSIONS,
+};
+use crate::manifest_upgrade::{declared_cadl_version, renamed_field};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
-#[derive(Debug, Clone)]
-pub struct ValidationError {
+/// Diagnostic codes
+pub mod codes {
+    /// The source could not be parsed
+    pub const PARSE_ERROR: &str = "CADL000";
+    /// A manifest field that no part of CADI reads
+    pub const UNKNOWN_FIELD: &str = "CADL001";
+    /// Two interfaces share a name
+    pub const DUPLICATE_INTERFACE: &str = "CADL002";
+    /// Two methods of an interface share a name
+    pub const DUPLICATE_METHOD: &str = "CADL003";
+    /// An implementation has no name
+    pub const MISSING_IMPL_NAME: &str = "CADL004";
+    /// An implementation name is not `Interface.Implementation`
+    pub const IMPL_NAME_FORMAT: &str = "CADL005";
+    /// A constraints block has no rules
+    pub const EMPTY_CONSTRAINT: &str = "CADL006";
+    /// A contract describes nothing
+    pub const EMPTY_CONTRACT: &str = "CADL007";
+    /// An unrecognized `@effects` concurrency value
+    pub const INVALID_CONCURRENCY: &str = "CADL008";
+    /// A permissions annotation grants and denies nothing
+    pub const EMPTY_PERMISSIONS: &str = "CADL009";
+    /// A resource bound is empty
+    pub const EMPTY_RESOURCE: &str = "CADL010";
+    /// A protocol with states has no initial state
+    pub const MISSING_INITIAL_STATE: &str = "CADL011";
+    /// A protocol's initial state is not one of its states
+    pub const UNKNOWN_INITIAL_STATE: &str = "CADL012";
+    /// An unrecognized `@abi` string encoding
+    pub const INVALID_STRING_ENCODING: &str = "CADL013";
+    /// An implementation provides an interface the document does not define
+    pub const MISSING_PROVIDES: &str = "CADL014";
+    /// An annotation CADI does not recognize
+    pub const UNKNOWN_ANNOTATION: &str = "CADL015";
+    /// Two parameters of a method share a name
+    pub const DUPLICATE_PARAMETER: &str = "CADL016";
+    /// The manifest does not match the manifest schema
+    pub const INVALID_MANIFEST: &str = "CADL020";
+    /// A required manifest field is empty
+    pub const EMPTY_REQUIRED_FIELD: &str = "CADL021";
+    /// Two build graph nodes share an ID
+    pub const DUPLICATE_NODE: &str = "CADL022";
+    /// A build graph edge names an unknown node
+    pub const UNKNOWN_EDGE_NODE: &str = "CADL023";
+    /// Two build targets share a name
+    pub const DUPLICATE_TARGET: &str = "CADL024";
+    /// A build target has no platform
+    pub const MISSING_PLATFORM: &str = "CADL025";
+    /// A build target names an unknown node
+    pub const UNKNOWN_TARGET_NODE: &str = "CADL026";
+    /// A node's `chunk_ref` is not `name@range`
+    pub const INVALID_CHUNK_REF: &str = "CADL027";
+    /// An `${env:...}` interpolation does not resolve
+    pub const UNRESOLVED_INTERPOLATION: &str = "CADL028";
+    /// `cadi.lock` does not match the manifest
+    pub const STALE_LOCKFILE: &str = "CADL029";
+    /// A build graph node has no source to build from
+    pub const NODE_WITHOUT_SOURCE: &str = "CADL030";
+    /// A build target builds no nodes
+    pub const EMPTY_TARGET: &str = "CADL031";
+    /// The manifest does not declare `cadl_version`
+    pub const MISSING_CADL_VERSION: &str = "CADL032";
+    /// The manifest declares a `cadl_version` this build does not know
+    pub const UNSUPPORTED_CADL_VERSION: &str = "CADL033";
+    /// The manifest declares an older `cadl_version`
+    pub const OUTDATED_CADL_VERSION: &str = "CADL034";
+    /// A field uses its name from another `cadl_version`
+    pub const RENAMED_FIELD: &str = "CADL035";
+    /// A build target transform names neither or both of a builtin and a
+    /// command, or leaves a representation empty
+    pub const INVALID_TRANSFORM: &str = "CADL036";
+}
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A validation finding
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub code: String,
+    pub severity: Severity,
     pub message: String,
+    /// Path of the offending element (`build_targets[0].platform`)
     pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<Span>,
 }
 
-pub struct Validator {
-    errors: Vec<ValidationError>,
+impl Diagnostic {
+    pub fn error(code: &str, message: impl Into<String>, path: impl Into<String>) -> Self {
+        Self::new(Severity::Error, code, message, path)
+    }
+
+    pub fn warning(code: &str, message: impl Into<String>, path: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, code, message, path)
+    }
+
+    fn new(severity: Severity, code: &str, message: impl Into<String>, path: impl Into<String>) -> Self {
+        Self {
+            code: code.to_string(),
+            severity,
+            message: message.into(),
+            path: path.into(),
+            span: None,
+        }
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
 }
 
-impl Default for Validator {
-    fn default() -> Self {
-        Self::new()
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.code, self.message)?;
+        match self.span {
+            Some(span) => write!(f, " at {}:{}", span.line, span.column),
+            None => write!(f, " at {}", self.path),
+        }
     }
 }
 
+/// Outcome of validating manifest source text
+#[derive(Debug, Clone)]
+pub struct ManifestReport {
+    /// The interpolated manifest, when it parsed
+    pub manifest: Option<Manifest>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ManifestReport {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(Diagnostic::is_error)
+    }
+}
+
+#[derive(Default)]
+pub struct Validator {
+    diagnostics: Vec<Diagnostic>,
+}
+
 impl Validator {
     pub fn new() -> Self {
-        Self { errors: Vec::new() }
+        Self::default()
     }
 
-    pub fn validate(mut self, doc: &CadlDocument) -> Result<(), Vec<ValidationError>> {
+    /// Every finding in a CADL document, with spans from its source map
+    pub fn diagnose(mut self, doc: &CadlDocument) -> Vec<Diagnostic> {
         self.doc(doc);
-        if self.errors.is_empty() {
-            Ok(())
-        } else {
-            Err(self.errors)
+        for diagnostic in &mut self.diagnostics {
+            diagnostic.span = source_map_span(&doc.source_map, &diagnostic.path);
         }
+        self.diagnostics
     }
 
-    fn push_error(&mut self, message: String, path: &str) {
-        self.errors.push(ValidationError {
-            message,
-            path: path.to_string(),
-        });
+    /// Fails with the error diagnostics, if there are any
+    pub fn validate(self, doc: &CadlDocument) -> Result<(), Vec<Diagnostic>> {
+        into_result(self.diagnose(doc))
+    }
+
+    /// Check that a manifest's required fields are set and that its edges
+    /// and build targets only reference nodes in the build graph
+    pub fn diagnose_manifest(mut self, manifest: &Manifest) -> Vec<Diagnostic> {
+        self.manifest(manifest);
+        self.diagnostics
+    }
+
+    /// Fails with the error diagnostics, if there are any
+    pub fn validate_manifest(self, manifest: &Manifest) -> Result<(), Vec<Diagnostic>> {
+        into_result(self.diagnose_manifest(manifest))
+    }
+
+    /// Parse YAML or JSON manifest source and check it against the rules of
+    /// its `cadl_version`: unknown and renamed fields, the manifest rules,
+    /// `${env:...}` interpolations and, when given, the lockfile. Diagnostics
+    /// carry the position of the field they refer to.
+    pub fn validate_manifest_source(self, source: &str, lock: Option<&ManifestLock>) -> ManifestReport {
+        let mut diagnostics = Vec::new();
+        let located = |e: &serde_yaml::Error, code: &str| match e.location() {
+            Some(loc) => {
+                let message = e.to_string();
+                let suffix = format!(" at line {} column {}", loc.line(), loc.column());
+                let message = message.strip_suffix(suffix.as_str()).unwrap_or(&message);
+                Diagnostic::error(code, message, "root")
+                    .with_span(Span { line: loc.line(), column: loc.column() })
+            }
+            None => Diagnostic::error(code, e.to_string(), "root"),
+        };
+
+        let value: serde_yaml::Value = match serde_yaml::from_str(source) {
+            Ok(value) => value,
+            Err(e) => {
+                return ManifestReport {
+                    manifest: None,
+                    diagnostics: vec![located(&e, codes::PARSE_ERROR)],
+                }
+            }
+        };
+        let declared = declared_cadl_version(&value);
+        match declared.as_deref() {
+            None => diagnostics.push(Diagnostic::warning(
+                codes::MISSING_CADL_VERSION,
+                format!(
+                    "No cadl_version; assuming {} (run `cadi validate --upgrade` to upgrade to {})",
+                    LEGACY_CADL_VERSION, CURRENT_CADL_VERSION
+                ),
+                "cadl_version",
+            )),
+            Some(version) if version != CURRENT_CADL_VERSION && SUPPORTED_CADL_VERSIONS.contains(&version) => {
+                diagnostics.push(Diagnostic::warning(
+                    codes::OUTDATED_CADL_VERSION,
+                    format!(
+                        "cadl_version {} is outdated (run `cadi validate --upgrade` to upgrade to {})",
+                        version, CURRENT_CADL_VERSION
+                    ),
+                    "cadl_version",
+                ))
+            }
+            Some(_) => {}
+        }
+        let version = declared.as_deref().unwrap_or(LEGACY_CADL_VERSION);
+        unknown_fields(&value, &MANIFEST_SCHEMA, "", version, &mut diagnostics);
+
+        let manifest = match serde_yaml::from_str::<Manifest>(source) {
+            Ok(manifest) => match manifest.interpolate_env() {
+                Ok(manifest) => Some(manifest),
+                Err(errors) => {
+                    diagnostics.extend(errors);
+                    None
+                }
+            },
+            Err(e) => {
+                diagnostics.push(located(&e, codes::INVALID_MANIFEST));
+                None
+            }
+        };
+
+        if let Some(manifest) = &manifest {
+            diagnostics.extend(self.diagnose_manifest(manifest));
+            if let Some(lock) = lock {
+                diagnostics.extend(lock.check(manifest));
+            }
+        }
+
+        let entries = yaml_entries(source);
+        for diagnostic in &mut diagnostics {
+            if diagnostic.span.is_none() {
+                diagnostic.span = locate_yaml(&entries, &diagnostic.path);
+            }
+        }
+
+        ManifestReport { manifest, diagnostics }
+    }
+
+    fn error(&mut self, code: &str, message: String, path: &str) {
+        self.diagnostics.push(Diagnostic::error(code, message, path));
+    }
+
+    fn warning(&mut self, code: &str, message: String, path: &str) {
+        self.diagnostics.push(Diagnostic::warning(code, message, path));
     }
 
     fn doc(&mut self, doc: &CadlDocument) {
         let mut interface_names = HashSet::new();
-        for iface in &doc.interfaces {
-            if !interface_names.insert(&iface.name) {
-                self.push_error(format!("Duplicate interface name: {}", iface.name), "root");
+        for (i, iface) in doc.interfaces.iter().enumerate() {
+            let path = format!("interfaces[{}]", i);
+            if !interface_names.insert(iface.name.as_str()) {
+                self.error(codes::DUPLICATE_INTERFACE, format!("Duplicate interface name: {}", iface.name), &path);
             }
-            self.interface(iface);
+            self.interface(iface, &path);
         }
 
         for (i, imp) in doc.implementations.iter().enumerate() {
-            self.implementation(imp, i);
+            self.implementation(imp, i, &interface_names);
         }
 
         for (i, con) in doc.constraints.iter().enumerate() {
@@ -56,52 +316,205 @@ impl Validator {
         }
     }
 
-    fn interface(&mut self, iface: &InterfaceDef) {
+    fn manifest(&mut self, manifest: &Manifest) {
+        if !SUPPORTED_CADL_VERSIONS.contains(&manifest.cadl_version.as_str()) {
+            self.error(
+                codes::UNSUPPORTED_CADL_VERSION,
+                format!(
+                    "Unsupported cadl_version {} (this build supports {})",
+                    manifest.cadl_version,
+                    SUPPORTED_CADL_VERSIONS.join(", ")
+                ),
+                "cadl_version",
+            );
+        }
+
+        let required = [
+            (&manifest.manifest_id, "Manifest ID is empty", "manifest_id"),
+            (&manifest.manifest_version, "Manifest version is empty", "manifest_version"),
+            (&manifest.application.name, "Application name is empty", "application.name"),
+        ];
+        for (value, message, path) in required {
+            if value.trim().is_empty() {
+                self.error(codes::EMPTY_REQUIRED_FIELD, message.to_string(), path);
+            }
+        }
+        let version = manifest.application.version.as_deref().unwrap_or("");
+        if cadl_version_at_least(&manifest.cadl_version, "2") && version.trim().is_empty() {
+            self.error(
+                codes::EMPTY_REQUIRED_FIELD,
+                format!("Application version is required since cadl_version 2 (this manifest declares {})", manifest.cadl_version),
+                "application.version",
+            );
+        }
+
+        let mut node_ids = HashSet::new();
+        for (i, node) in manifest.build_graph.nodes.iter().enumerate() {
+            let path = format!("build_graph.nodes[{}]", i);
+            if node.id.trim().is_empty() {
+                self.error(codes::EMPTY_REQUIRED_FIELD, "Node ID is empty".to_string(), &path);
+            } else if !node_ids.insert(node.id.as_str()) {
+                self.error(codes::DUPLICATE_NODE, format!("Duplicate node ID: {}", node.id), &path);
+            }
+            if let Some(chunk_ref) = &node.chunk_ref {
+                if let Err(e) = ChunkRef::parse(chunk_ref) {
+                    self.error(codes::INVALID_CHUNK_REF, e.to_string(), &format!("{}.chunk_ref", path));
+                }
+            }
+            let has_source = node.chunk_ref.is_some()
+                || node.source_cadi.is_some()
+                || node.ir_cadi.is_some()
+                || node.blob_cadi.is_some()
+                || node.container_cadi.is_some()
+                || !node.representations.is_empty();
+            if !has_source {
+                self.warning(
+                    codes::NODE_WITHOUT_SOURCE,
+                    format!("Node '{}' has no source yet (run `cadi import` or set chunk_ref)", node.id),
+                    &path,
+                );
+            }
+        }
+
+        for (i, edge) in manifest.build_graph.edges.iter().enumerate() {
+            let path = format!("build_graph.edges[{}]", i);
+            for end in [&edge.from, &edge.to] {
+                if !node_ids.contains(end.as_str()) {
+                    self.error(codes::UNKNOWN_EDGE_NODE, format!("Edge references unknown node: {}", end), &path);
+                }
+            }
+        }
+
+        let mut target_names = HashSet::new();
+        for (i, target) in manifest.build_targets.iter().enumerate() {
+            let path = format!("build_targets[{}]", i);
+            if target.name.trim().is_empty() {
+                self.error(codes::EMPTY_REQUIRED_FIELD, "Build target name is empty".to_string(), &path);
+            } else if !target_names.insert(target.name.as_str()) {
+                self.error(codes::DUPLICATE_TARGET, format!("Duplicate build target: {}", target.name), &path);
+            }
+            if target.platform.trim().is_empty() {
+                self.error(
+                    codes::MISSING_PLATFORM,
+                    format!("Build target '{}' has no platform", target.name),
+                    &format!("{}.platform", path),
+                );
+            }
+            if target.nodes.is_empty() {
+                self.warning(codes::EMPTY_TARGET, format!("Build target '{}' has no nodes", target.name), &path);
+            }
+            for node in &target.nodes {
+                if !node_ids.contains(node.id.as_str()) {
+                    self.error(
+                        codes::UNKNOWN_TARGET_NODE,
+                        format!("Build target '{}' references unknown node: {}", target.name, node.id),
+                        &path,
+                    );
+                }
+            }
+            for (j, transform) in target.transforms.iter().enumerate() {
+                let transform_path = format!("{}.transforms[{}]", path, j);
+                let problem = match (&transform.builtin, transform.command.is_empty()) {
+                    (Some(_), false) => Some("names both a builtin and a command"),
+                    (None, true) => Some("names neither a builtin nor a command"),
+                    _ if transform.input.trim().is_empty() || transform.output.trim().is_empty() => {
+                        Some("needs an input and an output representation")
+                    }
+                    _ => None,
+                };
+                if let Some(problem) = problem {
+                    self.error(
+                        codes::INVALID_TRANSFORM,
+                        format!("Transform {} of build target '{}' {}", j, target.name, problem),
+                        &transform_path,
+                    );
+                }
+            }
+        }
+    }
+
+    fn interface(&mut self, iface: &InterfaceDef, path: &str) {
         let mut method_names = HashSet::new();
-        for method in &iface.methods {
+        for (i, method) in iface.methods.iter().enumerate() {
+            let method_path = format!("{}.methods[{}]", path, i);
             if !method_names.insert(&method.name) {
-                self.push_error(format!("Duplicate method name: {}", method.name), &iface.name);
+                self.error(codes::DUPLICATE_METHOD, format!("Duplicate method name: {}", method.name), &method_path);
+            }
+            let mut param_names = HashSet::new();
+            for param in &method.params {
+                if !param_names.insert(&param.name) {
+                    self.error(
+                        codes::DUPLICATE_PARAMETER,
+                        format!("Duplicate parameter '{}' in method {}", param.name, method.name),
+                        &method_path,
+                    );
+                }
             }
         }
 
-        for ann in &iface.annotations {
+        for (i, ann) in iface.annotations.iter().enumerate() {
+            let context = format!("{}.annotations[{}]", path, i);
+            let context = context.as_str();
             match ann {
-                Annotation::Contract(c) => self.contract(c, &iface.name),
-                Annotation::Effects(e) => self.effects(e, &iface.name),
-                Annotation::Permissions(p) => self.permissions(p, &iface.name),
-                Annotation::Resources(r) => self.resources(r, &iface.name),
-                Annotation::DataFormat(df) => self.data_format(df, &iface.name),
-                Annotation::Protocol(proto) => self.protocol(proto, &iface.name),
-                Annotation::Numerical(num) => self.numerical(num, &iface.name),
-                Annotation::Observability(obs) => self.observability(obs, &iface.name),
-                Annotation::Abi(abi) => self.abi(abi, &iface.name),
+                Annotation::Contract(c) => self.contract(c, context),
+                Annotation::Effects(e) => self.effects(e, context),
+                Annotation::Permissions(p) => self.permissions(p, context),
+                Annotation::Resources(r) => self.resources(r, context),
+                Annotation::DataFormat(df) => self.data_format(df, context),
+                Annotation::Protocol(proto) => self.protocol(proto, context),
+                Annotation::Numerical(num) => self.numerical(num, context),
+                Annotation::Observability(obs) => self.observability(obs, context),
+                Annotation::Abi(abi) => self.abi(abi, context),
                 Annotation::Unknown(name, _) => {
-                    // Unknown annotations at the interface level might be fine, but we can warn/error if desired.
-                    tracing::debug!("Unknown annotation: {} on interface {}", name, iface.name);
+                    self.warning(
+                        codes::UNKNOWN_ANNOTATION,
+                        format!("Unknown annotation @{} on interface {}", name, iface.name),
+                        context,
+                    );
                 }
             }
         }
     }
 
-    fn implementation(&mut self, imp: &ImplDef, index: usize) {
+    fn implementation(&mut self, imp: &ImplDef, index: usize, interfaces: &HashSet<&str>) {
+        let path = format!("implementations[{}]", index);
         if imp.name.is_empty() {
-             self.push_error(format!("Implementation at index {} has no name", index), "implementations");
+            self.error(codes::MISSING_IMPL_NAME, format!("Implementation at index {} has no name", index), &path);
+            return;
         }
         // Check if it follows InterfaceName.ImplName format
-        if !imp.name.contains('.') {
-            self.push_error(format!("Implementation name '{}' should be in 'Interface.Implementation' format", imp.name), &imp.name);
+        match imp.name.split_once('.') {
+            None => self.warning(
+                codes::IMPL_NAME_FORMAT,
+                format!("Implementation name '{}' should be in 'Interface.Implementation' format", imp.name),
+                &path,
+            ),
+            Some((interface, _)) if !interfaces.is_empty() && !interfaces.contains(interface) => self.warning(
+                codes::MISSING_PROVIDES,
+                format!("Implementation '{}' provides interface '{}', which is not defined", imp.name, interface),
+                &path,
+            ),
+            Some(_) => {}
         }
     }
 
     fn constraint(&mut self, con: &ConstraintDef, index: usize) {
         if con.rules.is_empty() && con.other.is_empty() {
-            self.push_error(format!("Constraint at index {} is empty", index), "constraints");
+            self.warning(
+                codes::EMPTY_CONSTRAINT,
+                format!("Constraint at index {} is empty", index),
+                &format!("constraints[{}]", index),
+            );
         }
     }
 
     fn contract(&mut self, c: &ContractDef, context: &str) {
         if c.codec.is_none() && c.other.is_empty() {
-            self.push_error("Contract missing descriptive fields (codec, etc.)".to_string(), context);
+            self.error(
+                codes::EMPTY_CONTRACT,
+                "Contract missing descriptive fields (codec, etc.)".to_string(),
+                context,
+            );
         }
     }
 
@@ -109,22 +522,26 @@ impl Validator {
         if let Some(c) = &e.concurrency {
             let valid = ["thread_safe", "single_threaded", "immutable", "reentrant"];
             if !valid.contains(&c.as_str()) {
-                 self.push_error(format!("Invalid concurrency value: {}", c), context);
+                self.error(
+                    codes::INVALID_CONCURRENCY,
+                    format!("Invalid concurrency value: {} (expected one of {})", c, valid.join(", ")),
+                    context,
+                );
             }
         }
     }
 
     fn permissions(&mut self, p: &PermissionsDef, context: &str) {
-         if p.allow.is_empty() && p.deny.is_empty() && p.sandbox.is_empty() {
-              self.push_error("Permissions annotation is empty".to_string(), context);
-         }
+        if p.allow.is_empty() && p.deny.is_empty() && p.sandbox.is_empty() {
+            self.warning(codes::EMPTY_PERMISSIONS, "Permissions annotation is empty".to_string(), context);
+        }
     }
 
     fn resources(&mut self, r: &ResourcesDef, context: &str) {
         for (k, v) in &r.memory {
-             if k == "peak" && v.is_empty() {
-                 self.push_error("Resource memory peak cannot be empty".to_string(), context);
-             }
+            if k == "peak" && v.is_empty() {
+                self.error(codes::EMPTY_RESOURCE, "Resource memory peak cannot be empty".to_string(), context);
+            }
         }
     }
 
@@ -134,11 +551,19 @@ impl Validator {
 
     fn protocol(&mut self, proto: &ProtocolDef, context: &str) {
         if !proto.states.is_empty() && proto.initial.is_none() {
-            self.push_error("Protocol with states must have an initial state".to_string(), context);
+            self.error(
+                codes::MISSING_INITIAL_STATE,
+                "Protocol with states must have an initial state".to_string(),
+                context,
+            );
         }
         if let Some(initial) = &proto.initial {
             if !proto.states.contains(initial) {
-                self.push_error(format!("Initial state '{}' not found in states list", initial), context);
+                self.error(
+                    codes::UNKNOWN_INITIAL_STATE,
+                    format!("Initial state '{}' not found in states list", initial),
+                    context,
+                );
             }
         }
     }
@@ -155,9 +580,300 @@ impl Validator {
         if let Some(encoding) = &abi.string_encoding {
             let valid = ["utf8", "utf16", "ascii", "cesu8"];
             if !valid.contains(&encoding.as_str()) {
-                self.push_error(format!("Invalid string encoding: {}", encoding), context);
+                self.error(
+                    codes::INVALID_STRING_ENCODING,
+                    format!("Invalid string encoding: {} (expected one of {})", encoding, valid.join(", ")),
+                    context,
+                );
             }
         }
     }
 }
 
+fn into_result(diagnostics: Vec<Diagnostic>) -> Result<(), Vec<Diagnostic>> {
+    let errors: Vec<Diagnostic> = diagnostics.into_iter().filter(Diagnostic::is_error).collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Span of the closest element on `path` that the parser recorded
+fn source_map_span(source_map: &HashMap<String, Span>, path: &str) -> Option<Span> {
+    let mut path = path;
+    loop {
+        if let Some(span) = source_map.get(path) {
+            return Some(*span);
+        }
+        path = &path[..path.rfind('.')?];
+    }
+}
+
+/// Fields of the manifest structs, for reporting keys serde would ignore
+enum Schema {
+    Fields(&'static [(&'static str, Schema)]),
+    List(&'static Schema),
+    Any,
+}
+
+const APPLICATION_SCHEMA: Schema = Schema::Fields(&[
+    ("name", Schema::Any),
+    ("description", Schema::Any),
+    ("version", Schema::Any),
+    ("authors", Schema::Any),
+    ("license", Schema::Any),
+    ("repository", Schema::Any),
+    ("namespace", Schema::Any),
+]);
+
+const NODE_SCHEMA: Schema = Schema::Fields(&[
+    ("id", Schema::Any),
+    ("chunk_ref", Schema::Any),
+    ("source_cadi", Schema::Any),
+    ("ir_cadi", Schema::Any),
+    ("blob_cadi", Schema::Any),
+    ("container_cadi", Schema::Any),
+    (
+        "representations",
+        Schema::List(&Schema::Fields(&[
+            ("form", Schema::Any),
+            ("language", Schema::Any),
+            ("format", Schema::Any),
+            ("architecture", Schema::Any),
+            ("chunk", Schema::Any),
+        ])),
+    ),
+    ("selection", Schema::Any),
+    (
+        "materialization",
+        Schema::Fields(&[
+            ("preferred", Schema::Any),
+            ("fallbacks", Schema::Any),
+            ("transformations", Schema::Any),
+        ]),
+    ),
+]);
+
+const EDGE_SCHEMA: Schema = Schema::Fields(&[
+    ("from", Schema::Any),
+    ("to", Schema::Any),
+    ("interface", Schema::Any),
+    ("relation", Schema::Any),
+]);
+
+const TRUST_SCHEMA: Schema = Schema::Fields(&[
+    ("minimum_signatures", Schema::Any),
+    ("required_attestation_types", Schema::Any),
+    ("required_signers", Schema::Any),
+    ("max_age_days", Schema::Any),
+    (
+        "transitive_policy",
+        Schema::Fields(&[("inherit", Schema::Any), ("allow_weaker", Schema::Any)]),
+    ),
+]);
+
+const TARGET_SCHEMA: Schema = Schema::Fields(&[
+    ("name", Schema::Any),
+    ("platform", Schema::Any),
+    (
+        "nodes",
+        Schema::List(&Schema::Fields(&[
+            ("id", Schema::Any),
+            ("require", Schema::Any),
+            ("prefer", Schema::Any),
+        ])),
+    ),
+    (
+        "bundle",
+        Schema::Fields(&[("format", Schema::Any), ("output", Schema::Any), ("minify", Schema::Any)]),
+    ),
+    (
+        "deploy",
+        Schema::Fields(&[("target", Schema::Any), ("replicas", Schema::Any), ("environment", Schema::Any)]),
+    ),
+    ("trust_requirements", TRUST_SCHEMA),
+    (
+        "transforms",
+        Schema::List(&Schema::Fields(&[
+            ("builtin", Schema::Any),
+            ("args", Schema::Any),
+            ("command", Schema::Any),
+            ("input", Schema::Any),
+            ("output", Schema::Any),
+            ("env", Schema::Any),
+            ("exit_code", Schema::Any),
+        ])),
+    ),
+    ("toolchain", Schema::Any),
+]);
+
+/// Field names of the current `cadl_version`; [`unknown_fields`] maps the
+/// names older versions used
+const MANIFEST_SCHEMA: Schema = Schema::Fields(&[
+    ("cadl_version", Schema::Any),
+    ("manifest_id", Schema::Any),
+    ("manifest_version", Schema::Any),
+    ("application", APPLICATION_SCHEMA),
+    (
+        "build_graph",
+        Schema::Fields(&[("nodes", Schema::List(&NODE_SCHEMA)), ("edges", Schema::List(&EDGE_SCHEMA))]),
+    ),
+    ("build_targets", Schema::List(&TARGET_SCHEMA)),
+    ("trust", TRUST_SCHEMA),
+    (
+        "dependencies",
+        Schema::Fields(&[("lock_file", Schema::Any), ("resolution", Schema::Any)]),
+    ),
+]);
+
+/// Report keys serde would ignore and keys named as in another `version`
+fn unknown_fields(value: &serde_yaml::Value, schema: &Schema, path: &str, version: &str, out: &mut Vec<Diagnostic>) {
+    match (schema, value) {
+        (Schema::Fields(fields), serde_yaml::Value::Mapping(map)) => {
+            for (key, child) in map {
+                let Some(key) = key.as_str() else { continue };
+                let child_path = if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+                let renamed = renamed_field(path, key);
+                // Files older than a rename still use the old name
+                let name = match renamed {
+                    Some((migration, rename)) if key == rename.from && !cadl_version_at_least(version, migration.to) => {
+                        rename.to
+                    }
+                    _ => key,
+                };
+                match fields.iter().find(|(field, _)| *field == name) {
+                    Some((_, child_schema)) => {
+                        if let Some((migration, rename)) = renamed {
+                            if key == rename.to && !cadl_version_at_least(version, migration.to) {
+                                let message = format!(
+                                    "`{}` is the cadl_version {} name of `{}`; this manifest declares cadl_version {}",
+                                    key, migration.to, rename.from, version
+                                );
+                                out.push(Diagnostic::warning(codes::RENAMED_FIELD, message, child_path.clone()));
+                            }
+                        }
+                        unknown_fields(child, child_schema, &child_path, version, out)
+                    }
+                    None => {
+                        let (code, message) = match renamed {
+                            Some((migration, rename)) if key == rename.from => (
+                                codes::RENAMED_FIELD,
+                                format!("`{}` was renamed to `{}` in cadl_version {}", key, rename.to, migration.to),
+                            ),
+                            _ => {
+                                let closest = fields
+                                    .iter()
+                                    .map(|(name, _)| (*name, DeduplicationEngine::levenshtein_similarity(key, name)))
+                                    .filter(|(_, score)| *score >= 0.6)
+                                    .max_by(|a, b| a.1.total_cmp(&b.1));
+                                let message = match closest {
+                                    Some((name, _)) => format!("Unknown field `{}` (did you mean `{}`?)", key, name),
+                                    None => format!("Unknown field `{}`", key),
+                                };
+                                (codes::UNKNOWN_FIELD, message)
+                            }
+                        };
+                        out.push(Diagnostic::warning(code, message, child_path));
+                    }
+                }
+            }
+        }
+        (Schema::List(item), serde_yaml::Value::Sequence(items)) => {
+            for (i, child) in items.iter().enumerate() {
+                unknown_fields(child, item, &format!("{}[{}]", path, i), version, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// A mapping key or sequence item in block-style YAML
+pub(crate) struct YamlEntry {
+    /// Zero-based line and column
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+    pub(crate) key: Option<String>,
+}
+
+/// Keys and sequence items of block-style YAML, in source order. Flow
+/// style (including JSON) yields no entries.
+pub(crate) fn yaml_entries(source: &str) -> Vec<YamlEntry> {
+    let mut entries = Vec::new();
+
+    for (line, text) in source.lines().enumerate() {
+        let mut column = text.len() - text.trim_start().len();
+        let mut rest = text.trim_start();
+        if rest.is_empty() || rest.starts_with('#') || rest.starts_with("---") {
+            continue;
+        }
+
+        while rest == "-" || rest.starts_with("- ") {
+            entries.push(YamlEntry { line, column, key: None });
+            let after = &rest[1..];
+            column += 1 + after.len() - after.trim_start().len();
+            rest = after.trim_start();
+        }
+
+        let key = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => rest[1..].find(quote).map(|end| (&rest[1..end + 1], end + 2)),
+            _ => rest.find(':').map(|end| (rest[..end].trim_end(), end)),
+        };
+        if let Some((key, end)) = key {
+            let after = &rest[end..];
+            if after == ":" || after.starts_with(": ") || after.starts_with(":\t") {
+                entries.push(YamlEntry { line, column, key: Some(key.to_string()) });
+            }
+        }
+    }
+
+    entries
+}
+
+/// Position of the entry at `path` (`build_targets[0].platform`), or of its
+/// closest ancestor when the path goes deeper than the source
+pub(crate) fn locate_yaml(entries: &[YamlEntry], path: &str) -> Option<Span> {
+    let mut segments = Vec::new();
+    for part in path.split('.').filter(|p| !p.is_empty() && *p != "root") {
+        let (key, indices) = part.split_once('[').map_or((part, ""), |(k, rest)| (k, rest));
+        if !key.is_empty() {
+            segments.push(Err(key));
+        }
+        for index in indices.split('[') {
+            if let Ok(index) = index.trim_end_matches(']').parse::<usize>() {
+                segments.push(Ok(index));
+            }
+        }
+    }
+
+    let mut range = 0..entries.len();
+    let mut found = None;
+
+    for segment in segments {
+        let candidates = || range.clone().filter(|&i| entries[i].key.is_some() == segment.is_err());
+        let Some(level) = candidates().map(|i| entries[i].column).min() else { break };
+        let at_level = candidates().filter(|&i| entries[i].column == level);
+        let hit = match segment {
+            Err(key) => at_level.into_iter().find(|&i| entries[i].key.as_deref() == Some(key)),
+            Ok(index) => at_level.into_iter().nth(index),
+        };
+        let Some(hit) = hit else { break };
+
+        // A key's value may be a sequence at the key's own indentation, so
+        // only a sibling key ends it; an item ends at the next item
+        let is_key = segment.is_err();
+        let end = (hit + 1..range.end)
+            .find(|&i| {
+                let entry = &entries[i];
+                entry.column < level || (entry.column == level && (!is_key || entry.key.is_some()))
+            })
+            .unwrap_or(range.end);
+        found = Some(Span {
+            line: entries[hit].line + 1,
+            column: entries[hit].column + 1,
+        });
+        range = hit + 1..end;
+    }
+
+    found
+}
@@ -0,0 +1,383 @@
+//! Workspace (monorepo) detection
+//!
+//! Finds the member packages of a Cargo workspace (`[workspace]` in the
+//! root `Cargo.toml`), an npm/yarn workspace (`workspaces` in the root
+//! `package.json`) or a pnpm workspace (`pnpm-workspace.yaml`), and which
+//! members depend on each other through path dependencies or the
+//! `workspace:` protocol.
+
+use crate::gitignore::glob_match;
+use crate::smart_chunker::to_kebab_case;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// The tool a workspace is declared for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkspaceKind {
+    Cargo,
+    /// `workspaces` in `package.json` (npm and yarn)
+    Npm,
+    Pnpm,
+}
+
+/// A package in a workspace
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceMember {
+    /// Package name from the member's manifest
+    pub name: String,
+    /// Directory relative to the workspace root (empty for a root package)
+    pub path: PathBuf,
+    /// Names of the other members this one depends on
+    pub dependencies: Vec<String>,
+}
+
+impl WorkspaceMember {
+    /// Alias segment for the member: its name in kebab-case, with an npm
+    /// scope folded in (`@acme/ui` -> `acme-ui`)
+    pub fn alias_segment(&self) -> String {
+        to_kebab_case(&self.name.trim_start_matches('@').replace('/', "-"))
+    }
+}
+
+/// A detected workspace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workspace {
+    pub kind: WorkspaceKind,
+    pub members: Vec<WorkspaceMember>,
+}
+
+/// A dependency as declared in a member's manifest
+struct DeclaredDependency {
+    name: String,
+    /// Directory of a path dependency, relative to the workspace root
+    path: Option<PathBuf>,
+}
+
+/// A member and the dependencies its manifest declares
+type MemberManifest = (WorkspaceMember, Vec<DeclaredDependency>);
+
+impl Workspace {
+    /// Detect the workspace rooted at `root`, if any. Directories named in
+    /// `ignore_dirs` are not searched for members.
+    pub fn detect(root: &Path, ignore_dirs: &[String]) -> Option<Self> {
+        let (kind, mut members) = cargo_members(root, ignore_dirs)
+            .map(|m| (WorkspaceKind::Cargo, m))
+            .or_else(|| pnpm_members(root, ignore_dirs).map(|m| (WorkspaceKind::Pnpm, m)))
+            .or_else(|| npm_members(root, ignore_dirs).map(|m| (WorkspaceKind::Npm, m)))?;
+        if members.is_empty() {
+            return None;
+        }
+        members.sort_by(|a, b| a.0.path.cmp(&b.0.path));
+
+        // Resolve declared dependencies to members, by path or by name
+        let names: HashSet<String> = members.iter().map(|(m, _)| m.name.clone()).collect();
+        let by_path: Vec<(PathBuf, String)> = members.iter().map(|(m, _)| (m.path.clone(), m.name.clone())).collect();
+        let members = members
+            .into_iter()
+            .map(|(mut member, declared)| {
+                for dependency in declared {
+                    let target = match &dependency.path {
+                        Some(path) => by_path.iter().find(|(p, _)| p == path).map(|(_, n)| n.clone()),
+                        None => names.contains(&dependency.name).then_some(dependency.name),
+                    };
+                    if let Some(target) = target {
+                        if target != member.name && !member.dependencies.contains(&target) {
+                            member.dependencies.push(target);
+                        }
+                    }
+                }
+                member
+            })
+            .collect();
+
+        Some(Self { kind, members })
+    }
+
+    /// The member containing `relative` (a path relative to the workspace
+    /// root); the innermost one when members are nested
+    pub fn member_for(&self, relative: &Path) -> Option<&WorkspaceMember> {
+        self.members
+            .iter()
+            .filter(|m| relative.starts_with(&m.path))
+            .max_by_key(|m| m.path.components().count())
+    }
+}
+
+/// Members of a Cargo workspace: the `members` globs minus `exclude`, plus
+/// the root package when the root manifest has one
+fn cargo_members(root: &Path, ignore_dirs: &[String]) -> Option<Vec<MemberManifest>> {
+    let manifest: toml::Value = toml::from_str(&std::fs::read_to_string(root.join("Cargo.toml")).ok()?).ok()?;
+    let workspace = manifest.get("workspace")?;
+    let patterns = |key: &str| -> Vec<String> {
+        workspace
+            .get(key)
+            .and_then(|v| v.as_array())
+            .map(|a| a.iter().filter_map(|p| p.as_str().map(str::to_string)).collect())
+            .unwrap_or_default()
+    };
+    let workspace_deps = workspace.get("dependencies").and_then(|d| d.as_table());
+
+    let mut dirs = expand(root, &patterns("members"), &patterns("exclude"), "Cargo.toml", ignore_dirs);
+    if manifest.get("package").is_some() && !dirs.contains(&PathBuf::new()) {
+        dirs.push(PathBuf::new());
+    }
+
+    let mut members = Vec::new();
+    for dir in dirs {
+        let Some(member) = std::fs::read_to_string(root.join(&dir).join("Cargo.toml"))
+            .ok()
+            .and_then(|s| toml::from_str::<toml::Value>(&s).ok())
+        else {
+            continue;
+        };
+        let Some(name) = member.get("package").and_then(|p| p.get("name")).and_then(|n| n.as_str()) else {
+            continue;
+        };
+
+        let mut declared = Vec::new();
+        for table in ["dependencies", "dev-dependencies", "build-dependencies"] {
+            let Some(deps) = member.get(table).and_then(|d| d.as_table()) else {
+                continue;
+            };
+            for (key, spec) in deps {
+                // `dep = { workspace = true }` takes its path, relative to
+                // the root, from `[workspace.dependencies]`
+                let (spec, base) = match spec.get("workspace").and_then(|w| w.as_bool()) {
+                    Some(true) => (workspace_deps.and_then(|w| w.get(key)).unwrap_or(spec), Path::new("")),
+                    _ => (spec, dir.as_path()),
+                };
+                let Some(path) = spec.get("path").and_then(|p| p.as_str()) else {
+                    continue;
+                };
+                declared.push(DeclaredDependency {
+                    name: spec.get("package").and_then(|p| p.as_str()).unwrap_or(key).to_string(),
+                    path: Some(normalize(&base.join(path))),
+                });
+            }
+        }
+
+        members.push((
+            WorkspaceMember { name: name.to_string(), path: dir, dependencies: Vec::new() },
+            declared,
+        ));
+    }
+    Some(members)
+}
+
+/// Members of a pnpm workspace: the `packages` globs, with `!` negations
+fn pnpm_members(root: &Path, ignore_dirs: &[String]) -> Option<Vec<MemberManifest>> {
+    let config: serde_yaml::Value =
+        serde_yaml::from_str(&std::fs::read_to_string(root.join("pnpm-workspace.yaml")).ok()?).ok()?;
+    let patterns: Vec<String> = config
+        .get("packages")
+        .and_then(|p| p.as_sequence())
+        .map(|s| s.iter().filter_map(|p| p.as_str().map(str::to_string)).collect())
+        .unwrap_or_default();
+    Some(package_json_members(root, &patterns, ignore_dirs))
+}
+
+/// Members of an npm or yarn workspace: `workspaces` in `package.json`, as
+/// an array or as `{ "packages": [...] }`
+fn npm_members(root: &Path, ignore_dirs: &[String]) -> Option<Vec<MemberManifest>> {
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(root.join("package.json")).ok()?).ok()?;
+    let workspaces = manifest.get("workspaces")?;
+    let patterns: Vec<String> = workspaces
+        .as_array()
+        .or_else(|| workspaces.get("packages").and_then(|p| p.as_array()))?
+        .iter()
+        .filter_map(|p| p.as_str().map(str::to_string))
+        .collect();
+    Some(package_json_members(root, &patterns, ignore_dirs))
+}
+
+fn package_json_members(
+    root: &Path,
+    patterns: &[String],
+    ignore_dirs: &[String],
+) -> Vec<MemberManifest> {
+    let (excludes, includes): (Vec<String>, Vec<String>) = patterns.iter().cloned().partition(|p| p.starts_with('!'));
+    let excludes: Vec<String> = excludes.iter().map(|p| p[1..].to_string()).collect();
+
+    let mut members = Vec::new();
+    for dir in expand(root, &includes, &excludes, "package.json", ignore_dirs) {
+        let Some(manifest) = std::fs::read_to_string(root.join(&dir).join("package.json"))
+            .ok()
+            .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok())
+        else {
+            continue;
+        };
+        let Some(name) = manifest.get("name").and_then(|n| n.as_str()) else {
+            continue;
+        };
+
+        let mut declared = Vec::new();
+        for field in ["dependencies", "devDependencies", "peerDependencies", "optionalDependencies"] {
+            let Some(deps) = manifest.get(field).and_then(|d| d.as_object()) else {
+                continue;
+            };
+            for (dep, spec) in deps {
+                let spec = spec.as_str().unwrap_or_default();
+                // `file:` and `link:` name a directory; anything else (a
+                // `workspace:` range or a version) links to a member by name
+                let path = spec
+                    .strip_prefix("file:")
+                    .or_else(|| spec.strip_prefix("link:"))
+                    .map(|p| normalize(&dir.join(p)));
+                declared.push(DeclaredDependency { name: dep.clone(), path });
+            }
+        }
+
+        members.push((
+            WorkspaceMember { name: name.to_string(), path: dir, dependencies: Vec::new() },
+            declared,
+        ));
+    }
+    members
+}
+
+/// Directories under `root` containing `manifest` that match one of
+/// `includes` and none of `excludes`
+fn expand(root: &Path, includes: &[String], excludes: &[String], manifest: &str, ignore_dirs: &[String]) -> Vec<PathBuf> {
+    let compile = |patterns: &[String]| -> Vec<Vec<char>> {
+        patterns
+            .iter()
+            .map(|p| p.trim_start_matches("./").trim_end_matches('/').chars().collect())
+            .collect()
+    };
+    let includes = compile(includes);
+    let excludes = compile(excludes);
+
+    let mut found = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(dir) = pending.pop() {
+        let text: Vec<char> = dir.to_string_lossy().replace('\\', "/").chars().collect();
+        if includes.iter().any(|p| glob_match(p, &text))
+            && !excludes.iter().any(|p| glob_match(p, &text))
+            && root.join(&dir).join(manifest).is_file()
+        {
+            found.push(dir.clone());
+        }
+
+        let Ok(entries) = std::fs::read_dir(root.join(&dir)) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if entry.file_type().is_ok_and(|t| t.is_dir())
+                && !name.starts_with('.')
+                && !ignore_dirs.contains(&name)
+            {
+                pending.push(dir.join(name));
+            }
+        }
+    }
+    found.sort();
+    found
+}
+
+/// Resolve `.` and `..` components lexically
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("cadi-workspace-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        root
+    }
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_cargo_workspace_members_and_path_dependencies() {
+        let root = &temp_root("cargo");
+        write(
+            root,
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"crates/*\"]\nexclude = [\"crates/scratch\"]\n\n\
+             [workspace.dependencies]\ncore = { path = \"crates/core\" }\n",
+        );
+        write(root, "crates/core/Cargo.toml", "[package]\nname = \"core\"\n");
+        write(
+            root,
+            "crates/cli/Cargo.toml",
+            "[package]\nname = \"cli\"\n\n[dependencies]\ncore.workspace = true\nserde = \"1\"\n\n\
+             [dev-dependencies]\nfixtures = { path = \"../../fixtures\" }\n",
+        );
+        write(root, "crates/app/Cargo.toml", "[package]\nname = \"app\"\n\n[dependencies]\nmy-cli = { path = \"../cli\", package = \"cli\" }\n");
+        write(root, "crates/scratch/Cargo.toml", "[package]\nname = \"scratch\"\n");
+        write(root, "target/debug/Cargo.toml", "[package]\nname = \"built\"\n");
+
+        let workspace = Workspace::detect(root, &["target".to_string()]).unwrap();
+        assert_eq!(workspace.kind, WorkspaceKind::Cargo);
+        let members: Vec<(&str, &Path, &[String])> = workspace
+            .members
+            .iter()
+            .map(|m| (m.name.as_str(), m.path.as_path(), m.dependencies.as_slice()))
+            .collect();
+        assert_eq!(
+            members,
+            [
+                ("app", Path::new("crates/app"), &["cli".to_string()][..]),
+                ("cli", Path::new("crates/cli"), &["core".to_string()][..]),
+                ("core", Path::new("crates/core"), &[][..]),
+            ]
+        );
+        assert_eq!(workspace.member_for(Path::new("crates/cli/src/main.rs")).unwrap().name, "cli");
+        assert!(workspace.member_for(Path::new("build.rs")).is_none());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_pnpm_and_npm_workspaces() {
+        let root = &temp_root("js");
+        write(root, "pnpm-workspace.yaml", "packages:\n  - 'packages/**'\n  - '!packages/**/test'\n");
+        write(root, "packages/ui/package.json", r#"{"name": "@acme/ui", "dependencies": {"@acme/utils": "workspace:^"}}"#);
+        write(root, "packages/utils/package.json", r#"{"name": "@acme/utils", "devDependencies": {"left-pad": "1.0.0"}}"#);
+        write(root, "packages/utils/test/package.json", r#"{"name": "utils-test"}"#);
+        write(root, "packages/ui/node_modules/x/package.json", r#"{"name": "x"}"#);
+
+        let ignore = vec!["node_modules".to_string()];
+        let workspace = Workspace::detect(root, &ignore).unwrap();
+        assert_eq!(workspace.kind, WorkspaceKind::Pnpm);
+        let names: Vec<&str> = workspace.members.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["@acme/ui", "@acme/utils"]);
+        assert_eq!(workspace.members[0].dependencies, ["@acme/utils"]);
+        assert!(workspace.members[1].dependencies.is_empty());
+        assert_eq!(workspace.members[0].alias_segment(), "acme-ui");
+
+        // The same layout declared in package.json, linked by a file: path
+        std::fs::remove_file(root.join("pnpm-workspace.yaml")).unwrap();
+        write(root, "package.json", r#"{"name": "acme", "workspaces": {"packages": ["packages/*"]}}"#);
+        write(root, "packages/ui/package.json", r#"{"name": "@acme/ui", "dependencies": {"utils": "file:../utils"}}"#);
+        let workspace = Workspace::detect(root, &ignore).unwrap();
+        assert_eq!(workspace.kind, WorkspaceKind::Npm);
+        assert_eq!(workspace.members[0].dependencies, ["@acme/utils"]);
+
+        assert!(Workspace::detect(&root.join("packages/ui"), &ignore).is_none());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,9 @@
+import { formatDate } from './utils';
+import { Header } from './components';
+import { percent } from './lib/math';
+import { missing } from './does-not-exist';
+import React from 'react';
+
+export function main(): void {
+  console.log(Header('app'), formatDate(new Date()), percent(42));
+}
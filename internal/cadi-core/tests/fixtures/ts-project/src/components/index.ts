@@ -0,0 +1,5 @@
+import { formatDate } from '../utils';
+
+export function Header(title: string): string {
+  return `${title} (${formatDate(new Date())})`;
+}
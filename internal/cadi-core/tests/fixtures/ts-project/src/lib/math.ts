@@ -0,0 +1,5 @@
+import { clamp } from '../../src/utils';
+
+export function percent(value: number): number {
+  return clamp(value, 0, 100);
+}
@@ -0,0 +1,7 @@
+export function formatDate(date: Date): string {
+  return date.toISOString().slice(0, 10);
+}
+
+export function clamp(value: number, min: number, max: number): number {
+  return Math.min(Math.max(value, min), max);
+}
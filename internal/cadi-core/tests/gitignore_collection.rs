@@ -0,0 +1,73 @@
+use std::path::{Path, PathBuf};
+
+use cadi_core::{ProjectAnalyzer, ProjectAnalyzerConfig};
+
+/// Build a fixture tree in a temp dir. It is generated rather than checked
+/// in because its own `.gitignore` files would keep parts of it out of git.
+fn fixture_tree(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("cadi-gitignore-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+
+    let files = [
+        (".gitignore", "# third-party and generated code\nvendor/\ngenerated/*.rs\n!keep.rs\n"),
+        ("src/main.rs", "fn main() {}\n"),
+        ("src/scratch.rs", "fn scratch() {}\n"),
+        ("src/.gitignore", "scratch.rs\n"),
+        ("vendor/dep/lib.rs", "pub fn dep() {}\n"),
+        ("generated/proto.rs", "pub struct Message;\n"),
+        ("generated/keep.rs", "pub struct Kept;\n"),
+        ("tools/codegen.rs", "fn codegen() {}\n"),
+    ];
+    for (path, content) in files {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    root
+}
+
+fn collected(root: &Path, config: ProjectAnalyzerConfig) -> Vec<String> {
+    let analysis = ProjectAnalyzer::new(config).analyze_project(root).unwrap();
+    let mut files: Vec<String> = analysis
+        .files
+        .iter()
+        .map(|f| f.path.strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/"))
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn gitignore_rules_skip_files_and_directories() {
+    let root = fixture_tree("respect");
+    let files = collected(&root, ProjectAnalyzerConfig::default());
+
+    assert_eq!(files, vec!["generated/keep.rs", "src/main.rs", "tools/codegen.rs"]);
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn extra_ignores_apply_without_gitignore() {
+    let root = fixture_tree("extra");
+    let config = ProjectAnalyzerConfig {
+        respect_gitignore: false,
+        extra_ignores: vec!["tools/".to_string()],
+        ..Default::default()
+    };
+    let files = collected(&root, config);
+
+    assert_eq!(
+        files,
+        vec![
+            "generated/keep.rs",
+            "generated/proto.rs",
+            "src/main.rs",
+            "src/scratch.rs",
+            "vendor/dep/lib.rs",
+        ]
+    );
+
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use cadi_core::atomizer::{AtomExtractor, AtomKind, AtomizerConfig, SymbolResolver};
+use cadi_core::chunk_id_from_content;
+use cadi_core::graph::GraphStore;
+
+fn fixture_root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/ts-project")
+}
+
+/// Atomize every fixture file and register its atoms with the resolver
+fn atomize_fixture(resolver: &mut SymbolResolver) -> HashMap<String, String> {
+    let mut ids = HashMap::new();
+    let extractor = AtomExtractor::new("typescript", AtomizerConfig::default());
+
+    for file in ["src/utils.ts", "src/components/index.ts", "src/lib/math.ts", "src/app.ts"] {
+        let source = std::fs::read_to_string(fixture_root().join(file)).unwrap();
+        let atoms = extractor.extract(&source).unwrap();
+
+        let symbols: Vec<(String, String)> = atoms
+            .iter()
+            .filter(|a| a.kind != AtomKind::Import)
+            .map(|a| (a.name.clone(), chunk_id_from_content(a.source.as_bytes())))
+            .collect();
+        ids.extend(symbols.iter().cloned());
+        resolver.register_file(Path::new(file), symbols);
+    }
+
+    ids
+}
+
+fn resolve(resolver: &mut SymbolResolver, file: &str, graph: &GraphStore) -> Vec<cadi_core::atomizer::ResolvedImport> {
+    let source = std::fs::read_to_string(fixture_root().join(file)).unwrap();
+    let raw = resolver.extract_imports(&source);
+    resolver.resolve_imports(Path::new(file), raw, graph).unwrap()
+}
+
+#[test]
+fn relative_imports_resolve_to_defining_atom() {
+    let graph = GraphStore::in_memory().unwrap();
+    let mut resolver = SymbolResolver::new(fixture_root(), "typescript");
+    let ids = atomize_fixture(&mut resolver);
+
+    let app = resolve(&mut resolver, "src/app.ts", &graph);
+    let utils = app.iter().find(|i| i.source_path == "./utils").unwrap();
+    assert!(!utils.is_external);
+    assert_eq!(utils.symbols[0].chunk_id, ids["formatDate"]);
+
+    // Directory import resolves through index.ts
+    let header = app.iter().find(|i| i.source_path == "./components").unwrap();
+    assert_eq!(header.symbols[0].chunk_id, ids["Header"]);
+}
+
+#[test]
+fn different_relative_spellings_share_chunk_id() {
+    let graph = GraphStore::in_memory().unwrap();
+    let mut resolver = SymbolResolver::new(fixture_root(), "typescript");
+    let ids = atomize_fixture(&mut resolver);
+
+    let from_app = resolve(&mut resolver, "src/app.ts", &graph);
+    let from_components = resolve(&mut resolver, "src/components/index.ts", &graph);
+    let from_lib = resolve(&mut resolver, "src/lib/math.ts", &graph);
+
+    let via_dot = &from_app.iter().find(|i| i.source_path == "./utils").unwrap().symbols[0];
+    let via_parent = &from_components[0].symbols[0];
+    assert_eq!(via_dot.chunk_id, via_parent.chunk_id);
+
+    // ../../src/utils from src/lib/ is the same file as ./utils from src/
+    assert_eq!(from_lib[0].symbols[0].chunk_id, ids["clamp"]);
+    assert_eq!(from_lib[0].symbols[0].chunk_hash, ids["clamp"].rsplit(':').next().unwrap());
+}
+
+#[test]
+fn missing_and_package_imports_are_external() {
+    let graph = GraphStore::in_memory().unwrap();
+    let mut resolver = SymbolResolver::new(fixture_root(), "typescript");
+    atomize_fixture(&mut resolver);
+
+    let app = resolve(&mut resolver, "src/app.ts", &graph);
+    let missing = app.iter().find(|i| i.source_path == "./does-not-exist").unwrap();
+    let react = app.iter().find(|i| i.source_path == "react").unwrap();
+    assert!(missing.is_external);
+    assert!(react.is_external);
+
+    // External imports never produce link references
+    let links = resolver.create_link_references(&app);
+    assert!(!links.contains_key("missing"));
+    assert!(!links.contains_key("React"));
+    assert!(links.contains_key("formatDate"));
+}
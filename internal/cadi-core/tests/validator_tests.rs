@@ -1,5 +1,9 @@
-use cadi_core::parser::parse_file;
-use cadi_core::validator::Validator;
+use cadi_core::ast::Span;
+use cadi_core::parser::{parse_document, parse_file};
+use cadi_core::validator::{codes, Diagnostic, ManifestReport, Severity, Validator};
+use cadi_core::manifest_upgrade::MIGRATIONS;
+use cadi_core::{upgrade_manifest_source, ManifestLock};
+use std::path::PathBuf;
 
 #[test]
 fn test_validate_valid_doc() {
@@ -76,3 +80,334 @@ fn test_validate_invalid_effects_concurrency() {
     assert!(res.is_err());
     assert!(res.unwrap_err()[0].message.contains("Invalid concurrency value"));
 }
+
+#[test]
+fn test_validate_manifest_references() {
+    let manifest: cadi_core::Manifest = serde_json::from_value(serde_json::json!({
+        "manifest_id": "demo-manifest",
+        "manifest_version": "1.0",
+        "application": { "name": "demo" },
+        "build_graph": {
+            "nodes": [{ "id": "core" }, { "id": "core" }],
+            "edges": [{ "from": "core", "to": "missing" }]
+        },
+        "build_targets": [{
+            "name": "dev",
+            "platform": "native",
+            "nodes": [{ "id": "core" }, { "id": "ghost" }]
+        }]
+    }))
+    .expect("Parse failed");
+
+    let errs = Validator::new().validate_manifest(&manifest).unwrap_err();
+    let messages: Vec<&str> = errs.iter().map(|e| e.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "Duplicate node ID: core",
+            "Edge references unknown node: missing",
+            "Build target 'dev' references unknown node: ghost",
+        ]
+    );
+    assert_eq!(errs[2].path, "build_targets[0]");
+}
+
+fn diagnose(input: &str) -> Vec<Diagnostic> {
+    let doc = parse_file(input).expect("Parse failed");
+    Validator::new().diagnose(&doc)
+}
+
+/// The single diagnostic a document produces
+fn only(diagnostics: Vec<Diagnostic>) -> Diagnostic {
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    diagnostics.into_iter().next().unwrap()
+}
+
+fn at(line: usize, column: usize) -> Option<Span> {
+    Some(Span { line, column })
+}
+
+#[test]
+fn test_parse_error_has_position() {
+    let err = parse_document("@interface A {\n    foo( -> void\n}\n").unwrap_err();
+    assert_eq!(err.code, codes::PARSE_ERROR);
+    assert_eq!(err.span.map(|s| s.line), Some(2));
+}
+
+#[test]
+fn test_duplicate_interface_points_at_second_definition() {
+    let d = only(diagnose("@interface A {}\n@interface A {}\n"));
+    assert_eq!(d.code, codes::DUPLICATE_INTERFACE);
+    assert_eq!(d.severity, Severity::Error);
+    assert_eq!(d.span, at(2, 1));
+}
+
+#[test]
+fn test_duplicate_method_and_parameter() {
+    let diagnostics = diagnose("@interface A {\n    foo(x: int, x: int) -> void\n    foo() -> int\n}\n");
+    let codes: Vec<&str> = diagnostics.iter().map(|d| d.code.as_str()).collect();
+    assert_eq!(codes, vec![codes::DUPLICATE_PARAMETER, codes::DUPLICATE_METHOD]);
+    assert_eq!(diagnostics[0].span, at(2, 5));
+    assert_eq!(diagnostics[1].span, at(3, 5));
+}
+
+#[test]
+fn test_impl_name_format_is_a_warning() {
+    let input = "@interface A {}\n@impl webgl {\n    target: \"browser\"\n}\n";
+    let d = only(diagnose(input));
+    assert_eq!(d.code, codes::IMPL_NAME_FORMAT);
+    assert_eq!(d.severity, Severity::Warning);
+    assert_eq!(d.span, at(2, 1));
+
+    let doc = parse_file(input).unwrap();
+    assert!(Validator::new().validate(&doc).is_ok());
+}
+
+#[test]
+fn test_impl_of_unknown_interface() {
+    let d = only(diagnose("@interface VideoCodec {}\n@impl AudioCodec.wasm {}\n"));
+    assert_eq!(d.code, codes::MISSING_PROVIDES);
+    assert!(d.message.contains("AudioCodec"));
+}
+
+#[test]
+fn test_empty_constraint_block() {
+    let d = only(diagnose("@constraints {}\n"));
+    assert_eq!(d.code, codes::EMPTY_CONSTRAINT);
+    assert_eq!(d.severity, Severity::Warning);
+}
+
+#[test]
+fn test_annotation_rules_point_at_annotation() {
+    let cases = [
+        ("@contract {}", codes::EMPTY_CONTRACT),
+        ("@effects { concurrency: \"unsafe_mode\" }", codes::INVALID_CONCURRENCY),
+        ("@permissions {}", codes::EMPTY_PERMISSIONS),
+        ("@resources { memory: { peak: \"\" } }", codes::EMPTY_RESOURCE),
+        ("@protocol { states: [\"idle\", \"busy\"] }", codes::MISSING_INITIAL_STATE),
+        ("@abi { string_encoding: \"latin1\" }", codes::INVALID_STRING_ENCODING),
+        ("@flavor { sweet: true }", codes::UNKNOWN_ANNOTATION),
+    ];
+    for (annotation, code) in cases {
+        let input = format!("@interface A {{\n    foo() -> void\n    {}\n}}\n", annotation);
+        let d = only(diagnose(&input));
+        assert_eq!(d.code, code, "{}", annotation);
+        assert_eq!(d.span, at(3, 5), "{}", annotation);
+    }
+}
+
+#[test]
+fn test_protocol_initial_state_must_exist() {
+    let d = only(diagnose(
+        "@interface A {\n    @protocol { states: [\"idle\"], initial: \"running\" }\n}\n",
+    ));
+    assert_eq!(d.code, codes::UNKNOWN_INITIAL_STATE);
+}
+
+const MANIFEST: &str = "\
+cadl_version: '2'
+manifest_id: demo
+manifest_version: '1.0'
+application:
+  name: demo
+  version: 0.1.0
+build_graph:
+  nodes:
+  - id: core
+    source_cadi: chunk:sha256:abc
+  - id: ui
+    chunk_ref: ui-kit@^2
+build_targets:
+- name: dev
+  platform: native
+  nodes:
+  - id: core
+";
+
+fn manifest_report(source: &str) -> ManifestReport {
+    Validator::new().validate_manifest_source(source, None)
+}
+
+#[test]
+fn test_valid_manifest_source() {
+    let report = manifest_report(MANIFEST);
+    assert!(report.diagnostics.is_empty(), "{:?}", report.diagnostics);
+    assert!(report.manifest.is_some());
+}
+
+#[test]
+fn test_unknown_manifest_field_suggests_known_one() {
+    let report = manifest_report(&MANIFEST.replace("  platform: native", "  platfrom: native"));
+    let unknown = report.diagnostics.iter().find(|d| d.code == codes::UNKNOWN_FIELD).unwrap();
+    assert_eq!(unknown.severity, Severity::Warning);
+    assert_eq!(unknown.path, "build_targets[0].platfrom");
+    assert!(unknown.message.contains("did you mean `platform`"));
+    assert_eq!(unknown.span, at(15, 3));
+
+    // Without a platform the manifest no longer deserializes
+    let invalid = report.diagnostics.iter().find(|d| d.code == codes::INVALID_MANIFEST).unwrap();
+    assert!(invalid.span.is_some());
+    assert!(report.has_errors());
+}
+
+#[test]
+fn test_manifest_rules_locate_yaml_nodes() {
+    let source = MANIFEST
+        .replace("  - id: ui\n", "  - id: core\n")
+        .replace("ui-kit@^2", "ui-kit")
+        + "- name: dev\n  platform: ''\n  nodes:\n  - id: ghost\n";
+    let report = manifest_report(&source);
+    let found: Vec<(&str, Option<Span>)> =
+        report.diagnostics.iter().map(|d| (d.code.as_str(), d.span)).collect();
+
+    assert_eq!(
+        found,
+        vec![
+            (codes::DUPLICATE_NODE, at(11, 3)),
+            (codes::INVALID_CHUNK_REF, at(12, 5)),
+            (codes::DUPLICATE_TARGET, at(18, 1)),
+            (codes::MISSING_PLATFORM, at(19, 3)),
+            (codes::UNKNOWN_TARGET_NODE, at(18, 1)),
+        ]
+    );
+}
+
+#[test]
+fn test_manifest_warnings_do_not_fail_validation() {
+    let source = MANIFEST.replace("    source_cadi: chunk:sha256:abc\n", "") + "- name: empty\n  platform: native\n";
+    let report = manifest_report(&source);
+    let codes: Vec<&str> = report.diagnostics.iter().map(|d| d.code.as_str()).collect();
+    assert_eq!(codes, vec![codes::NODE_WITHOUT_SOURCE, codes::EMPTY_TARGET]);
+    assert!(!report.has_errors());
+    assert_eq!(report.diagnostics[0].span, at(9, 3));
+}
+
+#[test]
+fn test_target_transforms_need_one_builtin_or_command() {
+    let transforms = "  transforms:
+  - command: [wasm-opt, -O3, '{input}', -o, '{output}']
+    input: blob
+    output: blob
+    env: [PATH]
+  - builtin: compile
+    command: [gcc]
+    input: source
+    output: blob
+";
+    let report = manifest_report(&(MANIFEST.to_string() + transforms));
+    let d = only(report.diagnostics);
+    assert_eq!(d.code, codes::INVALID_TRANSFORM);
+    assert_eq!(d.path, "build_targets[0].transforms[1]");
+    assert!(d.message.contains("both a builtin and a command"));
+    assert_eq!(d.span, at(23, 3));
+}
+
+#[test]
+fn test_unresolved_interpolation_and_stale_lock() {
+    let source = MANIFEST.replace("name: demo", "name: ${env:CADI_TEST_UNSET_APP_NAME}");
+    let report = manifest_report(&source);
+    let d = only(report.diagnostics);
+    assert_eq!(d.code, codes::UNRESOLVED_INTERPOLATION);
+    assert_eq!(d.span, at(5, 3));
+
+    let lock = ManifestLock::new();
+    let report = Validator::new().validate_manifest_source(MANIFEST, Some(&lock));
+    let d = only(report.diagnostics);
+    assert_eq!(d.code, codes::STALE_LOCKFILE);
+    assert_eq!(d.span, at(12, 5));
+}
+
+#[test]
+fn test_manifest_without_cadl_version_follows_v1_rules() {
+    let source = MANIFEST
+        .replace("cadl_version: '2'\n", "")
+        .replace("  version: 0.1.0\n", "")
+        .replace("    chunk_ref: ui-kit@^2\n", "    chunk_ref: ui-kit@^2\n    selection_strategy: prefer_source\n");
+    let report = manifest_report(&source);
+    let d = only(report.diagnostics);
+    assert_eq!((d.code.as_str(), d.severity), (codes::MISSING_CADL_VERSION, Severity::Warning));
+    assert!(d.message.contains("assuming 1"));
+
+    let manifest = report.manifest.unwrap();
+    assert_eq!(manifest.cadl_version, "1");
+    assert_eq!(manifest.build_graph.nodes[1].selection_strategy.as_deref(), Some("prefer_source"));
+
+    let report = manifest_report(&format!("cadl_version: 1\n{}", source.replace("source_cadi", "selection: x\n    source_cadi")));
+    let found: Vec<&str> = report.diagnostics.iter().map(|d| d.code.as_str()).collect();
+    assert_eq!(found, vec![codes::OUTDATED_CADL_VERSION, codes::RENAMED_FIELD]);
+    assert_eq!(report.diagnostics[1].path, "build_graph.nodes[0].selection");
+}
+
+#[test]
+fn test_manifest_checked_against_declared_cadl_version() {
+    let source = MANIFEST
+        .replace("  version: 0.1.0\n", "")
+        .replace("build_targets:", "trust_defaults:\n  minimum_signatures: 1\nbuild_targets:");
+    let report = manifest_report(&source);
+    let found: Vec<(&str, &str, Option<Span>)> = report
+        .diagnostics
+        .iter()
+        .map(|d| (d.code.as_str(), d.path.as_str(), d.span))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (codes::RENAMED_FIELD, "trust_defaults", at(12, 1)),
+            (codes::EMPTY_REQUIRED_FIELD, "application.version", at(4, 1)),
+        ]
+    );
+    assert!(report.diagnostics[0].message.contains("renamed to `trust`"));
+    assert_eq!(report.manifest.unwrap().trust_defaults.unwrap().minimum_signatures, Some(1));
+
+    let report = manifest_report(&MANIFEST.replace("cadl_version: '2'", "cadl_version: '9'"));
+    assert!(report.has_errors());
+    let d = only(report.diagnostics);
+    assert_eq!((d.code.as_str(), d.span), (codes::UNSUPPORTED_CADL_VERSION, at(1, 1)));
+}
+
+#[test]
+fn test_upgrade_fixtures() {
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/upgrade");
+    for migration in MIGRATIONS {
+        let name = format!("v{}_to_v{}", migration.from, migration.to);
+        let read = |suffix: &str| std::fs::read_to_string(fixtures.join(format!("{}.{}.yaml", name, suffix))).unwrap();
+        let (before, after) = (read("before"), read("after"));
+
+        let upgrade = upgrade_manifest_source(&before).unwrap();
+        assert_eq!(upgrade.source, after, "{}", name);
+        assert!(upgrade.preserved_formatting);
+        assert_eq!((upgrade.from.as_str(), upgrade.to.as_str()), (migration.from, migration.to));
+
+        let report = manifest_report(&upgrade.source);
+        assert!(report.diagnostics.is_empty(), "{}: {:?}", name, report.diagnostics);
+        assert!(!upgrade_manifest_source(&after).unwrap().is_upgraded());
+    }
+}
+
+#[test]
+fn test_upgrade_json_manifest() {
+    let source = r#"{"manifest_id": "demo", "manifest_version": "1.0", "application": {"name": "demo"},
+        "build_graph": {"nodes": [{"id": "core", "source_cadi": "chunk:sha256:abc", "selection_strategy": "prefer_source"}]}}"#;
+    let upgrade = upgrade_manifest_source(source).unwrap();
+    assert!(!upgrade.preserved_formatting);
+    assert_eq!(
+        upgrade.changes,
+        vec![
+            "renamed `build_graph.nodes[0].selection_strategy` to `selection`",
+            "added `application.version: 0.1.0`",
+            "set `cadl_version` to 2",
+        ]
+    );
+    let value: serde_json::Value = serde_json::from_str(&upgrade.source).unwrap();
+    assert_eq!(value["build_graph"]["nodes"][0]["selection"], "prefer_source");
+    assert!(manifest_report(&upgrade.source).diagnostics.is_empty());
+}
+
+#[test]
+fn test_invalid_yaml_is_parse_error() {
+    let report = manifest_report("manifest_id: [demo\n");
+    let d = only(report.diagnostics);
+    assert_eq!(d.code, codes::PARSE_ERROR);
+    assert!(d.span.is_some());
+    assert!(report.manifest.is_none());
+}
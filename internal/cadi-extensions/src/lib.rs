@@ -5,10 +5,17 @@
 
 pub mod loader;
 pub mod manifest;
+pub mod permissions;
+pub mod signing;
 pub mod traits;
 pub mod types;
+#[cfg(feature = "wasm-extensions")]
+pub mod wasm;
 
 pub use loader::ExtensionLoader;
-pub use manifest::ExtensionManifest;
+pub use manifest::{ExtensionManifest, ExtensionRuntime};
+pub use permissions::{ExtensionPermissions, FileAccess, HttpHandle, PermissionPolicy};
 pub use traits::*;
-pub use types::*;
\ No newline at end of file
+pub use types::*;
+#[cfg(feature = "wasm-extensions")]
+pub use wasm::{WasmAtomizer, WasmLimits};
@@ -7,11 +7,16 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use cadi_core::trust::{TrustStore, TrustVerdict};
 use cadi_core::AtomicChunk;
 
-use crate::manifest::{validate_manifest, ExtensionManifest};
+use crate::manifest::{validate_manifest, ExtensionManifest, ExtensionRuntime};
+use crate::permissions::{FileAccess, HttpHandle, PermissionPolicy, ReadOnlyRegistry};
+use crate::signing::{verify_extension, MANIFEST_FILE};
 use crate::traits::*;
 use crate::types::*;
+#[cfg(feature = "wasm-extensions")]
+use crate::wasm::{WasmAtomizer, WasmLimits};
 
 /// Loader for CADI extensions
 pub struct ExtensionLoader {
@@ -19,6 +24,11 @@ pub struct ExtensionLoader {
     loaded_libraries: HashMap<ExtensionId, Library>,
     loaded_extensions: HashMap<ExtensionId, LoadedExtension>,
     context: ExtensionContext,
+    trust_store: TrustStore,
+    allow_unsigned: bool,
+    policy: PermissionPolicy,
+    #[cfg(feature = "wasm-extensions")]
+    wasm_limits: WasmLimits,
 }
 
 impl ExtensionLoader {
@@ -34,10 +44,16 @@ impl ExtensionLoader {
             ],
             loaded_libraries: HashMap::new(),
             loaded_extensions: HashMap::new(),
-            context: ExtensionContext {
-                config: HashMap::new(),
-                registry: Box::new(LocalRegistry::new()), // Default registry
-            },
+            context: ExtensionContext::new(HashMap::new(), Arc::new(LocalRegistry::new())), // Default registry
+            trust_store: TrustStore::open(
+                dirs::home_dir()
+                    .map(|h| h.join(".cadi").join("trust"))
+                    .unwrap_or_else(|| "./.cadi/trust".into()),
+            ),
+            allow_unsigned: false,
+            policy: PermissionPolicy::default(),
+            #[cfg(feature = "wasm-extensions")]
+            wasm_limits: WasmLimits::default(),
         }
     }
 
@@ -47,12 +63,40 @@ impl ExtensionLoader {
         self
     }
 
-    /// Set the extension context
+    /// Set the host context. Each extension gets a context derived from it:
+    /// config entries named `<extension name>.<key>` (as `<key>`, over the
+    /// manifest's `[config]`), and the registry if it may write to it.
     pub fn with_context(mut self, context: ExtensionContext) -> Self {
         self.context = context;
         self
     }
 
+    /// Set the trust store extension signatures are checked against
+    pub fn with_trust_store(mut self, trust_store: TrustStore) -> Self {
+        self.trust_store = trust_store;
+        self
+    }
+
+    /// Load extensions that are unsigned or signed by untrusted keys.
+    /// Extensions whose signatures do not match are always refused.
+    pub fn allow_unsigned(mut self, allow: bool) -> Self {
+        self.allow_unsigned = allow;
+        self
+    }
+
+    /// Limit the permissions granted to extensions
+    pub fn with_permission_policy(mut self, policy: PermissionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Set the fuel and memory limits for WASM extensions
+    #[cfg(feature = "wasm-extensions")]
+    pub fn with_wasm_limits(mut self, limits: WasmLimits) -> Self {
+        self.wasm_limits = limits;
+        self
+    }
+
     /// Load all extensions from search paths
     pub async fn load_all(&mut self) -> Result<()> {
         let search_paths = self.search_paths.clone();
@@ -85,7 +129,7 @@ impl ExtensionLoader {
 
     /// Load a single extension from its directory
     async fn load_from_extension_directory(&mut self, dir: &Path) -> Result<()> {
-        let manifest_path = dir.join("extension.toml");
+        let manifest_path = dir.join(MANIFEST_FILE);
 
         if !manifest_path.exists() {
             return Ok(()); // Not an extension directory
@@ -96,6 +140,87 @@ impl ExtensionLoader {
         // Validate manifest
         validate_manifest(&manifest)?;
 
+        // Check signatures before any of the extension's code runs
+        let name = &manifest.extension.name;
+        let signers = match verify_extension(dir, &manifest, &self.trust_store)? {
+            TrustVerdict::SignedBy(signers) => signers,
+            TrustVerdict::Invalid => {
+                return Err(ExtensionError::SignatureInvalid(format!(
+                    "{} does not match its signature; it was changed after signing",
+                    name
+                )));
+            }
+            _ if self.allow_unsigned => Vec::new(),
+            TrustVerdict::Unsigned => {
+                return Err(ExtensionError::Untrusted(format!("{} is not signed", name)));
+            }
+            TrustVerdict::UntrustedSigner => {
+                return Err(ExtensionError::Untrusted(format!(
+                    "{} is not signed by a trusted signer",
+                    name
+                )));
+            }
+        };
+
+        let (mut extension, library) = match manifest.extension.runtime {
+            ExtensionRuntime::Native => {
+                let (extension, library) = self.load_native(dir)?;
+                (extension, Some(library))
+            }
+            ExtensionRuntime::Wasm => (self.load_wasm(dir, &manifest)?, None),
+        };
+        let metadata = extension.metadata();
+
+        // Initialize the extension with only what it was granted
+        let context = self.context_for(&manifest);
+        extension.initialize(&context).await?;
+
+        let loaded = LoadedExtension {
+            metadata: metadata.clone(),
+            status: ExtensionStatus::Active,
+            instance: extension,
+            permissions: context.permissions,
+            signers,
+        };
+
+        if let Some(library) = library {
+            self.loaded_libraries.insert(metadata.id.clone(), library);
+        }
+        self.loaded_extensions.insert(metadata.id.clone(), loaded);
+
+        Ok(())
+    }
+
+    /// Build the context for an extension from the host context and the
+    /// permissions the policy grants it
+    fn context_for(&self, manifest: &ExtensionManifest) -> ExtensionContext {
+        let permissions = manifest.permissions.granted(&self.policy);
+
+        let mut config = manifest.config.clone().unwrap_or_default();
+        let prefix = format!("{}.", manifest.extension.name);
+        for (key, value) in &self.context.config {
+            if let Some(key) = key.strip_prefix(&prefix) {
+                config.insert(key.to_string(), value.clone());
+            }
+        }
+
+        let registry: Arc<dyn RegistryExtension> = if permissions.registry_write {
+            self.context.registry.clone()
+        } else {
+            Arc::new(ReadOnlyRegistry::new(self.context.registry.clone()))
+        };
+
+        ExtensionContext {
+            config,
+            registry,
+            http: HttpHandle::new(permissions.network),
+            files: FileAccess::new(&permissions.filesystem_read),
+            permissions,
+        }
+    }
+
+    /// Load a native extension library
+    fn load_native(&self, dir: &Path) -> Result<(Box<dyn Extension>, Library)> {
         // Load the library
         let lib_path = self.find_library_file(dir)?;
         let library = unsafe {
@@ -115,35 +240,37 @@ impl ExtensionLoader {
             return Err(ExtensionError::LoadFailed("Constructor returned null".into()));
         }
 
-        let mut extension = unsafe { Box::from_raw(extension_ptr) };
-        let metadata = extension.metadata();
+        let extension = unsafe { Box::from_raw(extension_ptr) };
 
-        // Initialize the extension
-        extension.initialize(&self.context).await?;
+        Ok((extension, library))
+    }
 
-        let loaded = LoadedExtension {
-            metadata: metadata.clone(),
-            status: ExtensionStatus::Active,
-            instance: extension,
-        };
+    /// Load a WASM atomizer from `extension.wasm`
+    #[cfg(feature = "wasm-extensions")]
+    fn load_wasm(&self, dir: &Path, manifest: &ExtensionManifest) -> Result<Box<dyn Extension>> {
+        let wasm_path = code_file(dir, ExtensionRuntime::Wasm);
+        if !wasm_path.exists() {
+            return Err(ExtensionError::LoadFailed(format!(
+                "WASM module not found: {}",
+                wasm_path.display()
+            )));
+        }
 
-        self.loaded_libraries.insert(metadata.id.clone(), library);
-        self.loaded_extensions.insert(metadata.id.clone(), loaded);
+        let atomizer = WasmAtomizer::from_file(&wasm_path, manifest.to_metadata()?, self.wasm_limits)?;
+        Ok(Box::new(atomizer))
+    }
 
-        Ok(())
+    #[cfg(not(feature = "wasm-extensions"))]
+    fn load_wasm(&self, _dir: &Path, manifest: &ExtensionManifest) -> Result<Box<dyn Extension>> {
+        Err(ExtensionError::LoadFailed(format!(
+            "{} is a WASM extension, but CADI was built without the wasm-extensions feature",
+            manifest.extension.name
+        )))
     }
 
     /// Find the library file in an extension directory
     fn find_library_file(&self, dir: &Path) -> Result<PathBuf> {
-        let lib_name = if cfg!(target_os = "windows") {
-            "extension.dll"
-        } else if cfg!(target_os = "macos") {
-            "libextension.dylib"
-        } else {
-            "libextension.so"
-        };
-
-        let lib_path = dir.join(lib_name);
+        let lib_path = code_file(dir, ExtensionRuntime::Native);
         if lib_path.exists() {
             Ok(lib_path)
         } else {
@@ -203,6 +330,17 @@ impl Default for ExtensionLoader {
     }
 }
 
+/// The file holding an extension's code
+pub(crate) fn code_file(dir: &Path, runtime: ExtensionRuntime) -> PathBuf {
+    let file_name = match runtime {
+        ExtensionRuntime::Wasm => "extension.wasm",
+        ExtensionRuntime::Native if cfg!(target_os = "windows") => "extension.dll",
+        ExtensionRuntime::Native if cfg!(target_os = "macos") => "libextension.dylib",
+        ExtensionRuntime::Native => "libextension.so",
+    };
+    dir.join(file_name)
+}
+
 /// Default local registry implementation
 struct LocalRegistry {
     chunks: Arc<RwLock<HashMap<String, AtomicChunk>>>,
@@ -257,4 +395,122 @@ impl Extension for LocalRegistry {
     async fn shutdown(&mut self) -> Result<()> {
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permissions::ExtensionPermissions;
+
+    const MANIFEST: &str = r#"
+[extension]
+name = "ini-atomizer"
+version = "0.1.0"
+description = "Atomizer for INI files"
+author = "CADI Team"
+license = "MIT"
+type = "atomizer"
+runtime = "wasm"
+"#;
+
+    const MODULE: &str = r#"(module
+        (memory (export "memory") 1)
+        (data (i32.const 16) "ini")
+        (func (export "cadi_language") (result i64) i64.const 68719476739))"#; // (16 << 32) | 3
+
+    #[tokio::test]
+    async fn test_wasm_runtime_selected_from_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let ext_dir = dir.path().join("ini-atomizer");
+        std::fs::create_dir(&ext_dir).unwrap();
+        std::fs::write(ext_dir.join("extension.toml"), MANIFEST).unwrap();
+        std::fs::write(ext_dir.join("extension.wasm"), MODULE).unwrap();
+
+        let mut loader = ExtensionLoader::new().allow_unsigned(true);
+        let result = loader.load_from_directory(dir.path()).await;
+
+        #[cfg(feature = "wasm-extensions")]
+        {
+            result.unwrap();
+            let atomizers = loader.get_extensions_by_type(ExtensionType::Atomizer);
+            assert_eq!(atomizers.len(), 1);
+            assert_eq!(atomizers[0].metadata.name, "ini-atomizer");
+            assert!(loader.loaded_libraries.is_empty());
+        }
+        #[cfg(not(feature = "wasm-extensions"))]
+        assert!(matches!(result, Err(ExtensionError::LoadFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_unsigned_extensions_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let ext_dir = dir.path().join("ini-atomizer");
+        std::fs::create_dir(&ext_dir).unwrap();
+        let native = MANIFEST.replace("runtime = \"wasm\"", "");
+        std::fs::write(ext_dir.join(MANIFEST_FILE), &native).unwrap();
+        std::fs::write(code_file(&ext_dir, ExtensionRuntime::Native), b"not a library").unwrap();
+
+        let trust = TrustStore::open(dir.path().join("trust"));
+        let loader = || ExtensionLoader::new().with_trust_store(trust.clone());
+
+        let result = loader().load_from_directory(dir.path()).await;
+        assert!(matches!(result, Err(ExtensionError::Untrusted(_))));
+        // Allowed through, it gets as far as loading the (bogus) library
+        let result = loader().allow_unsigned(true).load_from_directory(dir.path()).await;
+        assert!(matches!(result, Err(ExtensionError::LoadFailed(_))));
+
+        let key = cadi_core::trust::SigningKey::from_seed(&[9u8; 32]).unwrap();
+        crate::signing::sign_extension(&ext_dir, &key).unwrap();
+        let result = loader().load_from_directory(dir.path()).await;
+        assert!(matches!(result, Err(ExtensionError::Untrusted(_))));
+
+        trust.add("publisher", &key.public_key_pem()).unwrap();
+        let result = loader().load_from_directory(dir.path()).await;
+        assert!(matches!(result, Err(ExtensionError::LoadFailed(_))));
+
+        std::fs::write(ext_dir.join(MANIFEST_FILE), native + "\n[permissions]\nnetwork = true\n").unwrap();
+        let result = loader().allow_unsigned(true).load_from_directory(dir.path()).await;
+        assert!(matches!(result, Err(ExtensionError::SignatureInvalid(_))));
+    }
+
+    #[tokio::test]
+    async fn test_context_holds_only_granted_capabilities() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("granted.ini"), "a = 1").unwrap();
+        let outside = tempfile::NamedTempFile::new().unwrap();
+
+        let mut manifest = ExtensionManifest::from_str(MANIFEST).unwrap();
+        manifest.permissions = ExtensionPermissions {
+            filesystem_read: vec![dir.path().to_path_buf()],
+            network: true,
+            registry_write: true,
+        };
+        manifest.config = Some(HashMap::from([("dialect".to_string(), "git".into())]));
+
+        let mut host = ExtensionContext::new(HashMap::new(), Arc::new(LocalRegistry::new()));
+        host.config.insert("ini-atomizer.dialect".into(), "php".into());
+        host.config.insert("other.secret".into(), "hunter2".into());
+        let loader = ExtensionLoader::new()
+            .with_context(host)
+            .with_permission_policy(PermissionPolicy {
+                network: false,
+                registry_write: false,
+                ..PermissionPolicy::default()
+            });
+
+        let context = loader.context_for(&manifest);
+        assert!(matches!(context.http.client(), Err(ExtensionError::PermissionDenied(_))));
+        assert!(!context.permissions.registry_write);
+        assert!(!context.permissions.network);
+
+        assert_eq!(context.files.read(&dir.path().join("granted.ini")).unwrap(), b"a = 1");
+        assert!(matches!(context.files.read(outside.path()), Err(ExtensionError::PermissionDenied(_))));
+
+        assert_eq!(context.config.len(), 1);
+        assert_eq!(context.config["dialect"], "php");
+
+        let granted = ExtensionLoader::new().context_for(&manifest);
+        assert!(granted.http.client().is_ok());
+        assert!(granted.permissions.registry_write);
+    }
+}
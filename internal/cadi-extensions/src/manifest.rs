@@ -5,6 +5,7 @@ use serde_json::Value;
 use std::collections::HashMap;
 use std::path::Path;
 
+use crate::permissions::ExtensionPermissions;
 use crate::types::*;
 
 /// Extension manifest (extension.toml)
@@ -13,6 +14,9 @@ pub struct ExtensionManifest {
     pub extension: ExtensionInfo,
     pub dependencies: Option<HashMap<String, String>>,
     pub config: Option<HashMap<String, Value>>,
+    /// Capabilities the extension requests (none by default)
+    #[serde(default)]
+    pub permissions: ExtensionPermissions,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,6 +30,20 @@ pub struct ExtensionInfo {
     pub license: String,
     #[serde(rename = "type")]
     pub extension_type: String,
+    /// How the extension is loaded (defaults to native)
+    #[serde(default)]
+    pub runtime: ExtensionRuntime,
+}
+
+/// How an extension's code is loaded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtensionRuntime {
+    /// A platform library exporting `cadi_extension_create`
+    #[default]
+    Native,
+    /// A WASM module run in a sandbox (atomizers only)
+    Wasm,
 }
 
 impl ExtensionManifest {
@@ -91,5 +109,35 @@ pub fn validate_manifest(manifest: &ExtensionManifest) -> Result<()> {
         )),
     }
 
+    if manifest.extension.runtime == ExtensionRuntime::Wasm
+        && manifest.extension.extension_type != "atomizer"
+    {
+        return Err(ExtensionError::InvalidManifest(format!(
+            "The wasm runtime only supports atomizers, not {}",
+            manifest.extension.extension_type
+        )));
+    }
+
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runtime_defaults_to_native() {
+        let manifest = ExtensionManifest::from_str(
+            "[extension]\nname = \"x\"\nversion = \"1.0.0\"\ndescription = \"x\"\nauthor = \"x\"\nlicense = \"MIT\"\ntype = \"registry\"\n",
+        )
+        .unwrap();
+        assert_eq!(manifest.extension.runtime, ExtensionRuntime::Native);
+        validate_manifest(&manifest).unwrap();
+
+        let mut wasm = manifest.clone();
+        wasm.extension.runtime = ExtensionRuntime::Wasm;
+        assert!(validate_manifest(&wasm).is_err());
+        wasm.extension.extension_type = "atomizer".into();
+        validate_manifest(&wasm).unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+//! Capabilities granted to extensions
+//!
+//! An extension's manifest declares what it needs in a `[permissions]`
+//! section. The loader grants the subset its [`PermissionPolicy`] allows and
+//! hands the extension only the matching handles in its
+//! [`ExtensionContext`](crate::types::ExtensionContext): an HTTP client that
+//! errors without `network`, file reads limited to the granted paths, and a
+//! registry that rejects writes without `registry_write`.
+//!
+//! Native extensions run in-process, so these handles describe what an
+//! extension is allowed to do rather than sandbox it; that is what manifest
+//! signatures are for.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use cadi_core::AtomicChunk;
+
+use crate::traits::*;
+use crate::types::*;
+
+/// The `[permissions]` section of an extension manifest
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExtensionPermissions {
+    /// Paths the extension may read below. Relative paths are resolved
+    /// against the working directory, and `~/` against the home directory.
+    pub filesystem_read: Vec<PathBuf>,
+    /// Whether the extension may make network requests
+    pub network: bool,
+    /// Whether the extension may store chunks in the registry
+    pub registry_write: bool,
+}
+
+/// What the host is willing to grant, whatever extensions request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PermissionPolicy {
+    pub filesystem_read: bool,
+    pub network: bool,
+    pub registry_write: bool,
+}
+
+impl Default for PermissionPolicy {
+    fn default() -> Self {
+        Self {
+            filesystem_read: true,
+            network: true,
+            registry_write: true,
+        }
+    }
+}
+
+impl ExtensionPermissions {
+    /// The requested permissions that `policy` allows
+    pub fn granted(&self, policy: &PermissionPolicy) -> Self {
+        Self {
+            filesystem_read: if policy.filesystem_read {
+                self.filesystem_read.clone()
+            } else {
+                Vec::new()
+            },
+            network: self.network && policy.network,
+            registry_write: self.registry_write && policy.registry_write,
+        }
+    }
+}
+
+/// HTTP access for an extension
+#[derive(Clone)]
+pub struct HttpHandle {
+    client: Option<reqwest::Client>,
+}
+
+impl HttpHandle {
+    /// A handle whose client is available only if `allowed`
+    pub fn new(allowed: bool) -> Self {
+        Self {
+            client: allowed.then(reqwest::Client::new),
+        }
+    }
+
+    /// The HTTP client, or an error if network access was not granted
+    pub fn client(&self) -> Result<&reqwest::Client> {
+        self.client
+            .as_ref()
+            .ok_or_else(|| ExtensionError::PermissionDenied("network access was not granted".into()))
+    }
+}
+
+/// Read access to the filesystem, limited to granted paths
+#[derive(Debug, Clone, Default)]
+pub struct FileAccess {
+    roots: Vec<PathBuf>,
+}
+
+impl FileAccess {
+    /// Access below `paths`. Paths that do not exist are dropped.
+    pub fn new(paths: &[PathBuf]) -> Self {
+        let roots = paths
+            .iter()
+            .filter_map(|path| expand_home(path).canonicalize().ok())
+            .collect();
+        Self { roots }
+    }
+
+    /// Whether `path` lies below a granted path
+    pub fn allows(&self, path: &Path) -> bool {
+        path.canonicalize()
+            .map(|path| self.roots.iter().any(|root| path.starts_with(root)))
+            .unwrap_or(false)
+    }
+
+    /// Read a file below a granted path
+    pub fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        if !self.allows(path) {
+            return Err(ExtensionError::PermissionDenied(format!(
+                "reading {} was not granted",
+                path.display()
+            )));
+        }
+        Ok(std::fs::read(path)?)
+    }
+
+    /// Read a UTF-8 file below a granted path
+    pub fn read_to_string(&self, path: &Path) -> Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|e| ExtensionError::Other(format!("{} is not UTF-8: {}", path.display(), e)))
+    }
+}
+
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// A registry that forwards reads and rejects writes
+pub(crate) struct ReadOnlyRegistry {
+    inner: Arc<dyn RegistryExtension>,
+}
+
+impl ReadOnlyRegistry {
+    pub(crate) fn new(inner: Arc<dyn RegistryExtension>) -> Self {
+        Self { inner }
+    }
+}
+
+#[async_trait]
+impl RegistryExtension for ReadOnlyRegistry {
+    async fn store(&self, chunk: &AtomicChunk) -> Result<String> {
+        Err(ExtensionError::PermissionDenied(format!(
+            "storing {} requires registry_write",
+            chunk.chunk_id
+        )))
+    }
+
+    async fn retrieve(&self, id: &str) -> Result<Option<AtomicChunk>> {
+        self.inner.retrieve(id).await
+    }
+
+    async fn search(&self, query: &SearchQuery) -> Result<Vec<AtomicChunk>> {
+        self.inner.search(query).await
+    }
+}
+
+#[async_trait]
+impl Extension for ReadOnlyRegistry {
+    fn metadata(&self) -> ExtensionMetadata {
+        self.inner.metadata()
+    }
+
+    async fn initialize(&mut self, _context: &ExtensionContext) -> Result<()> {
+        Ok(())
+    }
+
+    async fn shutdown(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+//! Extension signatures
+//!
+//! An extension is signed over its manifest and its code file together, so
+//! neither can change after signing. Signatures live next to the manifest in
+//! `extension.sig`, one `ed25519:<public key>:<signature>` per line, and are
+//! checked against the same trust store as chunk signatures.
+
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+use cadi_core::trust::{SigningKey, TrustStore, TrustVerdict, SIGNATURE_PREFIX};
+
+use crate::loader::code_file;
+use crate::manifest::ExtensionManifest;
+use crate::types::*;
+
+/// File holding an extension's signatures
+pub const SIGNATURE_FILE: &str = "extension.sig";
+
+/// Manifest file of an extension directory
+pub const MANIFEST_FILE: &str = "extension.toml";
+
+/// The message an extension's signatures cover:
+/// `extension:sha256:<hex digest of the manifest, then the code file>`
+///
+/// Each part is prefixed with its length, so bytes can't be moved from the
+/// end of the manifest to the start of the code without changing the digest.
+pub fn extension_digest(dir: &Path, manifest: &ExtensionManifest) -> Result<String> {
+    let code_path = code_file(dir, manifest.extension.runtime);
+    let code = std::fs::read(&code_path).map_err(|e| {
+        ExtensionError::LoadFailed(format!("Failed to read {}: {}", code_path.display(), e))
+    })?;
+
+    let mut hasher = Sha256::new();
+    for part in [std::fs::read(dir.join(MANIFEST_FILE))?, code] {
+        hasher.update((part.len() as u64).to_be_bytes());
+        hasher.update(part);
+    }
+    Ok(format!("extension:sha256:{:x}", hasher.finalize()))
+}
+
+/// Sign an extension directory, adding the signature to its signature file
+/// in place of any earlier signature by the same key
+pub fn sign_extension(dir: &Path, key: &SigningKey) -> Result<String> {
+    let manifest = ExtensionManifest::from_file(dir.join(MANIFEST_FILE))?;
+    let signature = key.sign_chunk(&extension_digest(dir, &manifest)?);
+
+    let same_key = format!("{}{}:", SIGNATURE_PREFIX, key.public_key());
+    let mut signatures = read_signatures(dir)?;
+    signatures.retain(|s| !s.starts_with(&same_key));
+    signatures.push(signature.clone());
+    std::fs::write(dir.join(SIGNATURE_FILE), signatures.join("\n") + "\n")?;
+
+    Ok(signature)
+}
+
+/// Check an extension's signatures against the trust store
+pub fn verify_extension(dir: &Path, manifest: &ExtensionManifest, trust: &TrustStore) -> Result<TrustVerdict> {
+    let signatures = read_signatures(dir)?;
+    if signatures.is_empty() {
+        return Ok(TrustVerdict::Unsigned);
+    }
+
+    let digest = extension_digest(dir, manifest)?;
+    let statuses = trust
+        .verify_chunk(&digest, &signatures)
+        .map_err(|e| ExtensionError::SignatureInvalid(e.to_string()))?;
+    Ok(TrustVerdict::from_statuses(&statuses))
+}
+
+fn read_signatures(dir: &Path) -> Result<Vec<String>> {
+    let path = dir.join(SIGNATURE_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(std::fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(crate) const MANIFEST: &str = r#"
+[extension]
+name = "ini-atomizer"
+version = "0.1.0"
+description = "Atomizer for INI files"
+author = "CADI Team"
+license = "MIT"
+type = "atomizer"
+
+[permissions]
+filesystem_read = ["./src"]
+"#;
+
+    #[test]
+    fn test_tampering_after_signing_fails_verification() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(MANIFEST_FILE), MANIFEST).unwrap();
+        std::fs::write(code_file(dir.path(), Default::default()), b"\x7fELF").unwrap();
+
+        let trust = TrustStore::open(dir.path().join("trust"));
+        let key = SigningKey::from_seed(&[5u8; 32]).unwrap();
+        trust.add("publisher", &key.public_key_pem()).unwrap();
+        let verify = || {
+            let manifest = ExtensionManifest::from_file(dir.path().join(MANIFEST_FILE)).unwrap();
+            verify_extension(dir.path(), &manifest, &trust).unwrap()
+        };
+
+        assert_eq!(verify(), TrustVerdict::Unsigned);
+        sign_extension(dir.path(), &key).unwrap();
+        sign_extension(dir.path(), &key).unwrap();
+        assert_eq!(read_signatures(dir.path()).unwrap().len(), 1);
+        assert_eq!(verify(), TrustVerdict::SignedBy(vec!["publisher".to_string()]));
+
+        let tampered = MANIFEST.replace("[permissions]", "[permissions]\nnetwork = true");
+        std::fs::write(dir.path().join(MANIFEST_FILE), tampered).unwrap();
+        assert_eq!(verify(), TrustVerdict::Invalid);
+
+        // Swapping the code is caught too
+        std::fs::write(dir.path().join(MANIFEST_FILE), MANIFEST).unwrap();
+        std::fs::write(code_file(dir.path(), Default::default()), b"\x7fELF!").unwrap();
+        assert_eq!(verify(), TrustVerdict::Invalid);
+    }
+
+    #[test]
+    fn test_parts_are_framed() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = ExtensionManifest::from_str(MANIFEST).unwrap();
+        let digest = |manifest_bytes: &str, code: &[u8]| {
+            std::fs::write(dir.path().join(MANIFEST_FILE), manifest_bytes).unwrap();
+            std::fs::write(code_file(dir.path(), Default::default()), code).unwrap();
+            extension_digest(dir.path(), &manifest).unwrap()
+        };
+
+        // The same bytes split differently between the two files
+        let moved = digest(&format!("{}\n", MANIFEST), b"\x7fELF");
+        assert_ne!(moved, digest(MANIFEST, b"\n\x7fELF"));
+    }
+}
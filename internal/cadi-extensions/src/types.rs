@@ -2,9 +2,11 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 use uuid::Uuid;
 
-use crate::traits::Extension;
+use crate::permissions::{ExtensionPermissions, FileAccess, HttpHandle};
+use crate::traits::{Extension, RegistryExtension};
 
 /// Unique identifier for an extension
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -53,9 +55,31 @@ impl std::fmt::Display for ExtensionType {
 }
 
 /// Context passed to extensions during initialization
+///
+/// The loader builds one per extension holding only what it was granted:
+/// its own configuration, a registry that rejects writes unless
+/// `registry_write` was granted, and network and file handles that error
+/// unless those were granted.
 pub struct ExtensionContext {
     pub config: HashMap<String, serde_json::Value>,
-    pub registry: Box<dyn crate::traits::RegistryExtension>,
+    pub registry: Arc<dyn RegistryExtension>,
+    pub permissions: ExtensionPermissions,
+    pub http: HttpHandle,
+    pub files: FileAccess,
+}
+
+impl ExtensionContext {
+    /// A context with the given configuration and registry, and no network
+    /// or file access
+    pub fn new(config: HashMap<String, serde_json::Value>, registry: Arc<dyn RegistryExtension>) -> Self {
+        Self {
+            config,
+            registry,
+            permissions: ExtensionPermissions::default(),
+            http: HttpHandle::new(false),
+            files: FileAccess::default(),
+        }
+    }
 }
 
 /// Status of an extension
@@ -75,6 +99,10 @@ pub struct LoadedExtension {
     pub metadata: ExtensionMetadata,
     pub status: ExtensionStatus,
     pub instance: Box<dyn Extension>,
+    /// Permissions the extension was granted
+    pub permissions: ExtensionPermissions,
+    /// Trusted signers of the extension (empty if loaded unsigned)
+    pub signers: Vec<String>,
 }
 
 /// Search query for finding extensions
@@ -134,6 +162,18 @@ pub enum ExtensionError {
     #[error("Library loading error: {0}")]
     LibLoading(#[from] libloading::Error),
 
+    #[error("WASM extension error: {0}")]
+    Wasm(String),
+
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+
+    #[error("Untrusted extension: {0}")]
+    Untrusted(String),
+
+    #[error("Invalid extension signature: {0}")]
+    SignatureInvalid(String),
+
     #[error("Other error: {0}")]
     Other(String),
 }
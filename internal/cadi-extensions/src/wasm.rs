@@ -0,0 +1,302 @@
+//! WASM atomizer extensions
+//!
+//! A WASM atomizer is a core module with no imports. It exports:
+//!
+//! - `memory`
+//! - `cadi_alloc(len: i32) -> i32`: room for an input of `len` bytes
+//! - `cadi_language() -> i64`: the language name
+//! - `cadi_extract_atoms(ptr: i32, len: i32) -> i64`
+//! - `cadi_resolve_imports(ptr: i32, len: i32) -> i64`
+//!
+//! Inputs are JSON objects (`{"source": "..."}`) written where `cadi_alloc`
+//! says. Outputs are UTF-8 strings in guest memory, returned packed as
+//! `(ptr << 32) | len`. `cadi_extract_atoms` returns a JSON array of
+//! `AtomicChunk`s, `cadi_resolve_imports` an array of `ResolvedImport`s;
+//! either may instead return `{"error": "..."}`.
+//!
+//! Every call runs in a fresh instance with a fuel budget and a memory cap,
+//! so a guest that traps, loops or runs out of memory fails that call with an
+//! error instead of hanging or aborting the host.
+
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use wasmtime::{Config, Engine, Instance, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use cadi_core::{atomizer::ResolvedImport, AtomicChunk};
+
+use crate::traits::*;
+use crate::types::*;
+
+/// Resources a WASM extension may use per call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WasmLimits {
+    /// Fuel budget, roughly one unit per instruction
+    pub fuel: u64,
+    /// Largest size the guest's linear memory may grow to, in bytes
+    pub max_memory_bytes: usize,
+}
+
+impl Default for WasmLimits {
+    fn default() -> Self {
+        Self {
+            fuel: 2_000_000_000,
+            max_memory_bytes: 128 * 1024 * 1024,
+        }
+    }
+}
+
+/// An atomizer run inside a WASM sandbox
+pub struct WasmAtomizer {
+    metadata: ExtensionMetadata,
+    language: String,
+    guest: WasmGuest,
+}
+
+impl WasmAtomizer {
+    /// Load an atomizer from a `.wasm` (or `.wat`) file
+    pub fn from_file(path: &Path, metadata: ExtensionMetadata, limits: WasmLimits) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        Self::from_bytes(&bytes, metadata, limits)
+    }
+
+    /// Load an atomizer from module bytes (binary or text format)
+    pub fn from_bytes(bytes: &[u8], metadata: ExtensionMetadata, limits: WasmLimits) -> Result<Self> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).map_err(wasm_error)?;
+        let module = Module::new(&engine, bytes)
+            .map_err(|e| ExtensionError::LoadFailed(format!("Invalid WASM module: {:#}", e)))?;
+
+        let guest = WasmGuest { engine, module, limits };
+        let language = String::from_utf8(guest.call("cadi_language", None)?)
+            .map_err(|_| ExtensionError::Wasm("cadi_language returned invalid UTF-8".into()))?;
+
+        Ok(Self {
+            metadata,
+            language,
+            guest,
+        })
+    }
+
+    async fn call_json<T: DeserializeOwned + Send + 'static>(&self, export: &'static str, source: &str) -> Result<T> {
+        let input = serde_json::to_vec(&GuestInput { source })?;
+        let guest = self.guest.clone();
+        let output = tokio::task::spawn_blocking(move || guest.call(export, Some(&input)))
+            .await
+            .map_err(|e| ExtensionError::Wasm(format!("{}: {}", export, e)))??;
+
+        match serde_json::from_slice(&output)? {
+            GuestOutput::Ok(value) => Ok(value),
+            GuestOutput::Err { error } => Err(ExtensionError::Wasm(format!("{}: {}", export, error))),
+        }
+    }
+}
+
+#[async_trait]
+impl Extension for WasmAtomizer {
+    fn metadata(&self) -> ExtensionMetadata {
+        self.metadata.clone()
+    }
+
+    async fn initialize(&mut self, _context: &ExtensionContext) -> Result<()> {
+        Ok(())
+    }
+
+    async fn shutdown(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AtomizerExtension for WasmAtomizer {
+    fn language(&self) -> &str {
+        &self.language
+    }
+
+    async fn extract_atoms(&self, source: &str) -> Result<Vec<AtomicChunk>> {
+        self.call_json("cadi_extract_atoms", source).await
+    }
+
+    async fn resolve_imports(&self, source: &str) -> Result<Vec<ResolvedImport>> {
+        self.call_json("cadi_resolve_imports", source).await
+    }
+}
+
+#[derive(Serialize)]
+struct GuestInput<'a> {
+    source: &'a str,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GuestOutput<T> {
+    Err { error: String },
+    Ok(T),
+}
+
+/// A compiled module, instantiated afresh for every call
+#[derive(Clone)]
+struct WasmGuest {
+    engine: Engine,
+    module: Module,
+    limits: WasmLimits,
+}
+
+struct GuestState {
+    limits: StoreLimits,
+}
+
+impl WasmGuest {
+    /// Call an export, passing `input` through guest memory when given,
+    /// and return the bytes it points to
+    fn call(&self, export: &str, input: Option<&[u8]>) -> Result<Vec<u8>> {
+        let context = |e: wasmtime::Error| ExtensionError::Wasm(format!("{}: {:#}", export, e));
+
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.limits.max_memory_bytes)
+            .instances(1)
+            .build();
+        let mut store = Store::new(&self.engine, GuestState { limits });
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(self.limits.fuel).map_err(context)?;
+
+        let instance = Instance::new(&mut store, &self.module, &[]).map_err(context)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| ExtensionError::Wasm("module does not export its memory".into()))?;
+
+        let packed = match input {
+            Some(input) => {
+                let len = i32::try_from(input.len())
+                    .map_err(|_| ExtensionError::Wasm(format!("{}: input is too large", export)))?;
+                let alloc = instance
+                    .get_typed_func::<i32, i32>(&mut store, "cadi_alloc")
+                    .map_err(context)?;
+                let ptr = alloc.call(&mut store, len).map_err(context)?;
+                memory
+                    .write(&mut store, ptr as u32 as usize, input)
+                    .map_err(|e| ExtensionError::Wasm(format!("{}: {}", export, e)))?;
+
+                let func = instance
+                    .get_typed_func::<(i32, i32), i64>(&mut store, export)
+                    .map_err(context)?;
+                func.call(&mut store, (ptr, len)).map_err(context)?
+            }
+            None => {
+                let func = instance.get_typed_func::<(), i64>(&mut store, export).map_err(context)?;
+                func.call(&mut store, ()).map_err(context)?
+            }
+        };
+
+        let ptr = (packed as u64 >> 32) as usize;
+        let len = (packed as u64 & 0xffff_ffff) as usize;
+        let mut output = vec![0; len];
+        memory
+            .read(&store, ptr, &mut output)
+            .map_err(|e| ExtensionError::Wasm(format!("{}: result out of bounds: {}", export, e)))?;
+        Ok(output)
+    }
+}
+
+fn wasm_error(e: wasmtime::Error) -> ExtensionError {
+    ExtensionError::Wasm(format!("{:#}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ATOMS: &str = r#"[{"chunk_id":"chunk:sha256:ab","name":"greeting","language":"ini","content_hash":"ab","size":12}]"#;
+
+    /// A guest whose exports can be swapped out per test
+    fn guest(extract_atoms: &str, resolve_imports: &str) -> String {
+        format!(
+            r#"(module
+                (memory (export "memory") 1)
+                (data (i32.const 16) "ini")
+                (data (i32.const 32) "{atoms}")
+                (data (i32.const 512) "{{\"error\":\"no imports in ini\"}}")
+                (func (export "cadi_alloc") (param i32) (result i32) i32.const 1024)
+                (func (export "cadi_language") (result i64) i64.const {language})
+                (func (export "cadi_extract_atoms") (param i32 i32) (result i64) {extract_atoms})
+                (func (export "cadi_resolve_imports") (param i32 i32) (result i64) {resolve_imports}))"#,
+            atoms = ATOMS.replace('"', "\\\""),
+            language = (16u64 << 32) | 3,
+            extract_atoms = extract_atoms,
+            resolve_imports = resolve_imports,
+        )
+    }
+
+    fn atoms_result() -> String {
+        format!("i64.const {}", (32u64 << 32) | ATOMS.len() as u64)
+    }
+
+    fn error_result() -> String {
+        format!("i64.const {}", (512u64 << 32) | r#"{"error":"no imports in ini"}"#.len() as u64)
+    }
+
+    fn metadata() -> ExtensionMetadata {
+        ExtensionMetadata {
+            id: ExtensionId::new(),
+            name: "ini-atomizer".into(),
+            version: "0.1.0".into(),
+            description: "Test atomizer".into(),
+            author: "CADI Team".into(),
+            homepage: None,
+            repository: None,
+            license: "MIT".into(),
+            extension_type: ExtensionType::Atomizer,
+        }
+    }
+
+    fn load(wat: &str, limits: WasmLimits) -> Result<WasmAtomizer> {
+        WasmAtomizer::from_bytes(wat.as_bytes(), metadata(), limits)
+    }
+
+    #[tokio::test]
+    async fn test_guest_results_are_decoded() {
+        let atomizer = load(&guest(&atoms_result(), &error_result()), WasmLimits::default()).unwrap();
+        assert_eq!(atomizer.language(), "ini");
+
+        let atoms = atomizer.extract_atoms("greeting = hi").await.unwrap();
+        assert_eq!(atoms.len(), 1);
+        assert_eq!(atoms[0].name, "greeting");
+
+        let err = atomizer.resolve_imports("greeting = hi").await.unwrap_err();
+        assert!(err.to_string().contains("no imports in ini"));
+    }
+
+    #[tokio::test]
+    async fn test_guest_crash_is_an_error() {
+        let atomizer = load(&guest("unreachable", &error_result()), WasmLimits::default()).unwrap();
+        let err = atomizer.extract_atoms("greeting = hi").await.unwrap_err();
+        assert!(matches!(err, ExtensionError::Wasm(_)), "{}", err);
+
+        // Each call gets a fresh instance, so the host carries on
+        assert!(atomizer.resolve_imports("").await.is_err());
+        assert_eq!(atomizer.language(), "ini");
+    }
+
+    #[tokio::test]
+    async fn test_limits_are_enforced() {
+        let limits = WasmLimits {
+            fuel: 100_000,
+            ..WasmLimits::default()
+        };
+        let spinning = load(&guest("(loop br 0) unreachable", &error_result()), limits).unwrap();
+        assert!(spinning.extract_atoms("").await.is_err());
+
+        let small = WasmLimits {
+            max_memory_bytes: 64 * 1024,
+            ..WasmLimits::default()
+        };
+        let growing = guest(
+            "(drop (memory.grow (i32.const 16))) (if (i32.lt_s (memory.size) (i32.const 17)) (then unreachable)) i64.const 0",
+            &error_result(),
+        );
+        let atomizer = load(&growing, small).unwrap();
+        assert!(atomizer.extract_atoms("").await.is_err());
+    }
+}
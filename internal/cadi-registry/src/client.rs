@@ -1,6 +1,9 @@
 //! Registry client for CADI
 
-use cadi_core::{CadiError, CadiResult, Chunk, Manifest};
+use cadi_core::{Chunk, ChunkCategory, ChunkGranularity, ChunkRef, ImportResult, LockedChunk, Manifest};
+use std::collections::HashSet;
+use crate::error::{RegistryError, RegistryResult};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 /// Registry client configuration
@@ -10,12 +13,28 @@ pub struct RegistryConfig {
     pub url: String,
     /// Authentication token
     pub token: Option<String>,
+    /// Namespace sent with every request in the [`NAMESPACE_HEADER`], so a
+    /// multi-tenant registry checks the token against that namespace
+    pub namespace: Option<String>,
     /// Request timeout
     pub timeout: Duration,
     /// Whether to verify TLS certificates
     pub verify_tls: bool,
     /// Maximum concurrent requests
     pub max_concurrent: usize,
+    /// Number of times a failed request is retried
+    pub max_retries: u32,
+    /// Backoff before the first retry; doubles on every further attempt
+    pub base_backoff_ms: u64,
+    /// HTTP status codes that are retried (network errors always are)
+    pub retry_on_status: Vec<u16>,
+    /// Spool directory for chunks that could not be published because the
+    /// registry was unreachable. Spooling is disabled when unset.
+    pub spool_dir: Option<PathBuf>,
+    /// Cache directory for [`fetch_chunk_cached`](RegistryClient::fetch_chunk_cached).
+    /// Cached chunks are revalidated with `If-None-Match` on every fetch.
+    /// Caching is disabled when unset.
+    pub cache_dir: Option<PathBuf>,
 }
 
 impl Default for RegistryConfig {
@@ -23,9 +42,15 @@ impl Default for RegistryConfig {
         Self {
             url: "https://registry.cadi.dev".to_string(),
             token: None,
+            namespace: None,
             timeout: Duration::from_secs(30),
             verify_tls: true,
             max_concurrent: 4,
+            max_retries: 3,
+            base_backoff_ms: 200,
+            retry_on_status: vec![408, 429, 500, 502, 503, 504],
+            spool_dir: None,
+            cache_dir: None,
         }
     }
 }
@@ -36,165 +61,396 @@ pub struct RegistryClient {
     http: reqwest::Client,
 }
 
+/// A request that failed after all retries
+struct RequestFailure {
+    error: RegistryError,
+    /// Whether the registry was unreachable or returned a retryable status
+    transient: bool,
+}
+
+/// Header naming the namespace a request publishes into
+pub const NAMESPACE_HEADER: &str = "x-cadi-namespace";
+
+/// Most hashes sent in one bulk existence check
+pub const EXISTS_BATCH: usize = 1000;
+
+/// Most chunks named in one usage report
+pub const USAGE_BATCH: usize = 1000;
+
+/// Response of the bulk existence check
+#[derive(serde::Deserialize)]
+struct ExistingChunks {
+    existing: Vec<String>,
+}
+
+/// Longest `Retry-After` the client waits out itself; longer waits are
+/// returned to the caller as [`RegistryError::RateLimited`]
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
 impl RegistryClient {
     /// Create a new registry client
-    pub fn new(config: RegistryConfig) -> CadiResult<Self> {
+    pub fn new(config: RegistryConfig) -> RegistryResult<Self> {
         let http = reqwest::Client::builder()
             .timeout(config.timeout)
             .danger_accept_invalid_certs(!config.verify_tls)
-            .build()
-            .map_err(|e| CadiError::RegistryError(e.to_string()))?;
+            .build()?;
         
         Ok(Self { config, http })
     }
 
     /// Create a client with default configuration
-    pub fn default_client() -> CadiResult<Self> {
+    pub fn default_client() -> RegistryResult<Self> {
         Self::new(RegistryConfig::default())
     }
 
     /// Check if a chunk exists in the registry
-    pub async fn chunk_exists(&self, chunk_id: &str) -> CadiResult<bool> {
+    pub async fn chunk_exists(&self, chunk_id: &str) -> RegistryResult<bool> {
         let url = format!("{}/v1/chunks/{}", self.config.url, chunk_id);
         
-        let mut request = self.http.head(&url);
-        if let Some(ref token) = self.config.token {
-            request = request.bearer_auth(token);
-        }
-        
-        let response = request.send().await
-            .map_err(|e| CadiError::RegistryError(e.to_string()))?;
+        let response = self.send_with_retry(|| self.authorized(self.http.head(&url))).await
+            .map_err(|f| f.error)?;
         
-        Ok(response.status().is_success())
+        match response.status() {
+            reqwest::StatusCode::NOT_FOUND => Ok(false),
+            status if status.is_success() => Ok(true),
+            _ => Err(RegistryError::from_response(response, chunk_id).await),
+        }
+    }
+
+    /// Which of `hashes` (chunk storage keys, see
+    /// [`chunk_storage_key`](cadi_core::hash::chunk_storage_key)) the
+    /// registry already holds. Asks in batches of [`EXISTS_BATCH`]; a
+    /// registry without the bulk endpoint is asked chunk by chunk.
+    pub async fn existing_chunks(&self, hashes: &[String]) -> RegistryResult<HashSet<String>> {
+        let url = format!("{}/v1/chunks/exists", self.config.url);
+        let mut existing = HashSet::new();
+
+        for batch in hashes.chunks(EXISTS_BATCH) {
+            let body = serde_json::json!({ "hashes": batch });
+            let response = self.send_with_retry(|| self.authorized(self.http.post(&url).json(&body))).await
+                .map_err(|f| f.error)?;
+
+            if matches!(response.status(), reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::METHOD_NOT_ALLOWED) {
+                for hash in batch {
+                    let chunk_id = cadi_core::hash::ChunkId::from_storage_key(hash).to_string();
+                    if self.chunk_exists(&chunk_id).await? {
+                        existing.insert(hash.clone());
+                    }
+                }
+                continue;
+            }
+
+            let found: ExistingChunks = success(response, "chunks/exists").await?.json().await?;
+            existing.extend(found.existing);
+        }
+
+        Ok(existing)
+    }
+
+    /// Mark the chunks of an import that the registry already holds as
+    /// reused, so publishing skips them. Returns how many were marked.
+    pub async fn reconcile_import(&self, result: &mut ImportResult) -> RegistryResult<usize> {
+        let existing = self.existing_chunks(&result.chunk_hashes()).await?;
+        Ok(result.mark_reused_from_registry(&existing))
     }
 
     /// Fetch a chunk from the registry
-    pub async fn fetch_chunk(&self, chunk_id: &str) -> CadiResult<Vec<u8>> {
+    pub async fn fetch_chunk(&self, chunk_id: &str) -> RegistryResult<Vec<u8>> {
         let url = format!("{}/v1/chunks/{}", self.config.url, chunk_id);
         
-        let mut request = self.http.get(&url);
-        if let Some(ref token) = self.config.token {
-            request = request.bearer_auth(token);
-        }
-        
-        let response = request.send().await
-            .map_err(|e| CadiError::RegistryError(e.to_string()))?;
+        let response = self.send_with_retry(|| self.authorized(self.http.get(&url))).await
+            .map_err(|f| f.error)?;
+        let response = success(response, chunk_id).await?;
         
-        if !response.status().is_success() {
-            if response.status() == reqwest::StatusCode::NOT_FOUND {
-                return Err(CadiError::ChunkNotFound(chunk_id.to_string()));
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    /// Fetch a chunk through the local cache. A cached copy is revalidated
+    /// with its ETag and served when the registry answers 304 Not Modified,
+    /// so repeated fetches transfer no body. Without a cache directory this
+    /// is [`fetch_chunk`](Self::fetch_chunk).
+    pub async fn fetch_chunk_cached(&self, chunk_id: &str) -> RegistryResult<Vec<u8>> {
+        let Some(ref dir) = self.config.cache_dir else {
+            return self.fetch_chunk(chunk_id).await;
+        };
+        let url = format!("{}/v1/chunks/{}", self.config.url, chunk_id);
+        let cached = load_cached_chunk(dir, chunk_id);
+
+        let response = self.send_with_retry(|| {
+            let request = self.authorized(self.http.get(&url));
+            match cached {
+                Some((ref etag, _)) => request.header(reqwest::header::IF_NONE_MATCH, etag),
+                None => request,
+            }
+        }).await
+            .map_err(|f| f.error)?;
+
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            if let Some((_, data)) = cached {
+                ChunkCacheStats::record(dir, true).map_err(RegistryError::Storage)?;
+                return Ok(data);
             }
-            return Err(CadiError::RegistryError(
-                format!("HTTP {}: {}", response.status(), response.status().canonical_reason().unwrap_or("Unknown"))
-            ));
         }
-        
-        let bytes = response.bytes().await
-            .map_err(|e| CadiError::RegistryError(e.to_string()))?;
-        
-        Ok(bytes.to_vec())
+        let response = success(response, chunk_id).await?;
+
+        let etag = response.headers().get(reqwest::header::ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let data = response.bytes().await?.to_vec();
+
+        if let Some(etag) = etag {
+            store_cached_chunk(dir, chunk_id, &etag, &data).map_err(RegistryError::Storage)?;
+        }
+        ChunkCacheStats::record(dir, false).map_err(RegistryError::Storage)?;
+        Ok(data)
+    }
+
+    /// Hits and misses of [`fetch_chunk_cached`](Self::fetch_chunk_cached),
+    /// or `None` when caching is disabled
+    pub fn cache_stats(&self) -> RegistryResult<Option<ChunkCacheStats>> {
+        self.config.cache_dir.as_deref()
+            .map(ChunkCacheStats::load)
+            .transpose()
+            .map_err(RegistryError::Storage)
+    }
+
+    /// Change the directory [`fetch_chunk_cached`](Self::fetch_chunk_cached) uses
+    pub(crate) fn set_cache_dir(&mut self, dir: Option<PathBuf>) {
+        self.config.cache_dir = dir;
+    }
+
+    /// Fetch the manifest of every chunk `chunk_id` transitively references,
+    /// following references at most `max_depth` levels deep
+    pub async fn fetch_closure(&self, chunk_id: &str, max_depth: Option<usize>) -> RegistryResult<crate::types::ChunkClosure> {
+        let mut url = format!("{}/v1/chunks/{}/closure", self.config.url, chunk_id);
+        if let Some(depth) = max_depth {
+            url.push_str(&format!("?max_depth={}", depth));
+        }
+
+        let response = self.send_with_retry(|| self.authorized(self.http.get(&url))).await
+            .map_err(|f| f.error)?;
+
+        Ok(success(response, chunk_id).await?.json().await?)
+    }
+
+    /// Usage of a chunk on the registry, or `None` if the registry doesn't
+    /// hold it
+    pub async fn fetch_usage_stats(&self, chunk_id: &str) -> RegistryResult<Option<crate::types::ChunkUsageStats>> {
+        let url = format!("{}/v1/chunks/{}/stats", self.config.url, chunk_id);
+
+        let response = self.send_with_retry(|| self.authorized(self.http.get(&url))).await
+            .map_err(|f| f.error)?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Ok(Some(success(response, chunk_id).await?.json().await?))
+    }
+
+    /// Tell the registry the chunks of a build plan were used, so they rank
+    /// by popularity in search. Reports in batches of [`USAGE_BATCH`] and
+    /// needs a token with write access.
+    pub async fn report_usage(&self, chunk_ids: &[String]) -> RegistryResult<()> {
+        let url = format!("{}/v1/usage", self.config.url);
+
+        for batch in chunk_ids.chunks(USAGE_BATCH) {
+            let body = serde_json::json!({ "chunks": batch });
+            let response = self.send_with_retry(|| self.authorized(self.http.post(&url).json(&body))).await
+                .map_err(|f| f.error)?;
+            success(response, "usage").await?;
+        }
+        Ok(())
     }
 
     /// Fetch chunk metadata
-    pub async fn fetch_chunk_meta(&self, chunk_id: &str) -> CadiResult<Chunk> {
+    pub async fn fetch_chunk_meta(&self, chunk_id: &str) -> RegistryResult<Chunk> {
         let url = format!("{}/v1/chunks/{}/meta", self.config.url, chunk_id);
         
-        let mut request = self.http.get(&url);
-        if let Some(ref token) = self.config.token {
-            request = request.bearer_auth(token);
-        }
+        let response = self.authorized(self.http.get(&url)).send().await?;
         
-        let response = request.send().await
-            .map_err(|e| CadiError::RegistryError(e.to_string()))?;
-        
-        if !response.status().is_success() {
-            if response.status() == reqwest::StatusCode::NOT_FOUND {
-                return Err(CadiError::ChunkNotFound(chunk_id.to_string()));
-            }
-            return Err(CadiError::RegistryError(
-                format!("HTTP {}", response.status())
-            ));
+        Ok(success(response, chunk_id).await?.json().await?)
+    }
+
+    /// Fetch the metadata the registry holds for a chunk as raw JSON, or
+    /// `None` if it has none
+    pub async fn fetch_chunk_metadata(&self, chunk_id: &str) -> RegistryResult<Option<serde_json::Value>> {
+        let url = format!("{}/v1/chunks/{}/meta", self.config.url, chunk_id);
+
+        let response = self.send_with_retry(|| self.authorized(self.http.get(&url))).await
+            .map_err(|f| f.error)?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
         }
-        
-        let chunk: Chunk = response.json().await
-            .map_err(|e| CadiError::RegistryError(e.to_string()))?;
-        
-        Ok(chunk)
+        Ok(Some(success(response, chunk_id).await?.json().await?))
+    }
+
+    /// Store metadata for a chunk already on the registry
+    pub async fn publish_chunk_metadata(&self, chunk_id: &str, meta: &serde_json::Value) -> RegistryResult<()> {
+        let url = format!("{}/v1/chunks/{}/meta", self.config.url, chunk_id);
+
+        let response = self.send_with_retry(|| self.authorized(self.http.put(&url).json(meta))).await
+            .map_err(|f| f.error)?;
+
+        success(response, chunk_id).await?;
+        Ok(())
+    }
+
+    /// A page of the IDs of every chunk the registry stores
+    pub async fn list_chunks(&self, limit: usize, offset: usize) -> RegistryResult<crate::types::ChunkPage> {
+        let url = format!("{}/v1/chunk_ids", self.config.url);
+        let query = [("limit", limit.to_string()), ("offset", offset.to_string())];
+
+        let response = self.send_with_retry(|| self.authorized(self.http.get(&url).query(&query))).await
+            .map_err(|f| f.error)?;
+
+        Ok(success(response, "chunk list").await?.json().await?)
+    }
+
+    /// Fetch the signatures the registry holds for a chunk
+    pub async fn fetch_signatures(&self, chunk_id: &str) -> RegistryResult<Vec<String>> {
+        let url = format!("{}/v1/chunks/{}/meta", self.config.url, chunk_id);
+
+        let response = self.authorized(self.http.get(&url)).send().await?;
+        let meta: serde_json::Value = success(response, chunk_id).await?.json().await?;
+
+        Ok(meta.get("signatures")
+            .cloned()
+            .and_then(|s| serde_json::from_value(s).ok())
+            .unwrap_or_default())
     }
 
     /// Publish a chunk to the registry
-    pub async fn publish_chunk(&self, chunk_id: &str, data: &[u8]) -> CadiResult<PublishResult> {
-        let url = format!("{}/v1/chunks/{}", self.config.url, chunk_id);
-        
-        let mut request = self.http
-            .put(&url)
-            .body(data.to_vec());
-        
-        if let Some(ref token) = self.config.token {
-            request = request.bearer_auth(token);
+    ///
+    /// If the registry is unreachable and a spool directory is configured,
+    /// the chunk is spooled for a later [`flush_spool`](Self::flush_spool)
+    /// and the result is marked as `spooled`.
+    pub async fn publish_chunk(&self, chunk_id: &str, data: &[u8]) -> RegistryResult<PublishResult> {
+        match self.try_publish(chunk_id, data).await {
+            Ok(result) => Ok(result),
+            Err(failure) => match self.config.spool_dir {
+                Some(ref dir) if failure.transient => {
+                    spool_chunk(dir, chunk_id, data).map_err(RegistryError::Storage)?;
+                    Ok(PublishResult {
+                        success: false,
+                        chunk_id: Some(chunk_id.to_string()),
+                        message: Some(format!("registry unreachable, spooled: {}", failure.error)),
+                        spooled: true,
+                    })
+                }
+                _ => Err(failure.error),
+            },
+        }
+    }
+
+    /// Retry publishing every spooled chunk. Chunks that publish successfully
+    /// are removed from the spool; the rest stay for the next flush.
+    pub async fn flush_spool(&self) -> RegistryResult<SpoolFlushResult> {
+        let mut result = SpoolFlushResult::default();
+        let Some(ref dir) = self.config.spool_dir else {
+            return Ok(result);
+        };
+        if !dir.exists() {
+            return Ok(result);
+        }
+
+        let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)
+            .map_err(RegistryError::Storage)?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == SPOOL_EXTENSION))
+            .collect();
+        entries.sort();
+
+        for path in entries {
+            let (chunk_id, data) = read_spooled(&path).map_err(RegistryError::Storage)?;
+            match self.try_publish(&chunk_id, &data).await {
+                Ok(_) => {
+                    std::fs::remove_file(&path).map_err(RegistryError::Storage)?;
+                    result.flushed.push(chunk_id);
+                }
+                Err(failure) => {
+                    tracing::warn!("Failed to flush spooled chunk {}: {}", chunk_id, failure.error);
+                    result.remaining.push(chunk_id);
+                }
+            }
         }
+
+        Ok(result)
+    }
+
+    async fn try_publish(&self, chunk_id: &str, data: &[u8]) -> Result<PublishResult, RequestFailure> {
+        let url = format!("{}/v1/chunks/{}", self.config.url, chunk_id);
         
-        let response = request.send().await
-            .map_err(|e| CadiError::RegistryError(e.to_string()))?;
+        let response = self
+            .send_with_retry(|| self.authorized(self.http.put(&url).body(data.to_vec())))
+            .await?;
         
         if !response.status().is_success() {
-            return Err(CadiError::RegistryError(
-                format!("HTTP {}: {}", response.status(), response.status().canonical_reason().unwrap_or("Unknown"))
-            ));
+            return Err(RequestFailure {
+                transient: self.is_retryable(response.status()),
+                error: RegistryError::from_response(response, chunk_id).await,
+            });
         }
         
-        let result: PublishResult = response.json().await
-            .map_err(|e| CadiError::RegistryError(e.to_string()))?;
-        
+        let result: PublishResult = response.json().await.map_err(|e| RequestFailure {
+            error: e.into(),
+            transient: false,
+        })?;
+        // The registry answers 200 with `success: false` when the content
+        // doesn't hash to the chunk ID
+        if !result.success && result.message.as_deref() == Some("Hash mismatch") {
+            return Err(RequestFailure {
+                error: RegistryError::HashMismatch(chunk_id.to_string()),
+                transient: false,
+            });
+        }
         Ok(result)
     }
 
     /// Search for chunks
-    pub async fn search(&self, query: &SearchQuery) -> CadiResult<RegistrySearchResult> {
+    pub async fn search(&self, query: &SearchQuery) -> RegistryResult<RegistrySearchResult> {
         let url = format!("{}/v1/search", self.config.url);
         
-        let mut request = self.http
-            .post(&url)
-            .json(query);
-        
-        if let Some(ref token) = self.config.token {
-            request = request.bearer_auth(token);
-        }
-        
-        let response = request.send().await
-            .map_err(|e| CadiError::RegistryError(e.to_string()))?;
-        
-        if !response.status().is_success() {
-            return Err(CadiError::RegistryError(
-                format!("HTTP {}", response.status())
-            ));
-        }
+        let response = self.authorized(self.http.post(&url).json(query)).send().await?;
         
-        let result: RegistrySearchResult = response.json().await
-            .map_err(|e| CadiError::RegistryError(e.to_string()))?;
-        
-        Ok(result)
+        Ok(success(response, "search").await?.json().await?)
     }
 
-    /// Perform a semantic search against the registry
-    pub async fn semantic_search(&self, query: &str, limit: usize) -> CadiResult<Vec<(ChunkSummary, f32)>> {
-        let url = format!("{}/v1/semantic_search", self.config.url);
-        let body = serde_json::json!({ "query": query, "limit": limit });
+    /// Resolve a chunk reference to the newest published chunk of that name
+    /// whose version satisfies the range
+    pub async fn resolve_chunk_ref(&self, chunk_ref: &ChunkRef) -> RegistryResult<LockedChunk> {
+        let result = self.search(&SearchQuery {
+            query: Some(chunk_ref.name.clone()),
+            limit: 100,
+            ..Default::default()
+        }).await?;
 
-        let mut request = self.http.post(&url).json(&body);
-        if let Some(ref token) = self.config.token {
-            request = request.bearer_auth(token);
+        let mut candidates = Vec::new();
+        for summary in result.chunks {
+            if !summary.name.is_empty() && summary.name != chunk_ref.name {
+                continue;
+            }
+            let chunk = self.fetch_chunk_meta(&summary.chunk_id).await?;
+            if chunk.meta.name != chunk_ref.name {
+                continue;
+            }
+            if let Some(version) = chunk.meta.version {
+                candidates.push((version, chunk.chunk_id));
+            }
         }
 
-        let response = request.send().await
-            .map_err(|e| CadiError::RegistryError(e.to_string()))?;
+        chunk_ref.select(candidates).ok_or_else(|| {
+            RegistryError::NotFound(format!("a version of {} satisfying {}", chunk_ref.name, chunk_ref.req))
+        })
+    }
 
-        if !response.status().is_success() {
-            return Err(CadiError::RegistryError(format!("HTTP {}", response.status())));
-        }
+    /// Perform a semantic search against the registry
+    pub async fn semantic_search(&self, query: &str, limit: usize) -> RegistryResult<Vec<(ChunkSummary, f32)>> {
+        let url = format!("{}/v1/semantic_search", self.config.url);
+        let body = serde_json::json!({ "query": query, "limit": limit });
 
-        let hits: Vec<serde_json::Value> = response.json().await
-            .map_err(|e| CadiError::RegistryError(e.to_string()))?;
+        let response = self.authorized(self.http.post(&url).json(&body)).send().await?;
+        let hits: Vec<serde_json::Value> = success(response, "semantic search").await?.json().await?;
 
         // Map json results to (ChunkSummary, score) where ChunkSummary is partial
         let mut out = Vec::new();
@@ -206,6 +462,8 @@ impl RegistryClient {
                     cadi_type: "".to_string(),
                     concepts: vec![],
                     description: None,
+                    language: None,
+                    size: None,
                 };
                 out.push((cs, score.as_f64().unwrap_or(0.0) as f32));
             }
@@ -215,65 +473,145 @@ impl RegistryClient {
     }
 
     /// Fetch a manifest
-    pub async fn fetch_manifest(&self, manifest_id: &str) -> CadiResult<Manifest> {
+    pub async fn fetch_manifest(&self, manifest_id: &str) -> RegistryResult<Manifest> {
         let url = format!("{}/v1/manifests/{}", self.config.url, manifest_id);
         
-        let mut request = self.http.get(&url);
-        if let Some(ref token) = self.config.token {
-            request = request.bearer_auth(token);
-        }
-        
-        let response = request.send().await
-            .map_err(|e| CadiError::RegistryError(e.to_string()))?;
-        
-        if !response.status().is_success() {
-            if response.status() == reqwest::StatusCode::NOT_FOUND {
-                return Err(CadiError::ManifestNotFound(manifest_id.to_string()));
-            }
-            return Err(CadiError::RegistryError(
-                format!("HTTP {}", response.status())
-            ));
-        }
-        
-        let manifest: Manifest = response.json().await
-            .map_err(|e| CadiError::RegistryError(e.to_string()))?;
+        let response = self.authorized(self.http.get(&url)).send().await?;
         
-        Ok(manifest)
+        Ok(success(response, manifest_id).await?.json().await?)
     }
 
     /// Publish a manifest
-    pub async fn publish_manifest(&self, manifest: &Manifest) -> CadiResult<PublishResult> {
+    pub async fn publish_manifest(&self, manifest: &Manifest) -> RegistryResult<PublishResult> {
         let url = format!("{}/v1/manifests/{}", self.config.url, manifest.manifest_id);
         
-        let mut request = self.http
-            .put(&url)
-            .json(manifest);
+        let response = self.authorized(self.http.put(&url).json(manifest)).send().await?;
         
-        if let Some(ref token) = self.config.token {
-            request = request.bearer_auth(token);
+        Ok(success(response, &manifest.manifest_id).await?.json().await?)
+    }
+
+    /// Fetch a build cache entry, or `None` if the registry does not have it
+    pub async fn fetch_cache_entry(&self, key: &str) -> RegistryResult<Option<Vec<u8>>> {
+        let url = format!("{}/cache/{}", self.config.url, key);
+
+        let response = self.send_with_retry(|| self.authorized(self.http.get(&url))).await
+            .map_err(|f| f.error)?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
         }
-        
-        let response = request.send().await
-            .map_err(|e| CadiError::RegistryError(e.to_string()))?;
-        
-        if !response.status().is_success() {
-            return Err(CadiError::RegistryError(
-                format!("HTTP {}", response.status())
-            ));
+        Ok(Some(success(response, key).await?.bytes().await?.to_vec()))
+    }
+
+    /// Check if the registry has a build cache entry
+    pub async fn cache_entry_exists(&self, key: &str) -> RegistryResult<bool> {
+        let url = format!("{}/cache/{}", self.config.url, key);
+
+        let response = self.send_with_retry(|| self.authorized(self.http.head(&url))).await
+            .map_err(|f| f.error)?;
+
+        match response.status() {
+            reqwest::StatusCode::NOT_FOUND => Ok(false),
+            status if status.is_success() => Ok(true),
+            _ => Err(RegistryError::from_response(response, key).await),
         }
-        
-        let result: PublishResult = response.json().await
-            .map_err(|e| CadiError::RegistryError(e.to_string()))?;
-        
-        Ok(result)
+    }
+
+    /// Upload a build cache entry
+    pub async fn publish_cache_entry(&self, key: &str, data: &[u8]) -> RegistryResult<()> {
+        let url = format!("{}/cache/{}", self.config.url, key);
+
+        let response = self.send_with_retry(|| {
+            self.authorized(
+                self.http
+                    .put(&url)
+                    .header("Content-Type", "application/octet-stream")
+                    .body(data.to_vec()),
+            )
+        }).await
+            .map_err(|f| f.error)?;
+
+        success(response, key).await?;
+        Ok(())
+    }
+
+    /// Point an alias at a chunk on the registry
+    pub async fn publish_alias(&self, path: &str, chunk_id: &str) -> RegistryResult<crate::types::AliasEntry> {
+        let url = format!("{}/aliases/{}", self.config.url, path);
+        let body = serde_json::json!({ "chunk_id": chunk_id });
+
+        let response = self.send_with_retry(|| self.authorized(self.http.put(&url).json(&body))).await
+            .map_err(|f| f.error)?;
+
+        Ok(success(response, path).await?.json().await?)
+    }
+
+    /// Resolve an alias on the registry, or `None` if it is not registered
+    pub async fn resolve_alias(&self, path: &str) -> RegistryResult<Option<crate::types::AliasEntry>> {
+        let url = format!("{}/aliases/{}", self.config.url, path);
+
+        let response = self.send_with_retry(|| self.authorized(self.http.get(&url))).await
+            .map_err(|f| f.error)?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Ok(Some(success(response, path).await?.json().await?))
+    }
+
+    /// A page of the registry's aliases under `prefix`
+    pub async fn list_aliases(&self, prefix: &str, limit: usize, offset: usize) -> RegistryResult<crate::types::AliasPage> {
+        let url = format!("{}/aliases", self.config.url);
+        let query = [("prefix", prefix.to_string()), ("limit", limit.to_string()), ("offset", offset.to_string())];
+
+        let response = self.send_with_retry(|| self.authorized(self.http.get(&url).query(&query))).await
+            .map_err(|f| f.error)?;
+
+        Ok(success(response, prefix).await?.json().await?)
+    }
+
+    /// Remove an alias from the registry, returning whether it existed
+    pub async fn delete_alias(&self, path: &str) -> RegistryResult<bool> {
+        let url = format!("{}/aliases/{}", self.config.url, path);
+
+        let response = self.send_with_retry(|| self.authorized(self.http.delete(&url))).await
+            .map_err(|f| f.error)?;
+
+        match response.status() {
+            reqwest::StatusCode::NOT_FOUND => Ok(false),
+            status if status.is_success() => Ok(true),
+            _ => Err(RegistryError::from_response(response, path).await),
+        }
+    }
+
+    /// Start re-embedding every chunk with the registry's current embedding
+    /// model. Runs in the background on the registry; an interrupted run is
+    /// resumed.
+    pub async fn start_reembed(&self, batch_size: usize) -> RegistryResult<ReembedStatus> {
+        let url = format!("{}/v1/admin/reembed", self.config.url);
+        let body = serde_json::json!({ "batch_size": batch_size });
+
+        let response = self.send_with_retry(|| self.authorized(self.http.post(&url).json(&body))).await
+            .map_err(|f| f.error)?;
+
+        Ok(success(response, "reembed").await?.json().await?)
+    }
+
+    /// Progress of the registry's current or last re-embedding run
+    pub async fn reembed_status(&self) -> RegistryResult<ReembedStatus> {
+        let url = format!("{}/v1/admin/reembed", self.config.url);
+
+        let response = self.send_with_retry(|| self.authorized(self.http.get(&url))).await
+            .map_err(|f| f.error)?;
+
+        Ok(success(response, "reembed").await?.json().await?)
     }
 
     /// Get registry health status
-    pub async fn health(&self) -> CadiResult<HealthStatus> {
+    pub async fn health(&self) -> RegistryResult<HealthStatus> {
         let url = format!("{}/health", self.config.url);
         
-        let response = self.http.get(&url).send().await
-            .map_err(|e| CadiError::RegistryError(e.to_string()))?;
+        let response = self.http.get(&url).send().await?;
         
         if !response.status().is_success() {
             return Ok(HealthStatus {
@@ -283,10 +621,68 @@ impl RegistryClient {
             });
         }
         
-        let status: HealthStatus = response.json().await
-            .map_err(|e| CadiError::RegistryError(e.to_string()))?;
-        
-        Ok(status)
+        Ok(response.json().await?)
+    }
+
+    fn authorized(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        let request = match self.config.namespace {
+            Some(ref namespace) => request.header(NAMESPACE_HEADER, namespace),
+            None => request,
+        };
+        match self.config.token {
+            Some(ref token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    fn is_retryable(&self, status: reqwest::StatusCode) -> bool {
+        self.config.retry_on_status.contains(&status.as_u16())
+    }
+
+    /// Send a request, retrying network errors and retryable statuses with
+    /// exponential backoff. A `Retry-After` on a 429 stretches the backoff;
+    /// one longer than [`MAX_RETRY_AFTER`] ends the retries. The last
+    /// response is returned once retries run out, so callers still see the
+    /// final status.
+    async fn send_with_retry<F>(&self, build: F) -> Result<reqwest::Response, RequestFailure>
+    where
+        F: Fn() -> reqwest::RequestBuilder,
+    {
+        let mut attempt = 0;
+        loop {
+            let result = build().send().await;
+            let (retry, retry_after) = match &result {
+                Ok(response) => (
+                    self.is_retryable(response.status()),
+                    response.headers().get(reqwest::header::RETRY_AFTER)
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(crate::error::parse_retry_after),
+                ),
+                Err(_) => (true, None),
+            };
+
+            let backoff = Duration::from_millis(self.config.base_backoff_ms.saturating_mul(1u64 << attempt.min(16)));
+            let wait = retry_after.map_or(backoff, |retry_after| retry_after.max(backoff));
+            if !retry || attempt >= self.config.max_retries || wait > MAX_RETRY_AFTER {
+                return result.map_err(|e| RequestFailure {
+                    error: e.into(),
+                    transient: true,
+                });
+            }
+
+            tracing::debug!("Registry request failed, retrying in {}ms", wait.as_millis());
+            tokio::time::sleep(wait).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// The response if it is successful, otherwise the error its status maps to
+async fn success(response: reqwest::Response, resource: &str) -> RegistryResult<reqwest::Response> {
+    if response.status().is_success() {
+        Ok(response)
+    } else {
+        Err(RegistryError::from_response(response, resource).await)
     }
 }
 
@@ -296,6 +692,113 @@ pub struct PublishResult {
     pub success: bool,
     pub chunk_id: Option<String>,
     pub message: Option<String>,
+    /// Set when the chunk was spooled locally instead of published
+    #[serde(default)]
+    pub spooled: bool,
+}
+
+/// Hits and misses of the chunk cache, kept in its `stats.json`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ChunkCacheStats {
+    /// Fetches answered with 304 Not Modified and served from the cache
+    pub hits: u64,
+    /// Fetches that downloaded the chunk
+    pub misses: u64,
+}
+
+impl ChunkCacheStats {
+    /// Statistics of the cache in `dir`; zero when nothing was fetched yet
+    pub fn load(dir: &Path) -> std::io::Result<Self> {
+        match std::fs::read(dir.join(CACHE_STATS_FILE)) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Fraction of fetches served from the cache
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+
+    fn record(dir: &Path, hit: bool) -> std::io::Result<()> {
+        let mut stats = Self::load(dir).unwrap_or_default();
+        if hit {
+            stats.hits += 1;
+        } else {
+            stats.misses += 1;
+        }
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(dir.join(CACHE_STATS_FILE), serde_json::to_vec(&stats)?)?;
+        Ok(())
+    }
+}
+
+const CACHE_STATS_FILE: &str = "stats.json";
+
+/// A cached chunk and its ETag, if both files are present
+fn load_cached_chunk(dir: &Path, chunk_id: &str) -> Option<(String, Vec<u8>)> {
+    let name = file_name(chunk_id);
+    let etag = std::fs::read_to_string(dir.join(format!("{}.etag", name))).ok()?;
+    let data = std::fs::read(dir.join(format!("{}.bin", name))).ok()?;
+    Some((etag, data))
+}
+
+/// Cache layout: `<name>.bin` holds the chunk and `<name>.etag` its ETag.
+/// The ETag is written last, so a partial write is never revalidated.
+fn store_cached_chunk(dir: &Path, chunk_id: &str, etag: &str, data: &[u8]) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let name = file_name(chunk_id);
+    std::fs::write(dir.join(format!("{}.bin", name)), data)?;
+    std::fs::write(dir.join(format!("{}.etag", name)), etag)?;
+    Ok(())
+}
+
+/// A file name for a chunk ID
+fn file_name(chunk_id: &str) -> String {
+    chunk_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Result of flushing the offline spool
+#[derive(Debug, Default)]
+pub struct SpoolFlushResult {
+    /// Chunks published and removed from the spool
+    pub flushed: Vec<String>,
+    /// Chunks that are still spooled
+    pub remaining: Vec<String>,
+}
+
+const SPOOL_EXTENSION: &str = "spool";
+
+/// Spool file layout: the chunk ID on the first line, then the raw data
+fn spool_chunk(dir: &Path, chunk_id: &str, data: &[u8]) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let name = file_name(chunk_id);
+
+    let mut content = Vec::with_capacity(chunk_id.len() + 1 + data.len());
+    content.extend_from_slice(chunk_id.as_bytes());
+    content.push(b'\n');
+    content.extend_from_slice(data);
+
+    std::fs::write(dir.join(format!("{}.{}", name, SPOOL_EXTENSION)), content)?;
+    Ok(())
+}
+
+fn read_spooled(path: &Path) -> std::io::Result<(String, Vec<u8>)> {
+    let content = std::fs::read(path)?;
+    let split = content.iter().position(|&b| b == b'\n').ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Malformed spool file: {}", path.display()))
+    })?;
+    let chunk_id = String::from_utf8_lossy(&content[..split]).into_owned();
+    Ok((chunk_id, content[split + 1..].to_vec()))
 }
 
 /// Search query
@@ -309,6 +812,24 @@ pub struct SearchQuery {
     pub language: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cadi_type: Option<String>,
+    /// Only chunks of this granularity
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub granularity: Option<ChunkGranularity>,
+    /// Only chunks in at least one of these categories
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<ChunkCategory>,
+    /// Only chunks whose category was inferred with at least this confidence
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_category_confidence: Option<f32>,
+    /// Only chunks up to this size in bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<usize>,
+    /// Only chunks with at least this quality score
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_quality: Option<f32>,
+    /// Only chunks whose measured complexity is at most this
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_complexity: Option<f32>,
     #[serde(default)]
     pub limit: usize,
     #[serde(default)]
@@ -322,6 +843,12 @@ impl Default for SearchQuery {
             concepts: None,
             language: None,
             cadi_type: None,
+            granularity: None,
+            categories: Vec::new(),
+            min_category_confidence: None,
+            max_size: None,
+            min_quality: None,
+            max_complexity: None,
             limit: 20,
             offset: 0,
         }
@@ -341,12 +868,31 @@ pub struct RegistrySearchResult {
 #[derive(Debug, serde::Deserialize)]
 pub struct ChunkSummary {
     pub chunk_id: String,
+    #[serde(default)]
     pub name: String,
+    #[serde(default)]
     pub cadi_type: String,
     #[serde(default)]
     pub concepts: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Size in bytes, when the registry reports it
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+
+/// State of re-embedding on a registry
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ReembedStatus {
+    /// Whether a run is in progress
+    pub running: bool,
+    /// Progress of the current or last run
+    pub progress: Option<crate::db::ReembedProgress>,
+    /// Why the last run stopped early, if it failed
+    #[serde(default)]
+    pub error: Option<String>,
 }
 
 /// Health status of the registry
@@ -0,0 +1,72 @@
+//! Dependency closure of a chunk
+//!
+//! Walks the `composed_of`/`requires` references of a chunk breadth-first so
+//! a client can learn everything it needs in one request.
+
+use std::collections::{HashSet, VecDeque};
+use std::future::Future;
+
+use cadi_core::{chunk_references, hash::sha256_bytes};
+
+use crate::types::{ChunkClosure, ClosureEntry};
+
+/// Most chunks a single closure will visit
+pub const MAX_CLOSURE_NODES: usize = 10_000;
+
+/// Compute the closure of `root`, following references at most `max_depth`
+/// levels deep and visiting at most `node_limit` chunks. `lookup` returns a
+/// chunk's content, or `None` if it is unknown; unknown references are
+/// listed under `missing`. Returns `None` if `root` itself is unknown.
+///
+/// Each chunk is visited once, so reference cycles terminate.
+pub async fn resolve_closure<F, Fut>(
+    root: &str,
+    max_depth: Option<usize>,
+    node_limit: usize,
+    mut lookup: F,
+) -> Option<ChunkClosure>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Option<Vec<u8>>>,
+{
+    let mut closure = ChunkClosure {
+        root: root.to_string(),
+        ..Default::default()
+    };
+    let mut seen = HashSet::from([root.to_string()]);
+    let mut queue = VecDeque::from([(root.to_string(), 0)]);
+
+    while let Some((chunk_id, depth)) = queue.pop_front() {
+        let Some(data) = lookup(chunk_id.clone()).await else {
+            if chunk_id == root {
+                return None;
+            }
+            closure.missing.push(chunk_id);
+            continue;
+        };
+
+        closure.total_size += data.len() as u64;
+        closure.chunks.push(ClosureEntry {
+            chunk_id,
+            size: data.len() as u64,
+            content_hash: sha256_bytes(&data),
+        });
+
+        if max_depth.is_some_and(|max| depth >= max) {
+            continue;
+        }
+        for reference in chunk_references(&data) {
+            if seen.contains(&reference) {
+                continue;
+            }
+            if seen.len() >= node_limit {
+                closure.truncated = true;
+                break;
+            }
+            seen.insert(reference.clone());
+            queue.push_back((reference, depth + 1));
+        }
+    }
+
+    Some(closure)
+}
@@ -1,14 +1,15 @@
 //! CADI Registry Database Layer
 //!
 //! Provides SurrealDB integration for:
-//! - Vector search with MTREE indexing
+//! - Vector search with MTREE indexing sized to the embedding provider
 //! - Chunk storage and retrieval
 //! - Metadata normalization and indexing
 
-use cadi_core::{CadiError, CadiResult, Chunk};
+use cadi_core::{chunk_storage_key, CadiError, CadiResult, Chunk, ChunkCategory, ChunkGranularity, ChunkId, ChunkMetrics};
 use cadi_llm::embeddings::EmbeddingManager;
+use crate::types::{AliasEntry, AliasPage, ChunkUsageStats, DailyUsage};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use surrealdb::engine::local::Db;
 use surrealdb::Surreal;
 
@@ -21,8 +22,18 @@ pub struct ChunkRecord {
     pub language: String,
     pub metadata: ChunkMetadata,
     pub embedding: Option<Vec<f32>>,
+    /// Model that generated `embedding`
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+    /// Length of `embedding` when it was generated
+    #[serde(default)]
+    pub embedding_dimension: Option<usize>,
     pub created_at: chrono::DateTime<chrono::Utc>,
+    /// Fetches and build plan appearances, see [`RegistryDatabase::record_usage`]
     pub usage_count: u64,
+    /// When the chunk was last used (RFC 3339)
+    #[serde(default)]
+    pub last_used_at: Option<String>,
 }
 
 /// Normalized metadata for search indexing
@@ -37,24 +48,199 @@ pub struct ChunkMetadata {
     pub function_signatures: Vec<String>,
     pub quality_score: f64,
     pub test_coverage: f64,
+    pub granularity: Option<ChunkGranularity>,
+    pub categories: Vec<ChunkCategory>,
+    /// Confidence (0.0-1.0) in the first category, if it was inferred
+    pub category_confidence: Option<f64>,
+    /// Content size in bytes
+    pub size: usize,
+    /// Publisher signatures (`ed25519:<public key>:<signature>`)
+    pub signatures: Vec<String>,
+    /// Source metrics computed when the chunk was imported
+    pub metrics: Option<ChunkMetrics>,
 }
 
 /// Search query structure
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct SearchQuery {
     pub text: Option<String>,
     pub embedding: Option<Vec<f32>>,
     pub language: Option<String>,
     pub limit: usize,
     pub min_score: f64,
+    /// Only chunks of this granularity
+    #[serde(default)]
+    pub granularity: Option<ChunkGranularity>,
+    /// Only chunks in at least one of these categories
+    #[serde(default)]
+    pub categories: Vec<ChunkCategory>,
+    /// Only chunks whose category was inferred with at least this
+    /// confidence, or set explicitly; chunks without one are excluded
+    #[serde(default)]
+    pub min_category_confidence: Option<f32>,
+    /// Only chunks up to this size in bytes
+    #[serde(default)]
+    pub max_size: Option<usize>,
+    /// Only chunks with at least this quality score
+    #[serde(default)]
+    pub min_quality: Option<f32>,
+    /// Only chunks with at most this cyclomatic complexity; chunks without
+    /// metrics are excluded
+    #[serde(default)]
+    pub max_complexity: Option<f32>,
+    /// Which signals contribute to the score
+    #[serde(default)]
+    pub mode: SearchMode,
+    /// Weight split between the score components
+    #[serde(default)]
+    pub weights: HybridWeights,
+    /// Boost frequently used chunks by [`USAGE_WEIGHT`] times their
+    /// normalized usage count
+    #[serde(default)]
+    pub rank_by_usage: bool,
+}
+
+/// Signals used to score search results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// Keyword matching on `text` only
+    Keyword,
+    /// Vector similarity on `embedding` only
+    Semantic,
+    /// Weighted combination of keyword and vector scores
+    #[default]
+    Hybrid,
+}
+
+/// Relative weights of the hybrid score components
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HybridWeights {
+    /// Weight of the cosine similarity to the query embedding
+    pub semantic: f64,
+    /// Weight of the BM25 keyword score
+    pub keyword: f64,
+    /// Weight of the chunk's quality score and test coverage
+    pub quality: f64,
+}
+
+impl Default for HybridWeights {
+    fn default() -> Self {
+        Self {
+            semantic: 0.5,
+            keyword: 0.3,
+            quality: 0.2,
+        }
+    }
 }
 
+impl SearchQuery {
+    /// Whether any structural filter is set
+    pub fn has_filters(&self) -> bool {
+        self.granularity.is_some()
+            || !self.categories.is_empty()
+            || self.min_category_confidence.is_some()
+            || self.max_size.is_some()
+            || self.min_quality.is_some()
+            || self.max_complexity.is_some()
+    }
+
+    /// Check chunk metadata against the structural filters
+    pub fn matches(&self, metadata: &ChunkMetadata) -> bool {
+        self.granularity.is_none_or(|g| metadata.granularity == Some(g))
+            && (self.categories.is_empty()
+                || self.categories.iter().any(|c| metadata.categories.contains(c)))
+            && self.min_category_confidence.is_none_or(|min| {
+                metadata.category_confidence.is_some_and(|c| c >= min as f64)
+            })
+            && self.max_size.is_none_or(|max| metadata.size <= max)
+            && self.min_quality.is_none_or(|min| metadata.quality_score >= min as f64)
+            && self.max_complexity.is_none_or(|max| {
+                metadata.metrics.as_ref().and_then(|m| m.complexity).is_some_and(|c| c <= max)
+            })
+    }
+
+    /// SurrealQL `WHERE` clause for the structural filters on `chunk_metadata`
+    fn filter_clause(&self) -> String {
+        let mut conditions = Vec::new();
+        if self.granularity.is_some() {
+            conditions.push("granularity = $granularity");
+        }
+        if !self.categories.is_empty() {
+            conditions.push("categories CONTAINSANY $categories");
+        }
+        if self.min_category_confidence.is_some() {
+            conditions.push("category_confidence != NONE AND category_confidence >= $min_category_confidence");
+        }
+        if self.max_size.is_some() {
+            conditions.push("size <= $max_size");
+        }
+        if self.min_quality.is_some() {
+            conditions.push("quality_score >= $min_quality");
+        }
+        if self.max_complexity.is_some() {
+            conditions.push("metrics.complexity != NONE AND metrics.complexity <= $max_complexity");
+        }
+
+        if conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", conditions.join(" AND "))
+        }
+    }
+}
+
+/// Vector index dimension used when no embedding manager is configured
+pub const DEFAULT_EMBEDDING_DIMENSION: usize = 384;
+
+/// Weight of the usage score when a query ranks by usage
+pub const USAGE_WEIGHT: f64 = 0.2;
+
+/// Days of daily usage buckets kept per chunk
+pub const USAGE_RETENTION_DAYS: i64 = 90;
+
 /// Search result from database
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DbSearchResult {
     pub chunk_id: String,
     pub score: f64,
     pub metadata: ChunkMetadata,
+    /// Unweighted component scores explaining the match
+    #[serde(default)]
+    pub scores: ScoreBreakdown,
+}
+
+/// Component scores of a search result, each in `0.0..=1.0`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    /// Cosine similarity to the query embedding, if it was a vector hit
+    pub semantic: Option<f64>,
+    /// Normalized BM25 score against name, description and concepts, if
+    /// it was a keyword hit
+    pub keyword: Option<f64>,
+    /// Quality score and test coverage
+    pub quality: f64,
+    /// Usage count relative to the most used result (log-scaled), if the
+    /// query ranked by usage
+    #[serde(default)]
+    pub usage: Option<f64>,
+}
+
+/// Progress of re-embedding the registry with a new model, see
+/// [`RegistryDatabase::reembed_all`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReembedProgress {
+    /// Model the chunks are being re-embedded with
+    pub model: String,
+    /// Chunks re-embedded so far, including by interrupted earlier runs
+    pub processed: usize,
+    /// Chunks in the registry when the run started
+    pub total: usize,
+    /// Hash of the last chunk re-embedded; the run continues after it
+    pub cursor: Option<String>,
+    /// Whether every chunk was re-embedded and the index rebuilt
+    pub done: bool,
 }
 
 /// Registry database manager
@@ -69,7 +255,10 @@ impl RegistryDatabase {
         // Initialize schema
         Self::init_schema(&db).await?;
 
-        Ok(Self { db, embedding_manager })
+        let mut registry = Self { db, embedding_manager };
+        registry.init_embedding_index().await?;
+
+        Ok(registry)
     }
 
     /// Initialize database schema
@@ -78,6 +267,9 @@ impl RegistryDatabase {
         let schema = r#"
             DEFINE TABLE chunk SCHEMAFULL;
             DEFINE TABLE chunk_metadata SCHEMAFULL;
+            DEFINE TABLE registry_meta SCHEMALESS;
+            DEFINE TABLE alias SCHEMAFULL;
+            DEFINE TABLE chunk_usage SCHEMAFULL;
 
             DEFINE FIELD id ON chunk;
             DEFINE FIELD hash ON chunk TYPE string;
@@ -87,9 +279,12 @@ impl RegistryDatabase {
             DEFINE FIELD concepts ON chunk TYPE array;
             DEFINE FIELD quality_score ON chunk TYPE float;
             DEFINE FIELD test_coverage ON chunk TYPE float;
-            DEFINE FIELD embedding ON chunk TYPE array;
+            DEFINE FIELD embedding ON chunk TYPE option<array<float>>;
+            DEFINE FIELD embedding_model ON chunk TYPE option<string>;
+            DEFINE FIELD embedding_dimension ON chunk TYPE option<int>;
             DEFINE FIELD created_at ON chunk TYPE string DEFAULT time::now();
             DEFINE FIELD usage_count ON chunk TYPE int DEFAULT 0;
+            DEFINE FIELD last_used_at ON chunk TYPE option<string>;
 
             DEFINE FIELD chunk_id ON chunk_metadata TYPE string;
             DEFINE FIELD name ON chunk_metadata TYPE string;
@@ -100,9 +295,20 @@ impl RegistryDatabase {
             DEFINE FIELD function_signatures ON chunk_metadata TYPE array;
             DEFINE FIELD quality_score ON chunk_metadata TYPE float;
             DEFINE FIELD test_coverage ON chunk_metadata TYPE float;
+            DEFINE FIELD granularity ON chunk_metadata TYPE option<string>;
+            DEFINE FIELD categories ON chunk_metadata TYPE array DEFAULT [];
+            DEFINE FIELD category_confidence ON chunk_metadata TYPE option<float>;
+            DEFINE FIELD size ON chunk_metadata TYPE int DEFAULT 0;
+            DEFINE FIELD signatures ON chunk_metadata TYPE array DEFAULT [];
+            DEFINE FIELD metrics ON chunk_metadata FLEXIBLE TYPE option<object>;
 
-            -- Vector index for semantic search
-            DEFINE INDEX embedding_mtree ON chunk FIELDS embedding MTREE DIMENSION 5 DIST EUCLIDEAN;
+            DEFINE FIELD path ON alias TYPE string;
+            DEFINE FIELD chunk_id ON alias TYPE string;
+            DEFINE FIELD updated_at ON alias TYPE string;
+
+            DEFINE FIELD hash ON chunk_usage TYPE string;
+            DEFINE FIELD day ON chunk_usage TYPE string;
+            DEFINE FIELD count ON chunk_usage TYPE int DEFAULT 0;
 
             -- Text indexes for metadata search
             DEFINE INDEX metadata_name ON chunk FIELDS metadata.name;
@@ -113,34 +319,199 @@ impl RegistryDatabase {
             DEFINE INDEX chunk_metadata_chunk_id ON chunk_metadata FIELDS chunk_id UNIQUE;
             DEFINE INDEX chunk_metadata_name ON chunk_metadata FIELDS name;
             DEFINE INDEX chunk_metadata_concepts ON chunk_metadata FIELDS concepts;
+            DEFINE INDEX chunk_metadata_granularity ON chunk_metadata FIELDS granularity;
+
+            -- One chunk per alias path
+            DEFINE INDEX alias_path ON alias FIELDS path UNIQUE;
+
+            -- Daily usage buckets of a chunk
+            DEFINE INDEX chunk_usage_hash ON chunk_usage FIELDS hash;
         "#;
 
         db.query(schema).await.map_err(|e| CadiError::DatabaseError(e.to_string()))?;
         Ok(())
     }
 
+    /// Define the vector index for the embedding manager's dimension.
+    ///
+    /// The dimension is recorded in `registry_meta`. When it changes (for
+    /// example after switching embedding providers) the index is dropped,
+    /// chunks with embeddings of the old dimension are re-embedded and the
+    /// index is rebuilt.
+    async fn init_embedding_index(&mut self) -> CadiResult<()> {
+        let stored = self.stored_embedding_dimension().await?;
+        let dimension = match (&self.embedding_manager, stored) {
+            (Some(manager), _) => manager.dimension(),
+            (None, Some(stored)) => stored,
+            (None, None) => self.existing_embedding_dimension().await?.unwrap_or(DEFAULT_EMBEDDING_DIMENSION),
+        };
+
+        if stored == Some(dimension) {
+            return Ok(());
+        }
+
+        // The index may not exist yet, so the result is not checked
+        self.db.query("REMOVE INDEX embedding_mtree ON chunk")
+            .await
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+
+        let mut response = self.db.query(
+            "SELECT hash FROM chunk WHERE embedding != NONE AND array::len(embedding) != $dimension",
+        )
+            .bind(("dimension", dimension))
+            .await
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+        let stale: Vec<serde_json::Value> = response.take(0)
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+
+        if !stale.is_empty() {
+            tracing::info!(
+                "Embedding dimension changed from {:?} to {}, re-embedding {} chunks",
+                stored,
+                dimension,
+                stale.len()
+            );
+        }
+
+        for row in stale {
+            let Some(hash) = row.get("hash").and_then(|h| h.as_str()) else {
+                continue;
+            };
+            let (chunk_id, metadata) = self.metadata_for_hash(hash).await?;
+            let embedding = self.embed_metadata(&chunk_id, &metadata).await?;
+            self.set_embedding(hash, embedding).await?;
+        }
+
+        self.define_embedding_index(dimension).await
+    }
+
+    /// Define the vector index for `dimension` and record the dimension and
+    /// model it was built for
+    async fn define_embedding_index(&self, dimension: usize) -> CadiResult<()> {
+        let sql = format!(
+            r#"
+            DEFINE INDEX embedding_mtree ON chunk FIELDS embedding MTREE DIMENSION {} DIST EUCLIDEAN;
+            UPDATE registry_meta:schema SET embedding_dimension = $dimension, embedding_model = $model;
+            "#,
+            dimension
+        );
+        self.db.query(sql)
+            .bind(("dimension", dimension))
+            .bind(("model", self.embedding_model()))
+            .await
+            .map_err(|e| CadiError::DatabaseError(format!("Embedding index rebuild failed: {}", e)))?
+            .check()
+            .map_err(|e| CadiError::DatabaseError(format!("Embedding index rebuild failed: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// A chunk's ID and `chunk_metadata` row, looked up by content hash
+    async fn metadata_for_hash(&self, hash: &str) -> CadiResult<(String, serde_json::Value)> {
+        let chunk_id = ChunkId::from_storage_key(hash).to_string();
+
+        let mut meta_response = self.db.query("SELECT * FROM chunk_metadata WHERE chunk_id = $chunk_id OR chunk_id = $hash")
+            .bind(("chunk_id", &chunk_id))
+            .bind(("hash", hash))
+            .await
+            .map_err(|e| CadiError::DatabaseError(format!("Metadata fetch failed: {}", e)))?;
+        let meta_results: Vec<serde_json::Value> = meta_response.take(0)
+            .map_err(|e| CadiError::DatabaseError(format!("Metadata parse failed: {}", e)))?;
+        let metadata = meta_results.into_iter().next().unwrap_or_default();
+        let chunk_id = metadata.get("chunk_id").and_then(|c| c.as_str()).unwrap_or(&chunk_id).to_string();
+
+        Ok((chunk_id, metadata))
+    }
+
+    /// Replace a chunk's embedding, recording the model that generated it
+    async fn set_embedding(&self, hash: &str, embedding: Option<Vec<f32>>) -> CadiResult<()> {
+        self.db.query(
+            "UPDATE chunk SET embedding = $embedding, embedding_model = $model, embedding_dimension = $dimension WHERE hash = $hash",
+        )
+            .bind(("dimension", embedding.as_ref().map(|e| e.len())))
+            .bind(("model", embedding.as_ref().and(self.embedding_model())))
+            .bind(("embedding", embedding))
+            .bind(("hash", hash))
+            .await
+            .map_err(|e| CadiError::DatabaseError(format!("Chunk update failed: {}", e)))?
+            .check()
+            .map_err(|e| CadiError::DatabaseError(format!("Chunk update failed: {}", e)))?;
+        Ok(())
+    }
+
+    /// Model of the configured embedding manager
+    fn embedding_model(&self) -> Option<String> {
+        self.embedding_manager.as_ref().map(|m| m.model_id())
+    }
+
+    /// Dimension of embeddings already stored, for databases created before
+    /// the dimension was recorded
+    async fn existing_embedding_dimension(&self) -> CadiResult<Option<usize>> {
+        let mut response = self.db.query("SELECT array::len(embedding) AS dimension FROM chunk WHERE embedding != NONE LIMIT 1")
+            .await
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+        let rows: Vec<serde_json::Value> = response.take(0)
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+
+        Ok(rows
+            .first()
+            .and_then(|row| row.get("dimension"))
+            .and_then(|d| d.as_u64())
+            .map(|d| d as usize))
+    }
+
+    /// Embedding dimension the vector index was last built for
+    async fn stored_embedding_dimension(&self) -> CadiResult<Option<usize>> {
+        let mut response = self.db.query("SELECT embedding_dimension FROM registry_meta:schema")
+            .await
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+        let rows: Vec<serde_json::Value> = response.take(0)
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+
+        Ok(rows
+            .first()
+            .and_then(|row| row.get("embedding_dimension"))
+            .and_then(|d| d.as_u64())
+            .map(|d| d as usize))
+    }
+
+    /// Generate an embedding from chunk metadata, if a manager is configured
+    async fn embed_metadata(&mut self, chunk_id: &str, metadata: &serde_json::Value) -> CadiResult<Option<Vec<f32>>> {
+        let Some(manager) = self.embedding_manager.as_mut() else {
+            return Ok(None);
+        };
+
+        let content_for_embedding = content_for_embedding(metadata);
+        let embedding = manager.get_chunk_embedding(chunk_id, &content_for_embedding).await
+            .map_err(|e| CadiError::RegistryError(format!("Embedding generation failed: {}", e)))?;
+
+        let dimension = manager.dimension();
+        if embedding.len() != dimension {
+            return Err(CadiError::RegistryError(format!(
+                "Embedding for {} has dimension {}, expected {}",
+                chunk_id,
+                embedding.len(),
+                dimension
+            )));
+        }
+
+        Ok(Some(embedding))
+    }
+
     /// Store a chunk with metadata and embedding
     pub async fn store_chunk(&mut self, chunk: &Chunk, content: &str, metadata: serde_json::Value) -> CadiResult<String> {
         let chunk_id = chunk.chunk_id.clone();
-        let hash = chunk.chunk_id.strip_prefix("chunk:sha256:").unwrap_or(&chunk.chunk_id).to_string();
+        let hash = chunk_storage_key(&chunk.chunk_id);
 
         // Generate embedding if manager available
-        let embedding = if let Some(ref mut manager) = self.embedding_manager {
-            // Create content for embedding from metadata
-            let name = metadata.get("name").and_then(|n| n.as_str()).unwrap_or("");
-            let description = metadata.get("description").and_then(|d| d.as_str()).unwrap_or("");
-            let concepts = metadata.get("concepts")
-                .and_then(|c| c.as_array())
-                .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>().join(" "))
-                .unwrap_or_default();
-            let content_for_embedding = format!("{} {} {}", name, description, concepts);
-            match manager.get_chunk_embedding(&chunk_id, &content_for_embedding).await {
-                Ok(embedding) => Some(embedding),
-                Err(e) => return Err(CadiError::RegistryError(format!("Embedding generation failed: {}", e))),
+        let embedding = self.embed_metadata(&chunk_id, &metadata).await?;
+
+        let mut signatures = chunk.signatures.clone();
+        for signature in metadata_signatures(&metadata).unwrap_or_default() {
+            if !signatures.contains(&signature) {
+                signatures.push(signature);
             }
-        } else {
-            None
-        };
+        }
 
         // Store in database (metadata stored in separate table)
         let sql = r#"
@@ -154,6 +525,8 @@ impl RegistryDatabase {
                 quality_score = $quality_score,
                 test_coverage = $test_coverage,
                 embedding = $embedding,
+                embedding_model = $embedding_model,
+                embedding_dimension = $embedding_dimension,
                 created_at = time::now()
         "#;
 
@@ -162,11 +535,13 @@ impl RegistryDatabase {
             .bind(("id", &chunk_id))
             .bind(("hash", &hash))
             .bind(("content", content))
-            .bind(("language", "typescript"))
+            .bind(("language", metadata.get("language").and_then(|l| l.as_str()).unwrap_or("")))
             .bind(("concepts", metadata.get("concepts").and_then(|c| c.as_array()).unwrap_or(&vec![])))
             .bind(("quality_score", metadata.get("quality_score").and_then(|q| q.as_f64()).unwrap_or(0.0)))
             .bind(("test_coverage", metadata.get("test_coverage").and_then(|t| t.as_f64()).unwrap_or(0.0)))
             .bind(("embedding", &embedding))
+            .bind(("embedding_model", embedding.as_ref().and(self.embedding_model())))
+            .bind(("embedding_dimension", embedding.as_ref().map(|e| e.len())))
             .await
             .map_err(|e| CadiError::DatabaseError(format!("Store query failed: {}", e)))?;
 
@@ -183,7 +558,13 @@ impl RegistryDatabase {
                 dependencies = $dependencies,
                 function_signatures = $function_signatures,
                 quality_score = $quality_score,
-                test_coverage = $test_coverage
+                test_coverage = $test_coverage,
+                granularity = $granularity,
+                categories = $categories,
+                category_confidence = $category_confidence,
+                size = $size,
+                signatures = $signatures,
+                metrics = $metrics
         "#;
 
         let metadata_result = self.db.query(metadata_sql)
@@ -196,6 +577,12 @@ impl RegistryDatabase {
             .bind(("function_signatures", metadata.get("function_signatures").and_then(|c| c.as_array()).unwrap_or(&vec![])))
             .bind(("quality_score", metadata.get("quality_score").and_then(|q| q.as_f64()).unwrap_or(0.0)))
             .bind(("test_coverage", metadata.get("test_coverage").and_then(|t| t.as_f64()).unwrap_or(0.0)))
+            .bind(("granularity", metadata.get("granularity").and_then(|g| g.as_str())))
+            .bind(("categories", metadata.get("categories").and_then(|c| c.as_array()).unwrap_or(&vec![])))
+            .bind(("category_confidence", metadata.get("category_confidence").and_then(|c| c.as_f64())))
+            .bind(("size", metadata.get("size").and_then(|s| s.as_u64()).unwrap_or(content.len() as u64)))
+            .bind(("signatures", &signatures))
+            .bind(("metrics", metadata_metrics(&metadata)))
             .await
             .map_err(|e| CadiError::DatabaseError(format!("Metadata store failed: {}", e)))?;
 
@@ -241,21 +628,7 @@ impl RegistryDatabase {
     /// Update metadata for an existing chunk
     pub async fn update_chunk_metadata(&mut self, chunk_id: &str, metadata: serde_json::Value) -> CadiResult<()> {
         // Regenerate embedding if manager available
-        let embedding = if let Some(ref mut manager) = self.embedding_manager {
-            let name = metadata.get("name").and_then(|n| n.as_str()).unwrap_or("");
-            let description = metadata.get("description").and_then(|d| d.as_str()).unwrap_or("");
-            let concepts = metadata.get("concepts")
-                .and_then(|c| c.as_array())
-                .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>().join(" "))
-                .unwrap_or_default();
-            let content_for_embedding = format!("{} {} {}", name, description, concepts);
-            match manager.get_chunk_embedding(chunk_id, &content_for_embedding).await {
-                Ok(emb) => Some(emb),
-                Err(e) => return Err(CadiError::RegistryError(format!("Embedding generation failed: {}", e))),
-            }
-        } else {
-            None
-        };
+        let embedding = self.embed_metadata(chunk_id, &metadata).await?;
 
         println!("DEBUG: Updating chunk {} with metadata: {}", chunk_id, metadata);
 
@@ -265,7 +638,9 @@ impl RegistryDatabase {
                 concepts = $concepts,
                 quality_score = $quality_score,
                 test_coverage = $test_coverage,
-                embedding = $embedding
+                embedding = $embedding,
+                embedding_model = $embedding_model,
+                embedding_dimension = $embedding_dimension
             WHERE id = $chunk_id OR hash = $chunk_id
         "#;
 
@@ -275,6 +650,8 @@ impl RegistryDatabase {
             .bind(("quality_score", metadata.get("quality_score").and_then(|q| q.as_f64()).unwrap_or(0.0)))
             .bind(("test_coverage", metadata.get("test_coverage").and_then(|t| t.as_f64()).unwrap_or(0.0)))
             .bind(("embedding", &embedding))
+            .bind(("embedding_model", embedding.as_ref().and(self.embedding_model())))
+            .bind(("embedding_dimension", embedding.as_ref().map(|e| e.len())))
             .await
             .map_err(|e| CadiError::DatabaseError(format!("Chunk update failed: {}", e)))?;
 
@@ -288,7 +665,12 @@ impl RegistryDatabase {
                 dependencies = $dependencies,
                 function_signatures = $function_signatures,
                 quality_score = $quality_score,
-                test_coverage = $test_coverage
+                test_coverage = $test_coverage,
+                granularity = $granularity,
+                categories = $categories,
+                category_confidence = $category_confidence,
+                signatures = $signatures ?? signatures,
+                metrics = $metrics ?? metrics
             WHERE chunk_id = $chunk_id
         "#;
 
@@ -302,6 +684,12 @@ impl RegistryDatabase {
             .bind(("function_signatures", metadata.get("function_signatures").and_then(|c| c.as_array()).unwrap_or(&vec![])))
             .bind(("quality_score", metadata.get("quality_score").and_then(|q| q.as_f64()).unwrap_or(0.0)))
             .bind(("test_coverage", metadata.get("test_coverage").and_then(|t| t.as_f64()).unwrap_or(0.0)))
+            .bind(("granularity", metadata.get("granularity").and_then(|g| g.as_str())))
+            .bind(("categories", metadata.get("categories").and_then(|c| c.as_array()).unwrap_or(&vec![])))
+            .bind(("category_confidence", metadata.get("category_confidence").and_then(|c| c.as_f64())))
+            // Signatures are kept unless the update carries its own
+            .bind(("signatures", metadata_signatures(&metadata)))
+            .bind(("metrics", metadata_metrics(&metadata)))
             .await
             .map_err(|e| CadiError::DatabaseError(format!("Metadata update failed: {}", e)))?;
 
@@ -311,120 +699,296 @@ impl RegistryDatabase {
     }
 
     /// Perform hybrid search
+    ///
+    /// Keyword and vector hits are merged and scored as a weighted sum of
+    /// their component scores, see [`HybridWeights`].
     pub async fn search(&self, query: SearchQuery) -> CadiResult<Vec<DbSearchResult>> {
         let mut text_results = Vec::new();
         let mut semantic_results = Vec::new();
 
-        // Text-based search
-        if let Some(text) = &query.text {
-            text_results = self.text_search(text, query.limit * 2).await?;
+        // Keyword search
+        if let (Some(text), SearchMode::Keyword | SearchMode::Hybrid) = (&query.text, query.mode) {
+            text_results = self.text_search(text, &query, query.limit * 2).await?;
         }
 
         // Semantic search
-        if let Some(embedding) = &query.embedding {
+        if let (Some(embedding), SearchMode::Semantic | SearchMode::Hybrid) = (&query.embedding, query.mode) {
+            self.warn_if_mixed_models().await?;
             semantic_results = self.semantic_search(embedding, query.limit * 2).await?;
         }
 
-        // Combine results with weighted scoring
-        let mut combined = HashMap::new();
-        
-        // Add text results
+        // Merge the component scores of each chunk
+        let mut combined: HashMap<String, DbSearchResult> = HashMap::new();
+
         for result in text_results {
             combined.entry(result.chunk_id.clone())
-                .or_insert_with(|| DbSearchResult {
-                    chunk_id: result.chunk_id.clone(),
-                    score: 0.0,
-                    metadata: result.metadata.clone(),
-                })
-                .score += result.score * 0.3; // 30% weight for text
+                .or_insert_with(|| result.clone())
+                .scores.keyword = result.scores.keyword;
         }
 
-        // Add semantic results
         for result in semantic_results {
-            let entry = combined.entry(result.chunk_id.clone())
-                .or_insert_with(|| DbSearchResult {
-                    chunk_id: result.chunk_id.clone(),
-                    score: 0.0,
-                    metadata: result.metadata.clone(),
-                });
-            entry.score += result.score * 0.5; // 50% weight for semantic
+            combined.entry(result.chunk_id.clone())
+                .or_insert_with(|| result.clone())
+                .scores.semantic = result.scores.semantic;
         }
 
-        // Add quality/usage boost to all results
+        // Usage counts are log-scaled against the most used candidate so a
+        // handful of very popular chunks don't drown out relevance
+        let usage = if query.rank_by_usage {
+            let chunk_ids: Vec<String> = combined.keys().cloned().collect();
+            self.usage_counts(&chunk_ids).await?
+        } else {
+            HashMap::new()
+        };
+        let max_usage = usage.values().copied().max().unwrap_or(0);
+
+        let weights = query.weights;
         for result in combined.values_mut() {
-            let quality_boost = (result.metadata.quality_score * 0.5 + 
-                               result.metadata.test_coverage.min(1.0) * 0.5) * 0.2;
-            result.score += quality_boost;
+            result.scores.quality = result.metadata.quality_score.clamp(0.0, 1.0) * 0.5
+                + result.metadata.test_coverage.clamp(0.0, 1.0) * 0.5;
+            if query.rank_by_usage {
+                let count = usage.get(&result.chunk_id).copied().unwrap_or(0);
+                result.scores.usage = Some(if max_usage > 0 {
+                    (count as f64).ln_1p() / (max_usage as f64).ln_1p()
+                } else {
+                    0.0
+                });
+            }
+            result.score = weights.semantic * result.scores.semantic.unwrap_or(0.0)
+                + weights.keyword * result.scores.keyword.unwrap_or(0.0)
+                + weights.quality * result.scores.quality
+                + USAGE_WEIGHT * result.scores.usage.unwrap_or(0.0);
         }
 
         let mut final_results: Vec<_> = combined.into_values().collect();
 
-        // Filter by minimum score and language
-        final_results.retain(|r| r.score >= query.min_score);
+        // Filter by minimum score, language and structural filters
+        final_results.retain(|r| r.score >= query.min_score && query.matches(&r.metadata));
         if let Some(lang) = &query.language {
             final_results.retain(|r| r.metadata.language == *lang);
         }
 
         // Sort by score and limit
-        final_results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        final_results.sort_by(|a, b| b.score.total_cmp(&a.score));
         final_results.truncate(query.limit);
 
         Ok(final_results)
     }
 
-    /// Text-based search using BM25-like scoring
-    async fn text_search(&self, query: &str, limit: usize) -> CadiResult<Vec<DbSearchResult>> {
-        // Get all metadata records and filter in Rust
-        let meta_sql = "SELECT * FROM chunk_metadata LIMIT 1000"; // Reasonable limit for search
+    /// Models that generated the stored embeddings, with a chunk count for
+    /// each. Embeddings stored before models were recorded count as `None`.
+    pub async fn embedding_models(&self) -> CadiResult<Vec<(Option<String>, u64)>> {
+        let mut response = self.db.query(
+            "SELECT embedding_model, count() AS chunks FROM chunk WHERE embedding != NONE GROUP BY embedding_model",
+        )
+            .await
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+        let rows: Vec<serde_json::Value> = response.take(0)
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let model = row.get("embedding_model").and_then(|m| m.as_str()).map(String::from);
+                (model, row.get("chunks").and_then(|c| c.as_u64()).unwrap_or(0))
+            })
+            .collect())
+    }
+
+    /// Warn when stored embeddings come from more than one model, or from a
+    /// model other than the current one, since their similarities to a query
+    /// are not comparable
+    async fn warn_if_mixed_models(&self) -> CadiResult<()> {
+        let models = self.embedding_models().await?;
+        let current = self.embedding_model();
+        let mixed = models.len() > 1
+            || matches!((models.first(), &current), (Some((model, _)), Some(_)) if *model != current);
+
+        if mixed {
+            let models: Vec<String> = models
+                .iter()
+                .map(|(model, chunks)| format!("{} ({} chunks)", model.as_deref().unwrap_or("unknown"), chunks))
+                .collect();
+            tracing::warn!(
+                "Vector index mixes embedding models: {}; run `cadi registry reembed` to re-embed with {}",
+                models.join(", "),
+                current.as_deref().unwrap_or("the current model")
+            );
+        }
+        Ok(())
+    }
 
-        let mut meta_response = self.db.query(meta_sql).await
+    /// Progress of the current or last re-embedding run, if any
+    pub async fn reembed_status(&self) -> CadiResult<Option<ReembedProgress>> {
+        let mut response = self.db.query("SELECT model, processed, total, cursor, done FROM registry_meta:reembed")
+            .await
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+        let rows: Vec<serde_json::Value> = response.take(0)
             .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
 
-        let meta_results: Vec<serde_json::Value> = meta_response.take(0)
+        Ok(rows.into_iter().next().and_then(|row| serde_json::from_value(row).ok()))
+    }
+
+    /// Re-embed the next `batch_size` chunks with the current embedding
+    /// manager.
+    ///
+    /// Chunks are visited in hash order and the last hash done is stored as a
+    /// cursor in `registry_meta`, so a run that is interrupted continues
+    /// where it stopped as long as the model is unchanged. The vector index
+    /// is dropped when a run starts and rebuilt when it finishes.
+    pub async fn reembed_batch(&mut self, batch_size: usize) -> CadiResult<ReembedProgress> {
+        let Some(model) = self.embedding_model() else {
+            return Err(CadiError::RegistryError("No embedding manager configured".to_string()));
+        };
+
+        let mut progress = match self.reembed_status().await? {
+            Some(progress) if progress.model == model && !progress.done => progress,
+            _ => {
+                self.db.query("REMOVE INDEX embedding_mtree ON chunk")
+                    .await
+                    .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+                ReembedProgress {
+                    model,
+                    total: self.count_chunks().await?,
+                    ..Default::default()
+                }
+            }
+        };
+
+        let mut response = self.db.query(
+            "SELECT hash FROM chunk WHERE $cursor = NONE OR hash > $cursor ORDER BY hash LIMIT $limit",
+        )
+            .bind(("cursor", &progress.cursor))
+            .bind(("limit", batch_size.max(1)))
+            .await
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+        let rows: Vec<serde_json::Value> = response.take(0)
             .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+        let hashes: Vec<String> = rows
+            .iter()
+            .filter_map(|row| row.get("hash").and_then(|h| h.as_str()).map(String::from))
+            .collect();
+
+        let mut chunks = Vec::with_capacity(hashes.len());
+        for hash in &hashes {
+            let (chunk_id, metadata) = self.metadata_for_hash(hash).await?;
+            chunks.push((chunk_id, content_for_embedding(&metadata)));
+        }
 
-        let mut scored_results = Vec::new();
+        if !chunks.is_empty() {
+            let manager = self.embedding_manager.as_mut().expect("checked above");
+            let embeddings = manager.regenerate_chunk_embeddings(&chunks).await
+                .map_err(|e| CadiError::RegistryError(format!("Embedding generation failed: {}", e)))?;
+            for (hash, embedding) in hashes.iter().zip(embeddings) {
+                self.set_embedding(hash, Some(embedding)).await?;
+            }
+        }
 
-        for meta_row in meta_results {
-            let chunk_id = meta_row.get("chunk_id").and_then(|c| c.as_str()).unwrap_or("").to_string();
-            let name = meta_row.get("name").and_then(|n| n.as_str()).unwrap_or("").to_string();
-            let description = meta_row.get("description").and_then(|d| d.as_str()).unwrap_or("").to_string();
+        progress.processed += hashes.len();
+        progress.total = progress.total.max(progress.processed);
+        if let Some(last) = hashes.last() {
+            progress.cursor = Some(last.clone());
+        }
+        if hashes.len() < batch_size.max(1) {
+            let dimension = self.embedding_manager.as_ref().map(|m| m.dimension()).unwrap_or(DEFAULT_EMBEDDING_DIMENSION);
+            self.define_embedding_index(dimension).await?;
+            progress.done = true;
+        }
 
-            // Simple fuzzy matching using string contains for now
-            let name_match = name.to_lowercase().contains(&query.to_lowercase());
-            let desc_match = description.to_lowercase().contains(&query.to_lowercase());
+        self.db.query("UPDATE registry_meta:reembed CONTENT $progress")
+            .bind(("progress", &progress))
+            .await
+            .map_err(|e| CadiError::DatabaseError(format!("Re-embed cursor update failed: {}", e)))?
+            .check()
+            .map_err(|e| CadiError::DatabaseError(format!("Re-embed cursor update failed: {}", e)))?;
 
-            if name_match || desc_match {
-                let score = if name_match && desc_match { 1.0 } else { 0.7 };
+        Ok(progress)
+    }
 
-                let concepts = meta_row.get("concepts")
-                    .and_then(|c| c.as_array())
-                    .map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
-                    .unwrap_or_default();
-                let quality_score = meta_row.get("quality_score").and_then(|q| q.as_f64()).unwrap_or(0.0);
-                let test_coverage = meta_row.get("test_coverage").and_then(|t| t.as_f64()).unwrap_or(0.0);
-
-                let metadata = ChunkMetadata {
-                    name,
-                    description,
-                    language: "typescript".to_string(), // TODO: store language separately
-                    concepts,
-                    dependencies: vec![],
-                    function_signatures: vec![],
-                    quality_score,
-                    test_coverage,
-                };
-
-                scored_results.push(DbSearchResult {
-                    chunk_id,
-                    score,
-                    metadata,
-                });
+    /// Re-embed every chunk with the current embedding manager, `batch_size`
+    /// chunks at a time, and rebuild the vector index. Resumes an interrupted
+    /// run; see [`reembed_batch`](Self::reembed_batch).
+    pub async fn reembed_all<F>(&mut self, batch_size: usize, mut on_progress: F) -> CadiResult<ReembedProgress>
+    where
+        F: FnMut(&ReembedProgress),
+    {
+        loop {
+            let progress = self.reembed_batch(batch_size).await?;
+            on_progress(&progress);
+            if progress.done {
+                return Ok(progress);
             }
         }
+    }
+
+    async fn count_chunks(&self) -> CadiResult<usize> {
+        let mut response = self.db.query("SELECT count() AS total FROM chunk GROUP ALL")
+            .await
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+        let rows: Vec<serde_json::Value> = response.take(0)
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+
+        Ok(rows.first().and_then(|row| row.get("total")).and_then(|t| t.as_u64()).unwrap_or(0) as usize)
+    }
+
+    /// Keyword search using BM25 over name, description and concepts
+    async fn text_search(&self, query: &str, filters: &SearchQuery, limit: usize) -> CadiResult<Vec<DbSearchResult>> {
+        // Get metadata records matching the structural filters, then score in Rust
+        let meta_sql = format!(
+            "SELECT * FROM chunk_metadata{} LIMIT 1000", // Reasonable limit for search
+            filters.filter_clause()
+        );
+
+        let mut meta_response = bind_filters(self.db.query(meta_sql), filters)
+            .await
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+
+        let meta_results: Vec<serde_json::Value> = meta_response.take(0)
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+
+        let metadata: Vec<ChunkMetadata> = meta_results.iter().map(|row| {
+            let string_array = |key: &str| row.get(key)
+                .and_then(|c| c.as_array())
+                .map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
+                .unwrap_or_default();
+
+            ChunkMetadata {
+                name: row.get("name").and_then(|n| n.as_str()).unwrap_or("").to_string(),
+                description: row.get("description").and_then(|d| d.as_str()).unwrap_or("").to_string(),
+                language: row.get("language").and_then(|l| l.as_str()).unwrap_or("").to_string(),
+                concepts: string_array("concepts"),
+                dependencies: string_array("dependencies"),
+                function_signatures: string_array("function_signatures"),
+                quality_score: row.get("quality_score").and_then(|q| q.as_f64()).unwrap_or(0.0),
+                test_coverage: row.get("test_coverage").and_then(|t| t.as_f64()).unwrap_or(0.0),
+                ..structural_metadata(row)
+            }
+        }).collect();
+
+        let scores = bm25_scores(query, &metadata);
+        let max_score = scores.iter().copied().fold(0.0, f64::max);
+
+        let mut scored_results: Vec<DbSearchResult> = meta_results.iter()
+            .zip(metadata)
+            .zip(scores)
+            .filter(|(_, score)| *score > 0.0)
+            .map(|((row, metadata), score)| {
+                // Normalize against the best match so scores combine with cosine similarity
+                let keyword = score / max_score;
+                DbSearchResult {
+                    chunk_id: row.get("chunk_id").and_then(|c| c.as_str()).unwrap_or("").to_string(),
+                    score: keyword,
+                    metadata,
+                    scores: ScoreBreakdown {
+                        keyword: Some(keyword),
+                        ..Default::default()
+                    },
+                }
+            })
+            .collect();
 
         // Sort by score and limit
-        scored_results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        scored_results.sort_by(|a, b| b.score.total_cmp(&a.score));
         scored_results.truncate(limit);
 
         Ok(scored_results)
@@ -436,6 +1000,7 @@ impl RegistryDatabase {
         let chunk_sql = r#"
             SELECT
                 id.id as chunk_id,
+                language,
                 concepts,
                 quality_score,
                 test_coverage,
@@ -469,7 +1034,7 @@ impl RegistryDatabase {
             let score = chunk_row.get("score").and_then(|s| s.as_f64()).unwrap_or(0.0);
 
             // Get metadata for this chunk
-            let meta_sql = "SELECT name, description FROM chunk_metadata WHERE chunk_id = $chunk_id";
+            let meta_sql = "SELECT name, description, granularity, categories, category_confidence, size, metrics FROM chunk_metadata WHERE chunk_id = $chunk_id";
             let mut meta_response = self.db.query(meta_sql)
                 .bind(("chunk_id", &chunk_id))
                 .await
@@ -478,54 +1043,251 @@ impl RegistryDatabase {
             let meta_results: Vec<serde_json::Value> = meta_response.take(0)
                 .map_err(|e| CadiError::DatabaseError(format!("Metadata parse failed: {}", e)))?;
 
-            let (name, description) = if let Some(meta_row) = meta_results.first() {
+            let (name, description, structural) = if let Some(meta_row) = meta_results.first() {
                 let name = meta_row.get("name").and_then(|n| n.as_str()).unwrap_or("").to_string();
                 let description = meta_row.get("description").and_then(|d| d.as_str()).unwrap_or("").to_string();
-                (name, description)
+                (name, description, structural_metadata(meta_row))
             } else {
-                ("Unknown".to_string(), "No description".to_string())
+                ("Unknown".to_string(), "No description".to_string(), ChunkMetadata::default())
             };
 
             let concepts = chunk_row.get("concepts")
                 .and_then(|c| c.as_array())
                 .map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
                 .unwrap_or_default();
+            let language = chunk_row.get("language").and_then(|l| l.as_str()).unwrap_or("").to_string();
             let quality_score = chunk_row.get("quality_score").and_then(|q| q.as_f64()).unwrap_or(0.0);
             let test_coverage = chunk_row.get("test_coverage").and_then(|t| t.as_f64()).unwrap_or(0.0);
 
             let metadata = ChunkMetadata {
                 name,
                 description,
-                language: "typescript".to_string(), // TODO: store language separately
+                language,
                 concepts,
                 dependencies: vec![],
                 function_signatures: vec![],
                 quality_score,
                 test_coverage,
+                ..structural
             };
 
             search_results.push(DbSearchResult {
                 chunk_id,
                 score,
                 metadata,
+                scores: ScoreBreakdown {
+                    semantic: Some(score.clamp(0.0, 1.0)),
+                    ..Default::default()
+                },
             });
         }
 
         Ok(search_results)
     }
 
+    /// IDs of chunks whose metadata matches the structural filters of a query
+    pub async fn matching_chunk_ids(&self, query: &SearchQuery) -> CadiResult<HashSet<String>> {
+        let sql = format!("SELECT chunk_id FROM chunk_metadata{}", query.filter_clause());
+
+        let mut response = bind_filters(self.db.query(sql), query)
+            .await
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+
+        let rows: Vec<serde_json::Value> = response.take(0)
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+
+        Ok(rows
+            .iter()
+            .filter_map(|row| row.get("chunk_id").and_then(|c| c.as_str()).map(String::from))
+            .collect())
+    }
+
     /// Increment usage count for a chunk
     pub async fn increment_usage(&self, chunk_id: &str) -> CadiResult<()> {
+        self.record_usage(&HashMap::from([(chunk_id.to_string(), 1)]), chrono::Utc::now()).await
+    }
+
+    /// Add a batch of uses, counted per chunk, to the chunks' usage counts
+    /// and to their usage bucket for the day of `at`. Buckets older than
+    /// [`USAGE_RETENTION_DAYS`] are dropped. Chunks not in the registry are
+    /// ignored.
+    pub async fn record_usage(&self, uses: &HashMap<String, u64>, at: chrono::DateTime<chrono::Utc>) -> CadiResult<()> {
         let sql = r#"
-            UPDATE chunk SET usage_count += 1 WHERE id = $chunk_id
+            LET $used = (UPDATE chunk SET usage_count += $count, last_used_at = $at
+                WHERE id = $chunk_id OR hash = $hash RETURN id);
+            IF array::len($used) > 0 {
+                UPDATE type::thing('chunk_usage', [$hash, $day])
+                    SET hash = $hash, day = $day, count += $count;
+            };
         "#;
 
-        self.db.query(sql)
+        let day = at.format("%Y-%m-%d").to_string();
+        for (chunk_id, count) in uses {
+            let hash = chunk_storage_key(chunk_id);
+            self.db.query(sql)
+                .bind(("chunk_id", chunk_id))
+                .bind(("hash", hash))
+                .bind(("count", *count))
+                .bind(("at", at.to_rfc3339()))
+                .bind(("day", &day))
+                .await
+                .map_err(|e| CadiError::DatabaseError(format!("Usage update failed: {}", e)))?
+                .check()
+                .map_err(|e| CadiError::DatabaseError(format!("Usage update failed: {}", e)))?;
+        }
+
+        let cutoff = (at - chrono::Duration::days(USAGE_RETENTION_DAYS - 1)).format("%Y-%m-%d").to_string();
+        self.db.query("DELETE chunk_usage WHERE day < $cutoff")
+            .bind(("cutoff", cutoff))
+            .await
+            .map_err(|e| CadiError::DatabaseError(format!("Usage pruning failed: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Usage counts of the given chunks; chunks not in the registry are
+    /// left out
+    async fn usage_counts(&self, chunk_ids: &[String]) -> CadiResult<HashMap<String, u64>> {
+        let hashes: Vec<String> = chunk_ids.iter().map(|id| chunk_storage_key(id)).collect();
+        let mut response = self.db.query("SELECT hash, usage_count FROM chunk WHERE hash INSIDE $hashes")
+            .bind(("hashes", hashes))
+            .await
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+        let rows: Vec<serde_json::Value> = response.take(0)
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+
+        let counts: HashMap<&str, u64> = rows
+            .iter()
+            .filter_map(|row| {
+                let hash = row.get("hash").and_then(|h| h.as_str())?;
+                Some((hash, row.get("usage_count").and_then(|c| c.as_u64()).unwrap_or(0)))
+            })
+            .collect();
+
+        Ok(chunk_ids
+            .iter()
+            .filter_map(|id| {
+                counts.get(chunk_storage_key(id).as_str()).map(|count| (id.clone(), *count))
+            })
+            .collect())
+    }
+
+    /// Usage count and daily usage of a chunk over the retention window, or
+    /// `None` if it is not in the registry
+    pub async fn usage_stats(&self, chunk_id: &str) -> CadiResult<Option<ChunkUsageStats>> {
+        let hash = chunk_storage_key(chunk_id);
+        let sql = r#"
+            SELECT usage_count, last_used_at FROM chunk WHERE id = $chunk_id OR hash = $hash;
+            SELECT day, count FROM chunk_usage WHERE hash = $hash ORDER BY day;
+        "#;
+        let mut response = self.db.query(sql)
             .bind(("chunk_id", chunk_id))
+            .bind(("hash", hash))
             .await
             .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+        let chunks: Vec<serde_json::Value> = response.take(0)
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+        let days: Vec<serde_json::Value> = response.take(1)
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
 
-        Ok(())
+        let Some(chunk) = chunks.first() else {
+            return Ok(None);
+        };
+        Ok(Some(ChunkUsageStats {
+            chunk_id: chunk_id.to_string(),
+            usage_count: chunk.get("usage_count").and_then(|c| c.as_u64()).unwrap_or(0),
+            last_used_at: chunk.get("last_used_at").and_then(|t| t.as_str()).map(String::from),
+            daily: days
+                .iter()
+                .filter_map(|row| {
+                    Some(DailyUsage {
+                        day: row.get("day").and_then(|d| d.as_str())?.to_string(),
+                        count: row.get("count").and_then(|c| c.as_u64()).unwrap_or(0),
+                    })
+                })
+                .collect(),
+        }))
+    }
+
+    /// Point an alias at a chunk, replacing any previous binding
+    pub async fn put_alias(&self, path: &str, chunk_id: &str) -> CadiResult<AliasEntry> {
+        let sql = r#"
+            UPDATE type::thing('alias', $path)
+            SET path = $path, chunk_id = $chunk_id, updated_at = <string> time::now()
+            RETURN path, chunk_id, updated_at
+        "#;
+
+        let mut response = self.db.query(sql)
+            .bind(("path", path))
+            .bind(("chunk_id", chunk_id))
+            .await
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+        let rows: Vec<AliasEntry> = response.take(0)
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+
+        rows.into_iter().next()
+            .ok_or_else(|| CadiError::DatabaseError(format!("Alias {} was not stored", path)))
+    }
+
+    /// The chunk an alias points at, if the alias exists
+    pub async fn get_alias(&self, path: &str) -> CadiResult<Option<AliasEntry>> {
+        let mut response = self.db.query("SELECT path, chunk_id, updated_at FROM alias WHERE path = $path")
+            .bind(("path", path))
+            .await
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+        let rows: Vec<AliasEntry> = response.take(0)
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+
+        Ok(rows.into_iter().next())
+    }
+
+    /// Every alias pointing at a chunk, in path order
+    pub async fn aliases_of(&self, chunk_id: &str) -> CadiResult<Vec<AliasEntry>> {
+        let mut response = self.db.query("SELECT path, chunk_id, updated_at FROM alias WHERE chunk_id = $chunk_id ORDER BY path")
+            .bind(("chunk_id", chunk_id))
+            .await
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+        response.take(0).map_err(|e| CadiError::DatabaseError(e.to_string()))
+    }
+
+    /// A page of the aliases starting with `prefix`, in path order
+    pub async fn list_aliases(&self, prefix: &str, limit: usize, offset: usize) -> CadiResult<AliasPage> {
+        let sql = r#"
+            SELECT path, chunk_id, updated_at FROM alias
+            WHERE string::startsWith(path, $prefix)
+            ORDER BY path LIMIT $limit START $offset;
+            SELECT count() AS total FROM alias WHERE string::startsWith(path, $prefix) GROUP ALL;
+        "#;
+
+        let mut response = self.db.query(sql)
+            .bind(("prefix", prefix))
+            .bind(("limit", limit))
+            .bind(("offset", offset))
+            .await
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+        let aliases: Vec<AliasEntry> = response.take(0)
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+        let totals: Vec<serde_json::Value> = response.take(1)
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+
+        Ok(AliasPage {
+            aliases,
+            total: totals.first().and_then(|row| row.get("total")).and_then(|t| t.as_u64()).unwrap_or(0) as usize,
+            offset,
+            limit,
+        })
+    }
+
+    /// Remove an alias, returning whether it existed
+    pub async fn delete_alias(&self, path: &str) -> CadiResult<bool> {
+        let mut response = self.db.query("DELETE alias WHERE path = $path RETURN BEFORE")
+            .bind(("path", path))
+            .await
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+        let rows: Vec<serde_json::Value> = response.take(0)
+            .map_err(|e| CadiError::DatabaseError(e.to_string()))?;
+
+        Ok(!rows.is_empty())
     }
 
     /// Get embedding manager (for testing)
@@ -570,6 +1332,126 @@ impl RegistryDatabase {
     }
 }
 
+/// Bind the parameters referenced by [`SearchQuery::filter_clause`]
+fn bind_filters<'r>(
+    query: surrealdb::method::Query<'r, Db>,
+    filters: &SearchQuery,
+) -> surrealdb::method::Query<'r, Db> {
+    query
+        .bind(("granularity", filters.granularity))
+        .bind(("categories", filters.categories.clone()))
+        .bind(("min_category_confidence", filters.min_category_confidence))
+        .bind(("max_size", filters.max_size))
+        .bind(("min_quality", filters.min_quality))
+        .bind(("max_complexity", filters.max_complexity))
+}
+
+/// Text embedded for a chunk: its name, description and concepts
+fn content_for_embedding(metadata: &serde_json::Value) -> String {
+    let name = metadata.get("name").and_then(|n| n.as_str()).unwrap_or("");
+    let description = metadata.get("description").and_then(|d| d.as_str()).unwrap_or("");
+    let concepts = metadata.get("concepts")
+        .and_then(|c| c.as_array())
+        .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>().join(" "))
+        .unwrap_or_default();
+    format!("{} {} {}", name, description, concepts)
+}
+
+/// Granularity, categories, size and signatures from a `chunk_metadata` row
+fn structural_metadata(row: &serde_json::Value) -> ChunkMetadata {
+    ChunkMetadata {
+        granularity: row.get("granularity").cloned().and_then(|g| serde_json::from_value(g).ok()),
+        categories: row.get("categories").cloned().and_then(|c| serde_json::from_value(c).ok()).unwrap_or_default(),
+        category_confidence: row.get("category_confidence").and_then(|c| c.as_f64()),
+        size: row.get("size").and_then(|s| s.as_u64()).unwrap_or(0) as usize,
+        signatures: row.get("signatures").cloned().and_then(|s| serde_json::from_value(s).ok()).unwrap_or_default(),
+        metrics: row.get("metrics").cloned().and_then(|m| serde_json::from_value(m).ok()),
+        ..Default::default()
+    }
+}
+
+/// Signature strings in a metadata document, if it has a `signatures` field
+fn metadata_signatures(metadata: &serde_json::Value) -> Option<Vec<String>> {
+    metadata.get("signatures").and_then(|s| s.as_array()).map(|signatures| {
+        signatures.iter().filter_map(|s| s.as_str().map(|s| s.to_string())).collect()
+    })
+}
+
+/// The `metrics` object of published metadata (an imported chunk's
+/// [`ChunkMetrics`]), if it has one
+fn metadata_metrics(metadata: &serde_json::Value) -> Option<serde_json::Value> {
+    metadata.get("metrics").filter(|m| m.is_object()).cloned()
+}
+
+/// BM25 parameters
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+
+/// Split text into lowercase terms on non-alphanumerics and camelCase humps
+fn tokenize(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut current = String::new();
+    let mut prev_lower = false;
+
+    for c in text.chars() {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                terms.push(std::mem::take(&mut current));
+            }
+            prev_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && prev_lower && !current.is_empty() {
+            terms.push(std::mem::take(&mut current));
+        }
+        prev_lower = c.is_lowercase() || c.is_numeric();
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        terms.push(current);
+    }
+
+    terms
+}
+
+/// BM25 score of each document for the query. Names count twice so that a
+/// name hit outranks the same term in a description.
+fn bm25_scores(query: &str, documents: &[ChunkMetadata]) -> Vec<f64> {
+    let mut query_terms = tokenize(query);
+    query_terms.sort();
+    query_terms.dedup();
+
+    let documents: Vec<Vec<String>> = documents.iter().map(|doc| {
+        let mut terms = tokenize(&doc.name);
+        terms.extend(tokenize(&doc.name));
+        terms.extend(tokenize(&doc.description));
+        for concept in &doc.concepts {
+            terms.extend(tokenize(concept));
+        }
+        terms
+    }).collect();
+
+    if query_terms.is_empty() || documents.is_empty() {
+        return vec![0.0; documents.len()];
+    }
+
+    let count = documents.len() as f64;
+    let average_length = documents.iter().map(|d| d.len()).sum::<usize>() as f64 / count;
+
+    let idf: Vec<f64> = query_terms.iter().map(|term| {
+        let frequency = documents.iter().filter(|d| d.contains(term)).count() as f64;
+        (1.0 + (count - frequency + 0.5) / (frequency + 0.5)).ln()
+    }).collect();
+
+    documents.iter().map(|doc| {
+        let length_norm = 1.0 - BM25_B + BM25_B * doc.len() as f64 / average_length.max(1.0);
+        query_terms.iter().zip(&idf).map(|(term, idf)| {
+            let tf = doc.iter().filter(|t| *t == term).count() as f64;
+            idf * tf * (BM25_K1 + 1.0) / (tf + BM25_K1 * length_norm)
+        }).sum()
+    }).collect()
+}
+
 /// Registry statistics
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct RegistryStats {
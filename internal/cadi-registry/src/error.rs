@@ -0,0 +1,130 @@
+//! Errors of the registry client
+//!
+//! Every [`RegistryClient`](crate::RegistryClient) method fails with a
+//! [`RegistryError`], so callers can tell a missing chunk from bad
+//! credentials or a throttled registry without matching on message text.
+
+use cadi_core::CadiError;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Result of a registry client call
+pub type RegistryResult<T> = Result<T, RegistryError>;
+
+/// Why a registry request failed
+#[derive(Error, Debug)]
+pub enum RegistryError {
+    /// 404: the registry doesn't have the requested chunk, manifest or alias
+    #[error("Not found on the registry: {0}")]
+    NotFound(String),
+
+    /// 401 or 403: the token is missing, invalid or lacks permission
+    #[error("Not authorized by the registry: {0}")]
+    Unauthorized(String),
+
+    /// 409: the registry already has the chunk
+    #[error("Already on the registry: {0}")]
+    Conflict(String),
+
+    /// The registry rejected a chunk whose content doesn't hash to its ID
+    #[error("Registry rejected {0}: content does not match its hash")]
+    HashMismatch(String),
+
+    /// 429, after the client's own retries
+    #[error("Rate limited by the registry{}", retry_hint(.retry_after))]
+    RateLimited { retry_after: Option<Duration> },
+
+    /// The registry could not be reached or the connection failed
+    #[error("Network error: {0}")]
+    Network(std::io::Error),
+
+    /// Any other unsuccessful status
+    #[error("HTTP {status}: {body}")]
+    Server { status: u16, body: String },
+
+    /// A successful response whose body could not be decoded
+    #[error("Invalid registry response: {0}")]
+    InvalidResponse(String),
+
+    /// Reading or writing the local chunk cache or spool failed
+    #[error("Local storage error: {0}")]
+    Storage(std::io::Error),
+}
+
+fn retry_hint(retry_after: &Option<Duration>) -> String {
+    retry_after
+        .map(|wait| format!(", retry after {}s", wait.as_secs()))
+        .unwrap_or_default()
+}
+
+impl RegistryError {
+    /// Map an unsuccessful response onto an error. `resource` names what
+    /// was requested, for `NotFound`, `Conflict` and `HashMismatch`.
+    pub async fn from_response(response: reqwest::Response, resource: &str) -> Self {
+        let status = response.status().as_u16();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+        let body = response.text().await.unwrap_or_default();
+        Self::from_status(status, retry_after, body, resource)
+    }
+
+    /// Map an unsuccessful status and its response body onto an error
+    pub fn from_status(status: u16, retry_after: Option<Duration>, body: String, resource: &str) -> Self {
+        match status {
+            401 | 403 if body.trim().is_empty() => Self::Unauthorized(format!("HTTP {}", status)),
+            401 | 403 => Self::Unauthorized(body.trim().to_string()),
+            404 => Self::NotFound(resource.to_string()),
+            409 => Self::Conflict(resource.to_string()),
+            422 => Self::HashMismatch(resource.to_string()),
+            429 => Self::RateLimited { retry_after },
+            _ => Self::Server { status, body },
+        }
+    }
+
+    /// How long the registry asked to wait, for `RateLimited`
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::RateLimited { retry_after } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+/// Parse a `Retry-After` header: delay seconds or an HTTP date
+pub(crate) fn parse_retry_after(value: &str) -> Option<Duration> {
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    let wait = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(wait.to_std().unwrap_or_default())
+}
+
+impl From<reqwest::Error> for RegistryError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            return Self::InvalidResponse(e.to_string());
+        }
+        let kind = if e.is_timeout() {
+            std::io::ErrorKind::TimedOut
+        } else if e.is_connect() {
+            std::io::ErrorKind::ConnectionRefused
+        } else {
+            std::io::ErrorKind::Other
+        };
+        Self::Network(std::io::Error::new(kind, e))
+    }
+}
+
+impl From<RegistryError> for CadiError {
+    fn from(e: RegistryError) -> Self {
+        match e {
+            RegistryError::NotFound(resource) => CadiError::ChunkNotFound(resource),
+            RegistryError::Storage(e) => CadiError::Io(e),
+            e => CadiError::RegistryError(e.to_string()),
+        }
+    }
+}
@@ -1,18 +1,34 @@
 //! Registry federation support for CADI
 
 use cadi_core::{CadiError, CadiResult};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Environment variable overriding the federation config location
+pub const FEDERATION_CONFIG_ENV: &str = "CADI_REGISTRIES_CONFIG";
 
 /// A federated registry configuration
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct FederatedRegistry {
-    /// Registry identifier
+    /// Registry identifier (defaults to the URL)
+    #[serde(default)]
     pub id: String,
     /// Registry URL
     pub url: String,
+    /// Authentication token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Namespace sent with every request, for registries that check tokens
+    /// per namespace
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
     /// Priority (lower = higher priority)
+    #[serde(default)]
     pub priority: u32,
     /// Trust level
+    #[serde(default)]
     pub trust_level: TrustLevel,
     /// Whether this registry is enabled
     #[serde(default = "default_true")]
@@ -21,7 +37,7 @@ pub struct FederatedRegistry {
     #[serde(default)]
     pub regions: Vec<String>,
     /// Capabilities of this registry
-    #[serde(default)]
+    #[serde(default = "default_capabilities")]
     pub capabilities: RegistryCapabilities,
 }
 
@@ -29,6 +45,38 @@ fn default_true() -> bool {
     true
 }
 
+fn default_capabilities() -> RegistryCapabilities {
+    RegistryCapabilities {
+        push: true,
+        search: true,
+        ..Default::default()
+    }
+}
+
+/// Federation config file contents
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FederationConfig {
+    /// Registries to federate
+    #[serde(default)]
+    pub registries: Vec<FederatedRegistry>,
+    /// Seconds between health checks
+    #[serde(default = "default_health_check_interval")]
+    pub health_check_interval_secs: u64,
+}
+
+fn default_health_check_interval() -> u64 {
+    60
+}
+
+impl Default for FederationConfig {
+    fn default() -> Self {
+        Self {
+            registries: Vec::new(),
+            health_check_interval_secs: default_health_check_interval(),
+        }
+    }
+}
+
 /// Trust level for a registry
 #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -69,6 +117,12 @@ pub struct RegistryCapabilities {
 pub struct FederationManager {
     registries: Vec<FederatedRegistry>,
     clients: HashMap<String, super::RegistryClient>,
+    /// Registries that failed their last health check
+    unhealthy: RwLock<HashSet<String>>,
+    health_check_interval: Duration,
+    /// Cache shared by every registry's conditional fetches. Chunk IDs are
+    /// content hashes, so a chunk cached from one registry is valid for all.
+    cache_dir: Option<PathBuf>,
 }
 
 impl FederationManager {
@@ -77,13 +131,57 @@ impl FederationManager {
         Self {
             registries: Vec::new(),
             clients: HashMap::new(),
+            unhealthy: RwLock::new(HashSet::new()),
+            health_check_interval: Duration::from_secs(default_health_check_interval()),
+            cache_dir: None,
+        }
+    }
+
+    /// Cache fetched chunks in `dir`, revalidating them with the registry on
+    /// every fetch
+    pub fn with_cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        for client in self.clients.values_mut() {
+            client.set_cache_dir(Some(dir.clone()));
+        }
+        self.cache_dir = Some(dir);
+        self
+    }
+
+    /// Create a federation manager from a TOML or YAML registry list
+    pub fn from_config(path: &Path) -> CadiResult<Self> {
+        let config = load_federation_file(path)?;
+        let mut manager = Self::new();
+        manager.health_check_interval = Duration::from_secs(config.health_check_interval_secs);
+        for registry in config.registries {
+            manager.add_registry(registry)?;
         }
+        Ok(manager)
+    }
+
+    /// Create a federation manager from the shared config file, or an empty
+    /// one if the file does not exist or cannot be read
+    pub fn from_default_config() -> Self {
+        let Some(path) = default_federation_config_path().filter(|p| p.exists()) else {
+            return Self::new();
+        };
+        Self::from_config(&path).unwrap_or_else(|e| {
+            tracing::warn!("Failed to load federation config {}: {}", path.display(), e);
+            Self::new()
+        })
     }
 
     /// Add a registry to the federation
-    pub fn add_registry(&mut self, registry: FederatedRegistry) -> CadiResult<()> {
+    pub fn add_registry(&mut self, mut registry: FederatedRegistry) -> CadiResult<()> {
+        if registry.id.is_empty() {
+            registry.id = registry.url.clone();
+        }
+
         let config = super::RegistryConfig {
             url: registry.url.clone(),
+            token: registry.token.clone(),
+            namespace: registry.namespace.clone(),
+            cache_dir: self.cache_dir.clone(),
             ..Default::default()
         };
         
@@ -113,19 +211,74 @@ impl FederationManager {
         &self.registries
     }
 
+    /// Whether a registry passed its last health check. Registries are
+    /// assumed healthy until checked.
+    pub fn is_healthy(&self, id: &str) -> bool {
+        !self.unhealthy.read().map(|u| u.contains(id)).unwrap_or(false)
+    }
+
+    /// Enabled registries that are currently healthy, in priority order
+    fn available(&self) -> impl Iterator<Item = &FederatedRegistry> {
+        self.registries
+            .iter()
+            .filter(|r| r.enabled && self.is_healthy(&r.id))
+    }
+
+    /// Check the `/health` endpoint of every enabled registry, marking
+    /// failing registries unhealthy until a later check succeeds. Returns
+    /// the number of healthy registries.
+    pub async fn check_health(&self) -> usize {
+        let mut healthy = 0;
+
+        for registry in self.registries.iter().filter(|r| r.enabled) {
+            let Some(client) = self.clients.get(&registry.id) else {
+                continue;
+            };
+
+            let ok = matches!(client.health().await, Ok(status) if status.healthy);
+            let Ok(mut unhealthy) = self.unhealthy.write() else {
+                continue;
+            };
+            if ok {
+                healthy += 1;
+                if unhealthy.remove(&registry.id) {
+                    tracing::info!("Registry {} recovered", registry.id);
+                }
+            } else if unhealthy.insert(registry.id.clone()) {
+                tracing::warn!("Registry {} is unhealthy", registry.id);
+            }
+        }
+
+        healthy
+    }
+
+    /// Run health checks periodically in the background. The task stops once
+    /// the manager is dropped.
+    pub fn spawn_health_checks(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let manager = Arc::downgrade(self);
+        let interval = self.health_check_interval;
+
+        tokio::spawn(async move {
+            loop {
+                let Some(manager) = manager.upgrade() else {
+                    break;
+                };
+                manager.check_health().await;
+                drop(manager);
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+
     /// Fetch a chunk from the federation
     /// 
     /// Tries registries in priority order until one succeeds
-    pub async fn fetch_chunk(&self, chunk_id: &str) -> CadiResult<(Vec<u8>, String)> {
+    pub async fn fetch_chunk(&self, chunk_id: &str) -> super::RegistryResult<(Vec<u8>, String)> {
         let mut last_error = None;
         
-        for registry in &self.registries {
-            if !registry.enabled {
-                continue;
-            }
-            
+        for registry in self.available() {
             if let Some(client) = self.clients.get(&registry.id) {
-                match client.fetch_chunk(chunk_id).await {
+                match client.fetch_chunk_cached(chunk_id).await {
                     Ok(data) => {
                         return Ok((data, registry.id.clone()));
                     }
@@ -137,16 +290,33 @@ impl FederationManager {
             }
         }
         
-        Err(last_error.unwrap_or_else(|| CadiError::ChunkNotFound(chunk_id.to_string())))
+        Err(last_error.unwrap_or_else(|| super::RegistryError::NotFound(chunk_id.to_string())))
+    }
+
+    /// Client for a registry by ID
+    pub fn client(&self, id: &str) -> Option<&super::RegistryClient> {
+        self.clients.get(id)
+    }
+
+    /// Resolve an alias on the first registry that has it, returning the
+    /// alias and the registry's ID
+    pub async fn resolve_alias(&self, path: &str) -> CadiResult<Option<(super::AliasEntry, String)>> {
+        for registry in self.available() {
+            if let Some(client) = self.clients.get(&registry.id) {
+                match client.resolve_alias(path).await {
+                    Ok(Some(entry)) => return Ok(Some((entry, registry.id.clone()))),
+                    Ok(None) => {}
+                    Err(e) => tracing::debug!("Failed to resolve alias on {}: {}", registry.id, e),
+                }
+            }
+        }
+
+        Ok(None)
     }
 
     /// Check if a chunk exists in any registry
     pub async fn chunk_exists(&self, chunk_id: &str) -> CadiResult<Option<String>> {
-        for registry in &self.registries {
-            if !registry.enabled {
-                continue;
-            }
-            
+        for registry in self.available() {
             if let Some(client) = self.clients.get(&registry.id) {
                 if client.chunk_exists(chunk_id).await? {
                     return Ok(Some(registry.id.clone()));
@@ -159,8 +329,8 @@ impl FederationManager {
 
     /// Find the best registry for publishing
     pub fn best_push_registry(&self) -> Option<&FederatedRegistry> {
-        self.registries.iter()
-            .filter(|r| r.enabled && r.capabilities.push)
+        self.available()
+            .filter(|r| r.capabilities.push)
             .min_by_key(|r| r.priority)
     }
 
@@ -178,11 +348,14 @@ impl FederationManager {
     }
 
     /// Search across all registries
+    ///
+    /// Registries are queried in priority order; results are deduplicated by
+    /// chunk ID, keeping the one from the highest-priority registry.
     pub async fn search(&self, query: &super::SearchQuery) -> CadiResult<Vec<(super::ChunkSummary, String)>> {
         let mut results = Vec::new();
         
-        for registry in &self.registries {
-            if !registry.enabled || !registry.capabilities.search {
+        for registry in self.available() {
+            if !registry.capabilities.search {
                 continue;
             }
             
@@ -214,11 +387,45 @@ impl Default for FederationManager {
     }
 }
 
+/// Default location of the federation config, shared by the CLI and the
+/// MCP server. Can be overridden with `CADI_REGISTRIES_CONFIG`.
+pub fn default_federation_config_path() -> Option<PathBuf> {
+    if let Ok(path) = std::env::var(FEDERATION_CONFIG_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    directories::ProjectDirs::from("dev", "cadi", "cadi")
+        .map(|dirs| dirs.config_dir().join("registries.toml"))
+}
+
+/// Load a federation config file. `.toml` files are parsed as TOML, anything
+/// else as YAML (which also accepts JSON). Either a `registries` table or a
+/// bare list of registries is accepted.
+pub fn load_federation_file(path: &Path) -> CadiResult<FederationConfig> {
+    let content = std::fs::read_to_string(path)?;
+    let invalid = |e: String| {
+        CadiError::RegistryError(format!("Invalid federation config {}: {}", path.display(), e))
+    };
+
+    if path.extension().is_some_and(|ext| ext == "toml") {
+        return toml::from_str(&content).map_err(|e| invalid(e.to_string()));
+    }
+
+    match serde_yaml::from_str::<FederationConfig>(&content) {
+        Ok(config) => Ok(config),
+        Err(_) => {
+            let registries: Vec<FederatedRegistry> =
+                serde_yaml::from_str(&content).map_err(|e| invalid(e.to_string()))?;
+            Ok(FederationConfig {
+                registries,
+                ..Default::default()
+            })
+        }
+    }
+}
+
 /// Load federation config from file
 pub fn load_federation_config(path: &std::path::Path) -> CadiResult<Vec<FederatedRegistry>> {
-    let content = std::fs::read_to_string(path)?;
-    let registries: Vec<FederatedRegistry> = serde_json::from_str(&content)?;
-    Ok(registries)
+    Ok(load_federation_file(path)?.registries)
 }
 
 /// Save federation config to file
@@ -3,14 +3,20 @@
 //! This crate provides the registry client for interacting with CADI registries.
 
 pub mod client;
+pub mod error;
 pub mod types;
 pub mod federation;
 pub mod search;
 pub mod db;
 pub mod graph;
+pub mod closure;
+pub mod sync;
 
 pub use client::*;
+pub use error::*;
 pub use types::*;
 pub use federation::*;
 pub use search::*;
+pub use closure::*;
+pub use sync::*;
 // Don't export db types to avoid conflicts
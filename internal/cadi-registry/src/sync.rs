@@ -0,0 +1,231 @@
+//! Mirroring chunks between federated registries
+//!
+//! [`FederationManager::sync`] copies what one registry has and another
+//! lacks: chunk content (verified against its ID), chunk metadata and
+//! aliases. Chunks are content-addressed, so a chunk the target already has
+//! is never copied again. Aliases are not: when the target already points an
+//! alias at a different chunk, the conflict is reported and the target's
+//! alias is left alone.
+
+use cadi_core::hash::verify_chunk_content;
+use cadi_core::{CadiError, CadiResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use crate::client::RegistryClient;
+use crate::federation::FederationManager;
+use crate::types::AliasEntry;
+
+/// Chunk IDs and aliases requested per page while listing the source
+const PAGE_SIZE: usize = 500;
+
+/// What to mirror
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncFilter {
+    /// Only mirror aliases under this prefix (e.g. `acme/`) and the chunks
+    /// they point at. Without one, every chunk and alias is mirrored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+}
+
+impl SyncFilter {
+    /// Mirror only the aliases under `namespace`
+    pub fn namespace(namespace: impl Into<String>) -> Self {
+        Self {
+            namespace: Some(namespace.into()),
+        }
+    }
+}
+
+/// What happened to one chunk
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkSyncOutcome {
+    /// Copied to the target
+    Copied,
+    /// The target already had it
+    Present,
+    /// Could not be copied
+    Failed,
+}
+
+/// Progress report of a running sync, sent once per chunk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncProgress {
+    /// Chunks done so far
+    pub processed: usize,
+    /// Chunks to look at
+    pub total: usize,
+    pub chunk_id: String,
+    pub outcome: ChunkSyncOutcome,
+}
+
+/// An alias that points at different chunks on the two registries
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AliasConflict {
+    pub path: String,
+    /// Chunk the source points the alias at
+    pub source_chunk: String,
+    /// Chunk the target points the alias at, which was kept
+    pub target_chunk: String,
+}
+
+/// Outcome of [`FederationManager::sync`]: what the target lacked and what
+/// was done about it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncReport {
+    pub source: String,
+    pub target: String,
+    /// Chunks copied to the target
+    pub copied: Vec<String>,
+    /// Chunks the target already had
+    pub present: usize,
+    /// Chunks, or their metadata, that could not be copied, with the reason
+    pub failed: Vec<(String, String)>,
+    /// Aliases created on the target
+    pub aliases_copied: Vec<String>,
+    /// Aliases the target already had, pointing at the same chunk
+    pub aliases_present: usize,
+    /// Aliases left alone because the target points them elsewhere
+    pub conflicts: Vec<AliasConflict>,
+}
+
+impl SyncReport {
+    /// Whether everything was mirrored without failures or conflicts
+    pub fn is_clean(&self) -> bool {
+        self.failed.is_empty() && self.conflicts.is_empty()
+    }
+}
+
+impl FederationManager {
+    /// Copy the chunks and aliases `source_id` has and `target_id` lacks.
+    ///
+    /// Content is verified against the chunk ID before it is published to
+    /// the target. Metadata is copied along with each new chunk; if that
+    /// fails the chunk is listed under `failed`, although its content is
+    /// already on the target. An alias is copied only once its chunk is on
+    /// the target, and never replaces an alias the target already has.
+    pub async fn sync(
+        &self,
+        source_id: &str,
+        target_id: &str,
+        filter: &SyncFilter,
+        mut on_progress: impl FnMut(&SyncProgress),
+    ) -> CadiResult<SyncReport> {
+        let source = self.sync_client(source_id)?;
+        let target = self.sync_client(target_id)?;
+        let mut report = SyncReport {
+            source: source_id.to_string(),
+            target: target_id.to_string(),
+            ..Default::default()
+        };
+
+        let aliases = list_all_aliases(source, filter.namespace.as_deref().unwrap_or("")).await?;
+        let chunk_ids: BTreeSet<String> = match filter.namespace {
+            Some(_) => aliases.iter().map(|alias| alias.chunk_id.clone()).collect(),
+            None => list_all_chunks(source).await?,
+        };
+
+        let total = chunk_ids.len();
+        let mut on_target = BTreeSet::new();
+        for (processed, chunk_id) in chunk_ids.into_iter().enumerate() {
+            let outcome = match copy_chunk(source, target, &chunk_id).await {
+                Ok(true) => {
+                    report.copied.push(chunk_id.clone());
+                    on_target.insert(chunk_id.clone());
+                    ChunkSyncOutcome::Copied
+                }
+                Ok(false) => {
+                    report.present += 1;
+                    on_target.insert(chunk_id.clone());
+                    ChunkSyncOutcome::Present
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to sync {} to {}: {}", chunk_id, target_id, e);
+                    report.failed.push((chunk_id.clone(), e.to_string()));
+                    ChunkSyncOutcome::Failed
+                }
+            };
+            on_progress(&SyncProgress {
+                processed: processed + 1,
+                total,
+                chunk_id,
+                outcome,
+            });
+        }
+
+        for alias in aliases {
+            if !on_target.contains(&alias.chunk_id) {
+                continue;
+            }
+            match target.resolve_alias(&alias.path).await? {
+                None => {
+                    target.publish_alias(&alias.path, &alias.chunk_id).await?;
+                    report.aliases_copied.push(alias.path);
+                }
+                Some(existing) if existing.chunk_id == alias.chunk_id => report.aliases_present += 1,
+                Some(existing) => report.conflicts.push(AliasConflict {
+                    path: alias.path,
+                    source_chunk: alias.chunk_id,
+                    target_chunk: existing.chunk_id,
+                }),
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn sync_client(&self, id: &str) -> CadiResult<&RegistryClient> {
+        self.client(id)
+            .ok_or_else(|| CadiError::RegistryError(format!("Unknown registry: {}", id)))
+    }
+}
+
+/// Copy one chunk, returning `false` if the target already has it
+async fn copy_chunk(source: &RegistryClient, target: &RegistryClient, chunk_id: &str) -> CadiResult<bool> {
+    if target.chunk_exists(chunk_id).await? {
+        return Ok(false);
+    }
+
+    let content = source.fetch_chunk(chunk_id).await?;
+    if !verify_chunk_content(chunk_id, &content) {
+        return Err(CadiError::RegistryError(format!(
+            "content served for {} does not match its ID",
+            chunk_id
+        )));
+    }
+    let published = target.publish_chunk(chunk_id, &content).await?;
+    if published.spooled {
+        return Err(CadiError::RegistryError("target unreachable, chunk spooled".to_string()));
+    }
+
+    if let Some(meta) = source.fetch_chunk_metadata(chunk_id).await? {
+        target
+            .publish_chunk_metadata(chunk_id, &meta)
+            .await
+            .map_err(|e| CadiError::RegistryError(format!("copying metadata failed: {}", e)))?;
+    }
+    Ok(true)
+}
+
+async fn list_all_chunks(client: &RegistryClient) -> CadiResult<BTreeSet<String>> {
+    let mut chunk_ids = BTreeSet::new();
+    let mut offset = Some(0);
+    while let Some(current) = offset {
+        let page = client.list_chunks(PAGE_SIZE, current).await?;
+        offset = page.next_offset();
+        chunk_ids.extend(page.chunk_ids);
+    }
+    Ok(chunk_ids)
+}
+
+async fn list_all_aliases(client: &RegistryClient, prefix: &str) -> CadiResult<Vec<AliasEntry>> {
+    let mut aliases = Vec::new();
+    let mut offset = Some(0);
+    while let Some(current) = offset {
+        let page = client.list_aliases(prefix, PAGE_SIZE, current).await?;
+        offset = page.next_offset();
+        aliases.extend(page.aliases);
+    }
+    Ok(aliases)
+}
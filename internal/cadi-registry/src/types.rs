@@ -120,6 +120,111 @@ pub struct FetchFailure {
     pub error: String,
 }
 
+/// Transitive closure of a chunk's references, as served by
+/// `GET /v1/chunks/:chunk_id/closure`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChunkClosure {
+    /// The chunk the closure was computed for
+    pub root: String,
+    /// The root and every chunk it references, in breadth-first order
+    pub chunks: Vec<ClosureEntry>,
+    /// Referenced chunks the registry does not have
+    #[serde(default)]
+    pub missing: Vec<String>,
+    /// Sum of the sizes of `chunks`
+    pub total_size: u64,
+    /// Whether the node limit cut the closure short
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+/// A chunk in a [`ChunkClosure`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClosureEntry {
+    pub chunk_id: String,
+    pub size: u64,
+    /// SHA-256 of the chunk's content
+    pub content_hash: String,
+}
+
+/// An alias stored in a registry, as served by `GET /aliases/:path`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AliasEntry {
+    /// Alias path, e.g. `acme/utils/logger`
+    pub path: String,
+    pub chunk_id: String,
+    /// When the alias was last set (RFC 3339)
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+/// A page of aliases, as served by `GET /aliases?prefix=...`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AliasPage {
+    /// Aliases in path order
+    pub aliases: Vec<AliasEntry>,
+    /// Number of aliases matching the prefix
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+/// How often a chunk was used, as served by `GET /v1/chunks/:id/stats`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChunkUsageStats {
+    pub chunk_id: String,
+    /// Fetches and build plan appearances since the chunk was published
+    pub usage_count: u64,
+    /// When the chunk was last used (RFC 3339)
+    #[serde(default)]
+    pub last_used_at: Option<String>,
+    /// Uses per day, oldest first; days without uses are left out
+    #[serde(default)]
+    pub daily: Vec<DailyUsage>,
+}
+
+impl ChunkUsageStats {
+    /// Uses in the daily buckets, i.e. over the retention window
+    pub fn recent_usage(&self) -> u64 {
+        self.daily.iter().map(|d| d.count).sum()
+    }
+}
+
+/// Uses of a chunk on one day
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailyUsage {
+    /// UTC date, `YYYY-MM-DD`
+    pub day: String,
+    pub count: u64,
+}
+
+impl AliasPage {
+    /// Offset of the next page, if there is one
+    pub fn next_offset(&self) -> Option<usize> {
+        let next = self.offset + self.aliases.len();
+        (!self.aliases.is_empty() && next < self.total).then_some(next)
+    }
+}
+
+/// A page of chunk IDs, as served by `GET /v1/chunk_ids`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChunkPage {
+    /// Chunk IDs in sorted order
+    pub chunk_ids: Vec<String>,
+    /// Number of chunks stored
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl ChunkPage {
+    /// Offset of the next page, if there is one
+    pub fn next_offset(&self) -> Option<usize> {
+        let next = self.offset + self.chunk_ids.len();
+        (!self.chunk_ids.is_empty() && next < self.total).then_some(next)
+    }
+}
+
 /// Authentication info
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthInfo {
@@ -147,3 +252,85 @@ pub struct Publisher {
     /// Public key fingerprint
     pub key_fingerprint: Option<String>,
 }
+
+/// Version of the [`QueryOutput`] schema, bumped on incompatible changes
+pub const QUERY_OUTPUT_VERSION: u32 = 1;
+
+/// Machine-readable output of `cadi query --format json|yaml`.
+///
+/// Keys serialize in sorted order so scripts can diff or hash the output:
+/// fields here and in [`QueryRecord`] are declared alphabetically and
+/// projected records are ordered maps.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueryOutput {
+    /// Matching chunks, each projected onto the selected fields
+    pub chunks: Vec<std::collections::BTreeMap<String, serde_json::Value>>,
+    /// Number of matching chunks (at most the query limit)
+    pub count: usize,
+    /// Always [`QUERY_OUTPUT_VERSION`]
+    pub version: u32,
+}
+
+/// A chunk matched by `cadi query`. Fields the answering registry does not
+/// report are `None` and serialize as `null`, so every record has the same
+/// keys.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueryRecord {
+    pub cadi_type: Option<String>,
+    pub chunk_id: String,
+    pub content_type: Option<String>,
+    pub language: Option<String>,
+    pub name: Option<String>,
+    /// URL of the registry the chunk was found in
+    pub registry: Option<String>,
+    /// Relevance score, for semantic queries
+    pub score: Option<f64>,
+    /// Size in bytes
+    pub size: Option<u64>,
+}
+
+impl QueryRecord {
+    /// Names accepted by [`QueryRecord::project`], in serialization order
+    pub const FIELDS: &'static [&'static str] = &[
+        "cadi_type",
+        "chunk_id",
+        "content_type",
+        "language",
+        "name",
+        "registry",
+        "score",
+        "size",
+    ];
+
+    /// The value of a field by name; `None` for names not in
+    /// [`QueryRecord::FIELDS`]
+    pub fn field(&self, name: &str) -> Option<serde_json::Value> {
+        use serde_json::Value;
+
+        Some(match name {
+            "cadi_type" => self.cadi_type.clone().map_or(Value::Null, Value::from),
+            "chunk_id" => Value::from(self.chunk_id.clone()),
+            "content_type" => self.content_type.clone().map_or(Value::Null, Value::from),
+            "language" => self.language.clone().map_or(Value::Null, Value::from),
+            "name" => self.name.clone().map_or(Value::Null, Value::from),
+            "registry" => self.registry.clone().map_or(Value::Null, Value::from),
+            "score" => self.score.map_or(Value::Null, Value::from),
+            "size" => self.size.map_or(Value::Null, Value::from),
+            _ => return None,
+        })
+    }
+
+    /// The record restricted to `fields`, or every field when `fields` is
+    /// empty. Unknown names are skipped.
+    pub fn project(&self, fields: &[String]) -> std::collections::BTreeMap<String, serde_json::Value> {
+        let names: Vec<&str> = if fields.is_empty() {
+            Self::FIELDS.to_vec()
+        } else {
+            fields.iter().map(String::as_str).collect()
+        };
+        names
+            .into_iter()
+            .filter_map(|name| Some((name.to_string(), self.field(name)?)))
+            .collect()
+    }
+}
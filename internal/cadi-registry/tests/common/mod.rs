@@ -0,0 +1,142 @@
+//! Fixtures shared by the registry integration tests: a minimal chunk and a
+//! stub registry speaking just enough HTTP for the client
+//!
+//! Each test file uses only some of these.
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use cadi_core::{CadiType, Chunk, ChunkLicensing, ChunkLineage, ChunkMeta, ChunkProvides};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// A minimal source chunk with the given ID and name
+pub fn chunk(chunk_id: &str, name: &str) -> Chunk {
+    Chunk {
+        chunk_id: chunk_id.to_string(),
+        cadi_type: CadiType::Source,
+        meta: ChunkMeta {
+            name: name.to_string(),
+            description: None,
+            version: None,
+            tags: vec![],
+            created_at: None,
+            updated_at: None,
+        },
+        provides: ChunkProvides { concepts: vec![], interfaces: vec![], abi: None },
+        licensing: ChunkLicensing { license: "MIT".to_string(), restrictions: vec![] },
+        lineage: ChunkLineage::default(),
+        signatures: vec![],
+    }
+}
+
+/// A request received by a [`stub_registry`]
+#[derive(Debug, Clone, Default)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// Header lines, with lowercase names
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Value of a header, by lowercase name
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+}
+
+/// A response a [`stub_registry`] sends
+#[derive(Debug, Clone)]
+pub struct Response {
+    /// Status code and reason, e.g. `404 Not Found`
+    pub status: &'static str,
+    /// Extra header lines, each ending in `\r\n`
+    pub headers: String,
+    pub body: String,
+}
+
+impl Response {
+    pub fn new(status: &'static str, body: impl Into<String>) -> Self {
+        Self { status, headers: String::new(), body: body.into() }
+    }
+
+    /// A `200 OK` JSON response
+    pub fn json(body: impl Into<String>) -> Self {
+        Self::new("200 OK", body).header("content-type", "application/json")
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push_str(&format!("{}: {}\r\n", name, value));
+        self
+    }
+}
+
+/// Start a registry that answers every request with `respond`, one
+/// connection per request, and return its URL
+pub async fn stub_registry(respond: impl Fn(Request) -> Response + Send + Sync + 'static) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let respond = Arc::new(respond);
+
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut socket, _)) = listener.accept().await else {
+                break;
+            };
+            let response = respond(read_request(&mut socket).await);
+            let head = format!(
+                "HTTP/1.1 {}\r\n{}content-length: {}\r\nconnection: close\r\n\r\n",
+                response.status,
+                response.headers,
+                response.body.len()
+            );
+            let _ = socket.write_all(head.as_bytes()).await;
+            let _ = socket.write_all(response.body.as_bytes()).await;
+            let _ = socket.shutdown().await;
+        }
+    });
+
+    url
+}
+
+/// A URL nothing listens on
+pub fn unreachable_url() -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    format!("http://{}", listener.local_addr().unwrap())
+}
+
+/// Read a full HTTP request: headers plus a `content-length` body
+pub async fn read_request(socket: &mut TcpStream) -> Request {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while let Ok(n) = socket.read(&mut chunk).await {
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+
+        let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+            continue;
+        };
+        let head = String::from_utf8_lossy(&buf[..end]).into_owned();
+        let mut lines = head.lines();
+        let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+        let mut request = Request {
+            method: request_line.next().unwrap_or_default().to_string(),
+            path: request_line.next().unwrap_or_default().to_string(),
+            headers: lines
+                .filter_map(|line| line.split_once(':'))
+                .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+                .collect(),
+            body: Vec::new(),
+        };
+        let length = request.header("content-length").and_then(|v| v.parse::<usize>().ok()).unwrap_or(0);
+        if buf.len() >= end + 4 + length {
+            request.body = buf[end + 4..end + 4 + length].to_vec();
+            return request;
+        }
+    }
+    Request::default()
+}
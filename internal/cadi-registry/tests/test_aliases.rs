@@ -0,0 +1,54 @@
+use cadi_registry::db::RegistryDatabase;
+use surrealdb::{engine::local::Mem, Surreal};
+
+async fn registry() -> Result<RegistryDatabase, Box<dyn std::error::Error>> {
+    let db = Surreal::new::<Mem>(()).await?;
+    db.use_ns("test").use_db("test").await?;
+    Ok(RegistryDatabase::new(db, None).await?)
+}
+
+#[tokio::test]
+async fn test_alias_round_trip_and_rebind() -> Result<(), Box<dyn std::error::Error>> {
+    let registry = registry().await?;
+
+    let stored = registry.put_alias("acme/utils/logger", "chunk:sha256:aaa").await?;
+    assert_eq!(stored.path, "acme/utils/logger");
+    assert_eq!(stored.chunk_id, "chunk:sha256:aaa");
+    assert!(!stored.updated_at.is_empty());
+
+    // Setting the alias again rebinds it rather than adding a second row
+    registry.put_alias("acme/utils/logger", "chunk:sha256:bbb").await?;
+    let resolved = registry.get_alias("acme/utils/logger").await?.expect("alias exists");
+    assert_eq!(resolved.chunk_id, "chunk:sha256:bbb");
+    assert_eq!(registry.list_aliases("acme/", 10, 0).await?.total, 1);
+
+    assert!(registry.get_alias("acme/utils/missing").await?.is_none());
+
+    assert!(registry.delete_alias("acme/utils/logger").await?);
+    assert!(!registry.delete_alias("acme/utils/logger").await?);
+    assert!(registry.get_alias("acme/utils/logger").await?.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_aliases_listed_by_prefix_in_pages() -> Result<(), Box<dyn std::error::Error>> {
+    let registry = registry().await?;
+    for name in ["e", "b", "d", "a", "c"] {
+        registry.put_alias(&format!("acme/{}", name), &format!("chunk:sha256:{}", name)).await?;
+    }
+    registry.put_alias("other/a", "chunk:sha256:other").await?;
+
+    let first = registry.list_aliases("acme/", 2, 0).await?;
+    let paths: Vec<&str> = first.aliases.iter().map(|a| a.path.as_str()).collect();
+    assert_eq!(paths, ["acme/a", "acme/b"]);
+    assert_eq!(first.total, 5);
+    assert_eq!(first.next_offset(), Some(2));
+
+    let last = registry.list_aliases("acme/", 2, 4).await?;
+    let paths: Vec<&str> = last.aliases.iter().map(|a| a.path.as_str()).collect();
+    assert_eq!(paths, ["acme/e"]);
+    assert_eq!(last.next_offset(), None);
+
+    assert_eq!(registry.list_aliases("", 100, 0).await?.total, 6);
+    Ok(())
+}
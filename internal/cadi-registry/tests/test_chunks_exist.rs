@@ -0,0 +1,114 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use cadi_core::hash::{chunk_storage_key, sha256_str};
+use cadi_core::ProjectAnalyzer;
+use cadi_registry::client::{RegistryClient, RegistryConfig};
+
+mod common;
+use common::{stub_registry, Response};
+
+/// Start a mock registry holding the chunks with storage keys `known`. With
+/// `bulk` it answers `POST /v1/chunks/exists`, else only per-chunk HEADs
+/// like a registry that predates the bulk endpoint. Returns the URL and the
+/// method and path of each request it received.
+async fn mock_registry(known: HashSet<String>, bulk: bool) -> (String, Arc<Mutex<Vec<String>>>) {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let log = requests.clone();
+
+    let url = stub_registry(move |request| {
+        log.lock().unwrap().push(format!("{} {}", request.method, request.path));
+        match (request.method.as_str(), request.path.as_str()) {
+            ("POST", "/v1/chunks/exists") if bulk => {
+                let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+                let existing: Vec<&str> = body["hashes"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .filter_map(|hash| hash.as_str())
+                    .filter(|hash| known.contains(*hash))
+                    .collect();
+                Response::json(serde_json::json!({ "existing": existing }).to_string())
+            }
+            ("HEAD", path) => {
+                let chunk_id = path.trim_start_matches("/v1/chunks/");
+                let status = if known.contains(&chunk_storage_key(chunk_id)) { "200 OK" } else { "404 Not Found" };
+                Response::new(status, "")
+            }
+            _ => Response::new("405 Method Not Allowed", ""),
+        }
+    })
+    .await;
+
+    (url, requests)
+}
+
+fn client(url: String) -> RegistryClient {
+    RegistryClient::new(RegistryConfig {
+        url,
+        max_retries: 1,
+        base_backoff_ms: 1,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+fn hashes(n: usize) -> Vec<String> {
+    (0..n)
+        .map(|i| sha256_str(&format!("pub fn f{}() {{}}", i)))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_bulk_check_reports_known_half() {
+    let hashes = hashes(6);
+    let known: HashSet<String> = hashes.iter().step_by(2).cloned().collect();
+    let (url, requests) = mock_registry(known.clone(), true).await;
+
+    let existing = client(url).existing_chunks(&hashes).await.unwrap();
+    assert_eq!(existing, known);
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_registry_without_bulk_endpoint_is_asked_per_chunk() {
+    let hashes = hashes(4);
+    let known: HashSet<String> = hashes.iter().step_by(2).cloned().collect();
+    let (url, requests) = mock_registry(known.clone(), false).await;
+
+    let existing = client(url).existing_chunks(&hashes).await.unwrap();
+    assert_eq!(existing, known);
+    let requests = requests.lock().unwrap();
+    assert!(requests[0].starts_with("POST /v1/chunks/exists"));
+    assert_eq!(requests.iter().filter(|r| r.starts_with("HEAD ")).count(), hashes.len());
+}
+
+#[tokio::test]
+async fn test_reconcile_marks_chunks_the_registry_holds() {
+    let root = std::env::temp_dir().join(format!("cadi-reconcile-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("src")).unwrap();
+    for name in ["alpha", "beta", "gamma", "delta"] {
+        std::fs::write(
+            root.join(format!("src/{}.rs", name)),
+            format!("pub fn {}() -> &'static str {{\n    \"{}\"\n}}\n", name, name),
+        )
+        .unwrap();
+    }
+    let mut result = ProjectAnalyzer::default().import_project(&root).unwrap();
+    let _ = std::fs::remove_dir_all(&root);
+
+    let hashes = result.chunk_hashes();
+    let known: HashSet<String> = hashes.iter().take(hashes.len() / 2).cloned().collect();
+    let (url, _) = mock_registry(known.clone(), true).await;
+
+    let reused = client(url).reconcile_import(&mut result).await.unwrap();
+    assert_eq!(reused, known.len());
+    assert_eq!(result.summary.registry_chunks, known.len());
+    for chunk in result.chunks.iter().chain(&result.compositions) {
+        assert_eq!(
+            result.is_reused_from_registry(&chunk.chunk_id),
+            known.contains(&chunk_storage_key(&chunk.chunk_id))
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use cadi_registry::client::{ChunkCacheStats, RegistryClient, RegistryConfig};
+
+mod common;
+use common::{stub_registry, Response};
+
+/// Requests a mock registry received and chunk bodies it sent
+#[derive(Default)]
+struct Counters {
+    requests: AtomicUsize,
+    bodies: AtomicUsize,
+}
+
+/// Start a mock registry that serves every chunk with the body
+/// `content of <chunk_id>` and an ETag derived from the chunk ID, answering
+/// 304 when the request carries a matching `If-None-Match`.
+async fn mock_registry() -> (String, Arc<Counters>) {
+    let counters = Arc::new(Counters::default());
+    let seen = counters.clone();
+
+    let url = stub_registry(move |request| {
+        seen.requests.fetch_add(1, Ordering::SeqCst);
+        let chunk_id = request.path.strip_prefix("/v1/chunks/").unwrap_or_default();
+        let etag = format!("\"{}\"", chunk_id.replace(':', "-"));
+
+        if request.header("if-none-match") == Some(etag.as_str()) {
+            Response::new("304 Not Modified", "").header("etag", &etag)
+        } else {
+            seen.bodies.fetch_add(1, Ordering::SeqCst);
+            Response::new("200 OK", format!("content of {}", chunk_id)).header("etag", &etag)
+        }
+    })
+    .await;
+
+    (url, counters)
+}
+
+fn cached_client(url: String, name: &str) -> (RegistryClient, std::path::PathBuf) {
+    let cache = std::env::temp_dir().join(format!("cadi-http-cache-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&cache);
+    let client = RegistryClient::new(RegistryConfig {
+        url,
+        cache_dir: Some(cache.clone()),
+        ..Default::default()
+    })
+    .unwrap();
+    (client, cache)
+}
+
+#[tokio::test]
+async fn test_second_fetch_is_revalidated_without_body() {
+    let (url, counters) = mock_registry().await;
+    let (client, cache) = cached_client(url, "revalidate");
+
+    let first = client.fetch_chunk_cached("chunk:sha256:aaa").await.unwrap();
+    let second = client.fetch_chunk_cached("chunk:sha256:aaa").await.unwrap();
+
+    assert_eq!(first, b"content of chunk:sha256:aaa");
+    assert_eq!(second, first);
+    assert_eq!(counters.requests.load(Ordering::SeqCst), 2);
+    assert_eq!(counters.bodies.load(Ordering::SeqCst), 1);
+    assert_eq!(
+        client.cache_stats().unwrap(),
+        Some(ChunkCacheStats { hits: 1, misses: 1 })
+    );
+
+    let _ = std::fs::remove_dir_all(&cache);
+}
+
+#[tokio::test]
+async fn test_different_chunk_is_not_served_stale() {
+    let (url, counters) = mock_registry().await;
+    let (client, cache) = cached_client(url, "stale");
+
+    client.fetch_chunk_cached("chunk:sha256:aaa").await.unwrap();
+    let other = client.fetch_chunk_cached("chunk:sha256:bbb").await.unwrap();
+
+    assert_eq!(other, b"content of chunk:sha256:bbb");
+    assert_eq!(counters.bodies.load(Ordering::SeqCst), 2);
+    assert_eq!(ChunkCacheStats::load(&cache).unwrap(), ChunkCacheStats { hits: 0, misses: 2 });
+
+    let _ = std::fs::remove_dir_all(&cache);
+}
+
+#[tokio::test]
+async fn test_fetch_without_cache_dir_reports_no_stats() {
+    let (url, counters) = mock_registry().await;
+    let client = RegistryClient::new(RegistryConfig { url, ..Default::default() }).unwrap();
+
+    client.fetch_chunk_cached("chunk:sha256:aaa").await.unwrap();
+    client.fetch_chunk_cached("chunk:sha256:aaa").await.unwrap();
+
+    assert_eq!(counters.bodies.load(Ordering::SeqCst), 2);
+    assert_eq!(client.cache_stats().unwrap(), None);
+}
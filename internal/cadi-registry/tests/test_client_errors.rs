@@ -0,0 +1,134 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use cadi_registry::client::{RegistryClient, RegistryConfig};
+use cadi_registry::RegistryError;
+
+mod common;
+use common::{stub_registry, unreachable_url, Response};
+
+/// Start a mock registry that answers every request with `status`, the
+/// extra `headers` and `body`
+async fn mock_registry(status: &'static str, headers: &'static str, body: &'static str) -> (String, Arc<AtomicUsize>) {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    let url = stub_registry(move |_| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Response { headers: headers.to_string(), ..Response::new(status, body) }
+    })
+    .await;
+    (url, requests)
+}
+
+async fn stub_client(status: &'static str, headers: &'static str, body: &'static str) -> (RegistryClient, Arc<AtomicUsize>) {
+    let (url, requests) = mock_registry(status, headers, body).await;
+    let client = RegistryClient::new(RegistryConfig {
+        url,
+        max_retries: 1,
+        base_backoff_ms: 1,
+        ..Default::default()
+    })
+    .unwrap();
+    (client, requests)
+}
+
+const CHUNK: &str = "chunk:sha256:abc";
+
+#[tokio::test]
+async fn test_not_found() {
+    let (client, _) = stub_client("404 Not Found", "", "").await;
+
+    match client.fetch_chunk(CHUNK).await {
+        Err(RegistryError::NotFound(resource)) => assert_eq!(resource, CHUNK),
+        other => panic!("expected NotFound, got {:?}", other),
+    }
+    assert!(!client.chunk_exists(CHUNK).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_unauthorized_and_forbidden() {
+    let (client, requests) = stub_client("401 Unauthorized", "", "Invalid token").await;
+    match client.publish_chunk(CHUNK, b"data").await {
+        Err(RegistryError::Unauthorized(message)) => assert_eq!(message, "Invalid token"),
+        other => panic!("expected Unauthorized, got {:?}", other),
+    }
+    // Bad credentials aren't retried
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    let (client, _) = stub_client("403 Forbidden", "", "").await;
+    match client.fetch_chunk(CHUNK).await {
+        Err(RegistryError::Unauthorized(message)) => assert_eq!(message, "HTTP 403"),
+        other => panic!("expected Unauthorized, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_conflict() {
+    let (client, _) = stub_client("409 Conflict", "", "Chunk already exists").await;
+
+    match client.publish_chunk(CHUNK, b"data").await {
+        Err(RegistryError::Conflict(resource)) => assert_eq!(resource, CHUNK),
+        other => panic!("expected Conflict, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_hash_mismatch() {
+    let (client, _) = stub_client("422 Unprocessable Entity", "", "").await;
+    assert!(matches!(client.publish_chunk(CHUNK, b"data").await, Err(RegistryError::HashMismatch(_))));
+
+    let (client, _) = stub_client(
+        "200 OK",
+        "content-type: application/json\r\n",
+        r#"{"success":false,"chunk_id":null,"message":"Hash mismatch"}"#,
+    )
+    .await;
+    match client.publish_chunk(CHUNK, b"data").await {
+        Err(RegistryError::HashMismatch(resource)) => assert_eq!(resource, CHUNK),
+        other => panic!("expected HashMismatch, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_rate_limited() {
+    // A short Retry-After is waited out and retried
+    let (client, requests) = stub_client("429 Too Many Requests", "retry-after: 0\r\n", "").await;
+    match client.fetch_chunk(CHUNK).await {
+        Err(RegistryError::RateLimited { retry_after }) => assert_eq!(retry_after, Some(Duration::ZERO)),
+        other => panic!("expected RateLimited, got {:?}", other),
+    }
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    // A long one is handed back to the caller straight away
+    let (client, requests) = stub_client("429 Too Many Requests", "retry-after: 120\r\n", "").await;
+    let error = client.fetch_chunk(CHUNK).await.unwrap_err();
+    assert_eq!(error.retry_after(), Some(Duration::from_secs(120)));
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_server_error() {
+    let (client, _) = stub_client("500 Internal Server Error", "", "database unavailable").await;
+
+    match client.fetch_chunk(CHUNK).await {
+        Err(RegistryError::Server { status, body }) => {
+            assert_eq!(status, 500);
+            assert_eq!(body, "database unavailable");
+        }
+        other => panic!("expected Server, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_network_error() {
+    let client = RegistryClient::new(RegistryConfig {
+        url: unreachable_url(),
+        max_retries: 0,
+        ..Default::default()
+    })
+    .unwrap();
+
+    assert!(matches!(client.fetch_chunk(CHUNK).await, Err(RegistryError::Network(_))));
+}
+
@@ -0,0 +1,86 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use cadi_registry::client::{RegistryClient, RegistryConfig};
+
+mod common;
+use common::{stub_registry, unreachable_url, Response};
+
+/// Start a mock registry that answers the first `failures` requests with a
+/// 503 and every later one with a successful publish result.
+async fn mock_registry(failures: usize) -> (String, Arc<AtomicUsize>) {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    let url = stub_registry(move |_| {
+        if counter.fetch_add(1, Ordering::SeqCst) < failures {
+            Response::new("503 Service Unavailable", "")
+        } else {
+            Response::json(r#"{"success":true,"chunk_id":"chunk:sha256:abc","message":null}"#)
+        }
+    })
+    .await;
+    (url, requests)
+}
+
+fn config(url: String) -> RegistryConfig {
+    RegistryConfig {
+        url,
+        base_backoff_ms: 1,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_publish_retries_until_success() {
+    let (url, requests) = mock_registry(2).await;
+    let client = RegistryClient::new(config(url)).unwrap();
+
+    let result = client.publish_chunk("chunk:sha256:abc", b"data").await.unwrap();
+
+    assert!(result.success);
+    assert!(!result.spooled);
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_publish_fails_when_retries_exhausted() {
+    let (url, requests) = mock_registry(2).await;
+    let client = RegistryClient::new(RegistryConfig {
+        max_retries: 1,
+        ..config(url)
+    })
+    .unwrap();
+
+    assert!(client.publish_chunk("chunk:sha256:abc", b"data").await.is_err());
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_spooled_chunks_are_flushed_later() {
+    let spool = std::env::temp_dir().join(format!("cadi-spool-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&spool);
+
+    let offline = RegistryClient::new(RegistryConfig {
+        max_retries: 0,
+        spool_dir: Some(spool.clone()),
+        ..config(unreachable_url())
+    })
+    .unwrap();
+
+    let result = offline.publish_chunk("chunk:sha256:abc", b"data").await.unwrap();
+    assert!(result.spooled);
+
+    let (url, _) = mock_registry(2).await;
+    let online = RegistryClient::new(RegistryConfig {
+        spool_dir: Some(spool.clone()),
+        ..config(url)
+    })
+    .unwrap();
+
+    let flushed = online.flush_spool().await.unwrap();
+    assert_eq!(flushed.flushed, vec!["chunk:sha256:abc".to_string()]);
+    assert!(flushed.remaining.is_empty());
+    assert!(online.flush_spool().await.unwrap().flushed.is_empty());
+
+    let _ = std::fs::remove_dir_all(&spool);
+}
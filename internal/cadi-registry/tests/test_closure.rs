@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+
+use cadi_core::hash::sha256_bytes;
+use cadi_registry::{resolve_closure, MAX_CLOSURE_NODES};
+use serde_json::json;
+
+/// A chunk composed of `deps`
+fn chunk(deps: &[&str]) -> Vec<u8> {
+    let composed_of: Vec<_> = deps.iter().map(|d| json!({"chunk_id": d})).collect();
+    json!({"composition": {"composed_of": composed_of}}).to_string().into_bytes()
+}
+
+async fn closure(
+    store: &HashMap<String, Vec<u8>>,
+    root: &str,
+    max_depth: Option<usize>,
+    node_limit: usize,
+) -> Option<cadi_registry::ChunkClosure> {
+    resolve_closure(root, max_depth, node_limit, |id| {
+        let data = store.get(&id).cloned();
+        async move { data }
+    })
+    .await
+}
+
+fn ids(closure: &cadi_registry::ChunkClosure) -> Vec<&str> {
+    closure.chunks.iter().map(|c| c.chunk_id.as_str()).collect()
+}
+
+#[tokio::test]
+async fn test_closure_terminates_on_cycles() {
+    let store = HashMap::from([
+        ("chunk:a".to_string(), chunk(&["chunk:b", "chunk:c"])),
+        ("chunk:b".to_string(), chunk(&["chunk:c"])),
+        ("chunk:c".to_string(), chunk(&["chunk:a"])),
+    ]);
+
+    let result = closure(&store, "chunk:a", None, MAX_CLOSURE_NODES).await.unwrap();
+    assert_eq!(ids(&result), ["chunk:a", "chunk:b", "chunk:c"]);
+    assert!(result.missing.is_empty());
+    assert!(!result.truncated);
+    assert_eq!(result.total_size, store.values().map(|d| d.len() as u64).sum::<u64>());
+    assert_eq!(result.chunks[1].content_hash, sha256_bytes(&store["chunk:b"]));
+
+    let shallow = closure(&store, "chunk:a", Some(0), MAX_CLOSURE_NODES).await.unwrap();
+    assert_eq!(ids(&shallow), ["chunk:a"]);
+
+    let limited = closure(&store, "chunk:a", None, 2).await.unwrap();
+    assert_eq!(ids(&limited), ["chunk:a", "chunk:b"]);
+    assert!(limited.truncated);
+}
+
+#[tokio::test]
+async fn test_missing_dependencies_are_listed() {
+    let store = HashMap::from([
+        ("chunk:a".to_string(), chunk(&["chunk:b", "chunk:gone"])),
+        ("chunk:b".to_string(), chunk(&["chunk:also-gone"])),
+    ]);
+
+    let result = closure(&store, "chunk:a", None, MAX_CLOSURE_NODES).await.unwrap();
+    assert_eq!(ids(&result), ["chunk:a", "chunk:b"]);
+    assert_eq!(result.missing, ["chunk:gone", "chunk:also-gone"]);
+
+    assert!(closure(&store, "chunk:gone", None, MAX_CLOSURE_NODES).await.is_none());
+}
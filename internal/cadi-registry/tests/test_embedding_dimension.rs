@@ -0,0 +1,94 @@
+use async_trait::async_trait;
+use cadi_llm::embeddings::{Embedding, EmbeddingManager, EmbeddingProvider, MockProvider};
+use cadi_registry::db::{RegistryDatabase, SearchQuery};
+use surrealdb::{engine::local::Mem, Surreal};
+
+mod common;
+use common::chunk;
+
+/// Deterministic provider with the dimension of small sentence-transformer models
+struct Mock384;
+
+#[async_trait]
+impl EmbeddingProvider for Mock384 {
+    async fn generate(&self, text: &str) -> anyhow::Result<Embedding> {
+        let mut embedding = vec![0.0; 384];
+        for (i, byte) in text.bytes().enumerate() {
+            embedding[(i * 31 + byte as usize) % 384] += 1.0;
+        }
+        Ok(embedding)
+    }
+
+    fn dimension(&self) -> usize {
+        384
+    }
+}
+
+#[tokio::test]
+async fn test_store_and_semantic_search_with_384_dim_embeddings() -> Result<(), Box<dyn std::error::Error>> {
+    let db = Surreal::new::<Mem>(()).await?;
+    db.use_ns("test").use_db("test").await?;
+
+    let manager = EmbeddingManager::new(Box::new(Mock384), None);
+    let mut registry = RegistryDatabase::new(db, Some(manager)).await?;
+
+    for (chunk_id, name, description, language) in [
+        ("chunk:dim-parse-config", "parse_config", "Parse a TOML configuration file", "rust"),
+        ("chunk:dim-render-button", "render_button", "Render a clickable button", "typescript"),
+    ] {
+        let metadata = serde_json::json!({
+            "name": name,
+            "description": description,
+            "language": language,
+            "concepts": [],
+        });
+        registry.store_chunk(&chunk(chunk_id, name), "body", metadata).await?;
+    }
+
+    let query_embedding = registry.embedding_manager_mut().unwrap()
+        .get_chunk_embedding("query", "parse_config Parse a TOML configuration file ").await?;
+    assert_eq!(query_embedding.len(), 384);
+
+    let results = registry.search(SearchQuery {
+        embedding: Some(query_embedding),
+        limit: 5,
+        ..Default::default()
+    }).await?;
+
+    assert!(!results.is_empty());
+    assert_eq!(results[0].metadata.name, "parse_config");
+    assert_eq!(results[0].metadata.language, "rust");
+
+    let record = registry.get_chunk("chunk:dim-render-button").await?.expect("chunk must exist");
+    assert_eq!(record.language, "typescript");
+    assert_eq!(record.embedding.map(|e| e.len()), Some(384));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_index_is_rebuilt_when_dimension_changes() -> Result<(), Box<dyn std::error::Error>> {
+    let db = Surreal::new::<Mem>(()).await?;
+    db.use_ns("test").use_db("test").await?;
+
+    let mut registry = RegistryDatabase::new(db.clone(), Some(EmbeddingManager::new(Box::new(Mock384), None))).await?;
+    let metadata = serde_json::json!({"name": "parse_config", "description": "Parse a config file", "language": "rust"});
+    registry.store_chunk(&chunk("chunk:dim-parse-config", "parse_config"), "body", metadata).await?;
+
+    // Reopen with a provider of another dimension
+    let mut registry = RegistryDatabase::new(db, Some(EmbeddingManager::new(Box::new(MockProvider), None))).await?;
+
+    let record = registry.get_chunk("chunk:dim-parse-config").await?.expect("chunk must exist");
+    assert_eq!(record.embedding.map(|e| e.len()), Some(5));
+
+    let query_embedding = registry.embedding_manager_mut().unwrap()
+        .get_chunk_embedding("query", "parse_config").await?;
+    let results = registry.search(SearchQuery {
+        embedding: Some(query_embedding),
+        limit: 5,
+        ..Default::default()
+    }).await?;
+    assert_eq!(results.len(), 1);
+
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+use cadi_registry::client::SearchQuery;
+use cadi_registry::federation::FederationManager;
+
+mod common;
+use common::{stub_registry, unreachable_url, Response};
+
+/// Start a mock registry serving fixed JSON bodies keyed by request path
+async fn mock_registry(routes: HashMap<&'static str, String>) -> String {
+    stub_registry(move |request| match routes.get(request.path.as_str()) {
+        Some(body) => Response::json(body.clone()),
+        None => Response::new("404 Not Found", ""),
+    })
+    .await
+}
+
+fn search_body(chunks: &[(&str, &str)]) -> String {
+    let chunks: Vec<_> = chunks
+        .iter()
+        .map(|(id, name)| serde_json::json!({"chunk_id": id, "name": name, "cadi_type": "source"}))
+        .collect();
+    serde_json::json!({"chunks": chunks, "total": chunks.len(), "offset": 0, "limit": 10}).to_string()
+}
+
+fn healthy_routes(chunks: &[(&str, &str)]) -> HashMap<&'static str, String> {
+    HashMap::from([
+        ("/health", r#"{"healthy":true,"version":null,"message":null}"#.to_string()),
+        ("/v1/search", search_body(chunks)),
+    ])
+}
+
+fn write_config(name: &str, content: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("cadi-federation-{}-{}", std::process::id(), name));
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+#[test]
+fn test_from_config_toml_and_yaml() {
+    let toml = write_config(
+        "registries.toml",
+        r#"
+health_check_interval_secs = 5
+
+[[registries]]
+url = "https://mirror.example.com"
+priority = 2
+
+[[registries]]
+id = "primary"
+url = "https://registry.example.com"
+token = "secret"
+priority = 1
+
+[[registries]]
+url = "https://old.example.com"
+priority = 0
+enabled = false
+"#,
+    );
+    let manager = FederationManager::from_config(&toml).unwrap();
+    let ids: Vec<&str> = manager.registries().iter().map(|r| r.id.as_str()).collect();
+    assert_eq!(ids, vec!["https://old.example.com", "primary", "https://mirror.example.com"]);
+    assert_eq!(manager.registries()[1].token.as_deref(), Some("secret"));
+    assert!(!manager.registries()[0].enabled);
+    assert!(manager.registries()[1].capabilities.search);
+
+    let yaml = write_config(
+        "registries.yaml",
+        "registries:\n  - url: https://registry.example.com\n    priority: 1\n    enabled: true\n",
+    );
+    let manager = FederationManager::from_config(&yaml).unwrap();
+    assert_eq!(manager.registries().len(), 1);
+
+    let _ = std::fs::remove_file(toml);
+    let _ = std::fs::remove_file(yaml);
+}
+
+#[tokio::test]
+async fn test_search_merges_by_priority() {
+    let primary = mock_registry(healthy_routes(&[("chunk:a", "from-primary")])).await;
+    let mirror = mock_registry(healthy_routes(&[("chunk:a", "from-mirror"), ("chunk:b", "only-mirror")])).await;
+
+    let config = write_config(
+        "merge.yaml",
+        &format!(
+            "registries:\n  - id: mirror\n    url: {}\n    priority: 5\n  - id: primary\n    url: {}\n    priority: 1\n",
+            mirror, primary
+        ),
+    );
+    let manager = FederationManager::from_config(&config).unwrap();
+
+    let results = manager.search(&SearchQuery::default()).await.unwrap();
+    let merged: Vec<(&str, &str)> = results
+        .iter()
+        .map(|(chunk, source)| (chunk.chunk_id.as_str(), source.as_str()))
+        .collect();
+    assert_eq!(merged, vec![("chunk:a", "primary"), ("chunk:b", "mirror")]);
+    assert_eq!(results[0].0.name, "from-primary");
+
+    let _ = std::fs::remove_file(config);
+}
+
+#[tokio::test]
+async fn test_unhealthy_registries_are_skipped() {
+    let healthy = mock_registry(healthy_routes(&[("chunk:b", "healthy")])).await;
+
+    let config = write_config(
+        "health.yaml",
+        &format!(
+            "registries:\n  - id: down\n    url: {}\n    priority: 1\n  - id: up\n    url: {}\n    priority: 2\n",
+            unreachable_url(),
+            healthy
+        ),
+    );
+    let manager = FederationManager::from_config(&config).unwrap();
+
+    assert!(manager.is_healthy("down"));
+    assert_eq!(manager.check_health().await, 1);
+    assert!(!manager.is_healthy("down"));
+    assert!(manager.is_healthy("up"));
+
+    let results = manager.search(&SearchQuery::default()).await.unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].1, "up");
+
+    let _ = std::fs::remove_file(config);
+}
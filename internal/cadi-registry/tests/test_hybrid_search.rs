@@ -0,0 +1,96 @@
+use cadi_llm::embeddings::{EmbeddingManager, MockProvider};
+use cadi_registry::db::{HybridWeights, RegistryDatabase, SearchMode, SearchQuery};
+use surrealdb::{engine::local::Mem, Surreal};
+
+mod common;
+use common::chunk;
+
+async fn registry() -> Result<RegistryDatabase, Box<dyn std::error::Error>> {
+    let db = Surreal::new::<Mem>(()).await?;
+    db.use_ns("test").use_db("test").await?;
+
+    let manager = EmbeddingManager::new(Box::new(MockProvider), None);
+    let mut registry = RegistryDatabase::new(db, Some(manager)).await?;
+
+    for (chunk_id, name, description, concepts) in [
+        ("chunk:hybrid-jwt", "verifyJwtToken", "Verify a JWT and return its claims", vec!["auth", "jwt"]),
+        ("chunk:hybrid-session", "SessionStore", "Store user sessions in memory", vec!["auth", "session"]),
+        ("chunk:hybrid-csv", "parse_csv", "Parse CSV rows into records", vec!["parsing"]),
+    ] {
+        let metadata = serde_json::json!({
+            "name": name,
+            "description": description,
+            "language": "rust",
+            "concepts": concepts,
+            "quality_score": 0.5,
+            "test_coverage": 0.5,
+        });
+        registry.store_chunk(&chunk(chunk_id, name), "body", metadata).await?;
+    }
+
+    Ok(registry)
+}
+
+#[tokio::test]
+async fn test_keyword_scores_rank_name_and_concept_matches() -> Result<(), Box<dyn std::error::Error>> {
+    let registry = registry().await?;
+
+    let results = registry.search(SearchQuery {
+        text: Some("jwt token".to_string()),
+        mode: SearchMode::Keyword,
+        limit: 10,
+        ..Default::default()
+    }).await?;
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].chunk_id, "chunk:hybrid-jwt");
+    assert_eq!(results[0].scores.keyword, Some(1.0));
+    assert_eq!(results[0].scores.semantic, None);
+    assert!((results[0].scores.quality - 0.5).abs() < 1e-9);
+
+    let results = registry.search(SearchQuery {
+        text: Some("auth".to_string()),
+        mode: SearchMode::Keyword,
+        limit: 10,
+        ..Default::default()
+    }).await?;
+    let ids: Vec<&str> = results.iter().map(|r| r.chunk_id.as_str()).collect();
+    assert_eq!(ids.len(), 2);
+    assert!(!ids.contains(&"chunk:hybrid-csv"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_hybrid_score_is_weighted_sum_of_components() -> Result<(), Box<dyn std::error::Error>> {
+    let mut registry = registry().await?;
+    let embedding = registry.embedding_manager_mut().unwrap()
+        .get_chunk_embedding("query", "verify jwt").await?;
+
+    let weights = HybridWeights { semantic: 0.6, keyword: 0.4, quality: 0.0 };
+    let results = registry.search(SearchQuery {
+        text: Some("jwt".to_string()),
+        embedding: Some(embedding.clone()),
+        limit: 10,
+        weights,
+        ..Default::default()
+    }).await?;
+
+    let jwt = results.iter().find(|r| r.chunk_id == "chunk:hybrid-jwt").expect("keyword hit");
+    let semantic = jwt.scores.semantic.expect("vector hit");
+    assert_eq!(jwt.scores.keyword, Some(1.0));
+    assert!((jwt.score - (0.6 * semantic + 0.4)).abs() < 1e-9);
+
+    // Semantic mode ignores the text
+    let results = registry.search(SearchQuery {
+        text: Some("jwt".to_string()),
+        embedding: Some(embedding),
+        mode: SearchMode::Semantic,
+        limit: 10,
+        ..Default::default()
+    }).await?;
+    assert_eq!(results.len(), 3);
+    assert!(results.iter().all(|r| r.scores.keyword.is_none() && r.scores.semantic.is_some()));
+
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use async_trait::async_trait;
+use cadi_llm::embeddings::{Embedding, EmbeddingManager, EmbeddingProvider};
+use cadi_registry::db::RegistryDatabase;
+use surrealdb::{engine::local::Mem, Surreal};
+
+mod common;
+use common::chunk;
+
+/// Two "models" of the same dimension whose vectors differ
+struct Model(&'static str);
+
+#[async_trait]
+impl EmbeddingProvider for Model {
+    async fn generate(&self, text: &str) -> anyhow::Result<Embedding> {
+        let offset = if self.0 == "model-a" { 0 } else { 7 };
+        let mut embedding = vec![0.0; 8];
+        for (i, byte) in text.bytes().enumerate() {
+            embedding[(i + byte as usize + offset) % 8] += 1.0;
+        }
+        Ok(embedding)
+    }
+
+    fn dimension(&self) -> usize {
+        8
+    }
+
+    fn model_id(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+#[tokio::test]
+async fn test_reembed_resumes_after_interruption() -> Result<(), Box<dyn std::error::Error>> {
+    let db = Surreal::new::<Mem>(()).await?;
+    db.use_ns("test").use_db("test").await?;
+
+    let model = |name| Some(EmbeddingManager::new(Box::new(Model(name)), None));
+    let mut registry = RegistryDatabase::new(db.clone(), model("model-a")).await?;
+    for name in ["parse_config", "render_button", "open_socket"] {
+        let metadata = serde_json::json!({"name": name, "description": "", "language": "rust"});
+        registry.store_chunk(&chunk(&format!("chunk:reembed-{}", name), name), "body", metadata).await?;
+    }
+    assert_eq!(registry.embedding_models().await?, vec![(Some("model-a".to_string()), 3)]);
+
+    // Switch models and stop after the first batch
+    let mut registry = RegistryDatabase::new(db.clone(), model("model-b")).await?;
+    let progress = registry.reembed_batch(2).await?;
+    assert_eq!((progress.processed, progress.total, progress.done), (2, 3, false));
+    assert_eq!(registry.embedding_models().await?.len(), 2);
+
+    // A new run picks up after the cursor
+    let mut registry = RegistryDatabase::new(db, model("model-b")).await?;
+    let mut reported = Vec::new();
+    let progress = registry.reembed_all(2, |p| reported.push(p.processed)).await?;
+    assert_eq!(reported, vec![3]);
+    assert!(progress.done);
+    assert_eq!(registry.reembed_status().await?, Some(progress));
+    assert_eq!(registry.embedding_models().await?, vec![(Some("model-b".to_string()), 3)]);
+
+    let record = registry.get_chunk("chunk:reembed-open_socket").await?.expect("chunk must exist");
+    let expected = Model("model-b").generate("open_socket  ").await?;
+    assert_eq!(record.embedding, Some(expected));
+    assert_eq!(record.embedding_model.as_deref(), Some("model-b"));
+    assert_eq!(record.embedding_dimension, Some(8));
+
+    Ok(())
+}
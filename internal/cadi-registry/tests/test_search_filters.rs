@@ -0,0 +1,181 @@
+use cadi_core::{CadiType, Chunk, ChunkCategory, ChunkGranularity, ChunkLicensing, ChunkLineage, ChunkMeta, ChunkMetrics, ChunkProvides};
+use cadi_llm::embeddings::{EmbeddingManager, MockProvider};
+use cadi_registry::db::{ChunkMetadata, RegistryDatabase, SearchQuery};
+use surrealdb::{engine::local::Mem, Surreal};
+
+fn metadata(granularity: ChunkGranularity, categories: Vec<ChunkCategory>, size: usize, quality: f64) -> ChunkMetadata {
+    ChunkMetadata {
+        name: "chunk".to_string(),
+        granularity: Some(granularity),
+        categories,
+        size,
+        quality_score: quality,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_function_level_utilities_under_size() {
+    let query = SearchQuery {
+        granularity: Some(ChunkGranularity::Function),
+        categories: vec![ChunkCategory::Utility],
+        max_size: Some(2048),
+        ..Default::default()
+    };
+    assert!(query.has_filters());
+
+    assert!(query.matches(&metadata(ChunkGranularity::Function, vec![ChunkCategory::Utility], 512, 0.0)));
+    assert!(!query.matches(&metadata(ChunkGranularity::Module, vec![ChunkCategory::Utility], 512, 0.0)));
+    assert!(!query.matches(&metadata(ChunkGranularity::Function, vec![ChunkCategory::Api], 512, 0.0)));
+    assert!(!query.matches(&metadata(ChunkGranularity::Function, vec![ChunkCategory::Utility], 4096, 0.0)));
+}
+
+#[test]
+fn test_categories_match_any_and_quality_threshold() {
+    let query = SearchQuery {
+        categories: vec![ChunkCategory::Api, ChunkCategory::Ui],
+        min_quality: Some(0.5),
+        ..Default::default()
+    };
+
+    assert!(query.matches(&metadata(ChunkGranularity::Type, vec![ChunkCategory::Ui, ChunkCategory::Data], 100, 0.8)));
+    assert!(!query.matches(&metadata(ChunkGranularity::Type, vec![ChunkCategory::Ui], 100, 0.2)));
+    assert!(SearchQuery::default().matches(&ChunkMetadata::default()));
+    assert!(!SearchQuery::default().has_filters());
+}
+
+#[test]
+fn test_max_complexity_excludes_unmeasured_chunks() {
+    let query = SearchQuery {
+        max_complexity: Some(5.0),
+        ..Default::default()
+    };
+    assert!(query.has_filters());
+
+    let with_complexity = |complexity: f32| ChunkMetadata {
+        metrics: Some(ChunkMetrics { complexity: Some(complexity), ..Default::default() }),
+        ..Default::default()
+    };
+    assert!(query.matches(&with_complexity(3.0)));
+    assert!(!query.matches(&with_complexity(12.0)));
+    assert!(!query.matches(&ChunkMetadata::default()));
+}
+
+#[tokio::test]
+async fn test_max_complexity_filters_stored_metrics() -> Result<(), Box<dyn std::error::Error>> {
+    let db = Surreal::new::<Mem>(()).await?;
+    db.use_ns("test").use_db("test").await?;
+    let embedding_manager = EmbeddingManager::new(Box::new(MockProvider), None);
+    let mut registry = RegistryDatabase::new(db, Some(embedding_manager)).await?;
+
+    for (id, metrics) in [
+        ("chunk:simple", Some(3.0)),
+        ("chunk:tangled", Some(12.0)),
+        ("chunk:unmeasured", None),
+    ] {
+        let chunk = Chunk {
+            chunk_id: id.to_string(),
+            cadi_type: CadiType::Source,
+            meta: ChunkMeta {
+                name: id.to_string(),
+                description: None,
+                version: None,
+                tags: vec![],
+                created_at: None,
+                updated_at: None,
+            },
+            provides: ChunkProvides { concepts: vec![], interfaces: vec![], abi: None },
+            licensing: ChunkLicensing { license: "MIT".to_string(), restrictions: vec![] },
+            lineage: ChunkLineage::default(),
+            signatures: vec![],
+        };
+        let mut metadata = serde_json::json!({ "name": id, "language": "rust" });
+        if let Some(complexity) = metrics {
+            metadata["metrics"] = serde_json::json!({ "loc": 10, "complexity": complexity, "max_nesting": 2 });
+        }
+        registry.store_chunk(&chunk, id, metadata).await?;
+    }
+
+    let query = SearchQuery {
+        max_complexity: Some(5.0),
+        ..Default::default()
+    };
+    let ids = registry.matching_chunk_ids(&query).await?;
+    assert_eq!(ids.into_iter().collect::<Vec<_>>(), vec!["chunk:simple".to_string()]);
+
+    // Metadata updates without metrics keep the stored ones
+    registry
+        .update_chunk_metadata("chunk:simple", serde_json::json!({ "name": "simple" }))
+        .await?;
+    let record = registry.get_chunk("chunk:simple").await?.expect("chunk exists");
+    let metrics = record.metadata.metrics.expect("metrics kept");
+    assert_eq!(metrics.complexity, Some(3.0));
+    assert_eq!(metrics.max_nesting, 2);
+
+    Ok(())
+}
+
+#[test]
+fn test_min_category_confidence_excludes_unscored_chunks() {
+    let query = SearchQuery {
+        categories: vec![ChunkCategory::Api],
+        min_category_confidence: Some(0.7),
+        ..Default::default()
+    };
+    assert!(query.has_filters());
+
+    let api = |confidence: Option<f64>| ChunkMetadata {
+        category_confidence: confidence,
+        ..metadata(ChunkGranularity::Module, vec![ChunkCategory::Api], 100, 0.0)
+    };
+    assert!(query.matches(&api(Some(0.85))));
+    assert!(query.matches(&api(Some(1.0))));
+    assert!(!query.matches(&api(Some(0.4))));
+    assert!(!query.matches(&api(None)));
+}
+
+#[tokio::test]
+async fn test_min_category_confidence_filters_stored_metadata() -> Result<(), Box<dyn std::error::Error>> {
+    let db = Surreal::new::<Mem>(()).await?;
+    db.use_ns("test").use_db("test").await?;
+    let embedding_manager = EmbeddingManager::new(Box::new(MockProvider), None);
+    let mut registry = RegistryDatabase::new(db, Some(embedding_manager)).await?;
+
+    for (id, confidence) in [
+        ("chunk:routes", Some(0.85)),
+        ("chunk:maybe", Some(0.4)),
+        ("chunk:legacy", None),
+    ] {
+        let chunk = Chunk {
+            chunk_id: id.to_string(),
+            cadi_type: CadiType::Source,
+            meta: ChunkMeta {
+                name: id.to_string(),
+                description: None,
+                version: None,
+                tags: vec![],
+                created_at: None,
+                updated_at: None,
+            },
+            provides: ChunkProvides { concepts: vec![], interfaces: vec![], abi: None },
+            licensing: ChunkLicensing { license: "MIT".to_string(), restrictions: vec![] },
+            lineage: ChunkLineage::default(),
+            signatures: vec![],
+        };
+        let mut metadata = serde_json::json!({ "name": id, "language": "rust", "categories": ["api"] });
+        if let Some(confidence) = confidence {
+            metadata["category_confidence"] = serde_json::json!(confidence);
+        }
+        registry.store_chunk(&chunk, id, metadata).await?;
+    }
+
+    let query = SearchQuery {
+        categories: vec![ChunkCategory::Api],
+        min_category_confidence: Some(0.7),
+        ..Default::default()
+    };
+    let ids = registry.matching_chunk_ids(&query).await?;
+    assert_eq!(ids.into_iter().collect::<Vec<_>>(), vec!["chunk:routes".to_string()]);
+
+    Ok(())
+}
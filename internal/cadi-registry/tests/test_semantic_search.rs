@@ -121,6 +121,7 @@ export default app;"#,
         language: None,
         limit: 5,
         min_score: 0.1,
+        ..Default::default()
     };
 
     let results = registry.search(text_query).await?;
@@ -141,6 +142,7 @@ export default app;"#,
         language: None,
         limit: 5,
         min_score: 0.1,
+        ..Default::default()
     };
 
     let semantic_results = registry.search(semantic_query).await?;
@@ -160,6 +162,7 @@ export default app;"#,
         language: None,
         limit: 5,
         min_score: 0.1,
+        ..Default::default()
     };
 
     let hybrid_results = registry.search(hybrid_query).await?;
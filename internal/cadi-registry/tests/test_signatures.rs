@@ -0,0 +1,42 @@
+use cadi_core::trust::{verify_signature, SigningKey};
+use cadi_core::Chunk;
+use cadi_llm::embeddings::{EmbeddingManager, MockProvider};
+use cadi_registry::db::RegistryDatabase;
+use surrealdb::{engine::local::Mem, Surreal};
+
+mod common;
+use common::chunk;
+
+#[tokio::test]
+async fn test_signatures_are_stored_and_kept_on_update() -> Result<(), Box<dyn std::error::Error>> {
+    let db = Surreal::new::<Mem>(()).await?;
+    db.use_ns("test").use_db("test").await?;
+    let manager = EmbeddingManager::new(Box::new(MockProvider), None);
+    let mut registry = RegistryDatabase::new(db, Some(manager)).await?;
+
+    let chunk_id = "chunk:signed-example";
+    let alice = SigningKey::from_seed(&[1u8; 32])?;
+    let bob = SigningKey::from_seed(&[2u8; 32])?;
+
+    let metadata = serde_json::json!({"name": "signed", "signatures": [bob.sign_chunk(chunk_id)]});
+    let signed = Chunk { signatures: vec![alice.sign_chunk(chunk_id)], ..chunk(chunk_id, "signed") };
+    registry.store_chunk(&signed, "body", metadata).await?;
+
+    let record = registry.get_chunk(chunk_id).await?.expect("chunk must exist");
+    let signers: Vec<String> = record.metadata.signatures.iter()
+        .map(|s| verify_signature(chunk_id, s))
+        .collect::<Result<_, _>>()?;
+    assert_eq!(signers, vec![alice.public_key(), bob.public_key()]);
+
+    // Updates without signatures keep the stored ones
+    registry.update_chunk_metadata(chunk_id, serde_json::json!({"name": "renamed"})).await?;
+    let record = registry.get_chunk(chunk_id).await?.expect("chunk must exist");
+    assert_eq!(record.metadata.name, "renamed");
+    assert_eq!(record.metadata.signatures.len(), 2);
+
+    registry.update_chunk_metadata(chunk_id, serde_json::json!({"name": "renamed", "signatures": []})).await?;
+    let record = registry.get_chunk(chunk_id).await?.expect("chunk must exist");
+    assert!(record.metadata.signatures.is_empty());
+
+    Ok(())
+}
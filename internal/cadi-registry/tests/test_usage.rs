@@ -0,0 +1,87 @@
+use cadi_llm::embeddings::{EmbeddingManager, MockProvider};
+use cadi_registry::db::{RegistryDatabase, SearchMode, SearchQuery, USAGE_RETENTION_DAYS};
+use chrono::{Duration, TimeZone, Utc};
+use std::collections::HashMap;
+use surrealdb::{engine::local::Mem, Surreal};
+
+mod common;
+use common::chunk;
+
+async fn registry() -> Result<RegistryDatabase, Box<dyn std::error::Error>> {
+    let db = Surreal::new::<Mem>(()).await?;
+    db.use_ns("test").use_db("test").await?;
+    let manager = EmbeddingManager::new(Box::new(MockProvider), None);
+    let mut registry = RegistryDatabase::new(db, Some(manager)).await?;
+
+    for (chunk_id, name) in [
+        ("chunk:sha256:1111", "parse_config"),
+        ("chunk:sha256:2222", "parse_config_file"),
+    ] {
+        let metadata = serde_json::json!({
+            "name": name,
+            "description": "Parse a config file",
+            "language": "rust",
+            "concepts": ["config"],
+            "quality_score": 0.5,
+            "test_coverage": 0.5,
+        });
+        registry.store_chunk(&chunk(chunk_id, name), "body", metadata).await?;
+    }
+
+    Ok(registry)
+}
+
+#[tokio::test]
+async fn test_usage_is_bucketed_by_day() -> Result<(), Box<dyn std::error::Error>> {
+    let registry = registry().await?;
+    let day = Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap();
+
+    registry.record_usage(&HashMap::from([("chunk:sha256:1111".to_string(), 2)]), day).await?;
+    // By hash, the same chunk as by ID
+    registry.record_usage(&HashMap::from([("1111".to_string(), 1)]), day).await?;
+    registry.record_usage(&HashMap::from([("chunk:sha256:1111".to_string(), 4)]), day + Duration::days(1)).await?;
+    // Unknown chunks are ignored
+    registry.record_usage(&HashMap::from([("chunk:sha256:9999".to_string(), 1)]), day).await?;
+
+    let stats = registry.usage_stats("chunk:sha256:1111").await?.unwrap();
+    assert_eq!(stats.usage_count, 7);
+    assert_eq!(stats.last_used_at, Some((day + Duration::days(1)).to_rfc3339()));
+    let daily: Vec<(&str, u64)> = stats.daily.iter().map(|d| (d.day.as_str(), d.count)).collect();
+    assert_eq!(daily, vec![("2026-03-01", 3), ("2026-03-02", 4)]);
+
+    assert!(registry.usage_stats("chunk:sha256:9999").await?.is_none());
+
+    // Buckets past the retention window are dropped on the next write
+    let later = day + Duration::days(USAGE_RETENTION_DAYS);
+    registry.record_usage(&HashMap::from([("chunk:sha256:2222".to_string(), 1)]), later).await?;
+    let stats = registry.usage_stats("chunk:sha256:1111").await?.unwrap();
+    assert_eq!(stats.usage_count, 7);
+    assert_eq!(stats.recent_usage(), 4);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rank_by_usage_boosts_popular_chunks() -> Result<(), Box<dyn std::error::Error>> {
+    let registry = registry().await?;
+    let query = |rank_by_usage| SearchQuery {
+        text: Some("parse config".to_string()),
+        mode: SearchMode::Keyword,
+        limit: 10,
+        rank_by_usage,
+        ..Default::default()
+    };
+
+    let plain = registry.search(query(false)).await?;
+    assert_eq!(plain[0].chunk_id, "chunk:sha256:1111");
+    assert!(plain.iter().all(|r| r.scores.usage.is_none()));
+
+    registry.record_usage(&HashMap::from([("chunk:sha256:2222".to_string(), 50)]), Utc::now()).await?;
+
+    let ranked = registry.search(query(true)).await?;
+    assert_eq!(ranked[0].chunk_id, "chunk:sha256:2222");
+    assert_eq!(ranked[0].scores.usage, Some(1.0));
+    assert_eq!(ranked[1].scores.usage, Some(0.0));
+
+    Ok(())
+}
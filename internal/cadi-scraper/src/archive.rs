@@ -0,0 +1,204 @@
+//! Source archive extraction
+//!
+//! Unpacks the regular files of a zip, tarball or gzipped tarball in memory,
+//! checking zip entries against their CRC-32 and tar headers against their
+//! checksums.
+//! Every entry path is checked before use: absolute paths and `..`
+//! components reject the whole archive rather than being skipped, since an
+//! archive containing them was built to escape wherever it is unpacked.
+
+use std::io::{Cursor, Read};
+use std::path::{Component, Path, PathBuf};
+
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+use crate::error::{Error, Result};
+use crate::package::{gunzip, tar_entries};
+use crate::types::ArchiveFormat;
+
+/// Regular files of an archive with archive-relative paths. When every file
+/// sits under the same top-level directory (`project-main/` in a GitHub
+/// download) that directory is treated as the root and stripped. Fails once
+/// the unpacked contents exceed `max_unpacked` bytes.
+pub fn extract(bytes: &[u8], format: ArchiveFormat, max_unpacked: u64) -> Result<Vec<(PathBuf, Vec<u8>)>> {
+    let entries = match format {
+        ArchiveFormat::Zip => zip_entries(bytes, max_unpacked)?,
+        ArchiveFormat::Tar | ArchiveFormat::TarGz => {
+            let gunzipped;
+            let data = if format == ArchiveFormat::TarGz {
+                gunzipped = gunzip(bytes, max_unpacked)?;
+                &gunzipped[..]
+            } else {
+                bytes
+            };
+            if data.len() as u64 > max_unpacked {
+                return Err(too_large(max_unpacked));
+            }
+            tar_entries(data)?
+        }
+    };
+
+    let mut files = Vec::new();
+    for (name, body) in entries {
+        files.push((entry_path(&name)?, body));
+    }
+    strip_root(&mut files);
+    Ok(files)
+}
+
+/// Path of an archive entry, rejecting anything that escapes the archive
+fn entry_path(name: &str) -> Result<PathBuf> {
+    let escapes = || Error::InvalidInput(format!("Archive entry escapes the archive root: {}", name));
+
+    // Zips written on Windows may use `\` separators and drive prefixes
+    let name = name.replace('\\', "/");
+    let mut path = PathBuf::new();
+    for component in Path::new(&name).components() {
+        match component {
+            Component::Normal(part) if !part.to_string_lossy().contains(':') => path.push(part),
+            Component::CurDir => {}
+            _ => return Err(escapes()),
+        }
+    }
+    if path.as_os_str().is_empty() {
+        return Err(escapes());
+    }
+    Ok(path)
+}
+
+/// Strip a top-level directory shared by every file
+fn strip_root(files: &mut [(PathBuf, Vec<u8>)]) {
+    let mut roots = files.iter().map(|(path, _)| {
+        let mut components = path.components();
+        components.next().filter(|_| components.next().is_some())
+    });
+    let Some(Some(root)) = roots.next() else {
+        return;
+    };
+    if !roots.all(|r| r == Some(root)) {
+        return;
+    }
+
+    let root = PathBuf::from(root.as_os_str());
+    for (path, _) in files.iter_mut() {
+        *path = path.strip_prefix(&root).map(Path::to_path_buf).unwrap_or_default();
+    }
+}
+
+fn too_large(limit: u64) -> Error {
+    Error::InvalidInput(format!("Archive is larger than {} bytes unpacked", limit))
+}
+
+/// Names and contents of the regular files in a zip. Directories and
+/// symbolic links are skipped. Reading an entry to its end checks its
+/// CRC-32, so a corrupt entry fails the whole archive.
+fn zip_entries(data: &[u8], max_unpacked: u64) -> Result<Vec<(String, Vec<u8>)>> {
+    let invalid = |e: ZipError| match e {
+        ZipError::UnsupportedArchive(what) => Error::UnsupportedFormat(format!("zip archive: {}", what)),
+        e => Error::Parse(format!("Invalid zip archive: {}", e)),
+    };
+
+    let mut archive = ZipArchive::new(Cursor::new(data)).map_err(invalid)?;
+    let mut entries = Vec::new();
+    let mut unpacked = 0u64;
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index).map_err(invalid)?;
+        if file.is_dir() || file.is_symlink() {
+            continue;
+        }
+        let (name, size) = (file.name().to_string(), file.size());
+        unpacked += size;
+        if unpacked > max_unpacked {
+            return Err(too_large(max_unpacked));
+        }
+
+        // The declared size can't be trusted, so read at most one byte more
+        let mut body = Vec::new();
+        (&mut file)
+            .take(size + 1)
+            .read_to_end(&mut body)
+            .map_err(|e| Error::Parse(format!("Invalid zip entry {}: {}", name, e)))?;
+        if body.len() as u64 != size {
+            return Err(Error::Parse(format!("Invalid zip entry {}: size mismatch", name)));
+        }
+        entries.push((name, body));
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A zip of stored `(path, contents)` entries; paths ending in `/` are
+    /// directories
+    pub(crate) fn zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        use std::io::Write;
+        use zip::write::SimpleFileOptions;
+
+        let options = SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored)
+            .unix_permissions(0o644);
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in entries {
+            if name.ends_with('/') {
+                writer.add_directory(*name, options).unwrap();
+            } else {
+                writer.start_file(*name, options).unwrap();
+                writer.write_all(contents).unwrap();
+            }
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_zip_strips_shared_root() {
+        let archive = zip(&[
+            ("demo-main/", b""),
+            ("demo-main/README.md", b"# Demo\n"),
+            ("demo-main/src/lib.rs", b"pub fn demo() {}\n"),
+        ]);
+        let files = extract(&archive, ArchiveFormat::Zip, 1024).unwrap();
+        let paths: Vec<_> = files.iter().map(|(p, _)| p.to_string_lossy().into_owned()).collect();
+        assert_eq!(paths, ["README.md", "src/lib.rs"]);
+        assert_eq!(files[1].1, b"pub fn demo() {}\n");
+
+        // Files at the top level keep their paths
+        let flat = zip(&[("lib.rs", b"fn a() {}\n"), ("src/b.rs", b"fn b() {}\n")]);
+        let files = extract(&flat, ArchiveFormat::Zip, 1024).unwrap();
+        assert_eq!(files[0].0, PathBuf::from("lib.rs"));
+        assert_eq!(files[1].0, PathBuf::from("src/b.rs"));
+
+        assert!(matches!(extract(&archive, ArchiveFormat::Zip, 8), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_zip_rejects_corrupt_entries() {
+        let mut archive = zip(&[("demo/lib.rs", b"pub fn demo() {}\n")]);
+        let at = archive.windows(3).position(|w| w == b"pub").unwrap();
+        archive[at] = b'P';
+        assert!(matches!(extract(&archive, ArchiveFormat::Zip, 1024), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn test_zip_rejects_escaping_entries() {
+        for name in ["../evil", "demo/../../evil", "/etc/evil", "..\\evil", "C:/evil"] {
+            let archive = zip(&[("demo/ok.rs", b"fn ok() {}\n"), (name, b"pwned")]);
+            assert!(
+                matches!(extract(&archive, ArchiveFormat::Zip, 1024), Err(Error::InvalidInput(_))),
+                "{} was not rejected",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(ArchiveFormat::detect("https://x/archive/main.zip?raw=1"), Some(ArchiveFormat::Zip));
+        assert_eq!(ArchiveFormat::detect("demo-0.1.0.TGZ"), Some(ArchiveFormat::TarGz));
+        assert_eq!(ArchiveFormat::detect("demo.tar"), Some(ArchiveFormat::Tar));
+        assert_eq!(ArchiveFormat::detect("lib.rs"), None);
+    }
+}
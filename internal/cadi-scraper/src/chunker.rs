@@ -33,7 +33,18 @@ impl Chunker {
         language: Option<&str>,
         source_file: &str,
     ) -> Result<Vec<Chunk>> {
-        match self.config.chunking_strategy {
+        self.chunk_with(self.config.chunking_strategy, content, language, source_file)
+    }
+
+    /// Chunk content with an explicit strategy
+    pub fn chunk_with(
+        &self,
+        strategy: ChunkingStrategy,
+        content: &str,
+        language: Option<&str>,
+        source_file: &str,
+    ) -> Result<Vec<Chunk>> {
+        match strategy {
             ChunkingStrategy::ByFile => self.chunk_by_file(content, language, source_file),
             ChunkingStrategy::Semantic => self.chunk_semantic(content, language, source_file),
             ChunkingStrategy::FixedSize => self.chunk_fixed_size(content, language, source_file),
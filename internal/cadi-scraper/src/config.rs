@@ -1,5 +1,7 @@
 use crate::types::ScraperConfig;
-use crate::error::Result;
+use crate::error::{Error, Result};
+use cadi_core::{ChunkingRule, ChunkingRules};
+use serde::Deserialize;
 
 /// Load configuration from a YAML file
 pub fn load_config(path: &str) -> Result<ScraperConfig> {
@@ -8,6 +10,27 @@ pub fn load_config(path: &str) -> Result<ScraperConfig> {
     Ok(config)
 }
 
+/// Load chunking rules from a YAML file, either a bare list of rules or a
+/// mapping with a `rules` key (so a scraper config file also works)
+pub fn load_rules(path: &str) -> Result<Vec<ChunkingRule>> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RuleFile {
+        List(Vec<ChunkingRule>),
+        Config { rules: Vec<ChunkingRule> },
+    }
+
+    let content = std::fs::read_to_string(path)?;
+    let rules = match serde_yaml::from_str(&content)
+        .map_err(|e| Error::Config(format!("Invalid rule file {}: {}", path, e)))?
+    {
+        RuleFile::List(rules) | RuleFile::Config { rules } => rules,
+    };
+
+    ChunkingRules::compile(&rules).map_err(|e| Error::Config(e.to_string()))?;
+    Ok(rules)
+}
+
 /// Load configuration from environment variables
 pub fn load_from_env() -> ScraperConfig {
     let mut config = ScraperConfig::default();
@@ -20,6 +43,14 @@ pub fn load_from_env() -> ScraperConfig {
         config.auth_token = Some(token);
     }
 
+    if let Ok(token) = std::env::var("CADI_GIT_TOKEN") {
+        config.git_token = Some(token);
+    }
+
+    if let Ok(host) = std::env::var("CADI_GIT_TOKEN_HOST") {
+        config.git_token_host = host;
+    }
+
     if let Ok(namespace) = std::env::var("CADI_NAMESPACE") {
         config.namespace = Some(namespace);
     }
@@ -43,3 +74,42 @@ pub fn save_config(config: &ScraperConfig, path: &str) -> Result<()> {
     std::fs::write(path, content)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cadi_core::ChunkingStrategy;
+
+    fn write_rules(name: &str, content: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("cadi-rules-{}-{}.yaml", name, std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_load_rules() {
+        let path = write_rules(
+            "valid",
+            "rules:\n  - pattern: \"src/**/*.rs\"\n    strategy: by_entity\n  - pattern: \"docs/**/*.md\"\n    strategy: atomic\n    category: docs\n  - pattern: \"migrations/*.sql\"\n    strategy: skip\n",
+        );
+        let rules = load_rules(path.to_str().unwrap()).unwrap();
+
+        assert_eq!(rules.len(), 3);
+        assert_eq!(rules[0].strategy, ChunkingStrategy::ByEntity);
+        assert_eq!(rules[1].category, Some(cadi_core::ChunkCategory::Docs));
+        assert_eq!(rules[2].strategy, ChunkingStrategy::Skip);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_load_rules_invalid_glob() {
+        let path = write_rules("invalid", "- pattern: \"src/[a-z\"\n  strategy: atomic\n");
+        let err = load_rules(path.to_str().unwrap()).unwrap_err();
+
+        assert!(matches!(err, Error::Config(_)));
+        assert!(err.to_string().contains("Invalid pattern 'src/[a-z' in chunking rule 1"));
+
+        let _ = std::fs::remove_file(path);
+    }
+}
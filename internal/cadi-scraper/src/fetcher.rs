@@ -1,9 +1,13 @@
+use crate::archive;
 use crate::error::{Error, Result};
-use crate::types::ScraperConfig;
+use crate::package::{self, PackageArchive};
+use crate::types::{ArchiveFormat, PackageSource, ScraperConfig};
 use reqwest::Client;
+use base64::Engine;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use tokio::process::Command;
 use tokio::sync::Mutex;
 
 /// HTTP fetcher with rate limiting and caching
@@ -13,6 +17,20 @@ pub struct Fetcher {
     rate_limiter: Arc<Mutex<RateLimiter>>,
 }
 
+/// Shallow checkout of a git repository, removed when dropped
+pub struct GitCheckout {
+    dir: tempfile::TempDir,
+    /// Resolved commit SHA
+    pub commit: String,
+}
+
+impl GitCheckout {
+    /// Root of the checked-out tree
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
 /// Rate limiter using token bucket algorithm
 struct RateLimiter {
     tokens: f64,
@@ -117,7 +135,6 @@ impl Fetcher {
         use glob::Pattern;
 
         let mut results = Vec::new();
-        let exclude = self.config.exclude_patterns.clone();
 
         for entry in WalkDir::new(path)
             .into_iter()
@@ -132,12 +149,7 @@ impl Fetcher {
 
             // Check exclude patterns
             let path_str = relative.to_string_lossy();
-            if exclude.iter().any(|p| {
-                Pattern::new(p)
-                    .ok()
-                    .and_then(|pattern| pattern.matches(&path_str).then_some(true))
-                    .unwrap_or(false)
-            }) {
+            if self.is_excluded(&relative) {
                 continue;
             }
 
@@ -162,6 +174,232 @@ impl Fetcher {
         Ok(results)
     }
 
+    /// Whether a scraped-root-relative path matches an exclude pattern
+    fn is_excluded(&self, relative: &Path) -> bool {
+        let path_str = relative.to_string_lossy();
+        self.config.exclude_patterns.iter().any(|p| {
+            glob::Pattern::new(p)
+                .ok()
+                .and_then(|pattern| pattern.matches(&path_str).then_some(true))
+                .unwrap_or(false)
+        })
+    }
+
+    /// Download a published package and unpack its source files
+    ///
+    /// The tarball is checked against the checksum in the registry metadata
+    /// before anything is unpacked, and only regular files are kept.
+    pub async fn fetch_package(
+        &self,
+        source: PackageSource,
+        name: &str,
+        version: Option<&str>,
+    ) -> Result<PackageArchive> {
+        package::validate_name(source, name)?;
+        let registry = match source {
+            PackageSource::Crates => &self.config.crates_registry_url,
+            PackageSource::Npm => &self.config.npm_registry_url,
+        };
+
+        let metadata: serde_json::Value =
+            serde_json::from_slice(&self.fetch_url(&package::metadata_url(source, registry, name)).await?)?;
+        let release = match source {
+            PackageSource::Crates => package::crates_release(&metadata, registry, name, version)?,
+            PackageSource::Npm => package::npm_release(&metadata, name, version)?,
+        };
+
+        let tarball = self.fetch_url(&release.url).await?;
+        if !release.checksum.matches(&tarball) {
+            return Err(Error::Fetch(format!(
+                "Checksum mismatch for {} {} {}: expected {}",
+                source.as_str(),
+                name,
+                release.version,
+                release.checksum
+            )));
+        }
+
+        let files = package::unpack(&tarball)?
+            .into_iter()
+            .filter(|(path, _)| !self.is_excluded(path))
+            .collect::<Vec<_>>();
+        tracing::info!("Unpacked {} files from {} {} {}", files.len(), source.as_str(), name, release.version);
+
+        Ok(PackageArchive {
+            source,
+            name: name.to_string(),
+            release,
+            files,
+        })
+    }
+
+    /// Read a local path or download an HTTP(S) URL, failing once it
+    /// exceeds `max_download_bytes`
+    pub async fn fetch_bounded(&self, path_or_url: &str) -> Result<Vec<u8>> {
+        let limit = self.config.max_download_bytes;
+        let too_large = || Error::InvalidInput(format!("{} is larger than {} bytes", path_or_url, limit));
+
+        if !is_remote(path_or_url) {
+            let path = Path::new(path_or_url);
+            if tokio::fs::metadata(path).await?.len() > limit {
+                return Err(too_large());
+            }
+            return self.fetch_file(path).await;
+        }
+
+        let mut limiter = self.rate_limiter.lock().await;
+        let wait_time = limiter.acquire(1.0);
+        drop(limiter);
+
+        tokio::time::sleep(wait_time).await;
+
+        tracing::info!("Downloading: {}", path_or_url);
+
+        let mut response = self
+            .client
+            .get(path_or_url)
+            .header("User-Agent", "CADI-Scraper/1.0")
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(Error::Fetch(format!(
+                "HTTP {} from {}",
+                response.status(),
+                path_or_url
+            )));
+        }
+        if response.content_length().is_some_and(|len| len > limit) {
+            return Err(too_large());
+        }
+
+        // Content-Length may be missing or wrong, so count as the body arrives
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if (bytes.len() + chunk.len()) as u64 > limit {
+                return Err(too_large());
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        tracing::debug!("Downloaded {} bytes from {}", bytes.len(), path_or_url);
+
+        Ok(bytes)
+    }
+
+    /// Fetch an archive and unpack its regular files
+    ///
+    /// The format is detected from the file name unless given. Entries that
+    /// would escape the archive root reject the whole archive, and a single
+    /// top-level directory is stripped from every path.
+    pub async fn fetch_archive(
+        &self,
+        path_or_url: &str,
+        format: Option<ArchiveFormat>,
+    ) -> Result<Vec<(PathBuf, Vec<u8>)>> {
+        let format = format
+            .or_else(|| ArchiveFormat::detect(path_or_url))
+            .ok_or_else(|| Error::UnsupportedFormat(format!("Unknown archive format: {}", path_or_url)))?;
+
+        let bytes = self.fetch_bounded(path_or_url).await?;
+        let files = archive::extract(&bytes, format, self.config.max_unpacked_bytes)?
+            .into_iter()
+            .filter(|(path, _)| !self.is_excluded(path))
+            .collect::<Vec<_>>();
+        tracing::info!("Unpacked {} files from {}", files.len(), path_or_url);
+
+        Ok(files)
+    }
+
+    /// Shallow-fetch a git repository at a branch, tag or commit
+    ///
+    /// Without a ref the remote HEAD is checked out. The `.git` directory is
+    /// removed afterwards so only the working tree is left to scrape.
+    pub async fn fetch_git_repo(&self, url: &str, git_ref: Option<&str>) -> Result<GitCheckout> {
+        // git would take these for options
+        if let Some(git_ref) = git_ref.filter(|r| r.starts_with('-')) {
+            return Err(Error::InvalidInput(format!("Invalid git ref: {}", git_ref)));
+        }
+
+        let mut limiter = self.rate_limiter.lock().await;
+        let wait_time = limiter.acquire(1.0);
+        drop(limiter);
+
+        tokio::time::sleep(wait_time).await;
+
+        tracing::info!("Fetching git repository: {} ({})", url, git_ref.unwrap_or("HEAD"));
+
+        let dir = tempfile::tempdir()?;
+        self.git(dir.path(), &["init", "-q"], None).await?;
+        self.git(
+            dir.path(),
+            &["fetch", "-q", "--depth", "1", "--", url, git_ref.unwrap_or("HEAD")],
+            Some(url),
+        )
+        .await?;
+        self.git(dir.path(), &["checkout", "-q", "FETCH_HEAD"], None).await?;
+        let commit = self.git(dir.path(), &["rev-parse", "HEAD"], None).await?;
+
+        tokio::fs::remove_dir_all(dir.path().join(".git")).await?;
+        tracing::debug!("Checked out {} at {}", url, commit);
+
+        Ok(GitCheckout { dir, commit })
+    }
+
+    /// Token for private repositories on the configured token host: the
+    /// configured token, then `CADI_GIT_TOKEN`, then `GITHUB_TOKEN` when the
+    /// host is GitHub. Other hosts get no token.
+    fn git_token(&self, url: &str) -> Option<String> {
+        let host = https_host(url)?;
+        if !host.eq_ignore_ascii_case(&self.config.git_token_host) {
+            return None;
+        }
+        self.config
+            .git_token
+            .clone()
+            .or_else(|| std::env::var("CADI_GIT_TOKEN").ok())
+            .or_else(|| {
+                host.eq_ignore_ascii_case("github.com")
+                    .then(|| std::env::var("GITHUB_TOKEN").ok())
+                    .flatten()
+            })
+    }
+
+    /// Run a git command in `dir` and return its trimmed stdout, sending
+    /// credentials when `remote` is an HTTPS URL on the token host
+    async fn git(&self, dir: &Path, args: &[&str], remote: Option<&str>) -> Result<String> {
+        let mut command = Command::new("git");
+        command
+            .current_dir(dir)
+            .args(args)
+            .env("GIT_TERMINAL_PROMPT", "0");
+
+        // Passed through the environment so the token never shows up in
+        // process listings or error messages
+        let token = remote.and_then(|url| self.git_token(url));
+        if let Some(token) = token {
+            let credentials = base64::engine::general_purpose::STANDARD
+                .encode(format!("x-access-token:{}", token));
+            command
+                .env("GIT_CONFIG_COUNT", "1")
+                .env("GIT_CONFIG_KEY_0", "http.extraHeader")
+                .env("GIT_CONFIG_VALUE_0", format!("Authorization: Basic {}", credentials));
+        }
+
+        let output = command.output().await.map_err(|e| {
+            Error::Fetch(format!("Failed to run git (is it installed?): {}", e))
+        })?;
+
+        if !output.status.success() {
+            return Err(Error::Fetch(format!(
+                "git {} failed: {}",
+                args[0],
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
     /// Clone the fetcher for concurrent use
     pub fn clone_for_concurrent(&self) -> Arc<Mutex<Self>> {
         Arc::new(Mutex::new(Self {
@@ -172,6 +410,17 @@ impl Fetcher {
     }
 }
 
+/// Host (with any port) of an HTTPS URL, leaving out user info
+fn https_host(url: &str) -> Option<&str> {
+    let authority = url.strip_prefix("https://")?.split(['/', '?', '#']).next()?;
+    Some(authority.rsplit('@').next().unwrap_or(authority))
+}
+
+/// Whether an input is an HTTP(S) URL rather than a local path
+pub fn is_remote(path_or_url: &str) -> bool {
+    path_or_url.starts_with("http://") || path_or_url.starts_with("https://")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -183,4 +432,35 @@ mod tests {
         assert_eq!(wait, Duration::from_secs(0));
         assert_eq!(limiter.tokens, 5.0);
     }
+
+    #[test]
+    fn test_git_token_stays_on_its_host() {
+        let fetcher = Fetcher::new(ScraperConfig {
+            git_token: Some("secret".to_string()),
+            git_token_host: "git.example.com".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert_eq!(fetcher.git_token("https://git.example.com/team/repo.git").as_deref(), Some("secret"));
+        assert_eq!(fetcher.git_token("https://GIT.example.com/team/repo.git").as_deref(), Some("secret"));
+        for url in [
+            "https://attacker.example/repo.git",
+            "https://git.example.com.attacker.example/repo.git",
+            "https://git.example.com@attacker.example/repo.git",
+            "http://git.example.com/team/repo.git",
+            "git@git.example.com:team/repo.git",
+        ] {
+            assert_eq!(fetcher.git_token(url), None, "{} got the token", url);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_git_refs_cant_be_options() {
+        let fetcher = Fetcher::new(ScraperConfig::default()).unwrap();
+        let result = fetcher
+            .fetch_git_repo("https://example.com/repo.git", Some("--upload-pack=touch /tmp/pwned"))
+            .await;
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
+    }
 }
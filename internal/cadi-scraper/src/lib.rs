@@ -4,11 +4,13 @@
 //! into reusable CADI chunks. It handles fetching, parsing, semantic chunking, metadata
 //! extraction, and publishing to registry servers.
 
+pub mod archive;
 pub mod chunker;
 pub mod config;
 pub mod error;
 pub mod fetcher;
 pub mod metadata;
+pub mod package;
 pub mod parser;
 pub mod scraper;
 pub mod transformer;
@@ -16,4 +18,4 @@ pub mod types;
 
 pub use error::{Error, Result};
 pub use scraper::Scraper;
-pub use types::{ScraperConfig, ScraperInput, ScraperOutput, ChunkingStrategy};
+pub use types::{ScraperConfig, ScraperInput, ScraperOutput, ChunkingStrategy, PackageSource, ArchiveFormat};
@@ -0,0 +1,318 @@
+//! Published package fetching
+//!
+//! Resolves a crates.io or npm package version from its registry metadata
+//! and unpacks the regular files of its tarball in memory once the tarball
+//! matches the registry's checksum. Nothing in a package is ever run: npm
+//! lifecycle scripts and Cargo build scripts are only read as source.
+
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+use base64::Engine;
+use flate2::read::GzDecoder;
+use serde_json::Value;
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::error::{Error, Result};
+use crate::types::PackageSource;
+
+/// Packages larger than this once decompressed are rejected
+const MAX_UNPACKED_BYTES: u64 = 512 * 1024 * 1024;
+
+/// A package version resolved from registry metadata
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageRelease {
+    pub version: String,
+    /// Tarball download URL
+    pub url: String,
+    pub checksum: Checksum,
+}
+
+/// Digest a registry publishes for a tarball
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Checksum {
+    Sha1(Vec<u8>),
+    Sha256(Vec<u8>),
+    Sha512(Vec<u8>),
+}
+
+impl Checksum {
+    /// Whether `bytes` has this digest
+    pub fn matches(&self, bytes: &[u8]) -> bool {
+        match self {
+            Checksum::Sha1(expected) => sha1::Sha1::digest(bytes).as_slice() == expected.as_slice(),
+            Checksum::Sha256(expected) => Sha256::digest(bytes).as_slice() == expected.as_slice(),
+            Checksum::Sha512(expected) => Sha512::digest(bytes).as_slice() == expected.as_slice(),
+        }
+    }
+}
+
+impl std::fmt::Display for Checksum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (algorithm, digest) = match self {
+            Checksum::Sha1(d) => ("sha1", d),
+            Checksum::Sha256(d) => ("sha256", d),
+            Checksum::Sha512(d) => ("sha512", d),
+        };
+        write!(f, "{}:{}", algorithm, hex::encode(digest))
+    }
+}
+
+/// A verified and unpacked package
+#[derive(Debug, Clone)]
+pub struct PackageArchive {
+    pub source: PackageSource,
+    pub name: String,
+    pub release: PackageRelease,
+    /// Package-relative paths and contents of its regular files
+    pub files: Vec<(PathBuf, Vec<u8>)>,
+}
+
+/// Check a package name before it goes into a registry URL
+pub fn validate_name(source: PackageSource, name: &str) -> Result<()> {
+    let valid_part = |part: &str| {
+        !part.is_empty()
+            && !part.starts_with('.')
+            && part.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '~'))
+    };
+    let valid = match source {
+        PackageSource::Crates => valid_part(name) && !name.contains(['.', '~']),
+        PackageSource::Npm => match name.strip_prefix('@') {
+            Some(scoped) => scoped.split_once('/').is_some_and(|(scope, package)| valid_part(scope) && valid_part(package)),
+            None => valid_part(name),
+        },
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidInput(format!("Invalid {} package name: {}", source.as_str(), name)))
+    }
+}
+
+/// URL of a package's metadata document
+pub fn metadata_url(source: PackageSource, registry: &str, name: &str) -> String {
+    let registry = registry.trim_end_matches('/');
+    match source {
+        PackageSource::Crates => format!("{}/api/v1/crates/{}", registry, name),
+        // Scoped packages keep their `@` but escape the separator
+        PackageSource::Npm => format!("{}/{}", registry, name.replace('/', "%2F")),
+    }
+}
+
+/// Resolve a version from a crates.io `/api/v1/crates/<name>` response,
+/// defaulting to the newest stable release
+pub fn crates_release(metadata: &Value, registry: &str, name: &str, version: Option<&str>) -> Result<PackageRelease> {
+    let krate = &metadata["crate"];
+    let version = match version {
+        Some(version) => version,
+        None => krate["max_stable_version"]
+            .as_str()
+            .or_else(|| krate["max_version"].as_str())
+            .ok_or_else(|| Error::NotFound(format!("No published versions of crate {}", name)))?,
+    };
+
+    let entry = metadata["versions"]
+        .as_array()
+        .and_then(|versions| versions.iter().find(|v| v["num"].as_str() == Some(version)))
+        .ok_or_else(|| Error::NotFound(format!("Crate {} has no version {}", name, version)))?;
+    let checksum = entry["checksum"]
+        .as_str()
+        .and_then(|c| hex::decode(c).ok())
+        .ok_or_else(|| Error::Fetch(format!("Registry has no checksum for crate {} {}", name, version)))?;
+    let download = entry["dl_path"]
+        .as_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("/api/v1/crates/{}/{}/download", name, version));
+
+    Ok(PackageRelease {
+        version: version.to_string(),
+        url: format!("{}{}", registry.trim_end_matches('/'), download),
+        checksum: Checksum::Sha256(checksum),
+    })
+}
+
+/// Resolve a version from an npm packument, defaulting to the `latest` tag.
+/// The strongest digest in `dist.integrity` is used, falling back to the
+/// legacy SHA-1 `dist.shasum`.
+pub fn npm_release(metadata: &Value, name: &str, version: Option<&str>) -> Result<PackageRelease> {
+    let version = match version {
+        Some(version) => version,
+        None => metadata["dist-tags"]["latest"]
+            .as_str()
+            .ok_or_else(|| Error::NotFound(format!("No latest version of npm package {}", name)))?,
+    };
+
+    let dist = &metadata["versions"][version]["dist"];
+    let url = dist["tarball"]
+        .as_str()
+        .ok_or_else(|| Error::NotFound(format!("npm package {} has no version {}", name, version)))?;
+
+    let mut checksum = None;
+    for hash in dist["integrity"].as_str().unwrap_or_default().split_whitespace() {
+        let Some((algorithm, digest)) = hash.split_once('-') else {
+            continue;
+        };
+        let Ok(digest) = base64::engine::general_purpose::STANDARD.decode(digest) else {
+            continue;
+        };
+        match algorithm {
+            "sha512" => checksum = Some(Checksum::Sha512(digest)),
+            "sha256" if !matches!(checksum, Some(Checksum::Sha512(_))) => checksum = Some(Checksum::Sha256(digest)),
+            _ => {}
+        }
+    }
+    let checksum = checksum
+        .or_else(|| dist["shasum"].as_str().and_then(|s| hex::decode(s).ok()).map(Checksum::Sha1))
+        .ok_or_else(|| Error::Fetch(format!("Registry has no checksum for npm package {} {}", name, version)))?;
+
+    Ok(PackageRelease {
+        version: version.to_string(),
+        url: url.to_string(),
+        checksum,
+    })
+}
+
+/// Regular files of a gzipped package tarball, with the top-level directory
+/// (`<crate>-<version>/` or `package/`) stripped from their paths. Links,
+/// directories and device entries are skipped.
+pub fn unpack(tarball: &[u8]) -> Result<Vec<(PathBuf, Vec<u8>)>> {
+    let data = gunzip(tarball, MAX_UNPACKED_BYTES)?;
+
+    let mut files = Vec::new();
+    for (name, body) in tar_entries(&data)? {
+        if let Some(path) = package_path(&name)? {
+            files.push((path, body));
+        }
+    }
+
+    Ok(files)
+}
+
+/// Decompress gzipped data, failing once it exceeds `limit` bytes
+pub(crate) fn gunzip(compressed: &[u8], limit: u64) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    GzDecoder::new(compressed)
+        .take(limit + 1)
+        .read_to_end(&mut data)
+        .map_err(|e| Error::Parse(format!("Invalid gzip data: {}", e)))?;
+    if data.len() as u64 > limit {
+        return Err(Error::InvalidInput(format!("Archive is larger than {} bytes unpacked", limit)));
+    }
+    Ok(data)
+}
+
+/// Names and contents of the regular files in an uncompressed tarball.
+/// Names are returned as stored and still need checking before use.
+pub(crate) fn tar_entries(data: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    let invalid = |e: std::io::Error| Error::Parse(format!("Invalid tarball: {}", e));
+
+    let mut entries = Vec::new();
+    let mut archive = tar::Archive::new(data);
+    for entry in archive.entries().map_err(invalid)? {
+        let mut entry = entry.map_err(invalid)?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+        let mut body = Vec::new();
+        entry.read_to_end(&mut body).map_err(invalid)?;
+        if body.len() as u64 != entry.size() {
+            return Err(Error::Parse("Truncated tarball".to_string()));
+        }
+        entries.push((name, body));
+    }
+
+    Ok(entries)
+}
+
+/// Path of an entry within the package, or `None` for the top-level
+/// directory itself
+fn package_path(name: &str) -> Result<Option<PathBuf>> {
+    let mut components = Vec::new();
+    for component in Path::new(name).components() {
+        match component {
+            Component::Normal(part) => components.push(part),
+            Component::CurDir => {}
+            _ => {
+                return Err(Error::InvalidInput(format!(
+                    "Package entry escapes the package root: {}",
+                    name
+                )))
+            }
+        }
+    }
+    Ok((components.len() > 1).then(|| components[1..].iter().collect()))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+
+    /// A gzipped tarball of `(path, type flag, contents)` entries
+    pub(crate) fn tarball(entries: &[(&str, u8, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), flate2::Compression::default()));
+        for (path, kind, contents) in entries {
+            let mut header = tar::Header::new_ustar();
+            // Written as is: `set_path` refuses the escaping paths tests need
+            header.as_old_mut().name[..path.len()].copy_from_slice(path.as_bytes());
+            header.set_mode(0o644);
+            header.set_size(contents.len() as u64);
+            header.set_entry_type(tar::EntryType::new(*kind));
+            header.set_cksum();
+            builder.append(&header, *contents).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn test_unpack_keeps_regular_files_only() {
+        let long = format!("package/lib/{}.js", "a".repeat(120));
+        let pax = format!("{} path={}\n", long.len() + 10, long);
+        let archive = tarball(&[
+            ("package", b'5', b""),
+            ("package/index.js", b'0', b"module.exports = 1;\n"),
+            ("package/link.js", b'2', b""),
+            ("PaxHeader", b'x', pax.as_bytes()),
+            ("package/lib/truncated", b'0', b"exports.long = true;\n"),
+        ]);
+
+        let files = unpack(&archive).unwrap();
+        let paths: Vec<_> = files.iter().map(|(p, _)| p.to_string_lossy().into_owned()).collect();
+        assert_eq!(paths, ["index.js".to_string(), format!("lib/{}.js", "a".repeat(120))]);
+        assert_eq!(files[0].1, b"module.exports = 1;\n");
+
+        let escaping = tarball(&[("package/../../etc/passwd", b'0', b"root")]);
+        assert!(matches!(unpack(&escaping), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_npm_release_prefers_integrity() {
+        let tarball = b"tarball";
+        let integrity = base64::engine::general_purpose::STANDARD.encode(Sha512::digest(tarball));
+        let metadata = serde_json::json!({
+            "dist-tags": { "latest": "2.0.0" },
+            "versions": {
+                "1.0.0": { "dist": { "tarball": "https://r/@s/p/-/p-1.0.0.tgz", "shasum": hex::encode(sha1::Sha1::digest(tarball)) } },
+                "2.0.0": { "dist": { "tarball": "https://r/@s/p/-/p-2.0.0.tgz", "integrity": format!("sha1-AAAA sha512-{}", integrity) } },
+            }
+        });
+
+        let latest = npm_release(&metadata, "@s/p", None).unwrap();
+        assert_eq!(latest.version, "2.0.0");
+        assert!(matches!(latest.checksum, Checksum::Sha512(_)));
+        assert!(latest.checksum.matches(tarball));
+        assert!(!latest.checksum.matches(b"tampered"));
+
+        let legacy = npm_release(&metadata, "@s/p", Some("1.0.0")).unwrap();
+        assert!(matches!(legacy.checksum, Checksum::Sha1(_)));
+        assert!(legacy.checksum.matches(tarball));
+        assert!(matches!(npm_release(&metadata, "@s/p", Some("3.0.0")), Err(Error::NotFound(_))));
+
+        assert_eq!(metadata_url(PackageSource::Npm, "https://r/", "@s/p"), "https://r/@s%2Fp");
+        assert!(validate_name(PackageSource::Npm, "@s/p").is_ok());
+        assert!(validate_name(PackageSource::Npm, "../p").is_err());
+        assert!(validate_name(PackageSource::Crates, "serde_json").is_ok());
+        assert!(validate_name(PackageSource::Crates, "a/b").is_err());
+    }
+}
@@ -47,10 +47,27 @@ impl ContentParser {
 
     /// Parse content based on file extension or MIME type
     pub fn parse(&self, content: &[u8], file_path: Option<&Path>) -> Result<ParsedContent> {
+        self.parse_as(content, file_path, None)
+    }
+
+    /// Parse content as `language` when given, a language name such as
+    /// `rust` or a file extension such as `py`, else by file extension
+    pub fn parse_as(
+        &self,
+        content: &[u8],
+        file_path: Option<&Path>,
+        language: Option<&str>,
+    ) -> Result<ParsedContent> {
         let encoding = detect_encoding(content);
         let text = String::from_utf8_lossy(content).to_string();
 
-        let language = file_path.and_then(detect_language);
+        let language = match language {
+            Some(hint) => {
+                let hint = hint.trim().to_lowercase();
+                detect_language(Path::new(&format!("file.{}", hint))).or(Some(hint))
+            }
+            None => file_path.and_then(detect_language),
+        };
 
         // Try to parse as JSON
         if language.as_deref() == Some("json") {
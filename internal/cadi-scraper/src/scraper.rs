@@ -1,18 +1,23 @@
 use crate::chunker::{Chunk, Chunker};
 use crate::types::ScraperConfig;
-use crate::error::Result;
-use crate::fetcher::Fetcher;
+use crate::error::{Error, Result};
+use crate::fetcher::{self, Fetcher};
 use crate::metadata::MetadataExtractor;
 use crate::parser::{ContentParser, ParsedContent};
 use crate::transformer::Transformer;
-use crate::types::{ScraperInput, ScraperOutput, ScrapedChunk};
+use crate::types::{ChunkingStrategy, PackageLineage, ScraperInput, ScraperOutput, ScrapedChunk, SourceLineage};
+use cadi_core::{ChunkCategory, ChunkingRule, ChunkingRules};
 use chrono::Utc;
 use serde_json::json;
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
 use std::time::Instant;
 
+/// A chunk awaiting conversion, with its file's parse results and any
+/// category set by a chunking rule
+type PendingChunk = (Chunk, ParsedContent, Option<serde_json::Value>, Option<ChunkCategory>);
+
 /// Main scraper orchestrator
 #[allow(dead_code)]
 pub struct Scraper {
@@ -20,6 +25,7 @@ pub struct Scraper {
     fetcher: Fetcher,
     parser: ContentParser,
     chunker: Chunker,
+    rules: ChunkingRules,
 }
 
 impl Scraper {
@@ -28,12 +34,15 @@ impl Scraper {
         let fetcher = Fetcher::new(config.clone())?;
         let parser = ContentParser::new(config.clone());
         let chunker = Chunker::new(config.clone());
+        let rules =
+            ChunkingRules::compile(&config.rules).map_err(|e| Error::Config(e.to_string()))?;
 
         Ok(Self {
             config,
             fetcher,
             parser,
             chunker,
+            rules,
         })
     }
 
@@ -41,9 +50,10 @@ impl Scraper {
     pub async fn scrape(&self, input: &ScraperInput) -> Result<ScraperOutput> {
         let start = Instant::now();
         let mut chunks = Vec::new();
-        let mut file_count = 0;
+        let file_count;
         let mut total_bytes = 0u64;
         let mut errors = Vec::new();
+        let mut lineage = None;
 
         match input {
             ScraperInput::LocalPath(path) => {
@@ -51,7 +61,7 @@ impl Scraper {
                 total_bytes += content.len() as u64;
                 file_count = 1;
 
-                match self.process_file(path, &content, &mut chunks).await {
+                match self.process_file(path, path, &content, None, &mut chunks).await {
                     Ok(_) => {}
                     Err(e) => errors.push(e.to_string()),
                 }
@@ -70,7 +80,7 @@ impl Scraper {
                     total_bytes += content.len() as u64;
                     let full_path = path.join(&file_path);
 
-                    match self.process_file(&full_path, &content, &mut chunks).await {
+                    match self.process_file(&full_path, &file_path, &content, None, &mut chunks).await {
                         Ok(_) => {}
                         Err(e) => {
                             errors.push(format!("Error processing {}: {}", file_path.display(), e))
@@ -85,27 +95,148 @@ impl Scraper {
                 file_count = 1;
 
                 let temp_path = std::path::Path::new(url);
-                match self.process_file(temp_path, &content, &mut chunks).await {
+                match self.process_file(temp_path, temp_path, &content, None, &mut chunks).await {
                     Ok(_) => {}
                     Err(e) => errors.push(e.to_string()),
                 }
             }
 
-            ScraperInput::GitRepo { url, branch, commit } => {
-                // For MVP, treat git repo similar to URL
-                errors.push("Git repository scraping not yet implemented".to_string());
-                tracing::warn!(
-                    "Git scraping not implemented: {} branch={:?} commit={:?}",
-                    url,
-                    branch,
-                    commit
-                );
+            ScraperInput::GitRepo { url, git_ref, subdir } => {
+                if let Some(subdir) = subdir {
+                    if subdir
+                        .components()
+                        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+                    {
+                        return Err(Error::InvalidInput(format!(
+                            "Subdirectory must be relative to the repository root: {}",
+                            subdir.display()
+                        )));
+                    }
+                }
+
+                let checkout = self.fetcher.fetch_git_repo(url, git_ref.as_deref()).await?;
+                let root = match subdir {
+                    Some(subdir) => checkout.path().join(subdir),
+                    None => checkout.path().to_path_buf(),
+                };
+                if !root.is_dir() {
+                    return Err(Error::NotFound(format!(
+                        "Subdirectory {} not found in {} at {}",
+                        subdir.as_deref().unwrap_or(Path::new(".")).display(),
+                        url,
+                        checkout.commit
+                    )));
+                }
+
+                let files = self.fetcher.fetch_directory(&root, None).await?;
+                file_count = files.len();
+
+                for (file_path, content) in files {
+                    total_bytes += content.len() as u64;
+                    // Record repository-relative paths rather than the temporary checkout
+                    let repo_path = match subdir {
+                        Some(subdir) => subdir.join(&file_path),
+                        None => file_path.clone(),
+                    };
+
+                    match self.process_file(&repo_path, &repo_path, &content, None, &mut chunks).await {
+                        Ok(_) => {}
+                        Err(e) => {
+                            errors.push(format!("Error processing {}: {}", repo_path.display(), e))
+                        }
+                    }
+                }
+
+                lineage = Some(SourceLineage {
+                    repository: url.clone(),
+                    git_ref: git_ref.clone(),
+                    commit: Some(checkout.commit.clone()),
+                    package: None,
+                });
+            }
+
+            ScraperInput::Package { source, name, version } => {
+                let package = self.fetcher.fetch_package(*source, name, version.as_deref()).await?;
+                file_count = package.files.len();
+
+                for (file_path, content) in &package.files {
+                    total_bytes += content.len() as u64;
+
+                    match self.process_file(file_path, file_path, content, None, &mut chunks).await {
+                        Ok(_) => {}
+                        Err(e) => {
+                            errors.push(format!("Error processing {}: {}", file_path.display(), e))
+                        }
+                    }
+                }
+
+                lineage = Some(SourceLineage {
+                    repository: package.release.url.clone(),
+                    git_ref: None,
+                    commit: None,
+                    package: Some(PackageLineage {
+                        source: package.source,
+                        name: package.name.clone(),
+                        version: package.release.version.clone(),
+                        checksum: package.release.checksum.to_string(),
+                    }),
+                });
+            }
+
+            ScraperInput::Archive { path_or_url, format } => {
+                let files = self.fetcher.fetch_archive(path_or_url, *format).await?;
+                file_count = files.len();
+
+                for (file_path, content) in &files {
+                    total_bytes += content.len() as u64;
+
+                    match self.process_file(file_path, file_path, content, None, &mut chunks).await {
+                        Ok(_) => {}
+                        Err(e) => {
+                            errors.push(format!("Error processing {}: {}", file_path.display(), e))
+                        }
+                    }
+                }
+
+                if fetcher::is_remote(path_or_url) {
+                    lineage = Some(SourceLineage {
+                        repository: path_or_url.clone(),
+                        git_ref: None,
+                        commit: None,
+                        package: None,
+                    });
+                }
+            }
+
+            ScraperInput::RawFile { url, language_hint } => {
+                if !fetcher::is_remote(url) {
+                    return Err(Error::InvalidInput(format!("Raw file input must be an HTTP(S) URL: {}", url)));
+                }
+                let content = self.fetcher.fetch_bounded(url).await?;
+                total_bytes += content.len() as u64;
+                file_count = 1;
+
+                let file_path = raw_file_path(url);
+                match self
+                    .process_file(&file_path, &file_path, &content, language_hint.as_deref(), &mut chunks)
+                    .await
+                {
+                    Ok(_) => {}
+                    Err(e) => errors.push(e.to_string()),
+                }
+
+                lineage = Some(SourceLineage {
+                    repository: url.clone(),
+                    git_ref: None,
+                    commit: None,
+                    package: None,
+                });
             }
         }
 
         let chunk_count = chunks.len();
-        let scraped_chunks = self.convert_to_scraped_chunks(chunks)?;
-        let manifest = self.create_manifest(&scraped_chunks)?;
+        let scraped_chunks = self.convert_to_scraped_chunks(chunks, lineage.as_ref())?;
+        let manifest = self.create_manifest(&scraped_chunks, lineage.as_ref())?;
         let duration_ms = start.elapsed().as_millis();
 
         tracing::info!(
@@ -126,17 +257,28 @@ impl Scraper {
         })
     }
 
-    /// Process a single file
+    /// Process a single file. `rule_path` is the path chunking rules are
+    /// matched against, relative to the scraped root where there is one,
+    /// and `language_hint` overrides detection from the file extension.
     async fn process_file(
         &self,
         file_path: &Path,
+        rule_path: &Path,
         content: &[u8],
-        chunks: &mut Vec<(Chunk, ParsedContent, Option<serde_json::Value>)>,
+        language_hint: Option<&str>,
+        chunks: &mut Vec<PendingChunk>,
     ) -> Result<()> {
+        let rule = self.rules.resolve(rule_path);
+        let Some(strategy) = rule.map_or(Some(self.config.chunking_strategy), rule_strategy) else {
+            tracing::debug!("Skipping file by chunking rule: {}", rule_path.display());
+            return Ok(());
+        };
+        let category = rule.and_then(|r| r.category.clone());
+
         tracing::debug!("Processing file: {}", file_path.display());
 
         // Parse content
-        let parsed = self.parser.parse(content, Some(file_path))?;
+        let parsed = self.parser.parse_as(content, Some(file_path), language_hint)?;
 
         // Extract metadata
         let _metadata = MetadataExtractor::extract(
@@ -173,14 +315,15 @@ impl Scraper {
         };
 
         // Chunk the content
-        let file_chunks = self.chunker.chunk(
+        let file_chunks = self.chunker.chunk_with(
+            strategy,
             &parsed.text,
             parsed.language.as_deref(),
             &file_path.to_string_lossy(),
         )?;
 
         for chunk in file_chunks {
-            chunks.push((chunk, parsed.clone(), ast_info.clone()));
+            chunks.push((chunk, parsed.clone(), ast_info.clone(), category.clone()));
         }
 
         Ok(())
@@ -189,11 +332,17 @@ impl Scraper {
     /// Convert internal chunks to ScrapedChunk format
     fn convert_to_scraped_chunks(
         &self,
-        chunks: Vec<(Chunk, ParsedContent, Option<serde_json::Value>)>,
+        chunks: Vec<PendingChunk>,
+        lineage: Option<&SourceLineage>,
     ) -> Result<Vec<ScrapedChunk>> {
         let mut result = Vec::new();
+        // Package chunks get aliases under `<source>/<name>/<version>/`
+        let alias_prefix = lineage
+            .and_then(|l| l.package.as_ref())
+            .map(|p| format!("{}/{}/{}", p.source.as_str(), p.name, p.version));
+        let mut aliases = HashSet::new();
 
-        for (chunk, parsed, ast_info) in chunks {
+        for (chunk, parsed, ast_info, category) in chunks {
             let mut concepts = chunk.concepts.clone();
 
             // Add parsed metadata concepts
@@ -219,6 +368,16 @@ impl Scraper {
                 .title
                 .clone()
                 .unwrap_or_else(|| format!("chunk-{}", &chunk.id[..12]));
+            let alias = alias_prefix.as_ref().map(|prefix| {
+                let base = format!("{}/{}", prefix, alias_segment(&name));
+                let mut alias = base.clone();
+                let mut n = 1;
+                while !aliases.insert(alias.clone()) {
+                    n += 1;
+                    alias = format!("{}-{}", base, n);
+                }
+                alias
+            });
 
             result.push(ScrapedChunk {
                 chunk_id: chunk.id,
@@ -235,7 +394,10 @@ impl Scraper {
                 parent_chunk_id: chunk.parent_id.clone(),
                 child_chunk_ids: chunk.children.clone(),
                 tags: vec![],
+                category,
                 scraped_at: Utc::now().to_rfc3339(),
+                lineage: lineage.cloned(),
+                aliases: alias.into_iter().collect(),
             });
         }
 
@@ -243,12 +405,16 @@ impl Scraper {
     }
 
     /// Create a manifest for all chunks
-    fn create_manifest(&self, chunks: &[ScrapedChunk]) -> Result<Option<serde_json::Value>> {
+    fn create_manifest(
+        &self,
+        chunks: &[ScrapedChunk],
+        lineage: Option<&SourceLineage>,
+    ) -> Result<Option<serde_json::Value>> {
         if chunks.is_empty() {
             return Ok(None);
         }
 
-        let manifest = json!({
+        let mut manifest = json!({
             "version": "1.0.0",
             "cadi_type": "manifest",
             "scraped_at": Utc::now().to_rfc3339(),
@@ -259,9 +425,13 @@ impl Scraper {
                 "source": c.source,
                 "language": c.language,
                 "concepts": c.concepts,
+                "aliases": c.aliases,
             })).collect::<Vec<_>>(),
             "dependency_graph": self.build_dependency_graph(chunks)?,
         });
+        if let Some(lineage) = lineage {
+            manifest["source"] = serde_json::to_value(lineage)?;
+        }
 
         Ok(Some(manifest))
     }
@@ -281,6 +451,41 @@ impl Scraper {
     }
 }
 
+/// Scraper strategy for a chunking rule, or `None` if the rule skips the file
+fn rule_strategy(rule: &ChunkingRule) -> Option<ChunkingStrategy> {
+    match rule.strategy {
+        cadi_core::ChunkingStrategy::Atomic => Some(ChunkingStrategy::ByFile),
+        cadi_core::ChunkingStrategy::ByEntity => Some(ChunkingStrategy::Semantic),
+        cadi_core::ChunkingStrategy::BySections => Some(ChunkingStrategy::ByLineCount),
+        cadi_core::ChunkingStrategy::Hierarchical => Some(ChunkingStrategy::Hierarchical),
+        cadi_core::ChunkingStrategy::Skip => None,
+    }
+}
+
+/// File name a raw file URL is scraped under: the last segment of its path
+fn raw_file_path(url: &str) -> PathBuf {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let path = path.split_once("://").map_or(path, |(_, rest)| rest);
+    let name = path.split_once('/').map_or("", |(_, path)| path);
+    name.rsplit('/')
+        .find(|segment| !segment.is_empty())
+        .unwrap_or("index")
+        .into()
+}
+
+/// A chunk name as an alias path segment
+fn alias_segment(name: &str) -> String {
+    let mut segment = String::new();
+    for c in name.to_lowercase().chars() {
+        if c.is_alphanumeric() || matches!(c, '_' | '.') {
+            segment.push(c);
+        } else if !segment.is_empty() && !segment.ends_with('-') {
+            segment.push('-');
+        }
+    }
+    segment.trim_end_matches('-').to_string()
+}
+
 /// Compute SHA256 hash
 fn compute_hash(content: &str) -> String {
     let mut hasher = Sha256::new();
@@ -299,4 +504,283 @@ mod tests {
         let scraper = Scraper::new(config);
         assert!(scraper.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_chunking_rules_route_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for (path, content) in [
+            ("src/math.rs", "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\npub fn sub(a: i32, b: i32) -> i32 {\n    a - b\n}\n\npub fn neg(a: i32) -> i32 {\n    -a\n}\n"),
+            ("docs/intro.md", "# Intro\n\npub fn looks_like_code() {}\n\nfn neither() {}\n"),
+            ("migrations/001_init.sql", "CREATE TABLE users (id INTEGER);\n"),
+        ] {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+
+        let config = ScraperConfig {
+            include_overlap: false,
+            rules: vec![
+                ChunkingRule::new("src/**/*.rs", cadi_core::ChunkingStrategy::ByEntity),
+                ChunkingRule::new("docs/**/*.md", cadi_core::ChunkingStrategy::Atomic)
+                    .with_category(ChunkCategory::Docs),
+                ChunkingRule::new("migrations/*.sql", cadi_core::ChunkingStrategy::Skip),
+            ],
+            ..Default::default()
+        };
+        let scraper = Scraper::new(config).unwrap();
+        let input = ScraperInput::Directory {
+            path: dir.path().to_path_buf(),
+            patterns: None,
+        };
+        let output = scraper.scrape(&input).await.unwrap();
+
+        let from = |suffix: &str| -> Vec<&ScrapedChunk> {
+            output.chunks.iter().filter(|c| c.source.ends_with(suffix)).collect()
+        };
+        assert!(from("math.rs").len() > 1);
+        let docs = from("intro.md");
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].category, Some(ChunkCategory::Docs));
+        assert!(from("001_init.sql").is_empty());
+    }
+
+    #[test]
+    fn test_invalid_rule_is_config_error() {
+        let config = ScraperConfig {
+            rules: vec![ChunkingRule::new("src/[", cadi_core::ChunkingStrategy::Atomic)],
+            ..Default::default()
+        };
+        assert!(matches!(Scraper::new(config), Err(Error::Config(_))));
+    }
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = std::process::Command::new("git")
+            .current_dir(dir)
+            .args(["-c", "user.name=CADI", "-c", "user.email=cadi@example.com"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_scrape_git_repo_subdir_records_commit() {
+        let repo = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(repo.path().join("crates/util/src")).unwrap();
+        std::fs::write(repo.path().join("README.md"), "# Demo\n").unwrap();
+        std::fs::write(
+            repo.path().join("crates/util/src/lib.rs"),
+            "pub fn add(a: i32, b: i32) -> i32 { a + b }\n",
+        )
+        .unwrap();
+        git(repo.path(), &["init", "-q", "-b", "main"]);
+        git(repo.path(), &["add", "-A"]);
+        git(repo.path(), &["commit", "-q", "-m", "initial"]);
+        let commit = git(repo.path(), &["rev-parse", "HEAD"]);
+
+        let scraper = Scraper::new(ScraperConfig::default()).unwrap();
+        let input = ScraperInput::GitRepo {
+            url: format!("file://{}", repo.path().display()),
+            git_ref: Some("main".to_string()),
+            subdir: Some("crates/util".into()),
+        };
+        let output = scraper.scrape(&input).await.unwrap();
+
+        assert_eq!(output.file_count, 1);
+        assert!(!output.chunks.is_empty());
+        for chunk in &output.chunks {
+            assert_eq!(chunk.source, "crates/util/src/lib.rs");
+            let lineage = chunk.lineage.as_ref().unwrap();
+            assert_eq!(lineage.commit.as_deref(), Some(commit.as_str()));
+            assert_eq!(lineage.git_ref.as_deref(), Some("main"));
+        }
+        assert_eq!(output.manifest.unwrap()["source"]["commit"], commit);
+
+        let missing = ScraperInput::GitRepo {
+            url: format!("file://{}", repo.path().display()),
+            git_ref: None,
+            subdir: Some("../escape".into()),
+        };
+        assert!(matches!(scraper.scrape(&missing).await, Err(Error::InvalidInput(_))));
+    }
+
+    /// Serve fixed responses by request path until the test ends
+    async fn serve(routes: HashMap<String, Vec<u8>>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match socket.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let path = request.split_whitespace().nth(1).unwrap_or("/");
+                let (status, body) = match routes.get(path) {
+                    Some(body) => ("200 OK", body.clone()),
+                    None => ("404 Not Found", Vec::new()),
+                };
+                let head = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, body.len());
+                let _ = socket.write_all(head.as_bytes()).await;
+                let _ = socket.write_all(&body).await;
+            }
+        });
+        base
+    }
+
+    #[tokio::test]
+    async fn test_scrape_crate_verifies_checksum_and_records_package() {
+        let tarball = crate::package::tests::tarball(&[
+            ("demo-0.2.0/Cargo.toml", b'0', b"[package]\nname = \"demo\"\nversion = \"0.2.0\"\n"),
+            ("demo-0.2.0/build.rs", b'0', b"fn main() { std::process::exit(1) }\n"),
+            ("demo-0.2.0/src/lib.rs", b'0', b"pub fn add(a: i32, b: i32) -> i32 { a + b }\n"),
+            ("demo-0.2.0/src/escape.rs", b'2', b""),
+        ]);
+        let checksum = hex::encode(Sha256::digest(&tarball));
+        let metadata = |checksum: &str| {
+            json!({
+                "crate": { "name": "demo", "max_stable_version": "0.2.0" },
+                "versions": [
+                    { "num": "0.2.0", "checksum": checksum, "dl_path": "/api/v1/crates/demo/0.2.0/download" },
+                    { "num": "0.1.0", "checksum": checksum, "dl_path": "/api/v1/crates/demo/0.1.0/download" },
+                ]
+            })
+            .to_string()
+            .into_bytes()
+        };
+        let base = serve(HashMap::from([
+            ("/good/api/v1/crates/demo".to_string(), metadata(&checksum)),
+            ("/good/api/v1/crates/demo/0.2.0/download".to_string(), tarball.clone()),
+            ("/bad/api/v1/crates/demo".to_string(), metadata(&"0".repeat(64))),
+            ("/bad/api/v1/crates/demo/0.2.0/download".to_string(), tarball.clone()),
+        ]))
+        .await;
+
+        let config = ScraperConfig {
+            crates_registry_url: format!("{}/good", base),
+            ..Default::default()
+        };
+        let input = ScraperInput::Package {
+            source: crate::types::PackageSource::Crates,
+            name: "demo".to_string(),
+            version: None,
+        };
+        let output = Scraper::new(config).unwrap().scrape(&input).await.unwrap();
+
+        // Links are skipped; the build script is scraped, never run
+        assert_eq!(output.file_count, 3);
+        let sources: HashSet<&str> = output.chunks.iter().map(|c| c.source.as_str()).collect();
+        assert!(sources.contains("src/lib.rs") && sources.contains("build.rs"));
+        let aliases: HashSet<&String> = output.chunks.iter().flat_map(|c| &c.aliases).collect();
+        assert_eq!(aliases.len(), output.chunks.len());
+        for chunk in &output.chunks {
+            let lineage = chunk.lineage.as_ref().unwrap();
+            assert_eq!(lineage.repository, format!("{}/good/api/v1/crates/demo/0.2.0/download", base));
+            assert_eq!(lineage.commit, None);
+            let package = lineage.package.as_ref().unwrap();
+            assert_eq!((package.name.as_str(), package.version.as_str()), ("demo", "0.2.0"));
+            assert_eq!(package.checksum, format!("sha256:{}", checksum));
+            assert!(chunk.aliases[0].starts_with("crates/demo/0.2.0/"), "{}", chunk.aliases[0]);
+        }
+
+        let tampered = ScraperConfig {
+            crates_registry_url: format!("{}/bad", base),
+            ..Default::default()
+        };
+        let result = Scraper::new(tampered).unwrap().scrape(&input).await;
+        assert!(matches!(result, Err(Error::Fetch(ref e)) if e.contains("Checksum mismatch")));
+    }
+
+    #[tokio::test]
+    async fn test_scrape_archives_and_raw_files_record_url() {
+        let tarball = crate::package::tests::tarball(&[
+            ("demo-main/README.md", b'0', b"# Demo\n\nA demo project.\n"),
+            ("demo-main/src/lib.rs", b'0', b"pub fn add(a: i32, b: i32) -> i32 { a + b }\n"),
+            ("demo-main/.DS_Store", b'0', b"\0\0\0\x01Bud1"),
+        ]);
+        let evil = crate::archive::tests::zip(&[("demo/src/lib.rs", b"fn ok() {}\n"), ("../evil", b"pwned")]);
+        let base = serve(HashMap::from([
+            ("/demo-main.tar.gz".to_string(), tarball.clone()),
+            ("/download".to_string(), tarball),
+            ("/evil.zip".to_string(), evil),
+            ("/raw/3f2a9c/snippet?token=x".to_string(), b"def greet(name):\n    return name\n".to_vec()),
+        ]))
+        .await;
+        let scraper = Scraper::new(ScraperConfig::default()).unwrap();
+
+        let url = format!("{}/demo-main.tar.gz", base);
+        let input = ScraperInput::Archive { path_or_url: url.clone(), format: None };
+        let output = scraper.scrape(&input).await.unwrap();
+        assert!(output.errors.is_empty(), "{:?}", output.errors);
+        // The shared top-level directory is the root and excludes still apply
+        assert_eq!(output.file_count, 2);
+        let sources: HashSet<&str> = output.chunks.iter().map(|c| c.source.as_str()).collect();
+        assert_eq!(sources, HashSet::from(["README.md", "src/lib.rs"]));
+        for chunk in &output.chunks {
+            assert_eq!(chunk.lineage.as_ref().unwrap().repository, url);
+        }
+        assert_eq!(output.manifest.unwrap()["source"]["repository"], url);
+
+        // Without an extension the format has to be given
+        let unnamed = format!("{}/download", base);
+        let input = ScraperInput::Archive { path_or_url: unnamed.clone(), format: None };
+        assert!(matches!(scraper.scrape(&input).await, Err(Error::UnsupportedFormat(_))));
+        let input = ScraperInput::Archive { path_or_url: unnamed, format: Some(crate::types::ArchiveFormat::TarGz) };
+        assert_eq!(scraper.scrape(&input).await.unwrap().file_count, 2);
+
+        let input = ScraperInput::Archive { path_or_url: format!("{}/evil.zip", base), format: None };
+        assert!(matches!(scraper.scrape(&input).await, Err(Error::InvalidInput(ref e)) if e.contains("../evil")));
+
+        let small = Scraper::new(ScraperConfig { max_download_bytes: 16, ..Default::default() }).unwrap();
+        let input = ScraperInput::Archive { path_or_url: url, format: None };
+        assert!(matches!(small.scrape(&input).await, Err(Error::InvalidInput(_))));
+
+        let raw = format!("{}/raw/3f2a9c/snippet?token=x", base);
+        let input = ScraperInput::RawFile { url: raw.clone(), language_hint: Some("py".to_string()) };
+        let output = scraper.scrape(&input).await.unwrap();
+        assert_eq!(output.file_count, 1);
+        for chunk in &output.chunks {
+            assert_eq!(chunk.source, "snippet");
+            assert_eq!(chunk.language.as_deref(), Some("python"));
+            assert_eq!(chunk.lineage.as_ref().unwrap().repository, raw);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scrape_local_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("demo.zip");
+        std::fs::write(
+            &path,
+            crate::archive::tests::zip(&[("demo/src/lib.rs", b"pub fn demo() {}\n"), ("demo/README.md", b"# Demo\n")]),
+        )
+        .unwrap();
+
+        let scraper = Scraper::new(ScraperConfig::default()).unwrap();
+        let input = ScraperInput::Archive { path_or_url: path.to_string_lossy().into_owned(), format: None };
+        let output = scraper.scrape(&input).await.unwrap();
+        assert_eq!(output.file_count, 2);
+        assert!(output.chunks.iter().all(|c| c.lineage.is_none()));
+        assert!(output.chunks.iter().any(|c| c.source == "src/lib.rs"));
+    }
+
+    #[test]
+    fn test_raw_file_path() {
+        assert_eq!(raw_file_path("https://gist.githubusercontent.com/u/abc/raw/def/lib.rs?x=1"), PathBuf::from("lib.rs"));
+        assert_eq!(raw_file_path("https://example.com/"), PathBuf::from("index"));
+    }
+
+    #[test]
+    fn test_alias_segment() {
+        assert_eq!(alias_segment("chunk-3f2a9c"), "chunk-3f2a9c");
+        assert_eq!(alias_segment("Lodash: A Utility Library!"), "lodash-a-utility-library");
+    }
 }
@@ -1,3 +1,4 @@
+use cadi_core::{ChunkCategory, ChunkingRule};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -17,6 +18,11 @@ pub struct ScraperConfig {
     /// Chunking strategy to use
     pub chunking_strategy: ChunkingStrategy,
 
+    /// Per-path strategy overrides; the first matching rule wins and
+    /// unmatched files use `chunking_strategy`
+    #[serde(default)]
+    pub rules: Vec<ChunkingRule>,
+
     /// Maximum chunk size in bytes
     pub max_chunk_size: usize,
 
@@ -49,6 +55,50 @@ pub struct ScraperConfig {
 
     /// Local cache directory
     pub cache_dir: Option<PathBuf>,
+
+    /// Token for fetching private git repositories
+    #[serde(default)]
+    pub git_token: Option<String>,
+
+    /// Host the git token is sent to; other remotes are fetched without it
+    #[serde(default = "default_git_token_host")]
+    pub git_token_host: String,
+
+    /// crates.io-compatible registry packages are fetched from
+    #[serde(default = "default_crates_registry")]
+    pub crates_registry_url: String,
+
+    /// npm-compatible registry packages are fetched from
+    #[serde(default = "default_npm_registry")]
+    pub npm_registry_url: String,
+
+    /// Largest archive or raw file that will be downloaded or read, in bytes
+    #[serde(default = "default_max_download_bytes")]
+    pub max_download_bytes: u64,
+
+    /// Largest total size an archive may unpack to, in bytes
+    #[serde(default = "default_max_unpacked_bytes")]
+    pub max_unpacked_bytes: u64,
+}
+
+fn default_crates_registry() -> String {
+    "https://crates.io".to_string()
+}
+
+fn default_git_token_host() -> String {
+    "github.com".to_string()
+}
+
+fn default_npm_registry() -> String {
+    "https://registry.npmjs.org".to_string()
+}
+
+fn default_max_download_bytes() -> u64 {
+    100 * 1024 * 1024
+}
+
+fn default_max_unpacked_bytes() -> u64 {
+    512 * 1024 * 1024
 }
 
 /// Language-specific configuration
@@ -115,6 +165,7 @@ impl Default for ScraperConfig {
             auth_token: None,
             namespace: None,
             chunking_strategy: ChunkingStrategy::ByFile,
+            rules: Vec::new(),
             max_chunk_size: 50 * 1024 * 1024, // 50MB default
             include_overlap: true,
             overlap_size: 500,
@@ -134,6 +185,12 @@ impl Default for ScraperConfig {
             request_timeout: 30,
             rate_limit: 10.0,
             cache_dir: None,
+            git_token: None,
+            git_token_host: default_git_token_host(),
+            crates_registry_url: default_crates_registry(),
+            npm_registry_url: default_npm_registry(),
+            max_download_bytes: default_max_download_bytes(),
+            max_unpacked_bytes: default_max_unpacked_bytes(),
         }
     }
 }
@@ -147,13 +204,15 @@ pub enum ScraperInput {
     /// HTTP(S) URL
     Url(String),
 
-    /// Git repository URL
+    /// Git repository URL, optionally pinned to a ref and narrowed to a subdirectory
     GitRepo {
         url: String,
+        /// Branch, tag or commit SHA (defaults to the remote HEAD)
         #[serde(skip_serializing_if = "Option::is_none")]
-        branch: Option<String>,
+        git_ref: Option<String>,
+        /// Repository-relative directory to scrape
         #[serde(skip_serializing_if = "Option::is_none")]
-        commit: Option<String>,
+        subdir: Option<PathBuf>,
     },
 
     /// Directory path with optional filters
@@ -162,6 +221,75 @@ pub enum ScraperInput {
         #[serde(skip_serializing_if = "Option::is_none")]
         patterns: Option<Vec<String>>,
     },
+
+    /// Package published to crates.io or npm
+    Package {
+        source: PackageSource,
+        name: String,
+        /// Exact version (defaults to the latest stable release)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        version: Option<String>,
+    },
+
+    /// Zip or tarball of a source tree, from a local path or an HTTP(S) URL
+    Archive {
+        path_or_url: String,
+        /// Container format (detected from the file name when omitted)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        format: Option<ArchiveFormat>,
+    },
+
+    /// Single file downloaded from a URL, such as a raw gist
+    RawFile {
+        url: String,
+        /// Language to parse the file as, e.g. `rust` or `py`, when the URL
+        /// has no recognizable extension
+        #[serde(skip_serializing_if = "Option::is_none")]
+        language_hint: Option<String>,
+    },
+}
+
+/// Package registry a package is scraped from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageSource {
+    Crates,
+    Npm,
+}
+
+impl PackageSource {
+    /// Name used in lineage and as the first alias segment
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PackageSource::Crates => "crates",
+            PackageSource::Npm => "npm",
+        }
+    }
+}
+
+/// Container format of a source archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    /// Format implied by a file name or URL, ignoring any query string
+    pub fn detect(name: &str) -> Option<Self> {
+        let name = name.split(['?', '#']).next().unwrap_or(name).to_ascii_lowercase();
+        if name.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else if name.ends_with(".tar") {
+            Some(ArchiveFormat::Tar)
+        } else {
+            None
+        }
+    }
 }
 
 /// Output from the scraper
@@ -234,6 +362,47 @@ pub struct ScrapedChunk {
     /// Metadata tags
     pub tags: Vec<String>,
 
+    /// Category set by a chunking rule
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<ChunkCategory>,
+
     /// Timestamp when scraped
     pub scraped_at: String,
+
+    /// Repository and commit, or package, the chunk was scraped from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lineage: Option<SourceLineage>,
+
+    /// Aliases to register for the chunk, e.g. `npm/lodash/4.17.21/chunk-name`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+}
+
+/// Upstream origin of a scraped chunk
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceLineage {
+    /// Repository URL, or the URL a package was downloaded from
+    pub repository: String,
+
+    /// Requested branch, tag or commit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_ref: Option<String>,
+
+    /// Resolved commit SHA; `None` for packages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+
+    /// Published package the source was unpacked from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<PackageLineage>,
+}
+
+/// A published package version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageLineage {
+    pub source: PackageSource,
+    pub name: String,
+    pub version: String,
+    /// Upstream checksum the download was verified against, e.g. `sha256:<hex>`
+    pub checksum: String,
 }
@@ -0,0 +1,156 @@
+//! On-disk embedding cache
+//!
+//! Embeddings are filed under the SHA-256 of the model that produced them and
+//! the text that was embedded, so re-importing unchanged chunks never reaches
+//! the provider again. Each entry is a small JSON file, sharded by the first
+//! two hex digits of its key.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+use crate::embeddings::{Embedding, EmbeddingProvider};
+
+/// Embeddings stored on disk by content hash
+#[derive(Debug, Clone)]
+pub struct EmbeddingCache {
+    dir: PathBuf,
+}
+
+impl EmbeddingCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Cache key for `text` embedded by `model` into `dimension` dimensions
+    pub fn key(model: &str, dimension: usize, text: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(format!("{}:{}\n", model, dimension));
+        hasher.update(text);
+        format!("{:x}", hasher.finalize())
+    }
+
+    pub fn get(&self, key: &str) -> Option<Embedding> {
+        let s = std::fs::read_to_string(self.path(key)).ok()?;
+        serde_json::from_str(&s).ok()
+    }
+
+    pub fn insert(&self, key: &str, embedding: &Embedding) -> Result<()> {
+        let path = self.path(key);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Write then rename so a concurrent reader never sees half an entry
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_string(embedding)?)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        let (shard, rest) = key.split_at(2.min(key.len()));
+        self.dir.join(shard).join(format!("{}.json", rest))
+    }
+}
+
+/// A provider that consults an [`EmbeddingCache`] before its inner provider
+pub struct CachedProvider {
+    inner: Box<dyn EmbeddingProvider>,
+    cache: EmbeddingCache,
+}
+
+impl CachedProvider {
+    pub fn new(inner: Box<dyn EmbeddingProvider>, cache: EmbeddingCache) -> Self {
+        Self { inner, cache }
+    }
+
+    fn key(&self, text: &str) -> String {
+        EmbeddingCache::key(&self.inner.model_id(), self.inner.dimension(), text)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for CachedProvider {
+    async fn generate(&self, text: &str) -> Result<Embedding> {
+        let mut embeddings = self.generate_batch(&[text.to_string()]).await?;
+        Ok(embeddings.remove(0))
+    }
+
+    fn dimension(&self) -> usize {
+        self.inner.dimension()
+    }
+
+    fn model_id(&self) -> String {
+        self.inner.model_id()
+    }
+
+    async fn generate_batch(&self, texts: &[String]) -> Result<Vec<Embedding>> {
+        let keys: Vec<String> = texts.iter().map(|text| self.key(text)).collect();
+        let mut results: Vec<Option<Embedding>> = keys.iter().map(|key| self.cache.get(key)).collect();
+
+        let missing: Vec<usize> = (0..texts.len()).filter(|&i| results[i].is_none()).collect();
+        if !missing.is_empty() {
+            let batch: Vec<String> = missing.iter().map(|&i| texts[i].clone()).collect();
+            let generated = self.inner.generate_batch(&batch).await?;
+            for (i, embedding) in missing.into_iter().zip(generated) {
+                if let Err(e) = self.cache.insert(&keys[i], &embedding) {
+                    tracing::warn!("Failed to cache embedding: {}", e);
+                }
+                results[i] = Some(embedding);
+            }
+        }
+
+        results
+            .into_iter()
+            .map(|embedding| embedding.ok_or_else(|| anyhow::anyhow!("Provider returned too few embeddings")))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct CountingProvider {
+        embedded: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl EmbeddingProvider for CountingProvider {
+        async fn generate(&self, text: &str) -> Result<Embedding> {
+            self.embedded.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![text.len() as f32, 1.0])
+        }
+
+        fn dimension(&self) -> usize {
+            2
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_text_is_not_re_embedded() {
+        let dir = tempfile::tempdir().unwrap();
+        let embedded = Arc::new(AtomicUsize::new(0));
+        let provider = || {
+            CachedProvider::new(
+                Box::new(CountingProvider { embedded: embedded.clone() }),
+                EmbeddingCache::new(dir.path()),
+            )
+        };
+
+        let texts = vec!["fn a() {}".to_string(), "fn bb() {}".to_string()];
+        let first = provider().generate_batch(&texts).await.unwrap();
+        assert_eq!(embedded.load(Ordering::SeqCst), 2);
+
+        // A fresh provider over the same directory, as on re-import
+        let texts = vec!["fn bb() {}".to_string(), "fn ccc() {}".to_string(), "fn a() {}".to_string()];
+        let second = provider().generate_batch(&texts).await.unwrap();
+        assert_eq!(embedded.load(Ordering::SeqCst), 3);
+        assert_eq!(second[0], first[1]);
+        assert_eq!(second[2], first[0]);
+        assert_eq!(second[1], vec![11.0, 1.0]);
+    }
+}
@@ -3,7 +3,9 @@ use async_trait::async_trait;
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::time::Duration;
 
+use crate::cache::{CachedProvider, EmbeddingCache};
 use crate::store::EmbeddingStore;
 
 /// An embedding vector
@@ -14,6 +16,15 @@ pub type Embedding = Vec<f32>;
 pub trait EmbeddingProvider: Send + Sync {
     /// Generate an embedding for a piece of text
     async fn generate(&self, text: &str) -> Result<Embedding>;
+
+    /// Length of the vectors this provider generates
+    fn dimension(&self) -> usize;
+
+    /// Identifies the model behind this provider, so cached embeddings from
+    /// one model are never served for another
+    fn model_id(&self) -> String {
+        std::any::type_name::<Self>().to_string()
+    }
     
     /// Generate embeddings for multiple pieces of text
     async fn generate_batch(&self, texts: &[String]) -> Result<Vec<Embedding>> {
@@ -26,10 +37,20 @@ pub trait EmbeddingProvider: Send + Sync {
 }
 
 
-/// OpenAI embedding provider
+/// Texts sent per request by the HTTP providers unless configured otherwise
+pub const DEFAULT_BATCH_SIZE: usize = 64;
+
+/// Times a rate-limited (429) request is retried before giving up
+pub const DEFAULT_MAX_RETRIES: u32 = 5;
+
+/// OpenAI-compatible embedding provider (`POST {base_url}/embeddings`)
 pub struct OpenAiProvider {
     api_key: String,
     model: String,
+    base_url: String,
+    dimension: Option<usize>,
+    batch_size: usize,
+    max_retries: u32,
     client: reqwest::Client,
 }
 
@@ -38,9 +59,60 @@ impl OpenAiProvider {
         Self {
             api_key,
             model: model.unwrap_or_else(|| "text-embedding-3-small".to_string()),
+            base_url: "https://api.openai.com/v1".to_string(),
+            dimension: None,
+            batch_size: DEFAULT_BATCH_SIZE,
+            max_retries: DEFAULT_MAX_RETRIES,
             client: reqwest::Client::new(),
         }
     }
+
+    /// Create a provider with the API key from `OPENAI_API_KEY`
+    pub fn from_env(model: Option<String>) -> Result<Self> {
+        let api_key = std::env::var("OPENAI_API_KEY")
+            .map_err(|_| anyhow!("OPENAI_API_KEY is not set"))?;
+        Ok(Self::new(api_key, model))
+    }
+
+    /// Use another OpenAI-compatible server, e.g. `http://localhost:8080/v1`
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Declare the dimension of a model this provider does not know
+    pub fn with_dimension(mut self, dimension: usize) -> Self {
+        self.dimension = Some(dimension);
+        self
+    }
+
+    /// Maximum number of texts sent per request
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Embedding>> {
+        let url = format!("{}/embeddings", self.base_url);
+        let response = send_with_retry(self.max_retries, || {
+            self.client.post(&url)
+                .bearer_auth(&self.api_key)
+                .json(&serde_json::json!({
+                    "input": texts,
+                    "model": self.model,
+                }))
+        }).await?;
+
+        let mut result: OpenAiResponse = response.json().await?;
+        result.data.sort_by_key(|d| d.index);
+        let embeddings = result.data.into_iter().map(|d| d.embedding).collect();
+        check_embeddings(embeddings, texts.len(), self.dimension(), &self.model)
+    }
 }
 
 impl Default for MockProvider {
@@ -58,22 +130,27 @@ impl std::fmt::Debug for MockProvider {
 #[async_trait]
 impl EmbeddingProvider for OpenAiProvider {
     async fn generate(&self, text: &str) -> Result<Embedding> {
-        let response = self.client.post("https://api.openai.com/v1/embeddings")
-            .bearer_auth(&self.api_key)
-            .json(&serde_json::json!({
-                "input": text,
-                "model": self.model,
-            }))
-            .send()
-            .await?;
+        let mut embeddings = self.embed(&[text.to_string()]).await?;
+        Ok(embeddings.remove(0))
+    }
 
-        if !response.status().is_success() {
-            let error: serde_json::Value = response.json().await?;
-            return Err(anyhow!("OpenAI error: {}", error));
-        }
+    fn dimension(&self) -> usize {
+        self.dimension.unwrap_or(match self.model.as_str() {
+            "text-embedding-3-large" => 3072,
+            _ => 1536,
+        })
+    }
+
+    fn model_id(&self) -> String {
+        format!("openai:{}:{}", self.base_url, self.model)
+    }
 
-        let result: OpenAiResponse = response.json().await?;
-        Ok(result.data[0].embedding.clone())
+    async fn generate_batch(&self, texts: &[String]) -> Result<Vec<Embedding>> {
+        let mut results = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(self.batch_size) {
+            results.extend(self.embed(batch).await?);
+        }
+        Ok(results)
     }
 }
 
@@ -84,9 +161,225 @@ struct OpenAiResponse {
 
 #[derive(Deserialize)]
 struct OpenAiData {
+    #[serde(default)]
+    index: usize,
     embedding: Vec<f32>,
 }
 
+/// Ollama embedding provider for local models (`POST {base_url}/api/embed`)
+pub struct OllamaProvider {
+    model: String,
+    base_url: String,
+    dimension: Option<usize>,
+    batch_size: usize,
+    max_retries: u32,
+    client: reqwest::Client,
+}
+
+impl OllamaProvider {
+    pub fn new(model: Option<String>) -> Self {
+        Self {
+            model: model.unwrap_or_else(|| "nomic-embed-text".to_string()),
+            base_url: "http://localhost:11434".to_string(),
+            dimension: None,
+            batch_size: DEFAULT_BATCH_SIZE,
+            max_retries: DEFAULT_MAX_RETRIES,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Declare the dimension of a model this provider does not know
+    pub fn with_dimension(mut self, dimension: usize) -> Self {
+        self.dimension = Some(dimension);
+        self
+    }
+
+    /// Maximum number of texts sent per request
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Ask the server for the model's dimension if it is neither known nor
+    /// configured
+    pub async fn detect_dimension(mut self) -> Result<Self> {
+        if self.dimension.is_none() && known_ollama_dimension(&self.model).is_none() {
+            let probe = self.request(&["dimension probe".to_string()]).await?;
+            self.dimension = probe.first().map(Vec::len);
+        }
+        Ok(self)
+    }
+
+    async fn request(&self, texts: &[String]) -> Result<Vec<Embedding>> {
+        let url = format!("{}/api/embed", self.base_url);
+        let response = send_with_retry(self.max_retries, || {
+            self.client.post(&url).json(&serde_json::json!({
+                "model": self.model,
+                "input": texts,
+            }))
+        }).await?;
+
+        let result: OllamaResponse = response.json().await?;
+        Ok(result.embeddings)
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Embedding>> {
+        let embeddings = self.request(texts).await?;
+        check_embeddings(embeddings, texts.len(), self.dimension(), &self.model)
+    }
+}
+
+fn known_ollama_dimension(model: &str) -> Option<usize> {
+    // Tags like `nomic-embed-text:latest` share the base model's dimension
+    match model.split(':').next().unwrap_or(model) {
+        "nomic-embed-text" => Some(768),
+        "mxbai-embed-large" | "snowflake-arctic-embed" | "bge-m3" => Some(1024),
+        "all-minilm" => Some(384),
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OllamaProvider {
+    async fn generate(&self, text: &str) -> Result<Embedding> {
+        let mut embeddings = self.embed(&[text.to_string()]).await?;
+        Ok(embeddings.remove(0))
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+            .or_else(|| known_ollama_dimension(&self.model))
+            .unwrap_or(768)
+    }
+
+    fn model_id(&self) -> String {
+        format!("ollama:{}", self.model)
+    }
+
+    async fn generate_batch(&self, texts: &[String]) -> Result<Vec<Embedding>> {
+        let mut results = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(self.batch_size) {
+            results.extend(self.embed(batch).await?);
+        }
+        Ok(results)
+    }
+}
+
+#[derive(Deserialize)]
+struct OllamaResponse {
+    embeddings: Vec<Embedding>,
+}
+
+/// Send a request, backing off and retrying while the server answers 429.
+/// `Retry-After` is honoured when present; otherwise the delay doubles from
+/// half a second.
+async fn send_with_retry(
+    max_retries: u32,
+    request: impl Fn() -> reqwest::RequestBuilder,
+) -> Result<reqwest::Response> {
+    let mut attempt = 0;
+    loop {
+        let response = request().send().await?;
+        let status = response.status();
+
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS && attempt < max_retries {
+            let delay = response.headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .map(Duration::from_secs)
+                .unwrap_or_else(|| Duration::from_millis(500 << attempt.min(6)));
+            tracing::warn!("Embedding request rate limited; retrying in {:?}", delay);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+            continue;
+        }
+
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Embedding request failed ({}): {}", status, body));
+        }
+        return Ok(response);
+    }
+}
+
+/// Check a response has one embedding per text, each of the expected length
+fn check_embeddings(embeddings: Vec<Embedding>, count: usize, dimension: usize, model: &str) -> Result<Vec<Embedding>> {
+    if embeddings.len() != count {
+        return Err(anyhow!("Expected {} embeddings from {}, got {}", count, model, embeddings.len()));
+    }
+    if let Some(e) = embeddings.iter().find(|e| e.len() != dimension) {
+        return Err(anyhow!(
+            "{} returned {}-dimensional embeddings, expected {}; configure its dimension",
+            model, e.len(), dimension
+        ));
+    }
+    Ok(embeddings)
+}
+
+/// Build the provider selected by the environment:
+///
+/// - `CADI_EMBEDDING_PROVIDER`: `mock` (default), `openai` or `ollama`
+/// - `CADI_EMBEDDING_MODEL`, `CADI_EMBEDDING_URL`, `CADI_EMBEDDING_DIMENSION`
+///   and `CADI_EMBEDDING_BATCH_SIZE` override the provider's defaults
+/// - `OPENAI_API_KEY` is the key for `openai`
+///
+/// Real providers are wrapped in an [`EmbeddingCache`] under `cache_dir` when
+/// one is given.
+pub async fn provider_from_env(cache_dir: Option<PathBuf>) -> Result<Box<dyn EmbeddingProvider>> {
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+    let model = var("CADI_EMBEDDING_MODEL");
+    let url = var("CADI_EMBEDDING_URL");
+    let dimension = var("CADI_EMBEDDING_DIMENSION").map(|v| v.parse::<usize>()).transpose()?;
+    let batch_size = var("CADI_EMBEDDING_BATCH_SIZE").map(|v| v.parse::<usize>()).transpose()?;
+
+    let provider: Box<dyn EmbeddingProvider> = match var("CADI_EMBEDDING_PROVIDER").as_deref().unwrap_or("mock") {
+        "mock" => return Ok(Box::new(MockProvider)),
+        "openai" => {
+            let mut provider = OpenAiProvider::from_env(model)?;
+            if let Some(url) = url {
+                provider = provider.with_base_url(url);
+            }
+            if let Some(dimension) = dimension {
+                provider = provider.with_dimension(dimension);
+            }
+            if let Some(batch_size) = batch_size {
+                provider = provider.with_batch_size(batch_size);
+            }
+            Box::new(provider)
+        }
+        "ollama" => {
+            let mut provider = OllamaProvider::new(model);
+            if let Some(url) = url {
+                provider = provider.with_base_url(url);
+            }
+            if let Some(dimension) = dimension {
+                provider = provider.with_dimension(dimension);
+            }
+            if let Some(batch_size) = batch_size {
+                provider = provider.with_batch_size(batch_size);
+            }
+            Box::new(provider.detect_dimension().await?)
+        }
+        other => return Err(anyhow!("Unknown embedding provider: {}", other)),
+    };
+
+    Ok(match cache_dir {
+        Some(dir) => Box::new(CachedProvider::new(provider, EmbeddingCache::new(dir))),
+        None => provider,
+    })
+}
+
 /// Mock embedding provider for testing and dev
 pub struct MockProvider;
 
@@ -97,6 +390,10 @@ impl EmbeddingProvider for MockProvider {
         let len = text.len() as f32;
         Ok(vec![len, len * 2.0, len / 2.0, 0.0, 1.0])
     }
+
+    fn dimension(&self) -> usize {
+        5
+    }
 }
 
 /// Manager for chunk semantic search
@@ -123,13 +420,26 @@ impl EmbeddingManager {
         }
     }
 
-    /// Get or generate an embedding for a chunk
+    /// Length of the vectors generated by the configured provider
+    pub fn dimension(&self) -> usize {
+        self.provider.dimension()
+    }
+
+    /// Identifies the model behind the configured provider
+    pub fn model_id(&self) -> String {
+        self.provider.model_id()
+    }
+
+    /// Get or generate an embedding for a chunk. Cached embeddings of a
+    /// different dimension (from a previous provider) are regenerated.
     pub async fn get_chunk_embedding(&mut self, chunk_id: &str, content: &str) -> Result<Embedding> {
-        if let Some(emb) = self.cache.get(chunk_id) {
+        let dimension = self.dimension();
+
+        if let Some(emb) = self.cache.get(chunk_id).filter(|e| e.len() == dimension) {
             return Ok(emb.clone());
         }
 
-        if let Some(emb) = self.store.get(chunk_id) {
+        if let Some(emb) = self.store.get(chunk_id).filter(|e| e.len() == dimension) {
             self.cache.insert(chunk_id.to_string(), emb.clone());
             return Ok(emb);
         }
@@ -145,6 +455,66 @@ impl EmbeddingManager {
         Ok(emb)
     }
 
+    /// Generate an embedding for free text, such as a search query
+    pub async fn embed_text(&self, text: &str) -> Result<Embedding> {
+        self.provider.generate(text).await
+    }
+
+    /// Get or generate embeddings for several `(chunk_id, content)` pairs,
+    /// sending everything not already stored to the provider as one batch
+    pub async fn get_chunk_embeddings(&mut self, chunks: &[(String, String)]) -> Result<Vec<Embedding>> {
+        let dimension = self.dimension();
+        let mut results: Vec<Option<Embedding>> = chunks.iter()
+            .map(|(id, _)| {
+                self.cache.get(id).cloned()
+                    .or_else(|| self.store.get(id))
+                    .filter(|e| e.len() == dimension)
+            })
+            .collect();
+
+        let missing: Vec<usize> = (0..chunks.len()).filter(|&i| results[i].is_none()).collect();
+        if !missing.is_empty() {
+            let texts: Vec<String> = missing.iter().map(|&i| chunks[i].1.clone()).collect();
+            let generated = self.provider.generate_batch(&texts).await?;
+            if generated.len() != texts.len() {
+                return Err(anyhow!("Expected {} embeddings, got {}", texts.len(), generated.len()));
+            }
+            for (i, emb) in missing.into_iter().zip(generated) {
+                self.store.insert(chunks[i].0.clone(), emb.clone());
+                results[i] = Some(emb);
+            }
+            if let Some(ref p) = self.store_path {
+                let _ = self.store.save(p);
+            }
+        }
+
+        let results: Vec<Embedding> = results.into_iter().flatten().collect();
+        for ((id, _), emb) in chunks.iter().zip(&results) {
+            self.cache.insert(id.clone(), emb.clone());
+        }
+        Ok(results)
+    }
+
+    /// Generate embeddings for `(chunk_id, content)` pairs with the current
+    /// provider, replacing any stored for those chunks
+    pub async fn regenerate_chunk_embeddings(&mut self, chunks: &[(String, String)]) -> Result<Vec<Embedding>> {
+        let texts: Vec<String> = chunks.iter().map(|(_, content)| content.clone()).collect();
+        let embeddings = self.provider.generate_batch(&texts).await?;
+        if embeddings.len() != chunks.len() {
+            return Err(anyhow!("Expected {} embeddings, got {}", chunks.len(), embeddings.len()));
+        }
+
+        for ((id, _), emb) in chunks.iter().zip(&embeddings) {
+            self.cache.insert(id.clone(), emb.clone());
+            self.store.insert(id.clone(), emb.clone());
+        }
+        if let Some(ref p) = self.store_path {
+            let _ = self.store.save(p);
+        }
+
+        Ok(embeddings)
+    }
+
     /// Perform a semantic search
     pub async fn search(&self, query: &str, candidates: &HashMap<String, Embedding>, limit: usize) -> Result<Vec<(String, f32)>> {
         let query_emb = self.provider.generate(query).await?;
@@ -190,3 +560,80 @@ fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     
     dot_product / (norm_a * norm_b)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// A one-route OpenAI-compatible server that rate limits its first
+    /// request and records the size of every batch it embeds
+    async fn serve(batches: Arc<Mutex<Vec<usize>>>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut requests = 0;
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = Vec::new();
+                let body = loop {
+                    let mut chunk = [0u8; 4096];
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                    let text = String::from_utf8_lossy(&buf);
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let length = text[..end].lines()
+                            .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                            .unwrap_or(0);
+                        if buf.len() >= end + 4 + length {
+                            break buf[end + 4..end + 4 + length].to_vec();
+                        }
+                    }
+                };
+
+                requests += 1;
+                let response = if requests == 1 {
+                    "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 0\r\ncontent-length: 0\r\n\r\n".to_string()
+                } else {
+                    let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                    let inputs = request["input"].as_array().unwrap();
+                    batches.lock().unwrap().push(inputs.len());
+                    // Answer out of order; the provider sorts by index
+                    let data: Vec<_> = inputs.iter().enumerate().rev()
+                        .map(|(i, text)| serde_json::json!({
+                            "index": i,
+                            "embedding": [text.as_str().unwrap().len() as f32, 0.0, 1.0],
+                        }))
+                        .collect();
+                    let body = serde_json::json!({ "data": data }).to_string();
+                    format!("HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}", body.len(), body)
+                };
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        format!("http://{}/v1", addr)
+    }
+
+    #[tokio::test]
+    async fn test_openai_provider_batches_and_retries() {
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        let provider = OpenAiProvider::new("key".into(), Some("local-model".into()))
+            .with_base_url(serve(batches.clone()).await)
+            .with_dimension(3)
+            .with_batch_size(2);
+        assert_eq!(provider.dimension(), 3);
+
+        let texts: Vec<String> = ["a", "bb", "ccc", "dddd", "eeeee"].iter().map(|s| s.to_string()).collect();
+        let embeddings = provider.generate_batch(&texts).await.unwrap();
+
+        assert_eq!(*batches.lock().unwrap(), vec![2, 2, 1]);
+        let lengths: Vec<f32> = embeddings.iter().map(|e| e[0]).collect();
+        assert_eq!(lengths, vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+
+        // The server's embeddings don't match a declared dimension
+        let wrong = OpenAiProvider::new("key".into(), Some("local-model".into()))
+            .with_base_url(serve(Arc::new(Mutex::new(Vec::new()))).await)
+            .with_max_retries(1);
+        assert!(wrong.generate("a").await.is_err());
+    }
+}
@@ -1,5 +1,7 @@
 pub mod store;
+pub mod cache;
 pub mod embeddings;
 
+pub use cache::*;
 pub use embeddings::*;
 pub use store::*;
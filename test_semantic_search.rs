@@ -103,6 +103,7 @@ export default app;"#,
         language: None,
         limit: 5,
         min_score: 0.1,
+        ..Default::default()
     };
 
     let results = registry.search(text_query).await?;
@@ -123,6 +124,7 @@ export default app;"#,
         language: None,
         limit: 5,
         min_score: 0.1,
+        ..Default::default()
     };
 
     let semantic_results = registry.search(semantic_query).await?;
@@ -142,6 +144,7 @@ export default app;"#,
         language: None,
         limit: 5,
         min_score: 0.1,
+        ..Default::default()
     };
 
     let hybrid_results = registry.search(hybrid_query).await?;